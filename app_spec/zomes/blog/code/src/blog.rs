@@ -13,6 +13,7 @@ use hdk::{
     AGENT_ADDRESS,
 };
 use post::Post;
+use serde_json;
 
 pub fn handle_check_sum(num1: u32, num2: u32) -> ZomeApiResult<JsonString> {
     #[derive(Serialize, Deserialize, Debug, DefaultJson)]
@@ -42,7 +43,7 @@ pub fn handle_create_post(content: String, in_reply_to: Option<Address>) -> Zome
         Post::new(&content, "now").into(),
     );
 
-    let address = hdk::commit_entry(&post_entry)?;
+    let address = hdk::commit_entry_address(&post_entry)?;
 
     hdk::link_entries(&AGENT_ADDRESS, &address, "authored_posts")?;
 
@@ -55,6 +56,19 @@ pub fn handle_create_post(content: String, in_reply_to: Option<Address>) -> Zome
     Ok(address)
 }
 
+/// Creates a post for each content of the given JSON array of strings
+pub fn handle_create_posts(contents: String) -> ZomeApiResult<Vec<Address>> {
+    // serde_json::Error converts into a ZomeApiError, so ? is all it takes
+    let contents: Vec<String> = serde_json::from_str(&contents)?;
+    if contents.is_empty() {
+        return Err(zome_api_error!("no posts in {:?}", contents));
+    }
+    contents
+        .into_iter()
+        .map(|content| handle_create_post(content, None))
+        .collect()
+}
+
 pub fn handle_posts_by_agent(agent: Address) -> ZomeApiResult<GetLinksResult> {
     hdk::get_links(&agent, "authored_posts")
 }
@@ -80,3 +94,93 @@ pub fn handle_get_post(post_address: Address) -> ZomeApiResult<Option<Entry>> {
     // Otherwise its a Some(T) or a None
     hdk::get_entry(post_address)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hdk::{
+        error::ZomeApiError, holochain_core_types::cas::content::AddressableContent,
+        testing::MockRibosome,
+    };
+    use post::definition;
+
+    #[test]
+    fn create_post_and_get_it_back() {
+        let _ribosome = MockRibosome::new();
+
+        let address = handle_create_post(String::from("hello"), None).unwrap();
+        let post_entry = handle_get_post(address.clone()).unwrap().unwrap();
+
+        assert_eq!(post_entry.address(), address);
+        assert_eq!(handle_post_address(String::from("hello")), Ok(address));
+    }
+
+    #[test]
+    fn create_posts_from_a_json_array() {
+        let _ribosome = MockRibosome::new();
+
+        let addresses = handle_create_posts(String::from("[\"hello\", \"hi\"]")).unwrap();
+        assert_eq!(addresses.len(), 2);
+        assert_eq!(
+            handle_post_address(String::from("hi")),
+            Ok(addresses[1].clone())
+        );
+
+        let serde_error = serde_json::from_str::<Vec<String>>("hello").unwrap_err();
+        assert_eq!(
+            handle_create_posts(String::from("hello")),
+            Err(ZomeApiError::Internal(serde_error.to_string()))
+        );
+        assert_eq!(
+            handle_create_posts(String::from("[]")),
+            Err(ZomeApiError::Internal(String::from("no posts in []")))
+        );
+    }
+
+    #[test]
+    fn posts_are_linked_from_their_author_and_replies_from_their_post() {
+        let ribosome = MockRibosome::new();
+
+        let post = handle_create_post(String::from("hello"), None).unwrap();
+        let reply = handle_create_post(String::from("hi"), Some(post.clone())).unwrap();
+
+        assert_eq!(
+            handle_my_posts().unwrap().addresses(),
+            &vec![post.clone(), reply.clone()]
+        );
+        assert_eq!(
+            handle_posts_by_agent(ribosome.agent_address())
+                .unwrap()
+                .addresses(),
+            &vec![post.clone(), reply.clone()]
+        );
+        assert_eq!(ribosome.links(&post, "comments"), vec![reply.clone()]);
+        assert_eq!(handle_my_posts_as_commited(), Ok(vec![reply, post]));
+    }
+
+    #[test]
+    fn posts_get_validated_against_the_chain_they_are_committed_to() {
+        let ribosome = MockRibosome::new();
+        handle_create_post(String::from("hello"), None).unwrap();
+        let mut post_definition = definition();
+
+        let validation_data = ribosome.validation_data();
+        assert_eq!(
+            validation_data
+                .package
+                .source_chain_headers
+                .as_ref()
+                .map(|headers| headers.len()),
+            // DNA, agent, the post and its link
+            Some(4)
+        );
+        let post_entry = Entry::App(
+            AppEntryType::from("post"),
+            Post::new(&"x".repeat(300), "now").into(),
+        );
+        assert_eq!(
+            (post_definition.validator)(post_entry, validation_data),
+            Err(String::from("Content too long"))
+        );
+    }
+}
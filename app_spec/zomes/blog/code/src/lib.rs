@@ -48,6 +48,12 @@ define_zome! {
                 handler: blog::handle_create_post
             }
 
+            create_posts: {
+                inputs: |contents: String|,
+                outputs: |result: ZomeApiResult<Vec<Address>>|,
+                handler: blog::handle_create_posts
+            }
+
             posts_by_agent: {
                 inputs: |agent: Address|,
                 outputs: |post_hashes: ZomeApiResult<GetLinksResult>|,
@@ -1,24 +1,33 @@
 use holochain_core_types::{
     cas::{
         content::{Address, AddressableContent, Content},
-        storage::ContentAddressableStorage,
+        storage::{ContentAddressableStorage, GcReport},
     },
     error::HolochainError,
 };
 use std::{
-    fs::{create_dir_all, read_to_string, write},
-    path::{Path, MAIN_SEPARATOR},
+    collections::HashSet,
+    ffi::OsStr,
+    fs::{create_dir_all, metadata, read_dir, read_to_string, remove_dir_all, rename, write, File},
+    io::ErrorKind,
+    path::{Path, PathBuf, MAIN_SEPARATOR},
     sync::{Arc, RwLock},
 };
 
 use uuid::Uuid;
 
+/// name of the directory in the storage's directory that garbage collections move
+/// unreachable content to
+pub const TRASH_DIR_NAME: &str = "trash";
+
 #[derive(Clone, Debug)]
 pub struct FilesystemStorage {
     /// path to the directory where content will be saved to disk
     dir_path: String,
     id: Uuid,
     lock: Arc<RwLock<()>>,
+    /// content added since the last `sync()`, which might not be on the disk yet
+    unsynced: Arc<RwLock<HashSet<Address>>>,
 }
 
 impl PartialEq for FilesystemStorage {
@@ -33,6 +42,7 @@ impl FilesystemStorage {
             dir_path: String::from(dir_path),
             id: Uuid::new_v4(),
             lock: Arc::new(RwLock::new(())),
+            unsynced: Arc::new(RwLock::new(HashSet::new())),
         })
     }
 
@@ -42,6 +52,28 @@ impl FilesystemStorage {
         // AddressableContent trait implementation
         format!("{}{}{}.txt", self.dir_path, MAIN_SEPARATOR, address)
     }
+
+    /// path of the directory holding the content removed by the last garbage collection
+    pub fn trash_path(&self) -> PathBuf {
+        Path::new(&self.dir_path).join(TRASH_DIR_NAME)
+    }
+
+    /// addresses of all content files in the directory, the caller has to hold the lock
+    fn stored_addresses(&self) -> Result<HashSet<Address>, HolochainError> {
+        let mut addresses = HashSet::new();
+        if !Path::new(&self.dir_path).is_dir() {
+            return Ok(addresses);
+        }
+        for dir_entry in read_dir(&self.dir_path)? {
+            let path = dir_entry?.path();
+            if path.is_file() && path.extension() == Some(OsStr::new("txt")) {
+                if let Some(stem) = path.file_stem() {
+                    addresses.insert(Address::from(stem.to_string_lossy().to_string()));
+                }
+            }
+        }
+        Ok(addresses)
+    }
 }
 
 impl ContentAddressableStorage for FilesystemStorage {
@@ -55,6 +87,7 @@ impl ContentAddressableStorage for FilesystemStorage {
             self.address_to_path(&content.address()),
             content.content().to_string(),
         )?;
+        self.unsynced.write()?.insert(content.address());
 
         Ok(())
     }
@@ -73,6 +106,59 @@ impl ContentAddressableStorage for FilesystemStorage {
         }
     }
 
+    fn addresses(&self) -> Result<HashSet<Address>, HolochainError> {
+        let _guard = self.lock.read()?;
+        self.stored_addresses()
+    }
+
+    /// Removed content is not deleted right away but moved to the trash directory,
+    /// where it can be recovered from until the next garbage collection empties it.
+    fn gc(&mut self, roots: &HashSet<Address>) -> Result<GcReport, HolochainError> {
+        let _guard = self.lock.write()?;
+        let trash_path = self.trash_path();
+        if trash_path.is_dir() {
+            remove_dir_all(&trash_path)?;
+        }
+
+        let mut report = GcReport::default();
+        for address in self.stored_addresses()? {
+            if roots.contains(&address) {
+                report.kept += 1;
+                continue;
+            }
+            let path = self.address_to_path(&address);
+            create_dir_all(&trash_path)?;
+            report.bytes_reclaimed += metadata(&path)?.len();
+            rename(&path, trash_path.join(format!("{}.txt", address)))?;
+            report.removed += 1;
+        }
+        Ok(report)
+    }
+
+    /// Syncs the files added since the last sync to the disk, and the directory listing them.
+    fn sync(&self) -> Result<(), HolochainError> {
+        let _guard = self.lock.write()?;
+        let mut unsynced = self.unsynced.write()?;
+        if unsynced.is_empty() {
+            return Ok(());
+        }
+        for address in unsynced.iter() {
+            match File::open(self.address_to_path(address)) {
+                Ok(file) => file.sync_all()?,
+                // moved to the trash by a garbage collection since
+                Err(ref error) if error.kind() == ErrorKind::NotFound => (),
+                Err(error) => return Err(error.into()),
+            }
+        }
+        // new files are only there after a crash once the directory entry is synced as well,
+        // which only works on Unix, where directories can be opened like files
+        if cfg!(unix) {
+            File::open(&self.dir_path)?.sync_all()?;
+        }
+        unsynced.clear();
+        Ok(())
+    }
+
     fn get_id(&self) -> Uuid {
         self.id
     }
@@ -87,11 +173,15 @@ pub mod tests {
     use crate::cas::file::FilesystemStorage;
     use holochain_core_types::{
         cas::{
-            content::{ExampleAddressableContent, OtherExampleAddressableContent},
-            storage::StorageTestSuite,
+            content::{
+                AddressableContent, Content, ExampleAddressableContent,
+                OtherExampleAddressableContent,
+            },
+            storage::{ContentAddressableStorage, GcReport, StorageTestSuite},
         },
         json::RawString,
     };
+    use std::{collections::HashSet, fs::read_to_string};
 
     pub fn test_file_cas() -> (FilesystemStorage, TempDir) {
         let dir = tempdir().expect("Could not create a tempdir for CAS testing");
@@ -113,4 +203,49 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn file_gc_test() {
+        let (cas, _dir) = test_file_cas();
+        let test_suite = StorageTestSuite::new(cas);
+        test_suite.gc_test(RawString::from("foo").into(), RawString::from("bar").into());
+    }
+
+    #[test]
+    /// removed content stays in the trash until the next garbage collection
+    fn file_gc_keeps_removed_content_for_one_collection() {
+        let (mut cas, _dir) = test_file_cas();
+        let content: Content = RawString::from("foo").into();
+        cas.add(&content).unwrap();
+
+        let report = cas.gc(&HashSet::new()).unwrap();
+        assert_eq!(report.removed, 1);
+        let trashed = cas.trash_path().join(format!("{}.txt", content.address()));
+        assert_eq!(read_to_string(&trashed).unwrap(), content.to_string());
+
+        assert_eq!(cas.gc(&HashSet::new()).unwrap(), GcReport::default());
+        assert!(!trashed.exists());
+    }
+
+    #[test]
+    /// syncing goes over the content added since the last sync, also if some of it is gone
+    fn file_sync_test() {
+        let (mut cas, _dir) = test_file_cas();
+        let kept: Content = RawString::from("foo").into();
+        let removed: Content = RawString::from("bar").into();
+        cas.add(&kept).unwrap();
+        cas.add(&removed).unwrap();
+        assert_eq!(cas.unsynced.read().unwrap().len(), 2);
+
+        let mut roots = HashSet::new();
+        roots.insert(kept.address());
+        cas.gc(&roots).unwrap();
+        assert_eq!(cas.sync(), Ok(()));
+        assert!(cas.unsynced.read().unwrap().is_empty());
+        assert_eq!(cas.fetch(&kept.address()).unwrap(), Some(kept));
+
+        // nothing to do, not even for a storage whose directory doesn't exist yet
+        let (empty_cas, _empty_dir) = test_file_cas();
+        assert_eq!(empty_cas.sync(), Ok(()));
+    }
+
 }
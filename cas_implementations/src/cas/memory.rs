@@ -1,12 +1,12 @@
 use holochain_core_types::{
     cas::{
         content::{Address, AddressableContent, Content},
-        storage::ContentAddressableStorage,
+        storage::{ContentAddressableStorage, GcReport},
     },
     error::HolochainError,
 };
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, RwLock},
 };
 use uuid::Uuid;
@@ -49,6 +49,27 @@ impl ContentAddressableStorage for MemoryStorage {
         Ok(map.get(address).cloned())
     }
 
+    fn addresses(&self) -> Result<HashSet<Address>, HolochainError> {
+        let map = self.storage.read()?;
+        Ok(map.keys().cloned().collect())
+    }
+
+    fn gc(&mut self, roots: &HashSet<Address>) -> Result<GcReport, HolochainError> {
+        let mut map = self.storage.write()?;
+        let mut report = GcReport::default();
+        map.retain(|address, content| {
+            if roots.contains(address) {
+                report.kept += 1;
+                true
+            } else {
+                report.removed += 1;
+                report.bytes_reclaimed += content.to_string().len() as u64;
+                false
+            }
+        });
+        Ok(report)
+    }
+
     fn get_id(&self) -> Uuid {
         self.id
     }
@@ -78,4 +99,10 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn memory_gc() {
+        let test_suite = StorageTestSuite::new(test_memory_storage());
+        test_suite.gc_test(RawString::from("foo").into(), RawString::from("bar").into());
+    }
+
 }
@@ -0,0 +1,295 @@
+use crate::error::DefaultResult;
+use colored::*;
+use holochain_cas_implementations::{cas::file::FilesystemStorage, eav::file::EavFileStorage};
+use holochain_container_api::Holochain;
+use holochain_core::{context::Context, logger::Logger, persister::SimplePersister};
+use holochain_core_types::{
+    agent::AgentId, cas::content::AddressableContent, chain_header::ChainHeader, dna::Dna,
+    entry::entry_type::EntryType, json::JsonString,
+};
+use std::{
+    convert::TryFrom,
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const DEFAULT_STORAGE_DIR: &str = ".hc/storage";
+
+const CALL_AGENT_NAME: &str = "hc-call-agent";
+const NO_NETWORK_CONFIG: &str = "{\"backend\":\"none\"}";
+const HOLD_TIMEOUT_SECS: u64 = 5;
+
+/// Zome calls made from the command line print their result, so the instance keeps quiet
+struct QuietLogger;
+
+impl Logger for QuietLogger {
+    fn log(&mut self, _msg: String) {}
+}
+
+/// Calls a single zome function of the given DNA on an instance over the given storage
+/// directory, without any network, and saves the resulting source chain there.
+/// The capability can be left out if only one capability of the zome exposes the function.
+pub fn call(
+    storage: &PathBuf,
+    dna_file: &PathBuf,
+    zome: &str,
+    capability: Option<String>,
+    function: &str,
+    params: &str,
+    create: bool,
+) -> DefaultResult<JsonString> {
+    let dna = load_dna(dna_file)?;
+    let capability = match capability {
+        Some(capability) => capability,
+        None => find_capability(&dna, zome, function)?,
+    };
+
+    if !storage.exists() {
+        ensure!(
+            create || confirm_create(storage)?,
+            "Storage directory {:?} does not exist",
+            storage
+        );
+        eprintln!(
+            "{} storage directory {:?} with a fresh chain",
+            "Creating".green().bold(),
+            storage
+        );
+    }
+
+    let context = storage_context(storage)?;
+    let mut hc =
+        Holochain::restore(dna, Arc::new(context)).map_err(|err| format_err!("{}", err))?;
+    let top_before_call = hc
+        .state()
+        .map_err(|err| format_err!("{}", err))?
+        .agent()
+        .top_chain_header();
+
+    hc.start().map_err(|err| format_err!("{}", err))?;
+    let result = hc.call(zome, &capability, function, params);
+    wait_for_holds(&hc, &top_before_call);
+    hc.save().map_err(|err| format_err!("{}", err))?;
+    hc.stop().map_err(|err| format_err!("{}", err))?;
+
+    result.map_err(|err| format_err!("{}", err))
+}
+
+/// The result as pretty-printed JSON, or exactly as the zome returned it for `raw`
+pub fn format_call_result(result: &JsonString, raw: bool) -> String {
+    if raw {
+        return String::from(result.to_owned());
+    }
+    serde_json::from_str::<serde_json::Value>(&String::from(result.to_owned()))
+        .and_then(|value| serde_json::to_string_pretty(&value))
+        .unwrap_or_else(|_| String::from(result.to_owned()))
+}
+
+pub(crate) fn load_dna(dna_file: &PathBuf) -> DefaultResult<Dna> {
+    ensure!(
+        dna_file.exists(),
+        "DNA file {:?} not found, package the app first",
+        dna_file
+    );
+    let contents = fs::read_to_string(dna_file)?;
+    Dna::try_from(JsonString::from(contents))
+        .map_err(|err| format_err!("Could not load DNA file {:?}: {}", dna_file, err))
+}
+
+pub(crate) fn find_capability(dna: &Dna, zome: &str, function: &str) -> DefaultResult<String> {
+    let zome_def = dna
+        .get_zome(zome)
+        .ok_or_else(|| format_err!("Zome {} not found in DNA", zome))?;
+    let capabilities: Vec<&String> = zome_def
+        .capabilities
+        .iter()
+        .filter(|(_, capability)| capability.functions.iter().any(|f| f.name == function))
+        .map(|(name, _)| name)
+        .collect();
+    match capabilities.as_slice() {
+        [capability] => Ok(capability.to_string()),
+        [] => bail!("Function {} not found in zome {}", function, zome),
+        _ => bail!(
+            "Function {} is part of several capabilities of zome {}, pick one with --capability",
+            function,
+            zome
+        ),
+    }
+}
+
+fn confirm_create(storage: &PathBuf) -> DefaultResult<bool> {
+    let mut rl = rustyline::Editor::<()>::new();
+    let answer = rl.readline(&format!(
+        "Storage directory {:?} does not exist. Create it with a fresh chain? [y/N] ",
+        storage
+    ))?;
+    Ok(answer.trim().eq_ignore_ascii_case("y") || answer.trim().eq_ignore_ascii_case("yes"))
+}
+
+fn storage_context(storage: &PathBuf) -> DefaultResult<Context> {
+    let cas_path = storage.join("cas");
+    let eav_path = storage.join("eav");
+    fs::create_dir_all(&cas_path)?;
+    fs::create_dir_all(&eav_path)?;
+
+    let file_storage = Arc::new(RwLock::new(FilesystemStorage::new(
+        cas_path.to_str().unwrap(),
+    )?));
+    let context = Context::new(
+        AgentId::generate_fake(CALL_AGENT_NAME),
+        Arc::new(Mutex::new(QuietLogger)),
+        Arc::new(Mutex::new(SimplePersister::new(file_storage.clone()))),
+        file_storage,
+        Arc::new(RwLock::new(EavFileStorage::new(
+            eav_path.to_str().unwrap().to_string(),
+        )?)),
+        JsonString::from(NO_NETWORK_CONFIG),
+    )?;
+    Ok(context)
+}
+
+/// Without a network, this node holds everything it publishes itself.
+/// Holding happens in the background, so before exiting we wait until the entries
+/// committed by the call are held, or the timeout is over.
+fn wait_for_holds(hc: &Holochain, top_before_call: &Option<ChainHeader>) {
+    let state = match hc.state() {
+        Ok(state) => state,
+        Err(_) => return,
+    };
+    let last_known = top_before_call.as_ref().map(|header| header.address());
+    let published: Vec<ChainHeader> = state
+        .agent()
+        .chain()
+        .iter(&state.agent().top_chain_header())
+        .take_while(|header| Some(header.address()) != last_known)
+        .filter(|header| match header.entry_type() {
+            EntryType::App(_) | EntryType::AgentId | EntryType::LinkAdd | EntryType::Deletion => {
+                true
+            }
+            _ => false,
+        })
+        .collect();
+
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_secs(HOLD_TIMEOUT_SECS) {
+        let dht = hc.state().map(|state| state.dht());
+        let all_held = dht
+            .map(|dht| {
+                published.iter().all(|header| {
+                    dht.get_headers(header.entry_address())
+                        .map(|headers| !headers.is_empty())
+                        .unwrap_or(false)
+                })
+            })
+            .unwrap_or(true);
+        if all_held {
+            return;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    eprintln!("Not all committed entries got stored in the local DHT shard in time");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::{tempdir, TempDir};
+    use test_utils::{
+        create_test_cap_with_fn_name, create_test_dna_with_cap, create_wasm_from_file,
+    };
+
+    /// Packages a DNA with the `commit_test` function of the container API test wasm
+    fn commit_test_dna_file() -> (TempDir, PathBuf) {
+        let wasm = create_wasm_from_file(
+            "../container_api/wasm-test/target/wasm32-unknown-unknown/release/example_api_wasm.wasm",
+        );
+        let capability = create_test_cap_with_fn_name("commit_test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        let dir = tempdir().unwrap();
+        let dna_file = dir.path().join("bundle.json");
+        fs::write(&dna_file, String::from(JsonString::from(dna))).unwrap();
+        (dir, dna_file)
+    }
+
+    #[test]
+    fn call_persists_commits_between_invocations() {
+        let (_dna_dir, dna_file) = commit_test_dna_file();
+        let storage_dir = tempdir().unwrap();
+        let storage = storage_dir.path().join("storage");
+
+        let first = call(
+            &storage,
+            &dna_file,
+            "test_zome",
+            None,
+            "commit_test",
+            "{}",
+            true,
+        )
+        .expect("first call failed");
+        assert!(storage.exists());
+        let second = call(
+            &storage,
+            &dna_file,
+            "test_zome",
+            None,
+            "commit_test",
+            "{}",
+            false,
+        )
+        .expect("second call failed");
+        assert_eq!(first, second);
+
+        // the second invocation continued the chain of the first one
+        let dna = load_dna(&dna_file).unwrap();
+        let context = storage_context(&storage).unwrap();
+        let hc = Holochain::restore(dna, Arc::new(context)).unwrap();
+        let state = hc.state().unwrap();
+        let chain_info = state.agent().chain_info();
+        assert_eq!(chain_info.count("%dna"), 1);
+        assert_eq!(chain_info.count("testEntryType"), 2);
+
+        // and the committed entry can be read back from the local DHT shard
+        let entry_header = state
+            .agent()
+            .chain()
+            .iter(&state.agent().top_chain_header())
+            .next()
+            .unwrap();
+        assert!(!state
+            .dht()
+            .get_headers(entry_header.entry_address())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn call_fails_for_unknown_functions() {
+        let (_dna_dir, dna_file) = commit_test_dna_file();
+        let storage_dir = tempdir().unwrap();
+        let storage = storage_dir.path().join("storage");
+
+        let result = call(&storage, &dna_file, "test_zome", None, "nope", "{}", true);
+        assert!(result.is_err());
+        // nothing gets created for a call that can't be made
+        assert!(!storage.exists());
+    }
+
+    #[test]
+    fn formats_call_results() {
+        let result = JsonString::from("{\"Ok\":{\"address\":\"Qm\"}}");
+        assert_eq!(
+            format_call_result(&result, true),
+            "{\"Ok\":{\"address\":\"Qm\"}}"
+        );
+        assert_eq!(
+            format_call_result(&result, false),
+            "{\n  \"Ok\": {\n    \"address\": \"Qm\"\n  }\n}"
+        );
+        let not_json = JsonString::from("hello");
+        assert_eq!(format_call_result(&not_json, false), "hello");
+    }
+}
@@ -0,0 +1,169 @@
+use crate::error::DefaultResult;
+use colored::*;
+use holochain_cas_implementations::cas::file::FilesystemStorage;
+use holochain_core::agent::chain_dump::{export_chain, holds_chain, import_chain, ChainDump};
+use holochain_core_types::{cas::storage::ContentAddressableStorage, json::JsonString};
+use std::{
+    convert::TryFrom,
+    fs,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+/// Writes the source chain of the storage directory, as `hc call` keeps it, into a JSON file.
+/// Entries of private types are part of it unless `redact_private` is set, which leaves
+/// their content out.
+pub fn chain_export(storage: &PathBuf, out: &PathBuf, redact_private: bool) -> DefaultResult<()> {
+    ensure!(
+        storage.join("cas").exists(),
+        "Storage directory {:?} holds no source chain",
+        storage
+    );
+    let mut dump = export_chain(&cas(storage)?).map_err(|err| format_err!("{}", err))?;
+
+    if redact_private {
+        let redacted = dump.redact_private();
+        eprintln!(
+            "{} the content of {} private entries",
+            "Redacted".green().bold(),
+            redacted
+        );
+    } else if dump.private_entry_count() > 0 {
+        eprintln!(
+            "{} {} contains the content of {} private entries, keep it as safe as the storage or export with --redact-private",
+            "WARNING:".red().bold(),
+            out.display(),
+            dump.private_entry_count()
+        );
+    }
+
+    let json = serde_json::to_string_pretty(&dump)?;
+    fs::write(out, json)?;
+    eprintln!(
+        "{} {} headers to {}",
+        "Exported".green().bold(),
+        dump.items.len(),
+        out.display()
+    );
+    Ok(())
+}
+
+/// Writes the chain of the JSON file written by chain_export() into the storage directory,
+/// once its headers are verified. Refuses storages that hold a chain already, unless `force`
+/// is set, in which case the imported chain replaces it.
+pub fn chain_import(dump_file: &PathBuf, storage: &PathBuf, force: bool) -> DefaultResult<()> {
+    let contents = fs::read_to_string(dump_file)?;
+    let dump = ChainDump::try_from(JsonString::from(contents))
+        .map_err(|err| format_err!("Could not read chain dump {:?}: {}", dump_file, err))?;
+
+    let storage_cas = cas(storage)?;
+    ensure!(
+        force || !holds_chain(&storage_cas).map_err(|err| format_err!("{}", err))?,
+        "Storage directory {:?} holds a chain already, import with --force to replace it",
+        storage
+    );
+    import_chain(&dump, &storage_cas).map_err(|err| format_err!("{}", err))?;
+    eprintln!(
+        "{} {} headers into {:?}",
+        "Imported".green().bold(),
+        dump.items.len(),
+        storage
+    );
+    Ok(())
+}
+
+fn cas(storage: &PathBuf) -> DefaultResult<Arc<RwLock<dyn ContentAddressableStorage>>> {
+    let cas_path = storage.join("cas");
+    fs::create_dir_all(&cas_path)?;
+    Ok(Arc::new(RwLock::new(FilesystemStorage::new(
+        cas_path.to_str().unwrap(),
+    )?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::call::call;
+    use holochain_core_types::entry::{entry_type::AppEntryType, Entry};
+    use tempfile::{tempdir, TempDir};
+    use test_utils::{
+        create_test_cap_with_fn_name, create_test_dna_with_cap, create_wasm_from_file,
+    };
+
+    /// A storage directory with a chain that `hc call` committed an entry to
+    fn storage_with_chain() -> (TempDir, PathBuf) {
+        let wasm = create_wasm_from_file(
+            "../container_api/wasm-test/target/wasm32-unknown-unknown/release/example_api_wasm.wasm",
+        );
+        let capability = create_test_cap_with_fn_name("commit_test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        let dir = tempdir().unwrap();
+        let dna_file = dir.path().join("bundle.json");
+        fs::write(&dna_file, String::from(JsonString::from(dna))).unwrap();
+        let storage = dir.path().join("storage");
+        call(
+            &storage,
+            &dna_file,
+            "test_zome",
+            None,
+            "commit_test",
+            "{}",
+            true,
+        )
+        .expect("call failed");
+        (dir, storage)
+    }
+
+    #[test]
+    fn chains_get_exported_and_imported() {
+        let (dir, storage) = storage_with_chain();
+        let out = dir.path().join("chain.json");
+        chain_export(&storage, &out, false).expect("export failed");
+        let exported = fs::read_to_string(&out).unwrap();
+
+        let imported_storage = dir.path().join("imported");
+        chain_import(&out, &imported_storage, false).expect("import failed");
+        let reexported = dir.path().join("reexported.json");
+        chain_export(&imported_storage, &reexported, false).unwrap();
+        assert_eq!(fs::read_to_string(&reexported).unwrap(), exported);
+
+        // the chain is there now
+        assert!(chain_import(&out, &imported_storage, false).is_err());
+        assert!(chain_import(&out, &imported_storage, true).is_ok());
+    }
+
+    #[test]
+    fn tampered_chains_do_not_get_imported() {
+        let (dir, storage) = storage_with_chain();
+        let out = dir.path().join("chain.json");
+        chain_export(&storage, &out, false).unwrap();
+        let mut dump =
+            ChainDump::try_from(JsonString::from(fs::read_to_string(&out).unwrap())).unwrap();
+        let top = dump.items.len() - 1;
+        dump.items[top].entry = Some(Entry::App(
+            AppEntryType::from("testEntryType"),
+            JsonString::from("tampered"),
+        ));
+        let tampered = dir.path().join("tampered.json");
+        fs::write(&tampered, serde_json::to_string(&dump).unwrap()).unwrap();
+
+        let imported_storage = dir.path().join("imported");
+        let result = chain_import(&tampered, &imported_storage, false);
+        assert!(result.is_err());
+        let message = format!("{}", result.unwrap_err());
+        assert!(message.contains(&format!("at position {} ", top)));
+        assert!(message.contains("does not match its entry"));
+        assert!(chain_export(&imported_storage, &out, false).is_err());
+    }
+
+    #[test]
+    fn storages_without_chain_can_not_be_exported() {
+        let dir = tempdir().unwrap();
+        let result = chain_export(
+            &dir.path().join("storage"),
+            &dir.path().join("chain.json"),
+            false,
+        );
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use crate::{cli::call::load_dna, error::DefaultResult};
+use colored::*;
+use holochain_core_types::dna::diff::DnaDiff;
+use std::path::PathBuf;
+
+/// Prints how the DNA in the `new` file differs from the one in the `old` file.
+/// With `check_compat`, fails if the new version can not replace the old one.
+pub fn diff(old: &PathBuf, new: &PathBuf, check_compat: bool) -> DefaultResult<()> {
+    let diff = load_dna(old)?.diff(&load_dna(new)?);
+
+    println!("{}", format_diff(&diff));
+
+    ensure!(
+        !(check_compat && diff.is_breaking()),
+        "{:?} is not compatible with {:?}",
+        new,
+        old
+    );
+    Ok(())
+}
+
+fn added(what: &str, name: &str) -> String {
+    format!("  {} {} {}", "+".green().bold(), what, name.green())
+}
+
+fn removed(what: &str, name: &str) -> String {
+    format!("  {} {} {}", "-".red().bold(), what, name.red())
+}
+
+fn changed(what: &str, name: &str, how: String, breaking: bool) -> String {
+    let marker = if breaking {
+        "~".red().bold()
+    } else {
+        "~".yellow().bold()
+    };
+    format!("  {} {} {}: {}", marker, what, name.yellow(), how)
+}
+
+/// A line per difference, marked `+` for additions, `-` for removals and `~` for changes
+pub fn format_diff(diff: &DnaDiff) -> String {
+    if diff.is_empty() {
+        return String::from("No differences");
+    }
+
+    let mut lines = Vec::new();
+    for zome in diff.added_zomes.iter() {
+        lines.push(added("zome", zome));
+    }
+    for zome in diff.removed_zomes.iter() {
+        lines.push(removed("zome", zome));
+    }
+    for (zome, zome_diff) in diff.changed_zomes.iter() {
+        for function in zome_diff.added_functions.iter() {
+            lines.push(added("function", &format!("{}/{}", zome, function)));
+        }
+        for function in zome_diff.removed_functions.iter() {
+            lines.push(removed("function", &format!("{}/{}", zome, function)));
+        }
+        for entry_type in zome_diff.added_entry_types.iter() {
+            lines.push(added("entry type", &format!("{}/{}", zome, entry_type)));
+        }
+        for entry_type in zome_diff.removed_entry_types.iter() {
+            lines.push(removed("entry type", &format!("{}/{}", zome, entry_type)));
+        }
+        for (entry_type, sharing) in zome_diff.changed_sharing.iter() {
+            lines.push(changed(
+                "sharing of entry type",
+                &format!("{}/{}", zome, entry_type),
+                format!("{:?} -> {:?}", sharing.old, sharing.new),
+                true,
+            ));
+        }
+        for permission in zome_diff.added_host_permissions.iter() {
+            lines.push(changed(
+                "host permissions of zome",
+                zome,
+                format!("+{}", permission),
+                true,
+            ));
+        }
+        for permission in zome_diff.removed_host_permissions.iter() {
+            lines.push(changed(
+                "host permissions of zome",
+                zome,
+                format!("-{}", permission),
+                false,
+            ));
+        }
+        if let Some(ref code) = zome_diff.changed_code {
+            lines.push(changed(
+                "code of zome",
+                zome,
+                format!("{} -> {}", code.old_hash.short(), code.new_hash.short()),
+                false,
+            ));
+        }
+    }
+    for property in diff.changed_properties.iter() {
+        lines.push(changed(
+            "property",
+            property,
+            String::from("changed"),
+            false,
+        ));
+    }
+
+    if diff.is_breaking() {
+        lines.push(format!("{}", "Breaking changes".red().bold()));
+    } else {
+        lines.push(format!("{}", "No breaking changes".green().bold()));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use holochain_core_types::{
+        dna::{zome::Zome, Dna},
+        json::JsonString,
+    };
+    use std::fs;
+    use tempfile::{tempdir, TempDir};
+
+    /// Writes an old DNA and a new one without its only zome
+    fn dna_files_with_removed_zome() -> (TempDir, PathBuf, PathBuf) {
+        let dir = tempdir().unwrap();
+        let mut old_dna = Dna::new();
+        old_dna.zomes.insert(String::from("blog"), Zome::default());
+        let mut new_dna = old_dna.clone();
+        new_dna.zomes.clear();
+
+        let old = dir.path().join("old.json");
+        let new = dir.path().join("new.json");
+        fs::write(&old, String::from(JsonString::from(old_dna))).unwrap();
+        fs::write(&new, String::from(JsonString::from(new_dna))).unwrap();
+        (dir, old, new)
+    }
+
+    #[test]
+    fn diff_fails_on_breaking_changes_only_when_checking_compat() {
+        let (_dir, old, new) = dna_files_with_removed_zome();
+
+        assert!(diff(&old, &new, false).is_ok());
+        assert!(diff(&old, &new, true).is_err());
+        // Adding the zome back is compatible
+        assert!(diff(&new, &old, true).is_ok());
+    }
+
+    #[test]
+    fn format_diff_lists_differences() {
+        colored::control::set_override(false);
+        let mut old_dna = Dna::new();
+        old_dna.zomes.insert(String::from("blog"), Zome::default());
+        let mut new_dna = Dna::new();
+        new_dna
+            .zomes
+            .insert(String::from("comments"), Zome::default());
+
+        assert_eq!(format_diff(&old_dna.diff(&old_dna)), "No differences");
+        assert_eq!(
+            format_diff(&old_dna.diff(&new_dna)),
+            "  + zome comments\n  - zome blog\nBreaking changes",
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use crate::{
+    cli::{call::load_dna, init::APP_CONFIG_FILE_NAME, package::Object},
+    error::DefaultResult,
+};
+use colored::*;
+use holochain_core_types::dna::Dna;
+use serde_json::{self, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const PROPERTIES_KEY: &str = "properties";
+
+/// Prints the name, description and properties of the packaged DNA,
+/// and the entry types of each of its zomes
+pub fn dna_show(dna_file: &PathBuf) -> DefaultResult<()> {
+    let dna = load_dna(dna_file)?;
+    println!("{}", format_dna_summary(&dna));
+    Ok(())
+}
+
+/// A line per field of the DNA, then the properties and the zomes with their entry types
+pub fn format_dna_summary(dna: &Dna) -> String {
+    let mut lines = vec![
+        format!("{} {}", "Name:".bold(), dna.name),
+        format!("{} {}", "Description:".bold(), dna.description),
+        format!("{} {}", "Version:".bold(), dna.version),
+    ];
+
+    lines.push(format!("{}", "Properties:".bold()));
+    if let Some(properties) = dna.properties.as_object() {
+        for (key, value) in properties.iter() {
+            lines.push(format!("  {}: {}", key, value));
+        }
+    }
+
+    lines.push(format!("{}", "Zomes:".bold()));
+    for (name, zome) in dna.zomes.iter() {
+        let entry_types: Vec<String> = zome
+            .entry_types
+            .keys()
+            .map(|entry_type| entry_type.to_string())
+            .collect();
+        lines.push(format!("  {} ({})", name, entry_types.join(", ")));
+    }
+    lines.join("\n")
+}
+
+/// Sets the name of the DNA in the app.json of the project
+pub fn dna_set_name(project: &Path, name: &str) -> DefaultResult<()> {
+    let mut app_config = read_app_config(project)?;
+    app_config.insert(String::from("name"), Value::String(name.to_string()));
+    write_app_config(project, &app_config)
+}
+
+/// Sets the description of the DNA in the app.json of the project
+pub fn dna_set_description(project: &Path, description: &str) -> DefaultResult<()> {
+    let mut app_config = read_app_config(project)?;
+    app_config.insert(
+        String::from("description"),
+        Value::String(description.to_string()),
+    );
+    write_app_config(project, &app_config)
+}
+
+/// Prints the property with the given key from the app.json of the project:
+/// strings as they are, other values as JSON
+pub fn property_get(project: &Path, key: &str) -> DefaultResult<()> {
+    let app_config = read_app_config(project)?;
+    let value = app_config
+        .get(PROPERTIES_KEY)
+        .and_then(|properties| properties.get(key))
+        .ok_or_else(|| format_err!("The DNA has no property {}", key))?;
+    match value {
+        Value::String(string) => println!("{}", string),
+        _ => println!("{}", serde_json::to_string_pretty(value)?),
+    }
+    Ok(())
+}
+
+/// Sets the property with the given key in the app.json of the project, creating its
+/// properties if it has none yet. See `parse_property_value()` for how the value is taken.
+pub fn property_set(project: &Path, key: &str, value: &str, json: bool) -> DefaultResult<()> {
+    // parse first, so that an invalid value leaves app.json as it is
+    let value = parse_property_value(key, value, json)?;
+    let mut app_config = read_app_config(project)?;
+    let properties = app_config
+        .entry(PROPERTIES_KEY.to_string())
+        .or_insert_with(|| Value::Object(Object::new()));
+    if properties.is_null() {
+        *properties = Value::Object(Object::new());
+    }
+    properties
+        .as_object_mut()
+        .ok_or_else(|| format_err!("The properties of the DNA are not an object"))?
+        .insert(key.to_string(), value);
+    write_app_config(project, &app_config)
+}
+
+/// The value of a property as given on the command line. With `json`, it has to be JSON,
+/// e.g. `[1, 2]` or `{"a": 1}`. Otherwise `true` and `false` are booleans, numbers are
+/// numbers and anything else is a string.
+pub fn parse_property_value(key: &str, value: &str, json: bool) -> DefaultResult<Value> {
+    if json {
+        return serde_json::from_str(value).map_err(|error| {
+            format_err!(
+                "Invalid JSON value for property {}: {}. Strings need to be quoted, e.g. '\"{}\"'",
+                key,
+                error,
+                value
+            )
+        });
+    }
+    Ok(match value {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => value
+            .parse::<i64>()
+            .map(Value::from)
+            .or_else(|_| value.parse::<u64>().map(Value::from))
+            .ok()
+            .or_else(|| {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|number| number.is_finite())
+                    .map(Value::from)
+            })
+            .unwrap_or_else(|| Value::String(value.to_string())),
+    })
+}
+
+fn read_app_config(project: &Path) -> DefaultResult<Object> {
+    let path = project.join(APP_CONFIG_FILE_NAME);
+    ensure!(
+        path.exists(),
+        "No {} in {:?}, is this a Holochain project?",
+        APP_CONFIG_FILE_NAME,
+        project
+    );
+    let contents = fs::read_to_string(&path)?;
+    serde_json::from_str(&contents)
+        .map_err(|error| format_err!("Could not read {:?}: {}", path, error))
+}
+
+fn write_app_config(project: &Path, app_config: &Object) -> DefaultResult<()> {
+    let contents = serde_json::to_string_pretty(app_config)?;
+    fs::write(project.join(APP_CONFIG_FILE_NAME), contents)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::package::DEFAULT_BUNDLE_FILE_NAME;
+    use assert_cmd::prelude::*;
+    use std::process::Command;
+    use tempfile::{Builder, TempDir};
+
+    fn project_with_app_config(app_config: &str) -> TempDir {
+        let project = Builder::new()
+            .prefix("org.holochain.test")
+            .tempdir()
+            .unwrap();
+        fs::write(project.path().join(APP_CONFIG_FILE_NAME), app_config).unwrap();
+        project
+    }
+
+    fn app_config(project: &TempDir) -> Value {
+        let contents = fs::read_to_string(project.path().join(APP_CONFIG_FILE_NAME)).unwrap();
+        serde_json::from_str(&contents).unwrap()
+    }
+
+    #[test]
+    fn property_values_get_their_type_inferred() {
+        for (input, expected) in vec![
+            ("true", json!(true)),
+            ("false", json!(false)),
+            ("10", json!(10)),
+            ("-3", json!(-3)),
+            ("2.5", json!(2.5)),
+            ("hello", json!("hello")),
+            ("NaN", json!("NaN")),
+            ("[1, 2]", json!("[1, 2]")),
+        ] {
+            assert_eq!(parse_property_value("key", input, false).unwrap(), expected);
+        }
+        assert_eq!(
+            parse_property_value("key", r#"{"a": [1, 2]}"#, true).unwrap(),
+            json!({"a": [1, 2]})
+        );
+        assert_eq!(
+            parse_property_value("key", "\"10\"", true).unwrap(),
+            json!("10")
+        );
+    }
+
+    #[test]
+    fn setting_a_property_creates_the_properties() {
+        let project = project_with_app_config(r#"{"name": "test", "properties": null}"#);
+
+        property_set(project.path(), "max_posts", "10", false).unwrap();
+        property_set(project.path(), "moderated", "true", false).unwrap();
+        property_set(project.path(), "max_posts", "20", false).unwrap();
+
+        assert_eq!(
+            app_config(&project),
+            json!({"name": "test", "properties": {"max_posts": 20, "moderated": true}})
+        );
+    }
+
+    #[test]
+    fn invalid_json_values_are_not_written() {
+        let project = project_with_app_config(r#"{"name": "test"}"#);
+
+        let error = property_set(project.path(), "tags", "[1, 2", true).unwrap_err();
+
+        assert!(error
+            .to_string()
+            .starts_with("Invalid JSON value for property tags"));
+        assert_eq!(app_config(&project), json!({"name": "test"}));
+    }
+
+    #[test]
+    fn sets_name_and_description() {
+        let project = project_with_app_config(r#"{"name": "test", "description": ""}"#);
+
+        dna_set_name(project.path(), "blog").unwrap();
+        dna_set_description(project.path(), "A blog").unwrap();
+
+        assert_eq!(
+            app_config(&project),
+            json!({"name": "blog", "description": "A blog"})
+        );
+    }
+
+    #[test]
+    fn summarizes_the_dna() {
+        colored::control::set_override(false);
+        let dna = Dna {
+            name: String::from("blog"),
+            description: String::from("A blog"),
+            version: String::from("0.1.0"),
+            properties: json!({"max_posts": 10}),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_dna_summary(&dna),
+            "Name: blog\nDescription: A blog\nVersion: 0.1.0\nProperties:\n  max_posts: 10\nZomes:",
+        );
+    }
+
+    #[test]
+    /// A property set on the command line ends up in the packaged DNA
+    fn set_property_survives_packaging() {
+        let project = Builder::new()
+            .prefix("org.holochain.test")
+            .tempdir()
+            .unwrap();
+
+        Command::main_binary()
+            .unwrap()
+            .args(&["init", project.path().to_str().unwrap()])
+            .assert()
+            .success();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(project.path())
+            .args(&["property", "set", "max_posts", "10"])
+            .assert()
+            .success();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(project.path())
+            .args(&["property", "set", "tags", "[\"news\"", "--json"])
+            .assert()
+            .failure();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(project.path())
+            .args(&["package"])
+            .assert()
+            .success();
+
+        let dna = load_dna(&project.path().join(DEFAULT_BUNDLE_FILE_NAME)).unwrap();
+        assert_eq!(dna.properties, json!({"max_posts": 10}));
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{cli::call::load_dna, error::DefaultResult};
+use colored::*;
+use holochain_core_types::{dna::build_info::BuildInfo, entry::addressing::dna_hash};
+use std::path::PathBuf;
+
+/// Prints the hash of the packaged DNA, and what it was built from if it was packaged
+/// with build info
+pub fn hash(dna_file: &PathBuf) -> DefaultResult<()> {
+    let dna = load_dna(dna_file)?;
+
+    println!("{} {}", "DNA hash:".bold(), dna_hash(&dna));
+    if let Some(ref build_info) = dna.build_info {
+        println!("{}", format_build_info(build_info));
+    }
+    Ok(())
+}
+
+/// A line per piece of build info, leaving out what is unknown
+pub fn format_build_info(build_info: &BuildInfo) -> String {
+    let mut lines = vec![format!("{} {}", "hc:".bold(), build_info.hc_version)];
+    for (zome, version) in build_info.hdk_versions.iter() {
+        lines.push(format!("{} {} ({})", "HDK:".bold(), version, zome));
+    }
+    if let Some(ref commit) = build_info.git_commit {
+        let dirty = match build_info.git_dirty {
+            Some(true) => " with uncommitted changes",
+            _ => "",
+        };
+        lines.push(format!("{} {}{}", "Git commit:".bold(), commit, dirty));
+    }
+    if let Some(ref built_at) = build_info.unhashed.built_at {
+        lines.push(format!("{} {}", "Built at:".bold(), built_at));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use holochain_core_types::dna::build_info::UnhashedBuildInfo;
+
+    #[test]
+    fn formats_what_is_known_of_the_build() {
+        colored::control::set_override(false);
+        let mut build_info = BuildInfo {
+            hc_version: String::from("0.0.2"),
+            hdk_versions: vec![(String::from("blog"), String::from("0.0.2"))]
+                .into_iter()
+                .collect(),
+            git_commit: Some(String::from("4720ab7")),
+            git_dirty: Some(true),
+            unhashed: UnhashedBuildInfo {
+                built_at: Some(String::from("2018-12-01T10:00:00+00:00")),
+            },
+        };
+        assert_eq!(
+            format_build_info(&build_info),
+            "hc: 0.0.2\nHDK: 0.0.2 (blog)\nGit commit: 4720ab7 with uncommitted changes\nBuilt at: 2018-12-01T10:00:00+00:00",
+        );
+
+        build_info.git_commit = None;
+        build_info.unhashed.built_at = None;
+        assert_eq!(
+            format_build_info(&build_info),
+            "hc: 0.0.2\nHDK: 0.0.2 (blog)"
+        );
+    }
+}
@@ -1,89 +1,75 @@
-use colored::*;
 use crate::{
     cli::{
-        package::{DEFAULT_BUNDLE_FILE_NAME, GITIGNORE_FILE_NAME, IGNORE_FILE_NAME},
+        package::{DEFAULT_BUNDLE_FILE_NAME, IGNORE_FILE_NAME},
+        template::{Template, TemplateSource, NAME_VARIABLE},
         test::{DIST_DIR_NAME, TEST_DIR_NAME},
     },
     config_files::App as AppConfig,
     error::DefaultResult,
+    util,
 };
+use colored::*;
 use serde_json;
 use std::{
-    fs::{self, File, OpenOptions},
+    fs::{self, File},
     io::Write,
     path::PathBuf,
 };
 
-fn create_test_file(
-    test_folder_path: &PathBuf,
-    test_file_name: &str,
-    test_file_contents: &str,
-) -> DefaultResult<()> {
-    let dest_filepath = test_folder_path.join(test_file_name);
-    let mut file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .open(dest_filepath)?;
-    file.write_all(test_file_contents.as_bytes())?;
-    Ok(())
-}
+pub const APP_CONFIG_FILE_NAME: &str = "app.json";
 
-fn setup_test_folder(path: &PathBuf, test_folder: &str) -> DefaultResult<()> {
-    let tests_path = path.join(test_folder);
-    fs::create_dir_all(tests_path.clone())?;
-    create_test_file(
-        &tests_path,
-        "index.js",
-        include_str!("js-tests-scaffold/index.js"),
-    )?;
-    create_test_file(
-        &tests_path,
-        "package.json",
-        include_str!("js-tests-scaffold/package.json"),
-    )?;
-    Ok(())
-}
+pub const ZOMES_DIR_NAME: &str = "zomes";
 
-pub fn init(path: &PathBuf) -> DefaultResult<()> {
+/// Initializes a new project from the given template.
+/// Every project gets an app.json, a .hcignore and a zomes folder, templates can bring
+/// their own app.json and .hcignore.
+pub fn init(
+    path: &PathBuf,
+    source: &TemplateSource,
+    vars: &[(String, String)],
+    force: bool,
+) -> DefaultResult<()> {
     if !path.exists() {
         fs::create_dir_all(&path)?;
     } else {
         let zomes_dir = fs::read_dir(&path)?;
 
-        if zomes_dir.count() > 0 {
-            bail!("directory is not empty");
+        if zomes_dir.count() > 0 && !force {
+            bail!("directory is not empty, use --force to initialize it anyway");
         }
     }
 
-    // create empty zomes folder
-    fs::create_dir_all(path.join("zomes"))?;
+    let template = Template::load(source)?;
+    let project_name = util::file_name_string(&path.canonicalize()?)?;
+    let variables = template.variables(vars, &project_name)?;
+    template.instantiate(&path, &variables)?;
+
+    fs::create_dir_all(path.join(ZOMES_DIR_NAME))?;
 
     // create base DNA json config
-    let app_config_file = File::create(path.join("app.json"))?;
-    serde_json::to_writer_pretty(app_config_file, &AppConfig::default())?;
-
-    // create a default .gitignore file with good defaults
-    let gitignore_file_path = path.join(GITIGNORE_FILE_NAME);
-    let mut gitignore_file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .open(gitignore_file_path)?;
-    let gitignore_starter = include_str!("git-scaffold/.gitignore");
-    gitignore_file.write_all(gitignore_starter.as_bytes())?;
+    let app_config_path = path.join(APP_CONFIG_FILE_NAME);
+    if !app_config_path.exists() {
+        let app_config = AppConfig {
+            name: variables[NAME_VARIABLE].clone(),
+            ..Default::default()
+        };
+        let app_config_file = File::create(app_config_path)?;
+        serde_json::to_writer_pretty(app_config_file, &app_config)?;
+    }
 
     // create a default .hcignore file with good defaults
-    let ignores = [
-        &DIST_DIR_NAME,
-        &TEST_DIR_NAME,
-        &DEFAULT_BUNDLE_FILE_NAME,
-        "README.md",
-    ]
+    let hcignore_path = path.join(&IGNORE_FILE_NAME);
+    if !hcignore_path.exists() {
+        let ignores = [
+            &DIST_DIR_NAME,
+            &TEST_DIR_NAME,
+            &DEFAULT_BUNDLE_FILE_NAME,
+            "README.md",
+        ]
         .join("\n");
-    let mut hcignore_file = File::create(path.join(&IGNORE_FILE_NAME))?;
-    hcignore_file.write_all(ignores.as_bytes())?;
-
-    // create a test folder with useful files
-    setup_test_folder(&path, &TEST_DIR_NAME)?;
+        let mut hcignore_file = File::create(hcignore_path)?;
+        hcignore_file.write_all(ignores.as_bytes())?;
+    }
 
     // CLI feedback
     println!(
@@ -98,6 +84,12 @@ pub fn init(path: &PathBuf) -> DefaultResult<()> {
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::cli::{
+        package::GITIGNORE_FILE_NAME,
+        template::{built_in_template_names, TEMPLATE_CONFIG_FILE_NAME},
+    };
+    use assert_cmd::prelude::*;
+    use std::process::Command;
     use tempfile::{Builder, TempDir};
 
     const HOLOCHAIN_TEST_PREFIX: &str = "org.holochain.test";
@@ -109,32 +101,109 @@ pub mod tests {
             .unwrap()
     }
 
+    fn init_with(dir_path_buf: &PathBuf, template: &str) {
+        Command::main_binary()
+            .unwrap()
+            .args(&[
+                "init",
+                dir_path_buf.to_str().unwrap(),
+                "--template",
+                template,
+                "--var",
+                "agent=bob",
+            ])
+            .assert()
+            .success();
+    }
+
+    fn package(dir_path_buf: &PathBuf) {
+        Command::main_binary()
+            .unwrap()
+            .current_dir(dir_path_buf)
+            .args(&["package"])
+            .assert()
+            .success();
+    }
+
     #[test]
     fn init_test() {
         let dir = gen_dir();
         let dir_path_buf = &dir.path().to_path_buf();
-        let result = init(dir_path_buf);
+        let result = init(dir_path_buf, &TemplateSource::default(), &[], false);
 
         assert!(result.is_ok());
-        assert!(dir_path_buf.join("zomes").exists());
-        assert!(dir_path_buf.join("app.json").exists());
+        assert!(dir_path_buf.join(ZOMES_DIR_NAME).exists());
+        assert!(dir_path_buf.join(APP_CONFIG_FILE_NAME).exists());
         assert!(dir_path_buf.join(IGNORE_FILE_NAME).exists());
         assert!(dir_path_buf.join(GITIGNORE_FILE_NAME).exists());
-        assert!(dir_path_buf.join(TEST_DIR_NAME).exists());
+        assert!(dir_path_buf.join(&TEST_DIR_NAME).join("index.js").exists());
+        assert!(dir_path_buf
+            .join(&TEST_DIR_NAME)
+            .join("package.json")
+            .exists());
+        assert!(!dir_path_buf.join(TEMPLATE_CONFIG_FILE_NAME).exists());
     }
 
     #[test]
-    fn setup_test_folder_test() {
+    fn init_substitutes_variables() {
+        let dir = gen_dir();
+        let dir_path_buf = &dir.path().join("chat");
+        let vars = vec![
+            ("agent".to_string(), "bob".to_string()),
+            ("zome".to_string(), "rooms".to_string()),
+        ];
+        init(
+            dir_path_buf,
+            &TemplateSource::BuiltIn("rust-zome-with-tests".into()),
+            &vars,
+            false,
+        )
+        .unwrap();
+
+        let app_config: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(dir_path_buf.join(APP_CONFIG_FILE_NAME)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(app_config["name"], "chat");
+        let zome_path = dir_path_buf.join(ZOMES_DIR_NAME).join("rooms");
+        assert!(zome_path.join("code").join("src").join("lib.rs").exists());
+        let test_file =
+            fs::read_to_string(dir_path_buf.join(&TEST_DIR_NAME).join("index.js")).unwrap();
+        assert!(test_file.contains(r#"app.call("rooms", "main", "hello", {name: "bob"})"#));
+    }
+
+    #[test]
+    fn init_refuses_non_empty_dir_without_force() {
         let dir = gen_dir();
         let dir_path_buf = &dir.path().to_path_buf();
-        setup_test_folder(dir_path_buf, &TEST_DIR_NAME).expect("Test folder not set up");
+        fs::write(dir_path_buf.join("README.md"), "my app").unwrap();
 
-        assert!(dir_path_buf.join(&TEST_DIR_NAME).join("index.js").exists());
-        assert!(
-            dir_path_buf
-                .join(&TEST_DIR_NAME)
-                .join("package.json")
-                .exists()
+        assert!(init(dir_path_buf, &TemplateSource::default(), &[], false).is_err());
+        assert!(!dir_path_buf.join(APP_CONFIG_FILE_NAME).exists());
+
+        init(dir_path_buf, &TemplateSource::default(), &[], true).unwrap();
+        assert!(dir_path_buf.join(APP_CONFIG_FILE_NAME).exists());
+        assert_eq!(
+            fs::read_to_string(dir_path_buf.join("README.md")).unwrap(),
+            "my app"
         );
     }
+
+    #[test]
+    fn init_unknown_template_fails() {
+        let dir = gen_dir();
+        let dir_path_buf = &dir.path().to_path_buf();
+        let source = TemplateSource::BuiltIn("unknown".into());
+        assert!(init(dir_path_buf, &source, &[], false).is_err());
+    }
+
+    #[test]
+    fn built_in_templates_package() {
+        for template in built_in_template_names() {
+            let dir = gen_dir();
+            let dir_path_buf = &dir.path().to_path_buf();
+            init_with(dir_path_buf, template);
+            package(dir_path_buf);
+        }
+    }
 }
@@ -0,0 +1,293 @@
+use crate::error::DefaultResult;
+use serde_json;
+use std::io::Write;
+use structopt::clap::{App, ArgSettings, Shell};
+
+/// The name hc gets completed by
+const BIN_NAME: &str = "hc";
+
+/// The flags clap gives every command, which tools know about anyway
+const CLAP_FLAGS: [&str; 2] = ["help", "version"];
+
+/// Completes the names of the zomes of the project in the working directory after `--zome`,
+/// anything else like the script clap generated
+const BASH_ZOME_COMPLETION: &str = r#"
+_hc_with_zomes() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    local prev="${COMP_WORDS[COMP_CWORD-1]}"
+    if [[ ( "${prev}" == "--zome" || "${prev}" == "-z" ) && -d zomes ]]; then
+        COMPREPLY=( $(compgen -W "$(ls zomes)" -- "${cur}") )
+        return 0
+    fi
+    _hc "$@"
+}
+
+complete -F _hc_with_zomes -o bashdefault -o default hc
+"#;
+
+const FISH_ZOME_COMPLETION: &str = r#"
+complete -c hc -n "__fish_seen_subcommand_from call" -s z -l zome -x -a "(ls zomes 2>/dev/null)"
+"#;
+
+/// Writes the completion script of the given shell, bash, zsh or fish, for the commands of
+/// `app` to `out`. The bash and fish scripts also complete the zome names of `hc call`
+/// from the `zomes` directory of the project.
+pub fn completions<W: Write>(mut app: App, shell: &str, out: &mut W) -> DefaultResult<()> {
+    let for_shell = match shell {
+        "bash" => Shell::Bash,
+        "zsh" => Shell::Zsh,
+        "fish" => Shell::Fish,
+        _ => bail!("Can't complete hc in {}, only in bash, zsh and fish", shell),
+    };
+    app.gen_completions_to(BIN_NAME, for_shell, out);
+    let zome_completion = match for_shell {
+        Shell::Bash => BASH_ZOME_COMPLETION,
+        Shell::Fish => FISH_ZOME_COMPLETION,
+        _ => "",
+    };
+    out.write_all(zome_completion.as_bytes())?;
+    Ok(())
+}
+
+/// Prints the command tree of `app` as JSON, see `command_tree()`
+pub fn introspect(app: &App, json: bool) -> DefaultResult<()> {
+    ensure!(json, "hc introspect only knows --json so far");
+    println!("{}", serde_json::to_string_pretty(&command_tree(app))?);
+    Ok(())
+}
+
+/// An argument of a command, see `command_tree()`
+#[derive(Serialize)]
+pub struct ArgInfo<'a> {
+    pub name: &'a str,
+    pub kind: &'a str,
+    pub long: Option<&'a str>,
+    pub short: Option<char>,
+    pub help: Option<&'a str>,
+    pub required: bool,
+    pub multiple: bool,
+    pub default: Option<String>,
+}
+
+/// A command with its arguments and subcommands, see `command_tree()`
+#[derive(Serialize)]
+pub struct CommandInfo<'a> {
+    pub name: &'a str,
+    pub aliases: Vec<&'a str>,
+    pub about: Option<&'a str>,
+    pub args: Vec<ArgInfo<'a>>,
+    pub subcommands: Vec<CommandInfo<'a>>,
+}
+
+/// The commands of `app` for tools that wrap hc: a command has its `name`, `aliases`,
+/// `about`, `args` and `subcommands`. An arg has its `name`, its `kind`, which is
+/// `positional`, `option` or `flag`, its `long` and `short` switch, its `help`, whether it
+/// is `required` and whether it can be given `multiple` times, and its `default` value.
+/// The positionals come first, the options and flags are sorted by name. Values are strings
+/// that the commands parse themselves, clap doesn't know what into.
+/// This reads what clap keeps about the commands, which is not part of its documented API,
+/// so that the tree is generated from the same definitions as the help texts are.
+pub fn command_tree<'a>(app: &'a App) -> CommandInfo<'a> {
+    let parser = &app.p;
+    let mut args: Vec<ArgInfo> = parser
+        .positionals
+        .values()
+        .map(|positional| {
+            let default = positional
+                .v
+                .default_val
+                .map(|value| value.to_string_lossy().to_string());
+            ArgInfo {
+                name: positional.b.name,
+                kind: "positional",
+                long: None,
+                short: None,
+                help: positional.b.help,
+                // an argument with a default value can be left out
+                required: positional.b.is_set(ArgSettings::Required) && default.is_none(),
+                multiple: positional.b.is_set(ArgSettings::Multiple),
+                default,
+            }
+        })
+        .collect();
+    let mut named: Vec<ArgInfo> = parser
+        .opts
+        .iter()
+        .map(|option| {
+            let default = option
+                .v
+                .default_val
+                .map(|value| value.to_string_lossy().to_string());
+            ArgInfo {
+                name: option.b.name,
+                kind: "option",
+                long: option.s.long,
+                short: option.s.short,
+                help: option.b.help,
+                required: option.b.is_set(ArgSettings::Required) && default.is_none(),
+                multiple: option.b.is_set(ArgSettings::Multiple),
+                default,
+            }
+        })
+        .chain(
+            parser
+                .flags
+                .iter()
+                .filter(|flag| !CLAP_FLAGS.contains(&flag.b.name))
+                .map(|flag| ArgInfo {
+                    name: flag.b.name,
+                    kind: "flag",
+                    long: flag.s.long,
+                    short: flag.s.short,
+                    help: flag.b.help,
+                    required: false,
+                    multiple: flag.b.is_set(ArgSettings::Multiple),
+                    default: None,
+                }),
+        )
+        .collect();
+    named.sort_by_key(|arg| arg.name);
+    args.append(&mut named);
+
+    CommandInfo {
+        name: &parser.meta.name,
+        aliases: parser
+            .meta
+            .aliases
+            .as_ref()
+            .map(|aliases| aliases.iter().map(|(alias, _visible)| *alias).collect())
+            .unwrap_or_default(),
+        about: parser.meta.about,
+        args,
+        subcommands: parser.subcommands.iter().map(command_tree).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_cmd::prelude::*;
+    use serde_json::Value;
+    use std::{fs, path::PathBuf, process::Command};
+    use structopt::StructOpt;
+    use tempfile::{Builder, TempDir};
+
+    // only its definitions are needed
+    #[allow(dead_code)]
+    #[derive(StructOpt)]
+    #[structopt(name = "tool", about = "A tool")]
+    enum TestCli {
+        #[structopt(name = "call", alias = "c", about = "Calls a function")]
+        Call {
+            #[structopt(help = "The storage", parse(from_os_str))]
+            storage: Option<PathBuf>,
+            #[structopt(long = "zome", short = "z", help = "The zome")]
+            zome: String,
+            #[structopt(long = "raw", help = "Prints the result raw")]
+            raw: bool,
+            #[structopt(long = "params", help = "The params", default_value = "{}")]
+            params: String,
+        },
+    }
+
+    fn gen_dir() -> TempDir {
+        Builder::new().prefix("introspect").tempdir().unwrap()
+    }
+
+    #[test]
+    fn command_tree_has_the_commands_with_their_args() {
+        let expected: Value = serde_json::from_str(
+            r#"{
+                "name": "tool",
+                "aliases": [],
+                "about": "A tool",
+                "args": [],
+                "subcommands": [{
+                    "name": "call",
+                    "aliases": ["c"],
+                    "about": "Calls a function",
+                    "args": [
+                        {"name": "storage", "kind": "positional", "long": null, "short": null, "help": "The storage", "required": false, "multiple": false, "default": null},
+                        {"name": "params", "kind": "option", "long": "params", "short": null, "help": "The params", "required": false, "multiple": false, "default": "{}"},
+                        {"name": "raw", "kind": "flag", "long": "raw", "short": null, "help": "Prints the result raw", "required": false, "multiple": false, "default": null},
+                        {"name": "zome", "kind": "option", "long": "zome", "short": "z", "help": "The zome", "required": true, "multiple": false, "default": null}
+                    ],
+                    "subcommands": []
+                }]
+            }"#,
+        )
+        .unwrap();
+        let app = TestCli::clap();
+        assert_eq!(serde_json::to_value(command_tree(&app)).unwrap(), expected);
+    }
+
+    #[test]
+    fn introspect_command_dumps_the_command_tree_of_hc() {
+        let output = Command::main_binary()
+            .unwrap()
+            .args(&["introspect", "--json"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let tree: Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(tree["name"], "hc");
+        let commands = tree["subcommands"].as_array().unwrap();
+        for name in &["call", "completions", "introspect", "run", "test"] {
+            assert!(
+                commands.iter().any(|command| command["name"] == *name),
+                "{} should be a command",
+                name
+            );
+        }
+        let call = commands
+            .iter()
+            .find(|command| command["name"] == "call")
+            .unwrap();
+        assert_eq!(call["aliases"], json!(["c"]));
+        let zome = call["args"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|arg| arg["name"] == "zome")
+            .unwrap();
+        assert_eq!(zome["long"], "zome");
+        assert_eq!(zome["required"], true);
+
+        Command::main_binary()
+            .unwrap()
+            .args(&["introspect"])
+            .assert()
+            .failure();
+    }
+
+    #[test]
+    fn bash_completion_script_parses() {
+        let output = Command::main_binary()
+            .unwrap()
+            .args(&["completions", "bash"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let script = String::from_utf8(output.stdout).unwrap();
+        assert!(script.contains("_hc_with_zomes"));
+
+        let dir = gen_dir();
+        let script_path = dir.path().join("hc.bash");
+        fs::write(&script_path, script).unwrap();
+        Command::new("bash")
+            .arg("-n")
+            .arg(&script_path)
+            .assert()
+            .success();
+    }
+
+    #[test]
+    fn completes_only_known_shells() {
+        let mut script = Vec::new();
+        completions(TestCli::clap(), "fish", &mut script).unwrap();
+        assert!(String::from_utf8(script)
+            .unwrap()
+            .contains("complete -c hc"));
+        assert!(completions(TestCli::clap(), "cmd.exe", &mut Vec::new()).is_err());
+    }
+}
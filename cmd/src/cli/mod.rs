@@ -1,17 +1,32 @@
 mod agent;
+mod call;
+mod chain;
+mod diff;
+mod dna;
 mod generate;
+mod hash;
 mod init;
+mod introspect;
 pub mod package;
+mod repl;
 mod run;
 mod scaffold;
+mod template;
 mod test;
 mod test_context;
 
 pub use self::{
     agent::agent,
+    call::{call, format_call_result, DEFAULT_STORAGE_DIR},
+    chain::{chain_export, chain_import},
+    diff::diff,
+    dna::{dna_set_description, dna_set_name, dna_show, property_get, property_set},
     generate::generate,
+    hash::hash,
     init::init,
-    package::{package, unpack},
+    introspect::{completions, introspect},
+    package::{package, package_workspace, unpack},
     run::run,
-    test::{test, TEST_DIR_NAME},
+    template::{parse_variable, TemplateSource},
+    test::{test, RemoteContainer, TEST_DIR_NAME},
 };
@@ -1,14 +1,30 @@
 use base64;
+use chrono::Utc;
 use colored::*;
-use crate::{cli::test_context::test_context, config_files::Build, error::DefaultResult, util};
+use crate::{
+    cli::{call::load_dna, test_context::test_context},
+    config_files::{Build, PackagedMember, Workspace, WorkspaceManifest, WORKSPACE_FILE_NAME},
+    error::DefaultResult,
+    util,
+};
 use holochain_core::nucleus::{ribosome, ZomeFnCall};
+use holochain_core_types::{
+    dna::{
+        build_info::{BuildInfo, UnhashedBuildInfo},
+        MANIFEST_VERSION,
+    },
+    entry::addressing::dna_hash,
+};
 use ignore::WalkBuilder;
 use serde_json::{self, Map, Value};
 use std::{
+    collections::BTreeMap,
     fs::{self, File},
     io::{Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
 };
+use toml;
 
 pub const CODE_DIR_NAME: &str = "code";
 
@@ -22,6 +38,14 @@ pub const WASM_FILE_EXTENSION: &str = "wasm";
 
 pub const DEFAULT_BUNDLE_FILE_NAME: &str = "bundle.json";
 
+pub const BUILD_INFO_SECTION_NAME: &str = "build_info";
+
+pub const MANIFEST_VERSION_FIELD_NAME: &str = "manifest_version";
+
+pub const CARGO_LOCK_FILE_NAME: &str = "Cargo.lock";
+
+pub const HDK_CRATE_NAME: &str = "hdk";
+
 pub const META_FILE_ID: &str = "file";
 pub const META_DIR_ID: &str = "dir";
 pub const META_BIN_ID: &str = "bin";
@@ -44,11 +68,31 @@ impl Packager {
     pub fn package(strip_meta: bool, output: Option<PathBuf>) -> DefaultResult<()> {
         let output = output.unwrap_or_else(|| PathBuf::from(DEFAULT_BUNDLE_FILE_NAME));
 
-        Packager::new(strip_meta).run(&output)
+        Packager::new(strip_meta).run(&PathBuf::from("."), &output)
+    }
+
+    /// Packages the member of a workspace in `member_dir` and returns the hash of its DNA
+    fn package_member(&self, member_dir: &Path, output: &Path) -> DefaultResult<String> {
+        ensure!(member_dir.is_dir(), "{:?} is not a directory", member_dir);
+
+        self.run(&member_dir.to_path_buf(), &output.to_path_buf())?;
+
+        let dna = load_dna(&output.to_path_buf())?;
+        Ok(dna_hash(&dna).to_string())
     }
 
-    fn run(&self, output: &PathBuf) -> DefaultResult<()> {
-        let dir_obj_bundle = self.bundle_recurse(&PathBuf::from("."))?;
+    fn run(&self, root: &PathBuf, output: &PathBuf) -> DefaultResult<()> {
+        let mut dir_obj_bundle = self.bundle_recurse(root)?;
+
+        let build_info = build_info(root, &dir_obj_bundle);
+        dir_obj_bundle.insert(
+            BUILD_INFO_SECTION_NAME.into(),
+            serde_json::to_value(build_info)?,
+        );
+        dir_obj_bundle.insert(
+            MANIFEST_VERSION_FIELD_NAME.into(),
+            Value::from(MANIFEST_VERSION),
+        );
 
         let out_file = File::create(&output)?;
 
@@ -120,20 +164,12 @@ impl Packager {
 
                 main_tree.insert(file_name.clone(), encoded_content.into());
             } else if node.is_dir() {
-                // a folder within this folder has a .build in it, meaning this node
+                // a folder within this folder has code to build in it, meaning this node
                 // should build the json and insert it for this zome
-                if let Some(dir_with_code) = node
-                    .read_dir()?
-                    .filter(|e| e.is_ok())
-                    .map(|e| e.unwrap().path())
-                    .filter(|path| path.is_dir())
-                    .find(|path| path.join(BUILD_CONFIG_FILE_NAME).exists())
-                {
+                if let Some((dir_with_code, build)) = zome_code(&node)? {
                     meta_tree.insert(file_name.clone(), META_DIR_ID.into());
 
-                    let build = Build::from_file(dir_with_code.join(BUILD_CONFIG_FILE_NAME))?;
-
-                    let wasm = build.run(&dir_with_code)?;
+                    let wasm = build.run(&dir_with_code, &file_name)?;
 
                     let wasm_binary = base64::decode(&wasm)?;
 
@@ -178,7 +214,7 @@ impl Packager {
 
                     let build = Build::from_file(build_config)?;
 
-                    let wasm = build.run(&node)?;
+                    let wasm = build.run(&node, &file_name)?;
 
                     // here insert the wasm itself
                     main_tree.insert(file_name.clone(), json!({ "code": wasm }));
@@ -210,6 +246,153 @@ pub fn package(strip_meta: bool, output: Option<PathBuf>) -> DefaultResult<()> {
     Packager::package(strip_meta, output)
 }
 
+/// Packages every member of the workspace at `root` into its dist folder and writes
+/// the manifest of the bundles next to them.
+/// A member that fails does not stop the others, the failures get reported together
+/// once all members had their turn.
+pub fn package_workspace(root: &Path, strip_meta: bool) -> DefaultResult<WorkspaceManifest> {
+    let workspace = Workspace::from_dir(root)?
+        .ok_or_else(|| format_err!("{:?} has no {}", root, WORKSPACE_FILE_NAME))?;
+    fs::create_dir_all(WorkspaceManifest::path(root).parent().unwrap())?;
+
+    let packager = Packager::new(strip_meta);
+    let mut members = BTreeMap::new();
+    let mut failures = Vec::new();
+    for member in &workspace.members {
+        let bundle_path = Workspace::bundle_path(member);
+        match packager.package_member(&root.join(member), &root.join(&bundle_path)) {
+            Ok(hash) => {
+                members.insert(
+                    member.clone(),
+                    PackagedMember {
+                        file: bundle_path,
+                        hash,
+                    },
+                );
+            }
+            Err(error) => failures.push(format!("{}: {}", member, error)),
+        }
+    }
+    ensure!(
+        failures.is_empty(),
+        "packaging {} of {} workspace members failed:\n{}",
+        failures.len(),
+        workspace.members.len(),
+        failures.join("\n")
+    );
+
+    let manifest = WorkspaceManifest {
+        members,
+        bridges: workspace.bridges,
+    };
+    let manifest_path = WorkspaceManifest::path(root);
+    manifest.save_as(&manifest_path)?;
+
+    // CLI feedback
+    println!(
+        "{} workspace manifest at {:?}",
+        "Created".green().bold(),
+        manifest_path
+    );
+
+    Ok(manifest)
+}
+
+/// The folder with the code of the zome in `zome_dir` and how to build it:
+/// a folder with a .build in it, or else the code folder if it is a Rust crate,
+/// which then gets the default build of Rust
+fn zome_code(zome_dir: &Path) -> DefaultResult<Option<(PathBuf, Build)>> {
+    if let Some(dir_with_code) = zome_dir
+        .read_dir()?
+        .filter(|e| e.is_ok())
+        .map(|e| e.unwrap().path())
+        .filter(|path| path.is_dir())
+        .find(|path| path.join(BUILD_CONFIG_FILE_NAME).exists())
+    {
+        let build = Build::from_file(dir_with_code.join(BUILD_CONFIG_FILE_NAME))?;
+        return Ok(Some((dir_with_code, build)));
+    }
+
+    let code_dir = zome_dir.join(CODE_DIR_NAME);
+    Ok(Build::from_dir(&code_dir)?.map(|build| (code_dir, build)))
+}
+
+/// Where the DNA packaged from the project at `root` into `bundle` comes from
+fn build_info(root: &Path, bundle: &Object) -> BuildInfo {
+    let hdk_versions = bundle
+        .get("zomes")
+        .and_then(Value::as_object)
+        .map(|zomes| {
+            zomes
+                .keys()
+                .filter_map(|zome| {
+                    let code_dir = root.join("zomes").join(zome).join(CODE_DIR_NAME);
+                    // zomes can share the Cargo.lock of a workspace at the root
+                    hdk_version(&code_dir.join(CARGO_LOCK_FILE_NAME))
+                        .or_else(|| hdk_version(&root.join(CARGO_LOCK_FILE_NAME)))
+                        .map(|version| (zome.clone(), version))
+                })
+                .collect()
+        })
+        .unwrap_or_else(BTreeMap::new);
+
+    BuildInfo {
+        hc_version: env!("CARGO_PKG_VERSION").to_string(),
+        hdk_versions,
+        git_commit: git_output(root, &["rev-parse", "HEAD"]),
+        git_dirty: git_output(root, &["status", "--porcelain"]).map(|status| !status.is_empty()),
+        unhashed: UnhashedBuildInfo {
+            built_at: Some(Utc::now().to_rfc3339()),
+        },
+    }
+}
+
+#[derive(Deserialize)]
+struct CargoLock {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+}
+
+/// The version of the HDK locked in the given Cargo.lock, with where it came from
+/// unless that is crates.io
+fn hdk_version(cargo_lock: &Path) -> Option<String> {
+    let contents = fs::read_to_string(cargo_lock).ok()?;
+    let lock: CargoLock = toml::from_str(&contents).ok()?;
+    let hdk = lock
+        .package
+        .into_iter()
+        .find(|package| package.name == HDK_CRATE_NAME)?;
+    match hdk.source {
+        Some(ref source) if !source.starts_with("registry+") => {
+            Some(format!("{} ({})", hdk.version, source))
+        }
+        _ => Some(hdk.version),
+    }
+}
+
+/// The trimmed output of the git command, None if the project is not in a git repository
+/// or git is not installed
+fn git_output(root: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        None
+    }
+}
+
 pub fn unpack(path: &PathBuf, to: &PathBuf) -> DefaultResult<()> {
     ensure!(path.is_file(), "argument \"path\" doesn't point to a file");
 
@@ -220,7 +403,11 @@ pub fn unpack(path: &PathBuf, to: &PathBuf) -> DefaultResult<()> {
     ensure!(to.is_dir(), "argument \"to\" doesn't point to a directory");
 
     let raw_bundle_content = fs::read_to_string(&path)?;
-    let bundle_content: Object = serde_json::from_str(&raw_bundle_content)?;
+    let mut bundle_content: Object = serde_json::from_str(&raw_bundle_content)?;
+    // packaging the unpacked project again creates new build info
+    // and writes the manifest version of the hc doing it
+    bundle_content.remove(BUILD_INFO_SECTION_NAME);
+    bundle_content.remove(MANIFEST_VERSION_FIELD_NAME);
 
     unpack_recurse(bundle_content, &to)?;
 
@@ -291,12 +478,28 @@ fn unpack_recurse(mut obj: Object, to: &PathBuf) -> DefaultResult<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{config_files::CARGO_FILE_NAME, error::MissingArtifact};
     use assert_cmd::prelude::*;
     use std::process::Command;
     use tempfile::{Builder, TempDir};
 
     const HOLOCHAIN_TEST_PREFIX: &str = "org.holochain.test";
 
+    /// The header of an empty WASM module
+    const FIXTURE_WASM: &[u8] = b"\0asm\x01\0\0\0";
+
+    const CARGO_LOCK: &str = r#"
+[[package]]
+name = "hdk"
+version = "0.0.2"
+source = "git+https://github.com/holochain/holochain-rust?branch=develop#4720ab7"
+
+[[package]]
+name = "serde"
+version = "1.0.80"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
     fn gen_dir() -> TempDir {
         Builder::new()
             .prefix(HOLOCHAIN_TEST_PREFIX)
@@ -304,6 +507,173 @@ mod tests {
             .unwrap()
     }
 
+    #[test]
+    fn build_info_has_hdk_versions_of_zomes() {
+        let project = gen_dir();
+        let code_dir = project
+            .path()
+            .join("zomes")
+            .join("blog")
+            .join(CODE_DIR_NAME);
+        fs::create_dir_all(&code_dir).unwrap();
+        fs::write(code_dir.join(CARGO_LOCK_FILE_NAME), CARGO_LOCK).unwrap();
+        let bundle: Object = serde_json::from_str(r#"{"zomes":{"blog":{},"chat":{}}}"#).unwrap();
+
+        let build_info = build_info(project.path(), &bundle);
+
+        assert_eq!(build_info.hc_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(
+            build_info.hdk_versions.get("blog"),
+            Some(&String::from(
+                "0.0.2 (git+https://github.com/holochain/holochain-rust?branch=develop#4720ab7)"
+            ))
+        );
+        // no Cargo.lock, no HDK version
+        assert_eq!(build_info.hdk_versions.get("chat"), None);
+        assert!(build_info.unhashed.built_at.is_some());
+    }
+
+    /// A project with a folder whose .build copies a fixture WASM file in two steps
+    fn project_with_copied_wasm(copy: bool) -> TempDir {
+        let project = gen_dir();
+        let code_dir = project.path().join("dummy");
+        fs::create_dir_all(&code_dir).unwrap();
+        fs::write(code_dir.join("fixture.wasm"), FIXTURE_WASM).unwrap();
+
+        let mut build = Build::with_artifact("out/dummy.wasm").cmd("mkdir", &["-p", "out"]);
+        if copy {
+            build = build.cmd("cp", &["fixture.wasm", "out/dummy.wasm"]);
+        }
+        build
+            .save_as(code_dir.join(BUILD_CONFIG_FILE_NAME))
+            .unwrap();
+        project
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn package_runs_build_steps_and_bundles_the_artifact() {
+        let project = project_with_copied_wasm(true);
+
+        let bundle = Packager::new(false)
+            .bundle_recurse(&project.path().to_path_buf())
+            .unwrap();
+
+        assert_eq!(
+            bundle["dummy"],
+            json!({ "code": base64::encode(FIXTURE_WASM) })
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn package_fails_if_the_build_steps_produce_no_artifact() {
+        let project = project_with_copied_wasm(false);
+
+        let error = Packager::new(false)
+            .bundle_recurse(&project.path().to_path_buf())
+            .unwrap_err();
+
+        let missing_artifact = error.downcast_ref::<MissingArtifact>().unwrap();
+        assert_eq!(missing_artifact.zome, "dummy");
+        assert_eq!(
+            missing_artifact.path,
+            project.path().join("dummy").join("out/dummy.wasm")
+        );
+    }
+
+    #[test]
+    fn zome_code_falls_back_to_the_rust_build() {
+        let project = gen_dir();
+        let zome_dir = project.path().join("zomes").join("blog");
+        let code_dir = zome_dir.join(CODE_DIR_NAME);
+        fs::create_dir_all(&code_dir).unwrap();
+        assert!(zome_code(&zome_dir).unwrap().is_none());
+
+        fs::write(
+            code_dir.join(CARGO_FILE_NAME),
+            "[package]\nname = \"blog\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            zome_code(&zome_dir).unwrap(),
+            Some((code_dir, Build::rust("blog")))
+        );
+    }
+
+    /// A workspace with a main and an admin DNA, the admin one bridging to the main one
+    fn fixture_workspace(members: &[&str]) -> TempDir {
+        let workspace = gen_dir();
+        for member in &["main", "admin"] {
+            let member_dir = workspace.path().join(member);
+            fs::create_dir_all(&member_dir).unwrap();
+            fs::write(
+                member_dir.join("app.json"),
+                json!({ "name": member, "uuid": format!("{}-uuid", member) }).to_string(),
+            )
+            .unwrap();
+        }
+        let members: Vec<String> = members.iter().map(|m| format!("{:?}", m)).collect();
+        fs::write(
+            workspace.path().join(WORKSPACE_FILE_NAME),
+            format!(
+                "members = [{}]\n\n[[bridges]]\ncaller = \"admin\"\ncallee = \"main\"\n",
+                members.join(", ")
+            ),
+        )
+        .unwrap();
+        workspace
+    }
+
+    #[test]
+    fn package_workspace_bundles_every_member() {
+        let workspace = fixture_workspace(&["main", "admin"]);
+
+        let manifest = package_workspace(workspace.path(), false).unwrap();
+
+        assert_eq!(
+            manifest.members.keys().collect::<Vec<_>>(),
+            vec!["admin", "main"]
+        );
+        for (member, packaged) in manifest.members.iter() {
+            assert_eq!(packaged.file, Workspace::bundle_path(member));
+            let dna = load_dna(&workspace.path().join(&packaged.file)).unwrap();
+            assert_eq!(&dna.name, member);
+            assert_eq!(packaged.hash, dna_hash(&dna).to_string());
+        }
+        assert_ne!(
+            manifest.members["main"].hash,
+            manifest.members["admin"].hash
+        );
+        assert_eq!(manifest.bridges.len(), 1);
+
+        assert_eq!(
+            WorkspaceManifest::from_file(WorkspaceManifest::path(workspace.path())).unwrap(),
+            manifest
+        );
+    }
+
+    #[test]
+    fn package_workspace_reports_all_failed_members() {
+        let workspace = fixture_workspace(&["main", "missing", "admin", "gone"]);
+
+        let error = package_workspace(workspace.path(), false).unwrap_err();
+
+        let message = error.to_string();
+        assert!(message.starts_with("packaging 2 of 4 workspace members failed:\nmissing: "));
+        assert!(message.contains("\ngone: "));
+        // the other members got packaged, but without a manifest
+        assert!(workspace
+            .path()
+            .join(Workspace::bundle_path("main"))
+            .is_file());
+        assert!(workspace
+            .path()
+            .join(Workspace::bundle_path("admin"))
+            .is_file());
+        assert!(!WorkspaceManifest::path(workspace.path()).exists());
+    }
+
     #[test]
     fn package_and_unpack_isolated() {
         const DEFAULT_BUNDLE_FILE_NAME: &str = "bundle.json";
@@ -326,6 +696,13 @@ mod tests {
                 .current_dir(&temp_dir_path)
                 .assert()
                 .success();
+
+            let bundle: Object =
+                serde_json::from_str(&fs::read_to_string(&bundle_file_path).unwrap()).unwrap();
+            assert_eq!(
+                bundle.get(MANIFEST_VERSION_FIELD_NAME),
+                Some(&json!(MANIFEST_VERSION))
+            );
         }
 
         fn unpack(shared_file_path: &PathBuf) {
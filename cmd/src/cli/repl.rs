@@ -0,0 +1,679 @@
+use crate::{
+    cli::call::{find_capability, format_call_result},
+    error::DefaultResult,
+};
+use holochain_container_api::{container::Container, interface::InstanceMap, Holochain};
+use holochain_core::state::PendingWork;
+use holochain_core_types::{cas::content::AddressableContent, chain_header::ChainHeader, dna::Dna};
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter, Editor,
+    Helper,
+};
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    thread,
+    time::Duration,
+};
+
+/// How many of its latest events every instance keeps for `watch`
+pub const SIGNAL_BUFFER_SIZE: usize = 100;
+
+const WATCH_INTERVAL_MS: u64 = 200;
+
+/// The commands of the console with their usage and what they do
+const COMMANDS: &[(&str, &str)] = &[
+    ("instances", "Lists the instances with their status and DNA"),
+    (
+        "call <instance> <zome> <function> [<params as JSON>]",
+        "Calls a zome function and prints its result",
+    ),
+    (
+        "chain <instance>",
+        "Prints the source chain of the instance",
+    ),
+    (
+        "status",
+        "Prints how far every instance got in the startup, how long its chain is and what it waits on",
+    ),
+    (
+        "watch <instance>",
+        "Prints the events and new chain entries of the instance until Enter is pressed",
+    ),
+    ("help", "Lists the commands"),
+    ("exit", "Stops the container"),
+];
+
+/// A command of the console. IDs with spaces go in double quotes, like in
+/// `call "app spec instance" blog create_post {"content": "Hello"}`.
+#[derive(Debug, PartialEq)]
+pub enum ReplCommand {
+    Instances,
+    Call {
+        instance: String,
+        zome: String,
+        function: String,
+        params: String,
+    },
+    Chain {
+        instance: String,
+    },
+    Status,
+    Watch {
+        instance: String,
+    },
+    Help,
+    Exit,
+}
+
+/// A word of a command line and where it is in the line
+#[derive(Debug, PartialEq)]
+struct Word {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+/// Opens a console on the running container, see `ReplCommand` for what it can do.
+/// Returns when it gets closed with `exit` or Ctrl-D.
+/// The history of the console is kept in ~/.holochain/repl_history.
+pub fn repl(container: &Container) -> DefaultResult<()> {
+    let mut rl = Editor::<ReplHelper>::new();
+    rl.set_helper(Some(ReplHelper {
+        completions: Completions::new(&container.instances),
+    }));
+    let history = history_file();
+    if let Some(ref file) = history {
+        // there is no history before the first session
+        let _ = rl.load_history(file);
+    }
+    println!("Type 'help' for the commands of the console, Tab completes them");
+
+    loop {
+        let line = match rl.readline("hc> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(error) => return Err(error.into()),
+        };
+        rl.add_history_entry(line.as_str());
+        let result = match parse_command(&line) {
+            Ok(None) => continue,
+            Ok(Some(ReplCommand::Exit)) => break,
+            Ok(Some(ReplCommand::Watch { instance })) => watch(container, &instance),
+            Ok(Some(command)) => execute(container, &command).map(|output| println!("{}", output)),
+            Err(error) => Err(error),
+        };
+        if let Err(error) = result {
+            eprintln!("{}", error);
+        }
+    }
+
+    if let Some(ref file) = history {
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        rl.save_history(file)?;
+    }
+    Ok(())
+}
+
+fn history_file() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".holochain/repl_history"))
+}
+
+/// Parses a line of the console, empty lines are no command
+pub fn parse_command(line: &str) -> Result<Option<ReplCommand>, String> {
+    let words = words(line);
+    let name = match words.first() {
+        Some(word) => word.text.as_str(),
+        None => return Ok(None),
+    };
+    let args: Vec<String> = words[1..].iter().map(|word| word.text.clone()).collect();
+    let command = match (name, args.len()) {
+        ("instances", 0) => ReplCommand::Instances,
+        ("status", 0) => ReplCommand::Status,
+        ("help", 0) => ReplCommand::Help,
+        ("exit", 0) | ("quit", 0) => ReplCommand::Exit,
+        ("chain", 1) => ReplCommand::Chain {
+            instance: args[0].clone(),
+        },
+        ("watch", 1) => ReplCommand::Watch {
+            instance: args[0].clone(),
+        },
+        ("call", count) if count >= 3 => {
+            // the parameters are the rest of the line as it is, JSON has spaces and quotes too
+            let params = line[words[3].end..].trim();
+            ReplCommand::Call {
+                instance: args[0].clone(),
+                zome: args[1].clone(),
+                function: args[2].clone(),
+                params: if params.is_empty() {
+                    "{}".to_string()
+                } else {
+                    params.to_string()
+                },
+            }
+        }
+        _ => {
+            return Err(match usage(name) {
+                Some(usage) => format!("Usage: {}", usage),
+                None => format!("Unknown command {}, type 'help' for the commands", name),
+            });
+        }
+    };
+    Ok(Some(command))
+}
+
+fn usage(name: &str) -> Option<&'static str> {
+    COMMANDS
+        .iter()
+        .map(|(usage, _)| *usage)
+        .find(|usage| usage.split(' ').next() == Some(name))
+}
+
+/// The words of the line, words in double quotes can hold spaces
+fn words(line: &str) -> Vec<Word> {
+    let new_word = |start| Word {
+        start,
+        end: start,
+        text: String::new(),
+    };
+    let mut words = Vec::new();
+    let mut current: Option<Word> = None;
+    let mut in_quotes = false;
+    for (index, c) in line.char_indices() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+            current.get_or_insert_with(|| new_word(index));
+        } else if c.is_whitespace() && !in_quotes {
+            if let Some(mut word) = current.take() {
+                word.end = index;
+                words.push(word);
+            }
+        } else {
+            current.get_or_insert_with(|| new_word(index)).text.push(c);
+        }
+    }
+    if let Some(mut word) = current {
+        word.end = line.len();
+        words.push(word);
+    }
+    words
+}
+
+/// Runs every command but `watch` and `exit`, and returns what it prints
+pub fn execute(container: &Container, command: &ReplCommand) -> Result<String, String> {
+    match command {
+        ReplCommand::Instances => Ok(list_instances(container)),
+        ReplCommand::Status => Ok(status(container)),
+        ReplCommand::Chain { instance } => {
+            let hc = instance_by_id(container, instance)?;
+            let dump = hc.read().unwrap().dump_state();
+            dump.map_err(|error| error.to_string())
+        }
+        ReplCommand::Call {
+            instance,
+            zome,
+            function,
+            params,
+        } => call(container, instance, zome, function, params),
+        ReplCommand::Help => Ok(COMMANDS
+            .iter()
+            .map(|(usage, description)| format!("{}\n    {}", usage, description))
+            .collect::<Vec<String>>()
+            .join("\n")),
+        ReplCommand::Watch { .. } | ReplCommand::Exit => Ok(String::new()),
+    }
+}
+
+fn instance_by_id(container: &Container, id: &str) -> Result<Arc<RwLock<Holochain>>, String> {
+    container.instances.get(id).cloned().ok_or_else(|| {
+        format!(
+            "No instance {} in the container, type 'instances' for the list",
+            id
+        )
+    })
+}
+
+fn instance_dna(hc: &Arc<RwLock<Holochain>>) -> Option<Dna> {
+    hc.read()
+        .unwrap()
+        .state()
+        .ok()
+        .and_then(|state| state.nucleus().dna())
+}
+
+fn sorted_ids(container: &Container) -> Vec<String> {
+    let mut ids: Vec<String> = container.instances.keys().cloned().collect();
+    ids.sort();
+    ids
+}
+
+fn list_instances(container: &Container) -> String {
+    sorted_ids(container)
+        .into_iter()
+        .map(|id| {
+            let hc = &container.instances[&id];
+            let dna_name = instance_dna(hc)
+                .map(|dna| dna.name)
+                .unwrap_or_else(|| "?".to_string());
+            format!(
+                "{}: {:?}, DNA {}",
+                id,
+                hc.read().unwrap().status(),
+                dna_name
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn status(container: &Container) -> String {
+    sorted_ids(container)
+        .into_iter()
+        .map(|id| {
+            let startup_state = container
+                .startup_state(&id)
+                .map(|state| format!("{:?}", state))
+                .unwrap_or_else(|| "not started".to_string());
+            let chain_length = container.instances[&id]
+                .read()
+                .unwrap()
+                .chain_info()
+                .map(|chain_info| chain_info.length.to_string())
+                .unwrap_or_else(|_| "?".to_string());
+            let pending = container
+                .pending(&id)
+                .map(|pending| format_pending(&pending))
+                .unwrap_or_else(|_| "?".to_string());
+            format!(
+                "{}: {}, {} entries in the source chain, {}",
+                id, startup_state, chain_length, pending
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// The running zome calls with how long they have been running, and how many validations
+/// and network requests are waiting
+fn format_pending(pending: &PendingWork) -> String {
+    if pending.is_empty() {
+        return "nothing pending".to_string();
+    }
+    let mut parts: Vec<String> = pending
+        .zome_calls
+        .iter()
+        .map(|call| {
+            format!(
+                "{}/{} running for {}ms",
+                call.zome, call.function, call.elapsed_ms
+            )
+        })
+        .collect();
+    if !pending.validations.is_empty() {
+        parts.push(format!(
+            "{} validations waiting for dependencies",
+            pending.validations.len()
+        ));
+    }
+    if !pending.network_requests.is_empty() {
+        parts.push(format!(
+            "{} network requests waiting",
+            pending.network_requests.len()
+        ));
+    }
+    parts.join(", ")
+}
+
+fn call(
+    container: &Container,
+    instance_id: &str,
+    zome: &str,
+    function: &str,
+    params: &str,
+) -> Result<String, String> {
+    serde_json::from_str::<serde_json::Value>(params)
+        .map_err(|error| format!("The parameters are not valid JSON: {}", error))?;
+    let hc = instance_by_id(container, instance_id)?;
+    let dna =
+        instance_dna(&hc).ok_or_else(|| format!("Instance {} has no DNA yet", instance_id))?;
+    let capability = find_capability(&dna, zome, function).map_err(|error| error.to_string())?;
+    let result = hc
+        .write()
+        .unwrap()
+        .call(zome, &capability, function, params)
+        .map_err(|error| error.to_string())?;
+    Ok(format_call_result(&result, false))
+}
+
+/// Prints the events of the instance, starting with the ones it kept, and the headers
+/// of the entries it commits, until Enter gets pressed
+fn watch(container: &Container, instance_id: &str) -> Result<(), String> {
+    let hc = instance_by_id(container, instance_id)?;
+    println!("Watching instance {}, press Enter to stop", instance_id);
+    let stopped = Arc::new(AtomicBool::new(false));
+    let stop = stopped.clone();
+    thread::spawn(move || {
+        let mut line = String::new();
+        let _ = io::stdin().read_line(&mut line);
+        stop.store(true, Ordering::Relaxed);
+    });
+
+    let mut last_seq = 0;
+    let mut last_header = headers_since(&hc, &None).pop();
+    while !stopped.load(Ordering::Relaxed) {
+        let backlog = hc.read().unwrap().events_since(last_seq);
+        if backlog.gap {
+            println!("(earlier events got dropped)");
+        }
+        for sequenced in backlog.events {
+            println!("event {}: {:?}", sequenced.seq, sequenced.event);
+            last_seq = sequenced.seq;
+        }
+        let headers = headers_since(&hc, &last_header);
+        for header in headers.iter() {
+            println!("commit {} {}", header.address().short(), header);
+        }
+        if let Some(header) = headers.into_iter().last() {
+            last_header = Some(header);
+        }
+        thread::sleep(Duration::from_millis(WATCH_INTERVAL_MS));
+    }
+    Ok(())
+}
+
+/// The headers of the source chain above the given one, oldest first
+fn headers_since(hc: &Arc<RwLock<Holochain>>, since: &Option<ChainHeader>) -> Vec<ChainHeader> {
+    let state = match hc.read().unwrap().state() {
+        Ok(state) => state,
+        Err(_) => return Vec::new(),
+    };
+    let last_known = since.as_ref().map(|header| header.address());
+    let mut headers: Vec<ChainHeader> = state
+        .agent()
+        .chain()
+        .iter(&state.agent().top_chain_header())
+        .take_while(|header| Some(header.address()) != last_known)
+        .collect();
+    headers.reverse();
+    headers
+}
+
+/// What the console completes: its commands, the IDs of the instances,
+/// and the zomes and functions of the DNA of the instance a call is for
+#[derive(Default)]
+pub struct Completions {
+    /// The functions of every zome, by zome, by instance ID
+    instances: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+}
+
+impl Completions {
+    pub fn new(instances: &InstanceMap) -> Self {
+        Completions {
+            instances: instances
+                .iter()
+                .map(|(id, hc)| {
+                    let zomes = instance_dna(hc)
+                        .map(|dna| zome_functions(&dna))
+                        .unwrap_or_default();
+                    (id.clone(), zomes)
+                })
+                .collect(),
+        }
+    }
+
+    /// Where the word before the end of the line starts, and how it can be completed
+    pub fn complete(&self, line: &str) -> (usize, Vec<String>) {
+        let words = words(line);
+        let (start, prefix, position) = match words.last() {
+            Some(word) if word.end == line.len() => {
+                (word.start, word.text.clone(), words.len() - 1)
+            }
+            _ => (line.len(), String::new(), words.len()),
+        };
+        let arg = |index: usize| {
+            words
+                .get(index)
+                .map(|word| word.text.clone())
+                .unwrap_or_default()
+        };
+        let zomes = self.instances.get(&arg(1));
+        let candidates: Vec<String> = match (arg(0).as_str(), position) {
+            (_, 0) => COMMANDS
+                .iter()
+                .filter_map(|(usage, _)| usage.split(' ').next())
+                .map(String::from)
+                .collect(),
+            ("call", 1) | ("chain", 1) | ("watch", 1) => self.instances.keys().cloned().collect(),
+            ("call", 2) => zomes
+                .map(|zomes| zomes.keys().cloned().collect())
+                .unwrap_or_default(),
+            ("call", 3) => zomes
+                .and_then(|zomes| zomes.get(&arg(2)))
+                .cloned()
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        let completions = candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(&prefix))
+            .map(|candidate| {
+                if candidate.contains(' ') {
+                    format!("\"{}\"", candidate)
+                } else {
+                    candidate
+                }
+            })
+            .collect();
+        (start, completions)
+    }
+}
+
+/// The names of the functions of every zome of the DNA, over all its capabilities
+fn zome_functions(dna: &Dna) -> BTreeMap<String, Vec<String>> {
+    dna.zomes
+        .iter()
+        .map(|(zome_name, zome)| {
+            let mut functions: Vec<String> = zome
+                .capabilities
+                .values()
+                .flat_map(|capability| capability.functions.iter().map(|f| f.name.clone()))
+                .collect();
+            functions.sort();
+            functions.dedup();
+            (zome_name.clone(), functions)
+        })
+        .collect()
+}
+
+/// Hooks `Completions` into the line editor
+pub struct ReplHelper {
+    completions: Completions,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.completions.complete(&line[..pos]))
+    }
+}
+
+impl Hinter for ReplHelper {
+    fn hint(&self, _line: &str, _pos: usize) -> Option<String> {
+        None
+    }
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use holochain_core::{
+        network::state::{RequestId, RequestKind, WaitingRequest},
+        nucleus::state::RunningZomeCall,
+    };
+    use holochain_core_types::cas::content::Address;
+
+    fn completions() -> Completions {
+        let mut zomes = BTreeMap::new();
+        zomes.insert(
+            "blog".to_string(),
+            vec!["create_post".to_string(), "get_post".to_string()],
+        );
+        zomes.insert("summer".to_string(), vec!["sum".to_string()]);
+        let mut instances = BTreeMap::new();
+        instances.insert("app spec instance".to_string(), zomes);
+        instances.insert("other".to_string(), BTreeMap::new());
+        Completions { instances }
+    }
+
+    #[test]
+    fn formats_what_instances_wait_on() {
+        let mut pending = PendingWork::default();
+        assert_eq!(format_pending(&pending), "nothing pending");
+
+        pending.zome_calls.push(RunningZomeCall {
+            call_id: "1".to_string(),
+            zome: "blog".to_string(),
+            capability: "main".to_string(),
+            function: "create_post".to_string(),
+            elapsed_ms: 1520,
+        });
+        pending.network_requests.push(WaitingRequest {
+            id: RequestId::from("2"),
+            kind: RequestKind::GetEntry,
+            address: Address::from("QmEntry"),
+            elapsed_ms: 10,
+            remaining_timeout_ms: Some(59990),
+        });
+        assert_eq!(
+            format_pending(&pending),
+            "blog/create_post running for 1520ms, 1 network requests waiting"
+        );
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse_command("  "), Ok(None));
+        assert_eq!(parse_command("instances"), Ok(Some(ReplCommand::Instances)));
+        assert_eq!(parse_command(" status "), Ok(Some(ReplCommand::Status)));
+        assert_eq!(
+            parse_command("chain \"app spec instance\""),
+            Ok(Some(ReplCommand::Chain {
+                instance: "app spec instance".to_string()
+            }))
+        );
+        assert_eq!(
+            parse_command("watch other"),
+            Ok(Some(ReplCommand::Watch {
+                instance: "other".to_string()
+            }))
+        );
+        assert_eq!(
+            parse_command(
+                "call \"app spec instance\" blog create_post {\"content\": \"Hi there\"}"
+            ),
+            Ok(Some(ReplCommand::Call {
+                instance: "app spec instance".to_string(),
+                zome: "blog".to_string(),
+                function: "create_post".to_string(),
+                params: "{\"content\": \"Hi there\"}".to_string(),
+            }))
+        );
+        assert_eq!(
+            parse_command("call other summer sum"),
+            Ok(Some(ReplCommand::Call {
+                instance: "other".to_string(),
+                zome: "summer".to_string(),
+                function: "sum".to_string(),
+                params: "{}".to_string(),
+            }))
+        );
+        assert_eq!(parse_command("quit"), Ok(Some(ReplCommand::Exit)));
+    }
+
+    #[test]
+    fn reports_usage_and_unknown_commands() {
+        assert_eq!(
+            parse_command("call other summer"),
+            Err("Usage: call <instance> <zome> <function> [<params as JSON>]".to_string())
+        );
+        assert_eq!(
+            parse_command("chain"),
+            Err("Usage: chain <instance>".to_string())
+        );
+        assert_eq!(
+            parse_command("dump other"),
+            Err("Unknown command dump, type 'help' for the commands".to_string())
+        );
+    }
+
+    #[test]
+    fn splits_words_with_quotes() {
+        let words = words("call \"app spec\" x");
+        assert_eq!(
+            words,
+            vec![
+                Word {
+                    start: 0,
+                    end: 4,
+                    text: "call".to_string()
+                },
+                Word {
+                    start: 5,
+                    end: 15,
+                    text: "app spec".to_string()
+                },
+                Word {
+                    start: 16,
+                    end: 17,
+                    text: "x".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn completes_commands_instances_zomes_and_functions() {
+        let completions = completions();
+        assert_eq!(
+            completions.complete("c"),
+            (0, vec!["call".to_string(), "chain".to_string()])
+        );
+        assert_eq!(
+            completions.complete("chain "),
+            (
+                6,
+                vec!["\"app spec instance\"".to_string(), "other".to_string()]
+            )
+        );
+        assert_eq!(
+            completions.complete("call \"app"),
+            (5, vec!["\"app spec instance\"".to_string()])
+        );
+        assert_eq!(
+            completions.complete("call \"app spec instance\" "),
+            (25, vec!["blog".to_string(), "summer".to_string()])
+        );
+        assert_eq!(
+            completions.complete("call \"app spec instance\" blog g"),
+            (30, vec!["get_post".to_string()])
+        );
+        assert_eq!(
+            completions.complete("call other blog "),
+            (16, Vec::<String>::new())
+        );
+        assert_eq!(completions.complete("status "), (7, Vec::<String>::new()));
+    }
+}
@@ -1,48 +1,33 @@
-use cli::{self, package};
+use cli::{self, package, repl};
+use config_files::WorkspaceManifest;
 use error::DefaultResult;
 use holochain_container_api::{config::*, container::Container};
+use std::path::Path;
 
-/// Starts a small container with the current application running
-pub fn run(package: bool, port: u16) -> DefaultResult<()> {
-    if package {
-        cli::package(true, Some(package::DEFAULT_BUNDLE_FILE_NAME.into()))?;
-    }
-
-    let agent_config = AgentConfiguration {
-        id: "hc-run-agent".into(),
-        key_file: "hc_run.key".into(),
-    };
+const AGENT_ID: &str = "hc-run-agent";
 
-    let dna_config = DNAConfiguration {
-        id: "hc-run-dna".into(),
-        file: package::DEFAULT_BUNDLE_FILE_NAME.into(),
-        hash: "Qm328wyq38924ybogus".into(),
-    };
-
-    let instance_config = InstanceConfiguration {
-        id: "test-instance".into(),
-        dna: "hc-run-dna".into(),
-        agent: "hc-run-agent".into(),
-        logger: Default::default(),
-        storage: StorageConfiguration::Memory,
-        network: Some("{\"backend\": \"mock\"}".to_string()),
-    };
-
-    let interface_config = InterfaceConfiguration {
-        id: "websocket-interface".into(),
-        driver: InterfaceDriver::Websocket { port: port },
-        admin: true,
-        instances: vec![InstanceReferenceConfiguration {
-            id: "test-instance".into(),
-        }],
-    };
-
-    let base_config = Configuration {
-        agents: vec![agent_config],
-        dnas: vec![dna_config],
-        instances: vec![instance_config],
-        interfaces: vec![interface_config],
-        ..Default::default()
+/// Starts a small container with the current application running.
+/// With `workspace`, it runs an instance of every member of the workspace instead, see
+/// `workspace_config()`.
+/// With `repl`, it opens a console on the container, see `repl::repl()`.
+pub fn run(package: bool, port: u16, repl: bool, workspace: bool) -> DefaultResult<()> {
+    let base_config = if workspace {
+        let root = Path::new(".");
+        if package {
+            cli::package_workspace(root, true)?;
+        }
+        let manifest_path = WorkspaceManifest::path(root);
+        ensure!(
+            manifest_path.is_file(),
+            "{:?} not found, package the workspace first",
+            manifest_path
+        );
+        workspace_config(&WorkspaceManifest::from_file(&manifest_path)?, port, repl)
+    } else {
+        if package {
+            cli::package(true, Some(package::DEFAULT_BUNDLE_FILE_NAME.into()))?;
+        }
+        app_config(port, repl)
     };
 
     let mut container = Container::with_config(base_config.clone());
@@ -58,6 +43,9 @@ pub fn run(package: bool, port: u16) -> DefaultResult<()> {
         "Holochain development container started. Running websocket server on port {}",
         port
     );
+    if repl {
+        return repl::repl(&container);
+    }
     println!("Type 'exit' to stop the container and exit the program");
 
     let mut rl = rustyline::Editor::<()>::new();
@@ -77,3 +65,147 @@ pub fn run(package: bool, port: u16) -> DefaultResult<()> {
 
     Ok(())
 }
+
+/// The configuration running the packaged application of the current directory
+fn app_config(port: u16, repl: bool) -> Configuration {
+    let dna_config = DNAConfiguration {
+        id: "hc-run-dna".into(),
+        file: package::DEFAULT_BUNDLE_FILE_NAME.into(),
+        hash: "Qm328wyq38924ybogus".into(),
+    };
+
+    Configuration {
+        agents: vec![agent_config()],
+        dnas: vec![dna_config],
+        instances: vec![instance_config("test-instance", "hc-run-dna", repl)],
+        interfaces: vec![interface_config(port, &["test-instance"])],
+        ..Default::default()
+    }
+}
+
+/// The configuration running the members packaged by `hc package --workspace`:
+/// a DNA and an instance per member, both named like the member, and the bridges
+/// of the workspace between them
+fn workspace_config(manifest: &WorkspaceManifest, port: u16, repl: bool) -> Configuration {
+    let members: Vec<&str> = manifest.members.keys().map(String::as_str).collect();
+
+    Configuration {
+        agents: vec![agent_config()],
+        dnas: manifest
+            .members
+            .iter()
+            .map(|(member, packaged)| DNAConfiguration {
+                id: member.clone(),
+                file: packaged.file.to_string_lossy().into_owned(),
+                hash: packaged.hash.clone(),
+            })
+            .collect(),
+        instances: members
+            .iter()
+            .map(|member| instance_config(member, member, repl))
+            .collect(),
+        interfaces: vec![interface_config(port, &members)],
+        bridges: manifest
+            .bridges
+            .iter()
+            .map(|bridge| Bridge {
+                caller_id: bridge.caller.clone(),
+                callee_id: bridge.callee.clone(),
+                handle: bridge.handle.clone(),
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+fn agent_config() -> AgentConfiguration {
+    AgentConfiguration {
+        id: AGENT_ID.into(),
+        name: None,
+        key_file: "hc_run.key".into(),
+    }
+}
+
+fn instance_config(id: &str, dna: &str, repl: bool) -> InstanceConfiguration {
+    InstanceConfiguration {
+        id: id.into(),
+        dna: dna.into(),
+        agent: AGENT_ID.into(),
+        logger: Some(Default::default()),
+        storage: Some(StorageConfiguration::Memory),
+        network: Some("{\"backend\": \"mock\"}".to_string()),
+        timeouts: None,
+        archived: false,
+        record_actions: None,
+        depends_on: Vec::new(),
+        gc_interval: None,
+        properties_override: None,
+        max_wasm_memory_mb: None,
+        signal_buffer_size: if repl {
+            Some(repl::SIGNAL_BUFFER_SIZE)
+        } else {
+            None
+        },
+        audit_host_calls: false,
+        genesis_params: None,
+        restart: RestartPolicy::Never,
+    }
+}
+
+fn interface_config(port: u16, instance_ids: &[&str]) -> InterfaceConfiguration {
+    InterfaceConfiguration {
+        id: "websocket-interface".into(),
+        driver: InterfaceDriver::Websocket { port, tls: None },
+        admin: true,
+        instances: instance_ids
+            .iter()
+            .map(|id| InstanceReferenceConfiguration {
+                id: id.to_string(),
+                zomes: None,
+                functions: None,
+            })
+            .collect(),
+        idle_timeout: None,
+        request_log: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config_files::{PackagedMember, WorkspaceBridge};
+    use std::path::PathBuf;
+
+    #[test]
+    fn workspace_config_runs_every_member_with_its_bridges() {
+        let packaged = |member: &str| PackagedMember {
+            file: PathBuf::from("dist").join(format!("{}.dna.json", member)),
+            hash: format!("Qm{}", member),
+        };
+        let manifest = WorkspaceManifest {
+            members: vec![
+                (String::from("main"), packaged("main")),
+                (String::from("admin"), packaged("admin")),
+            ]
+            .into_iter()
+            .collect(),
+            bridges: vec![WorkspaceBridge {
+                caller: String::from("admin"),
+                callee: String::from("main"),
+                handle: Some(String::from("main")),
+            }],
+        };
+
+        let config = workspace_config(&manifest, 8888, false);
+
+        assert_eq!(config.check_consistency(), Ok(()));
+        assert_eq!(config.instance_ids(), vec!["admin", "main"]);
+        let main_dna = config.dna_by_id("main").unwrap();
+        assert_eq!(main_dna.file, "dist/main.dna.json");
+        assert_eq!(main_dna.hash, "Qmmain");
+        assert_eq!(config.bridges[0].caller_id, "admin");
+        assert_eq!(config.bridges[0].callee_id, "main");
+        assert_eq!(config.bridges[0].handle, Some(String::from("main")));
+        assert_eq!(config.interfaces[0].instances.len(), 2);
+    }
+}
@@ -2,7 +2,7 @@ use crate::{
     cli::{package, scaffold::Scaffold},
     config_files::Build,
     error::DefaultResult,
-    util,
+    util::CommandRunner,
 };
 use std::{
     fs::{self, OpenOptions},
@@ -39,22 +39,21 @@ impl Scaffold for AssemblyScriptScaffold {
         fs::create_dir_all(&base_path)?;
 
         // use npm to initialise a nodejs project
-        util::run_cmd(
-            base_path.as_ref().to_path_buf(),
-            "npm".into(),
-            vec!["init".to_owned(), "-y".to_owned()],
-        )?;
+        CommandRunner::new("npm", vec!["init".to_owned(), "-y".to_owned()])
+            .with_working_dir(base_path.as_ref())
+            .run()?;
 
         // add hdk-assemblyscript as a dependency
-        util::run_cmd(
-            base_path.as_ref().to_path_buf(),
-            "npm".into(),
+        CommandRunner::new(
+            "npm",
             vec![
                 "install".to_owned(),
                 "--save".to_owned(),
                 "holochain/hdk-assemblyscript".to_owned(),
             ],
-        )?;
+        )
+        .with_working_dir(base_path.as_ref())
+        .run()?;
 
         // create a index.ts file
         let typescript_file_path = base_path.as_ref().join(TYPESCRIPT_FILE_NAME);
@@ -1,8 +1,8 @@
 use crate::{
     cli::{package, scaffold::Scaffold},
-    config_files::Build,
+    config_files::{Build, CARGO_FILE_NAME},
     error::DefaultResult,
-    util,
+    util::CommandRunner,
 };
 use std::{
     fs::{self, OpenOptions},
@@ -11,7 +11,6 @@ use std::{
 };
 use toml::{self, value::Value};
 
-pub const CARGO_FILE_NAME: &str = "Cargo.toml";
 pub const LIB_RS_PATH: &str = "src/lib.rs";
 
 pub struct RustScaffold {
@@ -55,15 +54,8 @@ fn interpolate_cargo_template(
 
 impl RustScaffold {
     pub fn new(package_name: String) -> RustScaffold {
-        let artifact_name = format!(
-            "target/wasm32-unknown-unknown/release/{}.wasm",
-            package_name
-        );
         RustScaffold {
-            build_template: Build::with_artifact(artifact_name).cmd(
-                "cargo",
-                &["build", "--release", "--target=wasm32-unknown-unknown"],
-            ),
+            build_template: Build::rust(&package_name),
             package_name: package_name,
         }
     }
@@ -103,16 +95,17 @@ impl Scaffold for RustScaffold {
         fs::create_dir_all(&base_path)?;
 
         // use cargo to initialise a library Rust crate without any version control
-        util::run_cmd(
-            base_path.as_ref().to_path_buf(),
-            "cargo".into(),
+        CommandRunner::new(
+            "cargo",
             vec![
                 "init".to_owned(),
                 "--lib".to_owned(),
                 "--vcs".to_owned(),
                 "none".to_owned(),
             ],
-        )?;
+        )
+        .with_working_dir(base_path.as_ref())
+        .run()?;
 
         // immediately rewrite the generated Cargo file, using some values
         // and throwing away the rest
@@ -0,0 +1,468 @@
+//! Project templates for `hc init`.
+//!
+//! A template is a set of files whose names and contents can contain `{{variable}}`
+//! placeholders. A `template.toml` in the template root declares the variables it needs,
+//! besides `name` which every template gets. Built-in templates are compiled into `hc`,
+//! others get cloned from a git repository.
+
+use crate::{error::DefaultResult, util::CommandRunner};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use tempfile::tempdir;
+use toml;
+
+pub const TEMPLATE_CONFIG_FILE_NAME: &str = "template.toml";
+
+pub const DEFAULT_TEMPLATE_NAME: &str = "minimal";
+
+/// The variable every template gets, defaults to the name of the project directory
+pub const NAME_VARIABLE: &str = "name";
+
+const GIT_DIR_NAME: &str = ".git";
+
+type TemplateFiles = &'static [(&'static str, &'static str)];
+
+const BUILT_IN_TEMPLATES: &[(&str, TemplateFiles)] = &[
+    (
+        "minimal",
+        &[
+            (
+                TEMPLATE_CONFIG_FILE_NAME,
+                include_str!("templates/minimal/template.toml"),
+            ),
+            (".gitignore", include_str!("templates/common/.gitignore")),
+            ("test/index.js", include_str!("templates/minimal/index.js")),
+            (
+                "test/package.json",
+                include_str!("templates/common/package.json"),
+            ),
+            ("test/app.js", include_str!("templates/common/app.js")),
+        ],
+    ),
+    (
+        "rust-zome-with-tests",
+        &[
+            (
+                TEMPLATE_CONFIG_FILE_NAME,
+                include_str!("templates/rust-zome-with-tests/template.toml"),
+            ),
+            (".gitignore", include_str!("templates/common/.gitignore")),
+            (
+                "test/index.js",
+                include_str!("templates/rust-zome-with-tests/index.js"),
+            ),
+            (
+                "test/package.json",
+                include_str!("templates/common/package.json"),
+            ),
+            ("test/app.js", include_str!("templates/common/app.js")),
+            (
+                "zomes/{{zome}}/zome.json",
+                include_str!("templates/rust-zome-with-tests/zome.json"),
+            ),
+            (
+                "zomes/{{zome}}/code/.build",
+                include_str!("templates/common/rust/build.json"),
+            ),
+            (
+                "zomes/{{zome}}/code/Cargo.toml",
+                include_str!("templates/common/rust/Cargo.template.toml"),
+            ),
+            (
+                "zomes/{{zome}}/code/src/lib.rs",
+                include_str!("templates/rust-zome-with-tests/lib.rs"),
+            ),
+        ],
+    ),
+    (
+        "multi-zome",
+        &[
+            (
+                TEMPLATE_CONFIG_FILE_NAME,
+                include_str!("templates/multi-zome/template.toml"),
+            ),
+            (".gitignore", include_str!("templates/common/.gitignore")),
+            (
+                "test/index.js",
+                include_str!("templates/multi-zome/index.js"),
+            ),
+            (
+                "test/package.json",
+                include_str!("templates/common/package.json"),
+            ),
+            ("test/app.js", include_str!("templates/common/app.js")),
+            (
+                "zomes/greeter/zome.json",
+                include_str!("templates/multi-zome/greeter.json"),
+            ),
+            (
+                "zomes/greeter/code/.build",
+                include_str!("templates/common/rust/build.json"),
+            ),
+            (
+                "zomes/greeter/code/Cargo.toml",
+                include_str!("templates/common/rust/Cargo.template.toml"),
+            ),
+            (
+                "zomes/greeter/code/src/lib.rs",
+                include_str!("templates/multi-zome/greeter.rs"),
+            ),
+            (
+                "zomes/calculator/zome.json",
+                include_str!("templates/multi-zome/calculator.json"),
+            ),
+            (
+                "zomes/calculator/code/.build",
+                include_str!("templates/common/rust/build.json"),
+            ),
+            (
+                "zomes/calculator/code/Cargo.toml",
+                include_str!("templates/common/rust/Cargo.template.toml"),
+            ),
+            (
+                "zomes/calculator/code/src/lib.rs",
+                include_str!("templates/multi-zome/calculator.rs"),
+            ),
+        ],
+    ),
+];
+
+/// Names of the built-in templates
+pub fn built_in_template_names() -> Vec<&'static str> {
+    BUILT_IN_TEMPLATES.iter().map(|(name, _)| *name).collect()
+}
+
+/// Where `hc init` gets its template from
+#[derive(Clone, Debug, PartialEq)]
+pub enum TemplateSource {
+    BuiltIn(String),
+    /// A git repository URL, optionally followed by `#` and the branch, tag or commit to use
+    Git(String),
+}
+
+impl Default for TemplateSource {
+    fn default() -> Self {
+        TemplateSource::BuiltIn(DEFAULT_TEMPLATE_NAME.into())
+    }
+}
+
+/// Contents of a template.toml
+#[derive(Debug, Default, Deserialize)]
+pub struct TemplateConfig {
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub variables: Vec<TemplateVariable>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TemplateVariable {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Variables without a default get asked for if they are not given with --var.
+    /// The default can refer to variables declared before.
+    pub default: Option<String>,
+}
+
+pub struct Template {
+    pub config: TemplateConfig,
+    /// Paths relative to the project root and contents, both still with placeholders
+    pub files: Vec<(PathBuf, Vec<u8>)>,
+}
+
+impl Template {
+    pub fn load(source: &TemplateSource) -> DefaultResult<Template> {
+        match source {
+            TemplateSource::BuiltIn(name) => Template::built_in(name),
+            TemplateSource::Git(url) => Template::from_git(url),
+        }
+    }
+
+    pub fn built_in(name: &str) -> DefaultResult<Template> {
+        let files = BUILT_IN_TEMPLATES
+            .iter()
+            .find(|(template_name, _)| *template_name == name)
+            .map(|(_, files)| *files)
+            .ok_or_else(|| {
+                format_err!(
+                    "unknown template {:?}, available templates are: {}",
+                    name,
+                    built_in_template_names().join(", ")
+                )
+            })?;
+        Template::from_files(
+            files
+                .iter()
+                .map(|(path, contents)| (PathBuf::from(*path), contents.as_bytes().to_vec()))
+                .collect(),
+        )
+    }
+
+    /// Clones the given repository and reads the template from its root
+    pub fn from_git(source: &str) -> DefaultResult<Template> {
+        let (url, reference) = match source.rfind('#') {
+            Some(index) => (&source[..index], Some(&source[index + 1..])),
+            None => (source, None),
+        };
+        let clone_dir = tempdir()?;
+        let clone_path = clone_dir.path().to_path_buf();
+        CommandRunner::new(
+            "git",
+            vec![
+                "clone".to_owned(),
+                "--quiet".to_owned(),
+                url.to_owned(),
+                ".".to_owned(),
+            ],
+        )
+        .with_working_dir(clone_path.clone())
+        .run()?;
+        if let Some(reference) = reference {
+            CommandRunner::new(
+                "git",
+                vec![
+                    "checkout".to_owned(),
+                    "--quiet".to_owned(),
+                    reference.to_owned(),
+                ],
+            )
+            .with_working_dir(clone_path.clone())
+            .run()?;
+        }
+        Template::from_dir(&clone_path)
+    }
+
+    /// Reads all files below the given directory, except for git's own
+    pub fn from_dir(path: &Path) -> DefaultResult<Template> {
+        let mut files = Vec::new();
+        read_files(path, path, &mut files)?;
+        Template::from_files(files)
+    }
+
+    fn from_files(mut files: Vec<(PathBuf, Vec<u8>)>) -> DefaultResult<Template> {
+        let config_path = PathBuf::from(TEMPLATE_CONFIG_FILE_NAME);
+        let config = match files.iter().position(|(path, _)| *path == config_path) {
+            Some(index) => {
+                let (_, contents) = files.remove(index);
+                toml::from_str(&String::from_utf8(contents)?)?
+            }
+            None => TemplateConfig::default(),
+        };
+        Ok(Template { config, files })
+    }
+
+    /// Works out the values of all variables of the template: given ones first, then
+    /// defaults, and whatever is still missing gets asked for.
+    pub fn variables(
+        &self,
+        given: &[(String, String)],
+        project_name: &str,
+    ) -> DefaultResult<BTreeMap<String, String>> {
+        let mut variables: BTreeMap<String, String> = given.iter().cloned().collect();
+        variables
+            .entry(NAME_VARIABLE.to_string())
+            .or_insert_with(|| project_name.to_string());
+
+        for variable in &self.config.variables {
+            if variables.contains_key(&variable.name) {
+                continue;
+            }
+            let value = match variable.default {
+                Some(ref default) => substitute(default, &variables),
+                None => ask_for_variable(variable)?,
+            };
+            variables.insert(variable.name.clone(), value);
+        }
+        Ok(variables)
+    }
+
+    /// Writes the files of the template into the given directory,
+    /// with all placeholders in their names and contents replaced
+    pub fn instantiate(
+        &self,
+        path: &Path,
+        variables: &BTreeMap<String, String>,
+    ) -> DefaultResult<()> {
+        for (file_path, contents) in &self.files {
+            let file_path = substitute(&file_path.to_string_lossy(), variables);
+            let target_path = path.join(file_path);
+            if let Some(parent) = target_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            match String::from_utf8(contents.clone()) {
+                Ok(text) => fs::write(&target_path, substitute(&text, variables))?,
+                // binary files get copied as they are
+                Err(_) => fs::write(&target_path, contents)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_files(root: &Path, dir: &Path, files: &mut Vec<(PathBuf, Vec<u8>)>) -> DefaultResult<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if path
+                .file_name()
+                .map(|name| name != GIT_DIR_NAME)
+                .unwrap_or(true)
+            {
+                read_files(root, &path, files)?;
+            }
+        } else {
+            let relative_path = path.strip_prefix(root)?.to_path_buf();
+            files.push((relative_path, fs::read(&path)?));
+        }
+    }
+    Ok(())
+}
+
+fn ask_for_variable(variable: &TemplateVariable) -> DefaultResult<String> {
+    let mut rl = rustyline::Editor::<()>::new();
+    let prompt = if variable.description.is_empty() {
+        format!("{}: ", variable.name)
+    } else {
+        format!("{} ({}): ", variable.name, variable.description)
+    };
+    let answer = rl.readline(&prompt).map_err(|_| {
+        format_err!(
+            "no value for template variable {}, set it with --var {}=VALUE",
+            variable.name,
+            variable.name
+        )
+    })?;
+    let answer = answer.trim();
+    ensure!(
+        !answer.is_empty(),
+        "template variable {} can not be empty",
+        variable.name
+    );
+    Ok(answer.to_string())
+}
+
+/// Replaces every `{{variable}}` in the given text with the value of the variable.
+/// Placeholders of unknown variables are left as they are.
+pub fn substitute(text: &str, variables: &BTreeMap<String, String>) -> String {
+    variables
+        .iter()
+        .fold(text.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{{{}}}}}", name), value)
+        })
+}
+
+/// Parses a `--var key=value` argument
+pub fn parse_variable(argument: &str) -> Result<(String, String), String> {
+    let index = argument
+        .find('=')
+        .ok_or_else(|| format!("expected key=value, got {:?}", argument))?;
+    let (key, value) = (&argument[..index], &argument[index + 1..]);
+    if key.is_empty() {
+        return Err(format!("expected key=value, got {:?}", argument));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn variable_map(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn substitute_test() {
+        let variables = variable_map(&[("name", "chat"), ("zome", "rooms")]);
+        assert_eq!(
+            substitute("zomes/{{zome}}/code of {{name}}", &variables),
+            "zomes/rooms/code of chat"
+        );
+        assert_eq!(
+            substitute("{{unknown}} {name}", &variables),
+            "{{unknown}} {name}"
+        );
+    }
+
+    #[test]
+    fn parse_variable_test() {
+        assert_eq!(
+            parse_variable("agent=bob"),
+            Ok(("agent".to_string(), "bob".to_string()))
+        );
+        assert_eq!(
+            parse_variable("greeting=a=b"),
+            Ok(("greeting".to_string(), "a=b".to_string()))
+        );
+        assert!(parse_variable("agent").is_err());
+        assert!(parse_variable("=bob").is_err());
+    }
+
+    #[test]
+    fn built_in_templates_load() {
+        for name in built_in_template_names() {
+            let template = Template::built_in(name).unwrap();
+            assert!(!template.config.description.is_empty());
+            assert!(template
+                .files
+                .iter()
+                .all(|(path, _)| *path != PathBuf::from(TEMPLATE_CONFIG_FILE_NAME)));
+        }
+        assert!(Template::built_in("unknown").is_err());
+    }
+
+    #[test]
+    fn variables_use_given_values_and_defaults() {
+        let template = Template::built_in("rust-zome-with-tests").unwrap();
+        let given = vec![("agent".to_string(), "bob".to_string())];
+        let variables = template.variables(&given, "chat").unwrap();
+        assert_eq!(
+            variables,
+            variable_map(&[("agent", "bob"), ("name", "chat"), ("zome", "hello")])
+        );
+    }
+
+    #[test]
+    fn template_from_dir_test() {
+        let source = tempdir().unwrap();
+        fs::create_dir_all(source.path().join("{{zome}}")).unwrap();
+        fs::create_dir_all(source.path().join(GIT_DIR_NAME)).unwrap();
+        fs::write(source.path().join(GIT_DIR_NAME).join("HEAD"), "ref").unwrap();
+        fs::write(
+            source.path().join(TEMPLATE_CONFIG_FILE_NAME),
+            "[[variables]]\nname = \"zome\"\ndefault = \"{{name}}_zome\"\n",
+        )
+        .unwrap();
+        fs::write(
+            source.path().join("{{zome}}").join("readme.txt"),
+            "{{zome}} of {{name}}",
+        )
+        .unwrap();
+        fs::write(source.path().join("binary"), vec![0xff, 0xfe, 0x00]).unwrap();
+
+        let template = Template::from_dir(source.path()).unwrap();
+        assert_eq!(template.files.len(), 2);
+        let variables = template.variables(&[], "chat").unwrap();
+        assert_eq!(variables["zome"], "chat_zome");
+
+        let target = tempdir().unwrap();
+        template.instantiate(target.path(), &variables).unwrap();
+        assert_eq!(
+            fs::read_to_string(target.path().join("chat_zome").join("readme.txt")).unwrap(),
+            "chat_zome of chat"
+        );
+        assert_eq!(
+            fs::read(target.path().join("binary")).unwrap(),
+            vec![0xff, 0xfe, 0x00]
+        );
+        assert!(!target.path().join(GIT_DIR_NAME).exists());
+        assert!(!target.path().join(TEMPLATE_CONFIG_FILE_NAME).exists());
+    }
+}
@@ -0,0 +1,40 @@
+// Connects the tests to the app they test.
+// `hc test --container` runs the tests against a container and tells them where to
+// find it in HC_TEST_INTERFACE_URL and HC_TEST_INSTANCE_ID.
+// `consistency()` waits until what got published reached the other instances, through
+// the method in HC_TEST_CONSISTENCY_METHOD if the container has one.
+// Without those, the app gets instantiated in this process from the DNA JSON bundle.
+const Container = require('@holochain/holochain-nodejs');
+const WebSocket = require('rpc-websockets').Client;
+
+const interfaceUrl = process.env.HC_TEST_INTERFACE_URL
+const instanceId = process.env.HC_TEST_INSTANCE_ID
+const consistencyMethod = process.env.HC_TEST_CONSISTENCY_METHOD
+
+// Zome calls go through the websocket interface of the container
+const connect = () => {
+  const ws = new WebSocket(interfaceUrl)
+  const opened = new Promise((resolve) => ws.on('open', resolve))
+  return {
+    call: (zome, capability, fn, params) => opened
+      .then(() => ws.call(`${instanceId}/${zome}/${capability}/${fn}`, params))
+      .then(JSON.parse),
+    consistency: () => consistencyMethod
+      ? opened.then(() => ws.call(consistencyMethod, { instance_ids: [instanceId] }))
+      : Promise.resolve(),
+    close: () => ws.close(),
+  }
+}
+
+// Zome calls go to an instance running in this process
+const instantiate = (agent) => {
+  const app = Container.instanceFromNameAndDna(agent, "dist/bundle.json")
+  app.start()
+  return {
+    call: (zome, capability, fn, params) => Promise.resolve(app.call(zome, capability, fn, params)),
+    consistency: () => Promise.resolve(),
+    close: () => app.stop(),
+  }
+}
+
+module.exports = (agent) => interfaceUrl ? connect() : instantiate(agent)
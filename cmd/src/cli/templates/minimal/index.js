@@ -0,0 +1,23 @@
+// This test file uses the tape testing framework.
+// To learn more, go here: https://github.com/substack/tape
+const test = require('tape');
+
+// the app under test, see app.js
+const app = require('./app')("{{agent}}")
+
+test('description of example test', (t) => {
+  // Make a call to a Zome function
+  // indicating the capability and function, and passing it an input
+  // app.call("zome-name", "capability-name", "function-name", {})
+  //   .then((result) => {
+  //     // check for equality of the actual and expected results
+  //     t.equal(result, "expected result!")
+  //     t.end()
+  //   })
+
+  // ends this test
+  t.end()
+})
+
+// disconnects from the app once all tests ran
+test.onFinish(app.close)
@@ -0,0 +1,31 @@
+#[macro_use]
+extern crate hdk;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+
+use hdk::holochain_core_types::json::JsonString;
+
+// see https://developer.holochain.org/api/0.0.2/hdk/ for info on using the hdk library
+
+fn handle_sum(num1: u32, num2: u32) -> JsonString {
+    json!({ "sum": num1 + num2 }).into()
+}
+
+define_zome! {
+    entries: []
+
+    genesis: || { Ok(()) }
+
+    functions: {
+        main (Public) {
+            sum: {
+                inputs: |num1: u32, num2: u32|,
+                outputs: |sum: JsonString|,
+                handler: handle_sum
+            }
+        }
+    }
+}
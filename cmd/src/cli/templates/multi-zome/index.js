@@ -0,0 +1,23 @@
+// This test file uses the tape testing framework.
+// To learn more, go here: https://github.com/substack/tape
+const test = require('tape');
+
+// the app under test, see app.js
+const app = require('./app')("{{agent}}")
+
+test('greeter greets by name', (t) => {
+  t.plan(1)
+
+  app.call("greeter", "main", "hello", {name: "{{agent}}"})
+    .then((result) => t.deepEqual(result, {greeting: "Hello {{agent}}"}))
+})
+
+test('calculator sums', (t) => {
+  t.plan(1)
+
+  app.call("calculator", "main", "sum", {num1: 2, num2: 3})
+    .then((result) => t.deepEqual(result, {sum: 5}))
+})
+
+// disconnects from the app once all tests ran
+test.onFinish(app.close)
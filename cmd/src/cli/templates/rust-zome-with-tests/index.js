@@ -0,0 +1,18 @@
+// This test file uses the tape testing framework.
+// To learn more, go here: https://github.com/substack/tape
+const test = require('tape');
+
+// the app under test, see app.js
+const app = require('./app')("{{agent}}")
+
+test('hello greets by name', (t) => {
+  t.plan(1)
+
+  // Make a call to a Zome function
+  // indicating the capability and function, and passing it an input
+  app.call("{{zome}}", "main", "hello", {name: "{{agent}}"})
+    .then((result) => t.deepEqual(result, {greeting: "Hello {{agent}}"}))
+})
+
+// disconnects from the app once all tests ran
+test.onFinish(app.close)
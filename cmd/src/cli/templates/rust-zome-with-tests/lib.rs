@@ -0,0 +1,31 @@
+#[macro_use]
+extern crate hdk;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+
+use hdk::holochain_core_types::json::JsonString;
+
+// see https://developer.holochain.org/api/0.0.2/hdk/ for info on using the hdk library
+
+fn handle_hello(name: String) -> JsonString {
+    json!({ "greeting": format!("Hello {}", name) }).into()
+}
+
+define_zome! {
+    entries: []
+
+    genesis: || { Ok(()) }
+
+    functions: {
+        main (Public) {
+            hello: {
+                inputs: |name: String|,
+                outputs: |greeting: JsonString|,
+                handler: handle_hello
+            }
+        }
+    }
+}
@@ -1,15 +1,290 @@
 use colored::*;
-use crate::{cli::package, error::DefaultResult, util};
-use std::{fs, path::PathBuf};
+use crate::{
+    cli::{call::load_dna, package},
+    error::DefaultResult,
+    util::CommandRunner,
+};
+use holochain_container_api::{
+    config::*,
+    container::Container,
+    interface_client::{ClientError, InterfaceClient},
+};
+use holochain_core_types::entry::addressing::dna_hash;
+use serde_json::{self, Value};
+use std::{
+    env, fs,
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
 
 pub const TEST_DIR_NAME: &str = "test";
 pub const DIST_DIR_NAME: &str = "dist";
 
+/// Environment variable that tells the tests the websocket URL of the test container
+pub const INTERFACE_URL_ENV: &str = "HC_TEST_INTERFACE_URL";
+/// Environment variable that tells the tests the id of the instance in the test container
+pub const INSTANCE_ID_ENV: &str = "HC_TEST_INSTANCE_ID";
+/// Environment variable that tells the tests the interface method that waits until the
+/// instances of the test container are done with the network, set if the container has it
+pub const CONSISTENCY_METHOD_ENV: &str = "HC_TEST_CONSISTENCY_METHOD";
+/// Environment variable that tells the tests the token for a secured interface of a remote
+/// container, and that `hc test` takes it from if not given `--token`
+pub const TOKEN_ENV: &str = "HC_TEST_TOKEN";
+
+const TEST_INSTANCE_ID: &str = "test-instance";
+const TEST_AGENT_ID: &str = "hcTestAgent";
+
+/// How long to wait for the websocket interface of the test container to accept connections
+const INTERFACE_STARTUP_TIMEOUT_MS: u64 = 10000;
+const INTERFACE_STARTUP_POLL_MS: u64 = 100;
+
+/// How long to wait for a remote container to answer
+const REMOTE_CONTAINER_TIMEOUT_MS: u64 = 10000;
+
+/// A container that runs already, e.g. as a service of a CI job, to run the tests against
+/// instead of packaging the DNA and starting a test container
+#[derive(Clone, Debug, PartialEq)]
+pub struct RemoteContainer {
+    /// URL of its websocket interface, e.g. `ws://holochain:3000`
+    pub interface_url: String,
+    pub instance_id: String,
+    /// Token for a secured interface, `hc test` connects with it as the query parameter `token`
+    pub token: Option<String>,
+    /// Fail instead of warning if the instance runs another DNA than the local bundle
+    pub require_hash_match: bool,
+}
+
+impl RemoteContainer {
+    /// The remote container the options of `hc test` give, if any.
+    /// The token defaults to the one in HC_TEST_TOKEN.
+    pub fn from_options(
+        interface_url: Option<String>,
+        instance_id: Option<String>,
+        token: Option<String>,
+        require_hash_match: bool,
+    ) -> DefaultResult<Option<Self>> {
+        match (interface_url, instance_id) {
+            (Some(interface_url), Some(instance_id)) => Ok(Some(RemoteContainer {
+                interface_url,
+                instance_id,
+                token: token.or_else(|| env::var(TOKEN_ENV).ok()),
+                require_hash_match,
+            })),
+            (None, None) => Ok(None),
+            _ => bail!("--interface-url and --instance-id have to be given together"),
+        }
+    }
+
+    /// The URL `hc test` connects with, including the token if there is one
+    fn connect_url(&self) -> String {
+        match self.token {
+            Some(ref token) => {
+                let separator = if self.interface_url.contains('?') {
+                    '&'
+                } else {
+                    '?'
+                };
+                format!("{}{}token={}", self.interface_url, separator, token)
+            }
+            None => self.interface_url.clone(),
+        }
+    }
+}
+
+/// Returns a port that is free right now.
+/// The OS picks it, and doesn't hand it out again right away,
+/// so test runs in parallel don't end up on the same port.
+fn free_port() -> DefaultResult<u16> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    Ok(listener.local_addr()?.port())
+}
+
+fn test_container_config(bundle_path: &Path, port: u16) -> Configuration {
+    let agent_config = AgentConfiguration {
+        id: TEST_AGENT_ID.into(),
+        name: None,
+        key_file: "hc_test.key".into(),
+    };
+
+    let dna_config = DNAConfiguration {
+        id: "hc-test-dna".into(),
+        file: bundle_path.to_string_lossy().into(),
+        hash: "Qm328wyq38924ybogus".into(),
+    };
+
+    let instance_config = InstanceConfiguration {
+        id: TEST_INSTANCE_ID.into(),
+        dna: "hc-test-dna".into(),
+        agent: TEST_AGENT_ID.into(),
+        logger: Some(Default::default()),
+        storage: Some(StorageConfiguration::Memory),
+        network: Some("{\"backend\": \"mock\"}".to_string()),
+        timeouts: None,
+        archived: false,
+        record_actions: None,
+        depends_on: Vec::new(),
+        gc_interval: None,
+        properties_override: None,
+        max_wasm_memory_mb: None,
+        signal_buffer_size: None,
+        audit_host_calls: false,
+        genesis_params: None,
+        restart: RestartPolicy::Never,
+    };
+
+    let interface_config = InterfaceConfiguration {
+        id: "test-websocket-interface".into(),
+        driver: InterfaceDriver::Websocket { port, tls: None },
+        admin: false,
+        instances: vec![InstanceReferenceConfiguration {
+            id: TEST_INSTANCE_ID.into(),
+            zomes: None,
+            functions: None,
+        }],
+        idle_timeout: None,
+        request_log: None,
+    };
+
+    Configuration {
+        agents: vec![agent_config],
+        dnas: vec![dna_config],
+        instances: vec![instance_config],
+        interfaces: vec![interface_config],
+        test_mode: true,
+        ..Default::default()
+    }
+}
+
+/// Starts a container in test mode with a fresh agent that runs the given DNA bundle in memory,
+/// and returns once its websocket interface on the given port accepts connections
+fn start_test_container(bundle_path: &Path, port: u16) -> DefaultResult<Container> {
+    let config = test_container_config(bundle_path, port);
+    let mut container = Container::with_config(config.clone());
+    container
+        .load_config(&config)
+        .map_err(|err| format_err!("{}", err))?;
+    container.start_all_interfaces();
+    container.start_all_instances()?;
+
+    let mut waited_ms = 0;
+    while TcpStream::connect(("127.0.0.1", port)).is_err() {
+        ensure!(
+            waited_ms < INTERFACE_STARTUP_TIMEOUT_MS,
+            "Websocket interface of the test container did not come up on port {}",
+            port
+        );
+        thread::sleep(Duration::from_millis(INTERFACE_STARTUP_POLL_MS));
+        waited_ms += INTERFACE_STARTUP_POLL_MS;
+    }
+    Ok(container)
+}
+
+/// Runs the test file with node against a test container,
+/// which gets torn down again once node exits
+fn run_with_container(path: &PathBuf, testfile: &str) -> DefaultResult<()> {
+    let bundle_path = path
+        .join(&DIST_DIR_NAME)
+        .join(package::DEFAULT_BUNDLE_FILE_NAME);
+    let port = free_port()?;
+    let mut container = start_test_container(&bundle_path, port)?;
+    println!(
+        "{} test container, websocket interface on port {}",
+        "Started".green().bold(),
+        port
+    );
+
+    let result = CommandRunner::new("node", vec![testfile.to_string()])
+        .with_working_dir(path)
+        .with_env(INTERFACE_URL_ENV, format!("ws://localhost:{}", port))
+        .with_env(INSTANCE_ID_ENV, TEST_INSTANCE_ID)
+        .with_env(CONSISTENCY_METHOD_ENV, "test/consistency")
+        .run();
+    let shutdown = container.shutdown();
+
+    // the tests failing matters more than the container not stopping cleanly
+    result?;
+    shutdown.map_err(|err| format_err!("{}", err))?;
+    Ok(())
+}
+
+/// Makes sure the instance of the remote container answers before any test runs,
+/// and compares the hash of the DNA it runs with the one of the local bundle
+fn check_remote_container(bundle_path: &PathBuf, remote: &RemoteContainer) -> DefaultResult<()> {
+    let unreachable = |err: ClientError| {
+        format_err!(
+            "Could not reach the container at {}: {}",
+            remote.interface_url,
+            err
+        )
+    };
+    let client = InterfaceClient::connect(&remote.connect_url()).map_err(&unreachable)?;
+    let instances = client
+        .request("info/instances", json!({}))
+        .and_then(|request| {
+            request.wait_timeout(Duration::from_millis(REMOTE_CONTAINER_TIMEOUT_MS))
+        })
+        .map_err(&unreachable)?;
+    let instances: Value = match instances {
+        Value::String(instances) => serde_json::from_str(&instances)?,
+        instances => instances,
+    };
+    let instance = &instances[remote.instance_id.as_str()];
+    ensure!(
+        !instance.is_null(),
+        "The container at {} runs no instance {}",
+        remote.interface_url,
+        remote.instance_id
+    );
+
+    let local_hash = load_dna(bundle_path).map(|dna| dna_hash(&dna).to_string());
+    let mismatch = match (instance["dna_hash"].as_str(), local_hash) {
+        (Some(remote_hash), Ok(ref local_hash)) if remote_hash == local_hash => return Ok(()),
+        (Some(remote_hash), Ok(local_hash)) => format!(
+            "Instance {} runs the DNA {}, but {:?} has the hash {}",
+            remote.instance_id, remote_hash, bundle_path, local_hash
+        ),
+        (None, _) => format!(
+            "The container at {} does not tell the DNA hash of instance {}",
+            remote.interface_url, remote.instance_id
+        ),
+        (_, Err(err)) => format!(
+            "Could not compare the DNA of instance {} with the local one: {}",
+            remote.instance_id, err
+        ),
+    };
+    ensure!(!remote.require_hash_match, "{}", mismatch);
+    println!("{} {}", "Warning:".yellow().bold(), mismatch);
+    Ok(())
+}
+
+/// Runs the test file with node against the instance of the remote container
+fn run_with_remote_container(
+    path: &PathBuf,
+    testfile: &str,
+    remote: &RemoteContainer,
+) -> DefaultResult<()> {
+    let mut runner = CommandRunner::new("node", vec![testfile.to_string()])
+        .with_working_dir(path)
+        .with_env(INTERFACE_URL_ENV, remote.interface_url.clone())
+        .with_env(INSTANCE_ID_ENV, remote.instance_id.clone());
+    if let Some(ref token) = remote.token {
+        runner = runner.with_env(TOKEN_ENV, token.clone());
+    }
+    runner.run()?;
+    Ok(())
+}
+
+/// Runs the tests, against the given remote container instead of packaging the DNA
+/// if there is one
 pub fn test(
     path: &PathBuf,
     tests_folder: &str,
     testfile: &str,
     skip_build: bool,
+    container: bool,
+    remote: Option<RemoteContainer>,
 ) -> DefaultResult<()> {
     // create dist folder
     let dist_path = path.join(&DIST_DIR_NAME);
@@ -18,7 +293,11 @@ pub fn test(
         fs::create_dir(dist_path.as_path())?;
     }
 
-    if !skip_build {
+    if let Some(ref remote) = remote {
+        // fail before installing anything if the tests could not reach the container
+        let bundle_file_path = dist_path.join(package::DEFAULT_BUNDLE_FILE_NAME);
+        check_remote_container(&bundle_file_path, remote)?;
+    } else if !skip_build {
         // build the package file, within the dist folder
         let bundle_file_path = dist_path.join(package::DEFAULT_BUNDLE_FILE_NAME);
         println!(
@@ -42,21 +321,23 @@ pub fn test(
     if !node_modules_path.exists() {
         // CLI feedback
         println!("{}", "Installing node_modules".green().bold());
-        util::run_cmd(
-            tests_path.clone(),
-            "npm".to_string(),
-            vec!["install".to_string(), "--silent".to_string()],
-        )?;
+        CommandRunner::new("npm", vec!["install".to_string(), "--silent".to_string()])
+            .with_working_dir(tests_path.clone())
+            .run()?;
     }
 
     // execute the built test file using node
     // CLI feedback
     println!("{} tests in {}", "Running".green().bold(), testfile,);
-    util::run_cmd(
-        path.to_path_buf(),
-        "node".to_string(),
-        vec![testfile.to_string()],
-    )?;
+    if let Some(remote) = remote {
+        return run_with_remote_container(path, testfile, &remote);
+    }
+    if container {
+        return run_with_container(path, testfile);
+    }
+    CommandRunner::new("node", vec![testfile.to_string()])
+        .with_working_dir(path)
+        .run()?;
 
     Ok(())
 }
@@ -65,9 +346,50 @@ pub fn test(
 pub mod tests {
     use super::*;
     use assert_cmd::prelude::*;
-    use crate::cli::package;
-    use std::process::Command;
+    use crate::{cli::package, error::CommandFailed};
+    use holochain_core_types::{dna::Dna, json::JsonString};
+    use serde_json::Map;
+    use std::{
+        process::Command,
+        sync::{Arc, Mutex},
+    };
     use tempfile::{Builder, TempDir};
+    use test_utils::{
+        create_test_cap_with_fn_name, create_test_dna_with_cap, create_wasm_from_file,
+    };
+
+    /// Makes one zome call through the test container and exits with 0 if it worked
+    const ZOME_CALL_SCRIPT: &str = r#"
+const Client = require('rpc-websockets').Client
+const ws = new Client(process.env.HC_TEST_INTERFACE_URL)
+ws.on('open', () => {
+  const method = process.env.HC_TEST_INSTANCE_ID + '/test_zome/test_cap/commit_test'
+  ws.call(method, {})
+    .then(result => {
+      console.log(result)
+      process.exit(typeof result === 'string' && result.length > 0 ? 0 : 1)
+    })
+    .catch(error => {
+      console.error(error)
+      process.exit(1)
+    })
+})
+"#;
+
+    /// Waits for the test container to be consistent and exits with 0 if it could
+    const CONSISTENCY_SCRIPT: &str = r#"
+const Client = require('rpc-websockets').Client
+const ws = new Client(process.env.HC_TEST_INTERFACE_URL)
+ws.on('open', () => {
+  const instance_ids = [process.env.HC_TEST_INSTANCE_ID]
+  ws.call(process.env.HC_TEST_CONSISTENCY_METHOD, { instance_ids })
+    .then(result => process.exit(result === true ? 0 : 1))
+    .catch(error => {
+      console.error(error)
+      process.exit(1)
+    })
+})
+"#;
 
     const HOLOCHAIN_TEST_PREFIX: &str = "org.holochain.test";
 
@@ -91,8 +413,15 @@ pub mod tests {
             .assert()
             .success();
 
-        test(&temp_dir_path_buf, &TEST_DIR_NAME, "test/index.js", false)
-            .unwrap_or_else(|e| panic!("test call failed: {}", e));
+        test(
+            &temp_dir_path_buf,
+            &TEST_DIR_NAME,
+            "test/index.js",
+            false,
+            false,
+            None,
+        )
+        .unwrap_or_else(|e| panic!("test call failed: {}", e));
 
         // check success of packaging step
         assert!(
@@ -110,6 +439,314 @@ pub mod tests {
         );
     }
 
+    /// Inits a project and packages a DNA with the `commit_test` function
+    /// of the container API test wasm into its dist folder
+    fn gen_project_with_commit_test_dna() -> TempDir {
+        let temp_space = gen_dir();
+        Command::main_binary()
+            .unwrap()
+            .args(&["init", temp_space.path().to_str().unwrap()])
+            .assert()
+            .success();
+
+        let wasm = create_wasm_from_file(
+            "../container_api/wasm-test/target/wasm32-unknown-unknown/release/example_api_wasm.wasm",
+        );
+        let capability = create_test_cap_with_fn_name("commit_test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        let dist_path = temp_space.path().join(&DIST_DIR_NAME);
+        fs::create_dir_all(&dist_path).unwrap();
+        fs::write(
+            dist_path.join(package::DEFAULT_BUNDLE_FILE_NAME),
+            String::from(JsonString::from(dna)),
+        )
+        .unwrap();
+        temp_space
+    }
+
+    #[test]
+    fn test_command_runs_against_container() {
+        let temp_space = gen_project_with_commit_test_dna();
+        let temp_dir_path_buf = temp_space.path().to_path_buf();
+        fs::write(
+            temp_dir_path_buf.join(&TEST_DIR_NAME).join("zome_call.js"),
+            ZOME_CALL_SCRIPT,
+        )
+        .unwrap();
+
+        test(
+            &temp_dir_path_buf,
+            &TEST_DIR_NAME,
+            "test/zome_call.js",
+            true,
+            true,
+            None,
+        )
+        .unwrap_or_else(|e| panic!("test call failed: {}", e));
+    }
+
+    #[test]
+    fn test_container_offers_consistency() {
+        let temp_space = gen_project_with_commit_test_dna();
+        let temp_dir_path_buf = temp_space.path().to_path_buf();
+        fs::write(
+            temp_dir_path_buf
+                .join(&TEST_DIR_NAME)
+                .join("consistency.js"),
+            CONSISTENCY_SCRIPT,
+        )
+        .unwrap();
+
+        test(
+            &temp_dir_path_buf,
+            &TEST_DIR_NAME,
+            "test/consistency.js",
+            true,
+            true,
+            None,
+        )
+        .unwrap_or_else(|e| panic!("consistency failed: {}", e));
+    }
+
+    #[test]
+    fn test_command_propagates_exit_code_of_tests() {
+        let temp_space = gen_project_with_commit_test_dna();
+        let temp_dir_path_buf = temp_space.path().to_path_buf();
+        fs::write(
+            temp_dir_path_buf.join(&TEST_DIR_NAME).join("fail.js"),
+            "process.exit(3)",
+        )
+        .unwrap();
+
+        let error = test(
+            &temp_dir_path_buf,
+            &TEST_DIR_NAME,
+            "test/fail.js",
+            true,
+            true,
+            None,
+        )
+        .expect_err("failing tests should fail the command");
+        assert_eq!(
+            error
+                .downcast_ref::<CommandFailed>()
+                .map(|command_failed| command_failed.code),
+            Some(3)
+        );
+    }
+
+    /// Answers every request like a container would answer info/instances, and records the
+    /// resources clients connect to
+    struct MockContainer {
+        out: ws::Sender,
+        instances: String,
+        resources: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl ws::Handler for MockContainer {
+        fn on_open(&mut self, shake: ws::Handshake) -> ws::Result<()> {
+            let resource = shake.request.resource().to_string();
+            self.resources.lock().unwrap().push(resource);
+            Ok(())
+        }
+
+        fn on_message(&mut self, message: ws::Message) -> ws::Result<()> {
+            let request: Value = serde_json::from_str(&message.into_text()?).unwrap();
+            let response = json!({
+                "jsonrpc": "2.0",
+                "id": request["id"].clone(),
+                "result": self.instances.clone(),
+            });
+            self.out.send(response.to_string())
+        }
+    }
+
+    /// Starts a mock container whose only instance runs the DNA with the given hash,
+    /// returns the URL of its interface and the resources clients connected to
+    fn start_mock_container(
+        instance_id: &str,
+        dna_hash: &str,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let mut instances = Map::new();
+        instances.insert(
+            instance_id.to_string(),
+            json!({"id": instance_id, "dna": "test-dna", "dna_hash": dna_hash}),
+        );
+        let instances = Value::Object(instances).to_string();
+        let resources = Arc::new(Mutex::new(Vec::new()));
+        let connected = resources.clone();
+        let port = free_port().unwrap();
+        thread::spawn(move || {
+            ws::listen(("127.0.0.1", port), move |out: ws::Sender| MockContainer {
+                out,
+                instances: instances.clone(),
+                resources: connected.clone(),
+            })
+            .unwrap()
+        });
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        (format!("ws://127.0.0.1:{}", port), resources)
+    }
+
+    /// A project with a packaged DNA and tests that don't need `npm install`,
+    /// returns it together with the hash of the DNA
+    fn gen_project_with_bundle() -> (TempDir, String) {
+        let temp_space = gen_dir();
+        let dna = Dna::new();
+        let dist_path = temp_space.path().join(&DIST_DIR_NAME);
+        fs::create_dir_all(&dist_path).unwrap();
+        fs::write(
+            dist_path.join(package::DEFAULT_BUNDLE_FILE_NAME),
+            String::from(JsonString::from(dna.clone())),
+        )
+        .unwrap();
+        fs::create_dir_all(temp_space.path().join(&TEST_DIR_NAME).join("node_modules")).unwrap();
+        (temp_space, dna_hash(&dna).to_string())
+    }
+
+    /// Writes a test file that exits with 0 if the tests were told the given remote container,
+    /// and leaves a file "ran" behind to tell that it ran
+    fn write_env_check(project: &Path, remote: &RemoteContainer) {
+        let script = format!(
+            "require('fs').writeFileSync('ran', '')\n\
+             process.exit(process.env.{} === '{}' && process.env.{} === '{}' && process.env.{} === '{}' ? 0 : 1)",
+            INTERFACE_URL_ENV,
+            remote.interface_url,
+            INSTANCE_ID_ENV,
+            remote.instance_id,
+            TOKEN_ENV,
+            remote.token.clone().unwrap_or_default(),
+        );
+        fs::write(project.join(&TEST_DIR_NAME).join("env.js"), script).unwrap();
+    }
+
+    fn remote_container(interface_url: &str, require_hash_match: bool) -> RemoteContainer {
+        RemoteContainer {
+            interface_url: interface_url.to_string(),
+            instance_id: "ci-instance".to_string(),
+            token: Some("s3cr3t".to_string()),
+            require_hash_match,
+        }
+    }
+
+    #[test]
+    fn test_command_runs_against_remote_container() {
+        let (project, hash) = gen_project_with_bundle();
+        let (url, resources) = start_mock_container("ci-instance", &hash);
+        let remote = remote_container(&url, true);
+        write_env_check(project.path(), &remote);
+
+        test(
+            &project.path().to_path_buf(),
+            &TEST_DIR_NAME,
+            "test/env.js",
+            false,
+            false,
+            Some(remote),
+        )
+        .unwrap_or_else(|e| panic!("test call failed: {}", e));
+
+        assert!(project.path().join("ran").exists());
+        // the tests ran against the packaged bundle as it was
+        assert_eq!(
+            load_dna(
+                &project
+                    .path()
+                    .join(&DIST_DIR_NAME)
+                    .join(package::DEFAULT_BUNDLE_FILE_NAME)
+            )
+            .map(|dna| dna_hash(&dna).to_string())
+            .unwrap(),
+            hash
+        );
+        assert_eq!(
+            *resources.lock().unwrap(),
+            vec!["/?token=s3cr3t".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_command_checks_dna_hash_of_remote_container() {
+        let (project, _) = gen_project_with_bundle();
+        let (url, _) = start_mock_container("ci-instance", "QmOtherDna");
+
+        let remote = remote_container(&url, false);
+        write_env_check(project.path(), &remote);
+        test(
+            &project.path().to_path_buf(),
+            &TEST_DIR_NAME,
+            "test/env.js",
+            false,
+            false,
+            Some(remote),
+        )
+        .unwrap_or_else(|e| panic!("a mismatch should only warn: {}", e));
+        assert!(project.path().join("ran").exists());
+        fs::remove_file(project.path().join("ran")).unwrap();
+
+        let error = test(
+            &project.path().to_path_buf(),
+            &TEST_DIR_NAME,
+            "test/env.js",
+            false,
+            false,
+            Some(remote_container(&url, true)),
+        )
+        .expect_err("a mismatch should fail with --require-hash-match");
+        assert!(
+            error
+                .to_string()
+                .contains("Instance ci-instance runs the DNA QmOtherDna"),
+            "error = {}",
+            error
+        );
+        assert!(!project.path().join("ran").exists());
+
+        let error = test(
+            &project.path().to_path_buf(),
+            &TEST_DIR_NAME,
+            "test/env.js",
+            false,
+            false,
+            Some(RemoteContainer {
+                instance_id: "other-instance".to_string(),
+                ..remote_container(&url, false)
+            }),
+        )
+        .expect_err("an unknown instance should fail");
+        assert!(error
+            .to_string()
+            .contains("runs no instance other-instance"));
+    }
+
+    #[test]
+    fn test_command_fails_before_tests_if_remote_container_is_unreachable() {
+        let (project, _) = gen_project_with_bundle();
+        let url = format!("ws://127.0.0.1:{}", free_port().unwrap());
+        let remote = remote_container(&url, false);
+        write_env_check(project.path(), &remote);
+
+        let error = test(
+            &project.path().to_path_buf(),
+            &TEST_DIR_NAME,
+            "test/env.js",
+            false,
+            false,
+            Some(remote),
+        )
+        .expect_err("an unreachable container should fail the command");
+        assert!(
+            error
+                .to_string()
+                .starts_with(&format!("Could not reach the container at {}", url)),
+            "error = {}",
+            error
+        );
+        assert!(!project.path().join("ran").exists());
+    }
+
     #[test]
     fn test_command_no_test_folder() {
         let temp_space = gen_dir();
@@ -123,7 +760,14 @@ pub mod tests {
             .assert()
             .success();
 
-        let result = test(&temp_dir_path_buf, "west", "test/index.js", false);
+        let result = test(
+            &temp_dir_path_buf,
+            "west",
+            "test/index.js",
+            false,
+            false,
+            None,
+        );
 
         // should err because "west" directory doesn't exist
         assert!(result.is_err());
@@ -1,19 +1,82 @@
 use base64;
-use crate::{error::DefaultResult, util};
+use crate::{
+    cli::package::BUILD_CONFIG_FILE_NAME,
+    error::{DefaultResult, MissingArtifact},
+    util::CommandRunner,
+};
+use serde::{Deserialize, Deserializer};
 use serde_json;
 use std::{
-    collections::HashMap,
-    fs::File,
+    collections::BTreeMap,
+    fs::{self, File},
     io::Read,
     path::{Path, PathBuf},
 };
+use toml;
+
+pub const CARGO_FILE_NAME: &str = "Cargo.toml";
 
-#[derive(Clone, Deserialize, Serialize)]
+/// The contents of a `.build` file: the steps that build the code of a zome, in order,
+/// and the WASM file they produce, relative to the directory of the code
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct Build {
-    pub steps: HashMap<String, Vec<String>>,
+    #[serde(deserialize_with = "deserialize_steps")]
+    pub steps: Vec<BuildStep>,
     pub artifact: PathBuf,
 }
 
+/// A command that is part of a build, run in the directory of the code
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct BuildStep {
+    pub cmd: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Environment variables the command gets on top of the ones `hc` runs with
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+}
+
+/// Steps are a list, but older `.build` files have an object of commands and their arguments
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Steps {
+    List(Vec<BuildStep>),
+    Commands(BTreeMap<String, Vec<String>>),
+}
+
+fn deserialize_steps<'de, D>(deserializer: D) -> Result<Vec<BuildStep>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Steps::deserialize(deserializer)? {
+        Steps::List(steps) => steps,
+        Steps::Commands(commands) => commands
+            .into_iter()
+            .map(|(cmd, args)| BuildStep {
+                cmd,
+                args,
+                env: BTreeMap::new(),
+            })
+            .collect(),
+    })
+}
+
+#[derive(Deserialize)]
+struct CargoToml {
+    package: CargoPackage,
+    lib: Option<CargoLib>,
+}
+
+#[derive(Deserialize)]
+struct CargoPackage {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct CargoLib {
+    name: Option<String>,
+}
+
 impl Build {
     /// Creates a Build struct from a .build JSON file and returns it
     pub fn from_file<T: AsRef<Path>>(path: T) -> DefaultResult<Build> {
@@ -24,6 +87,39 @@ impl Build {
         Ok(build)
     }
 
+    /// The build of the code in the given directory: the one in its .build file, or
+    /// the default build of Rust if there is a Cargo.toml but no .build file.
+    /// None if the directory has neither.
+    pub fn from_dir<T: AsRef<Path>>(dir: T) -> DefaultResult<Option<Build>> {
+        let build_file = dir.as_ref().join(BUILD_CONFIG_FILE_NAME);
+        let cargo_file = dir.as_ref().join(CARGO_FILE_NAME);
+
+        if build_file.is_file() {
+            Ok(Some(Build::from_file(build_file)?))
+        } else if cargo_file.is_file() {
+            let cargo_toml: CargoToml = toml::from_str(&fs::read_to_string(cargo_file)?)?;
+            let crate_name = cargo_toml
+                .lib
+                .and_then(|lib| lib.name)
+                .unwrap_or(cargo_toml.package.name);
+            Ok(Some(Build::rust(&crate_name)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// The default build of a Rust crate with the given name
+    pub fn rust(crate_name: &str) -> Build {
+        Build::with_artifact(format!(
+            "target/wasm32-unknown-unknown/release/{}.wasm",
+            crate_name.replace("-", "_")
+        ))
+        .cmd(
+            "cargo",
+            &["build", "--release", "--target=wasm32-unknown-unknown"],
+        )
+    }
+
     pub fn save_as<T: AsRef<Path>>(&self, path: T) -> DefaultResult<()> {
         let file = File::create(path)?;
 
@@ -32,10 +128,18 @@ impl Build {
         Ok(())
     }
 
-    /// Starts the build using the supplied build steps and returns the contents of the artifact
-    pub fn run(&self, base_path: &PathBuf) -> DefaultResult<String> {
-        for (bin, args) in &self.steps {
-            util::run_cmd(base_path.to_path_buf(), bin.to_string(), args.clone())?;
+    /// Starts the build using the supplied build steps and returns the contents of the artifact.
+    /// The output of the steps is prefixed with `name`, the name of the zome being built.
+    /// Fails with MissingArtifact if the steps did not produce the artifact.
+    pub fn run(&self, base_path: &PathBuf, name: &str) -> DefaultResult<String> {
+        for step in &self.steps {
+            let mut runner = CommandRunner::new(step.cmd.as_str(), step.args.clone())
+                .with_working_dir(base_path)
+                .with_prefix(name);
+            for (key, value) in &step.env {
+                runner = runner.with_env(key.as_str(), value.as_str());
+            }
+            runner.run()?;
         }
 
         let artifact_path = base_path.join(&self.artifact);
@@ -46,7 +150,11 @@ impl Build {
 
             Ok(base64::encode(&wasm_buf))
         } else {
-            bail!("artifact path either doesn't point to a file or doesn't exist")
+            Err(MissingArtifact {
+                zome: name.to_string(),
+                path: artifact_path,
+            }
+            .into())
         }
     }
 
@@ -54,11 +162,12 @@ impl Build {
         let path: PathBuf = artifact.into();
 
         Build {
-            steps: HashMap::new(),
+            steps: Vec::new(),
             artifact: path,
         }
     }
 
+    /// Appends a step running the given command
     pub fn cmd<S: Into<String> + Clone>(mut self, cmd: S, args: &[S]) -> Build {
         let cmd: String = cmd.into();
         let args: Vec<_> = args
@@ -70,7 +179,93 @@ impl Build {
             })
             .collect();
 
-        self.steps.insert(cmd, args);
+        self.steps.push(BuildStep {
+            cmd,
+            args,
+            env: BTreeMap::new(),
+        });
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn steps_keep_their_order_and_env() {
+        let build: Build = serde_json::from_str(
+            r#"{
+                "steps": [
+                    {"cmd": "npm", "args": ["install"]},
+                    {"cmd": "npm", "args": ["run", "build"], "env": {"NODE_ENV": "production"}}
+                ],
+                "artifact": "build/zome.wasm"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            build.steps.iter().map(|step| &step.cmd).collect::<Vec<_>>(),
+            vec!["npm", "npm"]
+        );
+        assert_eq!(build.steps[1].args, vec!["run", "build"]);
+        assert_eq!(
+            build.steps[1].env.get("NODE_ENV"),
+            Some(&String::from("production"))
+        );
+        assert!(build.steps[0].env.is_empty());
+    }
+
+    #[test]
+    fn steps_can_be_given_as_commands() {
+        let build: Build = serde_json::from_str(
+            r#"{
+                "steps": {"cargo": ["build", "--release", "--target=wasm32-unknown-unknown"]},
+                "artifact": "target/wasm32-unknown-unknown/release/code.wasm"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(build, Build::rust("code"));
+    }
+
+    #[test]
+    fn rust_crates_without_build_file_get_the_default_build() {
+        let dir = tempdir().unwrap();
+        assert_eq!(Build::from_dir(dir.path()).unwrap(), None);
+
+        fs::write(
+            dir.path().join(CARGO_FILE_NAME),
+            "[package]\nname = \"my-zome\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            Build::from_dir(dir.path()).unwrap(),
+            Some(Build::rust("my-zome"))
+        );
+        assert_eq!(
+            Build::rust("my-zome").artifact,
+            PathBuf::from("target/wasm32-unknown-unknown/release/my_zome.wasm")
+        );
+
+        let build = Build::with_artifact("zome.wasm").cmd("make", &[]);
+        build
+            .save_as(dir.path().join(BUILD_CONFIG_FILE_NAME))
+            .unwrap();
+        assert_eq!(Build::from_dir(dir.path()).unwrap(), Some(build));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn missing_artifacts_name_the_zome_and_path() {
+        let dir = tempdir().unwrap();
+        let build = Build::with_artifact("zome.wasm").cmd("true", &[]);
+
+        let error = build.run(&dir.path().to_path_buf(), "chat").unwrap_err();
+        let missing_artifact = error.downcast_ref::<MissingArtifact>().unwrap();
+        assert_eq!(missing_artifact.zome, "chat");
+        assert_eq!(missing_artifact.path, dir.path().join("zome.wasm"));
+    }
+}
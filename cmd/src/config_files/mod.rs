@@ -1,9 +1,13 @@
 mod app;
 mod build;
 mod dht;
+mod workspace;
 
 pub use self::{
     app::{App, Author},
-    build::Build,
+    build::{Build, BuildStep, CARGO_FILE_NAME},
     dht::Dht,
+    workspace::{
+        PackagedMember, Workspace, WorkspaceBridge, WorkspaceManifest, WORKSPACE_FILE_NAME,
+    },
 };
@@ -0,0 +1,161 @@
+use crate::error::DefaultResult;
+use serde_json;
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+use toml;
+
+pub const WORKSPACE_FILE_NAME: &str = "workspace.toml";
+
+pub const WORKSPACE_DIST_DIR_NAME: &str = "dist";
+
+pub const WORKSPACE_MANIFEST_FILE_NAME: &str = "workspace.json";
+
+pub const MEMBER_BUNDLE_EXTENSION: &str = "dna.json";
+
+/// The contents of a `workspace.toml` file: the directories of the DNAs that get packaged
+/// together, relative to the workspace, and the bridges between their instances
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Workspace {
+    pub members: Vec<String>,
+    #[serde(default)]
+    pub bridges: Vec<WorkspaceBridge>,
+}
+
+/// An instance of the `caller` member that can call the zome functions
+/// of an instance of the `callee` member
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspaceBridge {
+    pub caller: String,
+    pub callee: String,
+    /// The handle the caller's zomes call the callee by, see `hdk::call_bridge()`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handle: Option<String>,
+}
+
+/// What `hc package --workspace` wrote to the dist folder: the bundle of every member
+/// with its DNA hash, and the bridges of the workspace
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct WorkspaceManifest {
+    pub members: BTreeMap<String, PackagedMember>,
+    #[serde(default)]
+    pub bridges: Vec<WorkspaceBridge>,
+}
+
+/// The bundle file of a member, relative to the workspace, and the hash of its DNA
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct PackagedMember {
+    pub file: PathBuf,
+    pub hash: String,
+}
+
+impl Workspace {
+    /// Creates a Workspace struct from a workspace.toml file and checks that its bridges
+    /// only name members
+    pub fn from_file<T: AsRef<Path>>(path: T) -> DefaultResult<Workspace> {
+        let workspace: Workspace = toml::from_str(&fs::read_to_string(path)?)?;
+
+        for bridge in &workspace.bridges {
+            for member in &[&bridge.caller, &bridge.callee] {
+                ensure!(
+                    workspace.members.contains(member),
+                    "bridge from {} to {} names {}, which is not a member of the workspace",
+                    bridge.caller,
+                    bridge.callee,
+                    member
+                );
+            }
+        }
+
+        Ok(workspace)
+    }
+
+    /// The workspace of the given directory, None if it has no workspace.toml
+    pub fn from_dir<T: AsRef<Path>>(dir: T) -> DefaultResult<Option<Workspace>> {
+        let workspace_file = dir.as_ref().join(WORKSPACE_FILE_NAME);
+
+        if workspace_file.is_file() {
+            Ok(Some(Workspace::from_file(workspace_file)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Where the bundle of the given member gets written to, relative to the workspace
+    pub fn bundle_path(member: &str) -> PathBuf {
+        PathBuf::from(WORKSPACE_DIST_DIR_NAME)
+            .join(format!("{}.{}", member, MEMBER_BUNDLE_EXTENSION))
+    }
+}
+
+impl WorkspaceManifest {
+    /// Where the manifest of the workspace at `root` gets written to
+    pub fn path(root: &Path) -> PathBuf {
+        root.join(WORKSPACE_DIST_DIR_NAME)
+            .join(WORKSPACE_MANIFEST_FILE_NAME)
+    }
+
+    pub fn from_file<T: AsRef<Path>>(path: T) -> DefaultResult<WorkspaceManifest> {
+        let file = File::open(path)?;
+
+        let manifest = serde_json::from_reader(&file)?;
+
+        Ok(manifest)
+    }
+
+    pub fn save_as<T: AsRef<Path>>(&self, path: T) -> DefaultResult<()> {
+        let file = File::create(path)?;
+
+        serde_json::to_writer_pretty(&file, self)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::Builder;
+
+    #[test]
+    fn workspace_bridges_have_to_name_members() {
+        let dir = Builder::new()
+            .prefix("org.holochain.test")
+            .tempdir()
+            .unwrap();
+        assert_eq!(Workspace::from_dir(dir.path()).unwrap(), None);
+
+        let workspace_file = dir.path().join(WORKSPACE_FILE_NAME);
+        fs::write(
+            &workspace_file,
+            "members = [\"main\", \"admin\"]\n\n[[bridges]]\ncaller = \"admin\"\ncallee = \"main\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            Workspace::from_dir(dir.path()).unwrap(),
+            Some(Workspace {
+                members: vec![String::from("main"), String::from("admin")],
+                bridges: vec![WorkspaceBridge {
+                    caller: String::from("admin"),
+                    callee: String::from("main"),
+                    handle: None,
+                }],
+            })
+        );
+
+        fs::write(
+            &workspace_file,
+            "members = [\"main\"]\n\n[[bridges]]\ncaller = \"admin\"\ncallee = \"main\"\n",
+        )
+        .unwrap();
+        let error = Workspace::from_file(&workspace_file).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "bridge from admin to main names admin, which is not a member of the workspace"
+        );
+    }
+}
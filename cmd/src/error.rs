@@ -1,4 +1,5 @@
 use failure::Error;
+use std::{path::PathBuf, time::Duration};
 
 #[derive(Debug, Fail)]
 pub enum HolochainError {
@@ -6,5 +7,44 @@ pub enum HolochainError {
     Default(Error),
 }
 
+/// A command that `hc` ran was not successful, `hc` exits with the same code
+#[derive(Debug, Fail)]
+#[fail(display = "command {} exited with code {}", command, code)]
+pub struct CommandFailed {
+    pub command: String,
+    pub code: i32,
+}
+
+/// A command that `hc` ran took longer than it was given and got killed
+#[derive(Debug, Fail)]
+#[fail(display = "command {} timed out after {:?}", command, timeout)]
+pub struct CommandTimedOut {
+    pub command: String,
+    pub timeout: Duration,
+}
+
+/// The build steps of a zome finished without producing the artifact they declare
+#[derive(Debug, Fail)]
+#[fail(
+    display = "building zome {} did not produce its artifact {:?}",
+    zome, path
+)]
+pub struct MissingArtifact {
+    pub zome: String,
+    pub path: PathBuf,
+}
+
+impl HolochainError {
+    /// The code `hc` exits with because of this error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            HolochainError::Default(error) => error
+                .downcast_ref::<CommandFailed>()
+                .map(|command_failed| command_failed.code)
+                .unwrap_or(1),
+        }
+    }
+}
+
 pub type DefaultResult<T> = Result<T, Error>;
 pub type HolochainResult<T> = Result<T, HolochainError>;
@@ -10,6 +10,7 @@ extern crate serde;
 extern crate serde_derive;
 extern crate assert_cmd;
 extern crate base64;
+extern crate chrono;
 extern crate colored;
 extern crate dir_diff;
 extern crate semver;
@@ -17,9 +18,15 @@ extern crate toml;
 #[macro_use]
 extern crate serde_json;
 extern crate ignore;
+#[cfg(unix)]
+extern crate libc;
 extern crate rustyline;
 extern crate tempfile;
+#[cfg(test)]
+extern crate test_utils;
 extern crate uuid;
+#[cfg(test)]
+extern crate ws;
 
 mod cli;
 mod config_files;
@@ -27,7 +34,7 @@ mod error;
 mod util;
 
 use crate::error::{HolochainError, HolochainResult};
-use std::path::PathBuf;
+use std::{io, path::PathBuf};
 use structopt::StructOpt;
 
 #[derive(StructOpt)]
@@ -52,6 +59,12 @@ enum Cli {
         strip_meta: bool,
         #[structopt(long = "output", short = "o", parse(from_os_str))]
         output: Option<PathBuf>,
+        #[structopt(
+            long,
+            help = "Packages every member DNA of the workspace.toml into dist/, with a manifest of their hashes",
+            conflicts_with = "output"
+        )]
+        workspace: bool,
     },
     #[structopt(
         name = "unpack",
@@ -71,6 +84,25 @@ enum Cli {
     Init {
         #[structopt(parse(from_os_str))]
         path: PathBuf,
+        #[structopt(
+            long,
+            help = "The built-in template to use: minimal, rust-zome-with-tests or multi-zome"
+        )]
+        template: Option<String>,
+        #[structopt(
+            long = "from-git",
+            help = "A git repository to use as template, a branch or tag can follow a '#'",
+            conflicts_with = "template"
+        )]
+        from_git: Option<String>,
+        #[structopt(
+            long = "var",
+            help = "Sets a template variable, as key=value",
+            parse(try_from_str = "cli::parse_variable")
+        )]
+        vars: Vec<(String, String)>,
+        #[structopt(long, help = "Initializes the directory even if it is not empty")]
+        force: bool,
     },
     #[structopt(
         name = "generate",
@@ -108,6 +140,57 @@ enum Cli {
             help = "Automatically package project before running"
         )]
         package: bool,
+        #[structopt(
+            long,
+            help = "Opens a console to list the instances, call their zome functions and watch their chains"
+        )]
+        repl: bool,
+        #[structopt(
+            long,
+            help = "Runs an instance of every member of the workspace, with its bridges, from the manifest in dist/"
+        )]
+        workspace: bool,
+    },
+    #[structopt(
+        name = "call",
+        alias = "c",
+        about = "Calls a single zome function on a local storage directory, without a network"
+    )]
+    Call {
+        #[structopt(
+            long,
+            help = "The storage directory of the instance, defaults to '.hc/storage'",
+            parse(from_os_str)
+        )]
+        storage: Option<PathBuf>,
+        #[structopt(
+            long,
+            help = "The packaged DNA file, defaults to 'bundle.json'",
+            parse(from_os_str)
+        )]
+        dna: Option<PathBuf>,
+        #[structopt(long, short, help = "The zome to call")]
+        zome: String,
+        #[structopt(
+            long,
+            help = "The capability of the function, can be left out if there is only one"
+        )]
+        capability: Option<String>,
+        #[structopt(long, short, help = "The function to call")]
+        function: String,
+        #[structopt(
+            long,
+            help = "The parameters of the call as JSON",
+            default_value = "{}"
+        )]
+        params: String,
+        #[structopt(long, help = "Prints the result as returned by the zome")]
+        raw: bool,
+        #[structopt(
+            long,
+            help = "Creates the storage directory with a fresh chain if it does not exist"
+        )]
+        create: bool,
     },
     #[structopt(
         name = "test",
@@ -133,6 +216,174 @@ enum Cli {
             help = "Skip packaging DNA"
         )]
         skip_build: bool,
+        #[structopt(
+            long,
+            help = "Runs the tests against a container with the packaged DNA, reachable at HC_TEST_INTERFACE_URL"
+        )]
+        container: bool,
+        #[structopt(
+            long = "interface-url",
+            requires = "instance_id",
+            conflicts_with = "container",
+            help = "Runs the tests against the instance given by --instance-id of a container that runs already, reachable at this websocket URL, instead of packaging the DNA"
+        )]
+        interface_url: Option<String>,
+        #[structopt(
+            long = "instance-id",
+            requires = "interface_url",
+            help = "The instance of the container at --interface-url to run the tests against"
+        )]
+        instance_id: Option<String>,
+        #[structopt(
+            long,
+            help = "Token for a secured interface of the container at --interface-url, defaults to HC_TEST_TOKEN"
+        )]
+        token: Option<String>,
+        #[structopt(
+            long = "require-hash-match",
+            help = "Fails instead of warning if the instance at --interface-url runs another DNA than dist/bundle.json"
+        )]
+        require_hash_match: bool,
+    },
+    #[structopt(
+        name = "diff",
+        about = "Shows how two packaged versions of a DNA differ"
+    )]
+    Diff {
+        #[structopt(help = "The packaged old version of the DNA", parse(from_os_str))]
+        old: PathBuf,
+        #[structopt(help = "The packaged new version of the DNA", parse(from_os_str))]
+        new: PathBuf,
+        #[structopt(
+            long = "check-compat",
+            help = "Fails if the new version has breaking changes"
+        )]
+        check_compat: bool,
+    },
+    #[structopt(
+        name = "hash",
+        about = "Prints the hash of a packaged DNA and what it was built from"
+    )]
+    Hash {
+        #[structopt(
+            help = "The packaged DNA file, defaults to 'bundle.json'",
+            parse(from_os_str)
+        )]
+        path: Option<PathBuf>,
+    },
+    #[structopt(
+        name = "dna",
+        about = "Shows the packaged DNA or edits the name and description in app.json"
+    )]
+    Dna {
+        #[structopt(subcommand)]
+        command: DnaCommand,
+    },
+    #[structopt(
+        name = "property",
+        about = "Gets or sets a property of the DNA in app.json"
+    )]
+    Property {
+        #[structopt(subcommand)]
+        command: PropertyCommand,
+    },
+    #[structopt(
+        name = "chain",
+        about = "Exports the source chain of a storage directory into a JSON file or imports it"
+    )]
+    Chain {
+        #[structopt(subcommand)]
+        command: ChainCommand,
+    },
+    #[structopt(
+        name = "completions",
+        about = "Prints the script that completes the commands of hc in the given shell"
+    )]
+    Completions {
+        #[structopt(help = "The shell: bash, zsh or fish")]
+        shell: String,
+    },
+    #[structopt(
+        name = "introspect",
+        about = "Prints the commands of hc with their arguments, for tools that wrap hc"
+    )]
+    Introspect {
+        #[structopt(long, help = "Prints them as JSON")]
+        json: bool,
+    },
+}
+
+#[derive(StructOpt)]
+enum DnaCommand {
+    #[structopt(
+        name = "show",
+        about = "Prints the name, description, properties, zomes and entry types of a packaged DNA"
+    )]
+    Show {
+        #[structopt(
+            help = "The packaged DNA file, defaults to 'bundle.json'",
+            parse(from_os_str)
+        )]
+        path: Option<PathBuf>,
+    },
+    #[structopt(name = "set-name", about = "Sets the name of the DNA")]
+    SetName { name: String },
+    #[structopt(name = "set-description", about = "Sets the description of the DNA")]
+    SetDescription { description: String },
+}
+
+#[derive(StructOpt)]
+enum PropertyCommand {
+    #[structopt(name = "get", about = "Prints the value of a property")]
+    Get { key: String },
+    #[structopt(
+        name = "set",
+        about = "Sets a property, numbers and true/false are taken as such, anything else as a string"
+    )]
+    Set {
+        key: String,
+        value: String,
+        #[structopt(long, help = "Takes the value as JSON, e.g. for lists and objects")]
+        json: bool,
+    },
+}
+
+#[derive(StructOpt)]
+enum ChainCommand {
+    #[structopt(
+        name = "export",
+        about = "Writes the headers and entries of the chain into a JSON file"
+    )]
+    Export {
+        #[structopt(
+            long,
+            help = "The storage directory of the chain, defaults to .hc/storage",
+            parse(from_os_str)
+        )]
+        storage: Option<PathBuf>,
+        #[structopt(long, help = "The file to write the chain to", parse(from_os_str))]
+        out: PathBuf,
+        #[structopt(
+            long = "redact-private",
+            help = "Leaves out the content of entries of private types, their hashes stay"
+        )]
+        redact_private: bool,
+    },
+    #[structopt(
+        name = "import",
+        about = "Verifies the chain of a JSON file written by export and writes it into a storage directory"
+    )]
+    Import {
+        #[structopt(help = "The file written by export", parse(from_os_str))]
+        path: PathBuf,
+        #[structopt(
+            long,
+            help = "The storage directory to import into, defaults to .hc/storage",
+            parse(from_os_str)
+        )]
+        storage: Option<PathBuf>,
+        #[structopt(long, help = "Replaces the chain the storage directory holds already")]
+        force: bool,
     },
 }
 
@@ -140,7 +391,7 @@ fn main() {
     run().unwrap_or_else(|err| {
         eprintln!("{}", err);
 
-        ::std::process::exit(1);
+        ::std::process::exit(err.exit_code());
     });
 }
 
@@ -149,28 +400,150 @@ fn run() -> HolochainResult<()> {
 
     match args {
         Cli::Agent => cli::agent().map_err(|err| HolochainError::Default(err))?,
-        Cli::Package { strip_meta, output } => {
-            cli::package(strip_meta, output).map_err(|err| HolochainError::Default(err))?
+        Cli::Package {
+            strip_meta,
+            output,
+            workspace,
+        } => if workspace {
+            cli::package_workspace(&PathBuf::from("."), strip_meta).map(|_| ())
+        } else {
+            cli::package(strip_meta, output)
         }
+        .map_err(|err| HolochainError::Default(err))?,
         Cli::Unpack { path, to } => {
             cli::unpack(&path, &to).map_err(|err| HolochainError::Default(err))?
         }
-        Cli::Init { path } => cli::init(&path).map_err(|err| HolochainError::Default(err))?,
+        Cli::Init {
+            path,
+            template,
+            from_git,
+            vars,
+            force,
+        } => {
+            let source = match (template, from_git) {
+                (_, Some(url)) => cli::TemplateSource::Git(url),
+                (Some(name), None) => cli::TemplateSource::BuiltIn(name),
+                (None, None) => cli::TemplateSource::default(),
+            };
+            cli::init(&path, &source, &vars, force).map_err(|err| HolochainError::Default(err))?
+        }
         Cli::Generate { zome, language } => {
             cli::generate(&zome, &language).map_err(|err| HolochainError::Default(err))?
         }
-        Cli::Run { package, port } => {
-            cli::run(package, port).map_err(|err| HolochainError::Default(err))?
+        Cli::Run {
+            package,
+            port,
+            repl,
+            workspace,
+        } => {
+            cli::run(package, port, repl, workspace).map_err(|err| HolochainError::Default(err))?
+        }
+        Cli::Call {
+            storage,
+            dna,
+            zome,
+            capability,
+            function,
+            params,
+            raw,
+            create,
+        } => {
+            let storage = storage.unwrap_or_else(|| PathBuf::from(cli::DEFAULT_STORAGE_DIR));
+            let dna = dna.unwrap_or_else(|| PathBuf::from(cli::package::DEFAULT_BUNDLE_FILE_NAME));
+            let result = cli::call(
+                &storage, &dna, &zome, capability, &function, &params, create,
+            )
+            .map_err(|err| HolochainError::Default(err))?;
+            println!("{}", cli::format_call_result(&result, raw));
         }
         Cli::Test {
             dir,
             testfile,
             skip_build,
+            container,
+            interface_url,
+            instance_id,
+            token,
+            require_hash_match,
         } => {
             let tests_folder = dir.unwrap_or(cli::TEST_DIR_NAME.to_string());
             let test_file = testfile.unwrap_or("test/index.js".to_string());
-            cli::test(&PathBuf::from("."), &tests_folder, &test_file, skip_build)
-                .map_err(|err| HolochainError::Default(err))?
+            let remote = cli::RemoteContainer::from_options(
+                interface_url,
+                instance_id,
+                token,
+                require_hash_match,
+            )
+            .map_err(|err| HolochainError::Default(err))?;
+            cli::test(
+                &PathBuf::from("."),
+                &tests_folder,
+                &test_file,
+                skip_build,
+                container,
+                remote,
+            )
+            .map_err(|err| HolochainError::Default(err))?
+        }
+        Cli::Diff {
+            old,
+            new,
+            check_compat,
+        } => cli::diff(&old, &new, check_compat).map_err(|err| HolochainError::Default(err))?,
+        Cli::Hash { path } => {
+            let path =
+                path.unwrap_or_else(|| PathBuf::from(cli::package::DEFAULT_BUNDLE_FILE_NAME));
+            cli::hash(&path).map_err(|err| HolochainError::Default(err))?
+        }
+        Cli::Dna { command } => {
+            let project = PathBuf::from(".");
+            match command {
+                DnaCommand::Show { path } => {
+                    let path = path
+                        .unwrap_or_else(|| PathBuf::from(cli::package::DEFAULT_BUNDLE_FILE_NAME));
+                    cli::dna_show(&path)
+                }
+                DnaCommand::SetName { name } => cli::dna_set_name(&project, &name),
+                DnaCommand::SetDescription { description } => {
+                    cli::dna_set_description(&project, &description)
+                }
+            }
+            .map_err(|err| HolochainError::Default(err))?
+        }
+        Cli::Property { command } => {
+            let project = PathBuf::from(".");
+            match command {
+                PropertyCommand::Get { key } => cli::property_get(&project, &key),
+                PropertyCommand::Set { key, value, json } => {
+                    cli::property_set(&project, &key, &value, json)
+                }
+            }
+            .map_err(|err| HolochainError::Default(err))?
+        }
+        Cli::Chain { command } => {
+            let default_storage = || PathBuf::from(cli::DEFAULT_STORAGE_DIR);
+            match command {
+                ChainCommand::Export {
+                    storage,
+                    out,
+                    redact_private,
+                } => cli::chain_export(
+                    &storage.unwrap_or_else(default_storage),
+                    &out,
+                    redact_private,
+                ),
+                ChainCommand::Import {
+                    path,
+                    storage,
+                    force,
+                } => cli::chain_import(&path, &storage.unwrap_or_else(default_storage), force),
+            }
+            .map_err(|err| HolochainError::Default(err))?
+        }
+        Cli::Completions { shell } => cli::completions(Cli::clap(), &shell, &mut io::stdout())
+            .map_err(|err| HolochainError::Default(err))?,
+        Cli::Introspect { json } => {
+            cli::introspect(&Cli::clap(), json).map_err(|err| HolochainError::Default(err))?
         }
     }
 
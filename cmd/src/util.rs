@@ -1,24 +1,236 @@
 use colored::*;
-use crate::error::DefaultResult;
-use std::{path::PathBuf, process::Command};
+use crate::error::{CommandFailed, CommandTimedOut, DefaultResult};
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Read},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
 
-pub fn run_cmd(base_path: PathBuf, bin: String, args: Vec<String>) -> DefaultResult<()> {
-    let pretty_command = format!("{} {}", bin.green(), args.join(" ").cyan());
+/// How often a command with a timeout gets checked for having exited, in milliseconds
+const POLL_INTERVAL_MS: u64 = 10;
 
-    println!("> {}", pretty_command);
+/// Runs an external command, like a build step, `npm` or `node`, on behalf of `hc`.
+/// Its output gets streamed line by line, prefixed with the name of the zome or step
+/// it runs for if given, so output of several commands running at once stays readable.
+pub struct CommandRunner {
+    bin: String,
+    args: Vec<String>,
+    working_dir: PathBuf,
+    env: HashMap<String, String>,
+    timeout: Option<Duration>,
+    prefix: Option<String>,
+}
+
+/// What came out of a command that ran
+#[derive(Debug)]
+pub struct CommandOutput {
+    /// None if the command got killed by a signal, which includes timing out
+    pub code: Option<i32>,
+    pub timed_out: bool,
+    pub duration: Duration,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+impl CommandRunner {
+    /// A runner of the given command in the current directory
+    pub fn new<S: Into<String>>(bin: S, args: Vec<String>) -> Self {
+        CommandRunner {
+            bin: bin.into(),
+            args,
+            working_dir: PathBuf::from("."),
+            env: HashMap::new(),
+            timeout: None,
+            prefix: None,
+        }
+    }
+
+    pub fn with_working_dir<P: Into<PathBuf>>(mut self, working_dir: P) -> Self {
+        self.working_dir = working_dir.into();
+        self
+    }
+
+    /// Sets an environment variable for the command, on top of the ones `hc` runs with
+    pub fn with_env<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    /// Kills the command, together with the processes it started, if it runs longer.
+    /// The command then runs in a process group of its own, so it doesn't get interrupted
+    /// together with `hc` by Ctrl-C anymore; `hc` itself still is.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Prefixes every line the command outputs, with the name of the zome or step it runs for
+    pub fn with_prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    fn pretty_command(&self) -> String {
+        format!("{} {}", self.bin.green(), self.args.join(" ").cyan())
+    }
+
+    /// Runs the command to its end and returns its output, whether it was successful or not.
+    /// Only fails if the command could not be run.
+    pub fn output(&self) -> DefaultResult<CommandOutput> {
+        match self.prefix {
+            Some(ref prefix) => println!(
+                "{} > {}",
+                format!("[{}]", prefix).bold(),
+                self.pretty_command()
+            ),
+            None => println!("> {}", self.pretty_command()),
+        }
+
+        let mut command = Command::new(&self.bin);
+        command
+            .args(&self.args)
+            .envs(&self.env)
+            .current_dir(&self.working_dir)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if self.timeout.is_some() {
+            own_process_group(&mut command);
+        }
+
+        let started = Instant::now();
+        let mut child = command.spawn()?;
+        let stdout = stream_lines(child.stdout.take(), self.prefix.clone(), false);
+        let stderr = stream_lines(child.stderr.take(), self.prefix.clone(), true);
+
+        let (status, timed_out) = match self.timeout {
+            None => (child.wait()?, false),
+            Some(timeout) => loop {
+                if let Some(status) = child.try_wait()? {
+                    break (status, false);
+                }
+                if started.elapsed() >= timeout {
+                    kill_process_group(&mut child)?;
+                    break (child.wait()?, true);
+                }
+                thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+            },
+        };
+
+        Ok(CommandOutput {
+            code: if timed_out { None } else { status.code() },
+            timed_out,
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+            // the output is only complete once both streams are closed
+            duration: started.elapsed(),
+        })
+    }
+
+    /// Runs the command to its end and returns its output.
+    /// Fails with CommandFailed, which carries the exit code, if it was not successful,
+    /// and with CommandTimedOut if it got killed for running too long.
+    pub fn run(&self) -> DefaultResult<CommandOutput> {
+        let output = self.output()?;
+
+        if output.timed_out {
+            return Err(CommandTimedOut {
+                command: self.pretty_command(),
+                timeout: self.timeout.unwrap_or_default(),
+            }
+            .into());
+        }
+        if !output.success() {
+            return Err(CommandFailed {
+                command: self.pretty_command(),
+                // killed by a signal
+                code: output.code.unwrap_or(1),
+            }
+            .into());
+        }
+
+        Ok(output)
+    }
+}
+
+/// Prints the lines of an output stream of a command as they come, and returns all of them
+/// once the stream is closed
+fn stream_lines<R: Read + Send + 'static>(
+    stream: Option<R>,
+    prefix: Option<String>,
+    is_stderr: bool,
+) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut captured = String::new();
+        let mut reader = match stream {
+            Some(stream) => BufReader::new(stream),
+            None => return captured,
+        };
+        let mut buffer = Vec::new();
+        loop {
+            buffer.clear();
+            match reader.read_until(b'\n', &mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => (),
+            }
+            let line = String::from_utf8_lossy(&buffer);
+            let line = line.trim_end_matches(|c| c == '\n' || c == '\r');
+            let printed = match prefix {
+                Some(ref prefix) => format!("{} {}", format!("[{}]", prefix).bold(), line),
+                None => line.to_string(),
+            };
+            if is_stderr {
+                eprintln!("{}", printed);
+            } else {
+                println!("{}", printed);
+            }
+            captured.push_str(line);
+            captured.push('\n');
+        }
+        captured
+    })
+}
+
+#[cfg(unix)]
+fn own_process_group(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    unsafe {
+        command.before_exec(|| {
+            if libc::setpgid(0, 0) == 0 {
+                Ok(())
+            } else {
+                Err(io::Error::last_os_error())
+            }
+        });
+    }
+}
 
-    let status = Command::new(bin)
-        .args(args)
-        .current_dir(base_path)
-        .status()?;
+#[cfg(not(unix))]
+fn own_process_group(_command: &mut Command) {}
 
-    ensure!(
-        status.success(),
-        "command {} was not successful",
-        pretty_command
-    );
+/// Kills the command and every process in its process group
+#[cfg(unix)]
+fn kill_process_group(child: &mut Child) -> io::Result<()> {
+    // the process group has the id of the command, which leads it
+    if unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) } == 0 {
+        Ok(())
+    } else {
+        child.kill()
+    }
+}
 
-    Ok(())
+/// Only kills the command itself, processes it started keep running
+#[cfg(not(unix))]
+fn kill_process_group(child: &mut Child) -> io::Result<()> {
+    child.kill()
 }
 
 /// Helper method for obtaining the file name of a path as a String
@@ -31,3 +243,72 @@ pub fn file_name_string(path: &PathBuf) -> DefaultResult<String> {
 
     Ok(file_name.into())
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::error::HolochainError;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn sh(script: &str) -> CommandRunner {
+        CommandRunner::new("sh", vec!["-c".to_string(), script.to_string()])
+    }
+
+    #[test]
+    fn run_captures_output_of_successful_commands() {
+        let output = sh("echo out; echo err >&2")
+            .with_prefix("step")
+            .run()
+            .unwrap();
+
+        assert_eq!(output.code, Some(0));
+        assert!(!output.timed_out);
+        assert_eq!(output.stdout, "out\n");
+        assert_eq!(output.stderr, "err\n");
+    }
+
+    #[test]
+    fn run_sets_env_and_working_dir() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("marker"), "in dir").unwrap();
+
+        let output = sh("echo $HC_TEST_VALUE; cat marker")
+            .with_env("HC_TEST_VALUE", "from env")
+            .with_working_dir(dir.path())
+            .run()
+            .unwrap();
+
+        assert_eq!(output.stdout, "from env\nin dir\n");
+    }
+
+    #[test]
+    fn run_fails_with_exit_code_of_failed_commands() {
+        let output = sh("exit 3").output().unwrap();
+        assert_eq!(output.code, Some(3));
+        assert!(!output.success());
+
+        let error = sh("exit 3").run().unwrap_err();
+        assert_eq!(error.downcast_ref::<CommandFailed>().unwrap().code, 3);
+        assert_eq!(HolochainError::Default(error).exit_code(), 3);
+    }
+
+    #[test]
+    fn run_kills_commands_that_time_out_with_their_children() {
+        // the background sleep keeps the output open unless it gets killed as well
+        let output = sh("sleep 10 & sleep 10")
+            .with_timeout(Duration::from_millis(200))
+            .output()
+            .unwrap();
+
+        assert!(output.timed_out);
+        assert_eq!(output.code, None);
+        assert!(output.duration < Duration::from_secs(5));
+
+        let error = sh("sleep 10")
+            .with_timeout(Duration::from_millis(200))
+            .run()
+            .unwrap_err();
+        assert!(error.downcast_ref::<CommandTimedOut>().is_some());
+    }
+}
@@ -14,17 +14,36 @@
 /// If called without arguments, this executable tries to load a configuration from
 /// ~/.holochain/container_config.toml.
 /// A custom config can be provided with the --config, -c flag.
+/// `check` only checks the configuration, `check --print-effective` also prints it with the
+/// defaults applied to every instance and the content of the files it includes merged in,
+/// and the hash of the DNA each instance runs, with its properties override applied.
+/// `replay --log <file> --storage <dir>` rebuilds the state of an instance from the actions
+/// it recorded (see `record_actions`) and prints it, `--until <index>` stops at that action.
+/// On SIGHUP, the running container loads the TLS certificates of its interfaces anew from
+/// the files the configuration names, e.g. after they got renewed.
 extern crate clap;
 extern crate holochain_container_api;
 extern crate holochain_core_types;
+#[cfg(unix)]
+extern crate signal_hook;
 extern crate structopt;
+extern crate tempfile;
 
 use holochain_container_api::{
-    config::{load_configuration, Configuration},
-    container::Container,
+    config::{load_configuration_from_file, serialize_configuration, Configuration},
+    container::{replay_instance, Container},
 };
 use holochain_core_types::error::HolochainError;
-use std::{convert::TryFrom, fs::File, io::prelude::*, path::PathBuf};
+use std::{
+    convert::TryFrom,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -33,16 +52,63 @@ struct Opt {
     /// Output file
     #[structopt(short = "c", long = "config", parse(from_os_str))]
     config: Option<PathBuf>,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// Checks the configuration without starting anything
+    #[structopt(name = "check")]
+    Check {
+        /// Prints the configuration with the defaults applied to every instance
+        /// and the content of included files merged in, and the DNA hash of every instance
+        #[structopt(long = "print-effective")]
+        print_effective: bool,
+    },
+    /// Replays the actions an instance recorded and prints the resulting state
+    #[structopt(name = "replay")]
+    Replay {
+        /// Action log the instance recorded
+        #[structopt(long = "log", parse(from_os_str))]
+        log: PathBuf,
+        /// The instance's storage as it was before the recording started.
+        /// It does not get changed, replay works on a copy.
+        #[structopt(long = "storage", parse(from_os_str))]
+        storage: PathBuf,
+        /// Stops after the action with this index
+        #[structopt(long = "until")]
+        until: Option<usize>,
+    },
 }
 
 #[cfg_attr(tarpaulin, skip)]
 fn main() {
     let opt = Opt::from_args();
+    if let Some(Command::Replay {
+        log,
+        storage,
+        until,
+    }) = opt.command
+    {
+        match replay(&log, &storage, until) {
+            Ok(dump) => println!("{}", dump),
+            Err(error) => println!("Error while replaying: {}", error),
+        }
+        return;
+    }
     let config_path = opt
         .config
         .unwrap_or(PathBuf::from(r"~/.holochain/container_config.toml"));
     let config_path_str = config_path.to_str().unwrap();
     println!("Using config path: {}", config_path_str);
+    if let Some(Command::Check { print_effective }) = opt.command {
+        match check_config(config_path_str, print_effective) {
+            Ok(()) => println!("Configuration is consistent."),
+            Err(error) => println!("Error in configuration: {}", error),
+        }
+        return;
+    }
     match bootstrap_from_config(config_path_str) {
         Ok(mut container) => {
             if container.instances.len() > 0 {
@@ -57,7 +123,7 @@ fn main() {
                 println!("Starting interfaces...");
                 container.start_all_interfaces();
                 println!("Done.");
-                loop {}
+                run_until_killed(&container, config_path_str);
             } else {
                 println!("No instance started, bailing...");
             }
@@ -66,19 +132,85 @@ fn main() {
     };
 }
 
+/// Keeps the container running and reloads the TLS certificates of its interfaces on SIGHUP
+#[cfg_attr(tarpaulin, skip)]
+fn run_until_killed(container: &Container, config_path: &str) {
+    let hangup = Arc::new(AtomicBool::new(false));
+    #[cfg(unix)]
+    {
+        if let Err(error) = signal_hook::flag::register(signal_hook::SIGHUP, hangup.clone()) {
+            println!(
+                "Could not listen for SIGHUP, TLS certificates won't get reloaded: {}",
+                error
+            );
+        }
+    }
+    loop {
+        thread::sleep(Duration::from_millis(500));
+        if hangup.swap(false, Ordering::SeqCst) {
+            let reloaded = load_config_file(&String::from(config_path)).and_then(|config| {
+                container
+                    .reload_tls_certificates(&config)
+                    .map_err(|string| HolochainError::ConfigError(string))
+            });
+            match reloaded {
+                Ok(()) => println!("Reloaded the TLS certificates."),
+                Err(error) => println!("Error while reloading the TLS certificates: {}", error),
+            }
+        }
+    }
+}
+
 #[cfg_attr(tarpaulin, skip)]
 fn bootstrap_from_config(path: &str) -> Result<Container, HolochainError> {
     let config = load_config_file(&String::from(path))?;
     config
         .check_consistency()
         .map_err(|string| HolochainError::ConfigError(string))?;
-    Container::try_from(&config)
+    let mut container = Container::try_from(&config)?;
+    // archiving instances through an admin interface gets saved to the config file
+    container.set_config_path(PathBuf::from(path));
+    Ok(container)
+}
+
+#[cfg_attr(tarpaulin, skip)]
+fn check_config(path: &str, print_effective: bool) -> Result<(), HolochainError> {
+    let config = load_config_file(&String::from(path))?;
+    config
+        .check_consistency()
+        .map_err(|string| HolochainError::ConfigError(string))?;
+    for (dna_id, version) in config.dna_manifest_versions() {
+        match version {
+            Ok(version) => println!("DNA \"{}\": manifest version {}", dna_id, version),
+            Err(error) => println!("DNA \"{}\" can't be read: {}", dna_id, error),
+        }
+    }
+    if print_effective {
+        println!("{}", serialize_configuration(&config.effective())?);
+        for (instance_id, hash) in config.effective_dna_hashes() {
+            match hash {
+                Ok(hash) => println!("DNA hash of instance \"{}\": {}", instance_id, hash),
+                Err(error) => println!(
+                    "DNA hash of instance \"{}\" unknown: {}",
+                    instance_id, error
+                ),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg_attr(tarpaulin, skip)]
+fn replay(
+    log: &PathBuf,
+    storage: &PathBuf,
+    until: Option<usize>,
+) -> Result<String, HolochainError> {
+    let work_dir = tempfile::tempdir()?;
+    replay_instance(log, storage, &work_dir.path().join("storage"), until)
 }
 
 #[cfg_attr(tarpaulin, skip)]
 fn load_config_file(path: &String) -> Result<Configuration, HolochainError> {
-    let mut f = File::open(path)?;
-    let mut contents = String::new();
-    f.read_to_string(&mut contents)?;
-    Ok(load_configuration::<Configuration>(&contents)?)
+    load_configuration_from_file(path)
 }
@@ -10,14 +10,27 @@
 ///   the container
 /// * bridges, which are
 use boolinator::*;
+use config_errors;
+use holochain_core::runtime::DEFAULT_RUNTIME_THREADS;
 use holochain_core_types::{
     agent::AgentId,
     dna::Dna,
+    entry::addressing,
     error::{HcResult, HolochainError},
+    hash::HashString,
     json::JsonString,
 };
-use serde::Deserialize;
-use std::{convert::TryFrom, fs::File, io::prelude::*};
+use serde::de::DeserializeOwned;
+use serde_json;
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fs::{self, File},
+    io::prelude::*,
+    iter, mem,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use toml;
 
 /// Main container configuration struct
@@ -28,10 +41,19 @@ use toml;
 /// the agent and DNA to be instantiated) are implemented
 /// via string IDs.
 #[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
 pub struct Configuration {
-    /// List of Agents, this mainly means identities and their keys. Required.
+    /// Other files with agents, DNAs, instances, interfaces and bridges of this configuration,
+    /// see `load_configuration_from_file()`. Optional.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// List of Agents, this mainly means identities and their keys.
+    /// Required, but they can come from included files.
+    #[serde(default)]
     pub agents: Vec<AgentConfiguration>,
-    /// List of DNAs, for each a path to the DNA file. Required.
+    /// List of DNAs, for each a path to the DNA file.
+    /// Required, but they can come from included files.
+    #[serde(default)]
     pub dnas: Vec<DNAConfiguration>,
     /// List of instances, includes references to an agent and a DNA. Required.
     #[serde(default)]
@@ -42,40 +64,393 @@ pub struct Configuration {
     /// List of bridges between instances. Optional.
     #[serde(default)]
     pub bridges: Vec<Bridge>,
+    /// Settings of the thread pool shared by all instances. Optional.
+    #[serde(default)]
+    pub runtime: RuntimeConfiguration,
+    /// Settings inherited by all instances that don't set them themselves. Optional.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub defaults: Option<DefaultsConfiguration>,
+    /// Whether the interfaces offer the methods that support tests, like `test/consistency`.
+    /// Only for containers that run tests, e.g. the one of `hc test`. Optional.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub test_mode: bool,
+    /// The included files that agents, DNAs, instances, interfaces and bridges came from,
+    /// by `item_key()`. Empty for items of the root file.
+    #[serde(skip)]
+    pub included_from: HashMap<String, PathBuf>,
+    /// The lines of the tables that define agents, DNAs, instances, interfaces and bridges,
+    /// by `item_key()`, in the file `included_from` names or else the root file.
+    /// Empty for configurations that did not get loaded from a file.
+    #[serde(skip)]
+    pub item_lines: HashMap<String, usize>,
+    /// The agents that run with the keys of `holochain_core::test_fixtures::test_agent()`,
+    /// by id, see `with_test_agent()`. Never read from or written to files.
+    #[serde(skip)]
+    pub test_agents: Vec<String>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 impl Configuration {
     /// This function basically checks if self is a semantically valid configuration.
     /// This mainly means checking for consistency between config structs that reference others.
+    /// Instances are checked with the defaults applied, see `effective()`.
+    /// Each instance needs storage of its own, so that the chains of instances running the
+    /// same DNA stay apart.
+    /// Errors about an item point at its table, if the configuration got loaded from a file.
     pub fn check_consistency(&self) -> Result<(), String> {
-        for ref instance in self.instances.iter() {
+        let mut storage_paths = HashMap::new();
+        for ref instance in self.effective().instances.iter() {
+            let location = self.location(&item_key("instance", &instance.id));
             self.agent_by_id(&instance.agent).is_some().ok_or_else(|| {
                 format!(
-                    "Agent configuration {} not found, mentioned in instance {}",
-                    instance.agent, instance.id
+                    "Agent configuration {} not found, mentioned in instance {}{}",
+                    instance.agent, instance.id, location
                 )
             })?;
             self.dna_by_id(&instance.dna).is_some().ok_or_else(|| {
                 format!(
-                    "DNA configuration \"{}\" not found, mentioned in instance \"{}\"",
-                    instance.dna, instance.id
+                    "DNA configuration \"{}\" not found, mentioned in instance \"{}\"{}",
+                    instance.dna, instance.id, location
+                )
+            })?;
+            instance.storage.is_some().ok_or_else(|| {
+                format!(
+                    "No storage configured for instance \"{}\", neither in the instance nor in the defaults{}",
+                    instance.id, location
                 )
             })?;
+            if let Some(StorageConfiguration::File { ref path }) = instance.storage {
+                if let Some(other) = storage_paths.insert(path.clone(), instance.id.clone()) {
+                    return Err(format!(
+                        "Instances \"{}\" and \"{}\" share the storage path \"{}\"{}",
+                        other, instance.id, path, location
+                    ));
+                }
+            }
         }
         for ref interface in self.interfaces.iter() {
             for ref instance in interface.instances.iter() {
                 self.instance_by_id(&instance.id).is_some().ok_or_else(|| {
                     format!(
-                        "Instance configuration \"{}\" not found, mentioned in interface",
-                        instance.id
+                        "Instance configuration \"{}\" not found, mentioned in interface{}",
+                        instance.id,
+                        self.location(&item_key("interface", &interface.id))
+                    )
+                })?;
+            }
+            if let Some(tls) = interface.driver.tls() {
+                for (what, path) in &[("certificate", &tls.cert_file), ("key", &tls.key_file)] {
+                    File::open(path).map_err(|error| {
+                        format!(
+                            "TLS {} file \"{}\" of interface \"{}\" can't be read: {}{}",
+                            what,
+                            path,
+                            interface.id,
+                            error,
+                            self.location(&item_key("interface", &interface.id))
+                        )
+                    })?;
+                }
+            }
+        }
+        for ref bridge in self.bridges.iter() {
+            for id in &[&bridge.caller_id, &bridge.callee_id] {
+                self.instance_by_id(id).is_some().ok_or_else(|| {
+                    format!(
+                        "Instance configuration \"{}\" not found, mentioned in bridge{}",
+                        id,
+                        self.location(&bridge_key(bridge))
+                    )
+                })?;
+            }
+        }
+        self.check_bridge_declarations()?;
+        if !self.test_agents.is_empty() && !cfg!(feature = "test_fixtures") {
+            return Err(String::from(
+                "Test agents have keys everyone can derive, they only run in containers built with the test_fixtures feature",
+            ));
+        }
+        for agent_id in self.test_agents.iter() {
+            self.agent_by_id(agent_id).is_some().ok_or_else(|| {
+                format!(
+                    "Agent configuration {} not found, mentioned as test agent",
+                    agent_id
+                )
+            })?;
+        }
+        for ref instance in self.instances.iter() {
+            for dependency in instance.depends_on.iter() {
+                self.instance_by_id(dependency).is_some().ok_or_else(|| {
+                    format!(
+                        "Instance configuration \"{}\" not found, instance \"{}\" depends on it{}",
+                        dependency,
+                        instance.id,
+                        self.location(&item_key("instance", &instance.id))
                     )
                 })?;
             }
         }
+        let _ = self.startup_order()?;
+        for warning in self
+            .interface_filter_warnings()
+            .into_iter()
+            .chain(self.properties_override_warnings())
+        {
+            println!("Warning: {}", warning);
+        }
+
+        Ok(())
+    }
 
+    /// Checks that the bridges of every instance satisfy the ones its DNA declares:
+    /// required ones have to be configured, and configured ones have to lead to an instance
+    /// of the declared DNA and with the declared functions.
+    /// DNA files that can't be loaded are skipped here, instantiating them fails anyway.
+    fn check_bridge_declarations(&self) -> Result<(), String> {
+        for instance in self.instances.iter() {
+            let dna = match self.instance_dna(instance) {
+                Some(dna) => dna,
+                None => continue,
+            };
+            for declaration in dna.bridges.iter() {
+                let bridge = match self.bridges.iter().find(|bridge| {
+                    bridge.caller_id == instance.id
+                        && bridge.handle.as_ref() == Some(&declaration.handle)
+                }) {
+                    Some(bridge) => bridge,
+                    None if declaration.is_required() => {
+                        return Err(format!(
+                            "Instance \"{}\" requires a bridge with handle \"{}\", but none is configured{}",
+                            instance.id,
+                            declaration.handle,
+                            self.location(&item_key("instance", &instance.id))
+                        ));
+                    }
+                    None => continue,
+                };
+                let callee_dna = match self
+                    .instance_by_id(&bridge.callee_id)
+                    .and_then(|callee| self.instance_dna(&callee))
+                {
+                    Some(callee_dna) => callee_dna,
+                    None => continue,
+                };
+                if let Some(ref dna_hash) = declaration.dna_hash {
+                    let callee_dna_hash = addressing::dna_hash(&callee_dna).to_string();
+                    if callee_dna_hash != *dna_hash {
+                        return Err(format!(
+                            "Bridge \"{}\" of instance \"{}\" has to lead to an instance of DNA {}, but instance \"{}\" runs {}{}",
+                            declaration.handle,
+                            instance.id,
+                            dna_hash,
+                            bridge.callee_id,
+                            callee_dna_hash,
+                            self.location(&bridge_key(bridge))
+                        ));
+                    }
+                }
+                for function in declaration.functions.iter() {
+                    let has_function = callee_dna
+                        .get_zome(&function.zome)
+                        .map(|zome| {
+                            zome.capabilities.values().any(|capability| {
+                                capability
+                                    .functions
+                                    .iter()
+                                    .any(|fn_declaration| fn_declaration.name == function.function)
+                            })
+                        })
+                        .unwrap_or(false);
+                    if !has_function {
+                        return Err(format!(
+                            "Bridge \"{}\" of instance \"{}\" has to lead to an instance with function {}/{}, which instance \"{}\" does not have{}",
+                            declaration.handle,
+                            instance.id,
+                            function.zome,
+                            function.function,
+                            bridge.callee_id,
+                            self.location(&bridge_key(bridge))
+                        ));
+                    }
+                }
+            }
+        }
         Ok(())
     }
 
+    /// The DNA the given instance runs, with its properties override applied,
+    /// None if its DNA file can't be loaded
+    fn instance_dna(&self, instance: &InstanceConfiguration) -> Option<Dna> {
+        self.dna_by_id(&instance.dna)
+            .and_then(|dna_config| Dna::try_from(dna_config).ok())
+            .map(|dna| instance.effective_dna(dna))
+    }
+
+    /// Where the item with the given `item_key()` is defined, to point at it in errors
+    fn location(&self, key: &str) -> String {
+        match (self.item_lines.get(key), self.included_from.get(key)) {
+            (Some(line), Some(file)) => format!(" (line {} of {})", line, file.display()),
+            (Some(line), None) => format!(" (line {})", line),
+            (None, Some(file)) => format!(" (in {})", file.display()),
+            (None, None) => String::new(),
+        }
+    }
+
+    /// Returns a warning for every zome or function in an interface's allow-lists
+    /// that does not exist in the DNA of the referenced instance.
+    /// DNA files that can't be loaded are skipped here, instantiating them fails anyway.
+    pub fn interface_filter_warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for ref interface in self.interfaces.iter() {
+            for ref instance_ref in interface.instances.iter() {
+                if instance_ref.zomes.is_none() && instance_ref.functions.is_none() {
+                    continue;
+                }
+                let maybe_dna = self
+                    .instance_by_id(&instance_ref.id)
+                    .and_then(|instance| self.dna_by_id(&instance.dna))
+                    .and_then(|dna_config| Dna::try_from(dna_config).ok());
+                if let Some(dna) = maybe_dna {
+                    warnings.extend(
+                        instance_ref
+                            .unknown_filter_entries(&dna)
+                            .into_iter()
+                            .map(|entry| {
+                                format!(
+                                    "{} of instance \"{}\" exposed in interface \"{}\" does not exist",
+                                    entry, instance_ref.id, interface.id
+                                )
+                            }),
+                    );
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Returns a warning for every instance that overrides the properties of its DNA
+    /// differently than an earlier instance of the same DNA, as they run different DNAs
+    /// with different hashes, and so end up in different networks.
+    pub fn properties_override_warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let mut first_by_dna: HashMap<String, &InstanceConfiguration> = HashMap::new();
+        for instance in self.instances.iter() {
+            let first = first_by_dna.entry(instance.dna.clone()).or_insert(instance);
+            let own = instance.properties_override.clone().unwrap_or_default();
+            if first.properties_override.clone().unwrap_or_default() != own {
+                warnings.push(format!(
+                    "Instances \"{}\" and \"{}\" override the properties of DNA \"{}\" differently, so they run DNAs with different hashes that don't share a network",
+                    first.id, instance.id, instance.dna
+                ));
+            }
+        }
+        warnings
+    }
+
+    /// Returns the hash of the DNA every instance runs, its DNA file with its properties
+    /// override applied, or why it could not be computed.
+    pub fn effective_dna_hashes(&self) -> Vec<(String, HcResult<HashString>)> {
+        self.instances
+            .iter()
+            .map(|instance| {
+                let hash = self
+                    .dna_by_id(&instance.dna)
+                    .ok_or_else(|| {
+                        HolochainError::ConfigError(format!(
+                            "DNA configuration \"{}\" not found",
+                            instance.dna
+                        ))
+                    })
+                    .and_then(Dna::try_from)
+                    .map(|dna| addressing::dna_hash(&instance.effective_dna(dna)));
+                (instance.id.clone(), hash)
+            })
+            .collect()
+    }
+
+    /// Returns the manifest version of every DNA file, or why it could not be read,
+    /// which for a DNA newer than this build can read says so.
+    pub fn dna_manifest_versions(&self) -> Vec<(String, HcResult<u32>)> {
+        self.dnas
+            .iter()
+            .map(|dna_config| {
+                let version = Dna::try_from(dna_config.clone()).map(|dna| dna.manifest_version);
+                (dna_config.id.clone(), version)
+            })
+            .collect()
+    }
+
+    /// Runs the agent with the given id with the keys of `test_agent(id)`, so that its
+    /// address is the same in every run. Everyone can derive these keys from the id,
+    /// so the configuration is only consistent in containers built with the
+    /// `test_fixtures` feature, which is meant for tests and demos.
+    pub fn with_test_agent(mut self, agent_id: &str) -> Self {
+        self.test_agents.push(agent_id.to_string());
+        self
+    }
+
+    /// Returns this configuration with the defaults applied to every instance,
+    /// which is what the container actually runs.
+    /// The configuration itself stays sparse, so serializing it does not expand the defaults.
+    /// Included content is already in it, so it does not include anything anymore.
+    pub fn effective(&self) -> Configuration {
+        let defaults = self.defaults.clone().unwrap_or_default();
+        Configuration {
+            include: Vec::new(),
+            instances: self
+                .instances
+                .iter()
+                .map(|instance| instance.with_defaults(&defaults))
+                .collect(),
+            defaults: None,
+            included_from: HashMap::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns this configuration without what came from included files,
+    /// which is what gets saved back to the root file
+    pub fn without_included(&self) -> Configuration {
+        let is_own = |key: String| !self.included_from.contains_key(&key);
+        Configuration {
+            agents: self
+                .agents
+                .iter()
+                .filter(|agent| is_own(item_key("agent", &agent.id)))
+                .cloned()
+                .collect(),
+            dnas: self
+                .dnas
+                .iter()
+                .filter(|dna| is_own(item_key("DNA", &dna.id)))
+                .cloned()
+                .collect(),
+            instances: self
+                .instances
+                .iter()
+                .filter(|instance| is_own(item_key("instance", &instance.id)))
+                .cloned()
+                .collect(),
+            interfaces: self
+                .interfaces
+                .iter()
+                .filter(|interface| is_own(item_key("interface", &interface.id)))
+                .cloned()
+                .collect(),
+            bridges: self
+                .bridges
+                .iter()
+                .filter(|bridge| is_own(bridge_key(bridge)))
+                .cloned()
+                .collect(),
+            included_from: HashMap::new(),
+            ..self.clone()
+        }
+    }
+
     /// Returns the agent configuration with the given ID if present
     pub fn agent_by_id(&self, id: &str) -> Option<AgentConfiguration> {
         self.agents.iter().find(|ac| &ac.id == id).cloned()
@@ -96,6 +471,70 @@ impl Configuration {
         self.interfaces.iter().find(|ic| &ic.id == id).cloned()
     }
 
+    /// Returns the IDs of the instances the given instance needs to be initialized before it:
+    /// the callees of its bridges and the ones it explicitly depends on.
+    pub fn instance_dependencies(&self, id: &str) -> Vec<String> {
+        let callees = self
+            .bridges
+            .iter()
+            .filter(|bridge| bridge.caller_id == id)
+            .map(|bridge| bridge.callee_id.clone());
+        let explicit = self
+            .instance_by_id(id)
+            .map(|instance| instance.depends_on)
+            .unwrap_or_default();
+        let mut dependencies = Vec::new();
+        for dependency in callees.chain(explicit.into_iter()) {
+            if !dependencies.contains(&dependency) {
+                dependencies.push(dependency);
+            }
+        }
+        dependencies
+    }
+
+    /// Returns all instance IDs in the order the container starts them:
+    /// every instance comes after its dependencies, otherwise the configured order is kept.
+    /// Fails if instances depend on each other in a cycle.
+    pub fn startup_order(&self) -> Result<Vec<String>, String> {
+        fn visit(
+            config: &Configuration,
+            id: &str,
+            path: &mut Vec<String>,
+            visited: &mut HashSet<String>,
+            order: &mut Vec<String>,
+        ) -> Result<(), String> {
+            if visited.contains(id) {
+                return Ok(());
+            }
+            if let Some(position) = path.iter().position(|on_path| on_path == id) {
+                let mut cycle = path[position..].to_vec();
+                cycle.push(id.to_string());
+                return Err(format!(
+                    "Instances depend on each other in a cycle: {}",
+                    cycle.join(" -> ")
+                ));
+            }
+            path.push(id.to_string());
+            for dependency in config.instance_dependencies(id) {
+                visit(config, &dependency, path, visited, order)?;
+            }
+            path.pop();
+            visited.insert(id.to_string());
+            // check_consistency() reports dependencies on unknown instances
+            if config.instance_by_id(id).is_some() {
+                order.push(id.to_string());
+            }
+            Ok(())
+        }
+
+        let mut visited = HashSet::new();
+        let mut order = Vec::new();
+        for id in self.instance_ids() {
+            visit(self, &id, &mut Vec::new(), &mut visited, &mut order)?;
+        }
+        Ok(order)
+    }
+
     /// Returns all defined instance IDs
     pub fn instance_ids(&self) -> Vec<String> {
         self.instances
@@ -107,8 +546,12 @@ impl Configuration {
 
 /// An agent has a name/ID and is defined by a private key that resides in a file
 #[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct AgentConfiguration {
     pub id: String,
+    /// A name for humans, unused by the container
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     pub key_file: String,
 }
 
@@ -122,6 +565,7 @@ impl From<AgentConfiguration> for AgentId {
 /// A DNA is represented by a DNA file.
 /// A hash has to be provided for sanity check.
 #[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct DNAConfiguration {
     pub id: String,
     pub file: String,
@@ -140,26 +584,265 @@ impl TryFrom<DNAConfiguration> for Dna {
 
 /// An instance combines a DNA with an agent.
 /// Each instance has its own network, storage and logger configuration.
+/// Whatever an instance leaves out gets inherited from the container's defaults.
 #[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct InstanceConfiguration {
     pub id: String,
     pub dna: String,
     pub agent: String,
-    pub logger: LoggerConfiguration,
-    pub storage: StorageConfiguration,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logger: Option<LoggerConfiguration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<StorageConfiguration>,
+    pub network: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeouts: Option<TimeoutConfiguration>,
+    /// Archived instances keep their storage but don't get started
+    #[serde(default)]
+    pub archived: bool,
+    /// Path of a file to record every action of the instance to, for replaying them later.
+    /// Each time the instance gets loaded the recording starts over.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_actions: Option<String>,
+    /// Instances that have to be initialized before this one, besides the callees of its bridges
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// Seconds between garbage collections of the instance's storage while it runs,
+    /// which remove rejected entries and other content nothing refers to anymore.
+    /// Unset means garbage only gets collected through the admin interface.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gc_interval: Option<u64>,
+    /// Properties that the instance sets in its DNA, replacing the ones of the same name in
+    /// the DNA file. They are what hdk::property and validation see. Overrides change the
+    /// hash of the DNA, so the instance joins the network of its variant of the DNA instead
+    /// of the DNA file's, on purpose. See `effective_dna()`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub properties_override: Option<PropertiesOverride>,
+    /// How much WASM memory each zome call of the instance may use, in MiB. Zomes that
+    /// try to grow their memory beyond it fail with OutOfMemory. Defaults to 64.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_wasm_memory_mb: Option<u32>,
+    /// How many of its latest signals, i.e. the notifications about its events, the
+    /// container keeps for clients that subscribe with `since_seq` after missing them.
+    /// None are kept by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal_buffer_size: Option<usize>,
+    /// Whether every zome call of the instance records the host functions it invokes,
+    /// with the sizes of their arguments and results but not their content, for security
+    /// reviews. Otherwise only calls made with `"debug": true` get audited.
+    #[serde(default)]
+    pub audit_host_calls: bool,
+    /// What the instance hands to the genesis callbacks of its zomes, e.g. the address of
+    /// a progenitor agent or an invite code. Zomes read it with `hdk::genesis_params()`,
+    /// during genesis and in later calls. Unlike a properties override it is not part of
+    /// the DNA and does not change its hash, so instances with different genesis parameters
+    /// share a network. That also means other nodes validating the entries of the instance
+    /// don't know them: validation must not depend on them.
+    /// Genesis gets an empty object if it is unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub genesis_params: Option<serde_json::Value>,
+    /// Whether the container rebuilds the instance from its storage when its action loop
+    /// crashes, see `RestartPolicy`. Crashed instances stay down by default.
+    #[serde(default, skip_serializing_if = "RestartPolicy::is_never")]
+    pub restart: RestartPolicy,
+}
+
+/// Properties of a DNA by their name, see `InstanceConfiguration::properties_override`
+pub type PropertiesOverride = serde_json::Map<String, serde_json::Value>;
+
+impl InstanceConfiguration {
+    /// Returns this instance's configuration with everything it does not set itself
+    /// taken from the given defaults. Values set by the instance always win.
+    /// A default file storage is a root path, each instance gets the directory
+    /// named after its id in there.
+    pub fn with_defaults(&self, defaults: &DefaultsConfiguration) -> InstanceConfiguration {
+        let storage = self.storage.clone().or_else(|| {
+            defaults.storage.clone().map(|storage| match storage {
+                StorageConfiguration::File { path } => StorageConfiguration::File {
+                    path: format!("{}/{}", path, self.id),
+                },
+                StorageConfiguration::Memory => StorageConfiguration::Memory,
+            })
+        });
+        let timeouts = match (&self.timeouts, &defaults.timeouts) {
+            (Some(timeouts), Some(default_timeouts)) => {
+                Some(timeouts.with_defaults(default_timeouts))
+            }
+            (timeouts, default_timeouts) => timeouts.clone().or_else(|| default_timeouts.clone()),
+        };
+        InstanceConfiguration {
+            logger: self.logger.clone().or_else(|| defaults.logger.clone()),
+            storage,
+            network: self.network.clone().or_else(|| defaults.network.clone()),
+            timeouts,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the given DNA with this instance's properties override applied,
+    /// which is the DNA the instance runs. DNAs without properties get them.
+    pub fn effective_dna(&self, dna: Dna) -> Dna {
+        let mut dna = dna;
+        if let Some(ref properties_override) = self.properties_override {
+            if !dna.properties.is_object() {
+                dna.properties = serde_json::Value::Object(PropertiesOverride::new());
+            }
+            let properties = dna.properties.as_object_mut().unwrap();
+            for (name, value) in properties_override.iter() {
+                properties.insert(name.clone(), value.clone());
+            }
+        }
+        dna
+    }
+}
+
+/// Settings shared by all instances of a container.
+/// Every instance inherits the ones it does not set itself.
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct DefaultsConfiguration {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logger: Option<LoggerConfiguration>,
+    /// For file storage, `path` is the root under which every instance
+    /// gets a directory named after its id.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<StorageConfiguration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub network: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeouts: Option<TimeoutConfiguration>,
+}
+
+/// Timeouts of an instance, in seconds unless their name says otherwise.
+/// Unset ones fall back to the defaults of the container and then to those of core.
+#[derive(Deserialize, Serialize, Clone, Default, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TimeoutConfiguration {
+    /// How long validations wait for the entries they depend on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validation_dependencies: Option<u64>,
+    /// How long a zome can sleep at once with `hdk::sleep()`, at most 2 seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_sleep: Option<u64>,
+    /// How long a zome call can sleep in total, at most 5 seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_total_sleep: Option<u64>,
+    /// How long a zome call can take before it gets logged as slow
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slow_call_threshold_ms: Option<u64>,
+}
+
+impl TimeoutConfiguration {
+    fn with_defaults(&self, defaults: &TimeoutConfiguration) -> TimeoutConfiguration {
+        TimeoutConfiguration {
+            validation_dependencies: self
+                .validation_dependencies
+                .or(defaults.validation_dependencies),
+            max_sleep: self.max_sleep.or(defaults.max_sleep),
+            max_total_sleep: self.max_total_sleep.or(defaults.max_total_sleep),
+            slow_call_threshold_ms: self
+                .slow_call_threshold_ms
+                .or(defaults.slow_call_threshold_ms),
+        }
+    }
+}
+
+/// What the container's watchdog does when the action loop of an instance panics or stops
+/// sending heartbeats, see `watchdog`. With
+/// `[instances.restart]`, `policy = "on_failure"`, `max_retries = 3` and `backoff_ms = 500`
+/// it rebuilds the instance from its storage at most 3 times, waiting 500ms before the first
+/// attempt and twice as long before each one after it.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum RestartPolicy {
+    /// Crashed instances stay down until the container gets restarted
+    Never,
+    OnFailure {
+        max_retries: u32,
+        #[serde(default = "default_restart_backoff_ms")]
+        backoff_ms: u64,
+    },
+}
+
+/// The longest the watchdog waits before it restarts a crashed instance
+pub const MAX_RESTART_BACKOFF_MS: u64 = 300_000;
+
+fn default_restart_backoff_ms() -> u64 {
+    1000
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy::Never
+    }
+}
+
+impl RestartPolicy {
+    pub fn is_never(&self) -> bool {
+        *self == RestartPolicy::Never
+    }
+
+    /// How long to wait before restarting an instance that got restarted `restarts` times
+    /// already, None once it should stay down
+    pub fn backoff(&self, restarts: u32) -> Option<Duration> {
+        match self {
+            RestartPolicy::Never => None,
+            RestartPolicy::OnFailure {
+                max_retries,
+                backoff_ms,
+            } => {
+                if restarts >= *max_retries {
+                    return None;
+                }
+                let factor = 1u64.checked_shl(restarts).unwrap_or(u64::max_value());
+                Some(Duration::from_millis(
+                    backoff_ms
+                        .saturating_mul(factor)
+                        .min(MAX_RESTART_BACKOFF_MS),
+                ))
+            }
+        }
+    }
 }
 
 /// There might be different kinds of loggers in the future.
-/// Currently there is no logger at all.
+/// Currently only type "debug" is in use, which pretty-prints every action
+/// that gets dispatched inside the instance. Any other type means no logging.
 /// TODO: make this an enum when it's actually in use
-#[derive(Deserialize, Serialize, Clone, Default)]
+#[derive(Deserialize, Serialize, Clone, Default, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct LoggerConfiguration {
     #[serde(rename = "type")]
     pub logger_type: String,
     pub file: Option<String>,
 }
 
+/// All instances of a container share one runtime that executes their
+/// workflows and zome calls, so that the number of threads does not grow
+/// with the number of instances.
+/// `threads` is the number of worker threads kept alive by that runtime. No more tasks
+/// than that run at the same time, the others wait for a free worker. Besides those,
+/// the action loop of each instance runs on a thread of its own.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeConfiguration {
+    #[serde(default = "default_runtime_threads")]
+    pub threads: usize,
+}
+
+impl Default for RuntimeConfiguration {
+    fn default() -> Self {
+        RuntimeConfiguration {
+            threads: default_runtime_threads(),
+        }
+    }
+}
+
+fn default_runtime_threads() -> usize {
+    DEFAULT_RUNTIME_THREADS
+}
+
 /// This configures the Content Addressable Storage (CAS) that
 /// the instance uses to store source chain and DHT shard in.
 /// There are two storage implementations in cas_implementations so far:
@@ -167,7 +850,7 @@ pub struct LoggerConfiguration {
 /// * file
 ///
 /// Projected are various DB adapters.
-#[derive(Deserialize, Serialize, Clone)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 #[serde(tag = "type")]
 pub enum StorageConfiguration {
     #[serde(rename = "memory")]
@@ -187,50 +870,392 @@ pub enum StorageConfiguration {
 /// Every interface lists the instances that are made available here.
 /// An admin flag will enable container functions for programmatically changing the configuration
 /// (i.e. installing apps)
+/// With an `idle_timeout` in minutes, websocket interfaces close the connections of clients
+/// that neither sent a request nor subscribed to events for that long.
+/// With a `request_log`, the interface logs every request it handles, see
+/// `RequestLogConfiguration`.
 #[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct InterfaceConfiguration {
     pub id: String,
     pub driver: InterfaceDriver,
     #[serde(default)]
     pub admin: bool,
     pub instances: Vec<InstanceReferenceConfiguration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_log: Option<RequestLogConfiguration>,
+}
+
+/// The access log of an interface: one line per request, written to the given logger, with
+/// who called which function when, how long the call took, whether it failed and how large
+/// its params and response were. Params and results can be private, so they don't get logged,
+/// except for the params of the functions named in the `log_params_for` allow-list.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RequestLogConfiguration {
+    pub logger: LoggerConfiguration,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub log_params_for: Vec<String>,
 }
 
 #[derive(Deserialize, Serialize, Clone)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum InterfaceDriver {
-    Websocket { port: u16 },
-    Http { port: u16 },
-    DomainSocket { file: String },
+    Websocket {
+        port: u16,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tls: Option<TlsConfiguration>,
+    },
+    Http {
+        port: u16,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tls: Option<TlsConfiguration>,
+    },
+    DomainSocket {
+        file: String,
+    },
     Custom(toml::value::Value),
 }
 
+impl InterfaceDriver {
+    /// The TLS configuration of the websocket and HTTP drivers, if they serve wss or https
+    pub fn tls(&self) -> Option<&TlsConfiguration> {
+        match self {
+            InterfaceDriver::Websocket { tls, .. } | InterfaceDriver::Http { tls, .. } => {
+                tls.as_ref()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The certificate, with the chain leading to it if any, and the private key an interface
+/// serves wss or https with, as PEM files.
+/// They get loaded and checked when the container starts, see `TlsCertificate`, and can be
+/// loaded anew while it runs, e.g. after they got renewed.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfiguration {
+    pub cert_file: String,
+    pub key_file: String,
+}
+
+/// An instance made available through an interface.
+/// The optional `zomes` and `functions` allow-lists restrict which of the instance's
+/// zome functions the interface exposes. Without them, every zome function is exposed.
 #[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct InstanceReferenceConfiguration {
     pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zomes: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub functions: Option<Vec<String>>,
+}
+
+impl InstanceReferenceConfiguration {
+    /// Returns true if the given zome function passes both allow-lists
+    pub fn exposes(&self, zome_name: &str, function_name: &str) -> bool {
+        let allowed = |list: &Option<Vec<String>>, name: &str| {
+            list.as_ref()
+                .map(|names| names.iter().any(|n| n == name))
+                .unwrap_or(true)
+        };
+        allowed(&self.zomes, zome_name) && allowed(&self.functions, function_name)
+    }
+
+    /// Returns the allow-list entries that name a zome or function the given DNA lacks
+    pub fn unknown_filter_entries(&self, dna: &Dna) -> Vec<String> {
+        let zomes = self
+            .zomes
+            .iter()
+            .flatten()
+            .filter(|zome_name| !dna.zomes.contains_key(*zome_name))
+            .map(|zome_name| format!("Zome \"{}\"", zome_name));
+        let functions = self
+            .functions
+            .iter()
+            .flatten()
+            .filter(|function_name| {
+                !dna.zomes.values().any(|zome| {
+                    zome.capabilities
+                        .values()
+                        .any(|cap| cap.functions.iter().any(|f| &f.name == *function_name))
+                })
+            })
+            .map(|function_name| format!("Function \"{}\"", function_name));
+        zomes.chain(functions).collect()
+    }
 }
 
 /// A bridge enables an instance to call zome functions of another instance.
 /// It is basically an internal interface.
 #[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Bridge {
     pub caller_id: String,
     pub callee_id: String,
+    /// The handle the caller's zomes call the callee by, as the caller's DNA declares it,
+    /// see `BridgeDeclaration`. Bridges without one can only be called through the container.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handle: Option<String>,
 }
 
 /// Use this function to load a `Configuration` from a string.
-pub fn load_configuration<'a, T>(toml: &'a str) -> HcResult<T>
-where
-    T: Deserialize<'a>,
-{
-    toml::from_str::<T>(toml).map_err(|e| {
-        HolochainError::IoError(format!("Could not serialize toml: {}", e.to_string()))
+/// Errors name the line of the problem, and the names that misspelled ones probably meant.
+/// Sections of newer containers, see `config_errors::FORWARD_COMPATIBLE_SECTIONS`,
+/// get skipped with a warning, any other unknown section or field is an error.
+pub fn load_configuration<T: DeserializeOwned>(toml: &str) -> HcResult<T> {
+    config_errors::deserialize::<T>(toml).map_err(HolochainError::ConfigError)
+}
+
+/// How deeply included files can include other files. Stops files that include each other.
+pub const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// What an included file can hold: the lists of a `Configuration`, and more includes.
+/// Their items get appended to the lists of the including configuration.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IncludedConfiguration {
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    agents: Vec<AgentConfiguration>,
+    #[serde(default)]
+    dnas: Vec<DNAConfiguration>,
+    #[serde(default)]
+    instances: Vec<InstanceConfiguration>,
+    #[serde(default)]
+    interfaces: Vec<InterfaceConfiguration>,
+    #[serde(default)]
+    bridges: Vec<Bridge>,
+}
+
+/// Loads a `Configuration` from the TOML file at the given path, together with the files
+/// it includes. `include = ["agents.toml", "instances/*.toml"]` includes files relative to
+/// the including file, a `*` in a file name matches any sequence of characters.
+/// Included files can hold agents, DNAs, instances, interfaces, bridges and more includes,
+/// which get appended to the lists of the including configuration.
+/// Fails if two of them have the same ID, naming the files they are in.
+pub fn load_configuration_from_file<P: AsRef<Path>>(path: P) -> HcResult<Configuration> {
+    let path = path.as_ref();
+    let toml = read_configuration_file(path)?;
+    let mut config = load_configuration::<Configuration>(&toml).map_err(|error| {
+        HolochainError::ConfigError(format!("In {}: {}", path.display(), error))
+    })?;
+    // the root file's own items go through the same checks as included ones
+    let own = IncludedConfiguration {
+        include: config.include.clone(),
+        agents: mem::replace(&mut config.agents, Vec::new()),
+        dnas: mem::replace(&mut config.dnas, Vec::new()),
+        instances: mem::replace(&mut config.instances, Vec::new()),
+        interfaces: mem::replace(&mut config.interfaces, Vec::new()),
+        bridges: mem::replace(&mut config.bridges, Vec::new()),
+    };
+    let mut origins = HashMap::new();
+    add_included(&mut config, &mut origins, own, &toml, path, 0)?;
+    config.included_from = origins
+        .into_iter()
+        .filter(|(_, file)| file != path)
+        .collect();
+    Ok(config)
+}
+
+/// The key of an agent, DNA, instance or interface in `Configuration::included_from`
+pub fn item_key(kind: &str, id: &str) -> String {
+    format!("{} \"{}\"", kind, id)
+}
+
+/// Bridges have no ID, they are told apart by the instances they connect
+pub fn bridge_key(bridge: &Bridge) -> String {
+    format!(
+        "bridge from \"{}\" to \"{}\"",
+        bridge.caller_id, bridge.callee_id
+    )
+}
+
+fn read_configuration_file(path: &Path) -> HcResult<String> {
+    fs::read_to_string(path).map_err(|error| {
+        HolochainError::IoError(format!("Could not read {}: {}", path.display(), error))
     })
 }
 
+fn add_origin(origins: &mut HashMap<String, PathBuf>, key: String, file: &Path) -> HcResult<()> {
+    if let Some(other) = origins.get(&key) {
+        return Err(HolochainError::ConfigError(format!(
+            "Duplicate {}, in {} and in {}",
+            key,
+            other.display(),
+            file.display()
+        )));
+    }
+    origins.insert(key, file.to_path_buf());
+    Ok(())
+}
+
+/// Remembers the line of the table of an item, if it has one, see `Configuration::item_lines`
+fn add_line(config: &mut Configuration, key: String, lines: &[usize], index: usize) {
+    if let Some(line) = lines.get(index) {
+        config.item_lines.insert(key, *line);
+    }
+}
+
+/// Appends the items of a file to the configuration, and then the ones of the files it includes
+fn add_included(
+    config: &mut Configuration,
+    origins: &mut HashMap<String, PathBuf>,
+    included: IncludedConfiguration,
+    toml: &str,
+    file: &Path,
+    depth: usize,
+) -> HcResult<()> {
+    let lines = |name| config_errors::array_lines(toml, name);
+    let agent_lines = lines("agents");
+    for (index, agent) in included.agents.into_iter().enumerate() {
+        let key = item_key("agent", &agent.id);
+        add_origin(origins, key.clone(), file)?;
+        add_line(config, key, &agent_lines, index);
+        config.agents.push(agent);
+    }
+    let dna_lines = lines("dnas");
+    for (index, dna) in included.dnas.into_iter().enumerate() {
+        let key = item_key("DNA", &dna.id);
+        add_origin(origins, key.clone(), file)?;
+        add_line(config, key, &dna_lines, index);
+        config.dnas.push(dna);
+    }
+    let instance_lines = lines("instances");
+    for (index, instance) in included.instances.into_iter().enumerate() {
+        let key = item_key("instance", &instance.id);
+        add_origin(origins, key.clone(), file)?;
+        add_line(config, key, &instance_lines, index);
+        config.instances.push(instance);
+    }
+    let interface_lines = lines("interfaces");
+    for (index, interface) in included.interfaces.into_iter().enumerate() {
+        let key = item_key("interface", &interface.id);
+        add_origin(origins, key.clone(), file)?;
+        add_line(config, key, &interface_lines, index);
+        config.interfaces.push(interface);
+    }
+    let bridge_lines = lines("bridges");
+    for (index, bridge) in included.bridges.into_iter().enumerate() {
+        let key = bridge_key(&bridge);
+        add_origin(origins, key.clone(), file)?;
+        add_line(config, key, &bridge_lines, index);
+        config.bridges.push(bridge);
+    }
+    for pattern in included.include.iter() {
+        if depth >= MAX_INCLUDE_DEPTH {
+            return Err(HolochainError::ConfigError(format!(
+                "Includes nest deeper than {} files, at \"{}\" in {}",
+                MAX_INCLUDE_DEPTH,
+                pattern,
+                file.display()
+            )));
+        }
+        let directory = file.parent().unwrap_or_else(|| Path::new(""));
+        for included_file in resolve_include(&directory.join(pattern))? {
+            let toml = read_configuration_file(&included_file)?;
+            let nested = load_configuration::<IncludedConfiguration>(&toml).map_err(|error| {
+                HolochainError::ConfigError(format!("In {}: {}", included_file.display(), error))
+            })?;
+            add_included(config, origins, nested, &toml, &included_file, depth + 1)?;
+        }
+    }
+    Ok(())
+}
+
+/// The files an include stands for, in the order of their names.
+/// Without a `*` in its file name, the file has to exist.
+fn resolve_include(path: &Path) -> HcResult<Vec<PathBuf>> {
+    let file_pattern = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+        .to_string();
+    if !file_pattern.contains('*') {
+        return if path.is_file() {
+            Ok(vec![path.to_path_buf()])
+        } else {
+            Err(HolochainError::ConfigError(format!(
+                "Included file {} not found",
+                path.display()
+            )))
+        };
+    }
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut files: Vec<PathBuf> = fs::read_dir(directory)
+        .map_err(|error| {
+            HolochainError::IoError(format!("Could not read {}: {}", directory.display(), error))
+        })?
+        .filter_map(|dir_entry| dir_entry.ok().map(|dir_entry| dir_entry.path()))
+        .filter(|file| {
+            file.is_file()
+                && file
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map_or(false, |name| pattern_matches(&file_pattern, name))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Whether the file name matches the pattern, in which a `*` matches any sequence of characters
+fn pattern_matches(pattern: &str, name: &str) -> bool {
+    match pattern.find('*') {
+        None => pattern == name,
+        Some(index) => {
+            let (prefix, rest) = (&pattern[..index], &pattern[index + 1..]);
+            name.starts_with(prefix)
+                && name
+                    .char_indices()
+                    .map(|(i, _)| i)
+                    .chain(iter::once(name.len()))
+                    .filter(|i| *i >= prefix.len())
+                    .any(|i| pattern_matches(rest, &name[i..]))
+        }
+    }
+}
+
+/// Use this function to turn a `Configuration` back into TOML.
+/// Going through a TOML value puts the tables after the plain values like TOML needs it.
+pub fn serialize_configuration(config: &Configuration) -> HcResult<String> {
+    toml::Value::try_from(config)
+        .and_then(|value| toml::to_string_pretty(&value))
+        .map_err(|e| HolochainError::IoError(format!("Could not serialize toml: {}", e)))
+}
+
 #[cfg(test)]
 pub mod tests {
-    use crate::config::{load_configuration, Configuration};
+    use crate::config::{
+        item_key, load_configuration, load_configuration_from_file, serialize_configuration,
+        Configuration, LoggerConfiguration, RestartPolicy, StorageConfiguration,
+        TimeoutConfiguration, TlsConfiguration, MAX_INCLUDE_DEPTH, MAX_RESTART_BACKOFF_MS,
+    };
+    use holochain_core_types::{
+        dna::{
+            self,
+            bridges::{BridgeDeclaration, BridgeFunction, BridgePresence},
+            Dna,
+        },
+        entry::addressing,
+        error::HolochainError,
+        hash::HashString,
+        json::JsonString,
+    };
+    use serde_json;
+    use std::{
+        fs::{self, File},
+        io::Write,
+        path::Path,
+        time::Duration,
+    };
+    use tempfile::tempdir;
+    use test_utils::create_test_dna_with_wat;
 
     pub fn example_serialized_network_config() -> String {
         String::from("{\\\"backend\\\":\\\"mock\\\"}")
@@ -328,6 +1353,9 @@ pub mod tests {
     file = "/tmp/holochain.sock"
     [[interfaces.instances]]
     id = "app spec instance"
+
+    [runtime]
+    threads = 8
     "#,
             "{\\\"backend\\\":\\\"special\\\"}"
         );
@@ -335,6 +1363,7 @@ pub mod tests {
         let config = load_configuration::<Configuration>(toml).unwrap();
 
         assert_eq!(config.check_consistency(), Ok(()));
+        assert_eq!(config.runtime.threads, 8);
         let dnas = config.dnas;
         let dna_config = dnas.get(0).expect("expected at least 1 DNA");
         assert_eq!(dna_config.id, "app spec rust");
@@ -393,6 +1422,7 @@ pub mod tests {
         let config = load_configuration::<Configuration>(toml).unwrap();
 
         assert_eq!(config.check_consistency(), Ok(()));
+        assert_eq!(config.runtime, RuntimeConfiguration::default());
         let dnas = config.dnas;
         let dna_config = dnas.get(0).expect("expected at least 1 DNA");
         assert_eq!(dna_config.id, "app spec rust");
@@ -440,6 +1470,36 @@ pub mod tests {
         assert_eq!(config.check_consistency(), Err("DNA configuration \"WRONG DNA ID\" not found, mentioned in instance \"app spec instance\"".to_string()));
     }
 
+    #[test]
+    #[cfg(not(feature = "test_fixtures"))]
+    fn test_test_agents_need_the_test_fixtures_feature() {
+        let config: Configuration = load_configuration(&dependent_instances_toml("")).unwrap();
+        assert_eq!(config.check_consistency(), Ok(()));
+        assert_eq!(
+            config.with_test_agent("test agent").check_consistency(),
+            Err(String::from("Test agents have keys everyone can derive, they only run in containers built with the test_fixtures feature"))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "test_fixtures")]
+    fn test_test_agents_have_to_be_configured() {
+        let config: Configuration = load_configuration(&dependent_instances_toml("")).unwrap();
+        assert_eq!(
+            config
+                .clone()
+                .with_test_agent("test agent")
+                .check_consistency(),
+            Ok(())
+        );
+        assert_eq!(
+            config.with_test_agent("mallory").check_consistency(),
+            Err(String::from(
+                "Agent configuration mallory not found, mentioned as test agent"
+            ))
+        );
+    }
+
     #[test]
     fn test_inconsistent_config_interface_1() {
         let toml = &format!(
@@ -487,6 +1547,64 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_inconsistent_config_tls_files() {
+        let toml = &format!(
+            r#"
+    [[agents]]
+    id = "test agent"
+    name = "Holo Tester"
+    key_file = "holo_tester.key"
+
+    [[dnas]]
+    id = "app spec rust"
+    file = "app_spec.hcpkg"
+    hash = "Qm328wyq38924y"
+
+    [[instances]]
+    id = "app spec instance"
+    dna = "app spec rust"
+    agent = "test agent"
+    network = "{}"
+    [instances.logger]
+    type = "simple"
+    file = "app_spec.log"
+    [instances.storage]
+    type = "file"
+    path = "app_spec_storage"
+
+    [[interfaces]]
+    id = "app spec interface"
+    [interfaces.driver]
+    type = "websocket"
+    port = 8888
+    [interfaces.driver.tls]
+    cert_file = "/nonexistent/cert.pem"
+    key_file = "/nonexistent/key.pem"
+    [[interfaces.instances]]
+    id = "app spec instance"
+    "#,
+            example_serialized_network_config()
+        );
+        let config = load_configuration::<Configuration>(toml).unwrap();
+        assert_eq!(
+            config.interfaces[0].driver.tls(),
+            Some(&TlsConfiguration {
+                cert_file: String::from("/nonexistent/cert.pem"),
+                key_file: String::from("/nonexistent/key.pem"),
+            })
+        );
+
+        let error = config.check_consistency().unwrap_err();
+        assert!(
+            error.starts_with(
+                "TLS certificate file \"/nonexistent/cert.pem\" of interface \"app spec interface\" can't be read: "
+            ),
+            "error = {}",
+            error
+        );
+    }
+
     #[test]
     fn test_invalid_toml_1() {
         let toml = &format!(
@@ -532,4 +1650,1008 @@ pub mod tests {
             panic!("Should have failed!")
         }
     }
+
+    fn filtered_interface_toml(dna_file: &str) -> String {
+        format!(
+            r#"
+    [[agents]]
+    id = "test agent"
+    key_file = "holo_tester.key"
+
+    [[dnas]]
+    id = "app spec rust"
+    file = "{}"
+    hash = "Qm328wyq38924y"
+
+    [[instances]]
+    id = "app spec instance"
+    dna = "app spec rust"
+    agent = "test agent"
+    [instances.logger]
+    type = "simple"
+    [instances.storage]
+    type = "memory"
+
+    [[interfaces]]
+    id = "public interface"
+    [interfaces.driver]
+    type = "websocket"
+    port = 8888
+    [[interfaces.instances]]
+    id = "app spec instance"
+    zomes = ["reader", "missing_zome"]
+    functions = ["main", "missing_function"]
+    [interfaces.request_log]
+    log_params_for = ["main"]
+    [interfaces.request_log.logger]
+    type = "debug"
+
+    [[interfaces]]
+    id = "internal interface"
+    [interfaces.driver]
+    type = "websocket"
+    port = 8889
+    [[interfaces.instances]]
+    id = "app spec instance"
+    "#,
+            dna_file
+        )
+    }
+
+    #[test]
+    fn test_load_interface_filters() {
+        let config =
+            load_configuration::<Configuration>(&filtered_interface_toml("app_spec.hcpkg"))
+                .unwrap();
+        assert_eq!(config.check_consistency(), Ok(()));
+
+        let public = config.interface_by_id("public interface").unwrap();
+        let reference = public.instances.get(0).unwrap();
+        assert!(reference.exposes("reader", "main"));
+        assert!(!reference.exposes("writer", "main"));
+        assert!(!reference.exposes("reader", "write"));
+
+        let internal = config.interface_by_id("internal interface").unwrap();
+        let reference = internal.instances.get(0).unwrap();
+        assert_eq!(reference.zomes, None);
+        assert_eq!(reference.functions, None);
+        assert!(reference.exposes("writer", "write"));
+    }
+
+    #[test]
+    fn test_load_request_log() {
+        let config =
+            load_configuration::<Configuration>(&filtered_interface_toml("app_spec.hcpkg"))
+                .unwrap();
+
+        let public = config.interface_by_id("public interface").unwrap();
+        assert_eq!(
+            public.request_log,
+            Some(RequestLogConfiguration {
+                logger: LoggerConfiguration {
+                    logger_type: "debug".to_string(),
+                    file: None,
+                },
+                log_params_for: vec!["main".to_string()],
+            })
+        );
+        let internal = config.interface_by_id("internal interface").unwrap();
+        assert_eq!(internal.request_log, None);
+    }
+
+    #[test]
+    fn test_interface_filter_warnings() {
+        let dir = tempdir().unwrap();
+        let dna_file = dir.path().join("test.dna.json");
+        let dna = create_test_dna_with_wat("reader", "test_cap", None);
+        File::create(&dna_file)
+            .unwrap()
+            .write_all(String::from(JsonString::from(dna)).as_bytes())
+            .unwrap();
+
+        let config = load_configuration::<Configuration>(&filtered_interface_toml(
+            dna_file.to_str().unwrap(),
+        ))
+        .unwrap();
+        let warnings = config.interface_filter_warnings();
+        assert_eq!(warnings.len(), 2, "warnings = {:?}", warnings);
+        assert!(warnings[0].contains("Zome \"missing_zome\""));
+        assert!(warnings[1].contains("Function \"missing_function\""));
+        assert!(warnings[1].contains("public interface"));
+    }
+
+    fn defaults_toml(defaults: &str) -> String {
+        format!(
+            r#"
+    [[agents]]
+    id = "test agent"
+    key_file = "holo_tester.key"
+
+    [[dnas]]
+    id = "app spec rust"
+    file = "app_spec.hcpkg"
+    hash = "Qm328wyq38924y"
+
+    [[instances]]
+    id = "inheriting instance"
+    dna = "app spec rust"
+    agent = "test agent"
+
+    [[instances]]
+    id = "overriding instance"
+    dna = "app spec rust"
+    agent = "test agent"
+    network = "{{\"backend\":\"mock\"}}"
+    [instances.logger]
+    type = "debug"
+    [instances.storage]
+    type = "memory"
+    [instances.timeouts]
+    validation_dependencies = 5
+    {}
+    "#,
+            defaults
+        )
+    }
+
+    #[test]
+    fn test_instances_inherit_defaults() {
+        let config = load_configuration::<Configuration>(&defaults_toml(
+            r#"
+    [defaults]
+    network = "{\"backend\":\"none\"}"
+    [defaults.logger]
+    type = "simple"
+    [defaults.storage]
+    type = "file"
+    path = "/var/lib/holochain"
+    [defaults.timeouts]
+    validation_dependencies = 30
+    max_sleep = 2
+    "#,
+        ))
+        .unwrap();
+        assert_eq!(config.check_consistency(), Ok(()));
+
+        let effective = config.effective();
+        let inheriting = effective.instance_by_id("inheriting instance").unwrap();
+        assert_eq!(
+            inheriting.storage,
+            Some(StorageConfiguration::File {
+                path: "/var/lib/holochain/inheriting instance".to_string()
+            })
+        );
+        assert_eq!(
+            inheriting.logger,
+            Some(LoggerConfiguration {
+                logger_type: "simple".to_string(),
+                file: None,
+            })
+        );
+        assert_eq!(
+            inheriting.network,
+            Some("{\"backend\":\"none\"}".to_string())
+        );
+        assert_eq!(
+            inheriting.timeouts,
+            Some(TimeoutConfiguration {
+                validation_dependencies: Some(30),
+                max_sleep: Some(2),
+                max_total_sleep: None,
+                slow_call_threshold_ms: None,
+            })
+        );
+
+        // what an instance sets itself always wins
+        let overriding = effective.instance_by_id("overriding instance").unwrap();
+        assert_eq!(overriding.storage, Some(StorageConfiguration::Memory));
+        assert_eq!(overriding.logger.unwrap().logger_type, "debug");
+        assert_eq!(
+            overriding.network,
+            Some("{\"backend\":\"mock\"}".to_string())
+        );
+        assert_eq!(
+            overriding.timeouts,
+            Some(TimeoutConfiguration {
+                validation_dependencies: Some(5),
+                max_sleep: Some(2),
+                max_total_sleep: None,
+                slow_call_threshold_ms: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_instance_without_storage() {
+        let config = load_configuration::<Configuration>(&defaults_toml("")).unwrap();
+        assert_eq!(
+            config.check_consistency(),
+            Err("No storage configured for instance \"inheriting instance\", neither in the instance nor in the defaults".to_string())
+        );
+
+        let config = load_configuration::<Configuration>(&defaults_toml(
+            r#"
+    [defaults.logger]
+    type = "simple"
+    "#,
+        ))
+        .unwrap();
+        assert!(config.check_consistency().is_err());
+    }
+
+    #[test]
+    fn test_serialization_keeps_defaults_sparse() {
+        let config = load_configuration::<Configuration>(&defaults_toml(
+            r#"
+    [defaults.storage]
+    type = "memory"
+    "#,
+        ))
+        .unwrap();
+
+        let serialized = serialize_configuration(&config).unwrap();
+        let reloaded = load_configuration::<Configuration>(&serialized).unwrap();
+        let inheriting = reloaded.instance_by_id("inheriting instance").unwrap();
+        assert_eq!(inheriting.storage, None);
+        assert_eq!(inheriting.logger, None);
+        assert_eq!(
+            reloaded.defaults.unwrap().storage,
+            Some(StorageConfiguration::Memory)
+        );
+
+        // the effective configuration has the defaults applied instead
+        let effective = load_configuration::<Configuration>(
+            &serialize_configuration(&config.effective()).unwrap(),
+        )
+        .unwrap();
+        assert!(effective.defaults.is_none());
+        assert_eq!(
+            effective
+                .instance_by_id("inheriting instance")
+                .unwrap()
+                .storage,
+            Some(StorageConfiguration::Memory)
+        );
+    }
+
+    fn dependent_instances_toml(more: &str) -> String {
+        format!(
+            r#"
+    [[agents]]
+    id = "test agent"
+    key_file = "holo_tester.key"
+
+    [[dnas]]
+    id = "app spec rust"
+    file = "app_spec.hcpkg"
+    hash = "Qm328wyq38924y"
+
+    [defaults.storage]
+    type = "memory"
+
+    [[instances]]
+    id = "caller"
+    dna = "app spec rust"
+    agent = "test agent"
+
+    [[instances]]
+    id = "callee"
+    dna = "app spec rust"
+    agent = "test agent"
+    depends_on = ["base"]
+
+    [[instances]]
+    id = "base"
+    dna = "app spec rust"
+    agent = "test agent"
+
+    [[bridges]]
+    caller_id = "caller"
+    callee_id = "callee"
+    {}
+    "#,
+            more
+        )
+    }
+
+    #[test]
+    fn test_startup_order() {
+        let config = load_configuration::<Configuration>(&dependent_instances_toml("")).unwrap();
+        assert_eq!(config.check_consistency(), Ok(()));
+        assert_eq!(config.instance_dependencies("caller"), vec!["callee"]);
+        assert_eq!(config.instance_dependencies("callee"), vec!["base"]);
+        assert_eq!(
+            config.startup_order(),
+            Ok(vec![
+                "base".to_string(),
+                "callee".to_string(),
+                "caller".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_dependency_cycle() {
+        let config = load_configuration::<Configuration>(&dependent_instances_toml(
+            r#"
+    [[bridges]]
+    caller_id = "base"
+    callee_id = "caller"
+    "#,
+        ))
+        .unwrap();
+        let error = Err(
+            "Instances depend on each other in a cycle: caller -> callee -> base -> caller"
+                .to_string(),
+        );
+        assert_eq!(config.startup_order(), error);
+        assert_eq!(config.check_consistency(), error);
+    }
+
+    #[test]
+    fn test_instances_sharing_storage() {
+        let mut config =
+            load_configuration::<Configuration>(&dependent_instances_toml("")).unwrap();
+        for instance in config.instances.iter_mut() {
+            instance.storage = Some(StorageConfiguration::File {
+                path: format!("/var/lib/holochain/{}", instance.id),
+            });
+        }
+        assert_eq!(config.check_consistency(), Ok(()));
+
+        config.instances[2].storage = config.instances[0].storage.clone();
+        assert_eq!(
+            config.check_consistency(),
+            Err(
+                "Instances \"caller\" and \"base\" share the storage path \"/var/lib/holochain/caller\""
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_unknown_dependency() {
+        let mut config =
+            load_configuration::<Configuration>(&dependent_instances_toml("")).unwrap();
+        config.instances[0].depends_on = vec!["unknown".to_string()];
+        assert_eq!(
+            config.check_consistency(),
+            Err(
+                "Instance configuration \"unknown\" not found, instance \"caller\" depends on it"
+                    .to_string()
+            )
+        );
+    }
+
+    fn write_config_file(path: &Path, toml: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(path)
+            .unwrap()
+            .write_all(toml.as_bytes())
+            .unwrap();
+    }
+
+    fn instance_toml(id: &str) -> String {
+        format!(
+            r#"
+    [[instances]]
+    id = "{}"
+    dna = "app spec rust"
+    agent = "test agent"
+    [instances.storage]
+    type = "memory"
+    "#,
+            id
+        )
+    }
+
+    #[test]
+    fn test_load_configuration_with_included_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("container_config.toml");
+        write_config_file(
+            &root,
+            r#"
+    include = ["agents.toml", "instances/*.toml"]
+
+    [[dnas]]
+    id = "app spec rust"
+    file = "app-spec-rust.hcpkg"
+    hash = "Qm328wyq38924y"
+    "#,
+        );
+        write_config_file(
+            &dir.path().join("agents.toml"),
+            r#"
+    [[agents]]
+    id = "test agent"
+    key_file = "holo_tester.key"
+    "#,
+        );
+        write_config_file(&dir.path().join("instances/b.toml"), &instance_toml("b"));
+        write_config_file(&dir.path().join("instances/a.toml"), &instance_toml("a"));
+        write_config_file(&dir.path().join("instances/c.txt"), &instance_toml("c"));
+
+        let config = load_configuration_from_file(&root).unwrap();
+        assert_eq!(config.check_consistency(), Ok(()));
+        assert_eq!(
+            config.agent_by_id("test agent").unwrap().key_file,
+            "holo_tester.key"
+        );
+        // in the order of the file names, files that don't match are left out
+        assert_eq!(config.instance_ids(), vec!["a", "b"]);
+        assert_eq!(
+            config.included_from.get(&item_key("instance", "b")),
+            Some(&dir.path().join("instances/b.toml"))
+        );
+        assert_eq!(
+            config.included_from.get(&item_key("DNA", "app spec rust")),
+            None
+        );
+
+        // saving writes back the root file as it was
+        let saved = load_configuration::<Configuration>(
+            &serialize_configuration(&config.without_included()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(saved.include, vec!["agents.toml", "instances/*.toml"]);
+        assert!(saved.agents.is_empty());
+        assert!(saved.instances.is_empty());
+        assert_eq!(saved.dnas.len(), 1);
+
+        // the effective configuration has it all and no includes
+        let effective = config.effective();
+        assert!(effective.include.is_empty());
+        assert_eq!(effective.instance_ids(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_included_duplicate_ids_name_both_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("container_config.toml");
+        write_config_file(
+            &root,
+            &format!("include = [\"instances/*.toml\"]\n{}", instance_toml("a")),
+        );
+        write_config_file(&dir.path().join("instances/a.toml"), &instance_toml("a"));
+
+        let error = load_configuration_from_file(&root)
+            .err()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            error,
+            format!(
+                "Duplicate instance \"a\", in {} and in {}",
+                root.display(),
+                dir.path().join("instances/a.toml").display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_nested_includes() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("container_config.toml");
+        write_config_file(&root, "include = [\"instances/all.toml\"]");
+        // relative to the including file
+        write_config_file(
+            &dir.path().join("instances/all.toml"),
+            "include = [\"more/*.toml\"]",
+        );
+        write_config_file(
+            &dir.path().join("instances/more/a.toml"),
+            &instance_toml("a"),
+        );
+
+        let config = load_configuration_from_file(&root).unwrap();
+        assert_eq!(config.instance_ids(), vec!["a"]);
+        assert_eq!(
+            config.included_from.get(&item_key("instance", "a")),
+            Some(&dir.path().join("instances/more/a.toml"))
+        );
+
+        // a file including itself stops at the depth limit
+        write_config_file(
+            &dir.path().join("instances/all.toml"),
+            "include = [\"all.toml\"]",
+        );
+        let error = load_configuration_from_file(&root)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(
+            error.starts_with(&format!("Includes nest deeper than {}", MAX_INCLUDE_DEPTH)),
+            "error = {}",
+            error
+        );
+    }
+
+    fn load_error(toml: &str) -> String {
+        load_configuration::<Configuration>(toml)
+            .err()
+            .expect("Should have failed!")
+            .to_string()
+    }
+
+    #[test]
+    fn test_configuration_errors_name_their_line() {
+        assert_eq!(
+            load_error(
+                r#"
+    [[agents]]
+    id = "test agent"
+    keyfile = "holo_tester.key"
+    "#
+            ),
+            "line 4: unknown field `keyfile`, did you mean `key_file`?"
+        );
+        assert_eq!(
+            load_error(
+                r#"
+    [[agents]]
+    id = "test agent"
+    key_file = "holo_tester.key"
+
+    [[dnas]]
+    id = "app spec rust"
+    file = "app_spec.hcpkg"
+    "#
+            ),
+            "line 6: missing field `hash`"
+        );
+        assert_eq!(
+            load_error(
+                r#"
+    [runtim]
+    threads = 2
+    "#
+            ),
+            "line 2: unknown section `runtim`, did you mean `runtime`?"
+        );
+        assert_eq!(
+            load_error(
+                r#"
+    [[interfaces]]
+    id = "app spec interface"
+    [interfaces.driver]
+    type = "websockt"
+    port = 8888
+    [[interfaces.instances]]
+    id = "app spec instance"
+    "#
+            ),
+            "line 5: unknown variant `websockt`, did you mean `websocket`?"
+        );
+        assert_eq!(
+            load_error(
+                r#"
+    [[interfaces]]
+    id = "app spec interface"
+    [interfaces.driver]
+    type = "websocket"
+    port = 8888
+    [[interfaces.instances]]
+    id = "app spec instance"
+
+    [[interfaces]]
+    id = "admin interface"
+    admin = true
+    [interfaces.driver]
+    type = "http"
+    port = "8889"
+    [[interfaces.instances]]
+    id = "app spec instance"
+    "#
+            ),
+            "line 15: invalid type: string \"8889\", expected u16"
+        );
+        // names too different from any expected one get no suggestion
+        assert_eq!(
+            load_error(
+                r#"
+    [[bridges]]
+    caller_id = "app spec instance"
+    callee_id = "other instance"
+    priority = "high"
+    "#
+            ),
+            "line 5: unknown field `priority`, expected one of `caller_id`, `callee_id`, `handle`"
+        );
+        let syntax_error = load_error(
+            r#"
+    [[agents]]
+    id = "test agent
+    "#,
+        );
+        assert!(syntax_error.contains("line 3"), "error = {}", syntax_error);
+    }
+
+    #[test]
+    fn test_forward_compatible_sections_are_skipped() {
+        let config = load_configuration::<Configuration>(
+            r#"
+    [[agents]]
+    id = "test agent"
+    key_file = "holo_tester.key"
+
+    [logger]
+    type = "debug"
+
+    [ui_bundles]
+    root_dir = "ui"
+    "#,
+        )
+        .unwrap();
+        assert_eq!(config.agents.len(), 1);
+    }
+
+    #[test]
+    fn test_errors_of_files_name_the_file_and_line() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("container_config.toml");
+        write_config_file(
+            &root,
+            &format!("include = [\"instances/*.toml\"]\n{}", instance_toml("a")),
+        );
+        write_config_file(&dir.path().join("instances/b.toml"), &instance_toml("b"));
+
+        let config = load_configuration_from_file(&root).unwrap();
+        assert_eq!(
+            config.check_consistency(),
+            Err(
+                "Agent configuration test agent not found, mentioned in instance a (line 3)"
+                    .to_string()
+            )
+        );
+        assert_eq!(config.item_lines.get(&item_key("instance", "b")), Some(&2));
+
+        let included = dir.path().join("instances/b.toml");
+        write_config_file(&included, &instance_toml("b").replace("agent =", "agnet ="));
+        assert_eq!(
+            load_configuration_from_file(&root)
+                .err()
+                .unwrap()
+                .to_string(),
+            format!(
+                "In {}: line 5: unknown field `agnet`, did you mean `agent`?",
+                included.display()
+            )
+        );
+
+        write_config_file(&root, "[[agents]]\nid = \"test agent\"\n");
+        assert_eq!(
+            load_configuration_from_file(&root)
+                .err()
+                .unwrap()
+                .to_string(),
+            format!("In {}: line 1: missing field `key_file`", root.display())
+        );
+    }
+
+    fn properties_override_toml(dna_file: &str) -> String {
+        format!(
+            r#"
+    [[agents]]
+    id = "test agent"
+    key_file = "holo_tester.key"
+
+    [[dnas]]
+    id = "app spec rust"
+    file = "{}"
+    hash = "Qm328wyq38924y"
+
+    [[instances]]
+    id = "alice's community"
+    dna = "app spec rust"
+    agent = "test agent"
+    [instances.storage]
+    type = "memory"
+    [instances.properties_override]
+    admin = "alice"
+    max_posts = 10
+
+    [[instances]]
+    id = "bob's community"
+    dna = "app spec rust"
+    agent = "test agent"
+    [instances.storage]
+    type = "memory"
+    [instances.properties_override]
+    admin = "bob"
+
+    [[instances]]
+    id = "plain"
+    dna = "app spec rust"
+    agent = "test agent"
+    [instances.storage]
+    type = "memory"
+    "#,
+            dna_file
+        )
+    }
+
+    fn properties(json: &str) -> serde_json::Value {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_genesis_params_leave_the_dna_alone() {
+        // the table belongs to the last instance, "plain"
+        let toml = format!(
+            "{}\n[instances.genesis_params]\nprogenitor = \"QmProgenitor\"\n",
+            properties_override_toml("app_spec.hcpkg")
+        );
+        let config = load_configuration::<Configuration>(&toml).unwrap();
+        let dna = create_test_dna_with_wat("reader", "test_cap", None);
+
+        let plain = config.instance_by_id("plain").unwrap();
+        assert_eq!(
+            plain.genesis_params,
+            Some(properties(r#"{"progenitor": "QmProgenitor"}"#))
+        );
+        assert_eq!(plain.effective_dna(dna.clone()), dna);
+        assert_eq!(
+            config
+                .instance_by_id("bob's community")
+                .unwrap()
+                .genesis_params,
+            None
+        );
+    }
+
+    #[test]
+    fn test_restart_policy() {
+        let toml = format!(
+            "{}\n[instances.restart]\npolicy = \"on_failure\"\nmax_retries = 3\n",
+            properties_override_toml("app_spec.hcpkg")
+        );
+        let config = load_configuration::<Configuration>(&toml).unwrap();
+        let restart = config.instance_by_id("plain").unwrap().restart;
+        assert_eq!(
+            restart,
+            RestartPolicy::OnFailure {
+                max_retries: 3,
+                backoff_ms: 1000,
+            }
+        );
+        assert_eq!(restart.backoff(0), Some(Duration::from_millis(1000)));
+        assert_eq!(restart.backoff(2), Some(Duration::from_millis(4000)));
+        assert_eq!(restart.backoff(3), None);
+        assert_eq!(
+            config.instance_by_id("bob's community").unwrap().restart,
+            RestartPolicy::Never
+        );
+        assert_eq!(RestartPolicy::Never.backoff(0), None);
+
+        let long_backoff = RestartPolicy::OnFailure {
+            max_retries: 100,
+            backoff_ms: 1000,
+        };
+        assert_eq!(
+            long_backoff.backoff(70),
+            Some(Duration::from_millis(MAX_RESTART_BACKOFF_MS))
+        );
+    }
+
+    #[test]
+    fn test_properties_override() {
+        let config =
+            load_configuration::<Configuration>(&properties_override_toml("app_spec.hcpkg"))
+                .unwrap();
+        let mut dna = create_test_dna_with_wat("reader", "test_cap", None);
+        dna.properties = properties(r#"{"admin": "nobody", "language": "en"}"#);
+
+        let alice = config.instance_by_id("alice's community").unwrap();
+        assert_eq!(
+            alice.effective_dna(dna.clone()).properties,
+            properties(r#"{"admin": "alice", "language": "en", "max_posts": 10}"#)
+        );
+        let plain = config.instance_by_id("plain").unwrap();
+        assert_eq!(plain.effective_dna(dna.clone()), dna);
+
+        dna.properties = serde_json::Value::Null;
+        let bob = config.instance_by_id("bob's community").unwrap();
+        assert_eq!(
+            bob.effective_dna(dna).properties,
+            properties(r#"{"admin": "bob"}"#)
+        );
+    }
+
+    #[test]
+    fn test_properties_override_warnings_and_hashes() {
+        let dir = tempdir().unwrap();
+        let dna_file = dir.path().join("test.dna.json");
+        let dna = create_test_dna_with_wat("reader", "test_cap", None);
+        File::create(&dna_file)
+            .unwrap()
+            .write_all(String::from(JsonString::from(dna.clone())).as_bytes())
+            .unwrap();
+        let config = load_configuration::<Configuration>(&properties_override_toml(
+            dna_file.to_str().unwrap(),
+        ))
+        .unwrap();
+        assert_eq!(config.check_consistency(), Ok(()));
+
+        let warnings = config.properties_override_warnings();
+        assert_eq!(warnings.len(), 2, "warnings = {:?}", warnings);
+        assert!(warnings[0].contains("\"alice's community\" and \"bob's community\""));
+        assert!(warnings[1].contains("\"alice's community\" and \"plain\""));
+
+        let hashes: Vec<HashString> = config
+            .effective_dna_hashes()
+            .into_iter()
+            .map(|(_, hash)| hash.unwrap())
+            .collect();
+        assert_eq!(hashes[2], addressing::dna_hash(&dna));
+        assert_ne!(hashes[0], hashes[1]);
+        assert_ne!(hashes[0], hashes[2]);
+    }
+
+    /// The configuration of an "app" instance, whose zomes call a "persona" instance with
+    /// the function profile/main over a required bridge and an instance of DNA QmSearch over
+    /// an optional one, and of a "persona" instance, which has that function.
+    fn bridge_declarations_config(bridges: &str) -> Result<(), String> {
+        let dir = tempdir().unwrap();
+        let write_dna = |name: &str, dna: Dna| {
+            let dna_file = dir.path().join(format!("{}.dna.json", name));
+            File::create(&dna_file)
+                .unwrap()
+                .write_all(String::from(JsonString::from(dna)).as_bytes())
+                .unwrap();
+            dna_file.to_str().unwrap().to_string()
+        };
+        let mut app_dna = create_test_dna_with_wat("reader", "test_cap", None);
+        app_dna.bridges = vec![
+            BridgeDeclaration {
+                handle: String::from("persona"),
+                presence: BridgePresence::Required,
+                dna_hash: None,
+                functions: vec![BridgeFunction {
+                    zome: String::from("profile"),
+                    function: String::from("main"),
+                }],
+            },
+            BridgeDeclaration {
+                handle: String::from("search"),
+                presence: BridgePresence::Optional,
+                dna_hash: Some(String::from("QmSearch")),
+                functions: Vec::new(),
+            },
+        ];
+        let app_file = write_dna("app", app_dna);
+        let persona_file = write_dna(
+            "persona",
+            create_test_dna_with_wat("profile", "test_cap", None),
+        );
+
+        let toml = format!(
+            r#"
+    [[agents]]
+    id = "test agent"
+    key_file = "holo_tester.key"
+
+    [[dnas]]
+    id = "app"
+    file = "{}"
+    hash = "Qm328wyq38924y"
+
+    [[dnas]]
+    id = "persona"
+    file = "{}"
+    hash = "Qm328wyq38924y"
+
+    [[instances]]
+    id = "app"
+    dna = "app"
+    agent = "test agent"
+    [instances.storage]
+    type = "memory"
+
+    [[instances]]
+    id = "persona"
+    dna = "persona"
+    agent = "test agent"
+    [instances.storage]
+    type = "memory"
+    {}
+    "#,
+            app_file, persona_file, bridges
+        );
+        load_configuration::<Configuration>(&toml)
+            .unwrap()
+            .check_consistency()
+    }
+
+    #[test]
+    fn test_bridge_declarations() {
+        let missing = bridge_declarations_config("").unwrap_err();
+        assert!(
+            missing.starts_with(
+                "Instance \"app\" requires a bridge with handle \"persona\", but none is configured"
+            ),
+            "error = {}",
+            missing
+        );
+
+        let satisfied = r#"
+    [[bridges]]
+    caller_id = "app"
+    callee_id = "persona"
+    handle = "persona"
+    "#;
+        // the optional bridge can be left out
+        assert_eq!(bridge_declarations_config(satisfied), Ok(()));
+
+        let without_the_function = r#"
+    [[bridges]]
+    caller_id = "app"
+    callee_id = "app"
+    handle = "persona"
+    "#;
+        let error = bridge_declarations_config(without_the_function).unwrap_err();
+        assert!(
+            error.contains("function profile/main, which instance \"app\" does not have"),
+            "error = {}",
+            error
+        );
+
+        let other_dna = format!(
+            "{}\n[[bridges]]\ncaller_id = \"app\"\ncallee_id = \"persona\"\nhandle = \"search\"\n",
+            satisfied
+        );
+        let error = bridge_declarations_config(&other_dna).unwrap_err();
+        assert!(
+            error.contains("has to lead to an instance of DNA QmSearch"),
+            "error = {}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_dna_manifest_versions() {
+        let dir = tempdir().unwrap();
+        let current_file = dir.path().join("current.dna.json");
+        let future_file = dir.path().join("future.dna.json");
+        File::create(&current_file)
+            .unwrap()
+            .write_all(String::from(JsonString::from(Dna::new())).as_bytes())
+            .unwrap();
+        File::create(&future_file)
+            .unwrap()
+            .write_all(br#"{"name": "future", "manifest_version": 1000}"#)
+            .unwrap();
+        let config = load_configuration::<Configuration>(&format!(
+            r#"
+    [[dnas]]
+    id = "current"
+    file = "{}"
+    hash = "Qm328wyq38924y"
+
+    [[dnas]]
+    id = "future"
+    file = "{}"
+    hash = "Qm328wyq38924y"
+    "#,
+            current_file.to_str().unwrap(),
+            future_file.to_str().unwrap(),
+        ))
+        .unwrap();
+
+        let versions = config.dna_manifest_versions();
+        assert_eq!(
+            versions[0],
+            (String::from("current"), Ok(dna::MANIFEST_VERSION))
+        );
+        assert_eq!(
+            versions[1],
+            (
+                String::from("future"),
+                Err(HolochainError::UnsupportedDnaVersion {
+                    found: 1000,
+                    supported: dna::SUPPORTED_MANIFEST_VERSIONS,
+                })
+            )
+        );
+    }
 }
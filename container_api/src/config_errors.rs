@@ -0,0 +1,459 @@
+/// Configuration errors that point at their cause.
+/// Serde reports the first problem it runs into, but not where in the TOML source it is.
+/// To find that place, the parsed configuration gets changed bit by bit until the error
+/// goes away or changes. The TOML source then tells the line of the table or key involved.
+/// Misspelled field and variant names get compared to the expected ones, to suggest
+/// the name that was probably meant.
+use serde::de::DeserializeOwned;
+use std::{cmp, collections::HashMap};
+use toml::Value;
+
+/// Top-level sections that newer versions of the container understand.
+/// Older ones skip them with a warning instead of rejecting the whole configuration,
+/// so that a configuration can be shared between containers of different versions.
+pub const FORWARD_COMPATIBLE_SECTIONS: &[&str] = &[
+    "logger",
+    "network",
+    "persistence_dir",
+    "signals",
+    "ui_bundles",
+    "ui_interfaces",
+];
+
+/// A step from a TOML value into one of its parts
+#[derive(Clone, Debug, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+/// Deserializes the TOML source. Syntax errors name their line already, errors of the
+/// content get the line of the table or key they are about and, for misspelled names,
+/// a suggestion of the name that was meant.
+pub fn deserialize<T: DeserializeOwned>(toml: &str) -> Result<T, String> {
+    let mut value = toml.parse::<Value>().map_err(|error| error.to_string())?;
+    loop {
+        let error = match value.clone().try_into::<T>() {
+            Ok(deserialized) => return Ok(deserialized),
+            Err(error) => error.to_string(),
+        };
+        let skipped = quoted(&error, "unknown field")
+            .filter(|name| FORWARD_COMPATIBLE_SECTIONS.contains(&name.as_str()))
+            .and_then(|name| {
+                value
+                    .as_table_mut()
+                    .and_then(|table| table.remove(&name))
+                    .map(|_| name)
+            });
+        match skipped {
+            Some(section) => println!(
+                "Warning: skipping section `{}` of the configuration, which this container does not support",
+                section
+            ),
+            None => return Err(explain::<T>(toml, &value, &error)),
+        }
+    }
+}
+
+/// The line numbers of the `[[name]]` headers in the TOML source, in their order,
+/// i.e. the lines of the tables of the top-level array `name`.
+pub fn array_lines(toml: &str, name: &str) -> Vec<usize> {
+    let prefix = format!("{}[", name);
+    SourceLines::new(toml)
+        .headers
+        .into_iter()
+        .filter(|(header, _)| header.starts_with(&prefix) && !header.contains('.'))
+        .map(|(_, index)| index + 1)
+        .collect()
+}
+
+fn explain<T: DeserializeOwned>(toml: &str, value: &Value, error: &str) -> String {
+    let lines = SourceLines::new(toml);
+    let (index, message) = match quoted(error, "missing field") {
+        Some(field) => (
+            table_missing::<T>(value, error, &field).and_then(|path| lines.line_of(&path)),
+            error.to_string(),
+        ),
+        None => match culprit::<T>(value, error) {
+            Some(path) => (
+                lines.line_of(&path),
+                with_suggestion(error, path.len() == 1),
+            ),
+            None => (None, error.to_string()),
+        },
+    };
+    match index {
+        Some(index) => format!("line {}: {}", index + 1, message),
+        None => message,
+    }
+}
+
+/// The path of the value the error is about. Serde stops at the first problem, so removing
+/// the parts of a table that serde went through before it leaves the error as it is, while
+/// removing the part with the problem changes it. Following those parts down from the
+/// top-level table leads to the value with the problem.
+fn culprit<T: DeserializeOwned>(value: &Value, error: &str) -> Option<Vec<Step>> {
+    let mut path = Vec::new();
+    loop {
+        let steps: Vec<Step> = match value_at(value, &path) {
+            Some(Value::Table(table)) => table.keys().map(|key| Step::Key(key.clone())).collect(),
+            Some(Value::Array(array)) => (0..array.len()).map(Step::Index).collect(),
+            _ => Vec::new(),
+        };
+        let next = steps.into_iter().find(|step| {
+            error_of::<T>(&without(value, &path, step))
+                .as_ref()
+                .map(String::as_str)
+                != Some(error)
+        });
+        match next {
+            Some(step) => path.push(step),
+            None if path.is_empty() => return None,
+            None => return Some(path),
+        }
+    }
+}
+
+/// A copy of the value, without the part the step leads to from the given path
+fn without(value: &Value, path: &[Step], step: &Step) -> Value {
+    let mut changed = value.clone();
+    match (value_at_mut(&mut changed, path), step) {
+        (Some(Value::Table(table)), Step::Key(key)) => {
+            table.remove(key);
+        }
+        (Some(Value::Array(array)), Step::Index(index)) => {
+            array.remove(*index);
+        }
+        _ => {}
+    }
+    changed
+}
+
+/// The path of the deepest table that lacks the field, i.e. where adding it changes the error.
+/// Tables of other types reject the added field as unknown instead, or ignore it.
+fn table_missing<T: DeserializeOwned>(
+    value: &Value,
+    error: &str,
+    field: &str,
+) -> Option<Vec<Step>> {
+    tables(value).into_iter().find(|path| {
+        let mut changed = value.clone();
+        let has_field = match value_at_mut(&mut changed, path) {
+            Some(Value::Table(table)) => {
+                if table.contains_key(field) {
+                    true
+                } else {
+                    table.insert(field.to_string(), Value::String(String::new()));
+                    false
+                }
+            }
+            _ => true,
+        };
+        if has_field {
+            return false;
+        }
+        match error_of::<T>(&changed) {
+            Some(ref other) => {
+                other != error
+                    && quoted(other, "unknown field").as_ref().map(String::as_str) != Some(field)
+            }
+            None => true,
+        }
+    })
+}
+
+fn error_of<T: DeserializeOwned>(value: &Value) -> Option<String> {
+    value
+        .clone()
+        .try_into::<T>()
+        .err()
+        .map(|error| error.to_string())
+}
+
+/// The paths of all tables in the value, the deepest ones first
+fn tables(value: &Value) -> Vec<Vec<Step>> {
+    fn collect(value: &Value, path: &[Step], paths: &mut Vec<Vec<Step>>) {
+        let children: Vec<(Step, &Value)> = match value {
+            Value::Table(table) => {
+                paths.push(path.to_vec());
+                table
+                    .iter()
+                    .map(|(key, child)| (Step::Key(key.clone()), child))
+                    .collect()
+            }
+            Value::Array(array) => array
+                .iter()
+                .enumerate()
+                .map(|(index, item)| (Step::Index(index), item))
+                .collect(),
+            _ => Vec::new(),
+        };
+        for (step, child) in children {
+            let mut child_path = path.to_vec();
+            child_path.push(step);
+            collect(child, &child_path, paths);
+        }
+    }
+    let mut paths = Vec::new();
+    collect(value, &[], &mut paths);
+    paths.sort_by(|a, b| b.len().cmp(&a.len()));
+    paths
+}
+
+fn value_at<'v>(value: &'v Value, path: &[Step]) -> Option<&'v Value> {
+    match (value, path.split_first()) {
+        (_, None) => Some(value),
+        (Value::Table(table), Some((Step::Key(key), rest))) => {
+            table.get(key).and_then(|child| value_at(child, rest))
+        }
+        (Value::Array(array), Some((Step::Index(index), rest))) => {
+            array.get(*index).and_then(|item| value_at(item, rest))
+        }
+        _ => None,
+    }
+}
+
+fn value_at_mut<'v>(value: &'v mut Value, path: &[Step]) -> Option<&'v mut Value> {
+    match (value, path.split_first()) {
+        (value, None) => Some(value),
+        (Value::Table(table), Some((Step::Key(key), rest))) => table
+            .get_mut(key)
+            .and_then(|child| value_at_mut(child, rest)),
+        (Value::Array(array), Some((Step::Index(index), rest))) => array
+            .get_mut(*index)
+            .and_then(|item| value_at_mut(item, rest)),
+        _ => None,
+    }
+}
+
+/// The path as it shows in table headers, with the indices of arrays of tables added,
+/// like `interfaces[0].instances[1]`
+fn path_name(path: &[Step]) -> String {
+    let mut name = String::new();
+    for step in path {
+        match step {
+            Step::Key(key) => {
+                if !name.is_empty() {
+                    name.push('.');
+                }
+                name.push_str(key);
+            }
+            Step::Index(index) => name.push_str(&format!("[{}]", index)),
+        }
+    }
+    name
+}
+
+/// The name in the first pair of backticks, if the message starts with the given kind
+fn quoted(message: &str, kind: &str) -> Option<String> {
+    if !message.starts_with(kind) {
+        return None;
+    }
+    message.split('`').nth(1).map(|name| name.to_string())
+}
+
+/// Rewrites errors about unknown fields and variants to suggest the expected name closest
+/// to the unknown one, if they are close enough for a typo.
+/// Unknown fields at the top level are unknown sections.
+fn with_suggestion(error: &str, top_level: bool) -> String {
+    for kind in &["unknown field", "unknown variant"] {
+        if let Some(name) = quoted(error, kind) {
+            let described = if top_level && *kind == "unknown field" {
+                "unknown section"
+            } else {
+                *kind
+            };
+            let expected: Vec<String> = error
+                .splitn(2, ", expected")
+                .nth(1)
+                .map(|names| {
+                    names
+                        .split('`')
+                        .skip(1)
+                        .step_by(2)
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default();
+            return match closest(&name, &expected) {
+                Some(meant) => format!("{} `{}`, did you mean `{}`?", described, name, meant),
+                None => error.replacen(*kind, described, 1),
+            };
+        }
+    }
+    error.to_string()
+}
+
+fn closest<'c>(name: &str, candidates: &'c [String]) -> Option<&'c String> {
+    candidates
+        .iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= cmp::max(2, name.len() / 3))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance, i.e. how many characters have to be inserted, removed
+/// or replaced to turn one string into the other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let replaced = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            current.push(cmp::min(
+                replaced,
+                cmp::min(previous[j + 1], current[j]) + 1,
+            ));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The lines of a TOML source, with the paths of the tables whose headers are on them
+struct SourceLines<'a> {
+    lines: Vec<&'a str>,
+    headers: Vec<(String, usize)>,
+}
+
+impl<'a> SourceLines<'a> {
+    fn new(toml: &'a str) -> Self {
+        let lines: Vec<&str> = toml.lines().collect();
+        let mut headers = Vec::new();
+        let mut array_lengths: HashMap<String, usize> = HashMap::new();
+        for (index, line) in lines.iter().enumerate() {
+            let line = line.trim();
+            let (name, is_array) = if line.starts_with("[[") {
+                (line[2..].split("]]").next().unwrap_or_default(), true)
+            } else if line.starts_with('[') {
+                (line[1..].split(']').next().unwrap_or_default(), false)
+            } else {
+                continue;
+            };
+            let keys: Vec<&str> = name
+                .split('.')
+                .map(|key| key.trim().trim_matches('"'))
+                .collect();
+            let mut path = String::new();
+            for (position, key) in keys.iter().enumerate() {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(key);
+                if is_array && position + 1 == keys.len() {
+                    let length = {
+                        let length = array_lengths.entry(path.clone()).or_insert(0);
+                        *length += 1;
+                        *length
+                    };
+                    path = format!("{}[{}]", path, length - 1);
+                } else if let Some(length) = array_lengths.get(&path).cloned() {
+                    path = format!("{}[{}]", path, length - 1);
+                }
+            }
+            headers.push((path, index));
+        }
+        SourceLines { lines, headers }
+    }
+
+    /// The index of the line with the header of the table, or with the key,
+    /// that the path leads to. Inline tables are on the line of their key.
+    fn line_of(&self, path: &[Step]) -> Option<usize> {
+        let name = path_name(path);
+        let array_prefix = format!("{}[", name);
+        let header = self
+            .headers
+            .iter()
+            .find(|(header, _)| *header == name || header.starts_with(&array_prefix));
+        if let Some((_, index)) = header {
+            return Some(*index);
+        }
+        match path.split_last() {
+            None => None,
+            Some((Step::Index(_), parent)) => self.line_of(parent),
+            Some((Step::Key(key), table)) => {
+                self.key_line(table, key).or_else(|| self.line_of(table))
+            }
+        }
+    }
+
+    /// The index of the line that assigns the key in the table,
+    /// if the table has a header or is the top-level one
+    fn key_line(&self, table: &[Step], key: &str) -> Option<usize> {
+        let start = if table.is_empty() {
+            0
+        } else {
+            let name = path_name(table);
+            self.headers
+                .iter()
+                .find(|(header, _)| *header == name)
+                .map(|(_, index)| index + 1)?
+        };
+        let end = self
+            .headers
+            .iter()
+            .map(|(_, index)| *index)
+            .find(|index| *index >= start)
+            .unwrap_or_else(|| self.lines.len());
+        let quoted_key = format!("\"{}\"", key);
+        (start..end).find(|index| {
+            let line = self.lines[*index].trim_start();
+            [key, quoted_key.as_str()].iter().any(|name| {
+                line.starts_with(name) && line[name.len()..].trim_start().starts_with('=')
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::{closest, edit_distance, SourceLines};
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("key_file", "key_file"), 0);
+        assert_eq!(edit_distance("keyfile", "key_file"), 1);
+        assert_eq!(edit_distance("websockt", "websocket"), 1);
+        assert_eq!(edit_distance("", "dnas"), 4);
+    }
+
+    #[test]
+    fn test_closest() {
+        let candidates = vec!["id".to_string(), "key_file".to_string()];
+        assert_eq!(closest("keyfile", &candidates), Some(&candidates[1]));
+        assert_eq!(closest("passphrase", &candidates), None);
+    }
+
+    #[test]
+    fn test_table_headers() {
+        let toml = r#"
+[[interfaces]]
+id = "first"
+[interfaces.driver]
+type = "websocket"
+[[interfaces.instances]]
+id = "a"
+[[interfaces.instances]]
+id = "b"
+
+[[interfaces]]
+id = "second"
+[[interfaces.instances]]
+id = "c"
+"#;
+        let headers: Vec<(String, usize)> = SourceLines::new(toml).headers;
+        assert_eq!(
+            headers,
+            vec![
+                ("interfaces[0]".to_string(), 1),
+                ("interfaces[0].driver".to_string(), 3),
+                ("interfaces[0].instances[0]".to_string(), 5),
+                ("interfaces[0].instances[1]".to_string(), 7),
+                ("interfaces[1]".to_string(), 10),
+                ("interfaces[1].instances[0]".to_string(), 12),
+            ]
+        );
+    }
+}
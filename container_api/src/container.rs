@@ -1,6 +1,10 @@
 use crate::{
-    config::{Configuration, InterfaceConfiguration, InterfaceDriver, StorageConfiguration},
+    config::{
+        serialize_configuration, Configuration, InterfaceConfiguration, InterfaceDriver,
+        LoggerConfiguration, StorageConfiguration,
+    },
     error::HolochainInstanceError,
+    holochain::PendingWorkProbe,
     Holochain,
 };
 use holochain_cas_implementations::{
@@ -8,24 +12,48 @@ use holochain_cas_implementations::{
     eav::{file::EavFileStorage, memory::EavMemoryStorage},
     path::create_path_if_not_exists,
 };
-use holochain_core::context::Context;
-use holochain_core_types::{dna::Dna, error::HolochainError, json::JsonString};
-use tempfile::tempdir;
+use holochain_core::{
+    context::{Context, MAX_SLEEP_SECS, MAX_TOTAL_SLEEP_SECS},
+    nucleus::{bridges::Bridges, ZomeFnCall},
+    state::PendingWork,
+};
+use holochain_core_types::{
+    cas::{content::Address, storage::GcReport},
+    dna::Dna,
+    entry::addressing::dna_hash,
+    error::HolochainError,
+    json::JsonString,
+};
+use holochain_wasm_utils::api_serialization::CallContext;
 
-use holochain_core::{logger::Logger, persister::SimplePersister};
+#[cfg(feature = "test_fixtures")]
+use holochain_core::test_fixtures::test_agent;
+use holochain_core::{
+    agent::keys::EncryptionKeys,
+    logger::{format_state_dump, DebugLogger, Logger},
+    persister::SimplePersister,
+    replay::{replay, ActionLog, ActionRecorder},
+    runtime::Runtime,
+};
 use holochain_core_types::agent::AgentId;
 use std::{
     clone::Clone,
-    collections::HashMap,
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
     convert::TryFrom,
-    fs::File,
+    fs::{self, File},
+    hash::{Hash, Hasher},
     io::prelude::*,
-    sync::{Arc, Mutex, RwLock},
+    path::{Path, PathBuf},
+    sync::{mpsc::SyncSender, Arc, Mutex, RwLock},
     thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use interface::{ContainerApiDispatcher, InstanceMap, Interface};
+use interface::{ContainerApiDispatcher, InstanceMap, Interface, InterfaceConnections, RequestLog};
 use interface_impls;
+use scenario::published_addresses;
+use tls::TlsCertificate;
+use watchdog::{spawn_watchdog, WatchedInstance};
 
 /// Main representation of the container.
 /// Holds a `HashMap` of Holochain instances referenced by ID.
@@ -39,11 +67,61 @@ use interface_impls;
 /// Dna object for a given path string) has to be injected on creation.
 pub struct Container {
     pub instances: InstanceMap,
-    config: Configuration,
+    config: Arc<RwLock<Configuration>>,
+    config_path: Option<PathBuf>,
+    purge_tokens: Arc<Mutex<HashMap<String, String>>>,
     interface_threads: HashMap<String, InterfaceThreadHandle>,
+    interface_connections: InterfaceConnections,
+    /// The certificates of the interfaces that serve TLS, by interface id
+    tls_certificates: HashMap<String, TlsCertificate>,
     pub dna_loader: DnaLoader,
+    runtime: Runtime,
+    startup_order: Vec<String>,
+    startup_states: StartupStates,
+    published_before_start: PublishedBeforeStart,
+    // read without the instance locks, which running zome calls hold
+    pending_work_probes: HashMap<String, PendingWorkProbe>,
+    // restarts crashed instances, stops once dropped
+    watchdog: Option<SyncSender<()>>,
+}
+
+/// Where an instance is in the container's startup.
+/// Instances get initialized and started after the ones they depend on,
+/// see `Configuration::startup_order()`.
+/// Once an instance runs, its `InstanceStatus` tells whether it keeps running.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupState {
+    /// Waiting for the instances it depends on
+    Starting,
+    /// Running genesis
+    Initializing,
+    /// Genesis done, waiting to be started
+    Initialized,
+    Running,
+    /// Could not be created or started, with the reason
+    Errored(String),
+    /// Not created since the instance with the given id, which it depends on, failed
+    DependencyFailed(String),
 }
 
+impl StartupState {
+    pub fn failed(&self) -> bool {
+        match self {
+            StartupState::Errored(_) | StartupState::DependencyFailed(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Startup states of all instances of a container, shared with its interfaces
+pub type StartupStates = Arc<RwLock<HashMap<String, StartupState>>>;
+
+/// What each instance of a container published before all of them were started, like its
+/// genesis entries, which instances that started later never got, by instance id.
+/// Shared with its interfaces, see `wait_for_consistency()`.
+pub type PublishedBeforeStart = Arc<RwLock<HashMap<String, HashSet<Address>>>>;
+
 type InterfaceThreadHandle = thread::JoinHandle<Result<(), String>>;
 type DnaLoader = Arc<Box<FnMut(&String) -> Result<Dna, HolochainError> + Send>>;
 
@@ -52,93 +130,344 @@ pub static DEFAULT_NETWORK_CONFIG: &'static str = "{\"backend\":\"mock\"}";
 impl Container {
     /// Creates a new instance with the default DnaLoader that actually loads files.
     pub fn with_config(config: Configuration) -> Self {
+        let runtime = Runtime::new(config.runtime.threads);
         Container {
             instances: HashMap::new(),
             interface_threads: HashMap::new(),
-            config,
+            interface_connections: Arc::new(RwLock::new(HashMap::new())),
+            tls_certificates: HashMap::new(),
+            config: Arc::new(RwLock::new(config)),
+            config_path: None,
+            purge_tokens: Arc::new(Mutex::new(HashMap::new())),
             dna_loader: Arc::new(Box::new(Self::load_dna)),
+            runtime,
+            startup_order: Vec::new(),
+            startup_states: Arc::new(RwLock::new(HashMap::new())),
+            published_before_start: Arc::new(RwLock::new(HashMap::new())),
+            pending_work_probes: HashMap::new(),
+            watchdog: None,
         }
     }
 
+    /// Archiving, restoring and purging instances writes the configuration back to this file
+    pub fn set_config_path(&mut self, path: PathBuf) {
+        self.config_path = Some(path);
+    }
+
     pub fn start_all_interfaces(&mut self) {
-        self.interface_threads = self
-            .config
-            .interfaces
+        let interfaces = self.config.read().unwrap().interfaces.clone();
+        self.interface_threads = interfaces
             .iter()
             .map(|ic| (ic.id.clone(), self.spawn_interface_thread(ic.clone())))
             .collect()
     }
 
     pub fn start_interface_by_id(&mut self, id: String) -> Result<(), String> {
-        self.config
-            .interface_by_id(&id)
+        let maybe_config = self.config.read().unwrap().interface_by_id(&id);
+        maybe_config
             .ok_or(format!("Interface does not exist: {}", id))
             .and_then(|config| self.start_interface(&config))
     }
 
-    /// Starts all instances
+    /// Starts all instances that are not archived, each one after the instances it depends on
     pub fn start_all_instances(&mut self) -> Result<(), HolochainInstanceError> {
-        self.instances
-            .iter_mut()
-            .map(|(id, hc)| {
-                println!("Starting instance \"{}\"...", id);
-                hc.write().unwrap().start()
-            })
-            .collect::<Result<Vec<()>, _>>()
-            .map(|_| ())
+        for id in self.startup_order.iter() {
+            let hc = match self.instances.get(id) {
+                Some(hc) => hc,
+                // failed to initialize, see its startup state
+                None => continue,
+            };
+            if hc.read().unwrap().archived() {
+                continue;
+            }
+            println!("Starting instance \"{}\"...", id);
+            match hc.write().unwrap().start() {
+                Ok(()) => self.set_startup_state(id, StartupState::Running),
+                Err(error) => {
+                    self.set_startup_state(id, StartupState::Errored(error.to_string()));
+                    return Err(error);
+                }
+            }
+        }
+        let mut published_before_start = self.published_before_start.write().unwrap();
+        for (id, hc) in self.instances.iter() {
+            if let Ok(state) = hc.read().unwrap().state() {
+                published_before_start.insert(id.clone(), published_addresses(&state));
+            }
+        }
+        Ok(())
     }
 
-    /// Stops all instances
+    /// What each instance published before all of them were started, by instance id
+    pub fn published_before_start(&self) -> PublishedBeforeStart {
+        self.published_before_start.clone()
+    }
+
+    /// Returns where the given instance is in the container's startup
+    pub fn startup_state(&self, instance_id: &str) -> Option<StartupState> {
+        self.startup_states
+            .read()
+            .unwrap()
+            .get(instance_id)
+            .cloned()
+    }
+
+    fn set_startup_state(&self, instance_id: &str, state: StartupState) {
+        self.startup_states
+            .write()
+            .unwrap()
+            .insert(instance_id.to_string(), state);
+    }
+
+    /// Stops all instances that are not archived, archived ones are stopped already.
+    /// Their pending commits get flushed first, so they are on the disk once this returns.
+    /// Crashed instances have no action loop left to flush them, they just get stopped.
     pub fn stop_all_instances(&mut self) -> Result<(), HolochainInstanceError> {
         self.instances
             .iter_mut()
+            .filter(|(_, hc)| !hc.read().unwrap().archived())
             .map(|(id, hc)| {
                 println!("Stopping instance \"{}\"...", id);
-                hc.write().unwrap().stop()
+                let mut hc = hc.write().unwrap();
+                if hc.crash_report().is_none() {
+                    hc.flush()?;
+                }
+                hc.stop()
             })
             .collect::<Result<Vec<()>, _>>()
             .map(|_| ())
     }
 
+    /// Starts validating the local DHT shard of the given instance again in the background.
+    /// The progress shows up in the instance's status.
+    pub fn revalidate_shard(&self, instance_id: &str) -> Result<(), String> {
+        self.instances
+            .get(instance_id)
+            .ok_or(format!("Instance does not exist: {}", instance_id))
+            .and_then(|hc| {
+                hc.write()
+                    .unwrap()
+                    .revalidate_shard()
+                    .map_err(|e| e.to_string())
+            })
+    }
+
+    /// Cancels a running revalidation of the given instance's local DHT shard
+    pub fn cancel_revalidation(&self, instance_id: &str) -> Result<(), String> {
+        self.instances
+            .get(instance_id)
+            .ok_or(format!("Instance does not exist: {}", instance_id))
+            .and_then(|hc| {
+                hc.read()
+                    .unwrap()
+                    .cancel_revalidation()
+                    .map_err(|e| e.to_string())
+            })
+    }
+
+    /// Removes the content the given instance's source chain and local DHT shard
+    /// don't refer to anymore from its storage
+    pub fn collect_garbage(&self, instance_id: &str) -> Result<GcReport, String> {
+        self.instances
+            .get(instance_id)
+            .ok_or(format!("Instance does not exist: {}", instance_id))
+            .and_then(|hc| {
+                hc.read()
+                    .unwrap()
+                    .collect_garbage()
+                    .map_err(|e| e.to_string())
+            })
+    }
+
+    /// Returns the zome calls, validations and network requests the given instance is
+    /// waiting on. Works while zome calls keep the instance busy.
+    pub fn pending(&self, instance_id: &str) -> Result<PendingWork, String> {
+        self.pending_work_probes
+            .get(instance_id)
+            .ok_or(format!("Instance does not exist: {}", instance_id))
+            .and_then(|probe| probe.pending().map_err(|e| e.to_string()))
+    }
+
+    /// Calls a zome function of the callee instance on behalf of the caller instance.
+    /// Only works if a bridge between the two is configured.
+    /// The called function sees a CallContext::Bridge with the DNA hash of the caller.
+    pub fn call_bridge(
+        &self,
+        caller_id: &str,
+        callee_id: &str,
+        zome: &str,
+        cap: &str,
+        fn_name: &str,
+        params: &str,
+    ) -> Result<JsonString, String> {
+        if !self
+            .config
+            .read()
+            .unwrap()
+            .bridges
+            .iter()
+            .any(|bridge| bridge.caller_id == caller_id && bridge.callee_id == callee_id)
+        {
+            return Err(format!(
+                "No bridge from instance {} to instance {}",
+                caller_id, callee_id
+            ));
+        }
+        let caller = self
+            .instances
+            .get(caller_id)
+            .ok_or(format!("Instance does not exist: {}", caller_id))?;
+        let callee = self
+            .instances
+            .get(callee_id)
+            .ok_or(format!("Instance does not exist: {}", callee_id))?;
+        let caller_dna = caller
+            .read()
+            .unwrap()
+            .state()
+            .map_err(|e| e.to_string())?
+            .nucleus()
+            .dna()
+            .ok_or(format!("Instance {} has no DNA", caller_id))?;
+        let call_context = CallContext::Bridge {
+            caller_instance_dna_hash: dna_hash(&caller_dna),
+        };
+        callee
+            .write()
+            .unwrap()
+            .call_with_context(zome, cap, fn_name, params, call_context)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Stops the given instance and marks it archived in the configuration.
+    /// Its storage is kept, so `restore_instance()` continues its chain.
+    pub fn archive_instance(&self, instance_id: &str) -> Result<(), String> {
+        self.instance_archive()
+            .archive(instance_id, self.get_instance(instance_id)?)
+    }
+
+    /// Starts an archived instance again from its retained storage
+    pub fn restore_instance(&self, instance_id: &str) -> Result<(), String> {
+        self.instance_archive()
+            .restore(instance_id, self.get_instance(instance_id)?)
+    }
+
+    /// Returns the token `purge_instance()` needs to delete the given archived instance
+    pub fn request_purge(&self, instance_id: &str) -> Result<String, String> {
+        self.instance_archive()
+            .request_purge(instance_id, self.get_instance(instance_id)?)
+    }
+
+    /// Deletes the storage of the given archived instance and removes it from the container
+    /// and its configuration. Needs the token returned by `request_purge()`.
+    pub fn purge_instance(
+        &mut self,
+        instance_id: &str,
+        confirmation_token: &str,
+    ) -> Result<(), String> {
+        self.instance_archive().purge(
+            instance_id,
+            self.get_instance(instance_id)?,
+            confirmation_token,
+        )?;
+        self.instances.remove(instance_id);
+        self.pending_work_probes.remove(instance_id);
+        self.startup_order.retain(|id| id != instance_id);
+        self.startup_states.write().unwrap().remove(instance_id);
+        Ok(())
+    }
+
+    fn get_instance(&self, instance_id: &str) -> Result<&Arc<RwLock<Holochain>>, String> {
+        self.instances
+            .get(instance_id)
+            .ok_or(format!("Instance does not exist: {}", instance_id))
+    }
+
+    fn instance_archive(&self) -> InstanceArchive {
+        InstanceArchive {
+            config: self.config.clone(),
+            config_path: self.config_path.clone(),
+            purge_tokens: self.purge_tokens.clone(),
+        }
+    }
+
     /// Stop and clear all instances
     pub fn shutdown(&mut self) -> Result<(), HolochainInstanceError> {
+        self.watchdog = None;
         self.stop_all_instances()?;
         self.instances = HashMap::new();
+        self.pending_work_probes = HashMap::new();
         Ok(())
     }
 
     /// Tries to create all instances configured in the given Configuration object.
     /// Calls `Configuration::check_consistency()` first and clears `self.instances`.
+    /// Instances get created in startup order, so genesis of an instance only runs once the
+    /// instances it depends on are initialized. Instances whose dependencies failed don't get
+    /// created at all, their startup state tells which dependency failed.
+    /// The TLS certificates of the interfaces get loaded before any instance gets created.
+    /// All created instances get watched by the watchdog, which restarts them after a crash
+    /// as their restart policy says, see `watchdog`.
     pub fn load_config(&mut self, config: &Configuration) -> Result<(), String> {
         let _ = config.check_consistency()?;
         self.shutdown().map_err(|e| e.to_string())?;
+        self.tls_certificates = load_tls_certificates(config)?;
         let default_network = DEFAULT_NETWORK_CONFIG.to_string();
-        let id_instance_pairs: Vec<_> = config
-            .instance_ids()
-            .clone()
-            .into_iter()
-            .map(|id| {
-                (
-                    id.clone(),
-                    instantiate_from_config(&id, config, &mut self.dna_loader, &default_network),
-                )
-            })
+        self.startup_order = config.startup_order()?;
+        *self.startup_states.write().unwrap() = self
+            .startup_order
+            .iter()
+            .map(|id| (id.clone(), StartupState::Starting))
             .collect();
 
-        let errors: Vec<_> = id_instance_pairs
-            .into_iter()
-            .filter_map(|(id, maybe_holochain)| match maybe_holochain {
+        let mut errors = Vec::new();
+        let mut watched = Vec::new();
+        for id in self.startup_order.clone() {
+            let failed_dependency =
+                config
+                    .instance_dependencies(&id)
+                    .into_iter()
+                    .find(|dependency| {
+                        self.startup_state(dependency)
+                            .map(|state| state.failed())
+                            .unwrap_or(false)
+                    });
+            if let Some(dependency) = failed_dependency {
+                self.set_startup_state(&id, StartupState::DependencyFailed(dependency));
+                continue;
+            }
+
+            self.set_startup_state(&id, StartupState::Initializing);
+            match instantiate_from_config(
+                &id,
+                config,
+                &mut self.dna_loader,
+                &default_network,
+                &self.runtime,
+                &self.instances,
+            ) {
                 Ok(holochain) => {
-                    self.instances
-                        .insert(id.clone(), Arc::new(RwLock::new(holochain)));
-                    None
+                    self.pending_work_probes
+                        .insert(id.clone(), holochain.pending_work_probe());
+                    let holochain = Arc::new(RwLock::new(holochain));
+                    let restart_policy = config
+                        .instance_by_id(&id)
+                        .map(|instance| instance.restart)
+                        .unwrap_or_default();
+                    watched.push(WatchedInstance::new(&id, &holochain, restart_policy));
+                    self.instances.insert(id.clone(), holochain);
+                    self.set_startup_state(&id, StartupState::Initialized);
                 }
-                Err(error) => Some(format!(
-                    "Error while trying to create instance \"{}\": {}",
-                    id, error
-                )),
-            })
-            .collect();
+                Err(error) => {
+                    self.set_startup_state(&id, StartupState::Errored(error.clone()));
+                    errors.push(format!(
+                        "Error while trying to create instance \"{}\": {}",
+                        id, error
+                    ));
+                }
+            }
+        }
+        self.watchdog = Some(spawn_watchdog(watched));
 
         if errors.len() == 0 {
             Ok(())
@@ -147,6 +476,35 @@ impl Container {
         }
     }
 
+    /// Loads the TLS certificates of the interfaces anew, from the files the given
+    /// configuration names, e.g. after they got renewed. Connections that are open keep the
+    /// certificate they got, new ones get the new one. Interfaces whose files can't be
+    /// loaded keep their certificate. Switching TLS on or off for an interface takes a restart.
+    pub fn reload_tls_certificates(&self, config: &Configuration) -> Result<(), String> {
+        let errors: Vec<String> = config
+            .interfaces
+            .iter()
+            .filter_map(|interface| {
+                match (
+                    self.tls_certificates.get(&interface.id),
+                    interface.driver.tls(),
+                ) {
+                    (Some(certificate), Some(tls)) => certificate.reload(tls).err(),
+                    (None, None) => None,
+                    _ => Some(format!(
+                        "Interface \"{}\": TLS can only be switched on or off with a restart",
+                        interface.id
+                    )),
+                }
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
     fn start_interface(&mut self, config: &InterfaceConfiguration) -> Result<(), String> {
         if self.interface_threads.contains_key(&config.id) {
             return Err(format!("Interface {} already started!", config.id));
@@ -176,7 +534,38 @@ impl Container {
             .filter(|(id, _)| instance_ids.contains(&id))
             .map(|(id, val)| (id.clone(), val.clone()))
             .collect();
-        ContainerApiDispatcher::new(&self.config, instance_subset)
+        let dispatcher = ContainerApiDispatcher::new(
+            &self.config.read().unwrap(),
+            instance_subset,
+            &interface_config.instances,
+            &interface_config.id,
+        );
+        self.interface_connections
+            .write()
+            .unwrap()
+            .insert(interface_config.id.clone(), dispatcher.connections());
+        let dispatcher = dispatcher
+            .with_startup_states(self.startup_states.clone())
+            .with_runtime(self.runtime.clone());
+        let dispatcher = match interface_config.request_log {
+            Some(ref request_log) => dispatcher.with_request_log(RequestLog::new(
+                create_logger(&request_log.logger),
+                &request_log.log_params_for,
+            )),
+            None => dispatcher,
+        };
+        let dispatcher = if self.config.read().unwrap().test_mode {
+            dispatcher.with_test_api(self.published_before_start.clone())
+        } else {
+            dispatcher
+        };
+        if interface_config.admin {
+            dispatcher
+                .with_admin_api(self.instance_archive())
+                .with_connections_admin_api(self.interface_connections.clone())
+        } else {
+            dispatcher
+        }
     }
 
     fn spawn_interface_thread(
@@ -184,8 +573,9 @@ impl Container {
         interface_config: InterfaceConfiguration,
     ) -> InterfaceThreadHandle {
         let dispatcher = self.make_dispatcher(&interface_config);
+        let tls = self.tls_certificates.get(&interface_config.id).cloned();
         thread::spawn(move || {
-            let iface = make_interface(&interface_config);
+            let iface = make_interface(&interface_config, tls);
             iface.run(dispatcher)
         })
     }
@@ -202,29 +592,94 @@ impl<'a> TryFrom<&'a Configuration> for Container {
     }
 }
 
+/// The certificates of the interfaces of the configuration that serve TLS, by interface id
+fn load_tls_certificates(
+    config: &Configuration,
+) -> Result<HashMap<String, TlsCertificate>, String> {
+    config
+        .interfaces
+        .iter()
+        .filter_map(|interface| {
+            interface.driver.tls().map(|tls| {
+                TlsCertificate::load(&interface.id, tls)
+                    .map(|certificate| (interface.id.clone(), certificate))
+            })
+        })
+        .collect()
+}
+
 /// This can eventually be dependency injected for third party Interface definitions
 fn make_interface(
     interface_config: &InterfaceConfiguration,
+    tls: Option<TlsCertificate>,
 ) -> Box<Interface<ContainerApiDispatcher>> {
     match interface_config.driver {
-        InterfaceDriver::Websocket { port } => {
-            Box::new(interface_impls::websocket::WebsocketInterface::new(port))
+        InterfaceDriver::Websocket { port, .. } => {
+            let mut interface = interface_impls::websocket::WebsocketInterface::new(port);
+            if let Some(minutes) = interface_config.idle_timeout {
+                interface = interface.with_idle_timeout(Duration::from_secs(minutes * 60));
+            }
+            if let Some(certificate) = tls {
+                interface = interface.with_tls(certificate);
+            }
+            Box::new(interface)
+        }
+        InterfaceDriver::Http { port, .. } => {
+            let interface = interface_impls::http::HttpInterface::new(port);
+            match tls {
+                Some(certificate) => Box::new(interface.with_tls(certificate)),
+                None => Box::new(interface),
+            }
         }
         _ => unimplemented!(),
     }
 }
 
+/// The instances an instance is bridged to, by the handles of the bridges.
+/// Set on the context of the caller, so its zomes can call them with `hdk::call_bridge()`.
+struct InstanceBridges {
+    callees: HashMap<String, Arc<RwLock<Holochain>>>,
+}
+
+impl Bridges for InstanceBridges {
+    fn call(&self, handle: &str, call: ZomeFnCall) -> Result<JsonString, HolochainError> {
+        let callee = self
+            .callees
+            .get(handle)
+            .ok_or_else(|| HolochainError::BridgeNotPresent(handle.to_string()))?;
+        callee
+            .write()
+            .unwrap()
+            .call_with_context(
+                &call.zome_name,
+                &call.cap_name,
+                &call.fn_name,
+                &String::from(call.parameters),
+                call.call_context,
+            )
+            .map_err(|error| match error {
+                HolochainInstanceError::InternalFailure(error) => error,
+                error => HolochainError::ErrorGeneric(error.to_string()),
+            })
+    }
+}
+
 /// Creates one specific Holochain instance from a given Configuration,
 /// id string and DnaLoader.
+/// The instances it is bridged to under a handle have to be in `instances` already,
+/// which they are when instances get created in startup order.
 pub fn instantiate_from_config(
     id: &String,
     config: &Configuration,
     dna_loader: &mut DnaLoader,
     default_network_config: &String,
+    runtime: &Runtime,
+    instances: &InstanceMap,
 ) -> Result<Holochain, String> {
     let _ = config.check_consistency()?;
 
     config
+        .effective()
         .instance_by_id(&id)
         .ok_or(String::from("Instance not found in config"))
         .and_then(|instance_config| {
@@ -236,27 +691,234 @@ pub fn instantiate_from_config(
                     dna_config.file
                 ))
             })?;
+            let dna = instance_config.effective_dna(dna);
 
             let network_config = instance_config
                 .network
                 .unwrap_or(default_network_config.to_owned())
                 .into();
 
-            let context: Context = match instance_config.storage {
+            let logger = create_logger(&instance_config.logger.unwrap_or_default());
+
+            // check_consistency() made sure every instance has storage
+            let mut context: Context = match instance_config.storage.unwrap() {
                 StorageConfiguration::File { path } => {
-                    create_file_context(&agent_config.id, &path, network_config)
+                    create_file_context(&agent_config.id, &path, network_config, logger)
                         .map_err(|hc_err| format!("Error creating context: {}", hc_err.to_string()))
                 }
                 StorageConfiguration::Memory => {
-                    create_memory_context(&agent_config.id, network_config)
+                    create_memory_context(&agent_config.id, network_config, logger)
                         .map_err(|hc_err| format!("Error creating context: {}", hc_err.to_string()))
                 }
             }?;
+            #[cfg(feature = "test_fixtures")]
+            use_test_agent_keys(&mut context, config, &agent_config.id);
+            context.set_runtime(runtime.clone());
+            let timeouts = instance_config.timeouts.unwrap_or_default();
+            if let Some(timeout) = timeouts.validation_dependencies {
+                context.set_validation_dependency_timeout(Duration::from_secs(timeout));
+            }
+            context.set_max_sleep(
+                Duration::from_secs(timeouts.max_sleep.unwrap_or(MAX_SLEEP_SECS)),
+                Duration::from_secs(timeouts.max_total_sleep.unwrap_or(MAX_TOTAL_SLEEP_SECS)),
+            );
+            if let Some(threshold) = timeouts.slow_call_threshold_ms {
+                context.set_slow_call_threshold(Duration::from_millis(threshold));
+            }
+            if let Some(max_wasm_memory_mb) = instance_config.max_wasm_memory_mb {
+                context.set_max_wasm_memory_mb(max_wasm_memory_mb);
+            }
+            context.set_audit_host_calls(instance_config.audit_host_calls);
+            if let Some(genesis_params) = instance_config.genesis_params {
+                context.set_genesis_params(genesis_params);
+            }
+            let callees = config
+                .bridges
+                .iter()
+                .filter(|bridge| &bridge.caller_id == id)
+                .filter_map(|bridge| {
+                    let handle = bridge.handle.clone()?;
+                    let callee = instances.get(&bridge.callee_id)?;
+                    Some((handle, callee.clone()))
+                })
+                .collect();
+            context.set_bridges(Arc::new(InstanceBridges { callees }));
+            if let Some(path) = instance_config.record_actions {
+                let recorder =
+                    ActionRecorder::create(&path, &context.agent_id).map_err(|hc_err| {
+                        format!(
+                            "Error creating action log \"{}\": {}",
+                            path,
+                            hc_err.to_string()
+                        )
+                    })?;
+                context.set_action_recorder(recorder);
+            }
 
-            Holochain::new(dna, Arc::new(context)).map_err(|hc_err| hc_err.to_string())
+            let mut holochain =
+                Holochain::restore(dna, Arc::new(context)).map_err(|hc_err| hc_err.to_string())?;
+            if let Some(interval) = instance_config.gc_interval {
+                holochain.set_gc_interval(Duration::from_secs(interval));
+            }
+            if let Some(size) = instance_config.signal_buffer_size {
+                holochain.set_signal_buffer_size(size);
+            }
+            if instance_config.archived {
+                holochain.archive().map_err(|hc_err| hc_err.to_string())?;
+            }
+            Ok(holochain)
         })
 }
 
+/// Archives, restores and purges instances while the container runs.
+/// Every admin interface gets its own copy, but they all change the same configuration,
+/// which gets written back to the container's config file, if there is one, after each change.
+#[derive(Clone)]
+pub struct InstanceArchive {
+    config: Arc<RwLock<Configuration>>,
+    config_path: Option<PathBuf>,
+    purge_tokens: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl InstanceArchive {
+    /// An archive over the given configuration that does not get saved anywhere
+    pub fn new(config: Arc<RwLock<Configuration>>) -> Self {
+        InstanceArchive {
+            config,
+            config_path: None,
+            purge_tokens: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Stops the given instance, saves its chain and marks it archived in the configuration
+    pub fn archive(&self, instance_id: &str, hc: &Arc<RwLock<Holochain>>) -> Result<(), String> {
+        hc.write().unwrap().archive().map_err(|e| e.to_string())?;
+        self.set_archived(instance_id, true)
+    }
+
+    /// Starts the given archived instance again
+    pub fn restore(&self, instance_id: &str, hc: &Arc<RwLock<Holochain>>) -> Result<(), String> {
+        let mut hc = hc.write().unwrap();
+        if !hc.archived() {
+            return Err(format!("Instance {} is not archived", instance_id));
+        }
+        self.set_archived(instance_id, false)?;
+        hc.unarchive();
+        hc.start().map_err(|e| e.to_string())
+    }
+
+    /// Hands out a new confirmation token for purging the given archived instance
+    pub fn request_purge(
+        &self,
+        instance_id: &str,
+        hc: &Arc<RwLock<Holochain>>,
+    ) -> Result<String, String> {
+        if !hc.read().unwrap().archived() {
+            return Err(format!(
+                "Only archived instances can be purged, {} is not archived",
+                instance_id
+            ));
+        }
+        let mut hasher = DefaultHasher::new();
+        instance_id.hash(&mut hasher);
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| (time.as_secs(), time.subsec_nanos()))
+            .unwrap_or((0, 0))
+            .hash(&mut hasher);
+        let token = format!("{:x}", hasher.finish());
+        self.purge_tokens
+            .lock()
+            .unwrap()
+            .insert(instance_id.to_string(), token.clone());
+        Ok(token)
+    }
+
+    /// Deletes the storage directory of the given archived instance and removes it from the
+    /// configuration, together with the interface references and bridges that mention it.
+    /// Each token from `request_purge()` can be used only once.
+    pub fn purge(
+        &self,
+        instance_id: &str,
+        hc: &Arc<RwLock<Holochain>>,
+        confirmation_token: &str,
+    ) -> Result<(), String> {
+        let expected_token = self.purge_tokens.lock().unwrap().remove(instance_id);
+        if expected_token.as_ref().map(String::as_str) != Some(confirmation_token) {
+            return Err(format!(
+                "Wrong confirmation token for purging instance {}",
+                instance_id
+            ));
+        }
+        if !hc.read().unwrap().archived() {
+            return Err(format!(
+                "Only archived instances can be purged, {} is not archived",
+                instance_id
+            ));
+        }
+        let storage = self
+            .config
+            .read()
+            .unwrap()
+            .effective()
+            .instance_by_id(instance_id)
+            .and_then(|instance| instance.storage);
+        if let Some(StorageConfiguration::File { path }) = storage {
+            if Path::new(&path).exists() {
+                fs::remove_dir_all(&path).map_err(|e| e.to_string())?;
+            }
+        }
+        {
+            let mut config = self.config.write().unwrap();
+            config
+                .instances
+                .retain(|instance| instance.id != instance_id);
+            for interface in config.interfaces.iter_mut() {
+                interface
+                    .instances
+                    .retain(|reference| reference.id != instance_id);
+            }
+            config.bridges.retain(|bridge| {
+                bridge.caller_id != instance_id && bridge.callee_id != instance_id
+            });
+        }
+        self.save_config()
+    }
+
+    fn set_archived(&self, instance_id: &str, archived: bool) -> Result<(), String> {
+        {
+            let mut config = self.config.write().unwrap();
+            let instance = config
+                .instances
+                .iter_mut()
+                .find(|instance| instance.id == instance_id)
+                .ok_or(format!("Instance does not exist: {}", instance_id))?;
+            instance.archived = archived;
+        }
+        self.save_config()
+    }
+
+    /// Writes the configuration back to the file it came from.
+    /// Only that file gets rewritten, not the ones it includes.
+    fn save_config(&self) -> Result<(), String> {
+        match self.config_path {
+            Some(ref path) => {
+                let config = self.config.read().unwrap();
+                if !config.included_from.is_empty() {
+                    println!(
+                        "Warning: only saving {}, changes to what it includes from other files don't get saved",
+                        path.display()
+                    );
+                }
+                let toml = serialize_configuration(&config.without_included())
+                    .map_err(|e| e.to_string())?;
+                fs::write(path, toml).map_err(|e| e.to_string())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 struct NullLogger {}
 
@@ -264,32 +926,68 @@ impl Logger for NullLogger {
     fn log(&mut self, _msg: String) {}
 }
 
+/// Only the "debug" logger type produces any output so far
+fn create_logger(logger_config: &LoggerConfiguration) -> Arc<Mutex<Logger>> {
+    match logger_config.logger_type.as_ref() {
+        "debug" => Arc::new(Mutex::new(DebugLogger::new())),
+        _ => Arc::new(Mutex::new(NullLogger {})),
+    }
+}
+
+/// Agents don't have real keys yet, so every agent of the configuration gets fake ones
+/// derived from its ID. Instances of different agents thereby have chains and network
+/// identities of their own, also when they run the same DNA.
+/// Their encryption keys work, but anyone knowing the agent's ID can derive the secret key.
+fn fake_agent_keys(agent_config_id: &str) -> (AgentId, EncryptionKeys) {
+    // the fake key gets decoded from the ID, which therefore has to be short base64
+    let key_seed: String = agent_config_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .take(80)
+        .collect();
+    let mut keys = EncryptionKeys::generate_fake(agent_config_id);
+    (keys.fake_agent_id(&key_seed), keys)
+}
+
+/// Replaces the fake keys of the agent with the ones of `test_agent()`, if the configuration
+/// makes it a test agent, see `Configuration::with_test_agent()`
+#[cfg(feature = "test_fixtures")]
+fn use_test_agent_keys(context: &mut Context, config: &Configuration, agent_config_id: &str) {
+    if config.test_agents.iter().any(|id| id == agent_config_id) {
+        let test_agent = test_agent(agent_config_id);
+        context.agent_id = test_agent.agent_id;
+        context.set_encryption_keys(test_agent.keys);
+        context.set_signing_keys(test_agent.signing_keys);
+    }
+}
+
 fn create_memory_context(
-    _: &String,
+    agent_config_id: &String,
     network_config: JsonString,
+    logger: Arc<Mutex<Logger>>,
 ) -> Result<Context, HolochainError> {
-    let agent = AgentId::generate_fake("c+bob");
-    let tempdir = tempdir().unwrap();
-    let file_storage = Arc::new(RwLock::new(
-        FilesystemStorage::new(tempdir.path().to_str().unwrap()).unwrap(),
-    ));
+    let (agent, keys) = fake_agent_keys(agent_config_id);
+    let memory_storage = Arc::new(RwLock::new(MemoryStorage::new()));
 
-    Context::new(
+    let mut context = Context::new(
         agent,
-        Arc::new(Mutex::new(NullLogger {})),
-        Arc::new(Mutex::new(SimplePersister::new(file_storage.clone()))),
-        Arc::new(RwLock::new(MemoryStorage::new())),
+        logger,
+        Arc::new(Mutex::new(SimplePersister::new(memory_storage.clone()))),
+        memory_storage,
         Arc::new(RwLock::new(EavMemoryStorage::new())),
         network_config,
-    )
+    )?;
+    context.set_encryption_keys(keys);
+    Ok(context)
 }
 
 fn create_file_context(
-    _: &String,
+    agent_config_id: &String,
     path: &String,
     network_config: JsonString,
+    logger: Arc<Mutex<Logger>>,
 ) -> Result<Context, HolochainError> {
-    let agent = AgentId::generate_fake("c+bob");
+    let (agent, keys) = fake_agent_keys(agent_config_id);
     let cas_path = format!("{}/cas", path);
     let eav_path = format!("{}/eav", path);
     create_path_if_not_exists(&cas_path)?;
@@ -297,21 +995,81 @@ fn create_file_context(
 
     let file_storage = Arc::new(RwLock::new(FilesystemStorage::new(&cas_path)?));
 
-    Context::new(
+    let mut context = Context::new(
         agent,
-        Arc::new(Mutex::new(NullLogger {})),
+        logger,
         Arc::new(Mutex::new(SimplePersister::new(file_storage.clone()))),
         file_storage.clone(),
         Arc::new(RwLock::new(EavFileStorage::new(eav_path)?)),
         network_config,
-    )
+    )?;
+    context.set_encryption_keys(keys);
+    Ok(context)
+}
+
+/// Copies the directory at `from` with everything in it to `to`
+fn copy_dir(from: &Path, to: &Path) -> Result<(), HolochainError> {
+    fs::create_dir_all(to)?;
+    for dir_entry in fs::read_dir(from)? {
+        let dir_entry = dir_entry?;
+        let target = to.join(dir_entry.file_name());
+        if dir_entry.file_type()?.is_dir() {
+            copy_dir(&dir_entry.path(), &target)?;
+        } else {
+            fs::copy(dir_entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Replays the action log an instance recorded (see `record_actions` in its configuration)
+/// on a copy of its file storage as it was before the recording started.
+/// The copy gets made in `work_path` and changes just like the original storage did,
+/// up to the action with index `until`, or to the end of the log.
+/// Returns the state dump of the replayed instance.
+pub fn replay_instance(
+    log_path: &Path,
+    storage_path: &Path,
+    work_path: &Path,
+    until: Option<usize>,
+) -> Result<String, HolochainError> {
+    let log = ActionLog::read(log_path)?;
+    copy_dir(storage_path, work_path)?;
+    let cas_path = work_path.join("cas");
+    let eav_path = work_path.join("eav");
+    fs::create_dir_all(&cas_path)?;
+    fs::create_dir_all(&eav_path)?;
+
+    let file_storage = Arc::new(RwLock::new(FilesystemStorage::new(
+        &cas_path.to_string_lossy(),
+    )?));
+    let context = Context::new(
+        log.header.agent_id.clone(),
+        create_logger(&LoggerConfiguration::default()),
+        Arc::new(Mutex::new(SimplePersister::new(file_storage.clone()))),
+        file_storage,
+        Arc::new(RwLock::new(EavFileStorage::new(
+            eav_path.to_string_lossy().to_string(),
+        )?)),
+        JsonString::from("{\"backend\": \"none\"}"),
+    )?;
+    let state = replay(context, &log, until)?;
+    Ok(format_state_dump(&state))
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::config::load_configuration;
+    use crate::{
+        config::{
+            load_configuration, AgentConfiguration, Bridge, InstanceConfiguration,
+            PropertiesOverride,
+        },
+        holochain::{tests::example_api_wasm, InstanceStatus},
+    };
+    use holochain_core::context::mock_network_config;
     use std::{fs::File, io::Write};
+    use test_utils::{create_test_cap_with_fn_name, create_test_dna_with_cap};
 
     use tempfile::tempdir;
 
@@ -417,6 +1175,8 @@ pub mod tests {
             &config,
             &mut test_dna_loader(),
             &default_network,
+            &Runtime::default(),
+            &InstanceMap::new(),
         );
 
         assert_eq!(maybe_holochain.err(), None);
@@ -481,8 +1241,435 @@ pub mod tests {
         let io = dispatcher.io;
 
         let request = r#"{"jsonrpc": "2.0", "method": "info/instances", "params": null, "id": 1}"#;
-        let response = r#"{"jsonrpc":"2.0","result":"{\"app spec instance\":{\"id\":\"app spec instance\",\"dna\":\"app spec rust\",\"agent\":\"test agent\",\"logger\":{\"type\":\"simple\",\"file\":\"app_spec.log\"},\"storage\":{\"type\":\"memory\"},\"network\":null}}","id":1}"#;
+        let response = r#"{"jsonrpc":"2.0","result":"{\"app spec instance\":{\"id\":\"app spec instance\",\"dna\":\"app spec rust\",\"agent\":\"test agent\",\"logger\":{\"type\":\"simple\",\"file\":\"app_spec.log\"},\"storage\":{\"type\":\"memory\"},\"network\":null,\"archived\":false}}","id":1}"#;
 
         assert_eq!(io.handle_request_sync(request), Some(response.to_owned()));
     }
+
+    #[test]
+    fn test_container_revalidate_shard() {
+        let config = load_configuration::<Configuration>(&test_toml()).unwrap();
+        let mut container = Container::with_config(config.clone());
+        container.dna_loader = test_dna_loader();
+        container.load_config(&config).unwrap();
+
+        assert!(container.revalidate_shard("app spec instance").is_err());
+        container.start_all_instances().unwrap();
+        assert_eq!(container.revalidate_shard("app spec instance"), Ok(()));
+        assert_eq!(
+            container.revalidate_shard("unknown"),
+            Err("Instance does not exist: unknown".to_string())
+        );
+        container.stop_all_instances().unwrap();
+    }
+
+    #[test]
+    fn test_container_pending() {
+        let config = load_configuration::<Configuration>(&test_toml()).unwrap();
+        let mut container = Container::with_config(config.clone());
+        container.dna_loader = test_dna_loader();
+        container.load_config(&config).unwrap();
+        container.start_all_instances().unwrap();
+
+        let pending = container.pending("app spec instance").unwrap();
+        assert!(pending.zome_calls.is_empty());
+        assert_eq!(
+            container.pending("unknown"),
+            Err("Instance does not exist: unknown".to_string())
+        );
+        container.stop_all_instances().unwrap();
+    }
+
+    #[test]
+    fn test_container_call_bridge() {
+        let mut config = load_configuration::<Configuration>(&test_toml()).unwrap();
+        let mut callee = config.instances[0].clone();
+        callee.id = "bridge callee".to_string();
+        config.instances.push(callee);
+        config.bridges.push(Bridge {
+            caller_id: "app spec instance".to_string(),
+            callee_id: "bridge callee".to_string(),
+            handle: None,
+        });
+        let mut container = Container::with_config(config.clone());
+        let capability = create_test_cap_with_fn_name("call_context_test");
+        let dna =
+            create_test_dna_with_cap("test_zome", "test_cap", &capability, &example_api_wasm());
+        let loaded_dna = dna.clone();
+        container.dna_loader = Arc::new(Box::new(move |_path: &String| Ok(loaded_dna.clone()))
+            as Box<FnMut(&String) -> Result<Dna, HolochainError> + Send>);
+        container.load_config(&config).unwrap();
+        // the callee gets initialized and started before its caller
+        assert_eq!(
+            container.startup_order,
+            vec!["bridge callee".to_string(), "app spec instance".to_string()]
+        );
+        assert_eq!(
+            container.startup_state("app spec instance"),
+            Some(StartupState::Initialized)
+        );
+        container.start_all_instances().unwrap();
+        assert_eq!(
+            container.startup_state("app spec instance"),
+            Some(StartupState::Running)
+        );
+        assert_eq!(
+            container.startup_state("bridge callee"),
+            Some(StartupState::Running)
+        );
+
+        let result = container.call_bridge(
+            "app spec instance",
+            "bridge callee",
+            "test_zome",
+            "test_cap",
+            "call_context_test",
+            "{}",
+        );
+        assert_eq!(
+            result,
+            Ok(JsonString::from(format!(
+                "{{\"Ok\":{{\"Bridge\":{{\"caller_instance_dna_hash\":\"{}\"}}}}}}",
+                dna_hash(&dna)
+            )))
+        );
+
+        // bridges only work in the configured direction
+        assert_eq!(
+            container.call_bridge(
+                "bridge callee",
+                "app spec instance",
+                "test_zome",
+                "test_cap",
+                "call_context_test",
+                "{}",
+            ),
+            Err("No bridge from instance bridge callee to instance app spec instance".to_string())
+        );
+        container.stop_all_instances().unwrap();
+    }
+
+    #[test]
+    fn test_container_dependency_failed() {
+        let mut config = load_configuration::<Configuration>(&test_toml()).unwrap();
+        let mut broken_dna = config.dnas[0].clone();
+        broken_dna.id = "broken dna".to_string();
+        broken_dna.file = "broken.hcpkg".to_string();
+        config.dnas.push(broken_dna);
+        let mut callee = config.instances[0].clone();
+        callee.id = "bridge callee".to_string();
+        callee.dna = "broken dna".to_string();
+        config.instances.push(callee);
+        config.bridges.push(Bridge {
+            caller_id: "app spec instance".to_string(),
+            callee_id: "bridge callee".to_string(),
+            handle: None,
+        });
+        let mut container = Container::with_config(config.clone());
+        container.dna_loader = Arc::new(Box::new(|path: &String| {
+            if path == "broken.hcpkg" {
+                Err(HolochainError::ErrorGeneric("broken".to_string()))
+            } else {
+                Ok(Dna::try_from(JsonString::from(example_dna_string())).unwrap())
+            }
+        })
+            as Box<FnMut(&String) -> Result<Dna, HolochainError> + Send>);
+
+        assert_eq!(
+            container.load_config(&config),
+            Err("Error while trying to create instance \"bridge callee\": Could not load DNA file \"broken.hcpkg\"".to_string())
+        );
+        assert_eq!(
+            container.startup_state("bridge callee"),
+            Some(StartupState::Errored(
+                "Could not load DNA file \"broken.hcpkg\"".to_string()
+            ))
+        );
+        assert_eq!(
+            container.startup_state("app spec instance"),
+            Some(StartupState::DependencyFailed("bridge callee".to_string()))
+        );
+        assert!(container.instances.is_empty());
+        assert!(container.start_all_instances().is_ok());
+
+        let dispatcher = container.make_dispatcher(&config.interfaces[0]);
+        let request = r#"{"jsonrpc":"2.0","id":1,"method":"info/status","params":{"instance_id":"app spec instance"}}"#;
+        let response = r#"{"jsonrpc":"2.0","result":{"startup":{"dependency_failed":"bridge callee"}},"id":1}"#;
+        assert_eq!(
+            dispatcher.io.handle_request_sync(request),
+            Some(response.to_string())
+        );
+    }
+
+    #[test]
+    fn test_instance_bridges_without_the_handle_are_not_present() {
+        let bridges = InstanceBridges {
+            callees: HashMap::new(),
+        };
+        let call = ZomeFnCall::new("test_zome", "test_cap", "hello", "{}");
+        assert_eq!(
+            bridges.call("search", call),
+            Err(HolochainError::BridgeNotPresent("search".to_string()))
+        );
+    }
+
+    #[test]
+    /// test that instances of the same DNA with different properties overrides see their
+    /// overridden properties and only share a network with instances that override alike
+    fn test_properties_override_forks_the_network() {
+        let mut config = load_configuration::<Configuration>(&test_toml()).unwrap();
+        config.interfaces.clear();
+        let template = config.instances.remove(0);
+        for (name, admin) in vec![("alice", "alice"), ("bob", "bob"), ("carol", "alice")] {
+            config.agents.push(AgentConfiguration {
+                id: name.to_string(),
+                name: None,
+                key_file: format!("{}.key", name),
+            });
+            let mut properties_override = PropertiesOverride::new();
+            properties_override.insert("admin".to_string(), admin.into());
+            config.instances.push(InstanceConfiguration {
+                id: name.to_string(),
+                agent: name.to_string(),
+                properties_override: Some(properties_override),
+                ..template.clone()
+            });
+        }
+        let mut container = Container::with_config(config.clone());
+        container.dna_loader = test_dna_loader();
+        container.load_config(&config).unwrap();
+        container.start_all_instances().unwrap();
+        thread::sleep(Duration::from_millis(1000));
+
+        let state = |id: &str| container.instances[id].read().unwrap().state().unwrap();
+        // what hdk::property reads
+        assert_eq!(
+            state("bob").nucleus().dna().unwrap().properties["admin"],
+            "bob"
+        );
+        let agent_id = |id: &str| state(id).network().agent_id.clone().unwrap();
+        let alice_peers = state("alice").network().peers.clone();
+        assert!(alice_peers.contains(&agent_id("carol")));
+        assert!(!alice_peers.contains(&agent_id("bob")));
+        assert!(state("bob").network().peers.is_empty());
+        container.stop_all_instances().unwrap();
+    }
+
+    fn dna_loader_for(dna: Dna) -> DnaLoader {
+        Arc::new(Box::new(move |_path: &String| Ok(dna.clone()))
+            as Box<FnMut(&String) -> Result<Dna, HolochainError> + Send>)
+    }
+
+    /// The test config with its instance running the commit_test function of the test wasm
+    /// over file storage in the given directory
+    fn file_storage_config(dir: &Path) -> (Configuration, Dna) {
+        let mut config = load_configuration::<Configuration>(&test_toml()).unwrap();
+        config.instances[0].storage = Some(StorageConfiguration::File {
+            path: dir.join("storage").to_str().unwrap().to_string(),
+        });
+        let capability = create_test_cap_with_fn_name("commit_test");
+        let dna =
+            create_test_dna_with_cap("test_zome", "test_cap", &capability, &example_api_wasm());
+        (config, dna)
+    }
+
+    fn start_container(config: &Configuration, dna: &Dna, config_path: &Path) -> Container {
+        let mut container = Container::with_config(config.clone());
+        container.set_config_path(config_path.to_path_buf());
+        container.dna_loader = dna_loader_for(dna.clone());
+        container.load_config(config).unwrap();
+        container.start_all_instances().unwrap();
+        container
+    }
+
+    fn saved_config(config_path: &Path) -> Configuration {
+        let mut contents = String::new();
+        File::open(config_path)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        load_configuration::<Configuration>(&contents).unwrap()
+    }
+
+    #[test]
+    fn test_container_archive_and_restore_instance() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("container_config.toml");
+        let (config, dna) = file_storage_config(dir.path());
+        let instance_id = "app spec instance";
+
+        let mut container = start_container(&config, &dna, &config_path);
+        let hc = container.instances[instance_id].clone();
+        let result = hc
+            .write()
+            .unwrap()
+            .call("test_zome", "test_cap", "commit_test", "{}");
+        assert!(result.is_ok(), "result = {:?}", result);
+        let chain_info = hc.read().unwrap().chain_info().unwrap();
+
+        container.archive_instance(instance_id).unwrap();
+        assert_eq!(hc.read().unwrap().status(), InstanceStatus::Archived);
+        assert!(container.start_all_instances().is_ok());
+        assert_eq!(hc.read().unwrap().status(), InstanceStatus::Archived);
+        container.shutdown().unwrap();
+        let config = saved_config(&config_path);
+        assert!(config.instances[0].archived);
+
+        // after a restart, the archived instance is there but does not get started
+        let container = start_container(&config, &dna, &config_path);
+        let hc = container.instances[instance_id].clone();
+        assert_eq!(hc.read().unwrap().status(), InstanceStatus::Archived);
+
+        container.restore_instance(instance_id).unwrap();
+        assert_eq!(hc.read().unwrap().status(), InstanceStatus::Running);
+        assert_eq!(hc.read().unwrap().chain_info().unwrap(), chain_info);
+        assert!(!saved_config(&config_path).instances[0].archived);
+        assert_eq!(
+            container.restore_instance(instance_id),
+            Err("Instance app spec instance is not archived".to_string())
+        );
+        hc.write().unwrap().stop().unwrap();
+    }
+
+    #[test]
+    fn test_container_purge_instance() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("container_config.toml");
+        let (config, dna) = file_storage_config(dir.path());
+        let instance_id = "app spec instance";
+        let mut container = start_container(&config, &dna, &config_path);
+        assert!(dir.path().join("storage").exists());
+
+        assert!(container.request_purge(instance_id).is_err());
+        container.archive_instance(instance_id).unwrap();
+        let token = container.request_purge(instance_id).unwrap();
+        assert_eq!(
+            container.purge_instance(instance_id, "wrong token"),
+            Err("Wrong confirmation token for purging instance app spec instance".to_string())
+        );
+        // the wrong guess used up the token
+        assert!(container.purge_instance(instance_id, &token).is_err());
+        assert!(dir.path().join("storage").exists());
+
+        let token = container.request_purge(instance_id).unwrap();
+        container.purge_instance(instance_id, &token).unwrap();
+        assert!(!dir.path().join("storage").exists());
+        assert!(container.instances.is_empty());
+        let config = saved_config(&config_path);
+        assert!(config.instances.is_empty());
+        assert!(config.interfaces[0].instances.is_empty());
+        assert_eq!(config.check_consistency(), Ok(()));
+    }
+
+    /// Waits up to 5 seconds for the given instance to get to the given status
+    #[cfg(feature = "test_fixtures")]
+    fn wait_for_status(hc: &Arc<RwLock<Holochain>>, status: InstanceStatus) -> bool {
+        for _ in 0..500 {
+            if hc.read().unwrap().status() == status {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    #[cfg(feature = "test_fixtures")]
+    fn test_container_restarts_crashed_instances() {
+        let dir = tempdir().unwrap();
+        let config_path = dir.path().join("container_config.toml");
+        let (mut config, dna) = file_storage_config(dir.path());
+        config.instances[0].restart = crate::config::RestartPolicy::OnFailure {
+            max_retries: 1,
+            backoff_ms: 10,
+        };
+        let instance_id = "app spec instance";
+        let mut container = start_container(&config, &dna, &config_path);
+        let hc = container.instances[instance_id].clone();
+
+        hc.write().unwrap().crash("boom");
+        assert!(wait_for_status(&hc, InstanceStatus::Errored));
+        assert!(wait_for_status(&hc, InstanceStatus::Running));
+        assert_eq!(hc.read().unwrap().restarts(), 1);
+        assert_eq!(hc.read().unwrap().last_crash().unwrap().message, "boom");
+        let result = hc
+            .write()
+            .unwrap()
+            .call("test_zome", "test_cap", "commit_test", "{}");
+        assert!(result.is_ok(), "result = {:?}", result);
+
+        // the one retry is used up, so the instance stays down this time
+        hc.write().unwrap().crash("boom again");
+        assert!(wait_for_status(&hc, InstanceStatus::Errored));
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(hc.read().unwrap().status(), InstanceStatus::Errored);
+        assert_eq!(hc.read().unwrap().restarts(), 1);
+        let result = hc
+            .write()
+            .unwrap()
+            .call("test_zome", "test_cap", "commit_test", "{}");
+        assert_eq!(
+            result,
+            Err(HolochainInstanceError::InstanceCrashed(
+                "boom again".to_string()
+            ))
+        );
+        container.shutdown().unwrap();
+    }
+
+    fn source_chain_of(dump: &str) -> String {
+        dump.split("DHT shard").next().unwrap().to_string()
+    }
+
+    #[test]
+    fn replayed_instance_has_same_chain() {
+        // The instance starts out with empty storage, so an empty directory is its snapshot
+        let storage = tempdir().unwrap();
+        let snapshot = tempdir().unwrap();
+        let log_dir = tempdir().unwrap();
+        let log_path = log_dir.path().join("actions.log");
+
+        let wasm = example_api_wasm();
+        let capability = create_test_cap_with_fn_name("commit_test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        let mut context = create_file_context(
+            &"bob".to_string(),
+            &storage.path().to_string_lossy().to_string(),
+            mock_network_config(),
+            create_logger(&LoggerConfiguration::default()),
+        )
+        .unwrap();
+        let recorder = ActionRecorder::create(&log_path, &context.agent_id).unwrap();
+        context.set_action_recorder(recorder);
+
+        let mut hc = Holochain::new(dna, Arc::new(context)).unwrap();
+        hc.start().unwrap();
+        let result = hc.call("test_zome", "test_cap", "commit_test", r#"{}"#);
+        assert!(result.is_ok(), "result = {:?}", result);
+        let original = format_state_dump(&hc.state().unwrap());
+        hc.stop().unwrap();
+        assert!(!original.starts_with("Source chain (0 headers)"));
+
+        let work = tempdir().unwrap();
+        let replayed =
+            replay_instance(&log_path, snapshot.path(), &work.path().join("copy"), None).unwrap();
+        assert_eq!(source_chain_of(&replayed), source_chain_of(&original));
+
+        // Stopping right after the first commit leaves just the DNA on the chain
+        let log = ActionLog::read(&log_path).unwrap();
+        let first_commit = log
+            .actions
+            .iter()
+            .find(|recorded| recorded.name == "Commit")
+            .unwrap();
+        let work = tempdir().unwrap();
+        let replayed = replay_instance(
+            &log_path,
+            snapshot.path(),
+            &work.path().join("copy"),
+            Some(first_commit.index),
+        )
+        .unwrap();
+        assert!(replayed.starts_with("Source chain (1 headers):"));
+    }
 }
@@ -9,6 +9,9 @@ pub enum HolochainInstanceError {
     InternalFailure(HolochainError),
     InstanceNotActiveYet,
     InstanceAlreadyActive,
+    InstanceArchived,
+    /// The action loop of the instance crashed, with what it panicked with
+    InstanceCrashed(String),
 }
 
 impl Error for HolochainInstanceError {
@@ -19,6 +22,8 @@ impl Error for HolochainInstanceError {
             HolochainInstanceError::InstanceAlreadyActive => {
                 "Holochain instance is already active."
             }
+            HolochainInstanceError::InstanceArchived => "Holochain instance is archived.",
+            HolochainInstanceError::InstanceCrashed(_) => "Holochain instance crashed.",
         }
     }
 
@@ -29,13 +34,28 @@ impl Error for HolochainInstanceError {
             HolochainInstanceError::InternalFailure(ref err)  => Some(err),
             HolochainInstanceError::InstanceNotActiveYet => None,
             HolochainInstanceError::InstanceAlreadyActive => None,
+            HolochainInstanceError::InstanceArchived => None,
+            HolochainInstanceError::InstanceCrashed(_) => None,
         }
     }
 }
 
 impl fmt::Display for HolochainInstanceError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Holochain Instance Error: {}", self.description())
+        match self {
+            // shows the whole context chain, not only the outermost context
+            HolochainInstanceError::InternalFailure(ref err) => {
+                write!(f, "Holochain Instance Error: {}", err)
+            }
+            HolochainInstanceError::InstanceCrashed(ref message) => {
+                write!(
+                    f,
+                    "Holochain Instance Error: Holochain instance crashed: {}",
+                    message
+                )
+            }
+            _ => write!(f, "Holochain Instance Error: {}", self.description()),
+        }
     }
 }
 
@@ -64,6 +84,14 @@ pub mod tests {
                 HolochainInstanceError::InstanceAlreadyActive,
                 "Holochain instance is already active.",
             ),
+            (
+                HolochainInstanceError::InstanceArchived,
+                "Holochain instance is archived.",
+            ),
+            (
+                HolochainInstanceError::InstanceCrashed("out of memory".to_string()),
+                "Holochain instance crashed.",
+            ),
             (
                 HolochainInstanceError::InternalFailure(HolochainError::DnaMissing),
                 "DNA is missing",
@@ -85,6 +113,14 @@ pub mod tests {
                 HolochainInstanceError::InstanceAlreadyActive,
                 "Holochain instance is already active.",
             ),
+            (
+                HolochainInstanceError::InstanceArchived,
+                "Holochain instance is archived.",
+            ),
+            (
+                HolochainInstanceError::InstanceCrashed("out of memory".to_string()),
+                "Holochain instance crashed: out of memory",
+            ),
             (
                 HolochainInstanceError::InternalFailure(HolochainError::DnaMissing),
                 "DNA is missing",
@@ -106,4 +142,16 @@ pub mod tests {
         );
     }
 
+    #[test]
+    /// show the whole context chain of an internal failure
+    fn holochain_instance_error_context_test() {
+        let error = HolochainInstanceError::from(
+            HolochainError::DnaMissing.with_context("loading the instance"),
+        );
+        assert_eq!(
+            error.to_string(),
+            "Holochain Instance Error: loading the instance: DNA is missing",
+        );
+    }
+
 }
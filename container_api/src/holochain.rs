@@ -62,43 +62,290 @@
 
 use crate::error::{HolochainInstanceError, HolochainResult};
 use futures::{executor::block_on, TryFutureExt};
+#[cfg(feature = "test_fixtures")]
+use holochain_core::action::{Action, ActionWrapper};
 use holochain_core::{
+    agent::actions::flush::flush,
     context::Context,
-    instance::Instance,
-    network::actions::initialize_network::initialize_network,
-    nucleus::{actions::initialize::initialize_application, call_and_wait_for_result, ZomeFnCall},
+    instance::{CrashReport, Heartbeat, Instance},
+    logger::format_state_dump,
+    network::{actions::initialize_network::initialize_network, state::NetworkStatus},
+    nucleus::{
+        actions::initialize::initialize_application,
+        call_and_wait_for_result_unless_crashed,
+        call_metrics::ZomeFnLatency,
+        ribosome::host_call_trace::{HostCallRecord, HostCallTrace},
+        state::ZomeCallStats,
+        validation_package_cache::ValidationPackageCacheStats,
+        ZomeFnCall,
+    },
     persister::{Persister, SimplePersister},
-    state::State,
+    state::{PendingWork, State},
+    workflows::{
+        collect_garbage::{collect_garbage, mark_expired_entries},
+        hold_entry::resume_pending_validations,
+        register_agent::publish_agent_id_link,
+        revalidate_shard::{RevalidationReport, ShardRevalidation},
+    },
 };
-use holochain_core_types::{dna::Dna, error::HolochainError, json::JsonString};
-use std::sync::Arc;
+use holochain_core_types::{
+    cas::storage::GcReport,
+    dna::{build_info::BuildInfo, Dna},
+    entry::addressing::dna_hash,
+    error::HolochainError,
+    hash::HashString,
+    json::JsonString,
+};
+use holochain_wasm_utils::api_serialization::{CallContext, ChainInfo};
+use std::{
+    cmp,
+    collections::VecDeque,
+    sync::{
+        mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender},
+        Arc, RwLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often the held entries of types with a TTL expire at most, in instances that don't
+/// collect garbage on their own, see `set_gc_interval()`
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How many heartbeats of an instance can wait for its watcher, see `watch()`
+const HEARTBEAT_BUFFER_SIZE: usize = 10;
 
 /// contains a Holochain application instance
 pub struct Holochain {
     instance: Instance,
-    #[allow(dead_code)]
     context: Arc<Context>,
+    // the context the instance got created with, for rebuilding it
+    base_context: Arc<Context>,
+    // the context of the current action loop, which the probes read, see `rebuild()`
+    probed_context: Arc<RwLock<Arc<Context>>>,
+    restarts: u32,
+    last_crash: Option<CrashReport>,
     active: bool,
+    archived: bool,
+    revalidation: Option<ShardRevalidation>,
+    gc_interval: Option<Duration>,
+    // dropping it stops the scheduled garbage collections
+    gc_schedule: Option<SyncSender<()>>,
+    event_listeners: Vec<InstanceEventListener>,
+    event_buffer: EventBuffer,
+}
+
+/// Changes of an instance that don't answer a request of the clients using it,
+/// e.g. it getting stopped through the admin interface. Interfaces tell their clients about them.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum InstanceEvent {
+    Started,
+    Stopped,
+    Archived,
+}
+
+/// Gets called with the events of an instance and their sequence numbers,
+/// see `add_event_listener()`
+pub type InstanceEventListener = Box<Fn(&InstanceEvent, u64) + Send + Sync>;
+
+/// An event of an instance with its sequence number, which counts up from 1
+/// with every event of the instance
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SequencedEvent {
+    pub seq: u64,
+    pub event: InstanceEvent,
+}
+
+/// The buffered events of an instance after some sequence number, see `events_since()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventBacklog {
+    pub events: Vec<SequencedEvent>,
+    /// Whether some of the events after that sequence number got dropped from the buffer
+    pub gap: bool,
+}
+
+/// What got measured while a zome call ran, see `call_streaming_with_debug_info()`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallDebugInfo {
+    /// the most WASM memory the call used in bytes
+    pub peak_memory: Option<usize>,
+    /// the host functions the call invoked, if it got audited
+    pub host_call_trace: Option<HostCallTrace>,
+}
+
+/// A recent audited zome call and the host functions it invoked, see `last_calls()`
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AuditedCall {
+    pub zome: String,
+    pub capability: String,
+    pub function: String,
+    pub host_calls: Vec<HostCallRecord>,
+    /// how many host function invocations did not fit into the trace
+    pub dropped: usize,
+}
+
+/// Reads what an instance is waiting on, see `Holochain::pending()`, without locking the
+/// instance, which running zome calls keep locked. It follows the instance through rebuilds.
+#[derive(Clone)]
+pub struct PendingWorkProbe {
+    context: Arc<RwLock<Arc<Context>>>,
+}
+
+impl PendingWorkProbe {
+    pub fn pending(&self) -> Result<PendingWork, HolochainInstanceError> {
+        let context = self.context.read().unwrap().clone();
+        let state = context
+            .state()
+            .ok_or_else(|| HolochainError::new("Context has no state"))?;
+        Ok(state.pending_work(Instant::now())?)
+    }
+}
+
+/// Tells whether the action loop of an instance crashed, see `Holochain::crash_report()`,
+/// without locking the instance, which calls waiting on a hung action loop keep locked
+/// until they see the crash. It follows the instance through rebuilds.
+#[derive(Clone)]
+pub struct CrashProbe {
+    context: Arc<RwLock<Arc<Context>>>,
+}
+
+impl CrashProbe {
+    pub fn crash_report(&self) -> Option<CrashReport> {
+        self.context.read().unwrap().crash_report()
+    }
+
+    /// Marks the action loop as crashed, e.g. when it stopped sending heartbeats
+    pub fn report_crash(&self, crash: CrashReport) {
+        self.context.read().unwrap().report_crash(crash);
+    }
+
+    /// Logs to the logger of the instance
+    pub fn log(&self, message: String) {
+        self.context.read().unwrap().log(message);
+    }
+}
+
+/// Keeps the latest events of an instance, so that clients that were not connected when
+/// they happened can still get them. Once it is full, the oldest events get dropped.
+struct EventBuffer {
+    capacity: usize,
+    last_seq: u64,
+    events: VecDeque<SequencedEvent>,
+}
+
+impl EventBuffer {
+    fn new(capacity: usize) -> Self {
+        EventBuffer {
+            capacity,
+            last_seq: 0,
+            events: VecDeque::new(),
+        }
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.events.len() > capacity {
+            self.events.pop_front();
+        }
+    }
+
+    /// Numbers the event and buffers it, returns its sequence number
+    fn push(&mut self, event: InstanceEvent) -> u64 {
+        self.last_seq += 1;
+        if self.capacity > 0 {
+            if self.events.len() == self.capacity {
+                self.events.pop_front();
+            }
+            self.events.push_back(SequencedEvent {
+                seq: self.last_seq,
+                event,
+            });
+        }
+        self.last_seq
+    }
+
+    fn since(&self, since_seq: u64) -> EventBacklog {
+        let first_buffered = self
+            .events
+            .front()
+            .map(|buffered| buffered.seq)
+            .unwrap_or(self.last_seq + 1);
+        EventBacklog {
+            events: self
+                .events
+                .iter()
+                .filter(|buffered| buffered.seq > since_seq)
+                .cloned()
+                .collect(),
+            gap: since_seq + 1 < first_buffered,
+        }
+    }
+}
+
+/// What an instance is doing, as shown in status listings
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum InstanceStatus {
+    Running,
+    Stopped,
+    Archived,
+    /// Its action loop crashed, see `Holochain::crash_report()`
+    Errored,
 }
 
 impl Holochain {
     /// create a new Holochain instance
     pub fn new(dna: Dna, context: Arc<Context>) -> HolochainResult<Self> {
-        let mut instance = Instance::new(context.clone());
+        let instance = Instance::new(context.clone());
+        Self::from_instance(instance, dna, context)
+    }
+
+    /// create a Holochain instance that continues the source chain saved with `save()`
+    /// in the context's storage, or starts a new one if nothing was saved there yet
+    pub fn restore(dna: Dna, context: Arc<Context>) -> HolochainResult<Self> {
+        let loaded_state = context
+            .persister
+            .lock()
+            .map_err(|_| HolochainError::new("Could not lock persister"))?
+            .load(context.clone())?;
+        let instance = match loaded_state {
+            Some(state) => Instance::from_state(state),
+            None => Instance::new(context.clone()),
+        };
+        Self::from_instance(instance, dna, context)
+    }
+
+    fn from_instance(
+        mut instance: Instance,
+        dna: Dna,
+        context: Arc<Context>,
+    ) -> HolochainResult<Self> {
         let name = dna.name.clone();
+        let base_context = context.clone();
         instance.start_action_loop(context.clone());
         let context = instance.initialize_context(context.clone());
         let context2 = context.clone();
         let result = block_on(
-            initialize_application(dna, &context2).and_then(|_| initialize_network(&context)),
+            initialize_application(dna, &context2)
+                .and_then(|_| initialize_network(&context))
+                .and_then(|_| publish_agent_id_link(&context)),
         );
         match result {
             Ok(_) => {
                 context.log(format!("{} instantiated", name));
                 let hc = Holochain {
                     instance,
+                    probed_context: Arc::new(RwLock::new(context.clone())),
                     context,
+                    base_context,
+                    restarts: 0,
+                    last_crash: None,
                     active: false,
+                    archived: false,
+                    revalidation: None,
+                    gc_interval: None,
+                    gc_schedule: None,
+                    event_listeners: Vec::new(),
+                    event_buffer: EventBuffer::new(0),
                 };
                 Ok(hc)
             }
@@ -118,16 +365,42 @@ impl Holochain {
         Ok(Holochain {
             instance,
             context: context.clone(),
+            base_context: context.clone(),
+            probed_context: Arc::new(RwLock::new(context.clone())),
+            restarts: 0,
+            last_crash: None,
             active: false,
+            archived: false,
+            revalidation: None,
+            gc_interval: None,
+            gc_schedule: None,
+            event_listeners: Vec::new(),
+            event_buffer: EventBuffer::new(0),
         })
     }
 
     /// activate the Holochain instance
+    /// Validations that were waiting for the entries they depend on are picked up again.
+    /// Archived instances can't be started before they got restored with `unarchive()`,
+    /// crashed ones before they got rebuilt with `rebuild()`.
     pub fn start(&mut self) -> Result<(), HolochainInstanceError> {
+        if self.archived {
+            return Err(HolochainInstanceError::InstanceArchived);
+        }
+        self.check_not_crashed()?;
         if self.active {
             return Err(HolochainInstanceError::InstanceAlreadyActive);
         }
+        self.context.resume_tasks();
+        resume_pending_validations(&self.context);
+        self.gc_schedule = match self.gc_interval {
+            Some(interval) => Some(schedule_gc(self.context.clone(), interval)),
+            None => self
+                .expiry_interval()
+                .map(|interval| schedule_expiry(self.context.clone(), interval)),
+        };
         self.active = true;
+        self.emit(InstanceEvent::Started);
         Ok(())
     }
 
@@ -136,11 +409,71 @@ impl Holochain {
         if !self.active {
             return Err(HolochainInstanceError::InstanceNotActiveYet);
         }
+        self.context.cancel_tasks();
+        self.gc_schedule = None;
         self.active = false;
+        self.emit(InstanceEvent::Stopped);
         Ok(())
     }
 
+    /// calls `listener` with the event and its sequence number whenever the instance
+    /// gets started, stopped or archived
+    pub fn add_event_listener<F>(&mut self, listener: F)
+    where
+        F: Fn(&InstanceEvent, u64) + Send + Sync + 'static,
+    {
+        self.event_listeners.push(Box::new(listener));
+    }
+
+    /// keeps the last `size` events of the instance for `events_since()`, none by default
+    pub fn set_signal_buffer_size(&mut self, size: usize) {
+        self.event_buffer.set_capacity(size);
+    }
+
+    /// the buffered events with a sequence number above `since_seq`, oldest first,
+    /// and whether events after it got dropped because the buffer was full
+    pub fn events_since(&self, since_seq: u64) -> EventBacklog {
+        self.event_buffer.since(since_seq)
+    }
+
+    fn emit(&mut self, event: InstanceEvent) {
+        let seq = self.event_buffer.push(event.clone());
+        for listener in self.event_listeners.iter() {
+            listener(&event, seq);
+        }
+    }
+
+    /// collects garbage in the instance's storage every `interval` while it is running,
+    /// starting with the next `start()`.
+    /// Without it, a running instance still lets the entries it holds expire, if the DNA has
+    /// entry types with a TTL, at least every minute and as often as the shortest TTL.
+    pub fn set_gc_interval(&mut self, interval: Duration) {
+        self.gc_interval = Some(interval);
+    }
+
+    /// how often to mark held entries as expired when not collecting garbage,
+    /// None if the DNA has no entry types with a TTL
+    fn expiry_interval(&self) -> Option<Duration> {
+        let ttl = self
+            .context
+            .state()
+            .and_then(|state| state.nucleus().dna())
+            .and_then(|dna| dna.min_entry_ttl())?;
+        Some(cmp::min(
+            Duration::from_secs(cmp::max(ttl, 1)),
+            EXPIRY_INTERVAL,
+        ))
+    }
+
+    /// removes the content the source chain and the local DHT shard don't refer to anymore
+    /// from the instance's storage, e.g. entries that got rejected
+    pub fn collect_garbage(&self) -> Result<GcReport, HolochainInstanceError> {
+        Ok(collect_garbage(&self.context)?)
+    }
+
     /// call a function in a zome
+    /// The called function sees the call as coming from an interface without a token,
+    /// use `call_with_context()` to tell it where the call came from.
     pub fn call(
         &mut self,
         zome: &str,
@@ -148,11 +481,126 @@ impl Holochain {
         fn_name: &str,
         params: &str,
     ) -> HolochainResult<JsonString> {
+        let call_context = CallContext::Interface {
+            token_id: String::new(),
+        };
+        self.call_with_context(zome, cap, fn_name, params, call_context)
+    }
+
+    /// call a function in a zome on behalf of the given caller
+    pub fn call_with_context(
+        &mut self,
+        zome: &str,
+        cap: &str,
+        fn_name: &str,
+        params: &str,
+        call_context: CallContext,
+    ) -> HolochainResult<JsonString> {
+        self.check_not_crashed()?;
+        if !self.active {
+            return Err(HolochainInstanceError::InstanceNotActiveYet);
+        }
+        let zome_call = ZomeFnCall::new(&zome, &cap, &fn_name, String::from(params))
+            .with_call_context(call_context);
+        Ok(call_and_wait_for_result_unless_crashed(
+            zome_call,
+            &mut self.instance,
+            &self.context,
+        )?)
+    }
+
+    /// call a function in a zome on behalf of the given caller, handing the partial results
+    /// it yields to `partial_results` as they come. The capacity of `partial_results` is how
+    /// many of them can wait to be consumed, the call fails with BackpressureExceeded if
+    /// more pile up. Partial results stop, i.e. the sender gets dropped, when the call returns.
+    pub fn call_streaming(
+        &mut self,
+        zome: &str,
+        cap: &str,
+        fn_name: &str,
+        params: &str,
+        call_context: CallContext,
+        partial_results: SyncSender<JsonString>,
+    ) -> HolochainResult<JsonString> {
+        self.call_streaming_with_debug_info(
+            zome,
+            cap,
+            fn_name,
+            params,
+            call_context,
+            false,
+            partial_results,
+        )
+        .map(|(result, _)| result)
+    }
+
+    /// like `call_streaming()`, but also returns the most WASM memory the call used and,
+    /// with `audit` or if the instance audits all its calls, the host functions it invoked
+    pub fn call_streaming_with_debug_info(
+        &mut self,
+        zome: &str,
+        cap: &str,
+        fn_name: &str,
+        params: &str,
+        call_context: CallContext,
+        audit: bool,
+        partial_results: SyncSender<JsonString>,
+    ) -> HolochainResult<(JsonString, CallDebugInfo)> {
+        self.check_not_crashed()?;
         if !self.active {
             return Err(HolochainInstanceError::InstanceNotActiveYet);
         }
-        let zome_call = ZomeFnCall::new(&zome, &cap, &fn_name, String::from(params));
-        Ok(call_and_wait_for_result(zome_call, &mut self.instance)?)
+        let zome_call = ZomeFnCall::new(&zome, &cap, &fn_name, String::from(params))
+            .with_call_context(call_context)
+            .with_audit(audit);
+        self.context
+            .partial_results
+            .register(&zome_call, partial_results);
+        let result = call_and_wait_for_result_unless_crashed(
+            zome_call.clone(),
+            &mut self.instance,
+            &self.context,
+        );
+        // losing partial results fails the call even if it returned successfully
+        self.context.partial_results.unregister(&zome_call)?;
+        let nucleus = self.state()?.nucleus();
+        let debug_info = CallDebugInfo {
+            peak_memory: nucleus.zome_call_memory(&zome_call),
+            host_call_trace: nucleus.host_call_trace(&zome_call),
+        };
+        Ok((result?, debug_info))
+    }
+
+    /// saves the top of the source chain to the context's storage, see `restore()`,
+    /// once the commits that are pending made it onto the chain, see `flush()`
+    pub fn save(&self) -> Result<(), HolochainInstanceError> {
+        self.flush()?;
+        Ok(())
+    }
+
+    /// blocks until the commits that are pending made it onto the chain and the storage
+    /// synced them, so that they survive a crash, and returns how many that were.
+    /// Containers flush instances before they stop them.
+    pub fn flush(&self) -> Result<usize, HolochainInstanceError> {
+        self.check_not_crashed()?;
+        Ok(block_on(flush(&self.context))?)
+    }
+
+    /// stops the instance if it is running, saves its chain and keeps it from being started
+    /// again until `unarchive()` is called. Its storage is left untouched.
+    pub fn archive(&mut self) -> Result<(), HolochainInstanceError> {
+        if self.active {
+            self.stop()?;
+        }
+        self.save()?;
+        self.archived = true;
+        self.emit(InstanceEvent::Archived);
+        Ok(())
+    }
+
+    /// lets an archived instance be started again
+    pub fn unarchive(&mut self) {
+        self.archived = false;
     }
 
     /// checks to see if an instance is active
@@ -160,14 +608,284 @@ impl Holochain {
         self.active
     }
 
+    /// checks to see if an instance is archived
+    pub fn archived(&self) -> bool {
+        self.archived
+    }
+
+    /// whether the instance is running, stopped, archived or crashed
+    pub fn status(&self) -> InstanceStatus {
+        if self.crash_report().is_some() {
+            InstanceStatus::Errored
+        } else if self.archived {
+            InstanceStatus::Archived
+        } else if self.active {
+            InstanceStatus::Running
+        } else {
+            InstanceStatus::Stopped
+        }
+    }
+
+    /// sends the heartbeats of the action loop of the instance to the returned receiver,
+    /// also the ones of the loops `rebuild()` starts, see `Heartbeat`.
+    /// Every watcher gets a receiver of its own.
+    pub fn watch(&self) -> Receiver<Heartbeat> {
+        let (sender, receiver) = sync_channel(HEARTBEAT_BUFFER_SIZE);
+        self.base_context.add_heartbeat_watcher(sender);
+        receiver
+    }
+
+    /// why the action loop of the instance crashed, None while it runs
+    pub fn crash_report(&self) -> Option<CrashReport> {
+        self.context.crash_report()
+    }
+
+    /// reads `crash_report()` while calls waiting on a hung action loop keep the instance locked
+    pub fn crash_probe(&self) -> CrashProbe {
+        CrashProbe {
+            context: self.probed_context.clone(),
+        }
+    }
+
+    /// the crash the instance is down with, or else the one it got rebuilt after last
+    pub fn last_crash(&self) -> Option<CrashReport> {
+        self.crash_report().or_else(|| self.last_crash.clone())
+    }
+
+    /// how often the instance got rebuilt after it crashed
+    pub fn restarts(&self) -> u32 {
+        self.restarts
+    }
+
+    fn check_not_crashed(&self) -> HolochainResult<()> {
+        match self.crash_report() {
+            Some(crash) => Err(HolochainInstanceError::InstanceCrashed(crash.message)),
+            None => Ok(()),
+        }
+    }
+
+    /// builds the instance anew from its storage, with the settings of the context it got
+    /// created with, after its action loop crashed, and starts it again if it was running.
+    /// Commits that were not flushed when it crashed are lost. Its settings, event listeners,
+    /// buffered events, heartbeat watchers and probes carry over.
+    /// The tasks of the old action loop get cancelled. If it only hung, it stays marked as
+    /// crashed and stops once it gets to its next action,
+    /// see `Context::for_rebuilt_action_loop()`.
+    pub fn rebuild(&mut self) -> Result<(), HolochainInstanceError> {
+        let dna = self
+            .state()?
+            .nucleus()
+            .dna()
+            .ok_or(HolochainError::DnaMissing)?;
+        let crash = self.crash_report();
+        let was_active = self.active;
+        self.gc_schedule = None;
+        self.revalidation = None;
+        self.context.cancel_tasks();
+        let base_context = Arc::new(self.base_context.for_rebuilt_action_loop());
+        let rebuilt = match Holochain::restore(dna, base_context.clone()) {
+            Ok(rebuilt) => rebuilt,
+            Err(error) => {
+                // the action loop that got started for the failed rebuild stops as well
+                base_context.forget_heartbeat_watchers();
+                base_context.cancel_tasks();
+                base_context.report_crash(CrashReport::new(error.to_string(), None));
+                return Err(error);
+            }
+        };
+        self.base_context.forget_heartbeat_watchers();
+        self.instance = rebuilt.instance;
+        self.context = rebuilt.context;
+        self.base_context = rebuilt.base_context;
+        *self.probed_context.write().unwrap() = self.context.clone();
+        self.restarts += 1;
+        if crash.is_some() {
+            self.last_crash = crash;
+        }
+        self.active = false;
+        if was_active {
+            self.start()
+        } else {
+            self.context.cancel_tasks();
+            Ok(())
+        }
+    }
+
+    /// makes the action loop of the instance panic with the given message,
+    /// for testing what happens to instances that crash
+    #[cfg(feature = "test_fixtures")]
+    pub fn crash(&mut self, message: &str) {
+        self.instance
+            .dispatch(ActionWrapper::new(Action::Crash(message.to_string())));
+    }
+
+    /// makes the action loop of the instance block for the given time,
+    /// for testing what happens to instances that hang
+    #[cfg(feature = "test_fixtures")]
+    pub fn hang(&mut self, duration: Duration) {
+        let millis = duration.as_secs() * 1000 + u64::from(duration.subsec_millis());
+        self.instance
+            .dispatch(ActionWrapper::new(Action::Hang(millis)));
+    }
+
     /// return
     pub fn state(&self) -> Result<State, HolochainInstanceError> {
         Ok(self.instance.state().clone())
     }
+
+    /// pretty-prints the source chain and DHT summary to the instance's logger
+    /// and returns the same text
+    pub fn dump_state(&self) -> Result<String, HolochainInstanceError> {
+        let dump = format_state_dump(&self.state()?);
+        self.context.log(dump.clone());
+        Ok(dump)
+    }
+
+    /// whether the instance can talk to the network and how often it had to reconnect
+    pub fn network_status(&self) -> Result<NetworkStatus, HolochainInstanceError> {
+        Ok(self.state()?.network().status())
+    }
+
+    /// the zome calls, validations and network requests the instance is waiting on
+    pub fn pending(&self) -> Result<PendingWork, HolochainInstanceError> {
+        self.pending_work_probe().pending()
+    }
+
+    /// reads `pending()` while zome calls keep the instance locked
+    pub fn pending_work_probe(&self) -> PendingWorkProbe {
+        PendingWorkProbe {
+            context: self.probed_context.clone(),
+        }
+    }
+
+    /// how many zome calls are running and how many of their results are kept or got pruned
+    pub fn zome_call_stats(&self) -> Result<ZomeCallStats, HolochainInstanceError> {
+        Ok(self.state()?.nucleus().zome_call_stats())
+    }
+
+    /// how long the calls of each zome function took, sorted by zome and function name
+    pub fn zome_fn_latencies(&self) -> Vec<ZomeFnLatency> {
+        self.context.zome_fn_latencies.latencies()
+    }
+
+    /// how often the validation packages the holders of the instance's entries asked for
+    /// were served from the cache, and how often they had to be built
+    pub fn validation_package_cache_stats(&self) -> ValidationPackageCacheStats {
+        self.context.validation_packages.stats()
+    }
+
+    /// how many entries the local DHT shard of the instance holds
+    pub fn held_count(&self) -> Result<usize, HolochainInstanceError> {
+        Ok(self.state()?.dht().held_count()?)
+    }
+
+    /// the host functions the last `count` audited zome calls invoked, most recent first
+    pub fn last_calls(&self, count: usize) -> Result<Vec<AuditedCall>, HolochainInstanceError> {
+        Ok(self
+            .state()?
+            .nucleus()
+            .last_host_call_traces(count)
+            .into_iter()
+            .map(|(call, trace)| AuditedCall {
+                zome: call.zome_name,
+                capability: call.cap_name,
+                function: call.fn_name,
+                host_calls: trace.calls,
+                dropped: trace.dropped,
+            })
+            .collect())
+    }
+
+    /// where the DNA of the instance came from, if it was packaged with build info
+    pub fn build_info(&self) -> Result<Option<BuildInfo>, HolochainInstanceError> {
+        Ok(self.state()?.nucleus().dna().and_then(|dna| dna.build_info))
+    }
+
+    /// the hash of the DNA the instance runs, None until it got initialized
+    pub fn dna_hash(&self) -> Result<Option<HashString>, HolochainInstanceError> {
+        Ok(self.state()?.nucleus().dna().map(|dna| dna_hash(&dna)))
+    }
+
+    /// returns the length of the source chain, its entry counts per type and its top header
+    pub fn chain_info(&self) -> Result<ChainInfo, HolochainInstanceError> {
+        Ok(self.state()?.agent().chain_info())
+    }
+
+    /// starts validating all entries and links of the local DHT shard again in the background,
+    /// follow its progress with `revalidation_report()`
+    pub fn revalidate_shard(&mut self) -> Result<(), HolochainInstanceError> {
+        if !self.active {
+            return Err(HolochainInstanceError::InstanceNotActiveYet);
+        }
+        if self
+            .revalidation
+            .as_ref()
+            .map(|revalidation| !revalidation.is_finished())
+            .unwrap_or(false)
+        {
+            return Err(HolochainInstanceError::InternalFailure(
+                HolochainError::ErrorGeneric(String::from("Shard revalidation is already running")),
+            ));
+        }
+        self.revalidation = Some(ShardRevalidation::start(&self.context));
+        Ok(())
+    }
+
+    /// cancels a running revalidation of the local DHT shard
+    pub fn cancel_revalidation(&self) -> Result<(), HolochainInstanceError> {
+        match self.revalidation {
+            Some(ref revalidation) if !revalidation.is_finished() => {
+                revalidation.cancel();
+                Ok(())
+            }
+            _ => Err(HolochainInstanceError::InternalFailure(
+                HolochainError::ErrorGeneric(String::from("No shard revalidation is running")),
+            )),
+        }
+    }
+
+    /// progress or result of the last revalidation of the local DHT shard, if there was any
+    pub fn revalidation_report(&self) -> Option<RevalidationReport> {
+        self.revalidation
+            .as_ref()
+            .map(|revalidation| revalidation.report())
+    }
+}
+
+/// Runs the task every `interval` on a thread of its own, until the returned sender gets dropped
+fn schedule<F>(interval: Duration, task: F) -> SyncSender<()>
+where
+    F: Fn() + Send + 'static,
+{
+    let (stop_sender, stop_receiver) = sync_channel::<()>(1);
+    thread::spawn(move || {
+        while let Err(RecvTimeoutError::Timeout) = stop_receiver.recv_timeout(interval) {
+            task();
+        }
+    });
+    stop_sender
+}
+
+/// Collects garbage in the context's storage every `interval`, see `schedule()`
+fn schedule_gc(context: Arc<Context>, interval: Duration) -> SyncSender<()> {
+    schedule(interval, move || match collect_garbage(&context) {
+        Ok(report) => context.log(format!("Collected garbage: {:?}", report)),
+        Err(error) => context.log(format!("Could not collect garbage: {}", error)),
+    })
+}
+
+/// Marks the held entries of the context that outlived their TTL as expired every
+/// `interval`, see `schedule()`
+fn schedule_expiry(context: Arc<Context>, interval: Duration) -> SyncSender<()> {
+    schedule(interval, move || {
+        if let Err(error) = mark_expired_entries(&context) {
+            context.log(format!("Could not expire entries: {}", error));
+        }
+    })
 }
 
 #[cfg(test)]
-mod tests {
+pub mod tests {
     extern crate holochain_cas_implementations;
 
     use self::holochain_cas_implementations::{
@@ -176,12 +894,25 @@ mod tests {
     use super::*;
     use holochain_core::{
         context::{mock_network_config, Context},
+        dht::actions::hold::hold_entry,
+        network::state::ConnectionState,
         nucleus::ribosome::{callback::Callback, Defn},
         persister::SimplePersister,
+        runtime::Runtime,
+    };
+    use holochain_core_types::{
+        agent::AgentId,
+        cas::content::{Address, AddressableContent},
+        dna::Dna,
+        entry::{entry_type::AppEntryType, Entry},
+        json::RawString,
+        time::ManualTimeSource,
     };
-    use holochain_core_types::{agent::AgentId, dna::Dna};
 
-    use std::sync::{Arc, Mutex, RwLock};
+    use std::{
+        sync::{Arc, Mutex, RwLock},
+        time::SystemTime,
+    };
     use tempfile::tempdir;
     use test_utils::{
         create_test_cap_with_fn_name, create_test_dna_with_cap, create_test_dna_with_wat,
@@ -192,7 +923,7 @@ mod tests {
     //  use holochain_core::{instance::tests::TestLogger};
     // doesn't work.
     // @see https://github.com/holochain/holochain-rust/issues/185
-    fn test_context(agent_name: &str) -> (Arc<Context>, Arc<Mutex<test_utils::TestLogger>>) {
+    pub fn test_context(agent_name: &str) -> (Arc<Context>, Arc<Mutex<test_utils::TestLogger>>) {
         let agent = AgentId::generate_fake(agent_name);
         let file_storage = Arc::new(RwLock::new(
             FilesystemStorage::new(tempdir().unwrap().path().to_str().unwrap()).unwrap(),
@@ -219,11 +950,11 @@ mod tests {
         )
     }
 
-    fn example_api_wasm_path() -> String {
+    pub fn example_api_wasm_path() -> String {
         "wasm-test/target/wasm32-unknown-unknown/release/example_api_wasm.wasm".into()
     }
 
-    fn example_api_wasm() -> Vec<u8> {
+    pub fn example_api_wasm() -> Vec<u8> {
         create_wasm_from_file(&example_api_wasm_path())
     }
 
@@ -336,6 +1067,173 @@ mod tests {
         assert!(!hc.active());
     }
 
+    #[test]
+    fn archived_instances_cannot_be_started() {
+        let (context, _) = test_context("bob");
+        let mut hc = Holochain::new(Dna::new(), context).unwrap();
+        hc.start().unwrap();
+        assert_eq!(hc.status(), InstanceStatus::Running);
+
+        hc.archive().unwrap();
+        assert!(!hc.active());
+        assert_eq!(hc.status(), InstanceStatus::Archived);
+        assert_eq!(hc.start(), Err(HolochainInstanceError::InstanceArchived));
+        assert_eq!(
+            hc.call("test_zome", "test_cap", "main", ""),
+            Err(HolochainInstanceError::InstanceNotActiveYet)
+        );
+
+        hc.unarchive();
+        assert_eq!(hc.status(), InstanceStatus::Stopped);
+        hc.start().unwrap();
+        assert_eq!(hc.status(), InstanceStatus::Running);
+    }
+
+    #[test]
+    fn running_calls_are_pending_until_they_return() {
+        // counts down from 20 million before returning "1337.0"
+        let dna = create_test_dna_with_wat(
+            "test_zome",
+            "test_cap",
+            Some(
+                r#"
+            (module
+                (memory (;0;) 17)
+                (func (export "main") (param $p0 i32) (result i32)
+                    (local $i i32)
+                    (set_local $i (i32.const 20000000))
+                    (block
+                        (loop
+                            (br_if 1 (i32.eqz (get_local $i)))
+                            (set_local $i (i32.sub (get_local $i) (i32.const 1)))
+                            (br 0)
+                        )
+                    )
+                    i32.const 6
+                )
+                (data (i32.const 0)
+                    "1337.0"
+                )
+                (export "memory" (memory 0))
+            )
+        "#,
+            ),
+        );
+        let (context, _) = test_context("bob");
+        let mut hc = Holochain::new(dna, context).unwrap();
+        hc.start().unwrap();
+        let probe = hc.pending_work_probe();
+        assert!(probe.pending().unwrap().is_empty());
+
+        let hc = Arc::new(RwLock::new(hc));
+        let caller = hc.clone();
+        let call = thread::spawn(move || {
+            caller
+                .write()
+                .unwrap()
+                .call("test_zome", "test_cap", "main", "")
+        });
+
+        let started = Instant::now();
+        let first = loop {
+            let pending = probe.pending().unwrap();
+            if !pending.zome_calls.is_empty() {
+                break pending.zome_calls[0].clone();
+            }
+            assert!(started.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(1));
+        };
+        assert_eq!(first.zome, "test_zome");
+        assert_eq!(first.function, "main");
+        thread::sleep(Duration::from_millis(20));
+        let second = probe.pending().unwrap().zome_calls[0].clone();
+        assert_eq!(second.call_id, first.call_id);
+        assert!(second.elapsed_ms > first.elapsed_ms);
+
+        assert_eq!(call.join().unwrap(), Ok(JsonString::from("1337.0")));
+        assert!(probe.pending().unwrap().zome_calls.is_empty());
+        assert!(hc.read().unwrap().pending().unwrap().is_empty());
+    }
+
+    #[test]
+    fn listeners_get_the_events_of_the_instance() {
+        let (context, _) = test_context("bob");
+        let mut hc = Holochain::new(Dna::new(), context).unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let received = events.clone();
+        hc.add_event_listener(move |event, _| received.lock().unwrap().push(event.clone()));
+
+        hc.start().unwrap();
+        hc.stop().unwrap();
+        // failed changes aren't events
+        assert!(hc.stop().is_err());
+        hc.start().unwrap();
+        hc.archive().unwrap();
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                InstanceEvent::Started,
+                InstanceEvent::Stopped,
+                InstanceEvent::Started,
+                InstanceEvent::Stopped,
+                InstanceEvent::Archived,
+            ]
+        );
+    }
+
+    #[test]
+    fn buffered_events_can_be_replayed() {
+        let (context, _) = test_context("bob");
+        let mut hc = Holochain::new(Dna::new(), context).unwrap();
+        let seqs = Arc::new(Mutex::new(Vec::new()));
+        let received = seqs.clone();
+        hc.add_event_listener(move |_, seq| received.lock().unwrap().push(seq));
+
+        hc.start().unwrap();
+        assert_eq!(hc.events_since(0).events, Vec::new());
+        assert!(hc.events_since(0).gap);
+
+        hc.set_signal_buffer_size(2);
+        hc.stop().unwrap();
+        hc.start().unwrap();
+        assert_eq!(
+            hc.events_since(1),
+            EventBacklog {
+                events: vec![
+                    SequencedEvent {
+                        seq: 2,
+                        event: InstanceEvent::Stopped,
+                    },
+                    SequencedEvent {
+                        seq: 3,
+                        event: InstanceEvent::Started,
+                    },
+                ],
+                gap: false,
+            }
+        );
+
+        hc.stop().unwrap();
+        let backlog = hc.events_since(1);
+        assert_eq!(backlog.events.len(), 2);
+        assert_eq!(backlog.events[0].seq, 3);
+        assert!(backlog.gap);
+        assert_eq!(hc.events_since(3).events.len(), 1);
+        assert!(!hc.events_since(3).gap);
+        assert!(hc.events_since(4).events.is_empty());
+        assert_eq!(*seqs.lock().unwrap(), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn can_get_network_status() {
+        let (context, _) = test_context("bob");
+        let hc = Holochain::new(Dna::new(), context).unwrap();
+        let network_status = hc.network_status().unwrap();
+        assert_eq!(network_status.connection, ConnectionState::Connected);
+        assert_eq!(network_status.reconnect_count, 0);
+    }
+
     #[test]
     fn can_call() {
         let wat = r#"
@@ -408,6 +1306,186 @@ mod tests {
         );
     }
 
+    /// A context over the given storage directory, like a container would create it
+    fn storage_context(path: &str) -> Arc<Context> {
+        let file_storage = Arc::new(RwLock::new(
+            FilesystemStorage::new(&format!("{}/cas", path)).unwrap(),
+        ));
+        Arc::new(
+            Context::new(
+                AgentId::generate_fake("alex"),
+                test_utils::test_logger(),
+                Arc::new(Mutex::new(SimplePersister::new(file_storage.clone()))),
+                file_storage,
+                Arc::new(RwLock::new(
+                    EavFileStorage::new(format!("{}/eav", path)).unwrap(),
+                )),
+                mock_network_config(),
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn can_restore_saved_chain() {
+        let wasm = example_api_wasm();
+        let capability = create_test_cap_with_fn_name("commit_test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        std::fs::create_dir_all(format!("{}/cas", path)).unwrap();
+        std::fs::create_dir_all(format!("{}/eav", path)).unwrap();
+
+        let mut hc = Holochain::restore(dna.clone(), storage_context(path)).unwrap();
+        hc.start().expect("couldn't start");
+        let result = hc.call("test_zome", "test_cap", "commit_test", r#"{}"#);
+        assert!(result.is_ok(), "result = {:?}", result);
+        hc.save().expect("couldn't save");
+        let chain_info = hc.chain_info().unwrap();
+        hc.stop().expect("couldn't stop");
+
+        // genesis does not run again for the restored chain
+        let hc = Holochain::restore(dna, storage_context(path)).unwrap();
+        let restored_chain_info = hc.chain_info().unwrap();
+        assert_eq!(restored_chain_info, chain_info);
+        assert_eq!(restored_chain_info.count("%dna"), 1);
+        assert_eq!(restored_chain_info.count("testEntryType"), 1);
+    }
+
+    #[test]
+    fn flushed_commits_survive_a_crash() {
+        let wasm = example_api_wasm();
+        let capability = create_test_cap_with_fn_name("commit_test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        std::fs::create_dir_all(format!("{}/cas", path)).unwrap();
+        std::fs::create_dir_all(format!("{}/eav", path)).unwrap();
+
+        let mut hc = Holochain::restore(dna.clone(), storage_context(path)).unwrap();
+        hc.start().expect("couldn't start");
+        for _ in 0..20 {
+            let result = hc.call("test_zome", "test_cap", "commit_test", r#"{}"#);
+            assert!(result.is_ok(), "result = {:?}", result);
+        }
+        assert!(hc.state().unwrap().agent().pending_commits() >= 20);
+        assert!(hc.flush().unwrap() >= 20);
+        assert_eq!(hc.state().unwrap().agent().pending_commits(), 0);
+        let chain_info = hc.chain_info().unwrap();
+        // killed without stopping or saving
+        std::mem::forget(hc);
+
+        let hc = Holochain::restore(dna, storage_context(path)).unwrap();
+        let restored_chain_info = hc.chain_info().unwrap();
+        assert_eq!(restored_chain_info, chain_info);
+        assert_eq!(restored_chain_info.count("testEntryType"), 20);
+    }
+
+    #[test]
+    #[cfg(feature = "test_fixtures")]
+    fn crashed_instance_fails_calls_until_it_got_rebuilt() {
+        let wasm = example_api_wasm();
+        let capability = create_test_cap_with_fn_name("commit_test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        std::fs::create_dir_all(format!("{}/cas", path)).unwrap();
+        std::fs::create_dir_all(format!("{}/eav", path)).unwrap();
+
+        let mut hc = Holochain::restore(dna, storage_context(path)).unwrap();
+        let heartbeats = hc.watch();
+        hc.start().expect("couldn't start");
+        let result = hc.call("test_zome", "test_cap", "commit_test", r#"{}"#);
+        assert!(result.is_ok(), "result = {:?}", result);
+        hc.flush().unwrap();
+        let chain_info = hc.chain_info().unwrap();
+
+        hc.crash("storage got corrupted");
+        let crash = loop {
+            match heartbeats.recv_timeout(Duration::from_secs(5)).unwrap() {
+                Heartbeat::Alive => continue,
+                Heartbeat::Crashed(crash) => break crash,
+            }
+        };
+        assert_eq!(crash.message, "storage got corrupted");
+        assert_eq!(hc.status(), InstanceStatus::Errored);
+        assert_eq!(hc.crash_probe().crash_report(), Some(crash.clone()));
+        assert_eq!(
+            hc.call("test_zome", "test_cap", "commit_test", r#"{}"#),
+            Err(HolochainInstanceError::InstanceCrashed(
+                "storage got corrupted".to_string()
+            ))
+        );
+
+        hc.rebuild().unwrap();
+        assert_eq!(hc.status(), InstanceStatus::Running);
+        assert_eq!(hc.restarts(), 1);
+        assert_eq!(hc.crash_report(), None);
+        assert_eq!(hc.last_crash(), Some(crash));
+        assert_eq!(hc.chain_info().unwrap(), chain_info);
+        let result = hc.call("test_zome", "test_cap", "commit_test", r#"{}"#);
+        assert!(result.is_ok(), "result = {:?}", result);
+        // the rebuilt action loop beats for the same watcher
+        assert_eq!(
+            heartbeats.recv_timeout(Duration::from_secs(5)),
+            Ok(Heartbeat::Alive)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "test_fixtures")]
+    fn calls_on_a_hung_instance_give_up_once_it_got_reported_as_crashed() {
+        let wasm = example_api_wasm();
+        let capability = create_test_cap_with_fn_name("commit_test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        let dir = tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        std::fs::create_dir_all(format!("{}/cas", path)).unwrap();
+        std::fs::create_dir_all(format!("{}/eav", path)).unwrap();
+
+        let mut hc = Holochain::restore(dna, storage_context(path)).unwrap();
+        // every watcher gets the heartbeats
+        let first_watcher = hc.watch();
+        let second_watcher = hc.watch();
+        hc.start().expect("couldn't start");
+        for watcher in [&first_watcher, &second_watcher].iter() {
+            assert_eq!(
+                watcher.recv_timeout(Duration::from_secs(5)),
+                Ok(Heartbeat::Alive)
+            );
+        }
+
+        hc.hang(Duration::from_secs(3));
+        let probe = hc.crash_probe();
+        let hc = Arc::new(RwLock::new(hc));
+        let caller = hc.clone();
+        let call = thread::spawn(move || {
+            caller
+                .write()
+                .unwrap()
+                .call("test_zome", "test_cap", "commit_test", r#"{}"#)
+        });
+        // the call keeps the instance locked while it waits
+        while hc.try_write().is_ok() {
+            thread::yield_now();
+        }
+        probe.report_crash(CrashReport::new("No heartbeat".to_string(), None));
+        let result = call.join().unwrap();
+        assert!(
+            format!("{:?}", result).contains("crashed before the call returned: No heartbeat"),
+            "result = {:?}",
+            result
+        );
+
+        let mut hc = hc.write().unwrap();
+        hc.rebuild().unwrap();
+        // the probe follows the instance to its new action loop
+        assert_eq!(probe.crash_report(), None);
+        assert_eq!(hc.status(), InstanceStatus::Running);
+        let result = hc.call("test_zome", "test_cap", "commit_test", r#"{}"#);
+        assert!(result.is_ok(), "result = {:?}", result);
+    }
+
     #[test]
     // TODO #165 - Move test to core/nucleus and use instance directly
     fn can_call_commit() {
@@ -544,6 +1622,40 @@ mod tests {
         assert_eq!(hc.state().unwrap().history.len(), 7);
     }
 
+    #[test]
+    fn called_function_sees_call_context() {
+        let wasm = example_api_wasm();
+        let capability = create_test_cap_with_fn_name("call_context_test");
+        let dna = create_test_dna_with_cap("test_zome", "test_cap", &capability, &wasm);
+        let (context, _) = test_context("alex");
+        let mut hc = Holochain::new(dna.clone(), context).unwrap();
+        hc.start().expect("couldn't start");
+
+        let result = hc.call("test_zome", "test_cap", "call_context_test", "{}");
+        assert_eq!(
+            Ok(JsonString::from(
+                "{\"Ok\":{\"Interface\":{\"token_id\":\"\"}}}"
+            )),
+            result,
+        );
+
+        let result = hc.call_with_context(
+            "test_zome",
+            "test_cap",
+            "call_context_test",
+            "{}",
+            CallContext::DirectMessage {
+                from_agent: Address::from("QmSender"),
+            },
+        );
+        assert_eq!(
+            Ok(JsonString::from(
+                "{\"Ok\":{\"DirectMessage\":{\"from_agent\":\"QmSender\"}}}"
+            )),
+            result,
+        );
+    }
+
     #[test]
     // TODO #165 - Move test to core/nucleus and use instance directly
     fn call_debug_stacked() {
@@ -554,4 +1666,136 @@ mod tests {
             call_result.unwrap()
         );
     }
+
+    #[test]
+    fn can_collect_garbage_on_schedule() {
+        let dna = create_test_dna_with_wat("test_zome", "test_cap", None);
+        let (context, _) = test_context("bob");
+        let garbage = Entry::App(
+            AppEntryType::from("testEntryType"),
+            JsonString::from(RawString::from("nobody refers to this")),
+        );
+        let is_stored = || {
+            context
+                .file_storage
+                .read()
+                .unwrap()
+                .contains(&garbage.address())
+                .unwrap()
+        };
+        let mut hc = Holochain::new(dna, context.clone()).unwrap();
+        context.file_storage.write().unwrap().add(&garbage).unwrap();
+
+        // nothing the instance needs is garbage
+        let report = hc.collect_garbage().unwrap();
+        assert_eq!(report.removed, 1);
+        assert!(!is_stored());
+        assert!(hc.chain_info().unwrap().length > 0);
+
+        context.file_storage.write().unwrap().add(&garbage).unwrap();
+        hc.set_gc_interval(Duration::from_millis(10));
+        hc.start().expect("couldn't start");
+        for _ in 0..100 {
+            if !is_stored() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!is_stored());
+
+        // stopping the instance stops the schedule
+        hc.stop().unwrap();
+        thread::sleep(Duration::from_millis(50));
+        context.file_storage.write().unwrap().add(&garbage).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert!(is_stored());
+    }
+
+    #[test]
+    fn expires_entries_of_types_with_a_ttl_without_collecting_garbage() {
+        let mut dna = create_test_dna_with_wat("test_zome", "test_cap", None);
+        dna.zomes
+            .get_mut("test_zome")
+            .unwrap()
+            .entry_types
+            .get_mut("testEntryType")
+            .unwrap()
+            .ttl_seconds = Some(1);
+        let clock = Arc::new(ManualTimeSource::new(SystemTime::now()));
+        let (context, _) = test_context("bob");
+        let mut context = (*context).clone();
+        context.set_time_source(clock.clone());
+        let mut hc = Holochain::new(dna, Arc::new(context)).unwrap();
+        let entry = Entry::App(
+            AppEntryType::from("testEntryType"),
+            JsonString::from(RawString::from("short-lived")),
+        );
+        let context = hc.context.clone();
+        block_on(hold_entry(&entry, &context)).unwrap();
+        let is_expired = || {
+            context
+                .state()
+                .unwrap()
+                .dht()
+                .is_expired(&entry.address())
+                .unwrap()
+        };
+
+        clock.advance(Duration::from_secs(1));
+        assert!(!is_expired());
+        hc.start().expect("couldn't start");
+        let mut expired = false;
+        for _ in 0..50 {
+            expired = is_expired();
+            if expired {
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        assert!(expired);
+        hc.stop().unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    fn thread_count() -> usize {
+        let status = std::fs::read_to_string("/proc/self/status").unwrap();
+        status
+            .lines()
+            .find(|line| line.starts_with("Threads:"))
+            .and_then(|line| line["Threads:".len()..].trim().parse().ok())
+            .unwrap()
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn instances_share_runtime_threads() {
+        let runtime = Runtime::new(2);
+        let before_instances = thread_count();
+
+        let mut instances: Vec<Holochain> = (0..10)
+            .map(|i| {
+                let dna = create_test_dna_with_wat("test_zome", "test_cap", None);
+                let (context, _) = test_context(&format!("agent {}", i));
+                let mut context = (*context).clone();
+                context.set_runtime(runtime.clone());
+                let mut hc = Holochain::new(dna, Arc::new(context)).unwrap();
+                hc.start().expect("couldn't start");
+                hc
+            })
+            .collect();
+        let after_instances = thread_count();
+
+        for hc in instances.iter_mut() {
+            let result = hc.call("test_zome", "test_cap", "main", "");
+            assert_eq!(result, Ok(JsonString::from("1337.0")));
+        }
+        let after_calls = thread_count();
+
+        println!(
+            "threads: {} before instantiation, {} after instantiation, {} after calls",
+            before_instances, after_instances, after_calls
+        );
+        // Zome calls run on the two runtime workers and don't add a thread per call
+        assert!(after_calls < after_instances + instances.len());
+    }
 }
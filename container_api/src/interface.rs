@@ -1,20 +1,691 @@
-use holochain_core::state::State;
+use container::{InstanceArchive, PublishedBeforeStart, StartupStates};
+use error::HolochainInstanceError;
+use holochain::{CrashProbe, InstanceEvent, PendingWorkProbe};
+use holochain_core::{
+    logger::Logger,
+    nucleus::state::MAX_HOST_CALL_TRACES,
+    runtime::{recv_helping, Runtime},
+    state::State,
+};
+use holochain_core_types::{error::ErrorTrail, json::JsonString};
+use holochain_wasm_utils::api_serialization::CallContext;
+use scenario::{wait_for_consistency, DEFAULT_CONSISTENCY_TIMEOUT_SECS};
+use signal_filter::PayloadFilter;
 use Holochain;
 
-use jsonrpc_ws_server::jsonrpc_core::{self, IoHandler, Value};
-use serde_json;
+use jsonrpc_ws_server::jsonrpc_core::{
+    self,
+    futures::{
+        future::{self, Either},
+        Future,
+    },
+    Call, ErrorCode, FutureResponse, Id, IoHandler, Middleware, Output, Params, Request, Response,
+    Value, Version,
+};
+use serde::Serialize;
+use serde_json::{self, Map};
 use std::{
-    collections::HashMap,
-    sync::{Arc, RwLock},
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{sync_channel, Receiver},
+        Arc, Mutex, RwLock,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use config::{Configuration, InstanceConfiguration};
+use config::{Configuration, InstanceConfiguration, InstanceReferenceConfiguration};
 
 pub type InterfaceError = String;
 pub type InstanceMap = HashMap<String, Arc<RwLock<Holochain>>>;
 
 pub trait DispatchRpc {
-    fn handler(self) -> IoHandler;
+    fn handler(self) -> IoHandler<CallMeta>;
+}
+
+/// How many partial results of a zome call can wait to be sent to the client,
+/// the call fails with BackpressureExceeded if more pile up
+pub const PARTIAL_RESULT_BUFFER_SIZE: usize = 100;
+
+/// Sends a message to the client over the connection the request came in on
+pub type PushSender = Arc<Fn(String) -> Result<(), String> + Send + Sync>;
+
+// connection ids are unique within the container, whatever interface a connection is on
+static NEXT_CONNECTION_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// What a client subscribed to: the events of an instance, optionally only the ones with
+/// the given notification methods as names whose payload, i.e. the notification params,
+/// passes a filter, see `signal_filter`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subscription {
+    pub id: u32,
+    pub instance_id: String,
+    /// Empty for all of them
+    pub signal_names: Vec<String>,
+    pub filter: Option<PayloadFilter>,
+}
+
+impl Subscription {
+    fn is_filtered(&self) -> bool {
+        !self.signal_names.is_empty() || self.filter.is_some()
+    }
+
+    /// Whether the event of the given instance passes the subscription
+    pub fn matches(&self, instance_id: &str, method: &str, params: &Value) -> bool {
+        self.instance_id == instance_id
+            && (self.signal_names.is_empty() || self.signal_names.iter().any(|name| name == method))
+            && self
+                .filter
+                .as_ref()
+                .map(|filter| filter.matches(params))
+                .unwrap_or(true)
+    }
+}
+
+/// A connection the server can push messages to, i.e. a websocket connection.
+/// Clients match responses to their requests by id, so the connection keeps track of the
+/// ids of the requests that are in flight and refuses a second request with the same id.
+/// It also keeps track of when it got opened, how many requests came in over it and
+/// when the last one did, of the instances its client subscribed to the events of and
+/// of the last event of each of them it acknowledged. A client can have several
+/// subscriptions to the events of an instance, each with its own filter.
+pub struct Connection {
+    id: u32,
+    push: PushSender,
+    disconnect: Option<Arc<Fn() + Send + Sync>>,
+    in_flight: Mutex<HashSet<Id>>,
+    subscriptions: Mutex<Vec<Subscription>>,
+    next_subscription_id: AtomicUsize,
+    acknowledged: Mutex<HashMap<String, u64>>,
+    requests: AtomicUsize,
+    connected_at: SystemTime,
+    last_request: Mutex<Instant>,
+    open: AtomicBool,
+}
+
+impl Connection {
+    pub fn new<F>(push: F) -> Self
+    where
+        F: Fn(String) -> Result<(), String> + Send + Sync + 'static,
+    {
+        Connection {
+            id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::SeqCst) as u32,
+            push: Arc::new(push),
+            disconnect: None,
+            in_flight: Mutex::new(HashSet::new()),
+            subscriptions: Mutex::new(Vec::new()),
+            next_subscription_id: AtomicUsize::new(1),
+            acknowledged: Mutex::new(HashMap::new()),
+            requests: AtomicUsize::new(0),
+            connected_at: SystemTime::now(),
+            last_request: Mutex::new(Instant::now()),
+            open: AtomicBool::new(true),
+        }
+    }
+
+    /// Sets what closes the underlying connection, e.g. the websocket, when the server
+    /// disconnects the client, see `Connections::disconnect()`
+    pub fn with_disconnect<F>(mut self, disconnect: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.disconnect = Some(Arc::new(disconnect));
+        self
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Sends a message to the client, fails once the connection is closed
+    pub fn push(&self, message: String) -> Result<(), String> {
+        if !self.is_open() {
+            return Err(String::from("Connection is closed"));
+        }
+        (self.push)(message)
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.load(Ordering::SeqCst)
+    }
+
+    /// The ids of the requests that did not get their response yet
+    pub fn in_flight(&self) -> HashSet<Id> {
+        self.in_flight.lock().unwrap().clone()
+    }
+
+    /// The ids of the instances the client subscribed to the events of
+    pub fn subscriptions(&self) -> HashSet<String> {
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|subscription| subscription.instance_id.clone())
+            .collect()
+    }
+
+    /// Subscribes the client to all events of the given instance,
+    /// false if it was subscribed to all of them already
+    pub fn subscribe(&self, instance_id: &str) -> bool {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        if subscriptions.iter().any(|subscription| {
+            subscription.instance_id == instance_id && !subscription.is_filtered()
+        }) {
+            return false;
+        }
+        subscriptions.push(Subscription {
+            id: self.next_subscription_id.fetch_add(1, Ordering::SeqCst) as u32,
+            instance_id: instance_id.to_string(),
+            signal_names: Vec::new(),
+            filter: None,
+        });
+        true
+    }
+
+    /// Subscribes the client to the events of the given instance that have one of the given
+    /// names, if there are any, and pass the filter, if there is one.
+    /// Returns the subscription, whose id unsubscribes it again.
+    pub fn subscribe_filtered(
+        &self,
+        instance_id: &str,
+        signal_names: Vec<String>,
+        filter: Option<PayloadFilter>,
+    ) -> Subscription {
+        let subscription = Subscription {
+            id: self.next_subscription_id.fetch_add(1, Ordering::SeqCst) as u32,
+            instance_id: instance_id.to_string(),
+            signal_names,
+            filter,
+        };
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .push(subscription.clone());
+        subscription
+    }
+
+    /// Removes all subscriptions to the events of the given instance,
+    /// false if the client had none
+    pub fn unsubscribe(&self, instance_id: &str) -> bool {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let count = subscriptions.len();
+        subscriptions.retain(|subscription| subscription.instance_id != instance_id);
+        subscriptions.len() < count
+    }
+
+    /// Removes the subscription with the given id, false if there is none
+    pub fn unsubscribe_by_id(&self, subscription_id: u32) -> bool {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let count = subscriptions.len();
+        subscriptions.retain(|subscription| subscription.id != subscription_id);
+        subscriptions.len() < count
+    }
+
+    /// Records that the client got the events of the given instance up to the sequence
+    /// number `seq`, false if it had acknowledged that one or a later one already
+    pub fn acknowledge(&self, instance_id: &str, seq: u64) -> bool {
+        let mut acknowledged = self.acknowledged.lock().unwrap();
+        let last = acknowledged.entry(instance_id.to_string()).or_insert(0);
+        if seq <= *last {
+            return false;
+        }
+        *last = seq;
+        true
+    }
+
+    /// The sequence number of the last acknowledged event of each instance
+    pub fn acknowledged(&self) -> HashMap<String, u64> {
+        self.acknowledged.lock().unwrap().clone()
+    }
+
+    /// Whether the client gets notified about the events of the given instance: clients that
+    /// did not subscribe to anything get the events of every instance, the others only
+    /// the ones of the instances they subscribed to
+    pub fn is_subscribed_to(&self, instance_id: &str) -> bool {
+        let subscriptions = self.subscriptions.lock().unwrap();
+        subscriptions.is_empty()
+            || subscriptions
+                .iter()
+                .any(|subscription| subscription.instance_id == instance_id)
+    }
+
+    /// Whether the client gets notified about the given event of the given instance:
+    /// like `is_subscribed_to()`, but the event has to pass one of the subscriptions
+    /// to the instance
+    pub fn wants_event(&self, instance_id: &str, method: &str, params: &Value) -> bool {
+        let subscriptions = self.subscriptions.lock().unwrap();
+        subscriptions.is_empty()
+            || subscriptions
+                .iter()
+                .any(|subscription| subscription.matches(instance_id, method, params))
+    }
+
+    /// Whether nothing happened on the connection for `max_idle`: no request came in for
+    /// that long, none is in flight and the client is not subscribed to any events
+    pub fn is_idle(&self, max_idle: Duration) -> bool {
+        self.in_flight.lock().unwrap().is_empty()
+            && self.subscriptions.lock().unwrap().is_empty()
+            && self.last_request.lock().unwrap().elapsed() >= max_idle
+    }
+
+    /// Cancels the requests in flight: zome calls that wait for their instance don't run
+    /// anymore and nothing gets sent to the client, neither partial results, responses nor
+    /// the events it subscribed to.
+    pub fn close(&self) {
+        self.open.store(false, Ordering::SeqCst);
+        self.in_flight.lock().unwrap().clear();
+        self.subscriptions.lock().unwrap().clear();
+    }
+
+    fn record_request(&self) {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        *self.last_request.lock().unwrap() = Instant::now();
+    }
+
+    // false if a request with the same id is in flight already
+    fn start_request(&self, id: &Id) -> bool {
+        self.in_flight.lock().unwrap().insert(id.clone())
+    }
+
+    fn finish_request(&self, id: &Id) {
+        self.in_flight.lock().unwrap().remove(id);
+    }
+}
+
+/// What admins get to know about a connection, see `admin/interface/connections`
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ConnectionInfo {
+    pub id: u32,
+    pub interface_id: String,
+    /// The token id the zome calls coming in over the connection are made with
+    pub token_id: String,
+    /// Seconds since the UNIX epoch
+    pub connected_at: u64,
+    pub requests: usize,
+    pub requests_in_flight: usize,
+    pub subscriptions: usize,
+    /// Sequence number of the last event the client acknowledged, by instance
+    pub acknowledged: HashMap<String, u64>,
+    /// Seconds since the last request came in, or since the connection got opened
+    pub idle_seconds: u64,
+}
+
+/// The open connections of an interface, so that server events reach all of its clients
+pub struct Connections {
+    interface_id: String,
+    open: Mutex<HashMap<u32, Arc<Connection>>>,
+}
+
+impl Connections {
+    pub fn new(interface_id: &str) -> Self {
+        Connections {
+            interface_id: interface_id.to_string(),
+            open: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn add(&self, connection: Arc<Connection>) {
+        self.open
+            .lock()
+            .unwrap()
+            .insert(connection.id(), connection);
+    }
+
+    /// Closes the connection, see `Connection::close()`, and forgets about it
+    pub fn remove(&self, connection_id: u32) {
+        if let Some(connection) = self.open.lock().unwrap().remove(&connection_id) {
+            connection.close();
+        }
+    }
+
+    /// Removes the connection and closes its underlying connection, which disconnects
+    /// the client. false if there is no such connection.
+    pub fn disconnect(&self, connection_id: u32) -> bool {
+        let connection = match self.open.lock().unwrap().remove(&connection_id) {
+            Some(connection) => connection,
+            None => return false,
+        };
+        connection.close();
+        if let Some(ref disconnect) = connection.disconnect {
+            disconnect();
+        }
+        true
+    }
+
+    /// Disconnects the connections that are idle for `max_idle`, see `Connection::is_idle()`,
+    /// and returns their ids
+    pub fn disconnect_idle(&self, max_idle: Duration) -> Vec<u32> {
+        let idle: Vec<u32> = self
+            .open
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|connection| connection.is_idle(max_idle))
+            .map(|connection| connection.id())
+            .collect();
+        idle.into_iter()
+            .filter(|connection_id| self.disconnect(*connection_id))
+            .collect()
+    }
+
+    pub fn count(&self) -> usize {
+        self.open.lock().unwrap().len()
+    }
+
+    /// The open connections, by id
+    pub fn list(&self) -> Vec<ConnectionInfo> {
+        let mut infos: Vec<ConnectionInfo> = self
+            .open
+            .lock()
+            .unwrap()
+            .values()
+            .map(|connection| ConnectionInfo {
+                id: connection.id(),
+                interface_id: self.interface_id.clone(),
+                // zome calls coming in over an interface carry its id as token
+                token_id: self.interface_id.clone(),
+                connected_at: connection
+                    .connected_at
+                    .duration_since(UNIX_EPOCH)
+                    .map(|since_epoch| since_epoch.as_secs())
+                    .unwrap_or(0),
+                requests: connection.requests.load(Ordering::SeqCst),
+                requests_in_flight: connection.in_flight.lock().unwrap().len(),
+                subscriptions: connection.subscriptions.lock().unwrap().len(),
+                acknowledged: connection.acknowledged(),
+                idle_seconds: connection.last_request.lock().unwrap().elapsed().as_secs(),
+            })
+            .collect();
+        infos.sort_by_key(|info| info.id);
+        infos
+    }
+
+    /// Sends a JSON-RPC notification to every client
+    pub fn notify(&self, method: &str, params: Value) {
+        let message = notification(method, params);
+        for connection in self.open.lock().unwrap().values() {
+            // a client that is going away doesn't need to know
+            let _ = connection.push(message.clone());
+        }
+    }
+
+    /// Sends a JSON-RPC notification about an event of the given instance to the clients
+    /// that want it, see `Connection::wants_event()`. A client gets it once, however many
+    /// of its subscriptions it passes.
+    pub fn notify_instance_event(&self, instance_id: &str, method: &str, params: Value) {
+        let message = notification(method, params.clone());
+        for connection in self.open.lock().unwrap().values() {
+            if connection.wants_event(instance_id, method, &params) {
+                let _ = connection.push(message.clone());
+            }
+        }
+    }
+}
+
+/// The connections of all interfaces of a container by interface id,
+/// so that admin interfaces can look after them
+pub type InterfaceConnections = Arc<RwLock<HashMap<String, Arc<Connections>>>>;
+
+/// Metadata of a JSON-RPC request. Interfaces that can push messages to their clients,
+/// i.e. the websocket interface, give it the connection of the request. Zome calls stream
+/// the partial results they yield through it, as `partial_result` notifications tagged with
+/// the id of the request. Without a connection, the partial results come before the return
+/// value in an array.
+#[derive(Clone, Default)]
+pub struct CallMeta {
+    connection: Option<Arc<Connection>>,
+    call_id: Option<Id>,
+}
+
+impl CallMeta {
+    pub fn with_connection(connection: Arc<Connection>) -> Self {
+        CallMeta {
+            connection: Some(connection),
+            call_id: None,
+        }
+    }
+
+    /// Whether the client went away before the request got handled
+    pub fn is_canceled(&self) -> bool {
+        self.connection
+            .as_ref()
+            .map(|connection| !connection.is_open())
+            .unwrap_or(false)
+    }
+}
+
+impl jsonrpc_core::Metadata for CallMeta {}
+
+impl fmt::Debug for CallMeta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CallMeta")
+            .field("connection", &self.connection.is_some())
+            .field("call_id", &self.call_id)
+            .finish()
+    }
+}
+
+/// Puts the id of every single request into its CallMeta, so its partial results
+/// can be tagged with it. Requests that come with a connection are counted and tracked as
+/// in flight on it until they got their response, a request reusing the id of one of them
+/// is invalid.
+#[derive(Default)]
+pub struct CallIdMiddleware;
+
+impl Middleware<CallMeta> for CallIdMiddleware {
+    type Future = FutureResponse;
+
+    fn on_request<F, X>(&self, request: Request, meta: CallMeta, next: F) -> Either<Self::Future, X>
+    where
+        F: FnOnce(Request, CallMeta) -> X + Send,
+        X: Future<Item = Option<Response>, Error = ()> + Send + 'static,
+    {
+        if let Some(ref connection) = meta.connection {
+            connection.record_request();
+        }
+        let call_id = match request {
+            Request::Single(Call::MethodCall(ref method_call)) => Some(method_call.id.clone()),
+            _ => None,
+        };
+        let (id, connection) = match (call_id.clone(), meta.connection.clone()) {
+            (Some(id), Some(connection)) => (id, connection),
+            _ => return Either::B(next(request, CallMeta { call_id, ..meta })),
+        };
+        if !connection.start_request(&id) {
+            let error = jsonrpc_core::Error {
+                code: ErrorCode::InvalidRequest,
+                message: format!(
+                    "Invalid request: id {} is in use by a pending request",
+                    serde_json::to_string(&id).unwrap_or_default()
+                ),
+                data: None,
+            };
+            let response = Response::Single(Output::from(Err(error), id, Some(Version::V2)));
+            return Either::A(Box::new(future::ok::<_, ()>(Some(response))));
+        }
+        let response = next(request, CallMeta { call_id, ..meta }).then(move |response| {
+            connection.finish_request(&id);
+            response
+        });
+        Either::A(Box::new(response))
+    }
+}
+
+/// Where an interface writes its request log to and the functions whose params get logged,
+/// see `RequestLogMiddleware`
+#[derive(Clone)]
+pub struct RequestLog {
+    logger: Arc<Mutex<Logger>>,
+    log_params_for: HashSet<String>,
+}
+
+impl RequestLog {
+    pub fn new(logger: Arc<Mutex<Logger>>, log_params_for: &[String]) -> Self {
+        RequestLog {
+            logger,
+            log_params_for: log_params_for.iter().cloned().collect(),
+        }
+    }
+
+    // the line of a request that still misses how it went
+    fn start_line(&self, request: &Request, meta: &CallMeta, token_id: &str) -> Map<String, Value> {
+        let mut line = Map::new();
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_secs() * 1000 + since_epoch.subsec_millis() as u64)
+            .unwrap_or(0);
+        line.insert("time".to_string(), Value::from(time));
+        line.insert(
+            "connection_id".to_string(),
+            meta.connection
+                .as_ref()
+                .map(|connection| Value::from(connection.id()))
+                .unwrap_or(Value::Null),
+        );
+        line.insert("token_id".to_string(), Value::from(token_id));
+        let (method, params) = match request {
+            Request::Single(Call::MethodCall(method_call)) => {
+                (method_call.method.as_str(), &method_call.params)
+            }
+            Request::Single(Call::Notification(notification)) => {
+                (notification.method.as_str(), &notification.params)
+            }
+            Request::Single(Call::Invalid { .. }) => {
+                line.insert("method".to_string(), Value::Null);
+                return line;
+            }
+            Request::Batch(calls) => {
+                line.insert("method".to_string(), Value::Null);
+                line.insert("batch".to_string(), Value::from(calls.len()));
+                line.insert("params_bytes".to_string(), Value::from(json_size(&request)));
+                return line;
+            }
+        };
+        // zome calls are {instance_id}/{zome}/{cap}/{func}, other methods are their own function
+        let parts: Vec<&str> = method.split('/').collect();
+        let (instance, zome, function) = match parts.as_slice() {
+            [instance, zome, _, function] => (Some(*instance), Some(*zome), *function),
+            _ => (None, None, method),
+        };
+        line.insert("method".to_string(), Value::from(method));
+        let name_or_null = |name: Option<&str>| name.map(Value::from).unwrap_or(Value::Null);
+        line.insert("instance".to_string(), name_or_null(instance));
+        line.insert("zome".to_string(), name_or_null(zome));
+        line.insert("function".to_string(), Value::from(function));
+        line.insert("params_bytes".to_string(), Value::from(json_size(params)));
+        if self.log_params_for.contains(function) {
+            line.insert(
+                "params".to_string(),
+                serde_json::to_value(params).unwrap_or(Value::Null),
+            );
+        }
+        line
+    }
+
+    // completes the line with how the request went and writes it to the log
+    fn finish_line(
+        &self,
+        mut line: Map<String, Value>,
+        response: &Option<Response>,
+        duration: Duration,
+    ) {
+        let duration_ms = duration.as_secs() * 1000 + duration.subsec_millis() as u64;
+        line.insert("duration_ms".to_string(), Value::from(duration_ms));
+        let failure = |output: &Output| match output {
+            Output::Failure(failure) => Some(failure.error.code.code()),
+            Output::Success(_) => None,
+        };
+        let error_code = match response {
+            Some(Response::Single(output)) => failure(output),
+            Some(Response::Batch(outputs)) => outputs.iter().filter_map(failure).next(),
+            // notifications don't get a response
+            None => None,
+        };
+        line.insert(
+            "outcome".to_string(),
+            error_code.map(Value::from).unwrap_or(Value::from("ok")),
+        );
+        line.insert(
+            "response_bytes".to_string(),
+            Value::from(response.as_ref().map(json_size).unwrap_or(0)),
+        );
+        self.logger
+            .lock()
+            .unwrap()
+            .log(Value::Object(line).to_string());
+    }
+}
+
+fn json_size<T: Serialize>(value: &T) -> usize {
+    serde_json::to_string(value)
+        .map(|json| json.len())
+        .unwrap_or(0)
+}
+
+/// Writes a line to the request log of the interface, if it has one, for every request that
+/// comes in. The line is a JSON object with when the request came in as `time` in
+/// milliseconds since the UNIX epoch, the `connection_id` of the connection it came in over,
+/// the `token_id` of the interface, the `method` with the `instance`, `zome` and `function`
+/// it called, the size of its params as `params_bytes`, how long it took as `duration_ms`,
+/// its `outcome`, which is "ok" or the code of its error, and the size of the response as
+/// `response_bytes`. Params and results can be private, so only their size gets logged.
+/// Only the functions in the `log_params_for` allow-list get their `params` logged as well.
+#[derive(Clone, Default)]
+pub struct RequestLogMiddleware {
+    token_id: String,
+    log: Option<RequestLog>,
+}
+
+impl Middleware<CallMeta> for RequestLogMiddleware {
+    type Future = FutureResponse;
+
+    fn on_request<F, X>(&self, request: Request, meta: CallMeta, next: F) -> Either<Self::Future, X>
+    where
+        F: FnOnce(Request, CallMeta) -> X + Send,
+        X: Future<Item = Option<Response>, Error = ()> + Send + 'static,
+    {
+        let log = match self.log {
+            Some(ref log) => log.clone(),
+            None => return Either::B(next(request, meta)),
+        };
+        let line = log.start_line(&request, &meta, &self.token_id);
+        let started = Instant::now();
+        let response = next(request, meta).map(move |response| {
+            log.finish_line(line, &response, started.elapsed());
+            response
+        });
+        Either::A(Box::new(response))
+    }
+}
+
+/// The method of the notification that tells clients about an event of one of the instances
+fn event_method(event: &InstanceEvent) -> &'static str {
+    match event {
+        InstanceEvent::Started => EVENT_METHODS[0],
+        InstanceEvent::Stopped => EVENT_METHODS[1],
+        InstanceEvent::Archived => EVENT_METHODS[2],
+    }
+}
+
+/// The names of all the events of instances, which are all the signals clients can subscribe to
+const EVENT_METHODS: [&str; 3] = ["instance_started", "instance_stopped", "instance_archived"];
+
+/// The params of the notification about the event of an instance with the given sequence number
+fn event_params(instance_id: &str, seq: u64) -> Value {
+    let mut params = Map::new();
+    params.insert("instance_id".to_string(), Value::from(instance_id));
+    params.insert("seq".to_string(), Value::from(seq));
+    Value::Object(params)
+}
+
+fn notification(method: &str, params: Value) -> String {
+    let mut notification = Map::new();
+    notification.insert("jsonrpc".to_string(), Value::from("2.0"));
+    notification.insert("method".to_string(), Value::from(method));
+    notification.insert("params".to_string(), params);
+    Value::Object(notification).to_string()
 }
 
 /// ContainerApiDispatcher exposes some subset of the Container API,
@@ -22,44 +693,819 @@ pub trait DispatchRpc {
 /// Each interface has their own dispatcher, and each may be configured differently.
 pub struct ContainerApiDispatcher {
     instances: InstanceMap,
-    pub io: IoHandler,
+    connections: Arc<Connections>,
+    // the calls of parallel batches run on it, if it is set, see `with_runtime()`
+    runtime: Arc<RwLock<Option<Runtime>>>,
+    request_log: RequestLogMiddleware,
+    // whether the admin methods got added, see `with_admin_api()`
+    admin: bool,
+    pub io: IoHandler<CallMeta>,
 }
 
 unsafe impl Send for ContainerApiDispatcher {}
 
+/// A single zome function call inside of a batch request
+#[derive(Clone, Deserialize)]
+struct BatchCall {
+    instance: String,
+    zome: String,
+    /// The capability of the function, if there are functions of the same name in several
+    #[serde(default)]
+    cap: Option<String>,
+    function: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum BatchMode {
+    Parallel,
+    Sequential,
+}
+
+impl Default for BatchMode {
+    fn default() -> Self {
+        BatchMode::Sequential
+    }
+}
+
+#[derive(Deserialize)]
+struct BatchParams {
+    calls: Vec<BatchCall>,
+    #[serde(default)]
+    mode: BatchMode,
+    /// Only used in sequential mode: skip the remaining calls after the first failing one
+    #[serde(default)]
+    stop_on_error: bool,
+}
+
 /// Implements routing for JSON-RPC calls:
 /// {instance_id}/{zome}/{cap}/{func} -> a zome call
-/// info/list_instances               -> Map of InstanceConfigs, keyed by ID
+/// info/list_instances               -> Map of InstanceConfigs, keyed by ID, with the build info
+///                                      of their DNA if it has some
+/// info/functions                    -> Sorted list of the zome call methods above
+/// batch                             -> several zome calls, answered by an array of results
+/// info/status                       -> Whether an instance is running, stopped, archived or
+///                                      crashed, how often it got restarted, its chain info and network connection, its DNA's build info,
+///                                      its validation package cache hits and misses,
+///                                      what it is waiting on and where it is in the container's startup
+/// debug/dump_state                  -> Pretty-printed chain and DHT summary of an instance
+/// events/subscribe                  -> Get the events of an instance over this connection,
+///                                      optionally only the ones with some names that pass a filter
+/// events/unsubscribe                -> Stop getting them, by instance or by subscription id
+/// events/ack                        -> Acknowledge the events of an instance up to a seq
+/// admin/instance/revalidate_shard   -> Validate an instance's DHT shard again (admin only)
+/// admin/instance/cancel_revalidation -> Stop that revalidation (admin only)
+/// admin/instance/gc                 -> Remove unreferenced content from an instance's storage
+///                                      and report what that freed up (admin only)
+/// admin/instance/flush              -> Wait until an instance's pending commits are on the
+///                                      disk (admin only)
+/// admin/instance/last_calls         -> The host functions the latest audited zome calls of an
+///                                      instance invoked (admin only)
+/// admin/instance/metrics            -> How long the calls of each zome function of an instance
+///                                      took (admin only)
+/// admin/instance/pending            -> The zome calls, validations and network requests an
+///                                      instance is waiting on, also while it is busy (admin only)
+/// admin/instance/crash              -> Whether and why an instance's action loop crashed,
+///                                      also while it is busy (admin only)
+/// admin/instance/archive            -> Stop an instance and keep it from starting (admin only)
+/// admin/instance/restore            -> Start an archived instance again (admin only)
+/// admin/instance/purge              -> Delete an archived instance's storage (admin only)
+/// admin/interface/connections       -> The open connections of the interfaces, with their
+///                                      request and subscription counts (admin only)
+/// admin/interface/disconnect        -> Close a connection (admin only)
 /// admin/...                         -> TODO
+/// test/consistency                 -> Wait until the given instances are done with the network,
+///                                      their validations and flushing their commits
+///                                      (containers in test mode only)
+///
+/// Only the zome functions that pass the allow-lists of the given instance references
+/// get a method. Calling a hidden function therefore fails exactly like calling
+/// a function that does not exist.
+///
+/// Zome functions called through the dispatcher see a CallContext::Interface with the
+/// given interface id as token. The partial results they yield get pushed to the client
+/// or returned before their return value, see CallMeta.
+/// Clients that are connected get notified when one of the instances gets started, stopped
+/// or archived, with an `instance_started`, `instance_stopped` or `instance_archived`
+/// notification that has the id of the instance as `instance_id` param and the sequence
+/// number of the event as `seq`. Once a client subscribed to the events of some instances,
+/// it only gets the events of those. Instances with a `signal_buffer_size` keep their latest
+/// events, which clients that subscribe with a `since_seq` get right away, see
+/// `setup_subscription_api()`.
 impl ContainerApiDispatcher {
-    pub fn new(config: &Configuration, instances: InstanceMap) -> Self {
+    pub fn new(
+        config: &Configuration,
+        instances: InstanceMap,
+        instance_references: &[InstanceReferenceConfiguration],
+        interface_id: &str,
+    ) -> Self {
         let instance_configs = config
             .instances
             .iter()
+            .filter(|inst| instances.contains_key(&inst.id))
             .map(|inst| (inst.id.clone(), inst.clone()))
             .collect();
         let io = IoHandler::new();
-        let mut this = Self { instances, io };
+        let mut this = Self {
+            instances,
+            connections: Arc::new(Connections::new(interface_id)),
+            runtime: Arc::new(RwLock::new(None)),
+            request_log: RequestLogMiddleware {
+                token_id: interface_id.to_string(),
+                log: None,
+            },
+            admin: false,
+            io,
+        };
+        let call_context = CallContext::Interface {
+            token_id: interface_id.to_string(),
+        };
         this.setup_info_api(instance_configs);
-        this.setup_zome_api();
+        this.setup_status_api(StartupStates::default());
+        this.setup_debug_api();
+        let zome_methods = this.setup_zome_api(instance_references, &call_context);
+        this.setup_functions_api(&zome_methods);
+        this.setup_batch_api(zome_methods, call_context);
+        this.setup_event_notifications();
+        this.setup_subscription_api();
         this
     }
 
+    /// The connections of the clients of this dispatcher's interface
+    pub fn connections(&self) -> Arc<Connections> {
+        self.connections.clone()
+    }
+
+    /// Runs the calls of parallel batches on the given (shared) runtime instead of on a
+    /// thread of their own each
+    pub fn with_runtime(self, runtime: Runtime) -> Self {
+        *self.runtime.write().unwrap() = Some(runtime);
+        self
+    }
+
+    /// Logs the requests of this dispatcher's interface to the given log
+    pub fn with_request_log(mut self, log: RequestLog) -> Self {
+        self.request_log.log = Some(log);
+        self
+    }
+
+    /// The middleware interfaces put in front of the dispatcher, so that their requests
+    /// get logged if they have a request log
+    pub fn request_log_middleware(&self) -> RequestLogMiddleware {
+        self.request_log.clone()
+    }
+
+    /// The instances this dispatcher calls, e.g. for `prometheus_metrics()`
+    pub fn instances(&self) -> InstanceMap {
+        self.instances.clone()
+    }
+
+    /// Whether this dispatcher serves an admin interface, see `with_admin_api()`
+    pub fn is_admin(&self) -> bool {
+        self.admin
+    }
+
+    // tell the clients about the events of the instances, e.g. one getting stopped
+    // by an admin interface
+    fn setup_event_notifications(&mut self) {
+        for (instance_id, hc_lock) in self.instances.iter() {
+            let connections = Arc::downgrade(&self.connections);
+            let instance_id = instance_id.clone();
+            hc_lock
+                .write()
+                .unwrap()
+                .add_event_listener(move |event, seq| {
+                    if let Some(connections) = connections.upgrade() {
+                        connections.notify_instance_event(
+                            &instance_id,
+                            event_method(event),
+                            event_params(&instance_id, seq),
+                        );
+                    }
+                });
+        }
+    }
+
+    // initialize json rpc methods for subscribing the connection of the request to the events
+    // of the instance given as {"instance_id": ..}, for unsubscribing it again and for
+    // acknowledging the events it got as {"instance_id": .., "seq": ..}.
+    // Subscribing with "signal_names": [..] or a "filter" expression, see `signal_filter`,
+    // adds a subscription to only those events of the instance, whose names have to be in
+    // `EVENT_METHODS`, and responds with
+    // {"subscribed": true, "subscription_id": ..}. A connection can have many of them,
+    // {"subscription_id": ..} unsubscribes one, {"instance_id": ..} all of the instance.
+    // Subscribing with a "since_seq" first pushes the buffered events after that sequence
+    // number that pass the subscription, then responds with
+    // {"subscribed": .., "backlog": <count>, "gap": ..}, where gap tells that some of the
+    // events after it got dropped from the buffer already.
+    fn setup_subscription_api(&mut self) {
+        #[derive(Deserialize)]
+        struct SubscriptionParams {
+            instance_id: String,
+            #[serde(default)]
+            since_seq: Option<u64>,
+            #[serde(default)]
+            signal_names: Vec<String>,
+            #[serde(default)]
+            filter: Option<String>,
+        }
+        #[derive(Deserialize)]
+        struct UnsubscriptionParams {
+            #[serde(default)]
+            instance_id: Option<String>,
+            #[serde(default)]
+            subscription_id: Option<u32>,
+        }
+        #[derive(Deserialize)]
+        struct AckParams {
+            instance_id: String,
+            seq: u64,
+        }
+        fn connection_of(meta: &CallMeta) -> Result<Arc<Connection>, jsonrpc_core::Error> {
+            meta.connection.clone().ok_or_else(|| {
+                jsonrpc_core::Error::invalid_params(
+                    "Subscriptions need a connection, e.g. a websocket one",
+                )
+            })
+        }
+        let instances = self.instances.clone();
+        self.io
+            .add_method_with_meta("events/subscribe", move |params: Params, meta: CallMeta| {
+                let params: SubscriptionParams = params.parse()?;
+                let hc_lock = get_instance(&instances, &params.instance_id)?;
+                let connection = connection_of(&meta)?;
+                let filter = match params.filter {
+                    Some(ref filter) => Some(PayloadFilter::parse(filter).map_err(|error| {
+                        jsonrpc_core::Error::invalid_params(format!("Invalid filter: {}", error))
+                    })?),
+                    None => None,
+                };
+                if let Some(unknown) = params
+                    .signal_names
+                    .iter()
+                    .find(|name| !EVENT_METHODS.contains(&name.as_str()))
+                {
+                    return Err(jsonrpc_core::Error::invalid_params(format!(
+                        "Unknown signal name: {}, instances only signal {}",
+                        unknown,
+                        EVENT_METHODS.join(", ")
+                    )));
+                }
+                let filtered = !params.signal_names.is_empty() || filter.is_some();
+                if !filtered && params.since_seq.is_none() {
+                    return Ok(Value::Bool(connection.subscribe(&params.instance_id)));
+                }
+                // instances emit their events while locked for writing, so none can come
+                // in between the backlog and the events the subscription gets
+                let hc = hc_lock.read().unwrap();
+                let mut result = Map::new();
+                let subscription = if filtered {
+                    let subscription = connection.subscribe_filtered(
+                        &params.instance_id,
+                        params.signal_names.clone(),
+                        filter,
+                    );
+                    result.insert("subscribed".to_string(), Value::Bool(true));
+                    result.insert("subscription_id".to_string(), Value::from(subscription.id));
+                    Some(subscription)
+                } else {
+                    let subscribed = connection.subscribe(&params.instance_id);
+                    result.insert("subscribed".to_string(), Value::Bool(subscribed));
+                    None
+                };
+                if let Some(since_seq) = params.since_seq {
+                    let backlog = hc.events_since(since_seq);
+                    let mut pushed = 0;
+                    for buffered in backlog.events.iter() {
+                        let method = event_method(&buffered.event);
+                        let payload = event_params(&params.instance_id, buffered.seq);
+                        let passes = subscription
+                            .as_ref()
+                            .map(|subscription| {
+                                subscription.matches(&params.instance_id, method, &payload)
+                            })
+                            .unwrap_or(true);
+                        if passes {
+                            let _ = connection.push(notification(method, payload));
+                            pushed += 1;
+                        }
+                    }
+                    result.insert("backlog".to_string(), Value::from(pushed));
+                    result.insert("gap".to_string(), Value::Bool(backlog.gap));
+                }
+                Ok(Value::Object(result))
+            });
+        self.io.add_method_with_meta(
+            "events/unsubscribe",
+            move |params: Params, meta: CallMeta| {
+                let params: UnsubscriptionParams = params.parse()?;
+                let connection = connection_of(&meta)?;
+                match (params.subscription_id, params.instance_id) {
+                    (Some(subscription_id), _) => {
+                        Ok(Value::Bool(connection.unsubscribe_by_id(subscription_id)))
+                    }
+                    (None, Some(instance_id)) => {
+                        Ok(Value::Bool(connection.unsubscribe(&instance_id)))
+                    }
+                    (None, None) => Err(jsonrpc_core::Error::invalid_params(
+                        "Unsubscribing needs an instance_id or a subscription_id",
+                    )),
+                }
+            },
+        );
+        self.io
+            .add_method_with_meta("events/ack", move |params: Params, meta: CallMeta| {
+                let params: AckParams = params.parse()?;
+                let connection = connection_of(&meta)?;
+                Ok(Value::Bool(
+                    connection.acknowledge(&params.instance_id, params.seq),
+                ))
+            });
+    }
+
     // initialize a json rpc method for accessing which instances exist
     fn setup_info_api(&mut self, instance_configs: HashMap<String, InstanceConfiguration>) {
+        let instances = self.instances.clone();
         self.io.add_method("info/instances", move |_| {
-            let configs = instance_configs.clone();
-            let config_string = serde_json::to_string(&configs)
-                .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
-            Ok(Value::String(config_string))
+            let mut configs = Map::new();
+            for (id, config) in instance_configs.iter() {
+                let mut config = serde_json::to_value(config)
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
+                let (build_info, dna_hash) = match instances.get(id) {
+                    Some(hc_lock) => {
+                        let hc = hc_lock.read().unwrap();
+                        (
+                            hc.build_info().map_err(instance_error)?,
+                            hc.dna_hash().map_err(instance_error)?,
+                        )
+                    }
+                    None => (None, None),
+                };
+                if let (Some(build_info), Some(config)) = (build_info, config.as_object_mut()) {
+                    config.insert(
+                        "build_info".to_string(),
+                        serde_json::to_value(build_info)
+                            .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?,
+                    );
+                }
+                if let (Some(dna_hash), Some(config)) = (dna_hash, config.as_object_mut()) {
+                    config.insert("dna_hash".to_string(), Value::String(dna_hash.to_string()));
+                }
+                configs.insert(id.clone(), config);
+            }
+            Ok(Value::String(Value::Object(configs).to_string()))
+        });
+    }
+
+    /// Lets the status API report where the instances are in the container's startup.
+    /// Instances that failed to start only show their startup state.
+    pub fn with_startup_states(mut self, startup_states: StartupStates) -> Self {
+        self.setup_status_api(startup_states);
+        self
+    }
+
+    // initialize a json rpc method that returns the status of the instance given as
+    // {"instance_id": ..}
+    fn setup_status_api(&mut self, startup_states: StartupStates) {
+        let instances = self.instances.clone();
+        self.io.add_method("info/status", move |params: Params| {
+            #[derive(Deserialize)]
+            struct StatusParams {
+                instance_id: String,
+            }
+            let params: StatusParams = params.parse()?;
+            let mut status = Map::new();
+            let startup_state = startup_states
+                .read()
+                .unwrap()
+                .get(&params.instance_id)
+                .cloned();
+            if let Some(ref startup_state) = startup_state {
+                status.insert(
+                    "startup".to_string(),
+                    serde_json::to_value(startup_state)
+                        .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?,
+                );
+            }
+            let hc_lock = match instances.get(&params.instance_id) {
+                Some(hc_lock) => hc_lock,
+                None if startup_state.is_some() => return Ok(Value::Object(status)),
+                None => {
+                    return Err(jsonrpc_core::Error::invalid_params(format!(
+                        "Unknown instance {}",
+                        params.instance_id
+                    )))
+                }
+            };
+            let hc = hc_lock.read().unwrap();
+            let chain_info = hc.chain_info().map_err(instance_error)?;
+            status.insert("active".to_string(), Value::Bool(hc.active()));
+            status.insert(
+                "state".to_string(),
+                serde_json::to_value(hc.status())
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?,
+            );
+            status.insert(
+                "crash".to_string(),
+                serde_json::to_value(hc.last_crash())
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?,
+            );
+            status.insert("restarts".to_string(), Value::from(hc.restarts()));
+            status.insert(
+                "chain_info".to_string(),
+                serde_json::to_value(chain_info)
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?,
+            );
+            let network_status = hc.network_status().map_err(instance_error)?;
+            status.insert(
+                "network".to_string(),
+                serde_json::to_value(network_status)
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?,
+            );
+            let zome_call_stats = hc.zome_call_stats().map_err(instance_error)?;
+            status.insert(
+                "zome_calls".to_string(),
+                serde_json::to_value(zome_call_stats)
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?,
+            );
+            status.insert(
+                "validation_package_cache".to_string(),
+                serde_json::to_value(hc.validation_package_cache_stats())
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?,
+            );
+            let held_count = hc.held_count().map_err(instance_error)?;
+            status.insert("held_entries".to_string(), Value::from(held_count));
+            if let Some(build_info) = hc.build_info().map_err(instance_error)? {
+                status.insert(
+                    "build_info".to_string(),
+                    serde_json::to_value(build_info)
+                        .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?,
+                );
+            }
+            let pending = hc.pending().map_err(instance_error)?;
+            status.insert(
+                "pending".to_string(),
+                serde_json::to_value(pending)
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?,
+            );
+            status.insert(
+                "revalidation".to_string(),
+                serde_json::to_value(hc.revalidation_report())
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?,
+            );
+            Ok(Value::Object(status))
         });
     }
 
-    // initialize json rpc methods for accessing all zomes' functions
-    fn setup_zome_api(&mut self) {
+    /// Adds the methods that only admin interfaces expose.
+    /// Archiving, restoring and purging instances goes through the given archive.
+    pub fn with_admin_api(mut self, archive: InstanceArchive) -> Self {
+        self.admin = true;
+        self.setup_admin_api();
+        self.setup_archive_api(archive);
+        self
+    }
+
+    // initialize json rpc methods for starting and canceling the revalidation of the
+    // DHT shard and for collecting garbage in the storage of the instance given as
+    // {"instance_id": ..}
+    fn setup_admin_api(&mut self) {
+        #[derive(Deserialize)]
+        struct InstanceParams {
+            instance_id: String,
+        }
+        let instances = self.instances.clone();
+        self.io
+            .add_method("admin/instance/revalidate_shard", move |params: Params| {
+                let params: InstanceParams = params.parse()?;
+                let hc_lock = get_instance(&instances, &params.instance_id)?;
+                let mut hc = hc_lock.write().unwrap();
+                hc.revalidate_shard()
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
+                Ok(Value::Bool(true))
+            });
+        let instances = self.instances.clone();
+        self.io.add_method(
+            "admin/instance/cancel_revalidation",
+            move |params: Params| {
+                let params: InstanceParams = params.parse()?;
+                let hc_lock = get_instance(&instances, &params.instance_id)?;
+                let hc = hc_lock.read().unwrap();
+                hc.cancel_revalidation()
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
+                Ok(Value::Bool(true))
+            },
+        );
+        let instances = self.instances.clone();
+        self.io
+            .add_method("admin/instance/last_calls", move |params: Params| {
+                #[derive(Deserialize)]
+                struct LastCallsParams {
+                    instance_id: String,
+                    #[serde(default)]
+                    count: Option<usize>,
+                }
+                let params: LastCallsParams = params.parse()?;
+                let hc_lock = get_instance(&instances, &params.instance_id)?;
+                let last_calls = hc_lock
+                    .read()
+                    .unwrap()
+                    .last_calls(params.count.unwrap_or(MAX_HOST_CALL_TRACES))
+                    .map_err(instance_error)?;
+                serde_json::to_value(last_calls)
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))
+            });
+        let instances = self.instances.clone();
+        self.io
+            .add_method("admin/instance/metrics", move |params: Params| {
+                let params: InstanceParams = params.parse()?;
+                let hc_lock = get_instance(&instances, &params.instance_id)?;
+                let latencies = hc_lock.read().unwrap().zome_fn_latencies();
+                let mut result = Map::new();
+                result.insert(
+                    "zome_functions".to_string(),
+                    serde_json::to_value(latencies)
+                        .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?,
+                );
+                Ok(Value::Object(result))
+            });
+        // read without the instance locks, so that the calls an instance is busy with
+        // show up while they run
+        let probes: HashMap<String, PendingWorkProbe> = self
+            .instances
+            .iter()
+            .map(|(id, hc_lock)| (id.clone(), hc_lock.read().unwrap().pending_work_probe()))
+            .collect();
+        self.io
+            .add_method("admin/instance/pending", move |params: Params| {
+                let params: InstanceParams = params.parse()?;
+                let probe = probes.get(&params.instance_id).ok_or_else(|| {
+                    jsonrpc_core::Error::invalid_params(format!(
+                        "Unknown instance {}",
+                        params.instance_id
+                    ))
+                })?;
+                let pending = probe.pending().map_err(instance_error)?;
+                serde_json::to_value(pending)
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))
+            });
+        let crash_probes: HashMap<String, CrashProbe> = self
+            .instances
+            .iter()
+            .map(|(id, hc_lock)| (id.clone(), hc_lock.read().unwrap().crash_probe()))
+            .collect();
+        self.io
+            .add_method("admin/instance/crash", move |params: Params| {
+                let params: InstanceParams = params.parse()?;
+                let probe = crash_probes.get(&params.instance_id).ok_or_else(|| {
+                    jsonrpc_core::Error::invalid_params(format!(
+                        "Unknown instance {}",
+                        params.instance_id
+                    ))
+                })?;
+                let crash = probe.crash_report();
+                let mut result = Map::new();
+                result.insert("crashed".to_string(), Value::Bool(crash.is_some()));
+                result.insert(
+                    "crash".to_string(),
+                    serde_json::to_value(crash)
+                        .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?,
+                );
+                Ok(Value::Object(result))
+            });
+        let instances = self.instances.clone();
+        self.io
+            .add_method("admin/instance/gc", move |params: Params| {
+                let params: InstanceParams = params.parse()?;
+                let hc_lock = get_instance(&instances, &params.instance_id)?;
+                let report = hc_lock
+                    .read()
+                    .unwrap()
+                    .collect_garbage()
+                    .map_err(instance_error)?;
+                serde_json::to_value(report)
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))
+            });
+        let instances = self.instances.clone();
+        self.io
+            .add_method("admin/instance/flush", move |params: Params| {
+                let params: InstanceParams = params.parse()?;
+                let hc_lock = get_instance(&instances, &params.instance_id)?;
+                let flushed_commits = hc_lock.read().unwrap().flush().map_err(instance_error)?;
+                let mut result = Map::new();
+                result.insert("flushed_commits".to_string(), Value::from(flushed_commits));
+                Ok(Value::Object(result))
+            });
+    }
+
+    /// Adds the methods with which admin interfaces look after the connections of
+    /// the given interfaces
+    pub fn with_connections_admin_api(
+        mut self,
+        interface_connections: InterfaceConnections,
+    ) -> Self {
+        self.setup_connections_api(interface_connections);
+        self
+    }
+
+    // initialize json rpc methods for listing the connections of all interfaces, or of the
+    // one given as {"interface_id": ..}, and for disconnecting the one given as
+    // {"connection_id": ..}
+    fn setup_connections_api(&mut self, interface_connections: InterfaceConnections) {
+        #[derive(Default, Deserialize)]
+        struct ListParams {
+            #[serde(default)]
+            interface_id: Option<String>,
+        }
+        #[derive(Deserialize)]
+        struct DisconnectParams {
+            connection_id: u32,
+        }
+        let all_connections = interface_connections.clone();
+        self.io
+            .add_method("admin/interface/connections", move |params: Params| {
+                let params: ListParams = match params {
+                    Params::None => ListParams::default(),
+                    params => params.parse()?,
+                };
+                let all_connections = all_connections.read().unwrap();
+                let mut interface_ids: Vec<&String> = all_connections.keys().collect();
+                interface_ids.sort();
+                let infos: Vec<ConnectionInfo> = interface_ids
+                    .into_iter()
+                    .filter(|id| {
+                        params
+                            .interface_id
+                            .as_ref()
+                            .map_or(true, |wanted| wanted == *id)
+                    })
+                    .flat_map(|id| all_connections[id].list())
+                    .collect();
+                serde_json::to_value(infos)
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))
+            });
+        self.io
+            .add_method("admin/interface/disconnect", move |params: Params| {
+                let params: DisconnectParams = params.parse()?;
+                let disconnected = interface_connections
+                    .read()
+                    .unwrap()
+                    .values()
+                    .any(|connections| connections.disconnect(params.connection_id));
+                if !disconnected {
+                    return Err(jsonrpc_core::Error::invalid_params(format!(
+                        "Unknown connection {}",
+                        params.connection_id
+                    )));
+                }
+                Ok(Value::Bool(true))
+            });
+    }
+
+    // initialize json rpc methods for archiving, restoring and purging the instance given as
+    // {"instance_id": ..}. Purging takes two calls: the first one returns a confirmation token,
+    // the second one has to send it back as {"instance_id": .., "confirmation_token": ..}.
+    fn setup_archive_api(&mut self, archive: InstanceArchive) {
+        #[derive(Deserialize)]
+        struct ArchiveParams {
+            instance_id: String,
+            #[serde(default)]
+            confirmation_token: Option<String>,
+        }
+        let instances = self.instances.clone();
+        let instance_archive = archive.clone();
+        self.io
+            .add_method("admin/instance/archive", move |params: Params| {
+                let params: ArchiveParams = params.parse()?;
+                let hc_lock = get_instance(&instances, &params.instance_id)?;
+                instance_archive
+                    .archive(&params.instance_id, hc_lock)
+                    .map_err(jsonrpc_core::Error::invalid_params)?;
+                Ok(Value::Bool(true))
+            });
+        let instances = self.instances.clone();
+        let instance_archive = archive.clone();
+        self.io
+            .add_method("admin/instance/restore", move |params: Params| {
+                let params: ArchiveParams = params.parse()?;
+                let hc_lock = get_instance(&instances, &params.instance_id)?;
+                instance_archive
+                    .restore(&params.instance_id, hc_lock)
+                    .map_err(jsonrpc_core::Error::invalid_params)?;
+                Ok(Value::Bool(true))
+            });
+        let instances = self.instances.clone();
+        self.io
+            .add_method("admin/instance/purge", move |params: Params| {
+                let params: ArchiveParams = params.parse()?;
+                let hc_lock = get_instance(&instances, &params.instance_id)?;
+                match params.confirmation_token {
+                    None => {
+                        let token = archive
+                            .request_purge(&params.instance_id, hc_lock)
+                            .map_err(jsonrpc_core::Error::invalid_params)?;
+                        let mut result = Map::new();
+                        result.insert("confirmation_token".to_string(), Value::String(token));
+                        Ok(Value::Object(result))
+                    }
+                    Some(token) => {
+                        archive
+                            .purge(&params.instance_id, hc_lock, &token)
+                            .map_err(jsonrpc_core::Error::invalid_params)?;
+                        Ok(Value::Bool(true))
+                    }
+                }
+            });
+    }
+
+    /// Adds the methods that support tests, for containers in test mode
+    pub fn with_test_api(mut self, published_before_start: PublishedBeforeStart) -> Self {
+        self.setup_test_api(published_before_start);
+        self
+    }
+
+    // initialize a json rpc method that waits for the instances given as
+    // {"instance_ids": [..]} to settle, see `wait_for_consistency()`, for at most the
+    // optional "timeout_ms". If they don't, it fails with the counter that was still busy.
+    fn setup_test_api(&mut self, published_before_start: PublishedBeforeStart) {
+        #[derive(Deserialize)]
+        struct ConsistencyParams {
+            instance_ids: Vec<String>,
+            #[serde(default)]
+            timeout_ms: Option<u64>,
+        }
+        let instances = self.instances.clone();
+        self.io
+            .add_method("test/consistency", move |params: Params| {
+                let params: ConsistencyParams = params.parse()?;
+                let mut consistency_instances = Vec::new();
+                for instance_id in params.instance_ids {
+                    let hc_lock = get_instance(&instances, &instance_id)?.clone();
+                    consistency_instances.push((instance_id, hc_lock));
+                }
+                let timeout = params
+                    .timeout_ms
+                    .map(Duration::from_millis)
+                    .unwrap_or_else(|| Duration::from_secs(DEFAULT_CONSISTENCY_TIMEOUT_SECS));
+                let published_before_start = published_before_start.read().unwrap();
+                match wait_for_consistency(&consistency_instances, &published_before_start, timeout)
+                {
+                    Ok(()) => Ok(Value::Bool(true)),
+                    Err(busy) => Err(jsonrpc_core::Error {
+                        code: ErrorCode::ServerError(-32001),
+                        message: format!(
+                            "Timeout: instance {} still has {} {}",
+                            busy.instance_id, busy.count, busy.counter
+                        ),
+                        data: serde_json::to_value(busy).ok(),
+                    }),
+                }
+            });
+    }
+
+    // initialize a json rpc method that dumps the state of the instance given as
+    // {"instance_id": ..} to its logger and returns it
+    fn setup_debug_api(&mut self) {
+        let instances = self.instances.clone();
+        self.io
+            .add_method("debug/dump_state", move |params: Params| {
+                #[derive(Deserialize)]
+                struct DumpStateParams {
+                    instance_id: String,
+                }
+                let params: DumpStateParams = params.parse()?;
+                let hc_lock = instances.get(&params.instance_id).ok_or_else(|| {
+                    jsonrpc_core::Error::invalid_params(format!(
+                        "Unknown instance {}",
+                        params.instance_id
+                    ))
+                })?;
+                let hc = hc_lock.read().unwrap();
+                let dump = hc
+                    .dump_state()
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
+                Ok(Value::String(dump))
+            });
+    }
+
+    // initialize json rpc methods for accessing all exposed zomes' functions,
+    // returns the names of all methods that got added
+    fn setup_zome_api(
+        &mut self,
+        instance_references: &[InstanceReferenceConfiguration],
+        call_context: &CallContext,
+    ) -> HashSet<String> {
+        let mut zome_methods = HashSet::new();
         for (instance_id, hc_lock) in self.instances.clone() {
+            let instance_reference = instance_references
+                .iter()
+                .find(|reference| reference.id == instance_id);
             let hc_lock = hc_lock.clone();
             let hc = hc_lock.read().unwrap();
+            // calls to a crashed instance fail right away instead of waiting on its lock
+            let crash_probe = hc.crash_probe();
             let state: State = hc.state().unwrap();
             let nucleus = state.nucleus();
             let dna = nucleus.dna();
@@ -69,6 +1515,12 @@ impl ContainerApiDispatcher {
                         for (cap_name, cap) in zome.capabilities {
                             for func in cap.functions {
                                 let func_name = func.name;
+                                let exposed = instance_reference
+                                    .map(|reference| reference.exposes(&zome_name, &func_name))
+                                    .unwrap_or(true);
+                                if !exposed {
+                                    continue;
+                                }
                                 let zome_name = zome_name.clone();
                                 let cap_name = cap_name.clone();
                                 let method_name = format!(
@@ -76,19 +1528,36 @@ impl ContainerApiDispatcher {
                                     instance_id, zome_name, cap_name, func_name
                                 );
                                 let hc_lock_inner = hc_lock.clone();
-                                self.io.add_method(&method_name, move |params| {
-                                    let mut hc = hc_lock_inner.write().unwrap();
-                                    let params_string =
-                                        serde_json::to_string(&params).map_err(|e| {
-                                            jsonrpc_core::Error::invalid_params(e.to_string())
-                                        })?;
-                                    let response = hc
-                                        .call(&zome_name, &cap_name, &func_name, &params_string)
-                                        .map_err(|e| {
-                                            jsonrpc_core::Error::invalid_params(e.to_string())
-                                        })?;
-                                    Ok(Value::String(response.to_string()))
-                                })
+                                let call_context = call_context.clone();
+                                let crash_probe = crash_probe.clone();
+                                zome_methods.insert(method_name.clone());
+                                self.io.add_method_with_meta(
+                                    &method_name,
+                                    move |params, meta: CallMeta| {
+                                        if let Some(crash) = crash_probe.crash_report() {
+                                            return Err(instance_error(
+                                                HolochainInstanceError::InstanceCrashed(
+                                                    crash.message,
+                                                ),
+                                            ));
+                                        }
+                                        let (params, debug) = take_debug_flag(params);
+                                        let params_string = serde_json::to_string(&params)
+                                            .map_err(|e| {
+                                                jsonrpc_core::Error::invalid_params(e.to_string())
+                                            })?;
+                                        call_zome_function(
+                                            &hc_lock_inner,
+                                            &zome_name,
+                                            &cap_name,
+                                            &func_name,
+                                            &params_string,
+                                            call_context.clone(),
+                                            debug,
+                                            &meta,
+                                        )
+                                    },
+                                )
                             }
                         }
                     }
@@ -96,12 +1565,343 @@ impl ContainerApiDispatcher {
                 None => unreachable!(),
             };
         }
+        zome_methods
+    }
+
+    // initialize a json rpc method that lists the names of all zome call methods
+    // of this dispatcher, so hidden functions don't show up here either
+    fn setup_functions_api(&mut self, zome_methods: &HashSet<String>) {
+        let mut method_names: Vec<String> = zome_methods.iter().cloned().collect();
+        method_names.sort();
+        self.io.add_method("info/functions", move |_| {
+            Ok(Value::Array(
+                method_names.iter().cloned().map(Value::String).collect(),
+            ))
+        });
+    }
+
+    // initialize the json rpc method for submitting several zome calls in one request.
+    // In parallel mode every call runs as a task of the runtime, or on a thread of its own
+    // without one, so a slow call only holds up calls to the same instance.
+    fn setup_batch_api(&mut self, zome_methods: HashSet<String>, call_context: CallContext) {
+        let instances = self.instances.clone();
+        let runtime = self.runtime.clone();
+        self.io.add_method("batch", move |params: Params| {
+            let batch: BatchParams = params.parse()?;
+            let results = match batch.mode {
+                BatchMode::Sequential => {
+                    let mut results = Vec::new();
+                    for call in batch.calls.iter() {
+                        let result =
+                            call_batch_entry(&instances, &zome_methods, call, &call_context);
+                        let failed = result.is_err();
+                        results.push(batch_result_to_value(result));
+                        if failed && batch.stop_on_error {
+                            break;
+                        }
+                    }
+                    results
+                }
+                BatchMode::Parallel => batch
+                    .calls
+                    .into_iter()
+                    .map(|call| {
+                        let instances = instances.clone();
+                        let zome_methods = zome_methods.clone();
+                        let call_context = call_context.clone();
+                        let (sender, receiver) = sync_channel(1);
+                        let task = move || {
+                            let _ = sender.send(call_batch_entry(
+                                &instances,
+                                &zome_methods,
+                                &call,
+                                &call_context,
+                            ));
+                        };
+                        match *runtime.read().unwrap() {
+                            Some(ref runtime) => runtime.spawn(task),
+                            None => {
+                                thread::spawn(task);
+                            }
+                        }
+                        receiver
+                    })
+                    // spawn all calls before waiting for the first one
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .map(|receiver| {
+                        // a panicking call drops its sender
+                        batch_result_to_value(
+                            recv_helping(&receiver, None)
+                                .unwrap_or_else(|_| Err(jsonrpc_core::Error::internal_error())),
+                        )
+                    })
+                    .collect(),
+            };
+            Ok(Value::Array(results))
+        });
     }
 }
 
-impl DispatchRpc for ContainerApiDispatcher {
-    fn handler(self) -> IoHandler {
-        self.io
+/// Errors from inside an instance carry the trail of contexts they passed as data,
+/// `{"error": .., "context": [..]}`, so clients can show where they came from.
+fn instance_error(error: HolochainInstanceError) -> jsonrpc_core::Error {
+    let mut rpc_error = jsonrpc_core::Error::invalid_params(error.to_string());
+    if let HolochainInstanceError::InternalFailure(ref holochain_error) = error {
+        rpc_error.data = serde_json::to_value(ErrorTrail::from(holochain_error)).ok();
+    }
+    rpc_error
+}
+
+/// The durations of the zome calls of the given instances in the Prometheus text format,
+/// as a histogram with the instance, zome and function as labels.
+pub fn prometheus_metrics(instances: &InstanceMap) -> String {
+    let name = "holochain_zome_call_duration_seconds";
+    let mut lines = vec![
+        format!("# HELP {} How long zome calls took", name),
+        format!("# TYPE {} histogram", name),
+    ];
+    let mut instance_ids: Vec<&String> = instances.keys().collect();
+    instance_ids.sort();
+    for instance_id in instance_ids {
+        let latencies = instances[instance_id].read().unwrap().zome_fn_latencies();
+        for latency in latencies {
+            let labels = format!(
+                "instance=\"{}\",zome=\"{}\",function=\"{}\"",
+                prometheus_label_value(instance_id),
+                prometheus_label_value(&latency.zome),
+                prometheus_label_value(&latency.function)
+            );
+            for bucket in latency.buckets.iter() {
+                lines.push(format!(
+                    "{}_bucket{{{},le=\"{}\"}} {}",
+                    name,
+                    labels,
+                    bucket.le_ms as f64 / 1000.0,
+                    bucket.count
+                ));
+            }
+            lines.push(format!(
+                "{}_bucket{{{},le=\"+Inf\"}} {}",
+                name, labels, latency.count
+            ));
+            lines.push(format!(
+                "{}_sum{{{}}} {}",
+                name,
+                labels,
+                latency.sum_ms / 1000.0
+            ));
+            lines.push(format!("{}_count{{{}}} {}", name, labels, latency.count));
+        }
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+fn prometheus_label_value(value: &str) -> String {
+    value
+        .replace("\\", "\\\\")
+        .replace("\"", "\\\"")
+        .replace("\n", "\\n")
+}
+
+fn get_instance<'a>(
+    instances: &'a InstanceMap,
+    instance_id: &str,
+) -> Result<&'a Arc<RwLock<Holochain>>, jsonrpc_core::Error> {
+    instances.get(instance_id).ok_or_else(|| {
+        jsonrpc_core::Error::invalid_params(format!("Unknown instance {}", instance_id))
+    })
+}
+
+/// Takes the `debug` flag out of the named parameters of a zome call, so that it does not
+/// reach the zome. Called with `"debug": true`, zome calls get audited and respond with
+/// a debug block besides their result, see `call_zome_function()`.
+fn take_debug_flag(params: Params) -> (Params, bool) {
+    match params {
+        Params::Map(mut map) => {
+            let debug = map.get("debug").and_then(Value::as_bool);
+            if debug.is_some() {
+                map.remove("debug");
+            }
+            (Params::Map(map), debug.unwrap_or(false))
+        }
+        params => (params, false),
+    }
+}
+
+/// Calls the zome function and responds with its result. With `debug`, the call gets audited
+/// and the response is `{"result": .., "debug": {"peak_memory_bytes": .., "host_calls": [..],
+/// "host_calls_dropped": ..}}` instead, with the host functions the call invoked in order.
+fn call_zome_function(
+    hc_lock: &Arc<RwLock<Holochain>>,
+    zome_name: &str,
+    cap_name: &str,
+    func_name: &str,
+    params_string: &str,
+    call_context: CallContext,
+    debug: bool,
+    meta: &CallMeta,
+) -> Result<Value, jsonrpc_core::Error> {
+    let mut hc = hc_lock.write().unwrap();
+    // the client went away while the call waited for the instance
+    if meta.is_canceled() {
+        return Err(jsonrpc_core::Error {
+            code: ErrorCode::ServerError(-32000),
+            message: String::from("Canceled: the connection of the request closed"),
+            data: None,
+        });
+    }
+    let (sender, receiver) = sync_channel(PARTIAL_RESULT_BUFFER_SIZE);
+    // partial results get forwarded while the call is still running
+    let forward_meta = meta.clone();
+    let forwarding = thread::spawn(move || forward_partial_results(receiver, &forward_meta));
+    let response = hc.call_streaming_with_debug_info(
+        zome_name,
+        cap_name,
+        func_name,
+        params_string,
+        call_context,
+        debug,
+        sender,
+    );
+    drop(hc);
+    let mut partial_results = forwarding.join().unwrap_or_default();
+    let (result, debug_info) = response.map_err(instance_error)?;
+    let mut response = Value::String(result.to_string());
+    if debug {
+        let mut debug_block = Map::new();
+        debug_block.insert(
+            "peak_memory_bytes".to_string(),
+            debug_info
+                .peak_memory
+                .map(Value::from)
+                .unwrap_or(Value::Null),
+        );
+        let trace = debug_info.host_call_trace.unwrap_or_default();
+        debug_block.insert(
+            "host_calls".to_string(),
+            serde_json::to_value(trace.calls)
+                .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?,
+        );
+        debug_block.insert("host_calls_dropped".to_string(), Value::from(trace.dropped));
+        let mut with_debug = Map::new();
+        with_debug.insert("result".to_string(), response);
+        with_debug.insert("debug".to_string(), Value::Object(debug_block));
+        response = Value::Object(with_debug);
+    }
+    if partial_results.is_empty() {
+        Ok(response)
+    } else {
+        partial_results.push(response);
+        Ok(Value::Array(partial_results))
+    }
+}
+
+/// Pushes the partial results of a call to the client as they come, if the interface can,
+/// and returns them otherwise, once the call is done
+fn forward_partial_results(receiver: Receiver<JsonString>, meta: &CallMeta) -> Vec<Value> {
+    let mut collected = Vec::new();
+    for partial_result in receiver {
+        let partial_result = Value::String(partial_result.to_string());
+        match meta.connection {
+            Some(ref connection) => {
+                let mut params = Map::new();
+                params.insert(
+                    "call_id".to_string(),
+                    serde_json::to_value(&meta.call_id).unwrap_or(Value::Null),
+                );
+                params.insert("partial_result".to_string(), partial_result);
+                // a client that went away doesn't get the return value either
+                let _ = connection.push(notification("partial_result", Value::Object(params)));
+            }
+            None => collected.push(partial_result),
+        }
+    }
+    collected
+}
+
+// The capability of the function the call is about: the given one, or the only one of the
+// exposed zome methods that has a function of that name
+fn batch_call_cap(
+    zome_methods: &HashSet<String>,
+    call: &BatchCall,
+) -> Result<String, jsonrpc_core::Error> {
+    if let Some(ref cap) = call.cap {
+        return Ok(cap.clone());
+    }
+    let mut caps = zome_methods.iter().filter_map(|method| {
+        match method.split('/').collect::<Vec<_>>().as_slice() {
+            [instance, zome, cap, function]
+                if *instance == call.instance
+                    && *zome == call.zome
+                    && *function == call.function =>
+            {
+                Some(cap.to_string())
+            }
+            _ => None,
+        }
+    });
+    match (caps.next(), caps.next()) {
+        (Some(cap), None) => Ok(cap),
+        (Some(_), Some(_)) => Err(jsonrpc_core::Error::invalid_params(format!(
+            "Several capabilities have a function {}, the call needs a cap",
+            call.function
+        ))),
+        (None, _) => Err(jsonrpc_core::Error::method_not_found()),
+    }
+}
+
+// Only calls that are exposed as methods of this dispatcher can be batched
+fn call_batch_entry(
+    instances: &InstanceMap,
+    zome_methods: &HashSet<String>,
+    call: &BatchCall,
+    call_context: &CallContext,
+) -> Result<Value, jsonrpc_core::Error> {
+    let cap = batch_call_cap(zome_methods, call)?;
+    let method_name = format!("{}/{}/{}/{}", call.instance, call.zome, cap, call.function);
+    let hc_lock = instances
+        .get(&call.instance)
+        .filter(|_| zome_methods.contains(&method_name))
+        .ok_or_else(jsonrpc_core::Error::method_not_found)?;
+    let params_string = serde_json::to_string(&call.params)
+        .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
+    call_zome_function(
+        hc_lock,
+        &call.zome,
+        &cap,
+        &call.function,
+        &params_string,
+        call_context.clone(),
+        false,
+        &CallMeta::default(),
+    )
+}
+
+// Wraps a single result of a batch like a JSON-RPC response does
+fn batch_result_to_value(result: Result<Value, jsonrpc_core::Error>) -> Value {
+    let mut object = Map::new();
+    match result {
+        Ok(value) => {
+            object.insert("result".to_string(), value);
+        }
+        Err(error) => {
+            let mut error_object = Map::new();
+            error_object.insert("code".to_string(), Value::from(error.code.code()));
+            error_object.insert("message".to_string(), Value::String(error.message));
+            if let Some(data) = error.data {
+                error_object.insert("data".to_string(), data);
+            }
+            object.insert("error".to_string(), Value::Object(error_object));
+        }
+    }
+    Value::Object(object)
+}
+
+impl DispatchRpc for ContainerApiDispatcher {
+    fn handler(self) -> IoHandler<CallMeta> {
+        self.io
     }
 }
 
@@ -119,6 +1919,30 @@ pub mod tests {
             tests::{test_dna_loader, test_toml},
             DEFAULT_NETWORK_CONFIG,
         },
+        holochain::tests::{example_api_wasm, test_context},
+        interface_client::InterfaceClient,
+        interface_impls::{http::HttpInterface, websocket::WebsocketInterface},
+        tls::{tests::write_self_signed_certificate, TlsCertificate},
+    };
+    use holochain_core::runtime::Runtime;
+    use holochain_core_types::{
+        dna::{
+            build_info::BuildInfo,
+            zome::capabilities::{Capability, FnDeclaration, FnParameter},
+        },
+        entry::addressing::dna_hash,
+        error::{HolochainError, RibosomeErrorCode},
+    };
+    use jsonrpc_ws_server::jsonrpc_core::MetaIoHandler;
+    use openssl::ssl::{SslConnector, SslMethod, SslStream};
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        time::Duration,
+    };
+    use tempfile::tempdir;
+    use test_utils::{
+        create_test_cap_with_fn_name, create_test_dna_with_cap, create_test_dna_with_wat,
     };
 
     fn example_config_and_instances() -> (Configuration, InstanceMap) {
@@ -128,6 +1952,8 @@ pub mod tests {
             &config,
             &mut test_dna_loader(),
             &DEFAULT_NETWORK_CONFIG.to_string(),
+            &Runtime::default(),
+            &InstanceMap::new(),
         )
         .unwrap();
         let mut instances = InstanceMap::new();
@@ -138,12 +1964,1700 @@ pub mod tests {
     #[test]
     fn test_new_dispatcher() {
         let (config, instances) = example_config_and_instances();
-        let dispatcher = ContainerApiDispatcher::new(&config, instances.clone());
+        let dispatcher =
+            ContainerApiDispatcher::new(&config, instances.clone(), &[], "test-interface");
         assert!(dispatcher.instances.get("test_instance").is_some());
         let handler = dispatcher.handler();
         let result = format!("{:?}", handler).to_string();
-        let ordering1: bool = result == r#"IoHandler(MetaIoHandler { middleware: Noop, compatibility: V2, methods: {"info/instances": <method>, "test_instance//test/test": <method>} })"#;
-        let ordering2: bool = result == r#"IoHandler(MetaIoHandler { middleware: Noop, compatibility: V2, methods: {"test_instance//test/test": <method>, "info/instances": <method>} })"#;
-        assert!(ordering1 || ordering2, "result = {:?}", result);
+        assert!(
+            result.starts_with(
+                "IoHandler(MetaIoHandler { middleware: Noop, compatibility: V2, methods: {"
+            ),
+            "result = {:?}",
+            result
+        );
+        for method in &[
+            "info/instances",
+            "info/status",
+            "info/functions",
+            "debug/dump_state",
+            "test_instance//test/test",
+            "batch",
+            "events/subscribe",
+            "events/unsubscribe",
+            "events/ack",
+        ] {
+            assert!(
+                result.contains(&format!("\"{}\": <method>", method)),
+                "result = {:?}",
+                result
+            );
+        }
+        assert_eq!(
+            result.matches("<method>").count(),
+            9,
+            "result = {:?}",
+            result
+        );
+    }
+
+    /// Dispatcher with one started instance whose zome function "main" succeeds
+    fn example_batch_dispatcher() -> ContainerApiDispatcher {
+        let (config, _) = example_config_and_instances();
+        let dna = create_test_dna_with_wat("test_zome", "test_cap", None);
+        let (context, _) = test_context("bob");
+        let mut holochain = Holochain::new(dna, context).unwrap();
+        holochain.start().expect("couldn't start");
+        let mut instances = InstanceMap::new();
+        instances.insert("test_instance".into(), Arc::new(RwLock::new(holochain)));
+        ContainerApiDispatcher::new(&config, instances, &[], "test-interface")
+    }
+
+    fn batch_request(mode: &str, stop_on_error: bool) -> String {
+        format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"batch","params":{{"mode":"{}","stop_on_error":{},"calls":[
+                {{"instance":"test_instance","zome":"test_zome","cap":"test_cap","function":"main","params":{{}}}},
+                {{"instance":"test_instance","zome":"test_zome","cap":"test_cap","function":"missing","params":{{}}}},
+                {{"instance":"test_instance","zome":"test_zome","cap":"test_cap","function":"main","params":{{}}}}
+            ]}}}}"#,
+            mode, stop_on_error
+        )
+    }
+
+    fn batch_results(dispatcher: ContainerApiDispatcher, request: &str) -> Vec<serde_json::Value> {
+        let response = dispatcher.handler().handle_request_sync(request).unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        response["result"]
+            .as_array()
+            .expect("batch should respond with an array")
+            .clone()
+    }
+
+    #[test]
+    fn test_batch_sequential_stop_on_error() {
+        let results = batch_results(
+            example_batch_dispatcher(),
+            &batch_request("sequential", true),
+        );
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["result"], "1337.0");
+        assert_eq!(results[1]["error"]["code"], -32601);
+    }
+
+    #[test]
+    fn test_batch_sequential() {
+        let results = batch_results(
+            example_batch_dispatcher(),
+            &batch_request("sequential", false),
+        );
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["result"], "1337.0");
+        assert!(results[1]["error"].is_object());
+        assert_eq!(results[2]["result"], "1337.0");
+    }
+
+    #[test]
+    fn test_zome_calls_respond_with_debug_block_on_request() {
+        let dispatcher = example_batch_dispatcher();
+        let call = |params: &str| {
+            let response = dispatcher
+                .io
+                .handle_request_sync(&format!(
+                    r#"{{"jsonrpc":"2.0","id":1,"method":"test_instance/test_zome/test_cap/main","params":{}}}"#,
+                    params
+                ))
+                .unwrap();
+            serde_json::from_str::<serde_json::Value>(&response).unwrap()
+        };
+
+        assert_eq!(call("{}")["result"], "1337.0");
+        let response = call(r#"{"debug": true}"#);
+        assert_eq!(response["result"]["result"], "1337.0");
+        // the example zome starts out with 17 pages of memory and does not grow it
+        assert_eq!(response["result"]["debug"]["peak_memory_bytes"], 17 * 65536);
+        // nor does it call any host functions
+        assert_eq!(response["result"]["debug"]["host_calls"], json_array());
+        assert_eq!(response["result"]["debug"]["host_calls_dropped"], 0);
+    }
+
+    fn json_array() -> serde_json::Value {
+        serde_json::Value::Array(Vec::new())
+    }
+
+    #[test]
+    fn test_last_calls_lists_the_audited_calls() {
+        let dispatcher = example_batch_dispatcher().with_admin_api(InstanceArchive::new(Arc::new(
+            RwLock::new(Configuration::default()),
+        )));
+        let last_calls = || {
+            let response = dispatcher
+                .io
+                .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"admin/instance/last_calls","params":{"instance_id":"test_instance"}}"#)
+                .unwrap();
+            serde_json::from_str::<serde_json::Value>(&response).unwrap()["result"].clone()
+        };
+        let call = |params: &str| {
+            dispatcher
+                .io
+                .handle_request_sync(&format!(
+                    r#"{{"jsonrpc":"2.0","id":1,"method":"test_instance/test_zome/test_cap/main","params":{}}}"#,
+                    params
+                ))
+                .unwrap();
+        };
+
+        // calls without the debug flag don't get audited
+        call("{}");
+        assert_eq!(last_calls(), json_array());
+
+        call(r#"{"debug": true}"#);
+        let calls = last_calls();
+        assert_eq!(calls.as_array().unwrap().len(), 1);
+        assert_eq!(calls[0]["zome"], "test_zome");
+        assert_eq!(calls[0]["function"], "main");
+        assert_eq!(calls[0]["host_calls"], json_array());
+
+        let response = dispatcher
+            .io
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"admin/instance/last_calls","params":{"instance_id":"unknown"}}"#)
+            .unwrap();
+        assert!(
+            response.contains(r#""code":-32602"#),
+            "response = {}",
+            response
+        );
+    }
+
+    #[test]
+    fn test_zomes_running_out_of_memory_leave_the_container_running() {
+        let (config, _) = example_config_and_instances();
+        let dna = create_test_dna_with_wat(
+            "test_zome",
+            "test_cap",
+            Some(
+                r#"
+            (module
+                (memory 1)
+                (func (export "main") (param $p0 i32) (result i32)
+                    (drop (grow_memory (i32.const 2000)))
+                    (i32.const 0)
+                )
+                (export "memory" (memory 0))
+            )
+        "#,
+            ),
+        );
+        let (context, _) = test_context("bob");
+        let mut holochain = Holochain::new(dna, context).unwrap();
+        holochain.start().expect("couldn't start");
+        let mut instances = InstanceMap::new();
+        instances.insert("test_instance".into(), Arc::new(RwLock::new(holochain)));
+        let dispatcher = ContainerApiDispatcher::new(&config, instances, &[], "test-interface");
+
+        for _ in 0..2 {
+            let response = request(&dispatcher, "test_instance/test_zome/test_cap/main");
+            assert!(response["error"]["message"]
+                .as_str()
+                .unwrap()
+                .contains(&RibosomeErrorCode::OutOfMemory.to_string()));
+        }
+        assert!(request(&dispatcher, "info/instances")["result"].is_array());
+    }
+
+    #[test]
+    fn test_batch_parallel() {
+        // stop_on_error has no effect in parallel mode
+        let results = batch_results(example_batch_dispatcher(), &batch_request("parallel", true));
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["result"], "1337.0");
+        assert!(results[1]["error"].is_object());
+        assert!(results[1].get("result").is_none());
+        assert_eq!(results[2]["result"], "1337.0");
+    }
+
+    #[test]
+    fn test_batch_parallel_on_a_runtime_without_caps() {
+        let dispatcher = example_batch_dispatcher().with_runtime(Runtime::new(1));
+        let request = r#"{"jsonrpc":"2.0","id":1,"method":"batch","params":{"mode":"parallel","calls":[
+            {"instance":"test_instance","zome":"test_zome","function":"main","params":{}},
+            {"instance":"test_instance","zome":"test_zome","function":"missing","params":{}}
+        ]}}"#;
+        let results = batch_results(dispatcher, request);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["result"], "1337.0");
+        assert_eq!(results[1]["error"]["code"], -32601);
+    }
+
+    #[test]
+    fn test_dump_state() {
+        let dispatcher = example_batch_dispatcher();
+        let response = dispatcher
+            .handler()
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"debug/dump_state","params":{"instance_id":"test_instance"}}"#)
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        let dump = response["result"].as_str().expect("should return the dump");
+        assert!(
+            dump.starts_with("Source chain (3 headers):"),
+            "dump = {}",
+            dump
+        );
+        assert!(dump.contains("%agent_id"), "dump = {}", dump);
+        assert!(dump.contains("DHT shard"), "dump = {}", dump);
+    }
+
+    #[test]
+    fn test_instance_status() {
+        let dispatcher = example_batch_dispatcher();
+        let response = dispatcher
+            .handler()
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"info/status","params":{"instance_id":"test_instance"}}"#)
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        let status = &response["result"];
+        assert_eq!(status["active"], true);
+        assert!(status["crash"].is_null());
+        assert_eq!(status["restarts"], 0);
+        assert_eq!(status["chain_info"]["length"], 3);
+        assert_eq!(status["chain_info"]["entry_type_counts"]["%dna"], 1);
+        assert_eq!(status["chain_info"]["entry_type_counts"]["%agent_id"], 1);
+        assert_eq!(status["chain_info"]["entry_type_counts"]["%link_add"], 1);
+        assert!(status["chain_info"]["top_header_address"].is_string());
+        assert_eq!(status["network"]["connection"], "Connected");
+        assert_eq!(status["network"]["reconnect_count"], 0);
+        assert!(status["zome_calls"]["retained"].is_number());
+        assert!(status["zome_calls"]["pruned"].is_number());
+        assert!(status["zome_calls"]["peak_memory_bytes"].is_number());
+        assert!(status["validation_package_cache"]["misses"].is_number());
+        assert!(status["held_entries"].as_u64().unwrap() >= 3);
+        assert!(status["pending"]["zome_calls"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+
+        let response = dispatcher
+            .handler()
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"info/status","params":{"instance_id":"unknown"}}"#)
+            .unwrap();
+        assert!(
+            response.contains(r#""code":-32602"#),
+            "response = {}",
+            response
+        );
+    }
+
+    #[test]
+    fn test_build_info_of_instances() {
+        let (config, _) = example_config_and_instances();
+        let mut dna = create_test_dna_with_wat("test_zome", "test_cap", None);
+        dna.build_info = Some(BuildInfo {
+            hc_version: "0.0.2".to_string(),
+            git_commit: Some("4720ab7".to_string()),
+            git_dirty: Some(false),
+            ..Default::default()
+        });
+        let (context, _) = test_context("bob");
+        let mut holochain = Holochain::new(dna.clone(), context).unwrap();
+        holochain.start().expect("couldn't start");
+        let mut instances = InstanceMap::new();
+        instances.insert("test_instance".into(), Arc::new(RwLock::new(holochain)));
+        let dispatcher = ContainerApiDispatcher::new(&config, instances, &[], "test-interface");
+        let expected: serde_json::Value = serde_json::from_str(
+            r#"{"hc_version":"0.0.2","git_commit":"4720ab7","git_dirty":false,"unhashed":{}}"#,
+        )
+        .unwrap();
+
+        let response = dispatcher
+            .io
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"info/status","params":{"instance_id":"test_instance"}}"#)
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["result"]["build_info"], expected);
+
+        let response = dispatcher
+            .io
+            .handle_request_sync(
+                r#"{"jsonrpc":"2.0","id":1,"method":"info/instances","params":null}"#,
+            )
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        let instances: serde_json::Value =
+            serde_json::from_str(response["result"].as_str().unwrap()).unwrap();
+        assert_eq!(instances["test_instance"]["build_info"], expected);
+        assert_eq!(instances["test_instance"]["id"], "test_instance");
+        assert_eq!(
+            instances["test_instance"]["dna_hash"],
+            dna_hash(&dna).to_string()
+        );
+    }
+
+    /// Two dispatchers over the same started instance, one of them only exposing the
+    /// "reader" zome, the other one exposing everything
+    fn example_filtered_dispatchers() -> (ContainerApiDispatcher, ContainerApiDispatcher) {
+        let (config, _) = example_config_and_instances();
+        let mut dna = create_test_dna_with_wat("reader", "test_cap", None);
+        let writer = dna.zomes.get("reader").unwrap().clone();
+        dna.zomes.insert("writer".to_string(), writer);
+        let (context, _) = test_context("bob");
+        let mut holochain = Holochain::new(dna, context).unwrap();
+        holochain.start().expect("couldn't start");
+        let mut instances = InstanceMap::new();
+        instances.insert("test_instance".into(), Arc::new(RwLock::new(holochain)));
+
+        let public_reference = InstanceReferenceConfiguration {
+            id: "test_instance".into(),
+            zomes: Some(vec!["reader".into()]),
+            functions: None,
+        };
+        let internal_reference = InstanceReferenceConfiguration {
+            id: "test_instance".into(),
+            zomes: None,
+            functions: None,
+        };
+        (
+            ContainerApiDispatcher::new(&config, instances.clone(), &[public_reference], "public"),
+            ContainerApiDispatcher::new(&config, instances, &[internal_reference], "internal"),
+        )
+    }
+
+    fn request(dispatcher: &ContainerApiDispatcher, method: &str) -> serde_json::Value {
+        let response = dispatcher
+            .io
+            .handle_request_sync(&format!(
+                r#"{{"jsonrpc":"2.0","id":1,"method":"{}","params":{{}}}}"#,
+                method
+            ))
+            .unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn test_interface_filters_visibility() {
+        let (public, internal) = example_filtered_dispatchers();
+        assert_eq!(
+            request(&public, "info/functions")["result"],
+            serde_json::Value::from(vec!["test_instance/reader/test_cap/main"])
+        );
+        assert_eq!(
+            request(&internal, "info/functions")["result"],
+            serde_json::Value::from(vec![
+                "test_instance/reader/test_cap/main",
+                "test_instance/writer/test_cap/main",
+            ])
+        );
+    }
+
+    #[test]
+    fn test_interface_filters_callability() {
+        let (public, internal) = example_filtered_dispatchers();
+        assert_eq!(
+            request(&public, "test_instance/reader/test_cap/main")["result"],
+            "1337.0"
+        );
+        assert_eq!(
+            request(&internal, "test_instance/writer/test_cap/main")["result"],
+            "1337.0"
+        );
+
+        // a hidden function looks exactly like one that does not exist
+        let hidden = request(&public, "test_instance/writer/test_cap/main");
+        let unknown = request(&public, "test_instance/unknown/test_cap/main");
+        assert_eq!(hidden["error"]["code"], -32601);
+        assert_eq!(hidden, unknown);
+
+        let batch = public
+            .io
+            .handle_request_sync(
+                r#"{"jsonrpc":"2.0","id":1,"method":"batch","params":{"calls":[
+                {"instance":"test_instance","zome":"writer","cap":"test_cap","function":"main"},
+                {"instance":"test_instance","zome":"unknown","cap":"test_cap","function":"main"}
+            ]}}"#,
+            )
+            .unwrap();
+        let batch: serde_json::Value = serde_json::from_str(&batch).unwrap();
+        assert_eq!(batch["result"][0]["error"]["code"], -32601);
+        assert_eq!(batch["result"][0], batch["result"][1]);
+    }
+
+    #[test]
+    fn test_zome_calls_come_from_interface() {
+        let (config, _) = example_config_and_instances();
+        let capability = create_test_cap_with_fn_name("call_context_test");
+        let dna =
+            create_test_dna_with_cap("test_zome", "test_cap", &capability, &example_api_wasm());
+        let (context, _) = test_context("bob");
+        let mut holochain = Holochain::new(dna, context).unwrap();
+        holochain.start().expect("couldn't start");
+        let mut instances = InstanceMap::new();
+        instances.insert("test_instance".into(), Arc::new(RwLock::new(holochain)));
+        let dispatcher = ContainerApiDispatcher::new(&config, instances, &[], "websocket");
+
+        let expected = "{\"Ok\":{\"Interface\":{\"token_id\":\"websocket\"}}}";
+        assert_eq!(
+            request(
+                &dispatcher,
+                "test_instance/test_zome/test_cap/call_context_test"
+            )["result"],
+            expected
+        );
+
+        let batch = dispatcher
+            .io
+            .handle_request_sync(
+                r#"{"jsonrpc":"2.0","id":1,"method":"batch","params":{"calls":[
+                {"instance":"test_instance","zome":"test_zome","cap":"test_cap","function":"call_context_test"}
+            ]}}"#,
+            )
+            .unwrap();
+        let batch: serde_json::Value = serde_json::from_str(&batch).unwrap();
+        assert_eq!(batch["result"][0]["result"], expected);
+    }
+
+    /// Dispatcher over a started instance of the example API wasm, which exposes stream_test
+    fn example_stream_dispatcher() -> ContainerApiDispatcher {
+        let (config, _) = example_config_and_instances();
+        let capability = create_test_cap_with_fn_name("stream_test");
+        let dna =
+            create_test_dna_with_cap("test_zome", "test_cap", &capability, &example_api_wasm());
+        let (context, _) = test_context("bob");
+        let mut holochain = Holochain::new(dna, context).unwrap();
+        holochain.start().expect("couldn't start");
+        let mut instances = InstanceMap::new();
+        instances.insert("test_instance".into(), Arc::new(RwLock::new(holochain)));
+        ContainerApiDispatcher::new(&config, instances, &[], "websocket")
+    }
+
+    const STREAM_REQUEST: &str = r#"{"jsonrpc":"2.0","id":7,"method":"test_instance/test_zome/test_cap/stream_test","params":{"count":100}}"#;
+
+    #[test]
+    fn test_partial_results_come_first_without_push() {
+        let response = example_stream_dispatcher()
+            .io
+            .handle_request_sync(STREAM_REQUEST)
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        let results = response["result"]
+            .as_array()
+            .expect("streaming call should respond with an array");
+        assert_eq!(results.len(), 101);
+        for (index, partial_result) in results[..100].iter().enumerate() {
+            assert_eq!(partial_result, &format!("{{\"index\":{}}}", index));
+        }
+        assert_eq!(results[100], "{\"Ok\":100}");
+    }
+
+    /// Runs a websocket interface with the dispatcher on a free port, returns its address
+    fn start_websocket_interface(dispatcher: ContainerApiDispatcher) -> String {
+        start_websocket_interface_with(dispatcher, WebsocketInterface::new)
+    }
+
+    fn start_websocket_interface_with<F>(dispatcher: ContainerApiDispatcher, interface: F) -> String
+    where
+        F: FnOnce(u16) -> WebsocketInterface + Send + 'static,
+    {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        thread::spawn(move || interface(port).run(dispatcher));
+        let address = format!("127.0.0.1:{}", port);
+        while TcpStream::connect(&address).is_err() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        address
+    }
+
+    #[test]
+    fn test_websocket_pushes_partial_results() {
+        let address = start_websocket_interface(example_stream_dispatcher());
+
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let received = messages.clone();
+        ws::connect(format!("ws://{}", address), move |out| {
+            out.send(STREAM_REQUEST).unwrap();
+            let received = received.clone();
+            move |message: ws::Message| {
+                let message: serde_json::Value =
+                    serde_json::from_str(&message.into_text()?).unwrap();
+                let is_response = message.get("result").is_some();
+                received.lock().unwrap().push(message);
+                if is_response {
+                    out.close(ws::CloseCode::Normal)?;
+                }
+                Ok(())
+            }
+        })
+        .unwrap();
+
+        let messages = messages.lock().unwrap();
+        assert_eq!(messages.len(), 101);
+        for (index, notification) in messages[..100].iter().enumerate() {
+            assert_eq!(notification["method"], "partial_result");
+            assert_eq!(notification["params"]["call_id"], 7);
+            assert_eq!(
+                notification["params"]["partial_result"],
+                format!("{{\"index\":{}}}", index)
+            );
+        }
+        assert_eq!(messages[100]["id"], 7);
+        assert_eq!(messages[100]["result"], "{\"Ok\":100}");
+    }
+
+    /// Dispatcher over two started instances of the example API wasm, alice_instance and
+    /// bob_instance, which expose round_trip_test
+    fn example_two_instances_dispatcher() -> ContainerApiDispatcher {
+        let (config, _) = example_config_and_instances();
+        let capability = create_test_cap_with_fn_name("round_trip_test");
+        let mut instances = InstanceMap::new();
+        for agent in &["alice", "bob"] {
+            let dna =
+                create_test_dna_with_cap("test_zome", "test_cap", &capability, &example_api_wasm());
+            let (context, _) = test_context(agent);
+            let mut holochain = Holochain::new(dna, context).unwrap();
+            holochain.start().expect("couldn't start");
+            instances.insert(
+                format!("{}_instance", agent),
+                Arc::new(RwLock::new(holochain)),
+            );
+        }
+        ContainerApiDispatcher::new(&config, instances, &[], "websocket")
+    }
+
+    #[test]
+    fn test_websocket_client_pipelines_requests_to_two_instances() {
+        let dispatcher = example_two_instances_dispatcher();
+        let connections = dispatcher.connections();
+        let address = start_websocket_interface(dispatcher);
+        let client = InterfaceClient::connect(&format!("ws://{}", address)).unwrap();
+
+        // all requests are on their way before the first response gets waited for
+        let pending: Vec<_> = (0..50u64)
+            .map(|index| {
+                let agent = if index % 2 == 0 { "alice" } else { "bob" };
+                let mut params = Map::new();
+                params.insert("input_int_val".to_string(), Value::from(index));
+                params.insert("input_str_val".to_string(), Value::from(agent));
+                let request = client
+                    .request(
+                        &format!("{}_instance/test_zome/test_cap/round_trip_test", agent),
+                        Value::Object(params),
+                    )
+                    .unwrap();
+                (index, agent, request)
+            })
+            .collect();
+        let ids: HashSet<u64> = pending.iter().map(|(_, _, request)| request.id()).collect();
+        assert_eq!(ids.len(), 50);
+
+        for (index, agent, request) in pending.into_iter().rev() {
+            let result = request.wait_timeout(Duration::from_secs(60)).unwrap();
+            let round_trip: serde_json::Value =
+                serde_json::from_str(result.as_str().unwrap()).unwrap();
+            assert_eq!(round_trip["input_int_val_plus2"], index + 2);
+            assert_eq!(
+                round_trip["input_str_val_plus_dog"],
+                format!("{}.puppy", agent)
+            );
+        }
+        assert_eq!(client.next_notification(Duration::from_millis(100)), None);
+
+        assert_eq!(connections.count(), 1);
+        client.close();
+        while connections.count() > 0 {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_websocket_answers_malformed_frames_with_errors() {
+        let address = start_websocket_interface(example_stream_dispatcher());
+
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let received = messages.clone();
+        ws::connect(format!("ws://{}", address), move |out| {
+            out.send("not json").unwrap();
+            out.send(r#"{"jsonrpc":"2.0","id":1}"#).unwrap();
+            out.send(vec![1u8, 2, 3]).unwrap();
+            // the connection stays usable
+            out.send(r#"{"jsonrpc":"2.0","id":2,"method":"info/instances"}"#)
+                .unwrap();
+            let received = received.clone();
+            move |message: ws::Message| {
+                let message: serde_json::Value =
+                    serde_json::from_str(&message.into_text()?).unwrap();
+                let mut received = received.lock().unwrap();
+                received.push(message);
+                if received.len() == 4 {
+                    out.close(ws::CloseCode::Normal)?;
+                }
+                Ok(())
+            }
+        })
+        .unwrap();
+
+        // every frame is handled on its own, so the answers come in any order
+        let messages = messages.lock().unwrap();
+        let mut error_codes: Vec<i64> = messages
+            .iter()
+            .filter_map(|message| message["error"]["code"].as_i64())
+            .collect();
+        error_codes.sort();
+        assert_eq!(error_codes, vec![-32700, -32700, -32600]);
+        let response = messages
+            .iter()
+            .find(|message| message["id"] == 2)
+            .expect("valid request should be answered");
+        assert!(response["result"].is_array());
+    }
+
+    #[test]
+    fn test_websocket_notifies_clients_of_instance_events() {
+        let dispatcher = example_stream_dispatcher();
+        let hc_lock = dispatcher.instances["test_instance"].clone();
+        let address = start_websocket_interface(dispatcher);
+        let client = InterfaceClient::connect(&format!("ws://{}", address)).unwrap();
+
+        hc_lock.write().unwrap().stop().unwrap();
+        hc_lock.write().unwrap().start().unwrap();
+        hc_lock.write().unwrap().archive().unwrap();
+
+        let methods: Vec<String> = (0..4)
+            .map(|_| {
+                let notification = client
+                    .next_notification(Duration::from_secs(10))
+                    .expect("should be notified");
+                assert_eq!(notification.get("id"), None);
+                assert_eq!(notification["params"]["instance_id"], "test_instance");
+                notification["method"].as_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(
+            methods,
+            vec![
+                "instance_stopped",
+                "instance_started",
+                "instance_stopped",
+                "instance_archived"
+            ]
+        );
+    }
+
+    /// The sequence numbers of the next `count` notifications the client gets
+    fn next_event_seqs(client: &InterfaceClient, count: usize) -> Vec<u64> {
+        (0..count)
+            .map(|_| {
+                client
+                    .next_notification(Duration::from_secs(10))
+                    .expect("should be notified")["params"]["seq"]
+                    .as_u64()
+                    .expect("events should have a sequence number")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_subscribers_get_the_buffered_events_they_missed() {
+        let dispatcher = example_stream_dispatcher();
+        let connections = dispatcher.connections();
+        let hc_lock = dispatcher.instances["test_instance"].clone();
+        // starting the instance was event 1, which did not get buffered
+        hc_lock.write().unwrap().set_signal_buffer_size(2);
+        let address = start_websocket_interface(dispatcher);
+        let disconnect_all = || {
+            for connection in connections.list() {
+                connections.disconnect(connection.id);
+            }
+        };
+
+        // nobody is connected yet
+        hc_lock.write().unwrap().stop().unwrap();
+        hc_lock.write().unwrap().start().unwrap();
+
+        let mut client = InterfaceClient::connect(&format!("ws://{}", address)).unwrap();
+        let response = client.subscribe("test_instance").unwrap();
+        assert_eq!(response["subscribed"], true);
+        assert_eq!(response["backlog"], 2);
+        assert_eq!(response["gap"], true);
+        assert_eq!(next_event_seqs(&client, 2), vec![2, 3]);
+        let mut acknowledged = None;
+        for _ in 0..100 {
+            acknowledged = connections
+                .list()
+                .first()
+                .and_then(|connection| connection.acknowledged.get("test_instance").cloned());
+            if acknowledged == Some(3) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(acknowledged, Some(3));
+
+        // resuming gets what happened meanwhile, and only that
+        disconnect_all();
+        hc_lock.write().unwrap().stop().unwrap();
+        hc_lock.write().unwrap().start().unwrap();
+        assert_eq!(client.reconnect().unwrap(), Vec::<String>::new());
+        assert_eq!(next_event_seqs(&client, 2), vec![4, 5]);
+
+        // more than the buffer keeps leaves a gap
+        disconnect_all();
+        hc_lock.write().unwrap().stop().unwrap();
+        hc_lock.write().unwrap().start().unwrap();
+        hc_lock.write().unwrap().stop().unwrap();
+        assert_eq!(
+            client.reconnect().unwrap(),
+            vec![String::from("test_instance")]
+        );
+        assert_eq!(next_event_seqs(&client, 2), vec![7, 8]);
+        assert_eq!(client.next_notification(Duration::from_millis(100)), None);
+    }
+
+    #[test]
+    fn test_connections_track_requests_in_flight() {
+        let dispatcher = example_stream_dispatcher();
+        let connections = dispatcher.connections();
+        let mut io = MetaIoHandler::with_middleware(CallIdMiddleware);
+        let handler: MetaIoHandler<CallMeta> = dispatcher.handler().into();
+        io.extend_with(handler);
+        let connection = Arc::new(Connection::new(|_| Ok(())));
+        connections.add(connection.clone());
+        let request = |connection: &Arc<Connection>| {
+            let response = io
+                .handle_request_sync(
+                    STREAM_REQUEST,
+                    CallMeta::with_connection(connection.clone()),
+                )
+                .unwrap();
+            serde_json::from_str::<serde_json::Value>(&response).unwrap()
+        };
+
+        // answered requests are not in flight anymore
+        assert_eq!(request(&connection)["result"], "{\"Ok\":100}");
+        assert!(connection.in_flight().is_empty());
+
+        // ids of requests in flight can't be used again
+        assert!(connection.start_request(&Id::Num(7)));
+        let response = request(&connection);
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["error"]["code"], -32600);
+        let in_flight: HashSet<Id> = vec![Id::Num(7)].into_iter().collect();
+        assert_eq!(connection.in_flight(), in_flight);
+
+        // closing cancels what is in flight and what is still to come
+        connections.remove(connection.id());
+        assert_eq!(connections.count(), 0);
+        assert!(!connection.is_open());
+        assert!(connection.in_flight().is_empty());
+        assert!(connection.push(String::from("too late")).is_err());
+        let response = request(&connection);
+        assert_eq!(response["error"]["code"], -32000);
+    }
+
+    #[derive(Default)]
+    struct CapturingLogger {
+        lines: Vec<String>,
+    }
+
+    impl Logger for CapturingLogger {
+        fn log(&mut self, msg: String) {
+            self.lines.push(msg);
+        }
+    }
+
+    // the lines the requests of the stream dispatcher got logged with
+    fn logged_requests(log_params_for: &[String], requests: &[&str]) -> Vec<Value> {
+        let logger = Arc::new(Mutex::new(CapturingLogger::default()));
+        let dispatcher = example_stream_dispatcher()
+            .with_request_log(RequestLog::new(logger.clone(), log_params_for));
+        let mut io =
+            MetaIoHandler::with_middleware((dispatcher.request_log_middleware(), CallIdMiddleware));
+        let handler: MetaIoHandler<CallMeta> = dispatcher.handler().into();
+        io.extend_with(handler);
+        let connection = Arc::new(Connection::new(|_| Ok(())));
+        for request in requests {
+            io.handle_request_sync(request, CallMeta::with_connection(connection.clone()));
+        }
+        let lines = logger.lock().unwrap().lines.clone();
+        lines
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_request_log_leaves_params_out() {
+        let lines = logged_requests(
+            &[],
+            &[
+                STREAM_REQUEST,
+                r#"{"jsonrpc":"2.0","id":8,"method":"test_instance/test_zome/test_cap/missing","params":{"secret":1}}"#,
+            ],
+        );
+        assert_eq!(lines.len(), 2);
+
+        let line = &lines[0];
+        assert_eq!(line["token_id"], "websocket");
+        assert!(line["connection_id"].is_u64());
+        assert!(line["time"].as_u64().unwrap() > 0);
+        assert_eq!(
+            line["method"],
+            "test_instance/test_zome/test_cap/stream_test"
+        );
+        assert_eq!(line["instance"], "test_instance");
+        assert_eq!(line["zome"], "test_zome");
+        assert_eq!(line["function"], "stream_test");
+        assert_eq!(line["params_bytes"], "{\"count\":100}".len());
+        assert!(line["duration_ms"].is_u64());
+        assert_eq!(line["outcome"], "ok");
+        assert!(line["response_bytes"].as_u64().unwrap() > 0);
+        assert_eq!(line.get("params"), None);
+
+        let line = &lines[1];
+        assert_eq!(line["function"], "missing");
+        assert_eq!(line["outcome"], ErrorCode::MethodNotFound.code());
+        assert!(!line.to_string().contains("secret"));
+    }
+
+    #[test]
+    fn test_request_log_has_the_params_of_allowed_functions() {
+        let lines = logged_requests(
+            &["stream_test".to_string()],
+            &[
+                STREAM_REQUEST,
+                r#"{"jsonrpc":"2.0","id":8,"method":"info/instances","params":{"secret":1}}"#,
+            ],
+        );
+        assert_eq!(lines[0]["params"]["count"], 100);
+        assert_eq!(lines[1]["function"], "info/instances");
+        assert_eq!(lines[1]["instance"], Value::Null);
+        assert_eq!(lines[1].get("params"), None);
+    }
+
+    #[test]
+    fn test_idle_connections_get_closed_unless_subscribed() {
+        let dispatcher = example_stream_dispatcher();
+        let connections = dispatcher.connections();
+        let address = start_websocket_interface_with(dispatcher, |port| {
+            WebsocketInterface::new(port).with_idle_timeout(Duration::from_millis(500))
+        });
+        let url = format!("ws://{}", address);
+        let instance: Value = serde_json::from_str(r#"{"instance_id":"test_instance"}"#).unwrap();
+        let no_params = Value::Object(Map::new());
+        let clients: Vec<InterfaceClient> = (0..3)
+            .map(|_| InterfaceClient::connect(&url).unwrap())
+            .collect();
+        let subscribed = clients[1]
+            .call("events/subscribe", instance.clone())
+            .unwrap();
+        assert_eq!(subscribed, true);
+        let subscriber_id = connections
+            .list()
+            .into_iter()
+            .find(|info| info.subscriptions == 1)
+            .expect("the subscriber should be connected")
+            .id;
+
+        for _ in 0..50 {
+            if connections.count() == 1 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        let remaining = connections.list();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, subscriber_id);
+        assert!(clients[1].call("info/instances", no_params.clone()).is_ok());
+        for index in &[0, 2] {
+            let result = clients[*index]
+                .request("info/instances", no_params.clone())
+                .and_then(|request| request.wait_timeout(Duration::from_secs(2)));
+            assert!(result.is_err(), "client {} should be disconnected", index);
+        }
+
+        // unsubscribed, it is idle like the others
+        let unsubscribed = clients[1].call("events/unsubscribe", instance).unwrap();
+        assert_eq!(unsubscribed, true);
+        for _ in 0..50 {
+            if connections.count() == 0 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        assert_eq!(connections.count(), 0);
+    }
+
+    #[test]
+    fn test_subscribers_only_get_events_of_their_instances() {
+        let connections = Connections::new("test-interface");
+        let pushed = |messages: &Arc<Mutex<Vec<String>>>| {
+            let messages = messages.clone();
+            Arc::new(Connection::new(move |message| {
+                messages.lock().unwrap().push(message);
+                Ok(())
+            }))
+        };
+        let (all_messages, subscriber_messages) = (Arc::default(), Arc::default());
+        let all = pushed(&all_messages);
+        let subscriber = pushed(&subscriber_messages);
+        assert!(subscriber.subscribe("instance_a"));
+        assert!(!subscriber.subscribe("instance_a"));
+        connections.add(all.clone());
+        connections.add(subscriber.clone());
+
+        connections.notify_instance_event("instance_a", "instance_stopped", Value::Null);
+        connections.notify_instance_event("instance_b", "instance_stopped", Value::Null);
+        assert_eq!(all_messages.lock().unwrap().len(), 2);
+        assert_eq!(subscriber_messages.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_subscribers_only_get_the_events_that_pass_their_filters() {
+        let dispatcher = example_batch_dispatcher();
+        let connections = dispatcher.connections();
+        let hc_lock = dispatcher.instances["test_instance"].clone();
+        let io: MetaIoHandler<CallMeta> = dispatcher.handler().into();
+        let pushed = |messages: &Arc<Mutex<Vec<(String, u64)>>>| {
+            let messages = messages.clone();
+            Arc::new(Connection::new(move |message| {
+                let message: serde_json::Value = serde_json::from_str(&message).unwrap();
+                messages.lock().unwrap().push((
+                    message["method"].as_str().unwrap().to_string(),
+                    message["params"]["seq"].as_u64().unwrap(),
+                ));
+                Ok(())
+            }))
+        };
+        let request = |connection: &Arc<Connection>, method: &str, params: &str| {
+            let response = io
+                .handle_request_sync(
+                    &format!(
+                        r#"{{"jsonrpc":"2.0","id":1,"method":"{}","params":{}}}"#,
+                        method, params
+                    ),
+                    CallMeta::with_connection(connection.clone()),
+                )
+                .unwrap();
+            serde_json::from_str::<serde_json::Value>(&response).unwrap()
+        };
+        let (stops_messages, seq_messages) = (Arc::default(), Arc::default());
+        let stops = pushed(&stops_messages);
+        let seqs = pushed(&seq_messages);
+        connections.add(stops.clone());
+        connections.add(seqs.clone());
+
+        let response = request(
+            &stops,
+            "events/subscribe",
+            r#"{"instance_id":"test_instance","signal_names":["instance_stopped"]}"#,
+        );
+        assert_eq!(response["result"]["subscribed"], true);
+        let seq_3 = request(
+            &seqs,
+            "events/subscribe",
+            r#"{"instance_id":"test_instance","filter":"payload.seq == 3"}"#,
+        )["result"]["subscription_id"]
+            .as_u64()
+            .unwrap();
+        request(
+            &seqs,
+            "events/subscribe",
+            r#"{"instance_id":"test_instance","signal_names":["instance_stopped"],"filter":"payload.instance_id == \"test_instance\" && payload.seq == 4"}"#,
+        );
+        assert_eq!(seqs.subscriptions().len(), 1);
+        assert_eq!(connections.list()[1].subscriptions, 2);
+
+        // starting the instance was event 1
+        for _ in 0..2 {
+            hc_lock.write().unwrap().stop().unwrap();
+            hc_lock.write().unwrap().start().unwrap();
+        }
+        let stopped = |seq| ("instance_stopped".to_string(), seq);
+        let started = |seq| ("instance_started".to_string(), seq);
+        assert_eq!(
+            *stops_messages.lock().unwrap(),
+            vec![stopped(2), stopped(4)]
+        );
+        assert_eq!(*seq_messages.lock().unwrap(), vec![started(3), stopped(4)]);
+
+        let unsubscribe = format!(r#"{{"subscription_id":{}}}"#, seq_3);
+        assert_eq!(
+            request(&seqs, "events/unsubscribe", &unsubscribe)["result"],
+            true
+        );
+        assert_eq!(
+            request(&seqs, "events/unsubscribe", &unsubscribe)["result"],
+            false
+        );
+        assert_eq!(connections.list()[1].subscriptions, 1);
+        assert_eq!(
+            request(&stops, "events/unsubscribe", "{}")["error"]["code"],
+            -32602
+        );
+
+        // malformed filters don't subscribe anything
+        let response = request(
+            &stops,
+            "events/subscribe",
+            r#"{"instance_id":"test_instance","filter":"payload.seq = 3"}"#,
+        );
+        assert_eq!(response["error"]["code"], -32602);
+        assert_eq!(
+            response["error"]["message"],
+            "Invalid filter: Expected `&&` at position 12"
+        );
+        // neither do names of signals instances don't emit
+        let response = request(
+            &stops,
+            "events/subscribe",
+            r#"{"instance_id":"test_instance","signal_names":["new_message"]}"#,
+        );
+        assert_eq!(response["error"]["code"], -32602);
+        assert_eq!(connections.list()[0].subscriptions, 1);
+    }
+
+    #[test]
+    fn test_admin_lists_and_disconnects_connections() {
+        let interface_connections = InterfaceConnections::default();
+        let dispatcher = example_batch_dispatcher();
+        let connections = dispatcher.connections();
+        interface_connections
+            .write()
+            .unwrap()
+            .insert("test-interface".to_string(), connections.clone());
+        let dispatcher = dispatcher.with_connections_admin_api(interface_connections);
+
+        let disconnected = Arc::new(AtomicBool::new(false));
+        let disconnected_flag = disconnected.clone();
+        let first = Arc::new(
+            Connection::new(|_| Ok(()))
+                .with_disconnect(move || disconnected_flag.store(true, Ordering::SeqCst)),
+        );
+        let second = Arc::new(Connection::new(|_| Ok(())));
+        second.subscribe("test_instance");
+        connections.add(first.clone());
+        connections.add(second.clone());
+
+        let request = |method: &str, params: &str| -> serde_json::Value {
+            let response = dispatcher
+                .io
+                .handle_request_sync(&format!(
+                    r#"{{"jsonrpc":"2.0","id":1,"method":"{}","params":{}}}"#,
+                    method, params
+                ))
+                .unwrap();
+            serde_json::from_str(&response).unwrap()
+        };
+        let listed = request("admin/interface/connections", "{}")["result"].clone();
+        assert_eq!(listed.as_array().unwrap().len(), 2);
+        assert_eq!(listed[0]["id"], first.id());
+        assert_eq!(listed[0]["interface_id"], "test-interface");
+        assert_eq!(listed[0]["token_id"], "test-interface");
+        assert_eq!(listed[0]["requests"], 0);
+        assert_eq!(listed[0]["subscriptions"], 0);
+        assert_eq!(listed[1]["id"], second.id());
+        assert_eq!(listed[1]["subscriptions"], 1);
+        let other_interface = request(
+            "admin/interface/connections",
+            r#"{"interface_id":"other-interface"}"#,
+        );
+        assert_eq!(other_interface["result"], Value::Array(Vec::new()));
+
+        let disconnect = format!(r#"{{"connection_id":{}}}"#, first.id());
+        assert_eq!(
+            request("admin/interface/disconnect", &disconnect)["result"],
+            true
+        );
+        assert!(disconnected.load(Ordering::SeqCst));
+        assert!(!first.is_open());
+        assert_eq!(connections.count(), 1);
+        // it is gone already
+        assert_eq!(
+            request("admin/interface/disconnect", &disconnect)["error"]["code"],
+            -32602
+        );
+
+        // disconnecting cleans up the subscriptions of the connection
+        let disconnect = format!(r#"{{"connection_id":{}}}"#, second.id());
+        request("admin/interface/disconnect", &disconnect);
+        assert!(second.subscriptions().is_empty());
+        assert_eq!(connections.count(), 0);
+    }
+
+    /// Dispatcher over a started instance of the example API wasm whose functions declare
+    /// their parameters, so that they can be called with positional or single value parameters
+    fn example_declared_parameters_dispatcher() -> ContainerApiDispatcher {
+        let (config, _) = example_config_and_instances();
+        let mut capability = Capability::new();
+        for (name, inputs) in vec![
+            ("stream_test", vec!["count"]),
+            ("round_trip_test", vec!["input_int_val", "input_str_val"]),
+        ] {
+            let mut fn_declaration = FnDeclaration::new();
+            fn_declaration.name = name.to_string();
+            fn_declaration.inputs = inputs
+                .into_iter()
+                .map(|input| FnParameter::new(input, ""))
+                .collect();
+            capability.functions.push(fn_declaration);
+        }
+        let dna =
+            create_test_dna_with_cap("test_zome", "test_cap", &capability, &example_api_wasm());
+        let (context, _) = test_context("bob");
+        let mut holochain = Holochain::new(dna, context).unwrap();
+        holochain.start().expect("couldn't start");
+        let mut instances = InstanceMap::new();
+        instances.insert("test_instance".into(), Arc::new(RwLock::new(holochain)));
+        ContainerApiDispatcher::new(&config, instances, &[], "http")
+    }
+
+    fn http_request(address: &str, body: &str) -> serde_json::Value {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "POST / HTTP/1.0\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let body = response
+            .splitn(2, "\r\n\r\n")
+            .nth(1)
+            .expect("response should have a body");
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[test]
+    fn test_http_calls_with_named_positional_and_single_value_params() {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let dispatcher = example_declared_parameters_dispatcher();
+        thread::spawn(move || HttpInterface::new(port).run(dispatcher));
+        let address = format!("127.0.0.1:{}", port);
+        while TcpStream::connect(&address).is_err() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let call = |function: &str, params: &str| {
+            http_request(
+                &address,
+                &format!(
+                    r#"{{"jsonrpc":"2.0","id":1,"method":"test_instance/test_zome/test_cap/{}","params":{}}}"#,
+                    function, params
+                ),
+            )
+        };
+        let expected =
+            serde_json::Value::from(vec!["{\"index\":0}", "{\"index\":1}", "{\"Ok\":2}"]);
+
+        assert_eq!(call("stream_test", r#"{"count":2}"#)["result"], expected);
+        assert_eq!(call("stream_test", "[2]")["result"], expected);
+        // JSON-RPC params are an object or an array, single values go through batch calls
+        let batch = http_request(
+            &address,
+            r#"{"jsonrpc":"2.0","id":1,"method":"batch","params":{"calls":[
+                {"instance":"test_instance","zome":"test_zome","cap":"test_cap","function":"stream_test","params":2}
+            ]}}"#,
+        );
+        assert_eq!(batch["result"][0]["result"], expected);
+
+        let round_trip: serde_json::Value = serde_json::from_str(
+            call("round_trip_test", r#"[2,"dog"]"#)["result"]
+                .as_str()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(round_trip["input_int_val_plus2"], 4);
+        assert_eq!(round_trip["input_str_val_plus_dog"], "dog.puppy");
+
+        let error = call("round_trip_test", "[2]");
+        assert_eq!(error["error"]["code"], -32602);
+        let message = error["error"]["message"].as_str().unwrap();
+        assert!(
+            message.contains("Zome function round_trip_test expects 2 parameters, got 1"),
+            "message = {}",
+            message
+        );
+    }
+
+    #[test]
+    fn test_websocket_serves_tls_and_reloads_its_certificate() {
+        let dir = tempdir().unwrap();
+        let first = write_self_signed_certificate(dir.path(), "first", false);
+        let second = write_self_signed_certificate(dir.path(), "second", false);
+        let certificate = TlsCertificate::load("wss interface", &first).unwrap();
+        let interface_certificate = certificate.clone();
+        let address = start_websocket_interface_with(example_stream_dispatcher(), move |port| {
+            WebsocketInterface::new(port).with_tls(interface_certificate)
+        });
+        let port = address.rsplit(':').next().unwrap().to_string();
+        let url = format!("wss://localhost:{}", port);
+        let no_params = Value::Object(Map::new());
+
+        let open_client = InterfaceClient::connect_with_ca_file(&url, &first.cert_file).unwrap();
+        assert!(open_client
+            .call("info/instances", no_params.clone())
+            .is_ok());
+        // the certificate is not one the system trusts
+        assert!(InterfaceClient::connect(&url).is_err());
+        let plain = InterfaceClient::connect(&format!("ws://{}", address)).and_then(|client| {
+            client
+                .request("info/instances", no_params.clone())?
+                .wait_timeout(Duration::from_secs(2))
+        });
+        assert!(plain.is_err());
+
+        certificate.reload(&second).unwrap();
+        // the open connection keeps going, new ones get the new certificate
+        assert!(open_client
+            .call("info/instances", no_params.clone())
+            .is_ok());
+        assert!(InterfaceClient::connect_with_ca_file(&url, &first.cert_file).is_err());
+        let new_client = InterfaceClient::connect_with_ca_file(&url, &second.cert_file).unwrap();
+        assert!(new_client.call("info/instances", no_params.clone()).is_ok());
+        // https URLs mean wss
+        let https_url = format!("https://localhost:{}", port);
+        assert!(InterfaceClient::connect_with_ca_file(&https_url, &second.cert_file).is_ok());
+    }
+
+    fn https_connect(address: &str, ca_file: &str) -> Result<SslStream<TcpStream>, String> {
+        let mut connector = SslConnector::builder(SslMethod::tls()).unwrap();
+        connector.set_ca_file(ca_file).unwrap();
+        let stream = TcpStream::connect(address).map_err(|e| e.to_string())?;
+        connector
+            .build()
+            .connect("localhost", stream)
+            .map_err(|e| e.to_string())
+    }
+
+    fn https_request(address: &str, ca_file: &str, body: &str) -> Result<Value, String> {
+        https_request_on(https_connect(address, ca_file)?, body)
+    }
+
+    fn https_request_on(mut stream: SslStream<TcpStream>, body: &str) -> Result<Value, String> {
+        write!(
+            stream,
+            "POST / HTTP/1.0\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .map_err(|e| e.to_string())?;
+        // servers can close the connection without closing the TLS session
+        let mut response = Vec::new();
+        let _ = stream.read_to_end(&mut response);
+        let response = String::from_utf8(response).map_err(|e| e.to_string())?;
+        let body = response
+            .splitn(2, "\r\n\r\n")
+            .nth(1)
+            .ok_or_else(|| format!("response without body: {}", response))?;
+        serde_json::from_str(body).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_http_serves_tls_with_reloaded_certificates_without_closing_connections() {
+        let dir = tempdir().unwrap();
+        let first = write_self_signed_certificate(dir.path(), "first", false);
+        let second = write_self_signed_certificate(dir.path(), "second", false);
+        let certificate = TlsCertificate::load("https interface", &first).unwrap();
+        let interface_certificate = certificate.clone();
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let dispatcher = example_declared_parameters_dispatcher();
+        thread::spawn(move || {
+            HttpInterface::new(port)
+                .with_tls(interface_certificate)
+                .run(dispatcher)
+        });
+        let address = format!("127.0.0.1:{}", port);
+        while TcpStream::connect(&address).is_err() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let request = r#"{"jsonrpc":"2.0","id":1,"method":"info/instances","params":{}}"#;
+
+        let response = https_request(&address, &first.cert_file, request).unwrap();
+        assert!(!response["result"].is_null(), "response = {}", response);
+        // plain http gets no response
+        let mut plain = TcpStream::connect(&address).unwrap();
+        let _ = write!(
+            plain,
+            "POST / HTTP/1.0\r\nContent-Length: {}\r\n\r\n{}",
+            request.len(),
+            request
+        );
+        let mut response = String::new();
+        let _ = plain.read_to_string(&mut response);
+        assert!(!response.contains("result"), "response = {}", response);
+
+        let open_connection = https_connect(&address, &first.cert_file).unwrap();
+        certificate.reload(&second).unwrap();
+        // the open connection keeps going, new ones get the new certificate right away
+        let response = https_request_on(open_connection, request).unwrap();
+        assert!(!response["result"].is_null(), "response = {}", response);
+        let response = https_request(&address, &second.cert_file, request).unwrap();
+        assert!(!response["result"].is_null(), "response = {}", response);
+        assert!(https_request(&address, &first.cert_file, request).is_err());
+    }
+
+    #[test]
+    fn test_revalidate_shard() {
+        let dispatcher = example_batch_dispatcher().with_admin_api(InstanceArchive::new(Arc::new(
+            RwLock::new(Configuration::default()),
+        )));
+        let status_request = r#"{"jsonrpc":"2.0","id":1,"method":"info/status","params":{"instance_id":"test_instance"}}"#;
+        let status = |dispatcher: &ContainerApiDispatcher| -> serde_json::Value {
+            let response = dispatcher.io.handle_request_sync(status_request).unwrap();
+            let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+            response["result"].clone()
+        };
+        assert!(status(&dispatcher)["revalidation"].is_null());
+
+        let response = dispatcher
+            .io
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"admin/instance/revalidate_shard","params":{"instance_id":"test_instance"}}"#)
+            .unwrap();
+        assert!(
+            response.contains(r#""result":true"#),
+            "response = {}",
+            response
+        );
+
+        let mut revalidation = status(&dispatcher)["revalidation"].clone();
+        for _ in 0..50 {
+            if revalidation["finished"] == true {
+                break;
+            }
+            thread::sleep(::std::time::Duration::from_millis(100));
+            revalidation = status(&dispatcher)["revalidation"].clone();
+        }
+        assert_eq!(revalidation["finished"], true);
+        assert_eq!(revalidation["canceled"], false);
+        // the genesis entries are system entries that don't get validated by zomes
+        assert_eq!(revalidation["total"], 0);
+
+        // nothing left to cancel
+        let response = dispatcher
+            .io
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"admin/instance/cancel_revalidation","params":{"instance_id":"test_instance"}}"#)
+            .unwrap();
+        assert!(
+            response.contains(r#""code":-32602"#),
+            "response = {}",
+            response
+        );
+    }
+
+    #[test]
+    fn test_pending() {
+        let dispatcher = example_batch_dispatcher().with_admin_api(InstanceArchive::new(Arc::new(
+            RwLock::new(Configuration::default()),
+        )));
+        let response = dispatcher
+            .io
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"admin/instance/pending","params":{"instance_id":"test_instance"}}"#)
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        let pending = &response["result"];
+        assert!(pending["zome_calls"].as_array().unwrap().is_empty());
+        assert!(pending["validations"].is_array());
+        assert!(pending["network_requests"].is_array());
+
+        let response = dispatcher
+            .io
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"admin/instance/pending","params":{"instance_id":"unknown"}}"#)
+            .unwrap();
+        assert!(
+            response.contains(r#""code":-32602"#),
+            "response = {}",
+            response
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "test_fixtures")]
+    fn test_calls_to_crashed_instance_fail_right_away() {
+        let dispatcher = example_batch_dispatcher().with_admin_api(InstanceArchive::new(Arc::new(
+            RwLock::new(Configuration::default()),
+        )));
+        let hc_lock = dispatcher.instances["test_instance"].clone();
+        hc_lock.write().unwrap().crash("boom");
+        let crash_probe = hc_lock.read().unwrap().crash_probe();
+        for _ in 0..500 {
+            if crash_probe.crash_report().is_some() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // neither of these waits on the instance, which stays locked
+        let _hc = hc_lock.write().unwrap();
+        let response = dispatcher
+            .io
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"test_instance/test_zome/test_cap/main","params":{}}"#)
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(
+            response["error"]["message"],
+            "Holochain Instance Error: Holochain instance crashed: boom"
+        );
+        let response = dispatcher
+            .io
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"admin/instance/crash","params":{"instance_id":"test_instance"}}"#)
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["result"]["crashed"], true);
+        assert_eq!(response["result"]["crash"]["message"], "boom");
+        assert!(response["result"]["crash"]["crashed_at"].is_number());
+    }
+
+    #[test]
+    fn test_gc() {
+        let dispatcher = example_batch_dispatcher().with_admin_api(InstanceArchive::new(Arc::new(
+            RwLock::new(Configuration::default()),
+        )));
+        let response = dispatcher
+            .io
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"admin/instance/gc","params":{"instance_id":"test_instance"}}"#)
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        let report = &response["result"];
+        assert_eq!(report["removed"], 0);
+        assert_eq!(report["bytes_reclaimed"], 0);
+        assert!(report["kept"].as_u64().unwrap() > 0);
+
+        let response = dispatcher
+            .io
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"admin/instance/gc","params":{"instance_id":"unknown"}}"#)
+            .unwrap();
+        assert!(
+            response.contains(r#""code":-32602"#),
+            "response = {}",
+            response
+        );
+    }
+
+    #[test]
+    fn test_flush() {
+        let dispatcher = example_batch_dispatcher().with_admin_api(InstanceArchive::new(Arc::new(
+            RwLock::new(Configuration::default()),
+        )));
+        let response = dispatcher
+            .io
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"admin/instance/flush","params":{"instance_id":"test_instance"}}"#)
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        // genesis committed the DNA and the agent
+        assert!(response["result"]["flushed_commits"].as_u64().unwrap() >= 2);
+
+        let response = dispatcher
+            .io
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"admin/instance/flush","params":{"instance_id":"test_instance"}}"#)
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["result"]["flushed_commits"], 0);
+    }
+
+    #[test]
+    fn test_consistency() {
+        let request = |params: &str| {
+            let dispatcher =
+                example_batch_dispatcher().with_test_api(PublishedBeforeStart::default());
+            let response = dispatcher
+                .io
+                .handle_request_sync(&format!(
+                    r#"{{"jsonrpc":"2.0","id":1,"method":"test/consistency","params":{}}}"#,
+                    params
+                ))
+                .unwrap();
+            serde_json::from_str::<serde_json::Value>(&response).unwrap()
+        };
+        // genesis commits get flushed
+        assert_eq!(
+            request(r#"{"instance_ids":["test_instance"]}"#)["result"],
+            true
+        );
+        assert_eq!(
+            request(r#"{"instance_ids":["unknown"]}"#)["error"]["code"],
+            -32602
+        );
+
+        // interfaces of containers that are not in test mode don't have it
+        let response = example_batch_dispatcher()
+            .io
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"test/consistency","params":{"instance_ids":[]}}"#)
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["error"]["code"], -32601);
+    }
+
+    #[test]
+    fn test_zome_call_metrics() {
+        let dispatcher = example_batch_dispatcher();
+        assert!(!dispatcher.is_admin());
+        let instances = dispatcher.instances();
+        for _ in 0..2 {
+            let result = instances["test_instance"].write().unwrap().call(
+                "test_zome",
+                "test_cap",
+                "main",
+                "",
+            );
+            assert!(result.is_ok(), "result = {:?}", result);
+        }
+
+        let dispatcher = dispatcher.with_admin_api(InstanceArchive::new(Arc::new(RwLock::new(
+            Configuration::default(),
+        ))));
+        assert!(dispatcher.is_admin());
+        let response = dispatcher
+            .io
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"admin/instance/metrics","params":{"instance_id":"test_instance"}}"#)
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        let main = &response["result"]["zome_functions"][0];
+        assert_eq!(main["zome"], "test_zome");
+        assert_eq!(main["function"], "main");
+        assert_eq!(main["count"], 2);
+        assert!(main["p50_ms"].as_f64().unwrap() <= main["p95_ms"].as_f64().unwrap());
+        assert!(main["p95_ms"].as_f64().unwrap() <= main["max_ms"].as_f64().unwrap());
+
+        let metrics = prometheus_metrics(&instances);
+        assert!(metrics.contains("# TYPE holochain_zome_call_duration_seconds histogram\n"));
+        let labels = r#"instance="test_instance",zome="test_zome",function="main""#;
+        assert!(metrics.contains(&format!(
+            "holochain_zome_call_duration_seconds_bucket{{{},le=\"10\"}} 2\n",
+            labels
+        )));
+        assert!(metrics.contains(&format!(
+            "holochain_zome_call_duration_seconds_bucket{{{},le=\"+Inf\"}} 2\n",
+            labels
+        )));
+        assert!(metrics.contains(&format!(
+            "holochain_zome_call_duration_seconds_count{{{}}} 2\n",
+            labels
+        )));
+    }
+
+    #[test]
+    fn test_archive_api() {
+        let (mut config, _) = example_config_and_instances();
+        config.instances[0].id = "test_instance".to_string();
+        let shared_config = Arc::new(RwLock::new(config));
+        let dispatcher =
+            example_batch_dispatcher().with_admin_api(InstanceArchive::new(shared_config.clone()));
+        let request = |method: &str, params: &str| -> serde_json::Value {
+            let response = dispatcher
+                .io
+                .handle_request_sync(&format!(
+                    r#"{{"jsonrpc":"2.0","id":1,"method":"{}","params":{}}}"#,
+                    method, params
+                ))
+                .unwrap();
+            serde_json::from_str(&response).unwrap()
+        };
+        let instance = r#"{"instance_id":"test_instance"}"#;
+        let state = || request("info/status", instance)["result"]["state"].clone();
+        assert_eq!(state(), "Running");
+
+        assert_eq!(request("admin/instance/archive", instance)["result"], true);
+        assert_eq!(state(), "Archived");
+        assert!(shared_config.read().unwrap().instances[0].archived);
+        // archived instances fail zome calls just like stopped ones
+        let call = request("test_instance/test_zome/test_cap/main", "{}");
+        assert_eq!(call["error"]["code"], -32602);
+        assert_eq!(
+            call["error"]["message"],
+            "Holochain Instance Error: Holochain instance is not active yet."
+        );
+
+        assert_eq!(request("admin/instance/restore", instance)["result"], true);
+        assert_eq!(state(), "Running");
+        assert!(!shared_config.read().unwrap().instances[0].archived);
+        assert!(request("test_instance/test_zome/test_cap/main", "{}")["result"].is_string());
+
+        // only archived instances can be purged
+        assert_eq!(
+            request("admin/instance/purge", instance)["error"]["code"],
+            -32602
+        );
+        request("admin/instance/archive", instance);
+        let token = request("admin/instance/purge", instance)["result"]["confirmation_token"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let confirmed = format!(
+            r#"{{"instance_id":"test_instance","confirmation_token":"{}"}}"#,
+            token
+        );
+        assert_eq!(request("admin/instance/purge", &confirmed)["result"], true);
+        assert!(shared_config.read().unwrap().instances.is_empty());
+        // the token can't be used twice
+        assert_eq!(
+            request("admin/instance/purge", &confirmed)["error"]["code"],
+            -32602
+        );
+    }
+
+    #[test]
+    fn test_admin_api_needs_admin_interface() {
+        let response = example_batch_dispatcher()
+            .io
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"admin/instance/revalidate_shard","params":{"instance_id":"test_instance"}}"#)
+            .unwrap();
+        assert!(
+            response.contains(r#""code":-32601"#),
+            "response = {}",
+            response
+        );
+    }
+
+    #[test]
+    fn test_batch_invalid_params() {
+        let (config, instances) = example_config_and_instances();
+        let dispatcher = ContainerApiDispatcher::new(&config, instances, &[], "test-interface");
+        let response = dispatcher
+            .handler()
+            .handle_request_sync(r#"{"jsonrpc":"2.0","id":1,"method":"batch","params":{"mode":"random","calls":[]}}"#)
+            .unwrap();
+        assert!(
+            response.contains(r#""code":-32602"#),
+            "response = {}",
+            response
+        );
+    }
+
+    #[test]
+    fn test_instance_error_carries_context_trail() {
+        let error = HolochainInstanceError::InternalFailure(
+            HolochainError::IoError(String::from("sync poison error"))
+                .with_context("fetching links of Qm123 from the meta storage")
+                .with_context("hc_get_links"),
+        );
+        let rpc_error = instance_error(error);
+        assert_eq!(
+            rpc_error.message,
+            "Holochain Instance Error: hc_get_links: fetching links of Qm123 from the meta storage: sync poison error"
+        );
+        assert_eq!(
+            rpc_error.data,
+            Some(
+                serde_json::from_str::<Value>(
+                    r#"{"error":"sync poison error","context":["hc_get_links","fetching links of Qm123 from the meta storage"]}"#
+                )
+                .unwrap()
+            )
+        );
+
+        let batch_result = batch_result_to_value(Err(rpc_error));
+        assert_eq!(batch_result["error"]["data"]["context"][0], "hc_get_links");
+
+        let rpc_error = instance_error(HolochainInstanceError::InstanceNotActiveYet);
+        assert_eq!(rpc_error.data, None);
     }
 }
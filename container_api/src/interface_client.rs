@@ -0,0 +1,496 @@
+//! A client of the websocket interface of a container.
+//!
+//! Requests are pipelined: `request()` sends a request right away and returns a
+//! `PendingRequest` to wait for its response with, so many requests can be in flight over
+//! one connection. Every request gets an id of its own, which its response carries, so
+//! responses find their request whatever order they come in.
+//! Messages that don't answer a pending request, i.e. the notifications of the server like
+//! `instance_stopped` and `partial_result`, or error frames about frames it could not read,
+//! can be received with `next_notification()`.
+//!
+//! Subscribing to the events of an instance with `subscribe()` acknowledges every event
+//! that comes in, by its sequence number. After `reconnect()`, the client subscribes again
+//! from the last event it acknowledged, so it gets the events it missed meanwhile from the
+//! container's signal buffer, as far as that keeps them, and none twice.
+//!
+//! Interfaces that serve TLS are reached at `wss://` URLs, or `https://` ones, which mean
+//! the same. Their certificate gets verified with the CAs of the system, or with the ones
+//! of a file given to `connect_with_ca_file()`, e.g. for self-signed certificates.
+//!
+//! ```rust,no_run
+//! extern crate holochain_container_api;
+//! #[macro_use]
+//! extern crate serde_json;
+//! use holochain_container_api::interface_client::InterfaceClient;
+//!
+//! fn main() {
+//!     let client = InterfaceClient::connect("ws://localhost:3000").unwrap();
+//!     let first = client.request("info/instances", json!({})).unwrap();
+//!     let second = client.request("info/functions", json!({})).unwrap();
+//!     println!("{:?}", second.wait());
+//!     println!("{:?}", first.wait());
+//! }
+//! ```
+
+use openssl::ssl::{SslConnector, SslMethod, SslStream};
+use serde_json::{self, Map, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, Sender, SyncSender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use url::Url;
+use ws::{self, util::TcpStream, CloseCode, Handler, Handshake, Message};
+
+/// The error of a JSON-RPC response
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClientError {
+    /// The connection could not be opened
+    ConnectionFailed(String),
+    /// The connection closed before the response came
+    ConnectionClosed,
+    /// The response did not come in time
+    Timeout,
+    /// The server responded with an error
+    Rpc(RpcError),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientError::ConnectionFailed(reason) => write!(f, "Could not connect: {}", reason),
+            ClientError::ConnectionClosed => write!(f, "Connection closed"),
+            ClientError::Timeout => write!(f, "Timeout while waiting for the response"),
+            ClientError::Rpc(error) => write!(f, "{} ({})", error.message, error.code),
+        }
+    }
+}
+
+type ResponseSender = Sender<Result<Value, RpcError>>;
+type PendingResponses = Arc<Mutex<HashMap<u64, ResponseSender>>>;
+/// Sequence number of the last event of each instance the client acknowledged
+type Acknowledged = Arc<Mutex<HashMap<String, u64>>>;
+
+/// A connection to the websocket interface of a container, see the module docs
+pub struct InterfaceClient {
+    url: String,
+    ca_file: Option<String>,
+    out: ws::Sender,
+    next_id: Arc<AtomicUsize>,
+    pending: PendingResponses,
+    acknowledged: Acknowledged,
+    subscriptions: Mutex<HashSet<String>>,
+    notification_sender: Sender<Value>,
+    notifications: Receiver<Value>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// A request that was sent and is waiting for its response
+pub struct PendingRequest {
+    id: u64,
+    response: Receiver<Result<Value, RpcError>>,
+}
+
+impl PendingRequest {
+    /// The id the request was sent with
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Waits for the response and returns its result
+    pub fn wait(self) -> Result<Value, ClientError> {
+        match self.response.recv() {
+            Ok(response) => response.map_err(ClientError::Rpc),
+            Err(_) => Err(ClientError::ConnectionClosed),
+        }
+    }
+
+    /// Waits for the response for at most `timeout`
+    pub fn wait_timeout(self, timeout: Duration) -> Result<Value, ClientError> {
+        match self.response.recv_timeout(timeout) {
+            Ok(response) => response.map_err(ClientError::Rpc),
+            Err(RecvTimeoutError::Timeout) => Err(ClientError::Timeout),
+            Err(RecvTimeoutError::Disconnected) => Err(ClientError::ConnectionClosed),
+        }
+    }
+}
+
+impl InterfaceClient {
+    /// Connects to the interface at `url`, e.g. `ws://localhost:3000`
+    /// or `wss://example.org:3000`
+    pub fn connect(url: &str) -> Result<Self, ClientError> {
+        Self::open(url, None)
+    }
+
+    /// Connects to the interface at the `wss://` or `https://` URL, trusting the CA
+    /// certificates of the PEM file instead of the ones of the system
+    pub fn connect_with_ca_file(url: &str, ca_file: &str) -> Result<Self, ClientError> {
+        Self::open(url, Some(ca_file.to_string()))
+    }
+
+    fn open(url: &str, ca_file: Option<String>) -> Result<Self, ClientError> {
+        let url = websocket_url(url);
+        let (notification_sender, notifications) = channel();
+        let pending = PendingResponses::default();
+        let next_id = Arc::new(AtomicUsize::new(1));
+        let acknowledged = Acknowledged::default();
+        let (out, thread) = open_connection(
+            &url,
+            &ca_file,
+            &pending,
+            &next_id,
+            &acknowledged,
+            &notification_sender,
+        )?;
+        Ok(InterfaceClient {
+            url,
+            ca_file,
+            out,
+            next_id,
+            pending,
+            acknowledged,
+            subscriptions: Mutex::new(HashSet::new()),
+            notification_sender,
+            notifications,
+            thread: Some(thread),
+        })
+    }
+
+    /// Sends a request without waiting for its response
+    pub fn request(&self, method: &str, params: Value) -> Result<PendingRequest, ClientError> {
+        let (sender, response) = channel();
+        let id = send_request(
+            &self.out,
+            &self.next_id,
+            &self.pending,
+            sender,
+            method,
+            params,
+        )?;
+        Ok(PendingRequest { id, response })
+    }
+
+    /// Subscribes to the events of the given instance, starting after the last one of them
+    /// the client acknowledged, so the container sends the ones it buffered first.
+    /// Returns the response of `events/subscribe`, whose `gap` tells whether some of the
+    /// events got dropped from the buffer before the client could get them.
+    pub fn subscribe(&self, instance_id: &str) -> Result<Value, ClientError> {
+        let since_seq = self
+            .acknowledged
+            .lock()
+            .unwrap()
+            .get(instance_id)
+            .cloned()
+            .unwrap_or(0);
+        let mut params = Map::new();
+        params.insert("instance_id".to_string(), Value::from(instance_id));
+        params.insert("since_seq".to_string(), Value::from(since_seq));
+        let response = self.call("events/subscribe", Value::Object(params))?;
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .insert(instance_id.to_string());
+        Ok(response)
+    }
+
+    /// Opens a new connection to the same interface, e.g. after the last one dropped, and
+    /// subscribes to the instances the client subscribed to before, see `subscribe()`.
+    /// Requests still pending on the old connection fail with ConnectionClosed.
+    /// Returns the ids of the instances some of whose events got lost meanwhile.
+    pub fn reconnect(&mut self) -> Result<Vec<String>, ClientError> {
+        self.shutdown();
+        let (out, thread) = open_connection(
+            &self.url,
+            &self.ca_file,
+            &self.pending,
+            &self.next_id,
+            &self.acknowledged,
+            &self.notification_sender,
+        )?;
+        self.out = out;
+        self.thread = Some(thread);
+        let mut subscriptions: Vec<String> =
+            self.subscriptions.lock().unwrap().iter().cloned().collect();
+        subscriptions.sort();
+        let mut gaps = Vec::new();
+        for instance_id in subscriptions {
+            if self.subscribe(&instance_id)?["gap"] == Value::Bool(true) {
+                gaps.push(instance_id);
+            }
+        }
+        Ok(gaps)
+    }
+
+    /// Sends a request and waits for its response
+    pub fn call(&self, method: &str, params: Value) -> Result<Value, ClientError> {
+        self.request(method, params)?.wait()
+    }
+
+    /// Waits for at most `timeout` for the next message that is not a response
+    /// to one of the requests, e.g. an `instance_stopped` notification
+    pub fn next_notification(&self, timeout: Duration) -> Option<Value> {
+        self.notifications.recv_timeout(timeout).ok()
+    }
+
+    /// Closes the connection, which cancels the requests that are still pending
+    pub fn close(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = self.out.close(CloseCode::Normal);
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for InterfaceClient {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// The URL with `https://` and `http://` taken as `wss://` and `ws://`
+fn websocket_url(url: &str) -> String {
+    if url.starts_with("https://") {
+        url.replacen("https://", "wss://", 1)
+    } else if url.starts_with("http://") {
+        url.replacen("http://", "ws://", 1)
+    } else {
+        url.to_string()
+    }
+}
+
+/// Opens the websocket connection in a thread of its own, returns what sends over it
+/// and that thread
+fn open_connection(
+    url: &str,
+    ca_file: &Option<String>,
+    pending: &PendingResponses,
+    next_id: &Arc<AtomicUsize>,
+    acknowledged: &Acknowledged,
+    notifications: &Sender<Value>,
+) -> Result<(ws::Sender, JoinHandle<()>), ClientError> {
+    let (opened, open) = sync_channel(1);
+    let handler_pending = pending.clone();
+    let next_id = next_id.clone();
+    let acknowledged = acknowledged.clone();
+    let notifications = notifications.clone();
+    let url = url.to_string();
+    let ca_file = ca_file.clone();
+    let thread = thread::spawn(move || {
+        let result = ws::connect(url, |out| ClientHandler {
+            out,
+            ca_file: ca_file.clone(),
+            opened: Some(opened.clone()),
+            pending: handler_pending.clone(),
+            next_id: next_id.clone(),
+            acknowledged: acknowledged.clone(),
+            notifications: notifications.clone(),
+        });
+        if let Err(error) = result {
+            let _ = opened.send(Err(error.to_string()));
+        }
+        // nothing is going to answer the requests that are still pending
+        handler_pending.lock().unwrap().clear();
+    });
+    match open.recv() {
+        Ok(Ok(out)) => Ok((out, thread)),
+        Ok(Err(reason)) => Err(ClientError::ConnectionFailed(reason)),
+        Err(_) => Err(ClientError::ConnectionFailed(String::from(
+            "Connection closed before it was open",
+        ))),
+    }
+}
+
+/// Sends a request whose response goes to `sender`, returns its id
+fn send_request(
+    out: &ws::Sender,
+    next_id: &AtomicUsize,
+    pending: &PendingResponses,
+    sender: ResponseSender,
+    method: &str,
+    params: Value,
+) -> Result<u64, ClientError> {
+    let id = next_id.fetch_add(1, Ordering::SeqCst) as u64;
+    // registered before sending, so that even the quickest response finds it
+    pending.lock().unwrap().insert(id, sender);
+    let mut request = Map::new();
+    request.insert("jsonrpc".to_string(), Value::from("2.0"));
+    request.insert("id".to_string(), Value::from(id));
+    request.insert("method".to_string(), Value::from(method));
+    request.insert("params".to_string(), params);
+    if out.send(Value::Object(request).to_string()).is_err() {
+        pending.lock().unwrap().remove(&id);
+        return Err(ClientError::ConnectionClosed);
+    }
+    Ok(id)
+}
+
+struct ClientHandler {
+    out: ws::Sender,
+    ca_file: Option<String>,
+    opened: Option<SyncSender<Result<ws::Sender, String>>>,
+    pending: PendingResponses,
+    next_id: Arc<AtomicUsize>,
+    acknowledged: Acknowledged,
+    notifications: Sender<Value>,
+}
+
+impl ClientHandler {
+    /// Acknowledges the event the notification is about, if it is one. false if the client
+    /// got the event already, e.g. from the buffer and as it happened.
+    fn acknowledge(&self, notification: &Value) -> bool {
+        let params = &notification["params"];
+        let (instance_id, seq) = match (params["instance_id"].as_str(), params["seq"].as_u64()) {
+            (Some(instance_id), Some(seq)) => (instance_id, seq),
+            _ => return true,
+        };
+        {
+            let mut acknowledged = self.acknowledged.lock().unwrap();
+            let last = acknowledged.entry(instance_id.to_string()).or_insert(0);
+            if seq <= *last {
+                return false;
+            }
+            *last = seq;
+        }
+        // nobody waits for the response
+        let (sender, _) = channel();
+        let mut ack = Map::new();
+        ack.insert("instance_id".to_string(), Value::from(instance_id));
+        ack.insert("seq".to_string(), Value::from(seq));
+        let _ = send_request(
+            &self.out,
+            &self.next_id,
+            &self.pending,
+            sender,
+            "events/ack",
+            Value::Object(ack),
+        );
+        true
+    }
+}
+
+impl Handler for ClientHandler {
+    fn upgrade_ssl_client(
+        &mut self,
+        sock: TcpStream,
+        url: &Url,
+    ) -> ws::Result<SslStream<TcpStream>> {
+        let internal = |error: String| ws::Error::new(ws::ErrorKind::Internal, error);
+        let mut builder =
+            SslConnector::builder(SslMethod::tls()).map_err(|e| internal(e.to_string()))?;
+        if let Some(ref ca_file) = self.ca_file {
+            builder
+                .set_ca_file(ca_file)
+                .map_err(|e| internal(format!("Could not load CA file {}: {}", ca_file, e)))?;
+        }
+        // IP addresses are no domains, but certificates can be issued for them as well
+        let host = url
+            .host_str()
+            .ok_or_else(|| internal(format!("{} names no host", url)))?;
+        // handshakes that would block get continued by ws
+        builder.build().connect(host, sock).map_err(ws::Error::from)
+    }
+
+    fn on_open(&mut self, _: Handshake) -> ws::Result<()> {
+        if let Some(opened) = self.opened.take() {
+            let _ = opened.send(Ok(self.out.clone()));
+        }
+        Ok(())
+    }
+
+    fn on_message(&mut self, message: Message) -> ws::Result<()> {
+        let message: Value = match serde_json::from_str(&message.into_text()?) {
+            Ok(message) => message,
+            // not JSON-RPC, nothing to do with it
+            Err(_) => return Ok(()),
+        };
+        let request = message["id"]
+            .as_u64()
+            .and_then(|id| self.pending.lock().unwrap().remove(&id));
+        match request {
+            Some(request) => {
+                let _ = request.send(response_result(message));
+            }
+            None => {
+                if self.acknowledge(&message) {
+                    let _ = self.notifications.send(message);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn on_close(&mut self, _: CloseCode, _: &str) {
+        self.pending.lock().unwrap().clear();
+    }
+}
+
+fn response_result(mut response: Value) -> Result<Value, RpcError> {
+    match response.get("error").cloned() {
+        Some(error) => Err(
+            serde_json::from_value(error.clone()).unwrap_or_else(|_| RpcError {
+                code: -32603,
+                message: error.to_string(),
+                data: None,
+            }),
+        ),
+        None => Ok(response["result"].take()),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn responses_are_results_or_errors() {
+        assert_eq!(
+            response_result(json_value(r#"{"jsonrpc":"2.0","id":1,"result":"ok"}"#)),
+            Ok(Value::from("ok"))
+        );
+        assert_eq!(
+            response_result(json_value(
+                r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"Method not found"}}"#
+            )),
+            Err(RpcError {
+                code: -32601,
+                message: String::from("Method not found"),
+                data: None,
+            })
+        );
+    }
+
+    #[test]
+    fn connecting_to_nothing_fails() {
+        let port = ::std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        match InterfaceClient::connect(&format!("ws://127.0.0.1:{}", port)) {
+            Err(ClientError::ConnectionFailed(_)) => (),
+            other => panic!("expected ConnectionFailed, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    fn json_value(json: &str) -> Value {
+        serde_json::from_str(json).unwrap()
+    }
+}
@@ -1,31 +1,216 @@
-// use holochain_core_types::json::JsonString;
+use jsonrpc_ws_server::jsonrpc_core::MetaIoHandler;
+use openssl::ssl::SslStream;
+use std::{
+    io::{self, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use tiny_http::{Header, Method, Request, Response, Server};
 
-use std::sync::Arc;
-use tiny_http::{Response, Server};
+use interface::{
+    prometheus_metrics, CallMeta, ContainerApiDispatcher, DispatchRpc, InstanceMap, Interface,
+    RequestLogMiddleware,
+};
+use tls::TlsCertificate;
 
-use interface::{DispatchRpc, Interface};
+/// How long a client may take to finish the TLS handshake
+const TLS_HANDSHAKE_TIMEOUT_MS: u64 = 10_000;
+/// How long relaying a TLS connection reads from its client at a time, before it lets
+/// the response of the server through
+const TLS_RELAY_READ_TIMEOUT_MS: u64 = 50;
 
 pub struct HttpInterface {
     port: u16,
+    tls: Option<TlsCertificate>,
 }
 
-/// TODO: this is a stub, to be implemented later
 impl HttpInterface {
     pub fn new(port: u16) -> Self {
-        Self { port }
+        HttpInterface { port, tls: None }
+    }
+
+    pub fn with_tls(mut self, certificate: TlsCertificate) -> Self {
+        self.tls = Some(certificate);
+        self
+    }
+
+    // a client that went away before it got its response must not stop the interface
+    fn respond(
+        &self,
+        io: &MetaIoHandler<CallMeta, RequestLogMiddleware>,
+        instances: Option<&InstanceMap>,
+        request: Request,
+    ) {
+        if let Err(error) = respond(io, instances, request) {
+            eprintln!(
+                "Could not respond to a request on port {}: {}",
+                self.port, error
+            );
+        }
     }
 }
 
-impl Interface for HttpInterface {
-    fn run(&self, _dispatcher: Arc<DispatchRpc>) -> Result<(), String> {
-        let server_url = format!("0.0.0.0:{}", self.port);
-        let server = Server::http(server_url.as_str()).unwrap();
+/// Serves the same JSON-RPC methods as the websocket interface,
+/// with the request being the body of a POST request.
+/// The partial results a zome call yields come before its return value, in an array.
+/// On admin interfaces, a GET request for /metrics gets the durations of the zome calls of
+/// the instances, for Prometheus to scrape, see `prometheus_metrics()`.
+/// With a TLS certificate, it serves https: the port accepts the TLS connections and relays
+/// them to an http server only listening on the loopback interface. Every connection gets
+/// the certificate that is current when it opens, see `TlsCertificate::reload()`, so
+/// reloading it neither closes connections nor the port.
+impl Interface<ContainerApiDispatcher> for HttpInterface {
+    fn run(&self, dispatcher: ContainerApiDispatcher) -> Result<(), String> {
+        let mut io = MetaIoHandler::with_middleware(dispatcher.request_log_middleware());
+        let instances = if dispatcher.is_admin() {
+            Some(dispatcher.instances())
+        } else {
+            None
+        };
+        let handler: MetaIoHandler<CallMeta> = dispatcher.handler().into();
+        io.extend_with(handler);
+        let server = match self.tls {
+            Some(ref certificate) => {
+                let listener = TcpListener::bind(format!("0.0.0.0:{}", self.port))
+                    .map_err(|e| e.to_string())?;
+                let server = Server::http("127.0.0.1:0").map_err(|e| e.to_string())?;
+                let server_address = server.server_addr();
+                let certificate = certificate.clone();
+                thread::spawn(move || {
+                    for stream in listener.incoming() {
+                        // failed accepts only concern the client that tried to connect
+                        if let Ok(stream) = stream {
+                            let certificate = certificate.clone();
+                            thread::spawn(move || relay_tls(stream, &certificate, server_address));
+                        }
+                    }
+                });
+                server
+            }
+            None => Server::http(format!("0.0.0.0:{}", self.port).as_str())
+                .map_err(|e| e.to_string())?,
+        };
         for request in server.incoming_requests() {
-            let method = request.url().to_string();
-            println!("{}", method);
-            let response = Response::from_string(method);
-            request.respond(response).unwrap();
+            self.respond(&io, instances.as_ref(), request);
         }
-        unimplemented!();
+        Ok(())
+    }
+}
+
+/// Accepts the TLS connection with the current certificate and relays what comes through it
+/// to the http server at the given address and back, until one of them closes it
+fn relay_tls(stream: TcpStream, certificate: &TlsCertificate, server_address: SocketAddr) {
+    let accepted = stream
+        .set_read_timeout(Some(Duration::from_millis(TLS_HANDSHAKE_TIMEOUT_MS)))
+        .map_err(|e| e.to_string())
+        .and_then(|_| {
+            certificate
+                .current()
+                .acceptor()
+                .accept(stream)
+                .map_err(|e| e.to_string())
+        })
+        .and_then(|tls| {
+            let server = TcpStream::connect(server_address).map_err(|e| e.to_string())?;
+            // the client gets read in turns with the responses being written to it
+            tls.get_ref()
+                .set_read_timeout(Some(Duration::from_millis(TLS_RELAY_READ_TIMEOUT_MS)))
+                .map_err(|e| e.to_string())?;
+            Ok((tls, server))
+        });
+    let (tls, server) = match accepted {
+        Ok(accepted) => accepted,
+        // the client did not speak TLS or went away, nothing to tell it
+        Err(_) => return,
+    };
+    let tls = Arc::new(Mutex::new(tls));
+    let from_server = match server.try_clone() {
+        Ok(from_server) => from_server,
+        Err(_) => return,
+    };
+    let to_client = tls.clone();
+    let responses = thread::spawn(move || relay_responses(from_server, &to_client));
+    relay_requests(&tls, server);
+    let _ = responses.join();
+}
+
+// from the client to the server, until the client closes the connection
+fn relay_requests(tls: &Mutex<SslStream<TcpStream>>, mut server: TcpStream) {
+    let mut buffer = [0; 8192];
+    loop {
+        let read = tls.lock().unwrap().read(&mut buffer);
+        match read {
+            Ok(0) => break,
+            Ok(count) => {
+                if server.write_all(&buffer[..count]).is_err() {
+                    break;
+                }
+            }
+            Err(ref error)
+                if error.kind() == io::ErrorKind::WouldBlock
+                    || error.kind() == io::ErrorKind::TimedOut => {}
+            Err(_) => break,
+        }
+    }
+    let _ = server.shutdown(Shutdown::Write);
+}
+
+// from the server to the client, until the server closes the connection
+fn relay_responses(mut server: TcpStream, tls: &Mutex<SslStream<TcpStream>>) {
+    let mut buffer = [0; 8192];
+    loop {
+        match server.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(count) => {
+                let mut tls = tls.lock().unwrap();
+                if tls
+                    .write_all(&buffer[..count])
+                    .and_then(|_| tls.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        }
+    }
+    let mut tls = tls.lock().unwrap();
+    let _ = tls.shutdown();
+    let _ = tls.get_ref().shutdown(Shutdown::Both);
+}
+
+fn respond(
+    io: &MetaIoHandler<CallMeta, RequestLogMiddleware>,
+    instances: Option<&InstanceMap>,
+    mut request: Request,
+) -> Result<(), String> {
+    if *request.method() == Method::Get && request.url() == "/metrics" {
+        let instances = match instances {
+            Some(instances) => instances,
+            None => {
+                let response = Response::from_string("Not Found").with_status_code(404);
+                return request.respond(response).map_err(|e| e.to_string());
+            }
+        };
+        let content_type =
+            Header::from_bytes(&b"Content-Type"[..], &b"text/plain; version=0.0.4"[..])
+                .expect("Content-Type header should be valid");
+        let response =
+            Response::from_string(prometheus_metrics(instances)).with_header(content_type);
+        return request.respond(response).map_err(|e| e.to_string());
+    }
+    let mut body = String::new();
+    if let Err(e) = request.as_reader().read_to_string(&mut body) {
+        let response = Response::from_string(e.to_string()).with_status_code(400);
+        return request.respond(response).map_err(|e| e.to_string());
     }
+    // Notifications don't get a response, so the body stays empty
+    let response_body = io
+        .handle_request_sync(&body, CallMeta::default())
+        .unwrap_or_default();
+    let content_type = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+        .expect("Content-Type header should be valid");
+    let response = Response::from_string(response_body).with_header(content_type);
+    request.respond(response).map_err(|e| e.to_string())
 }
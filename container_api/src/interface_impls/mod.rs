@@ -1,3 +1,4 @@
+pub mod http;
 pub mod websocket;
 
-pub use self::websocket::*;
+pub use self::{http::*, websocket::*};
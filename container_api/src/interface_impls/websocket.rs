@@ -1,25 +1,167 @@
-use jsonrpc_ws_server::ServerBuilder;
+use jsonrpc_ws_server::jsonrpc_core::{self, MetaIoHandler, Response, Version};
+use openssl::ssl::SslStream;
+use serde_json;
+use std::{
+    cmp,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use ws::{self, util::TcpStream, CloseCode, Handler, Handshake, Message};
 
-use interface::{ContainerApiDispatcher, DispatchRpc, Interface};
+use interface::{
+    CallIdMiddleware, CallMeta, Connection, Connections, ContainerApiDispatcher, DispatchRpc,
+    Interface, RequestLogMiddleware,
+};
+use tls::TlsCertificate;
 
+type WebsocketIoHandler = MetaIoHandler<CallMeta, (RequestLogMiddleware, CallIdMiddleware)>;
+
+/// Serves the container API as JSON-RPC 2.0 over websockets. Every connection can have many
+/// requests in flight, each one is handled in its own thread and gets its response, with
+/// its id, as soon as it is done. Frames that are no JSON-RPC request get an error frame.
+/// With an idle timeout, connections that are idle for that long get closed,
+/// see `Connection::is_idle()`.
+/// With a TLS certificate, it serves wss. Every connection gets the certificate that is
+/// current when it opens, see `TlsCertificate::reload()`.
 pub struct WebsocketInterface {
     port: u16,
+    idle_timeout: Option<Duration>,
+    tls: Option<TlsCertificate>,
 }
 
 impl WebsocketInterface {
     pub fn new(port: u16) -> Self {
-        WebsocketInterface { port }
+        WebsocketInterface {
+            port,
+            idle_timeout: None,
+            tls: None,
+        }
+    }
+
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+
+    pub fn with_tls(mut self, certificate: TlsCertificate) -> Self {
+        self.tls = Some(certificate);
+        self
     }
 }
 
 impl Interface<ContainerApiDispatcher> for WebsocketInterface {
     fn run(&self, dispatcher: ContainerApiDispatcher) -> Result<(), String> {
-        let io = dispatcher.handler();
+        let connections = dispatcher.connections();
+        // requests get logged before anything else, so the ones refused for reusing the id
+        // of a request in flight show up in the log as well
+        let middleware = (dispatcher.request_log_middleware(), CallIdMiddleware);
+        let mut io = MetaIoHandler::with_middleware(middleware);
+        let handler: MetaIoHandler<CallMeta> = dispatcher.handler().into();
+        io.extend_with(handler);
+        let io = Arc::new(io);
+        if let Some(idle_timeout) = self.idle_timeout {
+            let connections = Arc::downgrade(&connections);
+            // idle connections get closed at most a quarter of the timeout too late
+            let check_interval = cmp::min(idle_timeout / 4, Duration::from_secs(10));
+            thread::spawn(move || loop {
+                thread::sleep(check_interval);
+                match connections.upgrade() {
+                    Some(connections) => {
+                        connections.disconnect_idle(idle_timeout);
+                    }
+                    None => break,
+                }
+            });
+        }
         let url = format!("0.0.0.0:{}", self.port);
-        let server = ServerBuilder::new(io)
-            .start(&url.parse().expect("Invalid URL!"))
-            .map_err(|e| e.to_string())?;
-        server.wait().map_err(|e| e.to_string())?;
+        let settings = ws::Settings {
+            encrypt_server: self.tls.is_some(),
+            ..ws::Settings::default()
+        };
+        let tls = self.tls.clone();
+        let factory = move |out: ws::Sender| {
+            // partial results of zome calls and notifications get pushed over the connection
+            let push_out = Mutex::new(out.clone());
+            let close_out = Mutex::new(out.clone());
+            let connection = Connection::new(move |message| {
+                push_out
+                    .lock()
+                    .unwrap()
+                    .send(message)
+                    .map_err(|e| e.to_string())
+            })
+            .with_disconnect(move || {
+                let _ = close_out.lock().unwrap().close(CloseCode::Away);
+            });
+            ConnectionHandler {
+                io: io.clone(),
+                connections: connections.clone(),
+                connection: Arc::new(connection),
+                out,
+                tls: tls.clone(),
+            }
+        };
+        ws::Builder::new()
+            .with_settings(settings)
+            .build(factory)
+            .and_then(|socket| socket.listen(url))
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+struct ConnectionHandler {
+    io: Arc<WebsocketIoHandler>,
+    connections: Arc<Connections>,
+    connection: Arc<Connection>,
+    out: ws::Sender,
+    tls: Option<TlsCertificate>,
+}
+
+impl Handler for ConnectionHandler {
+    fn upgrade_ssl_server(&mut self, sock: TcpStream) -> ws::Result<SslStream<TcpStream>> {
+        let certificate = self.tls.as_ref().ok_or_else(|| {
+            ws::Error::new(ws::ErrorKind::Internal, "The interface serves no TLS")
+        })?;
+        // handshakes that would block get continued by ws
+        certificate
+            .current()
+            .acceptor()
+            .accept(sock)
+            .map_err(ws::Error::from)
+    }
+
+    fn on_open(&mut self, _: Handshake) -> ws::Result<()> {
+        self.connections.add(self.connection.clone());
+        Ok(())
+    }
+
+    fn on_message(&mut self, message: Message) -> ws::Result<()> {
+        let request = match message {
+            Message::Text(request) => request,
+            // requests are JSON text, so binary frames can't be parsed as one
+            Message::Binary(_) => return self.out.send(parse_error()),
+        };
+        // a slow zome call doesn't hold up the requests that came after it
+        let io = self.io.clone();
+        let connection = self.connection.clone();
+        thread::spawn(move || {
+            let meta = CallMeta::with_connection(connection.clone());
+            if let Some(response) = io.handle_request_sync(&request, meta) {
+                // clients that went away don't get the responses of their canceled requests
+                let _ = connection.push(response);
+            }
+        });
         Ok(())
     }
+
+    fn on_close(&mut self, _: CloseCode, _: &str) {
+        self.connections.remove(self.connection.id());
+    }
+}
+
+fn parse_error() -> String {
+    let response = Response::from(jsonrpc_core::Error::parse_error(), Some(Version::V2));
+    serde_json::to_string(&response).expect("JSON-RPC responses can be serialized")
 }
@@ -94,6 +94,7 @@ extern crate holochain_cas_implementations;
 extern crate holochain_core;
 extern crate holochain_core_types;
 extern crate holochain_net;
+extern crate holochain_wasm_utils;
 
 extern crate serde;
 extern crate tempfile;
@@ -101,17 +102,26 @@ extern crate tempfile;
 extern crate serde_derive;
 extern crate boolinator;
 extern crate jsonrpc_ws_server;
+extern crate openssl;
 extern crate serde_json;
 #[cfg(test)]
 extern crate test_utils;
 extern crate tiny_http;
 extern crate toml;
+extern crate url;
+extern crate ws;
 
 pub mod config;
+pub mod config_errors;
 pub mod container;
 pub mod error;
 pub mod holochain;
 pub mod interface;
+pub mod interface_client;
 pub mod interface_impls;
+pub mod scenario;
+pub mod signal_filter;
+pub mod tls;
+pub mod watchdog;
 
 pub use crate::holochain::Holochain;
@@ -0,0 +1,389 @@
+//! Scenarios of several agents interacting through instances of the same DNA, for tests
+//! like "alice comments on bob's post".
+//! All instances run in one container and talk over the in-process mock network, so every
+//! agent holds everything that gets published. Scenarios of different tests only stay apart
+//! if their DNAs have different UUIDs.
+//! `wait_for_consistency()` waits for the instances of any container to settle, which
+//! interfaces of containers in test mode expose as `test/consistency`.
+
+use crate::{
+    config::{
+        AgentConfiguration, Configuration, DNAConfiguration, InstanceConfiguration, RestartPolicy,
+        StorageConfiguration,
+    },
+    container::{Container, PublishedBeforeStart},
+    error::HolochainResult,
+    holochain::Holochain,
+};
+use holochain_core::state::State;
+use holochain_core_types::{
+    cas::content::Address, dna::Dna, error::HolochainError, json::JsonString,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long `Scenario::consistency()` and `test/consistency` wait by default
+pub const DEFAULT_CONSISTENCY_TIMEOUT_SECS: u64 = 30;
+
+/// How often `Scenario::consistency()` and `wait_for_consistency()` check whether the
+/// instances settled, in milliseconds
+const CONSISTENCY_POLL_INTERVAL_MS: u64 = 10;
+
+const SCENARIO_DNA_ID: &str = "scenario dna";
+
+/// The counters `wait_for_consistency()` waits to get to zero, in the order it checks them
+pub const NETWORK_OPS_COUNTER: &str = "network_ops";
+pub const PENDING_VALIDATIONS_COUNTER: &str = "pending_validations";
+pub const UNSETTLED_PUBLISHES_COUNTER: &str = "unsettled_publishes";
+pub const UNFLUSHED_COMMITS_COUNTER: &str = "unflushed_commits";
+
+/// A counter of an instance that was not zero yet when `wait_for_consistency()` gave up
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BusyCounter {
+    pub instance_id: String,
+    pub counter: String,
+    pub count: usize,
+}
+
+/// What the instance with the given state published so far
+pub(crate) fn published_addresses(state: &State) -> HashSet<Address> {
+    state.network().pending_publishes.keys().cloned().collect()
+}
+
+/// The first counter of the state that is not zero: the network requests and direct messages
+/// the instance waits for, its validations that wait for their dependencies, what it published
+/// since the instances started that not all of the given number of instances hold yet and the
+/// commits that were not flushed yet
+fn busy_counter(
+    state: &State,
+    published_before_start: &HashSet<Address>,
+    instance_count: usize,
+) -> Option<(&'static str, usize)> {
+    let pending_validations = state
+        .dht()
+        .pending_validations()
+        .map(|pending_validations| pending_validations.len())
+        .unwrap_or(0);
+    let network = state.network();
+    let unsettled_publishes = network
+        .pending_publishes
+        .keys()
+        .filter(|address| !published_before_start.contains(address))
+        .filter(|address| network.holders_count(address) < instance_count)
+        .count();
+    vec![
+        (
+            NETWORK_OPS_COUNTER,
+            state.pending_network_ops() - pending_validations,
+        ),
+        (PENDING_VALIDATIONS_COUNTER, pending_validations),
+        (UNSETTLED_PUBLISHES_COUNTER, unsettled_publishes),
+        (UNFLUSHED_COMMITS_COUNTER, state.agent().pending_commits()),
+    ]
+    .into_iter()
+    .find(|(_, count)| *count > 0)
+}
+
+/// Blocks until none of the given instances waits for the network or for the dependencies of
+/// a validation anymore and what each of them published since the instances started is held
+/// by all of them, flushing the commits of the instances that only have those left, see
+/// `Holochain::flush()`. Tests call it after publishing instead of sleeping until the other
+/// instances got it.
+/// Fails with the first counter of an instance that was still busy once the timeout elapsed.
+pub fn wait_for_consistency(
+    instances: &[(String, Arc<RwLock<Holochain>>)],
+    published_before_start: &HashMap<String, HashSet<Address>>,
+    timeout: Duration,
+) -> Result<(), BusyCounter> {
+    let deadline = Instant::now() + timeout;
+    let no_addresses = HashSet::new();
+    loop {
+        let busy = instances
+            .iter()
+            .filter_map(|(instance_id, hc_lock)| {
+                let published_before_start = published_before_start
+                    .get(instance_id)
+                    .unwrap_or(&no_addresses);
+                let counter_of =
+                    |state: State| busy_counter(&state, published_before_start, instances.len());
+                let hc = hc_lock.read().unwrap();
+                let mut counter = hc.state().ok().and_then(counter_of);
+                if let Some((UNFLUSHED_COMMITS_COUNTER, _)) = counter {
+                    if hc.flush().is_ok() {
+                        counter = hc.state().ok().and_then(counter_of);
+                    }
+                }
+                counter.map(|(counter, count)| BusyCounter {
+                    instance_id: instance_id.clone(),
+                    counter: counter.to_string(),
+                    count,
+                })
+            })
+            .next();
+        match busy {
+            None => return Ok(()),
+            Some(busy) => {
+                if Instant::now() >= deadline {
+                    return Err(busy);
+                }
+            }
+        }
+        thread::sleep(Duration::from_millis(CONSISTENCY_POLL_INTERVAL_MS));
+    }
+}
+
+/// One agent of a scenario, with its own instance of the scenario's DNA
+pub struct ScenarioAgent {
+    pub name: String,
+    holochain: Arc<RwLock<Holochain>>,
+}
+
+impl ScenarioAgent {
+    fn new(name: &str, container: &Container) -> Result<Self, String> {
+        let holochain = container
+            .instances
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("Instance of agent \"{}\" not found", name))?;
+        Ok(ScenarioAgent {
+            name: name.to_string(),
+            holochain,
+        })
+    }
+
+    /// Calls a function of the agent's instance, like an interface of the container would
+    pub fn call(
+        &self,
+        zome: &str,
+        cap: &str,
+        fn_name: &str,
+        params: &str,
+    ) -> HolochainResult<JsonString> {
+        self.holochain
+            .write()
+            .unwrap()
+            .call(zome, cap, fn_name, params)
+    }
+
+    pub fn state(&self) -> HolochainResult<State> {
+        self.holochain.read().unwrap().state()
+    }
+
+    /// Blocks until the commits of the agent are on the disk, see `Holochain::flush()`
+    pub fn flush(&self) -> HolochainResult<usize> {
+        self.holochain.read().unwrap().flush()
+    }
+}
+
+/// A container with an instance of one DNA for each of the agents alice and bob,
+/// which have chains of their own and share a mock network.
+/// The container shuts down when the scenario gets dropped.
+pub struct Scenario {
+    pub container: Container,
+    pub alice: ScenarioAgent,
+    pub bob: ScenarioAgent,
+    consistency_timeout: Duration,
+}
+
+impl Scenario {
+    /// Starts instances of the DNA for alice and bob, with memory storage.
+    /// With the `test_fixtures` feature they are the agents of `test_agent("alice")` and
+    /// `test_agent("bob")`.
+    pub fn two_agents(dna: Dna) -> Result<Self, String> {
+        let names = ["alice", "bob"];
+        let config = Configuration {
+            agents: names
+                .iter()
+                .map(|name| AgentConfiguration {
+                    id: name.to_string(),
+                    name: None,
+                    key_file: format!("{}.key", name),
+                })
+                .collect(),
+            dnas: vec![DNAConfiguration {
+                id: SCENARIO_DNA_ID.to_string(),
+                file: format!("{}.dna.json", dna.name),
+                hash: String::new(),
+            }],
+            instances: names
+                .iter()
+                .map(|name| InstanceConfiguration {
+                    id: name.to_string(),
+                    dna: SCENARIO_DNA_ID.to_string(),
+                    agent: name.to_string(),
+                    logger: None,
+                    storage: Some(StorageConfiguration::Memory),
+                    network: None,
+                    timeouts: None,
+                    archived: false,
+                    record_actions: None,
+                    depends_on: Vec::new(),
+                    gc_interval: None,
+                    properties_override: None,
+                    max_wasm_memory_mb: None,
+                    signal_buffer_size: None,
+                    audit_host_calls: false,
+                    genesis_params: None,
+                    restart: RestartPolicy::Never,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        // alice and bob have the same addresses in every run, if the feature allows for it
+        #[cfg(feature = "test_fixtures")]
+        let config = names
+            .iter()
+            .fold(config, |config, name| config.with_test_agent(name));
+
+        let mut container = Container::with_config(config.clone());
+        container.dna_loader = Arc::new(Box::new(move |_path: &String| Ok(dna.clone()))
+            as Box<FnMut(&String) -> Result<Dna, HolochainError> + Send>);
+        container.load_config(&config)?;
+        container
+            .start_all_instances()
+            .map_err(|error| error.to_string())?;
+
+        let alice = ScenarioAgent::new("alice", &container)?;
+        let bob = ScenarioAgent::new("bob", &container)?;
+        Ok(Scenario {
+            container,
+            alice,
+            bob,
+            consistency_timeout: Duration::from_secs(DEFAULT_CONSISTENCY_TIMEOUT_SECS),
+        })
+    }
+
+    /// Sets how long `consistency()` waits before it gives up
+    pub fn with_consistency_timeout(mut self, timeout: Duration) -> Self {
+        self.consistency_timeout = timeout;
+        self
+    }
+
+    pub fn agents(&self) -> Vec<&ScenarioAgent> {
+        vec![&self.alice, &self.bob]
+    }
+
+    fn instances(&self) -> Vec<(String, Arc<RwLock<Holochain>>)> {
+        self.agents()
+            .iter()
+            .map(|agent| (agent.name.clone(), agent.holochain.clone()))
+            .collect()
+    }
+
+    /// Blocks until what the agents published since the scenario started is held by all
+    /// of them and no instance waits for the network anymore, so that every agent sees what
+    /// the others did, see `wait_for_consistency()`, which flushes their commits too.
+    /// Fails with Timeout if that does not happen in time, which it never does if an agent
+    /// rejected something another one published.
+    pub fn consistency(&self) -> Result<(), HolochainError> {
+        let published_before_start = self.container.published_before_start();
+        let published_before_start = published_before_start.read().unwrap();
+        wait_for_consistency(
+            &self.instances(),
+            &published_before_start,
+            self.consistency_timeout,
+        )
+        .map_err(|_| HolochainError::Timeout)
+    }
+}
+
+impl Drop for Scenario {
+    fn drop(&mut self) {
+        let _ = self.container.shutdown();
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::holochain::tests::example_api_wasm;
+    use test_utils::{create_test_cap_with_fn_name, create_test_dna_with_cap};
+
+    fn test_dna(uuid: &str) -> Dna {
+        let capability = create_test_cap_with_fn_name("commit_test");
+        let mut dna =
+            create_test_dna_with_cap("test_zome", "test_cap", &capability, &example_api_wasm());
+        dna.uuid = uuid.to_string();
+        dna
+    }
+
+    fn test_entry_count(agent: &ScenarioAgent) -> usize {
+        agent
+            .state()
+            .unwrap()
+            .agent()
+            .chain_info()
+            .count("testEntryType")
+    }
+
+    #[test]
+    fn agents_of_a_scenario_have_chains_of_their_own() {
+        let scenario = Scenario::two_agents(test_dna("agents_have_chains_of_their_own")).unwrap();
+        assert_ne!(
+            scenario.alice.state().unwrap().agent().get_agent_address(),
+            scenario.bob.state().unwrap().agent().get_agent_address()
+        );
+
+        let result = scenario
+            .alice
+            .call("test_zome", "test_cap", "commit_test", r#"{}"#);
+        assert!(result.is_ok(), "result = {:?}", result);
+
+        assert_eq!(test_entry_count(&scenario.alice), 1);
+        assert_eq!(test_entry_count(&scenario.bob), 0);
+    }
+
+    #[test]
+    fn consistency_of_a_scenario_without_publishes() {
+        let scenario = Scenario::two_agents(test_dna("consistency_without_publishes"))
+            .unwrap()
+            .with_consistency_timeout(Duration::from_secs(5));
+        assert_eq!(scenario.consistency(), Ok(()));
+        for agent in scenario.agents() {
+            assert_eq!(agent.state().unwrap().agent().pending_commits(), 0);
+        }
+    }
+
+    #[test]
+    fn waiting_for_consistency_flushes_the_commits() {
+        let scenario =
+            Scenario::two_agents(test_dna("waiting_for_consistency_flushes_the_commits")).unwrap();
+        let result = scenario
+            .alice
+            .call("test_zome", "test_cap", "commit_test", r#"{}"#);
+        assert!(result.is_ok(), "result = {:?}", result);
+        assert!(scenario.alice.state().unwrap().agent().pending_commits() > 0);
+
+        let published_before_start = scenario.container.published_before_start();
+        assert_eq!(
+            wait_for_consistency(
+                &scenario.instances(),
+                &published_before_start.read().unwrap(),
+                Duration::from_secs(5)
+            ),
+            Ok(())
+        );
+        for agent in scenario.agents() {
+            assert_eq!(agent.state().unwrap().agent().pending_commits(), 0);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "test_fixtures")]
+    fn agents_of_a_scenario_are_test_agents() {
+        use holochain_core::test_fixtures::test_agent;
+        use holochain_core_types::cas::content::AddressableContent;
+
+        let scenario = Scenario::two_agents(test_dna("agents_are_test_agents")).unwrap();
+        for agent in scenario.agents() {
+            assert_eq!(
+                agent.state().unwrap().agent().get_agent_address(),
+                Ok(test_agent(&agent.name).agent_id.address())
+            );
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Filters on the payload of the events a client subscribes to, see `events/subscribe`.
+//! The payload of an event are the params of its notification, which for the lifecycle events
+//! of instances, the only ones there are, is `{"instance_id": .., "seq": ..}`. A filter is one or more
+//! conditions joined by `&&`, each of them either checking that a field of the payload
+//! exists, like `payload.seq`, or that it equals a JSON string, number, boolean or null,
+//! like `payload.instance_id == "chat"`. Nested fields are separated by dots.
+//! The container evaluates the filters before it pushes an event, once for each
+//! subscription, so that clients only get the events they are interested in.
+
+use serde_json::{self, Value};
+use std::fmt;
+
+/// Why a filter expression is invalid, with the position of the character it is about,
+/// counting from 0
+#[derive(Clone, Debug, PartialEq)]
+pub struct FilterError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Condition {
+    Exists(Vec<String>),
+    Equals(Vec<String>, Value),
+}
+
+/// A parsed filter expression, see the module docs
+#[derive(Clone, Debug, PartialEq)]
+pub struct PayloadFilter {
+    source: String,
+    conditions: Vec<Condition>,
+}
+
+impl PayloadFilter {
+    pub fn parse(source: &str) -> Result<Self, FilterError> {
+        let mut parser = Parser {
+            chars: source.chars().collect(),
+            position: 0,
+        };
+        let mut conditions = vec![parser.condition()?];
+        loop {
+            parser.skip_whitespace();
+            if parser.peek().is_none() {
+                break;
+            }
+            parser.expect("&&")?;
+            conditions.push(parser.condition()?);
+        }
+        Ok(PayloadFilter {
+            source: source.to_string(),
+            conditions,
+        })
+    }
+
+    /// The expression the filter got parsed from
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether the payload passes all of the conditions
+    pub fn matches(&self, payload: &Value) -> bool {
+        self.conditions.iter().all(|condition| match condition {
+            Condition::Exists(path) => lookup(payload, path).is_some(),
+            Condition::Equals(path, expected) => lookup(payload, path)
+                .map(|value| equals(value, expected))
+                .unwrap_or(false),
+        })
+    }
+}
+
+fn lookup<'a>(payload: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter()
+        .try_fold(payload, |value, field| value.get(field.as_str()))
+}
+
+// numbers are equal whether they are written as integers or floats
+fn equals(value: &Value, expected: &Value) -> bool {
+    match (value.as_f64(), expected.as_f64()) {
+        (Some(number), Some(expected_number)) => number == expected_number,
+        _ => value == expected,
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map(char::is_whitespace).unwrap_or(false) {
+            self.position += 1;
+        }
+    }
+
+    fn error_at<T>(&self, position: usize, message: &str) -> Result<T, FilterError> {
+        Err(FilterError {
+            position,
+            message: message.to_string(),
+        })
+    }
+
+    fn lookahead(&self, token: &str) -> bool {
+        token
+            .chars()
+            .enumerate()
+            .all(|(offset, c)| self.chars.get(self.position + offset) == Some(&c))
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), FilterError> {
+        if !self.lookahead(token) {
+            return self.error_at(self.position, &format!("Expected `{}`", token));
+        }
+        self.position += token.chars().count();
+        Ok(())
+    }
+
+    // a path, optionally followed by `==` and a literal
+    fn condition(&mut self) -> Result<Condition, FilterError> {
+        self.skip_whitespace();
+        let path = self.path()?;
+        self.skip_whitespace();
+        if !self.lookahead("==") {
+            return Ok(Condition::Exists(path));
+        }
+        self.position += 2;
+        self.skip_whitespace();
+        Ok(Condition::Equals(path, self.literal()?))
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        let start = self.position;
+        while self
+            .peek()
+            .map(|c| c.is_alphanumeric() || c == '_')
+            .unwrap_or(false)
+        {
+            self.position += 1;
+        }
+        if self.position == start {
+            return None;
+        }
+        Some(self.chars[start..self.position].iter().collect())
+    }
+
+    // `payload` and the names of the fields below it
+    fn path(&mut self) -> Result<Vec<String>, FilterError> {
+        let start = self.position;
+        if self.identifier() != Some("payload".to_string()) {
+            return self.error_at(start, "Expected a path starting with `payload`");
+        }
+        let mut path = Vec::new();
+        while self.peek() == Some('.') {
+            self.position += 1;
+            match self.identifier() {
+                Some(field) => path.push(field),
+                None => return self.error_at(self.position, "Expected a field name"),
+            }
+        }
+        Ok(path)
+    }
+
+    fn literal(&mut self) -> Result<Value, FilterError> {
+        let start = self.position;
+        if self.peek() == Some('"') {
+            self.position += 1;
+            loop {
+                match self.peek() {
+                    None => return self.error_at(start, "Unterminated string"),
+                    Some('\\') => self.position += 2,
+                    Some('"') => break,
+                    Some(_) => self.position += 1,
+                }
+            }
+            self.position += 1;
+        } else {
+            while self
+                .peek()
+                .map(|c| c.is_alphanumeric() || c == '-' || c == '+' || c == '.')
+                .unwrap_or(false)
+            {
+                self.position += 1;
+            }
+        }
+        let token: String = self.chars[start..self.position].iter().collect();
+        serde_json::from_str(&token).or_else(|_| {
+            self.error_at(start, "Expected a JSON string, number, true, false or null")
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn payload() -> Value {
+        serde_json::from_str(
+            r#"{
+                "instance_id": "chat",
+                "seq": 3,
+                "message": {"channel": "general", "pinned": false, "reply_to": null}
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn filters_check_equality_and_existence() {
+        let matches = |source: &str| PayloadFilter::parse(source).unwrap().matches(&payload());
+        assert!(matches("payload"));
+        assert!(matches("payload.seq"));
+        assert!(matches("payload.message.reply_to"));
+        assert!(!matches("payload.message.author"));
+        assert!(matches(r#"payload.message.channel == "general""#));
+        assert!(!matches(r#"payload.message.channel == "random""#));
+        assert!(matches("payload.seq == 3.0"));
+        assert!(!matches(r#"payload.seq == "3""#));
+        assert!(matches("payload.message.pinned == false"));
+        assert!(matches("payload.message.reply_to == null"));
+        assert!(!matches("payload.message.author == null"));
+        assert!(matches(
+            r#" payload.instance_id=="chat" &&payload.message.channel == "general" "#
+        ));
+        assert!(!matches(
+            r#"payload.instance_id == "chat" && payload.message.author"#
+        ));
+        assert!(matches(r#"payload.instance_id == "chat""#));
+    }
+
+    #[test]
+    fn malformed_filters_tell_where_they_went_wrong() {
+        let error = |source: &str| PayloadFilter::parse(source).unwrap_err();
+        assert_eq!(
+            error(""),
+            FilterError {
+                position: 0,
+                message: "Expected a path starting with `payload`".to_string(),
+            }
+        );
+        assert_eq!(error("message.channel").position, 0);
+        assert_eq!(error("payload.").position, 8);
+        assert_eq!(error("payload.seq ==").position, 14);
+        assert_eq!(error("payload.seq == three").position, 15);
+        assert_eq!(
+            error(r#"payload.seq == 3 & payload.instance_id"#).to_string(),
+            "Expected `&&` at position 17"
+        );
+        assert_eq!(error(r#"payload.seq == 3 && "#).position, 20);
+        assert_eq!(
+            error(r#"payload.instance_id == "chat"#).to_string(),
+            "Unterminated string at position 23"
+        );
+        assert_eq!(error("payload.seq = 3").position, 12);
+    }
+}
@@ -0,0 +1,281 @@
+//! TLS for the websocket and HTTP interfaces, see `TlsConfiguration`.
+//! The certificate and key of an interface get loaded and checked when the container loads
+//! its configuration, so that a missing, invalid or expired file stops it from starting
+//! instead of failing the first connection. They can be loaded anew while the interface
+//! runs, see `Container::reload_tls_certificates()`: connections that are open keep the
+//! certificate they were opened with, new ones get the new one.
+
+use config::TlsConfiguration;
+use openssl::{
+    asn1::Asn1Time,
+    pkey::PKey,
+    ssl::{SslAcceptor, SslMethod},
+    x509::X509,
+};
+use std::{
+    fs,
+    sync::{Arc, RwLock},
+};
+
+/// A certificate and the private key that belongs to it, checked to be valid now
+pub struct TlsIdentity {
+    /// The certificate file as it was read, with the chain leading to the certificate if any
+    pub certificate_pem: Vec<u8>,
+    pub private_key_pem: Vec<u8>,
+    acceptor: Arc<SslAcceptor>,
+}
+
+impl TlsIdentity {
+    /// What accepts the connections of a websocket interface with this identity
+    pub fn acceptor(&self) -> Arc<SslAcceptor> {
+        self.acceptor.clone()
+    }
+}
+
+/// The identity an interface serves wss or https with. Clones share it, so that the
+/// interface sees when it gets reloaded.
+#[derive(Clone)]
+pub struct TlsCertificate {
+    interface_id: String,
+    current: Arc<RwLock<Arc<TlsIdentity>>>,
+}
+
+impl TlsCertificate {
+    /// Loads the files of the configuration, fails with an error naming the interface and
+    /// the file if they are missing, no valid PEM, don't belong together or are expired
+    pub fn load(interface_id: &str, config: &TlsConfiguration) -> Result<Self, String> {
+        Ok(TlsCertificate {
+            interface_id: interface_id.to_string(),
+            current: Arc::new(RwLock::new(Arc::new(load_identity(interface_id, config)?))),
+        })
+    }
+
+    /// Loads the files of the configuration anew, e.g. after they got renewed.
+    /// If they can't be loaded, the certificate stays the one loaded before.
+    pub fn reload(&self, config: &TlsConfiguration) -> Result<(), String> {
+        let identity = load_identity(&self.interface_id, config)?;
+        *self.current.write().unwrap() = Arc::new(identity);
+        Ok(())
+    }
+
+    pub fn current(&self) -> Arc<TlsIdentity> {
+        self.current.read().unwrap().clone()
+    }
+}
+
+fn load_identity(interface_id: &str, config: &TlsConfiguration) -> Result<TlsIdentity, String> {
+    let error = |message: String| format!("Interface \"{}\": {}", interface_id, message);
+    let certificate_pem = fs::read(&config.cert_file).map_err(|e| {
+        error(format!(
+            "could not read TLS certificate file \"{}\": {}",
+            config.cert_file, e
+        ))
+    })?;
+    let private_key_pem = fs::read(&config.key_file).map_err(|e| {
+        error(format!(
+            "could not read TLS key file \"{}\": {}",
+            config.key_file, e
+        ))
+    })?;
+
+    let invalid_certificate = |e: String| {
+        error(format!(
+            "TLS certificate file \"{}\" holds no valid PEM certificate: {}",
+            config.cert_file, e
+        ))
+    };
+    let mut chain = X509::stack_from_pem(&certificate_pem)
+        .map_err(|e| invalid_certificate(e.to_string()))?
+        .into_iter();
+    let certificate = chain
+        .next()
+        .ok_or_else(|| invalid_certificate(String::from("the file is empty")))?;
+    let now = Asn1Time::days_from_now(0).map_err(|e| error(e.to_string()))?;
+    if certificate.not_after() < now {
+        return Err(error(format!(
+            "TLS certificate \"{}\" expired on {}",
+            config.cert_file,
+            certificate.not_after()
+        )));
+    }
+    if certificate.not_before() > now {
+        return Err(error(format!(
+            "TLS certificate \"{}\" is not valid before {}",
+            config.cert_file,
+            certificate.not_before()
+        )));
+    }
+
+    let private_key = PKey::private_key_from_pem(&private_key_pem).map_err(|e| {
+        error(format!(
+            "TLS key file \"{}\" holds no valid PEM private key: {}",
+            config.key_file, e
+        ))
+    })?;
+    let key_matches = certificate
+        .public_key()
+        .map(|public_key| public_key.public_eq(&private_key))
+        .unwrap_or(false);
+    if !key_matches {
+        return Err(error(format!(
+            "TLS key \"{}\" does not belong to the certificate \"{}\"",
+            config.key_file, config.cert_file
+        )));
+    }
+
+    let mut builder =
+        SslAcceptor::mozilla_intermediate(SslMethod::tls()).map_err(|e| error(e.to_string()))?;
+    builder
+        .set_private_key(&private_key)
+        .and_then(|_| builder.set_certificate(&certificate))
+        .map_err(|e| error(e.to_string()))?;
+    for chain_certificate in chain {
+        builder
+            .add_extra_chain_cert(chain_certificate)
+            .map_err(|e| error(e.to_string()))?;
+    }
+    Ok(TlsIdentity {
+        certificate_pem,
+        private_key_pem,
+        acceptor: Arc::new(builder.build()),
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use openssl::{
+        asn1::Asn1Integer,
+        bn::BigNum,
+        hash::MessageDigest,
+        rsa::Rsa,
+        x509::{extension::SubjectAlternativeName, X509NameBuilder},
+    };
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    /// Writes a self-signed certificate for localhost and 127.0.0.1 with its key into the
+    /// directory, as `<name>.pem` and `<name>.key`. An expired one was valid in 1970.
+    /// Clients trust it with the certificate file as their CA.
+    pub fn write_self_signed_certificate(
+        dir: &Path,
+        name: &str,
+        expired: bool,
+    ) -> TlsConfiguration {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut subject = X509NameBuilder::new().unwrap();
+        subject.append_entry_by_text("CN", "localhost").unwrap();
+        let subject = subject.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        let serial = Asn1Integer::from_bn(&BigNum::from_u32(1).unwrap()).unwrap();
+        builder.set_serial_number(&serial).unwrap();
+        builder.set_subject_name(&subject).unwrap();
+        builder.set_issuer_name(&subject).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        let (not_before, not_after) = if expired {
+            (Asn1Time::from_unix(0), Asn1Time::from_unix(3600))
+        } else {
+            (Asn1Time::days_from_now(0), Asn1Time::days_from_now(1))
+        };
+        builder.set_not_before(&not_before.unwrap()).unwrap();
+        builder.set_not_after(&not_after.unwrap()).unwrap();
+        let alternative_names = SubjectAlternativeName::new()
+            .dns("localhost")
+            .ip("127.0.0.1")
+            .build(&builder.x509v3_context(None, None))
+            .unwrap();
+        builder.append_extension(alternative_names).unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+
+        let config = TlsConfiguration {
+            cert_file: dir
+                .join(format!("{}.pem", name))
+                .to_str()
+                .unwrap()
+                .to_string(),
+            key_file: dir
+                .join(format!("{}.key", name))
+                .to_str()
+                .unwrap()
+                .to_string(),
+        };
+        fs::write(&config.cert_file, builder.build().to_pem().unwrap()).unwrap();
+        fs::write(&config.key_file, key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+        config
+    }
+
+    #[test]
+    fn certificates_get_checked_when_they_load() {
+        let dir = tempdir().unwrap();
+        let valid = write_self_signed_certificate(dir.path(), "valid", false);
+        let other = write_self_signed_certificate(dir.path(), "other", false);
+        let expired = write_self_signed_certificate(dir.path(), "expired", true);
+        let load = |config: &TlsConfiguration| TlsCertificate::load("web", config).err();
+
+        assert_eq!(load(&valid), None);
+
+        let missing = TlsConfiguration {
+            cert_file: dir.path().join("missing.pem").to_str().unwrap().to_string(),
+            ..valid.clone()
+        };
+        let error = load(&missing).unwrap();
+        assert!(
+            error.starts_with("Interface \"web\": could not read TLS certificate file"),
+            "error = {}",
+            error
+        );
+
+        let error = load(&expired).unwrap();
+        assert!(
+            error.starts_with("Interface \"web\": TLS certificate") && error.contains("expired on"),
+            "error = {}",
+            error
+        );
+
+        let mismatched = TlsConfiguration {
+            key_file: other.key_file.clone(),
+            ..valid.clone()
+        };
+        let error = load(&mismatched).unwrap();
+        assert!(
+            error.contains("does not belong to the certificate"),
+            "error = {}",
+            error
+        );
+
+        let not_pem = TlsConfiguration {
+            cert_file: valid.key_file.clone(),
+            ..valid.clone()
+        };
+        let error = load(&not_pem).unwrap();
+        assert!(
+            error.contains("holds no valid PEM certificate"),
+            "error = {}",
+            error
+        );
+    }
+
+    #[test]
+    fn failed_reloads_keep_the_certificate() {
+        let dir = tempdir().unwrap();
+        let first = write_self_signed_certificate(dir.path(), "first", false);
+        let second = write_self_signed_certificate(dir.path(), "second", false);
+        let expired = write_self_signed_certificate(dir.path(), "expired", true);
+        let certificate = TlsCertificate::load("web", &first).unwrap();
+        let first_pem = fs::read(&first.cert_file).unwrap();
+        assert_eq!(certificate.current().certificate_pem, first_pem);
+
+        assert!(certificate.reload(&expired).is_err());
+        assert_eq!(certificate.current().certificate_pem, first_pem);
+
+        // clones see the reload
+        let interface_copy = certificate.clone();
+        certificate.reload(&second).unwrap();
+        assert_eq!(
+            interface_copy.current().certificate_pem,
+            fs::read(&second.cert_file).unwrap()
+        );
+    }
+}
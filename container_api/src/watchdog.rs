@@ -0,0 +1,190 @@
+//! The watchdog of a container looks after the action loops of its instances.
+//! Every action loop sends a heartbeat every second, see `Heartbeat`. When a loop panics,
+//! or goes without a heartbeat for HEARTBEAT_TIMEOUT, the watchdog takes the instance for
+//! crashed: its status turns Errored, interfaces fail calls to it right away, and calls that
+//! were waiting on it give up instead of waiting forever. The restart policy of the instance, see `RestartPolicy`, decides
+//! whether and when the watchdog rebuilds it from its storage.
+
+use config::RestartPolicy;
+use holochain::{CrashProbe, Holochain};
+use holochain_core::instance::{CrashReport, Heartbeat};
+use std::{
+    sync::{
+        mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender, TryRecvError},
+        Arc, RwLock, TryLockError, Weak,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often the watchdog looks at the heartbeats of the instances
+pub const WATCHDOG_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long an action loop can go without a heartbeat before the watchdog takes it for hung
+pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An instance for the watchdog to look after
+pub struct WatchedInstance {
+    id: String,
+    // instances that got removed from the container stop being watched
+    holochain: Weak<RwLock<Holochain>>,
+    crash_probe: CrashProbe,
+    heartbeats: Receiver<Heartbeat>,
+    restart_policy: RestartPolicy,
+}
+
+impl WatchedInstance {
+    /// Takes over the heartbeats of the given instance, see `Holochain::watch()`
+    pub fn new(
+        id: &str,
+        holochain: &Arc<RwLock<Holochain>>,
+        restart_policy: RestartPolicy,
+    ) -> Self {
+        let hc = holochain.read().unwrap();
+        WatchedInstance {
+            id: id.to_string(),
+            holochain: Arc::downgrade(holochain),
+            crash_probe: hc.crash_probe(),
+            heartbeats: hc.watch(),
+            restart_policy,
+        }
+    }
+}
+
+struct Watch {
+    instance: WatchedInstance,
+    last_heartbeat: Instant,
+    restarts: u32,
+    // when to rebuild the crashed instance, None while it runs or once it stays down
+    restart_at: Option<Instant>,
+}
+
+impl Watch {
+    /// Takes in the heartbeats that came and restarts the instance if it is due,
+    /// returns false once the instance is gone
+    fn check(&mut self) -> bool {
+        loop {
+            match self.instance.heartbeats.try_recv() {
+                Ok(Heartbeat::Alive) => self.last_heartbeat = Instant::now(),
+                Ok(Heartbeat::Crashed(crash)) => self.crashed(&crash),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+        let holochain = match self.instance.holochain.upgrade() {
+            Some(holochain) => holochain,
+            None => return false,
+        };
+        if self.instance.crash_probe.crash_report().is_none()
+            && self.last_heartbeat.elapsed() >= HEARTBEAT_TIMEOUT
+        {
+            let crash = CrashReport::new(
+                format!(
+                    "No heartbeat for {} seconds",
+                    self.last_heartbeat.elapsed().as_secs()
+                ),
+                None,
+            );
+            self.instance.crash_probe.report_crash(crash.clone());
+            self.crashed(&crash);
+        }
+        let restart_due = self
+            .restart_at
+            .map(|restart_at| Instant::now() >= restart_at)
+            .unwrap_or(false);
+        if restart_due {
+            self.restart(&holochain);
+        }
+        true
+    }
+
+    fn crashed(&mut self, crash: &CrashReport) {
+        self.log(format!(
+            "Instance \"{}\" crashed: {}",
+            self.instance.id, crash.message
+        ));
+        self.schedule_restart();
+    }
+
+    fn schedule_restart(&mut self) {
+        self.restart_at = self
+            .instance
+            .restart_policy
+            .backoff(self.restarts)
+            .map(|backoff| Instant::now() + backoff);
+        if self.restart_at.is_none() {
+            self.log(format!(
+                "Instance \"{}\" stays down after {} restarts",
+                self.instance.id, self.restarts
+            ));
+        }
+    }
+
+    fn restart(&mut self, holochain: &Arc<RwLock<Holochain>>) {
+        let mut hc = match holochain.try_write() {
+            Ok(hc) => hc,
+            // calls that waited on the crashed instance still hold it until they see the
+            // crash, see `call_and_wait_for_result_unless_crashed()`, try again later
+            Err(TryLockError::WouldBlock) => return,
+            Err(TryLockError::Poisoned(_)) => {
+                self.log(format!(
+                    "Instance \"{}\" can't be restarted, its lock got poisoned",
+                    self.instance.id
+                ));
+                self.restart_at = None;
+                return;
+            }
+        };
+        self.restarts += 1;
+        match hc.rebuild() {
+            Ok(()) => {
+                self.log(format!(
+                    "Restarted instance \"{}\" after it crashed, {} restarts so far",
+                    self.instance.id, self.restarts
+                ));
+                self.restart_at = None;
+                self.last_heartbeat = Instant::now();
+            }
+            Err(error) => {
+                self.log(format!(
+                    "Could not restart instance \"{}\": {}",
+                    self.instance.id, error
+                ));
+                self.schedule_restart();
+            }
+        }
+    }
+
+    // through the logger of the instance, like everything else about it
+    fn log(&self, message: String) {
+        self.instance.crash_probe.log(message);
+    }
+}
+
+/// Watches the given instances on a thread of its own, until the returned sender
+/// gets dropped
+pub fn spawn_watchdog(instances: Vec<WatchedInstance>) -> SyncSender<()> {
+    let (stop_sender, stop_receiver) = sync_channel::<()>(1);
+    thread::spawn(move || {
+        let mut watches: Vec<Watch> = instances
+            .into_iter()
+            .map(|instance| Watch {
+                instance,
+                last_heartbeat: Instant::now(),
+                restarts: 0,
+                restart_at: None,
+            })
+            .collect();
+        while let Err(RecvTimeoutError::Timeout) = stop_receiver.recv_timeout(WATCHDOG_INTERVAL) {
+            let mut i = 0;
+            while i != watches.len() {
+                if watches[i].check() {
+                    i += 1;
+                } else {
+                    watches.remove(i);
+                }
+            }
+        }
+    });
+    stop_sender
+}
@@ -11,7 +11,11 @@ use holochain_core_types::{
     cas::content::Address, error::HolochainError,
     error::RibosomeReturnCode, error::ZomeApiInternalResult, json::JsonString, json::RawString,
 };
-use holochain_wasm_utils::{memory_allocation::*, memory_serialization::*};
+use holochain_wasm_utils::{
+    api_serialization::{CallContext, ZomeApiGlobals},
+    memory_allocation::*,
+    memory_serialization::*,
+};
 use std::convert::TryInto;
 use holochain_core_types::entry::Entry;
 use holochain_core_types::entry::entry_type::AppEntryType;
@@ -136,6 +140,55 @@ fn hdk_commit(
     }
 }
 
+//-------------------------------------------------------------------------------------------------
+// HC INIT GLOBALS Function Call
+//-------------------------------------------------------------------------------------------------
+
+extern "C" {
+    fn hc_init_globals(encoded_allocation_of_input: i32) -> i32;
+}
+
+/// Call HC API INIT GLOBALS function
+/// return where the current zome call came from
+fn hdk_call_context() -> Result<CallContext, String> {
+    let encoded_allocation_of_result: i32;
+    unsafe {
+        encoded_allocation_of_result = hc_init_globals(0);
+    }
+    let result: ZomeApiInternalResult = load_json(encoded_allocation_of_result as u32)?;
+    let globals: ZomeApiGlobals = match JsonString::from(result.value).try_into() {
+        Ok(globals) => globals,
+        Err(hc_err) => return Err(hc_err.into()),
+    };
+    Ok(globals.call_context)
+}
+
+//-------------------------------------------------------------------------------------------------
+// HC YIELD Function Call
+//-------------------------------------------------------------------------------------------------
+
+extern "C" {
+    fn hc_yield(encoded_allocation_of_input: i32) -> i32;
+}
+
+/// Call HC API YIELD function with a partial result of the running call
+fn hdk_yield(mem_stack: &mut SinglePageStack, partial_result: JsonString) -> Result<(), String> {
+    let allocation_of_input = store_as_json(mem_stack, partial_result)?;
+    let encoded_allocation_of_result: i32;
+    unsafe {
+        encoded_allocation_of_result = hc_yield(allocation_of_input.encode() as i32);
+    }
+    let result: ZomeApiInternalResult = load_json(encoded_allocation_of_result as u32)?;
+    mem_stack
+        .deallocate(allocation_of_input)
+        .expect("deallocate failed");
+    if result.ok {
+        Ok(())
+    } else {
+        Err(result.error)
+    }
+}
+
 //-------------------------------------------------------------------------------------------------
 // HC COMMIT Function Call - Fail
 //-------------------------------------------------------------------------------------------------
@@ -237,3 +290,38 @@ pub extern "C" fn round_trip_test(encoded_allocation_of_input: usize) -> i32 {
     let output = test_inner(input);
     return store_as_json_into_encoded_allocation(&mut mem_stack, JsonString::from(output));
 }
+
+/// Function called by Holochain Instance
+/// encoded_allocation_of_input : encoded memory offset and length of the memory allocation
+/// holding input arguments
+/// returns encoded allocation used to store output
+#[no_mangle]
+pub extern "C" fn call_context_test(encoded_allocation_of_input: usize) -> i32 {
+    let mut mem_stack =
+        SinglePageStack::from_encoded_allocation(encoded_allocation_of_input as u32).unwrap();
+    let result = hdk_call_context();
+    store_as_json_into_encoded_allocation(&mut mem_stack, result)
+}
+
+#[derive(Deserialize, Default)]
+struct StreamInput {
+    count: u32,
+}
+
+/// Function called by Holochain Instance
+/// encoded_allocation_of_input : encoded memory offset and length of the memory allocation
+/// holding input arguments
+/// yields `count` partial results, then returns how many it yielded
+#[no_mangle]
+pub extern "C" fn stream_test(encoded_allocation_of_input: usize) -> i32 {
+    let mut mem_stack =
+        SinglePageStack::from_encoded_allocation(encoded_allocation_of_input as u32).unwrap();
+    let input: StreamInput = load_json(encoded_allocation_of_input as u32).unwrap();
+    for index in 0..input.count {
+        let partial_result = JsonString::from(format!("{{\"index\":{}}}", index));
+        if let Err(error) = hdk_yield(&mut mem_stack, partial_result) {
+            return store_as_json_into_encoded_allocation(&mut mem_stack, Err::<u32, String>(error));
+        }
+    }
+    store_as_json_into_encoded_allocation(&mut mem_stack, Ok::<u32, String>(input.count))
+}
@@ -1,29 +1,103 @@
 use crate::{
     agent::state::AgentState,
     context::Context,
-    network::{direct_message::DirectMessage, state::NetworkState},
+    dht::{dht_store::RejectionReason, pending_validation::PendingValidation},
+    network::{
+        direct_message::DirectMessage,
+        state::{NetworkState, RequestId},
+        traffic::RateLimit,
+    },
     nucleus::{
         state::{NucleusState, ValidationResult},
         ExecuteZomeFnResponse, ZomeFnCall,
     },
 };
 use holochain_core_types::{
-    cas::content::Address,
+    cas::content::{Address, AddressableContent},
     chain_header::ChainHeader,
     dna::Dna,
     entry::{Entry, EntryWithMeta},
     error::HolochainError,
     json::JsonString,
-    link::Link,
+    link::LinkOperation,
     validation::ValidationPackage,
 };
-use holochain_net_connection::protocol_wrapper::{DhtData, GetDhtData};
+use holochain_net_connection::protocol_wrapper::{
+    DhtData, GetDhtData, HoldAckData, PeerData, RateLimitedData, UnsupportedMessageData,
+};
+use holochain_wasm_utils::api_serialization::CommitOptions;
+use rand;
 use snowflake;
 use std::{
+    fmt,
     hash::{Hash, Hasher},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
 };
 
+lazy_static! {
+    /// Random UUID (v4) of this launch, which all action ids of the launch share
+    static ref LAUNCH_ID: String = {
+        let mut bytes: [u8; 16] = rand::random();
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+        format!(
+            "{}-{}-{}-{}-{}",
+            hex[0..4].concat(),
+            hex[4..6].concat(),
+            hex[6..8].concat(),
+            hex[8..10].concat(),
+            hex[10..16].concat()
+        )
+    };
+    static ref NEXT_SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+}
+
+/// Identifies an action, and the zome function call it belongs to, beyond the process that
+/// dispatched it: the UUID of the launch the instance was running in, and a sequence number
+/// that only increases within that launch.
+/// Unlike snowflakes, these ids survive being persisted or recorded and read back in another
+/// launch, so action logs and anything keyed by them stay valid.
+/// Ids of the same launch are ordered by when they were assigned.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ActionId {
+    launch_id: String,
+    sequence: u64,
+}
+
+impl ActionId {
+    /// The next id of the current launch
+    pub fn new() -> Self {
+        ActionId {
+            launch_id: LAUNCH_ID.clone(),
+            sequence: NEXT_SEQUENCE.fetch_add(1, Ordering::SeqCst) as u64,
+        }
+    }
+
+    pub fn launch_id(&self) -> &str {
+        &self.launch_id
+    }
+
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+}
+
+impl Default for ActionId {
+    fn default() -> Self {
+        ActionId::new()
+    }
+}
+
+impl fmt::Display for ActionId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}#{}", self.launch_id, self.sequence)
+    }
+}
+
 /// Wrapper for actions that provides a unique ID
 /// The unique ID is needed for state tracking to ensure that we can differentiate between two
 /// Action dispatches containing the same value when doing "time travel debug".
@@ -33,27 +107,33 @@ use std::{
 #[derive(Clone, Debug)]
 pub struct ActionWrapper {
     action: Action,
-    id: snowflake::ProcessUniqueId,
+    id: ActionId,
 }
 
 impl ActionWrapper {
     /// constructor from &Action
-    /// internal snowflake ID is automatically set
+    /// the next ActionId of this launch is automatically assigned, so actions get ids
+    /// in the order they get wrapped for dispatch
     pub fn new(a: Action) -> Self {
         ActionWrapper {
             action: a,
             // auto generate id
-            id: snowflake::ProcessUniqueId::new(),
+            id: ActionId::new(),
         }
     }
 
+    /// Wrapper with the id the action had when it got recorded, for replaying it
+    pub(crate) fn with_id(a: Action, id: ActionId) -> Self {
+        ActionWrapper { action: a, id }
+    }
+
     /// read only access to action
     pub fn action(&self) -> &Action {
         &self.action
     }
 
     /// read only access to id
-    pub fn id(&self) -> &snowflake::ProcessUniqueId {
+    pub fn id(&self) -> &ActionId {
         &self.id
     }
 }
@@ -67,9 +147,6 @@ impl PartialEq for ActionWrapper {
 impl Eq for ActionWrapper {}
 
 impl Hash for ActionWrapper {
-    /// @TODO dangerous when persisted!
-    /// snowflake only guarantees uniqueness per process
-    /// @see https://github.com/holochain/holochain-rust/issues/203
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.id.hash(state);
     }
@@ -83,18 +160,46 @@ pub enum Action {
     // ----------------
     /// Writes an entry to the source chain.
     /// Does not validate, assumes entry is valid.
-    Commit((Entry, Option<Address>)),
+    /// The options can make it fail instead of committing on top of an unexpected chain head.
+    Commit((Entry, Option<Address>, CommitOptions)),
+
+    /// Saves the agent state and syncs the storage, so that the commits reduced before
+    /// survive a crash of the instance.
+    Flush,
 
     // -------------
     // DHT actions:
     // -------------
-    /// Adds an entry to the local DHT shard.
+    /// Adds an entry to the local DHT shard, held since the given time in seconds since the
+    /// Unix epoch. Entries of types with a TTL expire counting from then.
     /// Does not validate, assumes entry is valid.
-    Hold(Entry),
+    Hold((Entry, u64)),
 
     /// Adds a link to the local DHT shard's meta/EAV storage
     /// Does not validate, assumes link is valid.
-    AddLink(Link),
+    AddLink(LinkOperation),
+
+    /// Removes a link from the local DHT shard's meta/EAV storage, in both directions.
+    /// Removes the add of the link with the given header, or else all adds made before it.
+    /// Removes that arrive before the add they match keep it from showing up once it does,
+    /// see `DhtStore::is_link_live()`.
+    RemoveLink((LinkOperation, Option<Address>)),
+
+    /// Marks the entry with the given address as REJECTED in the local DHT shard's
+    /// meta/EAV storage, after it failed validation on this holding node or the entries
+    /// it depends on could not be fetched in time, and records the reason.
+    RejectEntry((Address, RejectionReason)),
+
+    /// Parks the validation of an entry we were asked to hold in the local DHT shard,
+    /// until the entries it depends on are available.
+    QueueValidation(PendingValidation),
+
+    /// Takes a pending validation out of the queue, after it was done or timed out.
+    DequeueValidation(PendingValidation),
+
+    /// Stores the header of an entry held in the local DHT shard, so that the entry's
+    /// validation package can be requested again later.
+    HoldHeader(ChainHeader),
 
     // ----------------
     // Network actions:
@@ -102,20 +207,54 @@ pub enum Action {
     /// Create a network proxy instance from the given [NetworkSettings](struct.NetworkSettings.html)
     InitNetwork(NetworkSettings),
 
+    /// The connection to the network backend broke down with the given error.
+    /// Triggered from the network handler.
+    /// Makes the network module go into reconnecting and schedule a ReconnectNetwork.
+    NetworkLost(String),
+
+    /// Tries to connect to the network backend again, with the settings of InitNetwork.
+    /// On success, re-announces DNA and agent and re-sends every request that was still
+    /// waiting for an answer. Otherwise schedules the next try with a longer delay.
+    ReconnectNetwork,
+
+    /// The network told us about another agent of the DNA.
+    /// Triggered from the network handler.
+    HandlePeerConnected(PeerData),
+
     /// Makes the network PUT the given entry to the DHT.
     /// Distinguishes between different entry types and does
     /// the right thing respectively.
     /// (only publish for AppEntryType, publish and publish_meta for links etc)
+    /// Starts tracking which nodes acknowledge holding the entry.
+    /// Skipped if we published the entry with the same meta before.
     Publish(Address),
 
-    /// GetEntry by address
-    GetEntry(Address),
+    /// Like Publish, but also sends entries we published with the same meta before,
+    /// to repair the DHT.
+    Republish(Address),
+
+    /// Lets the network module tell the publisher of an entry that we hold it.
+    /// Triggered from the network handler once an entry we were asked to store
+    /// passed validation and is held.
+    AcknowledgeHold(HoldAckData),
+
+    /// A node acknowledged holding an entry we published.
+    /// Triggered from the network handler.
+    HandleHoldAck(HoldAckData),
+
+    /// A node told us that an entry we published failed its validation.
+    /// Records the rejection, unless that node was not asked to hold the entry.
+    /// Triggered from the network handler, with the entry address, the node and the reason.
+    HandleValidationFailureNotice((Address, Address, String)),
+
+    /// GetEntry by address, the result gets stored under the given request ID
+    GetEntry((Address, RequestId)),
     ///
     UpdateEntry((Address, Address)),
     ///
     RemoveEntry((Address, Address)),
-    ///
-    GetEntryTimeout(Address),
+    /// Fails the GET request with the given ID, if it is still waiting for an answer
+    GetEntryTimeout(RequestId),
 
     /// Lets the network module respond to a GET request.
     /// Triggered from the corresponding workflow after retrieving the
@@ -137,16 +276,36 @@ pub enum Action {
     /// Makes the network module forget about the direct message
     /// connection with the given ID.
     /// Triggered when we got an answer to our initial DM.
-    ResolveDirectConnection(String),
+    ResolveDirectConnection(RequestId),
+
+    /// Makes the network module forget about the request with the given ID.
+    /// Triggered by the process that made the request, once it got the result.
+    ClearPendingRequest(RequestId),
 
     /// Makes the network module DM the source of the given entry
-    /// and prepare for receiveing an answer
-    GetValidationPackage(ChainHeader),
+    /// and prepare for receiveing an answer, which gets stored under the given request ID
+    GetValidationPackage((ChainHeader, RequestId)),
 
     /// Updates the state to hold the response that we got for
     /// our previous request for a validation package.
     /// Triggered from the network handler when we got the response.
-    HandleGetValidationPackage((Address, Option<ValidationPackage>)),
+    HandleGetValidationPackage((RequestId, Option<ValidationPackage>)),
+
+    /// Lets the network module tell another node that we could not handle
+    /// its message because of an unknown message type or protocol version.
+    /// Triggered from the network handler.
+    RespondUnsupportedMessage(UnsupportedMessageData),
+
+    /// Another node could not handle a message we sent.
+    /// Resolves the request that message belonged to as failed, instead of
+    /// waiting for a response that will never come.
+    /// Triggered from the network handler.
+    HandleUnsupportedMessage(UnsupportedMessageData),
+
+    /// Another node dropped a request we sent because we exceeded its rate limit.
+    /// Fails the request that message belonged to with HolochainError::RateLimited.
+    /// Triggered from the network handler.
+    HandleRateLimited(RateLimitedData),
 
     // ----------------
     // Nucleus actions:
@@ -168,6 +327,15 @@ pub enum Action {
     /// Execute a zome function call called by another zome function
     Call(ZomeFnCall),
 
+    /// Whoever made a zome function call picked up its result,
+    /// which can be pruned from the state from now on
+    AcknowledgeZomeCallResult(ZomeFnCall),
+
+    /// Drop the zome function results that got delivered a while ago or were not picked
+    /// up within the zome call timeout of the context.
+    /// Triggered periodically from the action loop.
+    PruneZomeCalls,
+
     /// A validation result is returned from a local callback execution
     /// Key is an unique id of the calling context
     /// and the hash of the entry that was validated
@@ -181,6 +349,176 @@ pub enum Action {
             Result<ValidationPackage, HolochainError>,
         ),
     ),
+
+    // ----------------
+    // Test actions:
+    // ----------------
+    /// Makes the action loop panic with the given message, for testing what happens
+    /// to instances whose action loop crashes, see `instance::CrashReport`
+    #[cfg(any(test, feature = "test_fixtures"))]
+    Crash(String),
+    /// Makes the action loop block for the given number of milliseconds, for testing what
+    /// happens to instances whose action loop hangs
+    #[cfg(any(test, feature = "test_fixtures"))]
+    Hang(u64),
+}
+
+impl Action {
+    /// name of the action variant, without any of its data
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Commit(_) => "Commit",
+            Action::Flush => "Flush",
+            Action::Hold(_) => "Hold",
+            Action::AddLink(_) => "AddLink",
+            Action::RemoveLink(_) => "RemoveLink",
+            Action::RejectEntry(_) => "RejectEntry",
+            Action::QueueValidation(_) => "QueueValidation",
+            Action::DequeueValidation(_) => "DequeueValidation",
+            Action::HoldHeader(_) => "HoldHeader",
+            Action::InitNetwork(_) => "InitNetwork",
+            Action::NetworkLost(_) => "NetworkLost",
+            Action::ReconnectNetwork => "ReconnectNetwork",
+            Action::HandlePeerConnected(_) => "HandlePeerConnected",
+            Action::Publish(_) => "Publish",
+            Action::Republish(_) => "Republish",
+            Action::AcknowledgeHold(_) => "AcknowledgeHold",
+            Action::HandleHoldAck(_) => "HandleHoldAck",
+            Action::HandleValidationFailureNotice(_) => "HandleValidationFailureNotice",
+            Action::GetEntry(_) => "GetEntry",
+            Action::UpdateEntry(_) => "UpdateEntry",
+            Action::RemoveEntry(_) => "RemoveEntry",
+            Action::GetEntryTimeout(_) => "GetEntryTimeout",
+            Action::RespondGet(_) => "RespondGet",
+            Action::HandleGetResult(_) => "HandleGetResult",
+            Action::SendDirectMessage(_) => "SendDirectMessage",
+            Action::ResolveDirectConnection(_) => "ResolveDirectConnection",
+            Action::ClearPendingRequest(_) => "ClearPendingRequest",
+            Action::GetValidationPackage(_) => "GetValidationPackage",
+            Action::HandleGetValidationPackage(_) => "HandleGetValidationPackage",
+            Action::RespondUnsupportedMessage(_) => "RespondUnsupportedMessage",
+            Action::HandleUnsupportedMessage(_) => "HandleUnsupportedMessage",
+            Action::HandleRateLimited(_) => "HandleRateLimited",
+            Action::InitApplication(_) => "InitApplication",
+            Action::ReturnInitializationResult(_) => "ReturnInitializationResult",
+            Action::ExecuteZomeFunction(_) => "ExecuteZomeFunction",
+            Action::ReturnZomeFunctionResult(_) => "ReturnZomeFunctionResult",
+            Action::Call(_) => "Call",
+            Action::AcknowledgeZomeCallResult(_) => "AcknowledgeZomeCallResult",
+            Action::PruneZomeCalls => "PruneZomeCalls",
+            Action::ReturnValidationResult(_) => "ReturnValidationResult",
+            Action::ReturnValidationPackage(_) => "ReturnValidationPackage",
+            #[cfg(any(test, feature = "test_fixtures"))]
+            Action::Crash(_) => "Crash",
+            #[cfg(any(test, feature = "test_fixtures"))]
+            Action::Hang(_) => "Hang",
+        }
+    }
+
+    /// id of the zome function call this action belongs to, if any
+    pub fn call_id(&self) -> Option<ActionId> {
+        match self {
+            Action::ExecuteZomeFunction(call)
+            | Action::Call(call)
+            | Action::AcknowledgeZomeCallResult(call) => Some(call.id()),
+            Action::ReturnZomeFunctionResult(response) => Some(response.call().id()),
+            _ => None,
+        }
+    }
+
+    /// all addresses this action refers to, in the order they are displayed
+    pub fn addresses(&self) -> Vec<Address> {
+        match self {
+            Action::Commit((entry, _, _)) | Action::Hold((entry, _)) => vec![entry.address()],
+            Action::AddLink(operation) | Action::RemoveLink((operation, _)) => vec![
+                operation.link().base().clone(),
+                operation.link().target().clone(),
+            ],
+            Action::AcknowledgeHold(hold_ack_data) | Action::HandleHoldAck(hold_ack_data) => {
+                vec![Address::from(hold_ack_data.address.clone())]
+            }
+            Action::HandleValidationFailureNotice((address, holder, _)) => {
+                vec![address.clone(), holder.clone()]
+            }
+            Action::Publish(address)
+            | Action::Republish(address)
+            | Action::RejectEntry((address, _))
+            | Action::GetEntry((address, _))
+            | Action::ReturnValidationResult(((_, address), _)) => vec![address.clone()],
+            Action::UpdateEntry((old_address, new_address))
+            | Action::RemoveEntry((old_address, new_address)) => {
+                vec![old_address.clone(), new_address.clone()]
+            }
+            Action::GetValidationPackage((header, _)) | Action::HoldHeader(header) => {
+                vec![header.entry_address().clone()]
+            }
+            Action::QueueValidation(pending_validation)
+            | Action::DequeueValidation(pending_validation) => {
+                vec![pending_validation.entry_address()]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Human readable form of an action: its name and the most relevant parts of its data,
+/// with addresses shortened.
+/// A precision truncates the content of contained entries, e.g. `format!("{:.40}", action)`.
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let precision = f.precision();
+        let display_entry = |entry: &Entry| match precision {
+            Some(max_len) => format!("{:.*}", max_len, entry),
+            None => format!("{}", entry),
+        };
+        let display_call =
+            |call: &ZomeFnCall| format!("{}/{}/{}", call.zome_name, call.cap_name, call.fn_name);
+        let details = match self {
+            Action::Commit((entry, _, _)) | Action::Hold((entry, _)) => {
+                format!("{} {}", entry.address().short(), display_entry(entry))
+            }
+            Action::AddLink(operation) | Action::RemoveLink((operation, _)) => format!(
+                "{} -> {} ({})",
+                operation.link().base().short(),
+                operation.link().target().short(),
+                operation.link().tag()
+            ),
+            Action::UpdateEntry((old_address, new_address))
+            | Action::RemoveEntry((old_address, new_address)) => {
+                format!("{} -> {}", old_address.short(), new_address.short())
+            }
+            Action::GetValidationPackage((header, _)) | Action::HoldHeader(header) => {
+                format!("{}", header)
+            }
+            Action::RespondUnsupportedMessage(data) | Action::HandleUnsupportedMessage(data) => {
+                format!("{} v{}", data.method, data.seen_version)
+            }
+            Action::InitApplication(dna) => dna.name.clone(),
+            Action::ExecuteZomeFunction(call)
+            | Action::Call(call)
+            | Action::AcknowledgeZomeCallResult(call) => display_call(call),
+            Action::ReturnZomeFunctionResult(response) => format!(
+                "{} {}",
+                display_call(&response.call()),
+                if response.result().is_ok() {
+                    "ok"
+                } else {
+                    "error"
+                }
+            ),
+            _ => self
+                .addresses()
+                .iter()
+                .map(|address| address.short())
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        if details.is_empty() {
+            write!(f, "{}", self.name())
+        } else {
+            write!(f, "{} {}", self.name(), details)
+        }
+    }
 }
 
 /// function signature for action handler functions
@@ -203,7 +541,7 @@ pub struct DirectMessageData {
 
     /// A unique message ID that is used to identify the response and attribute
     /// it to the right context
-    pub msg_id: String,
+    pub msg_id: RequestId,
 
     /// Should be true if we are responding to a previous message with this message.
     /// msg_id should then be the same as the in the message that we received.
@@ -224,21 +562,33 @@ pub struct NetworkSettings {
     /// The network module needs to know who we are.
     /// This is this agent's address.
     pub agent_id: String,
+
+    /// How much each peer may send us and we send to each peer.
+    pub rate_limit: RateLimit,
 }
 
 #[cfg(test)]
 pub mod tests {
 
     use crate::{
-        action::{Action, ActionWrapper},
+        action::{Action, ActionId, ActionWrapper},
+        network::state::RequestId,
         nucleus::tests::test_call_response,
     };
-    use holochain_core_types::entry::{expected_entry_address, test_entry};
+    use holochain_core_types::{
+        cas::content::AddressableContent,
+        entry::{expected_entry_address, test_entry},
+        link::{Link, LinkOperation},
+        time::Iso8601,
+    };
+    use holochain_wasm_utils::api_serialization::CommitOptions;
+    use serde_json;
+    use std::thread;
     use test_utils::calculate_hash;
 
     /// dummy action
     pub fn test_action() -> Action {
-        Action::GetEntry(expected_entry_address())
+        Action::GetEntry((expected_entry_address(), RequestId::from("test_request")))
     }
 
     /// dummy action wrapper with test_action()
@@ -248,12 +598,19 @@ pub mod tests {
 
     /// dummy action wrapper with commit of test_entry()
     pub fn test_action_wrapper_commit() -> ActionWrapper {
-        ActionWrapper::new(Action::Commit((test_entry(), None)))
+        ActionWrapper::new(Action::Commit((
+            test_entry(),
+            None,
+            CommitOptions::default(),
+        )))
     }
 
     /// dummy action for a get of test_hash()
     pub fn test_action_wrapper_get() -> ActionWrapper {
-        ActionWrapper::new(Action::GetEntry(expected_entry_address()))
+        ActionWrapper::new(Action::GetEntry((
+            expected_entry_address(),
+            RequestId::new(),
+        )))
     }
 
     pub fn test_action_wrapper_rzfr() -> ActionWrapper {
@@ -276,7 +633,7 @@ pub mod tests {
         let aw1 = test_action_wrapper();
         let aw2 = test_action_wrapper();
 
-        // snowflake enforces uniqueness
+        // ActionId enforces uniqueness
         assert_eq!(aw1, aw1);
         assert_ne!(aw1, aw2);
     }
@@ -300,6 +657,47 @@ pub mod tests {
         let aw2 = test_action_wrapper();
 
         assert_ne!(aw1.id(), aw2.id());
+        assert_eq!(aw1.id().launch_id(), aw2.id().launch_id());
+    }
+
+    #[test]
+    /// tests that action ids strictly increase within a launch, also across threads
+    fn action_ids_strictly_increase() {
+        let ids: Vec<ActionId> = (0..100).map(|_| ActionId::new()).collect();
+        for pair in ids.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert!(pair[0].sequence() < pair[1].sequence());
+        }
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| thread::spawn(|| (0..100).map(|_| ActionId::new()).collect::<Vec<_>>()))
+            .collect();
+        let mut all_ids: Vec<ActionId> = threads
+            .into_iter()
+            .flat_map(|thread| thread.join().unwrap())
+            .collect();
+        for ids in all_ids.chunks(100) {
+            assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+        all_ids.sort();
+        all_ids.dedup();
+        assert_eq!(all_ids.len(), 400);
+        assert!(ids.last().unwrap() < &all_ids[0]);
+    }
+
+    #[test]
+    /// tests that action ids are the same after a serialization round trip, and so is
+    /// the hash of a wrapper with them
+    fn action_ids_are_stable_across_serialization() {
+        let wrapper = test_action_wrapper_commit();
+        let serialized = serde_json::to_string(wrapper.id()).unwrap();
+        let id: ActionId = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(&id, wrapper.id());
+        assert_eq!(id.to_string(), wrapper.id().to_string());
+
+        let restored = ActionWrapper::with_id(wrapper.action().clone(), id);
+        assert_eq!(restored, wrapper);
+        assert_eq!(calculate_hash(&restored), calculate_hash(&wrapper));
     }
 
     #[test]
@@ -311,4 +709,62 @@ pub mod tests {
         assert_ne!(calculate_hash(&aw1), calculate_hash(&aw2));
     }
 
+    #[test]
+    /// tests the human readable form of actions
+    fn action_display() {
+        let short_address = expected_entry_address().short();
+        assert_eq!(
+            format!("{}", test_action()),
+            format!("GetEntry {}", short_address),
+        );
+        assert_eq!(
+            format!("{}", test_action_wrapper_commit().action()),
+            format!(
+                "Commit {} testEntryType \"test entry value\"",
+                short_address
+            ),
+        );
+        assert_eq!(
+            format!("{:.5}", test_action_wrapper_commit().action()),
+            format!("Commit {} testEntryType \"test...", short_address),
+        );
+        let link = Link::new(&expected_entry_address(), &expected_entry_address(), "tag");
+        assert_eq!(
+            format!(
+                "{}",
+                Action::AddLink(LinkOperation::new(
+                    &link,
+                    Iso8601::from("2019-01-01T00:00:00Z")
+                ))
+            ),
+            format!("AddLink {} -> {} (tag)", short_address, short_address),
+        );
+        assert_eq!(
+            format!("{}", Action::ReturnInitializationResult(None)),
+            "ReturnInitializationResult".to_string(),
+        );
+    }
+
+    #[test]
+    /// tests the addresses and call ids that actions refer to
+    fn action_addresses_and_call_id() {
+        assert_eq!(test_action().addresses(), vec![expected_entry_address()]);
+        assert_eq!(
+            test_action_wrapper_commit().action().addresses(),
+            vec![test_entry().address()]
+        );
+        assert!(test_action().call_id().is_none());
+
+        let rzfr = test_action_wrapper_rzfr();
+        let call_id = rzfr.action().call_id();
+        assert!(call_id.is_some());
+        assert_eq!(
+            call_id,
+            Some(
+                unwrap_to!(rzfr.action() => Action::ReturnZomeFunctionResult)
+                    .call()
+                    .id()
+            )
+        );
+    }
 }
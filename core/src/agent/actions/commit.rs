@@ -10,6 +10,7 @@ use futures::{
     task::{LocalWaker, Poll},
 };
 use holochain_core_types::{cas::content::Address, entry::Entry, error::HolochainError};
+use holochain_wasm_utils::api_serialization::{CommitEntryResult, CommitOptions};
 use std::{
     pin::{Pin, Unpin},
     sync::Arc,
@@ -20,13 +21,29 @@ use std::{
 /// This is the high-level commit function that wraps the whole commit process and is what should
 /// be called from zome api functions and other contexts that don't care about implementation details.
 ///
-/// Returns a future that resolves to an ActionResponse.
+/// Returns a future that resolves to the addresses of the committed entry and its chain header.
 pub async fn commit_entry(
     entry: Entry,
     maybe_crud_link: Option<Address>,
     context: &Arc<Context>,
-) -> Result<Address, HolochainError> {
-    let action_wrapper = ActionWrapper::new(Action::Commit((entry, maybe_crud_link)));
+) -> Result<CommitEntryResult, HolochainError> {
+    await!(commit_entry_with_options(
+        entry,
+        maybe_crud_link,
+        CommitOptions::default(),
+        context
+    ))
+}
+
+/// Like commit_entry(), but the options can make the commit fail with ChainHeadMoved
+/// instead of going on top of a chain head other than the expected one.
+pub async fn commit_entry_with_options(
+    entry: Entry,
+    maybe_crud_link: Option<Address>,
+    options: CommitOptions,
+    context: &Arc<Context>,
+) -> Result<CommitEntryResult, HolochainError> {
+    let action_wrapper = ActionWrapper::new(Action::Commit((entry, maybe_crud_link, options)));
     dispatch_action(&context.action_channel, action_wrapper.clone());
     await!(CommitFuture {
         context: context.clone(),
@@ -44,7 +61,7 @@ pub struct CommitFuture {
 impl Unpin for CommitFuture {}
 
 impl Future for CommitFuture {
-    type Output = Result<Address, HolochainError>;
+    type Output = Result<CommitEntryResult, HolochainError>;
 
     fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Self::Output> {
         //
@@ -61,7 +78,7 @@ impl Future for CommitFuture {
             .get(&self.action)
         {
             Some(ActionResponse::Commit(result)) => match result {
-                Ok(address) => Poll::Ready(Ok(address.clone())),
+                Ok(commit_result) => Poll::Ready(Ok(commit_result.clone())),
                 Err(error) => Poll::Ready(Err(error.clone())),
             },
             Some(_) => unreachable!(),
@@ -69,3 +86,134 @@ impl Future for CommitFuture {
         }
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::nucleus::actions::tests::instance;
+    use futures::executor::block_on;
+    use holochain_core_types::{
+        cas::content::AddressableContent, chain_header::ChainHeader,
+        entry::entry_type::test_app_entry_type, json::JsonString,
+    };
+    use std::{sync::Barrier, thread};
+
+    fn test_entry_with_value(value: String) -> Entry {
+        Entry::App(test_app_entry_type(), JsonString::from(value))
+    }
+
+    fn top_chain_header_address(context: &Arc<Context>) -> Option<Address> {
+        context
+            .state()
+            .unwrap()
+            .agent()
+            .top_chain_header()
+            .map(|chain_header| chain_header.address())
+    }
+
+    #[test]
+    /// test that concurrent commits end up on a straight chain, each on top of another
+    fn concurrent_commits_keep_the_chain_straight() {
+        let (_instance, context) = instance();
+        let length_before = context.state().unwrap().agent().chain_info().length;
+
+        let barrier = Arc::new(Barrier::new(2));
+        let threads: Vec<_> = (0..2)
+            .map(|i| {
+                let context = context.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    (0..10)
+                        .map(|j| {
+                            let entry = test_entry_with_value(format!("{} {}", i, j));
+                            block_on(commit_entry(entry, None, &context))
+                                .expect("Could not commit entry")
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let commit_results: Vec<CommitEntryResult> = threads
+            .into_iter()
+            .flat_map(|thread| thread.join().unwrap())
+            .collect();
+
+        let agent_state = context.state().unwrap().agent();
+        let chain_headers: Vec<ChainHeader> = agent_state
+            .chain()
+            .iter(&agent_state.top_chain_header())
+            .collect();
+        assert_eq!(chain_headers.len(), length_before + 20);
+        for pair in chain_headers.windows(2) {
+            assert_eq!(pair[0].link(), Some(pair[1].address()));
+        }
+        for commit_result in commit_results {
+            assert!(chain_headers
+                .iter()
+                .any(|chain_header| chain_header.address() == commit_result.header_address));
+        }
+    }
+
+    #[test]
+    /// test that of two commits requiring the same head only one goes through,
+    /// and the other one fails telling where the head moved to
+    fn commits_requiring_a_moved_head_fail() {
+        let (_instance, context) = instance();
+        let head = top_chain_header_address(&context).expect("genesis should be committed");
+
+        let barrier = Arc::new(Barrier::new(2));
+        let threads: Vec<_> = (0..2)
+            .map(|i| {
+                let context = context.clone();
+                let barrier = barrier.clone();
+                let options = CommitOptions {
+                    require_head: Some(head.clone()),
+                    ..Default::default()
+                };
+                thread::spawn(move || {
+                    barrier.wait();
+                    let entry = test_entry_with_value(format!("{}", i));
+                    block_on(commit_entry_with_options(entry, None, options, &context))
+                })
+            })
+            .collect();
+        let (committed, failed): (Vec<_>, Vec<_>) = threads
+            .into_iter()
+            .map(|thread| thread.join().unwrap())
+            .partition(|commit_result| commit_result.is_ok());
+
+        assert_eq!(committed.len(), 1);
+        assert_eq!(failed.len(), 1);
+        let committed = committed[0].clone().unwrap();
+        assert_eq!(
+            failed[0],
+            Err(HolochainError::ChainHeadMoved {
+                expected: head,
+                actual: Some(committed.header_address.clone()),
+            })
+        );
+        assert_eq!(
+            top_chain_header_address(&context),
+            Some(committed.header_address.clone())
+        );
+
+        // retrying on top of the head it moved to goes through
+        let options = CommitOptions {
+            require_head: Some(committed.header_address.clone()),
+            ..Default::default()
+        };
+        let entry = test_entry_with_value(String::from("retried"));
+        let retried = block_on(commit_entry_with_options(entry, None, options, &context))
+            .expect("Could not commit entry");
+        let chain_header = context
+            .state()
+            .unwrap()
+            .agent()
+            .chain()
+            .get_header(&retried.header_address)
+            .unwrap()
+            .expect("retried commit should be in the chain");
+        assert_eq!(chain_header.link(), Some(committed.header_address));
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{
+    action::{Action, ActionWrapper},
+    agent::state::ActionResponse,
+    context::Context,
+    instance::dispatch_action,
+};
+use futures::{
+    future::Future,
+    task::{LocalWaker, Poll},
+};
+use holochain_core_types::error::HolochainError;
+use std::{
+    pin::{Pin, Unpin},
+    sync::Arc,
+};
+
+/// Flush Action Creator
+/// Makes the commits that are pending durable, see `AgentState::pending_commits()`.
+/// Actions get reduced in the order they got dispatched, so the Flush only gets reduced once
+/// the commits dispatched before it are on the chain. Its reduction saves the agent state
+/// and syncs the storage, e.g. the file storage syncs the files it wrote to the disk.
+///
+/// Returns a future that resolves to the number of commits that got flushed.
+pub async fn flush(context: &Arc<Context>) -> Result<usize, HolochainError> {
+    let action_wrapper = ActionWrapper::new(Action::Flush);
+    dispatch_action(&context.action_channel, action_wrapper.clone());
+    await!(FlushFuture {
+        context: context.clone(),
+        action: action_wrapper,
+    })
+}
+
+/// FlushFuture resolves to the result of the flush
+pub struct FlushFuture {
+    context: Arc<Context>,
+    action: ActionWrapper,
+}
+
+impl Unpin for FlushFuture {}
+
+impl Future for FlushFuture {
+    type Output = Result<usize, HolochainError>;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Self::Output> {
+        //
+        // TODO: connect the waker to state updates for performance reasons
+        // See: https://github.com/holochain/holochain-rust/issues/314
+        //
+        lw.wake();
+        match self
+            .context
+            .state()
+            .unwrap()
+            .agent()
+            .actions()
+            .get(&self.action)
+        {
+            Some(ActionResponse::Flush(result)) => Poll::Ready(result.clone()),
+            Some(_) => unreachable!(),
+            None => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{agent::actions::commit::commit_entry, nucleus::actions::tests::instance};
+    use futures::executor::block_on;
+    use holochain_core_types::{
+        entry::{entry_type::test_app_entry_type, Entry},
+        json::JsonString,
+    };
+
+    fn pending_commits(context: &Arc<Context>) -> usize {
+        context.state().unwrap().agent().pending_commits()
+    }
+
+    #[test]
+    /// test that a flush waits for the commits dispatched before it and counts them
+    fn flush_makes_pending_commits_durable() {
+        let (_instance, context) = instance();
+        let pending_after_genesis = pending_commits(&context);
+
+        for i in 0..10 {
+            let entry = Entry::App(test_app_entry_type(), JsonString::from(format!("{}", i)));
+            block_on(commit_entry(entry, None, &context)).expect("Could not commit entry");
+        }
+        assert_eq!(pending_commits(&context), pending_after_genesis + 10);
+
+        assert_eq!(block_on(flush(&context)), Ok(pending_after_genesis + 10));
+        assert_eq!(pending_commits(&context), 0);
+        assert_eq!(block_on(flush(&context)), Ok(0));
+    }
+}
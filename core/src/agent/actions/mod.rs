@@ -1,2 +1,3 @@
 pub mod commit;
+pub mod flush;
 pub mod update_entry;
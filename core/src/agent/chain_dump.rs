@@ -0,0 +1,332 @@
+//! Source chains as a portable JSON document, to back them up or move them into another
+//! storage, see `hc chain export` and `hc chain import`.
+//! A ChainDump lists the headers of a chain from the first to the top one, each with the
+//! entry it points to. Imports verify the linkage of the headers before they write anything.
+
+use crate::agent::{
+    chain_store::ChainStore,
+    state::{AgentStateSnapshot, AGENT_SNAPSHOT_ADDRESS},
+};
+use holochain_core_types::{
+    cas::{
+        content::{Address, AddressableContent},
+        storage::ContentAddressableStorage,
+    },
+    chain_header::ChainHeader,
+    dna::{zome::entry_types::Sharing, Dna},
+    entry::{entry_type::EntryType, Entry},
+    error::HolochainError,
+    json::JsonString,
+};
+use std::{
+    convert::TryFrom,
+    sync::{Arc, RwLock},
+};
+
+/// The version of the format chains get exported in, imports refuse dumps of other versions
+pub const CHAIN_DUMP_VERSION: u32 = 1;
+
+/// A source chain with the entries its headers point to
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, DefaultJson)]
+pub struct ChainDump {
+    pub version: u32,
+    /// The headers from the first to the top of the chain
+    pub items: Vec<ChainDumpItem>,
+}
+
+/// A header of the chain and its entry
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChainDumpItem {
+    pub header: ChainHeader,
+    /// None if the entry got pruned or redacted, the header still tells its address
+    #[serde(default)]
+    pub entry: Option<Entry>,
+    /// Whether the DNA of the chain declares the type of the entry private
+    #[serde(default)]
+    pub private: bool,
+    /// Whether the content of the entry got left out, see ChainDump::redact_private()
+    #[serde(default)]
+    pub redacted: bool,
+}
+
+impl ChainDump {
+    /// How many entries of private types the dump holds the content of
+    pub fn private_entry_count(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|item| item.private && item.entry.is_some())
+            .count()
+    }
+
+    /// Leaves out the content of the entries of private types, their headers keep telling
+    /// their addresses. Returns how many entries got redacted.
+    pub fn redact_private(&mut self) -> usize {
+        let mut redacted = 0;
+        for item in self.items.iter_mut() {
+            if item.private && item.entry.is_some() {
+                item.entry = None;
+                item.redacted = true;
+                redacted += 1;
+            }
+        }
+        redacted
+    }
+
+    /// Checks that every header links to the one before it and follows it in sequence,
+    /// has a signature for each of its sources and points to the address of its entry.
+    /// Signatures are placeholders as long as agents have no signing keys, so only their
+    /// number gets checked.
+    /// Fails with HolochainError::ValidationFailed on the first header that doesn't.
+    pub fn verify(&self) -> Result<(), HolochainError> {
+        if self.version != CHAIN_DUMP_VERSION {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Chain dumps of version {} are not supported, only version {}",
+                self.version, CHAIN_DUMP_VERSION
+            )));
+        }
+        let mut previous: Option<&ChainHeader> = None;
+        for (position, item) in self.items.iter().enumerate() {
+            let header = &item.header;
+            let invalid = |reason: &str| {
+                Err(HolochainError::ValidationFailed(format!(
+                    "Header {} at position {} of the chain dump {}",
+                    header.address(),
+                    position,
+                    reason
+                )))
+            };
+            if header.link() != previous.map(|previous| previous.address()) {
+                return invalid("does not link to the header before it");
+            }
+            if !header.follows_in_sequence(previous) {
+                return invalid("has its sequence number out of order");
+            }
+            if header.entry_signatures().len() != header.sources().len() {
+                return invalid("does not have a signature for each of its sources");
+            }
+            if let Some(ref entry) = item.entry {
+                if entry.address() != *header.entry_address()
+                    || entry.entry_type() != *header.entry_type()
+                {
+                    return invalid("does not match its entry");
+                }
+            }
+            previous = Some(header);
+        }
+        Ok(())
+    }
+}
+
+/// Whether the storage holds a source chain, i.e. a snapshot of the agent state
+pub fn holds_chain(
+    storage: &Arc<RwLock<dyn ContentAddressableStorage>>,
+) -> Result<bool, HolochainError> {
+    storage
+        .read()?
+        .contains(&Address::from(AGENT_SNAPSHOT_ADDRESS))
+}
+
+/// Reads the source chain the storage holds, up to the top header of its agent state
+/// snapshot. Entries of the chain that are no longer in the storage because they got
+/// pruned are left out.
+pub fn export_chain(
+    storage: &Arc<RwLock<dyn ContentAddressableStorage>>,
+) -> Result<ChainDump, HolochainError> {
+    let snapshot = match storage
+        .read()?
+        .fetch(&Address::from(AGENT_SNAPSHOT_ADDRESS))?
+    {
+        Some(content) => AgentStateSnapshot::try_from_content(&content)?,
+        None => {
+            return Err(HolochainError::ErrorGeneric(
+                "The storage holds no source chain".to_string(),
+            ))
+        }
+    };
+    let chain = ChainStore::new(storage.clone());
+    let mut headers: Vec<ChainHeader> = chain
+        .iter(&Some(snapshot.top_chain_header().clone()))
+        .collect();
+    headers.reverse();
+
+    let mut items = Vec::new();
+    let mut dna: Option<Dna> = None;
+    for header in headers {
+        let entry = match storage.read()?.fetch(header.entry_address())? {
+            Some(content) => Some(Entry::try_from_content(&content)?),
+            None => None,
+        };
+        if let Some(Entry::Dna(ref chain_dna)) = entry {
+            dna = Some(chain_dna.clone());
+        }
+        items.push(ChainDumpItem {
+            private: is_private(&dna, header.entry_type()),
+            header,
+            entry,
+            redacted: false,
+        });
+    }
+    Ok(ChainDump {
+        version: CHAIN_DUMP_VERSION,
+        items,
+    })
+}
+
+/// Verifies the dump, see ChainDump::verify(), and writes its headers and entries into the
+/// storage, with an agent state snapshot that makes its top header the top of the chain.
+/// Entries the dump leaves out stay missing, like pruned ones.
+/// An agent state the storage holds already gets replaced, check holds_chain() first.
+pub fn import_chain(
+    dump: &ChainDump,
+    storage: &Arc<RwLock<dyn ContentAddressableStorage>>,
+) -> Result<(), HolochainError> {
+    dump.verify()?;
+    let top_chain_header = match dump.items.last() {
+        Some(item) => item.header.clone(),
+        None => {
+            return Err(HolochainError::ErrorGeneric(
+                "The chain dump holds no headers".to_string(),
+            ))
+        }
+    };
+    let mut storage = storage.write()?;
+    for item in dump.items.iter() {
+        if let Some(ref entry) = item.entry {
+            storage.add(entry)?;
+        }
+        storage.add(&item.header)?;
+    }
+    storage.add(&AgentStateSnapshot::new(top_chain_header))
+}
+
+fn is_private(dna: &Option<Dna>, entry_type: &EntryType) -> bool {
+    match (dna, entry_type) {
+        (Some(dna), EntryType::App(app_entry_type)) => dna
+            .get_entry_type_def(&String::from(app_entry_type.to_owned()))
+            .map(|entry_type_def| entry_type_def.sharing == Sharing::Private)
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate tempfile;
+    use self::tempfile::{tempdir, TempDir};
+    use super::*;
+    use crate::{
+        agent::actions::{commit::commit_entry, flush::flush},
+        nucleus::actions::tests::{instance_by_name, test_dna},
+    };
+    use futures::executor::block_on;
+    use holochain_cas_implementations::cas::file::FilesystemStorage;
+    use holochain_core_types::{
+        dna::zome::entry_types::EntryTypeDef,
+        entry::entry_type::{test_app_entry_type, AppEntryType},
+    };
+
+    /// The storage of an instance whose chain has a public and a private entry on top of
+    /// its genesis entries, with the agent state saved
+    fn storage_with_chain() -> Arc<RwLock<dyn ContentAddressableStorage>> {
+        let mut dna = test_dna();
+        let mut private_type = EntryTypeDef::new();
+        private_type.sharing = Sharing::Private;
+        dna.zomes
+            .get_mut("test_zome")
+            .unwrap()
+            .entry_types
+            .insert("private_note".into(), private_type);
+        let (_instance, context) = instance_by_name("jane", dna);
+
+        for entry in vec![
+            Entry::App(test_app_entry_type(), "public".into()),
+            Entry::App(AppEntryType::from("private_note"), "secret".into()),
+        ] {
+            block_on(commit_entry(entry, None, &context)).expect("Could not commit entry");
+        }
+        block_on(flush(&context)).expect("Could not flush");
+        context.file_storage.clone()
+    }
+
+    fn empty_storage() -> (TempDir, Arc<RwLock<dyn ContentAddressableStorage>>) {
+        let dir = tempdir().unwrap();
+        let storage = FilesystemStorage::new(dir.path().to_str().unwrap()).unwrap();
+        (dir, Arc::new(RwLock::new(storage)))
+    }
+
+    #[test]
+    fn chains_survive_an_export_and_import() {
+        let dump = export_chain(&storage_with_chain()).expect("Could not export");
+        assert_eq!(dump.version, CHAIN_DUMP_VERSION);
+        assert_eq!(dump.items[0].header.entry_type(), &EntryType::Dna);
+        assert_eq!(
+            dump.items.last().unwrap().entry,
+            Some(Entry::App(
+                AppEntryType::from("private_note"),
+                "secret".into()
+            ))
+        );
+        assert_eq!(dump.private_entry_count(), 1);
+        assert_eq!(dump.verify(), Ok(()));
+
+        // the dump goes through JSON
+        let dump = ChainDump::try_from(JsonString::from(dump.clone())).unwrap();
+
+        let (_dir, storage) = empty_storage();
+        assert_eq!(holds_chain(&storage), Ok(false));
+        import_chain(&dump, &storage).expect("Could not import");
+        assert_eq!(holds_chain(&storage), Ok(true));
+        assert_eq!(export_chain(&storage), Ok(dump));
+    }
+
+    #[test]
+    fn redacted_dumps_keep_the_headers_of_private_entries() {
+        let mut dump = export_chain(&storage_with_chain()).unwrap();
+        let headers: Vec<ChainHeader> = dump.items.iter().map(|item| item.header.clone()).collect();
+
+        assert_eq!(dump.redact_private(), 1);
+        assert_eq!(dump.private_entry_count(), 0);
+        let private_item = dump.items.last().unwrap();
+        assert!(private_item.redacted);
+        assert_eq!(private_item.entry, None);
+        assert_eq!(
+            dump.items
+                .iter()
+                .map(|item| item.header.clone())
+                .collect::<Vec<_>>(),
+            headers
+        );
+        assert_eq!(dump.verify(), Ok(()));
+    }
+
+    #[test]
+    fn imports_fail_at_the_header_of_a_tampered_entry() {
+        let mut dump = export_chain(&storage_with_chain()).unwrap();
+        let position = dump.items.len() - 2;
+        dump.items[position].entry = Some(Entry::App(test_app_entry_type(), "tampered".into()));
+        let tampered_header = dump.items[position].header.address();
+
+        let (_dir, storage) = empty_storage();
+        match import_chain(&dump, &storage) {
+            Err(HolochainError::ValidationFailed(message)) => {
+                assert!(message.contains(&String::from(tampered_header)));
+                assert!(message.contains(&format!("at position {} ", position)));
+                assert!(message.ends_with("does not match its entry"));
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+        // nothing got written
+        assert_eq!(holds_chain(&storage), Ok(false));
+
+        // headers that got taken out break the linkage of the one after them
+        let mut dump = export_chain(&storage_with_chain()).unwrap();
+        dump.items.remove(1);
+        match dump.verify() {
+            Err(HolochainError::ValidationFailed(message)) => {
+                assert!(message.contains("at position 1 "));
+                assert!(message.ends_with("does not link to the header before it"));
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
@@ -5,13 +5,23 @@ use holochain_core_types::{
     },
     chain_header::ChainHeader,
     entry::entry_type::EntryType,
+    error::HolochainError,
 };
-use std::sync::{Arc, RwLock};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+};
+
+/// Addresses of the headers of a chain by their sequence number, see ChainHeader::sequence().
+/// Legacy headers, which have none, are left out.
+pub type SequenceIndex = Arc<RwLock<BTreeMap<u64, Address>>>;
 
 #[derive(Debug, Clone)]
 pub struct ChainStore {
     // Storages holding local shard data
     content_storage: Arc<RwLock<dyn ContentAddressableStorage>>,
+    /// maintained with every commit, and rebuilt when the chain gets loaded
+    sequence_index: SequenceIndex,
 }
 
 impl PartialEq for ChainStore {
@@ -26,15 +36,63 @@ impl PartialEq for ChainStore {
 
 impl ChainStore {
     pub fn new(content_storage: Arc<RwLock<dyn ContentAddressableStorage>>) -> Self {
-        ChainStore { content_storage }
+        ChainStore {
+            content_storage,
+            sequence_index: Arc::new(RwLock::new(BTreeMap::new())),
+        }
     }
 
     pub fn content_storage(&self) -> Arc<RwLock<dyn ContentAddressableStorage>> {
         self.content_storage.clone()
     }
 
+    /// Looks up a chain header by its address.
+    /// Returns None if nothing is stored at the given address.
+    pub fn get_header(&self, address: &Address) -> Result<Option<ChainHeader>, HolochainError> {
+        match (*self.content_storage.read().unwrap()).fetch(address)? {
+            Some(content) => Ok(Some(ChainHeader::try_from_content(&content)?)),
+            None => Ok(None),
+        }
+    }
+
     pub fn iter(&self, start_chain_header: &Option<ChainHeader>) -> ChainStoreIterator {
         ChainStoreIterator::new(self.content_storage.clone(), start_chain_header.clone())
+            .with_sequence_index(self.sequence_index.clone())
+    }
+
+    /// Adds a header to the sequence index, unless it is a legacy header without sequence
+    pub fn index_header(&self, chain_header: &ChainHeader) {
+        if chain_header.sequence() > 0 {
+            self.sequence_index
+                .write()
+                .unwrap()
+                .insert(chain_header.sequence(), chain_header.address());
+        }
+    }
+
+    /// Address of the header with the given sequence number, if it is indexed
+    pub fn indexed_header_address(&self, sequence: u64) -> Option<Address> {
+        self.sequence_index.read().unwrap().get(&sequence).cloned()
+    }
+
+    /// Checks that the sequence numbers of the chain count up by one from its first header,
+    /// see ChainHeader::follows_in_sequence().
+    /// Fails with HolochainError::ValidationFailed on the first header that doesn't.
+    pub fn verify_sequence(
+        &self,
+        start_chain_header: &Option<ChainHeader>,
+    ) -> Result<(), HolochainError> {
+        let mut headers = self.iter(start_chain_header).peekable();
+        while let Some(chain_header) = headers.next() {
+            if !chain_header.follows_in_sequence(headers.peek()) {
+                return Err(HolochainError::ValidationFailed(format!(
+                    "Chain header {} has sequence number {} out of order",
+                    chain_header.address(),
+                    chain_header.sequence()
+                )));
+            }
+        }
+        Ok(())
     }
 
     pub fn iter_type(
@@ -67,11 +125,39 @@ impl ChainStore {
             base_iter.collect()
         }
     }
+
+    /// Returns up to `limit` headers of the given type, all of them for a limit of 0.
+    /// The walk starts at `start_chain_header`, or right after the header `after`
+    /// if one is given, so headers added later on don't shift it.
+    /// Also tells whether there are more headers of the type left after the returned ones.
+    pub fn query_page(
+        &self,
+        start_chain_header: &Option<ChainHeader>,
+        entry_type: &EntryType,
+        limit: u32,
+        after: Option<&ChainHeader>,
+    ) -> (Vec<ChainHeader>, bool) {
+        let iter: Box<dyn Iterator<Item = ChainHeader>> = match after {
+            Some(header) => Box::new(
+                ChainStoreTypeIterator::new(self.content_storage.clone(), Some(header.clone()))
+                    .skip(1),
+            ),
+            None => Box::new(self.iter_type(start_chain_header, entry_type)),
+        };
+        if limit == 0 {
+            return (iter.collect(), false);
+        }
+        let mut headers: Vec<ChainHeader> = iter.take(limit as usize + 1).collect();
+        let more = headers.len() > limit as usize;
+        headers.truncate(limit as usize);
+        (headers, more)
+    }
 }
 
 pub struct ChainStoreIterator {
     content_storage: Arc<RwLock<dyn ContentAddressableStorage>>,
     current: Option<ChainHeader>,
+    sequence_index: Option<SequenceIndex>,
 }
 
 impl ChainStoreIterator {
@@ -82,8 +168,67 @@ impl ChainStoreIterator {
         ChainStoreIterator {
             content_storage,
             current,
+            sequence_index: None,
         }
     }
+
+    /// Lets skip_to() look headers up in the given index instead of walking the chain
+    pub fn with_sequence_index(mut self, sequence_index: SequenceIndex) -> Self {
+        self.sequence_index = Some(sequence_index);
+        self
+    }
+
+    /// Moves on to the header with the given sequence number, so that it comes next.
+    /// Indexed headers get looked up directly, otherwise the iterator walks down the chain
+    /// and stops at the first header with a lower sequence number, or at a legacy header.
+    /// Headers that the iterator already passed are out of reach, it ends instead.
+    ///
+    /// May panic if there is an underlying error in the table
+    pub fn skip_to(mut self, sequence: u64) -> Self {
+        let current_sequence = match self.current {
+            Some(ref chain_header) => chain_header.sequence(),
+            None => return self,
+        };
+        if current_sequence == sequence || current_sequence == 0 {
+            return self;
+        }
+        if current_sequence < sequence {
+            self.current = None;
+            return self;
+        }
+        let indexed_address = self
+            .sequence_index
+            .as_ref()
+            .and_then(|index| index.read().unwrap().get(&sequence).cloned());
+        if let Some(address) = indexed_address {
+            self.current = fetch_chain_header(&self.content_storage, &address);
+            return self;
+        }
+        while self
+            .current
+            .as_ref()
+            .map(|chain_header| chain_header.sequence() > sequence)
+            .unwrap_or(false)
+        {
+            self.next();
+        }
+        self
+    }
+}
+
+fn fetch_chain_header(
+    content_storage: &Arc<RwLock<dyn ContentAddressableStorage>>,
+    address: &Address,
+) -> Option<ChainHeader> {
+    content_storage
+        .read()
+        .unwrap()
+        .fetch(address)
+        .expect("failed to fetch from CAS")
+        .map(|content| {
+            ChainHeader::try_from_content(&content)
+                .expect("failed to load ChainHeader from Content")
+        })
 }
 
 impl Iterator for ChainStoreIterator {
@@ -100,15 +245,7 @@ impl Iterator for ChainStoreIterator {
             // @TODO should this panic?
             // @see https://github.com/holochain/holochain-rust/issues/146
             .and_then(|linked_chain_header_address| {
-                storage
-                    .read()
-                    .unwrap()
-                    .fetch(linked_chain_header_address)
-                    .expect("failed to fetch from CAS")
-                    .map(|content| {
-                        ChainHeader::try_from_content(&content)
-                            .expect("failed to load ChainHeader from Content")
-                    })
+                fetch_chain_header(storage, linked_chain_header_address)
             });
         previous
     }
@@ -145,13 +282,7 @@ impl Iterator for ChainStoreTypeIterator {
             // @TODO should this panic?
             // @see https://github.com/holochain/holochain-rust/issues/146
             .and_then(|linked_chain_header_address| {
-                (*storage.read().unwrap())
-                    .fetch(linked_chain_header_address)
-                    .expect("failed to fetch from CAS")
-                    .map(|content| {
-                        ChainHeader::try_from_content(&content)
-                            .expect("failed to load ChainHeader from Content")
-                    })
+                fetch_chain_header(storage, linked_chain_header_address)
             });
         previous
     }
@@ -167,6 +298,7 @@ pub mod tests {
         cas::content::AddressableContent,
         chain_header::{test_chain_header, test_sources, ChainHeader},
         entry::{test_entry, test_entry_b, test_entry_c},
+        error::HolochainError,
         signature::{test_signature_b, test_signature_c, test_signatures},
         time::test_iso_8601,
     };
@@ -179,6 +311,24 @@ pub mod tests {
         )))
     }
 
+    #[test]
+    /// show that headers can be looked up by address
+    fn get_header_test() {
+        let chain_store = test_chain_store();
+        let chain_header = test_chain_header();
+
+        assert_eq!(None, chain_store.get_header(&chain_header.address()).unwrap());
+
+        (*chain_store.content_storage.write().unwrap())
+            .add(&chain_header)
+            .expect("could not add header to cas");
+
+        assert_eq!(
+            Some(chain_header.clone()),
+            chain_store.get_header(&chain_header.address()).unwrap(),
+        );
+    }
+
     #[test]
     /// show Iterator implementation for chain store
     fn iterator_test() {
@@ -347,4 +497,153 @@ pub mod tests {
         assert_eq!(expected, found);
     }
 
+    #[test]
+    /// show query_page() implementation
+    fn query_page_test() {
+        let chain_store = test_chain_store();
+
+        let chain_header_a = test_chain_header();
+        let entry = test_entry_b();
+        let chain_header_b = ChainHeader::new(
+            &entry.entry_type(),
+            &entry.address(),
+            &test_sources(),
+            &vec![test_signature_b()],
+            &Some(chain_header_a.address()),
+            &None,
+            &None,
+            &test_iso_8601(),
+        );
+        let entry = test_entry_c();
+        let chain_header_c = ChainHeader::new(
+            &entry.entry_type(),
+            &entry.address(),
+            &test_sources(),
+            &vec![test_signature_c()],
+            &Some(chain_header_b.address()),
+            &Some(chain_header_b.address()),
+            &None,
+            &test_iso_8601(),
+        );
+
+        let storage = chain_store.content_storage.clone();
+        for chain_header in &[&chain_header_a, &chain_header_b, &chain_header_c] {
+            (*storage.write().unwrap())
+                .add(*chain_header)
+                .expect("could not add header to cas");
+        }
+        let top = Some(chain_header_c.clone());
+
+        let (found, more) = chain_store.query_page(&top, &entry.entry_type(), 1, None);
+        assert_eq!(vec![chain_header_c.clone()], found);
+        assert!(more);
+
+        let (found, more) =
+            chain_store.query_page(&top, &entry.entry_type(), 1, Some(&chain_header_c));
+        assert_eq!(vec![chain_header_b.clone()], found);
+        assert!(!more);
+
+        let (found, more) =
+            chain_store.query_page(&top, &entry.entry_type(), 2, Some(&chain_header_b));
+        assert!(found.is_empty());
+        assert!(!more);
+
+        let (found, more) = chain_store.query_page(&top, &entry.entry_type(), 0, None);
+        assert_eq!(vec![chain_header_c.clone(), chain_header_b.clone()], found);
+        assert!(!more);
+    }
+
+    /// a chain of the given number of headers with sequence numbers, returns them top first
+    fn sequenced_chain(chain_store: &ChainStore, length: u64) -> Vec<ChainHeader> {
+        let mut headers: Vec<ChainHeader> = vec![];
+        for sequence in 1..=length {
+            let entry = test_entry();
+            let chain_header = ChainHeader::new(
+                &entry.entry_type(),
+                &entry.address(),
+                &test_sources(),
+                &test_signatures(),
+                &headers.first().map(|previous| previous.address()),
+                &None,
+                &None,
+                &test_iso_8601(),
+            )
+            .with_sequence(sequence);
+            (*chain_store.content_storage.write().unwrap())
+                .add(&chain_header)
+                .expect("could not add header to cas");
+            headers.insert(0, chain_header);
+        }
+        headers
+    }
+
+    #[test]
+    /// show that skip_to() finds headers with and without the sequence index
+    fn skip_to_test() {
+        let chain_store = test_chain_store();
+        let headers = sequenced_chain(&chain_store, 5);
+        let top = Some(headers[0].clone());
+
+        let walked: Vec<u64> = chain_store
+            .iter(&top)
+            .skip_to(3)
+            .map(|chain_header| chain_header.sequence())
+            .collect();
+        assert_eq!(walked, vec![3, 2, 1]);
+        assert_eq!(chain_store.indexed_header_address(3), None);
+
+        for chain_header in headers.iter() {
+            chain_store.index_header(chain_header);
+        }
+        assert_eq!(
+            chain_store.indexed_header_address(3),
+            Some(headers[2].address())
+        );
+        let mut iter = chain_store.iter(&top).skip_to(2);
+        assert_eq!(iter.next(), Some(headers[3].clone()));
+        assert_eq!(iter.next(), Some(headers[4].clone()));
+        assert_eq!(iter.next(), None);
+
+        assert_eq!(chain_store.iter(&top).skip_to(6).next(), None);
+        assert_eq!(
+            chain_store
+                .iter(&Some(headers[2].clone()))
+                .skip_to(4)
+                .next(),
+            None
+        );
+    }
+
+    #[test]
+    /// show that verify_sequence() rejects sequence numbers that don't count up by one
+    fn verify_sequence_test() {
+        let chain_store = test_chain_store();
+        let headers = sequenced_chain(&chain_store, 3);
+        assert!(chain_store
+            .verify_sequence(&Some(headers[0].clone()))
+            .is_ok());
+
+        let legacy = test_chain_header();
+        (*chain_store.content_storage.write().unwrap())
+            .add(&legacy)
+            .expect("could not add header to cas");
+        assert!(chain_store.verify_sequence(&Some(legacy.clone())).is_ok());
+
+        let entry = test_entry_b();
+        let out_of_order = ChainHeader::new(
+            &entry.entry_type(),
+            &entry.address(),
+            &test_sources(),
+            &vec![test_signature_b()],
+            &Some(headers[0].address()),
+            &None,
+            &None,
+            &test_iso_8601(),
+        )
+        .with_sequence(5);
+        match chain_store.verify_sequence(&Some(out_of_order)) {
+            Err(HolochainError::ValidationFailed(_)) => (),
+            result => panic!("expected ValidationFailed, got {:?}", result),
+        }
+    }
 }
@@ -0,0 +1,262 @@
+use holochain_core_types::{
+    agent::{AgentId, KeyBuffer},
+    cas::content::Address,
+    error::{HcResult, HolochainError},
+    signature::Signature,
+};
+use holochain_sodium::{
+    random,
+    sealed_box::{self, PUBLICKEYBYTES, SEALBYTES, SECRETKEYBYTES, SEEDBYTES},
+    secbuf::SecBuf,
+    sign,
+};
+
+/// The key pair an agent decrypts what other agents encrypted for it with.
+/// The public key is the encryption part of the agent's identity (see `KeyBuffer::get_enc()`),
+/// so everyone can encrypt for an agent knowing only its address.
+pub struct EncryptionKeys {
+    public_key: SecBuf,
+    secret_key: SecBuf,
+}
+
+impl EncryptionKeys {
+    /// Derives the key pair from a seed of SEEDBYTES bytes
+    pub fn from_seed(seed: &mut SecBuf) -> Self {
+        let mut public_key = SecBuf::with_insecure(PUBLICKEYBYTES);
+        let mut secret_key = SecBuf::with_secure(SECRETKEYBYTES);
+        sealed_box::seed_keypair(&mut public_key, &mut secret_key, seed);
+        EncryptionKeys {
+            public_key,
+            secret_key,
+        }
+    }
+
+    /// Generates a new random key pair
+    pub fn generate() -> Self {
+        let mut seed = SecBuf::with_secure(SEEDBYTES);
+        random::buf(&mut seed);
+        Self::from_seed(&mut seed)
+    }
+
+    /// Derives the key pair from the given name, for testing like `AgentId::generate_fake()`.
+    /// Everyone knowing the name can derive the secret key as well, so these keys keep
+    /// nothing secret.
+    pub fn generate_fake(name: &str) -> Self {
+        let mut seed = SecBuf::with_insecure(SEEDBYTES);
+        {
+            let mut seed = seed.write_lock();
+            for (seed_byte, name_byte) in seed.iter_mut().zip(name.bytes()) {
+                *seed_byte = name_byte;
+            }
+        }
+        Self::from_seed(&mut seed)
+    }
+
+    /// A fake agent like `AgentId::generate_fake(nick)`, but with this public key
+    /// as the encryption part of its identity
+    pub fn fake_agent_id(&mut self, nick: &str) -> AgentId {
+        self.agent_id(nick, AgentId::generate_fake(nick).to_buffer().get_sig())
+    }
+
+    /// The agent with the given signing key and this public key as its identity
+    pub fn agent_id(&mut self, nick: &str, signing_key: &[u8; 32]) -> AgentId {
+        let mut key = [0; 64];
+        key[..32].copy_from_slice(signing_key);
+        key[32..].copy_from_slice(&self.public_key.read_lock());
+        AgentId::new(nick, &KeyBuffer::with_raw(&key))
+    }
+
+    /// Decrypts a payload that got encrypted for this key pair with `encrypt_for()`.
+    /// Fails if it was encrypted for someone else or got modified on the way.
+    pub fn decrypt(&mut self, payload: &[u8]) -> HcResult<Vec<u8>> {
+        if payload.len() < SEALBYTES {
+            return Err(HolochainError::ErrorGeneric(String::from(
+                "Could not decrypt: the payload is too short to be encrypted",
+            )));
+        }
+        let mut sealed = insecure_buf(payload);
+        let mut message = SecBuf::with_insecure(payload.len() - SEALBYTES);
+        sealed_box::open(
+            &mut message,
+            &mut sealed,
+            &mut self.public_key,
+            &mut self.secret_key,
+        )
+        .map_err(|_| {
+            HolochainError::ErrorGeneric(String::from(
+                "Could not decrypt: the payload is not encrypted for this agent or got modified",
+            ))
+        })?;
+        let message = message.read_lock();
+        Ok(message.to_vec())
+    }
+}
+
+/// The key pair an agent signs its chain headers with.
+/// The public key is the signing part of the agent's identity (see `KeyBuffer::get_sig()`),
+/// so everyone can check what an agent signed knowing only its address.
+pub struct SigningKeys {
+    public_key: SecBuf,
+    secret_key: SecBuf,
+}
+
+impl SigningKeys {
+    /// Derives the key pair from a seed of `sign::SEEDBYTES` bytes
+    pub fn from_seed(seed: &mut SecBuf) -> Self {
+        let mut public_key = SecBuf::with_insecure(sign::PUBLICKEYBYTES);
+        let mut secret_key = SecBuf::with_secure(sign::SECRETKEYBYTES);
+        sign::seed_keypair(&mut public_key, &mut secret_key, seed);
+        SigningKeys {
+            public_key,
+            secret_key,
+        }
+    }
+
+    /// Generates a new random key pair
+    pub fn generate() -> Self {
+        let mut seed = SecBuf::with_secure(sign::SEEDBYTES);
+        random::buf(&mut seed);
+        Self::from_seed(&mut seed)
+    }
+
+    /// The public key, which is the signing part of the agent's identity
+    pub fn public_key(&mut self) -> [u8; 32] {
+        let mut key = [0; 32];
+        key.copy_from_slice(&self.public_key.read_lock());
+        key
+    }
+
+    /// Signs the message, see `verify_signature()`
+    pub fn sign(&mut self, message: &[u8]) -> Signature {
+        let mut message = insecure_buf(message);
+        let mut signature = SecBuf::with_insecure(sign::BYTES);
+        sign::sign(&mut signature, &mut message, &mut self.secret_key);
+        let signature = signature.read_lock();
+        Signature::from(base64::encode(&signature[..]))
+    }
+}
+
+/// Whether the agent with the given key (see `AgentId::key`) signed the message
+/// with the given signature. Agents without signing keys sign nothing, fake agents
+/// for instance, so none of their signatures checks out.
+pub fn verify_signature(signer_key: &str, message: &[u8], signature: &Signature) -> bool {
+    let key_buffer = match KeyBuffer::with_corrected(signer_key) {
+        Ok(key_buffer) => key_buffer,
+        Err(_) => return false,
+    };
+    let signature = match base64::decode(&String::from(signature.clone())) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    sign::verify(
+        &mut insecure_buf(&signature),
+        &mut insecure_buf(message),
+        &mut insecure_buf(key_buffer.get_sig()),
+    )
+    .is_ok()
+}
+
+/// Encrypts the payload so that only the agent with the given address can decrypt it.
+/// Everyone can encrypt this way, the payload does not tell who encrypted it.
+pub fn encrypt_for(recipient: &Address, payload: &[u8]) -> HcResult<Vec<u8>> {
+    let key_buffer = KeyBuffer::with_corrected(&String::from(recipient.clone()))
+        .map_err(|error| error.with_context(format!("{} is no agent address", recipient)))?;
+    let mut public_key = insecure_buf(key_buffer.get_enc());
+    let mut message = insecure_buf(payload);
+    let mut sealed = SecBuf::with_insecure(payload.len() + SEALBYTES);
+    sealed_box::seal(&mut sealed, &mut message, &mut public_key).map_err(|_| {
+        HolochainError::ErrorGeneric(format!("{} has no usable encryption key", recipient))
+    })?;
+    let sealed = sealed.read_lock();
+    Ok(sealed.to_vec())
+}
+
+pub(crate) fn insecure_buf(bytes: &[u8]) -> SecBuf {
+    let mut buf = SecBuf::with_insecure(bytes.len());
+    {
+        let mut buf = buf.write_lock();
+        buf.copy_from_slice(bytes);
+    }
+    buf
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::test_fixtures::test_agent;
+    use holochain_core_types::cas::content::AddressableContent;
+
+    #[test]
+    fn fake_agents_keep_their_nick_and_carry_the_public_key() {
+        let mut keys = EncryptionKeys::generate_fake("alice");
+        let agent_id = keys.fake_agent_id("alice");
+        assert_eq!(agent_id.nick, "alice");
+        assert!(agent_id.address().to_string().starts_with("alice---"));
+        assert_eq!(
+            agent_id.address(),
+            EncryptionKeys::generate_fake("alice")
+                .fake_agent_id("alice")
+                .address()
+        );
+        assert_ne!(
+            agent_id.address(),
+            EncryptionKeys::generate_fake("bob")
+                .fake_agent_id("alice")
+                .address()
+        );
+    }
+
+    #[test]
+    fn only_the_recipient_decrypts() {
+        let mut alice = test_agent("alice").keys;
+        let mut bob = test_agent("bob").keys;
+        let alice_address = test_agent("alice").agent_id.address();
+
+        let payload = b"for alice only".to_vec();
+        let encrypted = encrypt_for(&alice_address, &payload).unwrap();
+        assert_ne!(encrypted[..], payload[..]);
+        // the same payload does not encrypt to the same bytes twice
+        assert_ne!(encrypted, encrypt_for(&alice_address, &payload).unwrap());
+
+        assert_eq!(alice.decrypt(&encrypted), Ok(payload));
+        assert_eq!(
+            bob.decrypt(&encrypted),
+            Err(HolochainError::ErrorGeneric(String::from(
+                "Could not decrypt: the payload is not encrypted for this agent or got modified"
+            )))
+        );
+        assert!(alice.decrypt(b"too short").is_err());
+    }
+
+    #[test]
+    fn only_the_signer_of_a_message_verifies() {
+        let alice = test_agent("alice");
+        let mut keys = alice.signing_keys;
+        let signature = keys.sign(b"signed by alice");
+        assert!(verify_signature(
+            &alice.agent_id.key,
+            b"signed by alice",
+            &signature
+        ));
+        assert!(!verify_signature(
+            &alice.agent_id.key,
+            b"signed by mallory",
+            &signature
+        ));
+        assert!(!verify_signature(
+            &test_agent("bob").agent_id.key,
+            b"signed by alice",
+            &signature
+        ));
+        assert!(!verify_signature(
+            &alice.agent_id.key,
+            b"signed by alice",
+            &Signature::from("")
+        ));
+    }
+
+    #[test]
+    fn encrypting_needs_an_agent_address() {
+        assert!(encrypt_for(&Address::from("not an agent"), b"payload").is_err());
+    }
+}
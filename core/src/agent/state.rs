@@ -15,7 +15,7 @@ use holochain_core_types::{
     signature::Signature,
     time::Iso8601,
 };
-use holochain_wasm_utils::api_serialization::get_entry::*;
+use holochain_wasm_utils::api_serialization::{get_entry::*, ChainInfo, CommitEntryResult};
 use serde_json;
 use std::{collections::HashMap, convert::TryFrom, sync::Arc};
 
@@ -29,6 +29,11 @@ pub struct AgentState {
     actions: HashMap<ActionWrapper, ActionResponse>,
     chain: ChainStore,
     top_chain_header: Option<ChainHeader>,
+    /// number of entries in the chain per entry type, updated with every commit
+    /// so that chain statistics don't need a walk over the whole chain
+    entry_type_counts: HashMap<EntryType, usize>,
+    /// number of commits since the last Flush, which might not survive a crash
+    pending_commits: usize,
 }
 
 impl AgentState {
@@ -38,14 +43,29 @@ impl AgentState {
             actions: HashMap::new(),
             chain,
             top_chain_header: None,
+            entry_type_counts: HashMap::new(),
+            pending_commits: 0,
         }
     }
 
+    /// builds an AgentState for an existing chain, e.g. when loading it from storage.
+    /// The entry type counts and the sequence index of the chain store are not persisted
+    /// and get rebuilt by walking the chain once.
     pub fn new_with_top_chain_header(chain: ChainStore, chain_header: ChainHeader) -> AgentState {
+        let top_chain_header = Some(chain_header);
+        let mut entry_type_counts = HashMap::new();
+        for chain_header in chain.iter(&top_chain_header) {
+            *entry_type_counts
+                .entry(chain_header.entry_type().clone())
+                .or_insert(0) += 1;
+            chain.index_header(&chain_header);
+        }
         AgentState {
             actions: HashMap::new(),
             chain,
-            top_chain_header: Some(chain_header),
+            top_chain_header,
+            entry_type_counts,
+            pending_commits: 0,
         }
     }
 
@@ -63,10 +83,35 @@ impl AgentState {
         self.top_chain_header.clone()
     }
 
+    /// How many commits went on the chain since the last flush, see `agent::actions::flush`.
+    /// Their entries and headers are in the storage, but until the flush syncs it they might
+    /// not be on the disk, and a restored instance would not see them on its chain.
+    pub fn pending_commits(&self) -> usize {
+        self.pending_commits
+    }
+
+    /// length of the chain, number of entries per type and the top header address
+    pub fn chain_info(&self) -> ChainInfo {
+        ChainInfo {
+            length: self.entry_type_counts.values().sum(),
+            entry_type_counts: self
+                .entry_type_counts
+                .iter()
+                .map(|(entry_type, count)| (entry_type.to_string(), *count))
+                .collect(),
+            top_header_address: self
+                .top_chain_header
+                .as_ref()
+                .map(|chain_header| chain_header.address()),
+        }
+    }
+
+    /// The address of the agent, i.e. of its initial identity entry, which stays the same
+    /// when the agent updates its identity. `get_agent()` resolves the latest identity.
     pub fn get_agent_address(&self) -> HcResult<Address> {
         self.chain()
             .iter_type(&self.top_chain_header, &EntryType::AgentId)
-            .nth(0)
+            .last()
             .and_then(|chain_header| Some(chain_header.entry_address().clone()))
             .ok_or(HolochainError::ErrorGeneric(
                 "Agent entry not found".to_string(),
@@ -78,6 +123,7 @@ impl AgentState {
         let entry_args = GetEntryArgs {
             address: agent_entry_address,
             options: GetEntryOptions::default(),
+            resolve_latest: true,
         };
         let agent_entry_history = await!(get_entry_history_workflow(context, &entry_args))?;
         if agent_entry_history.entries.is_empty() {
@@ -146,30 +192,38 @@ impl AddressableContent for AgentStateSnapshot {
 // @TODO abstract this to a standard trait
 // @see https://github.com/holochain/holochain-rust/issues/196
 pub enum ActionResponse {
-    Commit(Result<Address, HolochainError>),
+    Commit(Result<CommitEntryResult, HolochainError>),
     GetEntry(Option<Entry>),
     GetLinks(Result<Vec<Address>, HolochainError>),
     LinkEntries(Result<Entry, HolochainError>),
+    /// the number of commits the flush made durable
+    Flush(Result<usize, HolochainError>),
 }
 
+/// Builds the header for committing the entry on top of the chain of the given agent state
 pub fn create_new_chain_header(
     entry: &Entry,
+    agent_state: &AgentState,
     context: Arc<Context>,
     crud_link: &Option<Address>,
 ) -> ChainHeader {
-    let agent_state = context
-        .state()
-        .expect("create_new_chain_header called without state")
-        .agent();
     let agent_address = agent_state
         .get_agent_address()
         .unwrap_or(context.agent_id.address());
+    let sequence = agent_state
+        .top_chain_header
+        .as_ref()
+        .map(|chain_header| chain_header.sequence())
+        .unwrap_or(0)
+        + 1;
     ChainHeader::new(
         &entry.entry_type(),
         &entry.address(),
         &vec![agent_address],
-        // @TODO signatures
-        &vec![Signature::from("")],
+        // agents without signing keys, fake agents for instance, leave their headers unsigned
+        &vec![context
+            .sign(String::from(entry.address()).as_bytes())
+            .unwrap_or_else(|_| Signature::from(""))],
         &agent_state
             .top_chain_header
             .clone()
@@ -183,6 +237,7 @@ pub fn create_new_chain_header(
         // @TODO timestamp
         &Iso8601::from(""),
     )
+    .with_sequence(sequence)
 }
 
 /// Do a Commit Action against an agent state.
@@ -193,26 +248,59 @@ pub fn create_new_chain_header(
 /// @see https://github.com/holochain/holochain-rust/issues/222
 /// @TODO Better error handling in the state persister section
 /// https://github.com/holochain/holochain-rust/issues/555
+/// The header gets built on top of the chain of the state being reduced, which no other
+/// commit can change meanwhile. So commits that raced each other, e.g. from concurrent
+/// zome calls, still end up on a straight chain, unless one of them required the head it
+/// expected, see CommitOptions, and fails with ChainHeadMoved instead.
 fn reduce_commit_entry(
     context: Arc<Context>,
     state: &mut AgentState,
     action_wrapper: &ActionWrapper,
 ) {
     let action = action_wrapper.action();
-    let (entry, maybe_crud_link) = unwrap_to!(action => Action::Commit);
-    let chain_header = create_new_chain_header(&entry, context.clone(), &maybe_crud_link);
+    let (entry, maybe_crud_link, options) = unwrap_to!(action => Action::Commit);
+
+    let head = state
+        .top_chain_header
+        .as_ref()
+        .map(|chain_header| chain_header.address());
+    if let Some(ref expected) = options.require_head {
+        if head.as_ref() != Some(expected) {
+            let error = HolochainError::ChainHeadMoved {
+                expected: expected.clone(),
+                actual: head,
+            };
+            state
+                .actions
+                .insert(action_wrapper.clone(), ActionResponse::Commit(Err(error)));
+            return;
+        }
+    }
+
+    let chain_header = create_new_chain_header(&entry, state, context.clone(), &maybe_crud_link);
 
     fn response(
         state: &mut AgentState,
         entry: &Entry,
         chain_header: &ChainHeader,
-    ) -> Result<Address, HolochainError> {
+    ) -> Result<CommitEntryResult, HolochainError> {
         let storage = &state.chain.content_storage().clone();
         storage.write().unwrap().add(entry)?;
         storage.write().unwrap().add(chain_header)?;
-        Ok(entry.address())
+        Ok(CommitEntryResult::new(
+            entry.address(),
+            chain_header.address(),
+        ))
     }
     let result = response(state, &entry, &chain_header);
+    if result.is_ok() {
+        *state
+            .entry_type_counts
+            .entry(entry.entry_type())
+            .or_insert(0) += 1;
+        state.chain.index_header(&chain_header);
+        state.pending_commits += 1;
+    }
     state.top_chain_header = Some(chain_header);
     let con = context.clone();
 
@@ -228,10 +316,38 @@ fn reduce_commit_entry(
         .insert(action_wrapper.clone(), ActionResponse::Commit(result));
 }
 
+/// Reduce Flush Action.
+/// Every action dispatched before got reduced already, so the global state the context holds
+/// has all of the commits that are pending. Saves its snapshot, like commits do with the state
+/// before them, and syncs the storage that snapshot and the chain are in.
+fn reduce_flush(context: Arc<Context>, state: &mut AgentState, action_wrapper: &ActionWrapper) {
+    let save = || -> Result<(), HolochainError> {
+        let global_state = context
+            .state()
+            .ok_or_else(|| HolochainError::new("Could not get the state to flush"))?
+            .clone();
+        context
+            .persister
+            .lock()
+            .map_err(|_| HolochainError::new("Could not lock persister"))?
+            .save(global_state)?;
+        state.chain.content_storage().read()?.sync()?;
+        Ok(())
+    };
+    let result = save().map(|_| state.pending_commits);
+    if result.is_ok() {
+        state.pending_commits = 0;
+    }
+    state
+        .actions
+        .insert(action_wrapper.clone(), ActionResponse::Flush(result));
+}
+
 /// maps incoming action to the correct handler
 fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<AgentReduceFn> {
     match action_wrapper.action() {
         Action::Commit(_) => Some(reduce_commit_entry),
+        Action::Flush => Some(reduce_flush),
         _ => None,
     }
 }
@@ -258,16 +374,21 @@ pub mod tests {
     extern crate tempfile;
     use super::{reduce_commit_entry, ActionResponse, AgentState, AgentStateSnapshot};
     use crate::{
-        action::tests::test_action_wrapper_commit, agent::chain_store::tests::test_chain_store,
-        instance::tests::test_context, state::State,
+        action::{tests::test_action_wrapper_commit, Action, ActionWrapper},
+        agent::chain_store::{tests::test_chain_store, ChainStore},
+        instance::tests::test_context,
+        state::State,
     };
     use holochain_core_types::{
-        cas::content::AddressableContent,
+        cas::content::{Address, AddressableContent},
         chain_header::test_chain_header,
-        entry::{expected_entry_address, test_entry, Entry},
+        entry::{
+            expected_entry_address, test_entry, test_entry_b, test_entry_c, test_sys_entry, Entry,
+        },
         error::HolochainError,
         json::JsonString,
     };
+    use holochain_wasm_utils::api_serialization::{CommitEntryResult, CommitOptions};
     use serde_json;
     use std::{
         collections::HashMap,
@@ -281,7 +402,10 @@ pub mod tests {
 
     /// dummy action response for a successful commit as test_entry()
     pub fn test_action_response_commit() -> ActionResponse {
-        ActionResponse::Commit(Ok(expected_entry_address()))
+        ActionResponse::Commit(Ok(CommitEntryResult::new(
+            expected_entry_address(),
+            test_chain_header().address(),
+        )))
     }
 
     #[test]
@@ -310,10 +434,133 @@ pub mod tests {
 
         reduce_commit_entry(context, &mut agent_state, &action_wrapper);
 
+        let header = agent_state
+            .top_chain_header()
+            .expect("commit should set the top chain header");
+        assert_eq!(
+            agent_state.actions().get(&action_wrapper),
+            Some(&ActionResponse::Commit(Ok(CommitEntryResult::new(
+                expected_entry_address(),
+                header.address(),
+            )))),
+        );
+        assert_eq!(
+            Some(header.clone()),
+            agent_state.chain().get_header(&header.address()).unwrap(),
+        );
+    }
+
+    #[test]
+    /// test that a commit requiring another head than the top of the chain fails
+    /// without touching the chain, and goes through once it requires the top
+    fn test_reduce_commit_entry_requiring_head() {
+        let mut agent_state = test_agent_state();
+        let context = test_context("bob");
+        let state = State::new_with_agent(context, Arc::new(agent_state.clone()));
+        let mut context = test_context("bob");
+        Arc::get_mut(&mut context)
+            .unwrap()
+            .set_state(Arc::new(RwLock::new(state)));
+        let commit = |require_head: Option<Address>| {
+            ActionWrapper::new(Action::Commit((
+                test_entry(),
+                None,
+                CommitOptions {
+                    require_head,
+                    ..Default::default()
+                },
+            )))
+        };
+
+        let action_wrapper = commit(Some(Address::from("QmNotTheHead")));
+        reduce_commit_entry(context.clone(), &mut agent_state, &action_wrapper);
+        let chain_head_moved = HolochainError::ChainHeadMoved {
+            expected: Address::from("QmNotTheHead"),
+            actual: None,
+        };
+        assert_eq!(
+            agent_state.actions().get(&action_wrapper),
+            Some(&ActionResponse::Commit(Err(chain_head_moved))),
+        );
+        assert_eq!(agent_state.top_chain_header(), None);
+
+        reduce_commit_entry(context.clone(), &mut agent_state, &commit(None));
+        let head = agent_state.top_chain_header().unwrap().address();
+        let action_wrapper = commit(Some(head.clone()));
+        reduce_commit_entry(context.clone(), &mut agent_state, &action_wrapper);
+        let top = agent_state.top_chain_header().unwrap();
+        assert_eq!(top.link(), Some(head));
         assert_eq!(
             agent_state.actions().get(&action_wrapper),
-            Some(&test_action_response_commit()),
+            Some(&ActionResponse::Commit(Ok(CommitEntryResult::new(
+                expected_entry_address(),
+                top.address(),
+            )))),
+        );
+    }
+
+    #[test]
+    /// test that chain info counts commits and gets rebuilt for a chain loaded from storage
+    fn test_chain_info() {
+        let mut agent_state = test_agent_state();
+        let mut context = test_context("bob");
+        let global_state = Arc::new(RwLock::new(State::new_with_agent(
+            context.clone(),
+            Arc::new(agent_state.clone()),
+        )));
+        Arc::get_mut(&mut context)
+            .unwrap()
+            .set_state(global_state.clone());
+
+        for entry in vec![test_entry(), test_entry_b(), test_entry_c(), test_sys_entry()] {
+            let action_wrapper =
+                ActionWrapper::new(Action::Commit((entry, None, CommitOptions::default())));
+            reduce_commit_entry(context.clone(), &mut agent_state, &action_wrapper);
+        }
+
+        let chain_info = agent_state.chain_info();
+        assert_eq!(chain_info.length, 4);
+        assert_eq!(chain_info.count("testEntryType"), 1);
+        assert_eq!(chain_info.count("testEntryTypeB"), 2);
+        assert_eq!(chain_info.count("%agent_id"), 1);
+        assert_eq!(
+            chain_info.top_header_address,
+            agent_state.top_chain_header().map(|header| header.address())
+        );
+
+        let loaded_agent_state = AgentState::new_with_top_chain_header(
+            agent_state.chain(),
+            agent_state.top_chain_header().unwrap(),
         );
+        assert_eq!(loaded_agent_state.chain_info(), chain_info);
+
+        let sequences: Vec<u64> = agent_state
+            .chain()
+            .iter(&agent_state.top_chain_header())
+            .map(|chain_header| chain_header.sequence())
+            .collect();
+        assert_eq!(sequences, vec![4, 3, 2, 1]);
+        assert!(agent_state
+            .chain()
+            .verify_sequence(&agent_state.top_chain_header())
+            .is_ok());
+
+        let reloaded_chain = ChainStore::new(agent_state.chain().content_storage());
+        assert_eq!(reloaded_chain.indexed_header_address(2), None);
+        let reloaded_agent_state = AgentState::new_with_top_chain_header(
+            reloaded_chain,
+            agent_state.top_chain_header().unwrap(),
+        );
+        let second_header = agent_state
+            .chain()
+            .iter(&agent_state.top_chain_header())
+            .nth(2)
+            .unwrap();
+        assert_eq!(
+            reloaded_agent_state.chain().indexed_header_address(2),
+            Some(second_header.address())
+        );
+        assert_eq!(test_agent_state().chain_info().length, 0);
     }
 
     #[test]
@@ -321,10 +568,11 @@ pub mod tests {
     fn test_commit_response_to_json() {
         assert_eq!(
             JsonString::from(format!(
-                "{{\"Commit\":{{\"Ok\":\"{}\"}}}}",
-                expected_entry_address()
+                "{{\"Commit\":{{\"Ok\":{{\"address\":\"{}\",\"header_address\":\"{}\"}}}}}}",
+                expected_entry_address(),
+                test_chain_header().address(),
             )),
-            JsonString::from(ActionResponse::Commit(Ok(expected_entry_address()))),
+            JsonString::from(test_action_response_commit()),
         );
         assert_eq!(
             JsonString::from("{\"Commit\":{\"Err\":{\"ErrorGeneric\":\"some error\"}}}"),
@@ -1,5 +1,17 @@
 use crate::{
-    action::ActionWrapper, instance::Observer, logger::Logger, persister::Persister, state::State,
+    action::ActionWrapper,
+    agent::keys::{EncryptionKeys, SigningKeys},
+    instance::{CrashReport, Heartbeat, Observer},
+    logger::Logger,
+    nucleus::{
+        bridges::Bridges, call_metrics::ZomeFnLatencies, partial_results::PartialResultSinks,
+        ribosome::memory_limit::DEFAULT_MAX_WASM_MEMORY_MB,
+        validation_package_cache::ValidationPackageCache,
+    },
+    persister::Persister,
+    replay::ActionRecorder,
+    runtime::Runtime,
+    state::State,
 };
 use holochain_core_types::{
     agent::AgentId,
@@ -8,16 +20,43 @@ use holochain_core_types::{
     eav::EntityAttributeValueStorage,
     error::HolochainError,
     json::JsonString,
+    signature::Signature,
+    time::{SystemTimeSource, TimeSource},
 };
+use serde_json::{self, Value};
 use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
     sync::{
-        mpsc::{sync_channel, SyncSender},
+        atomic::{AtomicBool, Ordering},
+        mpsc::{sync_channel, SyncSender, TrySendError},
         Arc, Mutex, RwLock, RwLockReadGuard,
     },
-    thread::sleep,
-    time::Duration,
+    thread::{self, sleep},
+    time::{Duration, SystemTime},
 };
 
+/// How long a validation waits for the entries it depends on by default,
+/// before the entry gets rejected for missing dependencies
+pub const DEFAULT_VALIDATION_DEPENDENCY_TIMEOUT_SECS: u64 = 60;
+
+/// How long commits with PublishMode::Block wait for holders of the entry by default
+pub const DEFAULT_PUBLISH_TIMEOUT_SECS: u64 = 60;
+
+/// How long the result of a zome call waits for whoever made the call by default,
+/// before it gets pruned from the state
+pub const DEFAULT_ZOME_CALL_TIMEOUT_SECS: u64 = 60;
+
+/// How long a single hc_sleep may last at most, instances can be configured to less
+pub const MAX_SLEEP_SECS: u64 = 2;
+
+/// How long all the hc_sleep calls of a zome call may last together at most,
+/// instances can be configured to less
+pub const MAX_TOTAL_SLEEP_SECS: u64 = 5;
+
+/// How long a zome call may take by default before it gets logged as slow
+pub const DEFAULT_SLOW_CALL_THRESHOLD_MS: u64 = 1000;
+
 /// Context holds the components that parts of a Holochain instance need in order to operate.
 /// This includes components that are injected from the outside like logger and persister
 /// but also the store of the instance that gets injected before passing on the context
@@ -33,6 +72,32 @@ pub struct Context {
     pub file_storage: Arc<RwLock<ContentAddressableStorage>>,
     pub eav_storage: Arc<RwLock<EntityAttributeValueStorage>>,
     pub network_config: JsonString,
+    runtime: Option<Runtime>,
+    tasks_cancelled: Arc<AtomicBool>,
+    validation_dependency_timeout: Duration,
+    publish_timeout: Duration,
+    zome_call_timeout: Duration,
+    max_sleep: Duration,
+    max_total_sleep: Duration,
+    slow_call_threshold: Duration,
+    max_wasm_memory_mb: u32,
+    audit_host_calls: bool,
+    genesis_params: Value,
+    action_recorder: Option<Arc<Mutex<ActionRecorder>>>,
+    /// Where partial results of running zome calls go, see hc_yield
+    pub partial_results: PartialResultSinks,
+    /// The validation packages this agent built for the holders of its entries
+    pub validation_packages: ValidationPackageCache,
+    /// How long the calls of each zome function took
+    pub zome_fn_latencies: ZomeFnLatencies,
+    encryption_keys: Option<Arc<Mutex<EncryptionKeys>>>,
+    signing_keys: Option<Arc<Mutex<SigningKeys>>>,
+    bridges: Option<Arc<Bridges>>,
+    time_source: Arc<TimeSource>,
+    /// Where the action loop tells that it is alive, shared by all clones of the context
+    heartbeats: Arc<Mutex<Vec<SyncSender<Heartbeat>>>>,
+    /// Why the action loop of the instance crashed, shared by all clones of the context
+    crash: Arc<RwLock<Option<CrashReport>>>,
 }
 
 impl Context {
@@ -60,6 +125,29 @@ impl Context {
             file_storage: cas,
             eav_storage: eav,
             network_config,
+            runtime: None,
+            tasks_cancelled: Arc::new(AtomicBool::new(false)),
+            validation_dependency_timeout: Duration::from_secs(
+                DEFAULT_VALIDATION_DEPENDENCY_TIMEOUT_SECS,
+            ),
+            publish_timeout: Duration::from_secs(DEFAULT_PUBLISH_TIMEOUT_SECS),
+            zome_call_timeout: Duration::from_secs(DEFAULT_ZOME_CALL_TIMEOUT_SECS),
+            max_sleep: Duration::from_secs(MAX_SLEEP_SECS),
+            max_total_sleep: Duration::from_secs(MAX_TOTAL_SLEEP_SECS),
+            slow_call_threshold: Duration::from_millis(DEFAULT_SLOW_CALL_THRESHOLD_MS),
+            max_wasm_memory_mb: DEFAULT_MAX_WASM_MEMORY_MB,
+            audit_host_calls: false,
+            genesis_params: Value::Object(serde_json::Map::new()),
+            action_recorder: None,
+            partial_results: PartialResultSinks::default(),
+            validation_packages: ValidationPackageCache::default(),
+            zome_fn_latencies: ZomeFnLatencies::default(),
+            encryption_keys: None,
+            signing_keys: None,
+            bridges: None,
+            time_source: Arc::new(SystemTimeSource),
+            heartbeats: Arc::new(Mutex::new(Vec::new())),
+            crash: Arc::new(RwLock::new(None)),
         })
     }
 
@@ -83,6 +171,29 @@ impl Context {
             file_storage: cas,
             eav_storage: eav,
             network_config,
+            runtime: None,
+            tasks_cancelled: Arc::new(AtomicBool::new(false)),
+            validation_dependency_timeout: Duration::from_secs(
+                DEFAULT_VALIDATION_DEPENDENCY_TIMEOUT_SECS,
+            ),
+            publish_timeout: Duration::from_secs(DEFAULT_PUBLISH_TIMEOUT_SECS),
+            zome_call_timeout: Duration::from_secs(DEFAULT_ZOME_CALL_TIMEOUT_SECS),
+            max_sleep: Duration::from_secs(MAX_SLEEP_SECS),
+            max_total_sleep: Duration::from_secs(MAX_TOTAL_SLEEP_SECS),
+            slow_call_threshold: Duration::from_millis(DEFAULT_SLOW_CALL_THRESHOLD_MS),
+            max_wasm_memory_mb: DEFAULT_MAX_WASM_MEMORY_MB,
+            audit_host_calls: false,
+            genesis_params: Value::Object(serde_json::Map::new()),
+            action_recorder: None,
+            partial_results: PartialResultSinks::default(),
+            validation_packages: ValidationPackageCache::default(),
+            zome_fn_latencies: ZomeFnLatencies::default(),
+            encryption_keys: None,
+            signing_keys: None,
+            bridges: None,
+            time_source: Arc::new(SystemTimeSource),
+            heartbeats: Arc::new(Mutex::new(Vec::new())),
+            crash: Arc::new(RwLock::new(None)),
         })
     }
 
@@ -92,10 +203,333 @@ impl Context {
             .logger
             .lock()
             .or(Err(HolochainError::LoggingError))
-            .expect("Logger should work");;
+            .expect("Logger should work");
         logger.log(msg.into());
     }
 
+    // helper function to make it easier to hand actions to the logger
+    pub fn log_action(&self, action_wrapper: &ActionWrapper) {
+        let mut logger = self
+            .logger
+            .lock()
+            .or(Err(HolochainError::LoggingError))
+            .expect("Logger should work");
+        logger.log_action(action_wrapper);
+    }
+
+    /// Makes the instance write every action it processes to the given recorder,
+    /// see replay::replay()
+    pub fn set_action_recorder(&mut self, recorder: ActionRecorder) {
+        self.action_recorder = Some(Arc::new(Mutex::new(recorder)));
+    }
+
+    /// Hands the action to the action recorder, if there is one.
+    /// Recording failures get logged but don't stop the instance.
+    pub fn record_action(&self, action_wrapper: &ActionWrapper) {
+        if let Some(ref recorder) = self.action_recorder {
+            let result = recorder
+                .lock()
+                .map_err(|_| HolochainError::new("Could not lock action recorder"))
+                .and_then(|mut recorder| recorder.record(action_wrapper));
+            if let Err(error) = result {
+                self.log(format!("Could not record action: {}", error));
+            }
+        }
+    }
+
+    /// Makes this context run its tasks on the given (shared) runtime
+    /// instead of spawning a thread for every task.
+    pub fn set_runtime(&mut self, runtime: Runtime) {
+        self.runtime = Some(runtime);
+    }
+
+    /// Sets how long validations of entries we are asked to hold wait for the entries they
+    /// depend on, see DEFAULT_VALIDATION_DEPENDENCY_TIMEOUT_SECS
+    pub fn set_validation_dependency_timeout(&mut self, timeout: Duration) {
+        self.validation_dependency_timeout = timeout;
+    }
+
+    pub fn validation_dependency_timeout(&self) -> Duration {
+        self.validation_dependency_timeout
+    }
+
+    /// Gives the agent the key pair it decrypts with, whose public key has to be
+    /// the encryption part of its identity (see EncryptionKeys::fake_agent_id())
+    pub fn set_encryption_keys(&mut self, keys: EncryptionKeys) {
+        self.encryption_keys = Some(Arc::new(Mutex::new(keys)));
+    }
+
+    /// Decrypts a payload that got encrypted for this agent, see hc_decrypt
+    pub fn decrypt(&self, payload: &[u8]) -> Result<Vec<u8>, HolochainError> {
+        self.encryption_keys
+            .as_ref()
+            .ok_or_else(|| HolochainError::new("This agent has no encryption keys"))?
+            .lock()
+            .map_err(|_| HolochainError::new("Could not lock encryption keys"))?
+            .decrypt(payload)
+    }
+
+    /// Gives the agent the key pair it signs its chain headers with, whose public key
+    /// has to be the signing part of its identity
+    pub fn set_signing_keys(&mut self, keys: SigningKeys) {
+        self.signing_keys = Some(Arc::new(Mutex::new(keys)));
+    }
+
+    /// Signs the message as this agent, see agent::keys::verify_signature()
+    pub fn sign(&self, message: &[u8]) -> Result<Signature, HolochainError> {
+        Ok(self
+            .signing_keys
+            .as_ref()
+            .ok_or_else(|| HolochainError::new("This agent has no signing keys"))?
+            .lock()
+            .map_err(|_| HolochainError::new("Could not lock signing keys"))?
+            .sign(message))
+    }
+
+    /// Gives the zomes the instances their DNA declares bridges to, see hc_call_bridge
+    pub fn set_bridges(&mut self, bridges: Arc<Bridges>) {
+        self.bridges = Some(bridges);
+    }
+
+    /// The instances bridged to this one, None if no container set them
+    pub fn bridges(&self) -> Option<Arc<Bridges>> {
+        self.bridges.clone()
+    }
+
+    /// Sets how long commits with PublishMode::Block wait for holders to acknowledge the
+    /// entry, see DEFAULT_PUBLISH_TIMEOUT_SECS
+    pub fn set_publish_timeout(&mut self, timeout: Duration) {
+        self.publish_timeout = timeout;
+    }
+
+    pub fn publish_timeout(&self) -> Duration {
+        self.publish_timeout
+    }
+
+    /// Sets how long results of zome calls wait to be picked up before they get pruned,
+    /// see DEFAULT_ZOME_CALL_TIMEOUT_SECS
+    pub fn set_zome_call_timeout(&mut self, timeout: Duration) {
+        self.zome_call_timeout = timeout;
+    }
+
+    pub fn zome_call_timeout(&self) -> Duration {
+        self.zome_call_timeout
+    }
+
+    /// Sets how long a single hc_sleep may last and how long all the hc_sleep calls of a
+    /// zome call may last together, no longer than MAX_SLEEP_SECS and MAX_TOTAL_SLEEP_SECS
+    pub fn set_max_sleep(&mut self, max_sleep: Duration, max_total_sleep: Duration) {
+        self.max_sleep = std::cmp::min(max_sleep, Duration::from_secs(MAX_SLEEP_SECS));
+        self.max_total_sleep =
+            std::cmp::min(max_total_sleep, Duration::from_secs(MAX_TOTAL_SLEEP_SECS));
+    }
+
+    pub fn max_sleep(&self) -> Duration {
+        self.max_sleep
+    }
+
+    /// How long a zome call may sleep in total, which is never longer than the zome call
+    /// timeout, since the caller would have given up on the result by then
+    pub fn max_total_sleep(&self) -> Duration {
+        std::cmp::min(self.max_total_sleep, self.zome_call_timeout)
+    }
+
+    /// Sets how long a zome call may take before it gets logged as slow, with how long it
+    /// spent in its WASM and in host functions, see DEFAULT_SLOW_CALL_THRESHOLD_MS
+    pub fn set_slow_call_threshold(&mut self, threshold: Duration) {
+        self.slow_call_threshold = threshold;
+    }
+
+    pub fn slow_call_threshold(&self) -> Duration {
+        self.slow_call_threshold
+    }
+
+    /// Sets how much WASM memory each zome call may use, in MiB,
+    /// see DEFAULT_MAX_WASM_MEMORY_MB
+    pub fn set_max_wasm_memory_mb(&mut self, max_wasm_memory_mb: u32) {
+        self.max_wasm_memory_mb = max_wasm_memory_mb;
+    }
+
+    pub fn max_wasm_memory_mb(&self) -> u32 {
+        self.max_wasm_memory_mb
+    }
+
+    /// Sets whether all zome calls record the host functions they invoke,
+    /// not only those asking for it, see ZomeFnCall::with_audit()
+    pub fn set_audit_host_calls(&mut self, audit_host_calls: bool) {
+        self.audit_host_calls = audit_host_calls;
+    }
+
+    pub fn audit_host_calls(&self) -> bool {
+        self.audit_host_calls
+    }
+
+    /// Sets what the configuration of the instance hands to the genesis callbacks of its
+    /// zomes. They are not part of the DNA, so they don't change its hash, and other nodes
+    /// validating the entries of this agent don't know them.
+    pub fn set_genesis_params(&mut self, genesis_params: Value) {
+        self.genesis_params = genesis_params;
+    }
+
+    /// The genesis parameters of the instance, an empty object if it has none
+    pub fn genesis_params(&self) -> Value {
+        self.genesis_params.clone()
+    }
+
+    /// Sets the clock the instance goes by when entries expire, see `now()`
+    pub fn set_time_source(&mut self, time_source: Arc<TimeSource>) {
+        self.time_source = time_source;
+    }
+
+    /// The current time as told by the time source of the instance, the system's clock
+    /// unless set otherwise
+    pub fn now(&self) -> SystemTime {
+        self.time_source.now()
+    }
+
+    /// Runs the given task on the runtime, or on a thread of its own if no runtime is set.
+    /// Tasks that did not start yet when cancel_tasks() gets called won't run at all.
+    /// A panic inside the task is caught and logged, so it can't affect other tasks or instances.
+    pub fn spawn_task<F>(&self, task: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.spawn_task_or_else(task, || ())
+    }
+
+    /// Like spawn_task(), for a task that somebody waits for: if the task gets cancelled
+    /// before it started, `cancelled` gets called instead, which should resolve whatever
+    /// waits for the task with an error.
+    pub fn spawn_task_or_else<F, G>(&self, task: F, cancelled: G)
+    where
+        F: FnOnce() + Send + 'static,
+        G: FnOnce() + Send + 'static,
+    {
+        let tasks_cancelled = self.tasks_cancelled.clone();
+        let logger = self.logger.clone();
+        let guarded_task = move || {
+            if tasks_cancelled.load(Ordering::SeqCst) {
+                cancelled();
+                return;
+            }
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(task)) {
+                if let Ok(mut logger) = logger.lock() {
+                    logger.log(format!("Task panicked: {}", panic_message(&payload)));
+                }
+            }
+        };
+        match self.runtime {
+            Some(ref runtime) => runtime.spawn(guarded_task),
+            None => {
+                thread::spawn(guarded_task);
+            }
+        }
+    }
+
+    /// Like spawn_task(), for a task handling the request of a peer with the given id.
+    /// If the task panics, the panic gets logged with the request id and `fail` gets called,
+    /// which should resolve the request as failed so the peer isn't left waiting.
+    /// `fail` also gets called if the task gets cancelled before it started.
+    pub fn spawn_request_task<F, G>(&self, request_id: String, task: F, fail: G)
+    where
+        F: FnOnce() + Send + 'static,
+        G: FnOnce() + Send + 'static,
+    {
+        let logger = self.logger.clone();
+        let fail = Arc::new(Mutex::new(Some(fail)));
+        let fail_on_cancel = fail.clone();
+        self.spawn_task_or_else(
+            move || {
+                if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(task)) {
+                    if let Ok(mut logger) = logger.lock() {
+                        logger.log(format!(
+                            "Request {} failed, its task panicked: {}",
+                            request_id,
+                            panic_message(&payload)
+                        ));
+                    }
+                    take_and_call(&fail);
+                }
+            },
+            move || take_and_call(&fail_on_cancel),
+        );
+    }
+
+    /// Runs the given long running loop, i.e. the action loop of an instance, on the runtime,
+    /// or on a thread of its own if no runtime is set, see Runtime::spawn_loop().
+    /// Cancelling the tasks of the context doesn't affect it.
+    pub fn spawn_loop<F>(&self, main_loop: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        match self.runtime {
+            Some(ref runtime) => runtime.spawn_loop(main_loop),
+            None => {
+                thread::spawn(main_loop);
+            }
+        }
+    }
+
+    /// Drops all tasks of this context (and its clones) that have not been started yet
+    /// and all that get spawned from now on, i.e. when the instance gets stopped.
+    /// Tasks of other instances on the same runtime are not affected.
+    pub fn cancel_tasks(&self) {
+        self.tasks_cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Lets tasks run again after cancel_tasks()
+    pub fn resume_tasks(&self) {
+        self.tasks_cancelled.store(false, Ordering::SeqCst);
+    }
+
+    /// Makes the action loops started with this context and its clones send their
+    /// heartbeats to the given channel as well, see `Heartbeat`
+    pub fn add_heartbeat_watcher(&self, heartbeat: SyncSender<Heartbeat>) {
+        self.heartbeats.lock().unwrap().push(heartbeat);
+    }
+
+    /// Hands the heartbeat to whoever watches the instance, if anyone does.
+    /// Heartbeats that don't fit into a channel get dropped, they are only a sign of life.
+    /// Watchers that went away are forgotten.
+    pub(crate) fn send_heartbeat(&self, heartbeat: Heartbeat) {
+        self.heartbeats
+            .lock()
+            .unwrap()
+            .retain(|sender| match sender.try_send(heartbeat.clone()) {
+                Err(TrySendError::Disconnected(_)) => false,
+                _ => true,
+            });
+    }
+
+    /// Stops sending heartbeats to the watchers, e.g. for an action loop that got replaced
+    pub fn forget_heartbeat_watchers(&self) {
+        self.heartbeats.lock().unwrap().clear();
+    }
+
+    /// A clone for the action loop of an instance that gets rebuilt after a crash.
+    /// It starts without a crash, with its own cancellation of tasks and its own copy of
+    /// the heartbeat watchers, so that the action loop this context started, if it is only
+    /// hung, stays marked as crashed and can't cancel the tasks of the new one.
+    pub fn for_rebuilt_action_loop(&self) -> Context {
+        let mut context = self.clone();
+        context.crash = Arc::new(RwLock::new(None));
+        context.tasks_cancelled = Arc::new(AtomicBool::new(false));
+        let watchers = self.heartbeats.lock().unwrap().clone();
+        context.heartbeats = Arc::new(Mutex::new(watchers));
+        context
+    }
+
+    /// Marks the action loop of the instance as crashed, either by itself when it panicked
+    /// or by whoever watches it when it stopped sending heartbeats
+    pub fn report_crash(&self, crash: CrashReport) {
+        *self.crash.write().unwrap() = Some(crash);
+    }
+
+    /// Why the action loop of the instance crashed, None while it is running
+    pub fn crash_report(&self) -> Option<CrashReport> {
+        self.crash.read().unwrap().clone()
+    }
+
     pub fn set_state(&mut self, state: Arc<RwLock<State>>) {
         self.state = Some(state);
     }
@@ -145,6 +579,23 @@ impl Context {
     }
 }
 
+/// What a caught panic got called with, if it was a string
+pub(crate) fn panic_message(payload: &Box<Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_default()
+}
+
+/// Calls the shared FnOnce, unless somebody did already
+fn take_and_call<F: FnOnce()>(shared: &Arc<Mutex<Option<F>>>) {
+    let maybe_f = shared.lock().ok().and_then(|mut maybe_f| maybe_f.take());
+    if let Some(f) = maybe_f {
+        f();
+    }
+}
+
 /// create a test network
 #[cfg_attr(tarpaulin, skip)]
 pub fn mock_network_config() -> JsonString {
@@ -158,12 +609,14 @@ pub mod tests {
     use self::tempfile::tempdir;
     use super::*;
     use crate::{
-        context::mock_network_config, instance::tests::test_logger, persister::SimplePersister,
+        context::mock_network_config,
+        instance::tests::{test_context_and_logger, test_logger},
+        persister::SimplePersister,
         state::State,
     };
     use holochain_cas_implementations::{cas::file::FilesystemStorage, eav::file::EavFileStorage};
     use holochain_core_types::agent::AgentId;
-    use std::sync::{Arc, Mutex, RwLock};
+    use std::sync::{mpsc::channel, Arc, Mutex, RwLock};
 
     #[test]
     fn default_buffer_size_test() {
@@ -199,6 +652,127 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn panicking_task_only_affects_its_own_context() {
+        let runtime = Runtime::new(1);
+        let (context1, logger1) = test_context_and_logger("jane");
+        let (context2, _) = test_context_and_logger("joan");
+        let mut context1 = (*context1).clone();
+        let mut context2 = (*context2).clone();
+        context1.set_runtime(runtime.clone());
+        context2.set_runtime(runtime.clone());
+
+        context1.spawn_task(|| panic!("workflow failed"));
+        thread::sleep(Duration::from_millis(100));
+        assert!(logger1
+            .lock()
+            .unwrap()
+            .log
+            .contains(&String::from("Task panicked: workflow failed")));
+
+        let (sender, receiver) = channel();
+        context2.spawn_task(move || sender.send(()).unwrap());
+        assert!(receiver.recv_timeout(Duration::from_secs(1)).is_ok());
+    }
+
+    #[test]
+    fn panicking_request_task_fails_its_request() {
+        let (context, logger) = test_context_and_logger("jane");
+
+        let (sender, receiver) = channel();
+        context.spawn_request_task(
+            String::from("request-1"),
+            || panic!("storage poisoned"),
+            move || sender.send(()).unwrap(),
+        );
+        assert!(receiver.recv_timeout(Duration::from_secs(1)).is_ok());
+        assert!(logger.lock().unwrap().log.contains(&String::from(
+            "Request request-1 failed, its task panicked: storage poisoned"
+        )));
+    }
+
+    #[test]
+    fn cancel_tasks_only_cancels_own_tasks() {
+        let runtime = Runtime::new(1);
+        let (context1, _) = test_context_and_logger("jane");
+        let (context2, _) = test_context_and_logger("joan");
+        let mut context1 = (*context1).clone();
+        let mut context2 = (*context2).clone();
+        context1.set_runtime(runtime.clone());
+        context2.set_runtime(runtime.clone());
+
+        context1.cancel_tasks();
+        let (sender1, receiver1) = channel();
+        let (sender2, receiver2) = channel();
+        // clones share the cancellation, like the sub contexts of an instance do
+        context1
+            .clone()
+            .spawn_task(move || sender1.send(()).unwrap());
+        context2.spawn_task(move || sender2.send(()).unwrap());
+        assert!(receiver2.recv_timeout(Duration::from_secs(1)).is_ok());
+        assert!(receiver1.recv_timeout(Duration::from_millis(100)).is_err());
+
+        context1.resume_tasks();
+        let (sender1, receiver1) = channel();
+        context1.spawn_task(move || sender1.send(()).unwrap());
+        assert!(receiver1.recv_timeout(Duration::from_secs(1)).is_ok());
+    }
+
+    #[test]
+    fn cancelled_tasks_resolve_their_waiters() {
+        let (context, _) = test_context_and_logger("jane");
+        context.cancel_tasks();
+
+        let (sender, receiver) = channel();
+        let cancelled_sender = sender.clone();
+        context.spawn_task_or_else(
+            move || sender.send("ran").unwrap(),
+            move || cancelled_sender.send("cancelled").unwrap(),
+        );
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(1)),
+            Ok("cancelled")
+        );
+
+        let (sender, receiver) = channel();
+        context.spawn_request_task(
+            String::from("request-1"),
+            || (),
+            move || sender.send(()).unwrap(),
+        );
+        assert!(receiver.recv_timeout(Duration::from_secs(1)).is_ok());
+    }
+
+    #[test]
+    fn rebuilt_action_loops_share_no_crash_or_tasks_with_the_old_one() {
+        let (context, _) = test_context_and_logger("jane");
+        let (first_sender, first_watcher) = sync_channel(10);
+        let (second_sender, second_watcher) = sync_channel(10);
+        context.add_heartbeat_watcher(first_sender);
+        context.add_heartbeat_watcher(second_sender);
+        context.send_heartbeat(Heartbeat::Alive);
+        assert_eq!(first_watcher.try_recv(), Ok(Heartbeat::Alive));
+        assert_eq!(second_watcher.try_recv(), Ok(Heartbeat::Alive));
+
+        context.report_crash(CrashReport::new("hung".to_string(), None));
+        let rebuilt = context.for_rebuilt_action_loop();
+        context.forget_heartbeat_watchers();
+        assert_eq!(rebuilt.crash_report(), None);
+        assert!(context.crash_report().is_some());
+
+        context.cancel_tasks();
+        let (sender, receiver) = channel();
+        rebuilt.spawn_task(move || sender.send(()).unwrap());
+        assert!(receiver.recv_timeout(Duration::from_secs(1)).is_ok());
+
+        // the old loop beats for nobody, the new one for both watchers
+        context.send_heartbeat(Heartbeat::Alive);
+        rebuilt.send_heartbeat(Heartbeat::Alive);
+        assert_eq!(first_watcher.try_recv(), Ok(Heartbeat::Alive));
+        assert!(first_watcher.try_recv().is_err());
+        assert_eq!(second_watcher.try_recv(), Ok(Heartbeat::Alive));
+    }
+
     #[test]
     #[should_panic]
     #[cfg(not(windows))] // RwLock does not panic on windows since mutexes are recursive
@@ -9,7 +9,11 @@ use futures::{
     future::Future,
     task::{LocalWaker, Poll},
 };
-use holochain_core_types::{error::HolochainError, link::Link};
+use holochain_core_types::{
+    error::HolochainError,
+    link::{Link, LinkOperation},
+    time::Iso8601,
+};
 use std::{
     pin::{Pin, Unpin},
     sync::Arc,
@@ -22,9 +26,20 @@ use std::{
 /// local storage and will return an error that the AddLinkFuture resolves to
 /// if that is not the case.
 ///
+/// The link counts as added at the current time of the context, see `add_link_operation()`
+/// for adds that come with a header.
+///
 /// Returns a future that resolves to an Ok(()) or an Err(HolochainError).
 pub fn add_link(link: &Link, context: &Arc<Context>) -> AddLinkFuture {
-    let action_wrapper = ActionWrapper::new(Action::AddLink(link.clone()));
+    add_link_operation(
+        &LinkOperation::new(link, Iso8601::from(context.now())),
+        context,
+    )
+}
+
+/// Like `add_link()`, for the add of a link that got published with a header
+pub fn add_link_operation(operation: &LinkOperation, context: &Arc<Context>) -> AddLinkFuture {
+    let action_wrapper = ActionWrapper::new(Action::AddLink(operation.clone()));
     dispatch_action(&context.action_channel, action_wrapper.clone());
 
     AddLinkFuture {
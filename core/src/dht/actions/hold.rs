@@ -4,6 +4,7 @@ use crate::{
     action::{Action, ActionWrapper},
     context::Context,
     instance::dispatch_action,
+    workflows::collect_garbage::seconds_since_epoch,
 };
 use futures::{
     future::Future,
@@ -11,6 +12,7 @@ use futures::{
 };
 use holochain_core_types::{
     cas::content::{Address, AddressableContent},
+    chain_header::ChainHeader,
     entry::Entry,
     error::HolochainError,
 };
@@ -19,11 +21,14 @@ use std::{
     sync::Arc,
 };
 
+/// Adds the entry to the local DHT shard, held from now on as told by the time source of
+/// the context, see `Context::now()`
 pub async fn hold_entry<'a>(
     entry: &'a Entry,
     context: &'a Arc<Context>,
 ) -> Result<Address, HolochainError> {
-    let action_wrapper = ActionWrapper::new(Action::Hold(entry.clone()));
+    let held_since = seconds_since_epoch(context.now());
+    let action_wrapper = ActionWrapper::new(Action::Hold((entry.clone(), held_since)));
     dispatch_action(&context.action_channel, action_wrapper.clone());
 
     await!(HoldEntryFuture {
@@ -32,6 +37,13 @@ pub async fn hold_entry<'a>(
     })
 }
 
+/// Remembers the header an entry we hold was published with, so that the entry
+/// can be validated again later. Does not wait for the DHT reducer.
+pub fn hold_header(header: &ChainHeader, context: &Arc<Context>) {
+    let action_wrapper = ActionWrapper::new(Action::HoldHeader(header.clone()));
+    dispatch_action(&context.action_channel, action_wrapper);
+}
+
 pub struct HoldEntryFuture {
     context: Arc<Context>,
     address: Address,
@@ -1,3 +1,6 @@
 pub mod add_link;
 pub mod hold;
+pub mod queue_validation;
+pub mod reject_entry;
 pub mod remove_entry;
+pub mod remove_link;
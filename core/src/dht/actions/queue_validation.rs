@@ -0,0 +1,81 @@
+extern crate futures;
+use crate::{
+    action::{Action, ActionWrapper},
+    context::Context,
+    dht::pending_validation::PendingValidation,
+    instance::dispatch_action,
+};
+use futures::{
+    future::Future,
+    task::{LocalWaker, Poll},
+};
+use holochain_core_types::error::HolochainError;
+use std::{
+    pin::{Pin, Unpin},
+    sync::Arc,
+};
+
+/// QueueValidation Action Creator
+/// This action creator dispatches a QueueValidation action which is consumed by the DHT reducer.
+/// It parks the validation of an entry whose dependencies are not available yet
+/// in the local DHT shard, so that it survives restarts.
+///
+/// Returns a future that resolves to an Ok(()) or an Err(HolochainError).
+pub fn queue_validation(
+    pending_validation: &PendingValidation,
+    context: &Arc<Context>,
+) -> ValidationQueueFuture {
+    let action_wrapper = ActionWrapper::new(Action::QueueValidation(pending_validation.clone()));
+    dispatch_action(&context.action_channel, action_wrapper.clone());
+
+    ValidationQueueFuture {
+        context: context.clone(),
+        action: action_wrapper,
+    }
+}
+
+/// DequeueValidation Action Creator
+/// This action creator dispatches a DequeueValidation action which is consumed by the DHT reducer.
+/// It takes a pending validation out of the queue once it got done or timed out.
+///
+/// Returns a future that resolves to an Ok(()) or an Err(HolochainError).
+pub fn dequeue_validation(
+    pending_validation: &PendingValidation,
+    context: &Arc<Context>,
+) -> ValidationQueueFuture {
+    let action_wrapper = ActionWrapper::new(Action::DequeueValidation(pending_validation.clone()));
+    dispatch_action(&context.action_channel, action_wrapper.clone());
+
+    ValidationQueueFuture {
+        context: context.clone(),
+        action: action_wrapper,
+    }
+}
+
+pub struct ValidationQueueFuture {
+    context: Arc<Context>,
+    action: ActionWrapper,
+}
+
+impl Unpin for ValidationQueueFuture {}
+
+impl Future for ValidationQueueFuture {
+    type Output = Result<(), HolochainError>;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Self::Output> {
+        //
+        // TODO: connect the waker to state updates for performance reasons
+        // See: https://github.com/holochain/holochain-rust/issues/314
+        //
+        lw.wake();
+        if let Some(state) = self.context.state() {
+            match state.dht().actions().get(&self.action) {
+                Some(Ok(_)) => Poll::Ready(Ok(())),
+                Some(Err(e)) => Poll::Ready(Err(e.clone())),
+                None => Poll::Pending,
+            }
+        } else {
+            Poll::Pending
+        }
+    }
+}
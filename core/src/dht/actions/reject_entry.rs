@@ -0,0 +1,65 @@
+extern crate futures;
+use crate::{
+    action::{Action, ActionWrapper},
+    context::Context,
+    dht::dht_store::RejectionReason,
+    instance::dispatch_action,
+};
+use futures::{
+    future::Future,
+    task::{LocalWaker, Poll},
+};
+use holochain_core_types::{cas::content::Address, error::HolochainError};
+use std::{
+    pin::{Pin, Unpin},
+    sync::Arc,
+};
+
+/// RejectEntry Action Creator
+/// This action creator dispatches a RejectEntry action which is consumed by the DHT reducer.
+/// It is used by holding nodes to mark entries (and links) that failed validation,
+/// or could not be validated because the entries they depend on were missing,
+/// instead of storing them.
+///
+/// Returns a future that resolves to an Ok(()) or an Err(HolochainError).
+pub fn reject_entry(
+    address: &Address,
+    reason: RejectionReason,
+    context: &Arc<Context>,
+) -> RejectEntryFuture {
+    let action_wrapper = ActionWrapper::new(Action::RejectEntry((address.clone(), reason)));
+    dispatch_action(&context.action_channel, action_wrapper.clone());
+
+    RejectEntryFuture {
+        context: context.clone(),
+        action: action_wrapper,
+    }
+}
+
+pub struct RejectEntryFuture {
+    context: Arc<Context>,
+    action: ActionWrapper,
+}
+
+impl Unpin for RejectEntryFuture {}
+
+impl Future for RejectEntryFuture {
+    type Output = Result<(), HolochainError>;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Self::Output> {
+        //
+        // TODO: connect the waker to state updates for performance reasons
+        // See: https://github.com/holochain/holochain-rust/issues/314
+        //
+        lw.wake();
+        if let Some(state) = self.context.state() {
+            match state.dht().actions().get(&self.action) {
+                Some(Ok(_)) => Poll::Ready(Ok(())),
+                Some(Err(e)) => Poll::Ready(Err(e.clone())),
+                None => Poll::Pending,
+            }
+        } else {
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,146 @@
+extern crate futures;
+use crate::{
+    action::{Action, ActionWrapper},
+    context::Context,
+    instance::dispatch_action,
+};
+use futures::{
+    future::Future,
+    task::{LocalWaker, Poll},
+};
+use holochain_core_types::{
+    cas::content::Address,
+    error::HolochainError,
+    link::{Link, LinkOperation},
+    time::Iso8601,
+};
+use std::{
+    pin::{Pin, Unpin},
+    sync::Arc,
+};
+
+/// RemoveLink Action Creator
+/// This action creator dispatches a RemoveLink action which is consumed by the DHT reducer.
+/// The link stops being returned by both get_links() from its base and get_links_to()
+/// its target. This removes all adds of the link made before the current time of the
+/// context, adding it again afterwards brings it back.
+///
+/// Returns a future that resolves to an Ok(()) or an Err(HolochainError).
+pub fn remove_link(link: &Link, context: &Arc<Context>) -> RemoveLinkFuture {
+    remove_link_operation(
+        &LinkOperation::new(link, Iso8601::from(context.now())),
+        None,
+        context,
+    )
+}
+
+/// Like `remove_link()`, for a remove that only removes the add of the link with the given
+/// header, or all adds made before the operation if it names none
+pub fn remove_link_operation(
+    operation: &LinkOperation,
+    removed_add: Option<&Address>,
+    context: &Arc<Context>,
+) -> RemoveLinkFuture {
+    let action_wrapper = ActionWrapper::new(Action::RemoveLink((
+        operation.clone(),
+        removed_add.cloned(),
+    )));
+    dispatch_action(&context.action_channel, action_wrapper.clone());
+
+    RemoveLinkFuture {
+        context: context.clone(),
+        action: action_wrapper,
+    }
+}
+
+pub struct RemoveLinkFuture {
+    context: Arc<Context>,
+    action: ActionWrapper,
+}
+
+impl Unpin for RemoveLinkFuture {}
+
+impl Future for RemoveLinkFuture {
+    type Output = Result<(), HolochainError>;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Self::Output> {
+        //
+        // TODO: connect the waker to state updates for performance reasons
+        // See: https://github.com/holochain/holochain-rust/issues/314
+        //
+        lw.wake();
+        if let Some(state) = self.context.state() {
+            match state.dht().actions().get(&self.action) {
+                Some(Ok(_)) => Poll::Ready(Ok(())),
+                Some(Err(e)) => Poll::Ready(Err(e.clone())),
+                None => Poll::Pending,
+            }
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dht::actions::add_link::add_link, nucleus};
+
+    use futures::executor::block_on;
+    use holochain_core_types::{cas::content::AddressableContent, link::Link};
+
+    #[test]
+    fn removes_links_in_both_directions() {
+        let (_instance, context) = nucleus::actions::tests::instance();
+
+        let a = nucleus::actions::tests::test_entry_package_entry();
+        let b = nucleus::actions::tests::test_entry_package_chain_entries();
+        nucleus::actions::tests::commit(a.clone(), &context);
+        nucleus::actions::tests::commit(b.clone(), &context);
+        let link = Link::new(&a.address(), &b.address(), "test-tag");
+        block_on(add_link(&link, &context)).expect("Could not add link");
+
+        let links_from = |context: &Arc<Context>| {
+            context
+                .state()
+                .unwrap()
+                .dht()
+                .get_links(a.address(), String::from("test-tag"))
+                .unwrap()
+                .iter()
+                .map(|eav| eav.value())
+                .collect::<Vec<_>>()
+        };
+        let links_to = |context: &Arc<Context>| {
+            context
+                .state()
+                .unwrap()
+                .dht()
+                .get_links_to(b.address(), String::from("test-tag"))
+                .unwrap()
+                .iter()
+                .map(|eav| eav.value())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(links_from(&context), vec![b.address()]);
+        assert_eq!(links_to(&context), vec![a.address()]);
+        // the tag is part of the link
+        assert!(context
+            .state()
+            .unwrap()
+            .dht()
+            .get_links_to(b.address(), String::from("other-tag"))
+            .unwrap()
+            .is_empty());
+
+        block_on(remove_link(&link, &context)).expect("Could not remove link");
+
+        assert!(links_from(&context).is_empty());
+        assert!(links_to(&context).is_empty());
+
+        // an add made after the remove brings the link back
+        block_on(add_link(&link, &context)).expect("Could not add link");
+        assert_eq!(links_from(&context), vec![b.address()]);
+        assert_eq!(links_to(&context), vec![a.address()]);
+    }
+}
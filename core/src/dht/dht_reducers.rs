@@ -3,7 +3,11 @@
 use crate::{
     action::{Action, ActionWrapper},
     context::Context,
-    dht::dht_store::DhtStore,
+    dht::dht_store::{
+        link_add_eavs, link_eavs, link_remove_eavs, DhtStore, DEQUEUED_VALIDATION_NAME,
+        HEADER_NAME, HELD_SINCE_NAME, LINK_TARGET_TYPE_NAME, PENDING_VALIDATION_NAME,
+        REJECTION_REASON_NAME,
+    },
 };
 use holochain_core_types::{
     cas::content::{Address, AddressableContent},
@@ -11,6 +15,7 @@ use holochain_core_types::{
     eav::EntityAttributeValue,
     entry::Entry,
     error::HolochainError,
+    hash::HashString,
 };
 
 use std::{collections::HashSet, convert::TryFrom, sync::Arc};
@@ -48,21 +53,28 @@ fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<DhtReducer> {
         Action::UpdateEntry(_) => Some(reduce_update_entry),
         Action::RemoveEntry(_) => Some(reduce_remove_entry),
         Action::AddLink(_) => Some(reduce_add_link),
+        Action::RemoveLink(_) => Some(reduce_remove_link),
+        Action::RejectEntry(_) => Some(reduce_reject_entry),
+        Action::HoldHeader(_) => Some(reduce_hold_header),
+        Action::QueueValidation(_) => Some(reduce_queue_validation),
+        Action::DequeueValidation(_) => Some(reduce_dequeue_validation),
         _ => None,
     }
 }
 
 //
 pub(crate) fn reduce_hold_entry(
-    _context: Arc<Context>,
+    context: Arc<Context>,
     old_store: &DhtStore,
     action_wrapper: &ActionWrapper,
 ) -> Option<DhtStore> {
     let action = action_wrapper.action();
 
-    let entry = match &action {
-        &Action::Hold(entry) => entry,
-        &Action::Commit((entry, _)) => entry,
+    // Own commits get their time recorded by the next garbage collection instead,
+    // see workflows::collect_garbage::mark_expired_entries()
+    let (entry, held_since) = match &action {
+        &Action::Hold((entry, held_since)) => (entry, Some(*held_since)),
+        &Action::Commit((entry, _, _)) => (entry, None),
         _ => unreachable!(),
     };
 
@@ -89,10 +101,56 @@ pub(crate) fn reduce_hold_entry(
         return None;
     }
 
+    // Entries of types with a TTL expire counting from when they got here
+    let held_since_eav =
+        held_since.and_then(|held_since| held_since_eav(&context, &new_store, entry, held_since));
+    if let Some(held_since_eav) = held_since_eav {
+        if let Err(error) = (*meta_storage.write().unwrap()).add_eav(&held_since_eav) {
+            context.log(format!(
+                "Could not record since when {} is held: {}",
+                entry.address(),
+                error
+            ));
+            return None;
+        }
+    }
+
     // Done
     Some(new_store)
 }
 
+/// The EAV recording the given time as when the given entry started being held,
+/// if its type has a TTL in the DNA of the instance and it is not held already
+fn held_since_eav(
+    context: &Arc<Context>,
+    store: &DhtStore,
+    entry: &Entry,
+    held_since: u64,
+) -> Option<EntityAttributeValue> {
+    context
+        .state()
+        .and_then(|state| state.nucleus().dna())
+        .and_then(|dna| dna.entry_ttl(&entry.entry_type()))?;
+    let held = store
+        .meta_storage()
+        .read()
+        .ok()?
+        .fetch_eav(
+            Some(entry.address()),
+            Some(HELD_SINCE_NAME.to_string()),
+            None,
+        )
+        .ok()?;
+    if !held.is_empty() {
+        return None;
+    }
+    Some(EntityAttributeValue::new(
+        &entry.address(),
+        &HELD_SINCE_NAME.to_string(),
+        &Address::from(held_since.to_string()),
+    ))
+}
+
 //
 pub(crate) fn reduce_add_link(
     _context: Arc<Context>,
@@ -101,7 +159,8 @@ pub(crate) fn reduce_add_link(
 ) -> Option<DhtStore> {
     // Get Action's input data
     let action = action_wrapper.action();
-    let link = unwrap_to!(action => Action::AddLink);
+    let operation = unwrap_to!(action => Action::AddLink);
+    let link = operation.link();
 
     let mut new_store = (*old_store).clone();
     let storage = &old_store.content_storage().clone();
@@ -115,11 +174,39 @@ pub(crate) fn reduce_add_link(
         return Some(new_store);
     }
 
-    let eav =
-        EntityAttributeValue::new(link.base(), &format!("link__{}", link.tag()), link.target());
+    // Remember the target's type so filtering links by it
+    // doesn't have to load every target entry
+    let target_type_eav = match new_store.link_target_type(link.target()) {
+        Ok(Some(entry_type)) => Some(EntityAttributeValue::new(
+            link.target(),
+            &LINK_TARGET_TYPE_NAME.to_string(),
+            &HashString::from(entry_type.to_string()),
+        )),
+        _ => None,
+    };
+
+    // The add might be of a link that is live already, or one that a remove which arrived
+    // before it removed, so it only gets counted if it makes the link live
+    let was_live = new_store.is_link_live(link);
 
     let storage = new_store.meta_storage();
-    let result = storage.write().unwrap().add_eav(&eav);
+    let result = was_live.and_then(|was_live| {
+        {
+            let mut meta_storage = storage.write().unwrap();
+            for eav in link_eavs(link)
+                .iter()
+                .chain(link_add_eavs(operation).iter())
+                .chain(target_type_eav.iter())
+            {
+                meta_storage.add_eav(eav)?;
+            }
+        }
+        new_store.index_link_add(operation)?;
+        if !was_live && new_store.is_link_live(link)? {
+            new_store.count_added_link(link)?;
+        }
+        Ok(())
+    });
     new_store
         .actions_mut()
         .insert(action_wrapper.clone(), result.map(|_| link.base().clone()));
@@ -127,40 +214,206 @@ pub(crate) fn reduce_add_link(
 }
 
 //
-pub(crate) fn reduce_update_entry(
+pub(crate) fn reduce_remove_link(
+    _context: Arc<Context>,
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore> {
+    let action = action_wrapper.action();
+    let (operation, removed_add) = unwrap_to!(action => Action::RemoveLink);
+    let link = operation.link();
+
+    let mut new_store = (*old_store).clone();
+    // Its base and tag get counted again without it
+    let invalidated = new_store.invalidate_link_counts(link.target());
+
+    // The add it removes might not have arrived yet, the remove gets kept anyway
+    // so that the add doesn't make the link live once it does
+    let storage = new_store.meta_storage();
+    let result = invalidated.and_then(|_| {
+        {
+            let mut meta_storage = storage.write()?;
+            for eav in link_remove_eavs(operation, removed_add.as_ref()) {
+                meta_storage.add_eav(&eav)?;
+            }
+        }
+        new_store.index_link_remove(operation, removed_add.as_ref())
+    });
+    new_store
+        .actions_mut()
+        .insert(action_wrapper.clone(), result.map(|_| link.base().clone()));
+    Some(new_store)
+}
+
+//
+pub(crate) fn reduce_reject_entry(
+    _context: Arc<Context>,
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore> {
+    let action = action_wrapper.action();
+    let (address, reason) = unwrap_to!(action => Action::RejectEntry);
+
+    let mut new_store = (*old_store).clone();
+    let status_eav = create_crud_status_eav(address, CrudStatus::REJECTED);
+    let reason_eav = EntityAttributeValue::new(
+        address,
+        &REJECTION_REASON_NAME.to_string(),
+        &HashString::from(reason.to_string()),
+    );
+    let meta_storage = new_store.meta_storage();
+    let result = meta_storage
+        .write()
+        .unwrap()
+        .add_eav(&status_eav)
+        .and_then(|_| meta_storage.write().unwrap().add_eav(&reason_eav));
+    let result = result.and_then(|_| new_store.invalidate_link_counts(address));
+    new_store
+        .actions_mut()
+        .insert(action_wrapper.clone(), result.map(|_| address.clone()));
+    Some(new_store)
+}
+
+//
+pub(crate) fn reduce_hold_header(
+    _context: Arc<Context>,
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore> {
+    let action = action_wrapper.action();
+    let header = unwrap_to!(action => Action::HoldHeader);
+
+    let mut new_store = (*old_store).clone();
+    let content_storage = new_store.content_storage();
+    let meta_storage = new_store.meta_storage();
+    let header_eav = EntityAttributeValue::new(
+        header.entry_address(),
+        &HEADER_NAME.to_string(),
+        &header.address(),
+    );
+    let result = content_storage
+        .write()
+        .unwrap()
+        .add(header)
+        .and_then(|_| meta_storage.write().unwrap().add_eav(&header_eav));
+    new_store
+        .actions_mut()
+        .insert(action_wrapper.clone(), result.map(|_| header.address()));
+    Some(new_store)
+}
+
+//
+pub(crate) fn reduce_queue_validation(
     _context: Arc<Context>,
     old_store: &DhtStore,
     action_wrapper: &ActionWrapper,
+) -> Option<DhtStore> {
+    let action = action_wrapper.action();
+    let pending_validation = unwrap_to!(action => Action::QueueValidation);
+
+    let mut new_store = (*old_store).clone();
+    let content_storage = new_store.content_storage();
+    let meta_storage = new_store.meta_storage();
+    let pending_eav = EntityAttributeValue::new(
+        &pending_validation.entry_address(),
+        &PENDING_VALIDATION_NAME.to_string(),
+        &pending_validation.address(),
+    );
+    let result = content_storage
+        .write()
+        .unwrap()
+        .add(pending_validation)
+        .and_then(|_| meta_storage.write().unwrap().add_eav(&pending_eav));
+    new_store.actions_mut().insert(
+        action_wrapper.clone(),
+        result.map(|_| pending_validation.address()),
+    );
+    Some(new_store)
+}
+
+//
+pub(crate) fn reduce_dequeue_validation(
+    _context: Arc<Context>,
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
+) -> Option<DhtStore> {
+    let action = action_wrapper.action();
+    let pending_validation = unwrap_to!(action => Action::DequeueValidation);
+
+    let mut new_store = (*old_store).clone();
+    let dequeued_eav = EntityAttributeValue::new(
+        &pending_validation.entry_address(),
+        &DEQUEUED_VALIDATION_NAME.to_string(),
+        &pending_validation.address(),
+    );
+    let result = new_store
+        .meta_storage()
+        .write()
+        .unwrap()
+        .add_eav(&dequeued_eav);
+    new_store.actions_mut().insert(
+        action_wrapper.clone(),
+        result.map(|_| pending_validation.address()),
+    );
+    Some(new_store)
+}
+
+/// Whether the meta storage of the store holds the given EAV already
+fn holds_eav(store: &DhtStore, eav: &EntityAttributeValue) -> Result<bool, HolochainError> {
+    let meta_storage = store.meta_storage();
+    let eavs = meta_storage.read()?.fetch_eav(
+        Some(eav.entity()),
+        Some(eav.attribute()),
+        Some(eav.value()),
+    )?;
+    Ok(!eavs.is_empty())
+}
+
+//
+pub(crate) fn reduce_update_entry(
+    context: Arc<Context>,
+    old_store: &DhtStore,
+    action_wrapper: &ActionWrapper,
 ) -> Option<DhtStore> {
     // Setup
     let action = action_wrapper.action();
     let (old_address, new_address) = unwrap_to!(action => Action::UpdateEntry);
     let mut new_store = (*old_store).clone();
-    // Update crud-status
-    let latest_old_address = old_address;
-    let meta_storage = &new_store.meta_storage().clone();
-    let new_status_eav = create_crud_status_eav(latest_old_address, CrudStatus::MODIFIED);
-    let res = (*meta_storage.write().unwrap()).add_eav(&new_status_eav);
-    if let Err(err) = res {
-        new_store
-            .actions_mut()
-            .insert(action_wrapper.clone(), Err(err));
-        return Some(new_store);
+    // Act
+    let res = reduce_update_entry_inner(context, &mut new_store, old_address, new_address);
+    // Done
+    new_store.actions_mut().insert(action_wrapper.clone(), res);
+    Some(new_store)
+}
+
+/// Marks the old entry MODIFIED and links it to the new one.
+/// Applying the same update again changes nothing, updating a deleted entry fails.
+fn reduce_update_entry_inner(
+    _context: Arc<Context>,
+    new_store: &mut DhtStore,
+    latest_old_address: &Address,
+    new_address: &Address,
+) -> Result<Address, HolochainError> {
+    // pre-condition: A deleted entry stays deleted
+    let deleted_status_eav = create_crud_status_eav(latest_old_address, CrudStatus::DELETED);
+    if holds_eav(new_store, &deleted_status_eav)? {
+        return Err(HolochainError::ErrorGeneric(String::from(
+            "trying to update a deleted entry",
+        )));
     }
-    // Update crud-link
+    let new_status_eav = create_crud_status_eav(latest_old_address, CrudStatus::MODIFIED);
     let crud_link_eav = create_crud_link_eav(latest_old_address, new_address);
-    let res = (*meta_storage.write().unwrap()).add_eav(&crud_link_eav);
-    if let Err(err) = res {
-        new_store
-            .actions_mut()
-            .insert(action_wrapper.clone(), Err(err));
-        return Some(new_store);
+    if holds_eav(new_store, &new_status_eav)? && holds_eav(new_store, &crud_link_eav)? {
+        return Ok(new_address.clone());
     }
+    // Update crud-status
+    let meta_storage = &new_store.meta_storage().clone();
+    (*meta_storage.write().unwrap()).add_eav(&new_status_eav)?;
+    new_store.invalidate_link_counts(latest_old_address)?;
+    // Update crud-link
+    (*meta_storage.write().unwrap()).add_eav(&crud_link_eav)?;
     // Done
-    new_store
-        .actions_mut()
-        .insert(action_wrapper.clone(), res.map(|_| new_address.clone()));
-    Some(new_store)
+    Ok(new_address.clone())
 }
 
 pub(crate) fn reduce_remove_entry(
@@ -198,6 +451,12 @@ fn reduce_remove_entry_inner(
             "trying to remove a missing entry",
         )));
     }
+    // Removing it with the same deletion again changes nothing
+    let deleted_status_eav = create_crud_status_eav(latest_deleted_address, CrudStatus::DELETED);
+    let crud_link_eav = create_crud_link_eav(latest_deleted_address, deletion_address);
+    if holds_eav(new_store, &deleted_status_eav)? && holds_eav(new_store, &crud_link_eav)? {
+        return Ok(latest_deleted_address.clone());
+    }
     let json_entry = maybe_json_entry.unwrap();
     let entry = Entry::try_from(json_entry).expect("Stored content should be a valid entry.");
     // pre-condition: entry_type must not by sys type, since they cannot be deleted
@@ -231,14 +490,13 @@ fn reduce_remove_entry_inner(
         )));
     }
     // Update crud-status
-    let new_status_eav = create_crud_status_eav(latest_deleted_address, CrudStatus::DELETED);
     let meta_storage = &new_store.meta_storage().clone();
-    let res = (*meta_storage.write().unwrap()).add_eav(&new_status_eav);
+    let res = (*meta_storage.write().unwrap()).add_eav(&deleted_status_eav);
+    let res = res.and_then(|_| new_store.invalidate_link_counts(latest_deleted_address));
     if let Err(err) = res {
         return Err(err);
     }
     // Update crud-link
-    let crud_link_eav = create_crud_link_eav(latest_deleted_address, deletion_address);
     let res = (*meta_storage.write().unwrap()).add_eav(&crud_link_eav);
     //    if let Err(err) = res {
     //        return Err(err);
@@ -265,17 +523,29 @@ pub mod tests {
         action::{Action, ActionWrapper},
         dht::{
             dht_reducers::{reduce, reduce_hold_entry},
-            dht_store::DhtStore,
+            dht_store::{DhtStore, RejectionReason, LINK_TARGET_TYPE_NAME},
+            pending_validation::PendingValidation,
         },
         instance::tests::test_context,
+        network::entry_with_header::EntryWithHeader,
         state::test_store,
     };
     use holochain_core_types::{
-        cas::content::AddressableContent,
-        entry::{test_entry, test_sys_entry, Entry},
-        link::Link,
+        cas::content::{Address, AddressableContent},
+        chain_header::test_chain_header,
+        crud_status::{CrudStatus, STATUS_NAME},
+        eav::EntityAttributeValue,
+        entry::{
+            deletion_entry::DeletionEntry, test_entry, test_entry_b, test_entry_c, test_sys_entry,
+            Entry,
+        },
+        error::HolochainError,
+        hash::HashString,
+        link::{Link, LinkOperation},
+        time::test_iso_8601,
     };
     use std::{
+        collections::HashSet,
         convert::TryFrom,
         sync::{Arc, RwLock},
     };
@@ -293,7 +563,7 @@ pub mod tests {
         let new_dht_store = reduce_hold_entry(
             Arc::clone(&context),
             &store.dht(),
-            &ActionWrapper::new(Action::Hold(sys_entry.clone())),
+            &ActionWrapper::new(Action::Hold((sys_entry.clone(), 0))),
         )
         .expect("there should be a new store for committing a sys entry");
 
@@ -315,6 +585,84 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn reduce_reject_entry_test() {
+        let context = test_context("bob");
+        let store = test_store(context.clone());
+        let entry = test_entry();
+
+        let action = ActionWrapper::new(Action::RejectEntry((
+            entry.address(),
+            RejectionReason::MissingDependencies,
+        )));
+        let new_dht_store = reduce(Arc::clone(&context), store.dht(), &action);
+
+        assert_eq!(
+            new_dht_store.actions().get(&action),
+            Some(&Ok(entry.address()))
+        );
+        let status_eavs = new_dht_store
+            .meta_storage()
+            .read()
+            .unwrap()
+            .fetch_eav(Some(entry.address()), Some(STATUS_NAME.to_string()), None)
+            .unwrap();
+        assert!(status_eavs
+            .iter()
+            .any(|eav| CrudStatus::from(String::from(eav.value())) == CrudStatus::REJECTED));
+        assert_eq!(
+            new_dht_store.rejection_reason(&entry.address()),
+            Ok(Some(RejectionReason::MissingDependencies))
+        );
+    }
+
+    #[test]
+    fn reduce_queue_and_dequeue_validation_test() {
+        let context = test_context("bob");
+        let store = test_store(context.clone());
+        let pending_validation = PendingValidation::new(
+            EntryWithHeader::new(test_entry(), test_chain_header()),
+            vec![test_entry_b().address()],
+            test_iso_8601(),
+        );
+
+        let queue_action = ActionWrapper::new(Action::QueueValidation(pending_validation.clone()));
+        let new_dht_store = reduce(Arc::clone(&context), store.dht(), &queue_action);
+        assert_eq!(
+            new_dht_store.actions().get(&queue_action),
+            Some(&Ok(pending_validation.address()))
+        );
+        assert_eq!(
+            new_dht_store.pending_validations(),
+            Ok(vec![pending_validation.clone()])
+        );
+
+        let dequeue_action =
+            ActionWrapper::new(Action::DequeueValidation(pending_validation.clone()));
+        let new_dht_store = reduce(Arc::clone(&context), new_dht_store, &dequeue_action);
+        assert_eq!(new_dht_store.pending_validations(), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn reduce_hold_header_test() {
+        let context = test_context("bob");
+        let store = test_store(context.clone());
+        let header = test_chain_header();
+
+        let action = ActionWrapper::new(Action::HoldHeader(header.clone()));
+        let new_dht_store = reduce(Arc::clone(&context), store.dht(), &action);
+
+        assert_eq!(
+            new_dht_store.actions().get(&action),
+            Some(&Ok(header.address()))
+        );
+        let headers = new_dht_store
+            .get_headers(header.entry_address())
+            .expect("could not get headers");
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[0].address(), header.address());
+    }
+
     #[test]
     fn can_add_links() {
         let context = test_context("bob");
@@ -330,7 +678,8 @@ pub mod tests {
         let context = Arc::new(context);
 
         let link = Link::new(&entry.address(), &entry.address(), "test-tag");
-        let action = ActionWrapper::new(Action::AddLink(link.clone()));
+        let action =
+            ActionWrapper::new(Action::AddLink(LinkOperation::new(&link, test_iso_8601())));
 
         let new_dht_store: DhtStore;
         {
@@ -339,10 +688,11 @@ pub mod tests {
             new_dht_store = (*reduce(Arc::clone(&context), state.dht(), &action)).clone();
         }
         let storage = new_dht_store.meta_storage();
-        let fetched = storage
-            .read()
-            .unwrap()
-            .fetch_eav(Some(entry.address()), None, None);
+        let fetched = storage.read().unwrap().fetch_eav(
+            Some(entry.address()),
+            Some(format!("link__{}", link.tag())),
+            None,
+        );
 
         assert!(fetched.is_ok());
         let hash_set = fetched.unwrap();
@@ -351,6 +701,190 @@ pub mod tests {
         assert_eq!(eav.entity(), *link.base());
         assert_eq!(eav.value(), *link.target());
         assert_eq!(eav.attribute(), format!("link__{}", link.tag()));
+
+        // the type of the held target got stored with the link
+        let fetched = storage.read().unwrap().fetch_eav(
+            Some(link.target().clone()),
+            Some(LINK_TARGET_TYPE_NAME.to_string()),
+            None,
+        );
+        let hash_set = fetched.unwrap();
+        assert_eq!(hash_set.len(), 1);
+        assert_eq!(
+            hash_set.iter().nth(0).unwrap().value(),
+            HashString::from(entry.entry_type().to_string())
+        );
+    }
+
+    #[test]
+    fn can_remove_links_from_both_directions() {
+        let context = test_context("bob");
+        let store = test_store(context.clone());
+        let base = test_entry();
+        let target = test_entry_b();
+
+        let locked_state = Arc::new(RwLock::new(store));
+
+        let mut context = (*context).clone();
+        context.set_state(locked_state.clone());
+        let storage = context.file_storage.clone();
+        let _ = (storage.write().unwrap()).add(&base);
+        let context = Arc::new(context);
+
+        let link = Link::new(&base.address(), &target.address(), "test-tag");
+        let add_action =
+            ActionWrapper::new(Action::AddLink(LinkOperation::new(&link, test_iso_8601())));
+        let remove_action = ActionWrapper::new(Action::RemoveLink((
+            LinkOperation::new(&link, test_iso_8601()),
+            None,
+        )));
+
+        let dht_store = locked_state.read().unwrap().dht();
+        let dht_store = reduce(Arc::clone(&context), dht_store, &add_action);
+        let values = |links: HashSet<EntityAttributeValue>| {
+            links.iter().map(|eav| eav.value()).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            values(
+                dht_store
+                    .get_links(base.address(), "test-tag".into())
+                    .unwrap()
+            ),
+            vec![target.address()]
+        );
+        assert_eq!(
+            values(
+                dht_store
+                    .get_links_to(target.address(), "test-tag".into())
+                    .unwrap()
+            ),
+            vec![base.address()]
+        );
+
+        let dht_store = reduce(Arc::clone(&context), dht_store, &remove_action);
+        assert_eq!(
+            dht_store.actions().get(&remove_action),
+            Some(&Ok(base.address()))
+        );
+        assert!(dht_store
+            .get_links(base.address(), "test-tag".into())
+            .unwrap()
+            .is_empty());
+        assert!(dht_store
+            .get_links_to(target.address(), "test-tag".into())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn held_entries_and_their_meta_can_be_read() {
+        let context = test_context("bob");
+        let store = test_store(context.clone());
+        let base = test_entry();
+        let target = test_entry_b();
+        let header = test_chain_header();
+
+        let locked_state = Arc::new(RwLock::new(store));
+
+        let mut context = (*context).clone();
+        context.set_state(locked_state.clone());
+        let storage = context.file_storage.clone();
+        let _ = (storage.write().unwrap()).add(&base);
+        let context = Arc::new(context);
+
+        let link = Link::new(&base.address(), &target.address(), "test-tag");
+        let mut dht_store = locked_state.read().unwrap().dht();
+        for action in vec![
+            Action::Hold((base.clone(), 0)),
+            Action::Hold((target.clone(), 0)),
+            Action::HoldHeader(header.clone()),
+            Action::AddLink(LinkOperation::new(&link, test_iso_8601())),
+        ] {
+            dht_store = reduce(Arc::clone(&context), dht_store, &ActionWrapper::new(action));
+        }
+
+        let mut held = vec![base.address(), target.address()];
+        held.sort();
+        assert_eq!(dht_store.held_count(), Ok(2));
+        assert_eq!(dht_store.held_addresses(), Ok(held.clone()));
+        let held_entries = dht_store
+            .iter_held()
+            .unwrap()
+            .map(|entry| entry.map(|entry| entry.address()))
+            .collect::<Result<Vec<Address>, HolochainError>>();
+        assert_eq!(held_entries, Ok(held));
+
+        let meta = dht_store.get_meta(&base.address()).unwrap();
+        assert_eq!(meta.crud_status, CrudStatus::LIVE);
+        assert!(meta.crud_links.is_empty());
+        assert_eq!(meta.link_counts.get("test-tag"), Some(&1));
+        assert_eq!(&meta.sources, header.sources());
+
+        let update = ActionWrapper::new(Action::UpdateEntry((target.address(), base.address())));
+        let dht_store = reduce(Arc::clone(&context), dht_store, &update);
+        let meta = dht_store.get_meta(&target.address()).unwrap();
+        assert_eq!(meta.crud_status, CrudStatus::MODIFIED);
+        assert_eq!(meta.crud_links, vec![base.address()]);
+        assert!(meta.link_counts.is_empty());
+        assert!(meta.sources.is_empty());
+        assert_eq!(dht_store.held_count(), Ok(2));
+    }
+
+    #[test]
+    /// test that updates and removals can be applied again without changing anything,
+    /// and that a deleted entry can't get updated any more
+    fn updates_and_removals_are_idempotent_and_removals_final() {
+        let context = test_context("bob");
+        let store = test_store(context.clone());
+        let (original, update, next_update) = (test_entry(), test_entry_b(), test_entry_c());
+        let deletion = Entry::Deletion(DeletionEntry::new(update.address()));
+
+        let mut dht_store = store.dht();
+        for entry in vec![&original, &update] {
+            let hold = ActionWrapper::new(Action::Hold((entry.clone(), 0)));
+            dht_store = reduce(Arc::clone(&context), dht_store, &hold);
+        }
+
+        for _ in 0..2 {
+            let update_action =
+                ActionWrapper::new(Action::UpdateEntry((original.address(), update.address())));
+            dht_store = reduce(Arc::clone(&context), dht_store, &update_action);
+            assert_eq!(
+                dht_store.actions().get(&update_action),
+                Some(&Ok(update.address()))
+            );
+            let meta = dht_store.get_meta(&original.address()).unwrap();
+            assert_eq!(meta.crud_status, CrudStatus::MODIFIED);
+            assert_eq!(meta.crud_links, vec![update.address()]);
+        }
+
+        for _ in 0..2 {
+            let remove_action =
+                ActionWrapper::new(Action::RemoveEntry((update.address(), deletion.address())));
+            dht_store = reduce(Arc::clone(&context), dht_store, &remove_action);
+            assert_eq!(
+                dht_store.actions().get(&remove_action),
+                Some(&Ok(update.address()))
+            );
+            let meta = dht_store.get_meta(&update.address()).unwrap();
+            assert_eq!(meta.crud_status, CrudStatus::DELETED);
+            assert_eq!(meta.crud_links, vec![deletion.address()]);
+        }
+
+        let update_after_delete = ActionWrapper::new(Action::UpdateEntry((
+            update.address(),
+            next_update.address(),
+        )));
+        let dht_store = reduce(Arc::clone(&context), dht_store, &update_after_delete);
+        assert_eq!(
+            dht_store.actions().get(&update_after_delete),
+            Some(&Err(HolochainError::ErrorGeneric(String::from(
+                "trying to update a deleted entry"
+            ))))
+        );
+        let meta = dht_store.get_meta(&update.address()).unwrap();
+        assert_eq!(meta.crud_status, CrudStatus::DELETED);
+        assert_eq!(meta.crud_links, vec![deletion.address()]);
     }
 
     #[test]
@@ -366,7 +900,8 @@ pub mod tests {
         let context = Arc::new(context);
 
         let link = Link::new(&entry.address(), &entry.address(), "test-tag");
-        let action = ActionWrapper::new(Action::AddLink(link.clone()));
+        let action =
+            ActionWrapper::new(Action::AddLink(LinkOperation::new(&link, test_iso_8601())));
 
         let new_dht_store: DhtStore;
         {
@@ -395,7 +930,7 @@ pub mod tests {
         let store = test_store(context.clone());
 
         let entry = test_entry();
-        let action_wrapper = ActionWrapper::new(Action::Hold(entry.clone()));
+        let action_wrapper = ActionWrapper::new(Action::Hold((entry.clone(), 0)));
 
         store.reduce(context.clone(), action_wrapper);
 
@@ -409,5 +944,4 @@ pub mod tests {
 
         assert_eq!(&entry, &result_entry,);
     }
-
 }
@@ -1,15 +1,274 @@
-use crate::action::ActionWrapper;
+use crate::{action::ActionWrapper, dht::pending_validation::PendingValidation};
 use holochain_core_types::{
-    cas::{content::Address, storage::ContentAddressableStorage},
+    cas::{
+        content::{Address, AddressableContent},
+        storage::ContentAddressableStorage,
+    },
+    chain_header::ChainHeader,
+    crud_status::{CrudStatus, LINK_NAME, STATUS_NAME},
     eav::{EntityAttributeValue, EntityAttributeValueStorage},
+    entry::{entry_type::EntryType, Entry},
     error::HolochainError,
-    link::Link,
+    hash::HashString,
+    link::{link_add::LinkAdd, Link, LinkOperation},
+    time::Iso8601,
 };
+use multihash::Hash;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    convert::TryFrom,
+    fmt,
     sync::{Arc, RwLock},
+    vec,
 };
 
+/// EAV attribute that points from a held entry to the header it was published with
+pub const HEADER_NAME: &str = "entry-header";
+/// EAV attribute that records why a REJECTED entry was rejected
+pub const REJECTION_REASON_NAME: &str = "rejection-reason";
+/// EAV attribute that points from an entry to its validation that waits for dependencies
+pub const PENDING_VALIDATION_NAME: &str = "pending-validation";
+/// EAV attribute that marks a pending validation as done, since EAVs can't be removed
+pub const DEQUEUED_VALIDATION_NAME: &str = "dequeued-validation";
+/// EAV attribute that records the entry type of a link target, stored when the link gets added
+pub const LINK_TARGET_TYPE_NAME: &str = "link-target-type";
+/// EAV attribute that points from a source chain entry whose content got pruned to its header
+pub const PRUNED_NAME: &str = "pruned";
+/// EAV attribute that records since when an entry of a prunable type is known to pruning,
+/// as an ISO 8601 timestamp its retention counts from
+pub const PRUNABLE_SINCE_NAME: &str = "prunable-since";
+/// EAV attribute that records since when an entry of a type with a TTL is held by this node,
+/// in seconds since the Unix epoch, see `EntryTypeDef::ttl_seconds`
+pub const HELD_SINCE_NAME: &str = "held-since";
+/// Prefix of the EAV attributes of links, followed by the link's tag
+const LINK_ATTRIBUTE_PREFIX: &str = "link__";
+/// Prefix of the EAV attributes of the reverse index of links, followed by the link's tag.
+/// They point from the target of a link back to its base, see get_links_to().
+const LINKED_FROM_ATTRIBUTE_PREFIX: &str = "linked_from__";
+/// Prefix of the EAV attributes that marked links as removed before their adds and removes
+/// got recorded, followed by the link's tag. Such links stay removed until they get added again.
+const REMOVED_LINK_ATTRIBUTE_PREFIX: &str = "removed_link__";
+/// EAV attributes that point from the address of the LinkAdd entry of a link to the adds and
+/// removes of the link, see `link_add_eavs()` and `link_remove_eavs()`
+pub const LINK_ADD_NAME: &str = "link-add";
+pub const LINK_REMOVE_NAME: &str = "link-remove";
+/// EAV attribute that records when an add or remove of a link got made, as the ISO 8601
+/// timestamp of its header
+pub const LINK_OPERATION_AT_NAME: &str = "link-operation-at";
+/// EAV attribute that points from an add or remove of a link to the agents that published it
+pub const LINK_OPERATION_BY_NAME: &str = "link-operation-by";
+/// EAV attribute that points from a remove of a link to the header of the one add it removes.
+/// Removes without it remove all adds of the link made before them.
+pub const LINK_REMOVES_NAME: &str = "link-removes";
+
+/// The EAVs that hold a link: from its base to its target and back
+pub(crate) fn link_eavs(link: &Link) -> Vec<EntityAttributeValue> {
+    vec![
+        EntityAttributeValue::new(
+            link.base(),
+            &format!("{}{}", LINK_ATTRIBUTE_PREFIX, link.tag()),
+            link.target(),
+        ),
+        EntityAttributeValue::new(
+            link.target(),
+            &format!("{}{}", LINKED_FROM_ATTRIBUTE_PREFIX, link.tag()),
+            link.base(),
+        ),
+    ]
+}
+
+/// The address the adds and removes of a link get recorded at: the one of its LinkAdd entry
+fn link_address(link: &Link) -> Address {
+    Entry::LinkAdd(LinkAdd::from_link(link)).address()
+}
+
+/// The address of an add or remove of a link: the one of the header it got published with,
+/// or for operations without a header, a hash of what it did to which link when
+fn link_operation_address(attribute: &str, operation: &LinkOperation) -> Address {
+    operation.header().cloned().unwrap_or_else(|| {
+        HashString::encode_from_str(
+            &format!(
+                "{}:{}:{}",
+                attribute,
+                link_address(operation.link()),
+                operation.timestamp()
+            ),
+            Hash::SHA2256,
+        )
+    })
+}
+
+fn link_operation_eavs(attribute: &str, operation: &LinkOperation) -> Vec<EntityAttributeValue> {
+    let address = link_operation_address(attribute, operation);
+    let mut eavs = vec![
+        EntityAttributeValue::new(
+            &link_address(operation.link()),
+            &attribute.to_string(),
+            &address,
+        ),
+        EntityAttributeValue::new(
+            &address,
+            &LINK_OPERATION_AT_NAME.to_string(),
+            &HashString::from(operation.timestamp().to_string()),
+        ),
+    ];
+    eavs.extend(operation.provenance().iter().map(|agent| {
+        EntityAttributeValue::new(&address, &LINK_OPERATION_BY_NAME.to_string(), agent)
+    }));
+    eavs
+}
+
+/// The EAVs that record an add of a link, besides the link_eavs() that index it
+pub(crate) fn link_add_eavs(operation: &LinkOperation) -> Vec<EntityAttributeValue> {
+    link_operation_eavs(LINK_ADD_NAME, operation)
+}
+
+/// The EAVs that record a remove of a link, which removes the add with the given header,
+/// or all adds made before it
+pub(crate) fn link_remove_eavs(
+    operation: &LinkOperation,
+    removed_add: Option<&Address>,
+) -> Vec<EntityAttributeValue> {
+    let mut eavs = link_operation_eavs(LINK_REMOVE_NAME, operation);
+    if let Some(removed_add) = removed_add {
+        eavs.push(EntityAttributeValue::new(
+            &link_operation_address(LINK_REMOVE_NAME, operation),
+            &LINK_REMOVES_NAME.to_string(),
+            removed_add,
+        ));
+    }
+    eavs
+}
+
+/// Number of links per CRUD status of their targets
+pub type LinkCounts = HashMap<CrudStatus, usize>;
+
+/// What the adds and removes of a link that this node holds come down to,
+/// see DhtStore::is_link_live()
+#[derive(Clone, Debug, Default)]
+struct LinkOperations {
+    /// When each add got made, by its address
+    adds: HashMap<Address, Iso8601>,
+    /// The adds that removes named
+    removed_adds: HashSet<Address>,
+    /// When the latest of the removes that name no add got made
+    removed_before: Option<Iso8601>,
+    /// Whether the link got added before adds got recorded, and not removed since
+    indexed_without_adds: bool,
+}
+
+impl LinkOperations {
+    fn add(&mut self, address: Address, timestamp: Iso8601) {
+        self.adds.insert(address, timestamp);
+    }
+
+    fn remove(&mut self, timestamp: Iso8601, removed_add: Option<Address>) {
+        match removed_add {
+            Some(removed_add) => {
+                self.removed_adds.insert(removed_add);
+            }
+            None => {
+                if self
+                    .removed_before
+                    .as_ref()
+                    .map(|latest| timestamp > *latest)
+                    .unwrap_or(true)
+                {
+                    self.removed_before = Some(timestamp);
+                }
+            }
+        }
+        self.indexed_without_adds = false;
+    }
+
+    fn is_live(&self) -> bool {
+        if self.adds.is_empty() {
+            return self.indexed_without_adds;
+        }
+        self.adds.iter().any(|(add, made)| {
+            !self.removed_adds.contains(add)
+                && self
+                    .removed_before
+                    .as_ref()
+                    .map(|removed_before| made > removed_before)
+                    .unwrap_or(true)
+        })
+    }
+}
+
+/// Why a holding node rejected an entry
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RejectionReason {
+    /// The entry failed validation
+    Invalid,
+    /// The entries it depends on could not be fetched in time, so it could not be validated
+    MissingDependencies,
+    /// The entry is older than the TTL of its type, see `EntryTypeDef::ttl_seconds`
+    Expired,
+}
+
+impl RejectionReason {
+    fn from_value(value: &HashString) -> Option<Self> {
+        match String::from(value.clone()).as_ref() {
+            "invalid" => Some(RejectionReason::Invalid),
+            "missing-dependencies" => Some(RejectionReason::MissingDependencies),
+            "expired" => Some(RejectionReason::Expired),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RejectionReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RejectionReason::Invalid => write!(f, "invalid"),
+            RejectionReason::MissingDependencies => write!(f, "missing-dependencies"),
+            RejectionReason::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+/// What the local shard knows about a held entry, see DhtStore::get_meta()
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct EntryMetaSummary {
+    pub crud_status: CrudStatus,
+    /// The entries that replaced this one
+    pub crud_links: Vec<Address>,
+    /// Number of links from the entry per tag, as get_links() returns them
+    pub link_counts: BTreeMap<String, usize>,
+    /// The agents that published the entry, going by the headers it was published with
+    pub sources: Vec<Address>,
+}
+
+/// The entries held in the local shard, see DhtStore::iter_held().
+/// Fetches each entry only when it gets to it, taking a read lock on the content storage
+/// for just that fetch.
+pub struct HeldEntries {
+    addresses: vec::IntoIter<Address>,
+    content_storage: Arc<RwLock<ContentAddressableStorage>>,
+}
+
+impl Iterator for HeldEntries {
+    type Item = Result<Entry, HolochainError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let address = self.addresses.next()?;
+            let fetched = self
+                .content_storage
+                .read()
+                .map_err(HolochainError::from)
+                .and_then(|storage| storage.fetch(&address));
+            match fetched {
+                Ok(Some(content)) => return Some(Entry::try_from(content)),
+                // its content got pruned or collected since
+                Ok(None) => continue,
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
 /// The state-slice for the DHT.
 /// Holds the agent's local shard and interacts with the network module
 #[derive(Clone, Debug)]
@@ -18,6 +277,14 @@ pub struct DhtStore {
     content_storage: Arc<RwLock<ContentAddressableStorage>>,
     meta_storage: Arc<RwLock<EntityAttributeValueStorage>>,
 
+    // Counts of the links per base and tag, see link_counts().
+    // A cache of what the meta storage holds, so it is shared by all versions of the state.
+    link_counts: Arc<RwLock<HashMap<(Address, String), LinkCounts>>>,
+
+    // The adds and removes of links by the address of their LinkAdd entry,
+    // see is_link_live(). A cache of the meta storage like link_counts.
+    link_operations: Arc<RwLock<HashMap<Address, LinkOperations>>>,
+
     actions: HashMap<ActionWrapper, Result<Address, HolochainError>>,
 }
 
@@ -44,6 +311,8 @@ impl DhtStore {
         DhtStore {
             content_storage,
             meta_storage,
+            link_counts: Arc::new(RwLock::new(HashMap::new())),
+            link_operations: Arc::new(RwLock::new(HashMap::new())),
             actions: HashMap::new(),
         }
     }
@@ -59,14 +328,499 @@ impl DhtStore {
         // FIXME
     }
 
+    /// The links from the given base with the given tag, as EAVs from the base to the targets.
+    /// Only returns the links that are live, see is_link_live().
     pub fn get_links(
         &self,
         address: Address,
         tag: String,
     ) -> Result<HashSet<EntityAttributeValue>, HolochainError> {
-        self.meta_storage
+        let links = self
+            .indexed_links(&address, &tag, LINK_ATTRIBUTE_PREFIX, |eav| {
+                Link::new(&eav.entity(), &eav.value(), &tag)
+            })
+            .map_err(|error| {
+                error.with_context(format!(
+                    "fetching links of {} from the meta storage",
+                    address
+                ))
+            })?;
+        self.without_rejected(links, |eav| LinkAdd::new(&eav.entity(), &eav.value(), &tag))
+    }
+
+    /// The links to the given target with the given tag, as EAVs from the target to the bases.
+    /// The holder of the target holds them, like the holder of the base holds get_links().
+    pub fn get_links_to(
+        &self,
+        target: Address,
+        tag: String,
+    ) -> Result<HashSet<EntityAttributeValue>, HolochainError> {
+        let links = self
+            .indexed_links(&target, &tag, LINKED_FROM_ATTRIBUTE_PREFIX, |eav| {
+                Link::new(&eav.value(), &eav.entity(), &tag)
+            })
+            .map_err(|error| {
+                error.with_context(format!(
+                    "fetching links to {} from the meta storage",
+                    target
+                ))
+            })?;
+        self.without_rejected(links, |eav| LinkAdd::new(&eav.value(), &eav.entity(), &tag))
+    }
+
+    /// The EAVs of one of the link indexes whose links are live
+    fn indexed_links<F: Fn(&EntityAttributeValue) -> Link>(
+        &self,
+        address: &Address,
+        tag: &str,
+        attribute_prefix: &str,
+        link_of: F,
+    ) -> Result<HashSet<EntityAttributeValue>, HolochainError> {
+        let indexed = self.meta_storage.read()?.fetch_eav(
+            Some(address.clone()),
+            Some(format!("{}{}", attribute_prefix, tag)),
+            None,
+        )?;
+        let mut live_links = HashSet::new();
+        for eav in indexed {
+            if self.is_link_live(&link_of(&eav))? {
+                live_links.insert(eav);
+            }
+        }
+        Ok(live_links)
+    }
+
+    /// Whether one of the adds of the given link is not matched by any of its removes.
+    /// A remove matches the add it names by its header, or else all adds made before it,
+    /// also the ones that only arrive after it. So the same adds and removes give the
+    /// same links, whatever order they arrived in.
+    /// The operations of a link get read from the meta storage once, e.g. the first time
+    /// the link gets asked for after a restart, and from then on kept up to date by the
+    /// reducers that add and remove links.
+    pub fn is_link_live(&self, link: &Link) -> Result<bool, HolochainError> {
+        let address = link_address(link);
+        if let Some(operations) = self.link_operations.read()?.get(&address) {
+            return Ok(operations.is_live());
+        }
+        let operations = self.read_link_operations(link).map_err(|error| {
+            error.with_context(format!(
+                "reading the adds and removes of link {} from the meta storage",
+                address
+            ))
+        })?;
+        let is_live = operations.is_live();
+        self.link_operations.write()?.insert(address, operations);
+        Ok(is_live)
+    }
+
+    fn read_link_operations(&self, link: &Link) -> Result<LinkOperations, HolochainError> {
+        let storage = self.meta_storage.read()?;
+        let values_of =
+            |entity: &Address, attribute: &str| -> Result<Vec<Address>, HolochainError> {
+                Ok(storage
+                    .fetch_eav(Some(entity.clone()), Some(attribute.to_string()), None)?
+                    .iter()
+                    .map(|eav| eav.value())
+                    .collect())
+            };
+        let timestamp_of = |operation: &Address| -> Result<Iso8601, HolochainError> {
+            values_of(operation, LINK_OPERATION_AT_NAME)?
+                .iter()
+                .map(|value| Iso8601::from_str_lossy(&String::from(value.clone())))
+                .min()
+                .ok_or_else(|| {
+                    HolochainError::ErrorGeneric(format!(
+                        "the operation {} has no timestamp",
+                        operation
+                    ))
+                })
+        };
+
+        let address = link_address(link);
+        let mut operations = LinkOperations::default();
+        for add in values_of(&address, LINK_ADD_NAME)? {
+            let timestamp = timestamp_of(&add)?;
+            operations.add(add, timestamp);
+        }
+        if operations.adds.is_empty() {
+            // Links that got added before adds got recorded are only in the index,
+            // and got marked as removed there
+            let in_index = |attribute_prefix: &str| -> Result<bool, HolochainError> {
+                Ok(!storage
+                    .fetch_eav(
+                        Some(link.base().clone()),
+                        Some(format!("{}{}", attribute_prefix, link.tag())),
+                        Some(link.target().clone()),
+                    )?
+                    .is_empty())
+            };
+            operations.indexed_without_adds =
+                in_index(LINK_ATTRIBUTE_PREFIX)? && !in_index(REMOVED_LINK_ATTRIBUTE_PREFIX)?;
+        }
+        for remove in values_of(&address, LINK_REMOVE_NAME)? {
+            let timestamp = timestamp_of(&remove)?;
+            let named_adds = values_of(&remove, LINK_REMOVES_NAME)?;
+            if named_adds.is_empty() {
+                operations.remove(timestamp, None);
+            }
+            for named_add in named_adds {
+                operations.remove(timestamp.clone(), Some(named_add));
+            }
+        }
+        Ok(operations)
+    }
+
+    /// Keeps the operations of the link that is_link_live() read up to date with an add
+    /// that got stored. Links it did not read yet get read with the add.
+    pub(crate) fn index_link_add(&self, operation: &LinkOperation) -> Result<(), HolochainError> {
+        if let Some(operations) = self
+            .link_operations
+            .write()?
+            .get_mut(&link_address(operation.link()))
+        {
+            operations.add(
+                link_operation_address(LINK_ADD_NAME, operation),
+                operation.timestamp().clone(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Like index_link_add(), for a remove that got stored
+    pub(crate) fn index_link_remove(
+        &self,
+        operation: &LinkOperation,
+        removed_add: Option<&Address>,
+    ) -> Result<(), HolochainError> {
+        if let Some(operations) = self
+            .link_operations
+            .write()?
+            .get_mut(&link_address(operation.link()))
+        {
+            operations.remove(operation.timestamp().clone(), removed_add.cloned());
+        }
+        Ok(())
+    }
+
+    /// Links whose LinkAdd entry got rejected are not served anymore
+    fn without_rejected<F: Fn(&EntityAttributeValue) -> LinkAdd>(
+        &self,
+        links: HashSet<EntityAttributeValue>,
+        link_add_of: F,
+    ) -> Result<HashSet<EntityAttributeValue>, HolochainError> {
+        let mut valid_links = HashSet::new();
+        for eav in links {
+            let link_entry = Entry::LinkAdd(link_add_of(&eav));
+            if !self.is_rejected(&link_entry.address())? {
+                valid_links.insert(eav);
+            }
+        }
+        Ok(valid_links)
+    }
+
+    /// Counts the links get_links() returns, per CRUD status of their targets.
+    /// The EAV storage can't count without fetching, so the counts of a base and tag get
+    /// computed once, e.g. the first time they are asked for after a restart, and from then
+    /// on kept up to date by the reducers that add links and change CRUD statuses.
+    pub fn link_counts(&self, address: Address, tag: String) -> Result<LinkCounts, HolochainError> {
+        let key = (address, tag);
+        if let Some(counts) = self.link_counts.read()?.get(&key) {
+            return Ok(counts.clone());
+        }
+        let mut counts = LinkCounts::new();
+        for eav in self.get_links(key.0.clone(), key.1.clone())? {
+            *counts.entry(self.crud_status(&eav.value())?).or_insert(0) += 1;
+        }
+        self.link_counts.write()?.insert(key, counts.clone());
+        Ok(counts)
+    }
+
+    /// Counts a link that an add just made live, unless it got rejected
+    pub(crate) fn count_added_link(&self, link: &Link) -> Result<(), HolochainError> {
+        if self.is_rejected(&link_address(link))? {
+            return Ok(());
+        }
+        let status = self.crud_status(link.target())?;
+        let key = (link.base().clone(), link.tag().to_string());
+        if let Some(counts) = self.link_counts.write()?.get_mut(&key) {
+            *counts.entry(status).or_insert(0) += 1;
+        }
+        Ok(())
+    }
+
+    /// Drops the link counts that a change of the CRUD status of the given entry affects:
+    /// those of the links pointing to it and, for a LinkAdd entry, the one of its link.
+    /// They get computed again the next time they are asked for.
+    pub(crate) fn invalidate_link_counts(&self, address: &Address) -> Result<(), HolochainError> {
+        let mut keys: Vec<(Address, String)> = self
+            .meta_storage
+            .read()?
+            .fetch_eav(None, None, Some(address.clone()))?
+            .iter()
+            .filter(|eav| eav.attribute().starts_with(LINK_ATTRIBUTE_PREFIX))
+            .map(|eav| {
+                (
+                    eav.entity(),
+                    eav.attribute()[LINK_ATTRIBUTE_PREFIX.len()..].to_string(),
+                )
+            })
+            .collect();
+        if let Some(content) = self.content_storage.read()?.fetch(address)? {
+            if let Entry::LinkAdd(link_add) = Entry::try_from(content)? {
+                keys.push((
+                    link_add.link().base().clone(),
+                    link_add.link().tag().to_string(),
+                ));
+            }
+        }
+        let mut link_counts = self.link_counts.write()?;
+        for key in keys {
+            link_counts.remove(&key);
+        }
+        Ok(())
+    }
+
+    /// Like get_links() but only returns the links pointing to entries of the given type.
+    /// Also returns how many links got skipped because the type of their target is unknown.
+    pub fn get_links_of_type(
+        &self,
+        address: Address,
+        tag: String,
+        entry_type: &EntryType,
+    ) -> Result<(HashSet<EntityAttributeValue>, usize), HolochainError> {
+        self.links_of_type(self.get_links(address, tag)?, entry_type)
+    }
+
+    /// Keeps the links whose other end, the value of their EAV, has the given entry type.
+    /// Works for both get_links() and get_links_to() results and counts the skipped links
+    /// like get_links_of_type().
+    pub fn links_of_type(
+        &self,
+        links: HashSet<EntityAttributeValue>,
+        entry_type: &EntryType,
+    ) -> Result<(HashSet<EntityAttributeValue>, usize), HolochainError> {
+        let mut links_of_type = HashSet::new();
+        let mut skipped = 0;
+        for eav in links {
+            match self.link_target_type(&eav.value())? {
+                Some(ref target_type) if target_type == entry_type => {
+                    links_of_type.insert(eav);
+                }
+                Some(_) => (),
+                None => skipped += 1,
+            }
+        }
+        Ok((links_of_type, skipped))
+    }
+
+    /// Returns the entry type of a link target.
+    /// Uses the type stored when the link got added and falls back to the target entry
+    /// if this node holds it, so None means the target is unknown here.
+    pub fn link_target_type(&self, target: &Address) -> Result<Option<EntryType>, HolochainError> {
+        let stored_type = self
+            .meta_storage
             .read()?
-            .fetch_eav(Some(address), Some(format!("link__{}", tag)), None)
+            .fetch_eav(
+                Some(target.clone()),
+                Some(LINK_TARGET_TYPE_NAME.to_string()),
+                None,
+            )?
+            .iter()
+            .map(|eav| EntryType::from(String::from(eav.value())))
+            .next();
+        if stored_type.is_some() {
+            return Ok(stored_type);
+        }
+        match self.content_storage.read()?.fetch(target)? {
+            Some(content) => Ok(Some(Entry::try_from(content)?.entry_type())),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns true if the entry with the given address failed validation on this node
+    pub fn is_rejected(&self, address: &Address) -> Result<bool, HolochainError> {
+        Ok(self
+            .meta_storage
+            .read()?
+            .fetch_eav(Some(address.clone()), Some(STATUS_NAME.to_string()), None)?
+            .iter()
+            .any(|eav| CrudStatus::from(String::from(eav.value())) == CrudStatus::REJECTED))
+    }
+
+    /// Returns true if the content of the source chain entry with the given address got pruned
+    pub fn is_pruned(&self, address: &Address) -> Result<bool, HolochainError> {
+        Ok(!self
+            .meta_storage
+            .read()?
+            .fetch_eav(Some(address.clone()), Some(PRUNED_NAME.to_string()), None)?
+            .is_empty())
+    }
+
+    /// Returns true if the entry with the given address outlived the TTL of its type,
+    /// see `workflows::collect_garbage::mark_expired_entries()`
+    pub fn is_expired(&self, address: &Address) -> Result<bool, HolochainError> {
+        Ok(self
+            .meta_storage
+            .read()?
+            .fetch_eav(Some(address.clone()), Some(STATUS_NAME.to_string()), None)?
+            .iter()
+            .any(|eav| CrudStatus::from(String::from(eav.value())) == CrudStatus::EXPIRED))
+    }
+
+    /// Returns the CRUD status of the entry with the given address, going by life-cycle order:
+    /// REJECTED, EXPIRED, DELETED, MODIFIED, LIVE.
+    /// Entries this node knows no status of count as LIVE.
+    pub fn crud_status(&self, address: &Address) -> Result<CrudStatus, HolochainError> {
+        let statuses = self
+            .meta_storage
+            .read()
+            .map_err(HolochainError::from)
+            .and_then(|storage| {
+                storage.fetch_eav(Some(address.clone()), Some(STATUS_NAME.to_string()), None)
+            })
+            .map_err(|error| {
+                error.with_context(format!(
+                    "fetching CRUD status of {} from the meta storage",
+                    address
+                ))
+            })?
+            .iter()
+            .map(|eav| CrudStatus::from(String::from(eav.value())))
+            .collect::<Vec<CrudStatus>>();
+        Ok([
+            CrudStatus::REJECTED,
+            CrudStatus::EXPIRED,
+            CrudStatus::DELETED,
+            CrudStatus::MODIFIED,
+        ]
+        .iter()
+        .find(|status| statuses.contains(status))
+        .cloned()
+        .unwrap_or(CrudStatus::LIVE))
+    }
+
+    /// Returns why the entry with the given address got rejected, if it did
+    pub fn rejection_reason(
+        &self,
+        address: &Address,
+    ) -> Result<Option<RejectionReason>, HolochainError> {
+        Ok(self
+            .meta_storage
+            .read()?
+            .fetch_eav(
+                Some(address.clone()),
+                Some(REJECTION_REASON_NAME.to_string()),
+                None,
+            )?
+            .iter()
+            .filter_map(|eav| RejectionReason::from_value(&eav.value()))
+            .next())
+    }
+
+    /// Returns the validations that wait for the entries they depend on
+    pub fn pending_validations(&self) -> Result<Vec<PendingValidation>, HolochainError> {
+        let meta_storage = self.meta_storage.read()?;
+        let dequeued: HashSet<(Address, Address)> = meta_storage
+            .fetch_eav(None, Some(DEQUEUED_VALIDATION_NAME.to_string()), None)?
+            .iter()
+            .map(|eav| (eav.entity(), eav.value()))
+            .collect();
+        let content_storage = self.content_storage.read()?;
+        let mut pending_validations = Vec::new();
+        for eav in meta_storage.fetch_eav(None, Some(PENDING_VALIDATION_NAME.to_string()), None)? {
+            if dequeued.contains(&(eav.entity(), eav.value())) {
+                continue;
+            }
+            if let Some(content) = content_storage.fetch(&eav.value())? {
+                pending_validations.push(PendingValidation::try_from(content)?);
+            }
+        }
+        Ok(pending_validations)
+    }
+
+    /// The addresses of the entries held in the local shard, in order.
+    /// Every entry gets a CRUD status when it is held, so these are the entries with one.
+    pub fn held_addresses(&self) -> Result<Vec<Address>, HolochainError> {
+        let addresses: BTreeSet<Address> = self
+            .meta_storage
+            .read()?
+            .fetch_eav(None, Some(STATUS_NAME.to_string()), None)?
+            .iter()
+            .map(|eav| eav.entity())
+            .collect();
+        Ok(addresses.into_iter().collect())
+    }
+
+    /// How many entries the local shard holds
+    pub fn held_count(&self) -> Result<usize, HolochainError> {
+        Ok(self.held_addresses()?.len())
+    }
+
+    /// The entries held in the local shard, in the order of held_addresses().
+    /// Entries whose content is not stored anymore are skipped.
+    pub fn iter_held(&self) -> Result<HeldEntries, HolochainError> {
+        Ok(HeldEntries {
+            addresses: self.held_addresses()?.into_iter(),
+            content_storage: self.content_storage.clone(),
+        })
+    }
+
+    /// Summarizes what the local shard knows about the entry with the given address:
+    /// its CRUD status and links, how many links it has per tag and who published it
+    pub fn get_meta(&self, address: &Address) -> Result<EntryMetaSummary, HolochainError> {
+        let (crud_links, tags) = {
+            let meta_storage = self.meta_storage.read()?;
+            let crud_links: BTreeSet<Address> = meta_storage
+                .fetch_eav(Some(address.clone()), Some(LINK_NAME.to_string()), None)?
+                .iter()
+                .map(|eav| eav.value())
+                .collect();
+            let tags: BTreeSet<String> = meta_storage
+                .fetch_eav(Some(address.clone()), None, None)?
+                .iter()
+                .filter(|eav| eav.attribute().starts_with(LINK_ATTRIBUTE_PREFIX))
+                .map(|eav| eav.attribute()[LINK_ATTRIBUTE_PREFIX.len()..].to_string())
+                .collect();
+            (crud_links, tags)
+        };
+        let mut link_counts = BTreeMap::new();
+        for tag in tags {
+            let count = self.get_links(address.clone(), tag.clone())?.len();
+            if count > 0 {
+                link_counts.insert(tag, count);
+            }
+        }
+        let mut sources = Vec::new();
+        for header in self.get_headers(address)? {
+            for source in header.sources() {
+                if !sources.contains(source) {
+                    sources.push(source.clone());
+                }
+            }
+        }
+        Ok(EntryMetaSummary {
+            crud_status: self.crud_status(address)?,
+            crud_links: crud_links.into_iter().collect(),
+            link_counts,
+            sources,
+        })
+    }
+
+    /// Returns the headers the entry with the given address was published with
+    pub fn get_headers(&self, entry_address: &Address) -> Result<Vec<ChainHeader>, HolochainError> {
+        let header_addresses = self.meta_storage.read()?.fetch_eav(
+            Some(entry_address.clone()),
+            Some(HEADER_NAME.to_string()),
+            None,
+        )?;
+        let content_storage = self.content_storage.read()?;
+        let mut headers = Vec::new();
+        for eav in header_addresses {
+            if let Some(content) = content_storage.fetch(&eav.value())? {
+                headers.push(ChainHeader::try_from(content)?);
+            }
+        }
+        Ok(headers)
     }
 
     // Getters (for reducers)
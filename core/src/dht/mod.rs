@@ -3,3 +3,4 @@
 pub mod actions;
 pub mod dht_reducers;
 pub mod dht_store;
+pub mod pending_validation;
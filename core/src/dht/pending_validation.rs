@@ -0,0 +1,70 @@
+use crate::network::entry_with_header::EntryWithHeader;
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent, Content},
+    error::HolochainError,
+    json::JsonString,
+    time::Iso8601,
+};
+use std::convert::TryFrom;
+
+/// The validation of an entry we were asked to hold, parked in the local DHT shard
+/// until the entries it depends on are available.
+/// If they don't show up before the deadline, the entry gets rejected.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, DefaultJson)]
+pub struct PendingValidation {
+    pub entry_with_header: EntryWithHeader,
+    pub dependencies: Vec<Address>,
+    pub deadline: Iso8601,
+}
+
+impl PendingValidation {
+    pub fn new(
+        entry_with_header: EntryWithHeader,
+        dependencies: Vec<Address>,
+        deadline: Iso8601,
+    ) -> Self {
+        PendingValidation {
+            entry_with_header,
+            dependencies,
+            deadline,
+        }
+    }
+
+    /// Address of the entry waiting to be validated
+    pub fn entry_address(&self) -> Address {
+        self.entry_with_header.entry_body.address()
+    }
+}
+
+/// A validation that waits for its dependencies, without the entry and its header,
+/// as listed when looking for what an instance is waiting on
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct WaitingValidation {
+    pub entry_address: Address,
+    pub dependencies: Vec<Address>,
+    pub deadline: Iso8601,
+}
+
+impl From<PendingValidation> for WaitingValidation {
+    fn from(pending_validation: PendingValidation) -> Self {
+        WaitingValidation {
+            entry_address: pending_validation
+                .entry_with_header
+                .header
+                .entry_address()
+                .clone(),
+            dependencies: pending_validation.dependencies,
+            deadline: pending_validation.deadline,
+        }
+    }
+}
+
+impl AddressableContent for PendingValidation {
+    fn content(&self) -> Content {
+        self.to_owned().into()
+    }
+
+    fn try_from_content(content: &Content) -> Result<Self, HolochainError> {
+        Self::try_from(content.to_owned())
+    }
+}
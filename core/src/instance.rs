@@ -1,15 +1,90 @@
-use crate::{action::ActionWrapper, context::Context, state::State};
+use crate::{
+    action::{Action, ActionWrapper},
+    context::{panic_message, Context},
+    state::State,
+};
+use failure::Backtrace;
 use std::{
+    cell::{Cell, RefCell},
+    mem,
+    panic::{self, AssertUnwindSafe},
     sync::{
-        mpsc::{sync_channel, Receiver, SyncSender},
-        Arc, RwLock, RwLockReadGuard,
+        mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender},
+        Arc, Once, RwLock, RwLockReadGuard,
     },
-    thread,
-    time::Duration,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 pub const RECV_DEFAULT_TIMEOUT_MS: Duration = Duration::from_millis(10000);
 
+/// How often the action loop prunes the results of zome calls, as long as there are any
+pub const ZOME_CALL_PRUNE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the action loop tells that it is alive, see `Context::add_heartbeat_watcher()`
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What the action loop of an instance sends to whoever watches it, e.g. a container
+#[derive(Clone, Debug, PartialEq)]
+pub enum Heartbeat {
+    /// The loop still processes actions, sent every HEARTBEAT_INTERVAL
+    Alive,
+    /// The loop panicked and stopped, the last heartbeat it sends
+    Crashed(CrashReport),
+}
+
+/// Why the action loop of an instance stopped processing actions
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CrashReport {
+    /// What the loop panicked with, or why whoever watches it takes it for dead
+    pub message: String,
+    /// Where the loop panicked, if backtraces are enabled with RUST_BACKTRACE
+    pub backtrace: Option<String>,
+    /// Seconds since the UNIX epoch
+    pub crashed_at: u64,
+}
+
+impl CrashReport {
+    /// A report of a crash that happens now
+    pub fn new(message: String, backtrace: Option<String>) -> Self {
+        CrashReport {
+            message,
+            backtrace,
+            crashed_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|since_epoch| since_epoch.as_secs())
+                .unwrap_or(0),
+        }
+    }
+}
+
+thread_local! {
+    /// Whether panics on this thread record their backtrace, see `record_backtraces()`
+    static RECORDS_BACKTRACES: Cell<bool> = Cell::new(false);
+    /// The backtrace of the last panic on this thread, if it got recorded
+    static PANIC_BACKTRACE: RefCell<Option<String>> = RefCell::new(None);
+}
+
+static PANIC_HOOK: Once = Once::new();
+
+/// Makes panics on the current thread record their backtrace for the crash report,
+/// on top of whatever the panic hook that was set before does
+fn record_backtraces() {
+    PANIC_HOOK.call_once(|| {
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if RECORDS_BACKTRACES.try_with(Cell::get).unwrap_or(false) {
+                // empty unless backtraces are enabled
+                let backtrace = Backtrace::new().to_string();
+                let _ = PANIC_BACKTRACE.try_with(|recorded| {
+                    *recorded.borrow_mut() = Some(backtrace).filter(|trace| !trace.is_empty())
+                });
+            }
+            previous_hook(info);
+        }));
+    });
+    RECORDS_BACKTRACES.with(|records| records.set(true));
+}
+
 /// Object representing a Holochain instance, i.e. a running holochain (DNA + DHT + source-chain)
 /// Holds the Event loop and processes it with the redux pattern.
 #[derive(Clone)]
@@ -99,22 +174,70 @@ impl Instance {
         Arc::new(sub_context)
     }
 
-    /// Start the Event Loop on a seperate thread
+    /// Start the Event Loop on a loop worker of the context's runtime, see Context::spawn_loop().
+    /// Every ZOME_CALL_PRUNE_INTERVAL, it also processes a PruneZomeCalls action
+    /// if the state holds results of zome calls.
+    /// The loop sends a heartbeat every HEARTBEAT_INTERVAL. If processing an action panics,
+    /// it reports the crash to the context and as its last heartbeat and stops, which
+    /// disconnects the channels of the instance, so callers waiting on it get an error.
+    /// A loop that got reported as crashed by whoever watches it, e.g. because it hung,
+    /// stops as well once it gets to its next action.
     pub fn start_action_loop(&mut self, context: Arc<Context>) {
         let (rx_action, rx_observer) = self.initialize_channels();
 
         let sync_self = self.clone();
         let sub_context = self.initialize_context(context);
 
-        thread::spawn(move || {
+        sub_context.clone().spawn_loop(move || {
+            record_backtraces();
             let mut state_observers: Vec<Observer> = Vec::new();
-            for action_wrapper in rx_action {
-                state_observers = sync_self.process_action(
-                    action_wrapper,
-                    state_observers,
-                    &rx_observer,
-                    &sub_context,
-                );
+            let mut last_prune = Instant::now();
+            let mut last_heartbeat = Instant::now();
+            sub_context.send_heartbeat(Heartbeat::Alive);
+            loop {
+                let received = rx_action.recv_timeout(ZOME_CALL_PRUNE_INTERVAL);
+                if let Err(RecvTimeoutError::Disconnected) = received {
+                    break;
+                }
+                if sub_context.crash_report().is_some() {
+                    break;
+                }
+                let processed = panic::catch_unwind(AssertUnwindSafe(|| {
+                    if let Ok(action_wrapper) = received {
+                        let observers = mem::replace(&mut state_observers, Vec::new());
+                        state_observers = sync_self.process_action(
+                            action_wrapper,
+                            observers,
+                            &rx_observer,
+                            &sub_context,
+                        );
+                    }
+                    if last_prune.elapsed() >= ZOME_CALL_PRUNE_INTERVAL {
+                        last_prune = Instant::now();
+                        let retained = sync_self.state().nucleus().zome_call_stats().retained;
+                        if retained > 0 {
+                            let observers = mem::replace(&mut state_observers, Vec::new());
+                            state_observers = sync_self.process_action(
+                                ActionWrapper::new(Action::PruneZomeCalls),
+                                observers,
+                                &rx_observer,
+                                &sub_context,
+                            );
+                        }
+                    }
+                }));
+                if let Err(payload) = processed {
+                    let backtrace = PANIC_BACKTRACE.with(|recorded| recorded.borrow_mut().take());
+                    let crash = CrashReport::new(panic_message(&payload), backtrace);
+                    sub_context.log(format!("Action loop crashed: {}", crash.message));
+                    sub_context.report_crash(crash.clone());
+                    sub_context.send_heartbeat(Heartbeat::Crashed(crash));
+                    break;
+                }
+                if last_heartbeat.elapsed() >= HEARTBEAT_INTERVAL {
+                    last_heartbeat = Instant::now();
+                    sub_context.send_heartbeat(Heartbeat::Alive);
+                }
             }
         });
     }
@@ -128,6 +251,9 @@ impl Instance {
         rx_observer: &Receiver<Observer>,
         context: &Arc<Context>,
     ) -> Vec<Observer> {
+        context.log_action(&action_wrapper);
+        context.record_action(&action_wrapper);
+
         // Mutate state
         {
             let new_state: State;
@@ -300,6 +426,7 @@ pub mod tests {
         entry::{entry_type::EntryType, test_entry},
         json::{JsonString, RawString},
     };
+    use holochain_wasm_utils::api_serialization::CommitOptions;
 
     use crate::{
         logger::Logger,
@@ -310,6 +437,8 @@ pub mod tests {
         },
         persister::SimplePersister,
         state::State,
+        test_fixtures::test_agent,
+        workflows::register_agent::publish_agent_id_link,
     };
 
     use std::{
@@ -379,6 +508,18 @@ pub mod tests {
         context
     }
 
+    /// create a test context with the identity and keys of `test_agent(seed)`,
+    /// so that the agent signs its chain headers unlike fake agents
+    #[cfg_attr(tarpaulin, skip)]
+    pub fn test_context_of_test_agent(seed: &str) -> Arc<Context> {
+        let test_agent = test_agent(seed);
+        let mut context = (*test_context(seed)).clone();
+        context.agent_id = test_agent.agent_id;
+        context.set_encryption_keys(test_agent.keys);
+        context.set_signing_keys(test_agent.signing_keys);
+        Arc::new(context)
+    }
+
     /// create a test context
     #[cfg_attr(tarpaulin, skip)]
     pub fn test_context_with_channels(
@@ -478,16 +619,25 @@ pub mod tests {
     pub fn test_instance_and_context_by_name(
         dna: Dna,
         name: &str,
+    ) -> Result<(Instance, Arc<Context>), String> {
+        test_instance_with_context(dna, test_context(name))
+    }
+
+    /// create a test instance with the given context, e.g. one with other timeouts
+    #[cfg_attr(tarpaulin, skip)]
+    pub fn test_instance_with_context(
+        dna: Dna,
+        context: Arc<Context>,
     ) -> Result<(Instance, Arc<Context>), String> {
         // Create instance and plug in our DNA
-        let context = test_context(name);
         let mut instance = Instance::new(context.clone());
         instance.start_action_loop(context.clone());
         let context = instance.initialize_context(context);
         block_on(
             async {
                 await!(initialize_application(dna.clone(), &context))?;
-                await!(initialize_network(&context))
+                await!(initialize_network(&context))?;
+                await!(publish_agent_id_link(&context))
             },
         )?;
 
@@ -521,7 +671,7 @@ pub mod tests {
             .history
             .iter()
             .find(|aw| match aw.action() {
-                Action::Commit((entry, _)) => {
+                Action::Commit((entry, _, _)) => {
                     assert!(
                         entry.entry_type() == EntryType::AgentId
                             || entry.entry_type() == EntryType::Dna
@@ -604,10 +754,12 @@ pub mod tests {
             .get(&action_wrapper)
             .expect("action and reponse should be added after Get action dispatch");
 
-        assert_eq!(
-            response,
-            &ActionResponse::Commit(Ok(test_entry().address()))
-        );
+        match response {
+            ActionResponse::Commit(Ok(commit_result)) => {
+                assert_eq!(commit_result.address, test_entry().address())
+            }
+            _ => panic!("expected a successful commit response, got {:?}", response),
+        }
     }
 
     #[test]
@@ -646,6 +798,37 @@ pub mod tests {
         assert_eq!(dna, stored_dna);
     }
 
+    #[test]
+    /// tests that an action loop that panics tells whoever watches it why,
+    /// and that callers waiting on it get an error instead of hanging
+    fn action_loop_reports_its_crash() {
+        let context = test_context("jane");
+        let (heartbeat_sender, heartbeats) = sync_channel(10);
+        context.add_heartbeat_watcher(heartbeat_sender);
+        let mut instance = Instance::new(test_context("jason"));
+        instance.start_action_loop(context.clone());
+        assert_eq!(
+            heartbeats.recv_timeout(Duration::from_secs(5)),
+            Ok(Heartbeat::Alive)
+        );
+
+        let (sender, receiver) = sync_channel::<()>(1);
+        instance.dispatch_with_observer(
+            ActionWrapper::new(Action::Crash("state got corrupted".to_string())),
+            move |_state: &State| sender.send(()).is_err(),
+        );
+        let crash = loop {
+            match heartbeats.recv_timeout(Duration::from_secs(5)).unwrap() {
+                Heartbeat::Alive => continue,
+                Heartbeat::Crashed(crash) => break crash,
+            }
+        };
+        assert_eq!(crash.message, "state got corrupted");
+        assert_eq!(context.crash_report(), Some(crash));
+        // the observer got dropped with the loop
+        assert!(receiver.recv().is_err());
+    }
+
     #[test]
     /// tests that we can dispatch an action and block until it completes
     fn can_dispatch_and_wait() {
@@ -759,7 +942,11 @@ pub mod tests {
         let context = test_context("alex");
         let dna = test_utils::create_test_dna_with_wat("test_zome", "test_cap", None);
         let dna_entry = Entry::Dna(dna);
-        let commit_action = ActionWrapper::new(Action::Commit((dna_entry.clone(), None)));
+        let commit_action = ActionWrapper::new(Action::Commit((
+            dna_entry.clone(),
+            None,
+            CommitOptions::default(),
+        )));
 
         // Set up instance and process the action
         let instance = Instance::new(test_context("jason"));
@@ -775,7 +962,7 @@ pub mod tests {
             .history
             .iter()
             .find(|aw| match aw.action() {
-                Action::Commit((entry, _)) => {
+                Action::Commit((entry, _, _)) => {
                     assert_eq!(entry.entry_type(), EntryType::Dna);
                     assert_eq!(entry.content(), dna_entry.content());
                     true
@@ -790,7 +977,11 @@ pub mod tests {
         // Create Context, Agent and Commit AgentIdEntry Action
         let context = test_context("alex");
         let agent_entry = Entry::AgentId(context.agent_id.clone());
-        let commit_agent_action = ActionWrapper::new(Action::Commit((agent_entry.clone(), None)));
+        let commit_agent_action = ActionWrapper::new(Action::Commit((
+            agent_entry.clone(),
+            None,
+            CommitOptions::default(),
+        )));
 
         // Set up instance and process the action
         let instance = Instance::new(test_context("jason"));
@@ -806,7 +997,7 @@ pub mod tests {
             .history
             .iter()
             .find(|aw| match aw.action() {
-                Action::Commit((entry, _)) => {
+                Action::Commit((entry, _, _)) => {
                     assert_eq!(entry.entry_type(), EntryType::AgentId);
                     assert_eq!(entry.content(), agent_entry.content());
                     true
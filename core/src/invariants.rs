@@ -0,0 +1,596 @@
+//! Checks of what has to hold for the state of an instance after every reduce.
+//! Corrupted state, like an answered request that waits again or a top chain header that is
+//! not in the storage, otherwise only shows much later as behavior that is hard to trace back.
+//! State::reduce() runs the checks when core is compiled with debug_assertions or with the
+//! `invariant-checks` feature, see enforce().
+
+use crate::{
+    action::{Action, ActionWrapper},
+    agent::state::AgentState,
+    context::Context,
+    dht::dht_store::DhtStore,
+    network::state::{NetworkState, PendingRequests},
+    nucleus::state::NucleusState,
+    state::State,
+};
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent},
+    chain_header::ChainHeader,
+    crud_status::{CrudStatus, LINK_NAME, STATUS_NAME},
+    error::HolochainError,
+};
+use std::{fmt, sync::Arc};
+
+pub const AGENT_SLICE: &str = "agent";
+pub const NUCLEUS_SLICE: &str = "nucleus";
+pub const NETWORK_SLICE: &str = "network";
+pub const DHT_SLICE: &str = "dht";
+
+/// An invariant the state slice with the given name does not hold
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvariantViolation {
+    pub slice: &'static str,
+    pub description: String,
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.slice, self.description)
+    }
+}
+
+pub type InvariantResult = Result<(), InvariantViolation>;
+
+fn violation(slice: &'static str, description: String) -> InvariantResult {
+    Err(InvariantViolation { slice, description })
+}
+
+/// The top header of the chain is in the storage of the chain, and so is the header it links
+/// to, which it follows in sequence
+pub fn check_agent(agent: &AgentState) -> InvariantResult {
+    let top = match agent.top_chain_header() {
+        Some(top) => top,
+        None => return Ok(()),
+    };
+    let storage = agent.chain().content_storage();
+    let fetch = |address: &Address| -> Result<Option<ChainHeader>, HolochainError> {
+        match storage.read()?.fetch(address)? {
+            Some(content) => Ok(Some(ChainHeader::try_from_content(&content)?)),
+            None => Ok(None),
+        }
+    };
+    match fetch(&top.address()) {
+        Ok(Some(_)) => (),
+        Ok(None) => {
+            return violation(
+                AGENT_SLICE,
+                format!(
+                    "the top chain header {} is not in the storage",
+                    top.address()
+                ),
+            )
+        }
+        Err(error) => {
+            return violation(
+                AGENT_SLICE,
+                format!(
+                    "the top chain header {} can't be read from the storage: {}",
+                    top.address(),
+                    error
+                ),
+            )
+        }
+    }
+    let link = match top.link() {
+        Some(link) => link,
+        None => return Ok(()),
+    };
+    match fetch(&link) {
+        Ok(Some(ref previous)) if top.follows_in_sequence(Some(previous)) => Ok(()),
+        Ok(Some(previous)) => violation(
+            AGENT_SLICE,
+            format!(
+                "the top chain header {} has the sequence {}, but links to {} with the sequence {}",
+                top.address(),
+                top.sequence(),
+                link,
+                previous.sequence()
+            ),
+        ),
+        Ok(None) => violation(
+            AGENT_SLICE,
+            format!(
+                "the top chain header {} links to {}, which is not in the storage",
+                top.address(),
+                link
+            ),
+        ),
+        Err(error) => violation(
+            AGENT_SLICE,
+            format!(
+                "the header {} the top chain header links to can't be read from the storage: {}",
+                link, error
+            ),
+        ),
+    }
+}
+
+/// Every zome call the nucleus keeps, running or with its result, is tracked with the time it
+/// started, so that it gets pruned, and nothing is tracked or kept of calls it does not keep
+pub fn check_nucleus(nucleus: &NucleusState) -> InvariantResult {
+    for zome_call in nucleus.zome_calls.keys() {
+        if !nucleus.zome_call_times.contains_key(zome_call) {
+            return violation(
+                NUCLEUS_SLICE,
+                format!(
+                    "the zome call {} of {}/{} does not get pruned, it has no start time",
+                    zome_call.id(),
+                    zome_call.zome_name,
+                    zome_call.fn_name
+                ),
+            );
+        }
+    }
+    for zome_call in nucleus.zome_call_times.keys() {
+        if !nucleus.zome_calls.contains_key(zome_call) {
+            return violation(
+                NUCLEUS_SLICE,
+                format!(
+                    "the zome call {} of {}/{} has a start time, but is neither running nor has a result",
+                    zome_call.id(),
+                    zome_call.zome_name,
+                    zome_call.fn_name
+                ),
+            );
+        }
+    }
+    for zome_call in nucleus.zome_call_memory.keys() {
+        if !nucleus.zome_calls.contains_key(zome_call) {
+            return violation(
+                NUCLEUS_SLICE,
+                format!(
+                    "the memory use of the zome call {} is kept without the call",
+                    zome_call.id()
+                ),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Request IDs are unique over all kinds of requests, and the sources to ask for an entry and
+/// the recipients of direct messages are only kept while their request is
+pub fn check_network(network: &NetworkState) -> InvariantResult {
+    for id in network.get_entry_with_meta_results.ids() {
+        if network.get_validation_package_results.get(id).is_some() {
+            return violation(
+                NETWORK_SLICE,
+                format!(
+                    "the request ID {} is pending both as a GET and as a validation package request",
+                    id
+                ),
+            );
+        }
+    }
+    for id in network.get_entry_sources.keys() {
+        if network.get_entry_with_meta_results.get(id).is_none() {
+            return violation(
+                NETWORK_SLICE,
+                format!("the sources of GET {} are kept without the request", id),
+            );
+        }
+    }
+    for id in network.direct_message_recipients.keys() {
+        if !network.direct_message_connections.contains_key(id) {
+            return violation(
+                NETWORK_SLICE,
+                format!(
+                    "the recipient of direct message {} is kept without the message",
+                    id
+                ),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Requests that got answered keep an answer until they get removed. Later answers may
+/// replace it, but nothing makes the request wait for one again.
+pub fn check_network_transition(old: &NetworkState, new: &NetworkState) -> InvariantResult {
+    check_answers_kept(
+        "GET",
+        &old.get_entry_with_meta_results,
+        &new.get_entry_with_meta_results,
+    )?;
+    check_answers_kept(
+        "validation package request",
+        &old.get_validation_package_results,
+        &new.get_validation_package_results,
+    )
+}
+
+fn check_answers_kept<T: Clone>(
+    kind: &str,
+    old: &PendingRequests<T>,
+    new: &PendingRequests<T>,
+) -> InvariantResult {
+    for id in old.ids() {
+        match old.get(id).and_then(|request| request.result.as_ref()) {
+            Some(Ok(_)) => (),
+            _ => continue,
+        }
+        if let Some(request) = new.get(id) {
+            if request.result.is_none() {
+                return violation(
+                    NETWORK_SLICE,
+                    format!(
+                        "{} {} waits for an answer again after it was answered",
+                        kind, id
+                    ),
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// An entry only gets DELETED or MODIFIED with a crud-link to the deletion or the newer
+/// version that replaced it. A LIVE entry that got both deleted and modified, by deletions
+/// and updates that met on the network, has a crud-link for each.
+pub fn check_dht_meta(dht: &DhtStore, address: &Address) -> InvariantResult {
+    let meta_storage = dht.meta_storage();
+    let fetch = |attribute: &str| {
+        meta_storage
+            .read()
+            .map_err(HolochainError::from)
+            .and_then(|storage| {
+                storage.fetch_eav(Some(address.clone()), Some(attribute.to_string()), None)
+            })
+    };
+    let statuses: Vec<CrudStatus> = match fetch(STATUS_NAME) {
+        Ok(eavs) => eavs
+            .iter()
+            .map(|eav| CrudStatus::from(String::from(eav.value())))
+            .collect(),
+        Err(error) => {
+            return violation(
+                DHT_SLICE,
+                format!("the CRUD status of {} can't be read: {}", address, error),
+            )
+        }
+    };
+    let replaced_by: Vec<&str> = [
+        (CrudStatus::DELETED, "DELETED"),
+        (CrudStatus::MODIFIED, "MODIFIED"),
+    ]
+    .iter()
+    .filter(|(status, _)| statuses.contains(status))
+    .map(|(_, name)| *name)
+    .collect();
+    if replaced_by.is_empty() {
+        return Ok(());
+    }
+    match fetch(LINK_NAME) {
+        Ok(ref eavs) if eavs.len() >= replaced_by.len() => Ok(()),
+        Ok(ref eavs) if eavs.is_empty() => violation(
+            DHT_SLICE,
+            format!(
+                "the entry {} is {}, but has no crud-link to what replaced it",
+                address,
+                replaced_by.join(" and ")
+            ),
+        ),
+        Ok(_) => violation(
+            DHT_SLICE,
+            format!(
+                "the entry {} is {}, but has only one crud-link to what replaced it",
+                address,
+                replaced_by.join(" and ")
+            ),
+        ),
+        Err(error) => violation(
+            DHT_SLICE,
+            format!("the crud-link of {} can't be read: {}", address, error),
+        ),
+    }
+}
+
+/// The invariants the state a reduce of the given action made out of the old state violates.
+/// Slices the reduce left as they were got checked when they last changed. Of the DHT meta,
+/// only the entries the action refers to get checked, instead of everything the shard holds.
+pub fn check_state(
+    old_state: &State,
+    new_state: &State,
+    action: &Action,
+) -> Vec<InvariantViolation> {
+    let mut results = Vec::new();
+    if !Arc::ptr_eq(&old_state.agent(), &new_state.agent()) {
+        results.push(check_agent(&new_state.agent()));
+    }
+    if !Arc::ptr_eq(&old_state.nucleus(), &new_state.nucleus()) {
+        results.push(check_nucleus(&new_state.nucleus()));
+    }
+    if !Arc::ptr_eq(&old_state.network(), &new_state.network()) {
+        results.push(check_network(&new_state.network()));
+        results.push(check_network_transition(
+            &old_state.network(),
+            &new_state.network(),
+        ));
+    }
+    if !Arc::ptr_eq(&old_state.dht(), &new_state.dht()) {
+        for address in action.addresses() {
+            results.push(check_dht_meta(&new_state.dht(), &address));
+        }
+    }
+    results.into_iter().filter_map(Result::err).collect()
+}
+
+/// Runs check_state() on the state a reduce made. Violations get logged with a dump of the
+/// slices they were found in. The tests of core panic on them instead, so that they fail
+/// right at the reduce that corrupted the state.
+pub fn enforce(
+    context: &Context,
+    old_state: &State,
+    new_state: &State,
+    action_wrapper: &ActionWrapper,
+) {
+    let violations = check_state(old_state, new_state, action_wrapper.action());
+    if violations.is_empty() {
+        return;
+    }
+    let report = violations
+        .iter()
+        .map(|violation| format!("- {}\n{}", violation, dump(new_state, violation.slice)))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let message = format!(
+        "The state violates its invariants after reducing {}:\n{}",
+        action_wrapper.action(),
+        report
+    );
+    if cfg!(test) {
+        panic!("{}", message);
+    } else {
+        context.log(message);
+    }
+}
+
+fn dump(state: &State, slice: &str) -> String {
+    match slice {
+        AGENT_SLICE => format!("{:#?}", state.agent()),
+        NUCLEUS_SLICE => format!("{:#?}", state.nucleus()),
+        NETWORK_SLICE => format!("{:#?}", state.network()),
+        _ => format!("{:#?}", state.dht()),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        action::tests::test_action_wrapper_commit,
+        agent::chain_store::ChainStore,
+        instance::tests::test_context,
+        network::state::RequestId,
+        nucleus::{state::ZomeCallTimes, ZomeFnCall},
+        state::test_store,
+    };
+    use holochain_cas_implementations::{
+        cas::memory::MemoryStorage, eav::memory::EavMemoryStorage,
+    };
+    use holochain_core_types::{
+        cas::storage::ContentAddressableStorage,
+        chain_header::test_chain_header,
+        crud_status::{create_crud_link_eav, create_crud_status_eav},
+        entry::{test_entry, test_entry_b, test_entry_c, EntryWithMeta},
+        json::JsonString,
+    };
+    use std::{sync::RwLock, time::Instant};
+
+    fn storage() -> Arc<RwLock<MemoryStorage>> {
+        Arc::new(RwLock::new(MemoryStorage::new()))
+    }
+
+    #[test]
+    fn agent_invariants_catch_top_headers_not_in_the_storage() {
+        let storage = storage();
+        let chain = ChainStore::new(storage.clone());
+        assert_eq!(check_agent(&AgentState::new(chain.clone())), Ok(()));
+
+        let first = test_chain_header().with_sequence(1);
+        let agent = AgentState::new_with_top_chain_header(chain.clone(), first.clone());
+        let error = check_agent(&agent).unwrap_err();
+        assert_eq!(error.slice, AGENT_SLICE);
+        assert!(error.description.ends_with("is not in the storage"));
+        storage.write().unwrap().add(&first).unwrap();
+        assert_eq!(check_agent(&agent), Ok(()));
+
+        let following = |sequence: u64| {
+            ChainHeader::new(
+                first.entry_type(),
+                first.entry_address(),
+                first.sources(),
+                first.entry_signatures(),
+                &Some(first.address()),
+                &None,
+                &None,
+                first.timestamp(),
+            )
+            .with_sequence(sequence)
+        };
+        let out_of_sequence = following(3);
+        storage.write().unwrap().add(&out_of_sequence).unwrap();
+        let agent = AgentState::new_with_top_chain_header(chain.clone(), out_of_sequence);
+        let error = check_agent(&agent).unwrap_err();
+        assert!(error.description.contains("has the sequence 3"));
+
+        let second = following(2);
+        storage.write().unwrap().add(&second).unwrap();
+        let agent = AgentState::new_with_top_chain_header(chain.clone(), second);
+        assert_eq!(check_agent(&agent), Ok(()));
+    }
+
+    #[test]
+    fn nucleus_invariants_catch_results_without_their_call() {
+        let mut nucleus = NucleusState::new();
+        let call = ZomeFnCall::new("test_zome", "test_cap", "test_fn", "{}");
+        nucleus.start_zome_call(&call);
+        nucleus.return_zome_call(&call, Ok(JsonString::from("result")));
+        nucleus.record_zome_call_memory(&call, 1024);
+        assert_eq!(check_nucleus(&nucleus), Ok(()));
+
+        let mut untracked = nucleus.clone();
+        untracked.zome_call_times.clear();
+        let error = check_nucleus(&untracked).unwrap_err();
+        assert_eq!(error.slice, NUCLEUS_SLICE);
+        assert!(error.description.contains("has no start time"));
+
+        let mut without_call = nucleus.clone();
+        without_call.zome_calls.clear();
+        assert!(check_nucleus(&without_call).is_err());
+
+        let mut memory_without_call = NucleusState::new();
+        memory_without_call
+            .zome_call_memory
+            .insert(call.clone(), 1024);
+        assert!(check_nucleus(&memory_without_call).is_err());
+
+        let mut time_without_call = NucleusState::new();
+        time_without_call.zome_call_times.insert(
+            call,
+            ZomeCallTimes {
+                started: Instant::now(),
+                delivered: None,
+            },
+        );
+        assert!(check_nucleus(&time_without_call).is_err());
+    }
+
+    #[test]
+    fn network_invariants_catch_duplicate_ids_and_leftovers() {
+        let mut network = NetworkState::new();
+        let id = RequestId::new();
+        network
+            .get_entry_with_meta_results
+            .insert(id.clone(), test_entry().address());
+        assert_eq!(check_network(&network), Ok(()));
+
+        let mut duplicate = network.clone();
+        duplicate
+            .get_validation_package_results
+            .insert(id.clone(), test_entry().address());
+        let error = check_network(&duplicate).unwrap_err();
+        assert_eq!(error.slice, NETWORK_SLICE);
+        assert!(error.description.contains(&id.to_string()));
+
+        let mut sources_left = NetworkState::new();
+        sources_left
+            .get_entry_sources
+            .insert(id.clone(), vec![String::from("bob")].into_iter().collect());
+        assert!(check_network(&sources_left).is_err());
+
+        let mut recipient_left = NetworkState::new();
+        recipient_left
+            .direct_message_recipients
+            .insert(id, Address::from("bob"));
+        assert!(check_network(&recipient_left).is_err());
+    }
+
+    #[test]
+    fn network_invariants_catch_answered_requests_waiting_again() {
+        let mut old = NetworkState::new();
+        let id = RequestId::new();
+        old.get_entry_with_meta_results
+            .insert(id.clone(), test_entry().address());
+        old.get_entry_with_meta_results.timeout(&id);
+
+        // answers can come after a timeout
+        let mut answered = old.clone();
+        answered.get_entry_with_meta_results.resolve(&id, Ok(None));
+        assert_eq!(check_network_transition(&old, &answered), Ok(()));
+
+        // and replace earlier answers
+        let mut answered_again = answered.clone();
+        answered_again.get_entry_with_meta_results.resolve(
+            &id,
+            Ok(Some(EntryWithMeta::new(
+                test_entry(),
+                CrudStatus::LIVE,
+                None,
+            ))),
+        );
+        assert_eq!(check_network_transition(&answered, &answered_again), Ok(()));
+
+        // but don't make the request wait again, unless it got removed
+        let mut corrupted = answered.clone();
+        corrupted
+            .get_entry_with_meta_results
+            .insert(id.clone(), test_entry().address());
+        let error = check_network_transition(&answered, &corrupted).unwrap_err();
+        assert!(error
+            .description
+            .ends_with("waits for an answer again after it was answered"));
+        let mut removed = answered.clone();
+        removed.get_entry_with_meta_results.remove(&id);
+        assert_eq!(check_network_transition(&answered, &removed), Ok(()));
+    }
+
+    #[test]
+    fn dht_invariants_catch_statuses_without_lineage() {
+        let dht = DhtStore::new(storage(), Arc::new(RwLock::new(EavMemoryStorage::new())));
+        let address = test_entry().address();
+        let add_status = |status: CrudStatus| {
+            dht.meta_storage()
+                .write()
+                .unwrap()
+                .add_eav(&create_crud_status_eav(&address, status))
+                .unwrap()
+        };
+        let add_link = |to: Address| {
+            dht.meta_storage()
+                .write()
+                .unwrap()
+                .add_eav(&create_crud_link_eav(&address, &to))
+                .unwrap()
+        };
+        add_status(CrudStatus::LIVE);
+        assert_eq!(check_dht_meta(&dht, &address), Ok(()));
+
+        add_status(CrudStatus::DELETED);
+        let error = check_dht_meta(&dht, &address).unwrap_err();
+        assert_eq!(error.slice, DHT_SLICE);
+        assert!(error
+            .description
+            .ends_with("is DELETED, but has no crud-link to what replaced it"));
+        add_link(test_entry_b().address());
+        assert_eq!(check_dht_meta(&dht, &address), Ok(()));
+
+        // deleted and modified by a deletion and an update that met on the network
+        add_status(CrudStatus::MODIFIED);
+        assert!(check_dht_meta(&dht, &address)
+            .unwrap_err()
+            .description
+            .ends_with("is DELETED and MODIFIED, but has only one crud-link to what replaced it"));
+        add_link(test_entry_c().address());
+        assert_eq!(check_dht_meta(&dht, &address), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "The state violates its invariants after reducing Commit")]
+    fn reduces_that_corrupt_the_state_panic() {
+        let context = test_context("alice");
+        let state = test_store(context.clone());
+        // an entry that got deleted without a crud-link, which no reducer writes
+        state
+            .dht()
+            .meta_storage()
+            .write()
+            .unwrap()
+            .add_eav(&create_crud_status_eav(
+                &test_entry().address(),
+                CrudStatus::DELETED,
+            ))
+            .unwrap();
+        state.reduce(context.clone(), test_action_wrapper_commit());
+    }
+}
@@ -11,6 +11,8 @@
 extern crate serde_derive;
 extern crate chrono;
 extern crate futures;
+#[macro_use]
+extern crate lazy_static;
 extern crate multihash;
 extern crate rust_base58;
 extern crate serde;
@@ -22,11 +24,13 @@ extern crate snowflake;
 #[cfg(test)]
 extern crate test_utils;
 extern crate wasmi;
+extern crate parity_wasm;
 #[macro_use]
 extern crate unwrap_to;
 #[macro_use]
 extern crate num_derive;
 extern crate num_traits;
+extern crate rand;
 extern crate regex;
 
 extern crate config;
@@ -39,17 +43,23 @@ extern crate holochain_core_types;
 extern crate holochain_core_types_derive;
 extern crate base64;
 extern crate holochain_net_connection;
+extern crate holochain_sodium;
 
 pub mod action;
 pub mod agent;
 pub mod context;
 pub mod dht;
 pub mod instance;
+pub mod invariants;
 #[cfg(test)]
 pub mod link_tests;
 pub mod logger;
 pub mod network;
 pub mod nucleus;
 pub mod persister;
+pub mod replay;
+pub mod runtime;
 pub mod state;
+#[cfg(any(test, feature = "test_fixtures"))]
+pub mod test_fixtures;
 pub mod workflows;
@@ -13,6 +13,7 @@ pub mod tests {
         entry::{entry_type::EntryType, Entry},
         link::{link_list::LinkList, Link},
     };
+    use holochain_wasm_utils::api_serialization::CommitOptions;
 
     use std::sync::mpsc::channel;
 
@@ -52,7 +53,11 @@ pub mod tests {
         let link = create_example_link();
         let link_list = LinkList::new(&[link]);
         let link_list_entry = Entry::LinkList(link_list);
-        let commit_action = ActionWrapper::new(Action::Commit((link_list_entry.clone(), None)));
+        let commit_action = ActionWrapper::new(Action::Commit((
+            link_list_entry.clone(),
+            None,
+            CommitOptions::default(),
+        )));
         // Set up instance and process the action
         let instance = Instance::new(test_context("jason"));
         let state_observers: Vec<Observer> = Vec::new();
@@ -66,7 +71,7 @@ pub mod tests {
             .history
             .iter()
             .find(|aw| match aw.action() {
-                Action::Commit((entry, _)) => {
+                Action::Commit((entry, _, _)) => {
                     assert_eq!(entry.entry_type(), EntryType::LinkList,);
                     assert_eq!(entry.content(), link_list_entry.content());
                     true
@@ -85,7 +90,11 @@ pub mod tests {
         let link_c = create_test_link_c();
         let link_list = LinkList::new(&[link_a, link_b, link_c]);
         let link_list_entry = Entry::LinkList(link_list.clone());
-        let commit_action = ActionWrapper::new(Action::Commit((link_list_entry.clone(), None)));
+        let commit_action = ActionWrapper::new(Action::Commit((
+            link_list_entry.clone(),
+            None,
+            CommitOptions::default(),
+        )));
         println!("commit_multilink: {:?}", commit_action);
         // Set up instance and process the action
         let instance = Instance::new(test_context("jason"));
@@ -100,7 +109,7 @@ pub mod tests {
             .history
             .iter()
             .find(|aw| match aw.action() {
-                Action::Commit((entry, _)) => {
+                Action::Commit((entry, _, _)) => {
                     assert_eq!(entry.entry_type(), EntryType::LinkList,);
                     assert_eq!(entry.content(), link_list_entry.content());
                     true
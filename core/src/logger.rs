@@ -2,13 +2,26 @@
 //! which is separate from standard logging via the log crate warn! info! debug! logging that
 //! gets emitted globaly from the container.
 
+use crate::{action::ActionWrapper, state::State};
 use chrono::Local;
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent},
+    chain_header::ChainHeader,
+};
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
 
 /// trait that defines the logging functionality that holochain_core requires
 pub trait Logger: Send {
     // Add log message to logger
     fn log(&mut self, msg: String);
 
+    // Gets called for every action before it gets reduced.
+    // Loggers that are not meant for debugging core can ignore this.
+    fn log_action(&mut self, _action_wrapper: &ActionWrapper) {}
+
     // Dump all held logs
     fn dump(&self) -> String {
         String::new()
@@ -31,3 +44,218 @@ impl Logger for SimpleLogger {
     //      SimpleLogger {}
     // }
 }
+
+/// Number of characters of entry content that get printed per action
+pub const DEBUG_LOGGER_CONTENT_LEN: usize = 60;
+
+/// Width of the action name column, which is the length of the longest action name
+const ACTION_NAME_WIDTH: usize = 26;
+
+const COLOR_DIM: &str = "\x1b[2m";
+const COLOR_NAME: &str = "\x1b[1;36m";
+const COLOR_ID: &str = "\x1b[33m";
+const COLOR_RESET: &str = "\x1b[0m";
+
+/// Logger for debugging core behavior.
+/// Pretty-prints every dispatched action on one aligned line: time elapsed since the
+/// previous action, action name, id of the originating zome call (or of the action itself),
+/// shortened addresses and truncated entry content.
+/// Every shortened address is followed by a legend line with the full address the first
+/// time it shows up.
+pub struct DebugLogger {
+    colored: bool,
+    last_action: Option<Instant>,
+    known_addresses: HashSet<Address>,
+}
+
+impl DebugLogger {
+    pub fn new() -> Self {
+        DebugLogger {
+            colored: true,
+            last_action: None,
+            known_addresses: HashSet::new(),
+        }
+    }
+
+    /// same as new() but without ANSI color codes, i.e. for log files
+    pub fn without_colors() -> Self {
+        DebugLogger {
+            colored: false,
+            ..DebugLogger::new()
+        }
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.colored {
+            format!("{}{}{}", color, text, COLOR_RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Formats the given action, including legend lines for addresses not seen before.
+    /// elapsed is the time since the previous action, None for the first one.
+    pub fn format_action(
+        &mut self,
+        action_wrapper: &ActionWrapper,
+        elapsed: Option<Duration>,
+    ) -> String {
+        let action = action_wrapper.action();
+        let elapsed = match elapsed {
+            Some(duration) => format!(
+                "+{:>9.3}ms",
+                duration.as_secs() as f64 * 1000.0 + f64::from(duration.subsec_nanos()) / 1e6
+            ),
+            None => format!("{:>12}", "start"),
+        };
+        let id = match action.call_id() {
+            Some(call_id) => format!("call {}", call_id),
+            None => format!("id {}", action_wrapper.id()),
+        };
+        // Display of the action starts with its name which gets its own column here
+        let display = format!("{:.*}", DEBUG_LOGGER_CONTENT_LEN, action);
+        let details = display[action.name().len()..].trim_left();
+        let mut lines = vec![format!(
+            "{} {} {} {}",
+            self.paint(COLOR_DIM, &elapsed),
+            self.paint(
+                COLOR_NAME,
+                &format!("{:<width$}", action.name(), width = ACTION_NAME_WIDTH)
+            ),
+            self.paint(COLOR_ID, &format!("[{}]", id)),
+            details
+        )
+        .trim_right()
+        .to_string()];
+        for address in action.addresses() {
+            if self.known_addresses.insert(address.clone()) {
+                lines.push(self.paint(
+                    COLOR_DIM,
+                    &format!("{:>12} {} = {}", "", address.short(), address),
+                ));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+// ignore this in test coverage as it is only side effects
+#[cfg_attr(tarpaulin, skip)]
+impl Logger for DebugLogger {
+    fn log(&mut self, msg: String) {
+        let date = Local::now();
+        println!(
+            "{} {}",
+            self.paint(COLOR_DIM, &date.format("%H:%M:%S%.3f").to_string()),
+            msg
+        );
+    }
+
+    fn log_action(&mut self, action_wrapper: &ActionWrapper) {
+        let now = Instant::now();
+        let elapsed = self.last_action.map(|last| now.duration_since(last));
+        self.last_action = Some(now);
+        println!("{}", self.format_action(action_wrapper, elapsed));
+    }
+}
+
+fn format_chain_header(header: &ChainHeader) -> String {
+    format!("  {} {}", header.address().short(), header)
+}
+
+/// Pretty-prints the source chain, newest header first, and a summary of the local DHT shard.
+pub fn format_state_dump(state: &State) -> String {
+    let agent = state.agent();
+    let headers: Vec<String> = agent
+        .chain()
+        .iter(&agent.top_chain_header())
+        .map(|header| format_chain_header(&header))
+        .collect();
+    let mut lines = vec![format!("Source chain ({} headers):", headers.len())];
+    lines.extend(headers);
+    match state.dht().held_addresses() {
+        Ok(held) => {
+            lines.push(format!(
+                "DHT shard ({} actions, {} entries held):",
+                state.dht().actions().len(),
+                held.len()
+            ));
+            lines.extend(
+                held.iter()
+                    .map(|address| format!("  {} = {}", address.short(), address)),
+            );
+        }
+        Err(error) => lines.push(format!(
+            "DHT shard ({} actions, held entries unreadable: {})",
+            state.dht().actions().len(),
+            error
+        )),
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        action::{
+            tests::{test_action_wrapper_commit, test_action_wrapper_get},
+            Action,
+        },
+        instance::tests::test_context,
+        state::test_store,
+    };
+    use holochain_core_types::entry::{expected_entry_address, test_entry};
+
+    #[test]
+    fn debug_logger_formats_actions() {
+        let mut logger = DebugLogger::without_colors();
+        let commit = test_action_wrapper_commit();
+        let address = test_entry().address();
+
+        assert_eq!(
+            logger.format_action(&commit, None),
+            format!(
+                "       start Commit                     [id {}] {} testEntryType \"test entry value\"\n             {} = {}",
+                commit.id(),
+                address.short(),
+                address.short(),
+                address
+            ),
+        );
+
+        // the address is known by now, so no legend line for it
+        let get = test_action_wrapper_get();
+        assert_eq!(
+            logger.format_action(&get, Some(Duration::from_micros(1500))),
+            format!(
+                "+    1.500ms GetEntry                   [id {}] {}",
+                get.id(),
+                expected_entry_address().short()
+            ),
+        );
+
+        let init = ActionWrapper::new(Action::ReturnInitializationResult(None));
+        assert_eq!(
+            logger.format_action(&init, Some(Duration::from_secs(2))),
+            format!("+ 2000.000ms ReturnInitializationResult [id {}]", init.id()),
+        );
+    }
+
+    #[test]
+    fn debug_logger_colors() {
+        let mut logger = DebugLogger::new();
+        let get = test_action_wrapper_get();
+        let line = logger.format_action(&get, None);
+        assert!(line.starts_with("\x1b[2m       start\x1b[0m \x1b[1;36mGetEntry"));
+    }
+
+    #[test]
+    fn state_dump_of_empty_state() {
+        let state = test_store(test_context("jane"));
+        assert_eq!(
+            format_state_dump(&state),
+            "Source chain (0 headers):\nDHT shard (0 actions, 0 entries held):".to_string(),
+        );
+    }
+}
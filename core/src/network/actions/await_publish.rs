@@ -0,0 +1,70 @@
+extern crate futures;
+use crate::context::Context;
+use futures::{
+    future::Future,
+    task::{LocalWaker, Poll},
+};
+use holochain_core_types::{
+    cas::content::Address,
+    error::{HcResult, HolochainError},
+};
+use std::{
+    pin::{Pin, Unpin},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// AwaitPublish Action Creator
+/// Waits until at least min_holders agents acknowledged holding the entry with the given
+/// address, which has to be published by this node, or until the timeout has passed.
+/// Holders only acknowledge entries that passed their validation.
+///
+/// Returns a future that resolves to the number of holders, or to a Timeout error.
+pub async fn await_publish<'a>(
+    address: &'a Address,
+    min_holders: usize,
+    timeout: Duration,
+    context: &'a Arc<Context>,
+) -> HcResult<usize> {
+    await!(AwaitPublishFuture {
+        context: context.clone(),
+        address: address.clone(),
+        min_holders,
+        deadline: Instant::now() + timeout,
+    })
+}
+
+/// AwaitPublishFuture resolves to the number of holders of the entry
+/// Tracks the acknowledgements for its address in the network state
+pub struct AwaitPublishFuture {
+    context: Arc<Context>,
+    address: Address,
+    min_holders: usize,
+    deadline: Instant,
+}
+
+impl Unpin for AwaitPublishFuture {}
+
+impl Future for AwaitPublishFuture {
+    type Output = HcResult<usize>;
+
+    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Self::Output> {
+        let state = self.context.state().unwrap().network();
+        if let Err(error) = state.initialized() {
+            return Poll::Ready(Err(error));
+        }
+        let holders_count = state.holders_count(&self.address);
+        if holders_count >= self.min_holders {
+            return Poll::Ready(Ok(holders_count));
+        }
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(Err(HolochainError::Timeout));
+        }
+        //
+        // TODO: connect the waker to state updates for performance reasons
+        // See: https://github.com/holochain/holochain-rust/issues/314
+        //
+        lw.wake();
+        Poll::Pending
+    }
+}
@@ -3,6 +3,7 @@ use crate::{
     action::{Action, ActionWrapper},
     context::Context,
     instance::dispatch_action,
+    network::state::RequestId,
 };
 use futures::{
     future::Future,
@@ -12,37 +13,47 @@ use holochain_core_types::{cas::content::Address, entry::EntryWithMeta, error::H
 use std::{
     pin::{Pin, Unpin},
     sync::Arc,
-    thread::sleep,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+/// Seconds after which an unanswered get request resolves to HolochainError::Timeout,
+/// see `GetEntryFuture`
+pub const GET_ENTRY_TIMEOUT_SECS: u64 = 60;
+
 /// GetEntry Action Creator
 /// This is the network version of get_entry that makes the network module start
 /// a look-up process.
 ///
 /// Returns a future that resolves to an ActionResponse.
+/// Once it has the result, the request gets removed from the network state again.
 pub async fn get_entry<'a>(
     context: &'a Arc<Context>,
     address: &'a Address,
 ) -> HcResult<Option<EntryWithMeta>> {
-    let action_wrapper = ActionWrapper::new(Action::GetEntry(address.clone()));
+    let id = RequestId::new();
+    let action_wrapper = ActionWrapper::new(Action::GetEntry((address.clone(), id.clone())));
     dispatch_action(&context.action_channel, action_wrapper.clone());
-    async {
-        sleep(Duration::from_secs(60));
-        let action_wrapper = ActionWrapper::new(Action::GetEntryTimeout(address.clone()));
-        dispatch_action(&context.action_channel, action_wrapper.clone());
-    };
-    await!(GetEntryFuture {
+
+    let result = await!(GetEntryFuture {
         context: context.clone(),
-        address: address.clone(),
-    })
+        id: id.clone(),
+        timed_out: false,
+    });
+    dispatch_action(
+        &context.action_channel,
+        ActionWrapper::new(Action::ClearPendingRequest(id)),
+    );
+    result
 }
 
 /// GetEntryFuture resolves to a HcResult<Entry>.
-/// Tracks the state of the network module
+/// Tracks the state of the network module. Once the request waited for
+/// GET_ENTRY_TIMEOUT_SECS since it got sent, it dispatches Action::GetEntryTimeout,
+/// which resolves it to HolochainError::Timeout unless its answer came in meanwhile.
 pub struct GetEntryFuture {
     context: Arc<Context>,
-    address: Address,
+    id: RequestId,
+    timed_out: bool,
 }
 
 impl Unpin for GetEntryFuture {}
@@ -50,7 +61,7 @@ impl Unpin for GetEntryFuture {}
 impl Future for GetEntryFuture {
     type Output = HcResult<Option<EntryWithMeta>>;
 
-    fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Self::Output> {
+    fn poll(mut self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Self::Output> {
         let state = self.context.state().unwrap().network();
         if let Err(error) = state.initialized() {
             return Poll::Ready(Err(error));
@@ -60,9 +71,23 @@ impl Future for GetEntryFuture {
         // See: https://github.com/holochain/holochain-rust/issues/314
         //
         lw.wake();
-        match state.get_entry_with_meta_results.get(&self.address) {
-            Some(Some(result)) => Poll::Ready(result.clone()),
-            _ => Poll::Pending,
+        let request = match state.get_entry_with_meta_results.get(&self.id) {
+            Some(request) => request,
+            // the request did not get reduced yet
+            None => return Poll::Pending,
+        };
+        if let Some(ref result) = request.result {
+            return Poll::Ready(result.clone());
+        }
+        if !self.timed_out
+            && Instant::now() >= request.sent + Duration::from_secs(GET_ENTRY_TIMEOUT_SECS)
+        {
+            self.timed_out = true;
+            dispatch_action(
+                &self.context.action_channel,
+                ActionWrapper::new(Action::GetEntryTimeout(self.id.clone())),
+            );
         }
+        Poll::Pending
     }
 }
@@ -3,14 +3,14 @@ use crate::{
     action::{Action, ActionWrapper},
     context::Context,
     instance::dispatch_action,
+    network::state::RequestId,
 };
 use futures::{
     future::Future,
     task::{LocalWaker, Poll},
 };
 use holochain_core_types::{
-    cas::content::Address, chain_header::ChainHeader, error::HcResult,
-    validation::ValidationPackage,
+    chain_header::ChainHeader, error::HcResult, validation::ValidationPackage,
 };
 use std::{
     pin::{Pin, Unpin},
@@ -23,17 +23,23 @@ use std::{
 ///
 /// Returns a future that resolves to Option<ValidationPackage> (or HolochainError).
 /// If that is None this means that we couldn't get a validation package from the source.
+/// Once it has the result, the request gets removed from the network state again.
 pub async fn get_validation_package(
     header: ChainHeader,
     context: &Arc<Context>,
 ) -> HcResult<Option<ValidationPackage>> {
-    let entry_address = header.entry_address().clone();
-    let action_wrapper = ActionWrapper::new(Action::GetValidationPackage(header));
+    let id = RequestId::new();
+    let action_wrapper = ActionWrapper::new(Action::GetValidationPackage((header, id.clone())));
     dispatch_action(&context.action_channel, action_wrapper.clone());
-    await!(GetValidationPackageFuture {
+    let result = await!(GetValidationPackageFuture {
         context: context.clone(),
-        address: entry_address,
-    })
+        id: id.clone(),
+    });
+    dispatch_action(
+        &context.action_channel,
+        ActionWrapper::new(Action::ClearPendingRequest(id)),
+    );
+    result
 }
 
 /// GetValidationPackageFuture resolves to an Option<ValidationPackage>
@@ -41,7 +47,7 @@ pub async fn get_validation_package(
 /// is not the source.
 pub struct GetValidationPackageFuture {
     context: Arc<Context>,
-    address: Address,
+    id: RequestId,
 }
 
 impl Unpin for GetValidationPackageFuture {}
@@ -59,9 +65,9 @@ impl Future for GetValidationPackageFuture {
         // See: https://github.com/holochain/holochain-rust/issues/314
         //
         lw.wake();
-        match state.get_validation_package_results.get(&self.address) {
-            Some(Some(result)) => Poll::Ready(result.clone()),
-            _ => Poll::Pending,
+        match state.get_validation_package_results.result(&self.id) {
+            Some(result) => Poll::Ready(result),
+            None => Poll::Pending,
         }
     }
 }
@@ -4,6 +4,7 @@ use crate::{
     action::{Action, ActionWrapper, NetworkSettings},
     context::Context,
     instance::dispatch_action,
+    network::{actions::ActionResponse, traffic::RateLimit},
 };
 use futures::{
     task::{LocalWaker, Poll},
@@ -32,23 +33,41 @@ async fn get_dna_and_agent(context: &Arc<Context>) -> Result<(String, String), H
     Ok((dna_hash, agent_id))
 }
 /// Creates a network proxy object and stores DNA and agent hash in the network state.
+/// Then dispatches a Publish action for each of the entries that got authored before,
+/// i.e. during genesis, in the order they got authored, without waiting for them.
+/// Fails with the reason the network could not be initialized, which leaves those entries
+/// unpublished, see `NetworkStatus::deferred_publishes`.
 pub async fn initialize_network(context: &Arc<Context>) -> Result<(), HolochainError> {
     let (dna_hash, agent_id) = await!(get_dna_and_agent(context))?;
     let network_settings = NetworkSettings {
         config: context.network_config.clone(),
         dna_hash,
         agent_id,
+        rate_limit: RateLimit::from_network_config(&context.network_config)?,
     };
     let action_wrapper = ActionWrapper::new(Action::InitNetwork(network_settings));
     dispatch_action(&context.action_channel, action_wrapper.clone());
 
     await!(InitNetworkFuture {
         context: context.clone(),
-    })
+        action: action_wrapper,
+    })?;
+
+    let state = context
+        .state()
+        .ok_or("initialize_network() could not get application state".to_string())?;
+    for address in state.network().deferred_publishes.iter() {
+        dispatch_action(
+            &context.action_channel,
+            ActionWrapper::new(Action::Publish(address.clone())),
+        );
+    }
+    Ok(())
 }
 
 pub struct InitNetworkFuture {
     context: Arc<Context>,
+    action: ActionWrapper,
 }
 
 impl Unpin for InitNetworkFuture {}
@@ -62,17 +81,13 @@ impl Future for InitNetworkFuture {
         // See: https://github.com/holochain/holochain-rust/issues/314
         //
         lw.wake();
-        if let Some(state) = self.context.state() {
-            if state.network().network.is_some()
-                || state.network().dna_hash.is_some()
-                || state.network().agent_id.is_some()
-            {
-                Poll::Ready(Ok(()))
-            } else {
-                Poll::Pending
-            }
-        } else {
-            Poll::Pending
+        let state = match self.context.state() {
+            Some(state) => state,
+            None => return Poll::Pending,
+        };
+        match state.network().actions().get(&self.action) {
+            Some(ActionResponse::InitNetwork(result)) => Poll::Ready(result.clone()),
+            _ => Poll::Pending,
         }
     }
 }
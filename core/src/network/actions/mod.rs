@@ -1,3 +1,4 @@
+pub mod await_publish;
 pub mod get_entry;
 pub mod get_validation_package;
 pub mod initialize_network;
@@ -7,6 +8,9 @@ use holochain_core_types::{cas::content::Address, error::HcResult};
 
 #[derive(Clone, Debug)]
 pub enum ActionResponse {
+    AcknowledgeHold(HcResult<()>),
+    InitNetwork(HcResult<()>),
     Publish(HcResult<Address>),
     RespondGet(HcResult<()>),
+    RespondUnsupportedMessage(HcResult<()>),
 }
@@ -29,6 +29,18 @@ pub async fn publish(address: Address, context: &Arc<Context>) -> HcResult<Addre
     })
 }
 
+/// Republish Action Creator
+/// Publishes the entry like publish() does, even if it got published with the same meta before,
+/// to repair a DHT that lost it.
+pub async fn republish(address: Address, context: &Arc<Context>) -> HcResult<Address> {
+    let action_wrapper = ActionWrapper::new(Action::Republish(address));
+    dispatch_action(&context.action_channel, action_wrapper.clone());
+    await!(PublishFuture {
+        context: context.clone(),
+        action: action_wrapper,
+    })
+}
+
 /// PublishFuture resolves to ActionResponse
 /// Tracks the state for a response to its ActionWrapper
 pub struct PublishFuture {
@@ -42,10 +54,9 @@ impl Future for PublishFuture {
     type Output = HcResult<Address>;
 
     fn poll(self: Pin<&mut Self>, lw: &LocalWaker) -> Poll<Self::Output> {
+        // before the network is up, the publish gets deferred and still responds,
+        // see reduce_publish()
         let state = self.context.state().unwrap().network();
-        if let Err(error) = state.initialized() {
-            return Poll::Ready(Err(error));
-        }
         //
         // TODO: connect the waker to state updates for performance reasons
         // See: https://github.com/holochain/holochain-rust/issues/314
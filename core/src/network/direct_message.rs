@@ -18,4 +18,12 @@ pub enum DirectMessage {
     /// Option<> since there has to be a way to respond saying
     /// "I can't"
     ValidationPackage(Option<ValidationPackage>),
+
+    /// A node that was asked to hold an entry tells its source that the entry
+    /// failed validation, and why. Expects no response.
+    ValidationFailureNotice {
+        address: Address,
+        reason: String,
+        holder: Address,
+    },
 }
@@ -2,9 +2,9 @@ use crate::{agent::find_chain_header, context::Context};
 use holochain_core_types::{
     cas::content::Address, chain_header::ChainHeader, entry::Entry, error::HolochainError,
 };
-use std::{convert::TryInto, sync::Arc};
+use std::{convert::TryInto, sync::Arc, time::Duration};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct EntryWithHeader {
     pub entry_body: Entry,
     pub header: ChainHeader,
@@ -42,3 +42,26 @@ pub fn fetch_entry_with_header(
 
     Ok(EntryWithHeader::new(entry, header))
 }
+
+/// Whether the entry is older than the TTL of its type in the DNA of the instance, going by
+/// the timestamp of its header, see `EntryTypeDef::ttl_seconds`.
+/// Headers without a valid timestamp don't tell the age of their entry, so it doesn't expire.
+pub fn outlived_ttl(entry_with_header: &EntryWithHeader, context: &Arc<Context>) -> bool {
+    let ttl = match context
+        .state()
+        .and_then(|state| state.nucleus().dna())
+        .and_then(|dna| dna.entry_ttl(&entry_with_header.entry_body.entry_type()))
+    {
+        Some(ttl) => ttl,
+        None => return false,
+    };
+    let timestamp = entry_with_header.header.timestamp();
+    if !timestamp.is_valid() {
+        return false;
+    }
+    context
+        .now()
+        .duration_since(timestamp.to_system_time())
+        .map(|age| age >= Duration::from_secs(ttl))
+        .unwrap_or(false)
+}
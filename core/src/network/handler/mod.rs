@@ -1,22 +1,51 @@
 pub mod get;
+pub mod peer_connected;
+pub mod rate_limited;
 pub mod send;
 pub mod store;
+pub mod unsupported;
 
 use crate::{
+    action::{Action, ActionWrapper},
     context::Context,
-    network::handler::{get::*, send::*, store::*},
+    network::handler::{
+        get::*, peer_connected::*, rate_limited::*, send::*, store::*, unsupported::*,
+    },
+};
+use holochain_net_connection::{
+    net_connection::NetHandler,
+    protocol_wrapper::{ProtocolWrapper, UnsupportedMessageData},
 };
-use holochain_net_connection::{net_connection::NetHandler, protocol_wrapper::ProtocolWrapper};
 use std::{convert::TryFrom, sync::Arc};
 
 /// Creates the network handler.
 /// The returned closure is called by the network thread for every network event that core
 /// has to handle.
+/// Messages we can't make sense of (unknown types or a newer protocol version) don't fail the
+/// handler but get answered with an UnsupportedMessage.
+/// Requests of peers that send more than the rate limit allows get dropped, see `admit()`.
+/// Errors mean that the connection to the network backend broke down, which makes the
+/// network module reconnect.
 pub fn create_handler(c: &Arc<Context>) -> NetHandler {
     let context = c.clone();
+    let mut warnings = UnsupportedMessageWarnings::new();
     Box::new(move |message| {
-        let message = message.unwrap();
-        let protocol_wrapper = ProtocolWrapper::try_from(message);
+        let message = match message {
+            Ok(message) => message,
+            Err(error) => {
+                context.log(format!("Error received from network: {:?}", error));
+                let action_wrapper = ActionWrapper::new(Action::NetworkLost(error.to_string()));
+                // The instance might be gone by now, so there is nobody left to reconnect
+                let _ = context.action_channel.send(action_wrapper);
+                return Ok(());
+            }
+        };
+        let protocol_wrapper = ProtocolWrapper::try_from(&message);
+        if let Ok(ref protocol_wrapper) = protocol_wrapper {
+            if !admit(protocol_wrapper, &message, &context) {
+                return Ok(());
+            }
+        }
         match protocol_wrapper {
             Ok(ProtocolWrapper::StoreDht(dht_data)) => handle_store_dht(dht_data, context.clone()),
             Ok(ProtocolWrapper::StoreDhtMeta(dht_meta_data)) => {
@@ -29,13 +58,279 @@ pub fn create_handler(c: &Arc<Context>) -> NetHandler {
                 handle_get_dht_result(dht_data, context.clone())
             }
             Ok(ProtocolWrapper::HandleSend(message_data)) => {
-                handle_send(message_data, context.clone())
+                handle_send(message_data, &mut warnings, context.clone())
             }
             Ok(ProtocolWrapper::SendResult(message_data)) => {
                 handle_send_result(message_data, context.clone())
             }
-            _ => {}
+            Ok(ProtocolWrapper::HoldAck(hold_ack_data)) => {
+                handle_hold_ack(hold_ack_data, context.clone())
+            }
+            Ok(ProtocolWrapper::PeerConnected(peer_data)) => {
+                handle_peer_connected(peer_data, context.clone())
+            }
+            Ok(ProtocolWrapper::UnsupportedMessage(unsupported_message_data)) => {
+                handle_unsupported_message_reply(unsupported_message_data, context.clone())
+            }
+            Ok(ProtocolWrapper::RateLimited(rate_limited_data)) => {
+                handle_rate_limited(rate_limited_data, context.clone())
+            }
+            Ok(_) => {}
+            Err(_) => {
+                if let Some(reply) = UnsupportedMessageData::reply_to(&message) {
+                    handle_unsupported_message(reply, &mut warnings, context.clone())
+                }
+            }
         }
         Ok(())
     })
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        action::Action,
+        instance::{dispatch_action, tests::test_instance_and_context_by_name},
+        network::{
+            actions::ActionResponse, direct_message::DirectMessage, state::RequestId,
+            traffic::RateLimit,
+        },
+    };
+    use holochain_core_types::{
+        cas::content::{Address, AddressableContent},
+        entry::test_entry,
+        error::HolochainError,
+    };
+    use holochain_net_connection::{protocol::Protocol, protocol_wrapper::PROTOCOL_VERSION};
+    use std::{
+        io, thread,
+        time::{Duration, Instant},
+    };
+    use test_utils::create_test_dna_with_wat;
+
+    /// Waits until the state of the given context satisfies the given condition
+    fn wait_for<F: Fn(&Arc<Context>) -> bool>(context: &Arc<Context>, condition: F) -> bool {
+        for _ in 0..100 {
+            if condition(context) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        false
+    }
+
+    #[test]
+    fn handler_answers_messages_of_future_protocol_versions() {
+        let mut dna = create_test_dna_with_wat("test_zome", "test_cap", None);
+        dna.uuid = String::from("handler_answers_messages_of_future_protocol_versions");
+        let (_, context1) = test_instance_and_context_by_name(dna.clone(), "alice1").unwrap();
+        let (_, context2) = test_instance_and_context_by_name(dna.clone(), "bob1").unwrap();
+
+        let network_state2 = context2.state().unwrap().network();
+        let future_message = json!({
+            "method": "getDhtLinks",
+            "protocolVersion": PROTOCOL_VERSION + 1,
+            "_id": "future_message",
+            "dnaHash": network_state2.dna_hash.clone().unwrap(),
+            "fromAgentId": network_state2.agent_id.clone().unwrap(),
+            "address": "QmFutureAddress",
+        });
+
+        // Feed alice's handler a message from the future and an unknown direct message
+        let mut handler = create_handler(&context1);
+        let result = handler(Ok(Protocol::Json(future_message.to_string().into())));
+        assert!(result.is_ok());
+        let unknown_direct_message = json!({
+            "method": "handleSend",
+            "protocolVersion": PROTOCOL_VERSION,
+            "_id": "unknown_direct_message",
+            "dnaHash": network_state2.dna_hash.clone().unwrap(),
+            "toAgentId": context1.state().unwrap().network().agent_id.clone().unwrap(),
+            "fromAgentId": network_state2.agent_id.clone().unwrap(),
+            "data": {"FutureDirectMessage": "hello"},
+        });
+        let result = handler(Ok(Protocol::Json(unknown_direct_message.to_string().into())));
+        assert!(result.is_ok());
+
+        // Alice answers both...
+        assert!(wait_for(&context1, |context| {
+            context
+                .state()
+                .unwrap()
+                .network()
+                .actions
+                .iter()
+                .filter(|(action_wrapper, response)| match (action_wrapper.action(), response) {
+                    (
+                        Action::RespondUnsupportedMessage(_),
+                        ActionResponse::RespondUnsupportedMessage(Ok(())),
+                    ) => true,
+                    _ => false,
+                })
+                .count()
+                == 2
+        }));
+
+        // ...and bob gets told which of his messages could not be handled
+        assert!(wait_for(&context2, |context| {
+            context
+                .state()
+                .unwrap()
+                .history
+                .iter()
+                .filter(|action_wrapper| match action_wrapper.action() {
+                    Action::HandleUnsupportedMessage(data) => {
+                        data.msg_id == "future_message" || data.msg_id == "unknown_direct_message"
+                    }
+                    _ => false,
+                })
+                .count()
+                == 2
+        }));
+    }
+
+    #[test]
+    fn handler_does_not_fail_on_garbage() {
+        let mut dna = create_test_dna_with_wat("test_zome", "test_cap", None);
+        dna.uuid = String::from("handler_does_not_fail_on_garbage");
+        let (_, context) = test_instance_and_context_by_name(dna.clone(), "alice1").unwrap();
+
+        let mut handler = create_handler(&context);
+        assert!(handler(Ok(Protocol::Json("no json".to_string().into()))).is_ok());
+        assert!(handler(Ok(Protocol::Json("{\"method\":\"unsupportedMessage\"}".to_string().into()))).is_ok());
+        let network_failure = io::Error::new(io::ErrorKind::Other, "network failure");
+        assert!(handler(Err(network_failure.into())).is_ok());
+    }
+
+    /// Asks the network for the given entry on behalf of the given context
+    fn get_entry(context: &Arc<Context>, address: Address) -> RequestId {
+        let id = RequestId::new();
+        let action_wrapper = ActionWrapper::new(Action::GetEntry((address, id.clone())));
+        dispatch_action(&context.action_channel, action_wrapper);
+        id
+    }
+
+    #[test]
+    fn handler_drops_requests_of_flooding_peers() {
+        let mut dna = create_test_dna_with_wat("test_zome", "test_cap", None);
+        dna.uuid = String::from("handler_drops_requests_of_flooding_peers");
+        // The mock network sends every GET to alice, who came first
+        let (_, alice) = test_instance_and_context_by_name(dna.clone(), "alice1").unwrap();
+        let (_, bob) = test_instance_and_context_by_name(dna.clone(), "bob1").unwrap();
+        let (_, mallory) = test_instance_and_context_by_name(dna.clone(), "mallory1").unwrap();
+        alice
+            .state()
+            .unwrap()
+            .network()
+            .traffic
+            .lock()
+            .unwrap()
+            .set_limit(RateLimit {
+                messages_per_second: 10,
+                bytes_per_second: 1024 * 1024,
+                burst_seconds: 2,
+            });
+
+        // Mallory floods alice with far more requests than she takes...
+        let flood: Vec<RequestId> = (0..200)
+            .map(|_| get_entry(&mallory, test_entry().address()))
+            .collect();
+
+        // ...which does not keep bob from getting his answer in time
+        let start = Instant::now();
+        let id = get_entry(&bob, test_entry().address());
+        assert!(wait_for(&bob, |context| {
+            context
+                .state()
+                .unwrap()
+                .network()
+                .get_entry_with_meta_results
+                .result(&id)
+                == Some(Ok(None))
+        }));
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // Mallory's excess got dropped and mallory told so
+        let rate_limited = |context: &Arc<Context>| {
+            let network_state = context.state().unwrap().network();
+            flood
+                .iter()
+                .filter(
+                    |id| match network_state.get_entry_with_meta_results.result(id) {
+                        Some(Err(HolochainError::RateLimited(_))) => true,
+                        _ => false,
+                    },
+                )
+                .count()
+        };
+        assert!(wait_for(&mallory, |context| rate_limited(context) >= 150));
+
+        let mallory_id = mallory.state().unwrap().network().agent_id.clone().unwrap();
+        let bob_id = bob.state().unwrap().network().agent_id.clone().unwrap();
+        let traffic = alice.state().unwrap().network().status().traffic;
+        assert_eq!(traffic[&mallory_id].messages_in, 200);
+        assert!(traffic[&mallory_id].dropped >= 150);
+        // the burst got through
+        assert!(traffic[&mallory_id].dropped <= 180);
+        assert_eq!(traffic[&bob_id].dropped, 0);
+    }
+
+    #[test]
+    fn handler_keeps_serving_with_a_poisoned_storage() {
+        let mut dna = create_test_dna_with_wat("test_zome", "test_cap", None);
+        dna.uuid = String::from("handler_keeps_serving_with_a_poisoned_storage");
+        let (_, alice) = test_instance_and_context_by_name(dna.clone(), "alice1").unwrap();
+        let (_, bob) = test_instance_and_context_by_name(dna.clone(), "bob1").unwrap();
+
+        // A writer panicking leaves alice's chain storage poisoned
+        let storage = alice.state().unwrap().agent().chain().content_storage();
+        let _ = thread::spawn(move || {
+            let _storage = storage.write().unwrap();
+            panic!("storage failure for testing");
+        })
+        .join();
+
+        // Bob asks alice for a validation package, which she can't build...
+        let bob_network = bob.state().unwrap().network();
+        let request = json!({
+            "method": "handleSend",
+            "protocolVersion": PROTOCOL_VERSION,
+            "_id": "poisoned_request",
+            "dnaHash": bob_network.dna_hash.clone().unwrap(),
+            "toAgentId": alice.state().unwrap().network().agent_id.clone().unwrap(),
+            "fromAgentId": bob_network.agent_id.clone().unwrap(),
+            "data": {"RequestValidationPackage": test_entry().address()},
+        });
+        let mut handler = create_handler(&alice);
+        assert!(handler(Ok(Protocol::Json(request.to_string().into()))).is_ok());
+
+        // ...so she tells him that there is none
+        assert!(wait_for(&alice, |context| {
+            context
+                .state()
+                .unwrap()
+                .history
+                .iter()
+                .any(|action_wrapper| match action_wrapper.action() {
+                    Action::SendDirectMessage(data) => {
+                        data.msg_id == RequestId::from("poisoned_request")
+                            && data.message == DirectMessage::ValidationPackage(None)
+                    }
+                    _ => false,
+                })
+        }));
+
+        // and keeps answering the requests that follow
+        let id = get_entry(&bob, test_entry().address());
+        assert!(wait_for(&bob, |context| {
+            context
+                .state()
+                .unwrap()
+                .network()
+                .get_entry_with_meta_results
+                .result(&id)
+                .is_some()
+        }));
+    }
+}
@@ -0,0 +1,14 @@
+use crate::{
+    action::{Action, ActionWrapper},
+    context::Context,
+    instance::dispatch_action,
+};
+use holochain_net_connection::protocol_wrapper::PeerData;
+use std::sync::Arc;
+
+/// The network tells us about another agent of the DNA,
+/// which might be among the holders of the entries that are not held by every node.
+pub fn handle_peer_connected(peer_data: PeerData, context: Arc<Context>) {
+    let action_wrapper = ActionWrapper::new(Action::HandlePeerConnected(peer_data));
+    dispatch_action(&context.action_channel, action_wrapper);
+}
@@ -0,0 +1,77 @@
+use crate::{
+    action::{Action, ActionWrapper},
+    context::Context,
+    instance::dispatch_action,
+    network::traffic::{message_size, sender},
+};
+use holochain_net_connection::{
+    net_connection::NetConnection,
+    protocol::Protocol,
+    protocol_wrapper::{ProtocolWrapper, RateLimitedData},
+};
+use std::{sync::Arc, time::Instant};
+
+/// Accounts for an incoming message of a peer and tells whether to handle it.
+/// Requests of a peer that exceeds the rate limit get dropped and answered with a
+/// RateLimited right away, without going through an action, so that a flooding peer
+/// can't fill up the action channel either.
+pub fn admit(
+    protocol_wrapper: &ProtocolWrapper,
+    message: &Protocol,
+    context: &Arc<Context>,
+) -> bool {
+    let (peer, request_id) = match sender(protocol_wrapper) {
+        Some(sender) => sender,
+        None => return true,
+    };
+    let state = match context.state() {
+        Some(state) => state,
+        None => return true,
+    };
+    let network_state = state.network();
+
+    // Without its traffic counts nobody can be held to the rate limit
+    let mut traffic = match network_state.traffic.lock() {
+        Ok(traffic) => traffic,
+        Err(_) => return true,
+    };
+    let admitted = traffic.incoming(
+        peer,
+        message_size(message),
+        request_id.is_some(),
+        Instant::now(),
+    );
+    drop(traffic);
+    let retry_after = match admitted {
+        Ok(()) => return true,
+        Err(retry_after) => retry_after,
+    };
+
+    if let (Some(network), Some(dna_hash), Some(agent_id), Some(msg_id)) = (
+        network_state.network.as_ref(),
+        network_state.dna_hash.as_ref(),
+        network_state.agent_id.as_ref(),
+        request_id,
+    ) {
+        let rate_limited = ProtocolWrapper::RateLimited(RateLimitedData {
+            msg_id: msg_id.to_string(),
+            dna_hash: dna_hash.clone(),
+            to_agent_id: peer.to_string(),
+            from_agent_id: agent_id.clone(),
+            retry_after_ms: retry_after.as_secs() * 1000
+                + u64::from(retry_after.subsec_millis())
+                + 1,
+        });
+        // A broken connection gets noticed by the handler, which reconnects
+        if let Ok(mut network) = network.lock() {
+            let _ = network.send(rate_limited.into());
+        }
+    }
+    false
+}
+
+/// Another node dropped a request we sent because we exceeded its rate limit.
+pub fn handle_rate_limited(rate_limited_data: RateLimitedData, context: Arc<Context>) {
+    let action_wrapper = ActionWrapper::new(Action::HandleRateLimited(rate_limited_data));
+    dispatch_action(&context.action_channel, action_wrapper);
+}
@@ -2,20 +2,51 @@ use crate::{
     action::{Action, ActionWrapper},
     context::Context,
     instance::dispatch_action,
-    network::direct_message::DirectMessage,
-    workflows::respond_validation_package_request::respond_validation_package_request,
+    network::{
+        direct_message::DirectMessage,
+        handler::unsupported::{
+            handle_unsupported_message, handle_unsupported_message_reply,
+            UnsupportedMessageWarnings,
+        },
+        state::RequestId,
+    },
+    workflows::respond_validation_package_request::{
+        respond_validation_package, respond_validation_package_request,
+    },
 };
 use futures::executor::block_on;
 use holochain_core_types::cas::content::Address;
-use std::{sync::Arc, thread};
+use std::sync::Arc;
 
-use holochain_net_connection::protocol_wrapper::MessageData;
+use holochain_net_connection::protocol_wrapper::{
+    MessageData, UnsupportedMessageData, PROTOCOL_VERSION,
+};
 
 /// We got a ProtocolWrapper::SendMessage, this means somebody initiates message roundtrip
 /// -> we are being called
-pub fn handle_send(message_data: MessageData, context: Arc<Context>) {
-    let message: DirectMessage =
-        serde_json::from_str(&serde_json::to_string(&message_data.data).unwrap()).unwrap();
+/// A direct message type we don't know gets answered with an UnsupportedMessage.
+pub fn handle_send(
+    message_data: MessageData,
+    warnings: &mut UnsupportedMessageWarnings,
+    context: Arc<Context>,
+) {
+    let message: DirectMessage = match serde_json::from_value(message_data.data.clone()) {
+        Ok(message) => message,
+        Err(_) => {
+            let unsupported_message_data = UnsupportedMessageData {
+                msg_id: message_data.msg_id,
+                dna_hash: message_data.dna_hash,
+                to_agent_id: message_data.from_agent_id,
+                from_agent_id: message_data.to_agent_id,
+                method: String::from("handleSend"),
+                address: None,
+                seen_version: PROTOCOL_VERSION,
+                supported: PROTOCOL_VERSION,
+            };
+            handle_unsupported_message(unsupported_message_data, warnings, context);
+            return;
+        }
+    };
 
     match message {
         DirectMessage::Custom(_) => context.log("DirectMessage::Custom not implemented"),
@@ -23,59 +54,102 @@ pub fn handle_send(message_data: MessageData, context: Arc<Context>) {
             // Async functions only get executed when they are polled.
             // I don't want to wait for this workflow to finish here as it would block the
             // network thread, so I use block_on to poll the async function but do that in
-            // another thread:
-            thread::spawn(move || {
-                block_on(respond_validation_package_request(
-                    Address::from(message_data.from_agent_id),
-                    message_data.msg_id,
-                    address,
-                    context.clone(),
-                ));
-            });
+            // a task of its own.
+            // If it panics, the requester gets told that there is no validation package.
+            let requester = Address::from(message_data.from_agent_id);
+            let msg_id = RequestId::from(message_data.msg_id);
+            let (fail_requester, fail_msg_id, fail_context) =
+                (requester.clone(), msg_id.clone(), context.clone());
+            context.clone().spawn_request_task(
+                msg_id.to_string(),
+                move || {
+                    block_on(respond_validation_package_request(
+                        requester,
+                        msg_id,
+                        address,
+                        context.clone(),
+                    ));
+                },
+                move || {
+                    respond_validation_package(fail_requester, fail_msg_id, None, &fail_context)
+                },
+            );
         }
         DirectMessage::ValidationPackage(_) => context.log(
             "Got DirectMessage::ValidationPackage as initial message. This should not happen.",
         ),
+        DirectMessage::ValidationFailureNotice {
+            address,
+            reason,
+            holder,
+        } => {
+            // Only the holder itself can tell us that it rejected the entry
+            if holder.to_string() != message_data.from_agent_id {
+                context.log(format!(
+                    "Ignoring validation failure notice for {} from {} on behalf of {}",
+                    address, message_data.from_agent_id, holder
+                ));
+                return;
+            }
+            let action_wrapper = ActionWrapper::new(Action::HandleValidationFailureNotice((
+                address, holder, reason,
+            )));
+            dispatch_action(&context.action_channel, action_wrapper);
+        }
     };
 }
 
 /// We got a ProtocolWrapper::SendResult, this means somebody has responded to our message
 /// -> we called and this is the answer
+/// A response we don't understand fails the request like an UnsupportedMessage reply would.
 pub fn handle_send_result(message_data: MessageData, context: Arc<Context>) {
-    let response: DirectMessage =
-        serde_json::from_str(&serde_json::to_string(&message_data.data).unwrap()).unwrap();
+    let response: DirectMessage = match serde_json::from_value(message_data.data.clone()) {
+        Ok(response) => response,
+        Err(_) => {
+            let unsupported_message_data = UnsupportedMessageData {
+                msg_id: message_data.msg_id,
+                dna_hash: message_data.dna_hash,
+                to_agent_id: message_data.to_agent_id,
+                from_agent_id: message_data.from_agent_id,
+                method: String::from("sendResult"),
+                address: None,
+                seen_version: PROTOCOL_VERSION,
+                supported: PROTOCOL_VERSION,
+            };
+            handle_unsupported_message_reply(unsupported_message_data, context);
+            return;
+        }
+    };
 
+    let id = RequestId::from(message_data.msg_id);
     let initial_message = context
         .state()
-        .unwrap()
-        .network()
-        .as_ref()
-        .direct_message_connections
-        .get(&message_data.msg_id)
-        .cloned();
+        .and_then(|state| state.network().direct_message_connections.get(&id).cloned());
 
     match response {
         DirectMessage::Custom(_) => context.log("DirectMessage::Custom not implemented"),
         DirectMessage::RequestValidationPackage(_) => context.log(
             "Got DirectMessage::RequestValidationPackage as a response. This should not happen.",
         ),
+        DirectMessage::ValidationFailureNotice { .. } => context.log(
+            "Got DirectMessage::ValidationFailureNotice as a response. This should not happen.",
+        ),
         DirectMessage::ValidationPackage(maybe_validation_package) => {
-            if initial_message.is_none() {
-                context.log("Received a validation package but could not find message ID in history. Not able to process.");
-                return;
+            match initial_message {
+                Some(DirectMessage::RequestValidationPackage(_)) => (),
+                _ => {
+                    context.log("Received a validation package but could not find message ID in history. Not able to process.");
+                    return;
+                }
             }
 
-            let initial_message = initial_message.unwrap();
-            let address = unwrap_to!(initial_message => DirectMessage::RequestValidationPackage);
-
             let action_wrapper = ActionWrapper::new(Action::HandleGetValidationPackage((
-                address.clone(),
+                id.clone(),
                 maybe_validation_package.clone(),
             )));
             dispatch_action(&context.action_channel, action_wrapper.clone());
 
-            let action_wrapper =
-                ActionWrapper::new(Action::ResolveDirectConnection(message_data.msg_id));
+            let action_wrapper = ActionWrapper::new(Action::ResolveDirectConnection(id));
             dispatch_action(&context.action_channel, action_wrapper.clone());
         }
     };
@@ -1,22 +1,137 @@
 use crate::{
+    action::{Action, ActionWrapper, DirectMessageData},
     context::Context,
-    dht::actions::{add_link::add_link, hold::hold_entry},
-    network::entry_with_header::EntryWithHeader,
+    instance::dispatch_action,
+    network::{
+        direct_message::DirectMessage, entry_with_header::EntryWithHeader,
+        sharding::is_in_neighborhood, state::RequestId,
+    },
+    nucleus::actions::get_entry::get_entry_with_meta,
+    workflows::{
+        hold_entry::hold_entry_workflow,
+        hold_link::{hold_link_remove_workflow, hold_link_workflow},
+        update_agent::hold_identity_update_workflow,
+    },
 };
 use futures::executor::block_on;
 use holochain_core_types::{
-    cas::content::Address,
+    cas::content::{Address, AddressableContent},
     crud_status::{CrudStatus, LINK_NAME, STATUS_NAME},
     entry::Entry,
+    error::HolochainError,
 };
-use holochain_net_connection::protocol_wrapper::{DhtData, DhtMetaData};
+use holochain_net_connection::protocol_wrapper::{DhtData, DhtMetaData, HoldAckData};
 use std::sync::Arc;
 
 /// The network requests us to store (i.e. hold) the given entry.
+/// Entries that are to be held by only some nodes are declined unless we are among the
+/// ones closest to them, see `sharding::neighborhood()`.
+/// Once it is held, the publisher gets told with a hold acknowledgement.
+/// If it fails validation, the publisher gets told why with a validation failure notice.
+/// Entries whose validation waits for dependencies don't get acknowledged, not even
+/// once they are held later on. Links get acknowledged once they got added to their base,
+/// see `handle_store_dht_meta()`.
 pub fn handle_store_dht(dht_data: DhtData, context: Arc<Context>) {
-    let entry_with_header: EntryWithHeader =
-        serde_json::from_str(&serde_json::to_string(&dht_data.content).unwrap()).unwrap();
-    let _ = block_on(hold_entry(&entry_with_header.entry_body, &context.clone()));
+    let address = Address::from(dht_data.address.clone());
+    if !is_in_neighborhood(&context, &address, dht_data.redundancy) {
+        context.log(format!(
+            "Not holding entry {}: out of its neighborhood of {} nodes",
+            address, dht_data.redundancy
+        ));
+        return;
+    }
+    let entry_with_header: EntryWithHeader = match serde_json::from_value(dht_data.content.clone())
+    {
+        Ok(entry_with_header) => entry_with_header,
+        Err(error) => {
+            context.log(format!(
+                "Not holding entry {} of request {}: {}",
+                address, dht_data.msg_id, error
+            ));
+            return;
+        }
+    };
+    // Validating the entry needs the network to get its validation package and
+    // dependencies, so don't block the network thread.
+    // Nobody waits for a response, so a panic only gets logged.
+    let msg_id = dht_data.msg_id.clone();
+    context.clone().spawn_request_task(
+        msg_id,
+        move || {
+            if let Err(error) = block_on(hold_entry_workflow(&entry_with_header, &context)) {
+                context.log(format!("Not holding entry: {}", error));
+                if let HolochainError::ValidationFailed(reason) = error {
+                    notify_validation_failure(&dht_data.agent_id, &address, reason, &context);
+                }
+                return;
+            }
+            if let Entry::LinkAdd(_) = entry_with_header.entry_body {
+                return;
+            }
+            // Not held yet if its validation waits for dependencies
+            if let Ok(Some(_)) = get_entry_with_meta(&context, address) {
+                acknowledge_hold(
+                    &dht_data.msg_id,
+                    &dht_data.dna_hash,
+                    &dht_data.agent_id,
+                    &dht_data.address,
+                    &context,
+                );
+            }
+        },
+        || (),
+    );
+}
+
+fn acknowledge_hold(
+    msg_id: &str,
+    dna_hash: &str,
+    publisher: &str,
+    address: &str,
+    context: &Arc<Context>,
+) {
+    let action_wrapper = ActionWrapper::new(Action::AcknowledgeHold(HoldAckData {
+        msg_id: msg_id.to_string(),
+        dna_hash: dna_hash.to_string(),
+        to_agent_id: publisher.to_string(),
+        // filled in by the reducer
+        from_agent_id: String::new(),
+        address: address.to_string(),
+    }));
+    dispatch_action(&context.action_channel, action_wrapper);
+}
+
+/// Tells the publisher of an entry we were asked to hold why it failed our validation
+fn notify_validation_failure(
+    publisher: &str,
+    address: &Address,
+    reason: String,
+    context: &Arc<Context>,
+) {
+    let agent_id = context
+        .state()
+        .and_then(|state| state.network().agent_id.clone());
+    let holder = match agent_id {
+        Some(agent_id) => Address::from(agent_id),
+        None => return,
+    };
+    let action_wrapper = ActionWrapper::new(Action::SendDirectMessage(DirectMessageData {
+        address: Address::from(publisher.to_string()),
+        message: DirectMessage::ValidationFailureNotice {
+            address: address.clone(),
+            reason,
+            holder,
+        },
+        msg_id: RequestId::new(),
+        is_response: false,
+    }));
+    dispatch_action(&context.action_channel, action_wrapper);
+}
+
+/// A holder of an entry we published tells us that it holds it.
+pub fn handle_hold_ack(hold_ack_data: HoldAckData, context: Arc<Context>) {
+    let action_wrapper = ActionWrapper::new(Action::HandleHoldAck(hold_ack_data));
+    dispatch_action(&context.action_channel, action_wrapper);
 }
 
 /// The network requests us to store meta information (links/CRUD/etc) for an
@@ -24,33 +139,93 @@ pub fn handle_store_dht(dht_data: DhtData, context: Arc<Context>) {
 pub fn handle_store_dht_meta(dht_meta_data: DhtMetaData, context: Arc<Context>) {
     match dht_meta_data.attribute.as_ref() {
         "link" => {
-            let entry_with_header: EntryWithHeader = serde_json::from_str(
-                &serde_json::to_string(&dht_meta_data.content)
-                    .expect("dht_meta_data should be EntryWithHader"),
-            )
-            .expect("dht_meta_data should be EntryWithHader");
-            let link_add = match entry_with_header.entry_body {
-                Entry::LinkAdd(link_add) => link_add,
-                _ => unreachable!(),
+            let entry_with_header: EntryWithHeader =
+                match serde_json::from_value(dht_meta_data.content.clone()) {
+                    Ok(entry_with_header) => entry_with_header,
+                    Err(error) => {
+                        context.log(format!(
+                            "Not holding link of request {}: {}",
+                            dht_meta_data.msg_id, error
+                        ));
+                        return;
+                    }
+                };
+            match entry_with_header.entry_body {
+                Entry::LinkAdd(_) | Entry::LinkRemove(_) => (),
+                _ => {
+                    context.log(format!(
+                        "Not holding link of request {}: not a link",
+                        dht_meta_data.msg_id
+                    ));
+                    return;
+                }
             };
-            let link = link_add.link().clone();
-            let _ = block_on(add_link(&link, &context.clone()));
+            // Validating the link might need the network to get the link's entries,
+            // so don't block the network thread
+            let dht_meta_data = dht_meta_data.clone();
+            let msg_id = dht_meta_data.msg_id.clone();
+            context.clone().spawn_request_task(
+                msg_id,
+                move || {
+                    let held = match entry_with_header.entry_body {
+                        Entry::LinkRemove(_) => {
+                            block_on(hold_link_remove_workflow(&entry_with_header, &context))
+                        }
+                        _ => block_on(hold_link_workflow(&entry_with_header, &context)),
+                    };
+                    if let Err(error) = held {
+                        context.log(format!("Not holding link: {}", error));
+                        return;
+                    }
+                    acknowledge_hold(
+                        &dht_meta_data.msg_id,
+                        &dht_meta_data.dna_hash,
+                        &dht_meta_data.agent_id,
+                        &entry_with_header.entry_body.address().to_string(),
+                        &context,
+                    );
+                },
+                || (),
+            );
         }
         STATUS_NAME => {
-            let _crud_status: CrudStatus = serde_json::from_str(
-                &serde_json::to_string(&dht_meta_data.content)
-                    .expect("dht_meta_data should be crud_status"),
-            )
-            .expect("dht_meta_data should be crud_status");
+            let _crud_status: CrudStatus =
+                match serde_json::from_value(dht_meta_data.content.clone()) {
+                    Ok(crud_status) => crud_status,
+                    Err(error) => {
+                        context.log(format!(
+                            "Not holding crud-status of request {}: {}",
+                            dht_meta_data.msg_id, error
+                        ));
+                        return;
+                    }
+                };
             // FIXME: block_on hold crud_status metadata in DHT?
         }
         LINK_NAME => {
-            let _crud_link: Address = serde_json::from_str(
-                &serde_json::to_string(&dht_meta_data.content)
-                    .expect("dht_meta_data should be crud_link"),
-            )
-            .expect("dht_meta_data should be crud_link");
-            // FIXME: block_on hold crud_link metadata in DHT?
+            // Only identity updates get held so far, whose crud-link carries the new
+            // identity with its signed header. Links of other updates are plain addresses.
+            let update: EntryWithHeader =
+                match serde_json::from_value(dht_meta_data.content.clone()) {
+                    Ok(update) => update,
+                    Err(_) => return,
+                };
+            // Checking it might need the network to get the replaced identity,
+            // so don't block the network thread
+            let msg_id = dht_meta_data.msg_id.clone();
+            context.clone().spawn_request_task(
+                msg_id,
+                move || {
+                    if let Err(error) = block_on(hold_identity_update_workflow(
+                        &Address::from(dht_meta_data.address.clone()),
+                        &update,
+                        &context,
+                    )) {
+                        context.log(format!("Not holding identity update: {}", error));
+                    }
+                },
+                || (),
+            );
         }
         _ => {}
     }
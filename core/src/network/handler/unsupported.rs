@@ -0,0 +1,99 @@
+use crate::{
+    action::{Action, ActionWrapper},
+    context::Context,
+    instance::dispatch_action,
+};
+use holochain_net_connection::protocol_wrapper::UnsupportedMessageData;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Minimum time between two warnings about the same message type from the same peer
+const UNSUPPORTED_MESSAGE_WARNING_INTERVAL_SECS: u64 = 60;
+
+/// Remembers when we last warned about which peer sending which unsupported message type,
+/// so that a chatty peer with a newer protocol version can't flood the log.
+pub struct UnsupportedMessageWarnings {
+    last_warnings: HashMap<(String, String), Instant>,
+}
+
+impl UnsupportedMessageWarnings {
+    pub fn new() -> Self {
+        UnsupportedMessageWarnings {
+            last_warnings: HashMap::new(),
+        }
+    }
+
+    /// Returns true if a warning about the given peer and message type is due at `now`
+    pub fn should_warn(&mut self, peer: &str, method: &str, now: Instant) -> bool {
+        let key = (peer.to_string(), method.to_string());
+        let due = self
+            .last_warnings
+            .get(&key)
+            .map(|last| {
+                now.duration_since(*last)
+                    >= Duration::from_secs(UNSUPPORTED_MESSAGE_WARNING_INTERVAL_SECS)
+            })
+            .unwrap_or(true);
+        if due {
+            self.last_warnings.insert(key, now);
+        }
+        due
+    }
+}
+
+/// Another node sent us a message we can't handle (unknown type or newer protocol version).
+/// Instead of failing we tell that node, so it does not wait for an answer until it times out.
+pub fn handle_unsupported_message(
+    unsupported_message_data: UnsupportedMessageData,
+    warnings: &mut UnsupportedMessageWarnings,
+    context: Arc<Context>,
+) {
+    if warnings.should_warn(
+        &unsupported_message_data.to_agent_id,
+        &unsupported_message_data.method,
+        Instant::now(),
+    ) {
+        context.log(format!(
+            "Peer {} sent unsupported message {} of protocol version {} (supported: {})",
+            unsupported_message_data.to_agent_id,
+            unsupported_message_data.method,
+            unsupported_message_data.seen_version,
+            unsupported_message_data.supported,
+        ));
+    }
+    let action_wrapper =
+        ActionWrapper::new(Action::RespondUnsupportedMessage(unsupported_message_data));
+    dispatch_action(&context.action_channel, action_wrapper);
+}
+
+/// Another node could not handle a message we sent.
+pub fn handle_unsupported_message_reply(
+    unsupported_message_data: UnsupportedMessageData,
+    context: Arc<Context>,
+) {
+    let action_wrapper =
+        ActionWrapper::new(Action::HandleUnsupportedMessage(unsupported_message_data));
+    dispatch_action(&context.action_channel, action_wrapper);
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn warnings_are_rate_limited_per_peer_and_message_type() {
+        let mut warnings = UnsupportedMessageWarnings::new();
+        let now = Instant::now();
+        assert!(warnings.should_warn("bob", "getDhtLinks", now));
+        assert!(!warnings.should_warn("bob", "getDhtLinks", now));
+        assert!(warnings.should_warn("bob", "gossip", now));
+        assert!(warnings.should_warn("carol", "getDhtLinks", now));
+
+        let later = now + Duration::from_secs(UNSUPPORTED_MESSAGE_WARNING_INTERVAL_SECS);
+        assert!(warnings.should_warn("bob", "getDhtLinks", later));
+        assert!(!warnings.should_warn("bob", "getDhtLinks", later));
+    }
+}
@@ -2,14 +2,28 @@ pub mod actions;
 pub mod direct_message;
 pub mod entry_with_header;
 pub mod handler;
+pub mod published;
 pub mod reducers;
+pub mod sharding;
 pub mod state;
+pub mod traffic;
 
 #[cfg(test)]
 pub mod tests {
     use crate::{
+        context::Context,
         instance::tests::test_instance_and_context_by_name,
-        network::actions::{get_entry::get_entry, get_validation_package::get_validation_package},
+        network::{
+            actions::{
+                await_publish::await_publish, get_entry::get_entry,
+                get_validation_package::get_validation_package,
+            },
+            sharding::neighborhood,
+        },
+        nucleus::actions::{
+            get_entry::get_entry_with_meta,
+            tests::{instance_by_name, test_dna},
+        },
         workflows::author_entry::author_entry,
     };
     use futures::executor::block_on;
@@ -18,6 +32,7 @@ pub mod tests {
         crud_status::{create_crud_status_eav, CrudStatus},
         entry::test_entry,
     };
+    use std::{sync::Arc, thread, time::Duration};
     use test_utils::*;
 
     #[test]
@@ -75,8 +90,70 @@ pub mod tests {
     }
 
     #[test]
-    fn get_validation_package_roundtrip() {
-        let wat = r#"
+    fn entries_with_redundancy_are_held_by_their_neighborhood() {
+        let mut dna = test_dna();
+        dna.uuid = String::from("entries_with_redundancy_are_held_by_their_neighborhood");
+        dna.properties = json!({"redundancy": 2});
+        let nodes: Vec<_> = vec!["alice4", "bob4", "carol4", "dave4", "eve4"]
+            .into_iter()
+            .map(|name| instance_by_name(name, dna.clone()))
+            .collect();
+        let contexts: Vec<Arc<Context>> =
+            nodes.iter().map(|(_, context)| context.clone()).collect();
+        let agent_ids: Vec<String> = contexts
+            .iter()
+            .map(|context| context.state().unwrap().network().agent_id.clone().unwrap())
+            .collect();
+
+        // neighborhoods only agree once everybody knows everybody
+        for context in contexts.iter() {
+            let mut peers_count = 0;
+            for _ in 0..100 {
+                peers_count = context.state().unwrap().network().peers.len();
+                if peers_count == 4 {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+            assert_eq!(peers_count, 4);
+        }
+
+        let publisher = &contexts[0];
+        let address = block_on(author_entry(&test_entry(), None, publisher))
+            .expect("Could not author entry")
+            .address;
+        block_on(await_publish(
+            &address,
+            2,
+            Duration::from_secs(10),
+            publisher,
+        ))
+        .expect("The neighborhood should hold the entry");
+        // give the nodes that decline time to show they don't
+        thread::sleep(Duration::from_millis(1000));
+        assert_eq!(
+            publisher.state().unwrap().network().holders_count(&address),
+            2
+        );
+
+        let holders = neighborhood(&address, &agent_ids, 2);
+        let requester = contexts
+            .iter()
+            .zip(agent_ids.iter())
+            .skip(1)
+            .find(|(_, agent_id)| !holders.contains(agent_id))
+            .map(|(context, _)| context)
+            .expect("There are more nodes than holders");
+        assert_eq!(get_entry_with_meta(requester, address.clone()), Ok(None));
+
+        let entry_with_meta = block_on(get_entry(requester, &address))
+            .expect("Could not get entry")
+            .expect("The holders should have the entry");
+        assert_eq!(entry_with_meta.entry, test_entry());
+    }
+
+    /// Validation callbacks that accept everything and ask for packages of only the entry
+    const ENTRY_PACKAGE_WAT: &str = r#"
 (module
 
     (memory 1)
@@ -143,7 +220,9 @@ pub mod tests {
 )
                 "#;
 
-        let mut dna = create_test_dna_with_wat("test_zome", "test_cap", Some(wat));
+    #[test]
+    fn get_validation_package_roundtrip() {
+        let mut dna = create_test_dna_with_wat("test_zome", "test_cap", Some(ENTRY_PACKAGE_WAT));
         dna.uuid = String::from("get_validation_package_roundtrip");
         let (_, context1) = test_instance_and_context_by_name(dna.clone(), "alice1").unwrap();
 
@@ -166,4 +245,34 @@ pub mod tests {
         let validation_package = maybe_validation_package.unwrap();
         assert_eq!(validation_package.chain_header, Some(header));
     }
+
+    #[test]
+    fn validation_packages_get_built_once() {
+        let mut dna = create_test_dna_with_wat("test_zome", "test_cap", Some(ENTRY_PACKAGE_WAT));
+        dna.uuid = String::from("validation_packages_get_built_once");
+        let (_, context1) = test_instance_and_context_by_name(dna.clone(), "alice1").unwrap();
+
+        let entry = test_entry();
+        block_on(author_entry(&entry, None, &context1)).expect("Could not author entry");
+        let agent1_state = context1.state().unwrap().agent();
+        let header = agent1_state
+            .chain()
+            .iter_type(&agent1_state.top_chain_header(), &entry.entry_type())
+            .find(|h| h.entry_address() == &entry.address())
+            .expect("There must be a header in the author's source chain after commit");
+
+        let (_, context2) = test_instance_and_context_by_name(dna.clone(), "bob1").unwrap();
+        let misses_before = context1.validation_packages.stats().misses;
+        for _ in 0..5 {
+            let validation_package = block_on(get_validation_package(header.clone(), &context2))
+                .expect("Could not get validation package")
+                .expect("Alice should have a validation package");
+            assert_eq!(validation_package.chain_header, Some(header.clone()));
+        }
+
+        let stats = context1.validation_packages.stats();
+        assert_eq!(stats.misses - misses_before, 1);
+        assert!(stats.hits >= 4);
+        assert_eq!(stats.packages, 1);
+    }
 }
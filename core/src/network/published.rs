@@ -0,0 +1,109 @@
+//! Persistence of what the agent published. Once a holder acknowledged an entry, the meta it
+//! got published with gets stored as a record of its own, so that a restarted instance does
+//! not publish it again, see `NetworkState::published`. Storing a record per ack instead of
+//! all of them with the agent state snapshot keeps long chains from rewriting the whole map
+//! on every commit.
+
+use crate::{context::Context, network::state::PublishedMeta};
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent, Content},
+    eav::EntityAttributeValue,
+    error::{HcResult, HolochainError},
+    json::JsonString,
+};
+use std::{collections::HashMap, convert::TryFrom, sync::Arc};
+
+/// Attribute of the EAVs that point from the entries to their records
+pub const PUBLISHED_ATTRIBUTE: &str = "published";
+
+/// The meta the entry with the given address got published with, as stored
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, DefaultJson)]
+pub struct PublishedRecord {
+    pub address: Address,
+    pub meta: PublishedMeta,
+}
+
+impl AddressableContent for PublishedRecord {
+    fn content(&self) -> Content {
+        self.to_owned().into()
+    }
+
+    fn try_from_content(content: &Content) -> Result<Self, HolochainError> {
+        Self::try_from(content.to_owned())
+    }
+
+    // derived from the entry address, so that newer meta replaces the record
+    fn address(&self) -> Address {
+        Address::from(format!("{}-{}", PUBLISHED_ATTRIBUTE, self.address))
+    }
+}
+
+/// Stores the meta the entry with the given address got published with
+pub fn store_published(
+    context: &Arc<Context>,
+    address: &Address,
+    meta: PublishedMeta,
+) -> HcResult<()> {
+    let record = PublishedRecord {
+        address: address.clone(),
+        meta,
+    };
+    context.file_storage.write()?.add(&record)?;
+    context
+        .eav_storage
+        .write()?
+        .add_eav(&EntityAttributeValue::new(
+            address,
+            &PUBLISHED_ATTRIBUTE.to_string(),
+            &record.address(),
+        ))
+}
+
+/// The meta all entries that got stored with `store_published()` got published with
+pub fn load_published(context: &Arc<Context>) -> HcResult<HashMap<Address, PublishedMeta>> {
+    let eavs = context.eav_storage.read()?.fetch_eav(
+        None,
+        Some(PUBLISHED_ATTRIBUTE.to_string()),
+        None,
+    )?;
+    let storage = context.file_storage.read()?;
+    let mut published = HashMap::new();
+    for eav in eavs {
+        if let Some(content) = storage.fetch(&eav.value())? {
+            let record = PublishedRecord::try_from_content(&content)?;
+            published.insert(record.address, record.meta);
+        }
+    }
+    Ok(published)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::instance::tests::test_context;
+    use holochain_core_types::crud_status::CrudStatus;
+
+    fn meta(crud_status: CrudStatus) -> PublishedMeta {
+        PublishedMeta {
+            crud_status,
+            crud_link: None,
+            sources: vec![Address::from("alice")],
+        }
+    }
+
+    #[test]
+    fn stored_publishes_load_with_their_latest_meta() {
+        let context = test_context("alice");
+        assert!(load_published(&context).unwrap().is_empty());
+
+        let (a, b) = (Address::from("QmA"), Address::from("QmB"));
+        store_published(&context, &a, meta(CrudStatus::LIVE)).unwrap();
+        store_published(&context, &b, meta(CrudStatus::LIVE)).unwrap();
+        store_published(&context, &a, meta(CrudStatus::DELETED)).unwrap();
+
+        let published = load_published(&context).unwrap();
+        assert_eq!(published.len(), 2);
+        assert_eq!(published[&a], meta(CrudStatus::DELETED));
+        assert_eq!(published[&b], meta(CrudStatus::LIVE));
+    }
+}
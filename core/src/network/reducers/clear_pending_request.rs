@@ -0,0 +1,49 @@
+use crate::{action::ActionWrapper, context::Context, network::state::NetworkState};
+use std::sync::Arc;
+
+/// Request IDs are unique, so whichever kind of request it was gets removed.
+pub fn reduce_clear_pending_request(
+    _context: Arc<Context>,
+    network_state: &mut NetworkState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let id = unwrap_to!(action => crate::action::Action::ClearPendingRequest);
+
+    network_state.get_entry_with_meta_results.remove(id);
+    network_state.get_entry_sources.remove(id);
+    network_state.get_validation_package_results.remove(id);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        action::{Action, ActionWrapper},
+        instance::tests::test_context,
+        network::state::RequestId,
+        state::test_store,
+    };
+    use holochain_core_types::{cas::content::AddressableContent, entry::test_entry};
+
+    #[test]
+    pub fn reduce_clear_pending_request_removes_result() {
+        let context = test_context("alice");
+        let store = test_store(context.clone());
+
+        // Without network the GET fails right away, but the result stays until cleared
+        let id = RequestId::new();
+        let action_wrapper =
+            ActionWrapper::new(Action::GetEntry((test_entry().address(), id.clone())));
+        let store = store.reduce(context.clone(), action_wrapper);
+        assert!(store
+            .network()
+            .get_entry_with_meta_results
+            .result(&id)
+            .unwrap()
+            .is_err());
+
+        let action_wrapper = ActionWrapper::new(Action::ClearPendingRequest(id));
+        let store = store.reduce(context.clone(), action_wrapper);
+        assert!(store.network().get_entry_with_meta_results.is_empty());
+    }
+}
@@ -1,42 +1,80 @@
 use crate::{
     action::ActionWrapper,
     context::Context,
-    network::{reducers::send, state::NetworkState},
+    network::{
+        reducers::send,
+        sharding::{get_redundancy, neighborhood},
+        state::{NetworkState, RequestId},
+    },
 };
 use holochain_core_types::{cas::content::Address, error::HolochainError};
 use holochain_net_connection::protocol_wrapper::{GetDhtData, ProtocolWrapper};
-use std::sync::Arc;
+use std::{collections::VecDeque, sync::Arc};
 
-fn inner(network_state: &mut NetworkState, address: &Address) -> Result<(), HolochainError> {
+fn inner(
+    network_state: &mut NetworkState,
+    address: &Address,
+    id: &RequestId,
+) -> Result<(), HolochainError> {
     network_state.initialized()?;
+    send_get_dht(network_state, address, id)
+}
+
+/// The nodes that hold the entry with the given address if it is not held by every node,
+/// closest first, to ask one after the other. Empty if the network can ask anyone.
+fn sources(network_state: &NetworkState, address: &Address, redundancy: u32) -> VecDeque<String> {
+    if redundancy == 0 {
+        return VecDeque::new();
+    }
+    neighborhood(address, &network_state.nodes(), redundancy)
+        .into_iter()
+        .filter(|agent_id| network_state.agent_id.as_ref() != Some(agent_id))
+        .collect()
+}
 
+/// Asks the network for the entry with the given address.
+/// The answer comes back with the given request ID.
+/// Entries that are not held by every node are asked from the source the request is at.
+/// Also used to ask again for entries we were still waiting for when the connection
+/// broke down.
+pub fn send_get_dht(
+    network_state: &mut NetworkState,
+    address: &Address,
+    id: &RequestId,
+) -> Result<(), HolochainError> {
     send(
         network_state,
         ProtocolWrapper::GetDht(GetDhtData {
-            msg_id: "?".to_string(),
+            msg_id: id.to_string(),
             dna_hash: network_state.dna_hash.clone().unwrap(),
             from_agent_id: network_state.agent_id.clone().unwrap(),
             address: address.to_string(),
+            to_agent_id: network_state
+                .get_entry_sources
+                .get(id)
+                .and_then(|sources| sources.front().cloned()),
         }),
     )
 }
 
 pub fn reduce_get_entry(
-    _context: Arc<Context>,
+    context: Arc<Context>,
     network_state: &mut NetworkState,
     action_wrapper: &ActionWrapper,
 ) {
     let action = action_wrapper.action();
-    let address = unwrap_to!(action => crate::action::Action::GetEntry);
-
-    let result = match inner(network_state, &address) {
-        Ok(()) => None,
-        Err(err) => Some(Err(err)),
-    };
+    let (address, id) = unwrap_to!(action => crate::action::Action::GetEntry);
 
     network_state
         .get_entry_with_meta_results
-        .insert(address.clone(), result);
+        .insert(id.clone(), address.clone());
+    let sources = sources(network_state, address, get_redundancy(&context));
+    if !sources.is_empty() {
+        network_state.get_entry_sources.insert(id.clone(), sources);
+    }
+    if let Err(err) = inner(network_state, address, id) {
+        network_state.get_entry_with_meta_results.fail(id, err);
+    }
 }
 
 pub fn reduce_get_entry_timeout(
@@ -45,26 +83,9 @@ pub fn reduce_get_entry_timeout(
     action_wrapper: &ActionWrapper,
 ) {
     let action = action_wrapper.action();
-    let address = unwrap_to!(action => crate::action::Action::GetEntryTimeout);
-
-    if network_state
-        .get_entry_with_meta_results
-        .get(address)
-        .is_none()
-    {
-        return;
-    }
+    let id = unwrap_to!(action => crate::action::Action::GetEntryTimeout);
 
-    if network_state
-        .get_entry_with_meta_results
-        .get(address)
-        .unwrap()
-        .is_none()
-    {
-        network_state
-            .get_entry_with_meta_results
-            .insert(address.clone(), Some(Err(HolochainError::Timeout)));
-    }
+    network_state.get_entry_with_meta_results.timeout(id);
 }
 
 #[cfg(test)]
@@ -74,6 +95,7 @@ mod tests {
         action::{Action, ActionWrapper, NetworkSettings},
         context::mock_network_config,
         instance::tests::test_context,
+        network::{state::RequestId, traffic::RateLimit},
         state::test_store,
     };
     use holochain_core_types::{
@@ -88,14 +110,15 @@ mod tests {
         let store = test_store(context.clone());
 
         let entry = test_entry();
-        let action_wrapper = ActionWrapper::new(Action::GetEntry(entry.address()));
+        let id = RequestId::new();
+        let action_wrapper = ActionWrapper::new(Action::GetEntry((entry.address(), id.clone())));
 
         let store = store.reduce(context.clone(), action_wrapper);
         let maybe_get_entry_result = store
             .network()
             .get_entry_with_meta_results
-            .get(&entry.address())
-            .map(|result| result.clone());
+            .get(&id)
+            .map(|request| request.result.clone());
         assert_eq!(
             maybe_get_entry_result,
             Some(Some(Err(HolochainError::ErrorGeneric(
@@ -115,18 +138,20 @@ mod tests {
             config: mock_network_config(),
             dna_hash: String::from("abcd"),
             agent_id: String::from("abcd"),
+            rate_limit: RateLimit::default(),
         }));
         let store = store.reduce(context.clone(), action_wrapper);
 
         let entry = test_entry();
-        let action_wrapper = ActionWrapper::new(Action::GetEntry(entry.address()));
+        let id = RequestId::new();
+        let action_wrapper = ActionWrapper::new(Action::GetEntry((entry.address(), id.clone())));
 
         let store = store.reduce(context.clone(), action_wrapper);
         let maybe_get_entry_result = store
             .network()
             .get_entry_with_meta_results
-            .get(&entry.address())
-            .map(|result| result.clone());
+            .get(&id)
+            .map(|request| request.result.clone());
         assert_eq!(maybe_get_entry_result, Some(None));
     }
 
@@ -142,6 +167,7 @@ mod tests {
             config: mock_network_config(),
             dna_hash: String::from("abcd"),
             agent_id: String::from("abcd"),
+            rate_limit: RateLimit::default(),
         }));
 
         {
@@ -150,7 +176,8 @@ mod tests {
         }
 
         let entry = test_entry();
-        let action_wrapper = ActionWrapper::new(Action::GetEntry(entry.address()));
+        let id = RequestId::new();
+        let action_wrapper = ActionWrapper::new(Action::GetEntry((entry.address(), id.clone())));
 
         {
             let mut new_store = store.write().unwrap();
@@ -161,11 +188,11 @@ mod tests {
             .unwrap()
             .network()
             .get_entry_with_meta_results
-            .get(&entry.address())
-            .map(|result| result.clone());
+            .get(&id)
+            .map(|request| request.result.clone());
         assert_eq!(maybe_get_entry_result, Some(None));
 
-        let action_wrapper = ActionWrapper::new(Action::GetEntryTimeout(entry.address()));
+        let action_wrapper = ActionWrapper::new(Action::GetEntryTimeout(id.clone()));
         {
             let mut new_store = store.write().unwrap();
             *new_store = new_store.reduce(context.clone(), action_wrapper);
@@ -175,21 +202,17 @@ mod tests {
             .unwrap()
             .network()
             .get_entry_with_meta_results
-            .get(&entry.address())
-            .map(|result| result.clone());
+            .get(&id)
+            .map(|request| request.result.clone());
         assert_eq!(
             maybe_get_entry_result,
             Some(Some(Err(HolochainError::Timeout)))
         );
 
         // test that an existing result does not get overwritten by timeout signal
-        let entry_with_meta = EntryWithMeta {
-            entry: entry.clone(),
-            crud_status: CrudStatus::LIVE,
-            maybe_crud_link: None,
-        };
+        let entry_with_meta = EntryWithMeta::new(entry.clone(), CrudStatus::LIVE, None);
         let dht_data = DhtData {
-            msg_id: String::from(""),
+            msg_id: id.to_string(),
             dna_hash: String::from(""),
             agent_id: String::from(""),
             address: entry.address().to_string(),
@@ -197,6 +220,7 @@ mod tests {
                 &serde_json::to_string(&Some(entry_with_meta.clone())).unwrap(),
             )
             .unwrap(),
+            redundancy: 0,
         };
 
         let action_wrapper = ActionWrapper::new(Action::HandleGetResult(dht_data));
@@ -209,15 +233,15 @@ mod tests {
             .unwrap()
             .network()
             .get_entry_with_meta_results
-            .get(&entry.address())
-            .map(|result| result.clone());
+            .get(&id)
+            .map(|request| request.result.clone());
         assert!(maybe_entry_with_meta_result.is_some());
         let maybe_entry_with_meta = maybe_entry_with_meta_result.unwrap().unwrap();
         let entry_with_meta = maybe_entry_with_meta.unwrap().unwrap();
         assert_eq!(entry_with_meta.entry, entry.clone());
 
         // Ok we got a positive result in the state
-        let action_wrapper = ActionWrapper::new(Action::GetEntryTimeout(entry.address()));
+        let action_wrapper = ActionWrapper::new(Action::GetEntryTimeout(id.clone()));
         {
             let mut new_store = store.write().unwrap();
             *new_store = new_store.reduce(context.clone(), action_wrapper);
@@ -227,8 +251,8 @@ mod tests {
             .unwrap()
             .network()
             .get_entry_with_meta_results
-            .get(&entry.address())
-            .map(|result| result.clone());
+            .get(&id)
+            .map(|request| request.result.clone());
         // The timeout should not have overwritten the entry
         assert!(maybe_entry_with_meta_result.is_some());
         let maybe_entry_with_meta = maybe_entry_with_meta_result.unwrap().unwrap();
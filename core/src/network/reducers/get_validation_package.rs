@@ -1,12 +1,20 @@
 use crate::{
     action::ActionWrapper,
     context::Context,
-    network::{direct_message::DirectMessage, reducers::send_message, state::NetworkState},
+    network::{
+        direct_message::DirectMessage,
+        reducers::send_message,
+        state::{NetworkState, RequestId},
+    },
 };
 use holochain_core_types::{chain_header::ChainHeader, error::HolochainError};
 use std::sync::Arc;
 
-fn inner(network_state: &mut NetworkState, header: &ChainHeader) -> Result<(), HolochainError> {
+fn inner(
+    network_state: &mut NetworkState,
+    header: &ChainHeader,
+    id: &RequestId,
+) -> Result<(), HolochainError> {
     network_state.initialized()?;
 
     let source_address = header
@@ -17,7 +25,7 @@ fn inner(network_state: &mut NetworkState, header: &ChainHeader) -> Result<(), H
         ))?;
     let direct_message = DirectMessage::RequestValidationPackage(header.entry_address().clone());
 
-    send_message(network_state, source_address, direct_message)
+    send_message(network_state, source_address, direct_message, id.clone())
 }
 
 pub fn reduce_get_validation_package(
@@ -26,15 +34,12 @@ pub fn reduce_get_validation_package(
     action_wrapper: &ActionWrapper,
 ) {
     let action = action_wrapper.action();
-    let header = unwrap_to!(action => crate::action::Action::GetValidationPackage);
-    let entry_address = header.entry_address().clone();
-
-    let result = match inner(network_state, header) {
-        Ok(()) => None,
-        Err(err) => Some(Err(err)),
-    };
+    let (header, id) = unwrap_to!(action => crate::action::Action::GetValidationPackage);
 
     network_state
         .get_validation_package_results
-        .insert(entry_address, result);
+        .insert(id.clone(), header.entry_address().clone());
+    if let Err(err) = inner(network_state, header, id) {
+        network_state.get_validation_package_results.fail(id, err);
+    }
 }
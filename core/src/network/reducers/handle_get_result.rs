@@ -1,5 +1,16 @@
-use crate::{action::ActionWrapper, context::Context, network::state::NetworkState};
-use holochain_core_types::{cas::content::Address, entry::EntryWithMeta, error::HolochainError};
+use crate::{
+    action::ActionWrapper,
+    context::Context,
+    network::{
+        reducers::get_entry::send_get_dht,
+        state::{NetworkState, RequestId},
+    },
+};
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent},
+    entry::EntryWithMeta,
+    error::HolochainError,
+};
 use holochain_net_connection::protocol_wrapper::DhtData;
 use std::sync::Arc;
 
@@ -18,17 +29,193 @@ fn inner(
     Ok(res.unwrap())
 }
 
+/// Asks the next source for an entry the last one did not have, if there is one left.
+/// Returns whether it did.
+fn ask_next_source(network_state: &mut NetworkState, id: &RequestId, address: &Address) -> bool {
+    let has_next = match network_state.get_entry_sources.get_mut(id) {
+        Some(sources) => {
+            sources.pop_front();
+            !sources.is_empty()
+        }
+        None => false,
+    };
+    has_next && send_get_dht(network_state, address, id).is_ok()
+}
+
+/// Entries that are not held by every node are asked from one of their holders after
+/// the other, so not finding it only resolves the request once none of them had it.
+/// Entries whose content does not hash to the requested address were tampered with or made
+/// up by the holder that sent them. They never get returned: the next holder gets asked
+/// instead, and the response counts against its sender in `rejected_responses`.
 pub fn reduce_handle_get_result(
-    _context: Arc<Context>,
+    context: Arc<Context>,
     network_state: &mut NetworkState,
     action_wrapper: &ActionWrapper,
 ) {
     let action = action_wrapper.action();
     let dht_data = unwrap_to!(action => crate::action::Action::HandleGetResult);
+    let id = RequestId::from(dht_data.msg_id.clone());
+    // the address in the response is up to its sender, the one we asked for is not
+    let address = match network_state.get_entry_with_meta_results.get(&id) {
+        Some(request) => request.address.clone(),
+        None => return,
+    };
 
-    let result = inner(network_state, dht_data);
+    let mut result = inner(network_state, dht_data);
+    let tampered_address = match result {
+        Ok(Some(ref entry_with_meta)) if entry_with_meta.entry.address() != address => {
+            Some(entry_with_meta.entry.address())
+        }
+        _ => None,
+    };
+    if let Some(tampered_address) = tampered_address {
+        let sender = network_state
+            .get_entry_sources
+            .get(&id)
+            .and_then(|sources| sources.front().cloned())
+            .unwrap_or_default();
+        context.log(format!(
+            "Rejected the response of {:?} to GET {}: its entry has the address {}",
+            sender, address, tampered_address
+        ));
+        *network_state.rejected_responses.entry(sender).or_insert(0) += 1;
+        if ask_next_source(network_state, &id, &address) {
+            return;
+        }
+        result = Err(HolochainError::ErrorGeneric(format!(
+            "No holder answered with the content of entry {}",
+            address
+        )));
+    }
+    if let Ok(None) = result {
+        if ask_next_source(network_state, &id, &address) {
+            return;
+        }
+    }
 
+    network_state.get_entry_sources.remove(&id);
     network_state
         .get_entry_with_meta_results
-        .insert(Address::from(dht_data.address.clone()), Some(result));
+        .resolve(&id, result);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        action::{Action, NetworkSettings},
+        context::mock_network_config,
+        instance::tests::test_context,
+        network::traffic::RateLimit,
+        state::test_store,
+    };
+    use holochain_core_types::{
+        cas::content::AddressableContent,
+        crud_status::CrudStatus,
+        entry::{test_entry, test_entry_b, Entry},
+    };
+    use std::collections::VecDeque;
+
+    fn get_result(id: &RequestId, address: &Address, entry: Entry) -> ActionWrapper {
+        let entry_with_meta = EntryWithMeta::new(entry, CrudStatus::LIVE, None);
+        ActionWrapper::new(Action::HandleGetResult(DhtData {
+            msg_id: id.to_string(),
+            dna_hash: String::from(""),
+            agent_id: String::from(""),
+            address: address.to_string(),
+            content: serde_json::from_str(&serde_json::to_string(&Some(entry_with_meta)).unwrap())
+                .unwrap(),
+            redundancy: 0,
+        }))
+    }
+
+    #[test]
+    fn tampered_entries_get_asked_from_the_next_holder() {
+        let context = test_context("alice");
+        let store = test_store(context.clone()).reduce(
+            context.clone(),
+            ActionWrapper::new(Action::InitNetwork(NetworkSettings {
+                config: mock_network_config(),
+                dna_hash: String::from("abcd"),
+                agent_id: String::from("alice"),
+                rate_limit: RateLimit::default(),
+            })),
+        );
+        let mut network_state = (*store.network()).clone();
+
+        let address = test_entry().address();
+        let id = RequestId::new();
+        network_state
+            .get_entry_with_meta_results
+            .insert(id.clone(), address.clone());
+        network_state.get_entry_sources.insert(
+            id.clone(),
+            vec![String::from("evil"), String::from("honest")]
+                .into_iter()
+                .collect::<VecDeque<_>>(),
+        );
+
+        // evil answers with other content under the requested address
+        reduce_handle_get_result(
+            context.clone(),
+            &mut network_state,
+            &get_result(&id, &address, test_entry_b()),
+        );
+        assert_eq!(network_state.get_entry_with_meta_results.result(&id), None);
+        assert_eq!(network_state.rejected_responses.get("evil"), Some(&1));
+        assert_eq!(
+            network_state.get_entry_sources[&id].front(),
+            Some(&String::from("honest"))
+        );
+
+        reduce_handle_get_result(
+            context.clone(),
+            &mut network_state,
+            &get_result(&id, &address, test_entry()),
+        );
+        let entry_with_meta = network_state
+            .get_entry_with_meta_results
+            .result(&id)
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry_with_meta.entry, test_entry());
+        assert_eq!(network_state.rejected_responses.get("honest"), None);
+        assert_eq!(network_state.status().rejected_responses.len(), 1);
+    }
+
+    #[test]
+    fn tampered_entries_fail_once_no_holder_is_left() {
+        let context = test_context("alice");
+        let store = test_store(context.clone()).reduce(
+            context.clone(),
+            ActionWrapper::new(Action::InitNetwork(NetworkSettings {
+                config: mock_network_config(),
+                dna_hash: String::from("abcd"),
+                agent_id: String::from("alice"),
+                rate_limit: RateLimit::default(),
+            })),
+        );
+        let mut network_state = (*store.network()).clone();
+
+        let address = test_entry().address();
+        let id = RequestId::new();
+        network_state
+            .get_entry_with_meta_results
+            .insert(id.clone(), address.clone());
+
+        reduce_handle_get_result(
+            context.clone(),
+            &mut network_state,
+            &get_result(&id, &address, test_entry_b()),
+        );
+        assert_eq!(
+            network_state.get_entry_with_meta_results.result(&id),
+            Some(Err(HolochainError::ErrorGeneric(format!(
+                "No holder answered with the content of entry {}",
+                address
+            ))))
+        );
+        assert_eq!(network_state.rejected_responses.get(""), Some(&1));
+    }
 }
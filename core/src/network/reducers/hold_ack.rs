@@ -0,0 +1,119 @@
+use crate::{
+    action::ActionWrapper,
+    context::Context,
+    network::{
+        actions::ActionResponse, published::store_published, reducers::send, state::NetworkState,
+    },
+};
+use holochain_core_types::{cas::content::Address, error::HolochainError};
+use holochain_net_connection::protocol_wrapper::{HoldAckData, ProtocolWrapper};
+use std::sync::Arc;
+
+fn reduce_acknowledge_hold_inner(
+    network_state: &mut NetworkState,
+    hold_ack_data: &HoldAckData,
+) -> Result<(), HolochainError> {
+    network_state.initialized()?;
+
+    send(
+        network_state,
+        ProtocolWrapper::HoldAck(HoldAckData {
+            dna_hash: network_state.dna_hash.clone().unwrap(),
+            from_agent_id: network_state.agent_id.clone().unwrap(),
+            ..hold_ack_data.clone()
+        }),
+    )
+}
+
+pub fn reduce_acknowledge_hold(
+    _context: Arc<Context>,
+    network_state: &mut NetworkState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let hold_ack_data = unwrap_to!(action => crate::action::Action::AcknowledgeHold);
+    let result = reduce_acknowledge_hold_inner(network_state, hold_ack_data);
+    network_state.actions.insert(
+        action_wrapper.clone(),
+        ActionResponse::AcknowledgeHold(result),
+    );
+}
+
+/// Counts the sender as a holder of the entry, if we published it.
+/// Every agent counts once, however often it acknowledges.
+/// The first ack of the meta the entry went out with last marks it as published with it,
+/// which gets stored, see `network::published`.
+pub fn reduce_handle_hold_ack(
+    context: Arc<Context>,
+    network_state: &mut NetworkState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let hold_ack_data = unwrap_to!(action => crate::action::Action::HandleHoldAck);
+    let address = Address::from(hold_ack_data.address.clone());
+    if let Some(holders) = network_state.pending_publishes.get_mut(&address) {
+        holders.insert(hold_ack_data.from_agent_id.clone());
+    }
+    if let Some(meta) = network_state.acknowledge_publish(&address) {
+        if let Err(error) = store_published(&context, &address, meta) {
+            context.log(format!(
+                "Could not store the publish of {}: {}",
+                address, error
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        action::{Action, ActionWrapper},
+        instance::tests::test_context,
+    };
+    use std::collections::HashSet;
+
+    fn hold_ack(address: &str, from_agent_id: &str) -> ActionWrapper {
+        ActionWrapper::new(Action::HandleHoldAck(HoldAckData {
+            msg_id: String::from("test_id"),
+            dna_hash: String::from("hold_ack"),
+            to_agent_id: String::from("alice"),
+            from_agent_id: String::from(from_agent_id),
+            address: String::from(address),
+        }))
+    }
+
+    #[test]
+    pub fn reduce_handle_hold_ack_counts_holders_of_published_entries() {
+        let context = test_context("alice");
+        let mut network_state = NetworkState::new();
+        network_state
+            .pending_publishes
+            .insert(Address::from("QmPublished"), HashSet::new());
+
+        for (address, from_agent_id) in vec![
+            ("QmPublished", "bob"),
+            ("QmPublished", "carol"),
+            ("QmPublished", "bob"),
+            ("QmNotPublished", "bob"),
+        ] {
+            reduce_handle_hold_ack(
+                context.clone(),
+                &mut network_state,
+                &hold_ack(address, from_agent_id),
+            );
+        }
+
+        assert_eq!(
+            network_state.holders_count(&Address::from("QmPublished")),
+            2
+        );
+        assert_eq!(
+            network_state.holders_count(&Address::from("QmNotPublished")),
+            0
+        );
+        assert!(!network_state
+            .pending_publishes
+            .contains_key(&Address::from("QmNotPublished")));
+    }
+}
@@ -1,15 +1,46 @@
 use crate::{
-    action::{Action, ActionWrapper},
+    action::{Action, ActionWrapper, NetworkSettings},
     context::Context,
-    network::{handler::create_handler, state::NetworkState},
+    network::{
+        actions::ActionResponse,
+        handler::create_handler,
+        state::{ConnectionState, NetworkState},
+    },
 };
+use holochain_core_types::error::HolochainError;
 use holochain_net::p2p_network::P2pNetwork;
 use holochain_net_connection::{
     net_connection::NetConnection,
     protocol_wrapper::{ProtocolWrapper, TrackAppData},
 };
-use std::sync::{Arc, Mutex};
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 
+/// Creates a network proxy instance with the given settings and announces
+/// our DNA and agent through it.
+pub fn connect(
+    context: &Arc<Context>,
+    network_settings: &NetworkSettings,
+) -> Result<P2pNetwork, HolochainError> {
+    let mut network = P2pNetwork::new(create_handler(context), &network_settings.config)
+        .map_err(|error| HolochainError::IoError(error.to_string()))?;
+    network
+        .send(
+            ProtocolWrapper::TrackApp(TrackAppData {
+                dna_hash: network_settings.dna_hash.clone(),
+                agent_id: network_settings.agent_id.clone(),
+            })
+            .into(),
+        )
+        .map_err(|error| HolochainError::IoError(error.to_string()))?;
+    Ok(network)
+}
+
+/// Connects to the network. What got authored before stays in `deferred_publishes` for
+/// `initialize_network()` to publish it, or to report it if the network could not be
+/// initialized.
 pub fn reduce_init(
     context: Arc<Context>,
     state: &mut NetworkState,
@@ -17,20 +48,35 @@ pub fn reduce_init(
 ) {
     let action = action_wrapper.action();
     let network_settings = unwrap_to!(action => Action::InitNetwork);
-    let mut network = P2pNetwork::new(create_handler(&context), &network_settings.config).unwrap();
 
-    let _ = network
-        .send(
-            ProtocolWrapper::TrackApp(TrackAppData {
-                dna_hash: network_settings.dna_hash.clone(),
-                agent_id: network_settings.agent_id.clone(),
-            })
-            .into(),
-        )
-        .and_then(|_| {
+    let result = connect(&context, network_settings);
+    match result {
+        Ok(network) => {
             state.network = Some(Arc::new(Mutex::new(network)));
             state.dna_hash = Some(network_settings.dna_hash.clone());
             state.agent_id = Some(network_settings.agent_id.clone());
-            Ok(())
-        });
+            state.settings = Some(network_settings.clone());
+            state
+                .traffic
+                .lock()
+                .unwrap()
+                .set_limit(network_settings.rate_limit.clone());
+            state.connection = ConnectionState::Connected;
+            state.connected_since = Some(Instant::now());
+            state
+                .actions
+                .insert(action_wrapper.clone(), ActionResponse::InitNetwork(Ok(())));
+        }
+        Err(error) => {
+            context.log(format!(
+                "Could not initialize the network, {} entries authored before don't get published: {}",
+                state.deferred_publishes.len(),
+                error
+            ));
+            state.actions.insert(
+                action_wrapper.clone(),
+                ActionResponse::InitNetwork(Err(error)),
+            );
+        }
+    }
 }
@@ -1,12 +1,19 @@
+pub mod clear_pending_request;
 pub mod get_entry;
 pub mod get_validation_package;
 pub mod handle_get_result;
 pub mod handle_get_validation_package;
+pub mod hold_ack;
 pub mod init;
+pub mod peer_connected;
 pub mod publish;
+pub mod rate_limited;
+pub mod reconnect;
 pub mod resolve_direct_connection;
 pub mod respond_get;
 pub mod send_direct_message;
+pub mod unsupported_message;
+pub mod validation_failure_notice;
 
 use crate::{
     action::{Action, ActionWrapper, NetworkReduceFn},
@@ -14,39 +21,62 @@ use crate::{
     network::{
         direct_message::DirectMessage,
         reducers::{
+            clear_pending_request::reduce_clear_pending_request,
             get_entry::{reduce_get_entry, reduce_get_entry_timeout},
             get_validation_package::reduce_get_validation_package,
             handle_get_result::reduce_handle_get_result,
             handle_get_validation_package::reduce_handle_get_validation_package,
+            hold_ack::{reduce_acknowledge_hold, reduce_handle_hold_ack},
             init::reduce_init,
+            peer_connected::reduce_handle_peer_connected,
             publish::reduce_publish,
+            rate_limited::reduce_handle_rate_limited,
+            reconnect::{reduce_network_lost, reduce_reconnect_network},
             resolve_direct_connection::reduce_resolve_direct_connection,
             respond_get::reduce_respond_get,
             send_direct_message::reduce_send_direct_message,
+            unsupported_message::{
+                reduce_handle_unsupported_message, reduce_respond_unsupported_message,
+            },
+            validation_failure_notice::reduce_handle_validation_failure_notice,
         },
-        state::NetworkState,
+        state::{NetworkState, RequestId},
+        traffic,
     },
 };
 use holochain_core_types::{cas::content::Address, error::HolochainError};
 use holochain_net_connection::{
     net_connection::NetConnection,
+    protocol::Protocol,
     protocol_wrapper::{MessageData, ProtocolWrapper},
 };
-use snowflake::ProcessUniqueId;
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 /// maps incoming action to the correct handler
 fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<NetworkReduceFn> {
     match action_wrapper.action() {
+        Action::AcknowledgeHold(_) => Some(reduce_acknowledge_hold),
+        Action::ClearPendingRequest(_) => Some(reduce_clear_pending_request),
         Action::GetEntry(_) => Some(reduce_get_entry),
         Action::GetEntryTimeout(_) => Some(reduce_get_entry_timeout),
         Action::GetValidationPackage(_) => Some(reduce_get_validation_package),
         Action::HandleGetResult(_) => Some(reduce_handle_get_result),
         Action::HandleGetValidationPackage(_) => Some(reduce_handle_get_validation_package),
+        Action::HandleHoldAck(_) => Some(reduce_handle_hold_ack),
+        Action::HandlePeerConnected(_) => Some(reduce_handle_peer_connected),
+        Action::HandleRateLimited(_) => Some(reduce_handle_rate_limited),
+        Action::HandleUnsupportedMessage(_) => Some(reduce_handle_unsupported_message),
+        Action::HandleValidationFailureNotice(_) => Some(reduce_handle_validation_failure_notice),
         Action::InitNetwork(_) => Some(reduce_init),
-        Action::Publish(_) => Some(reduce_publish),
+        Action::NetworkLost(_) => Some(reduce_network_lost),
+        Action::Publish(_) | Action::Republish(_) => Some(reduce_publish),
+        Action::ReconnectNetwork => Some(reduce_reconnect_network),
         Action::ResolveDirectConnection(_) => Some(reduce_resolve_direct_connection),
         Action::RespondGet(_) => Some(reduce_respond_get),
+        Action::RespondUnsupportedMessage(_) => Some(reduce_respond_unsupported_message),
         Action::SendDirectMessage(_) => Some(reduce_send_direct_message),
         _ => None,
     }
@@ -70,38 +100,55 @@ pub fn reduce(
 
 /// Sends the given ProtocolWrapper over the network using the network proxy instance
 /// that lives in the NetworkState.
+/// Messages to a peer we sent more than the rate limit allows to go through the delay
+/// queue instead, so they get sent once they are within the limit.
+/// Fails with HolochainError::NetworkUnavailable while we are reconnecting.
 pub fn send(
     network_state: &mut NetworkState,
     protocol_wrapper: ProtocolWrapper,
 ) -> Result<(), HolochainError> {
-    network_state
+    network_state.connected()?;
+    let network = network_state
         .network
-        .as_mut()
-        .map(|network| {
-            network
-                .lock()
-                .unwrap()
-                .send(protocol_wrapper.into())
-                .map_err(|error| HolochainError::IoError(error.to_string()))
-        })
+        .clone()
         .ok_or(HolochainError::ErrorGeneric(
             "Network not intialized".to_string(),
-        ))?
+        ))?;
+    let data = Protocol::from(&protocol_wrapper);
+
+    if let Some(peer) = traffic::recipient(&protocol_wrapper) {
+        let now = Instant::now();
+        let delay =
+            network_state
+                .traffic
+                .lock()
+                .unwrap()
+                .outgoing(peer, traffic::message_size(&data), now);
+        if delay > Duration::from_millis(0) {
+            network_state.delay_queue.push(now + delay, network, data);
+            return Ok(());
+        }
+    }
+
+    let result = network.lock().unwrap().send(data);
+    result.map_err(|error| {
+        HolochainError::IoError(error.to_string()).with_context("sending over the network")
+    })
 }
 
 /// Sends the given DirectMessage to the node given by to_agent_id.
 /// This creates a transient connection as every node-to-node communication follows a
 /// request-response pattern. This function therefore logs the open connection
-/// (expecting a response) in network_state.direct_message_connections.
+/// (expecting a response) in network_state.direct_message_connections,
+/// under the given ID which the response will come with.
 pub fn send_message(
     network_state: &mut NetworkState,
     to_agent_id: &Address,
     message: DirectMessage,
+    id: RequestId,
 ) -> Result<(), HolochainError> {
-    let id = ProcessUniqueId::new().to_string();
-
     let data = MessageData {
-        msg_id: id.clone(),
+        msg_id: id.to_string(),
         dna_hash: network_state.dna_hash.clone().unwrap(),
         to_agent_id: to_agent_id.to_string(),
         from_agent_id: network_state.agent_id.clone().unwrap(),
@@ -112,7 +159,31 @@ pub fn send_message(
 
     let _ = send(network_state, ProtocolWrapper::SendMessage(data))?;
 
-    network_state.direct_message_connections.insert(id, message);
+    network_state
+        .direct_message_connections
+        .insert(id.clone(), message);
+    network_state
+        .direct_message_recipients
+        .insert(id, to_agent_id.clone());
 
     Ok(())
 }
+
+/// Sends the open direct message with the given ID again, after the connection
+/// it was sent over broke down.
+pub fn resend_message(
+    network_state: &mut NetworkState,
+    id: &RequestId,
+) -> Result<(), HolochainError> {
+    let message = network_state.direct_message_connections.get(id).cloned();
+    let to_agent_id = network_state.direct_message_recipients.get(id).cloned();
+    match (message, to_agent_id) {
+        (Some(message), Some(to_agent_id)) => {
+            send_message(network_state, &to_agent_id, message, id.clone())
+        }
+        _ => Err(HolochainError::ErrorGeneric(format!(
+            "No open direct message with ID {}",
+            id
+        ))),
+    }
+}
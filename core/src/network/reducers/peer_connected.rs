@@ -0,0 +1,42 @@
+use crate::{action::ActionWrapper, context::Context, network::state::NetworkState};
+use std::sync::Arc;
+
+/// Remembers the agent the network told us about, unless it is us.
+pub fn reduce_handle_peer_connected(
+    _context: Arc<Context>,
+    network_state: &mut NetworkState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let peer_data = unwrap_to!(action => crate::action::Action::HandlePeerConnected);
+    if network_state.agent_id.as_ref() != Some(&peer_data.id) {
+        network_state.peers.insert(peer_data.id.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{action::Action, instance::tests::test_context};
+    use holochain_net_connection::protocol_wrapper::PeerData;
+
+    #[test]
+    pub fn reduce_handle_peer_connected_adds_other_agents() {
+        let context = test_context("alice");
+        let mut network_state = NetworkState::new();
+        network_state.agent_id = Some(String::from("alice"));
+
+        for id in vec!["bob", "alice", "carol", "bob"] {
+            let action_wrapper = ActionWrapper::new(Action::HandlePeerConnected(PeerData {
+                id: String::from(id),
+            }));
+            reduce_handle_peer_connected(context.clone(), &mut network_state, &action_wrapper);
+        }
+
+        assert_eq!(
+            network_state.peers.iter().collect::<Vec<_>>(),
+            vec!["bob", "carol"]
+        );
+        assert_eq!(network_state.nodes(), vec!["bob", "carol", "alice"]);
+    }
+}
@@ -1,11 +1,12 @@
 use crate::{
-    action::ActionWrapper,
+    action::{Action, ActionWrapper},
     context::Context,
     network::{
         actions::ActionResponse,
-        entry_with_header::{fetch_entry_with_header, EntryWithHeader},
+        entry_with_header::{fetch_entry_with_header, outlived_ttl, EntryWithHeader},
         reducers::send,
-        state::NetworkState,
+        sharding::entry_redundancy,
+        state::{NetworkState, PublishedMeta},
     },
     nucleus::actions::get_entry::get_entry_crud_meta_from_dht,
 };
@@ -16,11 +17,13 @@ use holochain_core_types::{
     error::HolochainError,
 };
 use holochain_net_connection::protocol_wrapper::{DhtData, DhtMetaData, ProtocolWrapper};
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc, time::Instant};
 
+/// Publishes the entry to the given number of nodes, 0 meaning all of them
 fn publish_entry(
     network_state: &mut NetworkState,
     entry_with_header: &EntryWithHeader,
+    redundancy: u32,
 ) -> Result<(), HolochainError> {
     //let entry_with_header = util::EntryWithHeader::from((entry.clone(), header.clone()));
 
@@ -33,6 +36,7 @@ fn publish_entry(
             address: entry_with_header.entry_body.address().to_string(),
             content: serde_json::from_str(&serde_json::to_string(&entry_with_header).unwrap())
                 .unwrap(),
+            redundancy,
         }),
     )
 }
@@ -75,20 +79,92 @@ fn publish_crud_meta(
     Ok(())
 }
 
+/// Publishes the crud meta of the identity that the given identity entry replaces, if any,
+/// so that its holders link it to the new identity, see `workflows::update_agent`.
+/// Instead of just its address, the crud-link carries the new identity with its header,
+/// so that holders can check that the agent signed it.
+fn publish_replaced_identity_meta(
+    context: &Arc<Context>,
+    network_state: &mut NetworkState,
+    entry_with_header: &EntryWithHeader,
+) -> Result<(), HolochainError> {
+    let replaced_address = match entry_with_header.entry_body {
+        Entry::AgentId(ref agent_id) => match agent_id.replaces {
+            Some(ref replaced_address) => replaced_address.clone(),
+            None => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+    let (crud_status, _) = get_entry_crud_meta_from_dht(context, replaced_address.clone())?
+        .expect("Replaced identity should have crud-status metadata in DHT.");
+    publish_crud_meta(network_state, replaced_address.clone(), crud_status, None)?;
+    send(
+        network_state,
+        ProtocolWrapper::PublishDhtMeta(DhtMetaData {
+            msg_id: "?".to_string(),
+            dna_hash: network_state.dna_hash.clone().unwrap(),
+            agent_id: network_state.agent_id.clone().unwrap(),
+            address: replaced_address.to_string(),
+            attribute: LINK_NAME.to_string(),
+            content: serde_json::from_str(&serde_json::to_string(entry_with_header).unwrap())
+                .unwrap(),
+        }),
+    )
+}
+
+/// Publishes the crud meta of the entry that the given deletion entry deletes,
+/// so that its holders know it got deleted and by which deletion
+fn publish_deleted_entry_meta(
+    context: &Arc<Context>,
+    network_state: &mut NetworkState,
+    entry: &Entry,
+) -> Result<(), HolochainError> {
+    let deleted_address = match entry {
+        Entry::Deletion(deletion_entry) => deletion_entry.clone().deleted_entry_address(),
+        _ => return Ok(()),
+    };
+    match get_entry_crud_meta_from_dht(context, deleted_address.clone())? {
+        Some((crud_status, maybe_crud_link)) => {
+            publish_crud_meta(network_state, deleted_address, crud_status, maybe_crud_link)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Publishes the crud meta of the entry that the given app entry updates, if any, going by
+/// the crud-link of its header, so that its holders know it got modified and by which entry.
+/// The updated entry may be one of another agent, so only its meta gets published.
+fn publish_updated_entry_meta(
+    context: &Arc<Context>,
+    network_state: &mut NetworkState,
+    entry_with_header: &EntryWithHeader,
+) -> Result<(), HolochainError> {
+    let updated_address = match entry_with_header.header.link_crud() {
+        Some(updated_address) => updated_address,
+        None => return Ok(()),
+    };
+    match get_entry_crud_meta_from_dht(context, updated_address.clone())? {
+        Some((crud_status, maybe_crud_link)) => {
+            publish_crud_meta(network_state, updated_address, crud_status, maybe_crud_link)
+        }
+        None => Ok(()),
+    }
+}
+
 fn publish_link_meta(
     network_state: &mut NetworkState,
     entry_with_header: &EntryWithHeader,
 ) -> Result<(), HolochainError> {
-    let link_add_entry = match entry_with_header.entry_body.clone() {
-        Entry::LinkAdd(link_add_entry) => link_add_entry,
+    let link = match entry_with_header.entry_body {
+        Entry::LinkAdd(ref link_add) => link_add.link().clone(),
+        Entry::LinkRemove(ref link_remove) => link_remove.link().clone(),
         _ => {
             return Err(HolochainError::ErrorGeneric(format!(
-                "Received bad entry type. Expected Entry::LinkAdd received {:?}",
+                "Received bad entry type. Expected Entry::LinkAdd or Entry::LinkRemove received {:?}",
                 entry_with_header.entry_body,
             )));
         }
     };
-    let link = link_add_entry.link().clone();
 
     send(
         network_state,
@@ -104,56 +180,130 @@ fn publish_link_meta(
     )
 }
 
+/// Publishes the entry with the given address, unless it went out with the same meta before
+/// and `force` does not say otherwise, see `NetworkState::needs_publish()`.
+/// Entries that outlived the TTL of their type, as told by the local shard or the timestamp
+/// of their header, don't get published anymore.
 fn reduce_publish_inner(
     context: &Arc<Context>,
     network_state: &mut NetworkState,
     address: &Address,
+    force: bool,
 ) -> Result<(), HolochainError> {
     network_state.initialized()?;
 
     let entry_with_header = fetch_entry_with_header(&address, &context)?;
     let (crud_status, maybe_crud_link) = get_entry_crud_meta_from_dht(context, address.clone())?
         .expect("Entry should have crud-status metadata in DHT.");
+    if crud_status == CrudStatus::EXPIRED || outlived_ttl(&entry_with_header, context) {
+        return Err(HolochainError::ErrorGeneric(format!(
+            "Entry {} outlived the TTL of its type, not publishing it",
+            address
+        )));
+    }
+    let published_meta = PublishedMeta {
+        crud_status,
+        crud_link: maybe_crud_link.clone(),
+        sources: entry_with_header.header.sources().clone(),
+    };
+    let now = Instant::now();
+    if !network_state.needs_publish(address, &published_meta, force, now) {
+        network_state.skipped_publishes += 1;
+        return Ok(());
+    }
+
+    let redundancy = entry_redundancy(context, &entry_with_header.entry_body.entry_type());
     match entry_with_header.entry_body.entry_type() {
-        EntryType::AgentId => publish_entry(network_state, &entry_with_header).and_then(|_| {
-            publish_crud_meta(
-                network_state,
-                entry_with_header.entry_body.address(),
-                crud_status,
-                maybe_crud_link,
-            )
-        }),
-        EntryType::App(_) => publish_entry(network_state, &entry_with_header).and_then(|_| {
-            publish_crud_meta(
-                network_state,
-                entry_with_header.entry_body.address(),
-                crud_status,
-                maybe_crud_link,
-            )
-        }),
-        EntryType::LinkAdd => publish_entry(network_state, &entry_with_header)
-            .and_then(|_| publish_link_meta(network_state, &entry_with_header)),
-        EntryType::Deletion => publish_entry(network_state, &entry_with_header).and_then(|_| {
-            publish_crud_meta(
-                network_state,
-                entry_with_header.entry_body.address(),
-                crud_status,
-                maybe_crud_link,
-            )
-        }),
+        EntryType::AgentId => publish_entry(network_state, &entry_with_header, redundancy)
+            .and_then(|_| {
+                publish_crud_meta(
+                    network_state,
+                    entry_with_header.entry_body.address(),
+                    crud_status,
+                    maybe_crud_link,
+                )
+            })
+            .and_then(|_| {
+                publish_replaced_identity_meta(context, network_state, &entry_with_header)
+            }),
+        EntryType::App(_) => publish_entry(network_state, &entry_with_header, redundancy)
+            .and_then(|_| {
+                publish_crud_meta(
+                    network_state,
+                    entry_with_header.entry_body.address(),
+                    crud_status,
+                    maybe_crud_link,
+                )
+            })
+            .and_then(|_| publish_updated_entry_meta(context, network_state, &entry_with_header)),
+        EntryType::LinkAdd | EntryType::LinkRemove => {
+            publish_entry(network_state, &entry_with_header, redundancy)
+                .and_then(|_| publish_link_meta(network_state, &entry_with_header))
+        }
+        EntryType::Deletion => publish_entry(network_state, &entry_with_header, redundancy)
+            .and_then(|_| {
+                publish_crud_meta(
+                    network_state,
+                    entry_with_header.entry_body.address(),
+                    crud_status,
+                    maybe_crud_link,
+                )
+            })
+            .and_then(|_| {
+                publish_deleted_entry_meta(context, network_state, &entry_with_header.entry_body)
+            }),
         _ => Err(HolochainError::NotImplemented),
-    }
+    }?;
+
+    network_state.record_publish(address, published_meta, now);
+    Ok(())
 }
 
+/// Publishes the entry, see `reduce_publish_inner()`. Entries that get authored before the
+/// network is initialized, i.e. during genesis, are only noted in `deferred_publishes`
+/// for `reduce_init()` to publish them once it is, so that their publish succeeds.
 pub fn reduce_publish(
     context: Arc<Context>,
     network_state: &mut NetworkState,
     action_wrapper: &ActionWrapper,
 ) {
-    let action = action_wrapper.action();
-    let address = unwrap_to!(action => crate::action::Action::Publish);
+    let (address, force) = match action_wrapper.action() {
+        Action::Publish(address) => (address, false),
+        Action::Republish(address) => (address, true),
+        _ => unreachable!(),
+    };
 
-    let result = reduce_publish_inner(&context, network_state, &address);
+    if network_state.initialized().is_err() {
+        if !network_state.deferred_publishes.contains(address) {
+            network_state.deferred_publishes.push(address.clone());
+        }
+        network_state.actions.insert(
+            action_wrapper.clone(),
+            ActionResponse::Publish(Ok(address.clone())),
+        );
+        return;
+    }
+
+    let result = reduce_publish_inner(&context, network_state, &address, force);
+    let deferred_count = network_state.deferred_publishes.len();
+    network_state
+        .deferred_publishes
+        .retain(|deferred| deferred != address);
+    if network_state.deferred_publishes.len() < deferred_count {
+        match result {
+            Ok(_) => network_state.published_deferred += 1,
+            Err(ref error) => context.log(format!(
+                "Could not publish {}, which got authored before the network was up: {}",
+                address, error
+            )),
+        }
+    }
+    if result.is_ok() {
+        network_state
+            .pending_publishes
+            .entry(address.clone())
+            .or_insert_with(HashSet::new);
+    }
     network_state.actions.insert(
         action_wrapper.clone(),
         ActionResponse::Publish(match result {
@@ -0,0 +1,76 @@
+use crate::{
+    action::ActionWrapper,
+    context::Context,
+    network::state::{NetworkState, RequestId},
+};
+use holochain_core_types::error::HolochainError;
+use std::sync::Arc;
+
+/// The peer dropped our request instead of answering it, so the process waiting for
+/// the answer gets resolved with HolochainError::RateLimited right away.
+/// The request is found by the message ID, whether it was a direct message or a GET.
+pub fn reduce_handle_rate_limited(
+    _context: Arc<Context>,
+    network_state: &mut NetworkState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let rate_limited_data = unwrap_to!(action => crate::action::Action::HandleRateLimited);
+
+    let error = HolochainError::RateLimited(rate_limited_data.retry_after_ms);
+
+    let id = RequestId::from(rate_limited_data.msg_id.clone());
+    network_state.direct_message_connections.remove(&id);
+    network_state.direct_message_recipients.remove(&id);
+    network_state
+        .get_validation_package_results
+        .fail(&id, error.clone());
+    network_state.get_entry_with_meta_results.fail(&id, error);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        action::{Action, ActionWrapper, NetworkSettings},
+        context::mock_network_config,
+        instance::tests::test_context,
+        network::{state::RequestId, traffic::RateLimit},
+        state::test_store,
+    };
+    use holochain_core_types::{
+        cas::content::AddressableContent, entry::test_entry, error::HolochainError,
+    };
+    use holochain_net_connection::protocol_wrapper::RateLimitedData;
+
+    #[test]
+    pub fn reduce_handle_rate_limited_fails_get_entry() {
+        let context = test_context("alice");
+        let store = test_store(context.clone());
+        let action_wrapper = ActionWrapper::new(Action::InitNetwork(NetworkSettings {
+            config: mock_network_config(),
+            dna_hash: String::from("rate_limited"),
+            agent_id: String::from("alice"),
+            rate_limit: RateLimit::default(),
+        }));
+        let store = store.reduce(context.clone(), action_wrapper);
+
+        let entry = test_entry();
+        let id = RequestId::new();
+        let action_wrapper = ActionWrapper::new(Action::GetEntry((entry.address(), id.clone())));
+        let store = store.reduce(context.clone(), action_wrapper);
+
+        let action_wrapper = ActionWrapper::new(Action::HandleRateLimited(RateLimitedData {
+            msg_id: id.to_string(),
+            dna_hash: String::from("rate_limited"),
+            to_agent_id: String::from("alice"),
+            from_agent_id: String::from("bob"),
+            retry_after_ms: 100,
+        }));
+        let store = store.reduce(context.clone(), action_wrapper);
+
+        assert_eq!(
+            store.network().get_entry_with_meta_results.result(&id),
+            Some(Err(HolochainError::RateLimited(100)))
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{
+    action::{Action, ActionWrapper},
+    context::Context,
+    network::{
+        reducers::{get_entry::send_get_dht, init::connect, resend_message},
+        state::{ConnectionState, NetworkState, RequestId},
+    },
+};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Milliseconds to wait before the first try to reconnect
+const RECONNECT_BACKOFF_MIN_MS: u64 = 100;
+/// Every further try waits twice as long, but never longer than this
+const RECONNECT_BACKOFF_MAX_MS: u64 = 10_000;
+
+/// How long to wait before the next try to reconnect, after the given number of tries
+fn backoff(attempts: u32) -> Duration {
+    let delay = RECONNECT_BACKOFF_MIN_MS * 2_u64.pow(attempts.min(16));
+    Duration::from_millis(delay.min(RECONNECT_BACKOFF_MAX_MS))
+}
+
+fn schedule_reconnect(context: &Arc<Context>, attempts: u32) {
+    let action_channel = context.action_channel.clone();
+    let delay = backoff(attempts);
+    thread::spawn(move || {
+        thread::sleep(delay);
+        // The instance might be gone by now, so there is nobody left to reconnect
+        let _ = action_channel.send(ActionWrapper::new(Action::ReconnectNetwork));
+    });
+}
+
+pub fn reduce_network_lost(
+    context: Arc<Context>,
+    network_state: &mut NetworkState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let error = unwrap_to!(action => Action::NetworkLost);
+
+    // We are already on it
+    if network_state.connection != ConnectionState::Connected {
+        return;
+    }
+    context.log(format!(
+        "Lost the connection to the network: {}. Reconnecting...",
+        error
+    ));
+
+    // A connection that breaks down right after we got it back doesn't reset the backoff
+    let was_stable = network_state
+        .connected_since
+        .map(|since| since.elapsed() >= Duration::from_millis(RECONNECT_BACKOFF_MAX_MS))
+        .unwrap_or(false);
+    if was_stable {
+        network_state.reconnect_attempts = 0;
+    }
+    network_state.connection = ConnectionState::Reconnecting;
+    network_state.connected_since = None;
+    schedule_reconnect(&context, network_state.reconnect_attempts);
+}
+
+pub fn reduce_reconnect_network(
+    context: Arc<Context>,
+    network_state: &mut NetworkState,
+    _action_wrapper: &ActionWrapper,
+) {
+    if network_state.connection != ConnectionState::Reconnecting {
+        return;
+    }
+    let network_settings = match network_state.settings.clone() {
+        Some(network_settings) => network_settings,
+        None => return,
+    };
+
+    network_state.reconnect_attempts += 1;
+    match connect(&context, &network_settings) {
+        Ok(network) => {
+            network_state.network = Some(Arc::new(Mutex::new(network)));
+            network_state.connection = ConnectionState::Connected;
+            network_state.connected_since = Some(Instant::now());
+            network_state.reconnect_count += 1;
+            context.log("Reconnected to the network");
+            resend_pending_requests(&context, network_state);
+        }
+        Err(error) => {
+            context.log(format!("Could not reconnect to the network: {}", error));
+            schedule_reconnect(&context, network_state.reconnect_attempts);
+        }
+    }
+}
+
+/// Whatever we were still waiting for got lost with the old connection, so we ask again.
+fn resend_pending_requests(context: &Arc<Context>, network_state: &mut NetworkState) {
+    let pending_gets = network_state.get_entry_with_meta_results.waiting();
+    for (id, address) in pending_gets {
+        if let Err(error) = send_get_dht(network_state, &address, &id) {
+            context.log(format!(
+                "Could not send GET request for {} again: {}",
+                address, error
+            ));
+        }
+    }
+
+    let open_messages: Vec<RequestId> = network_state
+        .direct_message_connections
+        .keys()
+        .cloned()
+        .collect();
+    for id in open_messages {
+        if let Err(error) = resend_message(network_state, &id) {
+            context.log(format!(
+                "Could not send direct message {} again: {}",
+                id, error
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        action::NetworkSettings,
+        context::mock_network_config,
+        instance::{tests::test_context_with_channels, Observer},
+        network::traffic::RateLimit,
+        state::{test_store, State},
+    };
+    use holochain_core_types::{
+        cas::content::{Address, AddressableContent},
+        entry::test_entry,
+        error::HolochainError,
+    };
+    use holochain_net::mock_worker::{kill_transport, restore_transport};
+    use std::sync::{
+        mpsc::{sync_channel, Receiver},
+        RwLock,
+    };
+
+    fn reduce(context: &Arc<Context>, store: &Arc<RwLock<State>>, action_wrapper: ActionWrapper) {
+        let mut state = store.write().unwrap();
+        *state = state.reduce(context.clone(), action_wrapper);
+    }
+
+    /// Reduces the actions dispatched to the given context until the state satisfies
+    /// the given condition
+    fn reduce_until<F: Fn(&State) -> bool>(
+        context: &Arc<Context>,
+        store: &Arc<RwLock<State>>,
+        action_rx: &Receiver<ActionWrapper>,
+        condition: F,
+    ) -> bool {
+        for _ in 0..500 {
+            if condition(&store.read().unwrap()) {
+                return true;
+            }
+            if let Ok(action_wrapper) = action_rx.recv_timeout(Duration::from_millis(10)) {
+                reduce(context, store, action_wrapper);
+            }
+        }
+        false
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        assert_eq!(backoff(0), Duration::from_millis(100));
+        assert_eq!(backoff(1), Duration::from_millis(200));
+        assert_eq!(backoff(3), Duration::from_millis(800));
+        assert_eq!(backoff(7), Duration::from_millis(RECONNECT_BACKOFF_MAX_MS));
+        assert_eq!(
+            backoff(1000),
+            Duration::from_millis(RECONNECT_BACKOFF_MAX_MS)
+        );
+    }
+
+    #[test]
+    fn pending_get_resolves_after_reconnect() {
+        let dna_hash = "pending_get_resolves_after_reconnect";
+        let agent_id = "reconnect_agent";
+        let (action_tx, action_rx) = sync_channel::<ActionWrapper>(100);
+        let (observer_tx, _) = sync_channel::<Observer>(100);
+        let mut context = test_context_with_channels(agent_id, &action_tx, &observer_tx);
+        let store = Arc::new(RwLock::new(test_store(context.clone())));
+        Arc::get_mut(&mut context).unwrap().set_state(store.clone());
+        reduce(
+            &context,
+            &store,
+            ActionWrapper::new(Action::InitNetwork(NetworkSettings {
+                config: mock_network_config(),
+                dna_hash: String::from(dna_hash),
+                agent_id: String::from(agent_id),
+                rate_limit: RateLimit::default(),
+            })),
+        );
+        assert_eq!(
+            store.read().unwrap().network().connection,
+            ConnectionState::Connected
+        );
+
+        // The transport dies while we wait for an answer, and takes everything in flight
+        // with it
+        let id = RequestId::new();
+        reduce(
+            &context,
+            &store,
+            ActionWrapper::new(Action::GetEntry((test_entry().address(), id.clone()))),
+        );
+        kill_transport(dna_hash, agent_id).unwrap();
+        let mut lost = false;
+        while let Ok(action_wrapper) = action_rx.recv_timeout(Duration::from_secs(5)) {
+            if let Action::NetworkLost(_) = action_wrapper.action() {
+                reduce(&context, &store, action_wrapper);
+                lost = true;
+                break;
+            }
+        }
+        assert!(lost);
+        {
+            let network_state = store.read().unwrap().network();
+            assert_eq!(network_state.connection, ConnectionState::Reconnecting);
+            assert_eq!(network_state.get_entry_with_meta_results.result(&id), None);
+            assert_eq!(network_state.status().pending_gets, 1);
+        }
+
+        // New requests fail right away
+        let other_address = Address::from("QmNotAskedBeforeTheConnectionBrokeDown");
+        let other_id = RequestId::new();
+        reduce(
+            &context,
+            &store,
+            ActionWrapper::new(Action::GetEntry((other_address, other_id.clone()))),
+        );
+        assert_eq!(
+            store
+                .read()
+                .unwrap()
+                .network()
+                .get_entry_with_meta_results
+                .result(&other_id),
+            Some(Err(HolochainError::NetworkUnavailable))
+        );
+
+        // Once the transport is back, the pending get gets asked again and resolves
+        restore_transport(dna_hash, agent_id).unwrap();
+        assert!(reduce_until(&context, &store, &action_rx, |state| {
+            match state.network().get_entry_with_meta_results.result(&id) {
+                Some(Ok(_)) => true,
+                _ => false,
+            }
+        }));
+        let status = store.read().unwrap().network().status();
+        assert_eq!(status.connection, ConnectionState::Connected);
+        assert_eq!(status.reconnect_count, 1);
+        assert_eq!(status.pending_gets, 0);
+    }
+}
@@ -22,6 +22,7 @@ fn reduce_respond_get_inner(
             agent_id: get_dht_data.from_agent_id.clone(),
             address: get_dht_data.address.clone(),
             content: serde_json::from_str(&serde_json::to_string(&maybe_entry).unwrap()).unwrap(),
+            redundancy: 0,
         }),
     )
 }
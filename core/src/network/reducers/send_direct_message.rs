@@ -14,7 +14,7 @@ fn inner(
     network_state.initialized()?;
 
     let data = MessageData {
-        msg_id: direct_message_data.msg_id.clone(),
+        msg_id: direct_message_data.msg_id.to_string(),
         dna_hash: network_state.dna_hash.clone().unwrap(),
         to_agent_id: direct_message_data.address.to_string(),
         from_agent_id: network_state.agent_id.clone().unwrap(),
@@ -0,0 +1,189 @@
+use crate::{
+    action::ActionWrapper,
+    context::Context,
+    network::{
+        actions::ActionResponse,
+        reducers::send,
+        state::{NetworkState, RequestId},
+    },
+};
+use holochain_core_types::error::HolochainError;
+use holochain_net_connection::protocol_wrapper::{ProtocolWrapper, UnsupportedMessageData};
+use std::sync::Arc;
+
+fn reduce_respond_unsupported_message_inner(
+    network_state: &mut NetworkState,
+    unsupported_message_data: &UnsupportedMessageData,
+) -> Result<(), HolochainError> {
+    network_state.initialized()?;
+
+    send(
+        network_state,
+        ProtocolWrapper::UnsupportedMessage(UnsupportedMessageData {
+            dna_hash: network_state.dna_hash.clone().unwrap(),
+            from_agent_id: network_state.agent_id.clone().unwrap(),
+            ..unsupported_message_data.clone()
+        }),
+    )
+}
+
+pub fn reduce_respond_unsupported_message(
+    _context: Arc<Context>,
+    network_state: &mut NetworkState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let unsupported_message_data =
+        unwrap_to!(action => crate::action::Action::RespondUnsupportedMessage);
+    let result = reduce_respond_unsupported_message_inner(network_state, unsupported_message_data);
+    network_state.actions.insert(
+        action_wrapper.clone(),
+        ActionResponse::RespondUnsupportedMessage(result),
+    );
+}
+
+/// The peer will never answer the request our message was part of, so the process
+/// waiting for that answer gets resolved with an error right away.
+/// The request is found by the message ID, whether it was a direct message or a GET.
+pub fn reduce_handle_unsupported_message(
+    _context: Arc<Context>,
+    network_state: &mut NetworkState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let unsupported_message_data =
+        unwrap_to!(action => crate::action::Action::HandleUnsupportedMessage);
+
+    let error = HolochainError::ErrorGeneric(format!(
+        "Peer {} does not support message {} of protocol version {} (supports version {})",
+        unsupported_message_data.from_agent_id,
+        unsupported_message_data.method,
+        unsupported_message_data.seen_version,
+        unsupported_message_data.supported,
+    ));
+
+    let id = RequestId::from(unsupported_message_data.msg_id.clone());
+    network_state.direct_message_connections.remove(&id);
+    network_state.direct_message_recipients.remove(&id);
+    network_state
+        .get_validation_package_results
+        .fail(&id, error.clone());
+    network_state.get_entry_with_meta_results.fail(&id, error);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        action::{Action, ActionWrapper, NetworkSettings},
+        context::mock_network_config,
+        instance::tests::test_context,
+        network::{state::RequestId, traffic::RateLimit},
+        state::{test_store, State},
+    };
+    use holochain_core_types::{
+        cas::content::AddressableContent, chain_header::test_chain_header, entry::test_entry,
+        error::HolochainError,
+    };
+    use holochain_net_connection::protocol_wrapper::{UnsupportedMessageData, PROTOCOL_VERSION};
+
+    fn initialized_store(agent_id: &str) -> State {
+        let context = test_context("alice");
+        let store = test_store(context.clone());
+        let action_wrapper = ActionWrapper::new(Action::InitNetwork(NetworkSettings {
+            config: mock_network_config(),
+            dna_hash: String::from("unsupported_message"),
+            agent_id: String::from(agent_id),
+            rate_limit: RateLimit::default(),
+        }));
+        store.reduce(context, action_wrapper)
+    }
+
+    fn unsupported_message(msg_id: &str, address: Option<String>) -> UnsupportedMessageData {
+        UnsupportedMessageData {
+            msg_id: String::from(msg_id),
+            dna_hash: String::from("unsupported_message"),
+            to_agent_id: String::from("alice"),
+            from_agent_id: String::from("bob"),
+            method: String::from("getDht"),
+            address,
+            seen_version: PROTOCOL_VERSION,
+            supported: PROTOCOL_VERSION - 1,
+        }
+    }
+
+    #[test]
+    pub fn reduce_handle_unsupported_message_resolves_get_entry() {
+        let context = test_context("alice");
+        let store = initialized_store("alice");
+
+        let entry = test_entry();
+        let id = RequestId::new();
+        let action_wrapper = ActionWrapper::new(Action::GetEntry((entry.address(), id.clone())));
+        let store = store.reduce(context.clone(), action_wrapper);
+
+        let action_wrapper = ActionWrapper::new(Action::HandleUnsupportedMessage(
+            unsupported_message(&id.to_string(), Some(entry.address().to_string())),
+        ));
+        let store = store.reduce(context.clone(), action_wrapper);
+
+        let maybe_get_entry_result = store.network().get_entry_with_meta_results.result(&id);
+        match maybe_get_entry_result {
+            Some(Err(HolochainError::ErrorGeneric(message))) => {
+                assert!(message.contains("getDht"), "message = {}", message)
+            }
+            other => panic!("Expected the GET to be failed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn reduce_handle_unsupported_message_resolves_direct_message() {
+        let context = test_context("alice");
+        let store = initialized_store("alice");
+
+        let header = test_chain_header();
+        let id = RequestId::new();
+        let action_wrapper =
+            ActionWrapper::new(Action::GetValidationPackage((header.clone(), id.clone())));
+        let store = store.reduce(context.clone(), action_wrapper);
+        assert!(store.network().direct_message_connections.contains_key(&id));
+
+        let action_wrapper = ActionWrapper::new(Action::HandleUnsupportedMessage(
+            unsupported_message(&id.to_string(), None),
+        ));
+        let store = store.reduce(context.clone(), action_wrapper);
+
+        assert!(store.network().direct_message_connections.is_empty());
+        assert!(store.network().direct_message_recipients.is_empty());
+        let maybe_validation_package_result =
+            store.network().get_validation_package_results.result(&id);
+        match maybe_validation_package_result {
+            Some(Err(_)) => (),
+            other => panic!("Expected the request to be failed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn reduce_handle_unsupported_message_ignores_unknown_requests() {
+        let context = test_context("alice");
+        let store = initialized_store("alice");
+        let entry = test_entry();
+        let id = RequestId::new();
+        let action_wrapper = ActionWrapper::new(Action::GetEntry((entry.address(), id.clone())));
+        let store = store.reduce(context.clone(), action_wrapper);
+
+        let action_wrapper = ActionWrapper::new(Action::HandleUnsupportedMessage(
+            unsupported_message("unknown", Some(entry.address().to_string())),
+        ));
+        let new_store = store.reduce(context.clone(), action_wrapper);
+
+        assert_eq!(
+            new_store.network().get_entry_with_meta_results.result(&id),
+            None
+        );
+        assert!(new_store
+            .network()
+            .get_entry_with_meta_results
+            .get(&RequestId::from("unknown"))
+            .is_none());
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{
+    action::ActionWrapper,
+    context::Context,
+    network::{
+        entry_with_header::fetch_entry_with_header,
+        sharding::{entry_redundancy, neighborhood},
+        state::NetworkState,
+    },
+};
+use holochain_core_types::cas::content::Address;
+use std::{collections::BTreeMap, sync::Arc};
+
+/// Whether the given node is among the ones we asked to hold the entry we published,
+/// out of the nodes we know of
+fn was_asked_to_hold(
+    context: &Arc<Context>,
+    network_state: &NetworkState,
+    address: &Address,
+    holder: &Address,
+) -> bool {
+    let redundancy = match fetch_entry_with_header(address, context) {
+        Ok(entry_with_header) => {
+            entry_redundancy(context, &entry_with_header.entry_body.entry_type())
+        }
+        Err(_) => return false,
+    };
+    redundancy == 0
+        || neighborhood(address, &network_state.nodes(), redundancy).contains(&holder.to_string())
+}
+
+/// Records why a holder rejected an entry we published.
+/// Notices about entries we did not publish, or from nodes that were not asked to hold
+/// them, get ignored. A later notice from the same node replaces its earlier reason.
+pub fn reduce_handle_validation_failure_notice(
+    context: Arc<Context>,
+    network_state: &mut NetworkState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let (address, holder, reason) =
+        unwrap_to!(action => crate::action::Action::HandleValidationFailureNotice);
+    if !network_state.pending_publishes.contains_key(address) {
+        context.log(format!(
+            "Ignoring validation failure notice from {} for entry {} we did not publish",
+            holder, address
+        ));
+        return;
+    }
+    if !was_asked_to_hold(&context, network_state, address, holder) {
+        context.log(format!(
+            "Ignoring validation failure notice from {} for entry {}: not asked to hold it",
+            holder, address
+        ));
+        return;
+    }
+    network_state
+        .publish_rejections
+        .entry(address.clone())
+        .or_insert_with(BTreeMap::new)
+        .insert(holder.clone(), reason.clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        action::{Action, ActionWrapper},
+        agent::actions::commit::commit_entry,
+        nucleus::actions::tests::{instance_by_name, test_dna},
+    };
+    use futures::executor::block_on;
+    use holochain_core_types::entry::test_entry;
+    use std::collections::HashSet;
+
+    fn notice(address: &Address, holder: &str, reason: &str) -> ActionWrapper {
+        ActionWrapper::new(Action::HandleValidationFailureNotice((
+            address.clone(),
+            Address::from(holder),
+            String::from(reason),
+        )))
+    }
+
+    #[test]
+    pub fn reduce_handle_validation_failure_notice_records_rejections_of_published_entries() {
+        let (_instance, context) = instance_by_name("alice", test_dna());
+        let address = block_on(commit_entry(test_entry(), None, &context))
+            .expect("Could not commit entry")
+            .address;
+        let mut network_state = NetworkState::new();
+        network_state.agent_id = Some(String::from("alice"));
+        network_state
+            .pending_publishes
+            .insert(address.clone(), HashSet::new());
+
+        for action_wrapper in vec![
+            notice(&address, "bob", "too short"),
+            notice(&address, "carol", "too long"),
+            notice(&address, "bob", "way too short"),
+            notice(&Address::from("QmNotPublished"), "bob", "too short"),
+        ] {
+            reduce_handle_validation_failure_notice(
+                context.clone(),
+                &mut network_state,
+                &action_wrapper,
+            );
+        }
+
+        assert_eq!(
+            network_state.rejections(&address),
+            vec![
+                (Address::from("bob"), String::from("way too short")),
+                (Address::from("carol"), String::from("too long")),
+            ]
+        );
+        assert!(network_state
+            .rejections(&Address::from("QmNotPublished"))
+            .is_empty());
+    }
+
+    #[test]
+    pub fn reduce_handle_validation_failure_notice_ignores_nodes_out_of_the_neighborhood() {
+        let mut dna = test_dna();
+        dna.properties = json!({"redundancy": 1});
+        let (_instance, context) = instance_by_name("alice", dna);
+        let address = block_on(commit_entry(test_entry(), None, &context))
+            .expect("Could not commit entry")
+            .address;
+        let mut network_state = NetworkState::new();
+        network_state.agent_id = Some(String::from("alice"));
+        network_state.peers.insert(String::from("bob"));
+        network_state.peers.insert(String::from("carol"));
+        network_state
+            .pending_publishes
+            .insert(address.clone(), HashSet::new());
+
+        let closest = neighborhood(&address, &network_state.nodes(), 1)[0].clone();
+        for holder in vec!["alice", "bob", "carol"] {
+            reduce_handle_validation_failure_notice(
+                context.clone(),
+                &mut network_state,
+                &notice(&address, holder, "invalid"),
+            );
+        }
+
+        assert_eq!(
+            network_state.rejections(&address),
+            vec![(Address::from(closest), String::from("invalid"))]
+        );
+    }
+}
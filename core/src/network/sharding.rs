@@ -0,0 +1,120 @@
+//! Where entries are held when they are not to be held by every node.
+//! Agents and entries have a location on the same 64 bit space, and an entry that is
+//! to be held by `redundancy` nodes is held by the ones closest to it: its neighborhood.
+//! Every node works out neighborhoods from the nodes it knows of, so as long as they
+//! know the same nodes, publishers, holders and nodes looking for an entry agree on them.
+
+use crate::context::Context;
+use holochain_core_types::{cas::content::Address, entry::entry_type::EntryType};
+use multihash::{encode, Hash};
+use std::sync::Arc;
+
+/// The location of the agent or entry with the given ID or address:
+/// the first 8 bytes of its SHA-256
+pub fn location(id: &str) -> u64 {
+    let hash = encode(Hash::SHA2256, id.as_bytes()).expect("SHA-256 can hash any bytes");
+    // the digest comes after a byte for the hash function and one for its length
+    hash[2..10]
+        .iter()
+        .fold(0, |location, byte| (location << 8) | u64::from(*byte))
+}
+
+/// How far the given agent is from the entry with the given address
+pub fn distance(agent_id: &str, address: &Address) -> u64 {
+    location(agent_id) ^ location(&address.to_string())
+}
+
+/// The given agents by their distance to the given address, closest first, cut down to
+/// the `redundancy` closest. A redundancy of 0 makes all of them the neighborhood.
+pub fn neighborhood(address: &Address, agents: &[String], redundancy: u32) -> Vec<String> {
+    let mut neighborhood = agents.to_vec();
+    neighborhood.sort_by_key(|agent_id| (distance(agent_id, address), agent_id.clone()));
+    neighborhood.dedup();
+    if redundancy > 0 {
+        neighborhood.truncate(redundancy as usize);
+    }
+    neighborhood
+}
+
+/// How many nodes are to hold entries of the given type in the DNA of the instance.
+/// 0, meaning every node, while there is no DNA.
+pub fn entry_redundancy(context: &Arc<Context>, entry_type: &EntryType) -> u32 {
+    context
+        .state()
+        .and_then(|state| state.nucleus().dna())
+        .map(|dna| dna.redundancy_for_entry_type(entry_type))
+        .unwrap_or(0)
+}
+
+/// How many of the nodes closest to an entry to ask for it, since the type of the entry
+/// is not known before getting it, see `Dna::max_redundancy()`.
+pub fn get_redundancy(context: &Arc<Context>) -> u32 {
+    context
+        .state()
+        .and_then(|state| state.nucleus().dna())
+        .map(|dna| dna.max_redundancy())
+        .unwrap_or(0)
+}
+
+/// Whether we are in the neighborhood of the entry with the given address that is to be
+/// held by `redundancy` nodes, out of the nodes we know of
+pub fn is_in_neighborhood(context: &Arc<Context>, address: &Address, redundancy: u32) -> bool {
+    if redundancy == 0 {
+        return true;
+    }
+    let network = match context.state() {
+        Some(state) => state.network(),
+        None => return true,
+    };
+    match network.agent_id.clone() {
+        Some(agent_id) => neighborhood(address, &network.nodes(), redundancy).contains(&agent_id),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn agents(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("agent-{}", i)).collect()
+    }
+
+    #[test]
+    fn distance_is_symmetric_and_stable() {
+        let address = Address::from("QmEntry");
+        assert_eq!(location("agent-0"), location("agent-0"));
+        assert_ne!(location("agent-0"), location("agent-1"));
+        assert_eq!(
+            distance("agent-0", &address),
+            location("QmEntry") ^ location("agent-0")
+        );
+        assert_eq!(distance("QmEntry", &Address::from("QmEntry")), 0);
+    }
+
+    #[test]
+    fn neighborhood_is_made_of_the_closest_agents() {
+        let address = Address::from("QmEntry");
+        let all = neighborhood(&address, &agents(5), 0);
+        assert_eq!(all.len(), 5);
+        for pair in all.windows(2) {
+            assert!(distance(&pair[0], &address) <= distance(&pair[1], &address));
+        }
+
+        let two = neighborhood(&address, &agents(5), 2);
+        assert_eq!(two, all[..2].to_vec());
+        // more nodes than there are leaves all of them in it
+        assert_eq!(neighborhood(&address, &agents(5), 10).len(), 5);
+    }
+
+    #[test]
+    fn neighborhood_does_not_depend_on_the_order_of_agents() {
+        let address = Address::from("QmEntry");
+        let mut reversed = agents(5);
+        reversed.reverse();
+        assert_eq!(
+            neighborhood(&address, &agents(5), 2),
+            neighborhood(&address, &reversed, 2)
+        );
+    }
+}
@@ -1,35 +1,249 @@
-use boolinator::*;
 use crate::{
-    action::ActionWrapper,
-    network::{actions::ActionResponse, direct_message::DirectMessage},
+    action::{ActionWrapper, NetworkSettings},
+    network::{
+        actions::{get_entry::GET_ENTRY_TIMEOUT_SECS, ActionResponse},
+        direct_message::DirectMessage,
+        traffic::{DelayQueue, Traffic, TrafficStats},
+    },
 };
+use boolinator::*;
 use holochain_core_types::{
-    cas::content::Address, entry::EntryWithMeta, error::HolochainError,
+    cas::content::Address, crud_status::CrudStatus, entry::EntryWithMeta, error::HolochainError,
     validation::ValidationPackage,
 };
 use holochain_net::p2p_network::P2pNetwork;
 use snowflake;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    fmt,
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 type Actions = HashMap<ActionWrapper, ActionResponse>;
 
-/// This represents the state of a get_entry network process:
-/// None: process started, but no response yet from the network
-/// Some(Err(_)): there was a problem at some point
-/// Some(Ok(None)): no problem but also no entry -> it does not exist
-/// Some(Ok(Some(entry_with_meta))): we have it
-type GetEntryWithMetaResult = Option<Result<Option<EntryWithMeta>, HolochainError>>;
+/// Milliseconds within which publishing an entry again with the meta it got sent with is
+/// coalesced into that publish
+pub const PUBLISH_COALESCE_MILLIS: u64 = 2000;
+
+/// Identifies a request we sent to the network, so that the answer can be attributed to it.
+/// New IDs are snowflakes, IDs that come in over the network are taken as they are.
+/// On the wire it is just the string.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct RequestId(String);
+
+impl RequestId {
+    pub fn new() -> Self {
+        RequestId(snowflake::ProcessUniqueId::new().to_string())
+    }
+}
 
-/// This represents the state of a get_validation_package network process:
-/// None: process started, but no response yet from the network
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<String> for RequestId {
+    fn from(id: String) -> Self {
+        RequestId(id)
+    }
+}
+
+impl<'a> From<&'a str> for RequestId {
+    fn from(id: &str) -> Self {
+        RequestId(id.to_string())
+    }
+}
+
+impl From<RequestId> for String {
+    fn from(id: RequestId) -> Self {
+        id.0
+    }
+}
+
+/// A request to the network and its result:
+/// None: request sent, but no response yet from the network
 /// Some(Err(_)): there was a problem at some point
-/// Some(Ok(None)): no error but also no validation package -> we seem to have asked the wrong
-///   agent which actually should not happen. Something weird is going on.
-/// Some(Ok(Some(entry))): we have it
-type GetValidationPackageResult = Option<Result<Option<ValidationPackage>, HolochainError>>;
+/// Some(Ok(_)): the answer, until the process that made the request picks it up
+#[derive(Clone, Debug, PartialEq)]
+pub struct PendingRequest<T> {
+    /// The address the request is about
+    pub address: Address,
+    pub result: Option<Result<T, HolochainError>>,
+    /// When the request started waiting for its answer
+    pub sent: Instant,
+}
+
+/// The requests of one kind we sent to the network, by their ID.
+/// A request stays in here until the process that made it got its result and
+/// removes it through Action::ClearPendingRequest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PendingRequests<T> {
+    requests: HashMap<RequestId, PendingRequest<T>>,
+}
+
+impl<T: Clone> PendingRequests<T> {
+    pub fn new() -> Self {
+        PendingRequests {
+            requests: HashMap::new(),
+        }
+    }
+
+    /// Starts waiting for the answer to the given request
+    pub fn insert(&mut self, id: RequestId, address: Address) {
+        self.requests.insert(
+            id,
+            PendingRequest {
+                address,
+                result: None,
+                sent: Instant::now(),
+            },
+        );
+    }
+
+    /// Sets the result of the given request, replacing an earlier one.
+    /// Answers to requests we don't know (anymore) get ignored.
+    pub fn resolve(&mut self, id: &RequestId, result: Result<T, HolochainError>) {
+        if let Some(request) = self.requests.get_mut(id) {
+            request.result = Some(result);
+        }
+    }
+
+    /// Fails the given request, unless it got its result already.
+    /// Returns whether it was still waiting.
+    pub fn fail(&mut self, id: &RequestId, error: HolochainError) -> bool {
+        if let Some(request) = self.requests.get_mut(id) {
+            if request.result.is_none() {
+                request.result = Some(Err(error));
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Fails the given request with HolochainError::Timeout, unless it got its result already
+    pub fn timeout(&mut self, id: &RequestId) -> bool {
+        self.fail(id, HolochainError::Timeout)
+    }
+
+    /// Forgets the given request, once its result got picked up
+    pub fn remove(&mut self, id: &RequestId) -> Option<PendingRequest<T>> {
+        self.requests.remove(id)
+    }
+
+    pub fn get(&self, id: &RequestId) -> Option<&PendingRequest<T>> {
+        self.requests.get(id)
+    }
+
+    /// The IDs of all requests, waiting or with a result
+    pub fn ids(&self) -> impl Iterator<Item = &RequestId> {
+        self.requests.keys()
+    }
+
+    /// The result of the given request, if it has one yet
+    pub fn result(&self, id: &RequestId) -> Option<Result<T, HolochainError>> {
+        self.requests
+            .get(id)
+            .and_then(|request| request.result.clone())
+    }
+
+    /// IDs and addresses of the requests that are still waiting for an answer
+    pub fn waiting(&self) -> Vec<(RequestId, Address)> {
+        self.requests
+            .iter()
+            .filter(|(_, request)| request.result.is_none())
+            .map(|(id, request)| (id.clone(), request.address.clone()))
+            .collect()
+    }
+
+    /// Like waiting(), with when the requests were sent
+    pub fn waiting_since(&self) -> Vec<(RequestId, Address, Instant)> {
+        self.requests
+            .iter()
+            .filter(|(_, request)| request.result.is_none())
+            .map(|(id, request)| (id.clone(), request.address.clone(), request.sent))
+            .collect()
+    }
+
+    /// Number of requests that are still waiting for an answer
+    pub fn waiting_count(&self) -> usize {
+        self.requests
+            .values()
+            .filter(|request| request.result.is_none())
+            .count()
+    }
+
+    /// Number of requests, waiting or with a result that did not get picked up yet
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+}
+
+/// The meta an entry got published with: its crud status and link, and the agents
+/// the header names as its sources.
+/// Publishing an entry again only sends something if one of them changed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PublishedMeta {
+    pub crud_status: CrudStatus,
+    pub crud_link: Option<Address>,
+    pub sources: Vec<Address>,
+}
+
+/// Whether we can talk to the network backend
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum ConnectionState {
+    /// The network was not initialized yet
+    Disconnected,
+    Connected,
+    /// The connection broke down and we are trying to get it back.
+    /// New requests fail with HolochainError::NetworkUnavailable in the meantime.
+    Reconnecting,
+}
+
+/// The connection state, how often it had to be re-established, how many
+/// requests are waiting for an answer and the recent traffic with every peer,
+/// as shown in status listings
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct NetworkStatus {
+    pub connection: ConnectionState,
+    pub reconnect_count: u32,
+    pub pending_gets: usize,
+    pub pending_validation_packages: usize,
+    pub open_direct_messages: usize,
+    /// Publishes that did not go out because the entry got published with the same meta before
+    pub skipped_publishes: u64,
+    /// Entries authored before the network was up that did not go out yet
+    pub deferred_publishes: usize,
+    /// Entries authored before the network was up that went out once it was
+    pub published_deferred: usize,
+    /// Responses whose content did not match what they claimed to be, by sender
+    pub rejected_responses: BTreeMap<String, u64>,
+    pub traffic: BTreeMap<String, TrafficStats>,
+}
+
+/// What a request we sent to the network asks for
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum RequestKind {
+    GetEntry,
+    GetValidationPackage,
+}
+
+/// A request that is still waiting for its answer, as listed when looking for what
+/// an instance is waiting on.
+/// Requests without a timeout have no remaining time.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct WaitingRequest {
+    pub id: RequestId,
+    pub kind: RequestKind,
+    pub address: Address,
+    pub elapsed_ms: u64,
+    pub remaining_timeout_ms: Option<u64>,
+}
 
 #[derive(Clone, Debug)]
 pub struct NetworkState {
@@ -42,16 +256,76 @@ pub struct NetworkState {
     pub agent_id: Option<String>,
 
     /// Here we store the results of GET entry processes.
-    /// None means that we are still waiting for a result from the network.
-    pub get_entry_with_meta_results: HashMap<Address, GetEntryWithMetaResult>,
+    /// Ok(None) means that the entry does not exist.
+    pub get_entry_with_meta_results: PendingRequests<Option<EntryWithMeta>>,
 
-    /// Here we store the results of get validation package processes.
-    /// None means that we are still waiting for a result from the network.
-    pub get_validation_package_results: HashMap<Address, GetValidationPackageResult>,
+    /// The nodes to ask, one after the other, for entries that are not held by every node,
+    /// by GET request ID. The first one is the node that was asked last.
+    pub get_entry_sources: HashMap<RequestId, VecDeque<String>>,
+
+    /// Here we store the results of get validation package processes, by the ID of the
+    /// direct message that asked for the package.
+    /// Ok(None) means that the agent we asked is not the source of the entry,
+    /// which actually should not happen. Something weird is going on.
+    pub get_validation_package_results: PendingRequests<Option<ValidationPackage>>,
 
     /// This stores every open (= waiting for response) node-to-node messages.
     /// Entries get removed when we receive an answer through Action::ResolveDirectConnection.
-    pub direct_message_connections: HashMap<String, DirectMessage>,
+    pub direct_message_connections: HashMap<RequestId, DirectMessage>,
+
+    /// The agents the open node-to-node messages went to, by message ID,
+    /// so they can be sent again after a reconnect.
+    pub direct_message_recipients: HashMap<RequestId, Address>,
+
+    /// The agents that acknowledged holding the entries we published, by entry address.
+    /// An entry is in here from when we published it successfully, so acks for entries
+    /// we did not publish get ignored.
+    // @TODO entries are never removed, like actions
+    // @see https://github.com/holochain/holochain-rust/issues/166
+    pub pending_publishes: HashMap<Address, HashSet<String>>,
+
+    /// The reasons the nodes that were asked to hold the entries we published gave
+    /// for rejecting them, by entry address and node.
+    pub publish_rejections: HashMap<Address, BTreeMap<Address, String>>,
+
+    /// The entries a holder acknowledged, with the meta they got published with,
+    /// so that publishing them again without new meta can be skipped.
+    /// Gets stored one by one as the acks come in, see `network::published`.
+    pub published: HashMap<Address, PublishedMeta>,
+    /// The meta the entries went out with last and when, to coalesce publishing them again
+    /// and to tell what the next ack is about.
+    pub last_sent: HashMap<Address, (PublishedMeta, Instant)>,
+    /// How many publishes got skipped because of `published`.
+    pub skipped_publishes: u64,
+    /// The entries that got authored before the network was initialized, i.e. during
+    /// genesis, in the order they got authored. They get published once it is.
+    pub deferred_publishes: Vec<Address>,
+    /// How many of them got published successfully after all.
+    pub published_deferred: usize,
+    /// How many responses got rejected because their content did not match what they
+    /// claimed to be, e.g. GET results whose entry does not hash to the requested address,
+    /// by the peer that sent them. Responses of unknown senders count under "".
+    pub rejected_responses: BTreeMap<String, u64>,
+
+    /// The other agents of the DNA, as the network told us about them.
+    /// Entries that are not held by every node are held by the ones closest to them
+    /// out of these and us, see `sharding::neighborhood()`.
+    pub peers: BTreeSet<String>,
+
+    /// The settings the network got initialized with, needed to reconnect.
+    pub settings: Option<NetworkSettings>,
+    pub connection: ConnectionState,
+    /// How often the connection was re-established after it broke down.
+    pub reconnect_count: u32,
+    /// Reconnection tries since the connection broke down, determines the backoff.
+    pub reconnect_attempts: u32,
+    /// When the current connection was established.
+    pub connected_since: Option<Instant>,
+
+    /// Traffic accounting and rate limiting per peer, shared with the network handler.
+    pub traffic: Arc<Mutex<Traffic>>,
+    /// Messages to peers that wait to stay within the rate limit.
+    pub delay_queue: Arc<DelayQueue>,
 
     id: snowflake::ProcessUniqueId,
 }
@@ -70,9 +344,29 @@ impl NetworkState {
             dna_hash: None,
             agent_id: None,
 
-            get_entry_with_meta_results: HashMap::new(),
-            get_validation_package_results: HashMap::new(),
+            get_entry_with_meta_results: PendingRequests::new(),
+            get_entry_sources: HashMap::new(),
+            get_validation_package_results: PendingRequests::new(),
             direct_message_connections: HashMap::new(),
+            direct_message_recipients: HashMap::new(),
+            pending_publishes: HashMap::new(),
+            publish_rejections: HashMap::new(),
+            published: HashMap::new(),
+            last_sent: HashMap::new(),
+            skipped_publishes: 0,
+            deferred_publishes: Vec::new(),
+            published_deferred: 0,
+            rejected_responses: BTreeMap::new(),
+            peers: BTreeSet::new(),
+
+            settings: None,
+            connection: ConnectionState::Disconnected,
+            reconnect_count: 0,
+            reconnect_attempts: 0,
+            connected_since: None,
+
+            traffic: Arc::new(Mutex::new(Traffic::default())),
+            delay_queue: Arc::new(DelayQueue::new()),
 
             id: snowflake::ProcessUniqueId::new(),
         }
@@ -87,4 +381,253 @@ impl NetworkState {
             HolochainError::ErrorGeneric("Network not initialized".to_string()),
         )
     }
+
+    /// Like initialized(), but also fails while the connection is broken down,
+    /// so that new requests don't get lost in a dead connection.
+    pub fn connected(&self) -> Result<(), HolochainError> {
+        self.initialized()?;
+        (self.connection == ConnectionState::Connected).ok_or(HolochainError::NetworkUnavailable)
+    }
+
+    /// How many agents acknowledged holding the entry with the given address we published
+    pub fn holders_count(&self, address: &Address) -> usize {
+        self.pending_publishes
+            .get(address)
+            .map(|holders| holders.len())
+            .unwrap_or(0)
+    }
+
+    /// The nodes that rejected the entry with the given address we published,
+    /// with their reasons
+    pub fn rejections(&self, address: &Address) -> Vec<(Address, String)> {
+        self.publish_rejections
+            .get(address)
+            .map(|rejections| {
+                rejections
+                    .iter()
+                    .map(|(holder, reason)| (holder.clone(), reason.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Whether publishing the entry with the given address and meta sends anything.
+    /// Publishes of the meta the entry went out with less than PUBLISH_COALESCE_MILLIS
+    /// before `now` get coalesced with that one. Otherwise entries a holder acknowledged with
+    /// the same meta don't need to go out again, unless `force`d to repair the DHT.
+    pub fn needs_publish(
+        &self,
+        address: &Address,
+        meta: &PublishedMeta,
+        force: bool,
+        now: Instant,
+    ) -> bool {
+        let coalesced = self
+            .last_sent
+            .get(address)
+            .map(|(sent_meta, sent_at)| {
+                sent_meta == meta && now < *sent_at + Duration::from_millis(PUBLISH_COALESCE_MILLIS)
+            })
+            .unwrap_or(false);
+        !coalesced && (force || self.published.get(address) != Some(meta))
+    }
+
+    /// Remembers that the entry with the given address went out with the given meta
+    pub fn record_publish(&mut self, address: &Address, meta: PublishedMeta, now: Instant) {
+        self.last_sent.insert(address.clone(), (meta, now));
+    }
+
+    /// Marks the entry with the given address as published with the meta it went out with
+    /// last, once a holder acknowledged it. Returns that meta if it is new.
+    pub fn acknowledge_publish(&mut self, address: &Address) -> Option<PublishedMeta> {
+        let (meta, _) = self.last_sent.get(address)?;
+        if self.published.get(address) == Some(meta) {
+            return None;
+        }
+        self.published.insert(address.clone(), meta.clone());
+        Some(meta.clone())
+    }
+
+    /// The agents of the DNA we know of, including us
+    pub fn nodes(&self) -> Vec<String> {
+        self.peers
+            .iter()
+            .cloned()
+            .chain(self.agent_id.clone())
+            .collect()
+    }
+
+    /// The requests that are still waiting for their answer, the longest waiting first
+    pub fn waiting_requests(&self, now: Instant) -> Vec<WaitingRequest> {
+        let millis =
+            |duration: Duration| duration.as_secs() * 1000 + u64::from(duration.subsec_millis());
+        let gets = self
+            .get_entry_with_meta_results
+            .waiting_since()
+            .into_iter()
+            .map(|request| (RequestKind::GetEntry, request));
+        let validation_packages = self
+            .get_validation_package_results
+            .waiting_since()
+            .into_iter()
+            .map(|request| (RequestKind::GetValidationPackage, request));
+        let mut waiting: Vec<WaitingRequest> = gets
+            .chain(validation_packages)
+            .map(|(kind, (id, address, sent))| {
+                let elapsed = if now > sent {
+                    now - sent
+                } else {
+                    Duration::default()
+                };
+                let timeout = match kind {
+                    RequestKind::GetEntry => Some(Duration::from_secs(GET_ENTRY_TIMEOUT_SECS)),
+                    RequestKind::GetValidationPackage => None,
+                };
+                WaitingRequest {
+                    id,
+                    kind,
+                    address,
+                    elapsed_ms: millis(elapsed),
+                    remaining_timeout_ms: timeout
+                        .map(|timeout| millis(timeout.checked_sub(elapsed).unwrap_or_default())),
+                }
+            })
+            .collect();
+        waiting.sort_by(|a, b| b.elapsed_ms.cmp(&a.elapsed_ms));
+        waiting
+    }
+
+    pub fn status(&self) -> NetworkStatus {
+        NetworkStatus {
+            connection: self.connection.clone(),
+            reconnect_count: self.reconnect_count,
+            pending_gets: self.get_entry_with_meta_results.waiting_count(),
+            pending_validation_packages: self.get_validation_package_results.waiting_count(),
+            open_direct_messages: self.direct_message_connections.len(),
+            skipped_publishes: self.skipped_publishes,
+            deferred_publishes: self.deferred_publishes.len(),
+            published_deferred: self.published_deferred,
+            rejected_responses: self.rejected_responses.clone(),
+            traffic: self.traffic.lock().unwrap().stats(Instant::now()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn request_ids_are_unique_and_strings_on_the_wire() {
+        let id = RequestId::new();
+        assert_ne!(id, RequestId::new());
+        assert_eq!(serde_json::to_string(&id).unwrap(), format!("\"{}\"", id));
+        assert_eq!(RequestId::from(String::from(id.clone())), id);
+    }
+
+    #[test]
+    fn pending_requests_lifecycle() {
+        let mut requests: PendingRequests<u32> = PendingRequests::new();
+        let answered = RequestId::new();
+        let timed_out = RequestId::new();
+        requests.insert(answered.clone(), Address::from("a"));
+        requests.insert(timed_out.clone(), Address::from("b"));
+        assert_eq!(requests.waiting_count(), 2);
+        assert_eq!(requests.result(&answered), None);
+
+        requests.resolve(&answered, Ok(42));
+        assert!(requests.timeout(&timed_out));
+        // a timeout does not replace a result
+        assert!(!requests.timeout(&answered));
+        assert_eq!(requests.result(&answered), Some(Ok(42)));
+        assert_eq!(
+            requests.result(&timed_out),
+            Some(Err(HolochainError::Timeout))
+        );
+        assert_eq!(requests.waiting_count(), 0);
+
+        // answers to requests we don't know get ignored
+        let unknown = RequestId::new();
+        requests.resolve(&unknown, Ok(1));
+        assert!(!requests.timeout(&unknown));
+        assert_eq!(requests.len(), 2);
+
+        requests.remove(&answered);
+        requests.remove(&timed_out);
+        assert!(requests.is_empty());
+    }
+
+    #[test]
+    fn waiting_requests_keep_their_address() {
+        let mut requests: PendingRequests<u32> = PendingRequests::new();
+        let id = RequestId::new();
+        requests.insert(id.clone(), Address::from("a"));
+        requests.insert(RequestId::new(), Address::from("b"));
+        requests.resolve(&id, Ok(1));
+        assert_eq!(requests.waiting().len(), 1);
+        assert_eq!(requests.waiting()[0].1, Address::from("b"));
+    }
+
+    #[test]
+    fn waiting_requests_count_down_their_timeout() {
+        let mut state = NetworkState::new();
+        let get = RequestId::new();
+        let validation_package = RequestId::new();
+        state
+            .get_entry_with_meta_results
+            .insert(get.clone(), Address::from("a"));
+        state
+            .get_validation_package_results
+            .insert(validation_package.clone(), Address::from("b"));
+        let sent = state.get_entry_with_meta_results.get(&get).unwrap().sent;
+        let now = sent + Duration::from_secs(GET_ENTRY_TIMEOUT_SECS - 10);
+
+        let waiting = state.waiting_requests(now);
+        assert_eq!(waiting.len(), 2);
+        let waiting_get = waiting.iter().find(|request| request.id == get).unwrap();
+        assert_eq!(waiting_get.kind, RequestKind::GetEntry);
+        assert_eq!(waiting_get.address, Address::from("a"));
+        assert_eq!(waiting_get.remaining_timeout_ms, Some(10_000));
+        let waiting_package = waiting
+            .iter()
+            .find(|request| request.id == validation_package)
+            .unwrap();
+        assert_eq!(waiting_package.kind, RequestKind::GetValidationPackage);
+        assert_eq!(waiting_package.remaining_timeout_ms, None);
+
+        // answered requests are not waiting anymore
+        state.get_entry_with_meta_results.resolve(&get, Ok(None));
+        assert_eq!(state.waiting_requests(now).len(), 1);
+    }
+
+    #[test]
+    fn publishing_again_needs_new_meta_or_force() {
+        let mut state = NetworkState::new();
+        let address = Address::from("a");
+        let meta = PublishedMeta {
+            crud_status: CrudStatus::LIVE,
+            crud_link: None,
+            sources: vec![Address::from("alice")],
+        };
+        let now = Instant::now();
+        let later = now + Duration::from_millis(PUBLISH_COALESCE_MILLIS);
+        assert!(state.needs_publish(&address, &meta, false, now));
+
+        // publishing right after a publish is coalesced into it, whether forced or not
+        state.record_publish(&address, meta.clone(), now);
+        assert!(!state.needs_publish(&address, &meta, false, now));
+        assert!(!state.needs_publish(&address, &meta, true, now));
+        let deleted = PublishedMeta {
+            crud_status: CrudStatus::DELETED,
+            ..meta.clone()
+        };
+        assert!(state.needs_publish(&address, &deleted, false, now));
+
+        // without an ack it goes out again later
+        assert!(state.needs_publish(&address, &meta, false, later));
+        assert_eq!(state.acknowledge_publish(&address), Some(meta.clone()));
+        assert_eq!(state.acknowledge_publish(&address), None);
+        assert!(!state.needs_publish(&address, &meta, false, later));
+        assert!(state.needs_publish(&address, &meta, true, later));
+    }
 }
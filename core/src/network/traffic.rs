@@ -0,0 +1,581 @@
+use holochain_core_types::{error::HolochainError, json::JsonString};
+use holochain_net::p2p_network::P2pNetwork;
+use holochain_net_connection::{
+    net_connection::NetConnection,
+    protocol::{NamedBinaryData, Protocol},
+    protocol_wrapper::ProtocolWrapper,
+};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Number of seconds the traffic stats of a peer cover
+pub const TRAFFIC_WINDOW_SECS: u64 = 60;
+
+/// How much a peer may send us and we send to a peer, in messages and bytes per second.
+/// Bursts of up to `burst_seconds` worth of traffic pass at once, so that gossip rounds
+/// don't get cut off.
+/// Configured with the network, e.g. `{"backend": "mock", "rateLimit": {"messagesPerSecond": 50}}`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RateLimit {
+    pub messages_per_second: u32,
+    pub bytes_per_second: u64,
+    pub burst_seconds: u32,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        RateLimit {
+            messages_per_second: 200,
+            bytes_per_second: 1024 * 1024,
+            burst_seconds: 5,
+        }
+    }
+}
+
+impl RateLimit {
+    /// The rate limit given as "rateLimit" in the given network config, the default if none is
+    pub fn from_network_config(config: &JsonString) -> Result<RateLimit, HolochainError> {
+        let config: serde_json::Value = serde_json::from_str(&String::from(config.clone()))
+            .map_err(|error| HolochainError::ConfigError(error.to_string()))?;
+        match config.get("rateLimit") {
+            Some(rate_limit) => serde_json::from_value(rate_limit.clone()).map_err(|error| {
+                HolochainError::ConfigError(format!(
+                    "Invalid rateLimit in network config: {}",
+                    error
+                ))
+            }),
+            None => Ok(RateLimit::default()),
+        }
+    }
+
+    fn message_bucket_size(&self) -> f64 {
+        f64::from(self.messages_per_second) * f64::from(self.burst_seconds.max(1))
+    }
+
+    fn byte_bucket_size(&self) -> f64 {
+        self.bytes_per_second as f64 * f64::from(self.burst_seconds.max(1))
+    }
+}
+
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1_000_000_000.0
+}
+
+fn duration_from_seconds(seconds: f64) -> Duration {
+    Duration::from_nanos((seconds * 1_000_000_000.0).ceil() as u64)
+}
+
+/// Tokens that refill at a given rate up to a given size.
+/// Starts full, so the first burst passes.
+#[derive(Clone, Debug)]
+struct TokenBucket {
+    tokens: Option<f64>,
+    last: Instant,
+}
+
+impl TokenBucket {
+    fn new(now: Instant) -> Self {
+        TokenBucket {
+            tokens: None,
+            last: now,
+        }
+    }
+
+    fn refill(&mut self, rate: f64, size: f64, now: Instant) -> f64 {
+        let elapsed = if now > self.last {
+            seconds(now - self.last)
+        } else {
+            0.0
+        };
+        self.last = now;
+        let tokens = self
+            .tokens
+            .map(|tokens| (tokens + elapsed * rate).min(size))
+            .unwrap_or(size);
+        self.tokens = Some(tokens);
+        tokens
+    }
+
+    /// How long until the bucket holds `cost` tokens, zero if it does now.
+    /// Costs bigger than the bucket count as a full bucket, so that they can pass at all.
+    fn wait_for(&mut self, cost: f64, rate: f64, size: f64, now: Instant) -> Duration {
+        let missing = cost.min(size) - self.refill(rate, size, now);
+        if missing <= 0.0 {
+            Duration::from_millis(0)
+        } else {
+            duration_from_seconds(missing / rate)
+        }
+    }
+
+    fn take(&mut self, cost: f64) {
+        self.tokens = self.tokens.map(|tokens| tokens - cost);
+    }
+}
+
+/// A bucket for messages and one for bytes, both have to allow a message to pass
+#[derive(Clone, Debug)]
+struct Buckets {
+    messages: TokenBucket,
+    bytes: TokenBucket,
+}
+
+impl Buckets {
+    fn new(now: Instant) -> Self {
+        Buckets {
+            messages: TokenBucket::new(now),
+            bytes: TokenBucket::new(now),
+        }
+    }
+
+    /// How long the given message has to wait until it is within the limit
+    fn wait_for(&mut self, bytes: usize, limit: &RateLimit, now: Instant) -> Duration {
+        let messages_wait = self.messages.wait_for(
+            1.0,
+            f64::from(limit.messages_per_second),
+            limit.message_bucket_size(),
+            now,
+        );
+        let bytes_wait = self.bytes.wait_for(
+            bytes as f64,
+            limit.bytes_per_second as f64,
+            limit.byte_bucket_size(),
+            now,
+        );
+        messages_wait.max(bytes_wait)
+    }
+
+    fn take(&mut self, bytes: usize, limit: &RateLimit) {
+        self.messages.take(1.0);
+        self.bytes
+            .take((bytes as f64).min(limit.byte_bucket_size()));
+    }
+}
+
+/// What went over the wire between us and a peer
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct TrafficStats {
+    pub messages_in: u64,
+    pub bytes_in: u64,
+    pub messages_out: u64,
+    pub bytes_out: u64,
+    /// Requests of the peer we dropped because it exceeded the rate limit
+    pub dropped: u64,
+    /// Messages to the peer that had to wait in the delay queue
+    pub delayed: u64,
+}
+
+impl TrafficStats {
+    fn add(&mut self, other: &TrafficStats) {
+        self.messages_in += other.messages_in;
+        self.bytes_in += other.bytes_in;
+        self.messages_out += other.messages_out;
+        self.bytes_out += other.bytes_out;
+        self.dropped += other.dropped;
+        self.delayed += other.delayed;
+    }
+}
+
+/// The traffic with one peer, by second, and how much it may send and get
+#[derive(Clone, Debug)]
+struct PeerTraffic {
+    incoming: Buckets,
+    outgoing: Buckets,
+    window: VecDeque<(u64, TrafficStats)>,
+}
+
+impl PeerTraffic {
+    fn new(now: Instant) -> Self {
+        PeerTraffic {
+            incoming: Buckets::new(now),
+            outgoing: Buckets::new(now),
+            window: VecDeque::new(),
+        }
+    }
+
+    fn record<F: FnOnce(&mut TrafficStats)>(&mut self, second: u64, update: F) {
+        if self.window.back().map(|(s, _)| *s) != Some(second) {
+            self.window.push_back((second, TrafficStats::default()));
+        }
+        while self
+            .window
+            .front()
+            .map(|(s, _)| s + TRAFFIC_WINDOW_SECS <= second)
+            .unwrap_or(false)
+        {
+            self.window.pop_front();
+        }
+        update(&mut self.window.back_mut().unwrap().1);
+    }
+
+    fn stats(&self, second: u64) -> TrafficStats {
+        let mut stats = TrafficStats::default();
+        for (_, slot) in self
+            .window
+            .iter()
+            .filter(|(s, _)| s + TRAFFIC_WINDOW_SECS > second)
+        {
+            stats.add(slot);
+        }
+        stats
+    }
+}
+
+/// Traffic accounting and rate limiting per peer.
+/// Shared by the network handler, which accounts for incoming messages and drops requests
+/// of peers that exceed the rate limit, and the network reducers, which account for
+/// outgoing messages and delay those that would exceed it.
+#[derive(Clone, Debug)]
+pub struct Traffic {
+    limit: RateLimit,
+    started: Instant,
+    peers: HashMap<String, PeerTraffic>,
+}
+
+impl Traffic {
+    pub fn new(limit: RateLimit) -> Self {
+        Traffic {
+            limit,
+            started: Instant::now(),
+            peers: HashMap::new(),
+        }
+    }
+
+    pub fn limit(&self) -> &RateLimit {
+        &self.limit
+    }
+
+    pub fn set_limit(&mut self, limit: RateLimit) {
+        self.limit = limit;
+    }
+
+    fn second(&self, now: Instant) -> u64 {
+        if now > self.started {
+            (now - self.started).as_secs()
+        } else {
+            0
+        }
+    }
+
+    fn peer(&mut self, peer: &str, now: Instant) -> &mut PeerTraffic {
+        self.peers
+            .entry(peer.to_string())
+            .or_insert_with(|| PeerTraffic::new(now))
+    }
+
+    /// Accounts for a message of `bytes` that came in from `peer`.
+    /// Requests beyond the rate limit fail with how long until the peer may send again
+    /// and should be dropped. Answers to our own requests always pass.
+    pub fn incoming(
+        &mut self,
+        peer: &str,
+        bytes: usize,
+        is_request: bool,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let second = self.second(now);
+        let limit = self.limit.clone();
+        let peer_traffic = self.peer(peer, now);
+        let wait = if is_request {
+            peer_traffic.incoming.wait_for(bytes, &limit, now)
+        } else {
+            Duration::from_millis(0)
+        };
+        let admitted = wait == Duration::from_millis(0);
+        if admitted && is_request {
+            peer_traffic.incoming.take(bytes, &limit);
+        }
+        peer_traffic.record(second, |stats| {
+            stats.messages_in += 1;
+            stats.bytes_in += bytes as u64;
+            if !admitted {
+                stats.dropped += 1;
+            }
+        });
+        if admitted {
+            Ok(())
+        } else {
+            Err(wait)
+        }
+    }
+
+    /// Accounts for a message of `bytes` we send to `peer` and returns how long it has to wait
+    /// to stay within the rate limit. Every message waits for the ones before it.
+    pub fn outgoing(&mut self, peer: &str, bytes: usize, now: Instant) -> Duration {
+        let second = self.second(now);
+        let limit = self.limit.clone();
+        let peer_traffic = self.peer(peer, now);
+        let wait = peer_traffic.outgoing.wait_for(bytes, &limit, now);
+        peer_traffic.outgoing.take(bytes, &limit);
+        peer_traffic.record(second, |stats| {
+            stats.messages_out += 1;
+            stats.bytes_out += bytes as u64;
+            if wait > Duration::from_millis(0) {
+                stats.delayed += 1;
+            }
+        });
+        wait
+    }
+
+    /// The traffic with every peer we exchanged messages with within the last
+    /// TRAFFIC_WINDOW_SECS seconds
+    pub fn stats(&self, now: Instant) -> BTreeMap<String, TrafficStats> {
+        let second = self.second(now);
+        self.peers
+            .iter()
+            .map(|(peer, peer_traffic)| (peer.clone(), peer_traffic.stats(second)))
+            .filter(|(_, stats)| *stats != TrafficStats::default())
+            .collect()
+    }
+}
+
+impl Default for Traffic {
+    fn default() -> Self {
+        Traffic::new(RateLimit::default())
+    }
+}
+
+/// Size of a message on the wire
+pub fn message_size(data: &Protocol) -> usize {
+    NamedBinaryData::from(data).data.len()
+}
+
+/// The peer an incoming message comes from, and the message ID if it is a request,
+/// which can be dropped, as opposed to an answer to one of our own requests.
+/// None for messages of the network module itself.
+pub fn sender(protocol_wrapper: &ProtocolWrapper) -> Option<(&str, Option<&str>)> {
+    match protocol_wrapper {
+        ProtocolWrapper::GetDht(data) => {
+            Some((data.from_agent_id.as_str(), Some(data.msg_id.as_str())))
+        }
+        ProtocolWrapper::GetDhtMeta(data) => {
+            Some((data.from_agent_id.as_str(), Some(data.msg_id.as_str())))
+        }
+        ProtocolWrapper::HandleSend(data) => {
+            Some((data.from_agent_id.as_str(), Some(data.msg_id.as_str())))
+        }
+        ProtocolWrapper::StoreDht(data) => {
+            Some((data.agent_id.as_str(), Some(data.msg_id.as_str())))
+        }
+        ProtocolWrapper::StoreDhtMeta(data) => {
+            Some((data.agent_id.as_str(), Some(data.msg_id.as_str())))
+        }
+        ProtocolWrapper::SendResult(data) => Some((data.from_agent_id.as_str(), None)),
+        ProtocolWrapper::HoldAck(data) => Some((data.from_agent_id.as_str(), None)),
+        ProtocolWrapper::UnsupportedMessage(data) => Some((data.from_agent_id.as_str(), None)),
+        ProtocolWrapper::RateLimited(data) => Some((data.from_agent_id.as_str(), None)),
+        _ => None,
+    }
+}
+
+/// The peer an outgoing message goes to.
+/// None for messages to the network as a whole, and for telling a peer it got rate limited,
+/// which must not queue up behind the traffic it got limited for.
+pub fn recipient(protocol_wrapper: &ProtocolWrapper) -> Option<&str> {
+    match protocol_wrapper {
+        ProtocolWrapper::GetDhtResult(data) => Some(data.agent_id.as_str()),
+        ProtocolWrapper::GetDhtMetaResult(data) => Some(data.agent_id.as_str()),
+        ProtocolWrapper::SendMessage(data) => Some(data.to_agent_id.as_str()),
+        ProtocolWrapper::HandleSendResult(data) => Some(data.to_agent_id.as_str()),
+        ProtocolWrapper::HoldAck(data) => Some(data.to_agent_id.as_str()),
+        ProtocolWrapper::UnsupportedMessage(data) => Some(data.to_agent_id.as_str()),
+        _ => None,
+    }
+}
+
+struct Delayed {
+    due: Instant,
+    network: Arc<Mutex<P2pNetwork>>,
+    data: Protocol,
+}
+
+/// Sends messages over the network once they are due, from a thread of its own that
+/// gets started with the first message.
+/// The thread ends when the queue is dropped and every message in it got sent.
+#[derive(Debug)]
+pub struct DelayQueue {
+    sender: Mutex<Option<mpsc::Sender<Delayed>>>,
+}
+
+impl DelayQueue {
+    pub fn new() -> Self {
+        DelayQueue {
+            sender: Mutex::new(None),
+        }
+    }
+
+    /// Sends the given message over the given network at `due`
+    pub fn push(&self, due: Instant, network: Arc<Mutex<P2pNetwork>>, data: Protocol) {
+        let mut sender = self.sender.lock().unwrap();
+        if sender.is_none() {
+            let (new_sender, receiver) = mpsc::channel();
+            thread::spawn(move || send_when_due(receiver));
+            *sender = Some(new_sender);
+        }
+        let delayed = Delayed { due, network, data };
+        // The thread only ends once the sender is gone
+        let _ = sender.as_ref().unwrap().send(delayed);
+    }
+}
+
+fn send_when_due(receiver: mpsc::Receiver<Delayed>) {
+    let mut queue: BTreeMap<(Instant, u64), Delayed> = BTreeMap::new();
+    let mut count = 0;
+    let mut open = true;
+    while open || !queue.is_empty() {
+        let now = Instant::now();
+        let due: Vec<(Instant, u64)> = queue
+            .keys()
+            .take_while(|(due, _)| *due <= now)
+            .cloned()
+            .collect();
+        for key in due {
+            let delayed = queue.remove(&key).unwrap();
+            // A broken connection gets noticed by the network handler, which reconnects.
+            // Messages to the old connection are lost like the ones still in it.
+            let _ = delayed.network.lock().unwrap().send(delayed.data);
+        }
+
+        let next_due = queue.keys().next().map(|(due, _)| *due);
+        let received = match next_due {
+            Some(due) => {
+                let now = Instant::now();
+                let wait = if due > now {
+                    due - now
+                } else {
+                    Duration::from_millis(0)
+                };
+                if open {
+                    receiver.recv_timeout(wait)
+                } else {
+                    thread::sleep(wait);
+                    Err(RecvTimeoutError::Timeout)
+                }
+            }
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(delayed) => {
+                count += 1;
+                queue.insert((delayed.due, count), delayed);
+            }
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => open = false,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn limit(messages_per_second: u32, bytes_per_second: u64, burst_seconds: u32) -> RateLimit {
+        RateLimit {
+            messages_per_second,
+            bytes_per_second,
+            burst_seconds,
+        }
+    }
+
+    #[test]
+    fn rate_limit_comes_from_the_network_config() {
+        assert_eq!(
+            RateLimit::from_network_config(&JsonString::from(r#"{"backend": "mock"}"#)),
+            Ok(RateLimit::default())
+        );
+        assert_eq!(
+            RateLimit::from_network_config(&JsonString::from(
+                r#"{"backend": "mock", "rateLimit": {"messagesPerSecond": 10, "burstSeconds": 2}}"#
+            )),
+            Ok(RateLimit {
+                messages_per_second: 10,
+                burst_seconds: 2,
+                ..RateLimit::default()
+            })
+        );
+        assert!(RateLimit::from_network_config(&JsonString::from(
+            r#"{"backend": "mock", "rateLimit": {"messagesPerSecond": "many"}}"#
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn requests_beyond_the_burst_get_dropped_until_tokens_refill() {
+        let mut traffic = Traffic::new(limit(10, 1_000_000, 2));
+        let now = Instant::now();
+
+        for _ in 0..20 {
+            assert_eq!(traffic.incoming("mallory", 100, true, now), Ok(()));
+        }
+        let wait = traffic.incoming("mallory", 100, true, now).unwrap_err();
+        assert!(wait > Duration::from_millis(90) && wait <= Duration::from_millis(100));
+        // answers to our own requests and other peers are not affected
+        assert_eq!(traffic.incoming("mallory", 100, false, now), Ok(()));
+        assert_eq!(traffic.incoming("bob", 100, true, now), Ok(()));
+
+        // a tenth of a second later there is room for one more
+        let later = now + Duration::from_millis(100);
+        assert_eq!(traffic.incoming("mallory", 100, true, later), Ok(()));
+        assert!(traffic.incoming("mallory", 100, true, later).is_err());
+
+        let stats = traffic.stats(later);
+        assert_eq!(stats["mallory"].messages_in, 24);
+        assert_eq!(stats["mallory"].bytes_in, 2400);
+        assert_eq!(stats["mallory"].dropped, 2);
+        assert_eq!(stats["bob"].messages_in, 1);
+        assert_eq!(stats["bob"].dropped, 0);
+    }
+
+    #[test]
+    fn bytes_are_limited_too() {
+        let mut traffic = Traffic::new(limit(1000, 1000, 1));
+        let now = Instant::now();
+        assert_eq!(traffic.incoming("mallory", 600, true, now), Ok(()));
+        assert!(traffic.incoming("mallory", 600, true, now).is_err());
+        // messages bigger than the whole burst pass on a full bucket
+        assert_eq!(traffic.incoming("bob", 5000, true, now), Ok(()));
+    }
+
+    #[test]
+    fn outgoing_messages_get_delayed_behind_each_other() {
+        let mut traffic = Traffic::new(limit(10, 1_000_000, 1));
+        let now = Instant::now();
+        for _ in 0..10 {
+            assert_eq!(traffic.outgoing("bob", 100, now), Duration::from_millis(0));
+        }
+        let first_wait = traffic.outgoing("bob", 100, now);
+        let second_wait = traffic.outgoing("bob", 100, now);
+        assert!(first_wait > Duration::from_millis(90));
+        assert!(second_wait > first_wait + Duration::from_millis(90));
+        assert_eq!(
+            traffic.outgoing("carol", 100, now),
+            Duration::from_millis(0)
+        );
+
+        let stats = traffic.stats(now);
+        assert_eq!(stats["bob"].messages_out, 12);
+        assert_eq!(stats["bob"].delayed, 2);
+    }
+
+    #[test]
+    fn stats_cover_the_window() {
+        let mut traffic = Traffic::new(RateLimit::default());
+        let now = Instant::now();
+        traffic.incoming("bob", 100, true, now).unwrap();
+        let later = now + Duration::from_secs(TRAFFIC_WINDOW_SECS / 2);
+        traffic.incoming("bob", 100, true, later).unwrap();
+        assert_eq!(traffic.stats(later)["bob"].messages_in, 2);
+
+        let much_later = now + Duration::from_secs(TRAFFIC_WINDOW_SECS + 1);
+        assert_eq!(traffic.stats(much_later)["bob"].messages_in, 1);
+        let way_later = now + Duration::from_secs(2 * TRAFFIC_WINDOW_SECS);
+        assert!(traffic.stats(way_later).is_empty());
+    }
+}
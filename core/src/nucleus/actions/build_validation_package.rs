@@ -2,7 +2,7 @@ extern crate futures;
 extern crate serde_json;
 use crate::{
     action::{Action, ActionWrapper},
-    agent::{self, find_chain_header},
+    agent::{self, chain_store::ChainStore, find_chain_header},
     context::Context,
     nucleus::ribosome::callback::{
         validation_package::get_validation_package_definition, CallbackResult,
@@ -13,6 +13,7 @@ use futures::{
     task::{LocalWaker, Poll},
 };
 use holochain_core_types::{
+    cas::content::Address,
     chain_header::ChainHeader,
     entry::{entry_type::EntryType, Entry},
     error::HolochainError,
@@ -20,10 +21,9 @@ use holochain_core_types::{
 };
 use snowflake;
 use std::{
-    convert::TryInto,
+    convert::TryFrom,
     pin::{Pin, Unpin},
     sync::Arc,
-    thread,
 };
 
 pub fn build_validation_package(entry: &Entry, context: &Arc<Context>) -> ValidationPackageFuture {
@@ -31,12 +31,17 @@ pub fn build_validation_package(entry: &Entry, context: &Arc<Context>) -> Valida
 
     match entry.entry_type() {
         EntryType::App(app_entry_type) => {
-            if context
-                .state()
-                .unwrap()
-                .nucleus()
-                .dna()
-                .unwrap()
+            let dna = match context.state().and_then(|state| state.nucleus().dna()) {
+                Some(dna) => dna,
+                None => {
+                    return ValidationPackageFuture {
+                        context: context.clone(),
+                        key: id,
+                        error: Some(HolochainError::DnaMissing),
+                    };
+                }
+            };
+            if dna
                 .get_zome_name_for_app_entry_type(&app_entry_type)
                 .is_none()
             {
@@ -51,8 +56,8 @@ pub fn build_validation_package(entry: &Entry, context: &Arc<Context>) -> Valida
             }
         }
 
-        EntryType::LinkAdd => {
-            // LinkAdd can always be validated
+        EntryType::LinkAdd | EntryType::LinkRemove => {
+            // LinkAdd and LinkRemove can always be validated
         }
         EntryType::Deletion => {
             // FIXME
@@ -70,6 +75,17 @@ pub fn build_validation_package(entry: &Entry, context: &Arc<Context>) -> Valida
         }
     };
 
+    let agent_state = match context.state() {
+        Some(state) => state.agent(),
+        None => {
+            return ValidationPackageFuture {
+                context: context.clone(),
+                key: id,
+                error: Some(no_state()),
+            };
+        }
+    };
+
     {
         let id = id.clone();
         let entry = entry.clone();
@@ -87,58 +103,76 @@ pub fn build_validation_package(entry: &Entry, context: &Arc<Context>) -> Valida
             // and just used for the validation, I don't see why it would be a problem.
             // If it was a problem, we would have to make sure that the whole commit process
             // (including validtion) is atomic.
-            agent::state::create_new_chain_header(&entry, context.clone(), &None),
+            agent::state::create_new_chain_header(&entry, &agent_state, context.clone(), &None),
         );
 
-        thread::spawn(move || {
-            let maybe_callback_result = get_validation_package_definition(&entry, context.clone());
-            let maybe_validation_package = maybe_callback_result
-                .and_then(|callback_result| match callback_result {
-                    CallbackResult::Fail(error_string) => {
-                        Err(HolochainError::ErrorGeneric(error_string))
-                    }
-                    CallbackResult::ValidationPackageDefinition(def) => Ok(def),
-                    CallbackResult::NotImplemented => Err(HolochainError::ErrorGeneric(format!(
-                        "ValidationPackage callback not implemented for {:?}",
-                        entry.entry_type().clone()
+        // A panic while building still resolves the future, with an error
+        let fail_id = id.clone();
+        let fail_context = context.clone();
+        let fail = move || {
+            let _ = fail_context.action_channel.send(ActionWrapper::new(
+                Action::ReturnValidationPackage((
+                    fail_id,
+                    Err(HolochainError::ErrorGeneric(String::from(
+                        "Building the validation package failed",
                     ))),
-                    _ => unreachable!(),
-                })
-                .and_then(|package_definition| {
-                    Ok(match package_definition {
-                        Entry => ValidationPackage::only_header(entry_header),
-                        ChainEntries => {
-                            let mut package = ValidationPackage::only_header(entry_header);
-                            package.source_chain_entries = Some(all_public_chain_entries(&context));
-                            package
-                        }
-                        ChainHeaders => {
-                            let mut package = ValidationPackage::only_header(entry_header);
-                            package.source_chain_headers = Some(all_public_chain_headers(&context));
-                            package
-                        }
-                        ChainFull => {
-                            let mut package = ValidationPackage::only_header(entry_header);
-                            package.source_chain_entries = Some(all_public_chain_entries(&context));
-                            package.source_chain_headers = Some(all_public_chain_headers(&context));
-                            package
+                )),
+            ));
+        };
+        context.clone().spawn_request_task(
+            id.to_string(),
+            move || {
+                let maybe_callback_result =
+                    get_validation_package_definition(&entry, context.clone());
+                let maybe_validation_package = maybe_callback_result
+                    .and_then(|callback_result| match callback_result {
+                        CallbackResult::Fail(error_string) => {
+                            Err(HolochainError::ErrorGeneric(error_string))
                         }
-                        Custom(string) => {
-                            let mut package = ValidationPackage::only_header(entry_header);
-                            package.custom = Some(string);
-                            package
+                        CallbackResult::ValidationPackageDefinition(def) => Ok(def),
+                        CallbackResult::NotImplemented => {
+                            Err(HolochainError::ErrorGeneric(format!(
+                                "ValidationPackage callback not implemented for {:?}",
+                                entry.entry_type().clone()
+                            )))
                         }
+                        _ => unreachable!(),
                     })
-                });
-
-            context
-                .action_channel
-                .send(ActionWrapper::new(Action::ReturnValidationPackage((
-                    id,
-                    maybe_validation_package,
-                ))))
-                .expect("action channel to be open in reducer");
-        });
+                    .and_then(|package_definition| {
+                        let mut package = ValidationPackage::only_header(entry_header);
+                        match package_definition {
+                            Entry => (),
+                            ChainEntries => {
+                                package.source_chain_entries =
+                                    Some(all_public_chain_entries(&context)?);
+                                package.pruned_entries = pruned_public_chain_entries(&context)?;
+                            }
+                            ChainHeaders => {
+                                package.source_chain_headers =
+                                    Some(all_public_chain_headers(&context)?);
+                            }
+                            ChainFull => {
+                                package.source_chain_entries =
+                                    Some(all_public_chain_entries(&context)?);
+                                package.source_chain_headers =
+                                    Some(all_public_chain_headers(&context)?);
+                                package.pruned_entries = pruned_public_chain_entries(&context)?;
+                            }
+                            Custom(string) => package.custom = Some(string),
+                        }
+                        Ok(package)
+                    });
+
+                context
+                    .action_channel
+                    .send(ActionWrapper::new(Action::ReturnValidationPackage((
+                        id,
+                        maybe_validation_package,
+                    ))))
+                    .expect("action channel to be open in reducer");
+            },
+            fail,
+        );
     }
 
     ValidationPackageFuture {
@@ -148,31 +182,66 @@ pub fn build_validation_package(entry: &Entry, context: &Arc<Context>) -> Valida
     }
 }
 
-fn all_public_chain_entries(context: &Arc<Context>) -> Vec<Entry> {
-    let chain = context.state().unwrap().agent().chain();
-    let top_header = context.state().unwrap().agent().top_chain_header();
+fn no_state() -> HolochainError {
+    HolochainError::new("Context has no state")
+}
+
+fn is_pruned(context: &Arc<Context>, entry_address: &Address) -> bool {
+    context
+        .state()
+        .map(|state| state.dht().is_pruned(entry_address).unwrap_or(false))
+        .unwrap_or(false)
+}
+
+fn chain_and_top_header(
+    context: &Arc<Context>,
+) -> Result<(ChainStore, Option<ChainHeader>), HolochainError> {
+    let agent = context.state().ok_or_else(no_state)?.agent();
+    Ok((agent.chain(), agent.top_chain_header()))
+}
+
+/// The entries of the source chain, but the ones whose content got pruned,
+/// see `pruned_public_chain_entries()`
+fn all_public_chain_entries(context: &Arc<Context>) -> Result<Vec<Entry>, HolochainError> {
+    let (chain, top_header) = chain_and_top_header(context)?;
+    let storage = chain.content_storage();
     chain
         .iter(&top_header)
         .filter(|ref chain_header| chain_header.entry_type().can_publish())
+        .filter(|chain_header| !is_pruned(context, chain_header.entry_address()))
         .map(|chain_header| {
-            let storage = chain.content_storage().clone();
-            let json = (*storage.read().unwrap())
-                .fetch(chain_header.entry_address())
-                .expect("Could not fetch from CAS");
-            json.expect("Could not find CAS for existing chain header")
-                .try_into()
-                .expect("Could not convert to serialized entry")
+            let content = storage
+                .read()?
+                .fetch(chain_header.entry_address())?
+                .ok_or_else(|| {
+                    HolochainError::ErrorGeneric(format!(
+                        "Entry {} of the chain is missing in the CAS",
+                        chain_header.entry_address()
+                    ))
+                })?;
+            Entry::try_from(content)
         })
-        .collect::<Vec<_>>()
+        .collect()
 }
 
-fn all_public_chain_headers(context: &Arc<Context>) -> Vec<ChainHeader> {
-    let chain = context.state().unwrap().agent().chain();
-    let top_header = context.state().unwrap().agent().top_chain_header();
-    chain
+/// The addresses of the entries of the source chain whose content got pruned,
+/// which go into validation packages as content-pruned markers
+fn pruned_public_chain_entries(context: &Arc<Context>) -> Result<Vec<Address>, HolochainError> {
+    let (chain, top_header) = chain_and_top_header(context)?;
+    Ok(chain
+        .iter(&top_header)
+        .filter(|ref chain_header| chain_header.entry_type().can_publish())
+        .map(|chain_header| chain_header.entry_address().clone())
+        .filter(|entry_address| is_pruned(context, entry_address))
+        .collect())
+}
+
+fn all_public_chain_headers(context: &Arc<Context>) -> Result<Vec<ChainHeader>, HolochainError> {
+    let (chain, top_header) = chain_and_top_header(context)?;
+    Ok(chain
         .iter(&top_header)
         .filter(|ref chain_header| chain_header.entry_type().can_publish())
-        .collect::<Vec<_>>()
+        .collect::<Vec<_>>())
 }
 
 /// ValidationPackageFuture resolves to the ValidationPackage or a HolochainError.
@@ -213,8 +282,13 @@ mod tests {
     use super::*;
     use crate::nucleus::actions::tests::*;
 
+    use crate::dht::dht_store::PRUNED_NAME;
     use futures::executor::block_on;
-    use holochain_core_types::validation::ValidationPackage;
+    use holochain_core_types::{
+        cas::content::AddressableContent,
+        eav::EntityAttributeValue,
+        validation::{ValidationData, ValidationPackage},
+    };
 
     #[test]
     fn test_building_validation_package_entry() {
@@ -239,6 +313,7 @@ mod tests {
             source_chain_entries: None,
             source_chain_headers: None,
             custom: None,
+            pruned_entries: Vec::new(),
         };
 
         assert_eq!(maybe_validation_package.unwrap(), expected);
@@ -266,6 +341,7 @@ mod tests {
             source_chain_entries: Some(all_public_chain_entries(&context)),
             source_chain_headers: None,
             custom: None,
+            pruned_entries: Vec::new(),
         };
 
         assert_eq!(maybe_validation_package.unwrap(), expected);
@@ -293,6 +369,7 @@ mod tests {
             source_chain_entries: None,
             source_chain_headers: Some(all_public_chain_headers(&context)),
             custom: None,
+            pruned_entries: Vec::new(),
         };
 
         assert_eq!(maybe_validation_package.unwrap(), expected);
@@ -320,8 +397,56 @@ mod tests {
             source_chain_entries: Some(all_public_chain_entries(&context)),
             source_chain_headers: Some(all_public_chain_headers(&context)),
             custom: None,
+            pruned_entries: Vec::new(),
         };
 
         assert_eq!(maybe_validation_package.unwrap(), expected);
     }
+
+    #[test]
+    fn test_building_validation_package_chain_full_with_pruned_entries() {
+        let (_instance, context) = instance();
+
+        let pruned_header = commit(test_entry_package_chain_entries(), &context);
+        commit(test_entry_package_entry(), &context);
+        let pruned_address = test_entry_package_chain_entries().address();
+        let dht = context.state().unwrap().dht();
+        dht.meta_storage()
+            .write()
+            .unwrap()
+            .add_eav(&EntityAttributeValue::new(
+                &pruned_address,
+                &PRUNED_NAME.to_string(),
+                &pruned_header.address(),
+            ))
+            .unwrap();
+        let mut roots = context.file_storage.read().unwrap().addresses().unwrap();
+        roots.remove(&pruned_address);
+        context.file_storage.write().unwrap().gc(&roots).unwrap();
+
+        commit(test_entry_package_chain_full(), &context);
+        let validation_package = block_on(build_validation_package(
+            &test_entry_package_chain_full(),
+            &context.clone(),
+        ))
+        .expect("pruned entries should not keep the package from being built");
+
+        assert_eq!(
+            validation_package.pruned_entries,
+            vec![pruned_address.clone()]
+        );
+        let entries = validation_package.source_chain_entries.unwrap();
+        assert!(!entries.contains(&test_entry_package_chain_entries()));
+        assert!(entries.contains(&test_entry_package_entry()));
+        let headers = validation_package.source_chain_headers.unwrap();
+        assert!(headers.contains(&pruned_header));
+        assert_eq!(headers.len(), entries.len() + 1);
+
+        let validation_data = ValidationData {
+            package: validation_package,
+            ..Default::default()
+        };
+        assert!(validation_data.is_pruned(&pruned_address));
+        assert!(!validation_data.is_pruned(&test_entry_package_entry().address()));
+    }
 }
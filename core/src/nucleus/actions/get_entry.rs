@@ -4,7 +4,7 @@ use holochain_core_types::{
     cas::content::Address,
     crud_status::{CrudStatus, LINK_NAME, STATUS_NAME},
     eav::EntityAttributeValue,
-    entry::{Entry, EntryWithMeta},
+    entry::{Entry, EntryMeta, EntryWithMeta},
     error::HolochainError,
 };
 
@@ -40,7 +40,19 @@ pub(crate) fn get_entry_crud_meta_from_dht(
     }
     let mut crud_status = CrudStatus::LIVE;
     // TODO waiting for update/remove_eav() assert!(status_eavs.len() <= 1);
-    // For now look for crud-status by life-cycle order: DELETED, MODIFIED, LIVE
+    // For now look for crud-status by life-cycle order: REJECTED, EXPIRED, DELETED, MODIFIED, LIVE
+    let has_rejected = status_eavs
+        .iter()
+        .any(|e| CrudStatus::from(String::from(e.value())) == CrudStatus::REJECTED);
+    if has_rejected {
+        return Ok(Some((CrudStatus::REJECTED, None)));
+    }
+    let has_expired = status_eavs
+        .iter()
+        .any(|e| CrudStatus::from(String::from(e.value())) == CrudStatus::EXPIRED);
+    if has_expired {
+        return Ok(Some((CrudStatus::EXPIRED, None)));
+    }
     let has_deleted = status_eavs
         .iter()
         .filter(|e| CrudStatus::from(String::from(e.value())) == CrudStatus::DELETED)
@@ -76,7 +88,53 @@ pub(crate) fn get_entry_crud_meta_from_dht(
     Ok(Some((crud_status, maybe_crud_link)))
 }
 
+/// Follows the crud-links of the local meta storage from the entry with the given address
+/// to its newest version, None if it did not get updated
+fn get_newest_version_from_dht(
+    context: &Arc<Context>,
+    address: Address,
+) -> Result<Option<Address>, HolochainError> {
+    let mut newest_version = None;
+    let mut visited = HashSet::new();
+    let mut address = address;
+    loop {
+        let next = match get_entry_crud_meta_from_dht(context, address.clone())? {
+            Some((ref crud_status, Some(ref next)))
+                if *crud_status == CrudStatus::MODIFIED && visited.insert(next.clone()) =>
+            {
+                next.clone()
+            }
+            _ => return Ok(newest_version),
+        };
+        newest_version = Some(next.clone());
+        address = next;
+    }
+}
+
+/// What the local DHT shard knows about the entry with the given address besides its
+/// crud-status and -link, see EntryMeta
+fn get_entry_meta_from_dht(
+    context: &Arc<Context>,
+    address: Address,
+) -> Result<EntryMeta, HolochainError> {
+    let dht = context.state().unwrap().dht();
+    let timestamps: Vec<_> = dht
+        .get_headers(&address)?
+        .iter()
+        .map(|header| header.timestamp().clone())
+        .collect();
+    Ok(EntryMeta {
+        link_counts: dht.get_meta(&address)?.link_counts,
+        newest_version: get_newest_version_from_dht(context, address)?,
+        first_published: timestamps.iter().min().cloned(),
+        last_published: timestamps.iter().max().cloned(),
+    })
+}
+
 /// GetEntry Action Creator
+/// Entries that failed validation on this node or expired are treated as if we didn't
+/// hold them.
+/// Tells the EntryMeta as well, which only takes reads of the local meta storage.
 ///
 /// Returns a future that resolves to an Ok(ActionWrapper) or an Err(error_message:String).
 pub fn get_entry_with_meta<'a>(
@@ -97,11 +155,11 @@ pub fn get_entry_with_meta<'a>(
     let (crud_status, maybe_crud_link) = maybe_meta
         .unwrap()
         .expect("Entry should have crud-status metadata");
-    let item = EntryWithMeta {
-        entry,
-        crud_status,
-        maybe_crud_link,
-    };
+    if crud_status == CrudStatus::REJECTED || crud_status == CrudStatus::EXPIRED {
+        return Ok(None);
+    }
+    let meta = get_entry_meta_from_dht(context, address)?;
+    let item = EntryWithMeta::new(entry, crud_status, maybe_crud_link).with_meta(meta);
     Ok(Some(item))
 }
 
@@ -8,6 +8,7 @@ use crate::{
         ribosome::callback::{genesis::genesis, CallbackParams, CallbackResult},
         state::NucleusStatus,
     },
+    workflows::register_agent::commit_agent_id_link,
 };
 use futures::{
     future::Future,
@@ -51,6 +52,18 @@ pub async fn initialize_application(
         action_wrapper.clone(),
     );
 
+    // A source chain restored from storage went through genesis already
+    if context_clone.state().unwrap().agent().top_chain_header().is_some() {
+        context_clone
+            .action_channel
+            .send(ActionWrapper::new(Action::ReturnInitializationResult(None)))
+            .expect("Action channel not usable in initialize_application()");
+        return await!(InitializationFuture {
+            context: context.clone(),
+            created_at: Instant::now(),
+        });
+    }
+
     // Commit DNA to chain
     let dna_entry = Entry::Dna(dna.clone());
     let dna_commit = await!(commit_entry(dna_entry, None, &context_clone));
@@ -99,11 +112,19 @@ pub async fn initialize_application(
         _ => false,
     });
 
-    let maybe_error = fail_result.and_then(|result| match result {
+    let mut maybe_error = fail_result.and_then(|result| match result {
         CallbackResult::Fail(error_string) => Some(error_string.clone()),
         _ => None,
     });
 
+    // Link the agent into the DNA's agent directory, the network is not up yet
+    // so the link gets published after initialize_network()
+    if maybe_error.is_none() {
+        if let Err(error) = await!(commit_agent_id_link(&dna, &context_clone)) {
+            maybe_error = Some(format!("error committing agent directory link: {}", error));
+        }
+    }
+
     context_clone
         .action_channel
         .send(ActionWrapper::new(Action::ReturnInitializationResult(
@@ -20,7 +20,6 @@ use snowflake;
 use std::{
     pin::{Pin, Unpin},
     sync::Arc,
-    thread,
 };
 
 /// ValidateEntry Action Creator
@@ -56,8 +55,8 @@ pub fn validate_entry<'a>(
             }
         }
 
-        EntryType::LinkAdd => {
-            // LinkAdd can always be validated
+        EntryType::LinkAdd | EntryType::LinkRemove => {
+            // LinkAdd and LinkRemove can always be validated
         }
 
         EntryType::Deletion => {
@@ -79,7 +78,20 @@ pub fn validate_entry<'a>(
         let address = address.clone();
         let entry = entry.clone();
         let context = context.clone();
-        thread::spawn(move || {
+        // The ValidationFuture waits for a result, also if the task gets cancelled
+        let cancelled_key = (id.clone(), address.clone());
+        let cancelled_context = context.clone();
+        let cancelled = move || {
+            let _ = cancelled_context.action_channel.send(ActionWrapper::new(
+                Action::ReturnValidationResult((
+                    cancelled_key,
+                    Err(String::from(
+                        "The instance got stopped before the entry got validated",
+                    )),
+                )),
+            ));
+        };
+        let task = move || {
             let maybe_validation_result = callback::validate_entry::validate_entry(
                 entry.clone(),
                 validation_data.clone(),
@@ -106,7 +118,8 @@ pub fn validate_entry<'a>(
                     result,
                 ))))
                 .expect("action channel to be open in reducer");
-        });
+        };
+        context.clone().spawn_task_or_else(task, cancelled);
     };
 
     FutureObj::new(Box::new(ValidationFuture {
@@ -14,6 +14,7 @@ use hdk::holochain_core_types::dna::zome::entry_types::Sharing;
 use hdk::holochain_core_types::json::JsonString;
 use hdk::holochain_core_types::json::RawString;
 use hdk::holochain_core_types::error::HolochainError;
+use hdk::holochain_core_types::cas::content::Address;
 
 #[derive(Serialize, Deserialize, DefaultJson, Debug)]
 struct TestEntryType {
@@ -34,7 +35,20 @@ define_zome! {
             validation: |s: RawString, _ctx: hdk::ValidationData| {
                 (String::from(s) != String::from("FAIL"))
                     .ok_or_else(|| "FAIL content is not allowed".to_string())
-            }
+            },
+
+            links: [
+                to!(
+                    "testEntryType",
+                    tag: "test-tag",
+                    validation_package: || {
+                        hdk::ValidationPackageDefinition::Entry
+                    },
+                    validation: |_base: Address, _target: Address, _ctx: hdk::ValidationData| {
+                        Ok(())
+                    }
+                )
+            ]
         ),
 
         entry!(
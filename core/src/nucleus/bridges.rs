@@ -0,0 +1,12 @@
+use crate::nucleus::ZomeFnCall;
+use holochain_core_types::{error::HolochainError, json::JsonString};
+
+/// The instances bridged to an instance, by the handles its DNA declares them under,
+/// see `BridgeDeclaration`. The container running the instances knows them and sets them
+/// on the context, see `Context::set_bridges()`. Zomes call them through hc_call_bridge.
+pub trait Bridges: Send + Sync {
+    /// Makes the given call to the instance bridged to under the handle and returns its
+    /// result. Fails with HolochainError::BridgeNotPresent if no instance is bridged to
+    /// under the handle.
+    fn call(&self, handle: &str, call: ZomeFnCall) -> Result<JsonString, HolochainError>;
+}
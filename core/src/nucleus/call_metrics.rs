@@ -0,0 +1,293 @@
+//! How long zome calls take, to find the slow zome functions before users do.
+//! The Ribosome times every call, see CallDurations, and the context keeps a
+//! LatencySummary per zome function, see ZomeFnLatencies. Calls that take longer than the slow-call threshold
+//! of the context get logged with a breakdown, see Context::set_slow_call_threshold().
+
+use crate::nucleus::ZomeFnCall;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+pub const LATENCY_BUCKET_COUNT: usize = 12;
+
+/// Upper bounds of the histogram buckets of LatencySummary, in milliseconds.
+/// Calls that took longer only count in the total.
+pub const LATENCY_BUCKETS_MS: [u64; LATENCY_BUCKET_COUNT] =
+    [1, 5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// How many of the latest calls of a zome function its percentiles and maximum go by
+pub const LATENCY_WINDOW: usize = 1000;
+
+/// How long a zome call took, and how much of that the zome function spent running
+/// its WASM and waiting for the host functions it invoked.
+/// The rest went to loading the module and passing the input and result.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CallDurations {
+    pub wall: Duration,
+    pub wasm: Duration,
+    pub host_calls: Duration,
+}
+
+/// The durations of the calls of a zome function
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LatencySummary {
+    /// How many calls there were, also before the window
+    pub count: u64,
+    pub sum_micros: u64,
+    /// How many calls took at most the bound of LATENCY_BUCKETS_MS with the same index
+    pub buckets: [u64; LATENCY_BUCKET_COUNT],
+    /// The durations of the latest LATENCY_WINDOW calls in microseconds, oldest first
+    window: VecDeque<u64>,
+}
+
+impl LatencySummary {
+    pub fn record(&mut self, duration: Duration) {
+        let micros = micros(duration);
+        self.count += 1;
+        self.sum_micros += micros;
+        for (bucket, bound_ms) in self.buckets.iter_mut().zip(LATENCY_BUCKETS_MS.iter()) {
+            if micros <= bound_ms * 1000 {
+                *bucket += 1;
+            }
+        }
+        if self.window.len() >= LATENCY_WINDOW {
+            self.window.pop_front();
+        }
+        self.window.push_back(micros);
+    }
+
+    /// The duration the given percentage of the latest calls took at most, in microseconds,
+    /// 0 if there were none
+    pub fn percentile_micros(&self, percent: u64) -> u64 {
+        let mut sorted: Vec<u64> = self.window.iter().cloned().collect();
+        sorted.sort();
+        let rank = (sorted.len() as u64 * percent + 99) / 100;
+        sorted.get((rank.max(1) - 1) as usize).cloned().unwrap_or(0)
+    }
+
+    /// The longest of the latest calls in microseconds, 0 if there were none
+    pub fn max_micros(&self) -> u64 {
+        self.window.iter().cloned().max().unwrap_or(0)
+    }
+}
+
+/// The latency summaries of the zome functions of an instance, by zome and function name.
+/// They get recorded by the task that ran the call, so they stay out of the state that gets
+/// cloned with every action. Shared by all clones of a context.
+#[derive(Clone, Default)]
+pub struct ZomeFnLatencies {
+    summaries: Arc<Mutex<BTreeMap<(String, String), LatencySummary>>>,
+}
+
+impl ZomeFnLatencies {
+    /// Counts the call in the latency summary of its zome function
+    pub fn record(&self, zome_call: &ZomeFnCall, durations: &CallDurations) {
+        self.summaries
+            .lock()
+            .unwrap()
+            .entry((zome_call.zome_name.clone(), zome_call.fn_name.clone()))
+            .or_insert_with(LatencySummary::default)
+            .record(durations.wall);
+    }
+
+    /// The latency summaries of the zome functions that got called, sorted by zome and
+    /// function name
+    pub fn latencies(&self) -> Vec<ZomeFnLatency> {
+        self.summaries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((zome, function), summary)| ZomeFnLatency::new(zome, function, summary))
+            .collect()
+    }
+}
+
+/// The latency summary of a zome function, as shown by admin interfaces
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ZomeFnLatency {
+    pub zome: String,
+    pub function: String,
+    pub count: u64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+    pub sum_ms: f64,
+    /// How many calls took at most `le_ms`, for each of LATENCY_BUCKETS_MS
+    pub buckets: Vec<LatencyBucket>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LatencyBucket {
+    pub le_ms: u64,
+    pub count: u64,
+}
+
+impl ZomeFnLatency {
+    pub fn new(zome: &str, function: &str, summary: &LatencySummary) -> Self {
+        ZomeFnLatency {
+            zome: zome.to_string(),
+            function: function.to_string(),
+            count: summary.count,
+            p50_ms: millis(summary.percentile_micros(50)),
+            p95_ms: millis(summary.percentile_micros(95)),
+            max_ms: millis(summary.max_micros()),
+            sum_ms: millis(summary.sum_micros),
+            buckets: LATENCY_BUCKETS_MS
+                .iter()
+                .zip(summary.buckets.iter())
+                .map(|(le_ms, count)| LatencyBucket {
+                    le_ms: *le_ms,
+                    count: *count,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The line that gets logged for a call that took longer than the threshold,
+/// with key=value pairs so it can be searched for
+pub fn slow_call_warning(
+    zome_call: &ZomeFnCall,
+    durations: &CallDurations,
+    threshold: Duration,
+) -> String {
+    format!(
+        "warn/slow_zome_call: zome={} function={} wall_ms={} wasm_ms={} host_calls_ms={} threshold_ms={}",
+        zome_call.zome_name,
+        zome_call.fn_name,
+        micros(durations.wall) / 1000,
+        micros(durations.wasm) / 1000,
+        micros(durations.host_calls) / 1000,
+        micros(threshold) / 1000,
+    )
+}
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros())
+}
+
+fn millis(micros: u64) -> f64 {
+    micros as f64 / 1000.0
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        instance::tests::{test_context_and_logger, test_instance_with_context},
+        nucleus::{
+            call_and_wait_for_result,
+            ribosome::{
+                api::{
+                    tests::{
+                        test_capability, test_function_name, test_zome_api_function_wasm,
+                        test_zome_name,
+                    },
+                    ZomeApiFunction,
+                },
+                run_dna_with_measurements, Defn,
+            },
+        },
+    };
+    use holochain_core_types::json::JsonString;
+    use holochain_wasm_utils::api_serialization::SleepArgs;
+    use std::sync::Arc;
+    use test_utils::{create_test_cap_with_fn_name, create_test_dna_with_cap};
+
+    fn sleep_call(millis: u64) -> ZomeFnCall {
+        ZomeFnCall::new(
+            &test_zome_name(),
+            &test_capability(),
+            &test_function_name(),
+            JsonString::from(SleepArgs { millis }),
+        )
+    }
+
+    #[test]
+    /// test that calls slower than the threshold get logged with their breakdown,
+    /// and that all calls count in the summary of their function
+    fn slow_calls_get_logged_and_counted() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::Sleep.as_str());
+        let capability = create_test_cap_with_fn_name(&test_function_name());
+        let dna =
+            create_test_dna_with_cap(&test_zome_name(), &test_capability(), &capability, &wasm);
+        let (context, logger) = test_context_and_logger("sleepy");
+        let mut context = (*context).clone();
+        context.set_slow_call_threshold(Duration::from_millis(100));
+        let (mut instance, context) =
+            test_instance_with_context(dna.clone(), Arc::new(context)).unwrap();
+
+        let (result, measurements) = run_dna_with_measurements(
+            &dna.name,
+            context.clone(),
+            wasm,
+            &sleep_call(150),
+            Some(JsonString::from(SleepArgs { millis: 150 }).into_bytes()),
+        );
+        assert!(result.is_ok());
+        let durations = measurements.durations.expect("call to be timed");
+        assert!(durations.host_calls >= Duration::from_millis(150));
+        assert!(durations.wasm < durations.host_calls);
+        assert!(durations.wall >= durations.wasm + durations.host_calls);
+
+        let is_warning = |line: &String| line.starts_with("warn/slow_zome_call:");
+        assert!(call_and_wait_for_result(sleep_call(1), &mut instance).is_ok());
+        assert!(!logger.lock().unwrap().log.iter().any(is_warning));
+
+        assert!(call_and_wait_for_result(sleep_call(150), &mut instance).is_ok());
+        let warnings: Vec<String> = logger
+            .lock()
+            .unwrap()
+            .log
+            .iter()
+            .filter(|line| is_warning(line))
+            .cloned()
+            .collect();
+        assert_eq!(warnings.len(), 1, "log = {:?}", logger.lock().unwrap().log);
+        assert!(warnings[0].contains(&format!(
+            "zome={} function={} wall_ms=",
+            test_zome_name(),
+            test_function_name()
+        )));
+        assert!(warnings[0].ends_with("threshold_ms=100"));
+
+        let latencies = context.zome_fn_latencies.latencies();
+        assert_eq!(latencies.len(), 1);
+        assert_eq!(latencies[0].function, test_function_name());
+        assert_eq!(latencies[0].count, 2);
+        assert!(latencies[0].max_ms >= 150.0);
+        // the quick call is in the bucket up to 100 ms, both are in the one up to 10 s
+        assert_eq!(latencies[0].buckets[5].le_ms, 100);
+        assert_eq!(latencies[0].buckets[5].count, 1);
+        assert_eq!(latencies[0].buckets[11].count, 2);
+    }
+
+    #[test]
+    fn summaries_count_calls_into_buckets_and_percentiles() {
+        let mut summary = LatencySummary::default();
+        assert_eq!(summary.percentile_micros(50), 0);
+        for millis in 1..=100 {
+            summary.record(Duration::from_millis(millis));
+        }
+        assert_eq!(summary.count, 100);
+        assert_eq!(summary.sum_micros, 5050 * 1000);
+        assert_eq!(summary.percentile_micros(50), 50_000);
+        assert_eq!(summary.percentile_micros(95), 95_000);
+        assert_eq!(summary.max_micros(), 100_000);
+        // buckets up to 1, 5, 10, 25, 50 and 100 ms and beyond
+        assert_eq!(
+            summary.buckets,
+            [1, 5, 10, 25, 50, 100, 100, 100, 100, 100, 100, 100]
+        );
+
+        for _ in 0..LATENCY_WINDOW {
+            summary.record(Duration::from_millis(2));
+        }
+        assert_eq!(summary.count, 100 + LATENCY_WINDOW as u64);
+        assert_eq!(summary.max_micros(), 2000);
+        assert_eq!(summary.buckets[0], 1);
+        assert_eq!(summary.buckets[1], 5 + LATENCY_WINDOW as u64);
+    }
+}
@@ -1,40 +1,57 @@
 /// Nucleus is the module that handles DNA, including the Ribosome.
 ///
 pub mod actions;
+pub mod bridges;
+pub mod call_metrics;
+pub mod parameters;
+pub mod partial_results;
 pub mod ribosome;
 pub mod state;
+pub mod validation_package_cache;
 
 use crate::{
-    action::{Action, ActionWrapper, NucleusReduceFn},
+    action::{Action, ActionId, ActionWrapper, NucleusReduceFn},
     context::Context,
-    instance::{dispatch_action_with_observer, Observer},
+    instance::{dispatch_action, dispatch_action_with_observer, Observer},
     nucleus::{
-        ribosome::api::call::reduce_call,
+        call_metrics::slow_call_warning,
+        parameters::normalize_parameters,
+        ribosome::{api::call::reduce_call, host_call_trace::HostCallTrace},
         state::{NucleusState, NucleusStatus},
     },
+    runtime::recv_helping,
 };
 use holochain_core_types::{
     dna::{wasm::DnaWasm, zome::capabilities::Capability, Dna},
     error::{DnaError, HcResult, HolochainError},
     json::JsonString,
 };
-use snowflake;
+use holochain_wasm_utils::api_serialization::CallContext;
 use std::{
+    panic::{self, AssertUnwindSafe},
     sync::{
-        mpsc::{sync_channel, SyncSender},
+        mpsc::{sync_channel, RecvTimeoutError, SyncSender},
         Arc,
     },
-    thread,
+    time::{Duration, Instant},
 };
 
+/// How often a call waiting for its result looks whether the instance got reported as
+/// crashed, see call_and_wait_for_result_unless_crashed()
+const CRASH_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
 /// Struct holding data for requesting the execution of a Zome function (ExecutionZomeFunction Action)
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ZomeFnCall {
-    id: snowflake::ProcessUniqueId,
+    id: ActionId,
     pub zome_name: String,
     pub cap_name: String,
     pub fn_name: String,
     pub parameters: JsonString,
+    /// Where the call came from, handed to the called function through the globals
+    pub call_context: CallContext,
+    /// Whether to record the host functions the call invokes, see HostCallTrace
+    pub audit: bool,
 }
 
 impl ZomeFnCall {
@@ -47,14 +64,35 @@ impl ZomeFnCall {
         ZomeFnCall {
             // @TODO can we defer to the ActionWrapper id?
             // @see https://github.com/holochain/holochain-rust/issues/198
-            id: snowflake::ProcessUniqueId::new(),
+            id: ActionId::new(),
             zome_name: zome.to_string(),
             cap_name: capability.to_string(),
             fn_name: function.to_string(),
             parameters: parameters.into(),
+            call_context: CallContext::Callback,
+            audit: false,
         }
     }
 
+    /// Calls default to CallContext::Callback, entry points outside of core set where
+    /// the call came from with this.
+    pub fn with_call_context(self, call_context: CallContext) -> Self {
+        ZomeFnCall {
+            call_context,
+            ..self
+        }
+    }
+
+    /// Audited calls record the host functions they invoke, see HostCallTrace
+    pub fn with_audit(self, audit: bool) -> Self {
+        ZomeFnCall { audit, ..self }
+    }
+
+    /// read only access to the id that is unique to this call
+    pub fn id(&self) -> ActionId {
+        self.id.clone()
+    }
+
     pub fn same_fn_as(&self, fn_call: &ZomeFnCall) -> bool {
         self.zome_name == fn_call.zome_name
             && self.cap_name == fn_call.cap_name
@@ -90,12 +128,13 @@ pub fn call_zome_and_wait_for_result(
 
     // Dispatch action with observer closure that waits for a result in the state
     let (sender, receiver) = sync_channel(1);
+    let observed_call = call.clone();
     dispatch_action_with_observer(
         action_channel,
         observer_channel,
         call_action_wrapper,
         move |state: &super::state::State| {
-            if let Some(result) = state.nucleus().zome_call_result(&call) {
+            if let Some(result) = state.nucleus().zome_call_result(&observed_call) {
                 sender
                     .send(result.clone())
                     .expect("local channel to be open");
@@ -105,8 +144,16 @@ pub fn call_zome_and_wait_for_result(
             }
         },
     );
-    // Block until we got that result through the channel:
-    receiver.recv().expect("local channel to work")
+    // Block until we got that result through the channel,
+    // which gets dropped with the observers if the action loop crashes:
+    // Bridge calls wait here on a worker of the runtime that runs the call, see recv_helping()
+    let result = recv_helping(&receiver, None)
+        .map_err(|_| HolochainError::new("The instance crashed before the call returned"))?;
+    dispatch_action(
+        action_channel,
+        ActionWrapper::new(Action::AcknowledgeZomeCallResult(call)),
+    );
+    result
 }
 
 /// Dispatch ExecuteZoneFunction to Instance and block until call has finished.
@@ -114,13 +161,34 @@ pub fn call_zome_and_wait_for_result(
 pub fn call_and_wait_for_result(
     call: ZomeFnCall,
     instance: &mut super::instance::Instance,
+) -> Result<JsonString, HolochainError> {
+    call_and_wait(call, instance, None)
+}
+
+/// Like call_and_wait_for_result(), but stops waiting with an error once the action loop
+/// of the instance got reported as crashed to the given context, e.g. by a watchdog that
+/// took it for hung, so that the caller does not wait forever on observers that never
+/// get called again
+pub fn call_and_wait_for_result_unless_crashed(
+    call: ZomeFnCall,
+    instance: &mut super::instance::Instance,
+    context: &Context,
+) -> Result<JsonString, HolochainError> {
+    call_and_wait(call, instance, Some(context))
+}
+
+fn call_and_wait(
+    call: ZomeFnCall,
+    instance: &mut super::instance::Instance,
+    context: Option<&Context>,
 ) -> Result<JsonString, HolochainError> {
     let call_action = ActionWrapper::new(Action::ExecuteZomeFunction(call.clone()));
 
     // Dispatch action with observer closure that waits for a result in the state
     let (sender, receiver) = sync_channel(1);
+    let observed_call = call.clone();
     instance.dispatch_with_observer(call_action, move |state: &super::state::State| {
-        if let Some(result) = state.nucleus().zome_call_result(&call) {
+        if let Some(result) = state.nucleus().zome_call_result(&observed_call) {
             sender
                 .send(result.clone())
                 .expect("local channel to be open");
@@ -130,8 +198,29 @@ pub fn call_and_wait_for_result(
         }
     });
 
-    // Block until we got that result through the channel:
-    receiver.recv().expect("local channel to work")
+    // Block until we got that result through the channel,
+    // which gets dropped with the observers if the action loop crashes:
+    // Bridge calls wait here on a worker of the runtime that runs the call, see recv_helping()
+    let result = loop {
+        match recv_helping(&receiver, Some(CRASH_CHECK_INTERVAL)) {
+            Ok(result) => break result,
+            Err(RecvTimeoutError::Timeout) => {
+                if let Some(crash) = context.and_then(|context| context.crash_report()) {
+                    return Err(HolochainError::ErrorGeneric(format!(
+                        "The instance crashed before the call returned: {}",
+                        crash.message
+                    )));
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(HolochainError::new(
+                    "The instance crashed before the call returned",
+                ))
+            }
+        }
+    };
+    instance.dispatch(ActionWrapper::new(Action::AcknowledgeZomeCallResult(call)));
+    result
 }
 
 pub type ZomeFnResult = HcResult<JsonString>;
@@ -140,11 +229,30 @@ pub type ZomeFnResult = HcResult<JsonString>;
 pub struct ExecuteZomeFnResponse {
     call: ZomeFnCall,
     result: ZomeFnResult,
+    peak_memory: Option<usize>,
+    host_call_trace: Option<HostCallTrace>,
 }
 
 impl ExecuteZomeFnResponse {
     fn new(call: ZomeFnCall, result: Result<JsonString, HolochainError>) -> Self {
-        ExecuteZomeFnResponse { call, result }
+        ExecuteZomeFnResponse {
+            call,
+            result,
+            peak_memory: None,
+            host_call_trace: None,
+        }
+    }
+
+    /// The most WASM memory the call used, see run_dna_with_memory_usage()
+    fn with_peak_memory(mut self, peak_memory: Option<usize>) -> Self {
+        self.peak_memory = peak_memory;
+        self
+    }
+
+    /// The host functions the call invoked, if it got audited
+    fn with_host_call_trace(mut self, host_call_trace: Option<HostCallTrace>) -> Self {
+        self.host_call_trace = host_call_trace;
+        self
     }
 
     /// read only access to call
@@ -156,6 +264,16 @@ impl ExecuteZomeFnResponse {
     pub fn result(&self) -> Result<JsonString, HolochainError> {
         self.result.clone()
     }
+
+    /// read only access to the most WASM memory the call used in bytes
+    pub fn peak_memory(&self) -> Option<usize> {
+        self.peak_memory
+    }
+
+    /// read only access to the host functions the call invoked, if it got audited
+    pub fn host_call_trace(&self) -> Option<HostCallTrace> {
+        self.host_call_trace.clone()
+    }
 }
 
 /// Reduce ReturnInitializationResult Action
@@ -212,25 +330,61 @@ fn reduce_init_application(
     }
 }
 
+/// Runs the zome function in a task with the given parameters, which are the ones of
+/// the call in their named form, see normalize_parameters()
 pub(crate) fn launch_zome_fn_call(
     context: Arc<Context>,
     zome_call: ZomeFnCall,
+    parameters: JsonString,
     wasm: &DnaWasm,
     dna_name: String,
 ) {
     let code = wasm.code.clone();
 
-    thread::spawn(move || {
-        // Have Ribosome spin up DNA and call the zome function
-        let call_result = ribosome::run_dna(
-            &dna_name,
-            context.clone(),
-            code,
-            &zome_call,
-            Some(zome_call.clone().parameters.into_bytes()),
+    // The caller waits for a result, also if the instance gets stopped before the call ran
+    let cancelled_context = context.clone();
+    let cancelled_call = zome_call.clone();
+    let cancelled = move || {
+        let response = ExecuteZomeFnResponse::new(
+            cancelled_call,
+            Err(HolochainError::ErrorGeneric(String::from(
+                "The instance got stopped before the zome function ran",
+            ))),
         );
+        let _ = cancelled_context.action_channel.send(ActionWrapper::new(
+            Action::ReturnZomeFunctionResult(response),
+        ));
+    };
+    let task = move || {
+        // Have Ribosome spin up DNA and call the zome function.
+        // A panic must still end in a result, otherwise the caller would wait forever.
+        let (call_result, measurements) = panic::catch_unwind(AssertUnwindSafe(|| {
+            ribosome::run_dna_with_measurements(
+                &dna_name,
+                context.clone(),
+                code,
+                &zome_call,
+                Some(parameters.into_bytes()),
+            )
+        }))
+        .unwrap_or_else(|_| {
+            let error = HolochainError::ErrorGeneric(format!(
+                "Zome function {} panicked",
+                zome_call.fn_name
+            ));
+            (Err(error), ribosome::CallMeasurements::default())
+        });
+        if let Some(ref durations) = measurements.durations {
+            context.zome_fn_latencies.record(&zome_call, durations);
+            let threshold = context.slow_call_threshold();
+            if durations.wall > threshold {
+                context.log(slow_call_warning(&zome_call, durations, threshold));
+            }
+        }
         // Construct response
-        let response = ExecuteZomeFnResponse::new(zome_call.clone(), call_result);
+        let response = ExecuteZomeFnResponse::new(zome_call.clone(), call_result)
+            .with_peak_memory(measurements.peak_memory)
+            .with_host_call_trace(measurements.host_call_trace);
         // Send ReturnZomeFunctionResult Action
         context
             .action_channel
@@ -238,7 +392,8 @@ pub(crate) fn launch_zome_fn_call(
                 response,
             )))
             .expect("action channel to be open in reducer");
-    });
+    };
+    context.clone().spawn_task_or_else(task, cancelled);
 }
 
 /// Reduce ExecuteZomeFunction Action
@@ -315,31 +470,39 @@ fn reduce_execute_zome_function(
         Some(capability) => capability,
     };
     // Get ZomeFn
-    let maybe_fn = capability
+    let fn_declaration = match capability
         .functions
         .iter()
-        .find(|&fn_declaration| fn_declaration.name == fn_call.fn_name);
-    if maybe_fn.is_none() {
-        dispatch_error_result(
-            &context.action_channel,
-            &fn_call,
-            HolochainError::Dna(DnaError::ZomeFunctionNotFound(format!(
-                "Zome function '{}' not found",
-                fn_call.fn_name.clone()
-            ))),
-        );
-        return;
-    }
+        .find(|&fn_declaration| fn_declaration.name == fn_call.fn_name)
+    {
+        None => {
+            dispatch_error_result(
+                &context.action_channel,
+                &fn_call,
+                HolochainError::Dna(DnaError::ZomeFunctionNotFound(format!(
+                    "Zome function '{}' not found",
+                    fn_call.fn_name.clone()
+                ))),
+            );
+            return;
+        }
+        Some(fn_declaration) => fn_declaration,
+    };
+    // Bring positional and single value parameters into their named form
+    let parameters = match normalize_parameters(fn_declaration, &fn_call.parameters) {
+        Err(error) => {
+            dispatch_error_result(&context.action_channel, &fn_call, error);
+            return;
+        }
+        Ok(parameters) => parameters,
+    };
+    let wasm = zome.code.clone();
+    let dna_name = dna.name.clone();
     // Ok Zome function is defined in given capability.
     // Prepare call - FIXME is this really useful?
-    state.zome_calls.insert(fn_call.clone(), None);
+    state.start_zome_call(&fn_call);
     // Launch thread with function call
-    launch_zome_fn_call(
-        context,
-        fn_call,
-        &zome.code,
-        state.dna.clone().unwrap().name,
-    );
+    launch_zome_fn_call(context, fn_call, parameters, &wasm, dna_name);
 }
 
 fn reduce_return_validation_result(
@@ -355,7 +518,7 @@ fn reduce_return_validation_result(
 }
 
 /// Reduce ReturnZomeFunctionResult Action.
-/// Simply drops function call into zome_calls state.
+/// Simply drops function call into zome_calls state, where it stays until it got pruned.
 #[allow(unknown_lints)]
 #[allow(needless_pass_by_value)]
 fn reduce_return_zome_function_result(
@@ -367,7 +530,39 @@ fn reduce_return_zome_function_result(
     let fr = unwrap_to!(action => Action::ReturnZomeFunctionResult);
     // @TODO store the action and result directly
     // @see https://github.com/holochain/holochain-rust/issues/198
-    state.zome_calls.insert(fr.call(), Some(fr.result()));
+    state.return_zome_call(&fr.call(), fr.result());
+    if let Some(peak_memory) = fr.peak_memory() {
+        state.record_zome_call_memory(&fr.call(), peak_memory);
+    }
+    if let Some(host_call_trace) = fr.host_call_trace() {
+        state.record_host_call_trace(&fr.call(), host_call_trace);
+    }
+}
+
+/// Reduce AcknowledgeZomeCallResult Action.
+/// Marks the result as delivered, so that it gets pruned after ZOME_CALL_RESULT_TTL.
+#[allow(unknown_lints)]
+#[allow(needless_pass_by_value)]
+fn reduce_acknowledge_zome_call_result(
+    _context: Arc<Context>,
+    state: &mut NucleusState,
+    action_wrapper: &ActionWrapper,
+) {
+    let action = action_wrapper.action();
+    let call = unwrap_to!(action => Action::AcknowledgeZomeCallResult);
+    state.deliver_zome_call(call, Instant::now());
+}
+
+/// Reduce PruneZomeCalls Action.
+/// Drops delivered results after their TTL and undelivered ones after the zome call timeout.
+#[allow(unknown_lints)]
+#[allow(needless_pass_by_value)]
+fn reduce_prune_zome_calls(
+    context: Arc<Context>,
+    state: &mut NucleusState,
+    _action_wrapper: &ActionWrapper,
+) {
+    state.prune_zome_calls(Instant::now(), context.zome_call_timeout());
 }
 
 fn reduce_return_validation_package(
@@ -390,6 +585,8 @@ fn resolve_reducer(action_wrapper: &ActionWrapper) -> Option<NucleusReduceFn> {
         Action::ExecuteZomeFunction(_) => Some(reduce_execute_zome_function),
         Action::ReturnZomeFunctionResult(_) => Some(reduce_return_zome_function_result),
         Action::Call(_) => Some(reduce_call),
+        Action::AcknowledgeZomeCallResult(_) => Some(reduce_acknowledge_zome_call_result),
+        Action::PruneZomeCalls => Some(reduce_prune_zome_calls),
         Action::ReturnValidationResult(_) => Some(reduce_return_validation_result),
         Action::ReturnValidationPackage(_) => Some(reduce_return_validation_package),
         _ => None,
@@ -437,13 +634,20 @@ pub mod tests {
     use crate::{
         action::{tests::test_action_wrapper_rzfr, ActionWrapper},
         instance::{
-            tests::{test_context, test_context_with_channels, test_instance},
+            tests::{
+                test_context, test_context_with_channels, test_instance, test_instance_with_context,
+            },
             Instance,
         },
         nucleus::state::tests::test_nucleus_state,
+        runtime::Runtime,
     };
     use holochain_core_types::dna::Dna;
-    use std::sync::Arc;
+    use std::{
+        sync::Arc,
+        thread::sleep,
+        time::{Duration, Instant},
+    };
 
     use holochain_core_types::json::{JsonString, RawString};
     use std::error::Error;
@@ -484,7 +688,7 @@ pub mod tests {
     }
 
     #[test]
-    /// test the equality and uniqueness of function calls (based on internal snowflakes)
+    /// test the equality and uniqueness of function calls (based on internal ActionIds)
     fn test_zome_call_eq() {
         let zc1 = test_zome_call();
         let zc2 = test_zome_call();
@@ -622,6 +826,79 @@ pub mod tests {
         assert_eq!(JsonString::from(RawString::from(1337)), result.unwrap());
     }
 
+    #[test]
+    /// tests that calls which got cancelled before they ran, i.e. because the instance got
+    /// stopped, return an error instead of leaving the caller waiting
+    fn cancelled_zome_call_returns_an_error() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", "test_cap", None);
+        let mut context = test_context("stopped_instance");
+        Arc::get_mut(&mut context)
+            .unwrap()
+            .set_runtime(Runtime::new(1));
+        let (mut instance, context) =
+            test_instance_with_context(dna, context).expect("Could not initialize test instance");
+
+        context.cancel_tasks();
+        let zome_call = ZomeFnCall::new("test_zome", "test_cap", "main", "");
+        let result = super::call_and_wait_for_result(zome_call, &mut instance);
+
+        assert_eq!(
+            result,
+            Err(HolochainError::ErrorGeneric(String::from(
+                "The instance got stopped before the zome function ran"
+            ))),
+        );
+    }
+
+    /// Waits up to ten seconds for the nucleus state of the instance to satisfy the condition
+    fn wait_for_nucleus<F: Fn(&NucleusState) -> bool>(instance: &Instance, condition: F) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(10) {
+            if condition(&instance.state().nucleus()) {
+                return true;
+            }
+            sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    /// tests that results nobody waits for get pruned after the zome call timeout,
+    /// and that delivered results get acknowledged
+    fn undelivered_zome_call_results_get_pruned() {
+        let dna = test_utils::create_test_dna_with_wat("test_zome", "test_cap", None);
+        let mut context = test_context("dropped_client");
+        Arc::get_mut(&mut context)
+            .unwrap()
+            .set_zome_call_timeout(Duration::from_millis(100));
+        let (mut instance, _) =
+            test_instance_with_context(dna, context).expect("Could not initialize test instance");
+
+        // Calls of a client that went away before they returned
+        let dropped_calls: Vec<ZomeFnCall> = (0..3)
+            .map(|_| ZomeFnCall::new("test_zome", "test_cap", "main", ""))
+            .collect();
+        for call in &dropped_calls {
+            instance.dispatch(ActionWrapper::new(Action::ExecuteZomeFunction(
+                call.clone(),
+            )));
+        }
+        let delivered_call = ZomeFnCall::new("test_zome", "test_cap", "main", "");
+        let result = super::call_and_wait_for_result(delivered_call.clone(), &mut instance);
+        assert_eq!(JsonString::from(RawString::from(1337)), result.unwrap());
+
+        assert!(wait_for_nucleus(&instance, |nucleus| nucleus
+            .zome_call_times
+            .get(&delivered_call)
+            .map(|times| times.delivered.is_some())
+            .unwrap_or(true)));
+        assert!(wait_for_nucleus(&instance, |nucleus| dropped_calls
+            .iter()
+            .all(|call| !nucleus.zome_calls.contains_key(call))));
+        let stats = instance.state().nucleus().zome_call_stats();
+        assert!(stats.pruned >= dropped_calls.len(), "stats = {:?}", stats);
+    }
+
     #[test]
     /// smoke test reducing over a nucleus
     fn can_reduce_execfn_action() {
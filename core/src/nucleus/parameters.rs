@@ -0,0 +1,125 @@
+//! Zome functions get their parameters as a JSON object with a field for every parameter.
+//! Callers can also give them positionally, as an array in the order the DNA declares them,
+//! or as a single value if the function declares exactly one parameter.
+//! The nucleus turns those into the named form before the WASM sees them.
+
+use holochain_core_types::{
+    dna::zome::capabilities::FnDeclaration, error::HolochainError, json::JsonString,
+};
+use serde_json::{self, Map, Value};
+
+/// Returns the parameters as a JSON object of named parameters:
+/// - objects stay as they are
+/// - arrays get mapped to the declared parameters by position
+/// - any other value becomes the only declared parameter
+///
+/// Parameters that are not JSON, or null, are handed to the function as they are.
+/// Fails if the number of parameters given differs from the number of declared ones.
+pub fn normalize_parameters(
+    fn_declaration: &FnDeclaration,
+    parameters: &JsonString,
+) -> Result<JsonString, HolochainError> {
+    let value: Value = match serde_json::from_str(&String::from(parameters.clone())) {
+        Ok(value) => value,
+        Err(_) => return Ok(parameters.clone()),
+    };
+    let values = match value {
+        Value::Object(_) | Value::Null => return Ok(parameters.clone()),
+        Value::Array(values) => values,
+        value => vec![value],
+    };
+    let inputs = &fn_declaration.inputs;
+    if values.len() != inputs.len() {
+        return Err(HolochainError::ErrorGeneric(format!(
+            "Zome function {} expects {} parameters, got {}",
+            fn_declaration.name,
+            inputs.len(),
+            values.len()
+        )));
+    }
+    let named: Map<String, Value> = inputs
+        .iter()
+        .map(|input| input.name.clone())
+        .zip(values)
+        .collect();
+    Ok(JsonString::from(Value::Object(named)))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core_types::dna::zome::capabilities::FnParameter;
+
+    fn fn_declaration(inputs: &[&str]) -> FnDeclaration {
+        let mut fn_declaration = FnDeclaration::new();
+        fn_declaration.name = String::from("test_fn");
+        fn_declaration.inputs = inputs
+            .iter()
+            .map(|name| FnParameter::new(*name, "String"))
+            .collect();
+        fn_declaration
+    }
+
+    fn normalize(inputs: &[&str], parameters: &str) -> Result<String, HolochainError> {
+        normalize_parameters(
+            &fn_declaration(inputs),
+            &JsonString::from(parameters.to_string()),
+        )
+        .map(String::from)
+    }
+
+    #[test]
+    fn named_parameters_stay_as_they_are() {
+        assert_eq!(
+            normalize(&["a", "b"], r#"{"b":2,"a":1}"#),
+            Ok(String::from(r#"{"b":2,"a":1}"#))
+        );
+        assert_eq!(normalize(&[], ""), Ok(String::new()));
+        assert_eq!(normalize(&["a"], "null"), Ok(String::from("null")));
+        assert_eq!(normalize(&["a"], "not json"), Ok(String::from("not json")));
+    }
+
+    #[test]
+    fn positional_parameters_get_their_declared_names() {
+        assert_eq!(
+            normalize(&["a", "b"], r#"[1,"two"]"#),
+            Ok(String::from(r#"{"a":1,"b":"two"}"#))
+        );
+        assert_eq!(
+            normalize(&["list"], r#"[[1,2]]"#),
+            Ok(String::from(r#"{"list":[1,2]}"#))
+        );
+        assert_eq!(normalize(&[], "[]"), Ok(String::from("{}")));
+    }
+
+    #[test]
+    fn single_values_go_to_the_only_parameter() {
+        assert_eq!(normalize(&["a"], "42"), Ok(String::from(r#"{"a":42}"#)));
+        assert_eq!(
+            normalize(&["a"], r#""hello""#),
+            Ok(String::from(r#"{"a":"hello"}"#))
+        );
+    }
+
+    #[test]
+    fn arity_mismatches_state_expected_and_given_counts() {
+        assert_eq!(
+            normalize(&["a", "b"], "[1]"),
+            Err(HolochainError::ErrorGeneric(String::from(
+                "Zome function test_fn expects 2 parameters, got 1"
+            )))
+        );
+        assert_eq!(
+            normalize(&["a", "b"], "true"),
+            Err(HolochainError::ErrorGeneric(String::from(
+                "Zome function test_fn expects 2 parameters, got 1"
+            )))
+        );
+        assert_eq!(
+            normalize(&[], r#"[1,2,3]"#),
+            Err(HolochainError::ErrorGeneric(String::from(
+                "Zome function test_fn expects 0 parameters, got 3"
+            )))
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use crate::nucleus::ZomeFnCall;
+use holochain_core_types::{
+    error::{HcResult, HolochainError},
+    json::JsonString,
+};
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+};
+
+struct PartialResultSink {
+    sender: SyncSender<JsonString>,
+    overflowed: bool,
+}
+
+/// Where the partial results that running zome calls yield through hc_yield go.
+/// Whoever makes a call and wants its partial results registers a sink for it beforehand,
+/// partial results of calls without a sink get dropped.
+/// Shared by all clones of a context.
+#[derive(Clone, Default)]
+pub struct PartialResultSinks {
+    sinks: Arc<Mutex<HashMap<ZomeFnCall, PartialResultSink>>>,
+}
+
+impl PartialResultSinks {
+    /// Hands the partial results of the call to the sender until `unregister()` gets called.
+    /// The capacity of the sender is how many partial results can wait to be consumed.
+    pub fn register(&self, call: &ZomeFnCall, sender: SyncSender<JsonString>) {
+        self.sinks.lock().unwrap().insert(
+            call.clone(),
+            PartialResultSink {
+                sender,
+                overflowed: false,
+            },
+        );
+    }
+
+    /// Hands a partial result of the call to its sink, if it has one.
+    /// Fails with BackpressureExceeded if the sink is full, which also fails the whole call,
+    /// see `unregister()`. A sink that got dropped just gets no more partial results.
+    pub fn send(&self, call: &ZomeFnCall, partial_result: JsonString) -> HcResult<()> {
+        let mut sinks = self.sinks.lock().unwrap();
+        let sink = match sinks.get_mut(call) {
+            Some(sink) => sink,
+            None => return Ok(()),
+        };
+        if sink.overflowed {
+            return Err(HolochainError::BackpressureExceeded);
+        }
+        match sink.sender.try_send(partial_result) {
+            Err(TrySendError::Full(_)) => {
+                sink.overflowed = true;
+                Err(HolochainError::BackpressureExceeded)
+            }
+            Ok(()) | Err(TrySendError::Disconnected(_)) => Ok(()),
+        }
+    }
+
+    /// Drops the sink of the call, which closes the stream of its partial results.
+    /// Fails with BackpressureExceeded if partial results were lost because it was full.
+    pub fn unregister(&self, call: &ZomeFnCall) -> HcResult<()> {
+        match self.sinks.lock().unwrap().remove(call) {
+            Some(ref sink) if sink.overflowed => Err(HolochainError::BackpressureExceeded),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::nucleus::tests::test_zome_call;
+    use std::sync::mpsc::sync_channel;
+
+    #[test]
+    fn partial_results_go_to_the_sink_of_their_call() {
+        let sinks = PartialResultSinks::default();
+        let call = test_zome_call();
+        let other_call = test_zome_call();
+        let (sender, receiver) = sync_channel(10);
+        sinks.register(&call, sender);
+
+        assert_eq!(sinks.send(&call, JsonString::from("1")), Ok(()));
+        assert_eq!(sinks.send(&other_call, JsonString::from("2")), Ok(()));
+        assert_eq!(sinks.send(&call, JsonString::from("3")), Ok(()));
+        assert_eq!(sinks.unregister(&call), Ok(()));
+
+        let received: Vec<JsonString> = receiver.iter().collect();
+        assert_eq!(received, vec![JsonString::from("1"), JsonString::from("3")]);
+    }
+
+    #[test]
+    fn full_sinks_fail_the_call() {
+        let sinks = PartialResultSinks::default();
+        let call = test_zome_call();
+        let (sender, _receiver) = sync_channel(1);
+        sinks.register(&call, sender);
+
+        assert_eq!(sinks.send(&call, JsonString::from("1")), Ok(()));
+        assert_eq!(
+            sinks.send(&call, JsonString::from("2")),
+            Err(HolochainError::BackpressureExceeded)
+        );
+        assert_eq!(
+            sinks.unregister(&call),
+            Err(HolochainError::BackpressureExceeded)
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use crate::{
+    network::actions::await_publish::await_publish,
+    nucleus::ribosome::{
+        api::{ZomeApiFunction, ZomeApiResult},
+        Defn, Runtime,
+    },
+};
+use futures::executor::block_on;
+use holochain_wasm_utils::api_serialization::AwaitPublishArgs;
+use std::{convert::TryFrom, time::Duration};
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::AwaitPublish function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Expected complex argument: AwaitPublishArgs
+/// Returns an HcApiReturnCode as I32
+/// Blocks until at least min_holders agents acknowledged holding the published entry,
+/// and returns how many did. Fails with Timeout if they don't in time.
+pub fn invoke_await_publish(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    let args_str = runtime.load_json_string_from_args(&args);
+    let input = match AwaitPublishArgs::try_from(args_str) {
+        Ok(input) => input,
+        Err(_) => return ribosome_error_code!(ArgumentDeserializationFailed),
+    };
+    let result = block_on(await_publish(
+        &input.address,
+        input.min_holders,
+        Duration::from_millis(input.timeout_ms),
+        &runtime.context,
+    ))
+    .map_err(|hc_err| hc_err.with_context(ZomeApiFunction::AwaitPublish.as_str()));
+    runtime.store_result(result)
+}
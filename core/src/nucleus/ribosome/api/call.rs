@@ -4,10 +4,12 @@ use crate::{
     instance::RECV_DEFAULT_TIMEOUT_MS,
     nucleus::{
         get_capability_with_zome_call, launch_zome_fn_call,
+        parameters::normalize_parameters,
         ribosome::{api::ZomeApiResult, Runtime},
         state::NucleusState,
         ZomeFnCall,
     },
+    runtime::recv_helping,
 };
 use holochain_core_types::{dna::zome::capabilities::Membrane, error::HolochainError};
 use holochain_wasm_utils::api_serialization::ZomeFnCallArgs;
@@ -45,7 +47,9 @@ pub fn invoke_call(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
     };
 
     // ZomeFnCallArgs to ZomeFnCall
-    let zome_call = ZomeFnCall::from_args(input);
+    // A call from within a zome is made on behalf of whoever made the outer call
+    let zome_call =
+        ZomeFnCall::from_args(input).with_call_context(runtime.zome_call.call_context.clone());
 
     // Don't allow recursive calls
     if zome_call.same_fn_as(&runtime.zome_call) {
@@ -56,13 +60,14 @@ pub fn invoke_call(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
     let action_wrapper = ActionWrapper::new(Action::Call(zome_call.clone()));
     // Send Action and block
     let (sender, receiver) = channel();
+    let observed_call = zome_call.clone();
     crate::instance::dispatch_action_with_observer(
         &runtime.context.action_channel,
         &runtime.context.observer_channel,
         action_wrapper.clone(),
         move |state: &crate::state::State| {
             // Observer waits for a ribosome_call_result
-            let maybe_result = state.nucleus().zome_call_result(&zome_call);
+            let maybe_result = state.nucleus().zome_call_result(&observed_call);
             match maybe_result {
                 Some(result) => {
                     // @TODO never panic in wasm
@@ -82,16 +87,21 @@ pub fn invoke_call(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
     // TODO #97 - Return error if timeout or something failed
     // return Err(_);
 
-    let result = receiver
-        .recv_timeout(RECV_DEFAULT_TIMEOUT_MS)
+    // The called function runs on the same runtime as this one, see recv_helping()
+    let result = recv_helping(&receiver, Some(RECV_DEFAULT_TIMEOUT_MS))
         .expect("observer dropped before done");
+    crate::instance::dispatch_action(
+        &runtime.context.action_channel,
+        ActionWrapper::new(Action::AcknowledgeZomeCallResult(zome_call)),
+    );
     runtime.store_result(result)
 }
 
 /// Reduce Call Action
 ///   1. Checks for correctness of ZomeFnCall inside the Action
 ///   2. Checks for permission to access Capability
-///   3. Execute the exposed Zome function in a separate thread
+///   3. Normalizes the parameters, see normalize_parameters()
+///   4. Execute the exposed Zome function in a separate thread
 /// Send the result in a ReturnZomeFunctionResult Action on success or failure like ExecuteZomeFunction
 pub(crate) fn reduce_call(
     context: Arc<Context>,
@@ -106,18 +116,14 @@ pub(crate) fn reduce_call(
     // Get Capability
     if state.dna.is_none() {
         // Notify failure
-        state
-            .zome_calls
-            .insert(fn_call.clone(), Some(Err(HolochainError::DnaMissing)));
+        state.return_zome_call(&fn_call, Err(HolochainError::DnaMissing));
         return;
     }
     let dna = state.dna.clone().unwrap();
     let maybe_cap = get_capability_with_zome_call(&dna, &fn_call);
     if let Err(fn_res) = maybe_cap {
         // Notify failure
-        state
-            .zome_calls
-            .insert(fn_call.clone(), Some(fn_res.result()));
+        state.return_zome_call(&fn_call, fn_res.result());
         return;
     }
     let cap = maybe_cap.unwrap().clone();
@@ -141,19 +147,38 @@ pub(crate) fn reduce_call(
     };
     if !can_call {
         // Notify failure
-        state.zome_calls.insert(
-            fn_call.clone(),
-            Some(Err(HolochainError::DoesNotHaveCapabilityToken)),
-        );
+        state.return_zome_call(&fn_call, Err(HolochainError::DoesNotHaveCapabilityToken));
         return;
     }
 
-    // 3. Get the exposed Zome function WASM and execute it in a separate thread
+    // 3. Bring positional and single value parameters into their named form
+    let parameters = match cap
+        .functions
+        .iter()
+        .find(|fn_declaration| fn_declaration.name == fn_call.fn_name)
+    {
+        Some(fn_declaration) => match normalize_parameters(fn_declaration, &fn_call.parameters) {
+            Ok(parameters) => parameters,
+            Err(error) => {
+                state.return_zome_call(&fn_call, Err(error));
+                return;
+            }
+        },
+        None => fn_call.parameters.clone(),
+    };
+
+    // 4. Get the exposed Zome function WASM and execute it in a separate thread
     let maybe_code = dna.get_wasm_from_zome_name(fn_call.zome_name.clone());
     let code =
         maybe_code.expect("zome not found, Should have failed before when getting capability.");
-    state.zome_calls.insert(fn_call.clone(), None);
-    launch_zome_fn_call(context, fn_call, &code, state.dna.clone().unwrap().name);
+    state.start_zome_call(&fn_call);
+    launch_zome_fn_call(
+        context,
+        fn_call,
+        parameters,
+        &code,
+        state.dna.clone().unwrap().name,
+    );
 }
 
 #[cfg(test)]
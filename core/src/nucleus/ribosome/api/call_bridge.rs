@@ -0,0 +1,177 @@
+use crate::{
+    context::Context,
+    nucleus::{
+        ribosome::{api::ZomeApiResult, Runtime},
+        ZomeFnCall,
+    },
+};
+use holochain_core_types::{entry::addressing::dna_hash, error::HolochainError, json::JsonString};
+use holochain_wasm_utils::api_serialization::{BridgeCallArgs, CallContext};
+use std::{convert::TryFrom, sync::Arc};
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::CallBridge function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Expected complex argument: BridgeCallArgs
+/// Returns an HcApiReturnCode as I32
+/// Calls a zome function of the instance bridged to under a handle the DNA declares,
+/// see BridgeDeclaration. The callee sees a CallContext::Bridge with the hash of this DNA.
+/// Fails with HolochainError::BridgeNotPresent if the container did not configure the bridge,
+/// which it only starts instances without if the bridge is optional.
+/// What the other instance answers depends on its state, that's why validation callbacks
+/// can't call it.
+pub fn invoke_call_bridge(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    if runtime.zome_call.call_context == CallContext::Validation {
+        return ribosome_error_code!(NonDeterministicCallInValidation);
+    }
+    let args_str = runtime.load_json_string_from_args(&args);
+    let input = match BridgeCallArgs::try_from(args_str) {
+        Ok(input) => input,
+        Err(_) => return ribosome_error_code!(ArgumentDeserializationFailed),
+    };
+    let result = call_bridge(&runtime.context, input);
+    runtime.store_result(result)
+}
+
+fn call_bridge(
+    context: &Arc<Context>,
+    input: BridgeCallArgs,
+) -> Result<JsonString, HolochainError> {
+    let dna = context.get_dna().ok_or(HolochainError::DnaMissing)?;
+    if dna.bridge(&input.handle).is_none() {
+        return Err(HolochainError::ErrorGeneric(format!(
+            "The DNA declares no bridge with handle \"{}\"",
+            input.handle
+        )));
+    }
+    let bridges = context
+        .bridges()
+        .ok_or_else(|| HolochainError::BridgeNotPresent(input.handle.clone()))?;
+    let call = ZomeFnCall::new(
+        &input.zome_name,
+        &input.cap_name,
+        &input.fn_name,
+        input.fn_args,
+    )
+    .with_call_context(CallContext::Bridge {
+        caller_instance_dna_hash: dna_hash(&dna),
+    });
+    bridges.call(&input.handle, call)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        context::Context,
+        instance::tests::{test_context, test_instance_with_context},
+        nucleus::{
+            bridges::Bridges,
+            ribosome::api::{
+                tests::{
+                    test_capability, test_zome_api_function_call, test_zome_api_function_wasm,
+                    test_zome_name,
+                },
+                ZomeApiFunction,
+            },
+            ZomeFnCall,
+        },
+    };
+    use holochain_core_types::{
+        dna::{
+            bridges::{BridgeDeclaration, BridgePresence},
+            Dna,
+        },
+        entry::addressing::dna_hash,
+        error::{HolochainError, ZomeApiInternalResult},
+        json::JsonString,
+    };
+    use holochain_wasm_utils::api_serialization::BridgeCallArgs;
+    use std::{convert::TryFrom, sync::Arc};
+
+    /// Bridges to a "persona" instance that answers with the call context of the call
+    struct TestBridges;
+
+    impl Bridges for TestBridges {
+        fn call(&self, handle: &str, call: ZomeFnCall) -> Result<JsonString, HolochainError> {
+            match handle {
+                "persona" => Ok(JsonString::from(call.call_context)),
+                _ => Err(HolochainError::BridgeNotPresent(handle.to_string())),
+            }
+        }
+    }
+
+    fn optional_bridge(handle: &str) -> BridgeDeclaration {
+        BridgeDeclaration {
+            handle: handle.to_string(),
+            presence: BridgePresence::Optional,
+            dna_hash: None,
+            functions: Vec::new(),
+        }
+    }
+
+    /// Calls over the bridge with the given handle from an instance whose DNA declares
+    /// the optional bridges "persona" and "search", returns the result and the DNA
+    fn call_bridge(bridges: Option<Arc<Bridges>>, handle: &str) -> (ZomeApiInternalResult, Dna) {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::CallBridge.as_str());
+        let mut dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability(),
+            wasm.clone(),
+        );
+        dna.bridges = vec![optional_bridge("persona"), optional_bridge("search")];
+        let mut context: Context = (*test_context("alice")).clone();
+        if let Some(bridges) = bridges {
+            context.set_bridges(bridges);
+        }
+        let (instance, context) =
+            test_instance_with_context(dna.clone(), Arc::new(context)).unwrap();
+        let args = BridgeCallArgs {
+            handle: handle.to_string(),
+            zome_name: String::from("profile"),
+            cap_name: String::from("main"),
+            fn_name: String::from("whoami"),
+            fn_args: String::from("{}"),
+        };
+        let call_result = test_zome_api_function_call(
+            &dna.name,
+            context,
+            &instance,
+            &wasm,
+            String::from(JsonString::from(args)).into_bytes(),
+        );
+        (ZomeApiInternalResult::try_from(call_result).unwrap(), dna)
+    }
+
+    #[test]
+    fn test_call_bridge_reaches_the_bridged_instance() {
+        let (result, dna) = call_bridge(Some(Arc::new(TestBridges)), "persona");
+        assert!(result.ok, "error = {}", result.error);
+        assert!(result.value.contains("Bridge"), "value = {}", result.value);
+        assert!(result.value.contains(&dna_hash(&dna).to_string()));
+    }
+
+    #[test]
+    fn test_call_bridge_fails_without_the_bridge() {
+        for result in vec![
+            call_bridge(Some(Arc::new(TestBridges)), "search").0,
+            call_bridge(None, "persona").0,
+        ] {
+            assert!(!result.ok);
+            assert!(
+                result.error.contains("BridgeNotPresent"),
+                "error = {}",
+                result.error
+            );
+        }
+
+        let (undeclared, _) = call_bridge(Some(Arc::new(TestBridges)), "other");
+        assert!(!undeclared.ok);
+        assert!(
+            undeclared
+                .error
+                .contains("The DNA declares no bridge with handle \\\"other\\\""),
+            "error = {}",
+            undeclared.error
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use crate::nucleus::ribosome::{api::ZomeApiResult, Runtime};
+use wasmi::RuntimeArgs;
+
+/// ZomeApiFunction::ChainInfo function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Not expecting any complex input
+/// Returns an HcApiReturnCode as I32
+pub fn invoke_chain_info(runtime: &mut Runtime, _args: &RuntimeArgs) -> ZomeApiResult {
+    // The counters are maintained by the commit reducer, so no chain walk is needed here
+    let chain_info = runtime.context.state().unwrap().agent().chain_info();
+    runtime.store_result(Ok(chain_info))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::nucleus::ribosome::{
+        api::{tests::test_zome_api_function, ZomeApiFunction},
+        Defn,
+    };
+    use holochain_core_types::{
+        cas::content::AddressableContent, error::ZomeApiInternalResult, json::JsonString,
+    };
+    use holochain_wasm_utils::api_serialization::ChainInfo;
+    use std::convert::TryFrom;
+
+    #[test]
+    /// test that the chain info of a freshly initialized instance shows the genesis entries
+    fn test_chain_info() {
+        let (call_result, context) =
+            test_zome_api_function(ZomeApiFunction::ChainInfo.as_str(), vec![]);
+
+        let zome_api_internal_result = ZomeApiInternalResult::try_from(call_result).unwrap();
+        assert!(zome_api_internal_result.ok);
+        let chain_info =
+            ChainInfo::try_from(JsonString::from(zome_api_internal_result.value)).unwrap();
+
+        assert_eq!(chain_info.length, 3);
+        assert_eq!(chain_info.count("%dna"), 1);
+        assert_eq!(chain_info.count("%agent_id"), 1);
+        // the link into the agent directory
+        assert_eq!(chain_info.count("%link_add"), 1);
+        assert_eq!(
+            chain_info.top_header_address,
+            context
+                .state()
+                .unwrap()
+                .agent()
+                .top_chain_header()
+                .map(|header| header.address())
+        );
+    }
+}
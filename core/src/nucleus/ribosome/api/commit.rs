@@ -1,52 +1,125 @@
 use crate::{
+    context::Context,
     nucleus::ribosome::{api::ZomeApiResult, Runtime},
-    workflows::author_entry::author_entry,
+    workflows::author_entry::author_entry_with_options,
 };
 use futures::executor::block_on;
-use holochain_core_types::{cas::content::Address, entry::Entry, error::HolochainError};
-use std::convert::TryFrom;
+use holochain_core_types::{
+    entry::{entry_type::EntryType, Entry},
+    error::HolochainError,
+};
+use holochain_wasm_utils::api_serialization::{CommitEntryArgs, CommitEntryResult, CommitOptions};
+use std::{convert::TryFrom, sync::Arc};
 use wasmi::{RuntimeArgs, RuntimeValue};
 
 /// ZomeApiFunction::CommitAppEntry function code
 /// args: [0] encoded MemoryAllocation as u32
-/// Expected complex argument: CommitArgs
+/// Expected complex argument: the Entry, or CommitEntryArgs to commit it with options
 /// Returns an HcApiReturnCode as I32
+/// On success the stored result is a CommitEntryResult
+/// The entry's type has to be an app entry type of the DNA, and the entry has to pass its
+/// validation callback, before anything gets written to the chain.
 pub fn invoke_commit_app_entry(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
     // deserialize args
     let args_str = runtime.load_json_string_from_args(&args);
-    let entry = match Entry::try_from(args_str.clone()) {
-        Ok(entry_input) => entry_input,
+    let commit_args = CommitEntryArgs::try_from(args_str.clone()).or_else(|_| {
+        Entry::try_from(args_str.clone()).map(|entry| CommitEntryArgs {
+            entry,
+            options: CommitOptions::default(),
+        })
+    });
+    let commit_args = match commit_args {
+        Ok(commit_args) => commit_args,
         // Exit on error
         Err(_) => {
             println!(
-                "invoke_commit_app_entry failed to deserialize Entry: {:?}",
+                "invoke_commit_app_entry failed to deserialize Entry or CommitEntryArgs: {:?}",
                 args_str
             );
             return ribosome_error_code!(ArgumentDeserializationFailed);
         }
     };
     // Wait for future to be resolved
-    let task_result: Result<Address, HolochainError> =
-        block_on(author_entry(&entry, None, &runtime.context));
+    let task_result: Result<CommitEntryResult, HolochainError> =
+        check_committable_entry_type(&commit_args.entry, &runtime.context).and_then(|_| {
+            block_on(author_entry_with_options(
+                &commit_args.entry,
+                None,
+                commit_args.options,
+                &runtime.context,
+            ))
+        });
 
     runtime.store_result(task_result)
 }
 
+/// Fails with HolochainError::ValidationFailed unless the entry is of an app entry type
+/// defined in the DNA: anything else couldn't be validated nor shared later on.
+/// System entries are only committed by core itself, e.g. during genesis, which doesn't go
+/// through this function.
+fn check_committable_entry_type(
+    entry: &Entry,
+    context: &Arc<Context>,
+) -> Result<(), HolochainError> {
+    match entry.entry_type() {
+        EntryType::App(app_entry_type) => {
+            let dna = context.get_dna().ok_or(HolochainError::DnaMissing)?;
+            let entry_type_name = String::from(app_entry_type.clone());
+            if EntryType::has_valid_app_name(&entry_type_name)
+                && dna
+                    .get_zome_name_for_app_entry_type(&app_entry_type)
+                    .is_some()
+            {
+                Ok(())
+            } else {
+                Err(HolochainError::ValidationFailed(format!(
+                    "unknown entry type {}",
+                    entry_type_name
+                )))
+            }
+        }
+        entry_type => Err(HolochainError::ValidationFailed(format!(
+            "system entry type {} can't be committed by a zome",
+            entry_type
+        ))),
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     extern crate test_utils;
     extern crate wabt;
 
-    use crate::nucleus::ribosome::{
-        api::{tests::test_zome_api_function, ZomeApiFunction},
-        Defn,
+    use crate::{
+        context::Context,
+        instance::tests::test_instance_and_context,
+        nucleus::{
+            ribosome::{
+                self,
+                api::{
+                    tests::{
+                        test_capability, test_function_name, test_zome_api_function,
+                        test_zome_api_function_call, test_zome_api_function_wasm,
+                        test_zome_api_function_wasm_validating_with, test_zome_name,
+                    },
+                    ZomeApiFunction,
+                },
+                Defn,
+            },
+            ZomeFnCall,
+        },
     };
     use holochain_core_types::{
-        cas::content::Address,
-        entry::{test_entry, Entry},
-        error::ZomeApiInternalResult,
-        json::JsonString,
+        cas::content::{Address, AddressableContent},
+        dna::zome::host_permissions::HostPermission,
+        entry::{entry_type::AppEntryType, test_entry, test_sys_entry, Entry},
+        error::{CoreError, HolochainError, RibosomeErrorCode, ZomeApiInternalResult},
+        json::{JsonString, RawString},
+    };
+    use holochain_wasm_utils::api_serialization::{
+        CommitEntryArgs, CommitEntryResult, CommitOptions,
     };
+    use std::{convert::TryFrom, sync::Arc};
 
     /// dummy commit args from standard test entry
     pub fn test_commit_args_bytes() -> Vec<u8> {
@@ -59,17 +132,214 @@ pub mod tests {
     #[test]
     /// test that we can round trip bytes through a commit action and get the result from WASM
     fn test_commit_round_trip() {
-        let (call_result, _) = test_zome_api_function(
+        let (call_result, context) = test_zome_api_function(
             ZomeApiFunction::CommitAppEntry.as_str(),
             test_commit_args_bytes(),
         );
 
+        let zome_api_internal_result = ZomeApiInternalResult::try_from(call_result)
+            .expect("valid ZomeApiInternalResult JsonString");
+        assert!(zome_api_internal_result.ok);
+        let commit_result =
+            CommitEntryResult::try_from(JsonString::from(zome_api_internal_result.value))
+                .expect("valid CommitEntryResult JsonString");
+
+        assert_eq!(
+            commit_result.address,
+            Address::from("Qma6RfzvZRL127UCEVEktPhQ7YSS1inxEFw7SjEsfMJcrq"),
+        );
+
+        // the returned header address must resolve to the header of the committed entry
+        let chain_header = context
+            .state()
+            .unwrap()
+            .agent()
+            .chain()
+            .get_header(&commit_result.header_address)
+            .expect("could not fetch header")
+            .expect("committed header should be in the chain");
+        assert_eq!(chain_header.entry_address(), &commit_result.address);
+    }
+
+    #[test]
+    /// test that a commit with options requiring another head than the top of the chain
+    /// fails with ChainHeadMoved, telling where the head is
+    fn test_commit_requiring_moved_head() {
+        let args = CommitEntryArgs {
+            entry: test_entry(),
+            options: CommitOptions {
+                require_head: Some(Address::from("QmNotTheHead")),
+                ..Default::default()
+            },
+        };
+        let (call_result, context) = test_zome_api_function(
+            ZomeApiFunction::CommitAppEntry.as_str(),
+            JsonString::from(args).into_bytes(),
+        );
+
+        let zome_api_internal_result = ZomeApiInternalResult::try_from(call_result)
+            .expect("valid ZomeApiInternalResult JsonString");
+        assert!(!zome_api_internal_result.ok);
+        let core_error = CoreError::try_from(zome_api_internal_result).expect("valid CoreError");
+        let head = context
+            .state()
+            .unwrap()
+            .agent()
+            .top_chain_header()
+            .map(|chain_header| chain_header.address());
+        assert_eq!(
+            core_error.kind,
+            HolochainError::ChainHeadMoved {
+                expected: Address::from("QmNotTheHead"),
+                actual: head,
+            },
+        );
+    }
+
+    #[test]
+    /// test that a zome that may only read the chain can't get instantiated if it
+    /// imports commit_entry
+    fn test_commit_without_chain_write_permission() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::CommitAppEntry.as_str());
+        let mut dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability(),
+            wasm.clone(),
+        );
+        dna.zomes
+            .get_mut(&test_zome_name())
+            .unwrap()
+            .host_permissions = Some(vec![HostPermission::ChainRead].into_iter().collect());
+        let dna_name = dna.name.clone();
+        let (_instance, context) =
+            test_instance_and_context(dna).expect("Could not create test instance");
+
+        let zome_call = ZomeFnCall::new(
+            &test_zome_name(),
+            &test_capability(),
+            &test_function_name(),
+            "",
+        );
+        let result = ribosome::run_dna(
+            &dna_name,
+            context,
+            wasm,
+            &zome_call,
+            Some(test_commit_args_bytes()),
+        );
+
+        assert_eq!(
+            result,
+            Err(HolochainError::PermissionDenied(String::from(
+                "Zome test_zome has no permission to call hc_commit_entry"
+            ))),
+        );
+    }
+
+    fn chain_length(context: &Arc<Context>) -> usize {
+        let state = context.state().unwrap();
+        let top_chain_header = state.agent().top_chain_header();
+        state.agent().chain().iter(&top_chain_header).count()
+    }
+
+    /// commits the entry from a zome whose validation callback returns the given value,
+    /// returns the error the commit failed with, if any, and by how much the chain grew
+    fn commit_validating_with(
+        entry: Entry,
+        validation_result: u32,
+    ) -> (Option<HolochainError>, usize) {
+        let wasm = test_zome_api_function_wasm_validating_with(
+            ZomeApiFunction::CommitAppEntry.as_str(),
+            validation_result,
+        );
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability(),
+            wasm.clone(),
+        );
+        let dna_name = dna.name.clone();
+        let (instance, context) =
+            test_instance_and_context(dna).expect("Could not create test instance");
+        let chain_length_before = chain_length(&context);
+
+        let call_result = test_zome_api_function_call(
+            &dna_name,
+            context.clone(),
+            &instance,
+            &wasm,
+            JsonString::from(entry).into_bytes(),
+        );
+        let zome_api_internal_result = ZomeApiInternalResult::try_from(call_result)
+            .expect("valid ZomeApiInternalResult JsonString");
+        let error = if zome_api_internal_result.ok {
+            None
+        } else {
+            Some(
+                CoreError::try_from(zome_api_internal_result)
+                    .expect("valid CoreError")
+                    .kind,
+            )
+        };
+        (error, chain_length(&context) - chain_length_before)
+    }
+
+    #[test]
+    /// test that only entries of a type defined in the DNA that pass validation get committed,
+    /// the others failing before anything is written to the chain
+    fn test_commit_validates_before_writing_to_the_chain() {
+        let undeclared_entry = Entry::App(
+            AppEntryType::from("undeclaredEntryType"),
+            RawString::from("some value").into(),
+        );
+        assert_eq!(
+            commit_validating_with(undeclared_entry, 0),
+            (
+                Some(HolochainError::ValidationFailed(String::from(
+                    "unknown entry type undeclaredEntryType"
+                ))),
+                0
+            ),
+        );
+
+        assert_eq!(
+            commit_validating_with(test_sys_entry(), 0),
+            (
+                Some(HolochainError::ValidationFailed(String::from(
+                    "system entry type %agent_id can't be committed by a zome"
+                ))),
+                0
+            ),
+        );
+
+        let rejected = commit_validating_with(
+            test_entry(),
+            RibosomeErrorCode::ArgumentDeserializationFailed as u32,
+        );
+        assert_eq!(
+            rejected,
+            (
+                Some(HolochainError::ValidationFailed(String::from(
+                    "JSON object does not match entry schema"
+                ))),
+                0
+            ),
+        );
+
+        assert_eq!(commit_validating_with(test_entry(), 0), (None, 1));
+    }
+
+    #[test]
+    /// test that cores which don't report a header address still deserialize
+    fn test_commit_result_without_header_address() {
+        let commit_result = CommitEntryResult::try_from(JsonString::from(
+            "{\"address\":\"Qma6RfzvZRL127UCEVEktPhQ7YSS1inxEFw7SjEsfMJcrq\"}",
+        ))
+        .expect("valid CommitEntryResult JsonString");
         assert_eq!(
-            call_result,
-            JsonString::from(
-                String::from(JsonString::from(ZomeApiInternalResult::success(
-                    Address::from("Qma6RfzvZRL127UCEVEktPhQ7YSS1inxEFw7SjEsfMJcrq")
-                ))) + "\u{0}"
+            commit_result,
+            CommitEntryResult::new(
+                Address::from("Qma6RfzvZRL127UCEVEktPhQ7YSS1inxEFw7SjEsfMJcrq"),
+                Address::from(""),
             ),
         );
     }
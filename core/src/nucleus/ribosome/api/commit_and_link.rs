@@ -0,0 +1,146 @@
+use crate::{
+    nucleus::ribosome::{api::ZomeApiResult, Runtime},
+    workflows::commit_and_link::commit_and_link,
+};
+use futures::executor::block_on;
+use holochain_core_types::error::HolochainError;
+use holochain_wasm_utils::api_serialization::link_entries::{
+    CommitAndLinkArgs, CommitAndLinkResult,
+};
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::CommitAndLink function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Expected complex argument: CommitAndLinkArgs
+/// On success the stored result is a CommitAndLinkResult
+pub fn invoke_commit_and_link(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    // deserialize args
+    let args_str = runtime.load_json_string_from_args(&args);
+    let input = match CommitAndLinkArgs::try_from(args_str.clone()) {
+        Ok(input) => input,
+        // Exit on error
+        Err(_) => {
+            println!(
+                "invoke_commit_and_link failed to deserialize CommitAndLinkArgs: {:?}",
+                args_str
+            );
+            return ribosome_error_code!(ArgumentDeserializationFailed);
+        }
+    };
+
+    // Wait for future to be resolved
+    let result: Result<CommitAndLinkResult, HolochainError> =
+        block_on(commit_and_link(&input, &runtime.context));
+
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        agent::actions::commit::commit_entry,
+        instance::tests::{test_context_and_logger, test_instance},
+        nucleus::ribosome::{
+            api::{
+                link_entries::tests::test_entry_b,
+                tests::{
+                    test_capability, test_zome_api_function_call, test_zome_api_function_wasm,
+                    test_zome_name,
+                },
+                ZomeApiFunction,
+            },
+            Defn,
+        },
+    };
+    use futures::executor::block_on;
+    use holochain_core_types::{
+        cas::content::AddressableContent,
+        entry::{test_entry, Entry},
+        error::{HolochainError, ZomeApiInternalResult},
+        json::JsonString,
+        link::{link_add::LinkAdd, Link, AGENT_ID_LINK_TAG},
+    };
+    use holochain_wasm_utils::api_serialization::link_entries::{
+        CommitAndLinkArgs, CommitAndLinkResult,
+    };
+    use std::convert::TryFrom;
+
+    #[test]
+    fn reports_the_outcome_of_every_link() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::CommitAndLink.as_str());
+        let dna =
+            test_utils::create_test_dna_with_wasm(&test_zome_name(), &test_capability(), wasm);
+        let instance = test_instance(dna).expect("Could not create test instance");
+        let (context, _) = test_context_and_logger("joan");
+        let context = instance.initialize_context(context);
+
+        let base = test_entry();
+        block_on(commit_entry(base.clone(), None, &context))
+            .expect("Could not commit entry for testing");
+        let args = CommitAndLinkArgs {
+            entry: test_entry_b(),
+            links: vec![
+                (base.address(), String::from("test-tag")),
+                (base.address(), String::from("wrong-tag")),
+                (base.address(), String::from(AGENT_ID_LINK_TAG)),
+            ],
+        };
+
+        let call_result = test_zome_api_function_call(
+            &context.get_dna().unwrap().name.to_string(),
+            context.clone(),
+            &instance,
+            &context.get_wasm(&test_zome_name()).unwrap().code,
+            JsonString::from(args).into_bytes(),
+        );
+
+        let zome_api_internal_result = ZomeApiInternalResult::try_from(call_result)
+            .expect("valid ZomeApiInternalResult JsonString");
+        assert!(zome_api_internal_result.ok);
+        let result =
+            CommitAndLinkResult::try_from(JsonString::from(zome_api_internal_result.value))
+                .expect("valid CommitAndLinkResult JsonString");
+
+        assert_eq!(result.entry.address, test_entry_b().address());
+        assert!(!result.all_linked());
+        assert_eq!(result.links.len(), 3);
+        let link_entry = Entry::LinkAdd(LinkAdd::from_link(&Link::new(
+            &base.address(),
+            &test_entry_b().address(),
+            "test-tag",
+        )));
+        assert_eq!(
+            result.links[0]
+                .as_ref()
+                .map(|commit| commit.address.clone()),
+            Ok(link_entry.address())
+        );
+        let link_error = result.links[1].clone().unwrap_err();
+        assert_eq!("not implemented", link_error.to_string());
+        assert_eq!(
+            result.links[2],
+            Err(HolochainError::ValidationFailed(format!(
+                "Link tag {} is reserved",
+                AGENT_ID_LINK_TAG
+            )))
+        );
+
+        // the entry and the valid link are on the chain, the failed links are not
+        let chain = context.state().unwrap().agent().chain();
+        let top_chain_header = context.state().unwrap().agent().top_chain_header();
+        let entry_addresses: Vec<_> = chain
+            .iter(&top_chain_header)
+            .map(|chain_header| chain_header.entry_address().clone())
+            .take(3)
+            .collect();
+        assert_eq!(
+            entry_addresses,
+            vec![
+                link_entry.address(),
+                test_entry_b().address(),
+                base.address()
+            ]
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use crate::nucleus::ribosome::{
+    api::{ZomeApiFunction, ZomeApiResult},
+    Defn, Runtime,
+};
+use holochain_wasm_utils::api_serialization::get_links::CountLinksArgs;
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::CountLinks function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Expected complex argument: CountLinksArgs
+/// Returns an HcApiReturnCode as I32
+/// Counts the links get_links would return for the same arguments,
+/// without loading their targets into the zome.
+pub fn invoke_count_links(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    let args_str = runtime.load_json_string_from_args(&args);
+    let input = match CountLinksArgs::try_from(args_str) {
+        Ok(input) => input,
+        Err(_) => return ribosome_error_code!(ArgumentDeserializationFailed),
+    };
+    let status_request = input.status_request;
+    let count = runtime
+        .context
+        .state()
+        .unwrap()
+        .dht()
+        .link_counts(input.entry_address, input.tag)
+        .map(|counts| {
+            counts
+                .iter()
+                .filter(|(status, _)| status_request.includes(**status))
+                .map(|(_, count)| count)
+                .sum::<usize>()
+        })
+        .map_err(|hc_err| hc_err.with_context(ZomeApiFunction::CountLinks.as_str()));
+    runtime.store_result(count)
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate test_utils;
+
+    use crate::{
+        agent::actions::commit::commit_entry,
+        dht::actions::{add_link::add_link, remove_entry::remove_entry},
+        instance::tests::{test_context_and_logger, test_instance},
+        nucleus::ribosome::{
+            api::{
+                get_links::tests::test_get_links_with_status_args_bytes, tests::*, ZomeApiFunction,
+            },
+            Defn,
+        },
+    };
+    use futures::executor::block_on;
+    use holochain_core_types::{
+        cas::content::Address,
+        entry::{entry_type::test_app_entry_type, Entry},
+        json::JsonString,
+        link::Link,
+    };
+    use holochain_wasm_utils::api_serialization::get_links::{
+        CountLinksArgs, GetLinksResult, LinksStatusFilter,
+    };
+    use serde_json;
+    use std::convert::TryFrom;
+
+    /// count_links args with the given filter on the status of the targets
+    pub fn test_count_links_args_bytes(
+        base: &Address,
+        tag: &str,
+        status_request: LinksStatusFilter,
+    ) -> Vec<u8> {
+        let args = CountLinksArgs {
+            entry_address: base.clone(),
+            tag: String::from(tag),
+            status_request,
+        };
+        serde_json::to_string(&args)
+            .expect("args should serialize")
+            .into_bytes()
+    }
+
+    /// The value of a ZomeApiInternalResult returned by a test zome
+    fn call_result_value(call_result: JsonString) -> String {
+        let call_result = String::from(call_result);
+        let call_result: serde_json::Value =
+            serde_json::from_str(call_result.trim_end_matches('\u{0}')).unwrap();
+        assert_eq!(call_result["ok"], true, "call_result = {}", call_result);
+        call_result["value"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    /// test that counting links agrees with getting them, also after links got added and
+    /// targets removed, whatever the filter on the status of the targets
+    fn counts_links_like_get_links() {
+        let count_wasm = test_zome_api_function_wasm(ZomeApiFunction::CountLinks.as_str());
+        let get_wasm = test_zome_api_function_wasm(ZomeApiFunction::GetLinks.as_str());
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability(),
+            count_wasm.clone(),
+        );
+
+        let dna_name = &dna.name.to_string().clone();
+        let instance = test_instance(dna).expect("Could not create test instance");
+
+        let (context, _) = test_context_and_logger("joan");
+        let initialized_context = instance.initialize_context(context);
+
+        let commit = |value: String| {
+            let entry = Entry::App(test_app_entry_type(), JsonString::from(value));
+            block_on(commit_entry(entry, None, &initialized_context))
+                .expect("Could not commit entry for testing")
+                .address
+        };
+        let link_to = |base: &Address, target: &Address| {
+            let link = Link::new(base, target, "likes");
+            assert!(block_on(add_link(&link, &initialized_context)).is_ok());
+        };
+        let count = |base: &Address, status_request: LinksStatusFilter| {
+            let value = call_result_value(test_zome_api_function_call(
+                &dna_name,
+                initialized_context.clone(),
+                &instance,
+                &count_wasm,
+                test_count_links_args_bytes(base, "likes", status_request),
+            ));
+            serde_json::from_str::<usize>(&value).unwrap()
+        };
+        let get_count = |base: &Address, status_request: LinksStatusFilter| {
+            let value = call_result_value(test_zome_api_function_call(
+                &dna_name,
+                initialized_context.clone(),
+                &instance,
+                &get_wasm,
+                test_get_links_with_status_args_bytes(base, "likes", status_request),
+            ));
+            GetLinksResult::try_from(JsonString::from(value))
+                .unwrap()
+                .addresses()
+                .len()
+        };
+        let assert_counts_agree = |base: &Address, live: usize, deleted: usize| {
+            for (status_request, expected) in vec![
+                (LinksStatusFilter::Live, live),
+                (LinksStatusFilter::Deleted, deleted),
+                (LinksStatusFilter::All, live + deleted),
+            ] {
+                assert_eq!(count(base, status_request.clone()), expected);
+                assert_eq!(get_count(base, status_request), expected);
+            }
+        };
+
+        let base = commit(String::from("post"));
+        let targets: Vec<Address> = (0..500).map(|i| commit(format!("like {}", i))).collect();
+        for target in targets[..490].iter() {
+            link_to(&base, target);
+        }
+        assert_counts_agree(&base, 490, 0);
+
+        // Links added after the first count get counted, adding one twice doesn't count twice
+        for target in targets[490..].iter() {
+            link_to(&base, target);
+        }
+        link_to(&base, &targets[0]);
+        assert_counts_agree(&base, 500, 0);
+
+        for (i, target) in targets.iter().enumerate().filter(|(i, _)| i % 25 == 0) {
+            block_on(remove_entry(
+                &initialized_context,
+                &initialized_context.action_channel,
+                target.clone(),
+                Address::from(format!("QmDeletion{}", i)),
+            ))
+            .expect("Could not remove entry for testing");
+        }
+        assert_counts_agree(&base, 480, 20);
+
+        assert_eq!(count(&targets[0], LinksStatusFilter::All), 0);
+    }
+}
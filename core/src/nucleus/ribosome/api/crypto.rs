@@ -0,0 +1,101 @@
+use crate::{
+    agent::keys::encrypt_for,
+    nucleus::ribosome::{
+        api::{ZomeApiFunction, ZomeApiResult},
+        Defn, Runtime,
+    },
+};
+use holochain_wasm_utils::api_serialization::{CallContext, DecryptArgs, EncryptForArgs};
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::EncryptFor function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Expected complex argument: EncryptForArgs
+/// Returns an HcApiReturnCode as I32
+/// Encrypts the payload with the public key in the recipient's agent address,
+/// so that only the recipient can decrypt it. Encrypting the same payload twice gives
+/// different bytes, that's why validation callbacks can't use it.
+pub fn invoke_encrypt_for(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    if runtime.zome_call.call_context == CallContext::Validation {
+        return ribosome_error_code!(NonDeterministicCallInValidation);
+    }
+    let args_str = runtime.load_json_string_from_args(&args);
+    let input = match EncryptForArgs::try_from(args_str) {
+        Ok(input) => input,
+        Err(_) => return ribosome_error_code!(ArgumentDeserializationFailed),
+    };
+    let result = encrypt_for(&input.recipient, &input.payload)
+        .map_err(|hc_err| hc_err.with_context(ZomeApiFunction::EncryptFor.as_str()));
+    runtime.store_result(result)
+}
+
+/// ZomeApiFunction::Decrypt function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Expected complex argument: DecryptArgs
+/// Returns an HcApiReturnCode as I32
+/// Decrypts a payload encrypted for the agent of this instance with its secret key.
+/// Other nodes can't do the same, that's why validation callbacks can't use it.
+pub fn invoke_decrypt(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    if runtime.zome_call.call_context == CallContext::Validation {
+        return ribosome_error_code!(NonDeterministicCallInValidation);
+    }
+    let args_str = runtime.load_json_string_from_args(&args);
+    let input = match DecryptArgs::try_from(args_str) {
+        Ok(input) => input,
+        Err(_) => return ribosome_error_code!(ArgumentDeserializationFailed),
+    };
+    let result = runtime
+        .context
+        .decrypt(&input.payload)
+        .map_err(|hc_err| hc_err.with_context(ZomeApiFunction::Decrypt.as_str()));
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::nucleus::ribosome::api::{tests::test_zome_api_function, ZomeApiFunction};
+    use holochain_core_types::{
+        cas::content::Address, error::ZomeApiInternalResult, json::JsonString,
+    };
+    use holochain_wasm_utils::api_serialization::{DecryptArgs, EncryptForArgs};
+    use std::convert::TryFrom;
+
+    fn call(function: ZomeApiFunction, args: JsonString) -> ZomeApiInternalResult {
+        let (call_result, _) = test_zome_api_function(function.as_str(), args.into_bytes());
+        ZomeApiInternalResult::try_from(call_result).unwrap()
+    }
+
+    #[test]
+    fn test_encrypt_for_needs_an_agent_address() {
+        let result = call(
+            ZomeApiFunction::EncryptFor,
+            JsonString::from(EncryptForArgs {
+                recipient: Address::from("QmXxdzM9uHiSfV1xDwUxMm5jX4rVU8jhtWVaeCzjkFW249"),
+                payload: b"secret".to_vec(),
+            }),
+        );
+        assert!(!result.ok);
+        assert!(
+            result.error.contains("is no agent address"),
+            "error = {}",
+            result.error
+        );
+    }
+
+    #[test]
+    fn test_decrypt_without_keys_fails() {
+        let result = call(
+            ZomeApiFunction::Decrypt,
+            JsonString::from(DecryptArgs {
+                payload: vec![0; 64],
+            }),
+        );
+        assert!(!result.ok);
+        assert!(
+            result.error.contains("This agent has no encryption keys"),
+            "error = {}",
+            result.error
+        );
+    }
+}
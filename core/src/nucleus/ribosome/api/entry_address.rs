@@ -1,9 +1,8 @@
 use crate::nucleus::ribosome::{api::ZomeApiResult, Runtime};
 use holochain_core_types::{
     self,
-    cas::content::AddressableContent,
     dna::Dna,
-    entry::{entry_type::EntryType, Entry},
+    entry::{addressing, entry_type::EntryType, Entry},
 };
 use std::{convert::TryFrom, str::FromStr};
 use wasmi::{RuntimeArgs, RuntimeValue};
@@ -52,8 +51,7 @@ pub fn invoke_entry_address(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeAp
     if let Err(err) = maybe_entry_type {
         return Ok(err);
     }
-    let entry = Entry::from(entry);
 
     // Return result
-    runtime.store_result(Ok(entry.address()))
+    runtime.store_result(Ok(addressing::entry_address(&entry)))
 }
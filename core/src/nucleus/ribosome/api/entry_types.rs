@@ -0,0 +1,171 @@
+use crate::nucleus::ribosome::{api::ZomeApiResult, Runtime};
+use holochain_core_types::{
+    dna::Dna,
+    entry::entry_type::EntryType,
+    error::HolochainError,
+    json::{JsonString, RawString},
+};
+use holochain_wasm_utils::api_serialization::EntryTypeDef;
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// The app entry types the zomes of the DNA declare, with the zome declaring them
+fn app_entry_types(dna: &Dna) -> Vec<EntryTypeDef> {
+    let mut entry_types: Vec<EntryTypeDef> = dna
+        .zomes
+        .iter()
+        .flat_map(|(zome_name, zome)| {
+            zome.entry_types
+                .iter()
+                .filter_map(move |(entry_type, entry_type_def)| match entry_type {
+                    EntryType::App(app_entry_type) => Some(EntryTypeDef::new(
+                        &String::from(app_entry_type.clone()),
+                        zome_name,
+                        entry_type_def,
+                    )),
+                    _ => None,
+                })
+        })
+        .collect();
+    entry_types.sort_by(|a, b| a.name.cmp(&b.name));
+    entry_types
+}
+
+/// ZomeApiFunction::EntryTypes function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Not expecting any complex input
+/// Returns an HcApiReturnCode as I32
+/// The names of the app entry types of all the zomes of the DNA, sorted
+pub fn invoke_entry_types(runtime: &mut Runtime, _args: &RuntimeArgs) -> ZomeApiResult {
+    let result = runtime
+        .context
+        .get_dna()
+        .ok_or(HolochainError::DnaMissing)
+        .map(|dna| {
+            let names: Vec<String> = app_entry_types(&dna)
+                .into_iter()
+                .map(|entry_type| entry_type.name)
+                .collect();
+            JsonString::from(names)
+        });
+    runtime.store_result(result)
+}
+
+/// ZomeApiFunction::EntryTypeProperties function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Expecting the name of the entry type as a string
+/// Returns an HcApiReturnCode as I32
+/// The EntryTypeDef of the app entry type, whichever zome of the DNA declares it
+pub fn invoke_entry_type_properties(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    let args_str = runtime.load_json_string_from_args(&args);
+    let name = match RawString::try_from(args_str) {
+        Ok(name) => String::from(name),
+        Err(_) => return ribosome_error_code!(ArgumentDeserializationFailed),
+    };
+    let result = runtime
+        .context
+        .get_dna()
+        .ok_or(HolochainError::DnaMissing)
+        .and_then(|dna| {
+            app_entry_types(&dna)
+                .into_iter()
+                .find(|entry_type| entry_type.name == name)
+                .ok_or_else(|| {
+                    HolochainError::ErrorGeneric(format!("DNA has no entry type {}", name))
+                })
+        });
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        instance::tests::test_instance_and_context,
+        nucleus::ribosome::{
+            api::{
+                tests::{
+                    test_capability, test_zome_api_function_call, test_zome_api_function_wasm,
+                    test_zome_name,
+                },
+                ZomeApiFunction,
+            },
+            Defn,
+        },
+    };
+    use holochain_core_types::{
+        dna::zome::entry_types::{self, LinkedFrom, LinksTo, Sharing},
+        entry::entry_type::EntryType,
+        error::ZomeApiInternalResult,
+        json::{JsonString, RawString},
+    };
+    use holochain_wasm_utils::api_serialization::EntryTypeDef;
+    use std::convert::TryFrom;
+
+    fn call(api_function: ZomeApiFunction, args: JsonString) -> ZomeApiInternalResult {
+        let wasm = test_zome_api_function_wasm(api_function.as_str());
+        let mut dna =
+            test_utils::create_test_dna_with_wasm(&test_zome_name(), &test_capability(), wasm);
+        let mut comment = entry_types::EntryTypeDef::new();
+        comment.description = "A comment on a post".to_string();
+        comment.sharing = Sharing::Private;
+        comment.links_to.push(LinksTo {
+            target_type: "comment".to_string(),
+            tag: "replies".to_string(),
+        });
+        comment.linked_from.push(LinkedFrom {
+            base_type: "testEntryType".to_string(),
+            tag: "comments".to_string(),
+        });
+        dna.zomes
+            .get_mut(&test_zome_name())
+            .unwrap()
+            .entry_types
+            .insert(EntryType::from("comment"), comment);
+        let dna_name = dna.name.clone();
+        let (instance, context) =
+            test_instance_and_context(dna).expect("Could not create test instance");
+
+        let call_result = test_zome_api_function_call(
+            &dna_name,
+            context.clone(),
+            &instance,
+            &context.get_wasm(&test_zome_name()).unwrap().code,
+            args.into_bytes(),
+        );
+        ZomeApiInternalResult::try_from(call_result)
+            .expect("valid ZomeApiInternalResult JsonString")
+    }
+
+    #[test]
+    /// test that the app entry types of the DNA get listed by their name
+    fn test_entry_types() {
+        let result = call(ZomeApiFunction::EntryTypes, JsonString::null());
+        assert!(result.ok);
+        let names: Vec<String> = serde_json::from_str(&result.value).unwrap();
+        assert_eq!(names, vec!["comment", "testEntryType", "testEntryTypeB"]);
+    }
+
+    #[test]
+    /// test that the properties of an entry type round-trip, including its links
+    fn test_entry_type_properties() {
+        let result = call(
+            ZomeApiFunction::EntryTypeProperties,
+            JsonString::from(RawString::from("comment")),
+        );
+        assert!(result.ok, "result = {:?}", result);
+        let entry_type = EntryTypeDef::try_from(JsonString::from(result.value)).unwrap();
+        assert_eq!(entry_type.name, "comment");
+        assert_eq!(entry_type.zome_name, test_zome_name());
+        assert_eq!(entry_type.description, "A comment on a post");
+        assert_eq!(entry_type.sharing, Sharing::Private);
+        assert_eq!(entry_type.links_to[0].tag, "replies");
+        assert_eq!(entry_type.linked_from[0].base_type, "testEntryType");
+
+        let result = call(
+            ZomeApiFunction::EntryTypeProperties,
+            JsonString::from(RawString::from("unknown")),
+        );
+        assert!(!result.ok);
+        assert!(result.error.contains("DNA has no entry type unknown"));
+    }
+}
@@ -54,14 +54,15 @@ mod tests {
         error::ZomeApiInternalResult,
         json::JsonString,
     };
-    use holochain_wasm_utils::api_serialization::get_entry::*;
-    use std::sync::Arc;
+    use holochain_wasm_utils::api_serialization::{get_entry::*, CommitEntryResult};
+    use std::{convert::TryFrom, sync::Arc};
 
     /// dummy get args from standard test entry
     pub fn test_get_args_bytes() -> Vec<u8> {
         let entry_args = GetEntryArgs {
             address: test_entry().address(),
             options: GetEntryOptions::new(StatusRequestKind::Latest),
+            resolve_latest: true,
         };
         JsonString::from(entry_args).into_bytes()
     }
@@ -71,6 +72,7 @@ mod tests {
         let entry_args = GetEntryArgs {
             address: Address::from("xxxxxxxxx"),
             options: GetEntryOptions::new(StatusRequestKind::Latest),
+            resolve_latest: true,
         };
         JsonString::from(entry_args).into_bytes()
     }
@@ -201,14 +203,13 @@ mod tests {
         )
         .expect("test should be callable");
 
-        assert_eq!(
-            call_result,
-            JsonString::from(
-                String::from(JsonString::from(ZomeApiInternalResult::success(
-                    test_entry().address()
-                ))) + "\u{0}"
-            ),
-        );
+        let commit_result = CommitEntryResult::try_from(JsonString::from(
+            ZomeApiInternalResult::try_from(call_result)
+                .expect("valid ZomeApiInternalResult JsonString")
+                .value,
+        ))
+        .expect("valid CommitEntryResult JsonString");
+        assert_eq!(commit_result.address, test_entry().address());
 
         let get_call = ZomeFnCall::new(
             &test_zome_name(),
@@ -229,6 +230,7 @@ mod tests {
         entry_history.addresses.push(test_entry().address());
         entry_history.entries.push(test_entry());
         entry_history.crud_status.push(CrudStatus::LIVE);
+        entry_history.status = GetResultStatus::Found;
         assert_eq!(
             JsonString::from(String::from(JsonString::from(
                 ZomeApiInternalResult::success(entry_history)
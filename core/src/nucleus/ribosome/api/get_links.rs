@@ -1,6 +1,11 @@
-use crate::nucleus::ribosome::{api::ZomeApiResult, Runtime};
-use holochain_core_types::cas::content::Address;
-use holochain_wasm_utils::api_serialization::get_links::{GetLinksArgs, GetLinksResult};
+use crate::nucleus::ribosome::{
+    api::{ZomeApiFunction, ZomeApiResult},
+    Defn, Runtime,
+};
+use holochain_core_types::{cas::content::Address, entry::entry_type::EntryType};
+use holochain_wasm_utils::api_serialization::get_links::{
+    GetLinksArgs, GetLinksDirection, GetLinksResult, LinksStatusFilter,
+};
 use std::convert::TryFrom;
 use wasmi::{RuntimeArgs, RuntimeValue};
 
@@ -22,21 +27,42 @@ pub fn invoke_get_links(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiRes
         }
     };
     // Get links from DHT
-    let maybe_links = runtime
-        .context
-        .state()
-        .unwrap()
-        .dht()
-        .get_links(input.entry_address, input.tag);
-
-    runtime.store_result(match maybe_links {
-        Ok(links) => Ok(GetLinksResult::new(
-            links
-                .iter()
-                .map(|eav| eav.value())
-                .collect::<Vec<Address>>(),
-        )),
-        Err(hc_err) => Err(hc_err),
+    let dht = runtime.context.state().unwrap().dht();
+    let maybe_all_links = match input.direction {
+        GetLinksDirection::From => dht.get_links(input.entry_address, input.tag),
+        GetLinksDirection::To => dht.get_links_to(input.entry_address, input.tag),
+    };
+    let maybe_links = maybe_all_links.and_then(|links| match input.target_entry_type {
+        Some(entry_type) => dht.links_of_type(links, &EntryType::from(entry_type)),
+        None => Ok((links, 0)),
+    });
+
+    // Join the other ends of the links with their CRUD status
+    let status_request = input.status_request;
+    let maybe_links_with_status = maybe_links.and_then(|(links, skipped)| {
+        let mut links_with_status = Vec::new();
+        for eav in links {
+            let target = eav.value();
+            let status = dht.crud_status(&target)?;
+            if status_request.includes(status) {
+                links_with_status.push((target, status));
+            }
+        }
+        Ok((links_with_status, skipped))
+    });
+
+    runtime.store_result(match maybe_links_with_status {
+        Ok((links, skipped)) => Ok(match status_request {
+            LinksStatusFilter::All => GetLinksResult::with_statuses(links, skipped),
+            _ => GetLinksResult::with_skipped(
+                links
+                    .into_iter()
+                    .map(|(address, _)| address)
+                    .collect::<Vec<Address>>(),
+                skipped,
+            ),
+        }),
+        Err(hc_err) => Err(hc_err.with_context(ZomeApiFunction::GetLinks.as_str())),
     })
 }
 
@@ -56,25 +82,77 @@ pub mod tests {
     };
     use futures::executor::block_on;
     use holochain_core_types::{
-        cas::content::Address,
-        entry::{entry_type::test_app_entry_type, Entry},
+        cas::content::{Address, AddressableContent},
+        crud_status::{create_crud_status_eav, CrudStatus},
+        entry::{
+            entry_type::{test_app_entry_type, AppEntryType},
+            test_entry, Entry,
+        },
+        error::{CoreError, ErrorTrail},
         json::JsonString,
         link::Link,
     };
-    use holochain_wasm_utils::api_serialization::get_links::GetLinksArgs;
+    use holochain_wasm_utils::api_serialization::get_links::{
+        GetLinksArgs, GetLinksDirection, GetLinksResult, LinksStatusFilter,
+    };
     use serde_json;
+    use std::{collections::HashSet, convert::TryFrom, thread};
 
-    /// dummy link_entries args from standard test entry
-    pub fn test_get_links_args_bytes(base: &Address, tag: &str) -> Vec<u8> {
+    /// get_links args only asking for targets of the given entry type
+    pub fn test_get_links_of_type_args_bytes(
+        base: &Address,
+        tag: &str,
+        entry_type: &str,
+    ) -> Vec<u8> {
         let args = GetLinksArgs {
             entry_address: base.clone(),
             tag: String::from(tag),
+            target_entry_type: Some(String::from(entry_type)),
+            status_request: LinksStatusFilter::default(),
+            direction: GetLinksDirection::default(),
+        };
+        serde_json::to_string(&args)
+            .expect("args should serialize")
+            .into_bytes()
+    }
+
+    /// get_links args with the given filter on the status of the targets
+    pub fn test_get_links_with_status_args_bytes(
+        base: &Address,
+        tag: &str,
+        status_request: LinksStatusFilter,
+    ) -> Vec<u8> {
+        let args = GetLinksArgs {
+            entry_address: base.clone(),
+            tag: String::from(tag),
+            target_entry_type: None,
+            status_request,
+            direction: GetLinksDirection::default(),
+        };
+        serde_json::to_string(&args)
+            .expect("args should serialize")
+            .into_bytes()
+    }
+
+    /// get_links args asking for the bases linking to the given target
+    pub fn test_get_links_to_args_bytes(target: &Address, tag: &str) -> Vec<u8> {
+        let args = GetLinksArgs {
+            entry_address: target.clone(),
+            tag: String::from(tag),
+            target_entry_type: None,
+            status_request: LinksStatusFilter::default(),
+            direction: GetLinksDirection::To,
         };
         serde_json::to_string(&args)
             .expect("args should serialize")
             .into_bytes()
     }
 
+    /// dummy link_entries args from standard test entry
+    pub fn test_get_links_args_bytes(base: &Address, tag: &str) -> Vec<u8> {
+        test_get_links_with_status_args_bytes(base, tag, LinksStatusFilter::default())
+    }
+
     #[test]
     fn returns_list_of_links() {
         let wasm = test_zome_api_function_wasm(ZomeApiFunction::GetLinks.as_str());
@@ -97,7 +175,8 @@ pub mod tests {
                 JsonString::from(format!("entry{} value", i)),
             );
             let address = block_on(commit_entry(entry, None, &initialized_context))
-                .expect("Could not commit entry for testing");
+                .expect("Could not commit entry for testing")
+                .address;
             entry_addresses.push(address);
         }
 
@@ -117,14 +196,14 @@ pub mod tests {
 
         let expected_1 = JsonString::from(
             format!(
-                r#"{{"ok":true,"value":"{{\"addresses\":[\"{}\",\"{}\"]}}","error":"null"}}"#,
+                r#"{{"ok":true,"value":"{{\"addresses\":[\"{}\",\"{}\"],\"skipped\":0}}","error":"null"}}"#,
                 entry_addresses[1], entry_addresses[2]
             ) + "\u{0}",
         );
 
         let expected_2 = JsonString::from(
             format!(
-                r#"{{"ok":true,"value":"{{\"addresses\":[\"{}\",\"{}\"]}}","error":"null"}}"#,
+                r#"{{"ok":true,"value":"{{\"addresses\":[\"{}\",\"{}\"],\"skipped\":0}}","error":"null"}}"#,
                 entry_addresses[2], entry_addresses[1]
             ) + "\u{0}",
         );
@@ -148,10 +227,272 @@ pub mod tests {
         assert_eq!(
             call_result,
             JsonString::from(
-                String::from(r#"{"ok":true,"value":"{\"addresses\":[]}","error":"null"}"#)
-                    + "\u{0}"
+                String::from(
+                    r#"{"ok":true,"value":"{\"addresses\":[],\"skipped\":0}","error":"null"}"#
+                ) + "\u{0}"
             ),
         );
     }
 
+    #[test]
+    fn filters_links_by_target_entry_type() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::GetLinks.as_str());
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability(),
+            wasm.clone(),
+        );
+
+        let dna_name = &dna.name.to_string().clone();
+        let instance = test_instance(dna).expect("Could not create test instance");
+
+        let (context, _) = test_context_and_logger("joan");
+        let initialized_context = instance.initialize_context(context);
+
+        let commit = |entry_type: &'static str, value: &'static str| {
+            let entry = Entry::App(AppEntryType::from(entry_type), JsonString::from(value));
+            block_on(commit_entry(entry, None, &initialized_context))
+                .expect("Could not commit entry for testing")
+                .address
+        };
+        let base = commit("post", "base");
+        let comment = commit("comment", "a comment");
+        let like = commit("like", "a like");
+        let unknown = Address::from("QmNotHeldHere");
+
+        for target in &[&comment, &like, &unknown] {
+            let link = Link::new(&base, target, "index");
+            assert!(block_on(add_link(&link, &initialized_context)).is_ok());
+        }
+
+        let call = |entry_type: &str| {
+            test_zome_api_function_call(
+                &dna_name,
+                initialized_context.clone(),
+                &instance,
+                &wasm,
+                test_get_links_of_type_args_bytes(&base, "index", entry_type),
+            )
+        };
+        let expected = |address: &Address| {
+            JsonString::from(
+                format!(
+                    r#"{{"ok":true,"value":"{{\"addresses\":[\"{}\"],\"skipped\":1}}","error":"null"}}"#,
+                    address
+                ) + "\u{0}",
+            )
+        };
+
+        assert_eq!(call("comment"), expected(&comment));
+        assert_eq!(call("like"), expected(&like));
+    }
+
+    #[test]
+    fn returns_links_to_target() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::GetLinks.as_str());
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability(),
+            wasm.clone(),
+        );
+
+        let dna_name = &dna.name.to_string().clone();
+        let instance = test_instance(dna).expect("Could not create test instance");
+
+        let (context, _) = test_context_and_logger("joan");
+        let initialized_context = instance.initialize_context(context);
+
+        let mut entry_addresses: Vec<Address> = Vec::new();
+        for i in 0..3 {
+            let entry = Entry::App(
+                test_app_entry_type(),
+                JsonString::from(format!("entry{} value", i)),
+            );
+            let address = block_on(commit_entry(entry, None, &initialized_context))
+                .expect("Could not commit entry for testing")
+                .address;
+            entry_addresses.push(address);
+        }
+
+        let link = Link::new(&entry_addresses[0], &entry_addresses[1], "test-tag");
+        assert!(block_on(add_link(&link, &initialized_context)).is_ok());
+
+        let call = |target: &Address| {
+            test_zome_api_function_call(
+                &dna_name,
+                initialized_context.clone(),
+                &instance,
+                &wasm,
+                test_get_links_to_args_bytes(target, "test-tag"),
+            )
+        };
+
+        assert_eq!(
+            call(&entry_addresses[1]),
+            JsonString::from(
+                format!(
+                    r#"{{"ok":true,"value":"{{\"addresses\":[\"{}\"],\"skipped\":0}}","error":"null"}}"#,
+                    entry_addresses[0]
+                ) + "\u{0}",
+            ),
+        );
+        // links only go one way
+        assert_eq!(
+            call(&entry_addresses[0]),
+            JsonString::from(
+                String::from(
+                    r#"{"ok":true,"value":"{\"addresses\":[],\"skipped\":0}","error":"null"}"#
+                ) + "\u{0}"
+            ),
+        );
+    }
+
+    #[test]
+    fn filters_links_by_target_status() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::GetLinks.as_str());
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability(),
+            wasm.clone(),
+        );
+
+        let dna_name = &dna.name.to_string().clone();
+        let instance = test_instance(dna).expect("Could not create test instance");
+
+        let (context, _) = test_context_and_logger("joan");
+        let initialized_context = instance.initialize_context(context);
+
+        let mut entry_addresses: Vec<Address> = Vec::new();
+        for i in 0..4 {
+            let entry = Entry::App(
+                test_app_entry_type(),
+                JsonString::from(format!("entry{} value", i)),
+            );
+            let address = block_on(commit_entry(entry, None, &initialized_context))
+                .expect("Could not commit entry for testing")
+                .address;
+            entry_addresses.push(address);
+        }
+        let base = &entry_addresses[0];
+        for target in &entry_addresses[1..] {
+            let link = Link::new(base, target, "test-tag");
+            assert!(block_on(add_link(&link, &initialized_context)).is_ok());
+        }
+
+        // Delete the second target
+        let deleted = &entry_addresses[2];
+        initialized_context
+            .state()
+            .unwrap()
+            .dht()
+            .meta_storage()
+            .write()
+            .unwrap()
+            .add_eav(&create_crud_status_eav(deleted, CrudStatus::DELETED))
+            .unwrap();
+
+        let call = |status_request: LinksStatusFilter| {
+            let call_result = test_zome_api_function_call(
+                &dna_name,
+                initialized_context.clone(),
+                &instance,
+                &wasm,
+                test_get_links_with_status_args_bytes(base, "test-tag", status_request),
+            );
+            let call_result = String::from(call_result);
+            let call_result: serde_json::Value =
+                serde_json::from_str(call_result.trim_end_matches('\u{0}')).unwrap();
+            GetLinksResult::try_from(JsonString::from(
+                call_result["value"].as_str().unwrap().to_string(),
+            ))
+            .unwrap()
+        };
+        let set = |addresses: &[&Address]| {
+            addresses
+                .iter()
+                .map(|address| (*address).clone())
+                .collect::<HashSet<Address>>()
+        };
+
+        let live = call(LinksStatusFilter::Live);
+        assert_eq!(
+            live.addresses()
+                .iter()
+                .cloned()
+                .collect::<HashSet<Address>>(),
+            set(&[&entry_addresses[1], &entry_addresses[3]]),
+        );
+        assert!(live.statuses().is_none());
+
+        let deleted_only = call(LinksStatusFilter::Deleted);
+        assert_eq!(deleted_only.addresses(), &vec![deleted.clone()]);
+        assert!(deleted_only.statuses().is_none());
+
+        let all = call(LinksStatusFilter::All);
+        let statuses = all.statuses().expect("All should return statuses");
+        assert_eq!(all.addresses().len(), 3);
+        for (address, status) in all.addresses().iter().zip(statuses) {
+            let expected = if address == deleted {
+                CrudStatus::DELETED
+            } else {
+                CrudStatus::LIVE
+            };
+            assert_eq!(status, &expected);
+        }
+    }
+
+    #[test]
+    fn storage_failure_keeps_context() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::GetLinks.as_str());
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability(),
+            wasm.clone(),
+        );
+
+        let dna_name = &dna.name.to_string().clone();
+        let instance = test_instance(dna).expect("Could not create test instance");
+
+        let (context, _) = test_context_and_logger("joan");
+        let initialized_context = instance.initialize_context(context);
+
+        // A writer panicking leaves the meta storage poisoned, so reading it fails
+        let meta_storage = initialized_context.state().unwrap().dht().meta_storage();
+        let _ = thread::spawn(move || {
+            let _storage = meta_storage.write().unwrap();
+            panic!("storage failure for testing");
+        })
+        .join();
+
+        let base = test_entry().address();
+        let call_result = test_zome_api_function_call(
+            &dna_name,
+            initialized_context.clone(),
+            &instance,
+            &wasm,
+            test_get_links_args_bytes(&base, "test-tag"),
+        );
+        let call_result = String::from(call_result);
+        let call_result: serde_json::Value =
+            serde_json::from_str(call_result.trim_end_matches('\u{0}')).unwrap();
+        assert_eq!(call_result["ok"], false);
+
+        let core_error = CoreError::try_from(JsonString::from(
+            call_result["error"].as_str().unwrap().to_string(),
+        ))
+        .unwrap();
+        let trail = ErrorTrail::from(&core_error.kind);
+        assert_eq!(
+            trail.context,
+            vec![
+                String::from("hc_get_links"),
+                format!("fetching links of {} from the meta storage", base),
+            ],
+        );
+        assert!(
+            trail.error.starts_with("sync poison error"),
+            "error = {}",
+            trail.error
+        );
+    }
 }
@@ -0,0 +1,54 @@
+use crate::nucleus::ribosome::{api::ZomeApiResult, Runtime};
+use holochain_core_types::cas::content::Address;
+use holochain_wasm_utils::api_serialization::PublishStatus;
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::GetPublishStatus function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Expected Address argument
+/// Returns an HcApiReturnCode as I32
+/// How many nodes acknowledged holding the entry this agent published,
+/// and why the ones that rejected it did
+pub fn invoke_get_publish_status(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    let args_str = runtime.load_json_string_from_args(&args);
+    let address = match Address::try_from(args_str) {
+        Ok(address) => address,
+        Err(_) => return ribosome_error_code!(ArgumentDeserializationFailed),
+    };
+    let network = runtime.context.state().unwrap().network();
+    let publish_status = PublishStatus {
+        holders: network.holders_count(&address),
+        rejections: network.rejections(&address),
+    };
+    runtime.store_result(Ok(publish_status))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::nucleus::ribosome::{
+        api::{tests::test_zome_api_function, ZomeApiFunction},
+        Defn,
+    };
+    use holochain_core_types::{
+        cas::content::Address, error::ZomeApiInternalResult, json::JsonString,
+    };
+    use holochain_wasm_utils::api_serialization::PublishStatus;
+    use std::convert::TryFrom;
+
+    #[test]
+    /// test that an entry we did not publish has neither holders nor rejections
+    fn test_get_publish_status_of_unpublished_entry() {
+        let (call_result, _) = test_zome_api_function(
+            ZomeApiFunction::GetPublishStatus.as_str(),
+            JsonString::from(Address::from("QmNotPublished")).into_bytes(),
+        );
+
+        let zome_api_internal_result = ZomeApiInternalResult::try_from(call_result).unwrap();
+        assert!(zome_api_internal_result.ok);
+        assert_eq!(
+            PublishStatus::try_from(JsonString::from(zome_api_internal_result.value)).unwrap(),
+            PublishStatus::default(),
+        );
+    }
+}
@@ -1,9 +1,11 @@
 use crate::nucleus::ribosome::{api::ZomeApiResult, Runtime};
 use holochain_core_types::{
-    cas::content::Address, entry::entry_type::EntryType, hash::HashString, json::JsonString,
+    cas::content::Address,
+    entry::{addressing, entry_type::EntryType},
+    hash::HashString,
+    json::JsonString,
 };
 use holochain_wasm_utils::api_serialization::ZomeApiGlobals;
-use multihash::Hash as Multihash;
 use wasmi::RuntimeArgs;
 
 /// ZomeApiFunction::InitGlobals secret function code
@@ -11,24 +13,32 @@ use wasmi::RuntimeArgs;
 /// Not expecting any complex input
 /// Returns an HcApiReturnCode as I32
 pub fn invoke_init_globals(runtime: &mut Runtime, _args: &RuntimeArgs) -> ZomeApiResult {
-    // Create the ZomeApiGlobals struct with some default values
+    // Create the ZomeApiGlobals struct with some default values.
+    // Until the AgentId entry shows up in the chain (i.e. during genesis) the agent fields
+    // are derived from the context's agent and flagged as provisional.
+    // TODO #233 - Implement agent pub key hash
+    let provisional_agent_address = addressing::agent_entry_address(&runtime.context.agent_id);
     let mut globals = ZomeApiGlobals {
         dna_name: runtime.dna_name.to_string(),
         dna_hash: HashString::from(""),
+        dna_entry_address: Address::from(""),
         agent_id_str: JsonString::from(runtime.context.agent_id.clone()).to_string(),
-        // TODO #233 - Implement agent pub key hash
-        agent_address: Address::encode_from_str("FIXME-agent_address", Multihash::SHA2256),
-        agent_initial_hash: HashString::from(""),
-        agent_latest_hash: HashString::from(""),
+        agent_address: provisional_agent_address.clone(),
+        agent_initial_hash: provisional_agent_address.clone(),
+        agent_latest_hash: provisional_agent_address,
+        agent_provisional: true,
+        call_context: runtime.zome_call.call_context.clone(),
     };
 
+    // Update dna_hash.
+    // Genesis can run before the DNA is visible in the state, get_dna() waits for it.
+    if let Some(dna) = runtime.context.get_dna() {
+        globals.dna_entry_address = addressing::dna_entry_address(&dna);
+        globals.dna_hash = addressing::dna_hash(&dna);
+    }
+
     // Update fields
     if let Some(state) = runtime.context.state() {
-        // Update dna_hash
-        if let Some(dna) = state.nucleus().dna() {
-            globals.dna_hash =
-                HashString::encode_from_json_string(JsonString::from(dna), Multihash::SHA2256);
-        }
         // Update agent hashes
         let maybe_top = state.agent().top_chain_header();
         if maybe_top.is_some() {
@@ -43,7 +53,9 @@ pub fn invoke_init_globals(runtime: &mut Runtime, _args: &RuntimeArgs) -> ZomeAp
             if found_entries.len() > 0 {
                 globals.agent_latest_hash = found_entries[0].clone();
                 globals.agent_initial_hash = found_entries.pop().unwrap();
-                globals.agent_address = globals.agent_latest_hash.clone();
+                // The agent keeps its address when it updates its identity
+                globals.agent_address = globals.agent_initial_hash.clone();
+                globals.agent_provisional = false;
             }
         }
     };
@@ -58,15 +70,18 @@ pub mod tests {
         api::{tests::test_zome_api_function, ZomeApiFunction},
         Defn,
     };
-    use holochain_core_types::{error::ZomeApiInternalResult, json::JsonString};
-    use holochain_wasm_utils::api_serialization::ZomeApiGlobals;
+    use holochain_core_types::{
+        entry::entry_type::EntryType, error::ZomeApiInternalResult, json::JsonString,
+    };
+    use holochain_wasm_utils::api_serialization::{CallContext, ZomeApiGlobals};
     use std::convert::TryFrom;
 
     #[test]
     /// test that bytes passed to debug end up in the log
     fn test_init_globals() {
         let input: Vec<u8> = vec![];
-        let (call_result, _) = test_zome_api_function(ZomeApiFunction::InitGlobals.as_str(), input);
+        let (call_result, context) =
+            test_zome_api_function(ZomeApiFunction::InitGlobals.as_str(), input);
 
         let zome_api_internal_result = ZomeApiInternalResult::try_from(call_result).unwrap();
         let globals =
@@ -82,5 +97,25 @@ pub mod tests {
         //     AgentId::generate_fake("jane").address()
         // );
         assert_eq!(globals.agent_initial_hash, globals.agent_latest_hash);
+        // the test instance is initialized so the agent entry is on the chain
+        assert!(!globals.agent_provisional);
+        // test calls are made by core itself
+        assert_eq!(globals.call_context, CallContext::Callback);
+
+        // addresses derived for the globals match what got committed to the chain
+        let agent_state = context.state().unwrap().agent();
+        let top_header = agent_state.top_chain_header();
+        assert_eq!(
+            vec![globals.dna_entry_address.clone()],
+            agent_state
+                .chain()
+                .query(&top_header, &EntryType::Dna, 0, 0),
+        );
+        assert_eq!(
+            vec![globals.agent_latest_hash.clone()],
+            agent_state
+                .chain()
+                .query(&top_header, &EntryType::AgentId, 0, 0),
+        );
     }
 }
@@ -3,7 +3,11 @@ use crate::{
     workflows::author_entry::author_entry,
 };
 use futures::executor::block_on;
-use holochain_core_types::{entry::Entry, error::HolochainError, link::link_add::LinkAdd};
+use holochain_core_types::{
+    entry::Entry,
+    error::HolochainError,
+    link::{link_add::LinkAdd, AGENT_ID_LINK_TAG},
+};
 use holochain_wasm_utils::api_serialization::link_entries::LinkEntriesArgs;
 use std::convert::TryFrom;
 use wasmi::{RuntimeArgs, RuntimeValue};
@@ -27,6 +31,14 @@ pub fn invoke_link_entries(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApi
     };
 
     let link = input.to_link();
+    // Only core links agents into the DNA's agent directory
+    if link.tag() == AGENT_ID_LINK_TAG {
+        let result: Result<(), HolochainError> = Err(HolochainError::ValidationFailed(format!(
+            "Link tag {} is reserved",
+            AGENT_ID_LINK_TAG
+        )));
+        return runtime.store_result(result);
+    }
     let link_add = LinkAdd::from_link(&link);
     let entry = Entry::LinkAdd(link_add);
 
@@ -60,6 +72,7 @@ pub mod tests {
         entry::{entry_type::AppEntryType, test_entry, AppEntryValue, Entry},
         error::{CoreError, ZomeApiInternalResult},
         json::JsonString,
+        link::AGENT_ID_LINK_TAG,
     };
     use holochain_wasm_utils::api_serialization::link_entries::*;
     use serde_json;
@@ -180,6 +193,28 @@ pub mod tests {
         assert_eq!("not implemented", core_err.kind.to_string(),);
     }
 
+    #[test]
+    fn refuses_reserved_agent_id_tag() {
+        let (instance, context) = create_test_instance();
+
+        block_on(commit_entry(test_entry(), None, &context))
+            .expect("Could not commit entry for testing");
+
+        let call_result = test_zome_api_function_call(
+            &context.get_dna().unwrap().name.to_string(),
+            context.clone(),
+            &instance,
+            &context.get_wasm(&test_zome_name()).unwrap().code,
+            test_link_args_bytes(String::from(AGENT_ID_LINK_TAG)),
+        );
+
+        let result = ZomeApiInternalResult::try_from(call_result)
+            .expect("valid ZomeApiInternalResult JsonString");
+
+        let core_err = CoreError::try_from(result).expect("valid CoreError JsonString");
+        assert_eq!("Link tag %agent_id is reserved", core_err.kind.to_string(),);
+    }
+
     #[test]
     fn works_with_linked_from_defined_link() {
         let (instance, context) = create_test_instance();
@@ -1,30 +1,60 @@
 //! Module for ZomeApiFunctions
 //! ZomeApiFunctions are the functions provided by the ribosome that are callable by Zomes.
 
+pub mod await_publish;
 pub mod call;
+pub mod call_bridge;
+pub mod chain_info;
 pub mod commit;
+pub mod commit_and_link;
+pub mod count_links;
+pub mod crypto;
 pub mod debug;
 pub mod entry_address;
+pub mod entry_types;
 pub mod get_entry;
 pub mod get_links;
+pub mod get_publish_status;
 pub mod init_globals;
 pub mod link_entries;
+pub mod property;
 pub mod query;
+pub mod random_bytes;
 pub mod remove_entry;
+pub mod republish;
+pub mod sleep;
+pub mod update_agent;
 pub mod update_entry;
+pub mod yield_partial;
 
 use crate::nucleus::ribosome::{
     api::{
-        call::invoke_call, commit::invoke_commit_app_entry, debug::invoke_debug,
-        entry_address::invoke_entry_address, get_entry::invoke_get_entry,
-        get_links::invoke_get_links, init_globals::invoke_init_globals,
-        link_entries::invoke_link_entries, query::invoke_query, remove_entry::invoke_remove_entry,
-        update_entry::invoke_update_entry,
+        await_publish::invoke_await_publish, call::invoke_call, call_bridge::invoke_call_bridge,
+        chain_info::invoke_chain_info,
+        commit::invoke_commit_app_entry, commit_and_link::invoke_commit_and_link,
+        count_links::invoke_count_links,
+        crypto::{invoke_decrypt, invoke_encrypt_for},
+        debug::invoke_debug,
+        entry_address::invoke_entry_address,
+        entry_types::{invoke_entry_type_properties, invoke_entry_types},
+        get_entry::invoke_get_entry,
+        get_links::invoke_get_links, get_publish_status::invoke_get_publish_status,
+        init_globals::invoke_init_globals,
+        link_entries::invoke_link_entries,
+        property::invoke_property,
+        query::{invoke_query, invoke_query_page},
+        random_bytes::invoke_random_bytes, remove_entry::invoke_remove_entry,
+        republish::invoke_republish,
+        sleep::invoke_sleep,
+        update_agent::invoke_update_agent, update_entry::invoke_update_entry,
+        yield_partial::invoke_yield,
     },
     runtime::Runtime,
     Defn,
 };
-use holochain_core_types::dna::zome::capabilities::ReservedCapabilityNames;
+use holochain_core_types::dna::zome::{
+    capabilities::ReservedCapabilityNames, host_permissions::HostPermission,
+};
 use num_traits::FromPrimitive;
 use std::str::FromStr;
 
@@ -85,6 +115,74 @@ pub enum ZomeApiFunction {
     /// the address algorithm is specific to the entry, typically sha256 but can differ
     /// entry_address(entry: Entry) -> Address
     EntryAddress,
+
+    /// Get statistics about the agent's source chain
+    /// chain_info() -> ChainInfo
+    ChainInfo,
+
+    /// Get random bytes from the node's CSPRNG, refused in validation callbacks
+    /// random_bytes(len: usize) -> Vec<u8>
+    RandomBytes,
+
+    /// Get a page of entries of a type from the source chain, older than a cursor
+    /// query_page(entry_type_name: String, limit: u32, cursor: Option<Address>) -> QueryPage
+    QueryPage,
+
+    /// Count the links from a base with a tag, filtered on the status of their targets
+    /// count_links(entry_address: Address, tag: String, status_request: LinksStatusFilter) -> usize
+    CountLinks,
+
+    /// Hand a partial result of the running call to the caller, ahead of its return value
+    /// yield(partial_result: JsonString)
+    Yield,
+
+    /// Wait for holders to acknowledge holding a published entry
+    /// await_publish(address: Address, min_holders: usize, timeout_ms: u64) -> usize
+    AwaitPublish,
+
+    /// Encrypt a payload so that only the given agent can decrypt it
+    /// encrypt_for(recipient: Address, payload: Vec<u8>) -> Vec<u8>
+    EncryptFor,
+
+    /// Decrypt a payload that got encrypted for this agent
+    /// decrypt(payload: Vec<u8>) -> Vec<u8>
+    Decrypt,
+
+    /// Commit an entry and links to it from bases, reporting the outcome of each link
+    /// commit_and_link(entry: Entry, links: Vec<(Address, String)>) -> CommitAndLinkResult
+    CommitAndLink,
+
+    /// Get how many nodes hold an entry this agent published, and why others rejected it
+    /// get_publish_status(address: Address) -> PublishStatus
+    GetPublishStatus,
+
+    /// Get a property of the DNA, as set in its `properties`
+    /// property(name: String) -> JsonString
+    Property,
+
+    /// Commit an update of the agent's identity, which replaces it on every node
+    /// update_agent(nick: String) -> Address
+    UpdateAgent,
+
+    /// Get the names of the app entry types of the DNA
+    /// entry_types() -> Vec<String>
+    EntryTypes,
+
+    /// Get the sharing, links and description of an app entry type of the DNA
+    /// entry_type_properties(name: String) -> EntryTypeDef
+    EntryTypeProperties,
+
+    /// Publish an entry again even if it got published with the same meta before
+    /// republish(address: Address) -> Address
+    Republish,
+
+    /// Suspend the zome call for a while, refused in validation callbacks
+    /// sleep(millis: u64)
+    Sleep,
+
+    /// Call a zome function of the instance bridged to under a handle the DNA declares
+    /// call_bridge(handle: String, zome_name: String, cap_name: String, fn_name: String, args: String)
+    CallBridge,
 }
 
 impl Defn for ZomeApiFunction {
@@ -103,6 +201,23 @@ impl Defn for ZomeApiFunction {
             ZomeApiFunction::GetLinks => "hc_get_links",
             ZomeApiFunction::Query => "hc_query",
             ZomeApiFunction::EntryAddress => "hc_entry_address",
+            ZomeApiFunction::ChainInfo => "hc_chain_info",
+            ZomeApiFunction::RandomBytes => "hc_random",
+            ZomeApiFunction::QueryPage => "hc_query_page",
+            ZomeApiFunction::CountLinks => "hc_count_links",
+            ZomeApiFunction::Yield => "hc_yield",
+            ZomeApiFunction::AwaitPublish => "hc_await_publish",
+            ZomeApiFunction::EncryptFor => "hc_encrypt_for",
+            ZomeApiFunction::Decrypt => "hc_decrypt",
+            ZomeApiFunction::CommitAndLink => "hc_commit_and_link",
+            ZomeApiFunction::GetPublishStatus => "hc_get_publish_status",
+            ZomeApiFunction::Property => "hc_property",
+            ZomeApiFunction::UpdateAgent => "hc_update_agent",
+            ZomeApiFunction::EntryTypes => "hc_entry_types",
+            ZomeApiFunction::EntryTypeProperties => "hc_entry_type_properties",
+            ZomeApiFunction::Republish => "hc_republish",
+            ZomeApiFunction::Sleep => "hc_sleep",
+            ZomeApiFunction::CallBridge => "hc_call_bridge",
         }
     }
 
@@ -140,6 +255,23 @@ impl FromStr for ZomeApiFunction {
             "hc_get_links" => Ok(ZomeApiFunction::GetLinks),
             "hc_query" => Ok(ZomeApiFunction::Query),
             "hc_entry_address" => Ok(ZomeApiFunction::EntryAddress),
+            "hc_chain_info" => Ok(ZomeApiFunction::ChainInfo),
+            "hc_random" => Ok(ZomeApiFunction::RandomBytes),
+            "hc_query_page" => Ok(ZomeApiFunction::QueryPage),
+            "hc_count_links" => Ok(ZomeApiFunction::CountLinks),
+            "hc_yield" => Ok(ZomeApiFunction::Yield),
+            "hc_await_publish" => Ok(ZomeApiFunction::AwaitPublish),
+            "hc_encrypt_for" => Ok(ZomeApiFunction::EncryptFor),
+            "hc_decrypt" => Ok(ZomeApiFunction::Decrypt),
+            "hc_commit_and_link" => Ok(ZomeApiFunction::CommitAndLink),
+            "hc_get_publish_status" => Ok(ZomeApiFunction::GetPublishStatus),
+            "hc_property" => Ok(ZomeApiFunction::Property),
+            "hc_update_agent" => Ok(ZomeApiFunction::UpdateAgent),
+            "hc_entry_types" => Ok(ZomeApiFunction::EntryTypes),
+            "hc_entry_type_properties" => Ok(ZomeApiFunction::EntryTypeProperties),
+            "hc_republish" => Ok(ZomeApiFunction::Republish),
+            "hc_sleep" => Ok(ZomeApiFunction::Sleep),
+            "hc_call_bridge" => Ok(ZomeApiFunction::CallBridge),
             _ => Err("Cannot convert string to ZomeApiFunction"),
         }
     }
@@ -171,6 +303,60 @@ impl ZomeApiFunction {
             ZomeApiFunction::GetLinks => invoke_get_links,
             ZomeApiFunction::Query => invoke_query,
             ZomeApiFunction::EntryAddress => invoke_entry_address,
+            ZomeApiFunction::ChainInfo => invoke_chain_info,
+            ZomeApiFunction::RandomBytes => invoke_random_bytes,
+            ZomeApiFunction::QueryPage => invoke_query_page,
+            ZomeApiFunction::CountLinks => invoke_count_links,
+            ZomeApiFunction::Yield => invoke_yield,
+            ZomeApiFunction::AwaitPublish => invoke_await_publish,
+            ZomeApiFunction::EncryptFor => invoke_encrypt_for,
+            ZomeApiFunction::Decrypt => invoke_decrypt,
+            ZomeApiFunction::CommitAndLink => invoke_commit_and_link,
+            ZomeApiFunction::GetPublishStatus => invoke_get_publish_status,
+            ZomeApiFunction::Property => invoke_property,
+            ZomeApiFunction::UpdateAgent => invoke_update_agent,
+            ZomeApiFunction::EntryTypes => invoke_entry_types,
+            ZomeApiFunction::EntryTypeProperties => invoke_entry_type_properties,
+            ZomeApiFunction::Republish => invoke_republish,
+            ZomeApiFunction::Sleep => invoke_sleep,
+            ZomeApiFunction::CallBridge => invoke_call_bridge,
+        }
+    }
+
+    /// The permission a zome needs to call this function, None if every zome may call it.
+    /// Calls to other zomes and bridged instances need none, the callee runs with its own
+    /// permissions.
+    pub fn required_permission(&self) -> Option<HostPermission> {
+        match *self {
+            ZomeApiFunction::MissingNo
+            | ZomeApiFunction::Abort
+            | ZomeApiFunction::Debug
+            | ZomeApiFunction::InitGlobals
+            | ZomeApiFunction::Call
+            | ZomeApiFunction::EntryAddress
+            | ZomeApiFunction::RandomBytes
+            | ZomeApiFunction::Yield
+            | ZomeApiFunction::Property
+            | ZomeApiFunction::EntryTypes
+            | ZomeApiFunction::EntryTypeProperties
+            | ZomeApiFunction::Sleep
+            | ZomeApiFunction::CallBridge => None,
+            ZomeApiFunction::CommitAppEntry
+            | ZomeApiFunction::UpdateEntry
+            | ZomeApiFunction::RemoveEntry
+            | ZomeApiFunction::LinkEntries
+            | ZomeApiFunction::CommitAndLink
+            | ZomeApiFunction::UpdateAgent => Some(HostPermission::ChainWrite),
+            ZomeApiFunction::Query | ZomeApiFunction::QueryPage | ZomeApiFunction::ChainInfo => {
+                Some(HostPermission::ChainRead)
+            }
+            ZomeApiFunction::GetAppEntry
+            | ZomeApiFunction::GetLinks
+            | ZomeApiFunction::CountLinks
+            | ZomeApiFunction::AwaitPublish
+            | ZomeApiFunction::GetPublishStatus
+            | ZomeApiFunction::Republish => Some(HostPermission::Dht),
+            ZomeApiFunction::EncryptFor | ZomeApiFunction::Decrypt => Some(HostPermission::Crypto),
         }
     }
 }
@@ -179,7 +365,11 @@ impl ZomeApiFunction {
 pub mod tests {
     extern crate wabt;
     use self::wabt::Wat2Wasm;
-    use holochain_core_types::json::JsonString;
+    use holochain_core_types::{
+        error::{HolochainError, RibosomeErrorCode},
+        json::JsonString,
+    };
+    use holochain_wasm_utils::memory_allocation::u32_merge_bits;
     extern crate test_utils;
     use super::ZomeApiFunction;
     use crate::{
@@ -197,6 +387,15 @@ pub mod tests {
     /// generates the wasm to dispatch any zome API function with a single memomry managed runtime
     /// and bytes argument
     pub fn test_zome_api_function_wasm(canonical_name: &str) -> Vec<u8> {
+        test_zome_api_function_wasm_validating_with(canonical_name, 0)
+    }
+
+    /// generates the wasm like test_zome_api_function_wasm(), with an app entry validation
+    /// callback returning the given encoded value, 0 meaning the entry is valid
+    pub fn test_zome_api_function_wasm_validating_with(
+        canonical_name: &str,
+        validation_result: u32,
+    ) -> Vec<u8> {
         Wat2Wasm::new()
             .canonicalize_lebs(false)
             .write_debug_names(true)
@@ -274,7 +473,7 @@ pub mod tests {
         (param $allocation i32)
         (result i32)
 
-        (i32.const 0)
+        (i32.const {})
     )
 
     (func
@@ -329,7 +528,7 @@ pub mod tests {
     )
 )
                 "#,
-                    canonical_name
+                    canonical_name, validation_result
                 ),
             )
             .unwrap()
@@ -423,6 +622,23 @@ pub mod tests {
             ("hc_get_links", ZomeApiFunction::GetLinks),
             ("hc_query", ZomeApiFunction::Query),
             ("hc_entry_address", ZomeApiFunction::EntryAddress),
+            ("hc_chain_info", ZomeApiFunction::ChainInfo),
+            ("hc_random", ZomeApiFunction::RandomBytes),
+            ("hc_query_page", ZomeApiFunction::QueryPage),
+            ("hc_count_links", ZomeApiFunction::CountLinks),
+            ("hc_yield", ZomeApiFunction::Yield),
+            ("hc_await_publish", ZomeApiFunction::AwaitPublish),
+            ("hc_encrypt_for", ZomeApiFunction::EncryptFor),
+            ("hc_decrypt", ZomeApiFunction::Decrypt),
+            ("hc_commit_and_link", ZomeApiFunction::CommitAndLink),
+            ("hc_get_publish_status", ZomeApiFunction::GetPublishStatus),
+            ("hc_property", ZomeApiFunction::Property),
+            ("hc_update_agent", ZomeApiFunction::UpdateAgent),
+            ("hc_entry_types", ZomeApiFunction::EntryTypes),
+            ("hc_entry_type_properties", ZomeApiFunction::EntryTypeProperties),
+            ("hc_republish", ZomeApiFunction::Republish),
+            ("hc_sleep", ZomeApiFunction::Sleep),
+            ("hc_call_bridge", ZomeApiFunction::CallBridge),
         ] {
             assert_eq!(ZomeApiFunction::from_str(input).unwrap(), output);
         }
@@ -451,6 +667,23 @@ pub mod tests {
             (ZomeApiFunction::GetLinks, "hc_get_links"),
             (ZomeApiFunction::Query, "hc_query"),
             (ZomeApiFunction::EntryAddress, "hc_entry_address"),
+            (ZomeApiFunction::ChainInfo, "hc_chain_info"),
+            (ZomeApiFunction::RandomBytes, "hc_random"),
+            (ZomeApiFunction::QueryPage, "hc_query_page"),
+            (ZomeApiFunction::CountLinks, "hc_count_links"),
+            (ZomeApiFunction::Yield, "hc_yield"),
+            (ZomeApiFunction::AwaitPublish, "hc_await_publish"),
+            (ZomeApiFunction::EncryptFor, "hc_encrypt_for"),
+            (ZomeApiFunction::Decrypt, "hc_decrypt"),
+            (ZomeApiFunction::CommitAndLink, "hc_commit_and_link"),
+            (ZomeApiFunction::GetPublishStatus, "hc_get_publish_status"),
+            (ZomeApiFunction::Property, "hc_property"),
+            (ZomeApiFunction::UpdateAgent, "hc_update_agent"),
+            (ZomeApiFunction::EntryTypes, "hc_entry_types"),
+            (ZomeApiFunction::EntryTypeProperties, "hc_entry_type_properties"),
+            (ZomeApiFunction::Republish, "hc_republish"),
+            (ZomeApiFunction::Sleep, "hc_sleep"),
+            (ZomeApiFunction::CallBridge, "hc_call_bridge"),
         ] {
             assert_eq!(output, input.as_str());
         }
@@ -470,6 +703,23 @@ pub mod tests {
             ("hc_get_links", 10),
             ("hc_query", 11),
             ("hc_entry_address", 12),
+            ("hc_chain_info", 13),
+            ("hc_random", 14),
+            ("hc_query_page", 15),
+            ("hc_count_links", 16),
+            ("hc_yield", 17),
+            ("hc_await_publish", 18),
+            ("hc_encrypt_for", 19),
+            ("hc_decrypt", 20),
+            ("hc_commit_and_link", 21),
+            ("hc_get_publish_status", 22),
+            ("hc_property", 23),
+            ("hc_update_agent", 24),
+            ("hc_entry_types", 25),
+            ("hc_entry_type_properties", 26),
+            ("hc_republish", 27),
+            ("hc_sleep", 28),
+            ("hc_call_bridge", 29),
         ] {
             assert_eq!(output, ZomeApiFunction::str_to_index(input));
         }
@@ -489,9 +739,111 @@ pub mod tests {
             (10, ZomeApiFunction::GetLinks),
             (11, ZomeApiFunction::Query),
             (12, ZomeApiFunction::EntryAddress),
+            (13, ZomeApiFunction::ChainInfo),
+            (14, ZomeApiFunction::RandomBytes),
+            (15, ZomeApiFunction::QueryPage),
+            (16, ZomeApiFunction::CountLinks),
+            (17, ZomeApiFunction::Yield),
+            (18, ZomeApiFunction::AwaitPublish),
+            (19, ZomeApiFunction::EncryptFor),
+            (20, ZomeApiFunction::Decrypt),
+            (21, ZomeApiFunction::CommitAndLink),
+            (22, ZomeApiFunction::GetPublishStatus),
+            (23, ZomeApiFunction::Property),
+            (24, ZomeApiFunction::UpdateAgent),
+            (25, ZomeApiFunction::EntryTypes),
+            (26, ZomeApiFunction::EntryTypeProperties),
+            (27, ZomeApiFunction::Republish),
+            (28, ZomeApiFunction::Sleep),
+            (29, ZomeApiFunction::CallBridge),
         ] {
             assert_eq!(output, ZomeApiFunction::from_index(input));
         }
     }
 
+    /// wasm that calls the given zome API function with a constant encoded allocation,
+    /// with two bytes of invalid UTF-8 at the start of its memory
+    fn test_corrupt_argument_wasm(canonical_name: &str, encoded_allocation: u32) -> Vec<u8> {
+        Wat2Wasm::new()
+            .canonicalize_lebs(false)
+            .write_debug_names(true)
+            .convert(format!(
+                r#"
+(module
+    (import "env" "{}"
+        (func $zome_api_function
+            (param i32)
+            (result i32)
+        )
+    )
+
+    (memory 1)
+    (export "memory" (memory 0))
+    (data (i32.const 0) "\ff\fe")
+
+    (func
+        (export "test")
+            (param $allocation i32)
+            (result i32)
+
+        (call
+            $zome_api_function
+            (i32.const {})
+        )
+    )
+)
+                "#,
+                canonical_name, encoded_allocation as i32
+            ))
+            .unwrap()
+            .as_ref()
+            .to_vec()
+    }
+
+    #[test]
+    /// test that every zome API function answers corrupt arguments with an error code
+    /// instead of reading beyond the zome's memory or panicking
+    fn zome_api_functions_reject_corrupt_arguments() {
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability(),
+            test_zome_api_function_wasm("hc_debug"),
+        );
+        let dna_name = dna.name.to_string();
+        let (_instance, context) =
+            test_instance_and_context(dna).expect("Could not create test instance");
+        let zome_call = ZomeFnCall::new(
+            &test_zome_name(),
+            &test_capability(),
+            &test_function_name(),
+            test_parameters(),
+        );
+
+        let zome_api_functions = (2..)
+            .map(ZomeApiFunction::from_index)
+            .take_while(|zome_api_function| *zome_api_function != ZomeApiFunction::MissingNo);
+        for zome_api_function in zome_api_functions {
+            for (encoded_allocation, err_code) in vec![
+                (
+                    u32_merge_bits(0xFF00, 0x0200),
+                    RibosomeErrorCode::OutOfBounds,
+                ),
+                (u32_merge_bits(5, 0), RibosomeErrorCode::ZeroLength),
+                (
+                    u32_merge_bits(0, 2),
+                    RibosomeErrorCode::ArgumentDeserializationFailed,
+                ),
+            ] {
+                let wasm =
+                    test_corrupt_argument_wasm(zome_api_function.as_str(), encoded_allocation);
+                assert_eq!(
+                    ribosome::run_dna(&dna_name, context.clone(), wasm, &zome_call, None),
+                    Err(HolochainError::RibosomeFailed(err_code.to_string())),
+                    "{} accepted a corrupt argument",
+                    zome_api_function.as_str()
+                );
+            }
+        }
+    }
+
 }
@@ -0,0 +1,93 @@
+use crate::nucleus::ribosome::{api::ZomeApiResult, Runtime};
+use holochain_core_types::{
+    error::HolochainError,
+    json::{JsonString, RawString},
+};
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::Property function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Expecting the name of the property as a string
+/// Returns an HcApiReturnCode as I32
+/// The value of the property in the `properties` of the DNA, as JSON
+pub fn invoke_property(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    let args_str = runtime.load_json_string_from_args(&args);
+    let name = match RawString::try_from(args_str) {
+        Ok(name) => String::from(name),
+        Err(_) => return ribosome_error_code!(ArgumentDeserializationFailed),
+    };
+    let result = runtime
+        .context
+        .get_dna()
+        .ok_or(HolochainError::DnaMissing)
+        .and_then(|dna| {
+            dna.properties.get(&name).cloned().ok_or_else(|| {
+                HolochainError::ErrorGeneric(format!("DNA has no property {}", name))
+            })
+        })
+        .map(JsonString::from);
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        instance::tests::test_instance_and_context,
+        nucleus::ribosome::{
+            api::{
+                tests::{
+                    test_capability, test_zome_api_function_call, test_zome_api_function_wasm,
+                    test_zome_name,
+                },
+                ZomeApiFunction,
+            },
+            Defn,
+        },
+    };
+    use holochain_core_types::{
+        error::ZomeApiInternalResult,
+        json::{JsonString, RawString},
+    };
+    use std::convert::TryFrom;
+
+    fn call_property(name: &str) -> ZomeApiInternalResult {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::Property.as_str());
+        let mut dna =
+            test_utils::create_test_dna_with_wasm(&test_zome_name(), &test_capability(), wasm);
+        dna.properties = json!({"greeting": "hello", "max_posts": 10});
+        let dna_name = dna.name.clone();
+        let (instance, context) =
+            test_instance_and_context(dna).expect("Could not create test instance");
+
+        let call_result = test_zome_api_function_call(
+            &dna_name,
+            context.clone(),
+            &instance,
+            &context.get_wasm(&test_zome_name()).unwrap().code,
+            JsonString::from(RawString::from(name)).into_bytes(),
+        );
+        ZomeApiInternalResult::try_from(call_result)
+            .expect("valid ZomeApiInternalResult JsonString")
+    }
+
+    #[test]
+    /// test that properties of the DNA are returned as JSON
+    fn test_property() {
+        let result = call_property("greeting");
+        assert!(result.ok);
+        assert_eq!(result.value, "\"hello\"");
+
+        let result = call_property("max_posts");
+        assert!(result.ok);
+        assert_eq!(result.value, "10");
+    }
+
+    #[test]
+    /// test that asking for a property the DNA does not have fails
+    fn test_missing_property() {
+        let result = call_property("unknown");
+        assert!(!result.ok);
+        assert!(result.error.contains("DNA has no property unknown"));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::nucleus::ribosome::{api::ZomeApiResult, Runtime};
-use holochain_core_types::entry::entry_type::EntryType;
-use holochain_wasm_utils::api_serialization::QueryArgs;
+use holochain_core_types::{cas::content::AddressableContent, entry::entry_type::EntryType};
+use holochain_wasm_utils::api_serialization::{QueryArgs, QueryPage, QueryPageArgs};
 use std::{convert::TryFrom, str::FromStr};
 use wasmi::{RuntimeArgs, RuntimeValue};
 
@@ -35,3 +35,211 @@ pub fn invoke_query(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult
         query.limit,
     )))
 }
+
+/// ZomeApiFunction::query_page function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Expected complex argument: QueryPageArgs
+/// Returns an HcApiReturnCode as I32
+/// A cursor has to be the address of a header of the given type that this agent committed,
+/// otherwise this fails with InvalidQueryCursor.
+pub fn invoke_query_page(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    // deserialize args
+    let args_str = runtime.load_json_string_from_args(&args);
+    let query = match QueryPageArgs::try_from(args_str) {
+        Ok(input) => input,
+        Err(..) => return ribosome_error_code!(ArgumentDeserializationFailed),
+    };
+
+    // Get entry_type
+    let entry_type = match EntryType::from_str(&query.entry_type_name) {
+        Ok(inner) => inner,
+        Err(..) => return ribosome_error_code!(UnknownEntryType),
+    };
+
+    let agent = runtime.context.state().unwrap().agent();
+    let chain = agent.chain();
+
+    // Look up the cursor header, only headers of our own chain qualify
+    let cursor_header = match query.cursor {
+        Some(ref cursor) => {
+            let agent_address = agent
+                .get_agent_address()
+                .unwrap_or(runtime.context.agent_id.address());
+            match chain.get_header(cursor) {
+                Ok(Some(ref header))
+                    if header.entry_type() == &entry_type
+                        && header.sources().contains(&agent_address) =>
+                {
+                    Some(header.clone())
+                }
+                _ => return ribosome_error_code!(InvalidQueryCursor),
+            }
+        }
+        None => None,
+    };
+
+    // Perform query
+    let top = agent
+        .top_chain_header()
+        .expect("Should have genesis entries.");
+    let (headers, more) =
+        chain.query_page(&Some(top), &entry_type, query.limit, cursor_header.as_ref());
+
+    let next_cursor = if more {
+        headers.last().map(|header| header.address())
+    } else {
+        None
+    };
+    runtime.store_result(Ok(QueryPage {
+        addresses: headers
+            .iter()
+            .map(|header| header.entry_address().clone())
+            .collect(),
+        next_cursor,
+    }))
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate test_utils;
+
+    use crate::{
+        agent::actions::commit::commit_entry,
+        instance::tests::{test_context_and_logger, test_instance},
+        nucleus::{
+            ribosome::{
+                self,
+                api::{tests::*, ZomeApiFunction},
+                Defn,
+            },
+            ZomeFnCall,
+        },
+    };
+    use futures::executor::block_on;
+    use holochain_core_types::{
+        cas::content::Address,
+        entry::{entry_type::test_app_entry_type, Entry},
+        error::HolochainError,
+        json::JsonString,
+    };
+    use holochain_wasm_utils::api_serialization::{QueryPage, QueryPageArgs};
+    use serde_json;
+    use std::convert::TryFrom;
+
+    /// query_page args for the test entry type
+    pub fn test_query_page_args_bytes(limit: u32, cursor: Option<Address>) -> Vec<u8> {
+        JsonString::from(QueryPageArgs {
+            entry_type_name: String::from(test_app_entry_type()),
+            limit,
+            cursor,
+        })
+        .into_bytes()
+    }
+
+    #[test]
+    fn pages_through_chain_without_gaps_or_duplicates() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::QueryPage.as_str());
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability(),
+            wasm.clone(),
+        );
+        let dna_name = &dna.name.to_string().clone();
+        let instance = test_instance(dna).expect("Could not create test instance");
+        let (context, _) = test_context_and_logger("joan");
+        let context = instance.initialize_context(context);
+
+        let commit = |i: u32| {
+            let entry = Entry::App(
+                test_app_entry_type(),
+                JsonString::from(format!("entry{} value", i)),
+            );
+            block_on(commit_entry(entry, None, &context))
+                .expect("Could not commit entry for testing")
+                .address
+        };
+        let query_page = |limit: u32, cursor: Option<Address>| {
+            let call_result = test_zome_api_function_call(
+                &dna_name,
+                context.clone(),
+                &instance,
+                &wasm,
+                test_query_page_args_bytes(limit, cursor),
+            );
+            let call_result = String::from(call_result);
+            let call_result: serde_json::Value =
+                serde_json::from_str(call_result.trim_end_matches('\u{0}')).unwrap();
+            assert_eq!(call_result["ok"], true, "error = {}", call_result["error"]);
+            QueryPage::try_from(JsonString::from(
+                call_result["value"].as_str().unwrap().to_string(),
+            ))
+            .unwrap()
+        };
+
+        let mut committed: Vec<Address> = (0..5).map(&commit).collect();
+
+        // Newest first, like query
+        let first = query_page(2, None);
+        assert_eq!(
+            first.addresses,
+            vec![committed[4].clone(), committed[3].clone()]
+        );
+        assert!(first.next_cursor.is_some());
+
+        // Entries committed in between don't shift the following pages
+        committed.push(commit(5));
+        let second = query_page(2, first.next_cursor.clone());
+        assert_eq!(
+            second.addresses,
+            vec![committed[2].clone(), committed[1].clone()]
+        );
+
+        let last = query_page(2, second.next_cursor.clone());
+        assert_eq!(last.addresses, vec![committed[0].clone()]);
+        assert_eq!(last.next_cursor, None);
+
+        // Without a limit everything comes in one page
+        let all = query_page(0, None);
+        assert_eq!(all.addresses.len(), committed.len());
+        assert_eq!(all.next_cursor, None);
+    }
+
+    #[test]
+    fn refuses_cursor_not_on_own_chain() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::QueryPage.as_str());
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability(),
+            wasm.clone(),
+        );
+        let instance = test_instance(dna.clone()).expect("Could not create test instance");
+        let (context, _) = test_context_and_logger("joan");
+        let context = instance.initialize_context(context);
+
+        // The address of an entry is not the address of its header
+        let entry = Entry::App(test_app_entry_type(), JsonString::from("entry value"));
+        let entry_address = block_on(commit_entry(entry, None, &context))
+            .expect("Could not commit entry for testing")
+            .address;
+
+        let zome_call = ZomeFnCall::new(
+            &test_zome_name(),
+            &test_capability(),
+            &test_function_name(),
+            test_parameters(),
+        );
+        let result = ribosome::run_dna(
+            &dna.name,
+            context,
+            wasm,
+            &zome_call,
+            Some(test_query_page_args_bytes(2, Some(entry_address))),
+        );
+        assert_eq!(
+            result,
+            Err(HolochainError::RibosomeFailed(String::from(
+                "Invalid query cursor"
+            )))
+        );
+    }
+}
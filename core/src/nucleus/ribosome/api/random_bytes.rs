@@ -0,0 +1,148 @@
+use crate::nucleus::ribosome::{api::ZomeApiResult, Runtime};
+use holochain_core_types::error::HolochainError;
+use holochain_wasm_utils::api_serialization::{CallContext, RandomBytesArgs, RANDOM_BYTES_MAX_LEN};
+use rand::{thread_rng, RngCore};
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::RandomBytes function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Expected complex argument: RandomBytesArgs
+/// Returns an HcApiReturnCode as I32
+/// The bytes come from the node's CSPRNG, so every node gets different ones.
+/// That's why validation callbacks can't use them.
+pub fn invoke_random_bytes(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    if runtime.zome_call.call_context == CallContext::Validation {
+        return ribosome_error_code!(NonDeterministicCallInValidation);
+    }
+    let args_str = runtime.load_json_string_from_args(&args);
+    let input = match RandomBytesArgs::try_from(args_str) {
+        Ok(input) => input,
+        Err(_) => return ribosome_error_code!(ArgumentDeserializationFailed),
+    };
+    if input.len > RANDOM_BYTES_MAX_LEN {
+        return runtime.store_result::<Vec<u8>>(Err(HolochainError::ErrorGeneric(format!(
+            "Can't get more than {} random bytes per call, asked for {}",
+            RANDOM_BYTES_MAX_LEN, input.len
+        ))));
+    }
+
+    let mut bytes = vec![0; input.len];
+    thread_rng().fill_bytes(&mut bytes);
+    runtime.store_result(Ok(bytes))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        instance::tests::test_instance_and_context,
+        nucleus::{
+            ribosome::{
+                self,
+                api::{
+                    tests::{
+                        test_capability, test_function_name, test_parameters,
+                        test_zome_api_function, test_zome_api_function_wasm, test_zome_name,
+                    },
+                    ZomeApiFunction,
+                },
+                Defn,
+            },
+            ZomeFnCall,
+        },
+    };
+    use holochain_core_types::{
+        error::{HolochainError, ZomeApiInternalResult},
+        json::JsonString,
+    };
+    use holochain_wasm_utils::api_serialization::{
+        CallContext, RandomBytesArgs, RANDOM_BYTES_MAX_LEN,
+    };
+    use std::{collections::HashSet, convert::TryFrom};
+
+    fn random_bytes_args(len: usize) -> Vec<u8> {
+        JsonString::from(RandomBytesArgs { len }).into_bytes()
+    }
+
+    fn random_bytes(len: usize) -> ZomeApiInternalResult {
+        let (call_result, _) = test_zome_api_function(
+            ZomeApiFunction::RandomBytes.as_str(),
+            random_bytes_args(len),
+        );
+        ZomeApiInternalResult::try_from(call_result).unwrap()
+    }
+
+    fn bytes_of(result: ZomeApiInternalResult) -> Vec<u8> {
+        assert!(result.ok, "error = {}", result.error);
+        serde_json::from_str(&result.value).unwrap()
+    }
+
+    #[test]
+    fn test_random_bytes_have_requested_length() {
+        assert_eq!(bytes_of(random_bytes(0)).len(), 0);
+        assert_eq!(bytes_of(random_bytes(32)).len(), 32);
+        assert_eq!(
+            bytes_of(random_bytes(RANDOM_BYTES_MAX_LEN)).len(),
+            RANDOM_BYTES_MAX_LEN
+        );
+    }
+
+    #[test]
+    fn test_random_bytes_length_is_capped() {
+        let result = random_bytes(RANDOM_BYTES_MAX_LEN + 1);
+        assert!(!result.ok);
+        assert!(
+            result
+                .error
+                .contains("Can't get more than 4096 random bytes per call"),
+            "error = {}",
+            result.error
+        );
+    }
+
+    #[test]
+    fn test_random_bytes_look_random() {
+        let bytes = bytes_of(random_bytes(RANDOM_BYTES_MAX_LEN));
+        // 4096 uniformly distributed bytes miss one of the 256 values with a chance of
+        // about 256 * (255/256)^4096, which is practically never
+        let distinct: HashSet<u8> = bytes.iter().cloned().collect();
+        assert_eq!(distinct.len(), 256);
+        // the mean is 127.5 with a standard deviation of about 1.15
+        let mean = bytes.iter().map(|b| *b as f64).sum::<f64>() / bytes.len() as f64;
+        assert!(mean > 120.0 && mean < 135.0, "mean = {}", mean);
+        // and two calls don't give the same bytes
+        assert_ne!(bytes_of(random_bytes(32)), bytes_of(random_bytes(32)));
+    }
+
+    #[test]
+    fn test_random_bytes_refused_in_validation() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::RandomBytes.as_str());
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability(),
+            wasm.clone(),
+        );
+        let (_instance, context) = test_instance_and_context(dna.clone()).unwrap();
+        let validation_call = ZomeFnCall::new(
+            &test_zome_name(),
+            &test_capability(),
+            &test_function_name(),
+            test_parameters(),
+        )
+        .with_call_context(CallContext::Validation);
+
+        let result = ribosome::run_dna(
+            &dna.name,
+            context,
+            wasm,
+            &validation_call,
+            Some(random_bytes_args(32)),
+        );
+        assert_eq!(
+            result,
+            Err(HolochainError::RibosomeFailed(String::from(
+                "Non-deterministic call in validation"
+            )))
+        );
+    }
+}
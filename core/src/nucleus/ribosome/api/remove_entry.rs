@@ -1,6 +1,7 @@
 use crate::{
     agent::actions::commit::commit_entry,
     dht::actions::remove_entry::remove_entry,
+    network::actions::publish::publish,
     nucleus::{
         actions::{build_validation_package::*, validate::*},
         ribosome::{api::ZomeApiResult, Runtime},
@@ -44,6 +45,7 @@ pub fn invoke_remove_entry(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApi
     let get_args = GetEntryArgs {
         address: deleted_entry_address,
         options: GetEntryOptions::default(),
+        resolve_latest: true,
     };
     let get_entry_history_result =
         block_on(get_entry_history_workflow(&runtime.context, &get_args));
@@ -92,6 +94,9 @@ pub fn invoke_remove_entry(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApi
                 )
             }),
     );
+    // 5. Publish the deletion entry, which publishes the meta of the deleted entry too
+    let result = result
+        .and_then(|_| block_on(publish(deletion_entry.address(), &runtime.context)).map(|_| ()));
     // Done
     match result {
         Err(_) => ribosome_error_code!(Unspecified),
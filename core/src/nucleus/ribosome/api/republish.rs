@@ -0,0 +1,52 @@
+use crate::{
+    network::actions::publish::republish,
+    nucleus::ribosome::{
+        api::{ZomeApiFunction, ZomeApiResult},
+        Defn, Runtime,
+    },
+};
+use futures::executor::block_on;
+use holochain_core_types::cas::content::Address;
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::Republish function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Expected Address argument
+/// Returns an HcApiReturnCode as I32
+/// Publishes an entry of the agent's chain again, even if it got published with the same meta
+/// before, to repair a DHT that lost it
+pub fn invoke_republish(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    let args_str = runtime.load_json_string_from_args(&args);
+    let address = match Address::try_from(args_str) {
+        Ok(address) => address,
+        Err(_) => return ribosome_error_code!(ArgumentDeserializationFailed),
+    };
+    let result = block_on(republish(address, &runtime.context))
+        .map_err(|hc_err| hc_err.with_context(ZomeApiFunction::Republish.as_str()));
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::nucleus::ribosome::{
+        api::{tests::test_zome_api_function, ZomeApiFunction},
+        Defn,
+    };
+    use holochain_core_types::{
+        cas::content::Address, error::ZomeApiInternalResult, json::JsonString,
+    };
+    use std::convert::TryFrom;
+
+    #[test]
+    /// test that only entries of the agent's chain can be republished
+    fn test_republish_of_unknown_entry_fails() {
+        let (call_result, _) = test_zome_api_function(
+            ZomeApiFunction::Republish.as_str(),
+            JsonString::from(Address::from("QmNotCommitted")).into_bytes(),
+        );
+
+        let zome_api_internal_result = ZomeApiInternalResult::try_from(call_result).unwrap();
+        assert!(!zome_api_internal_result.ok);
+    }
+}
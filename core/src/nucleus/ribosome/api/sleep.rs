@@ -0,0 +1,231 @@
+use crate::{
+    nucleus::ribosome::{api::ZomeApiResult, Runtime},
+    runtime::sleep_helping,
+};
+use holochain_core_types::error::HolochainError;
+use holochain_wasm_utils::api_serialization::{CallContext, SleepArgs};
+use std::{convert::TryFrom, time::Duration};
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::Sleep function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Expected complex argument: SleepArgs
+/// Returns an HcApiReturnCode as I32
+/// Suspends the zome call for the given number of milliseconds, so zomes waiting for
+/// something, e.g. the entry of another agent, don't have to spin.
+/// WASM execution can't be suspended halfway through, so the worker running the call sleeps.
+/// It runs the queued tasks of the runtime meanwhile, see runtime::sleep_helping(), which
+/// keeps sleeping calls from holding them up.
+/// Sleeps are capped one by one and in total per call to a few seconds, see
+/// Context::set_max_sleep().
+/// When a call wakes up depends on the node, that's why validation callbacks can't sleep.
+pub fn invoke_sleep(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    if runtime.zome_call.call_context == CallContext::Validation {
+        return ribosome_error_code!(NonDeterministicCallInValidation);
+    }
+    let args_str = runtime.load_json_string_from_args(&args);
+    let input = match SleepArgs::try_from(args_str) {
+        Ok(input) => input,
+        Err(_) => return ribosome_error_code!(ArgumentDeserializationFailed),
+    };
+    let duration = Duration::from_millis(input.millis);
+    if let Err(error) = check_sleep(
+        duration,
+        runtime.slept,
+        runtime.context.max_sleep(),
+        runtime.context.max_total_sleep(),
+    ) {
+        return runtime.store_result::<()>(Err(error));
+    }
+
+    sleep_helping(duration);
+    runtime.slept += duration;
+    runtime.store_result(Ok(()))
+}
+
+/// Fails if a call that slept for `slept` already can't sleep for `duration`
+fn check_sleep(
+    duration: Duration,
+    slept: Duration,
+    max_sleep: Duration,
+    max_total_sleep: Duration,
+) -> Result<(), HolochainError> {
+    let millis =
+        |duration: Duration| duration.as_secs() * 1000 + u64::from(duration.subsec_millis());
+    if duration > max_sleep {
+        return Err(HolochainError::ErrorGeneric(format!(
+            "Can't sleep for more than {} ms at once, asked for {} ms",
+            millis(max_sleep),
+            millis(duration)
+        )));
+    }
+    if slept + duration > max_total_sleep {
+        return Err(HolochainError::ErrorGeneric(format!(
+            "Can't sleep for more than {} ms per call, slept {} ms already and asked for {} ms",
+            millis(max_total_sleep),
+            millis(slept),
+            millis(duration)
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::check_sleep;
+    use crate::{
+        action::{Action, ActionWrapper},
+        instance::tests::test_instance_and_context,
+        nucleus::{
+            ribosome::{
+                self,
+                api::{
+                    tests::{
+                        test_capability, test_function_name, test_parameters,
+                        test_zome_api_function, test_zome_api_function_wasm, test_zome_name,
+                    },
+                    ZomeApiFunction,
+                },
+                Defn,
+            },
+            ZomeFnCall,
+        },
+    };
+    use holochain_core_types::{
+        dna::zome::capabilities::FnDeclaration,
+        error::{HolochainError, ZomeApiInternalResult},
+        json::JsonString,
+    };
+    use holochain_wasm_utils::api_serialization::{CallContext, SleepArgs};
+    use std::{
+        convert::TryFrom,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    fn sleep_args(millis: u64) -> Vec<u8> {
+        JsonString::from(SleepArgs { millis }).into_bytes()
+    }
+
+    #[test]
+    fn test_sleep_suspends_the_call() {
+        let started = Instant::now();
+        let (call_result, _) =
+            test_zome_api_function(ZomeApiFunction::Sleep.as_str(), sleep_args(200));
+        let result = ZomeApiInternalResult::try_from(call_result).unwrap();
+        assert!(result.ok, "error = {}", result.error);
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_sleep_is_capped() {
+        let (call_result, _) =
+            test_zome_api_function(ZomeApiFunction::Sleep.as_str(), sleep_args(2_001));
+        let result = ZomeApiInternalResult::try_from(call_result).unwrap();
+        assert!(!result.ok);
+        assert!(
+            result
+                .error
+                .contains("Can't sleep for more than 2000 ms at once"),
+            "error = {}",
+            result.error
+        );
+
+        let second = Duration::from_secs(1);
+        assert!(check_sleep(second, second * 2, second, second * 3).is_ok());
+        assert!(check_sleep(second, second * 3, second, second * 3).is_err());
+    }
+
+    /// Waits until the given condition holds
+    fn wait_for<F: Fn() -> bool>(condition: F) -> bool {
+        for _ in 0..100 {
+            if condition() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        false
+    }
+
+    #[test]
+    fn test_sleeping_calls_dont_wait_for_each_other() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::Sleep.as_str());
+        let mut dna =
+            test_utils::create_test_dna_with_wasm(&test_zome_name(), &test_capability(), wasm);
+        dna.zomes
+            .get_mut(&test_zome_name())
+            .unwrap()
+            .capabilities
+            .get_mut(&test_capability())
+            .unwrap()
+            .functions
+            .push(FnDeclaration {
+                name: test_function_name(),
+                ..FnDeclaration::default()
+            });
+        let (mut instance, context) = test_instance_and_context(dna).unwrap();
+
+        // more calls than the runtime has workers, so they have to share them
+        let started = Instant::now();
+        let calls: Vec<_> = (0..20)
+            .map(|_| {
+                ZomeFnCall::new(
+                    &test_zome_name(),
+                    &test_capability(),
+                    &test_function_name(),
+                    JsonString::from(SleepArgs { millis: 500 }),
+                )
+            })
+            .collect();
+        for call in &calls {
+            instance.dispatch(ActionWrapper::new(Action::ExecuteZomeFunction(
+                call.clone(),
+            )));
+        }
+        let results = || {
+            let nucleus = context.state().unwrap().nucleus();
+            calls
+                .iter()
+                .map(|call| nucleus.zome_call_result(call))
+                .collect::<Option<Vec<_>>>()
+        };
+        assert!(wait_for(|| results().is_some()));
+        for result in results().unwrap() {
+            assert!(ZomeApiInternalResult::try_from(result.unwrap()).unwrap().ok);
+        }
+        // one after the other, they would have taken 10 seconds
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_sleep_refused_in_validation() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::Sleep.as_str());
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability(),
+            wasm.clone(),
+        );
+        let (_instance, context) = test_instance_and_context(dna.clone()).unwrap();
+        let validation_call = ZomeFnCall::new(
+            &test_zome_name(),
+            &test_capability(),
+            &test_function_name(),
+            test_parameters(),
+        )
+        .with_call_context(CallContext::Validation);
+
+        let result = ribosome::run_dna(
+            &dna.name,
+            context,
+            wasm,
+            &validation_call,
+            Some(sleep_args(10)),
+        );
+        assert_eq!(
+            result,
+            Err(HolochainError::RibosomeFailed(String::from(
+                "Non-deterministic call in validation"
+            )))
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{
+    nucleus::ribosome::{api::ZomeApiResult, Runtime},
+    workflows::update_agent::update_agent_workflow,
+};
+use futures::executor::block_on;
+use holochain_wasm_utils::api_serialization::UpdateAgentArgs;
+use std::convert::TryFrom;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::UpdateAgent function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Expected complex argument: UpdateAgentArgs
+/// Returns an HcApiReturnCode as I32
+/// The address of the agent's new identity entry
+pub fn invoke_update_agent(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    let args_str = runtime.load_json_string_from_args(&args);
+    let update_args = match UpdateAgentArgs::try_from(args_str) {
+        Ok(update_args) => update_args,
+        Err(_) => return ribosome_error_code!(ArgumentDeserializationFailed),
+    };
+    let result = block_on(update_agent_workflow(&update_args.nick, &runtime.context));
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::nucleus::ribosome::{
+        api::{tests::test_zome_api_function, ZomeApiFunction},
+        Defn,
+    };
+    use holochain_core_types::{
+        cas::content::Address, error::ZomeApiInternalResult, json::JsonString,
+    };
+    use holochain_wasm_utils::api_serialization::UpdateAgentArgs;
+    use std::convert::TryFrom;
+
+    #[test]
+    /// test that the new identity gets committed on top of the chain
+    fn test_update_agent() {
+        let args = UpdateAgentArgs {
+            nick: String::from("jane doe"),
+        };
+        let (call_result, context) = test_zome_api_function(
+            ZomeApiFunction::UpdateAgent.as_str(),
+            JsonString::from(args).into_bytes(),
+        );
+
+        let result = ZomeApiInternalResult::try_from(call_result).unwrap();
+        assert!(result.ok, "{}", result.error);
+        let address = Address::try_from(JsonString::from(result.value)).unwrap();
+        let agent_state = context.state().unwrap().agent();
+        assert_eq!(
+            agent_state.top_chain_header().unwrap().entry_address(),
+            &address
+        );
+        assert_ne!(agent_state.get_agent_address().unwrap(), address);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{
     agent::actions::{commit::commit_entry, update_entry::update_entry},
+    network::actions::publish::publish,
     nucleus::{
         actions::{build_validation_package::*, validate::*},
         ribosome::{api::ZomeApiResult, Runtime},
@@ -44,6 +45,7 @@ pub fn invoke_update_entry(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApi
     let get_args = GetEntryArgs {
         address: entry_args.address,
         options: GetEntryOptions::default(),
+        resolve_latest: true,
     };
     let get_entry_history_result =
         block_on(get_entry_history_workflow(&runtime.context, &get_args));
@@ -56,15 +58,6 @@ pub fn invoke_update_entry(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApi
     }
     let latest_entry = entry_history.entries.iter().next().unwrap().clone();
 
-    // Get latest entry's ChainHeader
-    let agent_state = &runtime.context.state().unwrap().agent();
-    let chain_header_address = agent_state
-        .chain()
-        .iter(&agent_state.top_chain_header())
-        .find(|header| header.entry_address() == &latest_entry.address())
-        .map(|header| header.address().clone())
-        .expect("Modified entry should be in chain");
-
     // Create Chain Entry
     let entry = Entry::from(entry_args.new_entry.clone());
 
@@ -84,18 +77,28 @@ pub fn invoke_update_entry(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApi
             .and_then(|validation_data| {
                 validate_entry(entry.clone(), validation_data, &runtime.context)
             })
-            // 3. Commit the valid entry to chain and DHT
-            .and_then(|_| commit_entry(entry.clone(), Some(chain_header_address), &runtime.context))
+            // 3. Commit the valid entry to chain and DHT, with a crud-link to the entry it
+            // updates, which may be an entry of another agent
+            .and_then(|_| {
+                commit_entry(
+                    entry.clone(),
+                    Some(latest_entry.address()),
+                    &runtime.context,
+                )
+            })
             // 4. Update the entry in DHT metadata
-            .and_then(|new_address| {
+            .and_then(|commit_result| {
                 update_entry(
                     &runtime.context,
                     &runtime.context.action_channel,
                     latest_entry.address().clone(),
-                    new_address,
+                    commit_result.address,
                 )
             }),
     );
+    // 5. Publish the new entry, which publishes the meta of the updated entry too
+    let task_result = task_result
+        .and_then(|new_address| block_on(publish(new_address.clone(), &runtime.context)));
 
     runtime.store_result(task_result)
 }
@@ -0,0 +1,81 @@
+use crate::nucleus::ribosome::{
+    api::{ZomeApiFunction, ZomeApiResult},
+    Defn, Runtime,
+};
+use holochain_core_types::json::JsonString;
+use wasmi::{RuntimeArgs, RuntimeValue};
+
+/// ZomeApiFunction::Yield function code
+/// args: [0] encoded MemoryAllocation as u32
+/// Expected complex argument: the partial result, as any JSON
+/// Returns an HcApiReturnCode as I32
+/// Hands a partial result of the running call to whoever made it, ahead of its return value.
+/// Fails with BackpressureExceeded if they don't keep up, which fails the whole call.
+pub fn invoke_yield(runtime: &mut Runtime, args: &RuntimeArgs) -> ZomeApiResult {
+    let partial_result = runtime.load_json_string_from_args(&args);
+    let result = runtime
+        .context
+        .partial_results
+        .send(&runtime.zome_call, partial_result)
+        .map(|_| JsonString::null())
+        .map_err(|hc_err| hc_err.with_context(ZomeApiFunction::Yield.as_str()));
+    runtime.store_result(result)
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate test_utils;
+
+    use crate::{
+        instance::tests::{test_context_and_logger, test_instance},
+        nucleus::{
+            ribosome::{self, api::tests::*, Defn},
+            ZomeFnCall,
+        },
+    };
+    use holochain_core_types::json::JsonString;
+    use std::sync::mpsc::sync_channel;
+
+    use super::ZomeApiFunction;
+
+    #[test]
+    /// test that partial results go to the sink registered for the call
+    fn yields_partial_results_to_the_sink_of_the_call() {
+        let wasm = test_zome_api_function_wasm(ZomeApiFunction::Yield.as_str());
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability(),
+            wasm.clone(),
+        );
+        let dna_name = &dna.name.to_string().clone();
+        let instance = test_instance(dna).expect("Could not create test instance");
+        let (context, _) = test_context_and_logger("joan");
+        let context = instance.initialize_context(context);
+
+        let zome_call = ZomeFnCall::new(
+            &test_zome_name(),
+            &test_capability(),
+            &test_function_name(),
+            test_parameters(),
+        );
+        let (sender, receiver) = sync_channel(10);
+        context.partial_results.register(&zome_call, sender);
+
+        let call_result = ribosome::run_dna(
+            &dna_name,
+            context.clone(),
+            wasm.clone(),
+            &zome_call,
+            Some(r#"{"index":1}"#.as_bytes().to_vec()),
+        )
+        .expect("test should be callable");
+
+        assert_eq!(
+            call_result,
+            JsonString::from(r#"{"ok":true,"value":"null","error":"null"}"#.to_string() + "\u{0}"),
+        );
+        assert_eq!(context.partial_results.unregister(&zome_call), Ok(()));
+        let partial_results: Vec<JsonString> = receiver.iter().collect();
+        assert_eq!(partial_results, vec![JsonString::from(r#"{"index":1}"#)]);
+    }
+}
@@ -8,7 +8,7 @@ use std::sync::Arc;
 pub fn genesis(
     context: Arc<Context>,
     zome: &str,
-    // we ignore params for genesis
+    // genesis gets the genesis parameters of the instance, see CallbackParams::input()
     params: &CallbackParams,
 ) -> CallbackResult {
     call(context, zome, &Callback::Genesis, params)
@@ -25,6 +25,7 @@ pub mod tests {
             Defn,
         },
     };
+    use holochain_core_types::json::JsonString;
 
     #[test]
     fn pass() {
@@ -56,6 +57,25 @@ pub mod tests {
         assert_eq!(CallbackResult::NotImplemented, result);
     }
 
+    #[test]
+    fn genesis_params_are_the_input() {
+        let mut context = (*test_context("test")).clone();
+        assert_eq!(
+            CallbackParams::Genesis.input(&context),
+            JsonString::from("{}")
+        );
+
+        context.set_genesis_params(json!({"progenitor": "QmProgenitor"}));
+        assert_eq!(
+            CallbackParams::Genesis.input(&context),
+            JsonString::from("{\"progenitor\":\"QmProgenitor\"}")
+        );
+        assert_eq!(
+            CallbackParams::Receive.input(&context),
+            JsonString::from("\"Receive\"")
+        );
+    }
+
     #[test]
     fn fail() {
         let zome = "test_zome";
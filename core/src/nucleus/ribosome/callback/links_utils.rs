@@ -18,6 +18,7 @@ pub fn get_link_entries(
     let entry_args = &GetEntryArgs {
         address: base_address.clone(),
         options: GetEntryOptions::default(),
+        resolve_latest: true,
     };
     let base_entry_history = block_on(get_entry_history_workflow(&context, entry_args))?;
     if base_entry_history.entries.is_empty() {
@@ -30,6 +31,7 @@ pub fn get_link_entries(
     let entry_args = &GetEntryArgs {
         address: target_address.clone(),
         options: GetEntryOptions::default(),
+        resolve_latest: true,
     };
     let target_entry_history = block_on(get_entry_history_workflow(&context, entry_args))?;
     if target_entry_history.entries.is_empty() {
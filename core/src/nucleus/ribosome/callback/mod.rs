@@ -5,6 +5,7 @@ pub mod genesis;
 pub mod links_utils;
 pub mod receive;
 pub mod validate_entry;
+pub mod validation_dependencies;
 pub mod validation_package;
 
 use crate::{
@@ -25,6 +26,7 @@ use holochain_core_types::{
     json::{default_to_json, JsonString},
     validation::ValidationPackageDefinition,
 };
+use holochain_wasm_utils::api_serialization::CallContext;
 use num_traits::FromPrimitive;
 use serde_json;
 use std::{str::FromStr, sync::Arc};
@@ -139,6 +141,17 @@ impl ToString for CallbackParams {
     }
 }
 
+impl CallbackParams {
+    /// What the callback gets as its input. Genesis gets the genesis parameters of the
+    /// instance, see Context::genesis_params(), the other callbacks get their params.
+    pub fn input(&self, context: &Context) -> JsonString {
+        match self {
+            CallbackParams::Genesis => JsonString::from(context.genesis_params().to_string()),
+            _ => JsonString::from(self),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum CallbackResult {
     Pass,
@@ -204,13 +217,26 @@ pub fn call(
     zome: &str,
     function: &Callback,
     params: &CallbackParams,
+) -> CallbackResult {
+    call_with_context(context, zome, function, params, CallContext::Callback)
+}
+
+/// Like call() but for callbacks that run on behalf of somebody else,
+/// e.g. receive runs for the agent that sent the message.
+pub fn call_with_context(
+    context: Arc<Context>,
+    zome: &str,
+    function: &Callback,
+    params: &CallbackParams,
+    call_context: CallContext,
 ) -> CallbackResult {
     let zome_call = ZomeFnCall::new(
         zome,
         &function.capability().as_str().to_string(),
         &function.as_str().to_string(),
-        params,
-    );
+        params.input(&context),
+    )
+    .with_call_context(call_context);
 
     let dna = context.get_dna().expect("Callback called without DNA set!");
 
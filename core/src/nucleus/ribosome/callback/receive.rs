@@ -1,8 +1,10 @@
-use super::call;
+use super::{call, call_with_context};
 use crate::{
     context::Context,
     nucleus::ribosome::callback::{Callback, CallbackParams, CallbackResult},
 };
+use holochain_core_types::cas::content::Address;
+use holochain_wasm_utils::api_serialization::CallContext;
 use std::sync::Arc;
 
 pub fn receive(
@@ -14,10 +16,29 @@ pub fn receive(
     call(context, zome, &Callback::Receive, params)
 }
 
+/// Runs receive for a direct message from the given agent,
+/// so the zome can tell who sent it through hdk::call_context().
+pub fn receive_from(
+    context: Arc<Context>,
+    zome: &str,
+    from_agent: &Address,
+    params: &CallbackParams,
+) -> CallbackResult {
+    call_with_context(
+        context,
+        zome,
+        &Callback::Receive,
+        params,
+        CallContext::DirectMessage {
+            from_agent: from_agent.clone(),
+        },
+    )
+}
+
 #[cfg(test)]
 pub mod tests {
 
-    use super::receive;
+    use super::{receive, receive_from};
     use crate::{
         instance::tests::test_context,
         nucleus::ribosome::{
@@ -25,6 +46,7 @@ pub mod tests {
             Defn,
         },
     };
+    use holochain_core_types::cas::content::Address;
 
     #[test]
     fn not_implemented() {
@@ -69,4 +91,20 @@ pub mod tests {
         assert_eq!(CallbackResult::Fail("\"".to_string()), result);
     }
 
+    #[test]
+    fn receive_from_agent_runs_callback() {
+        let zome = "test_zome";
+        let instance = test_callback_instance(zome, Callback::Receive.as_str(), 0)
+            .expect("Test callback instance could not be initialized");
+        let context = instance.initialize_context(test_context("test"));
+
+        let result = receive_from(
+            context,
+            zome,
+            &Address::from("QmSender"),
+            &CallbackParams::Receive,
+        );
+
+        assert_eq!(CallbackResult::Pass, result);
+    }
 }
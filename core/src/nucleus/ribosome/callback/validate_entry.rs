@@ -18,8 +18,9 @@ use holochain_core_types::{
     error::HolochainError,
     validation::ValidationData,
 };
-use holochain_wasm_utils::api_serialization::validation::{
-    EntryValidationArgs, LinkValidationArgs,
+use holochain_wasm_utils::api_serialization::{
+    validation::{EntryValidationArgs, LinkValidationArgs},
+    CallContext,
 };
 use std::sync::Arc;
 
@@ -48,7 +49,7 @@ pub fn validate_entry(
             context,
         )?),
 
-        EntryType::LinkAdd => Ok(validate_link_entry(
+        EntryType::LinkAdd | EntryType::LinkRemove => Ok(validate_link_entry(
             entry.clone(),
             validation_data,
             context,
@@ -67,15 +68,16 @@ fn validate_link_entry(
     validation_data: ValidationData,
     context: Arc<Context>,
 ) -> Result<CallbackResult, HolochainError> {
-    let link_add = match entry {
-        Entry::LinkAdd(link_add) => link_add,
+    // Adds and removes get validated alike, the validation data tells them apart
+    let link = match entry {
+        Entry::LinkAdd(link_add) => link_add.link().clone(),
+        Entry::LinkRemove(link_remove) => link_remove.link().clone(),
         _ => {
             return Err(HolochainError::ValidationFailed(
-                "Could not extract link_add from entry".into(),
+                "Could not extract link from entry".into(),
             ));
         }
     };
-    let link = link_add.link().clone();
     let (base, target) = links_utils::get_link_entries(&link, &context)?;
     let link_definition_path = links_utils::find_link_definition_in_dna(
         &base.entry_type(),
@@ -83,7 +85,12 @@ fn validate_link_entry(
         &target.entry_type(),
         &context,
     )
-    .map_err(|_| HolochainError::NotImplemented)?;
+    .map_err(|_| {
+        HolochainError::ValidationFailed(format!(
+            "No link definition found for tag \"{}\"",
+            link.tag()
+        ))
+    })?;
 
     let wasm = context
         .get_wasm(&link_definition_path.zome_name)
@@ -100,7 +107,8 @@ fn validate_link_entry(
         "no capability, since this is an entry validation call",
         "__hdk_validate_link",
         params,
-    );
+    )
+    .with_call_context(CallContext::Validation);
     Ok(run_validation_callback(
         context.clone(),
         call,
@@ -158,7 +166,8 @@ fn build_validation_call(
         "no capability, since this is an entry validation call",
         "__hdk_validate_app_entry",
         params,
-    ))
+    )
+    .with_call_context(CallContext::Validation))
 }
 
 fn run_validation_callback(
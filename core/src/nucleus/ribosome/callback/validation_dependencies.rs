@@ -0,0 +1,67 @@
+extern crate serde_json;
+use crate::{
+    context::Context,
+    nucleus::{ribosome, ZomeFnCall},
+};
+use holochain_core_types::{
+    cas::content::Address,
+    entry::{entry_type::EntryType, Entry},
+    error::HolochainError,
+};
+use holochain_wasm_utils::api_serialization::{
+    validation::EntryValidationDependenciesArgs, CallContext,
+};
+use std::sync::Arc;
+
+/// Returns the addresses of the entries that have to be available before the given entry
+/// can be validated, as defined by the zome's `validation_dependencies` callback.
+/// Entries of types without such a callback (and zomes that were built without it) have none.
+/// Fails with HolochainError::ValidationFailed if the callback refuses the entry.
+pub fn get_validation_dependencies(
+    entry: &Entry,
+    context: Arc<Context>,
+) -> Result<Vec<Address>, HolochainError> {
+    let app_entry_type = match entry.entry_type() {
+        EntryType::App(app_entry_type) => app_entry_type,
+        _ => return Ok(Vec::new()),
+    };
+    let dna = context.get_dna().expect("Callback called without DNA set!");
+    let zome_name = match dna.get_zome_name_for_app_entry_type(&app_entry_type) {
+        Some(zome_name) => zome_name,
+        None => return Ok(Vec::new()),
+    };
+    let wasm = context
+        .get_wasm(&zome_name)
+        .ok_or(HolochainError::ErrorGeneric(String::from("no wasm found")))?;
+
+    let params = EntryValidationDependenciesArgs {
+        entry_type: EntryType::App(app_entry_type),
+        entry: entry.clone(),
+    };
+    let call = ZomeFnCall::new(
+        &zome_name,
+        "no capability, since this is an entry validation call",
+        "__hdk_get_validation_dependencies_for_entry",
+        params,
+    )
+    .with_call_context(CallContext::Validation);
+    let result = match ribosome::run_dna(
+        &dna.name.clone(),
+        context,
+        wasm.code.clone(),
+        &call,
+        Some(call.clone().parameters.into_bytes()),
+    ) {
+        Ok(result) => result,
+        // Zomes built before validation dependencies existed don't export the callback
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    let dependencies: Result<Vec<Address>, String> = serde_json::from_str(&String::from(result))
+        .map_err(|_| {
+            HolochainError::SerializationError(String::from(
+                "validation_dependencies result could not be deserialized as list of addresses",
+            ))
+        })?;
+    dependencies.map_err(HolochainError::ValidationFailed)
+}
@@ -15,7 +15,7 @@ use holochain_core_types::{
     json::JsonString,
     validation::ValidationPackageDefinition,
 };
-use holochain_wasm_utils::api_serialization::validation::LinkValidationPackageArgs;
+use holochain_wasm_utils::api_serialization::{validation::LinkValidationPackageArgs, CallContext};
 use std::{convert::TryFrom, sync::Arc};
 
 pub fn get_validation_package_definition(
@@ -44,24 +44,26 @@ pub fn get_validation_package_definition(
                     "no capability, since this is an entry validation call",
                     "__hdk_get_validation_package_for_entry_type",
                     app_entry_type.to_string(),
-                ),
+                )
+                .with_call_context(CallContext::Validation),
                 Some(app_entry_type.to_string().into_bytes()),
             )?
         }
-        EntryType::LinkAdd => {
-            let link_add = match entry {
-                Entry::LinkAdd(link_add) => link_add,
+        EntryType::LinkAdd | EntryType::LinkRemove => {
+            let link = match entry {
+                Entry::LinkAdd(link_add) => link_add.link().clone(),
+                Entry::LinkRemove(link_remove) => link_remove.link().clone(),
                 _ => {
                     return Err(HolochainError::ValidationFailed(
-                        "Failed to extract LinkAdd".into(),
+                        "Failed to extract link".into(),
                     ));
                 }
             };
-            let (base, target) = links_utils::get_link_entries(link_add.link(), &context)?;
+            let (base, target) = links_utils::get_link_entries(&link, &context)?;
 
             let link_definition_path = links_utils::find_link_definition_in_dna(
                 &base.entry_type(),
-                link_add.link().tag(),
+                link.tag(),
                 &target.entry_type(),
                 &context,
             )
@@ -82,7 +84,8 @@ pub fn get_validation_package_definition(
                 "no capability, since this is an entry validation call",
                 "__hdk_get_validation_package_for_link",
                 params,
-            );
+            )
+            .with_call_context(CallContext::Validation);
 
             ribosome::run_dna(
                 &dna.name.clone(),
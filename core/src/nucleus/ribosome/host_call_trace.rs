@@ -0,0 +1,248 @@
+//! Audit trace of the host functions a zome call invoked, for security reviews.
+//! The Ribosome only traces calls that asked for it, see ZomeFnCall::audit and
+//! Context::audit_host_calls(). It records the sizes of the arguments and results,
+//! never their content, so that private data doesn't end up in logs.
+
+use std::time::Duration;
+
+/// How many host function invocations a trace keeps, later ones only get counted
+pub const MAX_TRACED_HOST_CALLS: usize = 256;
+
+/// A host function invocation of a zome call
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct HostCallRecord {
+    /// Canonical name of the host function, e.g. "hc_commit_entry"
+    pub name: String,
+    pub input_bytes: usize,
+    pub output_bytes: usize,
+    pub duration_micros: u64,
+    /// false if the host function returned an error to the zome
+    pub success: bool,
+}
+
+/// The host function invocations of a zome call, in the order they happened
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct HostCallTrace {
+    pub calls: Vec<HostCallRecord>,
+    /// How many invocations came after the trace was full
+    pub dropped: usize,
+}
+
+impl HostCallTrace {
+    pub fn record(
+        &mut self,
+        name: &str,
+        input_bytes: usize,
+        output_bytes: usize,
+        duration: Duration,
+        success: bool,
+    ) {
+        if self.calls.len() >= MAX_TRACED_HOST_CALLS {
+            self.dropped += 1;
+            return;
+        }
+        self.calls.push(HostCallRecord {
+            name: name.to_string(),
+            input_bytes,
+            output_bytes,
+            duration_micros: duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros()),
+            success,
+        });
+    }
+
+    /// The names of the invoked host functions, in order
+    pub fn names(&self) -> Vec<&str> {
+        self.calls.iter().map(|call| call.name.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate test_utils;
+    extern crate wabt;
+
+    use self::wabt::Wat2Wasm;
+    use super::*;
+    use crate::{
+        instance::tests::{test_context_and_logger, test_instance},
+        nucleus::{
+            ribosome::{
+                api::{
+                    commit::tests::test_commit_args_bytes,
+                    tests::{test_capability, test_parameters, test_zome_name},
+                },
+                run_dna_with_measurements,
+            },
+            ZomeFnCall,
+        },
+    };
+    use holochain_core_types::{
+        cas::content::AddressableContent, entry::test_entry, json::JsonString,
+    };
+    use holochain_wasm_utils::api_serialization::get_entry::{
+        GetEntryArgs, GetEntryOptions, StatusRequestKind,
+    };
+    use std::sync::Arc;
+
+    /// Where commit_then_get_wasm() keeps the arguments for getting the test entry,
+    /// far enough from where the memory manager writes the input
+    const GET_ARGS_OFFSET: u32 = 16384;
+
+    fn test_get_args() -> String {
+        String::from(JsonString::from(GetEntryArgs {
+            address: test_entry().address(),
+            options: GetEntryOptions::new(StatusRequestKind::Latest),
+            resolve_latest: true,
+        }))
+    }
+
+    /// wasm with a function "commit_then_get" that commits the entry it gets as input and
+    /// then gets the test entry
+    fn commit_then_get_wasm() -> Vec<u8> {
+        let get_args = test_get_args();
+        Wat2Wasm::new()
+            .canonicalize_lebs(false)
+            .write_debug_names(true)
+            .convert(format!(
+                r#"
+(module
+    (import "env" "hc_commit_entry"
+        (func $commit
+            (param i32)
+            (result i32)
+        )
+    )
+
+    (import "env" "hc_get_entry"
+        (func $get
+            (param i32)
+            (result i32)
+        )
+    )
+
+    (memory 1)
+    (export "memory" (memory 0))
+    (data (i32.const {}) "{}")
+
+    (func
+        (export "commit_then_get")
+            (param $allocation i32)
+            (result i32)
+
+        (drop
+            (call
+                $commit
+                (get_local $allocation)
+            )
+        )
+        (call
+            $get
+            (i32.const {})
+        )
+    )
+
+    (func
+        (export "__hdk_validate_app_entry")
+        (param $allocation i32)
+        (result i32)
+
+        (i32.const 0)
+    )
+
+    (func
+        (export "__hdk_get_validation_package_for_entry_type")
+        (param $allocation i32)
+        (result i32)
+
+        ;; This writes "Entry" into memory
+        (i32.store (i32.const 0) (i32.const 34))
+        (i32.store (i32.const 1) (i32.const 69))
+        (i32.store (i32.const 2) (i32.const 110))
+        (i32.store (i32.const 3) (i32.const 116))
+        (i32.store (i32.const 4) (i32.const 114))
+        (i32.store (i32.const 5) (i32.const 121))
+        (i32.store (i32.const 6) (i32.const 34))
+
+        (i32.const 7)
+    )
+
+    (func
+        (export "__list_capabilities")
+        (param $allocation i32)
+        (result i32)
+
+        (i32.const 0)
+    )
+)
+                "#,
+                GET_ARGS_OFFSET,
+                get_args.replace("\\", "\\\\").replace("\"", "\\\""),
+                (GET_ARGS_OFFSET << 16) | get_args.len() as u32,
+            ))
+            .unwrap()
+            .as_ref()
+            .to_vec()
+    }
+
+    #[test]
+    /// test that audited calls record the host functions they invoke, in order
+    fn audited_calls_trace_their_host_calls() {
+        let wasm = commit_then_get_wasm();
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability(),
+            wasm.clone(),
+        );
+        let instance = test_instance(dna.clone()).expect("Could not initialize test instance");
+        let (context, _) = test_context_and_logger("joan");
+        let context = instance.initialize_context(context);
+        let call = ZomeFnCall::new(
+            &test_zome_name(),
+            &test_capability(),
+            "commit_then_get",
+            test_parameters(),
+        );
+
+        let (result, measurements) = run_dna_with_measurements(
+            &dna.name,
+            Arc::clone(&context),
+            wasm.clone(),
+            &call,
+            Some(test_commit_args_bytes()),
+        );
+        assert!(result.is_ok());
+        assert_eq!(measurements.host_call_trace, None);
+
+        let (result, measurements) = run_dna_with_measurements(
+            &dna.name,
+            Arc::clone(&context),
+            wasm.clone(),
+            &call.clone().with_audit(true),
+            Some(test_commit_args_bytes()),
+        );
+        assert!(result.is_ok());
+        let trace = measurements
+            .host_call_trace
+            .expect("audited call to be traced");
+        assert_eq!(trace.names(), vec!["hc_commit_entry", "hc_get_entry"]);
+        assert_eq!(trace.calls[0].input_bytes, test_commit_args_bytes().len());
+        assert_eq!(trace.calls[1].input_bytes, test_get_args().len());
+        assert!(trace
+            .calls
+            .iter()
+            .all(|host_call| host_call.success && host_call.output_bytes > 0));
+        assert_eq!(trace.dropped, 0);
+    }
+
+    #[test]
+    fn traces_are_bounded() {
+        let mut trace = HostCallTrace::default();
+        for _ in 0..MAX_TRACED_HOST_CALLS + 2 {
+            trace.record("hc_debug", 10, 0, Duration::from_millis(2), true);
+        }
+        assert_eq!(trace.calls.len(), MAX_TRACED_HOST_CALLS);
+        assert_eq!(trace.dropped, 2);
+        assert_eq!(trace.calls[0].duration_micros, 2000);
+        assert_eq!(trace.names()[0], "hc_debug");
+    }
+}
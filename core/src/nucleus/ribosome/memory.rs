@@ -1,7 +1,10 @@
 use holochain_core_types::error::RibosomeErrorCode;
-use holochain_wasm_utils::memory_allocation::{SinglePageAllocation, SinglePageStack, U16_MAX};
+use holochain_wasm_utils::memory_allocation::{
+    decode_payload_allocation, SinglePageAllocation, SinglePageStack, DEFAULT_MAX_PAYLOAD_SIZE,
+    U16_MAX,
+};
 
-use wasmi::{MemoryRef, ModuleRef};
+use wasmi::{memory_units::Bytes, MemoryRef, ModuleRef};
 
 //--------------------------------------------------------------------------------------------------
 // WASM Memory Manager
@@ -12,6 +15,7 @@ use wasmi::{MemoryRef, ModuleRef};
 pub struct SinglePageManager {
     stack: SinglePageStack,
     wasm_memory: MemoryRef,
+    max_payload_size: u32,
 }
 
 /// A Memory Manager limited to one wasm memory page that works like a stack.
@@ -44,9 +48,21 @@ impl SinglePageManager {
         return SinglePageManager {
             stack: SinglePageStack::default(),
             wasm_memory: wasm_memory.clone(),
+            max_payload_size: DEFAULT_MAX_PAYLOAD_SIZE,
         };
     }
 
+    /// Limits the payloads read_payload() accepts from the zome to the given size
+    pub fn with_max_payload_size(mut self, max_payload_size: u32) -> Self {
+        self.max_payload_size = max_payload_size;
+        self
+    }
+
+    /// The current size of the wasm memory in bytes
+    pub fn memory_size(&self) -> usize {
+        Bytes::from(self.wasm_memory.current_size()).0
+    }
+
     /// Allocate on stack without writing in it
     pub fn allocate(&mut self, length: u16) -> Result<SinglePageAllocation, RibosomeErrorCode> {
         if self.stack.top() as u32 + length as u32 > U16_MAX {
@@ -81,11 +97,25 @@ impl SinglePageManager {
         Ok(mem_buf)
     }
 
-    /// Read data somewhere in stack
-    pub fn read(&self, allocation: SinglePageAllocation) -> Vec<u8> {
-        return self
-            .wasm_memory
+    /// Read data somewhere in stack.
+    /// Fails with OutOfBounds if the allocation reaches beyond the memory.
+    pub fn read(&self, allocation: SinglePageAllocation) -> Result<Vec<u8>, RibosomeErrorCode> {
+        if allocation.offset() as usize + allocation.length() as usize > self.memory_size() {
+            return Err(RibosomeErrorCode::OutOfBounds);
+        }
+        self.wasm_memory
             .get(allocation.offset() as u32, allocation.length() as usize)
-            .expect("Successfully retrieve the result");
+            .map_err(|_| RibosomeErrorCode::OutOfBounds)
+    }
+
+    /// Read the payload in an allocation the zome encoded, which is not to be trusted,
+    /// see decode_payload_allocation()
+    pub fn read_payload(&self, encoded_allocation: u32) -> Result<Vec<u8>, RibosomeErrorCode> {
+        let allocation = decode_payload_allocation(
+            encoded_allocation,
+            self.memory_size(),
+            self.max_payload_size,
+        )?;
+        self.read(allocation)
     }
 }
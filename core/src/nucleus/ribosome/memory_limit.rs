@@ -0,0 +1,325 @@
+//! Caps the WASM memory a zome can use.
+//! wasmi lets a module grow its memory up to 4GiB, which would take the whole container down
+//! long before. So before instantiating a zome, the Ribosome rewrites its module: the memory
+//! gets the cap as its maximum and every `grow_memory` that would go beyond the cap traps
+//! instead, after setting an exported flag that tells the Ribosome why the zome trapped.
+
+use holochain_core_types::error::RibosomeErrorCode;
+use parity_wasm::elements::{
+    BlockType, ExportEntry, External, GlobalEntry, GlobalSection, GlobalType, InitExpr,
+    Instruction, Internal, Local, MemoryType, Module, Section, Type, ValueType,
+};
+
+/// How much memory an instance lets each zome call use by default, in MiB
+pub const DEFAULT_MAX_WASM_MEMORY_MB: u32 = 64;
+
+/// Name of the global the rewritten module exports, which is 1 once a zome tried
+/// to grow its memory beyond the cap
+pub const MEMORY_EXCEEDED_EXPORT: &str = "__hc_memory_exceeded";
+
+/// Size of a WASM memory page in bytes
+const PAGE_SIZE: u32 = 1 << 16;
+
+/// Number of WASM memory pages that make up the given MiB
+pub fn pages_of_mb(mb: u32) -> u32 {
+    mb.saturating_mul((1 << 20) / PAGE_SIZE)
+}
+
+/// Rewrites the module so that its memory can't grow beyond `max_pages`, see the module docs.
+/// Fails with OutOfMemory if the module already starts out with more.
+pub fn limit_memory(module: &mut Module, max_pages: u32) -> Result<(), RibosomeErrorCode> {
+    if let Some(memory_section) = module.memory_section_mut() {
+        for memory in memory_section.entries_mut().iter_mut() {
+            let initial = memory.limits().initial();
+            if initial > max_pages {
+                return Err(RibosomeErrorCode::OutOfMemory);
+            }
+            let maximum = memory
+                .limits()
+                .maximum()
+                .map(|maximum| maximum.min(max_pages))
+                .unwrap_or(max_pages);
+            *memory = MemoryType::new(initial, Some(maximum));
+        }
+    }
+
+    let exceeded_global = add_exceeded_global(module);
+    guard_memory_growth(module, max_pages, exceeded_global);
+    Ok(())
+}
+
+/// Adds the global flag and its export, returns the index of the global
+fn add_exceeded_global(module: &mut Module) -> u32 {
+    let imported_globals = module
+        .import_section()
+        .map(|imports| {
+            imports
+                .entries()
+                .iter()
+                .filter(|import| match import.external() {
+                    External::Global(_) => true,
+                    _ => false,
+                })
+                .count()
+        })
+        .unwrap_or(0);
+    let exceeded_global = GlobalEntry::new(
+        GlobalType::new(ValueType::I32, true),
+        InitExpr::new(vec![Instruction::I32Const(0), Instruction::End]),
+    );
+
+    // the global section comes before the export, start, element, code and data sections
+    if module.global_section().is_none() {
+        let sections = module.sections_mut();
+        let position = sections
+            .iter()
+            .position(|section| match section {
+                Section::Export(_)
+                | Section::Start(_)
+                | Section::Element(_)
+                | Section::Code(_)
+                | Section::Data(_) => true,
+                _ => false,
+            })
+            .unwrap_or_else(|| sections.len());
+        sections.insert(
+            position,
+            Section::Global(GlobalSection::with_entries(vec![])),
+        );
+    }
+    let globals = module
+        .global_section_mut()
+        .expect("the global section got added above")
+        .entries_mut();
+    let index = (imported_globals + globals.len()) as u32;
+    globals.push(exceeded_global);
+
+    // modules without exports have nothing to call anyway
+    if let Some(exports) = module.export_section_mut() {
+        exports.entries_mut().push(ExportEntry::new(
+            MEMORY_EXCEEDED_EXPORT.to_string(),
+            Internal::Global(index),
+        ));
+    }
+    index
+}
+
+/// Makes every `grow_memory` check first that the memory stays within `max_pages`.
+/// Each function gets a local to hold the number of pages to grow by.
+fn guard_memory_growth(module: &mut Module, max_pages: u32, exceeded_global: u32) {
+    let param_counts: Vec<u32> = {
+        let types = module
+            .type_section()
+            .map(|types| types.types().to_vec())
+            .unwrap_or_default();
+        module
+            .function_section()
+            .map(|functions| {
+                functions
+                    .entries()
+                    .iter()
+                    .map(|function| match types.get(function.type_ref() as usize) {
+                        Some(Type::Function(function_type)) => function_type.params().len() as u32,
+                        None => 0,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let bodies = match module.code_section_mut() {
+        Some(code_section) => code_section.bodies_mut(),
+        None => return,
+    };
+    for (body, param_count) in bodies.iter_mut().zip(param_counts) {
+        let grows_memory = body
+            .code()
+            .elements()
+            .iter()
+            .any(|instruction| match instruction {
+                Instruction::GrowMemory(_) => true,
+                _ => false,
+            });
+        if !grows_memory {
+            continue;
+        }
+
+        let delta = param_count + body.locals().iter().map(Local::count).sum::<u32>();
+        body.locals_mut().push(Local::new(1, ValueType::I32));
+        let instructions = body.code_mut().elements_mut();
+        let guarded: Vec<Instruction> = instructions
+            .drain(..)
+            .flat_map(|instruction| match instruction {
+                Instruction::GrowMemory(reserved) => vec![
+                    Instruction::SetLocal(delta),
+                    // the pages left, memory never exceeds max_pages so this can't wrap
+                    Instruction::I32Const(max_pages as i32),
+                    Instruction::CurrentMemory(reserved),
+                    Instruction::I32Sub,
+                    Instruction::GetLocal(delta),
+                    Instruction::I32LtU,
+                    Instruction::If(BlockType::NoResult),
+                    Instruction::I32Const(1),
+                    Instruction::SetGlobal(exceeded_global),
+                    Instruction::Unreachable,
+                    Instruction::End,
+                    Instruction::GetLocal(delta),
+                    Instruction::GrowMemory(reserved),
+                ],
+                instruction => vec![instruction],
+            })
+            .collect();
+        *instructions = guarded;
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    extern crate test_utils;
+    extern crate wabt;
+    use self::wabt::Wat2Wasm;
+    use super::*;
+    use crate::{
+        instance::tests::test_instance_and_context,
+        nucleus::{
+            ribosome::{
+                api::tests::{
+                    test_capability, test_function_name, test_parameters, test_zome_name,
+                },
+                run_dna_with_memory_usage,
+            },
+            ZomeFnCall,
+        },
+    };
+    use holochain_core_types::{error::HolochainError, json::JsonString};
+    use std::sync::Arc;
+
+    /// wasm with the given memory and a function "test" with the given body
+    fn wasm(memory: &str, body: &str) -> Vec<u8> {
+        Wat2Wasm::new()
+            .convert(format!(
+                r#"
+(module
+    {}
+    (export "memory" (memory 0))
+    (func (export "test") (param $allocation i32) (result i32)
+        {}
+    )
+)
+                "#,
+                memory, body
+            ))
+            .unwrap()
+            .as_ref()
+            .to_vec()
+    }
+
+    fn module(memory: &str, body: &str) -> Module {
+        parity_wasm::deserialize_buffer(&wasm(memory, body)).unwrap()
+    }
+
+    #[test]
+    fn memory_gets_capped() {
+        let mut capped = module("(memory 1)", "(i32.const 0)");
+        limit_memory(&mut capped, 16).unwrap();
+        let limits = capped.memory_section().unwrap().entries()[0]
+            .limits()
+            .clone();
+        assert_eq!(limits.initial(), 1);
+        assert_eq!(limits.maximum(), Some(16));
+
+        let mut smaller = module("(memory 1 4)", "(i32.const 0)");
+        limit_memory(&mut smaller, 16).unwrap();
+        let limits = smaller.memory_section().unwrap().entries()[0]
+            .limits()
+            .clone();
+        assert_eq!(limits.maximum(), Some(4));
+
+        let mut too_large = module("(memory 17)", "(i32.const 0)");
+        assert_eq!(
+            limit_memory(&mut too_large, 16),
+            Err(RibosomeErrorCode::OutOfMemory)
+        );
+    }
+
+    #[test]
+    fn memory_growth_gets_guarded() {
+        let mut growing = module("(memory 1)", "(grow_memory (i32.const 1))");
+        limit_memory(&mut growing, 16).unwrap();
+
+        let exports = growing.export_section().unwrap().entries();
+        assert!(exports
+            .iter()
+            .any(|export| export.field() == MEMORY_EXCEEDED_EXPORT));
+        let body = &growing.code_section().unwrap().bodies()[0];
+        assert_eq!(body.locals().len(), 1);
+        assert!(body.code().elements().contains(&Instruction::I32Const(16)));
+    }
+
+    #[test]
+    fn pages_of_mb_test() {
+        assert_eq!(pages_of_mb(1), 16);
+        assert_eq!(pages_of_mb(64), 1024);
+        assert_eq!(pages_of_mb(u32::max_value()), u32::max_value());
+    }
+
+    #[test]
+    /// test that a zome that grows its memory beyond the cap fails with OutOfMemory
+    /// and that the instance keeps working
+    fn growing_memory_beyond_the_cap_fails_with_out_of_memory() {
+        let dna = test_utils::create_test_dna_with_wasm(
+            &test_zome_name(),
+            &test_capability(),
+            wasm("(memory 1)", "(i32.const 0)"),
+        );
+        let dna_name = dna.name.clone();
+        let (_instance, context) =
+            test_instance_and_context(dna).expect("Could not create test instance");
+        let zome_call = ZomeFnCall::new(
+            &test_zome_name(),
+            &test_capability(),
+            &test_function_name(),
+            test_parameters(),
+        );
+        let out_of_memory = Err(HolochainError::RibosomeFailed(
+            RibosomeErrorCode::OutOfMemory.to_string(),
+        ));
+
+        let (result, _) = run_dna_with_memory_usage(
+            &dna_name,
+            context.clone(),
+            wasm(
+                "(memory 1)",
+                "(drop (grow_memory (i32.const 2000))) (i32.const 0)",
+            ),
+            &zome_call,
+            None,
+        );
+        assert_eq!(result, out_of_memory);
+
+        let (result, peak_memory) = run_dna_with_memory_usage(
+            &dna_name,
+            context.clone(),
+            wasm(
+                "(memory 1)",
+                "(drop (grow_memory (i32.const 2))) (i32.const 0)",
+            ),
+            &zome_call,
+            None,
+        );
+        assert_eq!(result, Ok(JsonString::null()));
+        assert_eq!(peak_memory, Some(3 * PAGE_SIZE as usize));
+
+        let mut small_context = (*context).clone();
+        small_context.set_max_wasm_memory_mb(1);
+        let (result, peak_memory) = run_dna_with_memory_usage(
+            &dna_name,
+            Arc::new(small_context),
+            wasm("(memory 17)", "(i32.const 0)"),
+            &zome_call,
+            None,
+        );
+        assert_eq!(result, out_of_memory);
+        assert_eq!(peak_memory, None);
+    }
+}
@@ -2,7 +2,9 @@
 
 pub mod api;
 pub mod callback;
+pub mod host_call_trace;
 pub mod memory;
+pub mod memory_limit;
 mod run_dna;
 mod runtime;
 
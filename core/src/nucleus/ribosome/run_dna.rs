@@ -1,21 +1,77 @@
 use crate::{
     context::Context,
     nucleus::{
-        ribosome::{api::ZomeApiFunction, memory::SinglePageManager, Runtime},
+        call_metrics::CallDurations,
+        ribosome::{
+            api::ZomeApiFunction,
+            host_call_trace::HostCallTrace,
+            memory::SinglePageManager,
+            memory_limit::{limit_memory, pages_of_mb, MEMORY_EXCEEDED_EXPORT},
+            Runtime,
+        },
         ZomeFnCall, ZomeFnResult,
     },
 };
 use holochain_core_types::{
+    dna::zome::Zome,
     error::{HcResult, HolochainError, RibosomeErrorCode, RibosomeReturnCode},
     json::JsonString,
+    time::Iso8601,
+};
+use holochain_wasm_utils::{
+    api_serialization::CallInitData, memory_allocation::decode_encoded_allocation,
+};
+use std::{
+    cell::RefCell,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
 };
-use holochain_wasm_utils::memory_allocation::decode_encoded_allocation;
-use std::{str::FromStr, sync::Arc};
 use wasmi::{
     self, Error as InterpreterError, FuncInstance, FuncRef, ImportsBuilder, ModuleImportResolver,
-    ModuleInstance, NopExternals, RuntimeValue, Signature, ValueType,
+    ModuleInstance, ModuleRef, NopExternals, RuntimeValue, Signature, ValueType,
 };
 
+/// The state of the source chain as of now, for the zome function about to be called
+pub fn call_init_data(context: &Arc<Context>) -> CallInitData {
+    let chain_info = context
+        .state()
+        .map(|state| state.agent().chain_info())
+        .unwrap_or_default();
+    CallInitData {
+        chain_top: chain_info.top_header_address,
+        chain_length: chain_info.length,
+        call_time: Iso8601::from(SystemTime::now()),
+        genesis_params: context.genesis_params(),
+    }
+}
+
+/// The zome of the DNA that the zome call goes to, if the DNA is known yet
+fn called_zome(context: &Arc<Context>, zome_call: &ZomeFnCall) -> Option<Zome> {
+    context
+        .state()
+        .and_then(|state| state.nucleus().dna())
+        .and_then(|dna| dna.get_zome(&zome_call.zome_name).cloned())
+}
+
+/// Whether the zome may call the given Zome API function, see Zome::host_permissions.
+/// Zomes we don't know may call all of them.
+pub fn permits(zome: &Option<Zome>, api_fn: &ZomeApiFunction) -> bool {
+    match (zome, api_fn.required_permission()) {
+        (Some(zome), Some(permission)) => zome.permits(permission),
+        _ => true,
+    }
+}
+
+/// Whether the zome trapped because it tried to grow its memory beyond the cap,
+/// see memory_limit
+fn memory_exceeded(wasm_instance: &ModuleRef) -> bool {
+    wasm_instance
+        .export_by_name(MEMORY_EXCEEDED_EXPORT)
+        .and_then(|export| export.as_global().map(|global| global.get()))
+        == Some(RuntimeValue::I32(1))
+}
+
 /// Executes an exposed zome function in a wasm binary.
 /// Multithreaded function
 /// panics if wasm binary isn't valid.
@@ -26,17 +82,92 @@ pub fn run_dna(
     zome_call: &ZomeFnCall,
     parameters: Option<Vec<u8>>,
 ) -> ZomeFnResult {
-    // Create wasm module from wasm binary
-    let module =
-        wasmi::Module::from_buffer(wasm).map_err(|e| HolochainError::ErrorGeneric(e.into()))?;
+    run_dna_with_memory_usage(dna_name, context, wasm, zome_call, parameters).0
+}
+
+/// What run_dna_with_measurements() found out about a zome call besides its result
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CallMeasurements {
+    /// The most WASM memory the zome function used in bytes, unless it did not get that far.
+    /// WASM memory never shrinks, so that is its size at the end.
+    pub peak_memory: Option<usize>,
+    /// The host functions the zome function invoked, if the call got audited
+    pub host_call_trace: Option<HostCallTrace>,
+    /// How long the call took, in total, in the WASM of the zome and in host functions
+    pub durations: Option<CallDurations>,
+}
+
+/// Like run_dna(), but also returns the most WASM memory the zome function used in bytes,
+/// see CallMeasurements::peak_memory
+pub fn run_dna_with_memory_usage(
+    dna_name: &str,
+    context: Arc<Context>,
+    wasm: Vec<u8>,
+    zome_call: &ZomeFnCall,
+    parameters: Option<Vec<u8>>,
+) -> (ZomeFnResult, Option<usize>) {
+    let (result, measurements) =
+        run_dna_with_measurements(dna_name, context, wasm, zome_call, parameters);
+    (result, measurements.peak_memory)
+}
+
+/// Like run_dna(), but also returns what got measured while running the zome function.
+/// Calls get audited if either the context or the call itself asks for it.
+pub fn run_dna_with_measurements(
+    dna_name: &str,
+    context: Arc<Context>,
+    wasm: Vec<u8>,
+    zome_call: &ZomeFnCall,
+    parameters: Option<Vec<u8>>,
+) -> (ZomeFnResult, CallMeasurements) {
+    let started = Instant::now();
+    let mut measurements = CallMeasurements::default();
+    let result = run_dna_measured(
+        dna_name,
+        context,
+        wasm,
+        zome_call,
+        parameters,
+        &mut measurements,
+    );
+    if let Some(ref mut durations) = measurements.durations {
+        durations.wall = started.elapsed();
+    }
+    (result, measurements)
+}
+
+fn run_dna_measured(
+    dna_name: &str,
+    context: Arc<Context>,
+    wasm: Vec<u8>,
+    zome_call: &ZomeFnCall,
+    parameters: Option<Vec<u8>>,
+    measurements: &mut CallMeasurements,
+) -> ZomeFnResult {
+    // Create wasm module from wasm binary, with its memory capped
+    let mut parsed_module: parity_wasm::elements::Module =
+        parity_wasm::deserialize_buffer(&wasm)
+            .map_err(|e| HolochainError::ErrorGeneric(e.to_string()))?;
+    limit_memory(
+        &mut parsed_module,
+        pages_of_mb(context.max_wasm_memory_mb()),
+    )
+    .map_err(|err_code| HolochainError::RibosomeFailed(err_code.to_string()))?;
+    let module = wasmi::Module::from_parity_wasm_module(parsed_module)
+        .map_err(|e| HolochainError::ErrorGeneric(e.into()))?;
 
     // invoke_index and resolve_func work together to enable callable host functions
     // within WASM modules, which is how the core API functions
     // read about the Externals trait for more detail
 
     // Correlate the names of the core ZomeApiFunction's with their indexes
-    // and declare its function signature (which is always the same)
-    struct RuntimeModuleImportResolver;
+    // and declare its function signature (which is always the same).
+    // Functions the zome has no permission for don't get linked, which fails the
+    // instantiation of a module that imports them. The resolver notes them to tell why.
+    struct RuntimeModuleImportResolver {
+        zome: Option<Zome>,
+        denied: RefCell<Vec<String>>,
+    }
     impl ModuleImportResolver for RuntimeModuleImportResolver {
         fn resolve_func(
             &self,
@@ -53,6 +184,14 @@ pub fn run_dna(
                 }
             };
 
+            if !permits(&self.zome, &api_fn) {
+                self.denied.borrow_mut().push(field_name.to_string());
+                return Err(InterpreterError::Function(format!(
+                    "zome has no permission to call {}",
+                    field_name
+                )));
+            }
+
             match api_fn {
                 // Abort is a way to receive useful debug info from
                 // assemblyscript memory allocators, see enum definition for function signature
@@ -78,33 +217,85 @@ pub fn run_dna(
     }
 
     // Create Imports with previously described Resolver
+    let zome = called_zome(&context, zome_call);
+    let resolver = RuntimeModuleImportResolver {
+        zome: zome.clone(),
+        denied: RefCell::new(Vec::new()),
+    };
     let mut imports = ImportsBuilder::new();
-    imports.push_resolver("env", &RuntimeModuleImportResolver);
+    imports.push_resolver("env", &resolver);
 
     // Create module instance from wasm module, and start it if start is defined
     let wasm_instance = ModuleInstance::new(&module, &imports)
-        .expect("Failed to instantiate module")
+        .map_err(|error| {
+            let denied = resolver.denied.borrow();
+            if denied.is_empty() {
+                HolochainError::RibosomeFailed(format!("Failed to instantiate module: {}", error))
+            } else {
+                HolochainError::PermissionDenied(format!(
+                    "Zome {} has no permission to call {}",
+                    zome_call.zome_name,
+                    denied.join(", ")
+                ))
+            }
+        })?
         .run_start(&mut NopExternals)
         .map_err(|_| HolochainError::RibosomeFailed("Module failed to start".to_string()))?;
 
     // write input arguments for module call in memory Buffer
     let input_parameters: Vec<_> = parameters.unwrap_or_default();
 
+    // Entry points generated by the HDK take the CallInitData as a second argument,
+    // hand-written ones might only take the input
+    let takes_call_init_data = wasm_instance
+        .export_by_name(&zome_call.fn_name)
+        .and_then(|export| {
+            export
+                .as_func()
+                .map(|func| func.signature().params().len() == 2)
+        })
+        .unwrap_or(false);
+
+    let host_call_trace = if zome_call.audit || context.audit_host_calls() {
+        Some(HostCallTrace::default())
+    } else {
+        None
+    };
+
     // instantiate runtime struct for passing external state data over wasm but not to wasm
     let mut runtime = Runtime {
         memory_manager: SinglePageManager::new(&wasm_instance),
         context,
         zome_call: zome_call.clone(),
         dna_name: dna_name.to_string(),
+        zome,
+        host_call_trace,
+        slept: Duration::from_secs(0),
+        host_call_time: Duration::from_secs(0),
     };
 
-    // Write input arguments in wasm memory
+    // Write the CallInitData and then the input arguments in wasm memory.
+    // The input has to come last, the zome's memory stack starts right after it.
     // scope for mutable borrow of runtime
-    let encoded_allocation_of_input: u32;
+    let mut invoke_args = Vec::new();
     {
         let mut_runtime = &mut runtime;
+        let mut encoded_allocation_of_call_init_data: Option<u32> = None;
+        if takes_call_init_data {
+            // Zomes read strings up to a null byte, which keeps the input that follows out
+            let mut call_init_data_bytes =
+                String::from(JsonString::from(call_init_data(&mut_runtime.context))).into_bytes();
+            call_init_data_bytes.push(0);
+            encoded_allocation_of_call_init_data = Some(
+                mut_runtime
+                    .memory_manager
+                    .write(&call_init_data_bytes)
+                    .map_err(|err| HolochainError::RibosomeFailed(err.to_string()))?
+                    .encode(),
+            );
+        }
         let maybe_allocation_of_input = mut_runtime.memory_manager.write(&input_parameters);
-        encoded_allocation_of_input = match maybe_allocation_of_input {
+        let encoded_allocation_of_input = match maybe_allocation_of_input {
             // No allocation to write is ok
             Err(RibosomeErrorCode::ZeroSizedAllocation) => 0,
             // Any other error is memory related
@@ -113,6 +304,10 @@ pub fn run_dna(
             }
             // Write successful, encode allocation
             Ok(allocation_of_input) => allocation_of_input.encode(),
+        };
+        invoke_args.push(RuntimeValue::I32(encoded_allocation_of_input as i32));
+        if let Some(encoded_allocation) = encoded_allocation_of_call_init_data {
+            invoke_args.push(RuntimeValue::I32(encoded_allocation as i32));
         }
     }
 
@@ -124,13 +319,30 @@ pub fn run_dna(
         // invoke function in wasm instance
         // arguments are info for wasm on how to retrieve complex input arguments
         // which have been set in memory module
-        returned_encoded_allocation = wasm_instance
-            .invoke_export(
-                zome_call.fn_name.clone().as_str(),
-                &[RuntimeValue::I32(encoded_allocation_of_input as i32)],
-                mut_runtime,
-            )
-            .map_err(|err| HolochainError::RibosomeFailed(err.to_string()))?
+        let invoked = Instant::now();
+        let invoke_result = wasm_instance.invoke_export(
+            zome_call.fn_name.clone().as_str(),
+            &invoke_args,
+            mut_runtime,
+        );
+        let invoke_duration = invoked.elapsed();
+        measurements.durations = Some(CallDurations {
+            wall: invoke_duration,
+            wasm: invoke_duration
+                .checked_sub(mut_runtime.host_call_time)
+                .unwrap_or_default(),
+            host_calls: mut_runtime.host_call_time,
+        });
+        measurements.peak_memory = Some(mut_runtime.memory_manager.memory_size());
+        measurements.host_call_trace = mut_runtime.host_call_trace.take();
+        returned_encoded_allocation = invoke_result
+            .map_err(|err| {
+                if memory_exceeded(&wasm_instance) {
+                    HolochainError::RibosomeFailed(RibosomeErrorCode::OutOfMemory.to_string())
+                } else {
+                    HolochainError::RibosomeFailed(err.to_string())
+                }
+            })?
             .unwrap()
             .try_into()
             .unwrap();
@@ -151,14 +363,17 @@ pub fn run_dna(
                 }
             };
         }
-        // Something in memory, try to read and return it
-        Ok(valid_allocation) => {
-            let result = runtime.memory_manager.read(valid_allocation);
-            let maybe_zome_result = String::from_utf8(result);
+        // Something in memory, try to read and return it, checking it lies within memory
+        Ok(_) => {
+            let maybe_zome_result = runtime
+                .memory_manager
+                .read_payload(returned_encoded_allocation)
+                .map_err(|err_code| err_code.to_string())
+                .and_then(|result| String::from_utf8(result).map_err(|err| err.to_string()));
             match maybe_zome_result {
                 Err(err) => {
-                    return_log_msg = err.to_string();
-                    return_result = Err(HolochainError::RibosomeFailed(err.to_string()));
+                    return_log_msg = err.clone();
+                    return_result = Err(HolochainError::RibosomeFailed(err));
                 }
                 Ok(json_str) => {
                     return_log_msg = json_str.clone();
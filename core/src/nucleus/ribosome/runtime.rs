@@ -3,18 +3,25 @@ use crate::{
     nucleus::{
         ribosome::{
             api::{ZomeApiFunction, ZomeApiResult},
+            host_call_trace::HostCallTrace,
             memory::SinglePageManager,
+            run_dna::permits,
             Defn,
         },
         ZomeFnCall,
     },
 };
 use holochain_core_types::{
-    error::{HolochainError, RibosomeReturnCode, ZomeApiInternalResult},
+    dna::zome::Zome,
+    error::{HolochainError, RibosomeErrorCode, RibosomeReturnCode, ZomeApiInternalResult},
     json::JsonString,
 };
-use holochain_wasm_utils::memory_allocation::decode_encoded_allocation;
-use std::sync::Arc;
+use holochain_wasm_utils::memory_allocation::{decode_encoded_allocation, u32_split_bits};
+use std::{
+    convert::TryFrom,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use wasmi::{Externals, RuntimeArgs, RuntimeValue};
 
 /// Object holding data to pass around to invoked Zome API functions
@@ -28,6 +35,14 @@ pub struct Runtime {
     pub dna_name: String,
     /// The zome function call that initiated the Ribosome.
     pub zome_call: ZomeFnCall,
+    /// The called zome, whose host permissions the Zome API functions check.
+    pub zome: Option<Zome>,
+    /// The host functions the zome invoked, if the call gets audited.
+    pub host_call_trace: Option<HostCallTrace>,
+    /// How long the zome slept so far, see hc_sleep.
+    pub slept: Duration,
+    /// How long the host functions the zome invoked took so far, all together.
+    pub host_call_time: Duration,
 }
 
 impl Runtime {
@@ -35,30 +50,41 @@ impl Runtime {
     /// Input RuntimeArgs should only have one input which is the encoded allocation holding
     /// the complex data as an utf8 string.
     /// Returns the utf8 string.
+    /// Zome API functions can rely on invoke_index() having checked the arguments already.
     pub fn load_json_string_from_args(&self, args: &RuntimeArgs) -> JsonString {
-        // @TODO don't panic in WASM
-        // @see https://github.com/holochain/holochain-rust/issues/159
-        assert_eq!(1, args.len());
+        self.try_load_json_string_from_args(args)
+            .expect("arguments should have been checked by invoke_index()")
+    }
+
+    /// Like load_json_string_from_args() but fails with the RibosomeErrorCode to return
+    /// to the zome if the arguments are corrupt: ZeroLength for an error code instead of an
+    /// allocation, OutOfBounds, PayloadTooLarge or ArgumentDeserializationFailed.
+    pub fn try_load_json_string_from_args(
+        &self,
+        args: &RuntimeArgs,
+    ) -> Result<JsonString, RibosomeErrorCode> {
+        if args.len() != 1 {
+            return Err(RibosomeErrorCode::ArgumentDeserializationFailed);
+        }
 
         // Read complex argument serialized in memory
-        let encoded_allocation: u32 = args.nth(0);
-        let maybe_allocation = decode_encoded_allocation(encoded_allocation);
-        let allocation = match maybe_allocation {
+        let encoded_allocation: u32 = args
+            .nth_checked(0)
+            .map_err(|_| RibosomeErrorCode::ArgumentDeserializationFailed)?;
+        match decode_encoded_allocation(encoded_allocation) {
             // Handle empty allocation edge case
-            Err(RibosomeReturnCode::Success) => return JsonString::null(),
-            // Handle error code
-            Err(_) => panic!("received error code instead of valid encoded allocation"),
-            // Handle normal allocation
-            Ok(allocation) => allocation,
+            Err(RibosomeReturnCode::Success) => return Ok(JsonString::null()),
+            Err(_) if u32_split_bits(encoded_allocation).1 == 0 => {
+                return Err(RibosomeErrorCode::ZeroLength);
+            }
+            _ => (),
         };
-        let bin_arg = self.memory_manager.read(allocation);
+        let bin_arg = self.memory_manager.read_payload(encoded_allocation)?;
 
         // convert complex argument
         String::from_utf8(bin_arg)
-            // @TODO don't panic in WASM
-            // @see https://github.com/holochain/holochain-rust/issues/159
-            .unwrap()
-            .into()
+            .map(JsonString::from)
+            .map_err(|_| RibosomeErrorCode::ArgumentDeserializationFailed)
     }
 
     /// Store anything that implements Into<JsonString> in wasm memory.
@@ -86,17 +112,87 @@ impl Runtime {
             Err(hc_err) => ZomeApiInternalResult::failure(core_error!(hc_err)),
         })
     }
+
+    /// The size of what a Zome API function returned to the zome and whether it succeeded.
+    /// Stored results tell by their ok flag, return codes by being Success.
+    fn host_call_outcome(&self, result: &ZomeApiResult) -> (usize, bool) {
+        let encoded_allocation = match result {
+            Ok(Some(RuntimeValue::I32(encoded_allocation))) => *encoded_allocation as u32,
+            Ok(_) => return (0, true),
+            Err(_) => return (0, false),
+        };
+        let (offset, length) = u32_split_bits(encoded_allocation);
+        if length == 0 {
+            let success = match RibosomeReturnCode::from_offset(offset) {
+                RibosomeReturnCode::Success => true,
+                RibosomeReturnCode::Failure(_) => false,
+            };
+            return (0, success);
+        }
+        let success = self
+            .memory_manager
+            .read_payload(encoded_allocation)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .and_then(|json| ZomeApiInternalResult::try_from(JsonString::from(json)).ok())
+            .map(|internal_result| internal_result.ok)
+            .unwrap_or(true);
+        (length as usize, success)
+    }
+
+    fn invoke_zome_api_function(&mut self, index: usize, args: RuntimeArgs) -> ZomeApiResult {
+        let zf = ZomeApiFunction::from_index(index);
+        match zf {
+            ZomeApiFunction::MissingNo => panic!("unknown function index"),
+            // linking already keeps zomes from calling what they have no permission for,
+            // checking again guards against imports that got linked some other way
+            _ if !permits(&self.zome, &zf) => {
+                let error = HolochainError::PermissionDenied(format!(
+                    "Zome {} has no permission to call {}",
+                    self.zome_call.zome_name,
+                    zf.as_str()
+                ));
+                self.store_result::<JsonString>(Err(error))
+            }
+            ZomeApiFunction::Abort => zf.as_fn()(self, &args),
+            // arguments come from the zome and are not to be trusted
+            _ => match self.try_load_json_string_from_args(&args) {
+                Err(err_code) => Ok(Some(RuntimeValue::I32(err_code as i32))),
+                // convert the function to its callable form and call it with the given arguments
+                Ok(_) => zf.as_fn()(self, &args),
+            },
+        }
+    }
 }
 
 // Correlate the indexes of core API functions with a call to the actual function
 // by implementing the Externals trait from Wasmi.
+// Every host call gets timed, calls that don't get audited only pay for that and for
+// checking whether they do.
 impl Externals for Runtime {
     fn invoke_index(&mut self, index: usize, args: RuntimeArgs) -> ZomeApiResult {
-        let zf = ZomeApiFunction::from_index(index);
-        match zf {
-            ZomeApiFunction::MissingNo => panic!("unknown function index"),
-            // convert the function to its callable form and call it with the given arguments
-            _ => zf.as_fn()(self, &args),
+        if self.host_call_trace.is_none() {
+            let started = Instant::now();
+            let result = self.invoke_zome_api_function(index, args);
+            self.host_call_time += started.elapsed();
+            return result;
+        }
+        let input_bytes = match args.len() {
+            1 => args
+                .nth_checked::<u32>(0)
+                .map(|encoded_allocation| u32_split_bits(encoded_allocation).1 as usize)
+                .unwrap_or(0),
+            _ => 0,
+        };
+        let started = Instant::now();
+        let result = self.invoke_zome_api_function(index, args);
+        let duration = started.elapsed();
+        self.host_call_time += duration;
+        let (output_bytes, success) = self.host_call_outcome(&result);
+        let name = ZomeApiFunction::from_index(index).as_str();
+        if let Some(ref mut trace) = self.host_call_trace {
+            trace.record(name, input_bytes, output_bytes, duration, success);
         }
+        result
     }
 }
@@ -1,10 +1,22 @@
-use crate::nucleus::ZomeFnCall;
+use crate::nucleus::{ribosome::host_call_trace::HostCallTrace, ZomeFnCall};
 use holochain_core_types::{
     cas::content::Address, dna::Dna, error::HolochainError, json::JsonString,
     validation::ValidationPackage,
 };
 use snowflake;
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+/// How long the result of a zome call stays in the state after it got delivered to
+/// whoever made the call, before it gets pruned
+pub const ZOME_CALL_RESULT_TTL: Duration = Duration::from_secs(5);
+
+/// How many traces of audited zome calls the state keeps, older ones get dropped.
+/// Unlike results, traces outlive the pruning of their call so they can be reviewed later.
+pub const MAX_HOST_CALL_TRACES: usize = 20;
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum NucleusStatus {
     New,
@@ -21,17 +33,53 @@ impl Default for NucleusStatus {
 
 pub type ValidationResult = Result<(), String>;
 
+/// When a zome call started and when its result got delivered to whoever made the call
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZomeCallTimes {
+    pub started: Instant,
+    pub delivered: Option<Instant>,
+}
+
+/// How many zome calls are still running, how many results the state keeps and how many
+/// it pruned, as shown in status listings, and the most WASM memory any call used so far
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ZomeCallStats {
+    pub running: usize,
+    pub retained: usize,
+    pub pruned: usize,
+    pub peak_memory_bytes: usize,
+}
+
+/// A zome call that has no result yet and how long it has been running,
+/// as listed when looking for what an instance is waiting on
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RunningZomeCall {
+    pub call_id: String,
+    pub zome: String,
+    pub capability: String,
+    pub function: String,
+    pub elapsed_ms: u64,
+}
+
 /// The state-slice for the Nucleus.
 /// Holds the dynamic parts of the DNA, i.e. zome calls and validation requests.
 #[derive(Clone, Debug, PartialEq, Default)]
 pub struct NucleusState {
     pub dna: Option<Dna>,
     pub status: NucleusStatus,
-    // @TODO eventually drop stale calls
-    // @see https://github.com/holochain/holochain-rust/issues/166
     // @TODO should this use the standard ActionWrapper/ActionResponse format?
     // @see https://github.com/holochain/holochain-rust/issues/196
     pub zome_calls: HashMap<ZomeFnCall, Option<Result<JsonString, HolochainError>>>,
+    /// Keeps track of the zome calls in zome_calls, so that their results can be pruned
+    pub zome_call_times: HashMap<ZomeFnCall, ZomeCallTimes>,
+    /// How many zome call results got pruned so far
+    pub pruned_zome_calls: usize,
+    /// The most WASM memory each zome call in zome_calls used, in bytes
+    pub zome_call_memory: HashMap<ZomeFnCall, usize>,
+    /// The most WASM memory any zome call used so far, in bytes
+    pub peak_zome_call_memory: usize,
+    /// The host functions the most recent audited zome calls invoked, oldest first
+    pub host_call_traces: VecDeque<(ZomeFnCall, HostCallTrace)>,
     pub validation_results: HashMap<(snowflake::ProcessUniqueId, Address), ValidationResult>,
     pub validation_packages:
         HashMap<snowflake::ProcessUniqueId, Result<ValidationPackage, HolochainError>>,
@@ -43,6 +91,11 @@ impl NucleusState {
             dna: None,
             status: NucleusStatus::New,
             zome_calls: HashMap::new(),
+            zome_call_times: HashMap::new(),
+            pruned_zome_calls: 0,
+            zome_call_memory: HashMap::new(),
+            peak_zome_call_memory: 0,
+            host_call_traces: VecDeque::new(),
             validation_results: HashMap::new(),
             validation_packages: HashMap::new(),
         }
@@ -57,6 +110,149 @@ impl NucleusState {
             .and_then(|value| value.clone())
     }
 
+    /// Starts keeping track of a zome call that has no result yet
+    pub fn start_zome_call(&mut self, zome_call: &ZomeFnCall) {
+        self.zome_calls.insert(zome_call.clone(), None);
+        self.track_zome_call(zome_call);
+    }
+
+    /// Sets the result of a zome call, which stays in the state until it got delivered
+    /// and pruned
+    pub fn return_zome_call(
+        &mut self,
+        zome_call: &ZomeFnCall,
+        result: Result<JsonString, HolochainError>,
+    ) {
+        self.zome_calls.insert(zome_call.clone(), Some(result));
+        self.track_zome_call(zome_call);
+    }
+
+    /// Records the most WASM memory the zome call used, see run_dna_with_memory_usage()
+    pub fn record_zome_call_memory(&mut self, zome_call: &ZomeFnCall, peak_memory: usize) {
+        self.zome_call_memory.insert(zome_call.clone(), peak_memory);
+        self.peak_zome_call_memory = self.peak_zome_call_memory.max(peak_memory);
+    }
+
+    /// The most WASM memory the zome call used in bytes, while the state keeps its result
+    pub fn zome_call_memory(&self, zome_call: &ZomeFnCall) -> Option<usize> {
+        self.zome_call_memory.get(zome_call).cloned()
+    }
+
+    /// Records the host functions an audited zome call invoked, dropping the oldest trace
+    /// if there are more than MAX_HOST_CALL_TRACES
+    pub fn record_host_call_trace(&mut self, zome_call: &ZomeFnCall, trace: HostCallTrace) {
+        if self.host_call_traces.len() >= MAX_HOST_CALL_TRACES {
+            self.host_call_traces.pop_front();
+        }
+        self.host_call_traces.push_back((zome_call.clone(), trace));
+    }
+
+    /// The host functions the zome call invoked, if it got audited recently
+    pub fn host_call_trace(&self, zome_call: &ZomeFnCall) -> Option<HostCallTrace> {
+        self.host_call_traces
+            .iter()
+            .find(|(call, _)| call == zome_call)
+            .map(|(_, trace)| trace.clone())
+    }
+
+    /// The traces of the last `count` audited zome calls, most recent first
+    pub fn last_host_call_traces(&self, count: usize) -> Vec<(ZomeFnCall, HostCallTrace)> {
+        self.host_call_traces
+            .iter()
+            .rev()
+            .take(count)
+            .cloned()
+            .collect()
+    }
+
+    fn track_zome_call(&mut self, zome_call: &ZomeFnCall) {
+        self.zome_call_times
+            .entry(zome_call.clone())
+            .or_insert_with(|| ZomeCallTimes {
+                started: Instant::now(),
+                delivered: None,
+            });
+    }
+
+    /// Marks the result of a zome call as delivered to whoever made the call
+    pub fn deliver_zome_call(&mut self, zome_call: &ZomeFnCall, now: Instant) {
+        if let Some(times) = self.zome_call_times.get_mut(zome_call) {
+            times.delivered = times.delivered.or(Some(now));
+        }
+    }
+
+    /// Drops the results of zome calls that got delivered more than ZOME_CALL_RESULT_TTL ago,
+    /// and the ones nobody picked up within call_timeout after the call started.
+    /// Calls that are still running are kept.
+    /// Returns how many results got dropped.
+    pub fn prune_zome_calls(&mut self, now: Instant, call_timeout: Duration) -> usize {
+        let zome_calls = &self.zome_calls;
+        let expired: Vec<ZomeFnCall> = self
+            .zome_call_times
+            .iter()
+            .filter(|(zome_call, times)| {
+                let has_result = zome_calls
+                    .get(zome_call)
+                    .map(|result| result.is_some())
+                    .unwrap_or(false);
+                has_result
+                    && match times.delivered {
+                        Some(delivered) => now >= delivered + ZOME_CALL_RESULT_TTL,
+                        None => now >= times.started + call_timeout,
+                    }
+            })
+            .map(|(zome_call, _)| zome_call.clone())
+            .collect();
+        for zome_call in &expired {
+            self.zome_calls.remove(zome_call);
+            self.zome_call_times.remove(zome_call);
+            self.zome_call_memory.remove(zome_call);
+        }
+        self.pruned_zome_calls += expired.len();
+        expired.len()
+    }
+
+    pub fn zome_call_stats(&self) -> ZomeCallStats {
+        let retained = self
+            .zome_calls
+            .values()
+            .filter(|result| result.is_some())
+            .count();
+        ZomeCallStats {
+            running: self.zome_calls.len() - retained,
+            retained,
+            pruned: self.pruned_zome_calls,
+            peak_memory_bytes: self.peak_zome_call_memory,
+        }
+    }
+
+    /// The zome calls that have no result yet, the longest running first.
+    /// Only their names get copied, not their parameters.
+    pub fn running_zome_calls(&self, now: Instant) -> Vec<RunningZomeCall> {
+        let mut running: Vec<RunningZomeCall> = self
+            .zome_calls
+            .iter()
+            .filter(|(_, result)| result.is_none())
+            .map(|(zome_call, _)| {
+                let elapsed = self
+                    .zome_call_times
+                    .get(zome_call)
+                    .filter(|times| now > times.started)
+                    .map(|times| now - times.started)
+                    .unwrap_or_default();
+                RunningZomeCall {
+                    call_id: zome_call.id().to_string(),
+                    zome: zome_call.zome_name.clone(),
+                    capability: zome_call.cap_name.clone(),
+                    function: zome_call.fn_name.clone(),
+                    elapsed_ms: elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis()),
+                }
+            })
+            .collect();
+        running.sort_by(|a, b| b.elapsed_ms.cmp(&a.elapsed_ms));
+        running
+    }
+
     pub fn has_initialized(&self) -> bool {
         self.status == NucleusStatus::Initialized
     }
@@ -80,11 +276,156 @@ impl NucleusState {
 #[cfg(test)]
 pub mod tests {
 
-    use super::NucleusState;
+    use super::*;
 
     /// dummy nucleus state
     pub fn test_nucleus_state() -> NucleusState {
         NucleusState::new()
     }
 
+    fn test_zome_call(fn_name: &str) -> ZomeFnCall {
+        ZomeFnCall::new("test_zome", "test_cap", fn_name, "{}")
+    }
+
+    #[test]
+    fn delivered_results_get_pruned_after_their_ttl() {
+        let mut state = test_nucleus_state();
+        let call = test_zome_call("delivered");
+        state.start_zome_call(&call);
+        state.return_zome_call(&call, Ok(JsonString::from("result")));
+        let delivered = Instant::now();
+        state.deliver_zome_call(&call, delivered);
+        let call_timeout = Duration::from_secs(60);
+
+        assert_eq!(state.prune_zome_calls(delivered, call_timeout), 0);
+        assert!(state.zome_call_result(&call).is_some());
+
+        let later = delivered + ZOME_CALL_RESULT_TTL;
+        assert_eq!(state.prune_zome_calls(later, call_timeout), 1);
+        assert_eq!(state.zome_call_result(&call), None);
+        assert!(state.zome_call_times.is_empty());
+        assert_eq!(
+            state.zome_call_stats(),
+            ZomeCallStats {
+                running: 0,
+                retained: 0,
+                pruned: 1,
+                peak_memory_bytes: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn undelivered_results_are_kept_until_the_call_timeout() {
+        let mut state = test_nucleus_state();
+        let undelivered = test_zome_call("undelivered");
+        let running = test_zome_call("running");
+        state.start_zome_call(&undelivered);
+        state.start_zome_call(&running);
+        state.return_zome_call(&undelivered, Ok(JsonString::from("result")));
+        let started = state.zome_call_times[&undelivered].started;
+        let call_timeout = Duration::from_secs(60);
+
+        // Way past the TTL of delivered results, but not past the call timeout
+        let before_timeout = started + ZOME_CALL_RESULT_TTL * 2;
+        assert_eq!(state.prune_zome_calls(before_timeout, call_timeout), 0);
+        assert_eq!(
+            state.zome_call_stats(),
+            ZomeCallStats {
+                running: 1,
+                retained: 1,
+                pruned: 0,
+                peak_memory_bytes: 0,
+            }
+        );
+
+        // Calls without a result stay, however old they are
+        let after_timeout = started + call_timeout * 2;
+        assert_eq!(state.prune_zome_calls(after_timeout, call_timeout), 1);
+        assert_eq!(state.zome_call_result(&undelivered), None);
+        assert!(state.zome_calls.contains_key(&running));
+        assert_eq!(
+            state.zome_call_stats(),
+            ZomeCallStats {
+                running: 1,
+                retained: 0,
+                pruned: 1,
+                peak_memory_bytes: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn running_zome_calls_are_listed_until_they_return() {
+        let mut state = test_nucleus_state();
+        let slow = test_zome_call("slow");
+        let returned = test_zome_call("returned");
+        state.start_zome_call(&slow);
+        state.start_zome_call(&returned);
+        state.return_zome_call(&returned, Ok(JsonString::from("result")));
+        let started = state.zome_call_times[&slow].started;
+
+        let running = state.running_zome_calls(started + Duration::from_millis(100));
+        assert_eq!(
+            running,
+            vec![RunningZomeCall {
+                call_id: slow.id().to_string(),
+                zome: String::from("test_zome"),
+                capability: String::from("test_cap"),
+                function: String::from("slow"),
+                elapsed_ms: 100,
+            }]
+        );
+        let later = state.running_zome_calls(started + Duration::from_millis(1500));
+        assert_eq!(later[0].elapsed_ms, 1500);
+
+        state.return_zome_call(&slow, Ok(JsonString::from("result")));
+        assert!(state
+            .running_zome_calls(started + Duration::from_secs(2))
+            .is_empty());
+    }
+
+    #[test]
+    fn memory_of_zome_calls_gets_recorded_until_they_get_pruned() {
+        let mut state = test_nucleus_state();
+        let small = test_zome_call("small");
+        let large = test_zome_call("large");
+        for (call, peak_memory) in vec![(&small, 65536), (&large, 196608)] {
+            state.start_zome_call(call);
+            state.return_zome_call(call, Ok(JsonString::from("result")));
+            state.record_zome_call_memory(call, peak_memory);
+        }
+        assert_eq!(state.zome_call_memory(&small), Some(65536));
+        assert_eq!(state.zome_call_memory(&large), Some(196608));
+
+        let delivered = Instant::now();
+        state.deliver_zome_call(&large, delivered);
+        state.prune_zome_calls(delivered + ZOME_CALL_RESULT_TTL, Duration::from_secs(60));
+        assert_eq!(state.zome_call_memory(&large), None);
+        assert_eq!(state.zome_call_stats().peak_memory_bytes, 196608);
+    }
+
+    #[test]
+    fn only_the_most_recent_host_call_traces_are_kept() {
+        let mut state = test_nucleus_state();
+        let calls: Vec<ZomeFnCall> = (0..MAX_HOST_CALL_TRACES + 1)
+            .map(|i| test_zome_call(&format!("audited_{}", i)))
+            .collect();
+        for call in calls.iter() {
+            let mut trace = HostCallTrace::default();
+            trace.record("hc_debug", 8, 0, Duration::from_micros(5), true);
+            state.record_host_call_trace(call, trace);
+        }
+        assert_eq!(state.host_call_traces.len(), MAX_HOST_CALL_TRACES);
+        assert_eq!(state.host_call_trace(&calls[0]), None);
+        assert_eq!(
+            state.host_call_trace(&calls[1]).unwrap().names(),
+            vec!["hc_debug"]
+        );
+
+        let last = state.last_host_call_traces(2);
+        assert_eq!(last.len(), 2);
+        assert_eq!(last[0].0, calls[MAX_HOST_CALL_TRACES]);
+        assert_eq!(last[1].0, calls[MAX_HOST_CALL_TRACES - 1]);
+    }
 }
@@ -0,0 +1,224 @@
+use holochain_core_types::{cas::content::Address, validation::ValidationPackage};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// How many bytes of serialized validation packages an agent keeps by default
+pub const DEFAULT_VALIDATION_PACKAGE_CACHE_BYTES: usize = 16 * 1024 * 1024;
+
+/// How often the packages asked for were in the cache, how often they had to be built,
+/// and what the cache holds right now
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ValidationPackageCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub packages: usize,
+    pub bytes: usize,
+}
+
+struct CachedPackage {
+    package: ValidationPackage,
+    /// The top of the chain when the package got built,
+    /// None for packages that don't contain any of the chain
+    chain_top: Option<Option<Address>>,
+    size: usize,
+    last_used: u64,
+}
+
+struct Cache {
+    max_bytes: usize,
+    packages: HashMap<Address, CachedPackage>,
+    bytes: usize,
+    /// Counts up with every use, orders the packages from least to most recently used
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+/// The validation packages of its own entries an agent built for the holders that asked for
+/// them, so that the other holders asking for the same package get it without it being built
+/// again, see `respond_validation_package_request()`.
+/// The entry type decides which package an entry gets, so its address is enough to tell the
+/// packages apart. Packages with source chain entries or headers contain the chain up to its
+/// top when they got built, and get built again once the chain grew.
+/// Keeps packages up to a total size, dropping the least recently used ones to make room.
+/// Shared by all clones of a context.
+#[derive(Clone)]
+pub struct ValidationPackageCache {
+    cache: Arc<Mutex<Cache>>,
+}
+
+impl Default for ValidationPackageCache {
+    fn default() -> Self {
+        ValidationPackageCache::new(DEFAULT_VALIDATION_PACKAGE_CACHE_BYTES)
+    }
+}
+
+impl ValidationPackageCache {
+    pub fn new(max_bytes: usize) -> Self {
+        ValidationPackageCache {
+            cache: Arc::new(Mutex::new(Cache {
+                max_bytes,
+                packages: HashMap::new(),
+                bytes: 0,
+                clock: 0,
+                hits: 0,
+                misses: 0,
+            })),
+        }
+    }
+
+    /// The package of the entry with the given address, if it is cached and still fits
+    /// the chain with the given top. Counts as a hit or a miss.
+    pub fn get(&self, address: &Address, chain_top: &Option<Address>) -> Option<ValidationPackage> {
+        let mut guard = self.cache.lock().unwrap();
+        let cache = &mut *guard;
+        let outdated = match cache.packages.get(address) {
+            Some(cached) => match cached.chain_top {
+                Some(ref built_on) => built_on != chain_top,
+                None => false,
+            },
+            None => {
+                cache.misses += 1;
+                return None;
+            }
+        };
+        if outdated {
+            cache.remove(address);
+            cache.misses += 1;
+            return None;
+        }
+        cache.clock += 1;
+        cache.hits += 1;
+        let clock = cache.clock;
+        cache.packages.get_mut(address).map(|cached| {
+            cached.last_used = clock;
+            cached.package.clone()
+        })
+    }
+
+    /// Keeps the package of the entry with the given address, built on the chain with the
+    /// given top, dropping the least recently used packages if it does not fit otherwise.
+    /// Packages larger than the whole cache don't get kept.
+    pub fn insert(
+        &self,
+        address: Address,
+        package: ValidationPackage,
+        chain_top: &Option<Address>,
+    ) {
+        let size = serde_json::to_string(&package)
+            .map(|json| json.len())
+            .unwrap_or(usize::max_value());
+        let mut guard = self.cache.lock().unwrap();
+        let cache = &mut *guard;
+        cache.remove(&address);
+        if size > cache.max_bytes {
+            return;
+        }
+        while cache.bytes + size > cache.max_bytes {
+            let least_recently_used = cache
+                .packages
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(cached_address, _)| cached_address.clone());
+            match least_recently_used {
+                Some(cached_address) => cache.remove(&cached_address),
+                None => break,
+            }
+        }
+        let depends_on_chain =
+            package.source_chain_entries.is_some() || package.source_chain_headers.is_some();
+        cache.clock += 1;
+        let cached = CachedPackage {
+            package,
+            chain_top: if depends_on_chain {
+                Some(chain_top.clone())
+            } else {
+                None
+            },
+            size,
+            last_used: cache.clock,
+        };
+        cache.bytes += size;
+        cache.packages.insert(address, cached);
+    }
+
+    pub fn stats(&self) -> ValidationPackageCacheStats {
+        let cache = self.cache.lock().unwrap();
+        ValidationPackageCacheStats {
+            hits: cache.hits,
+            misses: cache.misses,
+            packages: cache.packages.len(),
+            bytes: cache.bytes,
+        }
+    }
+}
+
+impl Cache {
+    fn remove(&mut self, address: &Address) {
+        if let Some(cached) = self.packages.remove(address) {
+            self.bytes -= cached.size;
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core_types::{
+        cas::content::AddressableContent, chain_header::test_chain_header, entry::test_entry,
+    };
+
+    fn chain_package() -> ValidationPackage {
+        let mut package = ValidationPackage::only_header(test_chain_header());
+        package.source_chain_entries = Some(vec![test_entry()]);
+        package
+    }
+
+    #[test]
+    fn chain_packages_are_cached_until_the_chain_grows() {
+        let cache = ValidationPackageCache::default();
+        let address = test_entry().address();
+        let top = Some(Address::from("top"));
+        assert_eq!(cache.get(&address, &top), None);
+
+        cache.insert(address.clone(), chain_package(), &top);
+        assert_eq!(cache.get(&address, &top), Some(chain_package()));
+        assert_eq!(cache.get(&address, &Some(Address::from("new top"))), None);
+        assert_eq!(cache.get(&address, &top), None);
+
+        // packages of only the header don't depend on the chain
+        let header_package = ValidationPackage::only_header(test_chain_header());
+        cache.insert(address.clone(), header_package.clone(), &top);
+        assert_eq!(
+            cache.get(&address, &Some(Address::from("new top"))),
+            Some(header_package)
+        );
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.packages), (2, 3, 1));
+    }
+
+    #[test]
+    fn least_recently_used_packages_make_room() {
+        let size = serde_json::to_string(&chain_package()).unwrap().len();
+        let cache = ValidationPackageCache::new(2 * size);
+        let top = None;
+        let (a, b, c) = (Address::from("a"), Address::from("b"), Address::from("c"));
+        cache.insert(a.clone(), chain_package(), &top);
+        cache.insert(b.clone(), chain_package(), &top);
+        assert!(cache.get(&a, &top).is_some());
+
+        cache.insert(c.clone(), chain_package(), &top);
+        assert!(cache.get(&b, &top).is_none());
+        assert!(cache.get(&a, &top).is_some());
+        assert!(cache.get(&c, &top).is_some());
+        assert_eq!(cache.stats().bytes, 2 * size);
+
+        // packages that don't fit at all don't push the others out
+        let tiny_cache = ValidationPackageCache::new(size - 1);
+        tiny_cache.insert(a.clone(), chain_package(), &top);
+        assert_eq!(tiny_cache.stats().packages, 0);
+    }
+}
@@ -17,10 +17,6 @@ use std::{
 
 /// trait that defines the persistence functionality that holochain_core requires
 pub trait Persister: Send {
-    // @TODO how does save/load work with snowflake IDs?
-    // snowflake is only unique across a single process, not a reboot save/load round trip
-    // we'd need real UUIDs for persistant uniqueness
-    // @see https://github.com/holochain/holochain-rust/issues/203
     fn save(&mut self, state: State) -> Result<(), HolochainError>;
     fn load(&self, context: Arc<Context>) -> Result<Option<State>, HolochainError>;
 }
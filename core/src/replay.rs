@@ -0,0 +1,312 @@
+//! Recording of the actions an instance processes and replaying them, for debugging.
+//!
+//! An action log is a file of JSON lines. The first line is an [ActionLogHeader] naming the
+//! agent, every further line a [RecordedAction]. Only actions that purely change state get
+//! recorded with their content (see [ReplayableAction]), all others are recorded by name
+//! and addresses, so the log tells what happened but replay does not trigger zome calls,
+//! network messages or other side effects again.
+
+use crate::{
+    action::{Action, ActionId, ActionWrapper},
+    context::Context,
+    dht::dht_store::RejectionReason,
+    state::State,
+};
+use chrono::Utc;
+use holochain_core_types::{
+    agent::AgentId, cas::content::Address, chain_header::ChainHeader, dna::Dna, entry::Entry,
+    error::HolochainError, link::LinkOperation,
+};
+use holochain_wasm_utils::api_serialization::CommitOptions;
+use serde_json;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+/// First line of an action log
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ActionLogHeader {
+    pub agent_id: AgentId,
+}
+
+/// Serializable stand-in for the actions that can be reduced again without side effects
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ReplayableAction {
+    Commit((Entry, Option<Address>, CommitOptions)),
+    Hold((Entry, u64)),
+    AddLink(LinkOperation),
+    RemoveLink((LinkOperation, Option<Address>)),
+    RejectEntry((Address, RejectionReason)),
+    HoldHeader(ChainHeader),
+    UpdateEntry((Address, Address)),
+    RemoveEntry((Address, Address)),
+    InitApplication(Dna),
+    ReturnInitializationResult(Option<String>),
+}
+
+impl ReplayableAction {
+    /// The stand-in for the given action, if it can be replayed
+    pub fn from_action(action: &Action) -> Option<Self> {
+        match action.clone() {
+            Action::Commit(data) => Some(ReplayableAction::Commit(data)),
+            Action::Hold(data) => Some(ReplayableAction::Hold(data)),
+            Action::AddLink(operation) => Some(ReplayableAction::AddLink(operation)),
+            Action::RemoveLink(data) => Some(ReplayableAction::RemoveLink(data)),
+            Action::RejectEntry(data) => Some(ReplayableAction::RejectEntry(data)),
+            Action::HoldHeader(header) => Some(ReplayableAction::HoldHeader(header)),
+            Action::UpdateEntry(data) => Some(ReplayableAction::UpdateEntry(data)),
+            Action::RemoveEntry(data) => Some(ReplayableAction::RemoveEntry(data)),
+            Action::InitApplication(dna) => Some(ReplayableAction::InitApplication(dna)),
+            Action::ReturnInitializationResult(result) => {
+                Some(ReplayableAction::ReturnInitializationResult(result))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<ReplayableAction> for Action {
+    fn from(replayable: ReplayableAction) -> Action {
+        match replayable {
+            ReplayableAction::Commit(data) => Action::Commit(data),
+            ReplayableAction::Hold(data) => Action::Hold(data),
+            ReplayableAction::AddLink(operation) => Action::AddLink(operation),
+            ReplayableAction::RemoveLink(data) => Action::RemoveLink(data),
+            ReplayableAction::RejectEntry(data) => Action::RejectEntry(data),
+            ReplayableAction::HoldHeader(header) => Action::HoldHeader(header),
+            ReplayableAction::UpdateEntry(data) => Action::UpdateEntry(data),
+            ReplayableAction::RemoveEntry(data) => Action::RemoveEntry(data),
+            ReplayableAction::InitApplication(dna) => Action::InitApplication(dna),
+            ReplayableAction::ReturnInitializationResult(result) => {
+                Action::ReturnInitializationResult(result)
+            }
+        }
+    }
+}
+
+/// One line of an action log
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedAction {
+    /// Position in the log, starting at 0
+    pub index: usize,
+    /// When the action got processed, RFC 3339
+    pub timestamp: String,
+    /// Id of the action wrapper, which it gets again on replay
+    pub action_id: ActionId,
+    pub name: String,
+    /// Id of the zome function call the action belongs to, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub call_id: Option<ActionId>,
+    pub addresses: Vec<Address>,
+    /// What gets reduced on replay, None for actions with side effects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay: Option<ReplayableAction>,
+}
+
+impl RecordedAction {
+    pub fn new(index: usize, action_wrapper: &ActionWrapper) -> Self {
+        let action = action_wrapper.action();
+        RecordedAction {
+            index,
+            timestamp: Utc::now().to_rfc3339(),
+            action_id: action_wrapper.id().clone(),
+            name: action.name().to_string(),
+            call_id: action.call_id(),
+            addresses: action.addresses(),
+            replay: ReplayableAction::from_action(action),
+        }
+    }
+}
+
+fn io_error<E: ToString>(path: &Path, error: E) -> HolochainError {
+    HolochainError::IoError(format!(
+        "Action log {}: {}",
+        path.display(),
+        error.to_string()
+    ))
+}
+
+/// Appends every action it gets to an action log
+pub struct ActionRecorder {
+    file: File,
+    path: PathBuf,
+    next_index: usize,
+}
+
+impl ActionRecorder {
+    /// Starts a new action log at the given path, replacing whatever was there.
+    /// Replaying it needs a copy of the storage as it was before the first recorded action.
+    pub fn create<P: AsRef<Path>>(path: P, agent_id: &AgentId) -> Result<Self, HolochainError> {
+        let path = path.as_ref();
+        let mut file = File::create(path).map_err(|e| io_error(path, e))?;
+        let header = ActionLogHeader {
+            agent_id: agent_id.clone(),
+        };
+        let line = serde_json::to_string(&header).map_err(|e| io_error(path, e))?;
+        writeln!(file, "{}", line).map_err(|e| io_error(path, e))?;
+        Ok(ActionRecorder {
+            file,
+            path: path.to_path_buf(),
+            next_index: 0,
+        })
+    }
+
+    pub fn record(&mut self, action_wrapper: &ActionWrapper) -> Result<(), HolochainError> {
+        let path = &self.path;
+        let recorded = RecordedAction::new(self.next_index, action_wrapper);
+        let line = serde_json::to_string(&recorded).map_err(|e| io_error(path, e))?;
+        writeln!(self.file, "{}", line).map_err(|e| io_error(path, e))?;
+        self.next_index += 1;
+        Ok(())
+    }
+}
+
+/// An action log read back from its file
+#[derive(Clone, Debug)]
+pub struct ActionLog {
+    pub header: ActionLogHeader,
+    pub actions: Vec<RecordedAction>,
+}
+
+impl ActionLog {
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, HolochainError> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| io_error(path, e))?;
+        let mut lines = BufReader::new(file).lines();
+        let header_line = lines
+            .next()
+            .ok_or_else(|| io_error(path, "empty file"))?
+            .map_err(|e| io_error(path, e))?;
+        let header = serde_json::from_str(&header_line).map_err(|e| io_error(path, e))?;
+        let mut actions = Vec::new();
+        for line in lines {
+            let line = line.map_err(|e| io_error(path, e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            actions.push(serde_json::from_str(&line).map_err(|e| io_error(path, e))?);
+        }
+        Ok(ActionLog { header, actions })
+    }
+}
+
+/// Rebuilds the state of the recorded instance by reducing the replayable actions of the
+/// log again, up to and including the one with index `until`, or all of them.
+/// The context's storage has to be a copy of the storage as it was when the recording started,
+/// and gets changed just like the original one did.
+/// Reducers of replayable actions don't dispatch, so the context's action channel can be
+/// one nobody listens to.
+pub fn replay(
+    mut context: Context,
+    log: &ActionLog,
+    until: Option<usize>,
+) -> Result<State, HolochainError> {
+    let loaded_state = context
+        .persister
+        .lock()
+        .map_err(|_| HolochainError::new("Could not lock persister"))?
+        .load(Arc::new(context.clone()))?;
+    let initial_state = loaded_state.unwrap_or_else(|| State::new(Arc::new(context.clone())));
+    let store = Arc::new(RwLock::new(initial_state));
+    context.set_state(store.clone());
+    let context = Arc::new(context);
+
+    let recorded_actions = log
+        .actions
+        .iter()
+        .take_while(|recorded| until.map(|until| recorded.index <= until).unwrap_or(true));
+    for recorded in recorded_actions {
+        if let Some(ref replayable) = recorded.replay {
+            // Just like the instance does it: reducers can read the state while we reduce
+            let new_state = store.read().unwrap().reduce(
+                context.clone(),
+                ActionWrapper::with_id(replayable.clone().into(), recorded.action_id.clone()),
+            );
+            *store.write().unwrap() = new_state;
+        }
+    }
+    let state = store.read().unwrap().clone();
+    Ok(state)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        action::tests::{test_action_wrapper_commit, test_action_wrapper_get},
+        instance::tests::test_context,
+    };
+    use holochain_core_types::{
+        cas::content::AddressableContent,
+        entry::{test_entry, test_entry_b},
+    };
+    use tempfile::tempdir;
+
+    #[test]
+    fn recorded_actions_read_back() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("actions.log");
+        let agent_id = AgentId::generate_fake("jane");
+        let commit = test_action_wrapper_commit();
+        let get = test_action_wrapper_get();
+
+        let mut recorder = ActionRecorder::create(&path, &agent_id).unwrap();
+        recorder.record(&commit).unwrap();
+        recorder.record(&get).unwrap();
+
+        let log = ActionLog::read(&path).unwrap();
+        assert_eq!(log.header.agent_id, agent_id);
+        assert_eq!(log.actions.len(), 2);
+        assert_eq!(log.actions[0].index, 0);
+        assert_eq!(log.actions[0].name, "Commit");
+        assert_eq!(&log.actions[0].action_id, commit.id());
+        assert_eq!(log.actions[0].addresses, vec![test_entry().address()]);
+        match log.actions[0].replay {
+            Some(ReplayableAction::Commit((ref entry, None, _))) => {
+                assert_eq!(entry.address(), test_entry().address())
+            }
+            ref other => panic!("unexpected replay {:?}", other),
+        }
+        // Gets go to the network, so they are not replayed
+        assert_eq!(log.actions[1].index, 1);
+        assert_eq!(log.actions[1].name, "GetEntry");
+        assert!(log.actions[1].replay.is_none());
+    }
+
+    #[test]
+    fn replay_stops_at_index() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("actions.log");
+        let context = test_context("jane");
+        let mut recorder = ActionRecorder::create(&path, &context.agent_id).unwrap();
+        let commit = ActionWrapper::new(Action::Commit((
+            test_entry(),
+            None,
+            CommitOptions::default(),
+        )));
+        let commit_b = ActionWrapper::new(Action::Commit((
+            test_entry_b(),
+            None,
+            CommitOptions::default(),
+        )));
+        recorder.record(&commit).unwrap();
+        recorder.record(&commit_b).unwrap();
+        let log = ActionLog::read(&path).unwrap();
+
+        let state = replay((*test_context("jane")).clone(), &log, Some(0)).unwrap();
+        let top = state.agent().top_chain_header().unwrap();
+        assert_eq!(top.entry_address(), &test_entry().address());
+        assert_eq!(top.link(), None);
+        // replayed actions keep their ids, so the history has the very same actions
+        assert!(state.history.contains(&commit));
+        assert!(!state.history.contains(&commit_b));
+
+        let state = replay((*test_context("jane")).clone(), &log, None).unwrap();
+        let top = state.agent().top_chain_header().unwrap();
+        assert_eq!(top.entry_address(), &test_entry_b().address());
+        assert!(top.link().is_some());
+    }
+}
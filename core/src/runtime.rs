@@ -0,0 +1,428 @@
+//! A pool of worker threads that can be shared by all instances of a container.
+//!
+//! Instances hand their tasks (zome function calls, validation callbacks, network triggered
+//! workflows) to the runtime through their [Context](../context/struct.Context.html) instead
+//! of spawning a thread for each of them.
+//! The runtime never runs tasks on more workers than it got created with: if all of them are
+//! busy, tasks wait in a queue. Tasks may block waiting for the results of other tasks though,
+//! so a task spawned by a task while all workers are busy runs right away on the worker of
+//! the spawning task, and a worker waiting for a result with `recv_helping()` runs queued
+//! tasks meanwhile.
+//! The action loops of the instances run on the runtime as well, each on a loop worker that
+//! the runtime adds for as long as the loop runs, see `spawn_loop()`. A loop blocks on the
+//! action channel of its instance and would otherwise occupy a task worker for good.
+
+use std::{
+    cell::RefCell,
+    cmp,
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, TryRecvError},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const DEFAULT_RUNTIME_THREADS: usize = 4;
+
+/// How long a worker waiting in recv_helping() waits for its message before it looks for
+/// queued tasks again
+const HELP_INTERVAL: Duration = Duration::from_millis(10);
+
+const QUEUE_POISONED: &str = "runtime queue poisoned";
+
+/// Boxed FnOnce that can be called
+trait Task: Send {
+    fn run(self: Box<Self>);
+}
+
+impl<F: FnOnce() + Send> Task for F {
+    fn run(self: Box<Self>) {
+        (*self)()
+    }
+}
+
+fn run_task(task: Box<Task>) {
+    // A panicking task must not take down the worker
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| task.run()));
+}
+
+#[derive(Default)]
+struct Queue {
+    tasks: VecDeque<Box<Task>>,
+    idle_workers: usize,
+    loops: usize,
+    shut_down: bool,
+}
+
+struct RuntimeInner {
+    queue: Mutex<Queue>,
+    task_queued: Condvar,
+    threads: usize,
+}
+
+/// Lets the workers exit once they ran the queued tasks, when the last Runtime is dropped
+struct RuntimeHandle {
+    inner: Arc<RuntimeInner>,
+}
+
+impl Drop for RuntimeHandle {
+    fn drop(&mut self) {
+        if let Ok(mut queue) = self.inner.queue.lock() {
+            queue.shut_down = true;
+        }
+        self.inner.task_queued.notify_all();
+    }
+}
+
+thread_local! {
+    /// The runtime the current thread is a worker of, if it is one
+    static WORKER_OF: RefCell<Option<Arc<RuntimeInner>>> = RefCell::new(None);
+}
+
+fn current_worker_of() -> Option<Arc<RuntimeInner>> {
+    WORKER_OF.with(|worker_of| worker_of.borrow().clone())
+}
+
+/// Handle to a pool of worker threads.
+/// Clones of a Runtime share the same pool. The workers exit when the last handle is dropped.
+#[derive(Clone)]
+pub struct Runtime {
+    inner: Arc<RuntimeInner>,
+    _handle: Arc<RuntimeHandle>,
+}
+
+impl Runtime {
+    /// Starts a runtime with the given number of worker threads
+    pub fn new(threads: usize) -> Self {
+        let inner = Arc::new(RuntimeInner {
+            queue: Mutex::new(Queue {
+                idle_workers: threads,
+                ..Default::default()
+            }),
+            task_queued: Condvar::new(),
+            threads,
+        });
+        for index in 0..threads {
+            let inner = inner.clone();
+            thread::Builder::new()
+                .name(format!("holochain-runtime-{}", index))
+                .spawn(move || Self::work(inner))
+                .expect("Could not spawn runtime worker thread");
+        }
+        Runtime {
+            _handle: Arc::new(RuntimeHandle {
+                inner: inner.clone(),
+            }),
+            inner,
+        }
+    }
+
+    fn work(inner: Arc<RuntimeInner>) {
+        WORKER_OF.with(|worker_of| *worker_of.borrow_mut() = Some(inner.clone()));
+        loop {
+            let task = {
+                let mut queue = inner.queue.lock().expect(QUEUE_POISONED);
+                loop {
+                    if let Some(task) = queue.tasks.pop_front() {
+                        queue.idle_workers -= 1;
+                        break task;
+                    }
+                    // All runtime handles are gone
+                    if queue.shut_down {
+                        return;
+                    }
+                    queue = inner.task_queued.wait(queue).expect(QUEUE_POISONED);
+                }
+            };
+            run_task(task);
+            inner.queue.lock().expect(QUEUE_POISONED).idle_workers += 1;
+        }
+    }
+
+    /// Number of worker threads in this runtime
+    pub fn threads(&self) -> usize {
+        self.inner.threads
+    }
+
+    /// Number of loops running on this runtime, see spawn_loop()
+    pub fn loops(&self) -> usize {
+        self.inner.queue.lock().expect(QUEUE_POISONED).loops
+    }
+
+    fn is_current_worker(&self) -> bool {
+        current_worker_of()
+            .map(|inner| Arc::ptr_eq(&inner, &self.inner))
+            .unwrap_or(false)
+    }
+
+    /// Runs the given task on the next free worker, the task waits in the queue until then.
+    /// A task spawned by a task of this runtime while there is no free worker runs right away
+    /// on the worker of the spawning task instead, since that one may block until it is done.
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let mut queue = self.inner.queue.lock().expect(QUEUE_POISONED);
+        if queue.tasks.len() >= queue.idle_workers && self.is_current_worker() {
+            drop(queue);
+            run_task(Box::new(task));
+            return;
+        }
+        queue.tasks.push_back(Box::new(task));
+        self.inner.task_queued.notify_one();
+    }
+
+    /// Runs a task that lasts as long as an instance, like its action loop, on a loop worker
+    /// that gets added for it and that exits when the task returns.
+    /// Tasks spawned by the loop go through the queue like the tasks of any other thread.
+    pub fn spawn_loop<F>(&self, task: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let inner = self.inner.clone();
+        let index = {
+            let mut queue = inner.queue.lock().expect(QUEUE_POISONED);
+            queue.loops += 1;
+            queue.loops
+        };
+        thread::Builder::new()
+            .name(format!("holochain-runtime-loop-{}", index))
+            .spawn(move || {
+                run_task(Box::new(task));
+                inner.queue.lock().expect(QUEUE_POISONED).loops -= 1;
+            })
+            .expect("Could not spawn runtime loop thread");
+    }
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Runtime::new(DEFAULT_RUNTIME_THREADS)
+    }
+}
+
+/// Receives from the given channel like `recv_timeout()`, or like `recv()` without a timeout.
+/// On a worker of a runtime, this runs the queued tasks of the runtime while waiting,
+/// since the message may be the result of one of them, which would never run if all workers
+/// were waiting like this one.
+pub fn recv_helping<T>(
+    receiver: &Receiver<T>,
+    timeout: Option<Duration>,
+) -> Result<T, RecvTimeoutError> {
+    let inner = match current_worker_of() {
+        Some(inner) => inner,
+        None => {
+            return match timeout {
+                Some(timeout) => receiver.recv_timeout(timeout),
+                None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+        }
+    };
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        match receiver.try_recv() {
+            Ok(message) => return Ok(message),
+            Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+            Err(TryRecvError::Empty) => (),
+        }
+        let wait = match deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(RecvTimeoutError::Timeout);
+                }
+                cmp::min(deadline - now, HELP_INTERVAL)
+            }
+            None => HELP_INTERVAL,
+        };
+        let task = inner.queue.lock().expect(QUEUE_POISONED).tasks.pop_front();
+        match task {
+            Some(task) => run_task(task),
+            None => match receiver.recv_timeout(wait) {
+                Err(RecvTimeoutError::Timeout) => (),
+                received => return received,
+            },
+        }
+    }
+}
+
+/// Sleeps for at least the given duration. On a worker of a runtime, this runs the queued
+/// tasks of the runtime meanwhile like `recv_helping()`, so a sleeping task doesn't keep
+/// the other tasks from running.
+pub fn sleep_helping(duration: Duration) {
+    let (_sender, receiver) = mpsc::channel::<()>();
+    let _ = recv_helping(&receiver, Some(duration));
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    fn current_thread_name() -> Option<String> {
+        thread::current().name().map(String::from)
+    }
+
+    fn wait_until<F: Fn() -> bool>(condition: F) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while !condition() {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::yield_now();
+        }
+        true
+    }
+
+    #[test]
+    fn runs_tasks_on_workers() {
+        let runtime = Runtime::new(2);
+        assert_eq!(runtime.threads(), 2);
+        let (sender, receiver) = channel();
+        for i in 0..10 {
+            let sender = sender.clone();
+            runtime.spawn(move || sender.send(i).unwrap());
+        }
+        let mut results: Vec<i32> = (0..10)
+            .map(|_| receiver.recv_timeout(Duration::from_secs(1)).unwrap())
+            .collect();
+        results.sort();
+        assert_eq!(results, (0..10).collect::<Vec<i32>>());
+    }
+
+    #[test]
+    fn tasks_waiting_on_each_other_dont_deadlock() {
+        let runtime = Runtime::new(1);
+        let (inner_sender, inner_receiver) = channel();
+        let (outer_sender, outer_receiver) = channel();
+        let inner_runtime = runtime.clone();
+        runtime.spawn(move || {
+            // occupies the only worker until the inner task ran
+            inner_runtime.spawn(move || inner_sender.send(()).unwrap());
+            inner_receiver.recv().unwrap();
+            outer_sender.send(()).unwrap();
+        });
+        assert!(outer_receiver.recv_timeout(Duration::from_secs(1)).is_ok());
+    }
+
+    #[test]
+    fn saturated_pool_queues_tasks_on_its_workers() {
+        let runtime = Runtime::new(2);
+        let (release_sender, release_receiver) = channel::<()>();
+        let release_receiver = Arc::new(Mutex::new(release_receiver));
+        let (sender, receiver) = channel();
+        for _ in 0..2 {
+            let release_receiver = release_receiver.clone();
+            let sender = sender.clone();
+            runtime.spawn(move || {
+                sender.send(current_thread_name()).unwrap();
+                release_receiver.lock().unwrap().recv().unwrap();
+            });
+        }
+        for _ in 0..2 {
+            receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        }
+
+        // all workers are busy, so these have to wait instead of getting threads of their own
+        for _ in 0..3 {
+            let sender = sender.clone();
+            runtime.spawn(move || sender.send(current_thread_name()).unwrap());
+        }
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+
+        release_sender.send(()).unwrap();
+        release_sender.send(()).unwrap();
+        let names: Vec<Option<String>> = (0..3)
+            .map(|_| receiver.recv_timeout(Duration::from_secs(1)).unwrap())
+            .collect();
+        for name in names {
+            assert!(
+                name == Some(String::from("holochain-runtime-0"))
+                    || name == Some(String::from("holochain-runtime-1")),
+                "task ran on {:?}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn waiting_worker_runs_queued_tasks() {
+        let runtime = Runtime::new(1);
+        let (result_sender, result_receiver) = channel();
+        let (done_sender, done_receiver) = channel();
+        runtime.spawn(move || {
+            let received = recv_helping(&result_receiver, Some(Duration::from_secs(1)));
+            done_sender.send(received).unwrap();
+        });
+        // queued by another thread, like the action loop does with nested zome calls
+        runtime.spawn(move || result_sender.send(current_thread_name()).unwrap());
+        assert_eq!(
+            done_receiver.recv_timeout(Duration::from_secs(2)).unwrap(),
+            Ok(Some(String::from("holochain-runtime-0")))
+        );
+    }
+
+    #[test]
+    fn sleeping_worker_runs_queued_tasks() {
+        let runtime = Runtime::new(1);
+        let (done_sender, done_receiver) = channel();
+        let started = Instant::now();
+        let sleep_done = done_sender.clone();
+        runtime.spawn(move || {
+            sleep_helping(Duration::from_millis(500));
+            sleep_done.send(started.elapsed()).unwrap();
+        });
+        runtime.spawn(move || done_sender.send(started.elapsed()).unwrap());
+        let ran_meanwhile = done_receiver.recv_timeout(Duration::from_secs(2)).unwrap();
+        let slept = done_receiver.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert!(ran_meanwhile < Duration::from_millis(500));
+        assert!(slept >= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn loops_run_beside_the_workers() {
+        let runtime = Runtime::new(1);
+        let (stop_sender, stop_receiver) = channel::<()>();
+        let (sender, receiver) = channel();
+        runtime.spawn_loop(move || {
+            sender.send(current_thread_name()).unwrap();
+            let _ = stop_receiver.recv();
+        });
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(1)).unwrap(),
+            Some(String::from("holochain-runtime-loop-1"))
+        );
+        assert_eq!(runtime.loops(), 1);
+
+        // the loop doesn't take the only worker
+        let (task_sender, task_receiver) = channel();
+        runtime.spawn(move || task_sender.send(()).unwrap());
+        assert!(task_receiver.recv_timeout(Duration::from_secs(1)).is_ok());
+
+        drop(stop_sender);
+        assert!(wait_until(|| runtime.loops() == 0));
+    }
+
+    #[test]
+    fn worker_survives_panicking_task() {
+        let runtime = Runtime::new(1);
+        runtime.spawn(|| panic!("task panicked on purpose"));
+        assert!(wait_until(|| runtime
+            .inner
+            .queue
+            .lock()
+            .unwrap()
+            .idle_workers
+            == 1));
+
+        let (sender, receiver) = channel();
+        runtime.spawn(move || sender.send(current_thread_name()).unwrap());
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(1)).unwrap(),
+            Some(String::from("holochain-runtime-0"))
+        );
+    }
+}
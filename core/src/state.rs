@@ -5,9 +5,12 @@ use crate::{
         state::{AgentState, AgentStateSnapshot},
     },
     context::Context,
-    dht::dht_store::DhtStore,
-    network::state::NetworkState,
-    nucleus::state::NucleusState,
+    dht::{dht_store::DhtStore, pending_validation::WaitingValidation},
+    network::{
+        published::load_published,
+        state::{NetworkState, WaitingRequest},
+    },
+    nucleus::state::{NucleusState, RunningZomeCall},
 };
 use holochain_core_types::{
     cas::storage::ContentAddressableStorage,
@@ -19,8 +22,26 @@ use std::{
     collections::HashSet,
     convert::TryInto,
     sync::{Arc, RwLock},
+    time::Instant,
 };
 
+/// What an instance is waiting on: its running zome calls, the validations that wait
+/// for their dependencies and the requests to the network that wait for their answer
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct PendingWork {
+    pub zome_calls: Vec<RunningZomeCall>,
+    pub validations: Vec<WaitingValidation>,
+    pub network_requests: Vec<WaitingRequest>,
+}
+
+impl PendingWork {
+    pub fn is_empty(&self) -> bool {
+        self.zome_calls.is_empty()
+            && self.validations.is_empty()
+            && self.network_requests.is_empty()
+    }
+}
+
 /// The Store of the Holochain instance Object, according to Redux pattern.
 /// It's composed of all sub-module's state slices.
 /// To plug in a new module, its state slice needs to be added here.
@@ -96,6 +117,17 @@ impl State {
     }
 
     pub fn reduce(&self, context: Arc<Context>, action_wrapper: ActionWrapper) -> Self {
+        #[cfg(any(test, feature = "test_fixtures"))]
+        {
+            match action_wrapper.action() {
+                crate::action::Action::Crash(message) => panic!("{}", message),
+                crate::action::Action::Hang(millis) => {
+                    std::thread::sleep(std::time::Duration::from_millis(*millis))
+                }
+                _ => (),
+            }
+        }
+
         let mut new_state = State {
             nucleus: crate::nucleus::reduce(
                 Arc::clone(&context),
@@ -120,6 +152,9 @@ impl State {
             history: self.history.clone(),
         };
 
+        #[cfg(any(debug_assertions, feature = "invariant-checks"))]
+        crate::invariants::enforce(&context, self, &new_state, &action_wrapper);
+
         new_state.history.insert(action_wrapper);
         new_state
     }
@@ -140,18 +175,49 @@ impl State {
         Arc::clone(&self.network)
     }
 
+    /// Number of network operations this instance is still waiting for: requests without an
+    /// answer, open direct messages and validations waiting for their dependencies.
+    /// Zero means the instance is quiescent, unless the network has messages for it in flight.
+    pub fn pending_network_ops(&self) -> usize {
+        let network = self.network();
+        let pending_validations = self
+            .dht()
+            .pending_validations()
+            .map(|pending_validations| pending_validations.len())
+            .unwrap_or(0);
+        network.get_entry_with_meta_results.waiting_count()
+            + network.get_validation_package_results.waiting_count()
+            + network.direct_message_connections.len()
+            + pending_validations
+    }
+
+    /// Snapshot of what this instance is waiting on, see PendingWork
+    pub fn pending_work(&self, now: Instant) -> HcResult<PendingWork> {
+        Ok(PendingWork {
+            zome_calls: self.nucleus().running_zome_calls(now),
+            validations: self
+                .dht()
+                .pending_validations()?
+                .into_iter()
+                .map(WaitingValidation::from)
+                .collect(),
+            network_requests: self.network().waiting_requests(now),
+        })
+    }
+
     pub fn try_from_agent_snapshot(
         context: Arc<Context>,
         snapshot: AgentStateSnapshot,
     ) -> HcResult<State> {
-        let agent_state = AgentState::new_with_top_chain_header(
-            ChainStore::new(context.file_storage.clone()),
-            snapshot.top_chain_header().clone(),
-        );
-        Ok(State::new_with_agent(
-            context.clone(),
-            Arc::new(agent_state),
-        ))
+        let chain_store = ChainStore::new(context.file_storage.clone());
+        let top_chain_header = snapshot.top_chain_header().clone();
+        chain_store.verify_sequence(&Some(top_chain_header.clone()))?;
+        let agent_state = AgentState::new_with_top_chain_header(chain_store, top_chain_header);
+        let mut state = State::new_with_agent(context.clone(), Arc::new(agent_state));
+        let mut network_state = NetworkState::new();
+        network_state.published = load_published(&context)?;
+        state.network = Arc::new(network_state);
+        Ok(state)
     }
 }
 
@@ -0,0 +1,91 @@
+//! Agents and DNAs that are the same in every run, on every machine, for tests and demos
+//! that write down their addresses and hashes.
+//! Their keys follow from names everyone knows, so they keep nothing secret and must never
+//! be used outside of tests. That's why this module only gets built for the tests of this
+//! crate and with the `test_fixtures` feature, and containers refuse to run test agents
+//! without it.
+
+use crate::agent::keys::{insecure_buf, EncryptionKeys, SigningKeys};
+use holochain_core_types::{agent::AgentId, dna::Dna};
+use multihash::{encode, Hash};
+
+/// The UUID of the DNAs `test_dna()` builds
+pub const TEST_DNA_UUID: &str = "00000000-0000-0000-0000-000000000000";
+
+/// An agent whose identity and keys follow from its seed alone
+pub struct TestAgent {
+    pub agent_id: AgentId,
+    pub keys: EncryptionKeys,
+    pub signing_keys: SigningKeys,
+}
+
+/// The agent with the given seed as its nick.
+/// `test_agent("alice")` always has the same keys and therefore the same address.
+pub fn test_agent(seed: &str) -> TestAgent {
+    let mut signing_keys = SigningKeys::from_seed(&mut insecure_buf(&derive_key(seed, "signing")));
+    let mut keys = EncryptionKeys::from_seed(&mut insecure_buf(&derive_key(seed, "encryption")));
+    TestAgent {
+        agent_id: keys.agent_id(seed, &signing_keys.public_key()),
+        keys,
+        signing_keys,
+    }
+}
+
+/// A minimal valid DNA with the given name and no zomes.
+/// It has `TEST_DNA_UUID` instead of a random one, so its hash is the same in every run.
+pub fn test_dna(name: &str) -> Dna {
+    Dna {
+        name: name.to_string(),
+        uuid: TEST_DNA_UUID.to_string(),
+        ..Dna::new()
+    }
+}
+
+/// The SHA2-256 of the seed and what the key is for, so that the keys of an agent
+/// don't follow from each other
+fn derive_key(seed: &str, purpose: &str) -> [u8; 32] {
+    let input = format!("holochain test agent {} key for {}", purpose, seed);
+    let multihash = encode(Hash::SHA2256, input.as_bytes()).expect("could not hash the seed");
+    let mut key = [0; 32];
+    key.copy_from_slice(&multihash[2..]);
+    key
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::agent::keys::encrypt_for;
+    use holochain_core_types::{
+        cas::content::{Address, AddressableContent},
+        entry::addressing::dna_hash,
+        hash::HashString,
+    };
+
+    #[test]
+    fn test_agents_and_dnas_are_pinned() {
+        assert_eq!(
+            test_agent("alice").agent_id.address(),
+            Address::from("1gfNhesNtbf0C2o3iZ0Fdqg8AsNB5sw3ak5UQxNZ3i6xarYXLkl3IoGmW9t602rZXSo_Ni22TlK6yizKfnyadUMM")
+        );
+        assert_eq!(
+            test_agent("bob").agent_id.address(),
+            Address::from("jEonNvpO71JgeEd8neIFuYk-A0zB1HAJ4OGgaXfatoOnxh9Ibhz4d6cq2vX2nK6D_SycP4_jvhRqJG8mDgAEHx5s")
+        );
+        assert_eq!(
+            dna_hash(&test_dna("fixture")),
+            HashString::from("QmNkQvgwckT7TCcBkKqEQ9qRY3BN45nmeKhCvgmTP58buL")
+        );
+    }
+
+    #[test]
+    fn test_agents_decrypt_what_gets_encrypted_for_their_address() {
+        let alice = test_agent("alice");
+        assert_eq!(alice.agent_id.nick, "alice");
+        let encrypted = encrypt_for(&alice.agent_id.address(), b"payload").unwrap();
+        assert_eq!(
+            test_agent("alice").keys.decrypt(&encrypted),
+            Ok(b"payload".to_vec())
+        );
+        assert!(test_agent("bob").keys.decrypt(&encrypted).is_err());
+    }
+}
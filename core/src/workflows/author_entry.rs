@@ -1,25 +1,53 @@
 use crate::{
-    agent::actions::commit::commit_entry,
+    agent::actions::commit::commit_entry_with_options,
     context::Context,
-    network::actions::publish::publish,
+    network::actions::{await_publish::await_publish, publish::publish},
     nucleus::actions::{
         build_validation_package::build_validation_package, validate::validate_entry,
     },
 };
 
 use holochain_core_types::{
-    cas::content::{Address, AddressableContent},
+    cas::content::Address,
     entry::Entry,
     error::HolochainError,
     validation::{EntryAction, EntryLifecycle, ValidationData},
 };
+use holochain_wasm_utils::api_serialization::{CommitEntryResult, CommitOptions, PublishMode};
 use std::sync::Arc;
 
 pub async fn author_entry<'a>(
     entry: &'a Entry,
     maybe_crud_link: Option<Address>,
     context: &'a Arc<Context>,
-) -> Result<Address, HolochainError> {
+) -> Result<CommitEntryResult, HolochainError> {
+    await!(author_entry_with_options(
+        entry,
+        maybe_crud_link,
+        CommitOptions::default(),
+        context
+    ))
+}
+
+/// Authors the entry like author_entry(), committing it with the given options.
+/// With PublishMode::Block, waits for the holders for as long as the context's publish timeout,
+/// which fails before the network is up.
+/// Other entries authored before the network is up, i.e. during genesis, get published once
+/// it is, without waiting for their holders.
+pub async fn author_entry_with_options<'a>(
+    entry: &'a Entry,
+    maybe_crud_link: Option<Address>,
+    options: CommitOptions,
+    context: &'a Arc<Context>,
+) -> Result<CommitEntryResult, HolochainError> {
+    if let PublishMode::Block { .. } = options.publish {
+        if !network_initialized(context) {
+            return Err(HolochainError::ErrorGeneric(
+                "Can't wait for the holders of an entry before the network is initialized"
+                    .to_string(),
+            ));
+        }
+    }
     // 1. Build the context needed for validation of the entry
     let validation_package = await!(build_validation_package(&entry, &context))?;
     let validation_data = ValidationData {
@@ -31,17 +59,59 @@ pub async fn author_entry<'a>(
     // 2. Validate the entry
     await!(validate_entry(entry.clone(), validation_data, &context))?;
     // 3. Commit the entry
-    await!(commit_entry(entry.clone(), maybe_crud_link, &context))?;
+    let publish_mode = options.publish.clone();
+    let commit_result = await!(commit_entry_with_options(
+        entry.clone(),
+        maybe_crud_link,
+        options,
+        &context
+    ))?;
     // 4. Publish the valid entry to DHT. This will call Hold to itself
-    await!(publish(entry.address(), &context))
+    match publish_mode {
+        PublishMode::Skip => (),
+        PublishMode::Block { min_holders } => {
+            await!(publish(commit_result.address.clone(), &context))?;
+            await!(await_publish(
+                &commit_result.address,
+                min_holders,
+                context.publish_timeout(),
+                &context
+            ))?;
+        }
+        PublishMode::Background => {
+            await!(publish(commit_result.address.clone(), &context))?;
+        }
+    }
+    Ok(commit_result)
+}
+
+fn network_initialized(context: &Arc<Context>) -> bool {
+    context
+        .state()
+        .map(|state| state.network().initialized().is_ok())
+        .unwrap_or(false)
 }
 
 #[cfg(test)]
 pub mod tests {
-    use super::author_entry;
-    use crate::nucleus::actions::tests::*;
+    use super::*;
+    use crate::{
+        agent::actions::commit::commit_entry,
+        instance::{tests::test_context, Instance},
+        network::{
+            actions::{initialize_network::initialize_network, publish::republish},
+            state::PUBLISH_COALESCE_MILLIS,
+        },
+        nucleus::actions::{
+            get_entry::get_entry_with_meta, initialize::initialize_application, tests::*,
+        },
+    };
     use futures::executor::block_on;
-    use holochain_core_types::entry::test_entry;
+    use holochain_core_types::{
+        cas::content::AddressableContent,
+        entry::{entry_type::test_app_entry_type, test_entry},
+        json::{JsonString, RawString},
+    };
     use std::{thread, time};
 
     #[test]
@@ -51,9 +121,21 @@ pub mod tests {
         let (_instance1, context1) = instance_by_name("jill", dna.clone());
         let (_instance2, context2) = instance_by_name("jack", dna);
 
-        let entry_address = block_on(author_entry(&test_entry(), None, &context1));
+        let commit_result = block_on(author_entry(&test_entry(), None, &context1))
+            .expect("Could not author entry");
+        let entry_address = commit_result.address;
+        assert_eq!(entry_address, test_entry().address());
+
+        let header = context1
+            .state()
+            .unwrap()
+            .agent()
+            .chain()
+            .get_header(&commit_result.header_address)
+            .expect("could not fetch header")
+            .expect("header of authored entry should be in the chain");
+        assert_eq!(header.entry_address(), &entry_address);
 
-        let entry_address = entry_address.unwrap();
         thread::sleep(time::Duration::from_millis(1000));
 
         let state = &context2.state().unwrap();
@@ -71,4 +153,217 @@ pub mod tests {
             "{\"App\":[\"testEntryType\",\"\\\"test entry value\\\"\"]}".to_string(),
         );
     }
+    #[test]
+    /// test that what gets authored before the network is up, like during genesis, gets
+    /// published once it is, so that nodes started after genesis hold it
+    fn test_entries_authored_before_network_init_get_published_after_it() {
+        let mut dna = test_dna();
+        dna.uuid = String::from("test_entries_authored_before_network_init");
+        let context1 = test_context("jill");
+        let mut instance1 = Instance::new(context1.clone());
+        instance1.start_action_loop(context1.clone());
+        let context1 = instance1.initialize_context(context1);
+        block_on(initialize_application(dna.clone(), &context1)).expect("Could not initialize");
+
+        let commit_result = block_on(author_entry(&test_entry(), None, &context1))
+            .expect("Entries authored before the network is up should not fail to publish");
+        let status = context1.state().unwrap().network().status();
+        assert_eq!(status.deferred_publishes, 1);
+        assert_eq!(status.published_deferred, 0);
+
+        // there are no holders to wait for yet
+        let options = CommitOptions {
+            publish: PublishMode::Block { min_holders: 1 },
+            ..Default::default()
+        };
+        let result = block_on(author_entry_with_options(
+            &test_entry(),
+            None,
+            options,
+            &context1,
+        ));
+        assert!(result.is_err(), "result = {:?}", result);
+        let status = context1.state().unwrap().network().status();
+        assert_eq!(status.deferred_publishes, 1);
+
+        let (_instance2, context2) = instance_by_name("jack", dna);
+        block_on(initialize_network(&context1)).expect("Could not initialize network");
+        let mut held = None;
+        for _ in 0..50 {
+            held = get_entry_with_meta(&context2, commit_result.address.clone())
+                .expect("Could not get entry");
+            if held.is_some() {
+                break;
+            }
+            thread::sleep(time::Duration::from_millis(100));
+        }
+        assert_eq!(
+            held.expect("jack should hold the entry once jill's network is up")
+                .entry,
+            test_entry()
+        );
+        let status = context1.state().unwrap().network().status();
+        assert_eq!(status.deferred_publishes, 0);
+        assert_eq!(status.published_deferred, 1);
+    }
+
+    #[test]
+    /// test that a commit with PublishMode::Block only returns once the holders hold the entry
+    fn test_commit_blocking_on_publish() {
+        let mut dna = test_dna();
+        dna.uuid = String::from("test_commit_blocking_on_publish");
+        let (_instance1, context1) = instance_by_name("jill", dna.clone());
+        let (_instance2, context2) = instance_by_name("jack", dna);
+
+        let options = CommitOptions {
+            publish: PublishMode::Block { min_holders: 2 },
+            ..Default::default()
+        };
+        let commit_result = block_on(author_entry_with_options(
+            &test_entry(),
+            None,
+            options,
+            &context1,
+        ))
+        .expect("Could not author entry");
+
+        assert_eq!(
+            context1
+                .state()
+                .unwrap()
+                .network()
+                .holders_count(&commit_result.address),
+            2
+        );
+        let held = get_entry_with_meta(&context2, commit_result.address.clone())
+            .expect("Could not get entry")
+            .expect("jack should hold the entry right away");
+        assert_eq!(held.entry, test_entry());
+    }
+
+    #[test]
+    /// test that waiting for holders of an entry they reject times out
+    fn test_await_publish_of_rejected_entry_times_out() {
+        let mut dna = test_dna();
+        dna.uuid = String::from("test_await_publish_of_rejected_entry_times_out");
+        let (_instance1, context1) = instance_by_name("jill", dna.clone());
+        let (_instance2, _context2) = instance_by_name("jack", dna);
+
+        // committing directly skips the validation that would keep jill from authoring it
+        let invalid_entry = Entry::App(
+            test_app_entry_type(),
+            JsonString::from(RawString::from("FAIL")),
+        );
+        let address = block_on(commit_entry(invalid_entry, None, &context1))
+            .expect("Could not commit entry")
+            .address;
+        block_on(publish(address.clone(), &context1)).expect("Could not publish entry");
+
+        assert_eq!(
+            block_on(await_publish(
+                &address,
+                1,
+                time::Duration::from_millis(2000),
+                &context1
+            )),
+            Err(HolochainError::Timeout)
+        );
+        assert_eq!(
+            context1.state().unwrap().network().holders_count(&address),
+            0
+        );
+    }
+
+    #[test]
+    /// test that the holders that reject an entry tell its author why
+    fn test_author_learns_why_holders_rejected_entry() {
+        let mut dna = test_dna();
+        dna.uuid = String::from("test_author_learns_why_holders_rejected_entry");
+        let (_instance1, context1) = instance_by_name("jill", dna.clone());
+        let (_instance2, context2) = instance_by_name("jack", dna);
+        let jack_id = context2.state().unwrap().network().agent_id.clone();
+        let jack = Address::from(jack_id.expect("jack should be on the network"));
+
+        // committing directly skips the validation that would keep jill from authoring it
+        let invalid_entry = Entry::App(
+            test_app_entry_type(),
+            JsonString::from(RawString::from("FAIL")),
+        );
+        let address = block_on(commit_entry(invalid_entry, None, &context1))
+            .expect("Could not commit entry")
+            .address;
+        block_on(publish(address.clone(), &context1)).expect("Could not publish entry");
+
+        let mut rejections = Vec::new();
+        for _ in 0..20 {
+            rejections = context1.state().unwrap().network().rejections(&address);
+            if rejections.iter().any(|(holder, _)| holder == &jack) {
+                break;
+            }
+            thread::sleep(time::Duration::from_millis(100));
+        }
+        let (_, reason) = rejections
+            .into_iter()
+            .find(|(holder, _)| holder == &jack)
+            .expect("jack should have told jill why the entry got rejected");
+        assert!(reason.contains("FAIL content is not allowed"));
+        assert_eq!(
+            context1.state().unwrap().network().holders_count(&address),
+            0
+        );
+    }
+
+    #[test]
+    /// test that committing an entry that got published already does not publish it again
+    fn test_committing_same_entry_publishes_once() {
+        let mut dna = test_dna();
+        dna.uuid = String::from("test_committing_same_entry_publishes_once");
+        let (_instance1, context1) = instance_by_name("jill", dna.clone());
+        let (_instance2, context2) = instance_by_name("jack", dna);
+        let jill_id = context1
+            .state()
+            .unwrap()
+            .network()
+            .agent_id
+            .clone()
+            .unwrap();
+        let messages_from_jill = || {
+            context2
+                .state()
+                .unwrap()
+                .network()
+                .status()
+                .traffic
+                .get(&jill_id)
+                .map(|stats| stats.messages_in)
+                .unwrap_or(0)
+        };
+
+        let anchor = test_entry();
+        block_on(author_entry(&anchor, None, &context1)).expect("Could not author entry");
+        thread::sleep(time::Duration::from_millis(1000));
+        let published_once = messages_from_jill();
+        assert!(published_once > 0);
+
+        for _ in 1..50 {
+            block_on(author_entry(&anchor, None, &context1)).expect("Could not author entry");
+        }
+        thread::sleep(time::Duration::from_millis(1000));
+        assert_eq!(messages_from_jill(), published_once);
+        assert_eq!(
+            context1
+                .state()
+                .unwrap()
+                .network()
+                .status()
+                .skipped_publishes,
+            49
+        );
+
+        // republishing goes out nonetheless, once it is not coalesced with the last publish
+        thread::sleep(time::Duration::from_millis(PUBLISH_COALESCE_MILLIS));
+        block_on(republish(anchor.address(), &context1)).expect("Could not republish entry");
+        thread::sleep(time::Duration::from_millis(1000));
+        assert!(messages_from_jill() > published_once);
+    }
 }
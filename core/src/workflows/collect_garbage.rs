@@ -0,0 +1,534 @@
+use crate::{
+    agent::state::AGENT_SNAPSHOT_ADDRESS,
+    context::Context,
+    dht::dht_store::{
+        DEQUEUED_VALIDATION_NAME, HELD_SINCE_NAME, PENDING_VALIDATION_NAME, PRUNABLE_SINCE_NAME,
+        PRUNED_NAME,
+    },
+};
+use holochain_core_types::{
+    cas::{
+        content::{Address, AddressableContent},
+        storage::GcReport,
+    },
+    crud_status::{create_crud_status_eav, CrudStatus, STATUS_NAME},
+    eav::EntityAttributeValue,
+    entry::Entry,
+    error::HolochainError,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub(crate) fn seconds_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Marks the source chain entries of prunable types that are past their retention as pruned,
+/// see `Dna::prune_retention()`, so that the next sweep removes their content while their
+/// headers stay. Chain headers don't carry the time of their commit yet, so the retention of
+/// an entry counts from the first time this came across it.
+/// Returns the addresses of the entries that got marked.
+pub fn mark_pruned_entries(context: &Arc<Context>) -> Result<Vec<Address>, HolochainError> {
+    let (chain, top_chain_header, dht, maybe_dna) = {
+        let state = context
+            .state()
+            .ok_or_else(|| HolochainError::new("Context has no state"))?;
+        let agent = state.agent();
+        (
+            agent.chain(),
+            agent.top_chain_header(),
+            state.dht(),
+            state.nucleus().dna(),
+        )
+    };
+    let dna = match maybe_dna {
+        Some(dna) => dna,
+        None => return Ok(Vec::new()),
+    };
+
+    let meta_storage = dht.meta_storage();
+    let mut meta_storage = meta_storage.write()?;
+    let pruned: HashSet<Address> = meta_storage
+        .fetch_eav(None, Some(PRUNED_NAME.to_string()), None)?
+        .iter()
+        .map(|eav| eav.entity())
+        .collect();
+    let prunable_since: HashMap<Address, u64> = meta_storage
+        .fetch_eav(None, Some(PRUNABLE_SINCE_NAME.to_string()), None)?
+        .iter()
+        .filter_map(|eav| {
+            String::from(eav.value())
+                .parse()
+                .ok()
+                .map(|since| (eav.entity(), since))
+        })
+        .collect();
+
+    let now = seconds_since_epoch(context.now());
+    let mut marked = Vec::new();
+    for chain_header in chain.iter(&top_chain_header) {
+        let entry_address = chain_header.entry_address().clone();
+        if pruned.contains(&entry_address) || marked.contains(&entry_address) {
+            continue;
+        }
+        let retention = match dna.prune_retention(chain_header.entry_type()) {
+            Some(retention) => retention,
+            None => continue,
+        };
+        let due = match prunable_since.get(&entry_address) {
+            Some(since) => now.saturating_sub(*since) >= retention,
+            None => {
+                meta_storage.add_eav(&EntityAttributeValue::new(
+                    &entry_address,
+                    &PRUNABLE_SINCE_NAME.to_string(),
+                    &Address::from(now.to_string()),
+                ))?;
+                retention == 0
+            }
+        };
+        if due {
+            meta_storage.add_eav(&EntityAttributeValue::new(
+                &entry_address,
+                &PRUNED_NAME.to_string(),
+                &chain_header.address(),
+            ))?;
+            marked.push(entry_address);
+        }
+    }
+    Ok(marked)
+}
+
+/// Marks the held entries of types with a TTL that are older than that as EXPIRED, counting
+/// from when this node started holding them, see `Dna::entry_ttl()`. Gets don't find them
+/// anymore, and the next sweep removes them unless they are on the own source chain.
+/// Entries of the own source chain count from the first time this came across them, since
+/// commits don't record when they got held.
+/// Returns the addresses of the entries that got marked.
+pub fn mark_expired_entries(context: &Arc<Context>) -> Result<Vec<Address>, HolochainError> {
+    let (chain, top_chain_header, dht, maybe_dna) = {
+        let state = context
+            .state()
+            .ok_or_else(|| HolochainError::new("Context has no state"))?;
+        let agent = state.agent();
+        (
+            agent.chain(),
+            agent.top_chain_header(),
+            state.dht(),
+            state.nucleus().dna(),
+        )
+    };
+    let dna = match maybe_dna {
+        Some(dna) => dna,
+        None => return Ok(Vec::new()),
+    };
+
+    let meta_storage = dht.meta_storage();
+    let mut meta_storage = meta_storage.write()?;
+    let expired: HashSet<Address> = meta_storage
+        .fetch_eav(None, Some(STATUS_NAME.to_string()), None)?
+        .iter()
+        .filter(|eav| CrudStatus::from(String::from(eav.value())) == CrudStatus::EXPIRED)
+        .map(|eav| eav.entity())
+        .collect();
+    let mut held_since: HashMap<Address, u64> = HashMap::new();
+    for eav in meta_storage.fetch_eav(None, Some(HELD_SINCE_NAME.to_string()), None)? {
+        if let Ok(since) = String::from(eav.value()).parse::<u64>() {
+            let earliest = held_since.entry(eav.entity()).or_insert(since);
+            *earliest = (*earliest).min(since);
+        }
+    }
+
+    let now = seconds_since_epoch(context.now());
+    for chain_header in chain.iter(&top_chain_header) {
+        let entry_address = chain_header.entry_address();
+        if held_since.contains_key(entry_address)
+            || dna.entry_ttl(chain_header.entry_type()).is_none()
+        {
+            continue;
+        }
+        meta_storage.add_eav(&EntityAttributeValue::new(
+            entry_address,
+            &HELD_SINCE_NAME.to_string(),
+            &Address::from(now.to_string()),
+        ))?;
+        held_since.insert(entry_address.clone(), now);
+    }
+    let content_storage = dht.content_storage();
+    let content_storage = content_storage.read()?;
+    let mut marked = Vec::new();
+    for (address, since) in held_since {
+        if expired.contains(&address) {
+            continue;
+        }
+        let entry_type = match content_storage.fetch(&address)? {
+            Some(content) => Entry::try_from_content(&content)?.entry_type(),
+            None => continue,
+        };
+        let ttl = match dna.entry_ttl(&entry_type) {
+            Some(ttl) => ttl,
+            None => continue,
+        };
+        if now.saturating_sub(since) >= ttl {
+            meta_storage.add_eav(&create_crud_status_eav(&address, CrudStatus::EXPIRED))?;
+            marked.push(address);
+        }
+    }
+    Ok(marked)
+}
+
+/// Marks everything that is in use: the source chain with its headers and entries,
+/// the snapshot of its top, and every address the meta storage refers to.
+/// EAVs of rejected or expired entries and of validations that are done don't count, so
+/// that these entries, the headers they came with and finished validations get collected.
+/// Neither do entries that got pruned, only their headers.
+/// Only takes read locks, so commits can go on while it runs.
+fn reachable_addresses(context: &Arc<Context>) -> Result<HashSet<Address>, HolochainError> {
+    let (chain, top_chain_header, dht) = {
+        let state = context
+            .state()
+            .ok_or_else(|| HolochainError::new("Context has no state"))?;
+        let agent = state.agent();
+        (agent.chain(), agent.top_chain_header(), state.dht())
+    };
+
+    let eavs = dht.meta_storage().read()?.fetch_eav(None, None, None)?;
+    let pruned: HashSet<Address> = eavs
+        .iter()
+        .filter(|eav| eav.attribute() == PRUNED_NAME)
+        .map(|eav| eav.entity())
+        .collect();
+
+    let mut reachable = HashSet::new();
+    reachable.insert(Address::from(AGENT_SNAPSHOT_ADDRESS));
+    for chain_header in chain.iter(&top_chain_header) {
+        reachable.insert(chain_header.address());
+        reachable.insert(chain_header.entry_address().clone());
+    }
+
+    let rejected_or_expired: HashSet<Address> = eavs
+        .iter()
+        .filter(|eav| {
+            eav.attribute() == STATUS_NAME
+                && (CrudStatus::REJECTED | CrudStatus::EXPIRED)
+                    .contains(CrudStatus::from(String::from(eav.value())))
+        })
+        .map(|eav| eav.entity())
+        .collect();
+    let dequeued: HashSet<(Address, Address)> = eavs
+        .iter()
+        .filter(|eav| eav.attribute() == DEQUEUED_VALIDATION_NAME)
+        .map(|eav| (eav.entity(), eav.value()))
+        .collect();
+    for eav in eavs {
+        if rejected_or_expired.contains(&eav.entity()) {
+            continue;
+        }
+        if eav.attribute() == PENDING_VALIDATION_NAME
+            && dequeued.contains(&(eav.entity(), eav.value()))
+        {
+            continue;
+        }
+        reachable.insert(eav.entity());
+        reachable.insert(eav.value());
+    }
+    for entry_address in pruned {
+        reachable.remove(&entry_address);
+    }
+    Ok(reachable)
+}
+
+/// Removes the candidates that are not reachable from the storage, holding the same
+/// write lock commits take. Content that was not among the candidates, i.e. got added
+/// by commits while marking, is kept.
+fn sweep(
+    context: &Arc<Context>,
+    candidates: &HashSet<Address>,
+    reachable: HashSet<Address>,
+) -> Result<GcReport, HolochainError> {
+    let mut storage = context.file_storage.write()?;
+    let mut roots = reachable;
+    roots.extend(storage.addresses()?.difference(candidates).cloned());
+    storage.gc(&roots)
+}
+
+/// Collects garbage in the content storage of the instance: content that neither the
+/// source chain nor the local DHT shard refers to anymore, like entries that got rejected
+/// or that were stored for a commit that never made it onto the chain, and the content of
+/// prunable entries past their retention, see `mark_pruned_entries()`, as well as held
+/// entries past the TTL of their type, see `mark_expired_entries()`.
+pub fn collect_garbage(context: &Arc<Context>) -> Result<GcReport, HolochainError> {
+    mark_pruned_entries(context)?;
+    mark_expired_entries(context)?;
+    let candidates = context.file_storage.read()?.addresses()?;
+    let reachable = reachable_addresses(context)?;
+    sweep(context, &candidates, reachable)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        agent::actions::commit::commit_entry,
+        dht::{
+            actions::{hold::hold_entry, reject_entry::reject_entry},
+            dht_store::RejectionReason,
+        },
+        instance::tests::{test_context, test_instance_with_context},
+        nucleus::actions::tests::*,
+        workflows::get_entry_history::get_entry_history_workflow,
+    };
+    use futures::executor::block_on;
+    use holochain_core_types::{
+        chain_header::ChainHeader,
+        dna::{
+            zome::entry_types::{EntryTypeDef, Sharing},
+            Dna,
+        },
+        entry::{
+            entry_type::{test_app_entry_type, AppEntryType},
+            test_entry, Entry,
+        },
+        json::{JsonString, RawString},
+        time::ManualTimeSource,
+    };
+    use holochain_wasm_utils::api_serialization::get_entry::{
+        GetEntryArgs, GetEntryOptions, GetResultStatus,
+    };
+    use std::time::Duration;
+
+    fn app_entry(content: &str) -> Entry {
+        Entry::App(
+            test_app_entry_type(),
+            JsonString::from(RawString::from(content)),
+        )
+    }
+
+    fn is_stored(context: &Arc<Context>, entry: &Entry) -> bool {
+        is_stored_address(context, &entry.address())
+    }
+
+    fn is_stored_address(context: &Arc<Context>, address: &Address) -> bool {
+        context
+            .file_storage
+            .read()
+            .unwrap()
+            .contains(address)
+            .unwrap()
+    }
+
+    #[test]
+    fn gc_removes_exactly_what_is_not_referenced() {
+        let (_instance, context) = instance_by_name("jill", test_dna());
+        let committed_entry = test_entry();
+        let held_entry = app_entry("held");
+        let rejected_entry = app_entry("rejected");
+        let rejected_own_entry = app_entry("rejected but committed");
+        let discarded_entry = app_entry("discarded");
+
+        block_on(commit_entry(committed_entry.clone(), None, &context)).unwrap();
+        block_on(commit_entry(rejected_own_entry.clone(), None, &context)).unwrap();
+        block_on(hold_entry(&held_entry, &context)).unwrap();
+        block_on(hold_entry(&rejected_entry, &context)).unwrap();
+        for entry in vec![&rejected_entry, &rejected_own_entry] {
+            block_on(reject_entry(
+                &entry.address(),
+                RejectionReason::Invalid,
+                &context,
+            ))
+            .unwrap();
+        }
+        context
+            .file_storage
+            .write()
+            .unwrap()
+            .add(&discarded_entry)
+            .unwrap();
+
+        let report = collect_garbage(&context).unwrap();
+        assert_eq!(report.removed, 2);
+        assert_eq!(
+            report.bytes_reclaimed,
+            (rejected_entry.content().to_string().len()
+                + discarded_entry.content().to_string().len()) as u64
+        );
+        assert!(!is_stored(&context, &rejected_entry));
+        assert!(!is_stored(&context, &discarded_entry));
+        assert!(is_stored(&context, &committed_entry));
+        assert!(is_stored(&context, &held_entry));
+        // rejected by the DHT, but still on our own chain
+        assert!(is_stored(&context, &rejected_own_entry));
+        let top_chain_header = context.state().unwrap().agent().top_chain_header();
+        assert!(context
+            .file_storage
+            .read()
+            .unwrap()
+            .contains(&top_chain_header.unwrap().address())
+            .unwrap());
+
+        let report = collect_garbage(&context).unwrap();
+        assert_eq!(report.removed, 0);
+    }
+
+    #[test]
+    fn content_added_while_marking_is_kept() {
+        let (_instance, context) = instance_by_name("jill", test_dna());
+        let candidates = context.file_storage.read().unwrap().addresses().unwrap();
+        let reachable = reachable_addresses(&context).unwrap();
+
+        let committed_entry = app_entry("committed while marking");
+        block_on(commit_entry(committed_entry.clone(), None, &context)).unwrap();
+
+        let report = sweep(&context, &candidates, reachable).unwrap();
+        assert_eq!(report.removed, 0);
+        assert!(is_stored(&context, &committed_entry));
+    }
+
+    /// The test DNA with the private entry types "drafts", prunable right away,
+    /// and "presence", prunable after an hour
+    fn prunable_dna() -> Dna {
+        let mut dna = test_dna();
+        for (name, retention) in vec![("drafts", None), ("presence", Some(3600))] {
+            let mut entry_type_def = EntryTypeDef::new();
+            entry_type_def.sharing = Sharing::Private;
+            entry_type_def.prunable = true;
+            entry_type_def.retention = retention;
+            dna.zomes
+                .get_mut("test_zome")
+                .unwrap()
+                .entry_types
+                .insert(name.into(), entry_type_def);
+        }
+        dna
+    }
+
+    fn chain_headers(context: &Arc<Context>) -> Vec<ChainHeader> {
+        let agent = context.state().unwrap().agent();
+        agent.chain().iter(&agent.top_chain_header()).collect()
+    }
+
+    #[test]
+    fn gc_prunes_prunable_entries_past_their_retention() {
+        let (_instance, context) = instance_by_name("jill", prunable_dna());
+        let drafts: Vec<Entry> = (0..3)
+            .map(|index| {
+                Entry::App(
+                    AppEntryType::from("drafts"),
+                    JsonString::from(RawString::from(format!("draft {}", index))),
+                )
+            })
+            .collect();
+        let presence = Entry::App(
+            AppEntryType::from("presence"),
+            JsonString::from(RawString::from("here")),
+        );
+        let kept_entry = app_entry("kept");
+        for entry in drafts.iter().chain(vec![&presence, &kept_entry]) {
+            block_on(commit_entry(entry.clone(), None, &context)).unwrap();
+        }
+        let headers = chain_headers(&context);
+        let stored_before = context.file_storage.read().unwrap().addresses().unwrap();
+
+        let report = collect_garbage(&context).unwrap();
+        assert_eq!(report.removed, 3);
+        assert_eq!(
+            report.bytes_reclaimed,
+            drafts
+                .iter()
+                .map(|draft| draft.content().to_string().len() as u64)
+                .sum::<u64>()
+        );
+        let stored_after = context.file_storage.read().unwrap().addresses().unwrap();
+        assert_eq!(stored_after.len(), stored_before.len() - 3);
+        let dht = context.state().unwrap().dht();
+        for draft in drafts.iter() {
+            assert!(!is_stored(&context, draft));
+            assert!(dht.is_pruned(&draft.address()).unwrap());
+        }
+        // within its retention
+        assert!(is_stored(&context, &presence));
+        assert!(!dht.is_pruned(&presence.address()).unwrap());
+        assert!(is_stored(&context, &kept_entry));
+
+        // the headers are all still there and still link up
+        assert_eq!(chain_headers(&context), headers);
+        for pair in headers.windows(2) {
+            assert_eq!(pair[0].link(), Some(pair[1].address()));
+            assert!(is_stored_address(&context, &pair[0].address()));
+        }
+        assert_eq!(headers.last().unwrap().link(), None);
+
+        let report = collect_garbage(&context).unwrap();
+        assert_eq!(report.removed, 0);
+        assert!(mark_pruned_entries(&context).unwrap().is_empty());
+    }
+
+    /// The test DNA with the entry type "presence", whose entries live for a second
+    fn ttl_dna() -> Dna {
+        let mut dna = test_dna();
+        let mut presence = EntryTypeDef::new();
+        presence.ttl_seconds = Some(1);
+        dna.zomes
+            .get_mut("test_zome")
+            .unwrap()
+            .entry_types
+            .insert("presence".into(), presence);
+        dna
+    }
+
+    fn get_status(context: &Arc<Context>, entry: &Entry) -> GetResultStatus {
+        let args = GetEntryArgs {
+            address: entry.address(),
+            options: GetEntryOptions::default(),
+            resolve_latest: true,
+        };
+        block_on(get_entry_history_workflow(context, &args))
+            .unwrap()
+            .status
+    }
+
+    #[test]
+    fn gc_expires_entries_past_their_ttl() {
+        let clock = Arc::new(ManualTimeSource::new(SystemTime::now()));
+        let mut context = (*test_context("jill")).clone();
+        context.set_time_source(clock.clone());
+        let (_instance, context) = test_instance_with_context(ttl_dna(), Arc::new(context))
+            .expect("Could not create test instance");
+        let presence = |content: &str| {
+            Entry::App(
+                AppEntryType::from("presence"),
+                JsonString::from(RawString::from(content)),
+            )
+        };
+        let held_presence = presence("held");
+        let own_presence = presence("own");
+        let kept_entry = app_entry("kept");
+        block_on(hold_entry(&held_presence, &context)).unwrap();
+        block_on(hold_entry(&kept_entry, &context)).unwrap();
+        block_on(commit_entry(own_presence.clone(), None, &context)).unwrap();
+
+        collect_garbage(&context).unwrap();
+        assert_eq!(get_status(&context, &held_presence), GetResultStatus::Found);
+        assert!(mark_expired_entries(&context).unwrap().is_empty());
+
+        clock.advance(Duration::from_secs(1));
+        let report = collect_garbage(&context).unwrap();
+        assert_eq!(report.removed, 1);
+        let dht = context.state().unwrap().dht();
+        for entry in vec![&held_presence, &own_presence] {
+            assert!(dht.is_expired(&entry.address()).unwrap());
+            assert_eq!(get_status(&context, entry), GetResultStatus::Expired);
+        }
+        assert!(!is_stored(&context, &held_presence));
+        // still on our own chain
+        assert!(is_stored(&context, &own_presence));
+        assert!(!dht.is_expired(&kept_entry.address()).unwrap());
+        assert_eq!(get_status(&context, &kept_entry), GetResultStatus::Found);
+
+        assert!(mark_expired_entries(&context).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{
+    context::Context, network::actions::publish::publish,
+    workflows::author_entry::author_entry_with_options,
+};
+
+use holochain_core_types::{
+    entry::Entry,
+    error::HolochainError,
+    link::{link_add::LinkAdd, Link, AGENT_ID_LINK_TAG},
+};
+use holochain_wasm_utils::api_serialization::{
+    link_entries::{CommitAndLinkArgs, CommitAndLinkResult},
+    CommitEntryResult, CommitOptions, PublishMode,
+};
+use std::sync::Arc;
+
+/// Commits the entry and then, one right after the other, the links to it from the given
+/// bases, and publishes what got committed once all of it is on the chain.
+/// Fails if the entry can't be committed, in which case none of the links get committed either.
+/// A link that fails, e.g. its validation, doesn't stop the others: the chain is append-only,
+/// so the result tells which of them landed.
+pub async fn commit_and_link<'a>(
+    args: &'a CommitAndLinkArgs,
+    context: &'a Arc<Context>,
+) -> Result<CommitAndLinkResult, HolochainError> {
+    let commit_only = CommitOptions {
+        publish: PublishMode::Skip,
+        ..Default::default()
+    };
+    let entry_result = await!(author_entry_with_options(
+        &args.entry,
+        None,
+        commit_only.clone(),
+        context
+    ))?;
+
+    let mut link_results = Vec::new();
+    for (base, tag) in args.links.iter() {
+        let link_result: Result<CommitEntryResult, HolochainError> = if tag == AGENT_ID_LINK_TAG {
+            // Only core links agents into the DNA's agent directory
+            Err(HolochainError::ValidationFailed(format!(
+                "Link tag {} is reserved",
+                AGENT_ID_LINK_TAG
+            )))
+        } else {
+            let link = Link::new(base, &entry_result.address, tag);
+            let link_entry = Entry::LinkAdd(LinkAdd::from_link(&link));
+            await!(author_entry_with_options(
+                &link_entry,
+                None,
+                commit_only.clone(),
+                context
+            ))
+        };
+        link_results.push(link_result);
+    }
+
+    await!(publish(entry_result.address.clone(), context))?;
+    for link_result in link_results.iter() {
+        if let Ok(link_commit) = link_result {
+            await!(publish(link_commit.address.clone(), context))?;
+        }
+    }
+
+    Ok(CommitAndLinkResult {
+        entry: entry_result,
+        links: link_results,
+    })
+}
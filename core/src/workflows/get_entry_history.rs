@@ -4,9 +4,9 @@ use holochain_core_types::{
     cas::content::Address, crud_status::CrudStatus, entry::EntryWithMeta, error::HolochainError,
 };
 use holochain_wasm_utils::api_serialization::get_entry::{
-    EntryHistory, GetEntryArgs, StatusRequestKind,
+    EntryHistory, GetEntryArgs, GetResultStatus, StatusRequestKind,
 };
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
 /// Get Entry workflow
 pub async fn get_entry_with_meta_workflow<'a>(
@@ -24,6 +24,13 @@ pub async fn get_entry_with_meta_workflow<'a>(
 }
 
 /// Get EntryHistory workflow
+/// Follows the crud-links of updated entries unless the request is for the initial entry
+/// or does not resolve the latest version.
+/// Entries of the local source chain whose content got pruned end the history with
+/// the Pruned status, without asking the network.
+/// So do entries that expired in the local shard, with the Expired status.
+/// Fails if the crud-links form a cycle.
+/// Tells the meta of the entries only if the options ask for it.
 pub async fn get_entry_history_workflow<'a>(
     context: &'a Arc<Context>,
     args: &'a GetEntryArgs,
@@ -31,12 +38,42 @@ pub async fn get_entry_history_workflow<'a>(
     // Setup
     let mut entry_history = EntryHistory::new();
     let mut maybe_address = Some(args.address.clone());
+    let mut visited = HashSet::new();
     // Accumulate entry history in a loop
     while maybe_address.is_some() {
         let address = maybe_address.unwrap();
         maybe_address = None;
+        if !visited.insert(address.clone()) {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "The updates of {} form a cycle at {}",
+                args.address, address
+            )));
+        }
+        let is_pruned = context
+            .state()
+            .map(|state| state.dht().is_pruned(&address))
+            .unwrap_or(Ok(false))?;
+        if is_pruned {
+            entry_history.status = GetResultStatus::Pruned;
+            break;
+        }
+        let is_expired = context
+            .state()
+            .map(|state| state.dht().is_expired(&address))
+            .unwrap_or(Ok(false))?;
+        if is_expired {
+            entry_history.status = GetResultStatus::Expired;
+            break;
+        }
         // Try to get entry
-        let maybe_entry_with_meta = await!(get_entry_with_meta_workflow(context, &address))?;
+        let maybe_entry_with_meta = match await!(get_entry_with_meta_workflow(context, &address)) {
+            // The network did not answer in time, report what we have so far
+            Err(HolochainError::Timeout) => {
+                entry_history.status = GetResultStatus::Timeout;
+                break;
+            }
+            result => result?,
+        };
         // Entry found
         if let Some(entry_with_meta) = maybe_entry_with_meta {
             // Erase history if request is for latest
@@ -49,46 +86,340 @@ pub async fn get_entry_history_workflow<'a>(
             if entry_with_meta.maybe_crud_link.is_some()
                 && entry_with_meta.crud_status != CrudStatus::DELETED
                 && args.options.status_request != StatusRequestKind::Initial
+                && args.resolve_latest
             {
                 maybe_address = Some(entry_with_meta.maybe_crud_link.unwrap());
             }
         }
     }
+    if !args.options.with_meta {
+        entry_history.meta.clear();
+    }
     Ok(entry_history)
 }
 
-//#[cfg(test)]
-//pub mod tests {
-//    use crate::instance::tests::test_context_with_state;
-//    use futures::executor::block_on;
-//    use holochain_core_types::{
-//        cas::content::AddressableContent,
-//        crud_status::{create_crud_status_eav, CrudStatus},
-//        entry::test_entry,
-//    };
-//    use holochain_wasm_utils::api_serialization::get_entry::*;
-//
-//    #[test]
-//    fn can_get_entry_history_workflow() {
-//        let entry = test_entry();
-//        let context = test_context_with_state();
-//        let args = GetEntryArgs {
-//            address: entry.address(),
-//            options: GetEntryOptions {
-//                status_request: StatusRequestKind::Latest,
-//            },
-//        };
-//        let maybe_entry_history = block_on(super::get_entry_history_workflow(&context, &args));
-////        assert_eq!(0, maybe_entry_history.unwrap().entries.len());
-////        let content_storage = &context.state().unwrap().dht().content_storage().clone();
-////        (*content_storage.write().unwrap()).add(&entry).unwrap();
-////        let status_eav = create_crud_status_eav(&entry.address(), CrudStatus::LIVE);
-////        let meta_storage = &context.state().unwrap().dht().meta_storage().clone();
-////        (*meta_storage.write().unwrap())
-////            .add_eav(&status_eav)
-////            .unwrap();
-////        let maybe_entry_history = block_on(super::get_entry_history_workflow(&context, &args));
-////        let entry_history = maybe_entry_history.unwrap();
-////        assert_eq!(&entry, entry_history.entries.iter().next().unwrap());
-//    }
-//}
+#[cfg(test)]
+pub mod tests {
+    use super::get_entry_history_workflow;
+    use crate::{
+        action::{Action, ActionWrapper, NetworkSettings},
+        context::{mock_network_config, Context},
+        dht::dht_store::PRUNED_NAME,
+        instance::{tests::test_context_with_channels, Observer},
+        network::traffic::RateLimit,
+        state::{test_store, State},
+    };
+    use futures::executor::block_on;
+    use holochain_core_types::{
+        cas::content::{Address, AddressableContent},
+        chain_header::test_chain_header,
+        crud_status::{create_crud_link_eav, create_crud_status_eav, CrudStatus},
+        eav::EntityAttributeValue,
+        entry::{test_entry, test_entry_b, test_entry_c, Entry},
+        error::HolochainError,
+        link::{Link, LinkOperation},
+    };
+    use holochain_wasm_utils::api_serialization::get_entry::*;
+    use std::{
+        sync::{
+            mpsc::{channel, sync_channel, Receiver},
+            Arc, RwLock,
+        },
+        thread,
+        time::Duration,
+    };
+
+    fn reduce(context: &Arc<Context>, store: &Arc<RwLock<State>>, action_wrapper: ActionWrapper) {
+        let mut state = store.write().unwrap();
+        *state = state.reduce(context.clone(), action_wrapper);
+    }
+
+    /// Creates a context on the mock network whose dispatched actions are not reduced by an
+    /// instance but handed back to the test through the returned receiver.
+    fn test_context_with_network(
+        agent_name: &str,
+        dna_hash: &str,
+    ) -> (Arc<Context>, Arc<RwLock<State>>, Receiver<ActionWrapper>) {
+        let (action_tx, action_rx) = sync_channel::<ActionWrapper>(100);
+        let (observer_tx, _) = sync_channel::<Observer>(100);
+        let mut context = test_context_with_channels(agent_name, &action_tx, &observer_tx);
+        let store = Arc::new(RwLock::new(test_store(context.clone())));
+        Arc::get_mut(&mut context).unwrap().set_state(store.clone());
+
+        reduce(
+            &context,
+            &store,
+            ActionWrapper::new(Action::InitNetwork(NetworkSettings {
+                config: mock_network_config(),
+                dna_hash: String::from(dna_hash),
+                agent_id: String::from(agent_name),
+                rate_limit: RateLimit::default(),
+            })),
+        );
+        (context, store, action_rx)
+    }
+
+    fn hold_entry_with_status(context: &Arc<Context>, entry: &Entry, crud_status: CrudStatus) {
+        let dht = context.state().unwrap().dht();
+        (*dht.content_storage().write().unwrap())
+            .add(entry)
+            .unwrap();
+        (*dht.meta_storage().write().unwrap())
+            .add_eav(&create_crud_status_eav(&entry.address(), crud_status))
+            .unwrap();
+    }
+
+    fn prune_entry(context: &Arc<Context>, entry: &Entry) {
+        let dht = context.state().unwrap().dht();
+        (*dht.meta_storage().write().unwrap())
+            .add_eav(&EntityAttributeValue::new(
+                &entry.address(),
+                &PRUNED_NAME.to_string(),
+                &test_chain_header().address(),
+            ))
+            .unwrap();
+    }
+
+    fn hold_update(context: &Arc<Context>, old_entry: &Entry, new_entry: &Entry) {
+        let dht = context.state().unwrap().dht();
+        (*dht.meta_storage().write().unwrap())
+            .add_eav(&create_crud_link_eav(
+                &old_entry.address(),
+                &new_entry.address(),
+            ))
+            .unwrap();
+    }
+
+    fn get_args(address: Address, resolve_latest: bool) -> GetEntryArgs {
+        GetEntryArgs {
+            address,
+            options: GetEntryOptions::default(),
+            resolve_latest,
+        }
+    }
+
+    /// Runs get_entry_history_workflow in its own thread and reduces every action it dispatches.
+    /// With inject_timeout set, the network get request gets answered by a timeout right away.
+    /// Checks that no network request is left behind once the workflow is done.
+    fn run_get_entry_history_workflow(
+        context: &Arc<Context>,
+        store: &Arc<RwLock<State>>,
+        action_rx: &Receiver<ActionWrapper>,
+        address: Address,
+        inject_timeout: bool,
+    ) -> EntryHistory {
+        let (result_tx, result_rx) = channel();
+        let workflow_context = context.clone();
+        thread::spawn(move || {
+            let args = get_args(address, true);
+            result_tx
+                .send(block_on(get_entry_history_workflow(&workflow_context, &args)))
+                .unwrap();
+        });
+
+        loop {
+            if let Ok(result) = result_rx.try_recv() {
+                while let Ok(action_wrapper) = action_rx.try_recv() {
+                    reduce(context, store, action_wrapper);
+                }
+                assert!(store
+                    .read()
+                    .unwrap()
+                    .network()
+                    .get_entry_with_meta_results
+                    .is_empty());
+                return result.expect("get_entry_history_workflow failed");
+            }
+            if let Ok(action_wrapper) = action_rx.recv_timeout(Duration::from_millis(10)) {
+                reduce(context, store, action_wrapper.clone());
+                if let Action::GetEntry((_, id)) = action_wrapper.action() {
+                    if inject_timeout {
+                        reduce(
+                            context,
+                            store,
+                            ActionWrapper::new(Action::GetEntryTimeout(id.clone())),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn get_entry_history_found() {
+        let (context, store, action_rx) =
+            test_context_with_network("found_agent", "get_entry_history_found");
+        let entry = test_entry();
+        hold_entry_with_status(&context, &entry, CrudStatus::LIVE);
+
+        let entry_history =
+            run_get_entry_history_workflow(&context, &store, &action_rx, entry.address(), false);
+        assert_eq!(GetResultStatus::Found, entry_history.status);
+        assert_eq!(vec![entry], entry_history.entries);
+    }
+
+    #[test]
+    fn get_entry_history_deleted() {
+        let (context, store, action_rx) =
+            test_context_with_network("deleted_agent", "get_entry_history_deleted");
+        let entry = test_entry();
+        hold_entry_with_status(&context, &entry, CrudStatus::DELETED);
+
+        let entry_history =
+            run_get_entry_history_workflow(&context, &store, &action_rx, entry.address(), false);
+        assert_eq!(GetResultStatus::Deleted, entry_history.status);
+        assert_eq!(vec![CrudStatus::DELETED], entry_history.crud_status);
+    }
+
+    #[test]
+    fn get_entry_history_not_found() {
+        let (context, store, action_rx) =
+            test_context_with_network("not_found_agent", "get_entry_history_not_found");
+
+        let entry_history = run_get_entry_history_workflow(
+            &context,
+            &store,
+            &action_rx,
+            test_entry().address(),
+            false,
+        );
+        assert_eq!(GetResultStatus::NotFound, entry_history.status);
+        assert!(entry_history.entries.is_empty());
+    }
+
+    #[test]
+    fn get_entry_history_pruned() {
+        let (context, store, action_rx) =
+            test_context_with_network("pruned_agent", "get_entry_history_pruned");
+        let entry = test_entry();
+        prune_entry(&context, &entry);
+
+        let entry_history =
+            run_get_entry_history_workflow(&context, &store, &action_rx, entry.address(), false);
+        assert_eq!(GetResultStatus::Pruned, entry_history.status);
+        assert!(entry_history.entries.is_empty());
+    }
+
+    #[test]
+    fn get_entry_history_expired() {
+        let (context, store, action_rx) =
+            test_context_with_network("expired_agent", "get_entry_history_expired");
+        let entry = test_entry();
+        hold_entry_with_status(&context, &entry, CrudStatus::LIVE);
+        hold_entry_with_status(&context, &entry, CrudStatus::EXPIRED);
+
+        let entry_history =
+            run_get_entry_history_workflow(&context, &store, &action_rx, entry.address(), false);
+        assert_eq!(GetResultStatus::Expired, entry_history.status);
+        assert!(entry_history.entries.is_empty());
+    }
+
+    #[test]
+    fn get_entry_history_timeout() {
+        let (context, store, action_rx) =
+            test_context_with_network("timeout_agent", "get_entry_history_timeout");
+
+        let entry_history = run_get_entry_history_workflow(
+            &context,
+            &store,
+            &action_rx,
+            test_entry().address(),
+            true,
+        );
+        assert_eq!(GetResultStatus::Timeout, entry_history.status);
+        assert!(entry_history.entries.is_empty());
+    }
+
+    #[test]
+    fn get_entry_history_follows_updates() {
+        let (context, _store, _action_rx) =
+            test_context_with_network("updates_agent", "get_entry_history_follows_updates");
+        let (initial, updated) = (test_entry(), test_entry_b());
+        hold_entry_with_status(&context, &initial, CrudStatus::MODIFIED);
+        hold_entry_with_status(&context, &updated, CrudStatus::LIVE);
+        hold_update(&context, &initial, &updated);
+
+        // Both entries are held locally, so the workflow does not dispatch any action
+        let latest = block_on(get_entry_history_workflow(
+            &context,
+            &get_args(initial.address(), true),
+        ))
+        .unwrap();
+        assert_eq!(vec![updated.clone()], latest.entries);
+        assert_eq!(Some(updated.address()), latest.resolved_address);
+
+        let unresolved = block_on(get_entry_history_workflow(
+            &context,
+            &get_args(initial.address(), false),
+        ))
+        .unwrap();
+        assert_eq!(vec![initial.clone()], unresolved.entries);
+        assert_eq!(Some(initial.address()), unresolved.resolved_address);
+    }
+
+    #[test]
+    fn get_entry_history_tells_meta_if_asked() {
+        let (context, store, _action_rx) =
+            test_context_with_network("meta_agent", "get_entry_history_tells_meta_if_asked");
+        let (initial, updated, comment) = (test_entry(), test_entry_b(), test_entry_c());
+        let header = test_chain_header();
+        for action in vec![
+            Action::Hold((initial.clone(), 0)),
+            Action::Hold((updated.clone(), 0)),
+            Action::Hold((comment.clone(), 0)),
+            Action::HoldHeader(header.clone()),
+            Action::AddLink(LinkOperation::from_header(
+                &Link::new(&initial.address(), &comment.address(), "comments"),
+                &header,
+            )),
+            Action::UpdateEntry((initial.address(), updated.address())),
+        ] {
+            reduce(&context, &store, ActionWrapper::new(action));
+        }
+
+        let mut args = get_args(initial.address(), false);
+        args.options.with_meta = true;
+        let entry_history = block_on(get_entry_history_workflow(&context, &args)).unwrap();
+        assert_eq!(vec![initial.clone()], entry_history.entries);
+        assert_eq!(vec![CrudStatus::MODIFIED], entry_history.crud_status);
+        assert_eq!(entry_history.meta.len(), 1);
+        let meta = &entry_history.meta[0];
+        assert_eq!(meta.link_counts.get("comments"), Some(&1));
+        assert_eq!(meta.newest_version, Some(updated.address()));
+        assert_eq!(meta.first_published, Some(header.timestamp().clone()));
+        assert_eq!(meta.last_published, Some(header.timestamp().clone()));
+
+        // Without asking for it, the history tells no meta
+        let latest = block_on(get_entry_history_workflow(
+            &context,
+            &get_args(initial.address(), true),
+        ))
+        .unwrap();
+        assert_eq!(vec![updated], latest.entries);
+        assert!(latest.meta.is_empty());
+    }
+
+    #[test]
+    fn get_entry_history_fails_on_update_cycles() {
+        let (context, _store, _action_rx) =
+            test_context_with_network("cycle_agent", "get_entry_history_fails_on_update_cycles");
+        let (entry_b, entry_c) = (test_entry_b(), test_entry_c());
+        hold_entry_with_status(&context, &entry_b, CrudStatus::MODIFIED);
+        hold_entry_with_status(&context, &entry_c, CrudStatus::MODIFIED);
+        hold_update(&context, &entry_b, &entry_c);
+        hold_update(&context, &entry_c, &entry_b);
+
+        let result = block_on(get_entry_history_workflow(
+            &context,
+            &get_args(entry_b.address(), true),
+        ));
+        assert_eq!(
+            Err(HolochainError::ErrorGeneric(format!(
+                "The updates of {} form a cycle at {}",
+                entry_b.address(),
+                entry_b.address()
+            ))),
+            result
+        );
+    }
+}
@@ -0,0 +1,431 @@
+use crate::{
+    context::Context,
+    dht::{
+        actions::{
+            hold::{hold_entry, hold_header},
+            queue_validation::{dequeue_validation, queue_validation},
+            reject_entry::reject_entry,
+        },
+        dht_store::RejectionReason,
+        pending_validation::PendingValidation,
+    },
+    network::{
+        actions::get_validation_package::get_validation_package,
+        entry_with_header::{outlived_ttl, EntryWithHeader},
+    },
+    nucleus::{
+        actions::validate::validate_entry,
+        ribosome::callback::validation_dependencies::get_validation_dependencies,
+    },
+    workflows::{
+        get_entry_history::get_entry_with_meta_workflow, update_agent::check_identity_entry,
+    },
+};
+use futures::executor::block_on;
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent},
+    entry::{entry_type::EntryType, Entry},
+    error::HolochainError,
+    time::Iso8601,
+    validation::{EntryAction, EntryLifecycle, ValidationData},
+};
+use std::{
+    cmp,
+    sync::Arc,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// Upper bound for the time between two attempts to fetch the dependencies of a pending validation
+const MAX_DEPENDENCY_RETRY_INTERVAL_MS: u64 = 1000;
+
+/// Returns the given dependencies that are neither held by us nor can be fetched from the network
+async fn missing_dependencies<'a>(
+    dependencies: &'a [Address],
+    context: &'a Arc<Context>,
+) -> Vec<Address> {
+    let mut missing = Vec::new();
+    for address in dependencies {
+        match await!(get_entry_with_meta_workflow(context, address)) {
+            Ok(Some(_)) => (),
+            _ => missing.push(address.clone()),
+        }
+    }
+    missing
+}
+
+/// Runs the zome's validation callback for the given entry with the validation package
+/// from its source and holds it if it is valid. Invalid entries get marked as REJECTED,
+/// as do entries older than the TTL of their type, see `outlived_ttl()`.
+/// Validations that were waiting for the entry get tried again once it is held.
+async fn validate_and_hold<'a>(
+    entry_with_header: &'a EntryWithHeader,
+    context: &'a Arc<Context>,
+) -> Result<(), HolochainError> {
+    let EntryWithHeader { entry_body, header } = entry_with_header;
+
+    if outlived_ttl(entry_with_header, context) {
+        await!(reject_entry(
+            &entry_body.address(),
+            RejectionReason::Expired,
+            context
+        ))?;
+        return Err(HolochainError::ValidationFailed(format!(
+            "Entry is older than the TTL of its type, committed at {}",
+            header.timestamp()
+        )));
+    }
+
+    // 1. Get the validation package from the source
+    let validation_package = match await!(get_validation_package(header.clone(), context))? {
+        Some(validation_package) => validation_package,
+        None => {
+            await!(reject_entry(
+                &entry_body.address(),
+                RejectionReason::Invalid,
+                context
+            ))?;
+            return Err(HolochainError::ValidationFailed(String::from(
+                "Could not get validation package from source",
+            )));
+        }
+    };
+    if let Err(error) = validation_package.check_sequence() {
+        await!(reject_entry(
+            &entry_body.address(),
+            RejectionReason::Invalid,
+            context
+        ))?;
+        return Err(error);
+    }
+    let validation_data = ValidationData {
+        package: validation_package,
+        sources: header.sources().clone(),
+        lifecycle: EntryLifecycle::Dht,
+        action: EntryAction::Create,
+    };
+
+    // 2. Run the zome's validation callback
+    if let Err(error) = await!(validate_entry(entry_body.clone(), validation_data, context)) {
+        await!(reject_entry(
+            &entry_body.address(),
+            RejectionReason::Invalid,
+            context
+        ))?;
+        return Err(error);
+    }
+
+    // 3. Hold the valid entry
+    await!(hold_entry(entry_body, context))?;
+    hold_header(header, context);
+    retry_dependent_validations(&entry_body.address(), context);
+    Ok(())
+}
+
+/// Validates and holds the given entry once the given dependencies are available.
+/// If some of them can't be fetched yet, the validation gets parked in the local DHT shard
+/// and tried again until they can, or the context's validation dependency timeout has
+/// passed. In that case, the entry gets rejected for missing dependencies.
+pub(crate) async fn hold_entry_with_dependencies<'a>(
+    entry_with_header: &'a EntryWithHeader,
+    dependencies: Vec<Address>,
+    context: &'a Arc<Context>,
+) -> Result<(), HolochainError> {
+    if !await!(missing_dependencies(&dependencies, context)).is_empty() {
+        let deadline =
+            Iso8601::from(SystemTime::now() + context.validation_dependency_timeout());
+        let pending_validation =
+            PendingValidation::new(entry_with_header.clone(), dependencies, deadline);
+        await!(queue_validation(&pending_validation, context))?;
+        watch_pending_validation(pending_validation, context.clone());
+        return Ok(());
+    }
+
+    await!(validate_and_hold(entry_with_header, context))
+}
+
+/// Validates an entry that the network asks us to hold and only adds it to our
+/// DHT shard if it is valid. Invalid entries get marked as REJECTED instead.
+/// App entries are only validated once the entries they depend on, as told by the zome's
+/// validation dependencies callback, are available.
+/// Entries of all other types are held without validation, except for identity updates,
+/// which have to come from the agent whose identity they update.
+pub async fn hold_entry_workflow<'a>(
+    entry_with_header: &'a EntryWithHeader,
+    context: &'a Arc<Context>,
+) -> Result<(), HolochainError> {
+    let EntryWithHeader { entry_body, header } = entry_with_header;
+    match entry_body.entry_type() {
+        EntryType::App(_) => (),
+        _ => {
+            if let Entry::AgentId(agent_id) = entry_body {
+                if let Err(error) = await!(check_identity_entry(agent_id, header, context)) {
+                    if let HolochainError::ValidationFailed(_) = error {
+                        await!(reject_entry(
+                            &entry_body.address(),
+                            RejectionReason::Invalid,
+                            context
+                        ))?;
+                    }
+                    return Err(error);
+                }
+            }
+            await!(hold_entry(entry_body, context))?;
+            hold_header(header, context);
+            retry_dependent_validations(&entry_body.address(), context);
+            return Ok(());
+        }
+    }
+
+    let dependencies = match get_validation_dependencies(entry_body, context.clone()) {
+        Ok(dependencies) => dependencies,
+        Err(HolochainError::ValidationFailed(reason)) => {
+            await!(reject_entry(
+                &entry_body.address(),
+                RejectionReason::Invalid,
+                context
+            ))?;
+            return Err(HolochainError::ValidationFailed(reason));
+        }
+        Err(error) => return Err(error),
+    };
+    await!(hold_entry_with_dependencies(
+        entry_with_header,
+        dependencies,
+        context
+    ))
+}
+
+fn is_pending(pending_validation: &PendingValidation, context: &Arc<Context>) -> bool {
+    context
+        .state()
+        .and_then(|state| state.dht().pending_validations().ok())
+        .map(|pending_validations| pending_validations.contains(pending_validation))
+        .unwrap_or(false)
+}
+
+/// Tries the given pending validation again: validates the entry if its dependencies are
+/// available by now, rejects it if the deadline has passed, and leaves it queued otherwise.
+/// Returns false as long as the validation is still pending.
+pub async fn retry_pending_validation<'a>(
+    pending_validation: &'a PendingValidation,
+    context: &'a Arc<Context>,
+) -> bool {
+    if !is_pending(pending_validation, context) {
+        return true;
+    }
+    let missing = await!(missing_dependencies(
+        &pending_validation.dependencies,
+        context
+    ));
+    if !missing.is_empty() && SystemTime::now() < pending_validation.deadline.to_system_time() {
+        return false;
+    }
+
+    if let Err(error) = await!(finish_pending_validation(
+        pending_validation,
+        missing,
+        context
+    )) {
+        context.log(format!(
+            "Not holding entry {}: {}",
+            pending_validation.entry_address(),
+            error
+        ));
+    }
+    true
+}
+
+/// Takes the pending validation out of the queue and validates its entry, or rejects it
+/// if dependencies are still missing
+async fn finish_pending_validation<'a>(
+    pending_validation: &'a PendingValidation,
+    missing: Vec<Address>,
+    context: &'a Arc<Context>,
+) -> Result<(), HolochainError> {
+    await!(dequeue_validation(pending_validation, context))?;
+    if missing.is_empty() {
+        return await!(validate_and_hold(
+            &pending_validation.entry_with_header,
+            context
+        ));
+    }
+    context.log(format!(
+        "Rejecting entry {}, dependencies missing after deadline: {:?}",
+        pending_validation.entry_address(),
+        missing
+    ));
+    await!(reject_entry(
+        &pending_validation.entry_address(),
+        RejectionReason::MissingDependencies,
+        context
+    ))
+}
+
+/// Retries the given pending validation in the background until it is done.
+/// The interval depends on the context's validation dependency timeout, so that
+/// the deadline is not overshot by much.
+pub fn watch_pending_validation(pending_validation: PendingValidation, context: Arc<Context>) {
+    let interval = cmp::min(
+        context.validation_dependency_timeout() / 10,
+        Duration::from_millis(MAX_DEPENDENCY_RETRY_INTERVAL_MS),
+    );
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        if block_on(retry_pending_validation(&pending_validation, &context)) {
+            break;
+        }
+    });
+}
+
+/// Tries the pending validations that wait for the entry with the given address again,
+/// right after we got to hold it.
+pub fn retry_dependent_validations(address: &Address, context: &Arc<Context>) {
+    let pending_validations = match context.state() {
+        Some(state) => state.dht().pending_validations().unwrap_or_default(),
+        None => return,
+    };
+    for pending_validation in pending_validations
+        .into_iter()
+        .filter(|pending_validation| pending_validation.dependencies.contains(address))
+    {
+        let task_context = context.clone();
+        context.spawn_task(move || {
+            block_on(retry_pending_validation(&pending_validation, &task_context));
+        });
+    }
+}
+
+/// Picks up the validations that were still waiting for their dependencies when the
+/// instance was stopped, e.g. after restoring it from storage.
+pub fn resume_pending_validations(context: &Arc<Context>) {
+    let pending_validations = match context.state() {
+        Some(state) => state.dht().pending_validations().unwrap_or_default(),
+        None => return,
+    };
+    for pending_validation in pending_validations {
+        watch_pending_validation(pending_validation, context.clone());
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        agent::actions::commit::commit_entry,
+        network::entry_with_header::fetch_entry_with_header,
+        nucleus::actions::{get_entry::get_entry_with_meta, tests::*},
+        workflows::author_entry::author_entry,
+    };
+    use holochain_core_types::{
+        entry::{entry_type::test_app_entry_type, Entry},
+        json::{JsonString, RawString},
+    };
+
+    fn app_entry(content: &str) -> Entry {
+        Entry::App(
+            test_app_entry_type(),
+            JsonString::from(RawString::from(content)),
+        )
+    }
+
+    /// Commits the entry without publishing it and returns it together with its header
+    fn committed_entry_with_header(entry: &Entry, context: &Arc<Context>) -> EntryWithHeader {
+        block_on(commit_entry(entry.clone(), None, context)).expect("Could not commit");
+        fetch_entry_with_header(&entry.address(), context).expect("Could not get header")
+    }
+
+    fn pending_validations(context: &Arc<Context>) -> Vec<PendingValidation> {
+        context
+            .state()
+            .unwrap()
+            .dht()
+            .pending_validations()
+            .expect("Could not get pending validations")
+    }
+
+    #[test]
+    /// test that an entry arriving before its dependency gets held once the dependency is published
+    fn validation_waits_for_dependencies() {
+        let (_instance1, context1) = instance_by_name("jill", test_dna());
+        let (_instance2, context2) = instance_by_name("jack", test_dna());
+        let dependency = app_entry("dependency");
+        let dependent = committed_entry_with_header(&app_entry("dependent"), &context1);
+
+        block_on(hold_entry_with_dependencies(
+            &dependent,
+            vec![dependency.address()],
+            &context2,
+        ))
+        .expect("Could not queue validation");
+        assert!(get_entry_with_meta(&context2, dependent.entry_body.address())
+            .unwrap()
+            .is_none());
+        assert_eq!(pending_validations(&context2).len(), 1);
+
+        block_on(author_entry(&dependency, None, &context1)).expect("Could not author dependency");
+        thread::sleep(Duration::from_millis(3000));
+
+        assert!(get_entry_with_meta(&context2, dependent.entry_body.address())
+            .unwrap()
+            .is_some());
+        assert!(pending_validations(&context2).is_empty());
+        assert_eq!(
+            context2
+                .state()
+                .unwrap()
+                .dht()
+                .rejection_reason(&dependent.entry_body.address()),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    /// test that an entry gets rejected for missing dependencies if they don't show up in time
+    fn validation_times_out_without_dependencies() {
+        let (_instance1, context1) = instance_by_name("jill", test_dna());
+        let (_instance2, context2) = instance_by_name("jack", test_dna());
+        let mut context2 = (*context2).clone();
+        context2.set_validation_dependency_timeout(Duration::from_millis(500));
+        let context2 = Arc::new(context2);
+        let dependent = committed_entry_with_header(&app_entry("dependent"), &context1);
+
+        block_on(hold_entry_with_dependencies(
+            &dependent,
+            vec![app_entry("never published").address()],
+            &context2,
+        ))
+        .expect("Could not queue validation");
+        thread::sleep(Duration::from_millis(3000));
+
+        assert!(pending_validations(&context2).is_empty());
+        assert_eq!(
+            context2
+                .state()
+                .unwrap()
+                .dht()
+                .rejection_reason(&dependent.entry_body.address()),
+            Ok(Some(RejectionReason::MissingDependencies))
+        );
+    }
+
+    #[test]
+    /// test that entries failing validation are rejected as invalid, not for missing dependencies
+    fn invalid_entry_gets_rejected_as_invalid() {
+        let (_instance1, context1) = instance_by_name("jill", test_dna());
+        let (_instance2, context2) = instance_by_name("jack", test_dna());
+        let invalid = committed_entry_with_header(&app_entry("FAIL"), &context1);
+
+        let result = block_on(hold_entry_with_dependencies(&invalid, Vec::new(), &context2));
+
+        assert!(result.is_err());
+        assert_eq!(
+            context2
+                .state()
+                .unwrap()
+                .dht()
+                .rejection_reason(&invalid.entry_body.address()),
+            Ok(Some(RejectionReason::Invalid))
+        );
+    }
+}
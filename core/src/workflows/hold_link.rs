@@ -0,0 +1,407 @@
+use crate::{
+    context::Context,
+    dht::{
+        actions::{
+            add_link::add_link_operation,
+            hold::{hold_entry, hold_header},
+            reject_entry::reject_entry,
+            remove_link::remove_link_operation,
+        },
+        dht_store::RejectionReason,
+    },
+    network::{
+        actions::get_validation_package::get_validation_package,
+        entry_with_header::EntryWithHeader,
+    },
+    nucleus::{
+        actions::validate::validate_entry,
+        ribosome::callback::links_utils::find_link_definition_in_dna,
+    },
+    workflows::{
+        get_entry_history::get_entry_with_meta_workflow, register_agent::check_agent_id_link,
+    },
+};
+
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent},
+    entry::Entry,
+    error::HolochainError,
+    link::{Link, LinkOperation, AGENT_ID_LINK_TAG},
+    validation::{EntryAction, EntryLifecycle, ValidationData},
+};
+use std::sync::Arc;
+
+/// Fetches the entry at the given address, from the network if we don't hold it ourselves
+async fn get_link_entry<'a>(
+    address: &'a Address,
+    context: &'a Arc<Context>,
+) -> Result<Entry, HolochainError> {
+    await!(get_entry_with_meta_workflow(context, address))?
+        .map(|entry_with_meta| entry_with_meta.entry)
+        .ok_or_else(|| {
+            HolochainError::ErrorGeneric(format!("Could not get link entry {}", address))
+        })
+}
+
+/// Checks that the DNA defines links with the link's tag between the entry types of
+/// its base and target. Fails with HolochainError::ValidationFailed if it doesn't,
+/// and with other errors if base or target couldn't be fetched.
+pub(crate) async fn check_link_definition<'a>(
+    link: &'a Link,
+    context: &'a Arc<Context>,
+) -> Result<(), HolochainError> {
+    let base = await!(get_link_entry(link.base(), context))?;
+    let target = await!(get_link_entry(link.target(), context))?;
+    find_link_definition_in_dna(
+        &base.entry_type(),
+        link.tag(),
+        &target.entry_type(),
+        context,
+    )
+    .map(|_| ())
+    .map_err(|_| {
+        HolochainError::ValidationFailed(format!(
+            "No link definition found for tag \"{}\" from {} to {}",
+            link.tag(),
+            base.entry_type(),
+            target.entry_type()
+        ))
+    })
+}
+
+/// Validates a link that the network asks us to hold and only adds it to our
+/// DHT shard if it is valid. Invalid links get marked as REJECTED instead.
+pub async fn hold_link_workflow<'a>(
+    entry_with_header: &'a EntryWithHeader,
+    context: &'a Arc<Context>,
+) -> Result<(), HolochainError> {
+    let EntryWithHeader { entry_body, header } = entry_with_header;
+    let link = match entry_body {
+        Entry::LinkAdd(link_add) => link_add.link().clone(),
+        _ => {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Expected Entry::LinkAdd, received {:?}",
+                entry_body
+            )));
+        }
+    };
+    // Removes of the link that arrive before it match it by its header or its timestamp
+    let operation = LinkOperation::from_header(&link, header);
+
+    // Links of the agent directory are not defined in the DNA and zomes don't validate
+    // them, core checks their structure instead
+    if link.tag() == AGENT_ID_LINK_TAG {
+        if let Err(error) = check_agent_id_link(&link, header, context) {
+            await!(reject_entry(&entry_body.address(), RejectionReason::Invalid, context))?;
+            return Err(error);
+        }
+    } else {
+        await!(validate_link_operation(
+            entry_with_header,
+            &link,
+            EntryAction::Create,
+            context
+        ))?;
+    }
+
+    // Hold the valid link, together with its entry and header so that it can be
+    // validated again later
+    await!(add_link_operation(&operation, context))?;
+    await!(hold_entry(entry_body, context))?;
+    hold_header(header, context);
+    Ok(())
+}
+
+/// Validates the remove of a link that the network asks us to hold like an add, see
+/// hold_link_workflow(), and only applies it to our DHT shard if it is valid. It removes
+/// the add it names, or else all adds of the link made before it. Adds that arrive after
+/// it don't make the link live then. Links of the agent directory can't be removed.
+pub async fn hold_link_remove_workflow<'a>(
+    entry_with_header: &'a EntryWithHeader,
+    context: &'a Arc<Context>,
+) -> Result<(), HolochainError> {
+    let EntryWithHeader { entry_body, header } = entry_with_header;
+    let link_remove = match entry_body {
+        Entry::LinkRemove(link_remove) => link_remove,
+        _ => {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Expected Entry::LinkRemove, received {:?}",
+                entry_body
+            )));
+        }
+    };
+    let link = link_remove.link();
+    if link.tag() == AGENT_ID_LINK_TAG {
+        await!(reject_entry(&entry_body.address(), RejectionReason::Invalid, context))?;
+        return Err(HolochainError::ValidationFailed(String::from(
+            "Links of the agent directory can't be removed",
+        )));
+    }
+    await!(validate_link_operation(
+        entry_with_header,
+        link,
+        EntryAction::Delete,
+        context
+    ))?;
+
+    let operation = LinkOperation::from_header(link, header);
+    await!(remove_link_operation(
+        &operation,
+        link_remove.removed_add(),
+        context
+    ))?;
+    await!(hold_entry(entry_body, context))?;
+    hold_header(header, context);
+    Ok(())
+}
+
+/// Validates an add or remove of the given link, which the given entry makes.
+/// Marks the entry as REJECTED if it is invalid.
+async fn validate_link_operation<'a>(
+    entry_with_header: &'a EntryWithHeader,
+    link: &'a Link,
+    action: EntryAction,
+    context: &'a Arc<Context>,
+) -> Result<(), HolochainError> {
+    let EntryWithHeader { entry_body, header } = entry_with_header;
+
+    // 1. Links that are not defined in the DNA are invalid, whatever the zome would say
+    if let Err(error) = await!(check_link_definition(link, context)) {
+        if let HolochainError::ValidationFailed(_) = error {
+            await!(reject_entry(&entry_body.address(), RejectionReason::Invalid, context))?;
+        }
+        return Err(error);
+    }
+
+    // 2. Get the validation package from the source
+    let validation_package = match await!(get_validation_package(header.clone(), context))? {
+        Some(validation_package) => validation_package,
+        None => {
+            await!(reject_entry(&entry_body.address(), RejectionReason::Invalid, context))?;
+            return Err(HolochainError::ValidationFailed(String::from(
+                "Could not get validation package from source",
+            )));
+        }
+    };
+    let validation_data = ValidationData {
+        package: validation_package,
+        sources: header.sources().clone(),
+        lifecycle: EntryLifecycle::Meta,
+        action,
+    };
+
+    // 3. Run the zome's link validation callback
+    if let Err(error) = await!(validate_entry(entry_body.clone(), validation_data, context)) {
+        await!(reject_entry(&entry_body.address(), RejectionReason::Invalid, context))?;
+        return Err(error);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        agent::actions::commit::commit_entry,
+        context::Context,
+        dht::dht_store::{LINK_ADD_NAME, LINK_REMOVE_NAME},
+        network::actions::publish::publish,
+        nucleus::actions::tests::*,
+        workflows::author_entry::author_entry,
+    };
+    use futures::executor::block_on;
+    use holochain_core_types::{
+        cas::content::{Address, AddressableContent},
+        crud_status::{CrudStatus, STATUS_NAME},
+        dna::{zome::entry_types::LinksTo, Dna},
+        eav::EntityAttributeValue,
+        entry::{entry_type::test_app_entry_type, test_entry, Entry},
+        json::{JsonString, RawString},
+        link::{link_add::LinkAdd, link_remove::LinkRemove, Link},
+    };
+    use std::{collections::HashSet, sync::Arc, thread, time::Duration};
+
+    fn test_dna_with_link_definition() -> Dna {
+        let mut dna = test_dna();
+        let mut links_to = LinksTo::new();
+        links_to.target_type = String::from("testEntryType");
+        links_to.tag = String::from("test-tag");
+        dna.zomes
+            .get_mut("test_zome")
+            .unwrap()
+            .entry_types
+            .get_mut("testEntryType")
+            .unwrap()
+            .links_to
+            .push(links_to);
+        dna
+    }
+
+    fn link_target_entry() -> Entry {
+        Entry::App(
+            test_app_entry_type(),
+            JsonString::from(RawString::from("link target")),
+        )
+    }
+
+    /// Authors base and target and returns a link between them with the given tag
+    fn author_link_entries(tag: &str, context: &Arc<Context>) -> Link {
+        let base = test_entry();
+        let target = link_target_entry();
+        block_on(author_entry(&base, None, context)).expect("Could not author base");
+        block_on(author_entry(&target, None, context)).expect("Could not author target");
+        Link::new(&base.address(), &target.address(), tag)
+    }
+
+    fn held_links(context: &Arc<Context>, link: &Link) -> HashSet<EntityAttributeValue> {
+        context
+            .state()
+            .unwrap()
+            .dht()
+            .meta_storage()
+            .read()
+            .unwrap()
+            .fetch_eav(
+                Some(link.base().clone()),
+                Some(format!("link__{}", link.tag())),
+                None,
+            )
+            .expect("could not fetch from EAV")
+    }
+
+    fn is_rejected(context: &Arc<Context>, entry: &Entry) -> bool {
+        context
+            .state()
+            .unwrap()
+            .dht()
+            .meta_storage()
+            .read()
+            .unwrap()
+            .fetch_eav(Some(entry.address()), Some(STATUS_NAME.to_string()), None)
+            .expect("could not fetch from EAV")
+            .iter()
+            .any(|eav| CrudStatus::from(String::from(eav.value())) == CrudStatus::REJECTED)
+    }
+
+    /// The number of adds and removes of the given link that the given node recorded
+    fn link_operations_held(context: &Arc<Context>, link: &Link) -> usize {
+        let link_address = Entry::LinkAdd(LinkAdd::from_link(link)).address();
+        let meta_storage = context.state().unwrap().dht().meta_storage();
+        let meta_storage = meta_storage.read().unwrap();
+        [LINK_ADD_NAME, LINK_REMOVE_NAME]
+            .iter()
+            .map(|attribute| {
+                meta_storage
+                    .fetch_eav(
+                        Some(link_address.clone()),
+                        Some(attribute.to_string()),
+                        None,
+                    )
+                    .expect("could not fetch from EAV")
+                    .len()
+            })
+            .sum()
+    }
+
+    /// Waits until the given condition holds
+    fn wait_for<F: Fn() -> bool>(condition: F) -> bool {
+        for _ in 0..100 {
+            if condition() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        false
+    }
+
+    /// Has jill publish the add of a kept link, and the add and the remove of a removed link
+    /// in the given order, each once jack recorded the one before. Returns the targets of
+    /// the links that jack holds on their base then.
+    fn links_held_after_publishing(uuid: &str, remove_first: bool) -> Vec<Address> {
+        let mut dna = test_dna_with_link_definition();
+        dna.uuid = String::from(uuid);
+        let (_jill_instance, jill) = instance_by_name("jill", dna.clone());
+        let (_jack_instance, jack) = instance_by_name("jack", dna);
+        let removed = author_link_entries("test-tag", &jill);
+        let kept = Link::new(removed.base(), removed.base(), "test-tag");
+
+        let kept_add = Entry::LinkAdd(LinkAdd::from_link(&kept));
+        commit(kept_add.clone(), &jill);
+        let removed_add = Entry::LinkAdd(LinkAdd::from_link(&removed));
+        let removed_add_header = commit(removed_add.clone(), &jill);
+        let remove = Entry::LinkRemove(LinkRemove::from_link(
+            &removed,
+            Some(&removed_add_header.address()),
+        ));
+        commit(remove.clone(), &jill);
+
+        block_on(publish(kept_add.address(), &jill)).expect("Could not publish");
+        assert!(wait_for(|| link_operations_held(&jack, &kept) == 1));
+        let order = if remove_first {
+            vec![remove, removed_add]
+        } else {
+            vec![removed_add, remove]
+        };
+        for (published, entry) in order.into_iter().enumerate() {
+            block_on(publish(entry.address(), &jill)).expect("Could not publish");
+            assert!(wait_for(
+                || link_operations_held(&jack, &removed) == published + 1
+            ));
+        }
+
+        jack.state()
+            .unwrap()
+            .dht()
+            .get_links(removed.base().clone(), String::from("test-tag"))
+            .expect("could not get links")
+            .iter()
+            .map(|eav| eav.value())
+            .collect()
+    }
+
+    #[test]
+    /// test that a removed link is not held, whether its remove arrives before or after its add
+    fn links_are_the_same_whatever_order_their_operations_arrive_in() {
+        let kept_target = test_entry().address();
+        assert_eq!(
+            links_held_after_publishing("links_added_then_removed", false),
+            vec![kept_target.clone()]
+        );
+        assert_eq!(
+            links_held_after_publishing("links_removed_then_added", true),
+            vec![kept_target]
+        );
+    }
+
+    #[test]
+    /// test that a valid link gets validated and held by another node
+    fn valid_link_gets_held() {
+        let dna = test_dna_with_link_definition();
+        let (_instance1, context1) = instance_by_name("jill", dna.clone());
+        let (_instance2, context2) = instance_by_name("jack", dna);
+        let link = author_link_entries("test-tag", &context1);
+        let link_entry = Entry::LinkAdd(LinkAdd::from_link(&link));
+
+        block_on(author_entry(&link_entry, None, &context1)).expect("Could not author link");
+
+        assert!(wait_for(|| held_links(&context2, &link).len() == 1));
+        assert!(!is_rejected(&context2, &link_entry));
+    }
+
+    #[test]
+    /// test that a link without link definition is not held but rejected by another node
+    fn undeclared_link_gets_rejected() {
+        let dna = test_dna_with_link_definition();
+        let (_instance1, context1) = instance_by_name("jill", dna.clone());
+        let (_instance2, context2) = instance_by_name("jack", dna);
+        let link = author_link_entries("undeclared-tag", &context1);
+        let link_entry = Entry::LinkAdd(LinkAdd::from_link(&link));
+
+        // Skip local validation, like a malicious node would
+        let commit_result =
+            block_on(commit_entry(link_entry.clone(), None, &context1)).expect("Could not commit");
+        block_on(publish(commit_result.address, &context1)).expect("Could not publish");
+
+        assert!(wait_for(|| is_rejected(&context2, &link_entry)));
+        assert!(held_links(&context2, &link).is_empty());
+    }
+}
@@ -1,3 +1,10 @@
 pub mod author_entry;
+pub mod collect_garbage;
+pub mod commit_and_link;
 pub mod get_entry_history;
+pub mod hold_entry;
+pub mod hold_link;
+pub mod register_agent;
 pub mod respond_validation_package_request;
+pub mod revalidate_shard;
+pub mod update_agent;
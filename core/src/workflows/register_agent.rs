@@ -0,0 +1,214 @@
+//! The agent directory of a DNA: every agent that joins links the DNA entry to its
+//! identity entry with the reserved tag AGENT_ID_LINK_TAG, so that zomes can list the
+//! agents of their DNA with get_links.
+//! The links target the agent's address, which stays the same when the agent updates its
+//! identity and resolves to the latest one, see `workflows::update_agent`.
+//! Core creates and checks these links itself, zomes can neither create nor validate them.
+
+use crate::{
+    agent::{actions::commit::commit_entry, find_chain_header},
+    context::Context,
+    dht::actions::add_link::add_link,
+    network::actions::publish::publish,
+};
+use holochain_core_types::{
+    agent::AgentId,
+    cas::content::AddressableContent,
+    chain_header::ChainHeader,
+    dna::Dna,
+    entry::{addressing, Entry},
+    error::HolochainError,
+    link::{link_add::LinkAdd, Link, AGENT_ID_LINK_TAG},
+};
+use std::sync::Arc;
+
+/// DNA property with which privacy-sensitive apps can opt out of the agent directory
+pub const REGISTER_AGENTS_PROPERTY: &str = "register_agents";
+
+/// True unless the DNA sets its `register_agents` property to false
+pub fn registers_agents(dna: &Dna) -> bool {
+    dna.properties
+        .get(REGISTER_AGENTS_PROPERTY)
+        .and_then(|register_agents| register_agents.as_bool())
+        .unwrap_or(true)
+}
+
+/// The link that adds the given agent to the directory of the given DNA
+pub fn agent_id_link(dna: &Dna, agent_id: &AgentId) -> Link {
+    Link::new(
+        &addressing::dna_entry_address(dna),
+        &Entry::AgentId(agent_id.clone()).address(),
+        AGENT_ID_LINK_TAG,
+    )
+}
+
+fn agent_id_link_entry(dna: &Dna, agent_id: &AgentId) -> Entry {
+    Entry::LinkAdd(LinkAdd::from_link(&agent_id_link(dna, agent_id)))
+}
+
+/// Commits the link that adds our agent to the DNA's directory, unless the DNA opted out,
+/// and holds it right away so that we find ourselves in the directory even without peers.
+/// This happens during initialization, before the network is up, so the link only gets
+/// published by publish_agent_id_link().
+pub async fn commit_agent_id_link<'a>(
+    dna: &'a Dna,
+    context: &'a Arc<Context>,
+) -> Result<(), HolochainError> {
+    if !registers_agents(dna) {
+        return Ok(());
+    }
+    let link = agent_id_link(dna, &context.agent_id);
+    await!(commit_entry(
+        Entry::LinkAdd(LinkAdd::from_link(&link)),
+        None,
+        context
+    ))?;
+    await!(add_link(&link, context))
+}
+
+/// Publishes the link that adds our agent to the DNA's directory, if it is on our chain.
+/// Needs an initialized network. Publishing it again on every start does no harm
+/// and reaches nodes that missed it before.
+pub async fn publish_agent_id_link(context: &Arc<Context>) -> Result<(), HolochainError> {
+    let dna = context
+        .state()
+        .and_then(|state| state.nucleus().dna())
+        .ok_or_else(|| HolochainError::ErrorGeneric(String::from("No DNA to register in")))?;
+    let entry = agent_id_link_entry(&dna, &context.agent_id);
+    if find_chain_header(&entry, context).is_none() {
+        return Ok(());
+    }
+    await!(publish(entry.address(), context)).map(|_| ())
+}
+
+/// Checks a directory link that the network asks us to hold: it has to link from the
+/// entry of our DNA, which must not have opted out, to the identity entry of the agent
+/// that authored it. Fails with HolochainError::ValidationFailed otherwise.
+pub(crate) fn check_agent_id_link(
+    link: &Link,
+    header: &ChainHeader,
+    context: &Arc<Context>,
+) -> Result<(), HolochainError> {
+    let dna = context
+        .state()
+        .and_then(|state| state.nucleus().dna())
+        .ok_or_else(|| HolochainError::ErrorGeneric(String::from("No DNA to check link")))?;
+    if !registers_agents(&dna) {
+        return Err(HolochainError::ValidationFailed(String::from(
+            "DNA does not register agents",
+        )));
+    }
+    if *link.base() != addressing::dna_entry_address(&dna) {
+        return Err(HolochainError::ValidationFailed(format!(
+            "Base of {} link is not the DNA entry",
+            AGENT_ID_LINK_TAG
+        )));
+    }
+    if !header.sources().contains(link.target()) {
+        return Err(HolochainError::ValidationFailed(format!(
+            "Target of {} link is not its source's agent",
+            AGENT_ID_LINK_TAG
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::nucleus::actions::tests::*;
+    use futures::executor::block_on;
+    use holochain_core_types::{cas::content::Address, eav::EntityAttributeValue};
+    use std::{collections::HashSet, thread, time};
+
+    fn directory(context: &Arc<Context>) -> HashSet<Address> {
+        let dna = context.state().unwrap().nucleus().dna().unwrap();
+        context
+            .state()
+            .unwrap()
+            .dht()
+            .get_links(addressing::dna_entry_address(&dna), AGENT_ID_LINK_TAG.to_string())
+            .expect("could not get links")
+            .iter()
+            .map(EntityAttributeValue::value)
+            .collect()
+    }
+
+    #[test]
+    fn registers_agents_unless_dna_opts_out() {
+        let mut dna = test_dna();
+        assert!(registers_agents(&dna));
+        dna.properties = serde_json::from_str(r#"{"register_agents": false}"#).unwrap();
+        assert!(!registers_agents(&dna));
+        dna.properties = serde_json::from_str(r#"{"register_agents": true}"#).unwrap();
+        assert!(registers_agents(&dna));
+    }
+
+    #[test]
+    /// test that every node finds itself and the other agents of its DNA in the directory
+    fn agents_see_each_other_in_directory() {
+        let dna = test_dna();
+        let (_instance1, context1) = instance_by_name("jill", dna.clone());
+        let (_instance2, context2) = instance_by_name("jack", dna.clone());
+        let (_instance3, context3) = instance_by_name("joan", dna);
+        let contexts = vec![context1, context2, context3];
+
+        // The mock network doesn't gossip, so publishing again stands in for it
+        // and reaches the nodes that joined after an agent registered
+        for context in contexts.iter() {
+            block_on(publish_agent_id_link(context)).expect("Could not publish link");
+        }
+
+        thread::sleep(time::Duration::from_millis(2000));
+
+        for context in contexts.iter() {
+            let directory = directory(context);
+            for other in contexts.iter() {
+                assert!(
+                    directory.contains(&other.agent_id.address()),
+                    "{} does not see {}",
+                    context.agent_id.nick,
+                    other.agent_id.nick
+                );
+            }
+        }
+    }
+
+    #[test]
+    /// test that agents of a DNA that opted out don't link themselves into the directory
+    fn opted_out_agents_are_not_registered() {
+        let mut dna = test_dna();
+        dna.properties = serde_json::from_str(r#"{"register_agents": false}"#).unwrap();
+        let (_instance1, context1) = instance_by_name("jill", dna.clone());
+        let (_instance2, context2) = instance_by_name("jack", dna.clone());
+
+        thread::sleep(time::Duration::from_millis(1000));
+
+        let entry = agent_id_link_entry(&dna, &context1.agent_id);
+        assert!(find_chain_header(&entry, &context1).is_none());
+        assert!(directory(&context2).is_empty());
+    }
+
+    #[test]
+    /// test that agents can't register someone else
+    fn refuses_links_to_other_agents() {
+        let dna = test_dna();
+        let (_instance, context) = instance_by_name("jill", dna.clone());
+
+        let own_entry = agent_id_link_entry(&dna, &context.agent_id);
+        let own_header =
+            find_chain_header(&own_entry, &context).expect("initialization commits the link");
+        let own_link = agent_id_link(&dna, &context.agent_id);
+        assert!(check_agent_id_link(&own_link, &own_header, &context).is_ok());
+
+        let other = AgentId::generate_fake("mallory");
+        let forged_entry = agent_id_link_entry(&dna, &other);
+        block_on(commit_entry(forged_entry.clone(), None, &context)).expect("Could not commit");
+        let forged_header = find_chain_header(&forged_entry, &context).unwrap();
+        let forged_link = agent_id_link(&dna, &other);
+        match check_agent_id_link(&forged_link, &forged_header, &context) {
+            Err(HolochainError::ValidationFailed(_)) => (),
+            result => panic!("expected ValidationFailed, got {:?}", result),
+        }
+    }
+}
@@ -2,39 +2,90 @@ use crate::{
     action::{Action, ActionWrapper, DirectMessageData},
     context::Context,
     instance::dispatch_action,
-    network::direct_message::DirectMessage,
+    network::{direct_message::DirectMessage, state::RequestId},
     nucleus::actions::build_validation_package::build_validation_package,
 };
 
-use holochain_core_types::{cas::content::Address, entry::Entry, error::HolochainError};
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent},
+    entry::Entry,
+    error::HolochainError,
+    validation::ValidationPackage,
+};
 use std::{convert::TryFrom, sync::Arc};
 
 fn get_entry(address: &Address, context: &Arc<Context>) -> Result<Entry, HolochainError> {
-    let raw = context
+    let storage = context
         .state()
-        .unwrap()
+        .ok_or_else(|| HolochainError::new("Context has no state"))?
         .agent()
         .chain()
-        .content_storage()
-        .read()
-        .unwrap()
+        .content_storage();
+    let raw = storage
+        .read()?
         .fetch(address)?
         .ok_or(HolochainError::ErrorGeneric("Entry not found".to_string()))?;
 
     Entry::try_from(raw)
 }
 
+fn chain_top(context: &Arc<Context>) -> Option<Address> {
+    context
+        .state()
+        .and_then(|state| state.agent().top_chain_header())
+        .map(|header| header.address())
+}
+
+/// Answers the request with the validation package of the requested entry,
+/// or with none if it can't be built.
+/// Packages get built once and then served from the context's ValidationPackageCache,
+/// as long as the chain they contain did not grow.
 pub async fn respond_validation_package_request(
     to_agent_id: Address,
-    msg_id: String,
+    msg_id: RequestId,
     requested_entry_address: Address,
     context: Arc<Context>,
 ) {
+    let chain_top = chain_top(&context);
+    if let Some(validation_package) = context
+        .validation_packages
+        .get(&requested_entry_address, &chain_top)
+    {
+        respond_validation_package(to_agent_id, msg_id, Some(validation_package), &context);
+        return;
+    }
+
     let maybe_validation_package = match get_entry(&requested_entry_address, &context) {
-        Ok(entry) => await!(build_validation_package(&entry, &context)).ok(),
-        Err(_) => None,
-    };
+        Ok(entry) => await!(build_validation_package(&entry, &context)),
+        Err(error) => Err(error),
+    }
+    .map(|validation_package| {
+        context.validation_packages.insert(
+            requested_entry_address.clone(),
+            validation_package.clone(),
+            &chain_top,
+        );
+        validation_package
+    })
+    .map_err(|error| {
+        context.log(format!(
+            "No validation package of {} for request {}: {}",
+            requested_entry_address, msg_id, error
+        ))
+    })
+    .ok();
+
+    respond_validation_package(to_agent_id, msg_id, maybe_validation_package, &context);
+}
 
+/// Sends the response to a validation package request,
+/// None tells the requester that there is no validation package.
+pub fn respond_validation_package(
+    to_agent_id: Address,
+    msg_id: RequestId,
+    maybe_validation_package: Option<ValidationPackage>,
+    context: &Arc<Context>,
+) {
     let direct_message = DirectMessage::ValidationPackage(maybe_validation_package);
     let direct_message_data = DirectMessageData {
         address: to_agent_id,
@@ -0,0 +1,336 @@
+use crate::{
+    agent::find_chain_header,
+    context::Context,
+    dht::{actions::reject_entry::reject_entry, dht_store::RejectionReason},
+    network::actions::get_validation_package::get_validation_package,
+    nucleus::actions::{
+        build_validation_package::build_validation_package, validate::validate_entry,
+    },
+    workflows::hold_link::check_link_definition,
+};
+use futures::executor::block_on;
+use holochain_core_types::{
+    cas::content::{Address, AddressableContent},
+    entry::Entry,
+    error::HolochainError,
+    link::AGENT_ID_LINK_TAG,
+    validation::{EntryAction, EntryLifecycle, ValidationData},
+};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+/// An entry that did not pass the re-validation, and why
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RevalidationIssue {
+    pub address: Address,
+    pub reason: String,
+}
+
+/// Progress and result of re-validating the local DHT shard.
+/// Rejected entries are marked REJECTED and not served anymore.
+/// Unresolved entries could not be validated because their validation package or
+/// the entries they depend on could not be fetched. They are left as they are.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct RevalidationReport {
+    pub total: usize,
+    pub checked: usize,
+    pub passed: Vec<Address>,
+    pub rejected: Vec<RevalidationIssue>,
+    pub unresolved: Vec<RevalidationIssue>,
+    pub finished: bool,
+    pub canceled: bool,
+}
+
+enum RevalidationOutcome {
+    Passed,
+    Rejected(String),
+    Unresolved(String),
+}
+
+/// Shared handle to a re-validation of the local DHT shard, used to follow
+/// its progress and to cancel it.
+#[derive(Clone, Default)]
+pub struct ShardRevalidation {
+    report: Arc<Mutex<RevalidationReport>>,
+    canceled: Arc<AtomicBool>,
+}
+
+impl ShardRevalidation {
+    pub fn new() -> Self {
+        ShardRevalidation::default()
+    }
+
+    /// Runs the re-validation on the context's runtime and returns right away,
+    /// so that zome calls are not blocked by it.
+    pub fn start(context: &Arc<Context>) -> Self {
+        let revalidation = ShardRevalidation::new();
+        let task_revalidation = revalidation.clone();
+        let task_context = context.clone();
+        context.clone().spawn_task(move || {
+            block_on(revalidate_shard_workflow(&task_context, &task_revalidation));
+        });
+        revalidation
+    }
+
+    pub fn report(&self) -> RevalidationReport {
+        self.report.lock().unwrap().clone()
+    }
+
+    /// Stops the re-validation before the next entry.
+    /// Entries that were checked already keep their outcome.
+    pub fn cancel(&self) {
+        self.canceled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.report.lock().unwrap().finished
+    }
+
+    fn is_canceled(&self) -> bool {
+        self.canceled.load(Ordering::SeqCst)
+    }
+
+    fn update<F: FnOnce(&mut RevalidationReport)>(&self, f: F) {
+        f(&mut self.report.lock().unwrap());
+    }
+}
+
+/// Returns all app entries and links held in the local DHT shard that have not been
+/// rejected yet. System entries and links of the agent directory are not validated by
+/// zomes, so they are left out.
+fn entries_to_revalidate(context: &Arc<Context>) -> Result<Vec<Entry>, HolochainError> {
+    let dht = context
+        .state()
+        .ok_or_else(|| HolochainError::new("Context has no state"))?
+        .dht();
+    let mut entries = Vec::new();
+    for entry in dht.iter_held()? {
+        let entry = entry?;
+        if dht.is_rejected(&entry.address())? {
+            continue;
+        }
+        match entry {
+            Entry::LinkAdd(ref link_add) if link_add.link().tag() == AGENT_ID_LINK_TAG => (),
+            Entry::App(_, _) | Entry::LinkAdd(_) => entries.push(entry),
+            _ => (),
+        }
+    }
+    Ok(entries)
+}
+
+/// Builds the validation data for an entry held in the local DHT shard with a fresh
+/// validation package: built by ourselves for entries we authored, requested from
+/// the source for all others.
+async fn fresh_validation_data<'a>(
+    entry: &'a Entry,
+    context: &'a Arc<Context>,
+) -> Result<ValidationData, HolochainError> {
+    let lifecycle = match entry {
+        Entry::LinkAdd(_) => EntryLifecycle::Meta,
+        _ => EntryLifecycle::Dht,
+    };
+    if find_chain_header(entry, context).is_some() {
+        let validation_package = await!(build_validation_package(entry, context))?;
+        return Ok(ValidationData {
+            package: validation_package,
+            sources: vec![context.agent_id.address()],
+            lifecycle,
+            action: EntryAction::Create,
+        });
+    }
+
+    let header = context
+        .state()
+        .ok_or_else(|| HolochainError::new("Context has no state"))?
+        .dht()
+        .get_headers(&entry.address())?
+        .into_iter()
+        .next()
+        .ok_or_else(|| HolochainError::ErrorGeneric(String::from("No header known for entry")))?;
+    let validation_package = await!(get_validation_package(header.clone(), context))?
+        .ok_or_else(|| {
+            HolochainError::ErrorGeneric(String::from(
+                "Could not get validation package from source",
+            ))
+        })?;
+    Ok(ValidationData {
+        package: validation_package,
+        sources: header.sources().clone(),
+        lifecycle,
+        action: EntryAction::Create,
+    })
+}
+
+async fn revalidate_entry<'a>(
+    entry: &'a Entry,
+    context: &'a Arc<Context>,
+) -> RevalidationOutcome {
+    // 1. Links that are not defined in the DNA are invalid, whatever the zome would say
+    if let Entry::LinkAdd(link_add) = entry {
+        match await!(check_link_definition(link_add.link(), context)) {
+            Ok(()) => (),
+            Err(HolochainError::ValidationFailed(reason)) => {
+                return RevalidationOutcome::Rejected(reason)
+            }
+            Err(error) => return RevalidationOutcome::Unresolved(error.to_string()),
+        }
+    }
+
+    // 2. Get a fresh validation package
+    let validation_data = match await!(fresh_validation_data(entry, context)) {
+        Ok(validation_data) => validation_data,
+        Err(error) => return RevalidationOutcome::Unresolved(error.to_string()),
+    };
+
+    // 3. Run the zome's validation callback
+    match await!(validate_entry(entry.clone(), validation_data, context)) {
+        Ok(_) => RevalidationOutcome::Passed,
+        Err(HolochainError::ValidationFailed(reason)) => RevalidationOutcome::Rejected(reason),
+        Err(error) => RevalidationOutcome::Unresolved(error.to_string()),
+    }
+}
+
+/// Runs the current validation callbacks again for every entry and link held in the
+/// local DHT shard, e.g. after a validation bug got fixed, and marks the ones that
+/// fail as REJECTED.
+/// Progress gets reported to the given handle after each entry.
+pub async fn revalidate_shard_workflow<'a>(
+    context: &'a Arc<Context>,
+    revalidation: &'a ShardRevalidation,
+) {
+    let entries = match entries_to_revalidate(context) {
+        Ok(entries) => entries,
+        Err(error) => {
+            context.log(format!("Could not read the local DHT shard: {}", error));
+            Vec::new()
+        }
+    };
+    revalidation.update(|report| report.total = entries.len());
+
+    for entry in entries {
+        if revalidation.is_canceled() {
+            break;
+        }
+        let address = entry.address();
+        let outcome = match await!(revalidate_entry(&entry, context)) {
+            RevalidationOutcome::Rejected(reason) => {
+                match await!(reject_entry(&address, RejectionReason::Invalid, context)) {
+                    Ok(()) => RevalidationOutcome::Rejected(reason),
+                    Err(error) => RevalidationOutcome::Unresolved(format!(
+                        "{} but could not be marked as rejected: {}",
+                        reason, error
+                    )),
+                }
+            }
+            outcome => outcome,
+        };
+        revalidation.update(|report| {
+            report.checked += 1;
+            match outcome {
+                RevalidationOutcome::Passed => report.passed.push(address),
+                RevalidationOutcome::Rejected(reason) => {
+                    report.rejected.push(RevalidationIssue { address, reason })
+                }
+                RevalidationOutcome::Unresolved(reason) => {
+                    report.unresolved.push(RevalidationIssue { address, reason })
+                }
+            }
+        });
+    }
+
+    revalidation.update(|report| {
+        report.canceled = revalidation.is_canceled();
+        report.finished = true;
+    });
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        agent::actions::commit::commit_entry,
+        dht::actions::hold::hold_entry,
+        nucleus::actions::{get_entry::get_entry_with_meta, tests::*},
+    };
+    use holochain_core_types::{
+        entry::{entry_type::test_app_entry_type, test_entry},
+        json::{JsonString, RawString},
+    };
+
+    fn app_entry(content: &str) -> Entry {
+        Entry::App(
+            test_app_entry_type(),
+            JsonString::from(RawString::from(content)),
+        )
+    }
+
+    /// Puts a valid entry, an entry that the validation callback rejects and an entry
+    /// without known header into the shard, all of them without validating them first
+    fn hold_test_entries(context: &Arc<Context>) -> (Entry, Entry, Entry) {
+        let valid_entry = test_entry();
+        let invalid_entry = app_entry("FAIL");
+        let orphan_entry = app_entry("orphan");
+        block_on(commit_entry(valid_entry.clone(), None, context)).expect("Could not commit");
+        block_on(commit_entry(invalid_entry.clone(), None, context)).expect("Could not commit");
+        block_on(hold_entry(&orphan_entry, context)).expect("Could not hold");
+        (valid_entry, invalid_entry, orphan_entry)
+    }
+
+    #[test]
+    fn revalidation_rejects_invalid_entries() {
+        let (_instance, context) = instance_by_name("jill", test_dna());
+        let (valid_entry, invalid_entry, orphan_entry) = hold_test_entries(&context);
+
+        let revalidation = ShardRevalidation::new();
+        block_on(revalidate_shard_workflow(&context, &revalidation));
+        let report = revalidation.report();
+
+        assert!(report.finished);
+        assert!(!report.canceled);
+        assert_eq!(report.total, 3);
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.passed, vec![valid_entry.address()]);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].address, invalid_entry.address());
+        assert!(report.rejected[0].reason.contains("FAIL content is not allowed"));
+        assert_eq!(report.unresolved.len(), 1);
+        assert_eq!(report.unresolved[0].address, orphan_entry.address());
+
+        // the shard only serves the valid entry and the one that couldn't be checked
+        assert!(get_entry_with_meta(&context, valid_entry.address())
+            .unwrap()
+            .is_some());
+        assert!(get_entry_with_meta(&context, invalid_entry.address())
+            .unwrap()
+            .is_none());
+        assert!(get_entry_with_meta(&context, orphan_entry.address())
+            .unwrap()
+            .is_some());
+
+        // rejected entries are not checked again
+        let revalidation = ShardRevalidation::new();
+        block_on(revalidate_shard_workflow(&context, &revalidation));
+        assert_eq!(revalidation.report().total, 2);
+    }
+
+    #[test]
+    fn canceled_revalidation_stops() {
+        let (_instance, context) = instance_by_name("jill", test_dna());
+        let (_, invalid_entry, _) = hold_test_entries(&context);
+
+        let revalidation = ShardRevalidation::new();
+        revalidation.cancel();
+        block_on(revalidate_shard_workflow(&context, &revalidation));
+        let report = revalidation.report();
+
+        assert!(report.finished);
+        assert!(report.canceled);
+        assert_eq!(report.total, 3);
+        assert_eq!(report.checked, 0);
+        assert!(get_entry_with_meta(&context, invalid_entry.address())
+            .unwrap()
+            .is_some());
+    }
+}
@@ -0,0 +1,328 @@
+//! Updates of the agent's identity.
+//! The address of an agent stays the one of its initial identity entry, i.e. its key.
+//! An update commits a new identity entry, which is addressed by its content, and links
+//! the identity it replaces to it like update_entry() does, so that getting the agent's
+//! address resolves to its latest identity on every node. Holders only apply such an
+//! update once they checked that the agent signed it with the key of the identity it
+//! replaces, see check_identity_update(). Fake agents can't sign, so their identity
+//! updates don't get held.
+//! The agent directory needs no update since its links target the agent's address.
+
+use crate::{
+    agent::{
+        actions::{commit::commit_entry, update_entry::update_entry},
+        keys::verify_signature,
+    },
+    context::Context,
+    network::{
+        actions::publish::publish,
+        entry_with_header::{fetch_entry_with_header, EntryWithHeader},
+    },
+    nucleus::actions::get_entry::get_entry_with_meta,
+    workflows::get_entry_history::get_entry_with_meta_workflow,
+};
+use holochain_core_types::{
+    agent::AgentId,
+    cas::content::{Address, AddressableContent},
+    chain_header::ChainHeader,
+    entry::{entry_type::EntryType, Entry},
+    error::HolochainError,
+};
+use std::sync::Arc;
+
+/// Commits an identity with the given nick that replaces the agent's latest identity,
+/// links the latter to it and publishes both. Returns the address of the new identity.
+pub async fn update_agent_workflow<'a>(
+    nick: &'a str,
+    context: &'a Arc<Context>,
+) -> Result<Address, HolochainError> {
+    let agent_state = context
+        .state()
+        .ok_or_else(|| HolochainError::ErrorGeneric(String::from("No state to update agent")))?
+        .agent();
+    let latest_address = agent_state
+        .chain()
+        .iter_type(&agent_state.top_chain_header(), &EntryType::AgentId)
+        .nth(0)
+        .map(|chain_header| chain_header.entry_address().clone())
+        .ok_or_else(|| HolochainError::ErrorGeneric(String::from("Agent entry not found")))?;
+    let latest = fetch_entry_with_header(&latest_address, context)?;
+    let updated = match latest.entry_body {
+        Entry::AgentId(ref agent_id) => agent_id.update(nick, &latest_address),
+        _ => unreachable!(),
+    };
+
+    let commit_result = await!(commit_entry(
+        Entry::AgentId(updated),
+        Some(latest.header.address()),
+        context
+    ))?;
+    await!(update_entry(
+        context,
+        &context.action_channel,
+        latest_address,
+        commit_result.address.clone()
+    ))?;
+    // Publishing the new identity also publishes the link from the one it replaces
+    await!(publish(commit_result.address.clone(), context))?;
+    Ok(commit_result.address)
+}
+
+/// Checks that an identity update comes from the agent whose identity it replaces:
+/// its header has to be signed with the key of the replaced identity, and it has to keep
+/// that key. Who published the update and which sources its header claims prove nothing,
+/// anyone could tell those. Key rotation would accept a successor key that the replaced
+/// identity declared instead, see is_successor_key().
+/// Fails with HolochainError::ValidationFailed otherwise.
+pub(crate) fn check_identity_update(
+    replaced: &AgentId,
+    update: &AgentId,
+    header: &ChainHeader,
+) -> Result<(), HolochainError> {
+    let update_address = Entry::AgentId(update.clone()).address();
+    let signed = String::from(update_address.clone());
+    let signed_by_agent = *header.entry_address() == update_address
+        && header
+            .sources()
+            .iter()
+            .zip(header.entry_signatures())
+            .any(|(source, signature)| {
+                *source == replaced.address()
+                    && verify_signature(&replaced.key, signed.as_bytes(), signature)
+            });
+    if !signed_by_agent {
+        return Err(HolochainError::ValidationFailed(format!(
+            "Identity update of {} is not signed by its agent",
+            replaced.address()
+        )));
+    }
+    if !is_successor_key(replaced, &update.key) {
+        return Err(HolochainError::ValidationFailed(format!(
+            "Identity update of {} changes its key",
+            replaced.address()
+        )));
+    }
+    Ok(())
+}
+
+/// Whether an identity that replaces the given one may have the given key.
+/// Keys can't be rotated yet, so only the key of the replaced identity succeeds it.
+fn is_successor_key(replaced: &AgentId, key: &str) -> bool {
+    replaced.key == key
+}
+
+/// Fetches the identity that the given identity update replaces, from the network
+/// if we don't hold it ourselves. Fails with HolochainError::ValidationFailed if the
+/// update replaces something else.
+async fn get_replaced_identity<'a>(
+    update: &'a AgentId,
+    context: &'a Arc<Context>,
+) -> Result<AgentId, HolochainError> {
+    let replaced_address = update.replaces.clone().ok_or_else(|| {
+        HolochainError::ErrorGeneric(String::from("Initial identities replace none"))
+    })?;
+    match await!(get_entry_with_meta_workflow(context, &replaced_address))? {
+        Some(entry_with_meta) => match entry_with_meta.entry {
+            Entry::AgentId(replaced) => Ok(replaced),
+            _ => Err(HolochainError::ValidationFailed(format!(
+                "Identity update replaces {}, which is no identity",
+                replaced_address
+            ))),
+        },
+        None => Err(HolochainError::ErrorGeneric(format!(
+            "Could not get the identity {} that got updated",
+            replaced_address
+        ))),
+    }
+}
+
+/// Checks an updated identity that the network asks us to hold, see check_identity_update().
+/// Initial identities need no check.
+pub(crate) async fn check_identity_entry<'a>(
+    agent_id: &'a AgentId,
+    header: &'a ChainHeader,
+    context: &'a Arc<Context>,
+) -> Result<(), HolochainError> {
+    if agent_id.is_initial() {
+        return Ok(());
+    }
+    let replaced = await!(get_replaced_identity(agent_id, context))?;
+    check_identity_update(&replaced, agent_id, header)
+}
+
+/// The publisher of an identity we hold links it to the identity that replaces it,
+/// which comes along with its header. Applies that link to our DHT shard once the new
+/// identity checks out, so that getting the agent's address from us resolves to it.
+pub async fn hold_identity_update_workflow<'a>(
+    replaced_address: &'a Address,
+    update: &'a EntryWithHeader,
+    context: &'a Arc<Context>,
+) -> Result<(), HolochainError> {
+    let replaced = match get_entry_with_meta(context, replaced_address.clone())? {
+        Some(entry_with_meta) => entry_with_meta.entry,
+        None => {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "Not holding updated identity {}",
+                replaced_address
+            )));
+        }
+    };
+    let update_address = update.entry_body.address();
+    match (&replaced, &update.entry_body) {
+        (Entry::AgentId(replaced), Entry::AgentId(agent_id))
+            if agent_id.replaces.as_ref() == Some(replaced_address) =>
+        {
+            check_identity_update(replaced, agent_id, &update.header)?
+        }
+        _ => {
+            return Err(HolochainError::ValidationFailed(format!(
+                "{} is no update of identity {}",
+                update_address, replaced_address
+            )));
+        }
+    }
+    await!(update_entry(
+        context,
+        &context.action_channel,
+        replaced_address.clone(),
+        update_address
+    ))?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        agent::keys::SigningKeys,
+        instance::tests::{test_context_of_test_agent, test_instance_with_context},
+        nucleus::actions::{get_entry::get_entry_crud_meta_from_dht, tests::*},
+        test_fixtures::test_agent,
+        workflows::get_entry_history::get_entry_history_workflow,
+    };
+    use futures::executor::block_on;
+    use holochain_core_types::{
+        crud_status::CrudStatus, entry::entry_type::EntryType, signature::Signature, time::Iso8601,
+    };
+    use holochain_wasm_utils::api_serialization::get_entry::{GetEntryArgs, GetEntryOptions};
+    use std::{thread, time::Duration};
+
+    /// The header of the given update that claims the given source and carries
+    /// the given signature
+    fn update_header(update: &AgentId, source: &AgentId, signature: Signature) -> ChainHeader {
+        ChainHeader::new(
+            &EntryType::AgentId,
+            &Entry::AgentId(update.clone()).address(),
+            &vec![source.address()],
+            &vec![signature],
+            &None,
+            &None,
+            &None,
+            &Iso8601::from(""),
+        )
+    }
+
+    fn sign_update(update: &AgentId, keys: &mut SigningKeys) -> Signature {
+        keys.sign(String::from(Entry::AgentId(update.clone()).address()).as_bytes())
+    }
+
+    /// Waits until the given condition holds
+    fn wait_for<F: Fn() -> bool>(condition: F) -> bool {
+        for _ in 0..100 {
+            if condition() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        false
+    }
+
+    #[test]
+    fn identity_updates_have_to_keep_agent_and_key() {
+        let mut jill_keys = test_agent("jill").signing_keys;
+        let jill = test_agent("jill").agent_id;
+        let update = jill.update("jill2", &jill.address());
+        let header = update_header(&update, &jill, sign_update(&update, &mut jill_keys));
+        assert!(check_identity_update(&jill, &update, &header).is_ok());
+
+        let mallory = test_agent("mallory").agent_id;
+        let rotated = mallory.update("jill2", &jill.address());
+        let signature = sign_update(&rotated, &mut jill_keys);
+        match check_identity_update(&jill, &rotated, &update_header(&rotated, &jill, signature)) {
+            Err(HolochainError::ValidationFailed(_)) => (),
+            result => panic!("expected ValidationFailed, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn identity_updates_have_to_be_signed_by_the_replaced_key() {
+        let mut jill_keys = test_agent("jill").signing_keys;
+        let mut mallory_keys = test_agent("mallory").signing_keys;
+        let jill = test_agent("jill").agent_id;
+        let mallory = test_agent("mallory").agent_id;
+        let update = jill.update("jill2", &jill.address());
+
+        let forged_headers = vec![
+            // claiming to be jill takes more than saying so
+            update_header(&update, &jill, Signature::from("")),
+            update_header(&update, &jill, sign_update(&update, &mut mallory_keys)),
+            // signed by mallory as mallory
+            update_header(&update, &mallory, sign_update(&update, &mut mallory_keys)),
+            // jill's signature of another update
+            update_header(
+                &update,
+                &jill,
+                sign_update(&jill.update("jill3", &jill.address()), &mut jill_keys),
+            ),
+        ];
+        for header in forged_headers {
+            match check_identity_update(&jill, &update, &header) {
+                Err(HolochainError::ValidationFailed(_)) => (),
+                result => panic!("expected ValidationFailed, got {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    /// test that another node that holds our identity resolves our address to the
+    /// identity we updated it with
+    fn other_nodes_resolve_updated_identity() {
+        let dna = test_dna();
+        let (_instance1, context1) =
+            test_instance_with_context(dna.clone(), test_context_of_test_agent("jill"))
+                .expect("Could not create test instance");
+        let (_instance2, context2) = instance_by_name("jack", dna);
+        let agent_address = context1.agent_id.address();
+
+        block_on(publish(agent_address.clone(), &context1)).expect("Could not publish identity");
+        let holds_identity = || {
+            get_entry_with_meta(&context2, agent_address.clone())
+                .unwrap()
+                .is_some()
+        };
+        assert!(wait_for(holds_identity));
+
+        let update_address =
+            block_on(update_agent_workflow("jill2", &context1)).expect("Could not update agent");
+        assert_ne!(update_address, agent_address);
+        let holds_update = || {
+            get_entry_crud_meta_from_dht(&context2, agent_address.clone()).unwrap()
+                == Some((CrudStatus::MODIFIED, Some(update_address.clone())))
+        };
+        assert!(wait_for(holds_update));
+
+        let args = GetEntryArgs {
+            address: agent_address.clone(),
+            options: GetEntryOptions::default(),
+            resolve_latest: true,
+        };
+        let history = block_on(get_entry_history_workflow(&context2, &args)).unwrap();
+        match history.entries.iter().next() {
+            Some(Entry::AgentId(agent_id)) => {
+                assert_eq!(agent_id.nick, "jill2");
+                assert_eq!(agent_id.address(), agent_address);
+            }
+            entry => panic!("expected the updated identity, got {:?}", entry),
+        }
+    }
+}
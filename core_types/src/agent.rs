@@ -29,6 +29,13 @@ impl KeyBuffer {
     pub fn with_corrected(s: &str) -> Result<KeyBuffer, HolochainError> {
         let s = s.replace("-", "+").replace("_", "/");
         let s = base64::decode(&s)?;
+        if s.len() != 64 + PARITY_LEN {
+            return Err(HolochainError::ErrorGeneric(format!(
+                "An identity key has {} bytes, got {}",
+                64 + PARITY_LEN,
+                s.len()
+            )));
+        }
         let dec = Decoder::new(PARITY_LEN);
         let dec = *dec.correct(s.as_slice(), None)?;
         Ok(KeyBuffer::with_raw(array_ref![dec, 0, 64]))
@@ -60,12 +67,16 @@ impl KeyBuffer {
 /// agent data that can be stored in the cas
 /// note thate the "address" of an agent entry is the base64url encoded
 /// public key identity string
+/// An agent can update its identity later on, see `AgentId::update()`.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
 pub struct AgentId {
     /// a nickname for referencing this agent
     pub nick: String,
     /// the base64url encoded public identity string for this agent
     pub key: String,
+    /// the address of the identity entry this one updates, None for the initial identity
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaces: Option<Address>,
 }
 
 impl AgentId {
@@ -90,9 +101,27 @@ impl AgentId {
         AgentId {
             nick: nick.to_string(),
             key: key.render(),
+            replaces: None,
         }
     }
 
+    /// the identity that updates the identity entry at the given address, which is
+    /// this identity's, with the given `nick`.
+    /// Unlike the initial identity, which is addressed by the key, updated identities
+    /// are addressed by their content like other entries.
+    pub fn update(&self, nick: &str, replaced_address: &Address) -> Self {
+        AgentId {
+            nick: nick.to_string(),
+            key: self.key.clone(),
+            replaces: Some(replaced_address.clone()),
+        }
+    }
+
+    /// whether this is the initial identity of the agent, i.e. it updates no other
+    pub fn is_initial(&self) -> bool {
+        self.replaces.is_none()
+    }
+
     /// get a key buffer based on this agent's key (no correction)
     pub fn to_buffer(&self) -> KeyBuffer {
         let key = base64::decode(&self.key).expect("corrupt identity key");
@@ -163,6 +192,13 @@ mod tests {
         assert_eq!("sandwich----------------------------------------------------------------------------AA-k".to_string(), AgentId::generate_fake("sandwich").address().to_string());
     }
 
+    #[test]
+    fn it_should_reject_keys_of_the_wrong_length() {
+        assert!(
+            KeyBuffer::with_corrected("QmXxdzM9uHiSfV1xDwUxMm5jX4rVU8jhtWVaeCzjkFW249").is_err()
+        );
+    }
+
     #[test]
     fn it_should_correct_errors() {
         assert_eq!(GOOD_ID.to_string(), test_agent_id().address().to_string());
@@ -174,6 +210,24 @@ mod tests {
         assert_eq!(test_identity_value(), test_agent_id().into());
     }
 
+    #[test]
+    /// show that only updated identities say which identity they replace
+    fn agent_update_test() {
+        let initial = test_agent_id();
+        let updated = initial.update("robert", &initial.address());
+        assert!(initial.is_initial());
+        assert!(!updated.is_initial());
+        assert_eq!(updated.key, initial.key);
+        assert_eq!(updated.address(), initial.address());
+        assert_eq!(
+            Content::from(format!(
+                "{{\"nick\":\"robert\",\"key\":\"{}\",\"replaces\":\"{}\"}}",
+                GOOD_ID, GOOD_ID
+            )),
+            updated.into()
+        );
+    }
+
     #[test]
     /// show AddressableContent implementation for Agent
     fn agent_addressable_content_test() {
@@ -29,6 +29,17 @@ pub trait ContentAddressableStorage: objekt::Clone + Send + Sync + Debug {
     /// AddressableContent::from_content() can be used to allow the compiler to infer the type
     /// @see the fetch implementation for ExampleCas in the cas module tests
     fn fetch(&self, address: &Address) -> Result<Option<Content>, HolochainError>;
+    /// returns the addresses of everything in the Store
+    fn addresses(&self) -> Result<HashSet<Address>, HolochainError>;
+    /// removes everything from the Store that is not in roots, the set of addresses that
+    /// are still in use, and reports what that freed up.
+    /// this is the sweep phase of a garbage collection, marking the roots is up to the caller
+    fn gc(&mut self, roots: &HashSet<Address>) -> Result<GcReport, HolochainError>;
+    /// makes sure everything added so far survives a crash of the process or the machine,
+    /// e.g. by syncing it to the disk. Storages that don't persist have nothing to do.
+    fn sync(&self) -> Result<(), HolochainError> {
+        Ok(())
+    }
     //needed to find a way to compare two different CAS for partialord derives.
     //easiest solution was to just compare two ids which are based on uuids
     fn get_id(&self) -> Uuid;
@@ -36,6 +47,17 @@ pub trait ContentAddressableStorage: objekt::Clone + Send + Sync + Debug {
 
 clone_trait_object!(ContentAddressableStorage);
 
+/// what a garbage collection of a ContentAddressableStorage kept and removed
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GcReport {
+    /// number of items that were in the roots and got kept
+    pub kept: usize,
+    /// number of items that were not in the roots and got removed
+    pub removed: usize,
+    /// size of the content of the removed items
+    pub bytes_reclaimed: u64,
+}
+
 impl PartialEq for ContentAddressableStorage {
     fn eq(&self, other: &ContentAddressableStorage) -> bool {
         self.get_id() == other.get_id()
@@ -78,6 +100,14 @@ impl ContentAddressableStorage for ExampleContentAddressableStorage {
         Ok(self.content.read()?.unthreadable_fetch(address)?)
     }
 
+    fn addresses(&self) -> Result<HashSet<Address>, HolochainError> {
+        self.content.read()?.unthreadable_addresses()
+    }
+
+    fn gc(&mut self, roots: &HashSet<Address>) -> Result<GcReport, HolochainError> {
+        self.content.write()?.unthreadable_gc(roots)
+    }
+
     fn get_id(&self) -> Uuid {
         Uuid::new_v4()
     }
@@ -112,6 +142,25 @@ impl ExampleContentAddressableStorageContent {
     fn unthreadable_fetch(&self, address: &Address) -> Result<Option<Content>, HolochainError> {
         Ok(self.storage.get(address).cloned())
     }
+
+    fn unthreadable_addresses(&self) -> Result<HashSet<Address>, HolochainError> {
+        Ok(self.storage.keys().cloned().collect())
+    }
+
+    fn unthreadable_gc(&mut self, roots: &HashSet<Address>) -> Result<GcReport, HolochainError> {
+        let mut report = GcReport::default();
+        self.storage.retain(|address, content| {
+            if roots.contains(address) {
+                report.kept += 1;
+                true
+            } else {
+                report.removed += 1;
+                report.bytes_reclaimed += content.to_string().len() as u64;
+                false
+            }
+        });
+        Ok(report)
+    }
 }
 
 //A struct for our test suite that infers a type of ContentAddressableStorage
@@ -231,6 +280,46 @@ where
 
         handle.join().unwrap();
     }
+
+    /// adds both contents and collects garbage with only the first one as root,
+    /// which removes exactly the other one
+    pub fn gc_test(mut self, kept_content: Content, removed_content: Content) {
+        assert_eq!(Ok(()), self.cas.add(&kept_content));
+        assert_eq!(Ok(()), self.cas.add(&removed_content));
+        let mut both = HashSet::new();
+        both.insert(kept_content.address());
+        both.insert(removed_content.address());
+        assert_eq!(Ok(both), self.cas_clone.addresses());
+
+        let mut roots = HashSet::new();
+        roots.insert(kept_content.address());
+        assert_eq!(
+            Ok(GcReport {
+                kept: 1,
+                removed: 1,
+                bytes_reclaimed: removed_content.to_string().len() as u64,
+            }),
+            self.cas_clone.gc(&roots)
+        );
+
+        for cas in vec![self.cas.clone(), self.cas_clone.clone()].iter() {
+            assert_eq!(Ok(roots.clone()), cas.addresses());
+            assert_eq!(
+                Ok(Some(kept_content.clone())),
+                cas.fetch(&kept_content.address())
+            );
+            assert_eq!(Ok(false), cas.contains(&removed_content.address()));
+        }
+
+        // nothing left to remove
+        assert_eq!(
+            Ok(GcReport {
+                kept: 1,
+                ..Default::default()
+            }),
+            self.cas.gc(&roots)
+        );
+    }
 }
 
 pub struct EavTestSuite;
@@ -452,4 +541,13 @@ pub mod tests {
             JsonString::from(RawString::from("bar")),
         );
     }
+
+    #[test]
+    fn example_gc_test() {
+        let test_suite = StorageTestSuite::new(test_content_addressable_storage());
+        test_suite.gc_test(
+            JsonString::from(RawString::from("foo")),
+            JsonString::from(RawString::from("bar")),
+        );
+    }
 }
@@ -10,7 +10,7 @@ use crate::{
     signature::{test_signatures, Signature},
     time::{test_iso_8601, Iso8601},
 };
-use std::convert::TryInto;
+use std::{convert::TryInto, fmt};
 
 /// ChainHeader of a source chain "Item"
 /// The address of the ChainHeader is used as the Item's key in the source chain hash table
@@ -38,6 +38,27 @@ pub struct ChainHeader {
     link_crud: Option<Address>,
     /// ISO8601 time stamp
     timestamp: Iso8601,
+    /// Position of this header in its chain, counting from 1 for the first header.
+    /// Headers from before sequence numbers were introduced have 0, see `sequence()`.
+    #[serde(default, skip_serializing_if = "is_unindexed")]
+    sequence: u64,
+}
+
+fn is_unindexed(sequence: &u64) -> bool {
+    *sequence == 0
+}
+
+/// Human readable summary of a header: entry type, shortened entry address and timestamp
+impl fmt::Display for ChainHeader {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} @ {}",
+            self.entry_type,
+            self.entry_address.short(),
+            self.timestamp
+        )
+    }
 }
 
 impl PartialEq for ChainHeader {
@@ -74,9 +95,16 @@ impl ChainHeader {
             link_same_type: link_same_type.to_owned(),
             link_crud: link_crud.to_owned(),
             timestamp: timestamp.to_owned(),
+            sequence: 0,
         }
     }
 
+    /// Sets the position of the header in its chain, see `sequence()`
+    pub fn with_sequence(mut self, sequence: u64) -> Self {
+        self.sequence = sequence;
+        self
+    }
+
     /// entry_type getter
     pub fn entry_type(&self) -> &EntryType {
         &self.entry_type
@@ -115,6 +143,21 @@ impl ChainHeader {
     pub fn sources(&self) -> &Vec<Address> {
         &self.sources
     }
+
+    /// Position of the header in its chain: 1 for the first header, one more than the
+    /// previous header for every other. 0 marks an unindexed legacy header, which left
+    /// its sequence out of its content, so that its address stays the same.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Whether this header may follow the given previous header, None for the first header
+    /// of a chain: its sequence has to be one more than the previous one. Legacy headers
+    /// may only follow legacy headers.
+    pub fn follows_in_sequence(&self, previous: Option<&ChainHeader>) -> bool {
+        let previous_sequence = previous.map(ChainHeader::sequence).unwrap_or(0);
+        (self.sequence == 0 && previous_sequence == 0) || self.sequence == previous_sequence + 1
+    }
 }
 
 impl AddressableContent for ChainHeader {
@@ -181,6 +224,18 @@ pub mod tests {
         Address::from("Qmc1n5gbUU2QKW6is9ENTqmaTcEjYMBwNkcACCxe3bBDnd".to_string())
     }
 
+    #[test]
+    /// tests for Display
+    fn display() {
+        assert_eq!(
+            format!("{}", test_chain_header()),
+            format!(
+                "testEntryType {} @ 2018-10-11T03:23:38+00:00",
+                test_entry().address().short()
+            ),
+        );
+    }
+
     #[test]
     /// tests for PartialEq
     fn eq() {
@@ -321,6 +376,28 @@ pub mod tests {
         );
     }
 
+    #[test]
+    /// test that sequences have to count up from 1 and legacy headers stay as they were
+    fn sequence_test() {
+        let legacy = test_chain_header();
+        assert_eq!(legacy.sequence(), 0);
+        assert!(!String::from(legacy.content()).contains("sequence"));
+        assert!(legacy.follows_in_sequence(None));
+
+        let first = test_chain_header().with_sequence(1);
+        let second = test_chain_header_b().with_sequence(2);
+        assert_ne!(first.address(), legacy.address());
+        assert!(first.follows_in_sequence(None));
+        assert!(second.follows_in_sequence(Some(&first)));
+        assert!(first.follows_in_sequence(Some(&legacy)));
+        assert!(legacy.follows_in_sequence(Some(&legacy)));
+
+        assert!(!second.follows_in_sequence(None));
+        assert!(!first.follows_in_sequence(Some(&second)));
+        assert!(!second.follows_in_sequence(Some(&second)));
+        assert!(!legacy.follows_in_sequence(Some(&first)));
+    }
+
     #[test]
     /// tests for chain_header.entry_signature()
     fn signature() {
@@ -413,5 +490,4 @@ pub mod tests {
             .address(),
         );
     }
-
 }
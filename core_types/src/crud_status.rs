@@ -35,6 +35,8 @@ bitflags! {
         const MODIFIED = 0x08;
         /// CRDT resolution in progress
         const LOCKED = 0x10;
+        /// held past the TTL of its entry type
+        const EXPIRED = 0x20;
     }
 }
 
@@ -49,6 +51,7 @@ impl From<CrudStatus> for String {
             CrudStatus::DELETED => "4",
             CrudStatus::MODIFIED => "8",
             CrudStatus::LOCKED => "16",
+            CrudStatus::EXPIRED => "32",
             _ => unreachable!(),
         })
     }
@@ -68,6 +71,7 @@ impl From<String> for CrudStatus {
             "4" => CrudStatus::DELETED,
             "8" => CrudStatus::MODIFIED,
             "16" => CrudStatus::LOCKED,
+            "32" => CrudStatus::EXPIRED,
             _ => unreachable!(),
         }
     }
@@ -102,13 +106,14 @@ mod tests {
     /// test the CrudStatus bit flags as ints
     fn status_bits() {
         assert_eq!(CrudStatus::default().bits(), 0);
-        assert_eq!(CrudStatus::all().bits(), 31);
+        assert_eq!(CrudStatus::all().bits(), 63);
 
         assert_eq!(CrudStatus::LIVE.bits(), 1);
         assert_eq!(CrudStatus::REJECTED.bits(), 2);
         assert_eq!(CrudStatus::DELETED.bits(), 4);
         assert_eq!(CrudStatus::MODIFIED.bits(), 8);
         assert_eq!(CrudStatus::LOCKED.bits(), 16);
+        assert_eq!(CrudStatus::EXPIRED.bits(), 32);
     }
 
     #[test]
@@ -144,6 +149,7 @@ mod tests {
         assert_eq!(String::from("4"), String::from(CrudStatus::DELETED));
         assert_eq!(String::from("8"), String::from(CrudStatus::MODIFIED));
         assert_eq!(String::from("16"), String::from(CrudStatus::LOCKED));
+        assert_eq!(String::from("32"), String::from(CrudStatus::EXPIRED));
     }
 
     #[test]
@@ -154,6 +160,7 @@ mod tests {
         assert_eq!(CrudStatus::from("4"), CrudStatus::DELETED);
         assert_eq!(CrudStatus::from("8"), CrudStatus::MODIFIED);
         assert_eq!(CrudStatus::from("16"), CrudStatus::LOCKED);
+        assert_eq!(CrudStatus::from("32"), CrudStatus::EXPIRED);
 
         assert_eq!(CrudStatus::from(String::from("1")), CrudStatus::LIVE);
         assert_eq!(CrudStatus::from(String::from("2")), CrudStatus::REJECTED);
@@ -202,6 +209,7 @@ mod tests {
             CrudStatus::DELETED,
             CrudStatus::MODIFIED,
             CrudStatus::LOCKED,
+            CrudStatus::EXPIRED,
         ];
         AddressableContentTestSuite::addressable_content_round_trip::<
             CrudStatus,
@@ -0,0 +1,91 @@
+//! The bridges to other DNAs the zomes of a DNA call, as declared in its manifest.
+//! Containers check that the bridges of their instances satisfy these declarations
+//! before starting them.
+
+/// Whether instances of the DNA can run without the bridge
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum BridgePresence {
+    /// Instances don't start unless the bridge is configured
+    Required,
+    /// Calls over the bridge fail with HolochainError::BridgeNotPresent if it is not configured
+    Optional,
+}
+
+/// A function the instance at the other end of a bridge has to have
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct BridgeFunction {
+    pub zome: String,
+    pub function: String,
+}
+
+/// A bridge the zomes of the DNA call another instance through, by its handle.
+/// The container configures which instance that is, the declaration says what it has to be:
+/// an instance of the DNA with `dna_hash`, or any instance with all of `functions`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[serde(deny_unknown_fields)]
+pub struct BridgeDeclaration {
+    pub handle: String,
+    pub presence: BridgePresence,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dna_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub functions: Vec<BridgeFunction>,
+}
+
+impl BridgeDeclaration {
+    pub fn is_required(&self) -> bool {
+        self.presence == BridgePresence::Required
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{dna::Dna, json::JsonString};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn bridges_come_from_the_manifest() {
+        let dna = Dna::try_from(JsonString::from(
+            r#"{
+                "bridges": [
+                    {"handle": "persona", "presence": "required", "dna_hash": "QmPersona"},
+                    {
+                        "handle": "search",
+                        "presence": "optional",
+                        "functions": [{"zome": "index", "function": "query"}]
+                    }
+                ]
+            }"#,
+        ))
+        .unwrap();
+        assert_eq!(
+            dna.bridges,
+            vec![
+                BridgeDeclaration {
+                    handle: String::from("persona"),
+                    presence: BridgePresence::Required,
+                    dna_hash: Some(String::from("QmPersona")),
+                    functions: Vec::new(),
+                },
+                BridgeDeclaration {
+                    handle: String::from("search"),
+                    presence: BridgePresence::Optional,
+                    dna_hash: None,
+                    functions: vec![BridgeFunction {
+                        zome: String::from("index"),
+                        function: String::from("query"),
+                    }],
+                },
+            ]
+        );
+        assert!(dna.bridge("persona").unwrap().is_required());
+        assert_eq!(dna.bridge("other"), None);
+
+        // DNAs without bridges hash like they did before bridges existed
+        let json = String::from(JsonString::from(Dna::new()));
+        assert!(!json.contains("bridges"));
+    }
+}
@@ -0,0 +1,38 @@
+//! Provenance of a packaged DNA, so a running instance tells which code it runs.
+
+use std::collections::BTreeMap;
+
+/// What `hc package` knows about the build of a DNA.
+/// All of it is part of the hash of the DNA, except for the `unhashed` section,
+/// see `Dna::hashable()`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct BuildInfo {
+    /// Version of the `hc` that packaged the DNA
+    #[serde(default)]
+    pub hc_version: String,
+
+    /// Versions of the HDK the zomes were built with, by zome, as locked in their Cargo.lock
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hdk_versions: BTreeMap<String, String>,
+
+    /// Commit the project was at, if it is a git repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+
+    /// Whether the project had uncommitted changes on top of that commit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_dirty: Option<bool>,
+
+    /// What differs between two builds of the same code
+    #[serde(default)]
+    pub unhashed: UnhashedBuildInfo,
+}
+
+/// The section of the build info that hashes of the DNA skip,
+/// so packaging the same code twice gives the same DNA hash
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct UnhashedBuildInfo {
+    /// When the DNA got packaged, RFC 3339
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub built_at: Option<String>,
+}
@@ -0,0 +1,471 @@
+//! Structured report of how two versions of a DNA differ, as computed by `Dna::diff()`.
+//! Tooling like `hc diff` uses it to tell whether a new version can replace an old one.
+
+use crate::{
+    dna::{
+        zome::{entry_types::Sharing, host_permissions::HostPermission, Zome},
+        Dna,
+    },
+    error::HolochainError,
+    hash::HashString,
+    json::JsonString,
+};
+use multihash::Hash;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How the sharing of an entry type changed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SharingChange {
+    pub old: Sharing,
+    pub new: Sharing,
+}
+
+/// How the validation code of a zome changed, by the hashes of its WASM
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CodeChange {
+    pub old_hash: HashString,
+    pub new_hash: HashString,
+}
+
+/// Differences within a zome that is part of both DNA versions.
+/// Functions are named `capability/function`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct ZomeDiff {
+    pub added_functions: Vec<String>,
+    pub removed_functions: Vec<String>,
+    pub added_entry_types: Vec<String>,
+    pub removed_entry_types: Vec<String>,
+    /// Entry types of both versions whose sharing changed, by name
+    pub changed_sharing: BTreeMap<String, SharingChange>,
+    pub changed_code: Option<CodeChange>,
+    /// Host permissions the zome gained or lost, see `HostPermission`.
+    /// A zome that declares none has all of them.
+    pub added_host_permissions: Vec<String>,
+    pub removed_host_permissions: Vec<String>,
+}
+
+impl ZomeDiff {
+    /// True if both versions of the zome are the same as far as a diff can tell
+    pub fn is_empty(&self) -> bool {
+        *self == ZomeDiff::default()
+    }
+
+    /// Removed functions and entry types break callers and existing data,
+    /// and so do entry types that get shared differently. Added host permissions
+    /// break the guarantees users of the zome relied on.
+    pub fn is_breaking(&self) -> bool {
+        !self.removed_functions.is_empty()
+            || !self.removed_entry_types.is_empty()
+            || !self.changed_sharing.is_empty()
+            || !self.added_host_permissions.is_empty()
+    }
+}
+
+/// Differences between an old and a new version of a DNA
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default, DefaultJson)]
+pub struct DnaDiff {
+    pub added_zomes: Vec<String>,
+    pub removed_zomes: Vec<String>,
+    /// Zomes of both versions that differ, by name
+    pub changed_zomes: BTreeMap<String, ZomeDiff>,
+    /// Top-level properties that got added, removed or changed their value
+    pub changed_properties: Vec<String>,
+}
+
+impl DnaDiff {
+    /// True if both versions of the DNA are the same as far as a diff can tell
+    pub fn is_empty(&self) -> bool {
+        *self == DnaDiff::default()
+    }
+
+    /// True if the new version can not replace the old one without breaking something:
+    /// a zome, function or entry type got removed, an entry type is shared differently,
+    /// or a zome may call host functions it could not call before.
+    /// Additions and changes of code or properties are not breaking.
+    pub fn is_breaking(&self) -> bool {
+        !self.removed_zomes.is_empty() || self.changed_zomes.values().any(ZomeDiff::is_breaking)
+    }
+}
+
+/// Elements of `new` that `old` lacks, and elements of `old` that `new` lacks
+fn added_and_removed(old: &BTreeSet<String>, new: &BTreeSet<String>) -> (Vec<String>, Vec<String>) {
+    (
+        new.difference(old).cloned().collect(),
+        old.difference(new).cloned().collect(),
+    )
+}
+
+fn function_names(zome: &Zome) -> BTreeSet<String> {
+    zome.capabilities
+        .iter()
+        .flat_map(|(capability_name, capability)| {
+            capability
+                .functions
+                .iter()
+                .map(move |function| format!("{}/{}", capability_name, function.name))
+        })
+        .collect()
+}
+
+fn entry_type_names(zome: &Zome) -> BTreeSet<String> {
+    zome.entry_types
+        .keys()
+        .map(|entry_type| String::from(entry_type.to_owned()))
+        .collect()
+}
+
+fn host_permission_names(zome: &Zome) -> BTreeSet<String> {
+    zome.host_permissions
+        .clone()
+        .unwrap_or_else(HostPermission::all)
+        .iter()
+        .map(|permission| permission.to_string())
+        .collect()
+}
+
+fn code_hash(zome: &Zome) -> HashString {
+    HashString::encode_from_bytes(&zome.code.code, Hash::SHA2256)
+}
+
+fn diff_zomes(old: &Zome, new: &Zome) -> ZomeDiff {
+    let (added_functions, removed_functions) =
+        added_and_removed(&function_names(old), &function_names(new));
+    let (added_entry_types, removed_entry_types) =
+        added_and_removed(&entry_type_names(old), &entry_type_names(new));
+    let (added_host_permissions, removed_host_permissions) =
+        added_and_removed(&host_permission_names(old), &host_permission_names(new));
+
+    let changed_sharing = old
+        .entry_types
+        .iter()
+        .filter_map(|(entry_type, old_def)| {
+            let new_def = new.entry_types.get(entry_type)?;
+            if old_def.sharing == new_def.sharing {
+                return None;
+            }
+            Some((
+                String::from(entry_type.to_owned()),
+                SharingChange {
+                    old: old_def.sharing.clone(),
+                    new: new_def.sharing.clone(),
+                },
+            ))
+        })
+        .collect();
+
+    let changed_code = if old.code == new.code {
+        None
+    } else {
+        Some(CodeChange {
+            old_hash: code_hash(old),
+            new_hash: code_hash(new),
+        })
+    };
+
+    ZomeDiff {
+        added_functions,
+        removed_functions,
+        added_entry_types,
+        removed_entry_types,
+        changed_sharing,
+        changed_code,
+        added_host_permissions,
+        removed_host_permissions,
+    }
+}
+
+fn changed_properties(old: &Value, new: &Value) -> Vec<String> {
+    let empty = Map::new();
+    let old = old.as_object().unwrap_or(&empty);
+    let new = new.as_object().unwrap_or(&empty);
+    old.keys()
+        .chain(new.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|name| old.get(*name) != new.get(*name))
+        .cloned()
+        .collect()
+}
+
+impl Dna {
+    /// How `other`, the new version of this DNA, differs from it
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use holochain_core_types::dna::{zome::Zome, Dna};
+    ///
+    /// let old = Dna::new();
+    /// let mut new = old.clone();
+    /// new.zomes.insert("blog".to_string(), Zome::default());
+    ///
+    /// let diff = old.diff(&new);
+    /// assert_eq!(diff.added_zomes, vec!["blog".to_string()]);
+    /// assert!(!diff.is_breaking());
+    /// assert!(new.diff(&old).is_breaking());
+    /// ```
+    pub fn diff(&self, other: &Dna) -> DnaDiff {
+        let old_zomes: BTreeSet<String> = self.zomes.keys().cloned().collect();
+        let new_zomes: BTreeSet<String> = other.zomes.keys().cloned().collect();
+        let (added_zomes, removed_zomes) = added_and_removed(&old_zomes, &new_zomes);
+
+        let changed_zomes = self
+            .zomes
+            .iter()
+            .filter_map(|(name, old_zome)| {
+                let zome_diff = diff_zomes(old_zome, other.zomes.get(name)?);
+                if zome_diff.is_empty() {
+                    None
+                } else {
+                    Some((name.clone(), zome_diff))
+                }
+            })
+            .collect();
+
+        DnaDiff {
+            added_zomes,
+            removed_zomes,
+            changed_zomes,
+            changed_properties: changed_properties(&self.properties, &other.properties),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::entry::entry_type::EntryType;
+    use std::convert::TryFrom;
+
+    fn fixture_dna() -> Dna {
+        Dna::try_from(JsonString::from(
+            r#"{
+                "name": "test",
+                "properties": {
+                    "max_length": 100,
+                    "moderated": false
+                },
+                "zomes": {
+                    "blog": {
+                        "entry_types": {
+                            "post": { "sharing": "public" },
+                            "draft": { "sharing": "private" }
+                        },
+                        "capabilities": {
+                            "main": {
+                                "capability": { "membrane": "public" },
+                                "functions": [
+                                    { "name": "create_post", "inputs": [], "outputs": [] },
+                                    { "name": "get_post", "inputs": [], "outputs": [] }
+                                ]
+                            }
+                        },
+                        "code": { "code": "AAECAw==" }
+                    },
+                    "profile": {
+                        "code": { "code": "AAECAw==" }
+                    }
+                }
+            }"#,
+        ))
+        .unwrap()
+    }
+
+    fn blog_diff(diff: &DnaDiff) -> &ZomeDiff {
+        diff.changed_zomes
+            .get("blog")
+            .expect("blog zome should differ")
+    }
+
+    #[test]
+    fn same_dna_has_empty_diff() {
+        let diff = fixture_dna().diff(&fixture_dna());
+        assert!(diff.is_empty());
+        assert!(!diff.is_breaking());
+    }
+
+    #[test]
+    fn diffs_zomes() {
+        let old = fixture_dna();
+        let mut new = fixture_dna();
+        let profile = new.zomes.remove("profile").unwrap();
+        new.zomes.insert("comments".to_string(), profile);
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added_zomes, vec!["comments".to_string()]);
+        assert_eq!(diff.removed_zomes, vec!["profile".to_string()]);
+        assert!(diff.changed_zomes.is_empty());
+        assert!(diff.is_breaking());
+
+        // Only adding the zome is fine
+        new.zomes
+            .insert("profile".to_string(), old.zomes["profile"].clone());
+        let diff = old.diff(&new);
+        assert_eq!(diff.added_zomes, vec!["comments".to_string()]);
+        assert!(diff.removed_zomes.is_empty());
+        assert!(!diff.is_breaking());
+    }
+
+    #[test]
+    fn diffs_functions() {
+        let old = fixture_dna();
+        let mut new = fixture_dna();
+        {
+            let functions = &mut new
+                .zomes
+                .get_mut("blog")
+                .unwrap()
+                .capabilities
+                .get_mut("main")
+                .unwrap()
+                .functions;
+            let mut update_post = functions.remove(1);
+            update_post.name = "update_post".to_string();
+            functions.push(update_post);
+        }
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            blog_diff(&diff).added_functions,
+            vec!["main/update_post".to_string()]
+        );
+        assert_eq!(
+            blog_diff(&diff).removed_functions,
+            vec!["main/get_post".to_string()]
+        );
+        assert!(diff.is_breaking());
+    }
+
+    #[test]
+    fn diffs_entry_types() {
+        let old = fixture_dna();
+        let mut new = fixture_dna();
+        {
+            let entry_types = &mut new.zomes.get_mut("blog").unwrap().entry_types;
+            let draft = entry_types.remove(&EntryType::from("draft")).unwrap();
+            entry_types.insert(EntryType::from("comment"), draft);
+        }
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            blog_diff(&diff).added_entry_types,
+            vec!["comment".to_string()]
+        );
+        assert_eq!(
+            blog_diff(&diff).removed_entry_types,
+            vec!["draft".to_string()]
+        );
+        assert!(diff.is_breaking());
+
+        // Adding an entry type is fine
+        let mut newer = new.clone();
+        {
+            let entry_types = &mut newer.zomes.get_mut("blog").unwrap().entry_types;
+            let comment = entry_types[&EntryType::from("comment")].clone();
+            entry_types.insert(EntryType::from("reaction"), comment);
+        }
+        let diff = new.diff(&newer);
+        assert_eq!(
+            blog_diff(&diff).added_entry_types,
+            vec!["reaction".to_string()]
+        );
+        assert!(!diff.is_breaking());
+    }
+
+    #[test]
+    fn diffs_sharing() {
+        let old = fixture_dna();
+        let mut new = fixture_dna();
+        new.zomes
+            .get_mut("blog")
+            .unwrap()
+            .entry_types
+            .get_mut(&EntryType::from("draft"))
+            .unwrap()
+            .sharing = Sharing::Public;
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            blog_diff(&diff).changed_sharing.get("draft"),
+            Some(&SharingChange {
+                old: Sharing::Private,
+                new: Sharing::Public,
+            })
+        );
+        assert!(diff.is_breaking());
+    }
+
+    #[test]
+    fn diffs_code() {
+        let old = fixture_dna();
+        let mut new = fixture_dna();
+        new.zomes.get_mut("blog").unwrap().code.code = vec![4, 5, 6, 7];
+
+        let diff = old.diff(&new);
+        let code_change = blog_diff(&diff).changed_code.clone().unwrap();
+        assert_eq!(code_change.old_hash, code_hash(&old.zomes["blog"]));
+        assert_eq!(code_change.new_hash, code_hash(&new.zomes["blog"]));
+        assert_ne!(code_change.old_hash, code_change.new_hash);
+        assert!(!diff.changed_zomes.contains_key("profile"));
+        assert!(!diff.is_breaking());
+    }
+
+    #[test]
+    fn diffs_host_permissions() {
+        let old = fixture_dna();
+        let mut new = fixture_dna();
+        new.zomes.get_mut("blog").unwrap().host_permissions = Some(
+            vec![HostPermission::ChainRead, HostPermission::Dht]
+                .into_iter()
+                .collect(),
+        );
+
+        // Restricting a zome that had all permissions is fine
+        let diff = old.diff(&new);
+        assert!(blog_diff(&diff).added_host_permissions.is_empty());
+        assert_eq!(
+            blog_diff(&diff).removed_host_permissions,
+            vec![
+                "chain_write".to_string(),
+                "crypto".to_string(),
+                "network_send".to_string(),
+                "signals".to_string(),
+            ]
+        );
+        assert!(!diff.is_breaking());
+
+        // Expanding its permissions again is not
+        let mut newer = new.clone();
+        newer
+            .zomes
+            .get_mut("blog")
+            .unwrap()
+            .host_permissions
+            .as_mut()
+            .unwrap()
+            .insert(HostPermission::ChainWrite);
+        let diff = new.diff(&newer);
+        assert_eq!(
+            blog_diff(&diff).added_host_permissions,
+            vec!["chain_write".to_string()]
+        );
+        assert!(diff.is_breaking());
+        assert!(new.diff(&old).is_breaking());
+    }
+
+    #[test]
+    fn diffs_properties() {
+        let old = fixture_dna();
+        let mut new = fixture_dna();
+        new.properties = json!({"max_length": 200, "moderated": false, "theme": "dark"});
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.changed_properties,
+            vec!["max_length".to_string(), "theme".to_string()]
+        );
+        assert!(diff.changed_zomes.is_empty());
+        assert!(!diff.is_breaking());
+    }
+}
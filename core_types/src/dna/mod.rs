@@ -23,11 +23,18 @@
 //! assert_eq!(name, dna2.name);
 //! ```
 
+pub mod bridges;
+pub mod build_info;
+pub mod diff;
 pub mod wasm;
 pub mod zome;
 
 use crate::{
-    dna::zome::{capabilities::Capability, entry_types::EntryTypeDef},
+    dna::{
+        bridges::BridgeDeclaration,
+        build_info::{BuildInfo, UnhashedBuildInfo},
+        zome::{capabilities::Capability, entry_types::EntryTypeDef},
+    },
     entry::entry_type::EntryType,
     error::{DnaError, HolochainError},
     json::JsonString,
@@ -37,6 +44,7 @@ use multihash;
 use serde_json::{self, Value};
 use std::{
     collections::BTreeMap,
+    convert::TryFrom,
     hash::{Hash, Hasher},
 };
 use uuid::Uuid;
@@ -46,13 +54,31 @@ fn empty_object() -> Value {
     json!({})
 }
 
+/// The manifest version `hc package` writes into the DNAs it packages.
+/// Bump it when the DNA format changes in a way older builds can't read.
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// The oldest and newest manifest versions, inclusive, this build can read.
+pub const SUPPORTED_MANIFEST_VERSIONS: (u32, u32) = (1, MANIFEST_VERSION);
+
+/// serde helper, DNAs packaged before manifest versions existed are version 1
+fn first_manifest_version() -> u32 {
+    1
+}
+
+/// serde helper, leaves the first manifest version out of serialized DNAs,
+/// so DNAs packaged before manifest versions existed keep their hash
+fn is_first_manifest_version(manifest_version: &u32) -> bool {
+    *manifest_version == first_manifest_version()
+}
+
 /// serde helper, provides a default newly generated v4 uuid
 fn new_uuid() -> String {
     Uuid::new_v4().to_string()
 }
 
 /// Represents the top-level holochain dna object.
-#[derive(Serialize, Deserialize, Clone, Debug, DefaultJson)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Dna {
     /// The top-level "name" of a holochain application.
     #[serde(default)]
@@ -74,6 +100,13 @@ pub struct Dna {
     #[serde(default)]
     pub dna_spec_version: String,
 
+    /// Which version of the DNA file format this is, see `MANIFEST_VERSION`.
+    #[serde(
+        default = "first_manifest_version",
+        skip_serializing_if = "is_first_manifest_version"
+    )]
+    pub manifest_version: u32,
+
     /// Any arbitrary application properties can be included in this object.
     #[serde(default = "empty_object")]
     pub properties: Value,
@@ -81,6 +114,20 @@ pub struct Dna {
     /// An array of zomes associated with your holochain application.
     #[serde(default)]
     pub zomes: BTreeMap<String, zome::Zome>,
+
+    /// The bridges to other instances the zomes call, see `BridgeDeclaration`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bridges: Vec<BridgeDeclaration>,
+
+    /// Where the packaged DNA came from, filled in by `hc package`.
+    /// DNAs packaged before it existed don't have it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_info: Option<BuildInfo>,
+
+    /// Top-level fields this build doesn't know, written by newer tools into a DNA
+    /// of a supported manifest version. They are kept so the DNA hashes the same.
+    #[serde(flatten)]
+    pub extras: BTreeMap<String, Value>,
 }
 
 impl Default for Dna {
@@ -92,8 +139,12 @@ impl Default for Dna {
             version: String::new(),
             uuid: new_uuid(),
             dna_spec_version: String::from("2.0"),
+            manifest_version: MANIFEST_VERSION,
             properties: empty_object(),
             zomes: BTreeMap::new(),
+            bridges: Vec::new(),
+            build_info: None,
+            extras: BTreeMap::new(),
         }
     }
 }
@@ -143,6 +194,11 @@ impl Dna {
         zome.capabilities.get(capability_name)
     }
 
+    /// Return the declaration of the bridge with the given handle
+    pub fn bridge(&self, handle: &str) -> Option<&BridgeDeclaration> {
+        self.bridges.iter().find(|bridge| bridge.handle == handle)
+    }
+
     /// Find a Zome and return it's WASM bytecode for a specified Capability
     pub fn get_wasm_from_zome_name<T: Into<String>>(&self, zome_name: T) -> Option<&wasm::DnaWasm> {
         let zome_name = zome_name.into();
@@ -215,16 +271,136 @@ impl Dna {
         None
     }
 
+    /// How many nodes are to hold each entry, as set by the `redundancy` property.
+    /// 0, the default, means every node.
+    pub fn redundancy(&self) -> u32 {
+        self.properties
+            .get("redundancy")
+            .and_then(|redundancy| redundancy.as_u64())
+            .map(|redundancy| redundancy as u32)
+            .unwrap_or(0)
+    }
+
+    /// How many nodes are to hold entries of the given type: the redundancy of its
+    /// definition if it sets one, or else the one of the DNA.
+    pub fn redundancy_for_entry_type(&self, entry_type: &EntryType) -> u32 {
+        match entry_type {
+            EntryType::App(app_entry_type) => self
+                .get_entry_type_def(&String::from(app_entry_type.to_owned()))
+                .and_then(|entry_type_def| entry_type_def.redundancy),
+            _ => None,
+        }
+        .unwrap_or_else(|| self.redundancy())
+    }
+
+    /// How many seconds the content of entries of the given type is kept for before it can
+    /// be pruned, None if the type is not prunable, see `EntryTypeDef::is_prunable()`
+    pub fn prune_retention(&self, entry_type: &EntryType) -> Option<u64> {
+        match entry_type {
+            EntryType::App(app_entry_type) => self
+                .get_entry_type_def(&String::from(app_entry_type.to_owned()))
+                .filter(|entry_type_def| entry_type_def.is_prunable())
+                .map(|entry_type_def| entry_type_def.retention.unwrap_or(0)),
+            _ => None,
+        }
+    }
+
+    /// How many seconds entries of the given type live before they expire, None if
+    /// they don't, see `EntryTypeDef::ttl_seconds`
+    pub fn entry_ttl(&self, entry_type: &EntryType) -> Option<u64> {
+        match entry_type {
+            EntryType::App(app_entry_type) => self
+                .get_entry_type_def(&String::from(app_entry_type.to_owned()))
+                .and_then(|entry_type_def| entry_type_def.ttl_seconds),
+            _ => None,
+        }
+    }
+
+    /// The shortest TTL of the entry types of the DNA, None if none of them has one
+    pub fn min_entry_ttl(&self) -> Option<u64> {
+        self.zomes
+            .values()
+            .flat_map(|zome| zome.entry_types.values())
+            .filter_map(|entry_type_def| entry_type_def.ttl_seconds)
+            .min()
+    }
+
+    /// The most nodes entries of any type are held by, 0 if every type is held by every node.
+    /// Asking that many of the nodes closest to an address finds the entry whatever its type.
+    pub fn max_redundancy(&self) -> u32 {
+        self.zomes
+            .values()
+            .flat_map(|zome| zome.entry_types.values())
+            .filter_map(|entry_type_def| entry_type_def.redundancy)
+            .fold(self.redundancy(), u32::max)
+    }
+
+    /// The DNA as it gets hashed: all of it but the `unhashed` section of its build info,
+    /// which holds what differs between builds of the same code, like when it got packaged.
+    /// `multihash()`, `dna_hash()`, `Hash` and `PartialEq` all go by this, so DNAs packaged
+    /// from the same code and with the same tools are the same DNA.
+    pub fn hashable(&self) -> Dna {
+        let mut dna = self.to_owned();
+        if let Some(ref mut build_info) = dna.build_info {
+            build_info.unhashed = UnhashedBuildInfo::default();
+        }
+        dna
+    }
+
     pub fn multihash(&self) -> Result<Vec<u8>, HolochainError> {
-        let s = String::from(JsonString::from(self.to_owned()));
+        let s = String::from(JsonString::from(self.hashable()));
         multihash::encode(multihash::Hash::SHA2256, &s.into_bytes())
             .map_err(|error| HolochainError::ErrorGeneric(error.to_string()))
     }
 }
 
+impl<'a> From<&'a Dna> for JsonString {
+    fn from(dna: &Dna) -> JsonString {
+        match serde_json::to_string(dna) {
+            Ok(s) => Ok(JsonString::from(s)),
+            Err(e) => Err(HolochainError::SerializationError(e.to_string())),
+        }
+        .expect(&format!("could not Jsonify Dna: {:?}", dna))
+    }
+}
+
+impl From<Dna> for JsonString {
+    fn from(dna: Dna) -> JsonString {
+        JsonString::from(&dna)
+    }
+}
+
+/// Like `DefaultJson`, but refuses DNAs of a manifest version this build can't read.
+/// Fields it doesn't know in the ones it can end up in `Dna::extras`.
+impl<'a> TryFrom<&'a JsonString> for Dna {
+    type Error = HolochainError;
+    fn try_from(json_string: &JsonString) -> Result<Self, Self::Error> {
+        let value: Value = serde_json::from_str(&String::from(json_string))
+            .map_err(|e| HolochainError::SerializationError(e.to_string()))?;
+        // not a number is left for serde to complain about
+        if let Some(found) = value.get("manifest_version").and_then(Value::as_u64) {
+            let (oldest, newest) = SUPPORTED_MANIFEST_VERSIONS;
+            if found < u64::from(oldest) || found > u64::from(newest) {
+                return Err(HolochainError::UnsupportedDnaVersion {
+                    found: found.min(u64::from(u32::max_value())) as u32,
+                    supported: SUPPORTED_MANIFEST_VERSIONS,
+                });
+            }
+        }
+        serde_json::from_value(value).map_err(|e| HolochainError::SerializationError(e.to_string()))
+    }
+}
+
+impl TryFrom<JsonString> for Dna {
+    type Error = HolochainError;
+    fn try_from(json_string: JsonString) -> Result<Self, Self::Error> {
+        Dna::try_from(&json_string)
+    }
+}
+
 impl Hash for Dna {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        let s = String::from(JsonString::from(self.to_owned()));
+        let s = String::from(JsonString::from(self.hashable()));
         s.hash(state);
     }
 }
@@ -232,7 +408,7 @@ impl Hash for Dna {
 impl PartialEq for Dna {
     fn eq(&self, other: &Dna) -> bool {
         // need to guarantee that PartialEq and Hash always agree
-        JsonString::from(self.to_owned()) == JsonString::from(other.to_owned())
+        JsonString::from(self.hashable()) == JsonString::from(other.hashable())
     }
 }
 
@@ -240,8 +416,17 @@ impl PartialEq for Dna {
 pub mod tests {
     use super::*;
     extern crate base64;
-    use crate::dna::zome::tests::test_zome;
-    use std::convert::TryFrom;
+    use crate::{
+        dna::zome::{entry_types::Sharing, tests::test_zome},
+        entry::addressing::dna_hash,
+    };
+    use std::{collections::hash_map::DefaultHasher, convert::TryFrom};
+
+    fn calculate_hash<T: Hash>(t: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        t.hash(&mut hasher);
+        hasher.finish()
+    }
 
     static UNIT_UUID: &'static str = "00000000-0000-0000-0000-000000000000";
 
@@ -264,6 +449,72 @@ pub mod tests {
         assert_eq!(Some(&entry_type_def), dna.get_entry_type_def("bar"));
     }
 
+    #[test]
+    fn redundancy_of_the_dna_and_its_entry_types() {
+        let mut dna = test_dna();
+        assert_eq!(dna.redundancy(), 0);
+        assert_eq!(dna.max_redundancy(), 0);
+
+        dna.properties = json!({"redundancy": 2});
+        let mut zome = test_zome();
+        let mut entry_type_def = EntryTypeDef::new();
+        entry_type_def.redundancy = Some(5);
+        zome.entry_types
+            .insert(EntryType::from("bar"), entry_type_def);
+        zome.entry_types
+            .insert(EntryType::from("baz"), EntryTypeDef::new());
+        dna.zomes.insert("zome".to_string(), zome);
+
+        assert_eq!(dna.redundancy(), 2);
+        assert_eq!(dna.redundancy_for_entry_type(&EntryType::from("bar")), 5);
+        assert_eq!(dna.redundancy_for_entry_type(&EntryType::from("baz")), 2);
+        assert_eq!(dna.redundancy_for_entry_type(&EntryType::AgentId), 2);
+        assert_eq!(dna.max_redundancy(), 5);
+    }
+
+    #[test]
+    fn prune_retention_of_prunable_private_entry_types() {
+        let mut dna = test_dna();
+        let mut zome = test_zome();
+        let mut drafts = EntryTypeDef::new();
+        drafts.sharing = Sharing::Private;
+        drafts.prunable = true;
+        drafts.retention = Some(3600);
+        let mut presence = drafts.clone();
+        presence.retention = None;
+        let mut public = drafts.clone();
+        public.sharing = Sharing::Public;
+        zome.entry_types.insert(EntryType::from("drafts"), drafts);
+        zome.entry_types
+            .insert(EntryType::from("presence"), presence);
+        zome.entry_types.insert(EntryType::from("public"), public);
+        dna.zomes.insert("zome".to_string(), zome);
+
+        assert_eq!(dna.prune_retention(&EntryType::from("drafts")), Some(3600));
+        assert_eq!(dna.prune_retention(&EntryType::from("presence")), Some(0));
+        assert_eq!(dna.prune_retention(&EntryType::from("public")), None);
+        assert_eq!(dna.prune_retention(&EntryType::from("unknown")), None);
+        assert_eq!(dna.prune_retention(&EntryType::AgentId), None);
+    }
+
+    #[test]
+    fn entry_ttl_of_entry_types_with_ttl() {
+        let mut dna = test_dna();
+        let mut zome = test_zome();
+        let mut presence = EntryTypeDef::new();
+        presence.ttl_seconds = Some(60);
+        zome.entry_types
+            .insert(EntryType::from("presence"), presence);
+        zome.entry_types
+            .insert(EntryType::from("post"), EntryTypeDef::new());
+        dna.zomes.insert("zome".to_string(), zome);
+
+        assert_eq!(dna.entry_ttl(&EntryType::from("presence")), Some(60));
+        assert_eq!(dna.entry_ttl(&EntryType::from("post")), None);
+        assert_eq!(dna.entry_ttl(&EntryType::from("unknown")), None);
+        assert_eq!(dna.entry_ttl(&EntryType::AgentId), None);
+    }
+
     #[test]
     fn can_parse_and_output_json() {
         let dna = test_dna();
@@ -396,6 +647,57 @@ pub mod tests {
         assert_eq!(dna, fixture);
     }
 
+    fn test_dna_built_at(built_at: &str) -> Dna {
+        let mut dna = test_dna();
+        dna.uuid = String::from(UNIT_UUID);
+        dna.build_info = Some(BuildInfo {
+            hc_version: String::from("0.0.2"),
+            hdk_versions: vec![(String::from("zome"), String::from("0.0.2"))]
+                .into_iter()
+                .collect(),
+            git_commit: Some(String::from("4720ab7")),
+            git_dirty: Some(false),
+            unhashed: UnhashedBuildInfo {
+                built_at: Some(String::from(built_at)),
+            },
+        });
+        dna
+    }
+
+    #[test]
+    fn build_time_does_not_change_hashes() {
+        let dna = test_dna_built_at("2018-12-01T10:00:00+00:00");
+        let rebuilt = test_dna_built_at("2018-12-02T18:30:00+00:00");
+
+        assert_eq!(dna, rebuilt);
+        assert_eq!(dna.multihash().unwrap(), rebuilt.multihash().unwrap());
+        assert_eq!(dna_hash(&dna), dna_hash(&rebuilt));
+        assert_eq!(calculate_hash(&dna), calculate_hash(&rebuilt));
+
+        // the rest of the build info tells apart DNAs built from different code
+        let mut other_commit = rebuilt.clone();
+        other_commit.build_info.as_mut().unwrap().git_commit = Some(String::from("886939f"));
+        assert_ne!(dna, other_commit);
+        assert_ne!(dna_hash(&dna), dna_hash(&other_commit));
+    }
+
+    #[test]
+    fn build_info_round_trips_through_json() {
+        let dna = test_dna_built_at("2018-12-01T10:00:00+00:00");
+        let json = JsonString::from(dna.clone());
+        assert!(String::from(json.clone()).contains(
+            r#""build_info":{"hc_version":"0.0.2","hdk_versions":{"zome":"0.0.2"},"git_commit":"4720ab7","git_dirty":false,"unhashed":{"built_at":"2018-12-01T10:00:00+00:00"}}"#
+        ));
+        let restored = Dna::try_from(json).unwrap();
+        assert_eq!(restored.build_info, dna.build_info);
+
+        // DNAs packaged before build info existed still parse
+        assert_eq!(
+            Dna::try_from(JsonString::from("{}")).unwrap().build_info,
+            None
+        );
+    }
+
     #[test]
     fn parse_with_defaults_dna() {
         let dna = Dna::try_from(JsonString::from(
@@ -407,6 +709,62 @@ pub mod tests {
         assert!(dna.uuid.len() > 0);
     }
 
+    #[test]
+    fn parse_defaults_to_the_first_manifest_version() {
+        let dna = Dna::try_from(JsonString::from("{}")).unwrap();
+
+        assert_eq!(1, dna.manifest_version);
+        assert_eq!(MANIFEST_VERSION, Dna::new().manifest_version);
+        // so it is left out again, and DNAs of before manifest versions hash the same
+        assert!(!String::from(JsonString::from(dna)).contains("manifest_version"));
+    }
+
+    #[test]
+    fn parse_fails_for_a_newer_manifest_version() {
+        let newest = SUPPORTED_MANIFEST_VERSIONS.1;
+        let result = Dna::try_from(JsonString::from(format!(
+            r#"{{
+                "name": "from the future",
+                "manifest_version": {},
+                "zomes": {{}}
+            }}"#,
+            newest + 1
+        )));
+
+        assert_eq!(
+            Err(HolochainError::UnsupportedDnaVersion {
+                found: newest + 1,
+                supported: SUPPORTED_MANIFEST_VERSIONS,
+            }),
+            result
+        );
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .ends_with("upgrade holochain to run it"));
+    }
+
+    #[test]
+    fn parse_keeps_unknown_fields_of_a_supported_manifest_version() {
+        let fixture = JsonString::from(
+            r#"{
+                "name": "test",
+                "manifest_version": 1,
+                "signing_keys": ["HcScI"],
+                "zomes": {}
+            }"#,
+        );
+
+        let dna = Dna::try_from(fixture).unwrap();
+
+        assert_eq!(String::from("test"), dna.name);
+        assert_eq!(Some(&json!(["HcScI"])), dna.extras.get("signing_keys"));
+        // they survive a round trip, so the DNA hashes the same
+        let restored = Dna::try_from(JsonString::from(dna.clone())).unwrap();
+        assert_eq!(dna.extras, restored.extras);
+        assert_eq!(dna, restored);
+    }
+
     #[test]
     fn parse_with_defaults_zome() {
         let dna = Dna::try_from(JsonString::from(
@@ -140,6 +140,30 @@ pub struct EntryTypeDef {
     /// An array of link definitions for links pointing to entries of this type
     #[serde(default)]
     pub linked_from: Vec<LinkedFrom>,
+
+    /// How many nodes are to hold each entry of this type, overriding the redundancy
+    /// of the DNA. 0 means every node.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redundancy: Option<u32>,
+
+    /// Whether the content of entries of this type can be removed from local storage once
+    /// they are older than the retention, while their headers stay on the chain.
+    /// Only private entry types can be prunable, see `is_prunable()`.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub prunable: bool,
+
+    /// How many seconds the content of prunable entries is kept for, 0 if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention: Option<u64>,
+
+    /// How many seconds entries of this type live after they got committed. The nodes
+    /// holding them let them expire once they are that old, and they stop being republished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_seconds: Option<u64>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 impl EntryTypeDef {
@@ -147,6 +171,12 @@ impl EntryTypeDef {
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Whether the content of entries of this type gets pruned: they have to be flagged
+    /// prunable and private, as the DHT holds on to public ones anyway.
+    pub fn is_prunable(&self) -> bool {
+        self.prunable && self.sharing == Sharing::Private
+    }
 }
 
 #[cfg(test)]
@@ -198,4 +228,19 @@ mod tests {
 
         assert_eq!(fixture, entry);
     }
+
+    #[test]
+    fn only_private_entry_types_are_prunable() {
+        let mut entry = EntryTypeDef::new();
+        entry.prunable = true;
+        assert!(!entry.is_prunable());
+        entry.sharing = Sharing::Private;
+        assert!(entry.is_prunable());
+
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(json.contains(r#""prunable":true"#), "json = {}", json);
+        assert!(!json.contains("retention"), "json = {}", json);
+        let unflagged = serde_json::to_string(&EntryTypeDef::new()).unwrap();
+        assert!(!unflagged.contains("prunable"), "json = {}", unflagged);
+    }
 }
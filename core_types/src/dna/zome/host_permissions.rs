@@ -0,0 +1,83 @@
+//! File holding the permissions a zome can declare for the host functions it may call.
+
+use std::{collections::BTreeSet, fmt};
+
+/// A group of host functions a zome may call. Zomes that declare their permissions
+/// get only the host functions of the groups they declared linked into their WASM.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum HostPermission {
+    /// Committing, updating and removing entries and links
+    ChainWrite,
+    /// Reading the agent's own source chain
+    ChainRead,
+    /// Getting entries and links from the DHT
+    Dht,
+    /// Sending messages to other nodes directly
+    NetworkSend,
+    /// Encrypting and decrypting with the agent's keys
+    Crypto,
+    /// Emitting signals to the clients of the instance
+    Signals,
+}
+
+impl HostPermission {
+    /// All permissions, which zomes that don't declare any have
+    pub fn all() -> HostPermissions {
+        vec![
+            HostPermission::ChainWrite,
+            HostPermission::ChainRead,
+            HostPermission::Dht,
+            HostPermission::NetworkSend,
+            HostPermission::Crypto,
+            HostPermission::Signals,
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            HostPermission::ChainWrite => "chain_write",
+            HostPermission::ChainRead => "chain_read",
+            HostPermission::Dht => "dht",
+            HostPermission::NetworkSend => "network_send",
+            HostPermission::Crypto => "crypto",
+            HostPermission::Signals => "signals",
+        }
+    }
+}
+
+impl fmt::Display for HostPermission {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+pub type HostPermissions = BTreeSet<HostPermission>;
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn host_permissions_json() {
+        let host_permissions: HostPermissions =
+            vec![HostPermission::Dht, HostPermission::ChainRead]
+                .into_iter()
+                .collect();
+        let json = serde_json::to_string(&host_permissions).unwrap();
+        assert_eq!(json, "[\"chain_read\",\"dht\"]");
+        assert_eq!(
+            serde_json::from_str::<HostPermissions>(&json).unwrap(),
+            host_permissions
+        );
+        for permission in host_permissions {
+            assert_eq!(
+                serde_json::to_string(&permission).unwrap(),
+                format!("\"{}\"", permission)
+            );
+        }
+    }
+}
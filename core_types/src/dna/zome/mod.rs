@@ -2,11 +2,15 @@
 
 pub mod capabilities;
 pub mod entry_types;
+pub mod host_permissions;
 
 use crate::{
     dna::wasm::DnaWasm, entry::entry_type::EntryType, error::HolochainError, json::JsonString,
 };
-use dna::zome::entry_types::{deserialize_entry_types, serialize_entry_types, EntryTypeDef};
+use dna::zome::{
+    entry_types::{deserialize_entry_types, serialize_entry_types, EntryTypeDef},
+    host_permissions::{HostPermission, HostPermissions},
+};
 use std::collections::BTreeMap;
 
 /// Enum for "zome" "config" "error_handling" property.
@@ -73,6 +77,11 @@ pub struct Zome {
     #[serde(default)]
     pub capabilities: ZomeCapabilities,
 
+    /// The groups of host functions this zome may call.
+    /// Zomes that don't declare any may call all of them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_permissions: Option<HostPermissions>,
+
     /// Validation code for this entry_type.
     #[serde(default)]
     pub code: DnaWasm,
@@ -88,6 +97,7 @@ impl Default for Zome {
             config: Config::new(),
             entry_types: BTreeMap::new(),
             capabilities: BTreeMap::new(),
+            host_permissions: None,
             code: DnaWasm::new(),
         }
     }
@@ -107,9 +117,18 @@ impl Zome {
             config: config.clone(),
             entry_types: entry_types.to_owned(),
             capabilities: capabilities.to_owned(),
+            host_permissions: None,
             code: code.clone(),
         }
     }
+
+    /// Whether this zome may call the host functions of the given group
+    pub fn permits(&self, permission: HostPermission) -> bool {
+        self.host_permissions
+            .as_ref()
+            .map(|host_permissions| host_permissions.contains(&permission))
+            .unwrap_or(true)
+    }
 }
 
 #[cfg(test)]
@@ -162,4 +181,18 @@ pub mod tests {
 
         assert_eq!(zome, Zome::try_from(JsonString::from(expected)).unwrap(),);
     }
+
+    #[test]
+    fn zomes_without_host_permissions_are_permitted_everything() {
+        let zome = Zome::default();
+        assert!(zome.permits(HostPermission::ChainWrite));
+        assert!(zome.permits(HostPermission::NetworkSend));
+
+        let zome: Zome = serde_json::from_str(r#"{"host_permissions": ["chain_read"]}"#).unwrap();
+        assert!(zome.permits(HostPermission::ChainRead));
+        assert!(!zome.permits(HostPermission::ChainWrite));
+
+        let zome: Zome = serde_json::from_str(r#"{"host_permissions": []}"#).unwrap();
+        assert!(!zome.permits(HostPermission::ChainRead));
+    }
 }
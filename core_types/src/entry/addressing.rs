@@ -0,0 +1,114 @@
+//! Address derivation for entries.
+//! The source chain, the DHT and the HDK all derive entry addresses through these functions
+//! so that an address computed in a zome always matches the one core commits under.
+
+use agent::AgentId;
+use cas::content::{Address, AddressableContent};
+use dna::Dna;
+use entry::Entry;
+use hash::HashString;
+use json::JsonString;
+use multihash::Hash;
+
+/// The address of an agent's initial identity entry is the agent's public key.
+/// Identities that update it are addressed by their content, see `entry_address()`.
+pub fn agent_entry_address(agent_id: &AgentId) -> Address {
+    agent_id.address()
+}
+
+/// The address of the DNA entry every source chain starts with.
+/// Like `dna_hash()`, it skips what differs between builds of the same code.
+pub fn dna_entry_address(dna: &Dna) -> Address {
+    entry_address(&Entry::Dna(dna.clone()))
+}
+
+/// The hash identifying a DNA, handed to zomes as their DNA hash.
+/// Skips what differs between builds of the same code, see `Dna::hashable()`.
+pub fn dna_hash(dna: &Dna) -> HashString {
+    HashString::encode_from_json_string(JsonString::from(dna.hashable()), Hash::SHA2256)
+}
+
+/// The address of any entry.
+/// Initial agent entries are addressed by their key, all other entries, including updated
+/// identities, by the SHA2-256 multihash of their serialized content.
+/// DNA entries hash the content of their `Dna::hashable()`.
+pub fn entry_address(entry: &Entry) -> Address {
+    match entry {
+        Entry::AgentId(agent_id) if agent_id.is_initial() => agent_entry_address(agent_id),
+        Entry::Dna(dna) => Address::encode_from_str(
+            &String::from(Entry::Dna(dna.hashable()).content()),
+            Hash::SHA2256,
+        ),
+        _ => Address::encode_from_str(&String::from(entry.content()), Hash::SHA2256),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use agent::test_agent_id;
+    use dna::build_info::{BuildInfo, UnhashedBuildInfo};
+    use entry::{expected_entry_address, test_entry};
+
+    #[test]
+    fn app_entry_address_test() {
+        assert_eq!(expected_entry_address(), entry_address(&test_entry()));
+    }
+
+    #[test]
+    fn agent_entry_address_test() {
+        let agent_id = test_agent_id();
+        assert_eq!(
+            agent_entry_address(&agent_id),
+            entry_address(&Entry::AgentId(agent_id.clone())),
+        );
+        assert_eq!(Address::from(agent_id.key.clone()), agent_entry_address(&agent_id));
+    }
+
+    #[test]
+    fn updated_agent_entry_address_test() {
+        let agent_id = test_agent_id();
+        let updated = Entry::AgentId(agent_id.update("robert", &agent_entry_address(&agent_id)));
+        assert_eq!(
+            Address::encode_from_str(&String::from(updated.content()), Hash::SHA2256),
+            entry_address(&updated),
+        );
+        assert_ne!(agent_entry_address(&agent_id), entry_address(&updated));
+    }
+
+    #[test]
+    fn dna_entry_address_test() {
+        let dna = Dna::new();
+        assert_eq!(
+            Address::encode_from_str(
+                &String::from(Entry::Dna(dna.clone()).content()),
+                Hash::SHA2256
+            ),
+            dna_entry_address(&dna),
+        );
+    }
+
+    #[test]
+    fn dna_entry_address_skips_build_time() {
+        let built_at = |time: &str| {
+            let mut dna = Dna::new();
+            dna.build_info = Some(BuildInfo {
+                hc_version: String::from("0.0.2"),
+                unhashed: UnhashedBuildInfo {
+                    built_at: Some(String::from(time)),
+                },
+                ..BuildInfo::default()
+            });
+            dna
+        };
+        let dna = built_at("2018-12-01T10:00:00+00:00");
+        let rebuilt = built_at("2018-12-02T18:30:00+00:00");
+
+        assert_eq!(dna_entry_address(&dna), dna_entry_address(&rebuilt));
+        assert_eq!(
+            dna_entry_address(&rebuilt),
+            Entry::Dna(rebuilt.clone()).address()
+        );
+        assert_ne!(dna_entry_address(&dna), dna_entry_address(&Dna::new()));
+    }
+}
@@ -1,3 +1,4 @@
+pub mod addressing;
 pub mod deletion_entry;
 pub mod entry_type;
 
@@ -12,10 +13,10 @@ use entry::entry_type::{test_app_entry_type, test_app_entry_type_b, AppEntryType
 use error::{HcResult, HolochainError};
 use json::{default_to_json, default_try_from_json, JsonString, RawString};
 use link::{link_add::LinkAdd, link_list::LinkList, link_remove::LinkRemove};
-use multihash::Hash;
 use serde::{ser::SerializeTuple, Deserialize, Deserializer, Serializer};
 use snowflake;
-use std::convert::TryFrom;
+use std::{collections::BTreeMap, convert::TryFrom, fmt};
+use time::Iso8601;
 
 pub type AppEntryValue = JsonString;
 
@@ -94,6 +95,24 @@ impl Entry {
     }
 }
 
+/// Human readable form of an entry: its type followed by its content.
+/// A precision truncates the content to that many characters, e.g. `format!("{:.20}", entry)`.
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let content = match self {
+            Entry::App(_, app_entry_value) => String::from(app_entry_value.to_owned()),
+            _ => String::from(self.content()),
+        };
+        let content = match f.precision() {
+            Some(max_len) if content.chars().count() > max_len => {
+                format!("{}...", content.chars().take(max_len).collect::<String>())
+            }
+            _ => content,
+        };
+        write!(f, "{} {}", self.entry_type(), content)
+    }
+}
+
 impl PartialEq for Entry {
     fn eq(&self, other: &Entry) -> bool {
         self.address() == other.address()
@@ -102,10 +121,7 @@ impl PartialEq for Entry {
 
 impl AddressableContent for Entry {
     fn address(&self) -> Address {
-        match &self {
-            Entry::AgentId(agent_id) => agent_id.address(),
-            _ => Address::encode_from_str(&String::from(self.content()), Hash::SHA2256),
-        }
+        addressing::entry_address(self)
     }
 
     fn content(&self) -> Content {
@@ -117,11 +133,50 @@ impl AddressableContent for Entry {
     }
 }
 
+/// What the holder of an entry knows about it besides its crud-status and -link.
+/// It all comes from the meta storage of the holder, so it's cheap to tell, and lists of
+/// entries can show link counts and "edited" badges without more requests per entry.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct EntryMeta {
+    /// How many links from the entry the holder has, by tag
+    #[serde(default)]
+    pub link_counts: BTreeMap<String, usize>,
+    /// Address of the newest version of the entry the holder knows of, if it got updated
+    #[serde(default)]
+    pub newest_version: Option<Address>,
+    /// Timestamp of the earliest header the entry got published with
+    #[serde(default)]
+    pub first_published: Option<Iso8601>,
+    /// Timestamp of the latest header the entry got published with
+    #[serde(default)]
+    pub last_published: Option<Iso8601>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
 pub struct EntryWithMeta {
     pub entry: Entry,
     pub crud_status: CrudStatus,
     pub maybe_crud_link: Option<Address>,
+    /// Empty if the holder predates it
+    #[serde(default)]
+    pub meta: EntryMeta,
+}
+
+impl EntryWithMeta {
+    /// The entry with the given crud-status and -link, and no other meta
+    pub fn new(entry: Entry, crud_status: CrudStatus, maybe_crud_link: Option<Address>) -> Self {
+        EntryWithMeta {
+            entry,
+            crud_status,
+            maybe_crud_link,
+            meta: EntryMeta::default(),
+        }
+    }
+
+    pub fn with_meta(mut self, meta: EntryMeta) -> Self {
+        self.meta = meta;
+        self
+    }
 }
 
 /// dummy entry value
@@ -235,6 +290,23 @@ pub mod tests {
         assert_ne!(entry_a, entry_b);
     }
 
+    #[test]
+    /// show Display implementation, with and without truncation
+    fn display() {
+        assert_eq!(
+            format!("{}", test_entry()),
+            "testEntryType \"test entry value\"".to_string(),
+        );
+        assert_eq!(
+            format!("{:.5}", test_entry()),
+            "testEntryType \"test...".to_string(),
+        );
+        assert_eq!(
+            format!("{:.50}", test_entry()),
+            "testEntryType \"test entry value\"".to_string(),
+        );
+    }
+
     #[test]
     /// test entry.address() against a known value
     fn known_address() {
@@ -311,4 +383,17 @@ pub mod tests {
             ExampleContentAddressableStorage,
         >(entries, test_content_addressable_storage());
     }
+
+    #[test]
+    /// show that entries with meta from holders that don't tell the EntryMeta still load
+    fn entry_with_meta_without_meta_deserializes() {
+        let entry_with_meta = EntryWithMeta::new(test_entry(), CrudStatus::LIVE, None);
+        let mut json: serde_json::Value =
+            serde_json::from_str(&String::from(JsonString::from(entry_with_meta.clone()))).unwrap();
+        json.as_object_mut().unwrap().remove("meta");
+        assert_eq!(
+            EntryWithMeta::try_from(JsonString::from(json.to_string())),
+            Ok(entry_with_meta)
+        );
+    }
 }
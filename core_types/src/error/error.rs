@@ -1,5 +1,6 @@
 use self::HolochainError::*;
 use crate::{
+    cas::content::Address,
     error::{DnaError, RibosomeErrorCode},
     json::*,
 };
@@ -95,11 +96,35 @@ pub enum HolochainError {
     SerializationError(String),
     InvalidOperationOnSysEntry,
     DoesNotHaveCapabilityToken,
+    /// A zome called a host function it did not declare the permission for
+    PermissionDenied(String),
     ValidationFailed(String),
     Ribosome(RibosomeErrorCode),
     RibosomeFailed(String),
     ConfigError(String),
     Timeout,
+    NetworkUnavailable,
+    /// Partial results of a zome call were yielded faster than they got consumed
+    BackpressureExceeded,
+    /// The peer a request went to dropped it because we sent it too much,
+    /// it accepts requests again after the given number of milliseconds
+    RateLimited(u64),
+    /// A commit that required the top of the source chain to be `expected` found it at
+    /// `actual` instead, because another commit came first. None is an empty chain.
+    ChainHeadMoved {
+        expected: Address,
+        actual: Option<Address>,
+    },
+    /// A zome called another instance over a bridge its DNA declares as optional,
+    /// with the given handle, that is not configured
+    BridgeNotPresent(String),
+    /// A DNA whose `manifest_version` is outside the range, inclusive, this build can read
+    UnsupportedDnaVersion {
+        found: u32,
+        supported: (u32, u32),
+    },
+    /// What was being done when the inner error happened, see `with_context()`
+    Context(String, Box<HolochainError>),
 }
 
 pub type HcResult<T> = Result<T, HolochainError>;
@@ -108,11 +133,70 @@ impl HolochainError {
     pub fn new(msg: &str) -> HolochainError {
         HolochainError::ErrorGeneric(msg.to_string())
     }
+
+    /// Wraps the error in a context telling what was being done when it happened.
+    /// Displays as "context: inner", so wrapping at every boundary an error passes
+    /// leaves a trail back to where it came from.
+    pub fn with_context<S: Into<String>>(self, context: S) -> HolochainError {
+        HolochainError::Context(context.into(), Box::new(self))
+    }
+
+    /// The error at the bottom of all contexts
+    pub fn root(&self) -> &HolochainError {
+        match self {
+            Context(_, inner) => inner.root(),
+            _ => self,
+        }
+    }
+
+    /// The contexts the error got wrapped in, outermost first
+    pub fn contexts(&self) -> Vec<String> {
+        let mut contexts = Vec::new();
+        let mut error = self;
+        while let Context(context, inner) = error {
+            contexts.push(context.clone());
+            error = &**inner;
+        }
+        contexts
+    }
 }
 
 impl fmt::Display for HolochainError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.description())
+        match self {
+            Context(context, inner) => write!(f, "{}: {}", context, inner),
+            RateLimited(retry_after_ms) => write!(
+                f,
+                "{}, retry after {} ms",
+                self.description(),
+                retry_after_ms
+            ),
+            ChainHeadMoved { expected, actual } => write!(
+                f,
+                "{}, expected {} but was {}",
+                self.description(),
+                expected,
+                actual
+                    .as_ref()
+                    .map(|address| address.to_string())
+                    .unwrap_or_else(|| String::from("empty")),
+            ),
+            BridgeNotPresent(handle) => write!(f, "{}: {}", self.description(), handle),
+            UnsupportedDnaVersion { found, supported } => write!(
+                f,
+                "{}: the DNA has manifest version {} but this build supports versions {} to {}, {}",
+                self.description(),
+                found,
+                supported.0,
+                supported.1,
+                if found > &supported.1 {
+                    "upgrade holochain to run it"
+                } else {
+                    "package it again with a newer hc"
+                }
+            ),
+            _ => write!(f, "{}", self.description()),
+        }
     }
 }
 
@@ -128,11 +212,45 @@ impl Error for HolochainError {
             SerializationError(err_msg) => &err_msg,
             InvalidOperationOnSysEntry => "operation cannot be done on a system entry type",
             DoesNotHaveCapabilityToken => "Caller does not have Capability to make that call",
+            PermissionDenied(err_msg) => &err_msg,
             ValidationFailed(fail_msg) => &fail_msg,
             Ribosome(err_code) => err_code.as_str(),
             RibosomeFailed(fail_msg) => &fail_msg,
             ConfigError(err_msg) => &err_msg,
             Timeout => "timeout",
+            NetworkUnavailable => "network unavailable, reconnecting",
+            BackpressureExceeded => "backpressure exceeded, partial results not consumed in time",
+            RateLimited(_) => "rate limited by peer",
+            ChainHeadMoved { .. } => "chain head moved",
+            BridgeNotPresent(_) => "no bridge configured for handle",
+            UnsupportedDnaVersion { .. } => "unsupported DNA manifest version",
+            Context(context, _) => &context,
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match self {
+            Context(_, inner) => Some(&**inner),
+            _ => None,
+        }
+    }
+}
+
+/// Structured form of a HolochainError for clients, which can show the contexts
+/// as a trail leading to the error.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, DefaultJson)]
+pub struct ErrorTrail {
+    /// The error at the bottom of all contexts
+    pub error: String,
+    /// Outermost first
+    pub context: Vec<String>,
+}
+
+impl<'a> From<&'a HolochainError> for ErrorTrail {
+    fn from(holochain_error: &HolochainError) -> Self {
+        ErrorTrail {
+            error: holochain_error.root().to_string(),
+            context: holochain_error.contexts(),
         }
     }
 }
@@ -230,6 +348,7 @@ impl ZomeApiInternalResult {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::convert::TryFrom;
     // a test function that returns our error result
     fn raises_holochain_error(yes: bool) -> Result<(), HolochainError> {
         if yes {
@@ -246,6 +365,57 @@ mod tests {
         assert_eq!("foo", err.to_string());
     }
 
+    #[test]
+    /// test that an unsupported DNA version says what to upgrade
+    fn unsupported_dna_version_to_string() {
+        let err = HolochainError::UnsupportedDnaVersion {
+            found: 3,
+            supported: (1, 2),
+        };
+        assert_eq!(
+            "unsupported DNA manifest version: the DNA has manifest version 3 but this build supports versions 1 to 2, upgrade holochain to run it",
+            err.to_string()
+        );
+        let err = HolochainError::UnsupportedDnaVersion {
+            found: 0,
+            supported: (1, 2),
+        };
+        assert_eq!(
+            "unsupported DNA manifest version: the DNA has manifest version 0 but this build supports versions 1 to 2, package it again with a newer hc",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    /// test that a moved chain head displays where it was expected and where it was
+    fn chain_head_moved_to_string() {
+        let err = HolochainError::ChainHeadMoved {
+            expected: Address::from("QmA"),
+            actual: Some(Address::from("QmB")),
+        };
+        assert_eq!(
+            "chain head moved, expected QmA but was QmB",
+            err.to_string()
+        );
+        let err = HolochainError::ChainHeadMoved {
+            expected: Address::from("QmA"),
+            actual: None,
+        };
+        assert_eq!(
+            "chain head moved, expected QmA but was empty",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    /// test that being rate limited displays when to retry
+    fn rate_limited_to_string() {
+        assert_eq!(
+            "rate limited by peer, retry after 250 ms",
+            HolochainError::RateLimited(250).to_string()
+        );
+    }
+
     #[test]
     /// test that we can convert an error to valid JSON
     fn test_to_json() {
@@ -317,12 +487,84 @@ mod tests {
                 HolochainError::DoesNotHaveCapabilityToken,
                 "Caller does not have Capability to make that call",
             ),
+            (HolochainError::PermissionDenied(String::from("foo")), "foo"),
             (HolochainError::Timeout, "timeout"),
+            (
+                HolochainError::NetworkUnavailable,
+                "network unavailable, reconnecting",
+            ),
+            (
+                HolochainError::BackpressureExceeded,
+                "backpressure exceeded, partial results not consumed in time",
+            ),
+            (HolochainError::RateLimited(100), "rate limited by peer"),
+            (
+                HolochainError::ChainHeadMoved {
+                    expected: Address::from("QmA"),
+                    actual: None,
+                },
+                "chain head moved",
+            ),
+            (
+                HolochainError::BridgeNotPresent(String::from("persona")),
+                "no bridge configured for handle",
+            ),
+            (
+                HolochainError::UnsupportedDnaVersion {
+                    found: 2,
+                    supported: (1, 1),
+                },
+                "unsupported DNA manifest version",
+            ),
+            (
+                HolochainError::Timeout.with_context("waiting for foo"),
+                "waiting for foo",
+            ),
         ] {
             assert_eq!(output, input.description());
         }
     }
 
+    #[test]
+    fn context_chain() {
+        let err = HolochainError::IoError(String::from("disk full"))
+            .with_context("reading links")
+            .with_context("hc_get_links");
+
+        assert_eq!("hc_get_links: reading links: disk full", err.to_string());
+        assert_eq!(
+            &HolochainError::IoError(String::from("disk full")),
+            err.root()
+        );
+        assert_eq!("reading links: disk full", err.cause().unwrap().to_string());
+        assert_eq!(
+            ErrorTrail {
+                error: String::from("disk full"),
+                context: vec![String::from("hc_get_links"), String::from("reading links")],
+            },
+            ErrorTrail::from(&err),
+        );
+        assert_eq!(
+            JsonString::from(ErrorTrail::from(&err)),
+            JsonString::from(
+                "{\"error\":\"disk full\",\"context\":[\"hc_get_links\",\"reading links\"]}"
+            ),
+        );
+        // the chain survives the trip through JSON, e.g. inside a CoreError
+        assert_eq!(
+            err,
+            HolochainError::try_from(JsonString::from(err.clone())).unwrap()
+        );
+    }
+
+    #[test]
+    fn error_without_context_is_its_own_root() {
+        let err = HolochainError::Timeout;
+        assert_eq!(&err, err.root());
+        assert!(err.contexts().is_empty());
+        assert!(err.cause().is_none());
+    }
+
     #[test]
     fn core_error_to_string() {
         let error =
@@ -343,5 +585,4 @@ mod tests {
             .to_string(),
         );
     }
-
 }
@@ -93,6 +93,11 @@ pub enum RibosomeErrorCode {
     NotAnAllocation                 = 8 << 16,
     ZeroSizedAllocation             = 9 << 16,
     UnknownEntryType                = 10 << 16,
+    NonDeterministicCallInValidation = 11 << 16,
+    InvalidQueryCursor              = 12 << 16,
+    OutOfBounds                     = 13 << 16,
+    ZeroLength                      = 14 << 16,
+    PayloadTooLarge                 = 15 << 16,
 }
 
 #[cfg_attr(rustfmt, rustfmt_skip)]
@@ -109,6 +114,11 @@ impl RibosomeErrorCode {
             NotAnAllocation                 => "Not an allocation",
             ZeroSizedAllocation             => "Zero-sized allocation",
             UnknownEntryType                => "Unknown entry type",
+            NonDeterministicCallInValidation => "Non-deterministic call in validation",
+            InvalidQueryCursor              => "Invalid query cursor",
+            OutOfBounds                     => "Out of bounds",
+            ZeroLength                      => "Zero length",
+            PayloadTooLarge                 => "Payload too large",
         }
     }
 }
@@ -138,6 +148,11 @@ impl RibosomeErrorCode {
             8 => NotAnAllocation,
             9 => ZeroSizedAllocation,
             10 => UnknownEntryType,
+            11 => NonDeterministicCallInValidation,
+            12 => InvalidQueryCursor,
+            13 => OutOfBounds,
+            14 => ZeroLength,
+            15 => PayloadTooLarge,
             1 | _ => Unspecified,
         }
     }
@@ -188,6 +203,13 @@ impl FromStr for RibosomeErrorCode {
             "Not an allocation" => Ok(RibosomeErrorCode::NotAnAllocation),
             "Zero-sized allocation" => Ok(RibosomeErrorCode::ZeroSizedAllocation),
             "Unknown entry type" => Ok(RibosomeErrorCode::UnknownEntryType),
+            "Non-deterministic call in validation" => {
+                Ok(RibosomeErrorCode::NonDeterministicCallInValidation)
+            }
+            "Invalid query cursor" => Ok(RibosomeErrorCode::InvalidQueryCursor),
+            "Out of bounds" => Ok(RibosomeErrorCode::OutOfBounds),
+            "Zero length" => Ok(RibosomeErrorCode::ZeroLength),
+            "Payload too large" => Ok(RibosomeErrorCode::PayloadTooLarge),
             _ => Err(HolochainError::ErrorGeneric(String::from(
                 "Unknown RibosomeErrorCode",
             ))),
@@ -217,8 +239,9 @@ pub mod tests {
 
     #[test]
     fn error_conversion() {
-        for code in 1..=10 {
+        for code in 1..=15 {
             let mut err = RibosomeErrorCode::from_offset(code);
+            assert_eq!(code as u32, err.clone() as u32 >> 16);
 
             let err_str = err.as_str().to_owned();
 
@@ -62,6 +62,11 @@ impl HashString {
     pub fn encode_from_json_string(json_string: JsonString, hash_type: Hash) -> HashString {
         HashString::encode_from_str(&String::from(json_string), hash_type)
     }
+
+    /// the first 8 characters of the hash, for human readable output like logs
+    pub fn short(&self) -> String {
+        self.0.chars().take(8).collect()
+    }
 }
 
 #[cfg(test)]
@@ -88,6 +93,16 @@ pub mod tests {
         )
     }
 
+    #[test]
+    /// show short() only keeps the first 8 characters
+    fn short_test() {
+        assert_eq!(
+            HashString::from("QmY8Mzg9F69e5P9AoQPYat655HEhc1TVGs11tmfNSzkqh2").short(),
+            "QmY8Mzg9".to_string(),
+        );
+        assert_eq!(HashString::from("Qm").short(), "Qm".to_string());
+    }
+
     #[test]
     /// show From<String> implementation
     fn from_string_test() {
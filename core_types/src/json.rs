@@ -62,6 +62,19 @@ impl TryFrom<JsonString> for u32 {
     }
 }
 
+impl From<usize> for JsonString {
+    fn from(u: usize) -> JsonString {
+        default_to_json(u)
+    }
+}
+
+impl TryFrom<JsonString> for usize {
+    type Error = HolochainError;
+    fn try_from(j: JsonString) -> Result<Self, Self::Error> {
+        default_try_from_json(j)
+    }
+}
+
 impl From<serde_json::Value> for JsonString {
     fn from(v: serde_json::Value) -> JsonString {
         JsonString::from(v.to_string())
@@ -13,6 +13,7 @@
 #[macro_use]
 extern crate arrayref;
 extern crate base64;
+extern crate chrono;
 extern crate futures;
 extern crate multihash;
 extern crate reed_solomon;
@@ -29,6 +30,9 @@ extern crate bitflags;
 extern crate holochain_core_types_derive;
 
 extern crate uuid;
+#[cfg(test)]
+#[macro_use]
+extern crate proptest;
 
 pub mod cas;
 pub mod chain_header;
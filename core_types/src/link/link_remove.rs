@@ -1,2 +1,80 @@
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct LinkRemove {}
+use crate::{
+    cas::content::Address,
+    error::HolochainError,
+    json::JsonString,
+    link::{Link, LinkActionKind},
+};
+
+//-------------------------------------------------------------------------------------------------
+// LinkRemove
+//-------------------------------------------------------------------------------------------------
+
+/// The entry that removes a link. It either names the header of the one add of the link it
+/// removes, or removes all adds of the link made before its own header.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, DefaultJson)]
+pub struct LinkRemove {
+    action_kind: LinkActionKind,
+    link: Link,
+    removed_add: Option<Address>,
+}
+
+impl LinkRemove {
+    pub fn new(base: &Address, target: &Address, tag: &str, removed_add: Option<&Address>) -> Self {
+        LinkRemove {
+            action_kind: LinkActionKind::DELETE,
+            link: Link::new(base, target, tag),
+            removed_add: removed_add.cloned(),
+        }
+    }
+
+    pub fn action_kind(&self) -> &LinkActionKind {
+        &self.action_kind
+    }
+
+    pub fn link(&self) -> &Link {
+        &self.link
+    }
+
+    /// The header of the add this removes, if it removes just that one
+    pub fn removed_add(&self) -> Option<&Address> {
+        self.removed_add.as_ref()
+    }
+
+    pub fn from_link(link: &Link, removed_add: Option<&Address>) -> Self {
+        LinkRemove {
+            action_kind: LinkActionKind::DELETE,
+            link: link.clone(),
+            removed_add: removed_add.cloned(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::{
+        cas::content::{Address, AddressableContent},
+        entry::Entry,
+        json::JsonString,
+        link::{link_remove::LinkRemove, tests::example_link},
+    };
+    use std::convert::TryFrom;
+
+    #[test]
+    fn link_remove_roundtrips_and_keeps_the_removed_add() {
+        let removed_add = Address::from("header of the add");
+        let link_remove = LinkRemove::from_link(&example_link(), Some(&removed_add));
+        assert_eq!(link_remove.link(), &example_link());
+        assert_eq!(link_remove.removed_add(), Some(&removed_add));
+
+        let entry = Entry::LinkRemove(link_remove);
+        assert_eq!(
+            Entry::try_from(JsonString::from(entry.clone())).unwrap(),
+            entry
+        );
+        // removing all adds is a different entry than removing one of them
+        assert_ne!(
+            Entry::LinkRemove(LinkRemove::from_link(&example_link(), None)).address(),
+            entry.address()
+        );
+    }
+}
@@ -2,10 +2,21 @@ pub mod link_add;
 pub mod link_list;
 pub mod link_remove;
 
-use crate::{cas::content::Address, error::HolochainError, json::JsonString};
+use crate::{
+    cas::content::{Address, AddressableContent},
+    chain_header::ChainHeader,
+    error::HolochainError,
+    json::JsonString,
+    time::Iso8601,
+};
 
 type LinkTag = String;
 
+/// Reserved tag of the links core publishes from the DNA entry to the identity entry of
+/// every agent that joins, so that zomes can list the agents of their DNA.
+/// Zomes can't create links with this tag themselves.
+pub const AGENT_ID_LINK_TAG: &str = "%agent_id";
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, DefaultJson)]
 pub struct Link {
     base: Address,
@@ -36,6 +47,58 @@ impl Link {
     }
 }
 
+/// An add or remove of a link as holders get it, with the timestamp and the provenance of
+/// the header it got published with. Holders keep all operations of a link, so the links
+/// they serve don't depend on the order the operations reached them in.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LinkOperation {
+    link: Link,
+    timestamp: Iso8601,
+    /// None for operations that only happened locally
+    header: Option<Address>,
+    provenance: Vec<Address>,
+}
+
+impl LinkOperation {
+    /// An operation without a header, made at the given time
+    pub fn new(link: &Link, timestamp: Iso8601) -> Self {
+        LinkOperation {
+            link: link.to_owned(),
+            timestamp,
+            header: None,
+            provenance: Vec::new(),
+        }
+    }
+
+    /// The operation that the given header published
+    pub fn from_header(link: &Link, header: &ChainHeader) -> Self {
+        LinkOperation {
+            link: link.to_owned(),
+            timestamp: header.timestamp().clone(),
+            header: Some(header.address()),
+            provenance: header.sources().clone(),
+        }
+    }
+
+    // Getters
+    pub fn link(&self) -> &Link {
+        &self.link
+    }
+
+    pub fn timestamp(&self) -> &Iso8601 {
+        &self.timestamp
+    }
+
+    pub fn header(&self) -> Option<&Address> {
+        self.header.as_ref()
+    }
+
+    /// The agents that published the operation
+    pub fn provenance(&self) -> &Vec<Address> {
+        &self.provenance
+    }
+}
+
 // HC.LinkAction sync with hdk-rust
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum LinkActionKind {
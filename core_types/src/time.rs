@@ -1,12 +1,325 @@
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
-pub struct Iso8601(String);
+//! Timestamps in the RFC 3339 profile of ISO 8601, as used in chain headers.
+use crate::error::HolochainError;
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    cmp::Ordering,
+    fmt,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The point in time malformed timestamps are treated as: 1970-01-01T00:00:00+00:00.
+/// Old chain headers might carry empty or otherwise malformed timestamps. Instead of refusing
+/// to load them, they get ordered as if they happened at the beginning of the Unix epoch.
+pub const EPOCH_SENTINEL: &str = "1970-01-01T00:00:00+00:00";
+
+/// A timestamp like "2018-10-11T03:23:38+00:00".
+/// Timestamps are ordered by the instant they describe, independent of their time zone offset.
+/// They serialize to the exact string they were parsed from, so that the address of
+/// content containing a timestamp does not change when it gets loaded and stored again.
+/// Timestamps created from a `SystemTime` use the canonical RFC 3339 format in UTC.
+#[derive(Clone, Debug)]
+pub struct Iso8601 {
+    instant: DateTime<FixedOffset>,
+    string: String,
+}
+
+impl Iso8601 {
+    /// The current time as told by the given time source
+    pub fn now(time_source: &TimeSource) -> Self {
+        Iso8601::from(time_source.now())
+    }
+
+    /// Parses the given string like `from_str()`, but degrades to the epoch sentinel
+    /// (keeping the original string) instead of failing, and prints a warning.
+    /// Empty strings are what chain headers get as long as they are not timestamped,
+    /// so they degrade without a warning.
+    pub fn from_str_lossy(s: &str) -> Self {
+        s.parse().unwrap_or_else(|error| {
+            if !s.is_empty() {
+                println!("Warning: {}, treating it as {}", error, EPOCH_SENTINEL);
+            }
+            Iso8601 {
+                instant: DateTime::parse_from_rfc3339(EPOCH_SENTINEL).unwrap(),
+                string: s.to_owned(),
+            }
+        })
+    }
+
+    /// False if this timestamp could not be parsed and stands for the epoch sentinel
+    pub fn is_valid(&self) -> bool {
+        DateTime::parse_from_rfc3339(&self.string).is_ok()
+    }
+
+    pub fn to_system_time(&self) -> SystemTime {
+        let seconds = self.instant.timestamp();
+        let nanos = Duration::from_nanos(u64::from(self.instant.timestamp_subsec_nanos()));
+        if seconds >= 0 {
+            UNIX_EPOCH + Duration::from_secs(seconds as u64) + nanos
+        } else {
+            UNIX_EPOCH - Duration::from_secs(seconds.abs() as u64) + nanos
+        }
+    }
+}
+
+impl FromStr for Iso8601 {
+    type Err = HolochainError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DateTime::parse_from_rfc3339(s)
+            .map(|instant| Iso8601 {
+                instant,
+                string: s.to_owned(),
+            })
+            .map_err(|error| {
+                HolochainError::ErrorGeneric(format!(
+                    "Malformed ISO 8601 timestamp \"{}\": {}",
+                    s, error
+                ))
+            })
+    }
+}
 
 impl From<&'static str> for Iso8601 {
     fn from(s: &str) -> Iso8601 {
-        Iso8601(s.to_owned())
+        Iso8601::from_str_lossy(s)
+    }
+}
+
+impl From<SystemTime> for Iso8601 {
+    fn from(time: SystemTime) -> Iso8601 {
+        let utc: DateTime<Utc> = match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => Utc.timestamp(duration.as_secs() as i64, duration.subsec_nanos()),
+            Err(error) => {
+                let duration = error.duration();
+                let seconds = -(duration.as_secs() as i64);
+                match duration.subsec_nanos() {
+                    0 => Utc.timestamp(seconds, 0),
+                    nanos => Utc.timestamp(seconds - 1, 1_000_000_000 - nanos),
+                }
+            }
+        };
+        Iso8601 {
+            instant: utc.with_timezone(&FixedOffset::east(0)),
+            string: utc.to_rfc3339(),
+        }
+    }
+}
+
+impl<'a> From<&'a Iso8601> for SystemTime {
+    fn from(timestamp: &Iso8601) -> SystemTime {
+        timestamp.to_system_time()
+    }
+}
+
+impl fmt::Display for Iso8601 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.string)
+    }
+}
+
+impl PartialEq for Iso8601 {
+    fn eq(&self, other: &Iso8601) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Iso8601 {}
+
+impl PartialOrd for Iso8601 {
+    fn partial_cmp(&self, other: &Iso8601) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Compares the instants first. Different strings for the same instant
+/// (e.g. in different time zones) are ordered by the string to be consistent with `Eq`.
+impl Ord for Iso8601 {
+    fn cmp(&self, other: &Iso8601) -> Ordering {
+        self.instant
+            .cmp(&other.instant)
+            .then_with(|| self.string.cmp(&other.string))
+    }
+}
+
+impl Serialize for Iso8601 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.string)
+    }
+}
+
+/// Malformed timestamps do not fail deserialization, see `EPOCH_SENTINEL`.
+impl<'de> Deserialize<'de> for Iso8601 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Iso8601Visitor;
+        impl<'de> de::Visitor<'de> for Iso8601Visitor {
+            type Value = Iso8601;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "an ISO 8601 timestamp string")
+            }
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Iso8601, E> {
+                Ok(Iso8601::from_str_lossy(s))
+            }
+        }
+        deserializer.deserialize_str(Iso8601Visitor)
+    }
+}
+
+/// Where the current time comes from.
+/// Lets tests and simulations run with a fixed or scripted clock.
+pub trait TimeSource: Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// The system's clock
+#[derive(Clone, Debug, Default)]
+pub struct SystemTimeSource;
+
+impl TimeSource for SystemTimeSource {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that always tells the same time
+#[derive(Clone, Debug)]
+pub struct FixedTimeSource(pub SystemTime);
+
+impl TimeSource for FixedTimeSource {
+    fn now(&self) -> SystemTime {
+        self.0
+    }
+}
+
+/// A clock that only moves when told to, for tests of things that happen after a while
+#[derive(Debug)]
+pub struct ManualTimeSource(Mutex<SystemTime>);
+
+impl ManualTimeSource {
+    pub fn new(time: SystemTime) -> Self {
+        ManualTimeSource(Mutex::new(time))
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.0.lock().unwrap() += duration;
+    }
+}
+
+impl TimeSource for ManualTimeSource {
+    fn now(&self) -> SystemTime {
+        *self.0.lock().unwrap()
     }
 }
 
 pub fn test_iso_8601() -> Iso8601 {
     Iso8601::from("2018-10-11T03:23:38+00:00")
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use serde_json;
+
+    #[test]
+    fn parses_valid_timestamps() {
+        let timestamp: Iso8601 = "2018-10-11T03:23:38+00:00".parse().unwrap();
+        assert!(timestamp.is_valid());
+        assert_eq!(timestamp.to_string(), "2018-10-11T03:23:38+00:00");
+        assert_eq!(
+            timestamp.to_system_time(),
+            UNIX_EPOCH + Duration::from_secs(1_539_228_218)
+        );
+        assert!("2018-10-11".parse::<Iso8601>().is_err());
+        assert!("".parse::<Iso8601>().is_err());
+    }
+
+    #[test]
+    fn orders_by_instant() {
+        let utc = Iso8601::from("2018-10-11T03:23:38+00:00");
+        let earlier_in_berlin = Iso8601::from("2018-10-11T04:23:37+01:00");
+        let later = Iso8601::from("2018-10-11T03:23:38.5+00:00");
+        // lexically, the timestamp in Berlin would come last
+        assert!(earlier_in_berlin < utc);
+        assert!(utc < later);
+        assert!(earlier_in_berlin < later);
+    }
+
+    #[test]
+    fn serializes_to_the_original_string() {
+        let json = r#""2018-10-11T04:23:38+01:00""#;
+        let timestamp: Iso8601 = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::to_string(&timestamp).unwrap(), json);
+        assert_eq!(
+            timestamp,
+            serde_json::from_str::<Iso8601>(r#""2018-10-11T04:23:38+01:00""#).unwrap()
+        );
+    }
+
+    #[test]
+    fn malformed_timestamps_degrade_to_the_epoch_sentinel() {
+        let malformed: Iso8601 = serde_json::from_str(r#""""#).unwrap();
+        assert!(!malformed.is_valid());
+        assert_eq!(malformed.to_system_time(), UNIX_EPOCH);
+        assert!(malformed < test_iso_8601());
+        // the original string is kept, so the content address of old headers stays the same
+        assert_eq!(serde_json::to_string(&malformed).unwrap(), r#""""#);
+    }
+
+    #[test]
+    fn now_uses_the_time_source() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_539_228_218_250);
+        let timestamp = Iso8601::now(&FixedTimeSource(time));
+        assert_eq!(timestamp.to_string(), "2018-10-11T03:23:38.250+00:00");
+        assert_eq!(timestamp.to_system_time(), time);
+        assert!(Iso8601::now(&SystemTimeSource) > timestamp);
+    }
+
+    #[test]
+    fn manual_time_sources_advance_when_told() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_539_228_218);
+        let time_source = ManualTimeSource::new(start);
+        assert_eq!(time_source.now(), start);
+        time_source.advance(Duration::from_secs(2));
+        assert_eq!(time_source.now(), start + Duration::from_secs(2));
+    }
+
+    fn system_time(secs: i64, nanos: u32) -> SystemTime {
+        let time = if secs >= 0 {
+            UNIX_EPOCH + Duration::from_secs(secs as u64)
+        } else {
+            UNIX_EPOCH - Duration::from_secs(secs.abs() as u64)
+        };
+        time + Duration::from_nanos(u64::from(nanos))
+    }
+
+    proptest! {
+        #[test]
+        fn system_time_round_trip(secs in -10_000_000_000i64..10_000_000_000i64, nanos in 0u32..1_000_000_000u32) {
+            let time = system_time(secs, nanos);
+            let timestamp = Iso8601::from(time);
+            prop_assert!(timestamp.is_valid());
+            prop_assert_eq!(timestamp.to_system_time(), time);
+        }
+
+        #[test]
+        fn format_parse_round_trip(secs in -10_000_000_000i64..10_000_000_000i64, nanos in 0u32..1_000_000_000u32) {
+            let timestamp = Iso8601::from(system_time(secs, nanos));
+            let parsed: Iso8601 = timestamp.to_string().parse().unwrap();
+            prop_assert_eq!(&parsed, &timestamp);
+            let json = serde_json::to_string(&timestamp).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Iso8601>(&json).unwrap(), timestamp);
+        }
+
+        #[test]
+        fn ordering_follows_system_time(a in 0u64..10_000_000_000u64, b in 0u64..10_000_000_000u64) {
+            let time_a = UNIX_EPOCH + Duration::from_millis(a);
+            let time_b = UNIX_EPOCH + Duration::from_millis(b);
+            prop_assert_eq!(
+                Iso8601::from(time_a).cmp(&Iso8601::from(time_b)),
+                time_a.cmp(&time_b)
+            );
+        }
+    }
+}
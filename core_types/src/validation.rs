@@ -1,7 +1,7 @@
 extern crate serde_json;
 use crate::{
-    chain_header::ChainHeader, entry::Entry, error::HolochainError, hash::HashString,
-    json::JsonString,
+    cas::content::Address, chain_header::ChainHeader, entry::Entry, error::HolochainError,
+    hash::HashString, json::JsonString,
 };
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, DefaultJson, Default)]
@@ -10,6 +10,11 @@ pub struct ValidationPackage {
     pub source_chain_entries: Option<Vec<Entry>>,
     pub source_chain_headers: Option<Vec<ChainHeader>>,
     pub custom: Option<String>,
+    /// Stands in for the source chain entries whose content got pruned by their author,
+    /// see `EntryTypeDef::prunable`: their addresses, in chain order. They are missing from
+    /// `source_chain_entries`, while their headers are still in `source_chain_headers`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pruned_entries: Vec<Address>,
 }
 
 impl ValidationPackage {
@@ -19,8 +24,36 @@ impl ValidationPackage {
             source_chain_entries: None,
             source_chain_headers: None,
             custom: None,
+            pruned_entries: Vec::new(),
         }
     }
+
+    /// Checks that the sequence numbers of the source chain headers, which come top first,
+    /// count down. Headers the source left out, e.g. private ones, may leave gaps, but
+    /// unindexed legacy headers can only come after all others, see ChainHeader::sequence().
+    /// Fails with HolochainError::ValidationFailed otherwise.
+    pub fn check_sequence(&self) -> Result<(), HolochainError> {
+        let headers = match self.source_chain_headers {
+            Some(ref headers) => headers,
+            None => return Ok(()),
+        };
+        for pair in headers.windows(2) {
+            let (header, previous) = (&pair[0], &pair[1]);
+            let in_order = if header.sequence() == 0 {
+                previous.sequence() == 0
+            } else {
+                previous.sequence() < header.sequence()
+            };
+            if !in_order {
+                return Err(HolochainError::ValidationFailed(format!(
+                    "Validation package has header sequence {} before {}",
+                    header.sequence(),
+                    previous.sequence()
+                )));
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, DefaultJson)]
@@ -54,6 +87,15 @@ pub struct ValidationData {
     pub action: EntryAction,
 }
 
+impl ValidationData {
+    /// Whether the package has a content-pruned marker instead of the source chain entry
+    /// with the given address, see `ValidationPackage::pruned_entries`.
+    /// Validation that needs the content of such entries can't have it anymore.
+    pub fn is_pruned(&self, address: &Address) -> bool {
+        self.package.pruned_entries.contains(address)
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum EntryLifecycle {
     Chain,
@@ -85,3 +127,38 @@ pub enum LinkAction {
     Create,
     Delete,
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::chain_header::{test_chain_header, tests::test_chain_header_b};
+
+    fn package_with_sequences(sequences: &[u64]) -> ValidationPackage {
+        ValidationPackage {
+            source_chain_headers: Some(
+                sequences
+                    .iter()
+                    .map(|sequence| test_chain_header().with_sequence(*sequence))
+                    .collect(),
+            ),
+            ..ValidationPackage::only_header(test_chain_header_b())
+        }
+    }
+
+    #[test]
+    /// test that packages with headers out of sequence get rejected
+    fn check_sequence_test() {
+        assert!(ValidationPackage::only_header(test_chain_header())
+            .check_sequence()
+            .is_ok());
+        for sequences in vec![vec![5, 4, 3], vec![7, 4, 1], vec![2, 1, 0, 0], vec![0, 0]] {
+            assert!(package_with_sequences(&sequences).check_sequence().is_ok());
+        }
+        for sequences in vec![vec![3, 4], vec![3, 3], vec![0, 1], vec![2, 0, 1]] {
+            match package_with_sequences(&sequences).check_sequence() {
+                Err(HolochainError::ValidationFailed(_)) => (),
+                result => panic!("expected ValidationFailed, got {:?}", result),
+            }
+        }
+    }
+}
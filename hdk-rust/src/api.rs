@@ -1,35 +1,56 @@
 use crate::{
+    entry_definition::ValidatingEntryType,
     error::{ZomeApiError, ZomeApiResult},
+    global_fns::{call_host, call_host_fn, call_init_data},
     globals::*,
+    host_fns::*,
+    meta::entry_type_definition,
 };
 use holochain_core_types::{
     cas::content::Address,
     crud_status::CrudStatus,
-    entry::Entry,
-    error::{CoreError, HolochainError, RibosomeReturnCode, ZomeApiInternalResult},
+    entry::{
+        entry_type::{AppEntryType, EntryType},
+        Entry,
+    },
+    error::{CoreError, HolochainError, RibosomeErrorCode},
+    json::default_try_from_json,
+    link::AGENT_ID_LINK_TAG,
+    time::Iso8601,
 };
 pub use holochain_wasm_utils::api_serialization::validation::*;
 use holochain_wasm_utils::{
     api_serialization::{
-        get_entry::{EntryHistory, GetEntryArgs, GetEntryOptions, StatusRequestKind},
-        get_links::{GetLinksArgs, GetLinksResult},
-        link_entries::LinkEntriesArgs,
-        QueryArgs, QueryResult, UpdateEntryArgs, ZomeFnCallArgs,
+        get_entry::{
+            EntryHistory, GetEntryArgs, GetEntryOptions, GetResultStatus, StatusRequestKind,
+        },
+        get_links::{
+            CountLinksArgs, GetLinksArgs, GetLinksDirection, GetLinksResult, LinksStatusFilter,
+        },
+        link_entries::{CommitAndLinkArgs, CommitAndLinkResult, LinkEntriesArgs},
+        AwaitPublishArgs, BridgeCallArgs, CallContext, ChainInfo, CommitEntryArgs,
+        CommitEntryResult, CommitOptions, DecryptArgs, EncryptForArgs, EntryTypeDef, PublishStatus,
+        QueryArgs, QueryPage, QueryPageArgs, QueryResult, RandomBytesArgs, SleepArgs,
+        UpdateAgentArgs, UpdateEntryArgs, ZomeFnCallArgs,
     },
     holochain_core_types::{
         hash::HashString,
         json::{JsonString, RawString},
     },
-    memory_allocation::*,
-    memory_serialization::*,
+    memory_serialization::load_json_from_str,
 };
 use serde_json;
-use std::{convert::TryInto, os::raw::c_char};
+use std::{
+    convert::{TryFrom, TryInto},
+    time::Duration,
+};
 
 //--------------------------------------------------------------------------------------------------
 // ZOME API GLOBAL VARIABLES
 //--------------------------------------------------------------------------------------------------
 
+// These panic if the globals can't be retrieved from the Ribosome.
+// Use `app_globals()` to handle that case instead.
 lazy_static! {
   /// The `name` property as taken from the DNA.
   pub static ref DNA_NAME: &'static str = &GLOBALS.dna_name;
@@ -51,7 +72,6 @@ lazy_static! {
   /// This is your peer's identity on the DHT.
   pub static ref AGENT_INITIAL_HASH: &'static HashString = &GLOBALS.agent_initial_hash;
 
-  #[doc(hidden)]
   /// The hash of the most recent identity entry that has been committed to your chain.
   /// Starts with the same value as AGENT_INITIAL_HASH.
   /// After a call to `update_agent` it will have the value of the hash of the newly committed identity entry.
@@ -194,18 +214,36 @@ pub enum BundleOnClose {
 /// # }
 /// ```
 pub fn debug<J: TryInto<JsonString>>(msg: J) -> ZomeApiResult<()> {
-    let mut mem_stack = unsafe { G_MEM_STACK.unwrap() };
-
-    let allocation_of_input = store_as_json(&mut mem_stack, msg)?;
-
-    unsafe {
-        hc_debug(allocation_of_input.encode());
-    }
-
-    mem_stack
-        .deallocate(allocation_of_input)
-        .expect("should be able to deallocate input that has been allocated on memory stack");
+    call_host(hc_debug, msg)?;
+    Ok(())
+}
 
+/// Hands a partial result of the running zome function to its caller right away,
+/// ahead of its return value, so long running functions can report progress or stream
+/// their results as they compute them.
+/// Clients of the websocket interface get each partial result pushed as a `partial_result`
+/// notification carrying the id of their request; clients of the HTTP interface get
+/// all of them before the return value in an array.
+/// If the client doesn't keep up, the zome call fails with a BackpressureExceeded error.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate holochain_core_types;
+/// # use holochain_core_types::json::JsonString;
+///
+/// # fn main() {
+/// pub fn handle_count_to(limit: u32) -> JsonString {
+///     for i in 0..limit {
+///         if let Err(hdk_error) = hdk::yield_partial(JsonString::from(i)) {
+///             return hdk_error.into();
+///         }
+///     }
+///     JsonString::from(limit)
+/// }
+/// # }
+/// ```
+pub fn yield_partial<J: TryInto<JsonString>>(partial_result: J) -> ZomeApiResult<()> {
+    call_host_fn(hc_yield, partial_result)?;
     Ok(())
 }
 
@@ -358,43 +396,80 @@ pub fn call<S: Into<String>>(
     fn_name: S,
     fn_args: JsonString,
 ) -> ZomeApiResult<JsonString> {
-    let mut mem_stack: SinglePageStack;
-    unsafe {
-        mem_stack = G_MEM_STACK.unwrap();
-    }
-
-    // Put args in struct and serialize into memory
-    let allocation_of_input = store_as_json(
-        &mut mem_stack,
-        ZomeFnCallArgs {
-            zome_name: zome_name.into(),
-            cap_name: cap_name.into(),
-            fn_name: fn_name.into(),
-            fn_args: String::from(fn_args),
-        },
-    )?;
+    // The called function's result comes back as it is, not as a ZomeApiInternalResult
+    let args = ZomeFnCallArgs {
+        zome_name: zome_name.into(),
+        cap_name: cap_name.into(),
+        fn_name: fn_name.into(),
+        fn_args: String::from(fn_args),
+    };
+    Ok(call_host(hc_call, args)?)
+}
 
-    // Call WASMI-able commit
-    let encoded_allocation_of_result: u32;
-    unsafe {
-        encoded_allocation_of_result = hc_call(allocation_of_input.encode() as u32);
-    }
-    // Deserialize complex result stored in memory and check for ERROR in encoding
-    let result = load_string(encoded_allocation_of_result as u32)?;
+/// Calls a function of the instance at the other end of a bridge, like `call()` does
+/// within the instance. The handle is one of the bridges the DNA declares in its manifest,
+/// see `BridgeDeclaration`; which instance it leads to is up to the container.
+/// Fails with `ZomeApiError::BridgeNotPresent` if the container configured no bridge with
+/// the handle, which only happens for optional bridges since instances whose required
+/// bridges are missing don't start.
+/// Like `call()`, the result is whatever the called function returns.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate holochain_core_types;
+/// # use hdk::error::{ZomeApiError, ZomeApiResult};
+/// # use holochain_core_types::json::JsonString;
+/// # fn main() {
+/// fn search(query: String) -> ZomeApiResult<JsonString> {
+///     match hdk::call_bridge("search", "index", "main", "query", JsonString::from(query)) {
+///         Err(ZomeApiError::BridgeNotPresent(_)) => Ok(JsonString::from("[]")),
+///         result => result,
+///     }
+/// }
+/// # }
+/// ```
+pub fn call_bridge<S: Into<String>>(
+    handle: S,
+    zome_name: S,
+    cap_name: S,
+    fn_name: S,
+    fn_args: JsonString,
+) -> ZomeApiResult<JsonString> {
+    let args = BridgeCallArgs {
+        handle: handle.into(),
+        zome_name: zome_name.into(),
+        cap_name: cap_name.into(),
+        fn_name: fn_name.into(),
+        fn_args: String::from(fn_args),
+    };
+    call_host_fn(hc_call_bridge, args).map_err(bring_out_matchable_error)
+}
 
-    // Free result & input allocations.
-    mem_stack
-        .deallocate(allocation_of_input)
-        .expect("deallocate failed");
+/// The addresses produced by a successful `commit_entry()`.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug, DefaultJson)]
+pub struct CommitResult {
+    /// The address of the committed entry.
+    pub entry_address: Address,
+    /// The address of the chain header that was created for the entry.
+    pub header_address: Address,
+}
 
-    // Done
-    Ok(result.into())
+impl From<CommitEntryResult> for CommitResult {
+    fn from(commit_entry_result: CommitEntryResult) -> Self {
+        CommitResult {
+            entry_address: commit_entry_result.address,
+            header_address: commit_entry_result.header_address,
+        }
+    }
 }
 
 /// Attempts to commit an entry to your local source chain. The entry
 /// will have to pass the defined validation rules for that entry type.
+/// Its type has to be one of the entry types defined in the DNA, and the validation
+/// callback runs before this returns, so an invalid entry never makes it onto the chain;
+/// the call takes as long as the validation does.
 /// If the entry type is defined as public, will also publish the entry to the DHT.
-/// Returns either an address of the committed entry as a string, or an error.
+/// Returns either the addresses of the committed entry and of its chain header, or an error.
 /// # Examples
 /// ```rust
 /// # #![feature(try_from)]
@@ -429,7 +504,7 @@ pub fn call<S: Into<String>>(
 ///     }.into());
 ///
 ///     match hdk::commit_entry(&post_entry) {
-///         Ok(address) => address.into(),
+///         Ok(commit_result) => commit_result.entry_address.into(),
 ///         Err(e) => e.into(),
 ///     }
 ///
@@ -437,38 +512,174 @@ pub fn call<S: Into<String>>(
 ///
 /// # }
 /// ```
-pub fn commit_entry(entry: &Entry) -> ZomeApiResult<Address> {
-    let mut mem_stack: SinglePageStack;
-    unsafe {
-        mem_stack = G_MEM_STACK.unwrap();
-    }
+pub fn commit_entry(entry: &Entry) -> ZomeApiResult<CommitResult> {
+    let commit_entry_result: CommitEntryResult =
+        call_host_fn(hc_commit_entry, entry)?.try_into()?;
+    Ok(commit_entry_result.into())
+}
 
-    let allocation_of_input = store_as_json(&mut mem_stack, entry)?;
+/// Commits an entry like [commit_entry](fn.commit_entry.html), with options.
+/// By default a commit goes on top of whatever the top of the source chain is by the time it
+/// gets written, which may be an entry another zome call committed meanwhile.
+/// Setting `require_head` to the header address the zome based its entry on, e.g. the
+/// `top_header_address` of [chain_info](fn.chain_info.html), makes the commit fail with
+/// `ZomeApiError::ChainHeadMoved` instead, so the zome can read again and retry.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate holochain_core_types;
+/// # use hdk::error::{ZomeApiError, ZomeApiResult};
+/// # use hdk::holochain_wasm_utils::api_serialization::CommitOptions;
+/// # use holochain_core_types::cas::content::Address;
+/// # use holochain_core_types::entry::Entry;
+/// # fn main() {
+/// fn commit_on_top(entry: &Entry) -> ZomeApiResult<Address> {
+///     loop {
+///         let options = CommitOptions {
+///             require_head: hdk::chain_info()?.top_header_address,
+///             ..Default::default()
+///         };
+///         match hdk::commit_entry_with_options(entry, options) {
+///             Err(ZomeApiError::ChainHeadMoved { .. }) => continue,
+///             result => return result.map(|commit_result| commit_result.entry_address),
+///         }
+///     }
+/// }
+/// # }
+/// ```
+pub fn commit_entry_with_options(
+    entry: &Entry,
+    options: CommitOptions,
+) -> ZomeApiResult<CommitResult> {
+    let args = CommitEntryArgs {
+        entry: entry.clone(),
+        options,
+    };
+    let commit_entry_result: CommitEntryResult = call_host_fn(hc_commit_entry, args)
+        .map_err(bring_out_matchable_error)?
+        .try_into()?;
+    Ok(commit_entry_result.into())
+}
 
-    // Call Ribosome's commit_entry()
-    let encoded_allocation_of_result: u32;
-    unsafe {
-        encoded_allocation_of_result = hc_commit_entry(allocation_of_input.encode() as u32);
+/// Core errors reach the zome as JSON in an Internal error.
+/// Brings out the ones zomes want to match on: ChainHeadMoved to retry, Timeout,
+/// and BridgeNotPresent for optional bridges.
+fn bring_out_matchable_error(zome_api_error: ZomeApiError) -> ZomeApiError {
+    if let ZomeApiError::Internal(ref core_error_json) = zome_api_error {
+        if let Ok(core_error) = CoreError::try_from(JsonString::from(core_error_json.clone())) {
+            match core_error.kind.root() {
+                HolochainError::ChainHeadMoved { .. }
+                | HolochainError::Timeout
+                | HolochainError::BridgeNotPresent(_) => {
+                    return ZomeApiError::from(core_error.kind.root().clone());
+                }
+                _ => (),
+            }
+        }
     }
+    zome_api_error
+}
 
-    // Deserialize complex result stored in wasm memory
-    let result: ZomeApiInternalResult = load_json(encoded_allocation_of_result as u32)?;
-    // Free result & input allocations
-    mem_stack
-        .deallocate(allocation_of_input)
-        .expect("deallocate failed");
-    // Done
-    if result.ok {
-        Ok(JsonString::from(result.value).try_into()?)
-    } else {
-        Err(ZomeApiError::from(result.error))
-    }
+/// Commits an entry like `commit_entry()` but only returns the address of the committed entry.
+pub fn commit_entry_address(entry: &Entry) -> ZomeApiResult<Address> {
+    commit_entry(entry).map(|commit_result| commit_result.entry_address)
+}
+
+/// Waits until at least `min_holders` nodes acknowledged holding an entry this agent committed,
+/// and returns how many did. Nodes only acknowledge entries that passed their validation, so once
+/// this returns, other agents can get the entry.
+/// Returns a ZomeApiError::Timeout if not enough nodes acknowledged it in time, e.g. because they
+/// rejected it; the entry stays committed and published nonetheless.
+/// Committing with `CommitOptions { publish: PublishMode::Block { min_holders }, .. }` does the
+/// same as part of the commit, with the timeout of the node.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate holochain_core_types;
+/// # use hdk::error::ZomeApiResult;
+/// # use holochain_core_types::cas::content::Address;
+/// # use holochain_core_types::entry::Entry;
+/// # use std::time::Duration;
+/// # fn main() {
+/// fn commit_and_confirm(entry: &Entry) -> ZomeApiResult<Address> {
+///     let address = hdk::commit_entry_address(entry)?;
+///     hdk::await_publish(&address, 3, Duration::from_secs(10))?;
+///     Ok(address)
+/// }
+/// # }
+/// ```
+pub fn await_publish(
+    address: &Address,
+    min_holders: usize,
+    timeout: Duration,
+) -> ZomeApiResult<usize> {
+    let args = AwaitPublishArgs {
+        address: address.clone(),
+        min_holders,
+        timeout_ms: timeout.as_secs() * 1000 + u64::from(timeout.subsec_millis()),
+    };
+    Ok(call_host_fn(hc_await_publish, args)
+        .map_err(bring_out_matchable_error)?
+        .try_into()?)
+}
+
+/// Returns how an entry this agent published fared so far: how many nodes acknowledged
+/// holding it, and which of the nodes asked to hold it rejected it, with the reason their
+/// validation gave. Entries this agent did not publish have neither.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate holochain_core_types;
+/// # use hdk::error::ZomeApiResult;
+/// # use holochain_core_types::cas::content::Address;
+/// # fn main() {
+/// fn rejection_reasons(address: &Address) -> ZomeApiResult<Vec<String>> {
+///     let status = hdk::get_publish_status(address)?;
+///     Ok(status
+///         .rejections
+///         .into_iter()
+///         .map(|(holder, reason)| format!("{}: {}", holder, reason))
+///         .collect())
+/// }
+/// # }
+/// ```
+pub fn get_publish_status(address: &Address) -> ZomeApiResult<PublishStatus> {
+    Ok(call_host_fn(hc_get_publish_status, address.clone())?.try_into()?)
+}
+
+/// Publishes an entry this agent committed again. Committing an entry that got published before
+/// only publishes it if its meta changed, e.g. it got updated or removed; this publishes it
+/// anyway, to repair a DHT that lost it. Republishing right after the entry went out with the
+/// same meta does nothing more.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate holochain_core_types;
+/// # use hdk::error::ZomeApiResult;
+/// # use holochain_core_types::cas::content::Address;
+/// # fn main() {
+/// fn repair(address: &Address) -> ZomeApiResult<()> {
+///     if hdk::get_publish_status(address)?.holders == 0 {
+///         hdk::republish(address)?;
+///     }
+///     Ok(())
+/// }
+/// # }
+/// ```
+pub fn republish(address: &Address) -> ZomeApiResult<()> {
+    call_host_fn(hc_republish, address.clone())?;
+    Ok(())
 }
 
 /// Retrieves latest version of an entry from the local chain or the DHT, by looking it up using
 /// the specified address.
-/// Returns None if no entry exists at the specified address or
-/// if the entry's crud-status is not LIVE.
+/// If the entry was updated, follows its updates to the newest version, so the entry returned
+/// can have another address than the one asked for. The `resolved_address` of the EntryHistory
+/// returned by [get_entry_result](fn.get_entry_result.html) tells where the updates ended.
+/// Use [get_entry_initial](fn.get_entry_initial.html) for the entry at the address itself.
+/// Returns None if no entry exists at the specified address,
+/// if the entry's crud-status is not LIVE or if its content got pruned.
+/// Returns a ZomeApiError::Timeout if the network did not answer in time.
 /// # Examples
 /// ```rust
 /// # extern crate hdk;
@@ -490,6 +701,9 @@ pub fn commit_entry(entry: &Entry) -> ZomeApiResult<Address> {
 /// ```
 pub fn get_entry(address: Address) -> ZomeApiResult<Option<Entry>> {
     let entry_result = get_entry_result(address, GetEntryOptions::default())?;
+    if entry_result.status == GetResultStatus::Timeout {
+        return Err(ZomeApiError::Timeout);
+    }
     if entry_result.entries.is_empty() {
         return Ok(None);
     }
@@ -501,10 +715,59 @@ pub fn get_entry(address: Address) -> ZomeApiResult<Option<Entry>> {
     Ok(Some(entry.clone()))
 }
 
+/// Commits an entry of an app entry type this zome defines, stamped with the entry type's
+/// schema version (see the [entry! macro](macro.entry.html)), so that `get_typed()` can
+/// migrate it when the native type of the entry type changes later on.
+/// Otherwise the same as `commit_entry()`.
+pub fn commit_typed<T: Into<JsonString>>(
+    entry_type_name: &str,
+    entry: T,
+) -> ZomeApiResult<CommitResult> {
+    let app_entry_type = AppEntryType::from(entry_type_name.to_string());
+    let definition = own_entry_type_definition(&EntryType::App(app_entry_type.clone()))?;
+    let content = definition.stamp_version(entry.into())?;
+    commit_entry(&Entry::App(app_entry_type, content))
+}
+
+/// Gets an entry of an app entry type this zome defines, as the native type of the
+/// entry type's current schema version.
+/// Entries committed with an older schema version run through the entry type's
+/// `migrate_from` callback first, for every version in between. This does not change
+/// the entry that is stored.
+/// Returns None if no live entry exists at the specified address.
+pub fn get_typed<T: TryFrom<JsonString, Error = HolochainError>>(
+    address: Address,
+) -> ZomeApiResult<Option<T>> {
+    match get_entry(address)? {
+        Some(Entry::App(app_entry_type, content)) => {
+            let mut definition = own_entry_type_definition(&EntryType::App(app_entry_type))?;
+            let content = definition.migrate(content)?;
+            Ok(Some(T::try_from(content)?))
+        }
+        Some(_) => Err(ZomeApiError::Internal(String::from(
+            "get_typed() only gets app entries",
+        ))),
+        None => Ok(None),
+    }
+}
+
+fn own_entry_type_definition(entry_type: &EntryType) -> ZomeApiResult<ValidatingEntryType> {
+    entry_type_definition(entry_type).ok_or_else(|| {
+        ZomeApiError::Internal(format!(
+            "Entry type {} is not defined in this zome",
+            String::from(entry_type.clone())
+        ))
+    })
+}
+
 /// Returns the Entry at the exact address specified, whatever its crud-status.
+/// Unlike `get_entry()`, does not follow the updates of the entry.
 /// Returns None if no entry exists at the specified address.
 pub fn get_entry_initial(address: Address) -> ZomeApiResult<Option<Entry>> {
     let entry_result = get_entry_result(address, GetEntryOptions::new(StatusRequestKind::Initial))?;
+    if entry_result.status == GetResultStatus::Timeout {
+        return Err(ZomeApiError::Timeout);
+    }
     if entry_result.entries.is_empty() {
         return Ok(None);
     }
@@ -518,6 +781,9 @@ pub fn get_entry_initial(address: Address) -> ZomeApiResult<Option<Entry>> {
 /// Returns None if no entry exists at the specified address.
 pub fn get_entry_history(address: Address) -> ZomeApiResult<Option<EntryHistory>> {
     let entry_result = get_entry_result(address, GetEntryOptions::new(StatusRequestKind::All))?;
+    if entry_result.status == GetResultStatus::Timeout {
+        return Err(ZomeApiError::Timeout);
+    }
     if entry_result.entries.is_empty() {
         return Ok(None);
     }
@@ -527,34 +793,20 @@ pub fn get_entry_history(address: Address) -> ZomeApiResult<Option<EntryHistory>
 /// Retrieves an entry and its metadata from the local chain or the DHT, by looking it up using
 /// the specified address.
 /// The data returned is configurable with the GetEntryOptions argument.
+/// The `status` of the returned EntryHistory tells apart entries that were found, never
+/// existed, got deleted or could not be retrieved before the network timed out.
+/// Unless the options request the initial entry, follows the updates of the entry and sets
+/// `resolved_address` to the address of the last version retrieved.
+/// With `with_meta` set in the options, `meta` tells for each entry what its holder knows
+/// about it: its link counts by tag, the newest version if it got updated, and when it got
+/// published first and last. That's enough to list entries without more calls per entry.
 pub fn get_entry_result(address: Address, options: GetEntryOptions) -> ZomeApiResult<EntryHistory> {
-    let mut mem_stack: SinglePageStack;
-    unsafe {
-        mem_stack = G_MEM_STACK.unwrap();
-    }
-
-    let entry_args = GetEntryArgs { address, options };
-
-    // Put args in struct and serialize into memory
-    let allocation_of_input = store_as_json(&mut mem_stack, entry_args)?;
-
-    // Call WASMI-able get_entry
-    let encoded_allocation_of_result: u32;
-    unsafe {
-        encoded_allocation_of_result = hc_get_entry(allocation_of_input.encode() as u32);
-    }
-    // Deserialize complex result stored in memory
-    let result: ZomeApiInternalResult = load_json(encoded_allocation_of_result as u32)?;
-    // Free result & input allocations
-    mem_stack
-        .deallocate(allocation_of_input)
-        .expect("deallocate failed");
-    // Done
-    if result.ok {
-        Ok(JsonString::from(result.value).try_into()?)
-    } else {
-        Err(ZomeApiError::from(result.error))
-    }
+    let entry_args = GetEntryArgs {
+        address,
+        resolve_latest: options.status_request != StatusRequestKind::Initial,
+        options,
+    };
+    Ok(call_host_fn(hc_get_entry, entry_args)?.try_into()?)
 }
 
 /// Consumes three values, two of which are the addresses of entries, and one of which is a string that defines a
@@ -611,48 +863,115 @@ pub fn link_entries<S: Into<String>>(
     target: &Address,
     tag: S,
 ) -> Result<(), ZomeApiError> {
-    let mut mem_stack = unsafe { G_MEM_STACK.unwrap() };
-
-    // Put args in struct and serialize into memory
-    let allocation_of_input = store_as_json(
-        &mut mem_stack,
-        LinkEntriesArgs {
-            base: base.clone(),
-            target: target.clone(),
-            tag: tag.into(),
-        },
-    )?;
+    let link_args = LinkEntriesArgs {
+        base: base.clone(),
+        target: target.clone(),
+        tag: tag.into(),
+    };
+    Ok(call_host_fn(hc_link_entries, link_args)?.try_into()?)
+}
 
-    let encoded_allocation_of_result: u32 =
-        unsafe { hc_link_entries(allocation_of_input.encode() as u32) };
-
-    // Deserialize complex result stored in memory and check for ERROR in encoding
-    let result: ZomeApiInternalResult = load_json(encoded_allocation_of_result as u32)?;
-    // Free result & input allocations
-    mem_stack
-        .deallocate(allocation_of_input)
-        .expect("deallocate failed");
-    // Done
-    if result.ok {
-        Ok(JsonString::from(result.value).try_into()?)
-    } else {
-        Err(ZomeApiError::from(result.error))
-    }
+/// Commits an entry and links to it from each of the given bases, with the given tag,
+/// in one call. Unlike committing and then linking in two calls, the links go on the chain
+/// right after the entry and get published together with it.
+/// Fails if the entry can't be committed, without any of the links. The chain is append-only,
+/// so a link that fails, e.g. its validation, doesn't undo the commit of the entry or of the
+/// other links: the result has the outcome of each link, in the order they were given.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate holochain_core_types;
+/// # use hdk::error::ZomeApiResult;
+/// # use holochain_core_types::cas::content::Address;
+/// # use holochain_core_types::entry::Entry;
+/// # use hdk::AGENT_ADDRESS;
+/// # fn main() {
+/// fn commit_post(post_entry: &Entry, anchor: Address) -> ZomeApiResult<Address> {
+///     let result = hdk::commit_entry_and_link(
+///         post_entry,
+///         vec![
+///             (AGENT_ADDRESS.clone(), "authored_posts".to_string()),
+///             (anchor, "posts".to_string()),
+///         ],
+///     )?;
+///     if !result.all_linked() {
+///         hdk::debug(format!("Not all links of the post landed: {:?}", result.links))?;
+///     }
+///     Ok(result.entry.address)
+/// }
+/// # }
+/// ```
+pub fn commit_entry_and_link(
+    entry: &Entry,
+    links: Vec<(Address, String)>,
+) -> ZomeApiResult<CommitAndLinkResult> {
+    let args = CommitAndLinkArgs {
+        entry: entry.clone(),
+        links,
+    };
+    Ok(call_host_fn(hc_commit_and_link, args)?.try_into()?)
 }
 
-/// Not Yet Available
-// Returns a DNA property, which are defined by the DNA developer.
-// They are custom values that are defined in the DNA file
-// that can be used in the zome code for defining configurable behaviors.
-// (e.g. Name, Language, Description, Author, etc.).
-pub fn property<S: Into<String>>(_name: S) -> ZomeApiResult<String> {
-    Err(ZomeApiError::FunctionNotImplemented)
+/// Returns a DNA property, which are defined by the DNA developer.
+/// They are custom values that are defined in the `properties` of the DNA file
+/// that can be used in the zome code for defining configurable behaviors.
+/// (e.g. Name, Language, Description, Author, etc.).
+/// String properties are returned as they are, others as JSON, e.g. `10` or `{"a":1}`.
+/// Fails if the DNA does not have the property.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # use hdk::error::ZomeApiResult;
+/// # fn main() {
+/// fn max_posts() -> ZomeApiResult<u32> {
+///     Ok(hdk::property("max_posts")?.parse().unwrap_or(10))
+/// }
+/// # }
+/// ```
+pub fn property<S: Into<String>>(name: S) -> ZomeApiResult<String> {
+    let value = call_host_fn(hc_property, RawString::from(name.into()))?;
+    Ok(match serde_json::from_str(&String::from(value.clone())) {
+        Ok(serde_json::Value::String(string)) => string,
+        _ => String::from(value),
+    })
+}
+
+/// Returns the names of the app entry types the zomes of the DNA declare, sorted.
+/// Together with [entry_type_properties](fn.entry_type_properties.html), this lets generic
+/// zomes, e.g. one indexing every entry, work without hard-coding the entry types of the DNA.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # use hdk::error::ZomeApiResult;
+/// # fn main() {
+/// fn public_entry_types() -> ZomeApiResult<Vec<String>> {
+///     let mut public = Vec::new();
+///     for name in hdk::entry_types()? {
+///         if hdk::entry_type_properties(name.as_str())?.sharing.can_publish() {
+///             public.push(name);
+///         }
+///     }
+///     Ok(public)
+/// }
+/// # }
+/// ```
+pub fn entry_types() -> ZomeApiResult<Vec<String>> {
+    let names = call_host_fn(hc_entry_types, JsonString::null())?;
+    Ok(load_json_from_str(&String::from(names))?)
+}
+
+/// Returns how the DNA declares the given app entry type: the zome declaring it,
+/// its description, its sharing and the links from and to entries of the type, with their tags.
+/// Fails if the DNA does not have the entry type.
+pub fn entry_type_properties<S: Into<String>>(name: S) -> ZomeApiResult<EntryTypeDef> {
+    Ok(call_host_fn(hc_entry_type_properties, RawString::from(name.into()))?.try_into()?)
 }
 
 /// Reconstructs an address of the given entry data.
 /// This is the same value that would be returned if `entry_type_name` and `entry_value` were passed
 /// to the [commit_entry](fn.commit_entry.html) function and by which it would be retrievable from the DHT using [get_entry](fn.get_entry.html).
 /// This is often used to reconstruct an address of a `base` argument when calling [get_links](fn.get_links.html).
+/// Works for app entries as well as for system entries like the agent or the DNA entry.
 /// # Examples
 /// ```rust
 /// # #![feature(try_from)]
@@ -692,30 +1011,104 @@ pub fn property<S: Into<String>>(_name: S) -> ZomeApiResult<String> {
 /// # }
 /// ```
 pub fn entry_address(entry: &Entry) -> ZomeApiResult<Address> {
-    let mut mem_stack: SinglePageStack;
-    unsafe {
-        mem_stack = G_MEM_STACK.unwrap();
-    }
-    // Put args in struct and serialize into memory
-    let allocation_of_input = store_as_json(&mut mem_stack, entry)?;
+    Ok(call_host_fn(hc_entry_address, entry)?.try_into()?)
+}
 
-    let encoded_allocation_of_result: u32;
-    unsafe {
-        encoded_allocation_of_result = hc_entry_address(allocation_of_input.encode() as u32);
-    }
+/// Returns the address of the DNA entry at the start of this agent's source chain.
+/// Useful as a well-known base to link from.
+pub fn dna_entry_address() -> ZomeApiResult<Address> {
+    Ok(app_globals()?.dna_entry_address.clone())
+}
 
-    // Deserialize complex result stored in memory and check for ERROR in encoding
-    let result: ZomeApiInternalResult = load_json(encoded_allocation_of_result as u32)?;
-    // Free result & input allocations
-    mem_stack
-        .deallocate(allocation_of_input)
-        .expect("deallocate failed");
-    // Done
-    if result.ok {
-        Ok(JsonString::from(result.value).try_into()?)
-    } else {
-        Err(ZomeApiError::from(result.error))
-    }
+/// Returns the address of this agent's latest identity entry.
+/// Useful as a base to link the agent's own data from.
+pub fn agent_entry_address() -> ZomeApiResult<Address> {
+    Ok(app_globals()?.agent_latest_hash.clone())
+}
+
+/// Returns where the currently running zome function call came from:
+/// a client through an interface, another instance over a bridge, an agent's direct message,
+/// or Holochain itself for callbacks.
+/// Functions calling other functions with [call](fn.call.html) pass their own context on.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # use hdk::holochain_wasm_utils::api_serialization::CallContext;
+/// # use hdk::error::{ZomeApiError, ZomeApiResult};
+///
+/// # fn main() {
+/// pub fn handle_admin_only() -> ZomeApiResult<()> {
+///     match hdk::call_context()? {
+///         CallContext::Interface { .. } => Ok(()),
+///         _ => Err(ZomeApiError::Internal("only callable through an interface".into())),
+///     }
+/// }
+/// # }
+/// ```
+pub fn call_context() -> ZomeApiResult<CallContext> {
+    Ok(app_globals()?.call_context.clone())
+}
+
+/// Returns the address of the header at the top of this agent's source chain,
+/// as of the start of the currently running call, or None if the chain is empty.
+/// Unlike [AGENT_LATEST_HASH](struct.AGENT_LATEST_HASH.html) this is fresh for every call,
+/// which makes it fit for optimistic concurrency: only update if the top is still the one seen.
+/// Fails for entry points that don't take the call init data, i.e. ones not generated by
+/// [define_zome!](macro.define_zome.html).
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate holochain_core_types;
+/// # use hdk::error::{ZomeApiError, ZomeApiResult};
+/// # use holochain_core_types::cas::content::Address;
+///
+/// # fn main() {
+/// pub fn handle_update_if_unchanged(seen_top: Option<Address>) -> ZomeApiResult<()> {
+///     if hdk::chain_top()? != seen_top {
+///         return Err(ZomeApiError::Internal("chain changed in the meantime".into()));
+///     }
+///     // update...
+///     Ok(())
+/// }
+/// # }
+/// ```
+pub fn chain_top() -> ZomeApiResult<Option<Address>> {
+    Ok(call_init_data()?.chain_top)
+}
+
+/// Returns the number of entries in this agent's source chain,
+/// as of the start of the currently running call.
+pub fn chain_length() -> ZomeApiResult<usize> {
+    Ok(call_init_data()?.chain_length)
+}
+
+/// Returns when Holochain started the currently running call.
+pub fn call_time() -> ZomeApiResult<Iso8601> {
+    Ok(call_init_data()?.call_time)
+}
+
+/// Returns the genesis parameters the configuration of the instance sets, an empty object
+/// if it sets none. They are meant for bootstrapping data that differs between instances
+/// of the same DNA, like the address of a progenitor agent or an invite code, and can be read
+/// in genesis and in every later call. They are not part of the DNA, so other nodes validating
+/// the entries of this agent don't know them: validation rules must not depend on them.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate serde_json;
+/// # use hdk::error::ZomeApiResult;
+/// # fn main() {
+/// fn progenitor() -> ZomeApiResult<Option<String>> {
+///     let genesis_params: serde_json::Value =
+///         serde_json::from_str(&String::from(hdk::genesis_params()?))?;
+///     Ok(genesis_params["progenitor"].as_str().map(String::from))
+/// }
+/// # }
+/// ```
+pub fn genesis_params() -> ZomeApiResult<JsonString> {
+    Ok(JsonString::from(
+        call_init_data()?.genesis_params.to_string(),
+    ))
 }
 
 /// Not Yet Available
@@ -732,6 +1125,45 @@ pub fn verify_signature<S: Into<String>>(
     Err(ZomeApiError::FunctionNotImplemented)
 }
 
+/// Encrypts the payload so that only the agent with the given address can decrypt it, with
+/// `decrypt()`. The payload does not tell who encrypted it or for whom, so it can be sent
+/// through anyone or stored in an entry.
+/// Encrypting the same payload twice gives different bytes, that's why validation callbacks
+/// can't use this.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate holochain_core_types;
+/// # use hdk::error::ZomeApiResult;
+/// # use holochain_core_types::cas::content::Address;
+/// # fn main() {
+/// fn secret_for(recipient: Address, secret: String) -> ZomeApiResult<Vec<u8>> {
+///     hdk::encrypt_for(recipient, secret.as_bytes())
+/// }
+/// # }
+/// ```
+pub fn encrypt_for(recipient_agent: Address, payload: &[u8]) -> ZomeApiResult<Vec<u8>> {
+    Ok(default_try_from_json(call_host_fn(
+        hc_encrypt_for,
+        EncryptForArgs {
+            recipient: recipient_agent,
+            payload: payload.to_vec(),
+        },
+    )?)?)
+}
+
+/// Decrypts a payload that got encrypted for this agent with `encrypt_for()`.
+/// Returns an error if it was encrypted for another agent or got modified, and in
+/// validation callbacks.
+pub fn decrypt(payload: &[u8]) -> ZomeApiResult<Vec<u8>> {
+    Ok(default_try_from_json(call_host_fn(
+        hc_decrypt,
+        DecryptArgs {
+            payload: payload.to_vec(),
+        },
+    )?)?)
+}
+
 /// Commit an entry to your local source chain that "updates" a previous entry, meaning when getting
 /// the previous entry, the updated entry will be returned.
 /// `update_entry` sets the previous entry's status metadata to `Modified` and adds the updated
@@ -739,67 +1171,41 @@ pub fn verify_signature<S: Into<String>>(
 /// The updated entry will hold the previous entry's address in its header,
 /// which will be used by validation routes.
 pub fn update_entry(new_entry: Entry, address: Address) -> ZomeApiResult<Address> {
-    let mut mem_stack: SinglePageStack;
-    unsafe {
-        mem_stack = G_MEM_STACK.unwrap();
-    }
-
     let update_args = UpdateEntryArgs { new_entry, address };
-
-    // Put args in struct and serialize into memory
-    let allocation_of_input = store_as_json(&mut mem_stack, update_args)?;
-
-    // Call Ribosome
-    let encoded_allocation_of_result: u32;
-    unsafe {
-        encoded_allocation_of_result = hc_update_entry(allocation_of_input.encode() as u32);
-    }
-    let result: ZomeApiInternalResult = load_json(encoded_allocation_of_result as u32)?;
-    // Free result & input allocations
-    mem_stack
-        .deallocate(allocation_of_input)
-        .expect("deallocate failed");
-    // Done
-    if result.ok {
-        Ok(JsonString::from(result.value).try_into()?)
-    } else {
-        Err(ZomeApiError::from(result.error))
-    }
+    Ok(call_host_fn(hc_update_entry, update_args)?.try_into()?)
 }
 
-/// Not Yet Available
-pub fn update_agent() -> ZomeApiResult<Address> {
-    Err(ZomeApiError::FunctionNotImplemented)
+/// Commits a new identity entry for this agent with the given nick, which replaces its
+/// latest identity, and returns its address, the new AGENT_LATEST_HASH.
+/// AGENT_ADDRESS stays the same and resolves to the new identity, like an updated entry
+/// does, on the nodes that hold the agent's identity too.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # use hdk::error::ZomeApiResult;
+/// # use hdk::holochain_core_types::cas::content::Address;
+/// # fn main() {
+/// fn rename(nick: String) -> ZomeApiResult<Address> {
+///     hdk::update_agent(nick)
+/// }
+/// # }
+/// ```
+pub fn update_agent<S: Into<String>>(nick: S) -> ZomeApiResult<Address> {
+    let update_args = UpdateAgentArgs { nick: nick.into() };
+    Ok(call_host_fn(hc_update_agent, update_args)?.try_into()?)
 }
 
 /// Commit a DeletionEntry to your local source chain that marks an entry as 'deleted' by setting
 /// its status metadata to `Deleted` and adding the DeleteEntry's address in the deleted entry's
 /// metadata, which will be used by validation routes.
 pub fn remove_entry(address: Address) -> ZomeApiResult<()> {
-    let mut mem_stack: SinglePageStack;
-    unsafe {
-        mem_stack = G_MEM_STACK.unwrap();
-    }
-    // Put args in struct and serialize into memory
-    let allocation_of_input = store_as_json(&mut mem_stack, address)?;
-
-    // Call WASMI-able get_entry
-    let encoded_allocation_of_result: u32;
-    unsafe {
-        encoded_allocation_of_result = hc_remove_entry(allocation_of_input.encode() as u32);
-    }
-    let res = check_for_ribosome_error(encoded_allocation_of_result);
-    // Free result & input allocations
-    mem_stack
-        .deallocate(allocation_of_input)
-        .expect("deallocate failed");
-    // Done
-    res
+    check_for_ribosome_error(call_host(hc_remove_entry, address))
 }
 
 /// Consumes two values, the first of which is the address of an entry, `base`, and the second of which is a string, `tag`,
 /// used to describe the relationship between the `base` and other entries you wish to lookup. Returns a list of addresses of other
 /// entries which matched as being linked by the given `tag`. Links are created in the first place using the Zome API function [link_entries](fn.link_entries.html).
+/// Links to entries that got removed are left out, see [get_links_with_status](fn.get_links_with_status.html) to get those.
 /// Once you have the addresses, there is a good likelihood that you will wish to call [get_entry](fn.get_entry.html) for each of them.
 /// # Examples
 /// ```rust
@@ -818,67 +1224,284 @@ pub fn remove_entry(address: Address) -> ZomeApiResult<()> {
 /// # }
 /// ```
 pub fn get_links<S: Into<String>>(base: &Address, tag: S) -> ZomeApiResult<GetLinksResult> {
-    let mut mem_stack = unsafe { G_MEM_STACK.unwrap() };
-    // Put args in struct and serialize into memory
-
-    let allocation_of_input = store_as_json(
-        &mut mem_stack,
-        GetLinksArgs {
-            entry_address: base.clone(),
-            tag: tag.into(),
-        },
-    )?;
+    get_links_with_args(GetLinksArgs {
+        entry_address: base.clone(),
+        tag: tag.into(),
+        target_entry_type: None,
+        status_request: LinksStatusFilter::default(),
+        direction: GetLinksDirection::From,
+    })
+}
 
-    // Call Ribosome
-    let encoded_allocation_of_result: u32 =
-        unsafe { hc_get_links(allocation_of_input.encode() as u32) };
+/// Like [get_links](fn.get_links.html) but filters the links by the CRUD status of their targets:
+/// `Live` leaves out links to removed entries, `Deleted` only returns those and `All` returns
+/// every link, with the status of its target in the result's `statuses()`.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate holochain_core_types;
+/// # use holochain_core_types::json::JsonString;
+/// # use holochain_core_types::cas::content::Address;
+/// # use hdk::holochain_wasm_utils::api_serialization::get_links::LinksStatusFilter;
+///
+/// # fn main() {
+/// pub fn handle_removed_posts(agent: Address) -> JsonString {
+///     match hdk::get_links_with_status(&agent, "authored_posts", LinksStatusFilter::Deleted) {
+///         Ok(result) => result.into(),
+///         Err(hdk_error) => hdk_error.into(),
+///     }
+/// }
+/// # }
+/// ```
+pub fn get_links_with_status<S: Into<String>>(
+    base: &Address,
+    tag: S,
+    status_request: LinksStatusFilter,
+) -> ZomeApiResult<GetLinksResult> {
+    get_links_with_args(GetLinksArgs {
+        entry_address: base.clone(),
+        tag: tag.into(),
+        target_entry_type: None,
+        status_request,
+        direction: GetLinksDirection::From,
+    })
+}
 
-    // Deserialize complex result stored in memory
-    let result: ZomeApiInternalResult = load_json(encoded_allocation_of_result as u32)?;
+/// Like [get_links](fn.get_links.html) but only returns the addresses of linked entries
+/// of the given entry type, for tags that link to several types.
+/// Links whose target type can't be determined by the node answering are left out and
+/// counted in the result's `skipped()`.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate holochain_core_types;
+/// # use holochain_core_types::json::JsonString;
+/// # use holochain_core_types::cas::content::Address;
+///
+/// # fn main() {
+/// pub fn handle_comments_of_post(post: Address) -> JsonString {
+///     match hdk::get_links_of_type(&post, "replies", "comment") {
+///         Ok(result) => result.into(),
+///         Err(hdk_error) => hdk_error.into(),
+///     }
+/// }
+/// # }
+/// ```
+pub fn get_links_of_type<S: Into<String>, T: Into<String>>(
+    base: &Address,
+    tag: S,
+    entry_type: T,
+) -> ZomeApiResult<GetLinksResult> {
+    get_links_with_args(GetLinksArgs {
+        entry_address: base.clone(),
+        tag: tag.into(),
+        target_entry_type: Some(entry_type.into()),
+        status_request: LinksStatusFilter::default(),
+        direction: GetLinksDirection::From,
+    })
+}
 
-    // Free result & input allocations
-    mem_stack
-        .deallocate(allocation_of_input)
-        .expect("deallocate failed");
+/// The reverse of [get_links](fn.get_links.html): returns the addresses of the entries
+/// linking to `target` with the given `tag`, e.g. the posts that reply to a post.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate holochain_core_types;
+/// # use holochain_core_types::json::JsonString;
+/// # use holochain_core_types::cas::content::Address;
+///
+/// # fn main() {
+/// pub fn handle_posts_replying_to(post: Address) -> JsonString {
+///     match hdk::get_links_to(&post, "replies") {
+///         Ok(result) => result.into(),
+///         Err(hdk_error) => hdk_error.into(),
+///     }
+/// }
+/// # }
+/// ```
+pub fn get_links_to<S: Into<String>>(target: &Address, tag: S) -> ZomeApiResult<GetLinksResult> {
+    get_links_with_args(GetLinksArgs {
+        entry_address: target.clone(),
+        tag: tag.into(),
+        target_entry_type: None,
+        status_request: LinksStatusFilter::default(),
+        direction: GetLinksDirection::To,
+    })
+}
 
-    if result.ok {
-        Ok(JsonString::from(result.value).try_into()?)
-    } else {
-        Err(ZomeApiError::from(result.error))
-    }
+fn get_links_with_args(args: GetLinksArgs) -> ZomeApiResult<GetLinksResult> {
+    Ok(call_host_fn(hc_get_links, args)?.try_into()?)
+}
+
+/// Returns how many links [get_links](fn.get_links.html) would return for the same `base` and `tag`,
+/// without loading their addresses into the zome. Nodes keep these counts up to date as links get
+/// added and their targets removed, so this stays cheap for bases with many links, like the likes of a post.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate holochain_core_types;
+/// # use holochain_core_types::json::JsonString;
+/// # use holochain_core_types::cas::content::Address;
+///
+/// # fn main() {
+/// pub fn handle_like_count(post: Address) -> JsonString {
+///     match hdk::count_links(&post, "likes") {
+///         Ok(count) => count.into(),
+///         Err(hdk_error) => hdk_error.into(),
+///     }
+/// }
+/// # }
+/// ```
+pub fn count_links<S: Into<String>>(base: &Address, tag: S) -> ZomeApiResult<usize> {
+    count_links_with_status(base, tag, LinksStatusFilter::default())
+}
+
+/// Like [count_links](fn.count_links.html) but counts the links whose targets have the given
+/// CRUD status, like [get_links_with_status](fn.get_links_with_status.html) returns them.
+pub fn count_links_with_status<S: Into<String>>(
+    base: &Address,
+    tag: S,
+    status_request: LinksStatusFilter,
+) -> ZomeApiResult<usize> {
+    let args = CountLinksArgs {
+        entry_address: base.clone(),
+        tag: tag.into(),
+        status_request,
+    };
+    Ok(call_host_fn(hc_count_links, args)?.try_into()?)
+}
+
+/// Returns the addresses of the agents that joined this DNA, as far as this node knows of them.
+/// Holochain links the [DNA entry](fn.dna_entry_address.html) to the identity entry of every
+/// agent that initializes an instance of the DNA, with the reserved tag `%agent_id`.
+/// DNAs can opt out of this for privacy by setting the DNA property `register_agents` to false,
+/// then the list stays empty.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate holochain_core_types;
+/// # use holochain_core_types::json::JsonString;
+///
+/// # fn main() {
+/// pub fn handle_list_members() -> JsonString {
+///     match hdk::get_all_agents() {
+///         Ok(agents) => agents.into(),
+///         Err(hdk_error) => hdk_error.into(),
+///     }
+/// }
+/// # }
+/// ```
+pub fn get_all_agents() -> ZomeApiResult<Vec<Address>> {
+    let dna_entry_address = dna_entry_address()?;
+    Ok(get_links(&dna_entry_address, AGENT_ID_LINK_TAG)?
+        .addresses()
+        .to_owned())
 }
 
 /// Returns a list of entries from your local source chain, that match a given type.
 /// entry_type_name: Specify type of entry to retrieve
 /// limit: Max number of entries to retrieve
 pub fn query(entry_type_name: &str, start: u32, limit: u32) -> ZomeApiResult<QueryResult> {
-    let mut mem_stack: SinglePageStack = unsafe { G_MEM_STACK.unwrap() };
-
-    // Put args in struct and serialize into memory
-    let allocation_of_input = store_as_json(
-        &mut mem_stack,
-        QueryArgs {
-            entry_type_name: entry_type_name.to_string(),
-            start,
-            limit,
+    let query_args = QueryArgs {
+        entry_type_name: entry_type_name.to_string(),
+        start,
+        limit,
+    };
+    Ok(call_host_fn(hc_query, query_args)?.try_into()?)
+}
+
+/// Returns one page of the entries from your local source chain that match a given type,
+/// newest first, together with a cursor to get the next page.
+/// entry_type_name: Specify type of entry to retrieve
+/// limit: Max number of entries on the page, 0 for all of them
+/// cursor: `next_cursor` of the previous page, None for the first page
+///
+/// Unlike with `query()`'s offset, entries committed between two calls don't make
+/// the following pages repeat or skip entries, since the cursor marks where the previous
+/// page stopped. `next_cursor` is None once there are no more entries.
+/// A cursor that is not the address of a header of that type on your chain fails with
+/// an "Invalid query cursor" error.
+pub fn query_with_cursor(
+    entry_type_name: &str,
+    limit: u32,
+    cursor: Option<Address>,
+) -> ZomeApiResult<QueryPage> {
+    let query_args = QueryPageArgs {
+        entry_type_name: entry_type_name.to_string(),
+        limit,
+        cursor,
+    };
+    Ok(call_host_fn(hc_query_page, query_args)?.try_into()?)
+}
+
+/// Returns statistics about your local source chain: its length, the number of entries
+/// per entry type and the address of the most recent header.
+/// This does not walk the chain, so it is cheap even for long chains.
+pub fn chain_info() -> ZomeApiResult<ChainInfo> {
+    Ok(call_host_fn(hc_chain_info, JsonString::null())?.try_into()?)
+}
+
+/// Returns the number of entries on your local source chain whose type matches the given
+/// pattern, without retrieving them.
+/// entry_type_pattern: an entry type name, in which `*` matches any sequence of characters,
+/// e.g. "post", "post*" or "*" for all entries
+pub fn query_count(entry_type_pattern: &str) -> ZomeApiResult<usize> {
+    Ok(chain_info()?.count(entry_type_pattern))
+}
+
+/// Returns `len` bytes from the node's cryptographically secure random number generator,
+/// at most 4096 per call.
+/// Only this node knows them, other nodes can't reproduce them. So they must never decide
+/// whether something is valid: in validation callbacks, this fails with a
+/// "Non-deterministic call in validation" error.
+pub fn random_bytes(len: usize) -> ZomeApiResult<Vec<u8>> {
+    Ok(default_try_from_json(call_host_fn(
+        hc_random,
+        RandomBytesArgs { len },
+    )?)?)
+}
+
+/// Returns a random number made of 8 bytes from `random_bytes()`,
+/// which can't be used in validation callbacks either.
+pub fn random_u64() -> ZomeApiResult<u64> {
+    Ok(random_bytes(8)?
+        .iter()
+        .fold(0, |number, byte| (number << 8) | u64::from(*byte)))
+}
+
+/// Suspends the zome call for the given duration, e.g. to wait for an entry of another
+/// agent before looking for it again, without spinning in a loop.
+/// A single sleep can last 2 seconds and all the sleeps of a call 5 seconds together,
+/// unless the instance is configured to less; longer sleeps fail right away.
+/// How long a call sleeps depends on the node, so this fails in validation callbacks
+/// with a "Non-deterministic call in validation" error.
+/// # Examples
+/// ```rust
+/// # extern crate hdk;
+/// # extern crate holochain_core_types;
+/// # use hdk::error::ZomeApiResult;
+/// # use holochain_core_types::{cas::content::Address, entry::Entry};
+/// # use std::time::Duration;
+/// # fn main() {
+/// fn wait_for(address: &Address) -> ZomeApiResult<Option<Entry>> {
+///     for _ in 0..10 {
+///         if let Some(entry) = hdk::get_entry(address.clone())? {
+///             return Ok(Some(entry));
+///         }
+///         hdk::sleep(Duration::from_millis(500))?;
+///     }
+///     Ok(None)
+/// }
+/// # }
+/// ```
+pub fn sleep(duration: Duration) -> ZomeApiResult<()> {
+    call_host_fn(
+        hc_sleep,
+        SleepArgs {
+            millis: duration.as_secs() * 1000 + u64::from(duration.subsec_millis()),
         },
     )?;
-
-    let encoded_allocation_of_result: u32 =
-        unsafe { hc_query(allocation_of_input.encode() as u32) };
-
-    // Deserialize complex result stored in memory
-    let result: ZomeApiInternalResult = load_json(encoded_allocation_of_result as u32)?;
-    // Free result & input allocations
-    mem_stack
-        .deallocate(allocation_of_input)
-        .expect("deallocate failed");
-    // Done
-    if result.ok {
-        Ok(JsonString::from(result.value).try_into()?)
-    } else {
-        Err(ZomeApiError::from(result.error))
-    }
+    Ok(())
 }
 
 /// Not Yet Available
@@ -900,21 +1523,18 @@ pub fn close_bundle(_action: BundleOnClose) -> ZomeApiResult<()> {
 // Helpers
 //--------------------------------------------------------------------------------------------------
 
-pub fn check_for_ribosome_error(encoded_allocation: u32) -> ZomeApiResult<()> {
-    // Check for error from Ribosome
-    let rib_result = decode_encoded_allocation(encoded_allocation);
-    match rib_result {
+/// Checks the result of a call to a Ribosome function that only returns something if it failed
+pub fn check_for_ribosome_error(
+    result_of_call: Result<JsonString, RibosomeErrorCode>,
+) -> ZomeApiResult<()> {
+    match result_of_call {
         // Expecting a 'Success' return code
-        Err(ret_code) => match ret_code {
-            RibosomeReturnCode::Success => Ok(()),
-            RibosomeReturnCode::Failure(err_code) => {
-                Err(ZomeApiError::Internal(err_code.to_string()))
-            }
-        },
-        // If we have an allocation, than it should be a CoreError
-        Ok(allocation) => {
+        Ok(ref result) if *result == JsonString::null() => Ok(()),
+        Err(err_code) => Err(ZomeApiError::Internal(err_code.to_string())),
+        // If we got something, than it should be a CoreError
+        Ok(result) => {
             let maybe_err: Result<CoreError, HolochainError> =
-                load_json_from_raw(allocation.offset() as *mut c_char);
+                load_json_from_str(&String::from(result));
             match maybe_err {
                 Err(hc_err) => Err(ZomeApiError::Internal(hc_err.to_string())),
                 Ok(core_err) => Err(ZomeApiError::Internal(core_err.to_string())),
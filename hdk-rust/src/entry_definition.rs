@@ -1,22 +1,37 @@
 use holochain_core_types::{
     dna::zome::entry_types::EntryTypeDef,
     entry::{entry_type::EntryType, Entry},
+    error::HolochainError,
     hash::HashString,
+    json::JsonString,
     validation::{ValidationData, ValidationPackageDefinition},
 };
 use holochain_wasm_utils::api_serialization::validation::LinkDirection;
+use serde_json;
 
 pub type PackageCreator = Box<FnMut() -> ValidationPackageDefinition + Sync>;
 pub type Validator = Box<FnMut(Entry, ValidationData) -> Result<(), String> + Sync>;
 pub type LinkValidator =
     Box<FnMut(HashString, HashString, ValidationData) -> Result<(), String> + Sync>;
+pub type DependencyResolver = Box<FnMut(Entry) -> Result<Vec<HashString>, String> + Sync>;
+pub type Migrator = Box<FnMut(u32, JsonString) -> Result<JsonString, String> + Sync>;
+
+/// How entries of versioned entry types are stored: their content together with the
+/// schema version it was written in. Content that is not wrapped like this is version 0.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct VersionedContent {
+    pub schema_version: u32,
+    pub content: serde_json::Value,
+}
 
 /// This struct represents a complete entry type definition.
 /// It wraps [EntryTypeDef](struct.EntryTypeDef.html) defined in the DNA crate
 /// which only represents the static parts that show up in the JSON definition
 /// of an entry type.
 /// What is missing from there is the validation callbacks that can not be defined as JSON
-/// and are added here as Box<FnMut> objects (types PackageCreator, Validator, LinkValidator)
+/// and are added here as Box<FnMut> objects (types PackageCreator, Validator, LinkValidator,
+/// DependencyResolver)
 ///
 /// Instances of this struct are expected and used in the [define_zome! macro](macro.define_zome.html).
 /// Although possible, a DNA developer does not need to create these instances directly but instead
@@ -31,10 +46,79 @@ pub struct ValidatingEntryType {
     pub package_creator: PackageCreator,
     /// This is the validation callback that is used to determine if an entry is valid.
     pub validator: Validator,
+    /// Optional callback that returns the addresses of the entries an entry refers to and
+    /// that need to be available before it can be validated. Holding nodes fetch them first
+    /// and only call the validator once they have them.
+    pub dependency_resolver: Option<DependencyResolver>,
+    /// Version of the schema of the entry type's native type, 0 if it does not declare one.
+    pub schema_version: u32,
+    /// Optional callback that turns content of the given older schema version into content
+    /// of the next version. Needed as soon as `schema_version` is above 0.
+    pub migrator: Option<Migrator>,
 
     pub links: Vec<ValidatingLinkDefinition>,
 }
 
+impl ValidatingEntryType {
+    /// Wraps the content of a new entry together with the current schema version.
+    /// Content of entry types without a schema version stays as it is.
+    pub fn stamp_version(&self, content: JsonString) -> Result<JsonString, HolochainError> {
+        if self.schema_version == 0 {
+            return Ok(content);
+        }
+        let versioned_content = VersionedContent {
+            schema_version: self.schema_version,
+            content: serde_json::from_str(&String::from(content))?,
+        };
+        Ok(JsonString::from(serde_json::to_string(&versioned_content)?))
+    }
+
+    /// Brings stored content to the current schema version: unwraps it and runs the migrator
+    /// once for every version it is behind.
+    /// This only changes what gets read, the stored entry stays as it was committed.
+    pub fn migrate(&mut self, stored: JsonString) -> Result<JsonString, String> {
+        if self.schema_version == 0 && self.migrator.is_none() {
+            return Ok(stored);
+        }
+        let (mut version, mut content) =
+            match serde_json::from_str::<VersionedContent>(&String::from(stored.clone())) {
+                Ok(versioned_content) => (
+                    versioned_content.schema_version,
+                    JsonString::from(versioned_content.content),
+                ),
+                Err(_) => (0, stored),
+            };
+        let entry_type_name = String::from(self.name.clone());
+        if version > self.schema_version {
+            return Err(format!(
+                "{} entry has schema version {}, this zome only knows up to version {}",
+                entry_type_name, version, self.schema_version
+            ));
+        }
+        while version < self.schema_version {
+            let migrator = self.migrator.as_mut().ok_or_else(|| {
+                format!(
+                    "{} entry has schema version {}, but there is no migration to version {}",
+                    entry_type_name, version, self.schema_version
+                )
+            })?;
+            content = (*migrator)(version, content)?;
+            version += 1;
+        }
+        Ok(content)
+    }
+
+    /// Like `migrate()` for the content of an app entry, other entries stay as they are
+    pub fn migrate_entry(&mut self, entry: Entry) -> Result<Entry, String> {
+        match entry {
+            Entry::App(app_entry_type, content) => {
+                Ok(Entry::App(app_entry_type, self.migrate(content)?))
+            }
+            _ => Ok(entry),
+        }
+    }
+}
+
 /// Similar to ValidatingEntryType, this provides the dynamic aspects of link definitions,
 /// the validation callbacks, and thus completes the structs in the DNA crate.
 /// The [entry! macro](macro.entry.html) expects an array of links that are represented by
@@ -79,6 +163,95 @@ pub struct ValidatingLinkDefinition {
 ///     to define an association pointing from this entry type to another, or one that points back from
 ///     the other entry type to this one.
 ///     See [link!](macro.link.html), [to!](macro.to.html) and [from!](macro.to.html) for more details.
+///
+/// Between `native_type` and `validation_package`, an entry type can optionally define
+/// `validation_dependencies`: a callback that gets the entry and returns the addresses of other
+/// entries that have to be available before it can be validated, e.g. the post a comment refers to.
+/// A node that is asked to hold the entry fetches these dependencies first, waits for them
+/// if they can't be found yet and rejects the entry if they don't show up in time.
+///
+/// Right after `sharing`, an entry type can set its `redundancy`: how many nodes are to hold
+/// each of its entries, instead of the number the `redundancy` property of the DNA sets.
+/// The nodes that hold an entry are the ones closest to its address. A redundancy of 0, which
+/// is also the default if neither sets one, means every node.
+///
+/// Private entry types can be `prunable: true`, optionally followed by a `retention` in
+/// seconds. Garbage collection removes the content of their entries from local storage
+/// once they are older than that, while their headers stay on the chain.
+/// [get_entry_result](fn.get_entry_result.html) reports pruned entries with the
+/// `Pruned` status, and validation packages have content-pruned markers instead of them,
+/// see `ValidationData::is_pruned()`.
+///
+/// Entries that are only of use for a while, like presence pings, can be given a
+/// `ttl_seconds` after that. The nodes that hold one of them drop it from their shard once
+/// it is that old, [get_entry_result](fn.get_entry_result.html) then reports it with the
+/// `Expired` status. Authors don't republish them anymore and validators reject them if
+/// the timestamp of their header is older than that.
+///
+/// Right after `native_type`, an entry type can declare the `version` of its schema, starting
+/// at 1, and a `migrate_from` callback. [commit_typed](fn.commit_typed.html) stores entries
+/// together with the current version. When the native type changes, bump the version and
+/// let `migrate_from` turn the JSON of an entry of the old version into the JSON of the
+/// next one. [get_typed](fn.get_typed.html) and the validation callback then get entries of
+/// older versions migrated step by step, while the entries on the chain stay untouched.
+/// Entries committed before the entry type had a version are version 0.
+/// ```rust
+/// # #![feature(try_from)]
+/// # extern crate serde_json;
+/// # #[macro_use]
+/// # extern crate hdk;
+/// # #[macro_use]
+/// # extern crate holochain_core_types_derive;
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// # use hdk::entry_definition::ValidatingEntryType;
+/// # use hdk::holochain_core_types::{
+/// #   dna::zome::entry_types::Sharing,
+/// #   json::JsonString,
+/// #   error::HolochainError,
+/// # };
+/// # fn main() {
+/// // version 1 only had a `name`
+/// #[derive(Serialize, Deserialize, Debug, DefaultJson)]
+/// pub struct Person {
+///     first_name: String,
+///     last_name: String,
+/// }
+///
+/// pub fn definition() -> ValidatingEntryType {
+///     entry!(
+///         name: "person",
+///         description: "someone we know",
+///         sharing: Sharing::Public,
+///         native_type: Person,
+///         version: 2,
+///
+///         migrate_from: |old_version: u32, old_content: JsonString| {
+///             if old_version != 1 {
+///                 return Err(format!("person entries of version {} are not supported", old_version));
+///             }
+///             let old: serde_json::Value = serde_json::from_str(&String::from(old_content))
+///                 .map_err(|e| e.to_string())?;
+///             let mut names = old["name"].as_str().unwrap_or_default().splitn(2, ' ');
+///             let person = Person {
+///                 first_name: names.next().unwrap_or_default().to_string(),
+///                 last_name: names.next().unwrap_or_default().to_string(),
+///             };
+///             Ok(person.into())
+///         },
+///
+///         validation_package: || {
+///             hdk::ValidationPackageDefinition::Entry
+///         },
+///
+///         validation: |_person: Person, _ctx: hdk::ValidationData| {
+///             Ok(())
+///         }
+///     )
+/// }
+/// # }
+/// ```
+///
 /// # Examples
 /// The following is a standalone Rust file that exports a function which can be called
 /// to get a `ValidatingEntryType` of a "post".
@@ -151,8 +324,18 @@ macro_rules! entry {
         name: $name:expr,
         description: $description:expr,
         sharing: $sharing:expr,
+        $(redundancy: $redundancy:expr,)*
+        $(prunable: $prunable:expr,)*
+        $(retention: $retention:expr,)*
+        $(ttl_seconds: $ttl_seconds:expr,)*
         $(native_type: $native_type:ty,)*
 
+        $(version: $version:expr,)*
+
+        $(migrate_from: | $old_version:ident : u32, $old_content:ident : JsonString | $migration:expr,)*
+
+        $(validation_dependencies: | $dependency_entry:ident : $dependency_entry_type:ty | $dependencies:expr,)*
+
         validation_package: || $package_creator:expr,
         validation: | $entry:ident : $entry_type:ty, $ctx:ident : hdk::ValidationData | $entry_validation:expr
 
@@ -169,6 +352,18 @@ macro_rules! entry {
             let mut entry_type = hdk::holochain_core_types::dna::zome::entry_types::EntryTypeDef::new();
             entry_type.description = String::from($description);
             entry_type.sharing = $sharing;
+            $(
+                entry_type.redundancy = Some($redundancy);
+            )*
+            $(
+                entry_type.prunable = $prunable;
+            )*
+            $(
+                entry_type.retention = Some($retention);
+            )*
+            $(
+                entry_type.ttl_seconds = Some($ttl_seconds);
+            )*
 
             $($(
                 match $link_expr.link_type {
@@ -210,11 +405,49 @@ macro_rules! entry {
                 }
             });
 
+            #[allow(unused_mut)]
+            let mut dependency_resolver: Option<hdk::entry_definition::DependencyResolver> = None;
+            $(
+                let resolver: hdk::entry_definition::DependencyResolver = Box::new(|entry: hdk::holochain_core_types::entry::Entry| {
+                    match entry {
+                        hdk::holochain_core_types::entry::Entry::App(_, app_entry_value) => {
+                            let entry: $dependency_entry_type = ::std::convert::TryInto::try_into(app_entry_value)?;
+                            let $dependency_entry = entry;
+                            Ok($dependencies)
+                        },
+                        _ => {
+                            Err(String::from("Schema validation failed"))
+                        }
+                    }
+                });
+                dependency_resolver = Some(resolver);
+            )*
+
+            #[allow(unused_mut, unused_assignments)]
+            let mut schema_version: u32 = 0;
+            $(
+                schema_version = $version;
+            )*
+
+            #[allow(unused_mut)]
+            let mut migrator: Option<hdk::entry_definition::Migrator> = None;
+            $(
+                let migration: hdk::entry_definition::Migrator = Box::new(|version: u32, content: hdk::holochain_core_types::json::JsonString| {
+                    let $old_version = version;
+                    let $old_content = content;
+                    $migration
+                });
+                migrator = Some(migration);
+            )*
+
             hdk::entry_definition::ValidatingEntryType {
                 name: hdk::holochain_core_types::entry::entry_type::EntryType::App(hdk::holochain_core_types::entry::entry_type::AppEntryType::from($name.to_string())),
                 entry_type_definition: entry_type,
                 package_creator,
                 validator,
+                dependency_resolver,
+                schema_version,
+                migrator,
                 links: vec![
                     $($(
                         $link_expr
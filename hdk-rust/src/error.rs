@@ -1,8 +1,10 @@
 use crate::holochain_core_types::{
+    cas::content::Address,
     error::{HolochainError, RibosomeErrorCode},
     json::{JsonError, JsonString},
 };
-use std::{error::Error, fmt};
+use serde_json;
+use std::{error::Error, fmt, string::FromUtf8Error};
 
 /// Error for DNA developers to use in their zome code.
 /// They do not have to send this error back to Ribosome unless its an InternalError.
@@ -12,14 +14,49 @@ pub enum ZomeApiError {
     FunctionNotImplemented,
     HashNotFound,
     ValidationFailed(String),
+    Timeout,
+    NetworkUnavailable,
+    /// A peer dropped a request of this node because it sent too much,
+    /// retry after the given number of milliseconds
+    RateLimited(u64),
+    /// A commit required another top of the source chain than it had, see
+    /// `commit_entry_with_options()`. None is an empty chain.
+    ChainHeadMoved {
+        expected: Address,
+        actual: Option<Address>,
+    },
+    /// The container configured no bridge with this handle, see `call_bridge()`
+    BridgeNotPresent(String),
+    /// What was being done when the inner error happened, see `with_context()`
+    Context(String, Box<ZomeApiError>),
 }
 
 impl JsonError for ZomeApiError {}
 
+impl ZomeApiError {
+    /// Wraps the error in a context telling what the zome was doing when it happened,
+    /// just like `HolochainError::with_context()`. Displays as "context: inner".
+    pub fn with_context<S: Into<String>>(self, context: S) -> ZomeApiError {
+        ZomeApiError::Context(context.into(), Box::new(self))
+    }
+}
+
 impl From<ZomeApiError> for HolochainError {
     fn from(zome_api_error: ZomeApiError) -> Self {
         match zome_api_error {
             ZomeApiError::ValidationFailed(s) => HolochainError::ValidationFailed(s),
+            ZomeApiError::Timeout => HolochainError::Timeout,
+            ZomeApiError::NetworkUnavailable => HolochainError::NetworkUnavailable,
+            ZomeApiError::RateLimited(retry_after_ms) => {
+                HolochainError::RateLimited(retry_after_ms)
+            }
+            ZomeApiError::ChainHeadMoved { expected, actual } => {
+                HolochainError::ChainHeadMoved { expected, actual }
+            }
+            ZomeApiError::BridgeNotPresent(handle) => HolochainError::BridgeNotPresent(handle),
+            ZomeApiError::Context(context, inner) => {
+                HolochainError::from(*inner).with_context(context)
+            }
             _ => HolochainError::RibosomeFailed(zome_api_error.description().into()),
         }
     }
@@ -27,7 +64,10 @@ impl From<ZomeApiError> for HolochainError {
 
 impl From<ZomeApiError> for String {
     fn from(zome_api_error: ZomeApiError) -> Self {
-        zome_api_error.description().into()
+        match zome_api_error {
+            ZomeApiError::Context(..) => zome_api_error.to_string(),
+            _ => zome_api_error.description().into(),
+        }
     }
 }
 
@@ -35,6 +75,18 @@ impl From<HolochainError> for ZomeApiError {
     fn from(holochain_error: HolochainError) -> Self {
         match holochain_error {
             HolochainError::ValidationFailed(s) => ZomeApiError::ValidationFailed(s),
+            HolochainError::Timeout => ZomeApiError::Timeout,
+            HolochainError::NetworkUnavailable => ZomeApiError::NetworkUnavailable,
+            HolochainError::RateLimited(retry_after_ms) => {
+                ZomeApiError::RateLimited(retry_after_ms)
+            }
+            HolochainError::ChainHeadMoved { expected, actual } => {
+                ZomeApiError::ChainHeadMoved { expected, actual }
+            }
+            HolochainError::BridgeNotPresent(handle) => ZomeApiError::BridgeNotPresent(handle),
+            HolochainError::Context(context, inner) => {
+                ZomeApiError::from(*inner).with_context(context)
+            }
             _ => ZomeApiError::Internal(holochain_error.description().into()),
         }
     }
@@ -52,6 +104,24 @@ impl From<String> for ZomeApiError {
     }
 }
 
+impl<'a> From<&'a str> for ZomeApiError {
+    fn from(s: &'a str) -> ZomeApiError {
+        ZomeApiError::Internal(s.to_string())
+    }
+}
+
+impl From<serde_json::Error> for ZomeApiError {
+    fn from(serde_error: serde_json::Error) -> ZomeApiError {
+        ZomeApiError::Internal(serde_error.to_string())
+    }
+}
+
+impl From<FromUtf8Error> for ZomeApiError {
+    fn from(utf8_error: FromUtf8Error) -> ZomeApiError {
+        ZomeApiError::Internal(utf8_error.to_string())
+    }
+}
+
 impl From<RibosomeErrorCode> for ZomeApiError {
     fn from(ribosome_error_code: RibosomeErrorCode) -> ZomeApiError {
         ZomeApiError::from(ribosome_error_code.to_string())
@@ -66,27 +136,67 @@ impl Error for ZomeApiError {
             ZomeApiError::FunctionNotImplemented  => "Function not implemented",
             ZomeApiError::HashNotFound            => "Hash not found",
             ZomeApiError::ValidationFailed(msg)   => &msg,
+            ZomeApiError::Timeout                 => "Timeout",
+            ZomeApiError::NetworkUnavailable      => "Network unavailable",
+            ZomeApiError::RateLimited(_)          => "Rate limited",
+            ZomeApiError::ChainHeadMoved { .. }   => "Chain head moved",
+            ZomeApiError::BridgeNotPresent(_)     => "Bridge not present",
+            ZomeApiError::Context(context, _)     => &context,
         }
     }
 }
 
 impl fmt::Display for ZomeApiError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // @TODO seems weird to use debug for display
-        // replacing {:?} with {} gives a stack overflow on to_string() (there's a test for this)
-        // what is the right way to do this?
-        // @see https://github.com/holochain/holochain-rust/issues/223
-        write!(f, "{:?}", self)
+        match self {
+            ZomeApiError::RateLimited(retry_after_ms) => {
+                write!(f, "Rate limited, retry after {} ms", retry_after_ms)
+            }
+            ZomeApiError::ChainHeadMoved { expected, actual } => write!(
+                f,
+                "Chain head moved: expected {}, actual {:?}",
+                expected, actual
+            ),
+            ZomeApiError::BridgeNotPresent(handle) => {
+                write!(f, "Bridge not present: {}", handle)
+            }
+            ZomeApiError::Context(context, inner) => write!(f, "{}: {}", context, inner),
+            _ => write!(f, "{}", self.description()),
+        }
     }
 }
 
 pub type ZomeApiResult<T> = Result<T, ZomeApiError>;
 
+/// Builds a `ZomeApiError::Internal` with a message formatted like `format!()`,
+/// for zome functions to fail with:
+/// ```rust
+/// # #[macro_use]
+/// # extern crate hdk;
+/// # use hdk::error::ZomeApiResult;
+/// fn check_title(title: &str) -> ZomeApiResult<()> {
+///     if title.is_empty() {
+///         return Err(zome_api_error!("posts need a title, got {:?}", title));
+///     }
+///     Ok(())
+/// }
+/// # fn main() {
+/// #     assert!(check_title("").is_err());
+/// # }
+/// ```
+#[macro_export]
+macro_rules! zome_api_error {
+    ($($arg:tt)*) => {
+        $crate::error::ZomeApiError::Internal(format!($($arg)*))
+    };
+}
+
 #[cfg(test)]
 mod tests {
 
     use error::{ZomeApiError, ZomeApiResult};
-    use holochain_core_types::json::JsonString;
+    use holochain_core_types::{cas::content::Address, error::HolochainError, json::JsonString};
+    use serde_json;
 
     #[test]
     fn zome_api_result_json_result_round_trip_test() {
@@ -97,4 +207,120 @@ mod tests {
             JsonString::from("{\"Err\":\"FunctionNotImplemented\"}"),
         );
     }
+
+    #[test]
+    fn context_chain_survives_conversions() {
+        let zome_api_error = ZomeApiError::Timeout
+            .with_context("getting the post")
+            .with_context("listing posts");
+        assert_eq!(
+            "listing posts: getting the post: Timeout",
+            zome_api_error.to_string()
+        );
+
+        let holochain_error = HolochainError::from(zome_api_error);
+        assert_eq!(
+            HolochainError::Timeout
+                .with_context("getting the post")
+                .with_context("listing posts"),
+            holochain_error,
+        );
+
+        assert_eq!(
+            ZomeApiError::Timeout
+                .with_context("getting the post")
+                .with_context("listing posts"),
+            ZomeApiError::from(holochain_error),
+        );
+    }
+
+    #[test]
+    fn chain_head_moved_survives_conversions() {
+        let holochain_error = HolochainError::ChainHeadMoved {
+            expected: Address::from("QmA"),
+            actual: Some(Address::from("QmB")),
+        };
+        let zome_api_error = ZomeApiError::from(holochain_error.clone());
+        assert_eq!(
+            ZomeApiError::ChainHeadMoved {
+                expected: Address::from("QmA"),
+                actual: Some(Address::from("QmB")),
+            },
+            zome_api_error,
+        );
+        assert_eq!(holochain_error, HolochainError::from(zome_api_error));
+    }
+
+    #[test]
+    fn errors_zome_code_runs_into_convert() {
+        assert_eq!(
+            ZomeApiError::from(String::from("no posts")),
+            ZomeApiError::Internal(String::from("no posts")),
+        );
+        assert_eq!(
+            ZomeApiError::from("no posts"),
+            ZomeApiError::Internal(String::from("no posts")),
+        );
+
+        let serde_error = serde_json::from_str::<u32>("not a number").unwrap_err();
+        assert_eq!(
+            ZomeApiError::from(serde_json::from_str::<u32>("not a number").unwrap_err()),
+            ZomeApiError::Internal(serde_error.to_string()),
+        );
+
+        let utf8_error = String::from_utf8(vec![0xff]).unwrap_err();
+        assert_eq!(
+            ZomeApiError::from(String::from_utf8(vec![0xff]).unwrap_err()),
+            ZomeApiError::Internal(utf8_error.to_string()),
+        );
+
+        assert_eq!(
+            ZomeApiError::from(HolochainError::ErrorGeneric(String::from("broken"))),
+            ZomeApiError::Internal(String::from("broken")),
+        );
+        assert_eq!(
+            ZomeApiError::from(HolochainError::Timeout),
+            ZomeApiError::Timeout
+        );
+    }
+
+    #[test]
+    fn zome_api_error_macro_formats_internal_errors() {
+        assert_eq!(
+            zome_api_error!("{} posts, at most {}", 12, 10),
+            ZomeApiError::Internal(String::from("12 posts, at most 10")),
+        );
+        assert_eq!(
+            zome_api_error!("no posts"),
+            ZomeApiError::Internal(String::from("no posts")),
+        );
+    }
+
+    #[test]
+    fn errors_display_their_message() {
+        assert_eq!(
+            ZomeApiError::Internal(String::from("no posts")).to_string(),
+            "no posts"
+        );
+        assert_eq!(
+            ZomeApiError::FunctionNotImplemented.to_string(),
+            "Function not implemented"
+        );
+        assert_eq!(
+            ZomeApiError::RateLimited(500).to_string(),
+            "Rate limited, retry after 500 ms"
+        );
+        assert_eq!(
+            ZomeApiError::ChainHeadMoved {
+                expected: Address::from("QmA"),
+                actual: None,
+            }
+            .to_string(),
+            "Chain head moved: expected QmA, actual None"
+        );
+        assert_eq!(
+            ZomeApiError::BridgeNotPresent(String::from("search")).to_string(),
+            "Bridge not present: search"
+        );
+    }
 }
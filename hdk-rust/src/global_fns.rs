@@ -1,20 +1,204 @@
-use crate::globals::G_MEM_STACK;
-use holochain_core_types::json::JsonString;
+use crate::{
+    error::{ZomeApiError, ZomeApiResult},
+    host_fns::HostFunction,
+};
+use holochain_core_types::{
+    error::{RibosomeErrorCode, ZomeApiInternalResult},
+    json::JsonString,
+};
 pub use holochain_wasm_utils::api_serialization::validation::*;
-use holochain_wasm_utils::{memory_allocation::*, memory_serialization::*};
-use std::convert::TryInto;
+use holochain_wasm_utils::{
+    api_serialization::CallInitData,
+    memory_serialization::{load_json, load_json_from_str},
+    ribosome::Ribosome,
+};
+use std::{cell::RefCell, convert::TryInto};
 
-/// Init global memory stack
+thread_local! {
+    // Handle on the Ribosome for the entry point call that is running
+    static RIBOSOME: RefCell<Option<Ribosome>> = RefCell::new(None);
+    // State of the source chain as of the entry point call that is running
+    static CALL_INIT_DATA: RefCell<Option<CallInitData>> = RefCell::new(None);
+}
+
+/// Init global memory stack: creates the Ribosome handle for a call of an entry point,
+/// with the stack starting right after the entry point's input
 pub fn init_global_memory(encoded_allocation_of_input: u32) {
-    unsafe {
-        G_MEM_STACK =
-            Some(SinglePageStack::from_encoded_allocation(encoded_allocation_of_input).unwrap());
+    let ribosome = Ribosome::from_encoded_allocation(encoded_allocation_of_input).unwrap();
+    RIBOSOME.with(|cell| *cell.borrow_mut() = Some(ribosome));
+}
+
+/// Init call data: keeps the CallInitData that core handed to the entry point call,
+/// for hdk::chain_top(), hdk::chain_length() and hdk::call_time() to read
+pub fn init_call_init_data(encoded_allocation_of_call_init_data: u32) {
+    let call_init_data = load_json(encoded_allocation_of_call_init_data).ok();
+    CALL_INIT_DATA.with(|cell| *cell.borrow_mut() = call_init_data);
+}
+
+/// Sets the CallInitData directly, for the mock ribosome of `hdk::testing`
+#[cfg(feature = "test")]
+pub(crate) fn set_call_init_data(call_init_data: Option<CallInitData>) {
+    CALL_INIT_DATA.with(|cell| *cell.borrow_mut() = call_init_data);
+}
+
+/// The CallInitData of the running entry point call
+pub(crate) fn call_init_data() -> ZomeApiResult<CallInitData> {
+    CALL_INIT_DATA
+        .with(|cell| cell.borrow().clone())
+        .ok_or_else(|| {
+            ZomeApiError::Internal("entry point did not receive the call init data".to_string())
+        })
+}
+
+/// Runs the given function with the Ribosome handle of the running entry point call
+pub(crate) fn with_ribosome<T, F: FnOnce(&mut Ribosome) -> T>(f: F) -> T {
+    RIBOSOME.with(|cell| {
+        let mut maybe_ribosome = cell.borrow_mut();
+        let ribosome = maybe_ribosome
+            .as_mut()
+            .expect("entry points must call init_global_memory() first");
+        f(ribosome)
+    })
+}
+
+/// Calls the given function of the Ribosome with the given input and returns its result
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn call_host<J: TryInto<JsonString>>(
+    host_fn: HostFunction,
+    input: J,
+) -> Result<JsonString, RibosomeErrorCode> {
+    with_ribosome(|ribosome| ribosome.call_host(host_fn.function, input))
+}
+
+/// Natively there is no Ribosome: calls go to the mock ribosome of `hdk::testing`
+#[cfg(all(not(target_arch = "wasm32"), feature = "test"))]
+pub(crate) fn call_host<J: TryInto<JsonString>>(
+    host_fn: HostFunction,
+    input: J,
+) -> Result<JsonString, RibosomeErrorCode> {
+    let input = input
+        .try_into()
+        .map_err(|_| RibosomeErrorCode::ArgumentDeserializationFailed)?;
+    crate::testing::call_mock_ribosome(host_fn.name, input)
+}
+
+/// Natively there is no Ribosome, and without the `test` feature no mock ribosome either
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "test")))]
+pub(crate) fn call_host<J: TryInto<JsonString>>(
+    _host_fn: HostFunction,
+    _input: J,
+) -> Result<JsonString, RibosomeErrorCode> {
+    Err(RibosomeErrorCode::Unspecified)
+}
+
+/// Calls the given function of the Ribosome and returns the value of the
+/// ZomeApiInternalResult it gives back
+pub(crate) fn call_host_fn<J: TryInto<JsonString>>(
+    host_fn: HostFunction,
+    input: J,
+) -> ZomeApiResult<JsonString> {
+    let result_json = call_host(host_fn, input)?;
+    let result: ZomeApiInternalResult = load_json_from_str(&String::from(result_json))?;
+    if result.ok {
+        Ok(JsonString::from(result.value))
+    } else {
+        Err(ZomeApiError::from(result.error))
     }
 }
 
 /// Serialize output as json in WASM memory
 pub fn store_and_return_output<J: TryInto<JsonString>>(jsonable: J) -> u32 {
-    unsafe {
-        return store_as_json_into_encoded_allocation(&mut G_MEM_STACK.unwrap(), jsonable) as u32;
+    with_ribosome(|ribosome| ribosome.store_output(jsonable))
+}
+
+/// The output of a zome function on its way into WASM memory, see `define_zome!`.
+/// Outputs that convert into a JsonString get stored as they are, through `ZomeFnOutput`.
+/// Results with any other error that converts into a ZomeApiError, like a serde_json::Error,
+/// get stored as a ZomeApiResult through `ZomeFnResult`, so zome functions can return
+/// whatever error `?` gives them.
+#[doc(hidden)]
+pub struct ZomeFnReturn<T>(Option<T>);
+
+impl<T> ZomeFnReturn<T> {
+    pub fn new(output: T) -> Self {
+        ZomeFnReturn(Some(output))
+    }
+
+    fn take(&mut self) -> T {
+        self.0
+            .take()
+            .expect("the output of a zome function gets stored once")
+    }
+}
+
+#[doc(hidden)]
+pub trait ZomeFnOutput {
+    type Output: TryInto<JsonString>;
+    fn zome_fn_output(self) -> Self::Output;
+}
+
+impl<J: TryInto<JsonString>> ZomeFnOutput for ZomeFnReturn<J> {
+    type Output = J;
+    fn zome_fn_output(mut self) -> J {
+        self.take()
+    }
+}
+
+/// Only gets picked when `ZomeFnOutput` does not apply: method calls on a ZomeFnReturn
+/// try the value itself before a mutable reference to it
+#[doc(hidden)]
+pub trait ZomeFnResult {
+    type Output: TryInto<JsonString>;
+    fn zome_fn_output(self) -> Self::Output;
+}
+
+impl<'a, T: Into<JsonString>, E: Into<ZomeApiError>> ZomeFnResult
+    for &'a mut ZomeFnReturn<Result<T, E>>
+{
+    type Output = ZomeApiResult<T>;
+    fn zome_fn_output(self) -> ZomeApiResult<T> {
+        self.take().map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use holochain_core_types::json::RawString;
+    use serde_json;
+
+    fn stored<J: TryInto<JsonString>>(output: J) -> Option<JsonString> {
+        output.try_into().ok()
+    }
+
+    #[test]
+    fn zome_fn_outputs_keep_their_json() {
+        #[allow(unused_mut)]
+        let mut output = ZomeFnReturn::new(Err::<RawString, String>(String::from("no posts")));
+        assert_eq!(
+            stored(output.zome_fn_output()),
+            Some(JsonString::from("{\"Err\":\"no posts\"}")),
+        );
+
+        #[allow(unused_mut)]
+        let mut output = ZomeFnReturn::new(ZomeApiResult::Ok(RawString::from("post")));
+        assert_eq!(
+            stored(output.zome_fn_output()),
+            Some(JsonString::from("{\"Ok\":\"post\"}")),
+        );
+    }
+
+    #[test]
+    fn zome_fn_errors_become_zome_api_errors() {
+        let serde_error = serde_json::from_str::<u32>("not a number").unwrap_err();
+        let expected = JsonString::from(ZomeApiResult::<RawString>::Err(ZomeApiError::Internal(
+            serde_error.to_string(),
+        )));
+
+        #[allow(unused_mut)]
+        let mut output = ZomeFnReturn::new(
+            serde_json::from_str::<u32>("not a number").map(|_| RawString::from("post")),
+        );
+        assert_eq!(stored(output.zome_fn_output()), Some(expected));
     }
 }
@@ -0,0 +1,73 @@
+//! Declarations of the functions the Ribosome provides to zomes.
+//! This is the only place they are declared, the zome API calls them through
+//! `global_fns::call_host()`, which takes care of the memory they need.
+//! They are only imported when compiling to WASM. Natively, calls go to the mock ribosome
+//! of `hdk::testing` instead, by the name of the function.
+
+#[cfg(target_arch = "wasm32")]
+use holochain_wasm_utils::ribosome::HostFn;
+
+/// A function of the Ribosome: its name and, in WASM, the import to call it with
+#[derive(Clone, Copy, Debug)]
+pub struct HostFunction {
+    pub name: &'static str,
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) function: HostFn,
+}
+
+macro_rules! host_fns {
+    ($($name:ident),* $(,)*) => {
+        #[cfg(target_arch = "wasm32")]
+        mod imports {
+            extern "C" {
+                $(pub fn $name(encoded_allocation_of_input: u32) -> u32;)*
+            }
+        }
+
+        $(
+            #[allow(dead_code, non_upper_case_globals)]
+            pub(crate) const $name: HostFunction = HostFunction {
+                name: stringify!($name),
+                #[cfg(target_arch = "wasm32")]
+                function: imports::$name,
+            };
+        )*
+    };
+}
+
+// WARNING Names must be in sync with ZomeAPIFunction in holochain-rust
+host_fns!(
+    hc_init_globals,
+    hc_property,
+    hc_entry_types,
+    hc_entry_type_properties,
+    hc_entry_address,
+    hc_debug,
+    hc_call,
+    hc_yield,
+    hc_sign,
+    hc_verify_signature,
+    hc_encrypt_for,
+    hc_decrypt,
+    hc_commit_entry,
+    hc_update_entry,
+    hc_update_agent,
+    hc_remove_entry,
+    hc_get_entry,
+    hc_link_entries,
+    hc_commit_and_link,
+    hc_get_links,
+    hc_count_links,
+    hc_await_publish,
+    hc_get_publish_status,
+    hc_republish,
+    hc_query,
+    hc_query_page,
+    hc_chain_info,
+    hc_random,
+    hc_sleep,
+    hc_call_bridge,
+    hc_send,
+    hc_start_bundle,
+    hc_close_bundle,
+);
@@ -30,12 +30,15 @@ pub mod entry_definition;
 pub mod error;
 pub mod global_fns;
 pub mod globals;
+mod host_fns;
 pub mod init_globals;
 pub mod macros;
+#[cfg(feature = "test")]
+pub mod testing;
 
 pub use holochain_wasm_utils::api_serialization::validation::*;
 
 pub mod meta;
 
-pub use crate::api::*;
+pub use crate::{api::*, globals::app_globals};
 pub use holochain_core_types::validation::*;
@@ -22,6 +22,17 @@ macro_rules! load_json {
 /// 3. functions: `functions` is divided up into `capabilities`, which specify who can access those functions.
 ///     `functions` must be a tree structure where the first children are `capabilities`
 ///     and the children of those `capabilities` are actual function definitions.
+///
+/// Between entries and genesis, a zome can declare the `host_permissions` it needs, as an array of
+/// [HostPermission](../holochain_core_types/dna/zome/host_permissions/enum.HostPermission.html)s.
+/// It can then only call the host functions these permit, e.g. `ChainRead` permits `query()` but not
+/// `commit_entry()`. Calling others fails with a PermissionDenied error. A zome that declares none
+/// may call every host function.
+///
+/// The output of a function can be anything that converts into a JsonString, usually a
+/// `ZomeApiResult`. It can also be a Result with any error that converts into a
+/// [ZomeApiError](error/enum.ZomeApiError.html), like a `serde_json::Error` or a `HolochainError`,
+/// which then gets returned as a ZomeApiError. That way `?` works on all of them in a handler.
 /// # Examples
 ///
 /// ```rust
@@ -98,6 +109,9 @@ macro_rules! load_json {
 ///         )
 ///     ]
 ///
+///     // post_address only computes an address, the zome needs no access to chain or network
+///     host_permissions: []
+///
 ///     genesis: || {
 ///         Ok(())
 ///     }
@@ -128,6 +142,12 @@ macro_rules! define_zome {
             $( $entry_expr:expr ),*
         ]
 
+        $(
+            host_permissions : [
+                $( $host_permission:ident ),*
+            ]
+        )*
+
         genesis : || {
             $genesis_expr:expr
         }
@@ -156,8 +176,9 @@ macro_rules! define_zome {
         }
 
         #[no_mangle]
-        pub extern "C" fn genesis(encoded_allocation_of_input: u32) -> u32 {
+        pub extern "C" fn genesis(encoded_allocation_of_input: u32, encoded_allocation_of_call_init_data: u32) -> u32 {
             $crate::global_fns::init_global_memory(encoded_allocation_of_input);
+            $crate::global_fns::init_call_init_data(encoded_allocation_of_call_init_data);
 
             fn execute() -> Result<(), String> {
                 $genesis_expr
@@ -169,6 +190,18 @@ macro_rules! define_zome {
             }
         }
 
+        #[no_mangle]
+        #[allow(unused_mut, unused_assignments)]
+        pub fn __list_host_permissions() -> Option<$crate::holochain_core_types::dna::zome::host_permissions::HostPermissions> {
+            let mut host_permissions = None;
+            $(
+                host_permissions = Some(vec![
+                    $( $crate::holochain_core_types::dna::zome::host_permissions::HostPermission::$host_permission ),*
+                ].into_iter().collect());
+            )*
+            host_permissions
+        }
+
         use $crate::holochain_core_types::dna::zome::capabilities::Capability;
         use std::collections::HashMap;
 
@@ -218,8 +251,9 @@ macro_rules! define_zome {
         $(
             $(
                 #[no_mangle]
-                pub extern "C" fn $zome_function_name(encoded_allocation_of_input: u32) -> u32 {
+                pub extern "C" fn $zome_function_name(encoded_allocation_of_input: u32, encoded_allocation_of_call_init_data: u32) -> u32 {
                     $crate::global_fns::init_global_memory(encoded_allocation_of_input);
+                    $crate::global_fns::init_call_init_data(encoded_allocation_of_call_init_data);
 
                     // Macro'd InputStruct
                     #[derive(Deserialize, Debug)]
@@ -238,7 +272,13 @@ macro_rules! define_zome {
                         $handler_path($($input_param_name),*)
                     }
 
-                    $crate::global_fns::store_and_return_output(execute(input))
+                    // Results with errors other than ZomeApiError or String get stored
+                    // as a ZomeApiResult, see ZomeFnReturn
+                    #[allow(unused_imports)]
+                    use $crate::global_fns::{ZomeFnOutput, ZomeFnResult};
+                    #[allow(unused_mut)]
+                    let mut output = $crate::global_fns::ZomeFnReturn::new(execute(input));
+                    $crate::global_fns::store_and_return_output(output.zome_fn_output())
                 }
             )+
         )*
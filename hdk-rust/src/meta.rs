@@ -1,7 +1,9 @@
-use crate::{entry_definition::ValidatingEntryType, globals::G_MEM_STACK};
+use crate::entry_definition::ValidatingEntryType;
 use holochain_core_types::{
+    cas::content::Address,
     dna::zome::{
         entry_types::{deserialize_entry_types, serialize_entry_types},
+        host_permissions::HostPermissions,
         ZomeCapabilities, ZomeEntryTypes,
     },
     entry::entry_type::{AppEntryType, EntryType},
@@ -10,10 +12,11 @@ use holochain_core_types::{
 };
 use holochain_wasm_utils::{
     api_serialization::validation::{
-        EntryValidationArgs, LinkValidationArgs, LinkValidationPackageArgs,
+        EntryValidationArgs, EntryValidationDependenciesArgs, LinkValidationArgs,
+        LinkValidationPackageArgs,
     },
     holochain_core_types::error::RibosomeErrorCode,
-    memory_serialization::{load_json, load_string, store_string_into_encoded_allocation},
+    memory_serialization::{load_json, load_string},
 };
 use std::collections::BTreeMap;
 
@@ -27,6 +30,8 @@ struct PartialZome {
     #[serde(deserialize_with = "deserialize_entry_types")]
     entry_types: ZomeEntryTypes,
     capabilities: ZomeCapabilities,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    host_permissions: Option<HostPermissions>,
 }
 
 #[allow(improper_ctypes)]
@@ -51,13 +56,27 @@ impl ZomeDefinition {
 extern "C" {
     fn zome_setup(zd: &mut ZomeDefinition);
     fn __list_capabilities() -> ZomeCapabilities;
+    fn __list_host_permissions() -> Option<HostPermissions>;
+}
+
+/// The definition of the given entry type, if this zome defines it
+pub(crate) fn entry_type_definition(entry_type: &EntryType) -> Option<ValidatingEntryType> {
+    let mut zd = ZomeDefinition::new();
+    unsafe {
+        zome_setup(&mut zd);
+    }
+    zd.entry_types
+        .into_iter()
+        .find(|validating_entry_type| validating_entry_type.name == *entry_type)
 }
 
 #[no_mangle]
 pub extern "C" fn __hdk_get_validation_package_for_entry_type(
     encoded_allocation_of_input: u32,
+    encoded_allocation_of_call_init_data: u32,
 ) -> u32 {
     crate::global_fns::init_global_memory(encoded_allocation_of_input);
+    crate::global_fns::init_call_init_data(encoded_allocation_of_call_init_data);
 
     let mut zd = ZomeDefinition::new();
     unsafe {
@@ -86,8 +105,12 @@ pub extern "C" fn __hdk_get_validation_package_for_entry_type(
 }
 
 #[no_mangle]
-pub extern "C" fn __hdk_validate_app_entry(encoded_allocation_of_input: u32) -> u32 {
+pub extern "C" fn __hdk_validate_app_entry(
+    encoded_allocation_of_input: u32,
+    encoded_allocation_of_call_init_data: u32,
+) -> u32 {
     crate::global_fns::init_global_memory(encoded_allocation_of_input);
+    crate::global_fns::init_call_init_data(encoded_allocation_of_call_init_data);
 
     let mut zd = ZomeDefinition::new();
     unsafe {
@@ -109,10 +132,12 @@ pub extern "C" fn __hdk_validate_app_entry(encoded_allocation_of_input: u32) ->
         }) {
         None => RibosomeErrorCode::CallbackFailed as u32,
         Some(mut entry_type_definition) => {
-            let validation_result = (*entry_type_definition.validator)(
-                entry_validation_args.entry,
-                entry_validation_args.validation_data,
-            );
+            // Validators know only the current schema of the entry type
+            let validation_result = entry_type_definition
+                .migrate_entry(entry_validation_args.entry)
+                .and_then(|entry| {
+                    (*entry_type_definition.validator)(entry, entry_validation_args.validation_data)
+                });
 
             match validation_result {
                 Ok(()) => 0,
@@ -123,8 +148,53 @@ pub extern "C" fn __hdk_validate_app_entry(encoded_allocation_of_input: u32) ->
 }
 
 #[no_mangle]
-pub extern "C" fn __hdk_get_validation_package_for_link(encoded_allocation_of_input: u32) -> u32 {
+pub extern "C" fn __hdk_get_validation_dependencies_for_entry(
+    encoded_allocation_of_input: u32,
+    encoded_allocation_of_call_init_data: u32,
+) -> u32 {
+    crate::global_fns::init_global_memory(encoded_allocation_of_input);
+    crate::global_fns::init_call_init_data(encoded_allocation_of_call_init_data);
+
+    let mut zd = ZomeDefinition::new();
+    unsafe {
+        zome_setup(&mut zd);
+    }
+
+    // Deserialize input
+    let maybe_args = load_json(encoded_allocation_of_input);
+    if let Err(hc_err) = maybe_args {
+        return crate::global_fns::store_and_return_output(hc_err);
+    }
+    let dependencies_args: EntryValidationDependenciesArgs = maybe_args.unwrap();
+
+    match zd
+        .entry_types
+        .into_iter()
+        .find(|ref validating_entry_type| {
+            validating_entry_type.name == dependencies_args.entry_type
+        }) {
+        None => RibosomeErrorCode::CallbackFailed as u32,
+        Some(mut entry_type_definition) => {
+            let dependencies: Result<Vec<Address>, String> =
+                match entry_type_definition.migrate_entry(dependencies_args.entry) {
+                    Err(migration_error) => Err(migration_error),
+                    Ok(entry) => match entry_type_definition.dependency_resolver {
+                        Some(ref mut dependency_resolver) => (*dependency_resolver)(entry),
+                        None => Ok(Vec::new()),
+                    },
+                };
+            crate::global_fns::store_and_return_output(dependencies)
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn __hdk_get_validation_package_for_link(
+    encoded_allocation_of_input: u32,
+    encoded_allocation_of_call_init_data: u32,
+) -> u32 {
     ::global_fns::init_global_memory(encoded_allocation_of_input);
+    ::global_fns::init_call_init_data(encoded_allocation_of_call_init_data);
 
     let mut zd = ZomeDefinition::new();
     unsafe {
@@ -157,8 +227,12 @@ pub extern "C" fn __hdk_get_validation_package_for_link(encoded_allocation_of_in
 }
 
 #[no_mangle]
-pub extern "C" fn __hdk_validate_link(encoded_allocation_of_input: u32) -> u32 {
+pub extern "C" fn __hdk_validate_link(
+    encoded_allocation_of_input: u32,
+    encoded_allocation_of_call_init_data: u32,
+) -> u32 {
     ::global_fns::init_global_memory(encoded_allocation_of_input);
+    ::global_fns::init_call_init_data(encoded_allocation_of_call_init_data);
 
     let mut zd = ZomeDefinition::new();
     unsafe {
@@ -218,18 +292,15 @@ pub extern "C" fn __hdk_get_json_definition(encoded_allocation_of_input: u32) ->
     }
 
     let capabilities = unsafe { __list_capabilities() };
+    let host_permissions = unsafe { __list_host_permissions() };
 
     let partial_zome = PartialZome {
         entry_types,
         capabilities,
+        host_permissions,
     };
 
-    let json_string = JsonString::from(partial_zome);
-
-    unsafe {
-        store_string_into_encoded_allocation(&mut G_MEM_STACK.unwrap(), &String::from(json_string))
-            as u32
-    }
+    crate::global_fns::store_and_return_output(JsonString::from(partial_zome))
 }
 
 #[cfg(test)]
@@ -237,12 +308,22 @@ pub mod tests {
     use crate as hdk;
     use crate::ValidationPackageDefinition;
     use holochain_core_types::{
-        dna::zome::{entry_types::Sharing, ZomeCapabilities},
+        cas::content::Address,
+        dna::{
+            zome::{
+                entry_types::Sharing,
+                host_permissions::{HostPermission, HostPermissions},
+                ZomeCapabilities,
+            },
+            Dna,
+        },
+        entry::{entry_type::AppEntryType, Entry},
         error::HolochainError,
         json::JsonString,
     };
     use meta::PartialZome;
-    use std::collections::BTreeMap;
+    use serde_json;
+    use std::{collections::BTreeMap, convert::TryFrom};
 
     // Adding empty zome_setup() so that the cfg(test) build can link.
     #[no_mangle]
@@ -251,6 +332,10 @@ pub mod tests {
     pub fn __list_capabilities() -> ZomeCapabilities {
         BTreeMap::new()
     }
+    #[no_mangle]
+    pub fn __list_host_permissions() -> Option<HostPermissions> {
+        None
+    }
 
     #[test]
     fn partial_zome_json() {
@@ -292,4 +377,224 @@ pub mod tests {
             JsonString::from("{\"entry_types\":{\"post\":{\"description\":\"blog entry post\",\"sharing\":\"public\",\"links_to\":[],\"linked_from\":[]}},\"capabilities\":{}}"),
         );
     }
+
+    #[test]
+    fn partial_zome_json_with_host_permissions() {
+        let partial_zome = PartialZome {
+            host_permissions: Some(
+                vec![HostPermission::Dht, HostPermission::ChainRead]
+                    .into_iter()
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            JsonString::from(partial_zome),
+            JsonString::from(
+                "{\"entry_types\":{},\"capabilities\":{},\"host_permissions\":[\"chain_read\",\"dht\"]}"
+            ),
+        );
+    }
+
+    #[test]
+    fn validation_dependencies_resolve_native_entries() {
+        #[derive(Serialize, Deserialize, Debug, DefaultJson)]
+        pub struct Comment {
+            content: String,
+            post: Address,
+        }
+
+        let mut validating_entry_type = entry!(
+            name: "comment",
+            description: "a comment on a post",
+            sharing: Sharing::Public,
+            native_type: Comment,
+
+            validation_dependencies: |comment: Comment| {
+                vec![comment.post]
+            },
+
+            validation_package: || {
+                ValidationPackageDefinition::Entry
+            },
+
+            validation: |_comment: Comment, _ctx: hdk::ValidationData| {
+                Ok(())
+            }
+        );
+
+        let comment = Comment {
+            content: String::from("nice post"),
+            post: Address::from("QmPost"),
+        };
+        let entry = Entry::App(AppEntryType::from("comment"), JsonString::from(comment));
+        let mut dependency_resolver = validating_entry_type
+            .dependency_resolver
+            .take()
+            .expect("validation_dependencies should define a dependency resolver");
+        assert_eq!(
+            (*dependency_resolver)(entry),
+            Ok(vec![Address::from("QmPost")])
+        );
+        assert!((*dependency_resolver)(Entry::Dna(Dna::new())).is_err());
+    }
+
+    #[derive(Serialize, Deserialize, Debug, DefaultJson, PartialEq)]
+    pub struct PersonV2 {
+        first_name: String,
+        last_name: String,
+        nickname: Option<String>,
+    }
+
+    /// "person" entries started out with a single `name` (version 0), version 1 split it
+    /// into `first_name` and `last_name` and version 2 added `nickname`.
+    fn versioned_person_entry_type() -> hdk::entry_definition::ValidatingEntryType {
+        entry!(
+            name: "person",
+            description: "someone we know",
+            sharing: Sharing::Public,
+            native_type: PersonV2,
+            version: 2,
+
+            migrate_from: |old_version: u32, old_content: JsonString| {
+                let mut person: serde_json::Value =
+                    serde_json::from_str(&String::from(old_content)).map_err(|e| e.to_string())?;
+                match old_version {
+                    0 => {
+                        let name = person["name"].as_str().unwrap_or_default().to_string();
+                        let mut names = name.splitn(2, ' ');
+                        let mut split = serde_json::Map::new();
+                        split.insert(String::from("first_name"), names.next().unwrap_or_default().into());
+                        split.insert(String::from("last_name"), names.next().unwrap_or_default().into());
+                        Ok(JsonString::from(serde_json::Value::Object(split)))
+                    }
+                    1 => {
+                        person["nickname"] = serde_json::Value::Null;
+                        Ok(JsonString::from(person))
+                    }
+                    _ => Err(format!("unknown person version {}", old_version)),
+                }
+            },
+
+            validation_package: || {
+                ValidationPackageDefinition::Entry
+            },
+
+            validation: |person: PersonV2, _ctx: hdk::ValidationData| {
+                if person.first_name.is_empty() {
+                    Err(String::from("Person needs a first name"))
+                } else {
+                    Ok(())
+                }
+            }
+        )
+    }
+
+    fn jane() -> PersonV2 {
+        PersonV2 {
+            first_name: String::from("Jane"),
+            last_name: String::from("Doe"),
+            nickname: None,
+        }
+    }
+
+    #[test]
+    fn migrates_older_versions_on_read() {
+        let mut entry_type = versioned_person_entry_type();
+        assert_eq!(entry_type.schema_version, 2);
+
+        // unversioned content is version 0
+        let v0 = JsonString::from("{\"name\":\"Jane Doe\"}");
+        assert_eq!(
+            PersonV2::try_from(entry_type.migrate(v0).unwrap()),
+            Ok(jane())
+        );
+
+        let v1 = JsonString::from(
+            "{\"schema_version\":1,\"content\":{\"first_name\":\"Jane\",\"last_name\":\"Doe\"}}",
+        );
+        assert_eq!(
+            PersonV2::try_from(entry_type.migrate(v1).unwrap()),
+            Ok(jane())
+        );
+
+        // what commit_typed() stores for the current version only gets unwrapped
+        let v2 = entry_type.stamp_version(JsonString::from(jane())).unwrap();
+        assert_eq!(
+            v2,
+            JsonString::from("{\"schema_version\":2,\"content\":{\"first_name\":\"Jane\",\"last_name\":\"Doe\",\"nickname\":null}}"),
+        );
+        assert_eq!(
+            PersonV2::try_from(entry_type.migrate(v2).unwrap()),
+            Ok(jane())
+        );
+
+        let v3 = JsonString::from("{\"schema_version\":3,\"content\":{}}");
+        assert_eq!(
+            entry_type.migrate(v3),
+            Err(String::from(
+                "person entry has schema version 3, this zome only knows up to version 2"
+            )),
+        );
+    }
+
+    #[test]
+    fn validates_migrated_entries() {
+        let mut entry_type = versioned_person_entry_type();
+        let validation_data = hdk::ValidationData::default();
+
+        let v0 = Entry::App(
+            AppEntryType::from("person"),
+            JsonString::from("{\"name\":\"Jane Doe\"}"),
+        );
+        let migrated = entry_type.migrate_entry(v0.clone()).unwrap();
+        assert_eq!(
+            (*entry_type.validator)(migrated, validation_data.clone()),
+            Ok(())
+        );
+        // the validator can't read the stored form of old versions by itself
+        assert!((*entry_type.validator)(v0, validation_data.clone()).is_err());
+
+        let nameless = Entry::App(
+            AppEntryType::from("person"),
+            JsonString::from("{\"name\":\"\"}"),
+        );
+        let migrated = entry_type.migrate_entry(nameless).unwrap();
+        assert_eq!(
+            (*entry_type.validator)(migrated, validation_data),
+            Err(String::from("Person needs a first name")),
+        );
+    }
+
+    #[test]
+    fn unversioned_entry_types_keep_content() {
+        #[derive(Serialize, Deserialize, Debug, DefaultJson)]
+        pub struct Post {
+            content: String,
+        }
+
+        let mut entry_type = entry!(
+            name: "post",
+            description: "blog entry post",
+            sharing: Sharing::Public,
+            native_type: Post,
+
+            validation_package: || {
+                ValidationPackageDefinition::Entry
+            },
+
+            validation: |_post: Post, _ctx: hdk::ValidationData| {
+                Ok(())
+            }
+        );
+        assert_eq!(entry_type.schema_version, 0);
+
+        let content = JsonString::from("{\"content\":\"hello\"}");
+        assert_eq!(
+            entry_type.stamp_version(content.clone()),
+            Ok(content.clone())
+        );
+        assert_eq!(entry_type.migrate(content.clone()), Ok(content));
+    }
 }
@@ -0,0 +1,488 @@
+//! Runs zome code natively, in ordinary Rust tests, instead of in WASM inside a container.
+//! Only there with the `test` feature, which zomes enable for their tests:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! hdk = { path = "..", features = ["test"] }
+//! ```
+//!
+//! A `MockRibosome` stands in for the Ribosome the zome API calls. It keeps a source chain,
+//! starting with a DNA and an agent entry, the entries committed to it and the links between
+//! them in memory, with the addresses Holochain would give them, so zome functions can be called
+//! like any other function and commits, gets, links and queries work as they would in a
+//! container. No bridges are configured, so `call_bridge()` fails with BridgeNotPresent.
+//! Other functions of the Ribosome fail with FunctionNotImplemented, unless the
+//! test programs their responses with `respond_to()`. Validation callbacks can be called
+//! directly, with the `validation_data()` of the mock ribosome.
+//!
+//! ```rust
+//! # extern crate hdk;
+//! # use hdk::holochain_core_types::entry::{entry_type::AppEntryType, Entry};
+//! # use hdk::testing::MockRibosome;
+//! # fn main() {
+//! let ribosome = MockRibosome::new();
+//! let entry = Entry::App(AppEntryType::from("post"), "\"hello\"".into());
+//! let address = hdk::commit_entry_address(&entry).unwrap();
+//! assert_eq!(hdk::get_entry(address.clone()).unwrap(), Some(entry));
+//! assert_eq!(ribosome.entry(&address).is_some(), true);
+//! # }
+//! ```
+//!
+//! Each thread has a mock ribosome of its own, the one most recently created on it, so tests
+//! running in parallel don't see each other's chains. The Zome API globals, e.g. AGENT_ADDRESS,
+//! are cached for the whole process, which is why every mock ribosome has the same agent and DNA.
+
+use crate::{
+    error::{ZomeApiError, ZomeApiResult},
+    global_fns::set_call_init_data,
+};
+use holochain_core_types::{
+    agent::AgentId,
+    cas::content::{Address, AddressableContent},
+    chain_header::ChainHeader,
+    crud_status::CrudStatus,
+    dna::Dna,
+    entry::{entry_type::EntryType, Entry, EntryWithMeta},
+    error::{CoreError, HolochainError, RibosomeErrorCode, ZomeApiInternalResult},
+    json::JsonString,
+    link::{link_add::LinkAdd, Link},
+    time::Iso8601,
+    validation::{EntryAction, EntryLifecycle, ValidationData, ValidationPackage},
+};
+use holochain_wasm_utils::api_serialization::{
+    get_entry::{EntryHistory, GetEntryArgs},
+    get_links::{GetLinksArgs, GetLinksResult},
+    link_entries::LinkEntriesArgs,
+    BridgeCallArgs, CallInitData, ChainInfo, CommitEntryArgs, CommitEntryResult, CommitOptions,
+    QueryArgs, ZomeApiGlobals,
+};
+use serde_json;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    rc::Rc,
+    str::FromStr,
+    time::SystemTime,
+};
+
+/// Nickname of the agent of every mock ribosome
+pub const MOCK_AGENT_NICK: &str = "mockagent";
+
+/// Programmed response to calls of a function of the mock ribosome:
+/// gets the input of a call and returns its result
+pub type MockResponse = Box<FnMut(JsonString) -> ZomeApiResult<JsonString>>;
+
+// The functions that hand back their result as it is, not in a ZomeApiInternalResult
+const RAW_RESULT_FUNCTIONS: [&str; 3] = ["debug", "call", "remove_entry"];
+
+thread_local! {
+    // The mock ribosome zome code running on this thread calls
+    static MOCK_RIBOSOME: RefCell<Option<Rc<RefCell<MockState>>>> = RefCell::new(None);
+}
+
+/// Calls the given function of the mock ribosome of this thread, like `Ribosome::call_host()`
+/// calls the actual one
+pub(crate) fn call_mock_ribosome(
+    host_fn_name: &str,
+    input: JsonString,
+) -> Result<JsonString, RibosomeErrorCode> {
+    let state = MOCK_RIBOSOME
+        .with(|cell| cell.borrow().clone())
+        .expect("zome code running natively needs a hdk::testing::MockRibosome");
+    let function = host_fn_name.trim_left_matches("hc_");
+
+    // taken out for the call, so that the response can call the zome API itself
+    let maybe_response = state.borrow_mut().responses.remove(function);
+    let result = match maybe_response {
+        Some(mut response) => {
+            let result = response(input).map_err(HolochainError::from);
+            state
+                .borrow_mut()
+                .responses
+                .insert(function.to_string(), response);
+            result
+        }
+        None => state.borrow_mut().call(function, input),
+    };
+
+    if RAW_RESULT_FUNCTIONS.contains(&function) {
+        result.map_err(|_| RibosomeErrorCode::Unspecified)
+    } else {
+        Ok(JsonString::from(match result {
+            Ok(value) => ZomeApiInternalResult::success(value),
+            Err(error) => ZomeApiInternalResult::failure(CoreError::new(error)),
+        }))
+    }
+}
+
+/// Handle on the mock ribosome of a test, see the module docs
+pub struct MockRibosome {
+    state: Rc<RefCell<MockState>>,
+}
+
+impl MockRibosome {
+    /// Creates a mock ribosome with a chain that only has the DNA and the agent entry on it,
+    /// and makes it the one the zome code running on this thread calls
+    pub fn new() -> Self {
+        let state = Rc::new(RefCell::new(MockState::new()));
+        MOCK_RIBOSOME.with(|cell| *cell.borrow_mut() = Some(state.clone()));
+        set_call_init_data(Some(state.borrow().call_init_data()));
+        MockRibosome { state }
+    }
+
+    /// Programs the response to calls of the given function, e.g. `"call"` or `"send"`,
+    /// named like in the Ribosome without the `hc_` prefix.
+    /// It replaces what the mock ribosome does otherwise, for the functions it implements too.
+    pub fn respond_to<F>(&self, function: &str, response: F)
+    where
+        F: FnMut(JsonString) -> ZomeApiResult<JsonString> + 'static,
+    {
+        self.state
+            .borrow_mut()
+            .responses
+            .insert(function.to_string(), Box::new(response));
+    }
+
+    /// Address of the agent the zome code runs as, the same as AGENT_ADDRESS
+    pub fn agent_address(&self) -> Address {
+        self.state.borrow().globals.agent_address.clone()
+    }
+
+    /// Sets what `hdk::genesis_params()` returns, an empty object by default
+    pub fn set_genesis_params(&self, genesis_params: serde_json::Value) {
+        self.state.borrow_mut().genesis_params = genesis_params;
+        set_call_init_data(Some(self.state.borrow().call_init_data()));
+    }
+
+    /// The headers of the chain, newest first
+    pub fn chain(&self) -> Vec<ChainHeader> {
+        self.state.borrow().headers.iter().rev().cloned().collect()
+    }
+
+    /// The entry with the given address, if it got committed
+    pub fn entry(&self, address: &Address) -> Option<Entry> {
+        self.state.borrow().entries.get(address).cloned()
+    }
+
+    /// The targets of the links with the given tag from the given base
+    pub fn links(&self, base: &Address, tag: &str) -> Vec<Address> {
+        self.state.borrow().targets(base, tag)
+    }
+
+    /// What the zome code printed with `hdk::debug()`
+    pub fn debug_messages(&self) -> Vec<String> {
+        self.state.borrow().debug_messages.clone()
+    }
+
+    /// ValidationData like Holochain hands to the validation callbacks of entries
+    /// this agent commits, with the whole chain as validation package
+    pub fn validation_data(&self) -> ValidationData {
+        let state = self.state.borrow();
+        ValidationData {
+            package: ValidationPackage {
+                chain_header: state.headers.last().cloned(),
+                source_chain_entries: Some(
+                    state
+                        .headers
+                        .iter()
+                        .rev()
+                        .filter_map(|header| state.entries.get(header.entry_address()).cloned())
+                        .collect(),
+                ),
+                source_chain_headers: Some(state.headers.iter().rev().cloned().collect()),
+                custom: None,
+                pruned_entries: Vec::new(),
+            },
+            sources: vec![state.globals.agent_address.clone()],
+            lifecycle: EntryLifecycle::Chain,
+            action: EntryAction::Create,
+        }
+    }
+}
+
+impl Default for MockRibosome {
+    fn default() -> Self {
+        MockRibosome::new()
+    }
+}
+
+struct MockState {
+    globals: ZomeApiGlobals,
+    // oldest first
+    headers: Vec<ChainHeader>,
+    entries: HashMap<Address, Entry>,
+    // the targets of the links from a base with a tag, in the order they got linked
+    links: HashMap<(Address, String), Vec<Address>>,
+    debug_messages: Vec<String>,
+    responses: HashMap<String, MockResponse>,
+    genesis_params: serde_json::Value,
+}
+
+impl MockState {
+    fn new() -> Self {
+        let dna_entry = Entry::Dna(Dna::new());
+        let agent_id = AgentId::generate_fake(MOCK_AGENT_NICK);
+        let agent_entry = Entry::AgentId(agent_id.clone());
+        let mut state = MockState {
+            globals: ZomeApiGlobals {
+                dna_name: Dna::new().name,
+                dna_hash: dna_entry.address(),
+                dna_entry_address: dna_entry.address(),
+                agent_id_str: agent_id.nick.clone(),
+                agent_address: agent_id.address(),
+                agent_initial_hash: agent_entry.address(),
+                agent_latest_hash: agent_entry.address(),
+                agent_provisional: false,
+                call_context: Default::default(),
+            },
+            headers: Vec::new(),
+            entries: HashMap::new(),
+            links: HashMap::new(),
+            debug_messages: Vec::new(),
+            responses: HashMap::new(),
+            genesis_params: serde_json::Value::Object(serde_json::Map::new()),
+        };
+        state.commit(dna_entry);
+        state.commit(agent_entry);
+        state
+    }
+
+    fn call(&mut self, function: &str, input: JsonString) -> Result<JsonString, HolochainError> {
+        match function {
+            "init_globals" => Ok(JsonString::from(self.globals.clone())),
+            "debug" => {
+                self.debug_messages.push(String::from(input));
+                Ok(JsonString::null())
+            }
+            "entry_address" => Ok(JsonString::from(Entry::try_from(input)?.address())),
+            "commit_entry" => {
+                // commit_entry() hands over the entry, commit_entry_with_options() args
+                let args = CommitEntryArgs::try_from(input.clone()).or_else(|_| {
+                    Entry::try_from(input).map(|entry| CommitEntryArgs {
+                        entry,
+                        options: CommitOptions::default(),
+                    })
+                })?;
+                let top_header_address = self.top_header_address();
+                if let Some(expected) = args.options.require_head {
+                    if Some(expected.clone()) != top_header_address {
+                        return Err(HolochainError::ChainHeadMoved {
+                            expected,
+                            actual: top_header_address,
+                        });
+                    }
+                }
+                Ok(JsonString::from(self.commit(args.entry)))
+            }
+            "get_entry" => {
+                let args = GetEntryArgs::try_from(input)?;
+                let mut entry_history = EntryHistory::new();
+                if let Some(entry) = self.entries.get(&args.address) {
+                    entry_history.push(&EntryWithMeta::new(entry.clone(), CrudStatus::LIVE, None));
+                }
+                Ok(JsonString::from(entry_history))
+            }
+            "link_entries" => {
+                let args = LinkEntriesArgs::try_from(input)?;
+                // like in Holochain, the link goes on the chain as a LinkAdd entry
+                let link = Link::new(&args.base, &args.target, &args.tag);
+                self.commit(Entry::LinkAdd(LinkAdd::from_link(&link)));
+                self.links
+                    .entry((args.base, args.tag))
+                    .or_insert_with(Vec::new)
+                    .push(args.target);
+                Ok(JsonString::null())
+            }
+            "get_links" => {
+                let args = GetLinksArgs::try_from(input)?;
+                let targets = self
+                    .targets(&args.entry_address, &args.tag)
+                    .into_iter()
+                    .filter(|target| match args.target_entry_type {
+                        Some(ref entry_type_name) => self
+                            .entries
+                            .get(target)
+                            .map_or(false, |e| &String::from(e.entry_type()) == entry_type_name),
+                        None => true,
+                    })
+                    .collect();
+                Ok(JsonString::from(GetLinksResult::new(targets)))
+            }
+            "query" => {
+                let args = QueryArgs::try_from(input)?;
+                let entry_type = EntryType::from_str(&args.entry_type_name).map_err(|_| {
+                    HolochainError::ErrorGeneric(format!(
+                        "Unknown entry type {}",
+                        args.entry_type_name
+                    ))
+                })?;
+                let addresses = self
+                    .headers
+                    .iter()
+                    .rev()
+                    .filter(|header| header.entry_type() == &entry_type)
+                    .map(|header| header.entry_address().clone())
+                    .skip(args.start as usize);
+                Ok(JsonString::from(if args.limit > 0 {
+                    addresses.take(args.limit as usize).collect::<Vec<_>>()
+                } else {
+                    addresses.collect::<Vec<_>>()
+                }))
+            }
+            "chain_info" => {
+                let mut entry_type_counts = BTreeMap::new();
+                for header in self.headers.iter() {
+                    *entry_type_counts
+                        .entry(String::from(header.entry_type().clone()))
+                        .or_insert(0) += 1;
+                }
+                Ok(JsonString::from(ChainInfo {
+                    length: self.headers.len(),
+                    entry_type_counts,
+                    top_header_address: self.top_header_address(),
+                }))
+            }
+            "call_bridge" => Err(HolochainError::BridgeNotPresent(
+                BridgeCallArgs::try_from(input)?.handle,
+            )),
+            _ => Err(HolochainError::from(ZomeApiError::FunctionNotImplemented)),
+        }
+    }
+
+    fn commit(&mut self, entry: Entry) -> CommitEntryResult {
+        let entry_type = entry.entry_type();
+        let last_of_same_type = self
+            .headers
+            .iter()
+            .rev()
+            .find(|header| header.entry_type() == &entry_type)
+            .map(|header| header.address());
+        let header = ChainHeader::new(
+            &entry_type,
+            &entry.address(),
+            &vec![self.globals.agent_address.clone()],
+            &Vec::new(),
+            &self.top_header_address(),
+            &last_of_same_type,
+            &None,
+            &Iso8601::from(""),
+        );
+        let result = CommitEntryResult::new(entry.address(), header.address());
+        self.entries.insert(entry.address(), entry);
+        self.headers.push(header);
+        result
+    }
+
+    fn top_header_address(&self) -> Option<Address> {
+        self.headers.last().map(|header| header.address())
+    }
+
+    fn targets(&self, base: &Address, tag: &str) -> Vec<Address> {
+        self.links
+            .get(&(base.clone(), tag.to_string()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn call_init_data(&self) -> CallInitData {
+        CallInitData {
+            chain_top: self.top_header_address(),
+            chain_length: self.headers.len(),
+            call_time: Iso8601::from(SystemTime::now()),
+            genesis_params: self.genesis_params.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::api;
+    use holochain_core_types::entry::entry_type::AppEntryType;
+
+    fn post(content: &str) -> Entry {
+        Entry::App(AppEntryType::from("post"), JsonString::from(content))
+    }
+
+    #[test]
+    fn commit_and_get_round_trip() {
+        let ribosome = MockRibosome::new();
+        let entry = post("\"hello\"");
+
+        let address = api::commit_entry_address(&entry).unwrap();
+        assert_eq!(address, entry.address());
+        assert_eq!(api::entry_address(&entry).unwrap(), address);
+        assert_eq!(api::get_entry(address.clone()).unwrap(), Some(entry));
+        assert_eq!(api::get_entry(Address::from("QmMissing")).unwrap(), None);
+
+        // DNA, agent and the post
+        let chain = ribosome.chain();
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain[0].entry_address(), &address);
+        assert_eq!(chain[0].link(), Some(chain[1].address()));
+    }
+
+    #[test]
+    fn links_and_queries_work_on_the_mock_chain() {
+        let ribosome = MockRibosome::new();
+        let first = api::commit_entry_address(&post("\"first\"")).unwrap();
+        let second = api::commit_entry_address(&post("\"second\"")).unwrap();
+        let agent_address = ribosome.agent_address();
+        api::link_entries(&agent_address, &first, "authored").unwrap();
+        api::link_entries(&agent_address, &second, "authored").unwrap();
+
+        assert_eq!(
+            api::get_links(&agent_address, "authored")
+                .unwrap()
+                .addresses(),
+            &vec![first.clone(), second.clone()]
+        );
+        assert_eq!(ribosome.links(&agent_address, "other"), Vec::new());
+        assert_eq!(
+            api::query("post", 0, 0).unwrap(),
+            vec![second, first.clone()]
+        );
+        assert_eq!(api::query("post", 1, 1).unwrap(), vec![first]);
+        assert_eq!(api::chain_info().unwrap().count("%link_add"), 2);
+    }
+
+    #[test]
+    fn programmed_responses_replace_the_mock_implementation() {
+        let ribosome = MockRibosome::new();
+        assert!(api::random_bytes(2).is_err());
+
+        ribosome.respond_to("random", |_| Ok(JsonString::from(vec![1u8, 2])));
+        assert_eq!(api::random_bytes(2), Ok(vec![1, 2]));
+
+        ribosome.respond_to("call", |input| Ok(input));
+        assert!(api::call("summer", "main", "sum", JsonString::from("{}")).is_ok());
+    }
+
+    #[test]
+    fn bridges_are_not_present_unless_programmed() {
+        let ribosome = MockRibosome::new();
+        let search =
+            || api::call_bridge("search", "index", "main", "query", JsonString::from("{}"));
+        assert_eq!(
+            search(),
+            Err(ZomeApiError::BridgeNotPresent(String::from("search")))
+        );
+
+        ribosome.respond_to("call_bridge", |_| Ok(JsonString::from("[]")));
+        assert_eq!(search(), Ok(JsonString::from("[]")));
+    }
+
+    #[test]
+    fn genesis_params_can_be_set() {
+        let ribosome = MockRibosome::new();
+        assert_eq!(api::genesis_params(), Ok(JsonString::from("{}")));
+
+        ribosome
+            .set_genesis_params(serde_json::from_str("{\"progenitor\":\"QmProgenitor\"}").unwrap());
+        assert_eq!(
+            api::genesis_params(),
+            Ok(JsonString::from("{\"progenitor\":\"QmProgenitor\"}"))
+        );
+    }
+}
@@ -13,14 +13,25 @@ extern crate holochain_wasm_utils;
 #[macro_use]
 extern crate holochain_core_types_derive;
 
-use hdk::error::{ZomeApiError, ZomeApiResult};
-use holochain_container_api::*;
+use hdk::{
+    error::{ZomeApiError, ZomeApiResult},
+    CommitResult,
+};
+use holochain_container_api::{
+    interface::DispatchRpc,
+    scenario::{Scenario, ScenarioAgent},
+    *,
+};
 use holochain_core_types::{
     cas::content::{Address, AddressableContent},
     crud_status::CrudStatus,
-    dna::zome::{
-        capabilities::{Capability, FnDeclaration, Membrane},
-        entry_types::{EntryTypeDef, LinksTo},
+    dna::{
+        zome::{
+            capabilities::{Capability, FnDeclaration, FnParameter, Membrane},
+            entry_types::{EntryTypeDef, LinksTo},
+            host_permissions::HostPermission,
+        },
+        Dna,
     },
     entry::{
         entry_type::{test_app_entry_type, AppEntryType, EntryType},
@@ -31,7 +42,9 @@ use holochain_core_types::{
     json::JsonString,
 };
 use holochain_wasm_utils::api_serialization::{
-    get_entry::EntryHistory, get_links::GetLinksResult, QueryResult,
+    get_entry::{EntryHistory, GetResultStatus},
+    get_links::GetLinksResult,
+    CallInitData, ChainInfo, QueryResult,
 };
 use std::sync::{Arc, Mutex};
 use test_utils::*;
@@ -72,6 +85,10 @@ pub fn zome_setup(_: u32) -> u32 {
 pub fn __list_capabilities(_: u32) -> u32 {
     0
 }
+#[no_mangle]
+pub fn __list_host_permissions(_: u32) -> u32 {
+    0
+}
 
 pub fn create_test_cap_with_fn_names(fn_names: Vec<&str>) -> Capability {
     let mut capability = Capability::new();
@@ -104,8 +121,10 @@ fn example_valid_entry_history() -> EntryHistory {
     let entry = example_valid_entry();
     let mut entry_history = EntryHistory::new();
     entry_history.addresses.push(entry.address());
+    entry_history.resolved_address = Some(entry.address());
     entry_history.entries.push(entry);
     entry_history.crud_status.push(CrudStatus::LIVE);
+    entry_history.status = GetResultStatus::Found;
     entry_history
 }
 
@@ -120,14 +139,14 @@ fn example_valid_entry_address() -> Address {
     Address::from("QmefcRdCAXM2kbgLW2pMzqWhUvKSDvwfFSVkvmwKvBQBHd")
 }
 
-fn start_holochain_instance<T: Into<String>>(uuid: T) -> (Holochain, Arc<Mutex<TestLogger>>) {
-    // Setup the holochain instance
+fn test_dna<T: Into<String>>(uuid: T) -> Dna {
     let wasm =
         create_wasm_from_file("wasm-test/target/wasm32-unknown-unknown/release/test_globals.wasm");
-    let capabability = create_test_cap_with_fn_names(vec![
+    let mut capabability = create_test_cap_with_fn_names(vec![
         "check_global",
         "check_commit_entry",
         "check_commit_entry_macro",
+        "check_commit_entry_header",
         "check_get_entry_result",
         "check_get_entry",
         "send_tweet",
@@ -136,14 +155,34 @@ fn start_holochain_instance<T: Into<String>>(uuid: T) -> (Holochain, Arc<Mutex<T
         "links_roundtrip",
         "link_validation",
         "check_query",
+        "check_chain_info",
+        "check_random",
+        "check_entry_types",
         "check_app_entry_address",
         "check_sys_entry_address",
+        "check_get_all_agents",
+        "check_call_init_data",
         "check_call",
         "check_call_with_args",
+        "check_call_with_param_shapes",
         "update_entry_ok",
         "remove_entry_ok",
         "remove_modified_entry_ok",
+        "check_versioned_entry",
+        "create_post",
+        "comment_on",
+        "get_comments",
+        "encrypt_message",
+        "decrypt_message",
     ]);
+    // Declare the inputs of the functions that get called with positional parameters
+    for fn_declaration in capabability.functions.iter_mut() {
+        if fn_declaration.name == "check_get_entry" {
+            fn_declaration
+                .inputs
+                .push(FnParameter::new("entry_address", "Address"));
+        }
+    }
     let mut dna = create_test_dna_with_cap("test_zome", "test_cap", &capabability, &wasm);
     dna.uuid = uuid.into();
 
@@ -167,6 +206,10 @@ fn start_holochain_instance<T: Into<String>>(uuid: T) -> (Holochain, Arc<Mutex<T
             target_type: String::from("testEntryType"),
             tag: String::from("test-tag"),
         });
+        test_entry_type.links_to.push(LinksTo {
+            target_type: String::from("testEntryType"),
+            tag: String::from("comments"),
+        });
     }
 
     {
@@ -177,8 +220,15 @@ fn start_holochain_instance<T: Into<String>>(uuid: T) -> (Holochain, Arc<Mutex<T
             tag: String::from("longer"),
         });
         entry_types.insert(EntryType::from("link_validator"), link_validator);
+        entry_types.insert(EntryType::from("versioned_note"), EntryTypeDef::new());
     }
 
+    dna
+}
+
+fn start_holochain_instance<T: Into<String>>(uuid: T) -> (Holochain, Arc<Mutex<TestLogger>>) {
+    // Setup the holochain instance
+    let dna = test_dna(uuid);
     let (context, test_logger) = test_context_and_logger("alex");
     let mut hc =
         Holochain::new(dna.clone(), context).expect("could not create new Holochain instance.");
@@ -203,6 +253,56 @@ fn can_use_globals() {
     );
 }
 
+#[test]
+fn genesis_gets_the_genesis_params_of_the_instance() {
+    let dna = test_dna("genesis_gets_the_genesis_params_of_the_instance");
+    let (context, _) = test_context_and_logger("alex");
+    let mut context = (*context).clone();
+    context.set_genesis_params(json!({"progenitor": "QmProgenitor"}));
+    let mut hc =
+        Holochain::new(dna, Arc::new(context)).expect("could not create new Holochain instance.");
+    hc.start().expect("couldn't start");
+
+    let result = hc.call("test_zome", "test_cap", "check_call_init_data", r#"{}"#);
+    assert!(result.is_ok(), "result = {:?}", result);
+    let result: serde_json::Value = serde_json::from_str(&String::from(result.unwrap())).unwrap();
+    let call_init_data: CallInitData = serde_json::from_value(result["Ok"].clone()).unwrap();
+    assert_eq!(call_init_data.genesis_params, json!({"progenitor": "QmProgenitor"}));
+    // DNA, agent, its link and the entry genesis committed
+    assert_eq!(call_init_data.chain_length, 4);
+
+    let progenitor_entry = Entry::App(
+        AppEntryType::from(test_app_entry_type()),
+        AppEntryValue::from(EntryStruct {
+            stuff: "progenitor QmProgenitor".into(),
+        }),
+    );
+    let result = hc.call(
+        "test_zome",
+        "test_cap",
+        "check_get_entry",
+        &String::from(JsonString::from(json!({
+            "entry_address": progenitor_entry.address()
+        }))),
+    );
+    let expected: ZomeApiResult<Option<Entry>> = Ok(Some(progenitor_entry));
+    assert_eq!(result, Ok(JsonString::from(expected)));
+}
+
+#[test]
+fn can_use_globals_in_genesis() {
+    let (_hc, test_logger) = start_holochain_instance("can_use_globals_in_genesis");
+    let test_logger = test_logger.lock().unwrap();
+    assert!(
+        test_logger
+            .log
+            .iter()
+            .any(|line| line.contains("genesis agent address: alex---")),
+        "log = {:?}",
+        test_logger.log
+    );
+}
+
 #[test]
 fn can_commit_entry() {
     let (mut hc, _) = start_holochain_instance("can_commit_entry");
@@ -221,6 +321,34 @@ fn can_commit_entry() {
         JsonString::from(example_valid_entry_address()),
     );
 }
+#[test]
+fn can_not_commit_entry_without_chain_write_permission() {
+    let mut dna = test_dna("can_not_commit_entry_without_chain_write_permission");
+    dna.zomes.get_mut("test_zome").unwrap().host_permissions =
+        Some(vec![HostPermission::ChainRead].into_iter().collect());
+    let (context, _) = test_context_and_logger("alex");
+    let mut hc = Holochain::new(dna, context).expect("could not create new Holochain instance.");
+    hc.start().expect("couldn't start");
+
+    let result = hc.call(
+        "test_zome",
+        "test_cap",
+        "check_commit_entry",
+        &String::from(JsonString::from(example_valid_entry())),
+    );
+    match result {
+        Err(holochain_container_api::error::HolochainInstanceError::InternalFailure(error)) => {
+            match error.root() {
+                HolochainError::PermissionDenied(message) => {
+                    assert!(message.contains("hc_commit_entry"), "message = {}", message)
+                }
+                other => panic!("expected PermissionDenied, got {:?}", other),
+            }
+        }
+        other => panic!("expected PermissionDenied, got {:?}", other),
+    }
+}
+
 #[test]
 fn can_commit_entry_macro() {
     let (mut hc, _) = start_holochain_instance("can_commit_entry_macro");
@@ -239,6 +367,59 @@ fn can_commit_entry_macro() {
     assert_eq!(result.unwrap(), JsonString::from(expected),);
 }
 
+#[test]
+fn can_commit_entry_with_header_address() {
+    let (mut hc, _) = start_holochain_instance("can_commit_entry_with_header_address");
+    let result = hc.call(
+        "test_zome",
+        "test_cap",
+        "check_commit_entry_header",
+        &example_valid_entry_params(),
+    );
+    assert!(result.is_ok(), "\t result = {:?}", result);
+    let commit_result: ZomeApiResult<CommitResult> =
+        serde_json::from_str(&String::from(result.unwrap())).unwrap();
+    let commit_result = commit_result.expect("commit should succeed");
+    assert_eq!(commit_result.entry_address, example_valid_entry_address());
+
+    // The returned header address must resolve to the header of the committed entry
+    let chain_header = hc
+        .state()
+        .unwrap()
+        .agent()
+        .chain()
+        .get_header(&commit_result.header_address)
+        .expect("could not fetch header")
+        .expect("committed header should be in the chain");
+    assert_eq!(
+        chain_header.entry_address(),
+        &example_valid_entry_address()
+    );
+}
+
+#[test]
+fn can_get_sys_entry_address() {
+    let (mut hc, _) = start_holochain_instance("can_get_sys_entry_address");
+    let result = hc.call("test_zome", "test_cap", "check_sys_entry_address", r#"{}"#);
+    assert!(result.is_ok(), "result = {:?}", result);
+    let expected: ZomeApiResult<Address> = Ok(Address::from(
+        "alex--------------------------------------------------------------------------------ADO_",
+    ));
+    assert_eq!(result.unwrap(), JsonString::from(expected));
+}
+
+#[test]
+fn can_get_all_agents() {
+    let (mut hc, _) = start_holochain_instance("can_get_all_agents");
+    let result = hc.call("test_zome", "test_cap", "check_get_all_agents", r#"{}"#);
+    assert!(result.is_ok(), "result = {:?}", result);
+    // Without peers, the directory only holds our own agent
+    let expected: ZomeApiResult<Vec<Address>> = Ok(vec![Address::from(
+        "alex--------------------------------------------------------------------------------ADO_",
+    )]);
+    assert_eq!(result.unwrap(), JsonString::from(expected));
+}
+
 #[test]
 fn can_round_trip() {
     let (mut hc, test_logger) = start_holochain_instance("can_round_trip");
@@ -496,6 +677,127 @@ fn can_check_query() {
     assert_eq!(result.unwrap(), JsonString::from(expected),);
 }
 
+#[test]
+fn can_check_chain_info() {
+    let (mut hc, _) = start_holochain_instance("can_check_chain_info");
+
+    let result = hc.call("test_zome", "test_cap", "check_chain_info", r#"{}"#);
+    assert!(result.is_ok(), "result = {:?}", result);
+
+    let result: serde_json::Value = serde_json::from_str(&String::from(result.unwrap())).unwrap();
+    let chain_info: ChainInfo = serde_json::from_value(result["Ok"].clone()).unwrap();
+    // genesis commits the DNA, the agent ID and its agent directory link,
+    // the zome function three test entries
+    assert_eq!(chain_info.length, 6);
+    assert_eq!(chain_info.count("testEntryType"), 3);
+    assert_eq!(chain_info.count("%*"), 3);
+    assert!(chain_info.top_header_address.is_some());
+}
+
+#[test]
+fn can_query_entry_types() {
+    let (mut hc, _) = start_holochain_instance("can_query_entry_types");
+
+    let result = hc.call("test_zome", "test_cap", "check_entry_types", r#"{}"#);
+    assert!(result.is_ok(), "result = {:?}", result);
+
+    let result: serde_json::Value = serde_json::from_str(&String::from(result.unwrap())).unwrap();
+    let entry_types = result["Ok"].as_array().expect("entry types");
+    let names: Vec<&str> = entry_types
+        .iter()
+        .map(|entry_type| entry_type["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "link_validator",
+            "testEntryType",
+            "validation_package_tester",
+            "versioned_note",
+        ]
+    );
+    // the link declarations of test_dna() make it into the zome
+    let test_entry_type = &entry_types[1];
+    assert_eq!(test_entry_type["zome_name"], "test_zome");
+    assert_eq!(test_entry_type["sharing"], "public");
+    assert_eq!(
+        test_entry_type["links_to"],
+        json!([
+            {"target_type": "testEntryType", "tag": "test-tag"},
+            {"target_type": "testEntryType", "tag": "comments"},
+        ])
+    );
+    assert_eq!(entry_types[0]["links_to"][0]["tag"], "longer");
+}
+
+#[test]
+fn can_get_chain_top_per_call() {
+    let (mut hc, _) = start_holochain_instance("can_get_chain_top_per_call");
+
+    let call_init_data = |hc: &mut Holochain| -> CallInitData {
+        let result = hc.call("test_zome", "test_cap", "check_call_init_data", r#"{}"#);
+        assert!(result.is_ok(), "result = {:?}", result);
+        let result: serde_json::Value =
+            serde_json::from_str(&String::from(result.unwrap())).unwrap();
+        serde_json::from_value(result["Ok"].clone()).unwrap()
+    };
+
+    let before = call_init_data(&mut hc);
+    assert_eq!(before.chain_length, 3);
+    assert!(before.chain_top.is_some());
+
+    let result = hc.call(
+        "test_zome",
+        "test_cap",
+        "check_commit_entry",
+        &String::from(JsonString::from(example_valid_entry())),
+    );
+    assert!(result.is_ok(), "result = {:?}", result);
+
+    let after = call_init_data(&mut hc);
+    assert_eq!(after.chain_length, before.chain_length + 1);
+    assert_ne!(after.chain_top, before.chain_top);
+    assert!(after.call_time >= before.call_time);
+}
+
+#[test]
+fn can_migrate_versioned_entries() {
+    let (mut hc, _) = start_holochain_instance("can_migrate_versioned_entries");
+
+    let result = hc.call("test_zome", "test_cap", "check_versioned_entry", r#"{}"#);
+    assert!(result.is_ok(), "result = {:?}", result);
+
+    let result: serde_json::Value = serde_json::from_str(&String::from(result.unwrap())).unwrap();
+    let check = &result["Ok"];
+    // the version 1 entry reads as version 2
+    assert_eq!(check["migrated"]["title"], "Groceries", "result = {}", result);
+    assert_eq!(check["migrated"]["body"], "milk, eggs");
+    // but it is still stored as it was committed
+    assert_eq!(
+        check["stored"]["App"][1],
+        r#"{"schema_version":1,"content":{"text":"Groceries\nmilk, eggs"}}"#
+    );
+    assert_eq!(check["round_trip"]["title"], "Todo");
+    assert_eq!(check["round_trip"]["body"], "call mom");
+}
+
+#[test]
+fn can_get_random_bytes() {
+    let (mut hc, _) = start_holochain_instance("can_get_random_bytes");
+
+    let first = hc.call("test_zome", "test_cap", "check_random", r#"{}"#);
+    assert!(first.is_ok(), "result = {:?}", first);
+    let first: serde_json::Value = serde_json::from_str(&String::from(first.unwrap())).unwrap();
+    let bytes: Vec<u8> = serde_json::from_value(first["Ok"].clone()).unwrap();
+    assert_eq!(bytes.len(), 16);
+
+    let second = hc
+        .call("test_zome", "test_cap", "check_random", r#"{}"#)
+        .unwrap();
+    let second: serde_json::Value = serde_json::from_str(&String::from(second)).unwrap();
+    assert_ne!(first["Ok"], second["Ok"]);
+}
+
 #[test]
 fn can_check_app_entry_address() {
     let (mut hc, _) = start_holochain_instance("can_check_app_entry_address");
@@ -560,6 +862,64 @@ fn can_check_call_with_args() {
     assert_eq!(result.unwrap(), JsonString::from(expected),);
 }
 
+#[test]
+fn can_call_with_positional_and_single_value_params() {
+    let (mut hc, _) = start_holochain_instance("can_call_with_positional_and_single_value_params");
+    let result = hc.call(
+        "test_zome",
+        "test_cap",
+        "check_commit_entry_macro",
+        &example_valid_entry_params(),
+    );
+    assert!(result.is_ok(), "\t result = {:?}", result);
+
+    let address = String::from(JsonString::from(json!(example_valid_entry_address())));
+    let named_params = String::from(JsonString::from(json!({
+        "entry_address": example_valid_entry_address()
+    })));
+    let named_result = hc
+        .call("test_zome", "test_cap", "check_get_entry", &named_params)
+        .unwrap();
+    let expected: ZomeApiResult<Entry> = Ok(example_valid_entry());
+    assert_eq!(named_result, JsonString::from(expected));
+
+    // Over the container, as an array in the declared order and as the only parameter
+    for params in vec![format!("[{}]", address), address.clone()] {
+        let result = hc.call("test_zome", "test_cap", "check_get_entry", &params);
+        assert!(result.is_ok(), "\t params = {}, result = {:?}", params, result);
+        assert_eq!(result.unwrap(), named_result);
+    }
+
+    let result = hc.call(
+        "test_zome",
+        "test_cap",
+        "check_get_entry",
+        &format!("[{},{}]", address, address),
+    );
+    assert!(
+        format!("{:?}", result)
+            .contains("Zome function check_get_entry expects 1 parameters, got 2"),
+        "\t result = {:?}",
+        result
+    );
+
+    // Between zome functions, through hdk::call
+    let result = hc.call(
+        "test_zome",
+        "test_cap",
+        "check_call_with_param_shapes",
+        &named_params,
+    );
+    assert!(result.is_ok(), "\t result = {:?}", result);
+    let result: serde_json::Value =
+        serde_json::from_str(&String::from(result.unwrap())).unwrap();
+    let results = result["Ok"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert!(results[0].as_str().unwrap().contains("non fail"));
+    assert_eq!(results[1], results[0]);
+    assert_eq!(results[2], results[0]);
+}
+
 #[test]
 fn can_remove_entry() {
     let (mut hc, _) = start_holochain_instance("can_remove_entry");
@@ -567,7 +927,7 @@ fn can_remove_entry() {
     assert!(result.is_ok(), "result = {:?}", result);
     assert_eq!(
         result.unwrap(),
-        JsonString::from("{\"addresses\":[\"QmefcRdCAXM2kbgLW2pMzqWhUvKSDvwfFSVkvmwKvBQBHd\"],\"entries\":[{\"App\":[\"testEntryType\",\"{\\\"stuff\\\":\\\"non fail\\\"}\"]}],\"crud_status\":[{\"bits\":4}],\"crud_links\":{\"QmefcRdCAXM2kbgLW2pMzqWhUvKSDvwfFSVkvmwKvBQBHd\":\"QmUhD35RLLvDJ7dGsonTTiHUirckQSbf7ceDC1xWVTrHk6\"}}"
+        JsonString::from("{\"addresses\":[\"QmefcRdCAXM2kbgLW2pMzqWhUvKSDvwfFSVkvmwKvBQBHd\"],\"entries\":[{\"App\":[\"testEntryType\",\"{\\\"stuff\\\":\\\"non fail\\\"}\"]}],\"crud_status\":[{\"bits\":4}],\"crud_links\":{\"QmefcRdCAXM2kbgLW2pMzqWhUvKSDvwfFSVkvmwKvBQBHd\":\"QmUhD35RLLvDJ7dGsonTTiHUirckQSbf7ceDC1xWVTrHk6\"},\"status\":\"Deleted\",\"resolved_address\":\"QmefcRdCAXM2kbgLW2pMzqWhUvKSDvwfFSVkvmwKvBQBHd\"}"
         ),
     );
 }
@@ -585,3 +945,158 @@ fn can_remove_modified_entry() {
     let result = hc.call("test_zome", "test_cap", "remove_modified_entry_ok", r#"{}"#);
     assert!(result.is_ok(), "result = {:?}", result);
 }
+
+#[test]
+#[cfg(not(windows))]
+fn alice_comments_on_bobs_post() {
+    let scenario = Scenario::two_agents(test_dna("alice_comments_on_bobs_post")).unwrap();
+
+    let result = scenario.bob.call(
+        "test_zome",
+        "test_cap",
+        "create_post",
+        r#"{"content": "Holochain is a framework for distributed apps"}"#,
+    );
+    assert!(result.is_ok(), "result = {:?}", result);
+    let post: ZomeApiResult<Address> =
+        serde_json::from_str(&String::from(result.unwrap())).unwrap();
+    let post = post.unwrap();
+    assert_eq!(scenario.consistency(), Ok(()));
+
+    let result = scenario.alice.call(
+        "test_zome",
+        "test_cap",
+        "check_get_entry",
+        &json!({ "entry_address": post }).to_string(),
+    );
+    assert!(result.is_ok(), "result = {:?}", result);
+    assert!(
+        String::from(result.unwrap()).contains("Holochain is a framework"),
+        "alice should see bob's post"
+    );
+
+    let result = scenario.alice.call(
+        "test_zome",
+        "test_cap",
+        "comment_on",
+        &json!({ "post": post, "content": "Which apps?" }).to_string(),
+    );
+    assert!(result.is_ok(), "result = {:?}", result);
+    let comment: ZomeApiResult<Address> =
+        serde_json::from_str(&String::from(result.unwrap())).unwrap();
+    let comment = comment.unwrap();
+    assert_eq!(scenario.consistency(), Ok(()));
+
+    let result = scenario.bob.call(
+        "test_zome",
+        "test_cap",
+        "get_comments",
+        &json!({ "post": post }).to_string(),
+    );
+    let expected: ZomeApiResult<GetLinksResult> = Ok(GetLinksResult::new(vec![comment.clone()]));
+    assert_eq!(result, Ok(JsonString::from(expected)));
+
+    let result = scenario.bob.call(
+        "test_zome",
+        "test_cap",
+        "check_get_entry",
+        &json!({ "entry_address": comment }).to_string(),
+    );
+    assert!(result.is_ok(), "result = {:?}", result);
+    assert!(
+        String::from(result.unwrap()).contains("Which apps?"),
+        "bob should see alice's comment"
+    );
+}
+
+#[test]
+#[cfg(not(windows))]
+fn alice_sees_bobs_post_after_consistency_over_the_interface() {
+    let scenario = Scenario::two_agents(test_dna("consistency_over_the_interface")).unwrap();
+    let handler = interface::ContainerApiDispatcher::new(
+        &config::Configuration::default(),
+        scenario.container.instances.clone(),
+        &[],
+        "test-interface",
+    )
+    .with_test_api()
+    .handler();
+
+    let result = scenario.bob.call(
+        "test_zome",
+        "test_cap",
+        "create_post",
+        r#"{"content": "Holochain is a framework for distributed apps"}"#,
+    );
+    assert!(result.is_ok(), "result = {:?}", result);
+    let post: ZomeApiResult<Address> =
+        serde_json::from_str(&String::from(result.unwrap())).unwrap();
+    let post = post.unwrap();
+
+    let response = handler
+        .handle_request_sync(
+            r#"{"jsonrpc":"2.0","id":1,"method":"test/consistency","params":{"instance_ids":["alice","bob"]}}"#,
+        )
+        .unwrap();
+    let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+    assert_eq!(response["result"], true, "response = {}", response);
+
+    let result = scenario.alice.call(
+        "test_zome",
+        "test_cap",
+        "check_get_entry",
+        &json!({ "entry_address": post }).to_string(),
+    );
+    assert!(result.is_ok(), "result = {:?}", result);
+    assert!(
+        String::from(result.unwrap()).contains("Holochain is a framework"),
+        "alice should see bob's post"
+    );
+}
+
+#[test]
+fn bob_encrypts_a_message_only_alice_can_read() {
+    let scenario =
+        Scenario::two_agents(test_dna("bob_encrypts_a_message_only_alice_can_read")).unwrap();
+    let alice = scenario
+        .alice
+        .state()
+        .unwrap()
+        .agent()
+        .get_agent_address()
+        .unwrap();
+
+    let result = scenario.bob.call(
+        "test_zome",
+        "test_cap",
+        "encrypt_message",
+        &json!({ "recipient": alice, "message": "meet me at noon" }).to_string(),
+    );
+    assert!(result.is_ok(), "result = {:?}", result);
+    let encrypted: ZomeApiResult<Vec<u8>> =
+        serde_json::from_str(&String::from(result.unwrap())).unwrap();
+    let encrypted = encrypted.unwrap();
+
+    let decrypt = |agent: &ScenarioAgent| -> ZomeApiResult<String> {
+        let result = agent.call(
+            "test_zome",
+            "test_cap",
+            "decrypt_message",
+            &json!({ "payload": encrypted }).to_string(),
+        );
+        assert!(result.is_ok(), "result = {:?}", result);
+        serde_json::from_str(&String::from(result.unwrap())).unwrap()
+    };
+    assert_eq!(
+        decrypt(&scenario.alice),
+        Ok(String::from("meet me at noon"))
+    );
+    match decrypt(&scenario.bob) {
+        Err(ZomeApiError::Internal(error)) => assert!(
+            error.contains("not encrypted for this agent"),
+            "error = {}",
+            error
+        ),
+        other => panic!("bob should not be able to decrypt, got {:?}", other),
+    }
+}
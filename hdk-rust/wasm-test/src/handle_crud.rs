@@ -1,6 +1,6 @@
 use holochain_wasm_utils::{
     api_serialization::{
-        get_entry::GetEntryOptions,
+        get_entry::{GetEntryOptions, StatusRequestKind},
     },
     holochain_core_types::{
         entry::Entry,
@@ -16,7 +16,7 @@ use TestEntryType;
 pub(crate) fn handle_update_entry_ok() -> JsonString {
     // Commit v1 entry
     hdk::debug("**** Commit v1 entry").ok();
-    let res = hdk::commit_entry(&hdk_test_entry());
+    let res = hdk::commit_entry_address(&hdk_test_entry());
     let addr_v1 = res.unwrap();
     // get it
     hdk::debug("**** Get it").ok();
@@ -67,6 +67,18 @@ pub(crate) fn handle_update_entry_ok() -> JsonString {
     let res = hdk::get_entry(addr_v2.clone());
     let entry_res = res.unwrap().unwrap();
     assert_eq!(entry_res, entry_v3.clone());
+    // v1 got updated twice: latest and initial from v1 differ
+    hdk::debug("**** get resolved address from v1").ok();
+    let latest = hdk::get_entry(addr_v1.clone()).unwrap().unwrap();
+    let initial = hdk::get_entry_initial(addr_v1.clone()).unwrap().unwrap();
+    assert_ne!(latest, initial);
+    let res = hdk::get_entry_result(addr_v1.clone(), GetEntryOptions::default());
+    assert_eq!(res.unwrap().resolved_address, Some(addr_v3.clone()));
+    let res = hdk::get_entry_result(
+        addr_v1.clone(),
+        GetEntryOptions::new(StatusRequestKind::Initial),
+    );
+    assert_eq!(res.unwrap().resolved_address, Some(addr_v1.clone()));
 
     // update it again from v3
     let entry_v4 = Entry::App(
@@ -153,7 +165,7 @@ pub fn handle_remove_entry_ok() -> JsonString {
     // Commit v1 entry
     hdk::debug("**** Commit v1 entry").ok();
     let entry_v1 = hdk_test_entry();
-    let res = hdk::commit_entry(&entry_v1);
+    let res = hdk::commit_entry_address(&entry_v1);
     let addr_v1 = res.unwrap();
     // Get it
     hdk::debug("**** Get it").ok();
@@ -188,7 +200,7 @@ pub fn handle_remove_modified_entry_ok() -> JsonString {
     // Commit entry v1
     hdk::debug("**** commit v1 entry").ok();
     let entry_v1 = hdk_test_entry();
-    let res = hdk::commit_entry(&entry_v1);
+    let res = hdk::commit_entry_address(&entry_v1);
     let addr_v1 = res.unwrap();
     // Get it
     hdk::debug("**** get it").ok();
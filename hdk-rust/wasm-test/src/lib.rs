@@ -16,15 +16,18 @@ use boolinator::Boolinator;
 use hdk::{
     error::ZomeApiError,
     error::ZomeApiResult,
-    globals::G_MEM_STACK,
+    global_fns::{init_global_memory, store_and_return_output},
+    CommitResult,
 };
 use holochain_wasm_utils::{
     api_serialization::{
         get_entry::{GetEntryOptions, EntryHistory},
         get_links::GetLinksResult,
+        CallInitData, ChainInfo, EntryTypeDef,
     },
     holochain_core_types::dna::zome::entry_types::Sharing,
     holochain_core_types::{
+        agent::AgentId,
         cas::content::{Address, AddressableContent},
         entry::{Entry, entry_type::EntryType},
         error::{HolochainError, RibosomeErrorCode},
@@ -32,7 +35,6 @@ use holochain_wasm_utils::{
         entry::AppEntryValue,
         entry::entry_type::AppEntryType,
     },
-    memory_allocation::*,
     memory_serialization::*,
 };
 use std::convert::TryFrom;
@@ -62,10 +64,7 @@ pub extern "C" fn handle_check_global() -> Address {
 
 #[no_mangle]
 pub extern "C" fn check_commit_entry(encoded_allocation_of_input: u32) -> u32 {
-    unsafe {
-        G_MEM_STACK =
-            Some(SinglePageStack::from_encoded_allocation(encoded_allocation_of_input).unwrap());
-    }
+    init_global_memory(encoded_allocation_of_input);
 
     // Deserialize and check for an encoded error
     let result = load_json(encoded_allocation_of_input as u32);
@@ -76,19 +75,21 @@ pub extern "C" fn check_commit_entry(encoded_allocation_of_input: u32) -> u32 {
 
     let entry: Entry = result.unwrap();
     hdk::debug(format!("Entry: {:?}", entry)).expect("debug() must work");
-    let res = hdk::commit_entry(&entry.into());
+    let res = hdk::commit_entry_address(&entry.into());
 
     let res_obj: JsonString = match res {
         Ok(hash) => hash.into(),
         Err(e) => e.into(),
     };
 
-    unsafe {
-        return store_as_json_into_encoded_allocation(&mut G_MEM_STACK.unwrap(), res_obj) as u32;
-    }
+    store_and_return_output(res_obj)
 }
 
 fn handle_check_commit_entry_macro(entry: Entry) -> ZomeApiResult<Address> {
+    hdk::commit_entry_address(&entry)
+}
+
+fn handle_check_commit_entry_header(entry: Entry) -> ZomeApiResult<CommitResult> {
     hdk::commit_entry(&entry)
 }
 
@@ -101,7 +102,7 @@ fn handle_check_get_entry(entry_address: Address) -> ZomeApiResult<Option<Entry>
 }
 
 fn handle_commit_validation_package_tester() -> ZomeApiResult<Address> {
-    hdk::commit_entry(&Entry::App(
+    hdk::commit_entry_address(&Entry::App(
         "validation_package_tester".into(),
         JsonString::from(RawString::from("test")),
     ))
@@ -114,7 +115,7 @@ fn handle_link_two_entries() -> ZomeApiResult<()> {
             stuff: "entry1".into(),
         }.into(),
     );
-    hdk::commit_entry(&entry_1)?;
+    hdk::commit_entry_address(&entry_1)?;
 
     let entry_2 = Entry::App(
         "testEntryType".into(),
@@ -123,7 +124,7 @@ fn handle_link_two_entries() -> ZomeApiResult<()> {
         }.into(),
     );
 
-    hdk::commit_entry(&entry_2)?;
+    hdk::commit_entry_address(&entry_2)?;
 
     hdk::link_entries(&entry_1.address(), &entry_2.address(), "test-tag")
 }
@@ -135,7 +136,7 @@ fn handle_links_roundtrip() -> ZomeApiResult<GetLinksResult> {
             stuff: "entry1".into(),
         }.into(),
     );
-    hdk::commit_entry(&entry_1)?;
+    hdk::commit_entry_address(&entry_1)?;
 
     let entry_2 = Entry::App(
         "testEntryType".into(),
@@ -143,7 +144,7 @@ fn handle_links_roundtrip() -> ZomeApiResult<GetLinksResult> {
             stuff: "entry2".into(),
         }.into(),
     );
-    hdk::commit_entry(&entry_2)?;
+    hdk::commit_entry_address(&entry_2)?;
 
     let entry_3 = Entry::App(
         "testEntryType".into(),
@@ -151,7 +152,7 @@ fn handle_links_roundtrip() -> ZomeApiResult<GetLinksResult> {
             stuff: "entry3".into(),
         }.into(),
     );
-    hdk::commit_entry(&entry_3)?;
+    hdk::commit_entry_address(&entry_3)?;
 
     hdk::link_entries(&entry_1.address(), &entry_2.address(), "test-tag")?;
     hdk::link_entries(&entry_1.address(), &entry_3.address(), "test-tag")?;
@@ -159,6 +160,38 @@ fn handle_links_roundtrip() -> ZomeApiResult<GetLinksResult> {
     hdk::get_links(&entry_1.address(), "test-tag")
 }
 
+fn post_or_comment(content: String) -> Entry {
+    Entry::App(
+        "testEntryType".into(),
+        EntryStruct {
+            stuff: content,
+        }.into(),
+    )
+}
+
+fn handle_create_post(content: String) -> ZomeApiResult<Address> {
+    hdk::commit_entry_address(&post_or_comment(content))
+}
+
+fn handle_comment_on(post: Address, content: String) -> ZomeApiResult<Address> {
+    let comment = hdk::commit_entry_address(&post_or_comment(content))?;
+    hdk::link_entries(&post, &comment, "comments")?;
+    Ok(comment)
+}
+
+fn handle_get_comments(post: Address) -> ZomeApiResult<GetLinksResult> {
+    hdk::get_links(&post, "comments")
+}
+
+fn handle_encrypt_message(recipient: Address, message: String) -> ZomeApiResult<Vec<u8>> {
+    hdk::encrypt_for(recipient, message.as_bytes())
+}
+
+fn handle_decrypt_message(payload: Vec<u8>) -> ZomeApiResult<String> {
+    let message = hdk::decrypt(&payload)?;
+    String::from_utf8(message).map_err(|error| ZomeApiError::Internal(error.to_string()))
+}
+
 fn handle_check_query() -> ZomeApiResult<Vec<Address>> {
     fn err(s: &str) -> ZomeApiResult<Vec<Address>> {
         Err(ZomeApiError::Internal(s.to_owned()))
@@ -186,7 +219,7 @@ fn handle_check_query() -> ZomeApiResult<Vec<Address>> {
     }
 
     // Query Zome entry
-    let _ = hdk::commit_entry(&Entry::App(
+    let _ = hdk::commit_entry_address(&Entry::App(
         "testEntryType".into(),
         EntryStruct {
             stuff: "entry1".into(),
@@ -199,13 +232,13 @@ fn handle_check_query() -> ZomeApiResult<Vec<Address>> {
     }
 
     // Query Zome entries
-    let _ = hdk::commit_entry(&Entry::App(
+    let _ = hdk::commit_entry_address(&Entry::App(
         "testEntryType".into(),
         EntryStruct {
             stuff: "entry2".into(),
         }.into(),
     )).unwrap();
-    let _ = hdk::commit_entry(&Entry::App(
+    let _ = hdk::commit_entry_address(&Entry::App(
         "testEntryType".into(),
         EntryStruct {
             stuff: "entry3".into(),
@@ -221,6 +254,47 @@ fn handle_check_query() -> ZomeApiResult<Vec<Address>> {
     hdk::query("testEntryType", 0, 1)
 }
 
+fn handle_check_chain_info() -> ZomeApiResult<ChainInfo> {
+    for stuff in vec!["entry1", "entry2", "entry3"] {
+        hdk::commit_entry(&Entry::App(
+            "testEntryType".into(),
+            EntryStruct {
+                stuff: stuff.into(),
+            }.into(),
+        ))?;
+    }
+
+    if hdk::query_count("testEntryType")? != 3 {
+        return Err(ZomeApiError::Internal(
+            "testEntryType count not 3".to_owned(),
+        ));
+    }
+
+    hdk::chain_info()
+}
+
+fn handle_check_random() -> ZomeApiResult<Vec<u8>> {
+    if hdk::random_bytes(5000).is_ok() {
+        return Err(ZomeApiError::Internal(
+            "random_bytes returned more than 4096 bytes".to_owned(),
+        ));
+    }
+    hdk::random_u64()?;
+    hdk::random_bytes(16)
+}
+
+fn handle_check_entry_types() -> ZomeApiResult<Vec<EntryTypeDef>> {
+    if hdk::entry_type_properties("unknownEntryType").is_ok() {
+        return Err(ZomeApiError::Internal(
+            "entry_type_properties found an entry type the DNA does not have".to_owned(),
+        ));
+    }
+    hdk::entry_types()?
+        .into_iter()
+        .map(hdk::entry_type_properties)
+        .collect()
+}
+
 fn handle_check_app_entry_address() -> ZomeApiResult<Address> {
     // Setup
     let entry_value = AppEntryValue::from(TestEntryType {
@@ -229,7 +303,7 @@ fn handle_check_app_entry_address() -> ZomeApiResult<Address> {
     let entry_type = AppEntryType::from("testEntryType");
     let entry = Entry::App(entry_type, entry_value.clone());
 
-    let commit_result = hdk::commit_entry(&entry);
+    let commit_result = hdk::commit_entry_address(&entry);
     if commit_result.is_err() {
         return commit_result.into();
     }
@@ -253,10 +327,52 @@ fn handle_check_app_entry_address() -> ZomeApiResult<Address> {
     }
 }
 
-// fn handle_check_sys_entry_address() -> JsonString {
-//     // TODO
-//     json!({"result": "FIXME"}).into()
-// }
+fn handle_check_sys_entry_address() -> ZomeApiResult<Address> {
+    // DNA entry
+    let dna_entry_address = hdk::dna_entry_address()?;
+    let dna_addresses = hdk::query(&EntryType::Dna.to_string(), 0, 0)?;
+    if dna_addresses != vec![dna_entry_address.clone()] {
+        return Err(ZomeApiError::from(format!(
+            "dna entry address: {:?} committed: {:?}",
+            dna_entry_address, dna_addresses
+        )));
+    }
+
+    // AgentId entry
+    let agent_entry_address = hdk::agent_entry_address()?;
+    let agent_addresses = hdk::query(&EntryType::AgentId.to_string(), 0, 0)?;
+    if agent_addresses != vec![agent_entry_address.clone()] {
+        return Err(ZomeApiError::from(format!(
+            "agent entry address: {:?} committed: {:?}",
+            agent_entry_address, agent_addresses
+        )));
+    }
+
+    // entry_address() has to agree for system entries as well
+    let agent_id = AgentId::try_from(JsonString::from(hdk::AGENT_ID_STR.to_string()))?;
+    let computed_address = hdk::entry_address(&Entry::AgentId(agent_id))?;
+    if computed_address != agent_entry_address {
+        return Err(ZomeApiError::from(format!(
+            "agent entry address: {:?} computed: {:?}",
+            agent_entry_address, computed_address
+        )));
+    }
+
+    Ok(agent_entry_address)
+}
+
+fn handle_check_get_all_agents() -> ZomeApiResult<Vec<Address>> {
+    hdk::get_all_agents()
+}
+
+fn handle_check_call_init_data() -> ZomeApiResult<CallInitData> {
+    Ok(CallInitData {
+        chain_top: hdk::chain_top()?,
+        chain_length: hdk::chain_length()?,
+        call_time: hdk::call_time()?,
+        genesis_params: serde_json::from_str(&String::from(hdk::genesis_params()?))?,
+    })
+}
 
 fn handle_check_call() -> ZomeApiResult<JsonString> {
     let empty_dumpty = JsonString::empty_object();
@@ -289,6 +405,22 @@ fn handle_check_call_with_args() -> ZomeApiResult<JsonString> {
     )
 }
 
+/// Calls check_get_entry with the same address as named, positional and single value parameters
+fn handle_check_call_with_param_shapes(entry_address: Address) -> ZomeApiResult<Vec<String>> {
+    let address = format!("\"{}\"", entry_address);
+    vec![
+        format!("{{\"entry_address\":{}}}", address),
+        format!("[{}]", address),
+        address,
+    ]
+        .into_iter()
+        .map(|parameters| {
+            hdk::call("test_zome", "test_cap", "check_get_entry", JsonString::from(parameters))
+                .map(String::from)
+        })
+        .collect()
+}
+
 #[derive(Serialize, Deserialize, Debug, DefaultJson)]
 struct TweetResponse {
     first: String,
@@ -313,12 +445,49 @@ fn handle_link_validation(stuff1: String, stuff2: String) -> JsonString {
     let entry1 = Entry::App(app_entry_type.clone(), entry_value1.clone());
     let entry2 = Entry::App(app_entry_type.clone(), entry_value2.clone());
 
-    let _ = hdk::commit_entry(&entry1);
-    let _ = hdk::commit_entry(&entry2);
+    let _ = hdk::commit_entry_address(&entry1);
+    let _ = hdk::commit_entry_address(&entry2);
 
     JsonString::from(hdk::link_entries(&entry1.address(), &entry2.address(), "longer"))
 }
 
+/// Version 1 of "versioned_note" entries only had a `text`
+#[derive(Serialize, Deserialize, Debug, DefaultJson)]
+struct NoteV2 {
+    title: String,
+    body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, DefaultJson)]
+struct VersionedEntryCheck {
+    migrated: Option<NoteV2>,
+    stored: Option<Entry>,
+    round_trip: Option<NoteV2>,
+}
+
+fn handle_check_versioned_entry() -> ZomeApiResult<VersionedEntryCheck> {
+    // What commit_typed() stored while the entry type was at version 1
+    let v1_entry = Entry::App(
+        "versioned_note".into(),
+        JsonString::from("{\"schema_version\":1,\"content\":{\"text\":\"Groceries\\nmilk, eggs\"}}"),
+    );
+    let v1_address = hdk::commit_entry_address(&v1_entry)?;
+
+    let v2_address = hdk::commit_typed(
+        "versioned_note",
+        NoteV2 {
+            title: "Todo".into(),
+            body: "call mom".into(),
+        },
+    )?.entry_address;
+
+    Ok(VersionedEntryCheck {
+        migrated: hdk::get_typed(v1_address.clone())?,
+        stored: hdk::get_entry(v1_address)?,
+        round_trip: hdk::get_typed(v2_address)?,
+    })
+}
+
 fn hdk_test_app_entry_type() -> AppEntryType {
     AppEntryType::from("testEntryType")
 }
@@ -360,6 +529,16 @@ define_zome! {
                     validation: |source: Address, target: Address, ctx: hdk::ValidationData | {
                         Ok(())
                     }
+                ),
+                to!(
+                    "testEntryType",
+                    tag: "comments",
+                    validation_package: || {
+                        hdk::ValidationPackageDefinition::Entry
+                    },
+                    validation: |post: Address, comment: Address, ctx: hdk::ValidationData | {
+                        Ok(())
+                    }
                 )
             ]
         ),
@@ -423,10 +602,60 @@ define_zome! {
 
                 )
             ]
+        ),
+
+        entry!(
+            name: "versioned_note",
+            description: "a note that gained a title in version 2",
+            sharing: Sharing::Public,
+            native_type: NoteV2,
+            version: 2,
+
+            migrate_from: |_old_version: u32, old_content: JsonString| {
+                let old: serde_json::Value = serde_json::from_str(&String::from(old_content))
+                    .map_err(|e| e.to_string())?;
+                let text = old["text"].as_str().unwrap_or_default();
+                let mut lines = text.splitn(2, '\n');
+                Ok(NoteV2 {
+                    title: lines.next().unwrap_or_default().into(),
+                    body: lines.next().unwrap_or_default().into(),
+                }.into())
+            },
+
+            validation_package: || {
+                hdk::ValidationPackageDefinition::Entry
+            },
+
+            validation: |note: NoteV2, _ctx: hdk::ValidationData| {
+                (!note.title.is_empty())
+                    .ok_or_else(|| "A note needs a title".to_string())
+            }
         )
     ]
 
-    genesis: || { Ok(()) }
+    genesis: || {
+        {
+            // Globals have to be available to HDK functions during genesis already
+            let globals = hdk::app_globals().map_err(|e| e.to_string())?;
+            hdk::debug(format!("genesis agent address: {}", globals.agent_address))
+                .map_err(|e| e.to_string())?;
+            // Instances can hand genesis the address of a progenitor to refer to
+            let genesis_params: serde_json::Value =
+                serde_json::from_str(&String::from(hdk::genesis_params().map_err(|e| e.to_string())?))
+                    .map_err(|e| e.to_string())?;
+            if let Some(progenitor) = genesis_params["progenitor"].as_str() {
+                let entry = Entry::App(
+                    "testEntryType".into(),
+                    TestEntryType {
+                        stuff: format!("progenitor {}", progenitor),
+                    }
+                    .into(),
+                );
+                hdk::commit_entry(&entry).map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        }
+    }
 
     functions: {
         test (Public) {
@@ -442,6 +671,12 @@ define_zome! {
                 handler: handle_check_commit_entry_macro
             }
 
+            check_commit_entry_header: {
+                inputs: |entry: Entry|,
+                outputs: |result: ZomeApiResult<CommitResult>|,
+                handler: handle_check_commit_entry_header
+            }
+
             check_get_entry: {
                 inputs: |entry_address: Address|,
                 outputs: |result: ZomeApiResult<Option<Entry>>|,
@@ -472,6 +707,36 @@ define_zome! {
                 handler: handle_links_roundtrip
             }
 
+            create_post: {
+                inputs: |content: String|,
+                outputs: |result: ZomeApiResult<Address>|,
+                handler: handle_create_post
+            }
+
+            comment_on: {
+                inputs: |post: Address, content: String|,
+                outputs: |result: ZomeApiResult<Address>|,
+                handler: handle_comment_on
+            }
+
+            get_comments: {
+                inputs: |post: Address|,
+                outputs: |result: ZomeApiResult<GetLinksResult>|,
+                handler: handle_get_comments
+            }
+
+            encrypt_message: {
+                inputs: |recipient: Address, message: String|,
+                outputs: |result: ZomeApiResult<Vec<u8>>|,
+                handler: handle_encrypt_message
+            }
+
+            decrypt_message: {
+                inputs: |payload: Vec<u8>|,
+                outputs: |result: ZomeApiResult<String>|,
+                handler: handle_decrypt_message
+            }
+
             link_validation: {
                 inputs: |stuff1: String, stuff2: String|,
                 outputs: |result: JsonString|,
@@ -490,6 +755,12 @@ define_zome! {
                 handler: handle_check_call_with_args
             }
 
+            check_call_with_param_shapes: {
+                inputs: |entry_address: Address|,
+                outputs: |result: ZomeApiResult<Vec<String>>|,
+                handler: handle_check_call_with_param_shapes
+            }
+
             check_app_entry_address: {
                 inputs: | |,
                 outputs: |result: ZomeApiResult<Address>|,
@@ -502,11 +773,41 @@ define_zome! {
                 handler: handle_check_query
             }
 
-            // check_sys_entry_address: {
-            //     inputs: | |,
-            //     outputs: |result: JsonString|,
-            //     handler: handle_check_sys_entry_address
-            // }
+            check_chain_info: {
+                inputs: | |,
+                outputs: |result: ZomeApiResult<ChainInfo>|,
+                handler: handle_check_chain_info
+            }
+
+            check_random: {
+                inputs: | |,
+                outputs: |result: ZomeApiResult<Vec<u8>>|,
+                handler: handle_check_random
+            }
+
+            check_entry_types: {
+                inputs: | |,
+                outputs: |result: ZomeApiResult<Vec<EntryTypeDef>>|,
+                handler: handle_check_entry_types
+            }
+
+            check_sys_entry_address: {
+                inputs: | |,
+                outputs: |result: ZomeApiResult<Address>|,
+                handler: handle_check_sys_entry_address
+            }
+
+            check_get_all_agents: {
+                inputs: | |,
+                outputs: |agents: ZomeApiResult<Vec<Address>>|,
+                handler: handle_check_get_all_agents
+            }
+
+            check_call_init_data: {
+                inputs: | |,
+                outputs: |result: ZomeApiResult<CallInitData>|,
+                handler: handle_check_call_init_data
+            }
 
             update_entry_ok: {
                 inputs: | |,
@@ -526,6 +827,12 @@ define_zome! {
                 handler: handle_remove_modified_entry_ok
             }
 
+            check_versioned_entry: {
+                inputs: | |,
+                outputs: |result: ZomeApiResult<VersionedEntryCheck>|,
+                handler: handle_check_versioned_entry
+            }
+
             send_tweet: {
                 inputs: |author: String, content: String|,
                 outputs: |response: TweetResponse|,
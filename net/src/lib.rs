@@ -20,4 +20,5 @@ extern crate serde_json;
 pub mod error;
 pub mod ipc_net_worker;
 pub mod mock_worker;
+pub mod null_worker;
 pub mod p2p_network;
@@ -4,14 +4,14 @@ use holochain_net_connection::{
     net_connection::{NetHandler, NetWorker},
     protocol::Protocol,
     protocol_wrapper::{
-        DhtData, DhtMetaData, FailureResultData, GetDhtData, GetDhtMetaData, MessageData,
-        ProtocolWrapper,
+        DhtData, DhtMetaData, FailureResultData, GetDhtData, GetDhtMetaData, HoldAckData,
+        MessageData, PeerData, ProtocolWrapper,
     },
     NetResult,
 };
 
 use std::{
-    collections::{hash_map::Entry, HashMap},
+    collections::{hash_map::Entry, HashMap, HashSet},
     convert::TryFrom,
     sync::{mpsc, Mutex, MutexGuard},
 };
@@ -27,6 +27,8 @@ struct MockSingleton {
     senders: HashMap<String, mpsc::Sender<Protocol>>,
     // keep track of senders as arrays by dna_hash
     senders_by_dna: HashMap<String, Vec<mpsc::Sender<Protocol>>>,
+    // `dna_hash::agent_id` of the nodes whose transport got killed
+    killed: HashSet<String>,
 }
 
 impl MockSingleton {
@@ -35,16 +37,33 @@ impl MockSingleton {
         Self {
             senders: HashMap::new(),
             senders_by_dna: HashMap::new(),
+            killed: HashSet::new(),
         }
     }
 
     /// register a data handler with the singleton (for message routing)
+    /// the new node and the ones already connected on this dna get told about each other
     pub fn register(
         &mut self,
         dna_hash: &str,
         agent_id: &str,
         sender: mpsc::Sender<Protocol>,
     ) -> NetResult<()> {
+        self.check_alive(&[cat_dna_agent(dna_hash, agent_id)])?;
+        for peer in self.priv_agents(dna_hash) {
+            if peer == agent_id {
+                continue;
+            }
+            self.priv_send_one(
+                dna_hash,
+                &peer,
+                ProtocolWrapper::PeerConnected(PeerData {
+                    id: agent_id.to_string(),
+                })
+                .into(),
+            )?;
+            let _ = sender.send(ProtocolWrapper::PeerConnected(PeerData { id: peer }).into());
+        }
         self.senders
             .insert(cat_dna_agent(dna_hash, agent_id), sender.clone());
         match self.senders_by_dna.entry(dna_hash.to_string()) {
@@ -100,18 +119,59 @@ impl MockSingleton {
                 ProtocolWrapper::PublishDhtMeta(msg) => {
                     self.priv_handle_publish_dht_meta(&msg)?;
                 }
+                ProtocolWrapper::HoldAck(msg) => {
+                    self.priv_handle_hold_ack(&msg)?;
+                }
+                ProtocolWrapper::UnsupportedMessage(msg) => {
+                    self.priv_send_one(
+                        &msg.dna_hash,
+                        &msg.to_agent_id,
+                        ProtocolWrapper::UnsupportedMessage(msg.clone()).into(),
+                    )?;
+                }
+                ProtocolWrapper::RateLimited(msg) => {
+                    self.priv_send_one(
+                        &msg.dna_hash,
+                        &msg.to_agent_id,
+                        ProtocolWrapper::RateLimited(msg.clone()).into(),
+                    )?;
+                }
                 _ => (),
             }
         }
         Ok(())
     }
 
+    /// fail if the transport of any of the given `dna_hash::agent_id`s got killed
+    pub fn check_alive(&self, tracked: &[String]) -> NetResult<()> {
+        if let Some(key) = tracked.iter().find(|key| self.killed.contains(*key)) {
+            bail!("mock transport of {} was killed", key);
+        }
+        Ok(())
+    }
+
     // -- private -- //
 
+    /// the agents that registered with this dna hash
+    fn priv_agents(&self, dna_hash: &str) -> Vec<String> {
+        let prefix = cat_dna_agent(dna_hash, "");
+        self.senders
+            .keys()
+            .filter(|key| key.starts_with(&prefix))
+            .map(|key| key[prefix.len()..].to_string())
+            .collect()
+    }
+
     /// send a message to the appropriate channel based on dna_hash::agent_id
+    /// senders of nodes that went away get dropped
     fn priv_send_one(&mut self, dna_hash: &str, agent_id: &str, data: Protocol) -> NetResult<()> {
-        if let Some(sender) = self.senders.get_mut(&cat_dna_agent(dna_hash, agent_id)) {
-            sender.send(data)?;
+        let key = cat_dna_agent(dna_hash, agent_id);
+        let gone = match self.senders.get_mut(&key) {
+            Some(sender) => sender.send(data).is_err(),
+            None => false,
+        };
+        if gone {
+            self.senders.remove(&key);
         }
         Ok(())
     }
@@ -119,13 +179,24 @@ impl MockSingleton {
     /// send a message to all nodes connected with this dna hash
     fn priv_send_all(&mut self, dna_hash: &str, data: Protocol) -> NetResult<()> {
         if let Some(arr) = self.senders_by_dna.get_mut(dna_hash) {
-            for val in arr.iter_mut() {
-                (*val).send(data.clone())?;
-            }
+            arr.retain(|sender| sender.send(data.clone()).is_ok());
         }
         Ok(())
     }
 
+    /// send a message to the first node still connected with this dna hash
+    fn priv_send_first(&mut self, dna_hash: &str, data: Protocol) -> bool {
+        if let Some(arr) = self.senders_by_dna.get_mut(dna_hash) {
+            while !arr.is_empty() {
+                if arr[0].send(data.clone()).is_ok() {
+                    return true;
+                }
+                arr.remove(0);
+            }
+        }
+        false
+    }
+
     /// we received a SendMessage message...
     /// normally this would travel over the network, then
     /// show up as a HandleSend message, fabricate that message && deliver
@@ -151,19 +222,26 @@ impl MockSingleton {
     }
 
     /// when someone makes a dht data request,
-    /// this mock module routes it to the first node connected on that dna.
-    /// this works because we also send store requests to all connected nodes.
+    /// this mock module routes it to the node it is meant for, if any,
+    /// or else to the first node connected on that dna.
+    /// this works because we also send store requests to all connected nodes,
+    /// and entries that only some of them hold get asked from one of those.
     fn priv_handle_get_dht(&mut self, msg: &GetDhtData) -> NetResult<()> {
-        match self.senders_by_dna.entry(msg.dna_hash.to_string()) {
-            Entry::Occupied(mut e) => {
-                if !e.get().is_empty() {
-                    let r = &e.get_mut()[0];
-                    r.send(ProtocolWrapper::GetDht(msg.clone()).into())?;
-                    return Ok(());
-                }
+        if let Some(to_agent_id) = msg.to_agent_id.clone() {
+            if self
+                .senders
+                .contains_key(&cat_dna_agent(&msg.dna_hash, &to_agent_id))
+            {
+                return self.priv_send_one(
+                    &msg.dna_hash,
+                    &to_agent_id,
+                    ProtocolWrapper::GetDht(msg.clone()).into(),
+                );
             }
-            _ => (),
-        };
+        }
+        if self.priv_send_first(&msg.dna_hash, ProtocolWrapper::GetDht(msg.clone()).into()) {
+            return Ok(());
+        }
 
         self.priv_send_one(
             &msg.dna_hash,
@@ -200,16 +278,12 @@ impl MockSingleton {
     /// this mock module routes it to the first node connected on that dna.
     /// this works because we also send store requests to all connected nodes.
     fn priv_handle_get_dht_meta(&mut self, msg: &GetDhtMetaData) -> NetResult<()> {
-        match self.senders_by_dna.entry(msg.dna_hash.to_string()) {
-            Entry::Occupied(mut e) => {
-                if !e.get().is_empty() {
-                    let r = &e.get_mut()[0];
-                    r.send(ProtocolWrapper::GetDhtMeta(msg.clone()).into())?;
-                    return Ok(());
-                }
-            }
-            _ => (),
-        };
+        if self.priv_send_first(
+            &msg.dna_hash,
+            ProtocolWrapper::GetDhtMeta(msg.clone()).into(),
+        ) {
+            return Ok(());
+        }
 
         self.priv_send_one(
             &msg.dna_hash,
@@ -244,6 +318,16 @@ impl MockSingleton {
         )?;
         Ok(())
     }
+
+    /// a holder tells the publisher of an entry that it holds it
+    fn priv_handle_hold_ack(&mut self, msg: &HoldAckData) -> NetResult<()> {
+        self.priv_send_one(
+            &msg.dna_hash,
+            &msg.to_agent_id,
+            ProtocolWrapper::HoldAck(msg.clone()).into(),
+        )?;
+        Ok(())
+    }
 }
 
 /// this is the actual memory space for our mock singleton
@@ -259,10 +343,27 @@ fn get_mock<'a>() -> NetResult<MutexGuard<'a, MockSingleton>> {
     }
 }
 
+/// simulate losing the transport of the given agent on the given dna:
+/// its worker fails from now on and it can't track that dna again until restored
+pub fn kill_transport(dna_hash: &str, agent_id: &str) -> NetResult<()> {
+    get_mock()?.killed.insert(cat_dna_agent(dna_hash, agent_id));
+    Ok(())
+}
+
+/// bring back a transport that was killed with `kill_transport()`
+pub fn restore_transport(dna_hash: &str, agent_id: &str) -> NetResult<()> {
+    get_mock()?
+        .killed
+        .remove(&cat_dna_agent(dna_hash, agent_id));
+    Ok(())
+}
+
 /// a p2p worker for mocking in-memory scenario tests
 pub struct MockWorker {
     handler: NetHandler,
     mock_msgs: Vec<mpsc::Receiver<Protocol>>,
+    // `dna_hash::agent_id` of every app tracked through this worker
+    tracked: Vec<String>,
 }
 
 impl NetWorker for MockWorker {
@@ -275,12 +376,15 @@ impl NetWorker for MockWorker {
     /// forward to our mock singleton
     fn receive(&mut self, data: Protocol) -> NetResult<()> {
         let mut mock = get_mock()?;
+        mock.check_alive(&self.tracked)?;
 
         if let Ok(wrap) = ProtocolWrapper::try_from(&data) {
             if let ProtocolWrapper::TrackApp(app) = wrap {
                 let (tx, rx) = mpsc::channel();
-                self.mock_msgs.push(rx);
                 mock.register(&app.dna_hash, &app.agent_id, tx)?;
+                self.mock_msgs.push(rx);
+                self.tracked
+                    .push(cat_dna_agent(&app.dna_hash, &app.agent_id));
                 return Ok(());
             }
         }
@@ -291,6 +395,7 @@ impl NetWorker for MockWorker {
 
     /// check for messages from our mock singleton
     fn tick(&mut self) -> NetResult<bool> {
+        get_mock()?.check_alive(&self.tracked)?;
         let mut did_something = false;
 
         for msg in self.mock_msgs.iter_mut() {
@@ -310,6 +415,7 @@ impl MockWorker {
         Ok(MockWorker {
             handler,
             mock_msgs: Vec::new(),
+            tracked: Vec::new(),
         })
     }
 }
@@ -369,6 +475,24 @@ mod tests {
         )
         .unwrap();
 
+        // -- nodes get told about each other -- //
+
+        cli1.tick().unwrap();
+        cli2.tick().unwrap();
+
+        assert_eq!(
+            ProtocolWrapper::try_from(handler_recv_1.recv().unwrap()).unwrap(),
+            ProtocolWrapper::PeerConnected(PeerData {
+                id: AGENT_ID_2.to_string(),
+            })
+        );
+        assert_eq!(
+            ProtocolWrapper::try_from(handler_recv_2.recv().unwrap()).unwrap(),
+            ProtocolWrapper::PeerConnected(PeerData {
+                id: AGENT_ID_1.to_string(),
+            })
+        );
+
         // -- node 2 node / send / receive -- //
 
         cli1.receive(
@@ -421,6 +545,7 @@ mod tests {
                 dna_hash: DNA_HASH.to_string(),
                 from_agent_id: AGENT_ID_2.to_string(),
                 address: "hello".to_string(),
+                to_agent_id: None,
             })
             .into(),
         )
@@ -438,6 +563,7 @@ mod tests {
                     agent_id: msg.from_agent_id.clone(),
                     address: msg.address.clone(),
                     content: json!(format!("data-for: {}", msg.address)),
+                    redundancy: 0,
                 })
                 .into(),
             )
@@ -465,6 +591,7 @@ mod tests {
                 agent_id: AGENT_ID_2.to_string(),
                 address: "hello".to_string(),
                 content: json!("test-data"),
+                redundancy: 0,
             })
             .into(),
         )
@@ -597,9 +724,59 @@ mod tests {
             panic!("bad msg");
         }
 
+        // -- hold acknowledgement -- //
+
+        cli1.receive(
+            ProtocolWrapper::HoldAck(HoldAckData {
+                msg_id: "yada".to_string(),
+                dna_hash: DNA_HASH.to_string(),
+                to_agent_id: AGENT_ID_2.to_string(),
+                from_agent_id: AGENT_ID_1.to_string(),
+                address: "hello".to_string(),
+            })
+            .into(),
+        )
+        .unwrap();
+
+        cli2.tick().unwrap();
+        let res = ProtocolWrapper::try_from(handler_recv_2.recv().unwrap()).unwrap();
+
+        if let ProtocolWrapper::HoldAck(msg) = res {
+            assert_eq!(AGENT_ID_1, &msg.from_agent_id);
+            assert_eq!("hello", &msg.address);
+        } else {
+            panic!("bad msg");
+        }
+
         // -- cleanup -- //
 
         cli1.stop().unwrap();
         cli2.stop().unwrap();
     }
+
+    #[test]
+    fn it_mock_worker_kill_and_restore_transport() {
+        let dna_hash = "killed-transport-dna-hash";
+        let new_worker = || Box::new(MockWorker::new(Box::new(|_r| Ok(()))).unwrap());
+        let track_app: Protocol = ProtocolWrapper::TrackApp(TrackAppData {
+            dna_hash: dna_hash.to_string(),
+            agent_id: AGENT_ID_1.to_string(),
+        })
+        .into();
+
+        let mut cli = new_worker();
+        cli.receive(track_app.clone()).unwrap();
+        assert!(!cli.tick().unwrap());
+
+        // the worker dies with its transport and no new one can take over
+        kill_transport(dna_hash, AGENT_ID_1).unwrap();
+        assert!(cli.tick().is_err());
+        assert!(new_worker().receive(track_app.clone()).is_err());
+
+        restore_transport(dna_hash, AGENT_ID_1).unwrap();
+        let mut cli = new_worker();
+        cli.receive(track_app).unwrap();
+        assert!(!cli.tick().unwrap());
+        cli.stop().unwrap();
+    }
 }
@@ -0,0 +1,177 @@
+//! provides a p2p worker for running a single node without any network ("backend": "none")
+
+use holochain_net_connection::{
+    net_connection::{NetHandler, NetWorker},
+    protocol::Protocol,
+    protocol_wrapper::ProtocolWrapper,
+    NetResult,
+};
+
+use std::convert::TryFrom;
+
+/// a p2p worker that never talks to anyone but the node it belongs to.
+/// That node is the whole network: its publishes come back to it as store
+/// requests and its gets get answered from its own shard.
+/// Everything addressed to other agents is dropped.
+pub struct NullWorker {
+    handler: NetHandler,
+    agent_id: Option<String>,
+    loopback: Vec<Protocol>,
+}
+
+impl NetWorker for NullWorker {
+    /// stop the net worker
+    fn stop(self: Box<Self>) -> NetResult<()> {
+        Ok(())
+    }
+
+    /// we got a message from holochain core
+    /// queue up what our own node would receive from a network of one
+    fn receive(&mut self, data: Protocol) -> NetResult<()> {
+        let wrap = match ProtocolWrapper::try_from(&data) {
+            Ok(wrap) => wrap,
+            Err(_) => return Ok(()),
+        };
+        let reply = match wrap {
+            ProtocolWrapper::TrackApp(app) => {
+                self.agent_id = Some(app.agent_id);
+                None
+            }
+            ProtocolWrapper::PublishDht(msg) => Some(ProtocolWrapper::StoreDht(msg)),
+            ProtocolWrapper::PublishDhtMeta(msg) => Some(ProtocolWrapper::StoreDhtMeta(msg)),
+            ProtocolWrapper::GetDht(msg) => Some(ProtocolWrapper::GetDht(msg)),
+            ProtocolWrapper::GetDhtResult(msg) => Some(ProtocolWrapper::GetDhtResult(msg)),
+            ProtocolWrapper::GetDhtMeta(msg) => Some(ProtocolWrapper::GetDhtMeta(msg)),
+            ProtocolWrapper::GetDhtMetaResult(msg) => Some(ProtocolWrapper::GetDhtMetaResult(msg)),
+            ProtocolWrapper::SendMessage(msg) => {
+                if self.is_own_agent(&msg.to_agent_id) {
+                    Some(ProtocolWrapper::HandleSend(msg))
+                } else {
+                    None
+                }
+            }
+            ProtocolWrapper::HandleSendResult(msg) => {
+                if self.is_own_agent(&msg.to_agent_id) {
+                    Some(ProtocolWrapper::SendResult(msg))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        if let Some(reply) = reply {
+            self.loopback.push(reply.into());
+        }
+        Ok(())
+    }
+
+    /// deliver the queued messages to our own node
+    fn tick(&mut self) -> NetResult<bool> {
+        if self.loopback.is_empty() {
+            return Ok(false);
+        }
+        for data in self.loopback.drain(..).collect::<Vec<_>>() {
+            (self.handler)(Ok(data))?;
+        }
+        Ok(true)
+    }
+}
+
+impl NullWorker {
+    /// create a new null worker... no configuration required
+    pub fn new(handler: NetHandler) -> NetResult<Self> {
+        Ok(NullWorker {
+            handler,
+            agent_id: None,
+            loopback: Vec::new(),
+        })
+    }
+
+    fn is_own_agent(&self, agent_id: &str) -> bool {
+        self.agent_id
+            .as_ref()
+            .map(|id| id == agent_id)
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use holochain_net_connection::protocol_wrapper::{DhtData, MessageData, TrackAppData};
+    use std::sync::mpsc;
+
+    static DNA_HASH: &'static str = "blabladnahash";
+    static AGENT_ID_1: &'static str = "agent-hash-test-1";
+    static AGENT_ID_2: &'static str = "agent-hash-test-2";
+
+    fn tracked_worker() -> (Box<NullWorker>, mpsc::Receiver<Protocol>) {
+        let (handler_send, handler_recv) = mpsc::channel::<Protocol>();
+        let mut worker = Box::new(
+            NullWorker::new(Box::new(move |r| {
+                handler_send.send(r?)?;
+                Ok(())
+            }))
+            .unwrap(),
+        );
+        worker
+            .receive(
+                ProtocolWrapper::TrackApp(TrackAppData {
+                    dna_hash: DNA_HASH.to_string(),
+                    agent_id: AGENT_ID_1.to_string(),
+                })
+                .into(),
+            )
+            .unwrap();
+        (worker, handler_recv)
+    }
+
+    #[test]
+    fn it_null_worker_stores_own_publishes() {
+        let (mut worker, handler_recv) = tracked_worker();
+        let dht_data = DhtData {
+            msg_id: "?".to_string(),
+            dna_hash: DNA_HASH.to_string(),
+            agent_id: AGENT_ID_1.to_string(),
+            address: "QmAddress".to_string(),
+            content: json!("hello"),
+            redundancy: 0,
+        };
+
+        worker
+            .receive(ProtocolWrapper::PublishDht(dht_data.clone()).into())
+            .unwrap();
+        assert!(handler_recv.try_recv().is_err());
+        assert!(worker.tick().unwrap());
+
+        let res = ProtocolWrapper::try_from(handler_recv.recv().unwrap()).unwrap();
+        assert_eq!(res, ProtocolWrapper::StoreDht(dht_data));
+        assert!(!worker.tick().unwrap());
+    }
+
+    #[test]
+    fn it_null_worker_drops_messages_to_others() {
+        let (mut worker, handler_recv) = tracked_worker();
+        let message = |to_agent_id: &str| MessageData {
+            dna_hash: DNA_HASH.to_string(),
+            to_agent_id: to_agent_id.to_string(),
+            from_agent_id: AGENT_ID_1.to_string(),
+            msg_id: "yada".to_string(),
+            data: json!("hello"),
+        };
+
+        worker
+            .receive(ProtocolWrapper::SendMessage(message(AGENT_ID_2)).into())
+            .unwrap();
+        assert!(!worker.tick().unwrap());
+        assert!(handler_recv.try_recv().is_err());
+
+        worker
+            .receive(ProtocolWrapper::SendMessage(message(AGENT_ID_1)).into())
+            .unwrap();
+        assert!(worker.tick().unwrap());
+        let res = ProtocolWrapper::try_from(handler_recv.recv().unwrap()).unwrap();
+        assert_eq!(res, ProtocolWrapper::HandleSend(message(AGENT_ID_1)));
+    }
+}
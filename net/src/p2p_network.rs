@@ -11,7 +11,7 @@ use holochain_net_connection::{
     NetResult,
 };
 
-use super::{ipc_net_worker::IpcNetWorker, mock_worker::MockWorker};
+use super::{ipc_net_worker::IpcNetWorker, mock_worker::MockWorker, null_worker::NullWorker};
 
 use serde_json;
 
@@ -56,6 +56,12 @@ impl P2pNetwork {
                     Box::new(move |h| Ok(Box::new(MockWorker::new(h)?) as Box<NetWorker>)),
                 )?,
             }),
+            "\"none\"" => Ok(P2pNetwork {
+                con: NetConnectionThread::new(
+                    handler,
+                    Box::new(move |h| Ok(Box::new(NullWorker::new(h)?) as Box<NetWorker>)),
+                )?,
+            }),
             _ => bail!("unknown p2p_network backend: {}", config["backend"]),
         }
     }
@@ -117,4 +123,18 @@ mod tests {
         res.send(Protocol::P2pReady).unwrap();
         res.stop().unwrap();
     }
+
+    #[test]
+    fn it_should_create_none() {
+        let mut res = P2pNetwork::new(
+            Box::new(|_r| Ok(())),
+            &json!({
+                "backend": "none"
+            })
+            .into(),
+        )
+        .unwrap();
+        res.send(Protocol::P2pReady).unwrap();
+        res.stop().unwrap();
+    }
 }
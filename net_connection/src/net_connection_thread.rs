@@ -1,5 +1,7 @@
 use super::NetResult;
 
+use failure::Error;
+
 use super::{
     net_connection::{NetConnection, NetHandler, NetWorkerFactory},
     protocol::Protocol,
@@ -9,10 +11,12 @@ use std::{thread, time};
 
 use std::sync::{
     atomic::{AtomicBool, Ordering},
-    mpsc, Arc,
+    mpsc, Arc, Mutex,
 };
 
 /// a NetConnection instance that is managed on another thread
+/// if the worker fails, the error is handed to the handler and the thread ends,
+/// so every later `send` fails as well
 #[derive(Debug)]
 pub struct NetConnectionThread {
     keep_running: Arc<AtomicBool>,
@@ -51,28 +55,38 @@ impl NetConnectionThread {
             send_channel: sender,
             thread: thread::spawn(move || {
                 let mut us = 100_u64;
-                let mut worker = worker_factory(handler).unwrap_or_else(|e| panic!("{:?}", e));
+                // the worker gets the handler, but we still need it to report worker failures
+                let handler = Arc::new(Mutex::new(handler));
+                let worker_handler = handler.clone();
+                let report = move |e: Error| {
+                    let mut handler = handler.lock().unwrap();
+                    let _ = (*handler)(Err(e));
+                };
+                let mut worker = match worker_factory(Box::new(move |r| {
+                    let mut handler = worker_handler.lock().unwrap();
+                    (*handler)(r)
+                })) {
+                    Ok(worker) => worker,
+                    Err(e) => return report(e),
+                };
 
                 while keep_running2.load(Ordering::Relaxed) {
                     let mut did_something = false;
-                    receiver
-                        .try_recv()
-                        .and_then(|data| {
-                            did_something = true;
-                            worker.receive(data).unwrap_or_else(|e| panic!("{:?}", e));
-                            Ok(())
-                        })
-                        .unwrap_or(());
-
-                    worker
-                        .tick()
-                        .and_then(|b| {
+                    if let Ok(data) = receiver.try_recv() {
+                        did_something = true;
+                        if let Err(e) = worker.receive(data) {
+                            return report(e);
+                        }
+                    }
+
+                    match worker.tick() {
+                        Ok(b) => {
                             if b {
                                 did_something = true;
                             }
-                            Ok(())
-                        })
-                        .unwrap_or_else(|e| panic!("{:?}", e));
+                        }
+                        Err(e) => return report(e),
+                    }
 
                     if did_something {
                         us = 100_u64;
@@ -159,6 +173,44 @@ mod tests {
         con.stop().unwrap();
     }
 
+    struct FailingWorker;
+
+    impl NetWorker for FailingWorker {
+        fn receive(&mut self, _data: Protocol) -> NetResult<()> {
+            bail!("transport lost")
+        }
+    }
+
+    #[test]
+    fn it_reports_worker_failures() {
+        let (sender, receiver) = mpsc::channel();
+
+        let mut con = NetConnectionThread::new(
+            Box::new(move |r| {
+                sender.send(r.map_err(|e| e.to_string()))?;
+                Ok(())
+            }),
+            Box::new(|_h| Ok(Box::new(FailingWorker) as Box<NetWorker>)),
+        )
+        .unwrap();
+
+        con.send("test".into()).unwrap();
+
+        let res = receiver.recv().unwrap();
+        assert_eq!(Err("transport lost".to_string()), res);
+
+        // the worker thread is gone, nobody is listening anymore
+        let mut send_failed = false;
+        for _ in 0..100 {
+            if con.send("test".into()).is_err() {
+                send_failed = true;
+                break;
+            }
+            thread::sleep(time::Duration::from_millis(10));
+        }
+        assert!(send_failed);
+    }
+
     #[test]
     fn it_can_tick() {
         let (sender, receiver) = mpsc::channel();
@@ -10,6 +10,13 @@ use std::convert::TryFrom;
 
 use super::protocol::Protocol;
 
+/// Version of the messages defined in this module.
+/// Every message gets sent with a "protocolVersion" field holding it.
+/// Messages without that field come from nodes that predate versioning, which speak version 1.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+const PROTOCOL_VERSION_KEY: &str = "protocolVersion";
+
 fn get_default_state_id() -> String {
     "undefined".to_string()
 }
@@ -110,6 +117,10 @@ pub struct GetDhtData {
     pub from_agent_id: String,
 
     pub address: String,
+
+    /// the node to ask, the network picks one if there is none
+    #[serde(rename = "toAgentId", default, skip_serializing_if = "Option::is_none")]
+    pub to_agent_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
@@ -125,6 +136,10 @@ pub struct DhtData {
 
     pub address: String,
     pub content: serde_json::Value,
+
+    /// how many nodes are to hold the published data, 0 meaning all of them
+    #[serde(default)]
+    pub redundancy: u32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
@@ -158,6 +173,123 @@ pub struct DhtMetaData {
     pub content: serde_json::Value,
 }
 
+/// Acknowledgement of a holder of an entry to the node that published it,
+/// sent once the entry passed validation and is held
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
+pub struct HoldAckData {
+    /// `_id` of the store request the entry came with
+    #[serde(rename = "_id")]
+    pub msg_id: String,
+
+    #[serde(rename = "dnaHash")]
+    pub dna_hash: String,
+
+    /// the publisher of the entry
+    #[serde(rename = "toAgentId")]
+    pub to_agent_id: String,
+
+    /// the holder of the entry
+    #[serde(rename = "fromAgentId")]
+    pub from_agent_id: String,
+
+    pub address: String,
+}
+
+/// Reply to a message that could not be handled because its method or its
+/// protocol version is unknown to the receiving node
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
+pub struct UnsupportedMessageData {
+    /// `_id` of the unsupported message, empty if it had none
+    #[serde(rename = "_id")]
+    pub msg_id: String,
+
+    #[serde(rename = "dnaHash")]
+    pub dna_hash: String,
+
+    #[serde(rename = "toAgentId")]
+    pub to_agent_id: String,
+
+    #[serde(rename = "fromAgentId")]
+    pub from_agent_id: String,
+
+    /// method of the unsupported message
+    pub method: String,
+
+    /// address the unsupported message was about, if any
+    #[serde(default)]
+    pub address: Option<String>,
+
+    #[serde(rename = "seenVersion")]
+    pub seen_version: u32,
+
+    pub supported: u32,
+}
+
+/// Reply to a request that was dropped because its sender exceeded the rate limit
+/// of the receiving node
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
+pub struct RateLimitedData {
+    /// `_id` of the dropped request
+    #[serde(rename = "_id")]
+    pub msg_id: String,
+
+    #[serde(rename = "dnaHash")]
+    pub dna_hash: String,
+
+    #[serde(rename = "toAgentId")]
+    pub to_agent_id: String,
+
+    #[serde(rename = "fromAgentId")]
+    pub from_agent_id: String,
+
+    /// milliseconds until the receiving node accepts requests of the sender again
+    #[serde(rename = "retryAfterMs")]
+    pub retry_after_ms: u64,
+}
+
+impl UnsupportedMessageData {
+    /// Builds the reply to a message that could not be converted into a ProtocolWrapper.
+    /// Returns None if there is nobody to reply to, i.e. if the message is no JSON object,
+    /// names no sender or is an UnsupportedMessage itself (which must never be answered
+    /// to avoid ping-pong between two nodes that don't understand each other).
+    /// The sender fields are filled with what the message says about its recipient
+    /// and should be overwritten by the replying node.
+    pub fn reply_to(p: &Protocol) -> Option<Self> {
+        let json = match p {
+            Protocol::Json(json) => json,
+            _ => return None,
+        };
+        let value: serde_json::Value = serde_json::from_str(&String::from(json.clone())).ok()?;
+        let field = |key: &str| value.get(key).and_then(|v| v.as_str()).map(String::from);
+
+        let method = field("method").unwrap_or_default();
+        if method == "unsupportedMessage" {
+            return None;
+        }
+        let to_agent_id = field("fromAgentId").or_else(|| field("agentId"))?;
+
+        Some(UnsupportedMessageData {
+            msg_id: field("_id").unwrap_or_default(),
+            dna_hash: field("dnaHash").unwrap_or_default(),
+            to_agent_id,
+            from_agent_id: field("toAgentId").unwrap_or_default(),
+            method,
+            address: field("address"),
+            seen_version: message_version(&value),
+            supported: PROTOCOL_VERSION,
+        })
+    }
+}
+
+/// The protocol version a message claims to be written in
+fn message_version(value: &serde_json::Value) -> u32 {
+    value
+        .get(PROTOCOL_VERSION_KEY)
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32)
+        .unwrap_or(1)
+}
+
 /// High level p2p / network message
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, DefaultJson)]
 #[serde(tag = "method")]
@@ -255,13 +387,41 @@ pub enum ProtocolWrapper {
     /// [recv] the network is requesting that we store this meta data
     #[serde(rename = "storeDhtMeta")]
     StoreDhtMeta(DhtMetaData),
+
+    /// [send] tell the publisher of an entry we were asked to store that we hold it
+    /// [recv] a holder of an entry we published holds it
+    #[serde(rename = "holdAck")]
+    HoldAck(HoldAckData),
+
+    /// [send] tell another node that we could not handle its message
+    /// [recv] another node could not handle a message we sent
+    #[serde(rename = "unsupportedMessage")]
+    UnsupportedMessage(UnsupportedMessageData),
+
+    /// [send] tell another node that we dropped its request because it sends too much
+    /// [recv] another node dropped a request we sent because we exceeded its rate limit
+    #[serde(rename = "rateLimited")]
+    RateLimited(RateLimitedData),
 }
 
 impl<'a> TryFrom<&'a Protocol> for ProtocolWrapper {
     type Error = Error;
     fn try_from(p: &Protocol) -> Result<Self, Error> {
         if let Protocol::Json(json) = p {
-            match ProtocolWrapper::try_from(json) {
+            let mut value: serde_json::Value = serde_json::from_str(&String::from(json.clone()))?;
+            let version = message_version(&value);
+            if version > PROTOCOL_VERSION {
+                bail!(
+                    "unsupported protocol version {} (supported: {}): {:?}",
+                    version,
+                    PROTOCOL_VERSION,
+                    p
+                );
+            }
+            if let Some(object) = value.as_object_mut() {
+                object.remove(PROTOCOL_VERSION_KEY);
+            }
+            match serde_json::from_value(value) {
                 Ok(w) => {
                     return Ok(w);
                 }
@@ -281,7 +441,14 @@ impl TryFrom<Protocol> for ProtocolWrapper {
 
 impl<'a> From<&'a ProtocolWrapper> for Protocol {
     fn from(w: &ProtocolWrapper) -> Self {
-        Protocol::Json(JsonString::from(w))
+        let mut value = serde_json::to_value(w).expect("ProtocolWrapper to be serializable");
+        if let Some(object) = value.as_object_mut() {
+            object.insert(
+                PROTOCOL_VERSION_KEY.to_string(),
+                serde_json::Value::from(PROTOCOL_VERSION),
+            );
+        }
+        Protocol::Json(JsonString::from(value.to_string()))
     }
 }
 
@@ -419,4 +586,147 @@ mod tests {
             agent_id: "test_to".to_string(),
         }));
     }
+
+    #[test]
+    fn it_can_convert_publish_dht() {
+        test_convert!(ProtocolWrapper::PublishDht(DhtData {
+            msg_id: "test_id".to_string(),
+            dna_hash: "test_dna".to_string(),
+            agent_id: "test_agent".to_string(),
+            address: "test_address".to_string(),
+            content: json!({"entry": "test"}),
+            redundancy: 2,
+        }));
+    }
+
+    #[test]
+    fn it_can_convert_get_dht() {
+        test_convert!(ProtocolWrapper::GetDht(GetDhtData {
+            msg_id: "test_id".to_string(),
+            dna_hash: "test_dna".to_string(),
+            from_agent_id: "test_from".to_string(),
+            address: "test_address".to_string(),
+            to_agent_id: Some("test_to".to_string()),
+        }));
+    }
+
+    #[test]
+    fn it_accepts_publishes_without_redundancy() {
+        let p = Protocol::Json(JsonString::from(
+            r#"{"method":"publishDht","_id":"id","dnaHash":"dna","agentId":"agent","address":"a","content":{}}"#,
+        ));
+        match ProtocolWrapper::try_from(p).unwrap() {
+            ProtocolWrapper::PublishDht(dht_data) => assert_eq!(dht_data.redundancy, 0),
+            other => panic!("bad enum type: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_can_convert_hold_ack() {
+        test_convert!(ProtocolWrapper::HoldAck(HoldAckData {
+            msg_id: "test_id".to_string(),
+            dna_hash: "test_dna".to_string(),
+            to_agent_id: "test_to".to_string(),
+            from_agent_id: "test_from".to_string(),
+            address: "test_address".to_string(),
+        }));
+    }
+
+    #[test]
+    fn it_can_convert_unsupported_message() {
+        test_convert!(ProtocolWrapper::UnsupportedMessage(
+            UnsupportedMessageData {
+                msg_id: "test_id".to_string(),
+                dna_hash: "test_dna".to_string(),
+                to_agent_id: "test_to".to_string(),
+                from_agent_id: "test_from".to_string(),
+                method: "getDhtLinks".to_string(),
+                address: Some("test_address".to_string()),
+                seen_version: 2,
+                supported: PROTOCOL_VERSION,
+            }
+        ));
+    }
+
+    #[test]
+    fn it_can_convert_rate_limited() {
+        test_convert!(ProtocolWrapper::RateLimited(RateLimitedData {
+            msg_id: "test_id".to_string(),
+            dna_hash: "test_dna".to_string(),
+            to_agent_id: "test_to".to_string(),
+            from_agent_id: "test_from".to_string(),
+            retry_after_ms: 100,
+        }));
+    }
+
+    #[test]
+    fn it_sends_the_protocol_version() {
+        let p = Protocol::from(ProtocolWrapper::RequestState);
+        if let Protocol::Json(json) = p {
+            let value: serde_json::Value = serde_json::from_str(&String::from(json)).unwrap();
+            assert_eq!(value["protocolVersion"], json!(PROTOCOL_VERSION));
+        } else {
+            panic!("bad protocol type");
+        }
+    }
+
+    #[test]
+    fn it_accepts_unversioned_messages() {
+        let p = Protocol::Json(JsonString::from(r#"{"method":"requestState"}"#));
+        assert_eq!(
+            ProtocolWrapper::try_from(p).unwrap(),
+            ProtocolWrapper::RequestState
+        );
+    }
+
+    #[test]
+    fn it_rejects_future_versions() {
+        let p = Protocol::Json(JsonString::from(
+            r#"{"method":"requestState","protocolVersion":2}"#,
+        ));
+        assert!(ProtocolWrapper::try_from(p).is_err());
+    }
+
+    #[test]
+    fn it_builds_a_reply_to_unsupported_messages() {
+        let p = Protocol::Json(JsonString::from(
+            r#"{
+            "method": "getDhtLinks",
+            "protocolVersion": 7,
+            "_id": "test_id",
+            "dnaHash": "test_dna",
+            "fromAgentId": "test_from",
+            "address": "test_address"
+        }"#,
+        ));
+        assert!(ProtocolWrapper::try_from(&p).is_err());
+        assert_eq!(
+            UnsupportedMessageData::reply_to(&p),
+            Some(UnsupportedMessageData {
+                msg_id: "test_id".to_string(),
+                dna_hash: "test_dna".to_string(),
+                to_agent_id: "test_from".to_string(),
+                from_agent_id: "".to_string(),
+                method: "getDhtLinks".to_string(),
+                address: Some("test_address".to_string()),
+                seen_version: 7,
+                supported: PROTOCOL_VERSION,
+            })
+        );
+    }
+
+    #[test]
+    fn it_does_not_reply_to_unsupported_message_replies() {
+        let p = Protocol::Json(JsonString::from(
+            r#"{
+            "method": "unsupportedMessage",
+            "protocolVersion": 7,
+            "fromAgentId": "test_from"
+        }"#,
+        ));
+        assert_eq!(UnsupportedMessageData::reply_to(&p), None);
+
+        let p = Protocol::Json(JsonString::from(r#"{"method":"getDhtLinks"}"#));
+        assert_eq!(UnsupportedMessageData::reply_to(&p), None);
+    }
 }
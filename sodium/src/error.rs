@@ -0,0 +1,19 @@
+//! This module holds the error type of the libsodium wrappers
+
+use std::fmt;
+
+/// An operation of libsodium failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum SodiumError {
+    Generic(String),
+}
+
+impl fmt::Display for SodiumError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SodiumError::Generic(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SodiumError {}
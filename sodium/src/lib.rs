@@ -25,6 +25,9 @@ macro_rules! rptr {
     };
 }
 
+pub mod error;
 pub mod random;
+pub mod sealed_box;
 pub mod secbuf;
+pub mod sign;
 pub mod util;
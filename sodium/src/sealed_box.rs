@@ -0,0 +1,189 @@
+//! This module provides access to libsodium sealed boxes:
+//! messages encrypted with a public key, which only the matching secret key opens
+
+use super::check_init;
+
+use super::{error::SodiumError, secbuf::SecBuf};
+
+/// length of a public key
+pub const PUBLICKEYBYTES: usize = rust_sodium_sys::crypto_box_PUBLICKEYBYTES as usize;
+
+/// length of a secret key
+pub const SECRETKEYBYTES: usize = rust_sodium_sys::crypto_box_SECRETKEYBYTES as usize;
+
+/// length of the seed a key pair can be derived from
+pub const SEEDBYTES: usize = rust_sodium_sys::crypto_box_SEEDBYTES as usize;
+
+/// how much longer a sealed message is than the message itself
+pub const SEALBYTES: usize = rust_sodium_sys::crypto_box_SEALBYTES as usize;
+
+/// derive the key pair of the given seed into public_key and secret_key
+pub fn seed_keypair(public_key: &mut SecBuf, secret_key: &mut SecBuf, seed: &mut SecBuf) {
+    check_init();
+    unsafe {
+        let mut public_key = public_key.write_lock();
+        let mut secret_key = secret_key.write_lock();
+        let seed = seed.read_lock();
+        assert_eq!(PUBLICKEYBYTES, public_key.len());
+        assert_eq!(SECRETKEYBYTES, secret_key.len());
+        assert_eq!(SEEDBYTES, seed.len());
+        rust_sodium_sys::crypto_box_seed_keypair(
+            public_key.as_mut_ptr(),
+            secret_key.as_mut_ptr(),
+            seed.as_ptr(),
+        );
+    }
+}
+
+/// encrypt message for the owner of public_key into sealed,
+/// which has to be SEALBYTES longer than the message.
+/// Fails if public_key is not a usable public key.
+pub fn seal(
+    sealed: &mut SecBuf,
+    message: &mut SecBuf,
+    public_key: &mut SecBuf,
+) -> Result<(), SodiumError> {
+    check_init();
+    let result = unsafe {
+        let mut sealed = sealed.write_lock();
+        let message = message.read_lock();
+        let public_key = public_key.read_lock();
+        assert_eq!(message.len() + SEALBYTES, sealed.len());
+        assert_eq!(PUBLICKEYBYTES, public_key.len());
+        rust_sodium_sys::crypto_box_seal(
+            sealed.as_mut_ptr(),
+            message.as_ptr(),
+            message.len() as libc::c_ulonglong,
+            public_key.as_ptr(),
+        )
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(SodiumError::Generic(String::from(
+            "Can't seal a box with this public key",
+        )))
+    }
+}
+
+/// decrypt sealed into message, which has to be SEALBYTES shorter than sealed.
+/// Fails if sealed was not sealed for public_key or got modified.
+pub fn open(
+    message: &mut SecBuf,
+    sealed: &mut SecBuf,
+    public_key: &mut SecBuf,
+    secret_key: &mut SecBuf,
+) -> Result<(), SodiumError> {
+    check_init();
+    let result = unsafe {
+        let mut message = message.write_lock();
+        let sealed = sealed.read_lock();
+        let public_key = public_key.read_lock();
+        let secret_key = secret_key.read_lock();
+        if sealed.len() < SEALBYTES || message.len() != sealed.len() - SEALBYTES {
+            return Err(SodiumError::Generic(format!(
+                "Can't open a sealed box of {} bytes into {} bytes",
+                sealed.len(),
+                message.len()
+            )));
+        }
+        assert_eq!(PUBLICKEYBYTES, public_key.len());
+        assert_eq!(SECRETKEYBYTES, secret_key.len());
+        rust_sodium_sys::crypto_box_seal_open(
+            message.as_mut_ptr(),
+            sealed.as_ptr(),
+            sealed.len() as libc::c_ulonglong,
+            public_key.as_ptr(),
+            secret_key.as_ptr(),
+        )
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(SodiumError::Generic(String::from(
+            "The sealed box is not for this key pair or got modified",
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random;
+
+    fn keypair(seed_byte: u8) -> (SecBuf, SecBuf) {
+        let mut seed = SecBuf::with_insecure(SEEDBYTES);
+        {
+            let mut seed = seed.write_lock();
+            for byte in seed.iter_mut() {
+                *byte = seed_byte;
+            }
+        }
+        let mut public_key = SecBuf::with_insecure(PUBLICKEYBYTES);
+        let mut secret_key = SecBuf::with_secure(SECRETKEYBYTES);
+        seed_keypair(&mut public_key, &mut secret_key, &mut seed);
+        (public_key, secret_key)
+    }
+
+    fn sealed_message(public_key: &mut SecBuf) -> (SecBuf, SecBuf) {
+        let mut message = SecBuf::with_insecure(16);
+        random::buf(&mut message);
+        let mut sealed = SecBuf::with_insecure(16 + SEALBYTES);
+        seal(&mut sealed, &mut message, public_key).unwrap();
+        (message, sealed)
+    }
+
+    #[test]
+    fn it_should_derive_the_same_keypair_from_a_seed() {
+        let (mut public_key_1, _) = keypair(1);
+        let (mut public_key_2, _) = keypair(1);
+        let (mut other_public_key, _) = keypair(2);
+        assert_eq!(**public_key_1.read_lock(), **public_key_2.read_lock());
+        assert_ne!(**public_key_1.read_lock(), **other_public_key.read_lock());
+    }
+
+    #[test]
+    fn it_should_open_what_was_sealed_for_it() {
+        let (mut public_key, mut secret_key) = keypair(1);
+        let (mut message, mut sealed) = sealed_message(&mut public_key);
+
+        let mut opened = SecBuf::with_insecure(16);
+        assert_eq!(
+            Ok(()),
+            open(&mut opened, &mut sealed, &mut public_key, &mut secret_key)
+        );
+        assert_eq!(**message.read_lock(), **opened.read_lock());
+    }
+
+    #[test]
+    fn it_should_not_open_what_was_sealed_for_others() {
+        let (mut public_key, _) = keypair(1);
+        let (mut other_public_key, mut other_secret_key) = keypair(2);
+        let (_, mut sealed) = sealed_message(&mut public_key);
+
+        let mut opened = SecBuf::with_insecure(16);
+        assert!(open(
+            &mut opened,
+            &mut sealed,
+            &mut other_public_key,
+            &mut other_secret_key
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn it_should_not_open_modified_or_short_boxes() {
+        let (mut public_key, mut secret_key) = keypair(1);
+        let (_, mut sealed) = sealed_message(&mut public_key);
+        {
+            let mut sealed = sealed.write_lock();
+            sealed[SEALBYTES] ^= 1;
+        }
+        let mut opened = SecBuf::with_insecure(16);
+        assert!(open(&mut opened, &mut sealed, &mut public_key, &mut secret_key).is_err());
+
+        let mut short = SecBuf::with_insecure(SEALBYTES - 1);
+        let mut opened = SecBuf::with_insecure(0);
+        assert!(open(&mut opened, &mut short, &mut public_key, &mut secret_key).is_err());
+    }
+}
@@ -119,6 +119,9 @@ pub struct SecBuf {
     p: ProtectState,
 }
 
+// A SecBuf owns its memory alone, so it can be handed to another thread
+unsafe impl Send for SecBuf {}
+
 impl std::fmt::Debug for SecBuf {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{:?}", self.b.ref_())
@@ -0,0 +1,150 @@
+//! This module provides access to libsodium signatures:
+//! messages signed with a secret key, which everyone can verify with the public key
+
+use super::check_init;
+
+use super::{error::SodiumError, secbuf::SecBuf};
+
+/// length of a public key
+pub const PUBLICKEYBYTES: usize = rust_sodium_sys::crypto_sign_PUBLICKEYBYTES as usize;
+
+/// length of a secret key
+pub const SECRETKEYBYTES: usize = rust_sodium_sys::crypto_sign_SECRETKEYBYTES as usize;
+
+/// length of the seed a key pair can be derived from
+pub const SEEDBYTES: usize = rust_sodium_sys::crypto_sign_SEEDBYTES as usize;
+
+/// length of a signature
+pub const BYTES: usize = rust_sodium_sys::crypto_sign_BYTES as usize;
+
+/// derive the key pair of the given seed into public_key and secret_key
+pub fn seed_keypair(public_key: &mut SecBuf, secret_key: &mut SecBuf, seed: &mut SecBuf) {
+    check_init();
+    unsafe {
+        let mut public_key = public_key.write_lock();
+        let mut secret_key = secret_key.write_lock();
+        let seed = seed.read_lock();
+        assert_eq!(PUBLICKEYBYTES, public_key.len());
+        assert_eq!(SECRETKEYBYTES, secret_key.len());
+        assert_eq!(SEEDBYTES, seed.len());
+        rust_sodium_sys::crypto_sign_seed_keypair(
+            public_key.as_mut_ptr(),
+            secret_key.as_mut_ptr(),
+            seed.as_ptr(),
+        );
+    }
+}
+
+/// sign message with secret_key into signature, which has to be BYTES long
+pub fn sign(signature: &mut SecBuf, message: &mut SecBuf, secret_key: &mut SecBuf) {
+    check_init();
+    unsafe {
+        let mut signature = signature.write_lock();
+        let message = message.read_lock();
+        let secret_key = secret_key.read_lock();
+        assert_eq!(BYTES, signature.len());
+        assert_eq!(SECRETKEYBYTES, secret_key.len());
+        rust_sodium_sys::crypto_sign_detached(
+            signature.as_mut_ptr(),
+            std::ptr::null_mut(),
+            message.as_ptr(),
+            message.len() as libc::c_ulonglong,
+            secret_key.as_ptr(),
+        );
+    }
+}
+
+/// check that signature is the signature of message by the owner of public_key.
+/// Fails if it isn't, or if message got modified.
+pub fn verify(
+    signature: &mut SecBuf,
+    message: &mut SecBuf,
+    public_key: &mut SecBuf,
+) -> Result<(), SodiumError> {
+    check_init();
+    let result = unsafe {
+        let signature = signature.read_lock();
+        let message = message.read_lock();
+        let public_key = public_key.read_lock();
+        if signature.len() != BYTES || public_key.len() != PUBLICKEYBYTES {
+            return Err(SodiumError::Generic(format!(
+                "Can't verify a signature of {} bytes with a public key of {} bytes",
+                signature.len(),
+                public_key.len()
+            )));
+        }
+        rust_sodium_sys::crypto_sign_verify_detached(
+            signature.as_ptr(),
+            message.as_ptr(),
+            message.len() as libc::c_ulonglong,
+            public_key.as_ptr(),
+        )
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(SodiumError::Generic(String::from(
+            "The signature is not of this message by this public key",
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random;
+
+    fn keypair(seed_byte: u8) -> (SecBuf, SecBuf) {
+        let mut seed = SecBuf::with_insecure(SEEDBYTES);
+        {
+            let mut seed = seed.write_lock();
+            for byte in seed.iter_mut() {
+                *byte = seed_byte;
+            }
+        }
+        let mut public_key = SecBuf::with_insecure(PUBLICKEYBYTES);
+        let mut secret_key = SecBuf::with_secure(SECRETKEYBYTES);
+        seed_keypair(&mut public_key, &mut secret_key, &mut seed);
+        (public_key, secret_key)
+    }
+
+    fn signed_message(secret_key: &mut SecBuf) -> (SecBuf, SecBuf) {
+        let mut message = SecBuf::with_insecure(16);
+        random::buf(&mut message);
+        let mut signature = SecBuf::with_insecure(BYTES);
+        sign(&mut signature, &mut message, secret_key);
+        (message, signature)
+    }
+
+    #[test]
+    fn it_should_verify_what_its_key_signed() {
+        let (mut public_key, mut secret_key) = keypair(1);
+        let (mut message, mut signature) = signed_message(&mut secret_key);
+        assert_eq!(
+            Ok(()),
+            verify(&mut signature, &mut message, &mut public_key)
+        );
+    }
+
+    #[test]
+    fn it_should_not_verify_what_others_signed() {
+        let (mut public_key, _) = keypair(1);
+        let (_, mut other_secret_key) = keypair(2);
+        let (mut message, mut signature) = signed_message(&mut other_secret_key);
+        assert!(verify(&mut signature, &mut message, &mut public_key).is_err());
+    }
+
+    #[test]
+    fn it_should_not_verify_modified_messages_or_short_signatures() {
+        let (mut public_key, mut secret_key) = keypair(1);
+        let (mut message, mut signature) = signed_message(&mut secret_key);
+        {
+            let mut message = message.write_lock();
+            message[0] ^= 1;
+        }
+        assert!(verify(&mut signature, &mut message, &mut public_key).is_err());
+
+        let mut short = SecBuf::with_insecure(BYTES - 1);
+        assert!(verify(&mut short, &mut message, &mut public_key).is_err());
+    }
+}
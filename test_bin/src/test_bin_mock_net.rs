@@ -8,7 +8,7 @@ extern crate serde_json;
 use holochain_net_connection::{
     net_connection::NetConnection,
     protocol::Protocol,
-    protocol_wrapper::{MessageData, ProtocolWrapper, TrackAppData},
+    protocol_wrapper::{MessageData, PeerData, ProtocolWrapper, TrackAppData},
     NetResult,
 };
 
@@ -76,6 +76,24 @@ fn exec() -> NetResult<()> {
         .into(),
     )?;
 
+    // the nodes get told about each other
+    let res = ProtocolWrapper::try_from(receiver1.recv()?)?;
+    println!("got: {:?}", res);
+    assert_eq!(
+        res,
+        ProtocolWrapper::PeerConnected(PeerData {
+            id: "node-2".to_string(),
+        })
+    );
+    let res = ProtocolWrapper::try_from(receiver2.recv()?)?;
+    println!("got: {:?}", res);
+    assert_eq!(
+        res,
+        ProtocolWrapper::PeerConnected(PeerData {
+            id: "node-1".to_string(),
+        })
+    );
+
     con1.send(
         ProtocolWrapper::SendMessage(MessageData {
             dna_hash: "sandwich".to_string(),
@@ -8,3 +8,14 @@ pub struct ZomeFnCallArgs {
     pub fn_name: String,
     pub fn_args: String,
 }
+
+/// Struct for input data received when Zome API function call_bridge() is invoked:
+/// a call to the instance bridged to under `handle`, see `BridgeDeclaration`
+#[derive(Deserialize, Default, Clone, PartialEq, Eq, Hash, Debug, Serialize, DefaultJson)]
+pub struct BridgeCallArgs {
+    pub handle: String,
+    pub zome_name: String,
+    pub cap_name: String,
+    pub fn_name: String,
+    pub fn_args: String,
+}
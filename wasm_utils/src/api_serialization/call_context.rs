@@ -0,0 +1,64 @@
+use holochain_core_types::{
+    cas::content::Address, error::HolochainError, hash::HashString, json::*,
+};
+
+/// Where a zome function call came from.
+/// Core sets this for every call it makes and the called function can read it through
+/// the Zome API globals, e.g. to decide whether to trust the caller.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, Hash, DefaultJson)]
+pub enum CallContext {
+    /// Called by a client through one of the container's interfaces.
+    /// Until there are capability tokens, the token is the id of that interface.
+    Interface { token_id: String },
+    /// Called by another instance of the container over a configured bridge
+    Bridge {
+        caller_instance_dna_hash: HashString,
+    },
+    /// Called in reaction to a direct message from another agent
+    DirectMessage { from_agent: Address },
+    /// Called by core itself, e.g. the genesis callback
+    Callback,
+    /// Called by core to validate an entry or link, or to prepare that validation.
+    /// Every node has to come to the same result here, so non-deterministic
+    /// Zome API functions refuse to run in this context.
+    Validation,
+}
+
+impl Default for CallContext {
+    fn default() -> Self {
+        CallContext::Callback
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn call_context_json_roundtrip() {
+        let contexts = vec![
+            CallContext::Interface {
+                token_id: String::from("websocket interface"),
+            },
+            CallContext::Bridge {
+                caller_instance_dna_hash: HashString::from("QmCaller"),
+            },
+            CallContext::DirectMessage {
+                from_agent: Address::from("QmAgent"),
+            },
+            CallContext::Callback,
+            CallContext::Validation,
+        ];
+        for context in contexts {
+            let json = JsonString::from(context.clone());
+            assert_eq!(context, CallContext::try_from(json).unwrap());
+        }
+        assert_eq!(
+            JsonString::from(CallContext::Interface {
+                token_id: String::from("ws"),
+            }),
+            JsonString::from("{\"Interface\":{\"token_id\":\"ws\"}}"),
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use holochain_core_types::{cas::content::Address, error::HolochainError, json::*, time::Iso8601};
+use serde_json;
+
+/// State of the source chain at the moment core calls into a zome.
+/// Core hands this to every entry point that takes it as a second argument,
+/// so that the zome sees the chain as of the current call and not as of some earlier one.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, DefaultJson)]
+pub struct CallInitData {
+    /// Address of the most recent header, None if the chain is empty
+    pub chain_top: Option<Address>,
+    /// Number of entries (i.e. headers) in the chain
+    pub chain_length: usize,
+    /// When core started the call
+    pub call_time: Iso8601,
+    /// What the configuration of the instance hands to genesis, an empty object if nothing.
+    /// Not part of the DNA, so other nodes validating our entries don't know them.
+    #[serde(default)]
+    pub genesis_params: serde_json::Value,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn call_init_data_json_roundtrip() {
+        let call_init_data = CallInitData {
+            chain_top: Some(Address::from("QmTop")),
+            chain_length: 3,
+            call_time: Iso8601::from("2018-10-11T03:23:38+00:00"),
+            genesis_params: serde_json::from_str("{\"progenitor\":\"QmProgenitor\"}").unwrap(),
+        };
+        let json = JsonString::from(call_init_data.clone());
+        assert_eq!(
+            json,
+            JsonString::from(
+                "{\"chain_top\":\"QmTop\",\"chain_length\":3,\"call_time\":\"2018-10-11T03:23:38+00:00\",\"genesis_params\":{\"progenitor\":\"QmProgenitor\"}}"
+            ),
+        );
+        assert_eq!(call_init_data, CallInitData::try_from(json).unwrap());
+    }
+}
@@ -0,0 +1,78 @@
+use holochain_core_types::{cas::content::Address, entry::Entry, error::HolochainError, json::*};
+
+/// Options of Zome API function commit_entry_with_options()
+#[derive(Deserialize, Default, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct CommitOptions {
+    /// Only commit if this is still the address of the top chain header, and fail with
+    /// ChainHeadMoved otherwise. By default the entry gets committed on top of whatever
+    /// the top of the chain is by then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub require_head: Option<Address>,
+
+    /// Whether and how long the commit waits for the entry to get published
+    #[serde(default)]
+    pub publish: PublishMode,
+}
+
+/// What a commit does about publishing the entry to the DHT
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize)]
+pub enum PublishMode {
+    /// Returns once at least that many nodes acknowledged holding the entry, so that other
+    /// agents can get it right away. Fails with Timeout if they don't in time, e.g. because
+    /// they rejected it, but the entry stays committed.
+    Block { min_holders: usize },
+    /// Returns once the entry got sent to the network, without waiting for holders
+    Background,
+    /// Only commits the entry to the local chain
+    Skip,
+}
+
+impl Default for PublishMode {
+    fn default() -> Self {
+        PublishMode::Background
+    }
+}
+
+/// Struct for input data received when Zome API function await_publish() is invoked
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct AwaitPublishArgs {
+    pub address: Address,
+    pub min_holders: usize,
+    pub timeout_ms: u64,
+}
+
+/// Struct for the result of Zome API function get_publish_status():
+/// how an entry this agent published fared with the nodes asked to hold it
+#[derive(Deserialize, Default, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct PublishStatus {
+    /// Number of nodes that acknowledged holding the entry
+    pub holders: usize,
+    /// The nodes that rejected the entry, with the reason their validation gave
+    pub rejections: Vec<(Address, String)>,
+}
+
+/// Struct for input data received when Zome API function commit_entry_with_options() is invoked
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct CommitEntryArgs {
+    pub entry: Entry,
+    pub options: CommitOptions,
+}
+
+/// Struct for the result of Zome API function commit_entry()
+#[derive(Deserialize, Default, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct CommitEntryResult {
+    pub address: Address,
+    /// Address of the chain header the commit produced.
+    /// Defaults to an empty address if the core did not report one.
+    #[serde(default)]
+    pub header_address: Address,
+}
+
+impl CommitEntryResult {
+    pub fn new(address: Address, header_address: Address) -> Self {
+        CommitEntryResult {
+            address,
+            header_address,
+        }
+    }
+}
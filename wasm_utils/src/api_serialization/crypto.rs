@@ -0,0 +1,15 @@
+use holochain_core_types::{cas::content::Address, error::HolochainError, json::*};
+
+/// Struct for input data received when Zome API function encrypt_for() is invoked
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct EncryptForArgs {
+    /// The agent who will be able to decrypt the payload
+    pub recipient: Address,
+    pub payload: Vec<u8>,
+}
+
+/// Struct for input data received when Zome API function decrypt() is invoked
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct DecryptArgs {
+    pub payload: Vec<u8>,
+}
@@ -0,0 +1,30 @@
+use holochain_core_types::{
+    dna::zome::entry_types::{self, LinkedFrom, LinksTo, Sharing},
+    error::HolochainError,
+    json::*,
+};
+
+/// An entry type as declared in the DNA, returned by Zome API function entry_type_properties()
+#[derive(Deserialize, Clone, PartialEq, Debug, Default, Serialize, DefaultJson)]
+pub struct EntryTypeDef {
+    pub name: String,
+    /// The zome that declares the entry type
+    pub zome_name: String,
+    pub description: String,
+    pub sharing: Sharing,
+    pub links_to: Vec<LinksTo>,
+    pub linked_from: Vec<LinkedFrom>,
+}
+
+impl EntryTypeDef {
+    pub fn new(name: &str, zome_name: &str, entry_type_def: &entry_types::EntryTypeDef) -> Self {
+        EntryTypeDef {
+            name: name.to_string(),
+            zome_name: zome_name.to_string(),
+            description: entry_type_def.description.clone(),
+            sharing: entry_type_def.sharing.clone(),
+            links_to: entry_type_def.links_to.clone(),
+            linked_from: entry_type_def.linked_from.clone(),
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use holochain_core_types::{
     cas::content::{Address, AddressableContent},
     crud_status::CrudStatus,
-    entry::{Entry, EntryWithMeta},
+    entry::{Entry, EntryMeta, EntryWithMeta},
     error::HolochainError,
     json::*,
 };
@@ -22,26 +22,64 @@ impl Default for StatusRequestKind {
 #[derive(Deserialize, Debug, Serialize, DefaultJson, Clone)]
 pub struct GetEntryOptions {
     pub status_request: StatusRequestKind,
+    /// Whether the EntryHistory should tell the `meta` of its entries
+    #[serde(default)]
+    pub with_meta: bool,
 }
 
 impl Default for GetEntryOptions {
     fn default() -> Self {
         GetEntryOptions {
             status_request: StatusRequestKind::default(),
+            with_meta: false,
         }
     }
 }
 
 impl GetEntryOptions {
     pub fn new(status_request: StatusRequestKind) -> Self {
-        GetEntryOptions { status_request }
+        GetEntryOptions {
+            status_request,
+            with_meta: false,
+        }
     }
 }
 
+fn default_resolve_latest() -> bool {
+    true
+}
+
 #[derive(Deserialize, Debug, Serialize, DefaultJson)]
 pub struct GetEntryArgs {
     pub address: Address,
     pub options: GetEntryOptions,
+    /// Whether to follow the crud-links of updated entries to their latest version.
+    /// Without, the result is the entry at the given address, whatever its crud-status.
+    #[serde(default = "default_resolve_latest")]
+    pub resolve_latest: bool,
+}
+
+/// Outcome of a get request.
+/// Cores that predate Deleted and Timeout only ever report Found or NotFound, and results of
+/// cores that predate the status altogether are Unknown, whether they have entries or not.
+/// Pruned means the entry is on the local source chain, but its content got pruned,
+/// see `EntryTypeDef::prunable`.
+/// Expired means the entry is of a type with a TTL and older than that, see
+/// `EntryTypeDef::ttl_seconds`. Like NotFound, it comes without the entry.
+#[derive(Deserialize, Debug, Serialize, DefaultJson, Clone, Copy, PartialEq)]
+pub enum GetResultStatus {
+    Found,
+    NotFound,
+    Deleted,
+    Timeout,
+    Pruned,
+    Expired,
+    Unknown,
+}
+impl Default for GetResultStatus {
+    fn default() -> Self {
+        GetResultStatus::Unknown
+    }
 }
 
 #[derive(Deserialize, Debug, Serialize, DefaultJson)]
@@ -50,6 +88,16 @@ pub struct EntryHistory {
     pub entries: Vec<Entry>,
     pub crud_status: Vec<CrudStatus>,
     pub crud_links: HashMap<Address, Address>,
+    #[serde(default)]
+    pub status: GetResultStatus,
+    /// Address of the last version in the history, which differs from the requested
+    /// address if the request followed updates of the entry
+    #[serde(default)]
+    pub resolved_address: Option<Address>,
+    /// What the holders know about each of the entries, in the same order,
+    /// if the request was made `with_meta`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub meta: Vec<EntryMeta>,
 }
 
 impl EntryHistory {
@@ -59,16 +107,68 @@ impl EntryHistory {
             entries: Vec::new(),
             crud_status: Vec::new(),
             crud_links: HashMap::new(),
+            status: GetResultStatus::NotFound,
+            resolved_address: None,
+            meta: Vec::new(),
         }
     }
 
+    /// Adds an entry to the history.
+    /// The status and resolved address of the history follow the last entry pushed.
     pub fn push(&mut self, entry_with_meta: &EntryWithMeta) {
+        self.status = if entry_with_meta.crud_status == CrudStatus::DELETED {
+            GetResultStatus::Deleted
+        } else {
+            GetResultStatus::Found
+        };
         let address = entry_with_meta.entry.address();
+        self.resolved_address = Some(address.clone());
         self.addresses.push(address.clone());
         self.entries.push(entry_with_meta.entry.clone());
         self.crud_status.push(entry_with_meta.crud_status);
+        self.meta.push(entry_with_meta.meta.clone());
         if let Some(new_address) = entry_with_meta.maybe_crud_link.clone() {
             self.crud_links.insert(address, new_address);
         }
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use holochain_core_types::entry::test_entry;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn entry_history_status_follows_crud_status() {
+        let mut entry_history = EntryHistory::new();
+        assert_eq!(GetResultStatus::NotFound, entry_history.status);
+
+        entry_history.push(&EntryWithMeta::new(test_entry(), CrudStatus::LIVE, None));
+        assert_eq!(GetResultStatus::Found, entry_history.status);
+
+        entry_history.push(&EntryWithMeta::new(test_entry(), CrudStatus::DELETED, None));
+        assert_eq!(GetResultStatus::Deleted, entry_history.status);
+    }
+
+    #[test]
+    fn entry_history_without_status_deserializes() {
+        let entry_history = EntryHistory::try_from(JsonString::from(
+            "{\"addresses\":[],\"entries\":[],\"crud_status\":[],\"crud_links\":{}}",
+        ))
+        .unwrap();
+        assert_eq!(GetResultStatus::Unknown, entry_history.status);
+        assert_eq!(None, entry_history.resolved_address);
+        assert!(entry_history.meta.is_empty());
+    }
+
+    #[test]
+    fn get_entry_args_resolve_latest_by_default() {
+        let args = GetEntryArgs::try_from(JsonString::from(
+            "{\"address\":\"QmA\",\"options\":{\"status_request\":\"Latest\"}}",
+        ))
+        .unwrap();
+        assert!(args.resolve_latest);
+        assert!(!args.options.with_meta);
+    }
+}
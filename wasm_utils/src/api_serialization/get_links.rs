@@ -1,22 +1,119 @@
-use holochain_core_types::{cas::content::Address, error::HolochainError, json::*};
+use holochain_core_types::{
+    cas::content::Address, crud_status::CrudStatus, error::HolochainError, json::*,
+};
+
+/// Which links get_links returns, depending on the CRUD status of their targets
+#[derive(Deserialize, Debug, Serialize, DefaultJson, Clone, PartialEq, Eq, Hash)]
+pub enum LinksStatusFilter {
+    /// Links whose target is not deleted
+    Live,
+    /// All links, with the status of their target
+    All,
+    /// Only links whose target is deleted
+    Deleted,
+}
+impl Default for LinksStatusFilter {
+    fn default() -> Self {
+        LinksStatusFilter::Live
+    }
+}
+
+impl LinksStatusFilter {
+    /// Whether a link whose target has the given status passes the filter
+    pub fn includes(&self, target_status: CrudStatus) -> bool {
+        match self {
+            LinksStatusFilter::Live => target_status != CrudStatus::DELETED,
+            LinksStatusFilter::All => true,
+            LinksStatusFilter::Deleted => target_status == CrudStatus::DELETED,
+        }
+    }
+}
+
+/// Which way get_links follows the links of `entry_address`
+#[derive(Deserialize, Debug, Serialize, DefaultJson, Clone, PartialEq, Eq, Hash)]
+pub enum GetLinksDirection {
+    /// Links from it as their base, returns their targets
+    From,
+    /// Links to it as their target, returns their bases
+    To,
+}
+impl Default for GetLinksDirection {
+    fn default() -> Self {
+        GetLinksDirection::From
+    }
+}
 
 #[derive(Deserialize, Default, Debug, Serialize, Clone, PartialEq, Eq, Hash, DefaultJson)]
 pub struct GetLinksArgs {
     pub entry_address: Address,
     pub tag: String,
+    /// Only return links to entries of this type,
+    /// or from entries of this type when going in the `To` direction
+    #[serde(default)]
+    pub target_entry_type: Option<String>,
+    #[serde(default)]
+    pub status_request: LinksStatusFilter,
+    #[serde(default)]
+    pub direction: GetLinksDirection,
+}
+
+/// Arguments of count_links, which counts the links get_links would return
+#[derive(Deserialize, Default, Debug, Serialize, Clone, PartialEq, Eq, Hash, DefaultJson)]
+pub struct CountLinksArgs {
+    pub entry_address: Address,
+    pub tag: String,
+    #[serde(default)]
+    pub status_request: LinksStatusFilter,
 }
 
 #[derive(Deserialize, Serialize, Debug, DefaultJson)]
 pub struct GetLinksResult {
     addresses: Vec<Address>,
+    /// Number of links left out when filtering by target entry type
+    /// because the type of their target could not be determined
+    #[serde(default)]
+    skipped: usize,
+    /// Status of the target of each link, in the order of `addresses`.
+    /// Only there when all links got requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    statuses: Option<Vec<CrudStatus>>,
 }
 
 impl GetLinksResult {
     pub fn new(addresses: Vec<Address>) -> GetLinksResult {
-        GetLinksResult { addresses }
+        GetLinksResult {
+            addresses,
+            skipped: 0,
+            statuses: None,
+        }
+    }
+
+    pub fn with_skipped(addresses: Vec<Address>, skipped: usize) -> GetLinksResult {
+        GetLinksResult {
+            addresses,
+            skipped,
+            statuses: None,
+        }
+    }
+
+    pub fn with_statuses(links: Vec<(Address, CrudStatus)>, skipped: usize) -> GetLinksResult {
+        let (addresses, statuses) = links.into_iter().unzip();
+        GetLinksResult {
+            addresses,
+            skipped,
+            statuses: Some(statuses),
+        }
     }
 
     pub fn addresses(&self) -> &Vec<Address> {
         &self.addresses
     }
+
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    pub fn statuses(&self) -> Option<&Vec<CrudStatus>> {
+        self.statuses.as_ref()
+    }
 }
@@ -1,4 +1,7 @@
-use holochain_core_types::{cas::content::Address, error::HolochainError, json::*, link::Link};
+use super::CommitEntryResult;
+use holochain_core_types::{
+    cas::content::Address, entry::Entry, error::HolochainError, json::*, link::Link,
+};
 
 #[derive(Deserialize, Default, Debug, Serialize, DefaultJson)]
 pub struct LinkEntriesArgs {
@@ -12,3 +15,27 @@ impl LinkEntriesArgs {
         Link::new(&self.base, &self.target, &self.tag)
     }
 }
+
+/// Struct for input data received when Zome API function commit_entry_and_link() is invoked:
+/// the entry to commit and the bases to link it from, each with the tag of its link
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct CommitAndLinkArgs {
+    pub entry: Entry,
+    pub links: Vec<(Address, String)>,
+}
+
+/// Struct for the result of Zome API function commit_entry_and_link().
+/// The entry got committed, each link has the outcome of its commit, in the order they were
+/// given. Links that failed, e.g. their validation, are not on the chain, the others are.
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct CommitAndLinkResult {
+    pub entry: CommitEntryResult,
+    pub links: Vec<Result<CommitEntryResult, HolochainError>>,
+}
+
+impl CommitAndLinkResult {
+    /// Whether all of the links got committed
+    pub fn all_linked(&self) -> bool {
+        self.links.iter().all(|link| link.is_ok())
+    }
+}
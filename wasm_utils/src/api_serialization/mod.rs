@@ -1,4 +1,9 @@
 mod call;
+mod call_context;
+mod call_init_data;
+mod commit;
+mod crypto;
+mod entry_type;
 /// This module holds structs for all arguments and return types
 /// that get serialized and deserialized between core native and
 /// the WASM based ribosome.
@@ -13,8 +18,13 @@ pub mod get_entry;
 pub mod get_links;
 pub mod link_entries;
 pub mod query;
+mod random;
+mod sleep;
 mod update_entry;
 pub mod validation;
 mod zome_api_globals;
 
-pub use self::{call::*, query::*, update_entry::*, zome_api_globals::*};
+pub use self::{
+    call::*, call_context::*, call_init_data::*, commit::*, crypto::*, entry_type::*, query::*,
+    random::*, sleep::*, update_entry::*, zome_api_globals::*,
+};
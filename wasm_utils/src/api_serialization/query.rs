@@ -1,4 +1,5 @@
 use holochain_core_types::{cas::content::Address, error::HolochainError, json::*};
+use std::{collections::BTreeMap, iter};
 
 #[derive(Deserialize, Default, Debug, Serialize, DefaultJson)]
 pub struct QueryArgs {
@@ -8,3 +9,99 @@ pub struct QueryArgs {
 }
 
 pub type QueryResult = Vec<Address>;
+
+#[derive(Deserialize, Default, Debug, Serialize, DefaultJson)]
+pub struct QueryPageArgs {
+    pub entry_type_name: String,
+    /// Maximum number of entries in the page, 0 for all that are left
+    pub limit: u32,
+    /// Where the page starts, see `QueryPage::next_cursor`. None starts at the top of the chain.
+    #[serde(default)]
+    pub cursor: Option<Address>,
+}
+
+/// One page of entries from the agent's own source chain, newest first
+#[derive(Deserialize, Default, Debug, Serialize, DefaultJson, Clone, PartialEq)]
+pub struct QueryPage {
+    pub addresses: Vec<Address>,
+    /// Address of the header of the page's last entry, which continues the query with the
+    /// entries before it. None if the page reaches back to the beginning of the chain.
+    /// New commits don't change which entries come after a cursor.
+    pub next_cursor: Option<Address>,
+}
+
+/// Statistics about the agent's own source chain
+#[derive(Deserialize, Default, Debug, Serialize, DefaultJson, Clone, PartialEq)]
+pub struct ChainInfo {
+    /// Number of entries (i.e. headers) in the chain
+    pub length: usize,
+    /// Number of entries per entry type name, for every type present in the chain
+    pub entry_type_counts: BTreeMap<String, usize>,
+    /// Address of the most recent header, None if the chain is empty
+    pub top_header_address: Option<Address>,
+}
+
+impl ChainInfo {
+    /// Number of entries whose type name matches the given pattern.
+    /// A `*` in the pattern matches any sequence of characters, so `"*"` counts all entries
+    /// and `"%*"` all system entries.
+    pub fn count(&self, entry_type_pattern: &str) -> usize {
+        self.entry_type_counts
+            .iter()
+            .filter(|(entry_type_name, _)| pattern_matches(entry_type_pattern, entry_type_name))
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
+
+fn pattern_matches(pattern: &str, name: &str) -> bool {
+    match pattern.find('*') {
+        None => pattern == name,
+        Some(index) => {
+            let (prefix, rest) = (&pattern[..index], &pattern[index + 1..]);
+            name.starts_with(prefix)
+                && name
+                    .char_indices()
+                    .map(|(i, _)| i)
+                    .chain(iter::once(name.len()))
+                    .filter(|i| *i >= prefix.len())
+                    .any(|i| pattern_matches(rest, &name[i..]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_chain_info() -> ChainInfo {
+        let mut entry_type_counts = BTreeMap::new();
+        entry_type_counts.insert("%dna".to_string(), 1);
+        entry_type_counts.insert("%agent_id".to_string(), 1);
+        entry_type_counts.insert("post".to_string(), 3);
+        entry_type_counts.insert("post_comment".to_string(), 2);
+        ChainInfo {
+            length: 7,
+            entry_type_counts,
+            top_header_address: Some(Address::from("QmTop")),
+        }
+    }
+
+    #[test]
+    fn count_by_exact_type() {
+        let chain_info = test_chain_info();
+        assert_eq!(chain_info.count("post"), 3);
+        assert_eq!(chain_info.count("%dna"), 1);
+        assert_eq!(chain_info.count("unknown"), 0);
+    }
+
+    #[test]
+    fn count_by_pattern() {
+        let chain_info = test_chain_info();
+        assert_eq!(chain_info.count("*"), 7);
+        assert_eq!(chain_info.count("%*"), 2);
+        assert_eq!(chain_info.count("post*"), 5);
+        assert_eq!(chain_info.count("*comment"), 2);
+        assert_eq!(chain_info.count("p*t"), 3);
+    }
+}
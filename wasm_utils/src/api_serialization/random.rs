@@ -0,0 +1,9 @@
+use holochain_core_types::{error::HolochainError, json::*};
+
+/// The most random bytes a single call of `hc_random` returns
+pub const RANDOM_BYTES_MAX_LEN: usize = 4096;
+
+#[derive(Deserialize, Default, Debug, Serialize, DefaultJson)]
+pub struct RandomBytesArgs {
+    pub len: usize,
+}
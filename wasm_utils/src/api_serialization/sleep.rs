@@ -0,0 +1,6 @@
+use holochain_core_types::{error::HolochainError, json::*};
+
+#[derive(Deserialize, Default, Debug, Serialize, DefaultJson)]
+pub struct SleepArgs {
+    pub millis: u64,
+}
@@ -6,3 +6,9 @@ pub struct UpdateEntryArgs {
     pub new_entry: Entry,
     pub address: Address,
 }
+
+/// Struct for input data received when Zome API function update_agent() is invoked
+#[derive(Deserialize, Clone, PartialEq, Debug, Serialize, DefaultJson)]
+pub struct UpdateAgentArgs {
+    pub nick: String,
+}
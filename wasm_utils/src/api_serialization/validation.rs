@@ -13,6 +13,12 @@ pub struct EntryValidationArgs {
     pub validation_data: ValidationData,
 }
 
+#[derive(Deserialize, Debug, Serialize, DefaultJson)]
+pub struct EntryValidationDependenciesArgs {
+    pub entry_type: EntryType,
+    pub entry: Entry,
+}
+
 #[derive(Deserialize, Debug, Serialize, DefaultJson, PartialEq, Clone)]
 pub enum LinkDirection {
     To,
@@ -1,3 +1,4 @@
+use super::CallContext;
 use holochain_core_types::{
     cas::content::Address, error::HolochainError, hash::HashString, json::*,
 };
@@ -6,8 +7,18 @@ use holochain_core_types::{
 pub struct ZomeApiGlobals {
     pub dna_name: String,
     pub dna_hash: HashString,
+    /// Address of the DNA entry at the start of the source chain
+    #[serde(default)]
+    pub dna_entry_address: Address,
     pub agent_id_str: String,
     pub agent_address: Address,
     pub agent_initial_hash: HashString,
     pub agent_latest_hash: HashString,
+    /// True if the agent fields are placeholders because the agent's identity entry
+    /// is not on the chain yet, e.g. early during genesis.
+    #[serde(default)]
+    pub agent_provisional: bool,
+    /// Where the zome function call currently running came from
+    #[serde(default)]
+    pub call_context: CallContext,
 }
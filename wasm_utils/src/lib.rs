@@ -17,3 +17,4 @@ pub mod api_serialization;
 pub mod macros;
 pub mod memory_allocation;
 pub mod memory_serialization;
+pub mod ribosome;
@@ -6,6 +6,14 @@ use holochain_core_types::error::{RibosomeErrorCode, RibosomeReturnCode};
 
 pub const U16_MAX: u32 = <u16>::max_value() as u32;
 
+/// The size of a WASM memory page. Every zome's memory spans at least the first one,
+/// which is all a SinglePageAllocation can address.
+pub const WASM_PAGE_SIZE: usize = 1 << 16;
+
+/// The largest payload host and zomes accept from each other by default.
+/// Payloads have to fit the single page they're passed in, and so do entries.
+pub const DEFAULT_MAX_PAYLOAD_SIZE: u32 = U16_MAX;
+
 /// returns the u16 high bits from a u32
 pub fn u32_high_bits(i: u32) -> u16 {
     (i >> 16) as u16
@@ -41,6 +49,29 @@ pub fn decode_encoded_allocation(
     }
 }
 
+/// Decodes an encoded allocation that should hold a payload, e.g. the argument of a host call,
+/// without trusting it: it has to lie within the first `memory_size` bytes of memory
+/// and within the first page, and hold at most `max_payload_size` bytes.
+/// Unlike decode_encoded_allocation(), encodings of return codes are errors here.
+pub fn decode_payload_allocation(
+    encoded_allocation: u32,
+    memory_size: usize,
+    max_payload_size: u32,
+) -> Result<SinglePageAllocation, RibosomeErrorCode> {
+    let (offset, length) = u32_split_bits(encoded_allocation);
+    if length == 0 {
+        return Err(RibosomeErrorCode::ZeroLength);
+    }
+    let end = offset as usize + length as usize;
+    if end > memory_size || end > U16_MAX as usize {
+        return Err(RibosomeErrorCode::OutOfBounds);
+    }
+    if u32::from(length) > max_payload_size {
+        return Err(RibosomeErrorCode::PayloadTooLarge);
+    }
+    SinglePageAllocation::new(offset, length)
+}
+
 //--------------------------------------------------------------------------------------------------
 // Single Page Memory Allocation
 //--------------------------------------------------------------------------------------------------
@@ -234,6 +265,77 @@ pub mod tests {
         );
     }
 
+    #[test]
+    /// tests that payload allocations get checked against memory size and payload limit
+    fn can_decode_payload_allocation() {
+        let decode = |offset, length, memory_size, max_payload_size| {
+            decode_payload_allocation(
+                u32_merge_bits(offset, length),
+                memory_size,
+                max_payload_size,
+            )
+            .map(|allocation| (allocation.offset(), allocation.length()))
+        };
+        assert_eq!(Ok((10, 20)), decode(10, 20, WASM_PAGE_SIZE, U16_MAX));
+        assert_eq!(Ok((10, 20)), decode(10, 20, 30, 20));
+        assert_eq!(
+            Err(RibosomeErrorCode::ZeroLength),
+            decode(0, 0, WASM_PAGE_SIZE, U16_MAX)
+        );
+        assert_eq!(
+            Err(RibosomeErrorCode::ZeroLength),
+            decode(3, 0, WASM_PAGE_SIZE, U16_MAX)
+        );
+        assert_eq!(
+            Err(RibosomeErrorCode::OutOfBounds),
+            decode(10, 21, 30, U16_MAX)
+        );
+        assert_eq!(
+            Err(RibosomeErrorCode::OutOfBounds),
+            decode(<u16>::max_value(), 1, 4 * WASM_PAGE_SIZE, U16_MAX)
+        );
+        assert_eq!(
+            Err(RibosomeErrorCode::OutOfBounds),
+            decode(10, 20, 0, U16_MAX)
+        );
+        assert_eq!(
+            Err(RibosomeErrorCode::PayloadTooLarge),
+            decode(10, 21, WASM_PAGE_SIZE, 20)
+        );
+    }
+
+    #[test]
+    /// feeds pseudo-random encodings to the decoders, which must never panic and only
+    /// accept payloads within bounds
+    fn decoders_survive_random_encodings() {
+        // xorshift, so that failures can be reproduced
+        let mut state: u32 = 0x9E37_79B9;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+        for _ in 0..100_000 {
+            let encoded_allocation = next();
+            let memory_size = (next() % (2 * WASM_PAGE_SIZE as u32)) as usize;
+            let max_payload_size = next() % (U16_MAX + 1);
+
+            let _ = decode_encoded_allocation(encoded_allocation);
+            let _ = SinglePageAllocation::from_encoded_allocation(encoded_allocation);
+            let _ = SinglePageStack::from_encoded_allocation(encoded_allocation);
+            if let Ok(allocation) =
+                decode_payload_allocation(encoded_allocation, memory_size, max_payload_size)
+            {
+                let end = allocation.offset() as usize + allocation.length() as usize;
+                assert!(allocation.length() > 0);
+                assert!(end <= memory_size);
+                assert!(u32::from(allocation.length()) <= max_payload_size);
+                assert_eq!(encoded_allocation, allocation.encode());
+            }
+        }
+    }
+
     #[test]
     /// tests that a SinglePageAllocation returns its encoded offset/length pair as u32
     fn can_single_page_allocation_encode() {
@@ -1,5 +1,6 @@
 use crate::memory_allocation::{
-    decode_encoded_allocation, SinglePageAllocation, SinglePageStack, U16_MAX,
+    decode_payload_allocation, u32_split_bits, SinglePageAllocation, SinglePageStack,
+    DEFAULT_MAX_PAYLOAD_SIZE, U16_MAX, WASM_PAGE_SIZE,
 };
 use holochain_core_types::{
     error::{CoreError, HolochainError, RibosomeErrorCode, RibosomeReturnCode},
@@ -7,7 +8,7 @@ use holochain_core_types::{
 };
 use serde::Deserialize;
 use serde_json;
-use std::{convert::TryInto, ffi::CStr, os::raw::c_char, slice};
+use std::{convert::TryInto, ffi::CStr, os::raw::c_char, slice, str};
 
 //-------------------------------------------------------------------------------------------------
 // Raw
@@ -19,6 +20,45 @@ fn load_str_from_raw<'a>(ptr_data: *mut c_char) -> &'a str {
     ptr_safe_c_str.to_str().unwrap()
 }
 
+/// The string in a payload the host wrote, up to the null byte the host terminates it with.
+/// Fails with ZeroLength if it is empty and ArgumentDeserializationFailed if it is no UTF-8.
+pub fn payload_str(payload: &[u8]) -> Result<&str, RibosomeErrorCode> {
+    let end = payload
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or_else(|| payload.len());
+    if end == 0 {
+        return Err(RibosomeErrorCode::ZeroLength);
+    }
+    str::from_utf8(&payload[..end]).map_err(|_| RibosomeErrorCode::ArgumentDeserializationFailed)
+}
+
+/// The string stored in the allocation the host encoded, see payload_str().
+/// Encodings of return codes give back the error they encode, or ZeroSizedAllocation for
+/// success, as the allocation holds nothing then. The allocation has to lie in the first page
+/// and hold at most `max_payload_size` bytes, anything the host sent is not to be trusted.
+fn load_payload_str<'a>(
+    encoded_allocation: u32,
+    max_payload_size: u32,
+) -> Result<&'a str, RibosomeErrorCode> {
+    let (offset, length) = u32_split_bits(encoded_allocation);
+    if length == 0 {
+        return Err(match RibosomeReturnCode::from_offset(offset) {
+            RibosomeReturnCode::Success => RibosomeErrorCode::ZeroSizedAllocation,
+            RibosomeReturnCode::Failure(err_code) => err_code,
+        });
+    }
+    let allocation =
+        decode_payload_allocation(encoded_allocation, WASM_PAGE_SIZE, max_payload_size)?;
+    let payload = unsafe {
+        slice::from_raw_parts(
+            allocation.offset() as *const u8,
+            allocation.length() as usize,
+        )
+    };
+    payload_str(payload)
+}
+
 /// Write in wasm memory according to stack state.
 fn write_in_wasm_memory(
     stack: &mut SinglePageStack,
@@ -60,16 +100,9 @@ pub fn store_string_into_encoded_allocation(stack: &mut SinglePageStack, s: &str
 }
 
 /// Retrieve a stored string from an encoded allocation.
-/// Return error code if encoded_allocation is invalid.
+/// Return error code if encoded_allocation is invalid, see load_json().
 pub fn load_string(encoded_allocation: u32) -> Result<String, RibosomeErrorCode> {
-    let maybe_allocation = decode_encoded_allocation(encoded_allocation);
-    match maybe_allocation {
-        Err(return_code) => match return_code {
-            RibosomeReturnCode::Success => Err(RibosomeErrorCode::ZeroSizedAllocation),
-            RibosomeReturnCode::Failure(err_code) => Err(err_code),
-        },
-        Ok(allocation) => Ok(load_str_from_raw(allocation.offset() as *mut c_char).to_string()),
-    }
+    load_payload_str(encoded_allocation, DEFAULT_MAX_PAYLOAD_SIZE).map(String::from)
 }
 
 //-------------------------------------------------------------------------------------------------
@@ -102,21 +135,25 @@ pub fn store_as_json_into_encoded_allocation<J: TryInto<JsonString>>(
 }
 
 /// Retrieve a stored data struct from an encoded allocation.
-/// Return error string if encoded_allocation is invalid.
+/// Return error string if encoded_allocation is invalid: OutOfBounds if it reaches beyond
+/// the first page, PayloadTooLarge if it holds more than DEFAULT_MAX_PAYLOAD_SIZE bytes
+/// and ZeroLength if it holds an empty string.
 pub fn load_json<'s, T: Deserialize<'s>>(encoded_allocation: u32) -> Result<T, HolochainError> {
-    let maybe_allocation = decode_encoded_allocation(encoded_allocation);
-    match maybe_allocation {
-        Err(return_code) => match return_code {
-            RibosomeReturnCode::Success => Err(HolochainError::Ribosome(
-                RibosomeErrorCode::ZeroSizedAllocation,
-            )),
-            RibosomeReturnCode::Failure(err_code) => Err(HolochainError::Ribosome(err_code)),
-        },
-        Ok(allocation) => load_json_from_raw(allocation.offset() as *mut c_char),
-    }
+    load_json_with_limit(encoded_allocation, DEFAULT_MAX_PAYLOAD_SIZE)
+}
+
+/// Like load_json() but with a custom limit for the size of the payload
+pub fn load_json_with_limit<'s, T: Deserialize<'s>>(
+    encoded_allocation: u32,
+    max_payload_size: u32,
+) -> Result<T, HolochainError> {
+    let stored_str =
+        load_payload_str(encoded_allocation, max_payload_size).map_err(HolochainError::Ribosome)?;
+    load_json_from_str(stored_str)
 }
 
 /// Convert a json string stored in wasm memory into a specified struct
+/// Reads up to the next null byte, prefer load_json() which stays within its allocation.
 /// If json deserialization of custom struct failed, tries to deserialize a CoreError struct.
 /// If that also failed, tries to load a string directly, since we are expecting an error string at this stage.
 #[allow(unknown_lints)]
@@ -124,7 +161,13 @@ pub fn load_json<'s, T: Deserialize<'s>>(encoded_allocation: u32) -> Result<T, H
 pub fn load_json_from_raw<'s, T: Deserialize<'s>>(
     ptr_data: *mut c_char,
 ) -> Result<T, HolochainError> {
-    let stored_str = load_str_from_raw(ptr_data);
+    load_json_from_str(load_str_from_raw(ptr_data))
+}
+
+/// Convert a json string into a specified struct, the same way as load_json_from_raw()
+pub fn load_json_from_str<'s, T: Deserialize<'s>>(
+    stored_str: &'s str,
+) -> Result<T, HolochainError> {
     let maybe_obj: Result<T, serde_json::Error> = serde_json::from_str(stored_str);
     match maybe_obj {
         Ok(obj) => Ok(obj),
@@ -142,3 +185,46 @@ pub fn load_json_from_raw<'s, T: Deserialize<'s>>(
         }
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_str_stops_at_null_byte() {
+        assert_eq!(Ok("fish"), payload_str(b"fish"));
+        assert_eq!(Ok("fish"), payload_str(b"fish\0"));
+        assert_eq!(Ok("fish"), payload_str(b"fish\0chips"));
+        assert_eq!(Err(RibosomeErrorCode::ZeroLength), payload_str(b""));
+        assert_eq!(Err(RibosomeErrorCode::ZeroLength), payload_str(b"\0fish"));
+        assert_eq!(
+            Err(RibosomeErrorCode::ArgumentDeserializationFailed),
+            payload_str(&[0x66, 0xff, 0xfe])
+        );
+    }
+
+    #[test]
+    /// feeds pseudo-random bytes to payload_str() and load_json_from_str(),
+    /// which must fail with errors instead of panicking
+    fn payload_parsing_survives_random_bytes() {
+        // xorshift, so that failures can be reproduced
+        let mut state: u32 = 0x2545_F491;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as usize
+        };
+        // mostly JSON-ish characters, to get past the first byte now and then
+        let alphabet = b"{}[]\":,0123456789aeflnrstu \\\0\xff";
+        for _ in 0..10_000 {
+            let length = next() % 64;
+            let payload: Vec<u8> = (0..length)
+                .map(|_| alphabet[next() % alphabet.len()])
+                .collect();
+            if let Ok(stored_str) = payload_str(&payload) {
+                let _: Result<CoreError, HolochainError> = load_json_from_str(stored_str);
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Safe handle on the memory stack a zome shares with the Ribosome.
+//! Calling a function of the Ribosome means putting its input on the stack, calling it,
+//! loading its result and taking the input off the stack again.
+//! `Ribosome::call_host()` does all of that, so zome code does not need any unsafe block.
+
+use crate::{
+    memory_allocation::{u32_split_bits, SinglePageStack},
+    memory_serialization::{load_string, store_as_json, store_as_json_into_encoded_allocation},
+};
+use holochain_core_types::{
+    error::{RibosomeErrorCode, RibosomeReturnCode},
+    json::JsonString,
+};
+use std::convert::TryInto;
+
+/// A function the Ribosome provides to zomes.
+/// It takes the encoded allocation of its input and returns the encoded allocation
+/// of its result, or a return code.
+pub type HostFn = unsafe extern "C" fn(u32) -> u32;
+
+/// Handle on the memory stack during one call of an entry point of a zome.
+/// The entry point creates it from the allocation of its input,
+/// everything it puts on the stack from then on goes through the handle.
+#[derive(Debug)]
+pub struct Ribosome {
+    stack: SinglePageStack,
+}
+
+impl Ribosome {
+    pub fn new(stack: SinglePageStack) -> Self {
+        Ribosome { stack }
+    }
+
+    /// The handle for an entry point that got the given input, its stack starts right after it
+    pub fn from_encoded_allocation(
+        encoded_allocation_of_input: u32,
+    ) -> Result<Self, RibosomeErrorCode> {
+        SinglePageStack::from_encoded_allocation(encoded_allocation_of_input).map(Ribosome::new)
+    }
+
+    pub fn stack(&self) -> SinglePageStack {
+        self.stack
+    }
+
+    /// Calls the given function of the Ribosome with the given input and returns its result.
+    /// A function that only returns the success code gives a null JsonString.
+    /// The input gets taken off the stack again, whether the call worked or not.
+    pub fn call_host<J: TryInto<JsonString>>(
+        &mut self,
+        host_fn: HostFn,
+        input: J,
+    ) -> Result<JsonString, RibosomeErrorCode> {
+        let allocation_of_input = store_as_json(&mut self.stack, input)?;
+
+        let encoded_allocation_of_result = unsafe { host_fn(allocation_of_input.encode()) };
+        let (offset, length) = u32_split_bits(encoded_allocation_of_result);
+        // load_string() checks that the allocation of the result lies within our memory
+        let result = if length == 0 {
+            match RibosomeReturnCode::from_offset(offset) {
+                RibosomeReturnCode::Success => Ok(JsonString::null()),
+                RibosomeReturnCode::Failure(err_code) => Err(err_code),
+            }
+        } else {
+            load_string(encoded_allocation_of_result).map(JsonString::from)
+        };
+
+        self.stack
+            .deallocate(allocation_of_input)
+            .expect("the input is on top of the stack, so it can always be deallocated");
+        result
+    }
+
+    /// Puts the output of the entry point on the stack and returns its encoded allocation
+    pub fn store_output<J: TryInto<JsonString>>(&mut self, output: J) -> u32 {
+        store_as_json_into_encoded_allocation(&mut self.stack, output) as u32
+    }
+}
@@ -15,7 +15,7 @@ extern crate test_utils;
 use holochain_container_api::error::{HolochainInstanceError, HolochainResult};
 use holochain_core::logger::Logger;
 use holochain_core_types::{
-    error::{CoreError, HolochainError, RibosomeErrorCode},
+    error::{CoreError, HolochainError, RibosomeErrorCode, ZomeApiInternalResult},
     json::{default_try_from_json, JsonString, RawString},
 };
 use std::{convert::TryFrom, error::Error};
@@ -179,3 +179,33 @@ fn call_stacked_mix() {
     let call_result = call_zome_function_with_hc("test_stacked_mix");
     assert_eq!(JsonString::from("third"), call_result.unwrap());
 }
+
+#[test]
+fn call_host_deallocates_input_on_error() {
+    let call_result = call_zome_function_with_hc("test_call_host_err_deallocates_input");
+    assert_eq!(
+        JsonString::from(RibosomeErrorCode::ArgumentDeserializationFailed.to_string()),
+        call_result.unwrap()
+    );
+}
+
+#[test]
+fn call_host_deallocates_input_on_success() {
+    let call_result = call_zome_function_with_hc("test_call_host_ok_deallocates_input").unwrap();
+    let result = ZomeApiInternalResult::try_from(call_result).unwrap();
+    assert!(result.ok);
+}
+
+#[test]
+fn call_load_corrupt_allocations() {
+    let call_result = call_zome_function_with_hc("test_load_corrupt_allocations");
+    assert_eq!(
+        JsonString::from(vec![
+            RibosomeErrorCode::OutOfBounds.to_string(),
+            RibosomeErrorCode::ZeroLength.to_string(),
+            RibosomeErrorCode::PayloadTooLarge.to_string(),
+            RibosomeErrorCode::OutOfBounds.to_string(),
+        ]),
+        call_result.unwrap()
+    );
+}
@@ -11,11 +11,16 @@ use holochain_wasm_utils::holochain_core_types::json::JsonString;
 use holochain_wasm_utils::holochain_core_types::json::RawString;
 
 use holochain_wasm_utils::{
-    memory_allocation::*, memory_serialization::*,
+    memory_allocation::*, memory_serialization::*, ribosome::Ribosome,
     holochain_core_types::error::HolochainError,
 };
 use std::os::raw::c_char;
 
+extern "C" {
+    fn hc_entry_address(encoded_allocation_of_input: u32) -> u32;
+    fn hc_chain_info(encoded_allocation_of_input: u32) -> u32;
+}
+
 #[derive(Serialize, Default, Clone, PartialEq, Deserialize, Debug, DefaultJson)]
 struct TestStruct {
     value: String,
@@ -213,3 +218,57 @@ pub extern "C" fn test_stacked_mix(_: u32) -> u32 {
     });
     third as u32
 }
+
+#[no_mangle]
+pub extern "C" fn test_call_host_err_deallocates_input(_: u32) -> u32 {
+    let mut ribosome = Ribosome::new(SinglePageStack::default());
+    let top_before = ribosome.stack().top();
+    // Not an entry, so the Ribosome only returns an error code
+    let res = ribosome.call_host(hc_entry_address, RawString::from("fish"));
+    let mut stack = ribosome.stack();
+    zome_assert!(stack, stack.top() == top_before);
+    zome_assert!(stack, res.is_err());
+    store_string_into_encoded_allocation(&mut stack, &res.err().unwrap().to_string()) as u32
+}
+
+#[no_mangle]
+pub extern "C" fn test_call_host_ok_deallocates_input(_: u32) -> u32 {
+    let mut ribosome = Ribosome::new(SinglePageStack::default());
+    let top_before = ribosome.stack().top();
+    let res = ribosome.call_host(hc_chain_info, RawString::from("fish"));
+    let mut stack = ribosome.stack();
+    zome_assert!(stack, stack.top() == top_before);
+    zome_assert!(stack, res.is_ok());
+    store_string_into_encoded_allocation(&mut stack, &String::from(res.unwrap())) as u32
+}
+
+/// Stands in for a host function that answers with an allocation beyond the memory
+extern "C" fn hc_out_of_bounds(_: u32) -> u32 {
+    u32_merge_bits(0xFF00, 0x0200)
+}
+
+#[no_mangle]
+pub extern "C" fn test_load_corrupt_allocations(_: u32) -> u32 {
+    let mut stack = SinglePageStack::default();
+    let mut errors = Vec::new();
+
+    let out_of_bounds: Result<TestStruct, HolochainError> = load_json(u32_merge_bits(0xFF00, 0x0200));
+    errors.push(out_of_bounds.err().unwrap().to_string());
+
+    let null_byte = store_string(&mut stack, "\0").unwrap();
+    let zero_length: Result<TestStruct, HolochainError> = load_json(null_byte.encode());
+    errors.push(zero_length.err().unwrap().to_string());
+
+    let fish = store_as_json(&mut stack, TestStruct {
+        value: "fish".to_string(),
+    }).unwrap();
+    let too_large: Result<TestStruct, HolochainError> = load_json_with_limit(fish.encode(), 2);
+    errors.push(too_large.err().unwrap().to_string());
+
+    let mut ribosome = Ribosome::new(stack);
+    let host_result = ribosome.call_host(hc_out_of_bounds, RawString::from("fish"));
+    errors.push(host_result.err().unwrap().to_string());
+
+    let mut stack = ribosome.stack();
+    store_as_json_into_encoded_allocation(&mut stack, errors) as u32
+}